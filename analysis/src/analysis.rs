@@ -42,6 +42,10 @@ pub struct AnalysisResult {
     pub perceptual_sharpness: f32,
     pub perceptual_loudness: [f32; 24],
     pub mfcc: [f32; 13],
+    /// Integrated loudness in LUFS, `None` for a silent track
+    pub integrated_loudness: Option<f32>,
+    /// Sample peak, 1.0 being full scale
+    pub peak: f32,
 }
 
 pub fn analyze_audio(
@@ -70,6 +74,7 @@ pub fn analyze_audio(
     };
 
     let audio_desc = audio_desc.expect("Audio desc should not be none");
+    let loudness = analyzer.loudness();
 
     let amp_spectrum = amp_spectrum(&audio_desc.spectrum, window_size);
 
@@ -138,6 +143,8 @@ pub fn analyze_audio(
         perceptual_spread,
         perceptual_sharpness,
         mfcc,
+        integrated_loudness: loudness.and_then(|x| x.integrated),
+        peak: loudness.map(|x| x.peak).unwrap_or_default(),
     }))
 }
 
@@ -25,8 +25,10 @@ use crate::{
         sub_analyzer::SubAnalyzer,
     },
     utils::{
-        audio_description::AudioDescription, audio_metadata_reader::*,
+        audio_description::AudioDescription,
+        audio_metadata_reader::*,
         computing_device::ComputingDevice,
+        loudness::{Loudness, LoudnessMeter},
     },
 };
 
@@ -70,6 +72,8 @@ pub struct Analyzer {
     pub resampler: Option<FftFixedInOut<f32>>,
     pub resampler_output_buffer: Vec<Vec<f32>>,
     sub_analyzer: Arc<Mutex<dyn SubAnalyzer>>,
+    /// Measures the unmixed channels, before resampling
+    loudness_meter: Option<LoudnessMeter>,
 }
 
 impl Analyzer {
@@ -113,6 +117,7 @@ impl Analyzer {
             resample_ratio: 0.0,
             resampler: None,
             resampler_output_buffer: vec![],
+            loudness_meter: None,
 
             sub_analyzer: if computing_device == ComputingDevice::Gpu {
                 Arc::new(Mutex::new(GpuSubAnalyzer::new(window_size, batch_size)))
@@ -158,6 +163,11 @@ impl Analyzer {
         })
     }
 
+    /// The loudness of the processed track, once `process` returned.
+    pub fn loudness(&self) -> Option<Loudness> {
+        self.loudness_meter.as_ref().map(|x| x.finish())
+    }
+
     fn process_audio_chunk(&mut self, chunk: &[f32], force: bool) {
         Arc::clone(&self.sub_analyzer)
             .lock()
@@ -171,8 +181,18 @@ impl Analyzer {
     {
         let frames = buf.frames();
         let num_channels = buf.spec().channels.count();
+        let sample_rate = self.sample_rate;
+        self.loudness_meter
+            .get_or_insert_with(|| LoudnessMeter::new(sample_rate, num_channels));
 
         for frame_idx in 0..frames {
+            if let Some(meter) = &mut self.loudness_meter {
+                meter.push_frame(
+                    (0..num_channels)
+                        .map(|ch| IntoSample::<f32>::into_sample(buf.chan(ch)[frame_idx])),
+                );
+            }
+
             let mixed_sample: f32 = (0..num_channels)
                 .map(|ch| IntoSample::<f32>::into_sample(buf.chan(ch)[frame_idx]))
                 .sum::<f32>()
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::utils::loudness::LoudnessMeter;
+
+    fn measure_sine(amplitude: f32, channels: usize) -> (Option<f32>, f32) {
+        let sample_rate = 48000;
+        let mut meter = LoudnessMeter::new(sample_rate, channels);
+        for i in 0..sample_rate * 5 {
+            let sample = amplitude * (2.0 * PI * 1000.0 * i as f32 / sample_rate as f32).sin();
+            meter.push_frame(std::iter::repeat_n(sample, channels));
+        }
+
+        let loudness = meter.finish();
+        (loudness.integrated, loudness.peak)
+    }
+
+    #[test]
+    fn test_full_scale_sine_loudness() {
+        // BS.1770 calibration: a 0 dBFS 1 kHz sine on one channel reads -3.01 LUFS
+        let (integrated, peak) = measure_sine(1.0, 1);
+        let integrated = integrated.unwrap();
+        assert!(
+            (integrated + 3.01).abs() < 0.05,
+            "Unexpected loudness: {integrated}"
+        );
+        assert!((peak - 1.0).abs() < 0.001, "Unexpected peak: {peak}");
+    }
+
+    #[test]
+    fn test_stereo_sine_loudness() {
+        // The power of both channels adds up, 20 dB below full scale
+        let (integrated, _) = measure_sine(0.1, 2);
+        let integrated = integrated.unwrap();
+        assert!(
+            (integrated + 20.0).abs() < 0.05,
+            "Unexpected loudness: {integrated}"
+        );
+    }
+
+    #[test]
+    fn test_silence_has_no_loudness() {
+        let (integrated, peak) = measure_sine(0.0, 2);
+        assert_eq!(integrated, None);
+        assert_eq!(peak, 0.0);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod analyzer_tests;
 pub mod fft_tests;
+pub mod loudness_tests;
@@ -1,3 +1,5 @@
+use std::sync::OnceLock;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(i32)]
 pub enum ComputingDevice {
@@ -24,3 +26,36 @@ impl From<&str> for ComputingDevice {
         }
     }
 }
+
+/// The GPU adapter the analysis runs on, as told by its driver.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GpuAdapterInfo {
+    pub name: String,
+    pub driver: String,
+    /// The graphics API, like Vulkan or Metal
+    pub backend: String,
+}
+
+/// Finds the adapter the GPU analysis picks, `None` without any. The
+/// adapter is only looked up once, the first call blocks until it is found.
+pub fn gpu_adapter_info() -> Option<GpuAdapterInfo> {
+    static ADAPTER: OnceLock<Option<GpuAdapterInfo>> = OnceLock::new();
+
+    ADAPTER
+        .get_or_init(|| {
+            let instance = wgpu::Instance::default();
+            let adapter = pollster::block_on(
+                instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+            )?;
+            let info = adapter.get_info();
+
+            Some(GpuAdapterInfo {
+                name: info.name,
+                driver: format!("{} {}", info.driver, info.driver_info)
+                    .trim()
+                    .to_owned(),
+                backend: format!("{:?}", info.backend),
+            })
+        })
+        .clone()
+}
@@ -0,0 +1,176 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+// Integrated loudness as specified by ITU-R BS.1770-4 and EBU R128, the
+// filter design follows libebur128 (MIT) so any sample rate is supported.
+
+/// Blocks quieter than this are never part of the integrated loudness.
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Blocks this far below the loudness of the ungated blocks are left out.
+const RELATIVE_GATE: f64 = -10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS, `None` for a silent track
+    pub integrated: Option<f32>,
+    /// The highest absolute sample value, 1.0 being full scale
+    pub peak: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two stages of the K-weighting filter: a high shelf modelling the
+/// head, followed by the RLB high-pass.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+/// The weight of each channel, surround channels being louder and the LFE
+/// channel left out.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        _ => vec![1.0; channels],
+    }
+}
+
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Measures the integrated loudness and the peak of a track, fed one frame
+/// at a time.
+pub struct LoudnessMeter {
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    /// Frames in 100 ms, a quarter of a gating block
+    step_len: usize,
+    step_pos: usize,
+    step_power: f64,
+    /// The weighted power of the last three steps
+    recent_steps: VecDeque<f64>,
+    /// The mean power of every 400 ms gating block, overlapping by 75%
+    blocks: Vec<f64>,
+    peak: f32,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        LoudnessMeter {
+            filters: vec![k_weighting(sample_rate); channels],
+            weights: channel_weights(channels),
+            step_len: (sample_rate as usize / 10).max(1),
+            step_pos: 0,
+            step_power: 0.0,
+            recent_steps: VecDeque::with_capacity(3),
+            blocks: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Adds a frame, one sample per channel.
+    pub fn push_frame<I>(&mut self, frame: I)
+    where
+        I: IntoIterator<Item = f32>,
+    {
+        for ((sample, filters), weight) in frame
+            .into_iter()
+            .zip(self.filters.iter_mut())
+            .zip(self.weights.iter())
+        {
+            self.peak = self.peak.max(sample.abs());
+
+            let filtered = filters
+                .iter_mut()
+                .fold(sample as f64, |x, filter| filter.process(x));
+            self.step_power += weight * filtered * filtered;
+        }
+
+        self.step_pos += 1;
+        if self.step_pos == self.step_len {
+            self.finish_step();
+        }
+    }
+
+    fn finish_step(&mut self) {
+        if self.recent_steps.len() == 3 {
+            let block_power: f64 = self.recent_steps.iter().sum::<f64>() + self.step_power;
+            self.blocks.push(block_power / (self.step_len * 4) as f64);
+            self.recent_steps.pop_front();
+        }
+
+        self.recent_steps.push_back(self.step_power);
+        self.step_power = 0.0;
+        self.step_pos = 0;
+    }
+
+    pub fn finish(&self) -> Loudness {
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+
+        let audible: Vec<f64> = self
+            .blocks
+            .iter()
+            .copied()
+            .filter(|&x| x > 0.0 && to_lufs(x) > ABSOLUTE_GATE)
+            .collect();
+        let integrated = if audible.is_empty() {
+            None
+        } else {
+            let relative_gate = to_lufs(mean(&audible)) + RELATIVE_GATE;
+            let gated: Vec<f64> = audible
+                .into_iter()
+                .filter(|&x| to_lufs(x) > relative_gate)
+                .collect();
+
+            Some(to_lufs(mean(&gated)) as f32)
+        };
+
+        Loudness {
+            integrated,
+            peak: self.peak,
+        }
+    }
+}
@@ -61,6 +61,7 @@ async fn main() {
         "",
         10,
         ComputingDevice::Gpu,
+        true,
         empty_analysis_progress_callback,
         None,
     )
@@ -21,11 +21,11 @@ async fn main() {
 
     let root_path = PathBuf::from(&path);
 
-    let mut scanner = AudioScanner::new(&fsio, &path).unwrap();
+    let mut scanner = AudioScanner::new(&fsio, &path, None).unwrap();
 
     // Example usage: Read 5 audio files at a time until no more files are available.
     while !scanner.has_ended() {
-        let files = scanner.read_files(5);
+        let files = scanner.read_files(5).await;
 
         let descriptions: Vec<Option<FileDescription>> = files
             .clone()
@@ -1,44 +1,137 @@
 use std::path::Path;
 use std::sync::Arc;
 
+use anyhow::{Context, Result};
+use log::info;
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
 use analysis::utils::computing_device::ComputingDevice;
-use database::actions::analysis::{analysis_audio_library, empty_progress_callback};
+use database::actions::analysis::{analysis_audio_library, get_analysis_summary};
+use database::actions::content_match::compute_file_landmarks;
 use database::actions::recommendation::sync_recommendation;
 use database::connection::{MainDbConnection, RecommendationDbConnection};
 use fsio::FsIo;
 
+use crate::progress::{ProgressMode, ProgressReporter};
+
+pub struct AnalyzeOptions<'a> {
+    pub lib_path: &'a Path,
+    pub node_id: &'a str,
+    pub computing_device: ComputingDevice,
+    /// Analyze again the files which failed before
+    pub retry_failed: bool,
+    pub progress: ProgressMode,
+}
+
+#[derive(Debug, Serialize)]
+struct AnalyzeSummaryLine {
+    task: &'static str,
+    /// Whether the analysis was stopped before the end
+    stopped: bool,
+    analyzed: usize,
+    pending: usize,
+    failed: usize,
+}
+
+/// Analyzes the files of the library not analyzed yet. Ctrl-C stops after
+/// the files in flight, running the command again resumes from there.
 pub async fn analyze_audio_library(
-    computing_device: ComputingDevice,
     fsio: Arc<FsIo>,
     main_db: &MainDbConnection,
     analysis_db: &RecommendationDbConnection,
-    path: &Path,
-    node_id: &str,
-) {
-    if let Err(e) = analysis_audio_library(
-        fsio,
-        main_db,
-        path,
-        node_id,
-        15,
-        computing_device,
-        empty_progress_callback,
-        None,
-    )
-    .await
+    options: AnalyzeOptions<'_>,
+) -> Result<()> {
+    let cancel_token = CancellationToken::new();
     {
-        eprintln!("Audio analysis failed: {e}");
-        return;
+        let cancel_token = cancel_token.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                info!("Stopping, finishing the files in flight");
+                cancel_token.cancel();
+            }
+        });
     }
 
-    print!("Analysis finished");
+    let before = get_analysis_summary(main_db).await?;
+    let progress = Arc::new(ProgressReporter::new("Analyzing", options.progress));
+    let result = {
+        let progress = Arc::clone(&progress);
+        analysis_audio_library(
+            Arc::clone(&fsio),
+            main_db,
+            options.lib_path,
+            options.node_id,
+            15,
+            options.computing_device,
+            options.retry_failed,
+            move |done, total| progress.report(done, total),
+            Some(cancel_token.clone()),
+        )
+        .await
+    };
+    progress.finish();
+    result.context("Audio analysis failed")?;
 
-    if let Err(e) = sync_recommendation(main_db, analysis_db).await {
-        eprintln!("Sync recommendation failed: {e}");
-        return;
+    // Landmarks for matching duplicates by their content
+    if !cancel_token.is_cancelled() {
+        let progress = Arc::new(ProgressReporter::new("Landmarks", options.progress));
+        let result = {
+            let progress = Arc::clone(&progress);
+            compute_file_landmarks(
+                fsio,
+                main_db,
+                options.lib_path,
+                options.node_id,
+                15,
+                move |done, total| progress.report(done, total),
+                Some(cancel_token.clone()),
+            )
+            .await
+        };
+        progress.finish();
+        result.context("Landmark computation failed")?;
     }
 
-    print!("Sync finished");
+    // Also keeps the work done before an interruption
+    sync_recommendation(main_db, analysis_db)
+        .await
+        .context("Sync recommendation failed")?;
+
+    let summary = get_analysis_summary(main_db).await?;
+    let stopped = cancel_token.is_cancelled();
+    match options.progress {
+        ProgressMode::Json => {
+            let line = AnalyzeSummaryLine {
+                task: "Summary",
+                stopped,
+                analyzed: summary.analyzed,
+                pending: summary.pending,
+                failed: summary.failed,
+            };
+            println!("{}", serde_json::to_string(&line)?);
+            return Ok(());
+        }
+        ProgressMode::Quiet => return Ok(()),
+        ProgressMode::Bar => {}
+    }
+
+    let analyzed = summary.analyzed.saturating_sub(before.analyzed);
+    if stopped {
+        println!(
+            "Analysis stopped after {analyzed} files, {} files left. Run the command again to resume.",
+            summary.pending
+        );
+    } else {
+        println!("Analyzed {analyzed} files.");
+    }
+    println!(
+        "{} files analyzed, {} pending, {} failed.",
+        summary.analyzed, summary.pending, summary.failed
+    );
+    if summary.failed > 0 && !options.retry_failed {
+        println!("Use --retry-failed to analyze the failed files again.");
+    }
 
-    println!("Audio analysis completed successfully");
+    Ok(())
 }
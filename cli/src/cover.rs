@@ -0,0 +1,225 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use prettytable::{Table, format, row};
+use serde::Serialize;
+
+use database::actions::albums::get_album_by_id;
+use database::actions::cover_art::{
+    EmbedReport, embed_album_cover_art, get_album_cover_art, get_albums_without_cover_art,
+    set_album_cover_art,
+};
+use database::connection::MainDbConnection;
+use fsio::FsIo;
+use metadata::cover_art::{EmbedOptions, cover_art_extension, cover_art_from_image};
+
+use crate::output::{OutputFormat, print_csv, print_json};
+use crate::progress::{ProgressMode, ProgressReporter};
+
+/// The longest album name kept in the names of the extracted files.
+const MAX_NAME_LENGTH: usize = 80;
+
+/// Makes an album name safe to use in a file name on every platform.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|x| match x {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            x if x.is_control() => '_',
+            x => x,
+        })
+        .take(MAX_NAME_LENGTH)
+        .collect();
+
+    // Windows drops the trailing dots and spaces of file names
+    sanitized.trim().trim_end_matches('.').to_owned()
+}
+
+/// Writes the cover art of an album as stored, named after the ID and the
+/// name of the album so the covers of albums sharing a name don't collide.
+pub async fn extract_album_cover(
+    main_db: &MainDbConnection,
+    album_id: i32,
+    output_dir: &Path,
+) -> Result<()> {
+    let Some(album) = get_album_by_id(main_db, album_id).await? else {
+        bail!("Album {album_id} does not exist");
+    };
+    let Some(cover_art) = get_album_cover_art(main_db, album_id).await? else {
+        bail!("Album {album_id} has no cover art");
+    };
+
+    let extension = cover_art_extension(&cover_art.binary).unwrap_or("bin");
+    let name = sanitize_file_name(&album.name);
+    let file_name = if name.is_empty() {
+        format!("{album_id:05}.{extension}")
+    } else {
+        format!("{album_id:05}-{name}.{extension}")
+    };
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+    let path = output_dir.join(file_name);
+    fs::write(&path, &cover_art.binary)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!("Cover art of {} written to {}", album.name, path.display());
+    Ok(())
+}
+
+pub struct SetCoverOptions<'a> {
+    pub lib_path: &'a Path,
+    pub node_id: &'a str,
+    pub album_id: i32,
+    pub image: &'a Path,
+    /// Also embed the image into the tags of the tracks, shrunk as asked
+    pub embed: Option<EmbedOptions>,
+    /// Tags can't be written when the library is read-only.
+    pub read_only: bool,
+}
+
+/// Uses an image as the cover art of every track of an album. Tracks whose
+/// tags can't be written are reported one by one, the others are still
+/// updated.
+pub async fn set_album_cover(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: SetCoverOptions<'_>,
+) -> Result<()> {
+    let SetCoverOptions {
+        lib_path,
+        node_id,
+        album_id,
+        image,
+        embed,
+        read_only,
+    } = options;
+
+    if embed.is_some() && read_only {
+        bail!("The library is read-only, the cover art cannot be embedded into its files.");
+    }
+
+    let Some(album) = get_album_by_id(main_db, album_id).await? else {
+        bail!("Album {album_id} does not exist");
+    };
+    let data = fs::read(image).with_context(|| format!("Failed to read {}", image.display()))?;
+    let cover_art = cover_art_from_image(data)
+        .with_context(|| format!("{} is not a usable image", image.display()))?;
+
+    let files = set_album_cover_art(main_db, album_id, &cover_art, node_id).await?;
+    if files.is_empty() {
+        bail!("Album {album_id} has no tracks");
+    }
+    println!(
+        "Set the cover art of {} tracks of {}.",
+        files.len(),
+        album.name
+    );
+
+    let Some(embed) = embed else {
+        return Ok(());
+    };
+
+    let report =
+        embed_album_cover_art(fsio, main_db, lib_path, &[album_id], &embed, |_, _| {}).await?;
+    print_embed_report(&report)
+}
+
+pub struct EmbedCoverOptions<'a> {
+    pub lib_path: &'a Path,
+    pub album_ids: &'a [i32],
+    pub embed: EmbedOptions,
+    pub progress: ProgressMode,
+    pub read_only: bool,
+}
+
+/// Embeds the stored cover art of albums into the tags of their tracks,
+/// for the players reading the files to show the cover Rune shows.
+pub async fn embed_album_covers(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: EmbedCoverOptions<'_>,
+) -> Result<()> {
+    if options.read_only {
+        bail!("The library is read-only, the cover art cannot be embedded into its files.");
+    }
+
+    let progress = ProgressReporter::new("Embedding", options.progress);
+    let report = embed_album_cover_art(
+        fsio,
+        main_db,
+        options.lib_path,
+        options.album_ids,
+        &options.embed,
+        |done, total| progress.report(done, total),
+    )
+    .await;
+    progress.finish();
+
+    print_embed_report(&report?)
+}
+
+/// Prints how embedding went, failing if any file could not be written.
+fn print_embed_report(report: &EmbedReport) -> Result<()> {
+    for album_id in &report.without_cover_art {
+        eprintln!("Warning: album {album_id} has no cover art to embed");
+    }
+    for (file_id, format) in &report.unsupported {
+        eprintln!("Warning: {format} files are not supported, file {file_id} was skipped");
+    }
+    for (file_id, error) in &report.failed {
+        eprintln!("File {file_id}: {error}");
+    }
+
+    println!(
+        "Embedded the cover art into {} files, {} already had it.",
+        report.embedded, report.unchanged
+    );
+    if !report.failed.is_empty() {
+        bail!(
+            "Failed to embed the cover art into {} files",
+            report.failed.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct MissingCoverRecord {
+    id: i32,
+    name: String,
+}
+
+/// Prints the albums without cover art. Returns whether there are any.
+pub async fn list_missing_covers(main_db: &MainDbConnection, output: OutputFormat) -> Result<bool> {
+    let records: Vec<MissingCoverRecord> = get_albums_without_cover_art(main_db)
+        .await?
+        .into_iter()
+        .map(|album| MissingCoverRecord {
+            id: album.id,
+            name: album.name,
+        })
+        .collect();
+
+    match output {
+        OutputFormat::Table => {
+            if records.is_empty() {
+                println!("Every album has a cover art.");
+            } else {
+                let mut table = Table::new();
+                table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+                table.set_titles(row!["ID", "Album"]);
+                for record in &records {
+                    table.add_row(row![record.id, record.name]);
+                }
+                table.printstd();
+            }
+        }
+        OutputFormat::Json => print_json(&records)?,
+        OutputFormat::Csv => print_csv(&records)?,
+    }
+
+    Ok(!records.is_empty())
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+
+use database::actions::maintenance::{backup_databases, vacuum_main_db, verify_databases};
+use database::actions::schema::{migrate_main_db, schema_status};
+use database::connection::{MainDbConnection, RecommendationDbConnection};
+
+pub async fn db_vacuum(main_db: &MainDbConnection, quiet: bool) -> Result<()> {
+    let outcome = vacuum_main_db(main_db).await?;
+
+    if !quiet {
+        println!(
+            "vacuumed {} -> {} bytes",
+            outcome.size_before, outcome.size_after
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints every problem on its own line and fails if there is any, so
+/// scripts can rely on the exit code.
+pub async fn db_verify(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    quiet: bool,
+) -> Result<()> {
+    let problems = verify_databases(main_db, recommend_db).await?;
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if !problems.is_empty() {
+        bail!("{} problems found", problems.len());
+    }
+
+    if !quiet {
+        println!("ok");
+    }
+
+    Ok(())
+}
+
+pub async fn db_backup(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    lib_path: &Path,
+    output_dir: &Path,
+    quiet: bool,
+) -> Result<()> {
+    let (backup_dir, _) = backup_databases(main_db, recommend_db, lib_path, output_dir).await?;
+
+    if !quiet {
+        println!("{}", backup_dir.display());
+    }
+
+    Ok(())
+}
+
+/// Prints the schema version without `target`, otherwise migrates the main
+/// database to it.
+pub async fn db_migrate(
+    main_db: &MainDbConnection,
+    target: Option<&str>,
+    node_id: &str,
+    quiet: bool,
+) -> Result<()> {
+    let Some(target) = target else {
+        let status = schema_status(main_db).await?;
+        println!("version: {}", status.version.as_deref().unwrap_or("none"));
+        println!("latest: {}", status.latest_version);
+        for name in &status.pending {
+            println!("pending: {name}");
+        }
+        for name in &status.unknown {
+            println!("unknown: {name}");
+        }
+        return Ok(());
+    };
+
+    let outcome = migrate_main_db(main_db, target, node_id).await?;
+
+    if !quiet {
+        println!(
+            "{} -> {} ({} applied, {} reverted)",
+            outcome.from.as_deref().unwrap_or("none"),
+            outcome.to,
+            outcome.applied,
+            outcome.reverted
+        );
+    }
+
+    Ok(())
+}
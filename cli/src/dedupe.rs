@@ -0,0 +1,203 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use prettytable::{Table, format, row};
+
+use database::actions::content_match::{compute_file_landmarks, match_content};
+use database::actions::dedup::{
+    DedupePlan, DuplicateGroup, DuplicateLabel, KeepPreference, RemovalMode, apply_dedupe_plan,
+    plan_dedupe,
+};
+use database::connection::MainDbConnection;
+use fsio::FsIo;
+
+use crate::progress::{ProgressMode, ProgressReporter};
+
+pub struct DedupeReportOptions<'a> {
+    pub lib_path: &'a Path,
+    pub threshold: f32,
+    pub prefer: KeepPreference,
+    /// Save the plan to this file, to review it before applying it
+    pub save: Option<&'a PathBuf>,
+    pub json: bool,
+}
+
+/// Why a file is in its group: where its content matches another file of
+/// the group, or only its fingerprint similarity.
+fn match_description(group: &DuplicateGroup, file_id: i32) -> String {
+    let content_match = group
+        .content_matches
+        .iter()
+        .find(|x| x.file_id1 == file_id || x.file_id2 == file_id);
+
+    match content_match {
+        Some(x) => {
+            let (other, offset) = if x.file_id1 == file_id {
+                (x.file_id2, x.offset)
+            } else {
+                (x.file_id1, -x.offset)
+            };
+            format!(
+                "content of {other} {offset:+.2}s, {:.0}%",
+                x.density * 100.0
+            )
+        }
+        None if group.labels.contains(&DuplicateLabel::Similarity) => "similarity".to_owned(),
+        None => "-".to_owned(),
+    }
+}
+
+fn display_plan(plan: &DedupePlan) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row![
+        "Group",
+        "Keep",
+        "ID",
+        "File Path",
+        "Format",
+        "Sample Rate",
+        "Bitrate",
+        "Match"
+    ]);
+
+    for (index, group) in plan.groups.iter().enumerate() {
+        for file in &group.files {
+            table.add_row(row![
+                index + 1,
+                if file.file_id == group.keep { "*" } else { "" },
+                file.file_id,
+                file.path.display(),
+                file.extension,
+                file.sample_rate,
+                file.bitrate_kbps
+                    .map(|x| format!("{x} kbps"))
+                    .unwrap_or_else(|| "-".to_owned()),
+                match_description(group, file.file_id)
+            ]);
+        }
+    }
+
+    table.printstd();
+}
+
+pub async fn dedupe_report(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: DedupeReportOptions<'_>,
+) -> Result<()> {
+    let DedupeReportOptions {
+        lib_path,
+        threshold,
+        prefer,
+        save,
+        json,
+    } = options;
+
+    let plan = plan_dedupe(fsio, main_db, lib_path, threshold, prefer).await?;
+
+    if let Some(save) = save {
+        fs::write(save, serde_json::to_string_pretty(&plan)?)
+            .with_context(|| format!("Failed to save the plan to {}", save.display()))?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&plan)?);
+        return Ok(());
+    }
+
+    if plan.groups.is_empty() {
+        println!(
+            "No duplicates found. Duplicates are found from fingerprint similarities, \
+             deduplicate the library in the app first, or run `dedupe match`."
+        );
+        return Ok(());
+    }
+
+    display_plan(&plan);
+    println!(
+        "{} groups, {} files would be removed.",
+        plan.groups.len(),
+        plan.removal_count()
+    );
+
+    Ok(())
+}
+
+pub async fn dedupe_apply(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    plan_path: &Path,
+    mode: RemovalMode,
+    yes: bool,
+) -> Result<()> {
+    let plan: DedupePlan = serde_json::from_str(
+        &fs::read_to_string(plan_path)
+            .with_context(|| format!("Failed to read {}", plan_path.display()))?,
+    )
+    .with_context(|| format!("{} is not a deduplication plan", plan_path.display()))?;
+
+    if !yes {
+        display_plan(&plan);
+        bail!(
+            "This removes {} files, pass --yes to go ahead.",
+            plan.removal_count()
+        );
+    }
+
+    let outcome = apply_dedupe_plan(fsio, main_db, lib_path, &plan, mode).await?;
+    println!(
+        "Removed {} files, skipped {} which changed since the plan was made.",
+        outcome.removed, outcome.skipped
+    );
+
+    if outcome.failed > 0 {
+        bail!("Failed to remove {} files", outcome.failed);
+    }
+
+    Ok(())
+}
+
+/// Computes the missing landmarks, then matches the content of every file
+/// against the others. The matches show in `dedupe report`.
+pub async fn dedupe_match(
+    fsio: Arc<FsIo>,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    quiet: bool,
+) -> Result<()> {
+    let mode = if quiet {
+        ProgressMode::Quiet
+    } else {
+        ProgressMode::Bar
+    };
+
+    let progress = Arc::new(ProgressReporter::new("Landmarks", mode));
+    let result = {
+        let progress = Arc::clone(&progress);
+        compute_file_landmarks(
+            fsio,
+            main_db,
+            lib_path,
+            "",
+            15,
+            move |done, total| progress.report(done, total),
+            None,
+        )
+        .await
+    };
+    progress.finish();
+    result.context("Landmark computation failed")?;
+
+    let progress = ProgressReporter::new("Matching", mode);
+    let result = match_content(main_db, |done, total| progress.report(done, total), None).await;
+    progress.finish();
+    let count = result.context("Content matching failed")?;
+
+    println!("{count} pairs of files carry the same recording.");
+
+    Ok(())
+}
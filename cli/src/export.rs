@@ -0,0 +1,68 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use database::actions::library_export::{ExportCategory, export_library};
+use database::connection::MainDbConnection;
+
+use crate::progress::{ProgressMode, ProgressReporter};
+
+pub struct ExportOptions<'a> {
+    /// The format of the export (json)
+    pub format: &'a str,
+    pub output: &'a Path,
+    /// The categories to export, every one if empty
+    pub include: &'a [ExportCategory],
+    pub exclude: &'a [ExportCategory],
+    pub progress: ProgressMode,
+}
+
+/// Exports the data of the library to a file. The file is removed if the
+/// export fails, so a partial export is never left behind.
+pub async fn export_library_data(
+    main_db: &MainDbConnection,
+    options: ExportOptions<'_>,
+) -> Result<()> {
+    if options.format != "json" {
+        bail!("Unsupported format. The only supported format is 'json'.");
+    }
+
+    let categories: Vec<ExportCategory> = ExportCategory::ALL
+        .into_iter()
+        .filter(|x| options.include.is_empty() || options.include.contains(x))
+        .filter(|x| !options.exclude.contains(x))
+        .collect();
+    if categories.is_empty() {
+        bail!("Nothing to export, every category is excluded");
+    }
+
+    let file = File::create(options.output)
+        .with_context(|| format!("Failed to create {}", options.output.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    let progress = ProgressReporter::new("Exporting", options.progress);
+    let result = export_library(main_db, &mut writer, &categories, |done, total| {
+        progress.report(done, total)
+    })
+    .await;
+    progress.finish();
+    drop(writer);
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(options.output);
+        return Err(e.context("Export failed"));
+    }
+
+    if options.progress != ProgressMode::Quiet {
+        let categories: Vec<String> = categories.iter().map(|x| x.to_string()).collect();
+        eprintln!(
+            "Exported {} to {}",
+            categories.join(", "),
+            options.output.display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,406 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use dunce::canonicalize;
+use serde::Serialize;
+
+use database::actions::file::{get_file_by_path, get_files_by_ids};
+use database::actions::metadata_edit::{apply_metadata_changes, diff_metadata};
+use database::connection::MainDbConnection;
+use fsio::FsIo;
+use metadata::scanner::is_audio_file;
+use metadata::writer::write_tags;
+
+use crate::meta::record_tag_write;
+use tag_editor::sampler::interval_sampler::IntervalSampler;
+use tag_editor::shazam::api::{Track, identify};
+use tag_editor::shazam::spectrogram::compute_signature;
+
+use crate::output::{NO_RESULTS_EXIT_CODE, OutputFormat, print_csv, print_json};
+
+/// Exit code of identifications which couldn't reach the recognition
+/// service, told apart from the ones finding no match.
+pub const NETWORK_FAILURE_EXIT_CODE: i32 = 5;
+
+/// The sample rate the signatures are computed at.
+const SAMPLE_RATE: u32 = 16000;
+/// The length of the excerpts sent for recognition, in seconds.
+const EXCERPT_DURATION: f64 = 12.0;
+/// The time between the candidate excerpts of a file, in seconds.
+const EXCERPT_INTERVAL: f64 = 30.0;
+
+/// The outcome of identifying files, the worst one winning for batches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentifyStatus {
+    Matched,
+    NoMatch,
+    NetworkFailure,
+}
+
+impl IdentifyStatus {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            IdentifyStatus::Matched => 0,
+            IdentifyStatus::NoMatch => NO_RESULTS_EXIT_CODE,
+            IdentifyStatus::NetworkFailure => NETWORK_FAILURE_EXIT_CODE,
+        }
+    }
+}
+
+/// What to identify.
+pub enum IdentifyTarget<'a> {
+    /// An audio file, in the library or not
+    Path(&'a Path),
+    /// Every audio file at or below a directory
+    Batch(&'a Path),
+    /// A file of the library
+    FileId(i32),
+}
+
+pub struct IdentifyOptions<'a> {
+    pub lib_path: &'a Path,
+    pub node_id: &'a str,
+    pub target: IdentifyTarget<'a>,
+    /// The number of excerpts sent per file, the confidence being the share
+    /// of them agreeing on the match
+    pub excerpts: usize,
+    /// Write the matches into the metadata of the library
+    pub apply: bool,
+    /// Also write the matches into the tags of the files
+    pub write_tags: bool,
+    /// The pause between two files of a batch
+    pub delay: Duration,
+    pub output: OutputFormat,
+    /// Tags can't be written when the library is read-only.
+    pub read_only: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct IdentifyRecord {
+    path: String,
+    file_id: Option<i32>,
+    status: IdentifyStatus,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    date: Option<String>,
+    confidence: Option<f64>,
+}
+
+struct Identification {
+    title: String,
+    artist: String,
+    album: Option<String>,
+    date: Option<String>,
+    /// The share of the answered excerpts agreeing on this track
+    confidence: f64,
+}
+
+impl Identification {
+    fn from_track(track: &Track, confidence: f64) -> Self {
+        Identification {
+            title: track.title.clone(),
+            artist: track.subtitle.clone(),
+            album: track.song_metadata("Album"),
+            date: track.song_metadata("Released"),
+            confidence,
+        }
+    }
+
+    fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            ("track_title".to_owned(), self.title.clone()),
+            ("artist".to_owned(), self.artist.clone()),
+        ];
+        if let Some(album) = &self.album {
+            fields.push(("album".to_owned(), album.clone()));
+        }
+        if let Some(date) = &self.date {
+            fields.push(("date".to_owned(), date.clone()));
+        }
+
+        fields
+    }
+}
+
+/// Decodes a file and picks `count` excerpts spread over it, skipping the
+/// intro when the file is long enough.
+fn sample_excerpts(fsio: &FsIo, path: &Path, count: usize) -> Result<Vec<Vec<f64>>> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid path {}", path.display()))?;
+    let mut sampler = IntervalSampler::new(
+        path_str,
+        EXCERPT_DURATION,
+        EXCERPT_INTERVAL,
+        SAMPLE_RATE,
+        None,
+    );
+    sampler
+        .process(fsio)
+        .with_context(|| format!("Failed to decode {}", path.display()))?;
+
+    // The sampler keeps its sender, so only the sent excerpts are drained
+    let mut excerpts: Vec<Vec<f64>> = sampler.receiver.try_iter().map(|x| x.data).collect();
+    if excerpts.is_empty() {
+        bail!("{} is too short to be identified", path.display());
+    }
+
+    let total = excerpts.len();
+    let mut indices: Vec<usize> = (1..=count).map(|i| i * total / (count + 1)).collect();
+    indices.dedup();
+
+    Ok(indices
+        .into_iter()
+        .map(|i| std::mem::take(&mut excerpts[i]))
+        .collect())
+}
+
+/// Identifies a file from its excerpts. Failing requests only make a
+/// network failure when no excerpt got an answer.
+async fn identify_file(
+    fsio: &Arc<FsIo>,
+    path: &Path,
+    excerpts: usize,
+) -> Result<(IdentifyStatus, Option<Identification>)> {
+    let samples = {
+        let fsio = Arc::clone(fsio);
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || sample_excerpts(&fsio, &path, excerpts)).await??
+    };
+
+    let mut answered = 0;
+    let mut votes: Vec<(Track, usize)> = Vec::new();
+    let mut last_error = None;
+    for sample in samples {
+        let signature = compute_signature(SAMPLE_RATE as i32, &sample);
+        match identify(signature).await {
+            Ok((_, track)) => {
+                answered += 1;
+                let Some(track) = track else {
+                    continue;
+                };
+                match votes
+                    .iter_mut()
+                    .find(|(x, _)| x.title == track.title && x.subtitle == track.subtitle)
+                {
+                    Some((_, count)) => *count += 1,
+                    None => votes.push((track, 1)),
+                }
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    if answered == 0 {
+        if let Some(e) = last_error {
+            eprintln!("Failed to reach the recognition service: {e:#}");
+        }
+        return Ok((IdentifyStatus::NetworkFailure, None));
+    }
+
+    // The first track reaching the highest count wins ties
+    let best = votes
+        .iter()
+        .fold(None, |best: Option<&(Track, usize)>, x| match best {
+            Some(best) if best.1 >= x.1 => Some(best),
+            _ => Some(x),
+        });
+    Ok(match best {
+        Some((track, count)) => (
+            IdentifyStatus::Matched,
+            Some(Identification::from_track(
+                track,
+                *count as f64 / answered as f64,
+            )),
+        ),
+        None => (IdentifyStatus::NoMatch, None),
+    })
+}
+
+/// Writes an identification into the metadata of a file of the library,
+/// and into its tags when asked to.
+async fn apply_identification(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: &IdentifyOptions<'_>,
+    file_id: i32,
+    path: &Path,
+    identification: &Identification,
+) -> Result<()> {
+    let fields = identification.fields();
+    let changes = diff_metadata(main_db, &[(file_id, fields.clone())]).await?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    apply_metadata_changes(main_db, options.node_id, &changes).await?;
+
+    if options.write_tags {
+        let outcome = write_tags(fsio, &path, &fields).await?;
+        record_tag_write(main_db, file_id, path, outcome).await?;
+    }
+
+    Ok(())
+}
+
+/// The files to identify, with their IDs when they are in the library.
+async fn resolve_targets(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    target: &IdentifyTarget<'_>,
+) -> Result<Vec<(PathBuf, Option<i32>)>> {
+    let paths = match target {
+        IdentifyTarget::FileId(file_id) => {
+            let Some(file) = get_files_by_ids(main_db, &[*file_id]).await?.pop() else {
+                bail!("File {file_id} does not exist");
+            };
+            let path = lib_path.join(&file.directory).join(&file.file_name);
+            return Ok(vec![(path, Some(file.id))]);
+        }
+        IdentifyTarget::Path(path) => vec![path.to_path_buf()],
+        IdentifyTarget::Batch(dir) => {
+            let mut paths: Vec<PathBuf> = fsio
+                .walk_dir(dir, true)
+                .with_context(|| format!("Failed to walk {}", dir.display()))?
+                .into_iter()
+                .filter(|x| x.is_file && is_audio_file(x))
+                .map(|x| x.path)
+                .collect();
+            paths.sort();
+            paths
+        }
+    };
+
+    let mut targets = Vec::new();
+    for path in paths {
+        let path =
+            canonicalize(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let file_id = match path.strip_prefix(lib_path) {
+            Ok(relative_path) => get_file_by_path(main_db, relative_path)
+                .await?
+                .map(|x| x.id),
+            Err(_) => None,
+        };
+        targets.push((path, file_id));
+    }
+
+    Ok(targets)
+}
+
+fn print_record(record: &IdentifyRecord) {
+    match record.status {
+        IdentifyStatus::Matched => {
+            print!(
+                "{}: {} - {}",
+                record.path,
+                record.artist.as_deref().unwrap_or_default(),
+                record.title.as_deref().unwrap_or_default()
+            );
+            if let Some(album) = &record.album {
+                print!(" ({album})");
+            }
+            println!(
+                ", confidence {:.0}%",
+                record.confidence.unwrap_or_default() * 100.0
+            );
+        }
+        IdentifyStatus::NoMatch => println!("{}: no match", record.path),
+        IdentifyStatus::NetworkFailure => {
+            println!("{}: recognition service unreachable", record.path)
+        }
+    }
+}
+
+/// Identifies tracks from excerpts of their audio, writing the matches into
+/// the metadata when asked to. Returns the worst outcome among the files,
+/// files which couldn't be read or updated are errors.
+///
+/// Batches pause between files on top of the pace the recognition service
+/// is queried at.
+pub async fn identify_tracks(
+    fsio: &Arc<FsIo>,
+    main_db: &MainDbConnection,
+    options: IdentifyOptions<'_>,
+) -> Result<IdentifyStatus> {
+    if options.write_tags && options.read_only {
+        bail!("The library is read-only, tags cannot be written to its files.");
+    }
+
+    let targets = resolve_targets(fsio, main_db, options.lib_path, &options.target).await?;
+
+    let mut status = if targets.is_empty() {
+        IdentifyStatus::NoMatch
+    } else {
+        IdentifyStatus::Matched
+    };
+    let mut records = Vec::new();
+    let mut failed = 0;
+    for (index, (path, file_id)) in targets.iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(options.delay).await;
+        }
+
+        let (file_status, identification) =
+            match identify_file(fsio, path, options.excerpts.max(1)).await {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("{e:#}");
+                    failed += 1;
+                    continue;
+                }
+            };
+        status = status.max(file_status);
+
+        if options.apply
+            && let Some(identification) = &identification
+        {
+            let result = match file_id {
+                Some(file_id) => {
+                    apply_identification(fsio, main_db, &options, *file_id, path, identification)
+                        .await
+                }
+                None => Err(anyhow!("{} is not in the library", path.display())),
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to apply the identification: {e:#}");
+                failed += 1;
+            }
+        }
+
+        let record = IdentifyRecord {
+            path: path.to_string_lossy().to_string(),
+            file_id: *file_id,
+            status: file_status,
+            title: identification.as_ref().map(|x| x.title.clone()),
+            artist: identification.as_ref().map(|x| x.artist.clone()),
+            album: identification.as_ref().and_then(|x| x.album.clone()),
+            date: identification.as_ref().and_then(|x| x.date.clone()),
+            confidence: identification.as_ref().map(|x| x.confidence),
+        };
+        if options.output == OutputFormat::Table {
+            print_record(&record);
+        }
+        records.push(record);
+    }
+
+    match options.output {
+        OutputFormat::Table => {
+            if targets.is_empty() {
+                println!("No audio files to identify.");
+            }
+        }
+        OutputFormat::Json => print_json(&records)?,
+        OutputFormat::Csv => print_csv(&records)?,
+    }
+
+    // Not reaching the service matters more than the files it couldn't read
+    if failed > 0 && status != IdentifyStatus::NetworkFailure {
+        bail!("Failed to identify {failed} files");
+    }
+
+    Ok(status)
+}
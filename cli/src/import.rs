@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+use plist::{Dictionary, Value};
+
+use database::actions::library_import::{
+    ImportedPlaylist, ImportedStats, apply_library_import, match_media_file, path_from_file_url,
+};
+use database::connection::MainDbConnection;
+
+use crate::progress::{ProgressMode, ProgressReporter};
+
+/// Exit code of imports which matched fewer tracks than required, distinct
+/// from the one of commands which found nothing (3).
+pub const LOW_MATCH_EXIT_CODE: i32 = 4;
+
+/// The group of the imported playlists.
+const IMPORT_PLAYLIST_GROUP: &str = "Imported";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// The `Library.xml` exported by iTunes or Apple Music
+    Itunes,
+}
+
+impl FromStr for ImportSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "itunes" => Ok(ImportSource::Itunes),
+            _ => Err(anyhow!("Invalid source {s}, expected itunes")),
+        }
+    }
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportSource::Itunes => write!(f, "itunes"),
+        }
+    }
+}
+
+pub struct ImportOptions<'a> {
+    pub lib_path: &'a Path,
+    pub node_id: &'a str,
+    pub source: ImportSource,
+    pub path: &'a Path,
+    /// Only report what would be imported
+    pub dry_run: bool,
+    /// Write the unmatched tracks to this file instead of stderr
+    pub report: Option<&'a Path>,
+    /// The percentage of tracks which must match
+    pub min_match: u8,
+    pub progress: ProgressMode,
+}
+
+#[derive(Debug)]
+struct SourceTrack {
+    id: i64,
+    name: String,
+    artist: String,
+    location: Option<String>,
+    stats: ImportedStats,
+}
+
+#[derive(Debug)]
+struct SourcePlaylist {
+    name: String,
+    track_ids: Vec<i64>,
+}
+
+fn get_string(dict: &Dictionary, key: &str) -> Option<String> {
+    dict.get(key).and_then(Value::as_string).map(str::to_owned)
+}
+
+fn get_integer(dict: &Dictionary, key: &str) -> Option<i64> {
+    dict.get(key).and_then(Value::as_signed_integer)
+}
+
+fn get_flag(dict: &Dictionary, key: &str) -> bool {
+    dict.get(key).and_then(Value::as_boolean).unwrap_or(false)
+}
+
+fn read_itunes_library(path: &Path) -> Result<(Vec<SourceTrack>, Vec<SourcePlaylist>)> {
+    let library = Value::from_file(path)
+        .with_context(|| format!("Failed to read the iTunes library {}", path.display()))?;
+    let library = library
+        .as_dictionary()
+        .ok_or_else(|| anyhow!("{} is not an iTunes library", path.display()))?;
+
+    let mut tracks: Vec<SourceTrack> = library
+        .get("Tracks")
+        .and_then(Value::as_dictionary)
+        .ok_or_else(|| anyhow!("{} has no tracks", path.display()))?
+        .values()
+        .filter_map(Value::as_dictionary)
+        .filter_map(|track| {
+            // Ratings inherited from the album are not the track's own
+            let rating = get_integer(track, "Rating")
+                .filter(|x| *x > 0 && !get_flag(track, "Rating Computed"))
+                .map(|x| ((x + 10) / 20).clamp(1, 5) as i32);
+
+            Some(SourceTrack {
+                id: get_integer(track, "Track ID")?,
+                name: get_string(track, "Name").unwrap_or_default(),
+                artist: get_string(track, "Artist").unwrap_or_default(),
+                location: get_string(track, "Location"),
+                stats: ImportedStats {
+                    liked: get_flag(track, "Loved") || get_flag(track, "Favorited"),
+                    rating,
+                    played_through: get_integer(track, "Play Count").unwrap_or(0) as i32,
+                    skipped: get_integer(track, "Skip Count").unwrap_or(0) as i32,
+                },
+            })
+        })
+        .collect();
+    tracks.sort_by_key(|x| x.id);
+
+    let playlists = library
+        .get("Playlists")
+        .and_then(Value::as_array)
+        .map(|x| x.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(Value::as_dictionary)
+        // Skips the whole library, the built-in lists and the folders
+        .filter(|playlist| {
+            !get_flag(playlist, "Master")
+                && !get_flag(playlist, "Folder")
+                && playlist.get("Distinguished Kind").is_none()
+                && playlist.get("Visible").and_then(Value::as_boolean) != Some(false)
+        })
+        .map(|playlist| SourcePlaylist {
+            name: get_string(playlist, "Name").unwrap_or_default(),
+            track_ids: playlist
+                .get("Playlist Items")
+                .and_then(Value::as_array)
+                .map(|x| x.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(Value::as_dictionary)
+                .filter_map(|item| get_integer(item, "Track ID"))
+                .collect(),
+        })
+        .collect();
+
+    Ok((tracks, playlists))
+}
+
+/// Imports the stats and playlists of another player, matching its tracks
+/// with the files of the library.
+///
+/// The tracks which didn't match are reported to stderr or to the report
+/// file. Returns whether enough tracks matched, a low match rate usually
+/// meaning the library is not the one the other player knew.
+pub async fn import_library_data(
+    main_db: &MainDbConnection,
+    options: ImportOptions<'_>,
+) -> Result<bool> {
+    let (tracks, playlists) = match options.source {
+        ImportSource::Itunes => read_itunes_library(options.path)?,
+    };
+    if tracks.is_empty() {
+        bail!("No tracks found in {}", options.path.display());
+    }
+
+    let progress = ProgressReporter::new("Matching", options.progress);
+    let mut matched = HashMap::new();
+    let mut unmatched = Vec::new();
+    for (index, track) in tracks.iter().enumerate() {
+        let path = track.location.as_deref().and_then(path_from_file_url);
+        let file_id = match &path {
+            Some(path) => match_media_file(main_db, options.lib_path, path).await?,
+            None => None,
+        };
+
+        match file_id {
+            Some(file_id) => {
+                matched.insert(track.id, file_id);
+            }
+            None => unmatched.push(format!(
+                "{} - {}\t{}",
+                track.artist,
+                track.name,
+                track.location.as_deref().unwrap_or("(no file)")
+            )),
+        }
+        progress.report(index + 1, tracks.len());
+    }
+    progress.finish();
+
+    let report = unmatched.join("\n");
+    match options.report {
+        Some(report_path) => fs::write(report_path, format!("{report}\n"))
+            .with_context(|| format!("Failed to write the report {}", report_path.display()))?,
+        None if !unmatched.is_empty() => {
+            eprintln!("Unmatched tracks ({}):\n{report}", unmatched.len());
+        }
+        None => {}
+    }
+
+    // Untouched tracks would only create empty stats
+    let stats: Vec<(i32, ImportedStats)> = tracks
+        .iter()
+        .filter(|x| {
+            x.stats.liked
+                || x.stats.rating.is_some()
+                || x.stats.played_through > 0
+                || x.stats.skipped > 0
+        })
+        .filter_map(|x| Some((*matched.get(&x.id)?, x.stats.clone())))
+        .collect();
+    let playlists: Vec<ImportedPlaylist> = playlists
+        .into_iter()
+        .map(|playlist| ImportedPlaylist {
+            name: playlist.name,
+            media_file_ids: playlist
+                .track_ids
+                .iter()
+                .filter_map(|id| matched.get(id).copied())
+                .collect(),
+        })
+        .filter(|x| !x.media_file_ids.is_empty())
+        .collect();
+
+    let match_rate = matched.len() as f64 / tracks.len() as f64 * 100.0;
+    println!(
+        "Matched {} of {} tracks ({match_rate:.1}%).",
+        matched.len(),
+        tracks.len()
+    );
+
+    if options.dry_run {
+        println!(
+            "Would update the stats of {} tracks and create {} playlists.",
+            stats.len(),
+            playlists.len()
+        );
+    } else {
+        let summary = apply_library_import(
+            main_db,
+            options.node_id,
+            &stats,
+            &playlists,
+            IMPORT_PLAYLIST_GROUP,
+        )
+        .await
+        .context("Import failed")?;
+
+        println!(
+            "Updated the stats of {} tracks, created {} playlists with {} tracks in group {IMPORT_PLAYLIST_GROUP}.",
+            summary.stats_updated, summary.playlists_created, summary.playlist_items
+        );
+        if summary.playlists_skipped > 0 {
+            println!(
+                "Skipped {} playlists already imported.",
+                summary.playlists_skipped
+            );
+        }
+    }
+
+    let enough = match_rate >= options.min_match as f64;
+    if !enough {
+        eprintln!(
+            "Only {match_rate:.1}% of the tracks matched, below the {}% required",
+            options.min_match
+        );
+    }
+
+    Ok(enough)
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use anyhow::Result;
+use prettytable::{Table, format, row};
+use serde::Serialize;
+
+use database::actions::metadata::get_metadata_summary_by_file_ids;
+use database::connection::MainDbConnection;
+
+use crate::output::{OutputFormat, is_lossy, print_csv, print_json};
+
+#[derive(Debug, Serialize)]
+struct InfoRecord {
+    id: i32,
+    /// Path relative to the library root
+    path: String,
+    /// Whether the file name was not valid UTF-8, and `path` is not exact
+    path_lossy: bool,
+    artist: String,
+    album: String,
+    genre: String,
+    title: String,
+    track_number: i32,
+    duration: f64,
+    cover_art_id: Option<i32>,
+    file_hash: String,
+}
+
+/// Prints the metadata summaries of the files. Returns whether any of the
+/// files exists.
+pub async fn show_info(
+    main_db: &MainDbConnection,
+    file_ids: &[i32],
+    output: OutputFormat,
+) -> Result<bool> {
+    let records: Vec<InfoRecord> = get_metadata_summary_by_file_ids(main_db, file_ids.to_vec())
+        .await?
+        .into_iter()
+        .map(|summary| {
+            let path = Path::new(&summary.directory)
+                .join(&summary.file_name)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            InfoRecord {
+                id: summary.id,
+                path_lossy: is_lossy(&path),
+                path,
+                artist: summary.artist,
+                album: summary.album,
+                genre: summary.genre,
+                title: summary.title,
+                track_number: summary.track_number,
+                duration: summary.duration,
+                cover_art_id: summary.cover_art_id,
+                file_hash: summary.file_hash,
+            }
+        })
+        .collect();
+
+    match output {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row![
+                "ID",
+                "Artist",
+                "Album",
+                "Title",
+                "Track Number",
+                "Duration",
+                "Cover Art ID"
+            ]);
+            for record in &records {
+                table.add_row(row![
+                    record.id,
+                    record.artist,
+                    record.album,
+                    record.title,
+                    record.track_number,
+                    record.duration,
+                    record.cover_art_id.unwrap_or_default()
+                ]);
+            }
+            table.printstd();
+        }
+        OutputFormat::Json => print_json(&records)?,
+        OutputFormat::Csv => print_csv(&records)?,
+    }
+
+    Ok(!records.is_empty())
+}
@@ -1,5 +1,27 @@
 pub mod analysis;
+pub mod cover;
+pub mod db;
+pub mod dedupe;
+pub mod export;
+pub mod identify;
+pub mod import;
 pub mod index;
+pub mod info;
+pub mod liked;
+pub mod lyrics;
+pub mod m3u8;
+pub mod meta;
 pub mod mix;
+pub mod organize;
+pub mod output;
 pub mod playback;
+pub mod playlist;
+pub mod progress;
 pub mod recommend;
+pub mod scrobble;
+pub mod search;
+pub mod serve;
+pub mod stats;
+pub mod sync;
+pub mod verify;
+pub mod watch;
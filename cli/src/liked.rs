@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use prettytable::{Table, format, row};
+use serde::Serialize;
+
+use database::actions::metadata::get_metadata_summary_by_file_ids;
+use database::actions::stats::{get_all_liked_media_files, set_liked_many, set_rating};
+use database::connection::MainDbConnection;
+
+use crate::m3u8::{PathStyle, write_m3u8};
+use crate::output::{OutputFormat, print_csv, print_json};
+
+/// Likes or unlikes the files in one go. Files which don't exist are
+/// reported one by one, the others are still updated.
+pub async fn like_files(main_db: &MainDbConnection, file_ids: &[i32], liked: bool) -> Result<()> {
+    let missing = set_liked_many(main_db, file_ids, liked).await?;
+    for file_id in &missing {
+        eprintln!("File {file_id} does not exist");
+    }
+
+    println!(
+        "{} {} files.",
+        if liked { "Liked" } else { "Unliked" },
+        file_ids.len() - missing.len()
+    );
+    if !missing.is_empty() {
+        bail!("{} of {} files do not exist", missing.len(), file_ids.len());
+    }
+
+    Ok(())
+}
+
+/// Rates a file from 1 to 5, 0 clears the rating.
+pub async fn rate_file(main_db: &MainDbConnection, file_id: i32, rating: u8) -> Result<()> {
+    let rating = (rating > 0).then_some(rating as i32);
+    if set_rating(main_db, file_id, rating).await?.is_none() {
+        bail!("File {file_id} does not exist");
+    }
+
+    match rating {
+        Some(rating) => println!("Rated file {file_id} {rating}/5."),
+        None => println!("Cleared the rating of file {file_id}."),
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct LikedRecord {
+    id: i32,
+    artist: String,
+    album: String,
+    title: String,
+}
+
+pub struct ListLikedOptions<'a> {
+    pub lib_path: &'a Path,
+    pub output: OutputFormat,
+    /// Export the tracks as a playlist in this format (m3u8)
+    pub export: Option<&'a str>,
+    pub export_path: Option<&'a PathBuf>,
+    pub path_style: PathStyle,
+}
+
+pub async fn list_liked(main_db: &MainDbConnection, options: ListLikedOptions<'_>) -> Result<()> {
+    let ListLikedOptions {
+        lib_path,
+        output,
+        export,
+        export_path,
+        path_style,
+    } = options;
+
+    if let Some(export) = export {
+        if export != "m3u8" {
+            bail!("Unsupported format {export}, the only supported format is m3u8");
+        }
+        let Some(export_path) = export_path else {
+            bail!("The output file path is required to export the liked tracks");
+        };
+
+        let files = get_all_liked_media_files(main_db).await?;
+        let export = write_m3u8(lib_path, export_path, &files, path_style)?;
+        println!(
+            "Saved {} liked tracks to M3U8 file: {}",
+            files.len(),
+            export.display()
+        );
+        return Ok(());
+    }
+
+    let files = get_all_liked_media_files(main_db).await?;
+    let file_ids = files.iter().map(|x| x.id).collect();
+    let records: Vec<LikedRecord> = get_metadata_summary_by_file_ids(main_db, file_ids)
+        .await?
+        .into_iter()
+        .map(|summary| LikedRecord {
+            id: summary.id,
+            artist: summary.artist,
+            album: summary.album,
+            title: summary.title,
+        })
+        .collect();
+
+    match output {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["ID", "Artist", "Album", "Title"]);
+            for record in records {
+                table.add_row(row![record.id, record.artist, record.album, record.title]);
+            }
+            table.printstd();
+        }
+        OutputFormat::Json => print_json(&records)?,
+        OutputFormat::Csv => print_csv(&records)?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use prettytable::{Table, format, row};
+use serde::Serialize;
+
+use database::actions::albums::get_media_file_ids_by_album_ids;
+use database::actions::file::{get_file_by_id, get_ordered_files_by_ids};
+use database::connection::MainDbConnection;
+use database::entities::media_files;
+use fsio::FsIo;
+use lyric::{
+    lrc::{parse_lrc, write_lrc},
+    parser::parse_audio_lyrics,
+    types::LyricFile,
+};
+use metadata::reader::get_lyrics;
+
+use crate::output::{OutputFormat, print_csv, print_json};
+
+pub struct ExportLyricsOptions<'a> {
+    pub lib_path: &'a Path,
+    pub file_ids: &'a [i32],
+    pub album_id: Option<i32>,
+    pub format: &'a str,
+    /// Sidecar files are written next to the audio files when `None`
+    pub output_dir: Option<&'a PathBuf>,
+    pub overwrite: bool,
+}
+
+/// Lyrics embedded in the file, or in a sidecar file next to it, like the
+/// player finds them.
+fn load_lyrics(path: &Path) -> Result<Option<(&'static str, LyricFile)>> {
+    if let Some(embedded) = get_lyrics(path).unwrap_or_default() {
+        return Ok(Some(("embedded", parse_lrc(&embedded)?)));
+    }
+
+    match parse_audio_lyrics(path.to_path_buf()) {
+        Some(lyric) => Ok(Some(("sidecar", lyric?))),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct LyricLineRecord {
+    /// Milliseconds from the start of the track
+    start_ms: i32,
+    end_ms: i32,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct LyricsRecord {
+    file_id: i32,
+    /// `embedded` or `sidecar`
+    source: &'static str,
+    lines: Vec<LyricLineRecord>,
+}
+
+/// Prints the lyrics of a file. Returns whether the file has lyrics.
+pub async fn show_lyrics(
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    file_id: i32,
+    output: OutputFormat,
+) -> Result<bool> {
+    let Some(file) = get_file_by_id(main_db, file_id).await? else {
+        bail!("File {file_id} does not exist");
+    };
+    let path = lib_path.join(&file.directory).join(&file.file_name);
+
+    let Some((source, lyric)) = load_lyrics(&path)? else {
+        if output == OutputFormat::Table {
+            println!("No lyrics found for {}", path.display());
+        }
+        return Ok(false);
+    };
+
+    match output {
+        OutputFormat::Table => {
+            println!("Lyrics of {} ({source})", path.display());
+
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["Time", "Text"]);
+            for line in lyric.lyrics {
+                table.add_row(row![line.start_time, line.text]);
+            }
+            table.printstd();
+        }
+        OutputFormat::Json | OutputFormat::Csv => {
+            let lines: Vec<_> = lyric
+                .lyrics
+                .into_iter()
+                .map(|line| LyricLineRecord {
+                    start_ms: line.start_time.into(),
+                    end_ms: line.end_time.into(),
+                    text: line.text,
+                })
+                .collect();
+
+            if output == OutputFormat::Csv {
+                print_csv(&lines)?;
+            } else {
+                print_json(&LyricsRecord {
+                    file_id,
+                    source,
+                    lines,
+                })?;
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+pub async fn export_lyrics(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: ExportLyricsOptions<'_>,
+) -> Result<()> {
+    let ExportLyricsOptions {
+        lib_path,
+        file_ids,
+        album_id,
+        format,
+        output_dir,
+        overwrite,
+    } = options;
+
+    if format != "lrc" {
+        bail!("Unsupported format. The only supported format is 'lrc'.");
+    }
+
+    let mut file_ids = file_ids.to_vec();
+    if let Some(album_id) = album_id {
+        match get_media_file_ids_by_album_ids(main_db, &[album_id])
+            .await?
+            .remove(&album_id)
+        {
+            Some(album_file_ids) => file_ids.extend(album_file_ids),
+            None => bail!("Album {album_id} does not exist"),
+        }
+    }
+    if file_ids.is_empty() {
+        bail!("Pass --file-ids or --album to pick the tracks to export.");
+    }
+
+    let files: Vec<media_files::Model> = get_ordered_files_by_ids(main_db, &file_ids).await?;
+    if files.len() < file_ids.len() {
+        eprintln!(
+            "Warning: {} files do not exist",
+            file_ids.len() - files.len()
+        );
+    }
+
+    let mut exported = 0;
+    for file in files {
+        let relative_path = Path::new(&file.directory).join(&file.file_name);
+        let path = lib_path.join(&relative_path);
+
+        let lyric = match load_lyrics(&path) {
+            Ok(Some((_, lyric))) => lyric,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("Failed to read the lyrics of {}: {e:#}", path.display());
+                continue;
+            }
+        };
+
+        let mut target = match output_dir {
+            Some(output_dir) => output_dir.join(&relative_path),
+            None => path.clone(),
+        };
+        target.set_extension("lrc");
+
+        if !overwrite && fsio.exists(&target)? {
+            println!("Skipping {}, it already exists", target.display());
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            fsio.create_dir_all(parent)?;
+        }
+        fsio.write_string(&target, &write_lrc(&lyric)).await?;
+        exported += 1;
+    }
+
+    println!("Exported lyrics of {exported} files.");
+
+    Ok(())
+}
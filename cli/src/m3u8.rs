@@ -0,0 +1,66 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+
+use database::entities::media_files;
+
+use crate::recommend::check_and_correct_extension;
+
+/// How the entries of an exported playlist point to the files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PathStyle {
+    /// Relative to the playlist file, so both can be moved together
+    #[default]
+    Relative,
+    /// Absolute paths, for players that don't resolve relative entries
+    Absolute,
+}
+
+/// Path written for `file` in a playlist stored in `playlist_dir`.
+pub fn entry_path(
+    lib_path: &Path,
+    playlist_dir: &Path,
+    file: &media_files::Model,
+    style: PathStyle,
+) -> Option<PathBuf> {
+    let file_path = lib_path.join(&file.directory).join(&file.file_name);
+
+    match style {
+        PathStyle::Relative => pathdiff::diff_paths(&file_path, playlist_dir),
+        PathStyle::Absolute => Some(file_path),
+    }
+}
+
+/// Writes `files` to an M3U8 playlist, correcting the extension of
+/// `output` if needed. Returns the path of the written playlist.
+pub fn write_m3u8(
+    lib_path: &Path,
+    output: &Path,
+    files: &[media_files::Model],
+    style: PathStyle,
+) -> Result<PathBuf> {
+    // Relative entries can only be computed between absolute paths
+    let output = std::path::absolute(output)?;
+    let corrected_path = check_and_correct_extension(&output, "m3u8");
+    if corrected_path != output {
+        eprintln!("Warning: Output file extension corrected to .m3u8");
+    }
+
+    let playlist_dir = corrected_path.parent().unwrap_or(Path::new("/"));
+    fs::create_dir_all(playlist_dir).context("Failed to create directories")?;
+
+    let mut playlist = File::create(&corrected_path)
+        .with_context(|| format!("Failed to create {}", corrected_path.display()))?;
+    playlist.write_all("#EXTM3U\n".as_bytes())?;
+
+    for file in files {
+        let entry = entry_path(lib_path, playlist_dir, file, style)
+            .context("Failed to calculate relative path")?;
+        writeln!(playlist, "{}", entry.display())?;
+    }
+
+    Ok(corrected_path)
+}
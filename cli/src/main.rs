@@ -1,28 +1,65 @@
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{fs, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use clap::{Parser, Subcommand};
 use directories::ProjectDirs;
 use dunce::canonicalize;
 use log::{error, info};
-use prettytable::{Table, row};
 use tracing_subscriber::filter::EnvFilter;
 
 use database::{
     actions::{
         cover_art::scan_cover_arts,
-        metadata::{empty_progress_callback, get_metadata_summary_by_file_ids, scan_audio_library},
-        search::search_for,
+        dedup::{KeepPreference, RemovalMode},
+        library_export::ExportCategory,
+        library_stats::RankBy,
+        metadata::{empty_progress_callback, scan_audio_library},
+    },
+    connection::{
+        LockMode, connect_main_db, connect_recommendation_db, get_storage_info, lock_library,
+        open_main_db, resolve_library_read_only,
     },
-    connection::{connect_main_db, connect_recommendation_db},
 };
 use fsio::FsIo;
+use hub::server::{
+    limits::ConnectionLimits,
+    runner::ServeOptions,
+    utils::bind::{DEFAULT_SERVER_PORT, ServerLayout},
+};
+use metadata::cover_art::{EmbedFormat, EmbedOptions};
 
 use rune::{
     analysis::*,
+    cover::{
+        EmbedCoverOptions, SetCoverOptions, embed_album_covers, extract_album_cover,
+        list_missing_covers, set_album_cover,
+    },
+    db::{db_backup, db_migrate, db_vacuum, db_verify},
+    dedupe::{DedupeReportOptions, dedupe_apply, dedupe_match, dedupe_report},
+    export::{ExportOptions, export_library_data},
+    identify::{IdentifyOptions, IdentifyTarget, identify_tracks},
+    import::{ImportOptions, ImportSource, LOW_MATCH_EXIT_CODE, import_library_data},
     index::index_audio_library,
-    mix::{RecommendMixOptions, mixes},
+    info::show_info,
+    liked::{ListLikedOptions, like_files, list_liked, rate_file},
+    lyrics::{ExportLyricsOptions, export_lyrics, show_lyrics},
+    m3u8::PathStyle,
+    meta::{
+        InferMetadataOptions, SetMetadataOptions, infer_metadata, set_metadata, show_metadata,
+    },
+    mix::{EvalMixOptions, RecommendMixOptions, eval_mix, list_mixes, mixes, show_mix},
+    organize::{OrganizePreviewOptions, organize_apply, organize_preview},
+    output::{NO_RESULTS_EXIT_CODE, OutputFormat},
     playback::*,
+    playlist::*,
+    progress::ProgressMode,
     recommend::*,
+    scrobble::{ScrobbleOptions, scrobble_login, scrobble_logout, scrobble_status},
+    search::search_library,
+    serve::{LogFile, ServeLibraryOptions, serve_library},
+    stats::{StatsOptions, library_stats},
+    sync::{Direction, SYNC_PARTIAL_FAILURE_EXIT_CODE, SyncOptions, sync_with_peer},
+    verify::{VerifyFix, VerifyOptions, verify_files},
+    watch::{WatchOptions, watch_library},
 };
 use uuid::Uuid;
 
@@ -34,6 +71,23 @@ struct Cli {
     #[arg()]
     library: Option<PathBuf>,
 
+    /// The format of the output of the read commands (table, json or csv)
+    ///
+    /// search prints an array of {collection_type, id, name, score}, a
+    /// higher score being a better match. info prints an array of {id, path,
+    /// path_lossy, artist, album, genre, title, track_number, duration,
+    /// cover_art_id, file_hash}. meta show prints an array of {file_id, path,
+    /// path_lossy, fields}, and lyrics show prints {file_id, source, lines},
+    /// each line being {start_ms, end_ms, text}. CSV has the same columns,
+    /// with one row per field for meta show and one row per line for lyrics
+    /// show. path_lossy is true when the file name was not valid UTF-8 and
+    /// path is not exact. playlist, dedupe and stats print JSON as with
+    /// their --json flag, and don't support CSV.
+    ///
+    /// Read commands exit with 3 when they find nothing, 1 on errors.
+    #[arg(long, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
     /// The subcommand to run
     #[command(subcommand)]
     command: Commands,
@@ -48,10 +102,25 @@ enum Commands {
     Index,
 
     /// Analyze the audio files in the library
+    ///
+    /// Only the files not analyzed yet are processed, so an interrupted
+    /// analysis resumes where it stopped.
     Analyze {
         /// The compute device to use (cpu/gpu)
         #[arg(short, long, default_value = "gpu")]
         computing_device: String,
+
+        /// Analyze again the files which failed before
+        #[arg(long)]
+        retry_failed: bool,
+
+        /// Do not show the progress or the summary
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// Print the progress as one JSON object per line
+        #[arg(long, conflicts_with = "quiet")]
+        json_progress: bool,
     },
 
     /// Show information of the track in the library
@@ -70,6 +139,11 @@ enum Commands {
         /// The ID of the file to play (used with playById mode)
         #[arg(short, long)]
         id: Option<i32>,
+
+        /// Play without the keyboard controls (space pauses, n and p skip,
+        /// the arrows seek, + and - change the volume, q quits), for scripts
+        #[arg(long)]
+        no_tty: bool,
     },
 
     /// Recommend music
@@ -82,6 +156,24 @@ enum Commands {
         #[arg(short = 'p', long, group = "recommend_group")]
         file_path: Option<PathBuf>,
 
+        /// A search query the recommendations are based on, e.g. `artist:four
+        /// tet`, recommending around every track of the best match
+        #[arg(short, long, group = "recommend_group")]
+        query: Option<String>,
+
+        /// Use the best match of a query matching several items
+        #[arg(long, requires = "query")]
+        first: bool,
+
+        /// Use the match of a query with this ID, among those listed when
+        /// the query is ambiguous
+        #[arg(long, requires = "query", conflicts_with = "first")]
+        seed_id: Option<i64>,
+
+        /// Leave the tracks the recommendations are based on out of them
+        #[arg(long)]
+        exclude_seeds: bool,
+
         /// The number of recommendations to retrieve
         #[arg(short, long, default_value_t = 10)]
         num: usize,
@@ -95,11 +187,12 @@ enum Commands {
         output: Option<PathBuf>,
     },
 
-    /// Recommend mixes
+    /// Recommend mixes, or inspect and evaluate the saved mixes
+    #[command(args_conflicts_with_subcommands = true)]
     Mix {
         /// The mix parameters to get recommendations for
         #[arg(short, long)]
-        mix_parameters: String,
+        mix_parameters: Option<String>,
 
         /// The number of recommendations to retrieve
         #[arg(short, long, default_value_t = 10)]
@@ -112,6 +205,9 @@ enum Commands {
         /// The output file path (required if format is specified)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: Option<MixCommands>,
     },
 
     /// Search the audio library
@@ -124,6 +220,784 @@ enum Commands {
         #[arg(short, long, default_value_t = 10)]
         num: usize,
     },
+
+    /// Manage playlists
+    Playlist {
+        /// Print JSON instead of tables
+        #[arg(long, global = true)]
+        json: bool,
+
+        #[command(subcommand)]
+        command: PlaylistCommands,
+    },
+
+    /// Extract, set and find missing album cover arts
+    Cover {
+        #[command(subcommand)]
+        command: CoverCommands,
+    },
+
+    /// Identify tracks from their audio and optionally fix their metadata
+    ///
+    /// Exits with 3 when nothing matches and with 5 when the recognition
+    /// service can't be reached.
+    Identify {
+        /// The audio file, or the directory to identify with --batch
+        #[arg(required_unless_present = "file_id", conflicts_with = "file_id")]
+        path: Option<PathBuf>,
+
+        /// The ID of a file of the library to identify
+        #[arg(long)]
+        file_id: Option<i32>,
+
+        /// Identify every audio file at or below the directory
+        #[arg(long, requires = "path")]
+        batch: bool,
+
+        /// The number of excerpts sent per file, more make the confidence
+        /// meaningful at the cost of more requests
+        #[arg(long, default_value_t = 1)]
+        excerpts: usize,
+
+        /// Write the matches into the metadata of the library
+        #[arg(long)]
+        apply: bool,
+
+        /// Also write the matches into the tags of the files
+        #[arg(long, requires = "apply")]
+        write_tags: bool,
+
+        /// The seconds to wait between two files of a batch
+        #[arg(long, default_value_t = 5)]
+        delay: u64,
+    },
+
+    /// Show or edit the metadata of tracks
+    Meta {
+        #[command(subcommand)]
+        command: MetaCommands,
+    },
+
+    /// Find and remove duplicate tracks
+    Dedupe {
+        /// Print JSON instead of tables
+        #[arg(long, global = true)]
+        json: bool,
+
+        #[command(subcommand)]
+        command: DedupeCommands,
+    },
+
+    /// Rename and move tracks after their metadata
+    Organize {
+        /// Print JSON instead of tables
+        #[arg(long, global = true)]
+        json: bool,
+
+        #[command(subcommand)]
+        command: OrganizeCommands,
+    },
+
+    /// Like tracks
+    Like {
+        /// The IDs of the files to like
+        #[arg(required = true, num_args = 1..)]
+        file_ids: Vec<i32>,
+    },
+
+    /// Unlike tracks
+    Unlike {
+        /// The IDs of the files to unlike
+        #[arg(required = true, num_args = 1..)]
+        file_ids: Vec<i32>,
+    },
+
+    /// Rate a track
+    Rate {
+        /// The ID of the file to rate
+        file_id: i32,
+
+        /// The rating from 1 to 5, 0 clears it
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=5))]
+        rating: u8,
+    },
+
+    /// List and export the liked tracks
+    Liked {
+        #[command(subcommand)]
+        command: LikedCommands,
+    },
+
+    /// Show and export lyrics
+    Lyrics {
+        #[command(subcommand)]
+        command: LyricsCommands,
+    },
+
+    /// Show library statistics and the most played tracks and artists
+    Stats {
+        /// The number of top tracks and artists to list
+        #[arg(short, long, default_value_t = 10)]
+        top: u64,
+
+        /// Rank by play count or by time spent listening (plays or time)
+        #[arg(short, long, default_value_t = RankBy::Plays)]
+        by: RankBy,
+
+        /// Also add up the size of the files, which reads every file
+        #[arg(long)]
+        with_size: bool,
+
+        /// Print JSON instead of tables
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Export the stats, playlists and mixes of the library to a file
+    Export {
+        /// The format of the export (json)
+        #[arg(short, long, default_value = "json")]
+        format: String,
+
+        /// The output file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// The categories to export, separated by commas (tracks, stats,
+        /// playlists, mixes), every one if omitted
+        #[arg(long, value_delimiter = ',')]
+        include: Vec<ExportCategory>,
+
+        /// The categories not to export, separated by commas
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<ExportCategory>,
+
+        /// Do not show the progress
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Import the stats and playlists of another player
+    ///
+    /// Tracks are matched with the files of the library by path. Exits with
+    /// 4 when fewer tracks than --min-match matched.
+    Import {
+        /// The player the library file comes from (itunes)
+        #[arg(long)]
+        from: ImportSource,
+
+        /// The library file of the player
+        #[arg()]
+        file: PathBuf,
+
+        /// Only report what would be imported
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Write the unmatched tracks to this file instead of stderr
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// The percentage of tracks which must match
+        #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_match: u8,
+
+        /// Do not show the progress
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Check the files of the library against the disk
+    Verify {
+        /// Compare the content of the files with the hash recorded by the
+        /// last scan, which reads every file
+        #[arg(long)]
+        hash: bool,
+
+        /// Save the problems found as JSON to a file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Fix the problems found (missing=remove)
+        #[arg(long)]
+        fix: Option<VerifyFix>,
+    },
+
+    /// Keep the library up to date as files change, until stopped
+    Watch {
+        /// Analyze the files added or changed
+        #[arg(long)]
+        analyze: bool,
+
+        /// The compute device used to analyze (cpu/gpu)
+        #[arg(short, long, default_value = "gpu")]
+        computing_device: String,
+
+        /// Only log what would be done
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Seconds the library must stay quiet before changes are handled
+        #[arg(long, default_value_t = 5)]
+        settle: u64,
+    },
+
+    /// Sync the library with another Rune device
+    ///
+    /// The peer must be trusted on this device, which presents its client
+    /// certificate. Exits with 2 when some tables failed to sync, 1 when the
+    /// peer could not be reached.
+    Sync {
+        /// The peer, as an rnsrv:// URL, a URL or a host with an optional
+        /// port
+        #[arg(long)]
+        peer: String,
+
+        /// Which way the changes go
+        #[arg(long, value_enum, default_value_t = Direction::Bidirectional)]
+        direction: Direction,
+
+        /// Only sync these tables, separated by commas
+        #[arg(long, value_delimiter = ',')]
+        tables: Vec<String>,
+
+        /// Only print how many records each side would send
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Serve the library to other devices, as rune-server does
+    ///
+    /// Runs in the foreground until Ctrl-C or SIGTERM, which finish the
+    /// requests being handled before exiting. With --log-file the logs are
+    /// appended to the file, which is reopened on SIGHUP.
+    Serve {
+        /// Address, IP or interface name to listen on, can be repeated
+        #[arg(short, long, default_value = "127.0.0.1")]
+        addr: Vec<String>,
+
+        /// Port used for addresses given without one
+        #[arg(short, long, default_value_t = DEFAULT_SERVER_PORT)]
+        port: u16,
+
+        /// Accept WebSocket connections on a separate port
+        #[arg(long)]
+        ws_port: Option<u16>,
+
+        /// Serve every route below this path, e.g. `/rune` behind a reverse
+        /// proxy
+        #[arg(long, default_value = "")]
+        path_prefix: String,
+
+        /// Refuse to start if any of the addresses cannot be bound
+        #[arg(long)]
+        strict_bind: bool,
+
+        /// Announce the server on the local network while it runs
+        #[arg(long)]
+        broadcast: bool,
+
+        /// Serve Prometheus metrics over plain HTTP on this address
+        #[arg(long, value_name = "ADDR")]
+        metrics: Option<SocketAddr>,
+
+        /// Record read-only requests in the audit log as well
+        #[arg(long)]
+        audit_verbose: bool,
+
+        /// Append the logs to this file instead of the terminal
+        #[arg(long)]
+        log_file: Option<PathBuf>,
+
+        /// Write the process id to this file while the server runs
+        #[arg(long)]
+        pid_file: Option<PathBuf>,
+    },
+
+    /// Log in to the scrobbling services the app submits plays to
+    ///
+    /// The credentials are stored encrypted in the settings of the app, which
+    /// logs in with them on its next start. The library is not needed. Logging
+    /// in and out is refused while the app is running.
+    Scrobble {
+        /// The directory of the settings of the app, the platform default if
+        /// unset
+        #[arg(long, global = true)]
+        settings_dir: Option<PathBuf>,
+
+        /// The profile of the app, as given to its --profile
+        #[arg(long, global = true, default_value = "default")]
+        profile: String,
+
+        #[command(subcommand)]
+        command: ScrobbleCommands,
+    },
+
+    /// Maintain the databases of the library
+    Db {
+        /// Wait for other Rune processes to close the library instead of
+        /// failing
+        #[arg(long, global = true)]
+        wait: bool,
+
+        /// Only print problems
+        #[arg(short, long, global = true)]
+        quiet: bool,
+
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Reclaim the space left by deleted rows
+    Vacuum,
+
+    /// Check the integrity of the databases, failing on any problem
+    Verify,
+
+    /// Copy the databases and a manifest into a new directory
+    Backup {
+        /// The directory the backup is created in
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Apply or roll back migrations until the given one is the newest
+    ///
+    /// Without --to, prints the schema version and the pending migrations.
+    /// Rolling back drops the tables and columns added since, back up the
+    /// databases first.
+    Migrate {
+        /// The migration to stop at, by name or by a unique prefix like
+        /// m20251018_000031
+        #[arg(long)]
+        to: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScrobbleCommands {
+    /// Log in to a service (lastfm, librefm, listenbrainz or gnufm:<id>)
+    ///
+    /// The password, and the API key and secret of Last.fm, are asked for
+    /// unless set in RUNE_SCROBBLE_PASSWORD, RUNE_SCROBBLE_API_KEY and
+    /// RUNE_SCROBBLE_API_SECRET.
+    Login {
+        /// The service to log in to
+        #[arg()]
+        service: String,
+
+        /// The username, read from RUNE_SCROBBLE_USERNAME or asked for if
+        /// unset
+        #[arg(short, long)]
+        username: Option<String>,
+
+        /// The API URL of a Libre.fm compatible server, required for GNU FM
+        /// servers
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Allow a server URL using plain HTTP, for servers on the local
+        /// network
+        #[arg(long)]
+        allow_insecure: bool,
+    },
+
+    /// Show the services the app is logged in to
+    Status {
+        /// Log in to each service to check the credentials still work
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Remove the credentials of a service
+    Logout {
+        /// The service to log out of
+        #[arg()]
+        service: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum LyricsCommands {
+    /// Show the lyrics of a track with their timestamps
+    Show {
+        /// The ID of the file
+        #[arg()]
+        file_id: i32,
+    },
+
+    /// Write lyrics to sidecar files
+    Export {
+        /// The IDs of the files to export
+        #[arg(short, long, num_args = 1.., value_delimiter = ',')]
+        file_ids: Vec<i32>,
+
+        /// Export every track of an album
+        #[arg(short, long)]
+        album: Option<i32>,
+
+        /// The format of the output (lrc)
+        #[arg(short = 'F', long, default_value = "lrc")]
+        format: String,
+
+        /// Mirror the library below this directory instead of writing next
+        /// to the audio files
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Replace existing sidecar files
+        #[arg(long)]
+        overwrite: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DedupeCommands {
+    /// Print the groups of duplicates and the file kept in each
+    Report {
+        /// The minimum fingerprint similarity of duplicates
+        #[arg(short, long, default_value_t = 0.9)]
+        threshold: f32,
+
+        /// The file to keep (sample-rate, lossless, bitrate or oldest)
+        #[arg(short, long, default_value_t = KeepPreference::SampleRate)]
+        prefer: KeepPreference,
+
+        /// Save the plan to a file, to apply it later
+        #[arg(short, long)]
+        save: Option<PathBuf>,
+    },
+
+    /// Match the content of the tracks with each other, to find the same
+    /// recording in different encodings or trimmed
+    Match {
+        /// Do not show the progress
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Remove the duplicates of a saved plan
+    Apply {
+        /// The plan saved by `dedupe report --save`
+        #[arg(short, long)]
+        plan: PathBuf,
+
+        /// Move the duplicates to the recycle bin (default)
+        #[arg(long, conflicts_with = "delete")]
+        trash: bool,
+
+        /// Delete the duplicates permanently
+        #[arg(long)]
+        delete: bool,
+
+        /// Confirm the removal
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum OrganizeCommands {
+    /// Print where a template moves files, without moving them
+    Preview {
+        /// The template, e.g. '{album_artist}/{year} - {album}/{disc}-{track:02} {title}.{ext}'
+        template: String,
+
+        /// The IDs of the files, separated by commas or spaces
+        #[arg(short, long, num_args = 1.., value_delimiter = ',', required = true)]
+        file_ids: Vec<i32>,
+
+        /// Save the plan to a file, to apply it later
+        #[arg(short, long)]
+        save: Option<PathBuf>,
+    },
+
+    /// Move the files of a saved plan
+    Apply {
+        /// The plan saved by `organize preview --save`
+        #[arg(short, long)]
+        plan: PathBuf,
+
+        /// Confirm the moves
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CoverCommands {
+    /// Write the cover art of an album to a directory at full resolution
+    Extract {
+        /// The ID of the album
+        #[arg(long)]
+        album: i32,
+
+        /// The directory to write the image to
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+
+    /// Use an image as the cover art of an album
+    Set {
+        /// The ID of the album
+        #[arg(long)]
+        album: i32,
+
+        /// The image to use
+        #[arg(long)]
+        image: PathBuf,
+
+        /// Also embed the image into the tags of the tracks (MP3, FLAC and
+        /// M4A)
+        #[arg(long)]
+        embed: bool,
+
+        /// The longest side of the embedded image, in pixels
+        #[arg(long, default_value_t = EmbedOptions::default().max_dimension)]
+        max_size: u32,
+
+        /// The format of the embedded image (jpeg or png)
+        #[arg(long, default_value_t = EmbedOptions::default().format)]
+        format: EmbedFormat,
+    },
+
+    /// Embed the cover art of albums into the tags of their tracks (MP3,
+    /// FLAC and M4A)
+    ///
+    /// Files already holding the cover art are left untouched.
+    Embed {
+        /// The IDs of the albums, separated by commas
+        #[arg(long, value_delimiter = ',', required = true)]
+        albums: Vec<i32>,
+
+        /// The longest side of the embedded image, in pixels
+        #[arg(long, default_value_t = EmbedOptions::default().max_dimension)]
+        max_size: u32,
+
+        /// The format of the embedded image (jpeg or png)
+        #[arg(long, default_value_t = EmbedOptions::default().format)]
+        format: EmbedFormat,
+
+        /// Do not show the progress
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// List the albums without cover art
+    Missing,
+}
+
+#[derive(Subcommand)]
+enum LikedCommands {
+    /// List the liked tracks
+    List {
+        /// Export the liked tracks as a playlist in this format (m3u8)
+        #[arg(long)]
+        export: Option<String>,
+
+        /// The output file path of the export
+        #[arg(short, long, requires = "export")]
+        output: Option<PathBuf>,
+
+        /// How entries point to the files
+        #[arg(long, value_enum, default_value_t = PathStyle::Relative)]
+        path_style: PathStyle,
+    },
+}
+
+#[derive(Subcommand)]
+enum MixCommands {
+    /// List the saved mixes
+    List,
+
+    /// Show the operators and parameters of a mix
+    Show {
+        /// The ID of the mix
+        id: i32,
+    },
+
+    /// Run the queries of a mix and print or export the tracks
+    Eval {
+        /// The ID of the mix
+        id: i32,
+
+        /// The number of tracks to retrieve
+        #[arg(short, long, default_value_t = 50)]
+        num: usize,
+
+        /// The format of the export (m3u8)
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// The output file path (required if format is specified)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Show how many tracks each stage of the query matched
+        #[arg(long)]
+        explain: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MetaCommands {
+    /// Show the editable metadata of files
+    Show {
+        /// The IDs of the files, separated by commas or spaces
+        #[arg(short, long, num_args = 1.., value_delimiter = ',', required = true)]
+        file_ids: Vec<i32>,
+    },
+
+    /// Set the metadata of files
+    Set {
+        /// The IDs of the files, separated by commas or spaces
+        #[arg(short, long, num_args = 1.., value_delimiter = ',', required = true)]
+        file_ids: Vec<i32>,
+
+        /// The track title
+        #[arg(long)]
+        title: Option<String>,
+
+        /// The artist
+        #[arg(long)]
+        artist: Option<String>,
+
+        /// The album
+        #[arg(long)]
+        album: Option<String>,
+
+        /// The album artist
+        #[arg(long)]
+        album_artist: Option<String>,
+
+        /// The genre
+        #[arg(long)]
+        genre: Option<String>,
+
+        /// The track number
+        #[arg(long)]
+        track_number: Option<String>,
+
+        /// The disc number
+        #[arg(long)]
+        disc_number: Option<String>,
+
+        /// The release date
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Parse values from file names, e.g. '{artist} - {title}'
+        #[arg(long)]
+        from_filename: Option<String>,
+
+        /// Also write the new values into the tags of the files
+        #[arg(long)]
+        write_tags: bool,
+
+        /// Print the changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Infer metadata from the paths of files, e.g.
+    /// '{artist}/{year} - {album}/{track} - {title}'
+    Infer {
+        /// The pattern, with {artist}, {album}, {year}, {track}, {title} or
+        /// {disc} placeholders
+        pattern: String,
+
+        /// The IDs of the files, separated by commas or spaces
+        #[arg(short, long, num_args = 1.., value_delimiter = ',', required = true)]
+        file_ids: Vec<i32>,
+
+        /// Apply the inferred values, they are only previewed otherwise
+        #[arg(long)]
+        apply: bool,
+
+        /// Also write the new values into the tags of the files
+        #[arg(long, requires = "apply")]
+        write_tags: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlaylistCommands {
+    /// List all playlists
+    List,
+
+    /// Show the files of a playlist
+    Show {
+        /// The ID of the playlist
+        #[arg()]
+        id: i32,
+    },
+
+    /// Create an empty playlist
+    Create {
+        /// The name of the playlist
+        #[arg()]
+        name: String,
+
+        /// The group of the playlist
+        #[arg(short, long, default_value = "Favorite")]
+        group: String,
+    },
+
+    /// Append files to a playlist
+    Add {
+        /// The ID of the playlist
+        #[arg()]
+        id: i32,
+
+        /// The IDs of the files to append
+        #[arg(short, long, num_args = 1.., required = true)]
+        file_ids: Vec<i32>,
+    },
+
+    /// Remove a file from a playlist
+    RemoveItem {
+        /// The ID of the playlist
+        #[arg()]
+        id: i32,
+
+        /// The ID of the file to remove
+        #[arg(short, long)]
+        file_id: i32,
+
+        /// The position of the item, the first occurrence of the file is
+        /// removed if omitted
+        #[arg(short, long)]
+        position: Option<i32>,
+    },
+
+    /// Delete a playlist
+    Delete {
+        /// The ID of the playlist
+        #[arg()]
+        id: i32,
+    },
+
+    /// Export a playlist to a file
+    Export {
+        /// The ID of the playlist
+        #[arg()]
+        id: i32,
+
+        /// The format of the output (m3u8)
+        #[arg(short, long, default_value = "m3u8")]
+        format: String,
+
+        /// The output file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// How entries point to the files
+        #[arg(long, value_enum, default_value_t = PathStyle::Relative)]
+        path_style: PathStyle,
+    },
 }
 
 #[tokio::main]
@@ -134,12 +1008,141 @@ async fn main() {
         "symphonia_format_ogg=off,symphonia_core=off,symphonia_bundle_mp3::demuxer=off,tantivy::directory=off,tantivy::indexer=off,sea_orm_migration::migrator=off,info",
     );
 
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_test_writer()
-        .init();
+    if let Err(e) = rustls::crypto::ring::default_provider().install_default() {
+        eprintln!("Failed to install the TLS provider: {e:?}");
+        std::process::exit(1);
+    }
+
+    // The server opens the library itself, with its own lock
+    if let Commands::Serve {
+        addr,
+        port,
+        ws_port,
+        path_prefix,
+        strict_bind,
+        broadcast,
+        metrics,
+        audit_verbose,
+        log_file,
+        pid_file,
+    } = cli.command
+    {
+        let log_file = match log_file.as_deref().map(LogFile::open).transpose() {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        };
+        match &log_file {
+            Some(log_file) => {
+                let log_file = log_file.clone();
+                tracing_subscriber::fmt()
+                    .with_env_filter(filter)
+                    .with_ansi(false)
+                    .with_writer(move || log_file.clone())
+                    .init();
+            }
+            None => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        }
+
+        let path = cli.library.expect("Path is required");
+        let lib_path = match canonicalize(&path) {
+            Ok(path) => path.to_string_lossy().into_owned(),
+            Err(e) => {
+                eprintln!("Failed to canonicalize path: {e}");
+                std::process::exit(1);
+            }
+        };
+
+        let result = serve_library(ServeLibraryOptions {
+            lib_path: &lib_path,
+            server: ServeOptions {
+                addr,
+                layout: ServerLayout::new(port, ws_port, &path_prefix),
+                strict_bind,
+                limits: ConnectionLimits::default(),
+                audit_verbose,
+                metrics_addr: metrics,
+                broadcast,
+            },
+            log_file,
+            pid_file: pid_file.as_deref(),
+        })
+        .await;
+
+        if let Err(e) = result {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if cli.output == OutputFormat::Table {
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_test_writer()
+            .init();
+    } else {
+        // Keeps the standard output parsable
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .init();
+    }
+
+    if let Commands::Scrobble {
+        settings_dir,
+        profile,
+        command,
+    } = &cli.command
+    {
+        let options = ScrobbleOptions {
+            settings_dir: settings_dir.as_deref(),
+            profile,
+        };
+        let result = match command {
+            ScrobbleCommands::Login {
+                service,
+                username,
+                url,
+                allow_insecure,
+            } => {
+                scrobble_login(
+                    service,
+                    username.as_deref(),
+                    url.as_deref(),
+                    *allow_insecure,
+                    options,
+                )
+                .await
+            }
+            ScrobbleCommands::Status { check } => scrobble_status(*check, options).await,
+            ScrobbleCommands::Logout { service } => scrobble_logout(service, options).await,
+        };
+
+        if let Err(e) = result {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Determine the path from either the option or the positional argument
     let path = cli.library.expect("Path is required");
+    let output = cli.output;
+    if output == OutputFormat::Csv
+        && matches!(
+            cli.command,
+            Commands::Playlist { .. }
+                | Commands::Dedupe { .. }
+                | Commands::Organize { .. }
+                | Commands::Stats { .. }
+        )
+    {
+        eprintln!("This command does not support CSV output");
+        std::process::exit(1);
+    }
 
     let proj_dirs = ProjectDirs::from("ci", "not", "rune").unwrap();
 
@@ -206,7 +1209,68 @@ async fn main() {
     }
     .to_string();
 
-    let main_db = match connect_main_db(&fsio, lib_path, None, &node_id).await {
+    // Read-only libraries keep their databases in the config directory
+    let read_only = match resolve_library_read_only(&fsio, lib_path, config_path.to_str()).await {
+        Ok(read_only) => read_only,
+        Err(e) => {
+            error!("Failed to locate the databases: {e}");
+            return;
+        }
+    };
+    let db_path = read_only.then(|| config_path.to_string_lossy().to_string());
+
+    // Maintenance needs the databases to itself, other commands share them
+    // with the app
+    let (lock_mode, wait) = match &cli.command {
+        Commands::Db { wait, .. } => (LockMode::Exclusive, *wait),
+        _ => (LockMode::Shared, false),
+    };
+    let storage_info = match get_storage_info(lib_path, db_path.as_deref(), read_only) {
+        Ok(storage_info) => storage_info,
+        Err(e) => {
+            error!("Failed to locate the databases: {e}");
+            return;
+        }
+    };
+    let _library_lock = match lock_library(&storage_info, lock_mode, false) {
+        Ok(Some(lock)) => lock,
+        Ok(None) if wait => {
+            info!("Waiting for other Rune processes to close the library");
+            match lock_library(&storage_info, lock_mode, true) {
+                Ok(Some(lock)) => lock,
+                Ok(None) => unreachable!("Waiting for a lock always acquires it"),
+                Err(e) => {
+                    eprintln!("{e:#}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Ok(None) => {
+            match lock_mode {
+                LockMode::Exclusive => {
+                    eprintln!("Another Rune process is using the library, close it or pass --wait")
+                }
+                LockMode::Shared => eprintln!("The library is being maintained, try again later"),
+            }
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    // Migrating applies or rolls back migrations itself
+    let main_db = if let Commands::Db {
+        command: DbCommands::Migrate { .. },
+        ..
+    } = &cli.command
+    {
+        open_main_db(&fsio, lib_path, db_path.as_deref()).await
+    } else {
+        connect_main_db(&fsio, lib_path, db_path.as_deref(), &node_id).await
+    };
+    let main_db = match main_db {
         Ok(db) => db,
         Err(e) => {
             error!("Failed to connect to main database: {e}");
@@ -214,7 +1278,7 @@ async fn main() {
         }
     };
 
-    let analysis_db = match connect_recommendation_db(&fsio, lib_path, None).await {
+    let analysis_db = match connect_recommendation_db(&fsio, lib_path, db_path.as_deref()).await {
         Ok(db) => db,
         Err(e) => {
             error!("Failed to connect to analysis database: {e}");
@@ -242,58 +1306,54 @@ async fn main() {
         Commands::Index => {
             index_audio_library(&main_db, &node_id).await;
         }
-        Commands::Analyze { computing_device } => {
-            analyze_audio_library(
-                computing_device.as_str().into(),
+        Commands::Analyze {
+            computing_device,
+            retry_failed,
+            quiet,
+            json_progress,
+        } => {
+            let progress = if *quiet {
+                ProgressMode::Quiet
+            } else if *json_progress {
+                ProgressMode::Json
+            } else {
+                ProgressMode::Bar
+            };
+            let result = analyze_audio_library(
                 fsio,
                 &main_db,
                 &analysis_db,
-                &path,
-                "",
+                AnalyzeOptions {
+                    lib_path: &path,
+                    node_id: "",
+                    computing_device: computing_device.as_str().into(),
+                    retry_failed: *retry_failed,
+                    progress,
+                },
             )
             .await;
-        }
-        Commands::Info { file_ids } => {
-            match get_metadata_summary_by_file_ids(&main_db, file_ids.to_vec()).await {
-                Ok(summaries) => {
-                    let mut table = Table::new();
-                    table.add_row(row![
-                        "ID",
-                        "Artist",
-                        "Album",
-                        "Title",
-                        "Track Number",
-                        "Duration",
-                        "Cover Art ID"
-                    ]);
-
-                    for summary in summaries {
-                        table.add_row(row![
-                            summary.id,
-                            summary.artist,
-                            summary.album,
-                            summary.title,
-                            summary.track_number,
-                            summary.duration,
-                            summary.cover_art_id.unwrap_or_default()
-                        ]);
-                    }
 
-                    table.printstd();
-                }
-                Err(e) => {
-                    error!("Failed to retrieve metadata summary: {e}");
-                }
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
             }
         }
+        Commands::Info { file_ids } => match show_info(&main_db, file_ids, output).await {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(NO_RESULTS_EXIT_CODE),
+            Err(e) => {
+                eprintln!("Failed to retrieve metadata summary: {e:#}");
+                std::process::exit(1);
+            }
+        },
         // In the main function, update the match statement for Commands::Play
-        Commands::Play { mode, id } => match mode.as_deref() {
+        Commands::Play { mode, id, no_tty } => match mode.as_deref() {
             Some("random") => {
-                play_random(&main_db, &canonicalized_path).await;
+                play_random(&main_db, &canonicalized_path, *no_tty).await;
             }
             Some("id") => {
                 if let Some(file_id) = id {
-                    play_by_id(&main_db, &canonicalized_path, *file_id).await;
+                    play_by_id(&main_db, &canonicalized_path, *file_id, *no_tty).await;
                 } else {
                     error!("File ID is required for playById mode.");
                 }
@@ -305,11 +1365,15 @@ async fn main() {
         Commands::Recommend {
             item_id,
             file_path,
+            query,
+            first,
+            seed_id,
+            exclude_seeds,
             num,
             format,
             output,
         } => {
-            recommend_music(
+            let found = recommend_music(
                 &main_db,
                 &analysis_db,
                 RecommendMusicOptions {
@@ -317,40 +1381,664 @@ async fn main() {
                     path: &path,
                     item_id: *item_id,
                     file_path: file_path.as_ref(),
+                    query: query.as_deref(),
+                    first: *first,
+                    seed_id: *seed_id,
+                    exclude_seeds: *exclude_seeds,
                     num: *num,
                     format: format.as_ref().map(|x| x.as_str()),
                     output: output.as_ref(),
+                    read_only,
                 },
             )
             .await;
+
+            if !found {
+                std::process::exit(1);
+            }
         }
         Commands::Mix {
             mix_parameters,
             num,
             format,
-            output,
+            output: output_path,
+            command,
+        } => {
+            let result = match (command, mix_parameters) {
+                (Some(MixCommands::List), _) => list_mixes(&main_db, output).await,
+                (Some(MixCommands::Show { id }), _) => show_mix(&main_db, *id, output).await,
+                (
+                    Some(MixCommands::Eval {
+                        id,
+                        num,
+                        format,
+                        output,
+                        explain,
+                    }),
+                    _,
+                ) => {
+                    eval_mix(
+                        &main_db,
+                        &analysis_db,
+                        EvalMixOptions {
+                            id: *id,
+                            num: *num,
+                            format: format.as_deref(),
+                            output: output.as_ref(),
+                            explain: *explain,
+                        },
+                    )
+                    .await
+                }
+                (None, Some(mix_parameters)) => {
+                    mixes(
+                        &main_db,
+                        &analysis_db,
+                        RecommendMixOptions {
+                            mix_parameters,
+                            num: *num,
+                            format: format.as_ref().map(|x| x.as_str()),
+                            output: output_path.as_ref(),
+                        },
+                    )
+                    .await;
+                    Ok(())
+                }
+                (None, None) => Err(anyhow::anyhow!(
+                    "Either --mix-parameters or a subcommand is required"
+                )),
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Search { query, num } => {
+            match search_library(&main_db, query, *num, output).await {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(NO_RESULTS_EXIT_CODE),
+                Err(e) => {
+                    eprintln!("Search failed: {e:#}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Playlist { json, command } => {
+            let json = *json || output == OutputFormat::Json;
+            let result = match command {
+                PlaylistCommands::List => list_playlists(&main_db, json).await,
+                PlaylistCommands::Show { id } => show_playlist(&main_db, *id, json).await,
+                PlaylistCommands::Create { name, group } => {
+                    create_playlist(&main_db, &node_id, name, group, json).await
+                }
+                PlaylistCommands::Add { id, file_ids } => {
+                    add_to_playlist(&main_db, &node_id, *id, file_ids).await
+                }
+                PlaylistCommands::RemoveItem {
+                    id,
+                    file_id,
+                    position,
+                } => remove_from_playlist(&main_db, &node_id, *id, *file_id, *position).await,
+                PlaylistCommands::Delete { id } => delete_playlist(&main_db, *id).await,
+                PlaylistCommands::Export {
+                    id,
+                    format,
+                    output,
+                    path_style,
+                } => {
+                    export_playlist(
+                        &main_db,
+                        &canonicalized_path,
+                        *id,
+                        format,
+                        output,
+                        *path_style,
+                    )
+                    .await
+                }
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Identify {
+            path,
+            file_id,
+            batch,
+            excerpts,
+            apply,
+            write_tags,
+            delay,
         } => {
-            mixes(
+            let target = match (path, file_id) {
+                (_, Some(file_id)) => IdentifyTarget::FileId(*file_id),
+                (Some(path), None) if *batch => IdentifyTarget::Batch(path),
+                (Some(path), None) => IdentifyTarget::Path(path),
+                (None, None) => unreachable!("clap requires a path or a file ID"),
+            };
+
+            let result = identify_tracks(
+                &fsio,
                 &main_db,
-                &analysis_db,
-                RecommendMixOptions {
-                    mix_parameters,
-                    num: *num,
-                    format: format.as_ref().map(|x| x.as_str()),
+                IdentifyOptions {
+                    lib_path: &canonicalized_path,
+                    node_id: &node_id,
+                    target,
+                    excerpts: *excerpts,
+                    apply: *apply,
+                    write_tags: *write_tags,
+                    delay: Duration::from_secs(*delay),
+                    output,
+                    read_only,
+                },
+            )
+            .await;
+
+            match result {
+                Ok(status) => {
+                    let exit_code = status.exit_code();
+                    if exit_code != 0 {
+                        std::process::exit(exit_code);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{e:#}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Cover { command } => {
+            let result = match command {
+                CoverCommands::Extract {
+                    album,
+                    output: output_dir,
+                } => extract_album_cover(&main_db, *album, output_dir).await,
+                CoverCommands::Set {
+                    album,
+                    image,
+                    embed,
+                    max_size,
+                    format,
+                } => {
+                    set_album_cover(
+                        &fsio,
+                        &main_db,
+                        SetCoverOptions {
+                            lib_path: &canonicalized_path,
+                            node_id: &node_id,
+                            album_id: *album,
+                            image,
+                            embed: embed.then_some(EmbedOptions {
+                                max_dimension: *max_size,
+                                format: *format,
+                            }),
+                            read_only,
+                        },
+                    )
+                    .await
+                }
+                CoverCommands::Embed {
+                    albums,
+                    max_size,
+                    format,
+                    quiet,
+                } => {
+                    embed_album_covers(
+                        &fsio,
+                        &main_db,
+                        EmbedCoverOptions {
+                            lib_path: &canonicalized_path,
+                            album_ids: albums,
+                            embed: EmbedOptions {
+                                max_dimension: *max_size,
+                                format: *format,
+                            },
+                            progress: if *quiet {
+                                ProgressMode::Quiet
+                            } else {
+                                ProgressMode::Bar
+                            },
+                            read_only,
+                        },
+                    )
+                    .await
+                }
+                CoverCommands::Missing => match list_missing_covers(&main_db, output).await {
+                    Ok(true) => Ok(()),
+                    Ok(false) => std::process::exit(NO_RESULTS_EXIT_CODE),
+                    Err(e) => Err(e),
+                },
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Meta { command } => {
+            let result = match command {
+                MetaCommands::Show { file_ids } => show_metadata(&main_db, file_ids, output).await,
+                MetaCommands::Set {
+                    file_ids,
+                    title,
+                    artist,
+                    album,
+                    album_artist,
+                    genre,
+                    track_number,
+                    disc_number,
+                    date,
+                    from_filename,
+                    write_tags,
+                    dry_run,
+                } => {
+                    let fields = [
+                        ("track_title", title),
+                        ("artist", artist),
+                        ("album", album),
+                        ("album_artist", album_artist),
+                        ("genre", genre),
+                        ("track_number", track_number),
+                        ("disc_number", disc_number),
+                        ("date", date),
+                    ]
+                    .into_iter()
+                    .filter_map(|(key, value)| Some((key.to_owned(), value.clone()?)))
+                    .collect();
+
+                    set_metadata(
+                        &fsio,
+                        &main_db,
+                        &node_id,
+                        SetMetadataOptions {
+                            lib_path: &canonicalized_path,
+                            file_ids,
+                            fields,
+                            from_filename: from_filename.as_deref(),
+                            write_tags: *write_tags,
+                            dry_run: *dry_run,
+                            read_only,
+                        },
+                    )
+                    .await
+                }
+                MetaCommands::Infer {
+                    pattern,
+                    file_ids,
+                    apply,
+                    write_tags,
+                } => {
+                    infer_metadata(
+                        &fsio,
+                        &main_db,
+                        &node_id,
+                        InferMetadataOptions {
+                            lib_path: &canonicalized_path,
+                            file_ids,
+                            pattern,
+                            apply: *apply,
+                            write_tags: *write_tags,
+                            read_only,
+                        },
+                        output,
+                    )
+                    .await
+                }
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Dedupe { json, command } => {
+            let json = *json || output == OutputFormat::Json;
+            let result = match command {
+                DedupeCommands::Report {
+                    threshold,
+                    prefer,
+                    save,
+                } => {
+                    dedupe_report(
+                        &fsio,
+                        &main_db,
+                        DedupeReportOptions {
+                            lib_path: &canonicalized_path,
+                            threshold: *threshold,
+                            prefer: *prefer,
+                            save: save.as_ref(),
+                            json,
+                        },
+                    )
+                    .await
+                }
+                DedupeCommands::Match { quiet } => {
+                    dedupe_match(Arc::clone(&fsio), &main_db, &canonicalized_path, *quiet).await
+                }
+                DedupeCommands::Apply {
+                    plan,
+                    trash: _,
+                    delete,
+                    yes,
+                } => {
+                    let mode = if *delete {
+                        RemovalMode::Delete
+                    } else {
+                        RemovalMode::Trash
+                    };
+
+                    dedupe_apply(&fsio, &main_db, &canonicalized_path, plan, mode, *yes).await
+                }
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Organize { json, command } => {
+            let json = *json || output == OutputFormat::Json;
+            let result = match command {
+                OrganizeCommands::Preview {
+                    template,
+                    file_ids,
+                    save,
+                } => {
+                    organize_preview(
+                        &fsio,
+                        &main_db,
+                        OrganizePreviewOptions {
+                            lib_path: &canonicalized_path,
+                            file_ids,
+                            template,
+                            save: save.as_ref(),
+                            json,
+                        },
+                    )
+                    .await
+                }
+                OrganizeCommands::Apply { plan, yes } => {
+                    organize_apply(
+                        &fsio,
+                        &main_db,
+                        &canonicalized_path,
+                        plan,
+                        read_only,
+                        *yes,
+                    )
+                    .await
+                }
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Export {
+            format,
+            output: output_path,
+            include,
+            exclude,
+            quiet,
+        } => {
+            let result = export_library_data(
+                &main_db,
+                ExportOptions {
+                    format,
+                    output: output_path,
+                    include,
+                    exclude,
+                    progress: if *quiet {
+                        ProgressMode::Quiet
+                    } else {
+                        ProgressMode::Bar
+                    },
+                },
+            )
+            .await;
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Import {
+            from,
+            file,
+            dry_run,
+            report,
+            min_match,
+            quiet,
+        } => {
+            let result = import_library_data(
+                &main_db,
+                ImportOptions {
+                    lib_path: &canonicalized_path,
+                    node_id: &node_id,
+                    source: *from,
+                    path: file,
+                    dry_run: *dry_run,
+                    report: report.as_deref(),
+                    min_match: *min_match,
+                    progress: if *quiet {
+                        ProgressMode::Quiet
+                    } else {
+                        ProgressMode::Bar
+                    },
+                },
+            )
+            .await;
+
+            match result {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(LOW_MATCH_EXIT_CODE),
+                Err(e) => {
+                    eprintln!("{e:#}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Verify { hash, output, fix } => {
+            let result = verify_files(
+                &fsio,
+                &main_db,
+                VerifyOptions {
+                    lib_path: &canonicalized_path,
+                    hash: *hash,
                     output: output.as_ref(),
+                    fix: *fix,
                 },
             )
             .await;
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
         }
-        Commands::Search { query, num } => match search_for(&main_db, query, None, *num).await {
-            Ok(results) => {
-                for (collection_type, ids) in results {
-                    info!("{collection_type:?}: {ids:?}");
+        Commands::Watch {
+            analyze,
+            computing_device,
+            dry_run,
+            settle,
+        } => {
+            let result = watch_library(
+                fsio,
+                &main_db,
+                &analysis_db,
+                WatchOptions {
+                    lib_path: &canonicalized_path,
+                    node_id: &node_id,
+                    analyze: analyze.then(|| computing_device.as_str().into()),
+                    dry_run: *dry_run,
+                    settle: Duration::from_secs(*settle),
+                },
+            )
+            .await;
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Sync {
+            peer,
+            direction,
+            tables,
+            dry_run,
+        } => {
+            let result = sync_with_peer(
+                &main_db,
+                SyncOptions {
+                    config_path: &config_path,
+                    node_id: &node_id,
+                    peer,
+                    direction: *direction,
+                    tables,
+                    dry_run: *dry_run,
+                },
+            )
+            .await;
+
+            match result {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(SYNC_PARTIAL_FAILURE_EXIT_CODE),
+                Err(e) => {
+                    eprintln!("{e:#}");
+                    std::process::exit(1);
                 }
             }
-            Err(e) => {
-                error!("Search failed: {e}");
+        }
+        // Handled before the library is opened
+        Commands::Serve { .. } | Commands::Scrobble { .. } => unreachable!(),
+        Commands::Db {
+            wait: _,
+            quiet,
+            command,
+        } => {
+            let result = match command {
+                DbCommands::Vacuum => db_vacuum(&main_db, *quiet).await,
+                DbCommands::Verify => db_verify(&main_db, &analysis_db, *quiet).await,
+                DbCommands::Backup { output } => {
+                    db_backup(&main_db, &analysis_db, &canonicalized_path, output, *quiet).await
+                }
+                DbCommands::Migrate { to } => {
+                    db_migrate(&main_db, to.as_deref(), &node_id, *quiet).await
+                }
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
             }
-        },
+        }
+        Commands::Stats {
+            top,
+            by,
+            with_size,
+            json,
+        } => {
+            let result = library_stats(
+                &fsio,
+                &main_db,
+                StatsOptions {
+                    lib_path: &canonicalized_path,
+                    top: *top,
+                    by: *by,
+                    with_size: *with_size,
+                    json: *json || output == OutputFormat::Json,
+                },
+            )
+            .await;
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Like { file_ids } => {
+            if let Err(e) = like_files(&main_db, file_ids, true).await {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Unlike { file_ids } => {
+            if let Err(e) = like_files(&main_db, file_ids, false).await {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Rate { file_id, rating } => {
+            if let Err(e) = rate_file(&main_db, *file_id, *rating).await {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Liked { command } => {
+            let result = match command {
+                LikedCommands::List {
+                    export,
+                    output: export_path,
+                    path_style,
+                } => {
+                    list_liked(
+                        &main_db,
+                        ListLikedOptions {
+                            lib_path: &canonicalized_path,
+                            output,
+                            export: export.as_deref(),
+                            export_path: export_path.as_ref(),
+                            path_style: *path_style,
+                        },
+                    )
+                    .await
+                }
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Lyrics { command } => {
+            let result = match command {
+                LyricsCommands::Show { file_id } => {
+                    match show_lyrics(&main_db, &canonicalized_path, *file_id, output).await {
+                        Ok(false) => std::process::exit(NO_RESULTS_EXIT_CODE),
+                        result => result.map(|_| ()),
+                    }
+                }
+                LyricsCommands::Export {
+                    file_ids,
+                    album,
+                    format,
+                    output_dir,
+                    overwrite,
+                } => {
+                    export_lyrics(
+                        &fsio,
+                        &main_db,
+                        ExportLyricsOptions {
+                            lib_path: &canonicalized_path,
+                            file_ids,
+                            album_id: *album,
+                            format,
+                            output_dir: output_dir.as_ref(),
+                            overwrite: *overwrite,
+                        },
+                    )
+                    .await
+                }
+            };
+
+            if let Err(e) = result {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
     }
 }
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use prettytable::{Table, format, row};
+use serde::Serialize;
+
+use database::actions::file::get_files_by_ids;
+use database::actions::metadata::set_file_last_modified;
+use database::actions::metadata_edit::{
+    EDITABLE_KEYS, FileNamePattern, MetadataChange, TagWriteReport, apply_metadata_changes,
+    diff_metadata, get_editable_metadata, infer_metadata_from_paths, write_changes_to_tags,
+};
+use database::connection::MainDbConnection;
+use fsio::FsIo;
+use metadata::writer::TagWriteOutcome;
+
+use crate::output::{OutputFormat, is_lossy, print_csv, print_json};
+
+pub struct SetMetadataOptions<'a> {
+    pub lib_path: &'a Path,
+    pub file_ids: &'a [i32],
+    /// Values set on every file
+    pub fields: Vec<(String, String)>,
+    /// Pattern extracting values from the file names, e.g. `{artist} - {title}`
+    pub from_filename: Option<&'a str>,
+    pub write_tags: bool,
+    pub dry_run: bool,
+    /// Tags can't be written when the library is read-only.
+    pub read_only: bool,
+}
+
+pub struct InferMetadataOptions<'a> {
+    pub lib_path: &'a Path,
+    pub file_ids: &'a [i32],
+    /// Pattern extracting values from the paths, e.g. `{artist}/{album}/{title}`
+    pub pattern: &'a str,
+    /// Apply the inferred values, they are only previewed otherwise
+    pub apply: bool,
+    pub write_tags: bool,
+    /// Tags can't be written when the library is read-only.
+    pub read_only: bool,
+}
+
+/// One field of a file, `path` comes first.
+#[derive(Debug, Serialize)]
+struct MetadataRecord {
+    file_id: i32,
+    field: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FileMetadata {
+    file_id: i32,
+    path: String,
+    /// Whether the file name was not valid UTF-8, and `path` is not exact
+    path_lossy: bool,
+    fields: HashMap<String, String>,
+}
+
+pub async fn show_metadata(
+    main_db: &MainDbConnection,
+    file_ids: &[i32],
+    output: OutputFormat,
+) -> Result<()> {
+    let files = get_files_by_ids(main_db, file_ids).await?;
+    let metadata = get_editable_metadata(main_db, file_ids).await?;
+
+    let mut entries = Vec::new();
+    for file_id in file_ids {
+        let Some(file) = files.iter().find(|file| file.id == *file_id) else {
+            bail!("File {file_id} does not exist");
+        };
+        let path = Path::new(&file.directory)
+            .join(&file.file_name)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        entries.push(FileMetadata {
+            file_id: file.id,
+            path_lossy: is_lossy(&path),
+            path,
+            fields: metadata.get(file_id).cloned().unwrap_or_default(),
+        });
+    }
+
+    if output == OutputFormat::Json {
+        return print_json(&entries);
+    }
+
+    let mut records = Vec::new();
+    for entry in entries {
+        records.push(MetadataRecord {
+            file_id: entry.file_id,
+            field: "path".to_owned(),
+            value: entry.path,
+        });
+        for key in EDITABLE_KEYS {
+            if let Some(value) = entry.fields.get(key) {
+                records.push(MetadataRecord {
+                    file_id: entry.file_id,
+                    field: key.to_string(),
+                    value: value.clone(),
+                });
+            }
+        }
+    }
+
+    if output == OutputFormat::Csv {
+        return print_csv(&records);
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["File ID", "Field", "Value"]);
+    for record in records {
+        table.add_row(row![record.file_id, record.field, record.value]);
+    }
+    table.printstd();
+
+    Ok(())
+}
+
+fn display_changes(changes: &[MetadataChange]) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["File ID", "Field", "Old Value", "New Value"]);
+
+    for change in changes {
+        table.add_row(row![
+            change.file_id,
+            change.key,
+            change.old_value.as_deref().unwrap_or("-"),
+            change.new_value
+        ]);
+    }
+
+    table.printstd();
+}
+
+/// Prints what writing tags to the files did, failing if some of them could
+/// not be written.
+fn print_tag_write_report(report: &TagWriteReport) -> Result<()> {
+    for (file_id, format) in &report.unsupported {
+        eprintln!(
+            "Warning: tags can't be written to file {file_id}, {format} files are not supported"
+        );
+    }
+    for (file_id, error) in &report.failed {
+        eprintln!("File {file_id}: {error}");
+    }
+
+    if !report.failed.is_empty() {
+        bail!("Failed to write tags to {} files", report.failed.len());
+    }
+    if report.unsupported.is_empty() {
+        println!("Tags written to the files.");
+    } else {
+        println!(
+            "Tags written to {} files, {} of unsupported formats were skipped.",
+            report.written,
+            report.unsupported.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Records a write to the tags of a file of the library, for the scanner not
+/// to take it for an external change. Returns whether the tags were written,
+/// the files of formats tags can't be written to being reported.
+pub async fn record_tag_write(
+    main_db: &MainDbConnection,
+    file_id: i32,
+    path: &Path,
+    outcome: TagWriteOutcome,
+) -> Result<bool> {
+    match outcome {
+        TagWriteOutcome::Written { last_modified } => {
+            set_file_last_modified(main_db, file_id, last_modified).await?;
+            Ok(true)
+        }
+        TagWriteOutcome::Unchanged => Ok(true),
+        TagWriteOutcome::NotSupported { format } => {
+            eprintln!(
+                "Warning: tags can't be written to {}, {format} files are not supported",
+                path.display()
+            );
+            Ok(false)
+        }
+    }
+}
+
+pub async fn set_metadata(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    node_id: &str,
+    options: SetMetadataOptions<'_>,
+) -> Result<()> {
+    let SetMetadataOptions {
+        lib_path,
+        file_ids,
+        fields,
+        from_filename,
+        write_tags: write_back,
+        dry_run,
+        read_only,
+    } = options;
+
+    if write_back && read_only {
+        bail!("The library is read-only, tags cannot be written to its files.");
+    }
+    if fields.is_empty() && from_filename.is_none() {
+        bail!("Nothing to set, pass fields like --artist or a --from-filename pattern.");
+    }
+
+    let pattern = from_filename.map(FileNamePattern::new).transpose()?;
+    let files = get_files_by_ids(main_db, file_ids).await?;
+
+    let mut edits = Vec::new();
+    for file_id in file_ids {
+        let Some(file) = files.iter().find(|file| file.id == *file_id) else {
+            bail!("File {file_id} does not exist");
+        };
+
+        let mut file_fields = Vec::new();
+        if let Some(pattern) = &pattern {
+            let path = Path::new(&file.directory)
+                .join(&file.file_name)
+                .to_string_lossy()
+                .replace('\\', "/");
+            match pattern.parse(&path) {
+                Some(parsed) => file_fields.extend(parsed),
+                None => eprintln!(
+                    "Warning: {} doesn't match the file name pattern",
+                    file.file_name
+                ),
+            }
+        }
+        // Explicit values win over the ones parsed from file names
+        file_fields.retain(|(key, _)| !fields.iter().any(|(x, _)| x == key));
+        file_fields.extend(fields.iter().cloned());
+
+        edits.push((*file_id, file_fields));
+    }
+
+    let changes = diff_metadata(main_db, &edits).await?;
+    if changes.is_empty() {
+        println!("Nothing changed.");
+        return Ok(());
+    }
+
+    display_changes(&changes);
+    if dry_run {
+        println!("Dry run, {} changes were not applied.", changes.len());
+        return Ok(());
+    }
+
+    apply_metadata_changes(main_db, node_id, &changes).await?;
+    println!("Applied {} changes.", changes.len());
+
+    if write_back {
+        let report = write_changes_to_tags(fsio, main_db, lib_path, &changes).await?;
+        print_tag_write_report(&report)?;
+    }
+
+    Ok(())
+}
+
+/// What a pattern inferred from the path of a file, the fields being empty
+/// when the path doesn't match.
+#[derive(Debug, Serialize)]
+struct InferredRecord {
+    file_id: i32,
+    path: String,
+    matched: bool,
+    fields: HashMap<String, String>,
+}
+
+/// Previews the metadata a pattern infers from the paths of files, then
+/// applies it when asked to. Files whose path doesn't match are reported and
+/// left as they are.
+pub async fn infer_metadata(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    node_id: &str,
+    options: InferMetadataOptions<'_>,
+    output: OutputFormat,
+) -> Result<()> {
+    let InferMetadataOptions {
+        lib_path,
+        file_ids,
+        pattern,
+        apply,
+        write_tags: write_back,
+        read_only,
+    } = options;
+
+    if write_back && read_only {
+        bail!("The library is read-only, tags cannot be written to its files.");
+    }
+
+    let pattern = FileNamePattern::new(pattern)?;
+    let inferred = infer_metadata_from_paths(main_db, file_ids, &pattern).await?;
+
+    let records: Vec<InferredRecord> = inferred
+        .iter()
+        .map(|x| InferredRecord {
+            file_id: x.file_id,
+            path: x.path.clone(),
+            matched: x.fields.is_some(),
+            fields: x.fields.iter().flatten().cloned().collect(),
+        })
+        .collect();
+
+    match output {
+        OutputFormat::Json => print_json(&records)?,
+        OutputFormat::Csv => {
+            let mut rows = Vec::new();
+            for record in &records {
+                for key in EDITABLE_KEYS {
+                    if let Some(value) = record.fields.get(key) {
+                        rows.push(MetadataRecord {
+                            file_id: record.file_id,
+                            field: key.to_string(),
+                            value: value.clone(),
+                        });
+                    }
+                }
+            }
+            print_csv(&rows)?;
+        }
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["File ID", "Path", "Field", "Value"]);
+            for record in &records {
+                if !record.matched {
+                    table.add_row(row![record.file_id, record.path, "-", "No match"]);
+                    continue;
+                }
+                for key in EDITABLE_KEYS {
+                    if let Some(value) = record.fields.get(key) {
+                        table.add_row(row![record.file_id, record.path, key, value]);
+                    }
+                }
+            }
+            table.printstd();
+        }
+    }
+
+    let unmatched = records.iter().filter(|x| !x.matched).count();
+    if unmatched > 0 {
+        eprintln!("Warning: {unmatched} paths don't match the pattern");
+    }
+    if !apply {
+        return Ok(());
+    }
+
+    let edits: Vec<(i32, Vec<(String, String)>)> = inferred
+        .into_iter()
+        .filter_map(|x| Some((x.file_id, x.fields?)))
+        .collect();
+    let changes = diff_metadata(main_db, &edits).await?;
+    if changes.is_empty() {
+        println!("Nothing changed.");
+        return Ok(());
+    }
+
+    apply_metadata_changes(main_db, node_id, &changes).await?;
+    println!("Applied {} changes.", changes.len());
+
+    if write_back {
+        let report = write_changes_to_tags(fsio, main_db, lib_path, &changes).await?;
+        print_tag_write_report(&report)?;
+    }
+
+    Ok(())
+}
@@ -2,15 +2,21 @@ use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use anyhow::{Result, bail};
 use log::error;
-use prettytable::{Table, row};
+use prettytable::{Table, format, row};
 use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
 
 use database::actions::metadata::get_metadata_summary_by_file_ids;
-use database::actions::mixes::query_mix_media_files;
+use database::actions::mixes::{
+    explain_mix_media_files, get_all_mixes, get_mix_by_id, get_mix_queries_by_mix_id,
+    query_mix_media_files,
+};
 use database::connection::{MainDbConnection, RecommendationDbConnection};
-use database::entities::media_files;
+use database::entities::{media_files, mixes};
 
+use crate::output::{OutputFormat, print_csv, print_json};
 use crate::recommend::check_and_correct_extension;
 
 pub struct RecommendMixOptions<'a> {
@@ -20,6 +26,176 @@ pub struct RecommendMixOptions<'a> {
     pub output: Option<&'a PathBuf>,
 }
 
+pub struct EvalMixOptions<'a> {
+    pub id: i32,
+    pub num: usize,
+    pub format: Option<&'a str>,
+    pub output: Option<&'a PathBuf>,
+    /// Show how many tracks each stage of the query matched
+    pub explain: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct MixRecord {
+    id: i32,
+    name: String,
+    group: String,
+    /// Built-in mixes can't be edited
+    readonly: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct MixQueryRecord {
+    operator: String,
+    parameter: String,
+}
+
+/// Built-in mixes are named with a leading zero-width space, which sorts
+/// them first in the app.
+fn display_name(name: &str) -> String {
+    name.trim_start_matches('\u{200B}').to_owned()
+}
+
+fn mix_record(mix: mixes::Model) -> MixRecord {
+    MixRecord {
+        id: mix.id,
+        name: display_name(&mix.name),
+        group: display_name(&mix.group),
+        readonly: mix.locked,
+    }
+}
+
+pub async fn list_mixes(main_db: &MainDbConnection, output: OutputFormat) -> Result<()> {
+    let records: Vec<MixRecord> = get_all_mixes(main_db)
+        .await?
+        .into_iter()
+        .map(mix_record)
+        .collect();
+
+    match output {
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["ID", "Name", "Group", "Read-only"]);
+            for record in records {
+                table.add_row(row![
+                    record.id,
+                    record.name,
+                    record.group,
+                    if record.readonly { "yes" } else { "" }
+                ]);
+            }
+            table.printstd();
+        }
+        OutputFormat::Json => print_json(&records)?,
+        OutputFormat::Csv => print_csv(&records)?,
+    }
+
+    Ok(())
+}
+
+pub async fn show_mix(main_db: &MainDbConnection, id: i32, output: OutputFormat) -> Result<()> {
+    let mix = get_mix_by_id(main_db, id).await?;
+    let queries: Vec<MixQueryRecord> = get_mix_queries_by_mix_id(main_db, id)
+        .await?
+        .into_iter()
+        .map(|query| MixQueryRecord {
+            operator: query.operator,
+            parameter: query.parameter,
+        })
+        .collect();
+
+    match output {
+        OutputFormat::Table => {
+            let mix = mix_record(mix);
+            println!(
+                "Mix {}: {}{}",
+                mix.id,
+                mix.name,
+                if mix.readonly { " (read-only)" } else { "" }
+            );
+
+            let mut table = Table::new();
+            table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.set_titles(row!["Operator", "Parameter"]);
+            for query in queries {
+                table.add_row(row![query.operator, query.parameter]);
+            }
+            table.printstd();
+        }
+        OutputFormat::Json => {
+            #[derive(Serialize)]
+            struct MixDetail {
+                #[serde(flatten)]
+                mix: MixRecord,
+                queries: Vec<MixQueryRecord>,
+            }
+
+            print_json(&MixDetail {
+                mix: mix_record(mix),
+                queries,
+            })?;
+        }
+        OutputFormat::Csv => print_csv(&queries)?,
+    }
+
+    Ok(())
+}
+
+/// Runs the queries of a saved mix, then prints the tracks or saves them
+/// as a playlist.
+pub async fn eval_mix(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    options: EvalMixOptions<'_>,
+) -> Result<()> {
+    let EvalMixOptions {
+        id,
+        num,
+        format,
+        output,
+        explain,
+    } = options;
+
+    if let Some(format) = format
+        && format != "m3u8"
+    {
+        bail!("Unsupported format {format}, the supported format is m3u8");
+    }
+
+    // Fails early for mixes which don't exist
+    get_mix_by_id(main_db, id).await?;
+    let queries: Vec<(String, String)> = get_mix_queries_by_mix_id(main_db, id)
+        .await?
+        .into_iter()
+        .map(|query| (query.operator, query.parameter))
+        .collect();
+
+    let files = if explain {
+        let (files, stages) = explain_mix_media_files(main_db, recommend_db, queries, num).await?;
+
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        table.set_titles(row!["Stage", "Tracks"]);
+        for stage in stages {
+            table.add_row(row![stage.stage, stage.matched]);
+        }
+        table.add_row(row!["returned", files.len()]);
+        table.printstd();
+
+        files
+    } else {
+        query_mix_media_files(main_db, recommend_db, queries, 0, num).await?
+    };
+
+    match format {
+        Some(_) => save_mixes_as_m3u8(output, &files).await,
+        None => display_mixes_in_table(main_db, &files).await,
+    }
+
+    Ok(())
+}
+
 pub async fn mixes(
     main_db: &MainDbConnection,
     recommend_db: &RecommendationDbConnection,
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use prettytable::{Table, format, row};
+
+use database::actions::organize::{OrganizePlan, PathTemplate, apply_organize_plan, plan_organize};
+use database::connection::MainDbConnection;
+use fsio::FsIo;
+
+pub struct OrganizePreviewOptions<'a> {
+    pub lib_path: &'a Path,
+    pub file_ids: &'a [i32],
+    pub template: &'a str,
+    /// Save the plan to this file, to review it before applying it
+    pub save: Option<&'a PathBuf>,
+    pub json: bool,
+}
+
+fn display_plan(plan: &OrganizePlan) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["ID", "From", "To"]);
+
+    for file_move in &plan.moves {
+        table.add_row(row![
+            file_move.file_id,
+            file_move.from.display(),
+            file_move.to.display()
+        ]);
+    }
+
+    table.printstd();
+}
+
+pub async fn organize_preview(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: OrganizePreviewOptions<'_>,
+) -> Result<()> {
+    let OrganizePreviewOptions {
+        lib_path,
+        file_ids,
+        template,
+        save,
+        json,
+    } = options;
+
+    let template = PathTemplate::new(template)?;
+    let plan = plan_organize(fsio, main_db, lib_path, file_ids, &template).await?;
+
+    if let Some(save) = save {
+        fs::write(save, serde_json::to_string_pretty(&plan)?)
+            .with_context(|| format!("Failed to save the plan to {}", save.display()))?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&plan)?);
+        return Ok(());
+    }
+
+    if plan.moves.is_empty() {
+        println!("Every file is already where the template puts it.");
+        return Ok(());
+    }
+
+    display_plan(&plan);
+    println!(
+        "{} files would be moved, {} are already in place.",
+        plan.moves.len(),
+        plan.unchanged
+    );
+
+    Ok(())
+}
+
+pub async fn organize_apply(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    plan_path: &Path,
+    read_only: bool,
+    yes: bool,
+) -> Result<()> {
+    if read_only {
+        bail!("The library is read-only, its files cannot be moved.");
+    }
+
+    let plan: OrganizePlan = serde_json::from_str(
+        &fs::read_to_string(plan_path)
+            .with_context(|| format!("Failed to read {}", plan_path.display()))?,
+    )
+    .with_context(|| format!("{} is not an organize plan", plan_path.display()))?;
+
+    if !yes {
+        display_plan(&plan);
+        bail!(
+            "This moves {} files, pass --yes to go ahead.",
+            plan.moves.len()
+        );
+    }
+
+    let outcome = apply_organize_plan(fsio, main_db, lib_path, &plan).await?;
+    println!(
+        "Moved {} files, skipped {} which changed since the plan was made.",
+        outcome.moved, outcome.skipped
+    );
+
+    if outcome.failed > 0 {
+        bail!("Failed to move {} files", outcome.failed);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+
+/// Exit code of the read commands which found nothing, distinct from the
+/// one of errors (1) and of invalid arguments (2).
+pub const NO_RESULTS_EXIT_CODE: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(anyhow!(
+                "Invalid output format {s}, expected table, json or csv"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Table => write!(f, "table"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Paths are stored as they were read at scan time, where names which are
+/// not valid UTF-8 got their invalid bytes replaced.
+pub fn is_lossy(path: &str) -> bool {
+    path.contains(char::REPLACEMENT_CHARACTER)
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+
+    Ok(())
+}
+
+/// Prints records as CSV with a header row, the fields of the records are
+/// the columns.
+pub fn print_csv<T: Serialize>(records: &[T]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(io::stdout().lock());
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
@@ -1,25 +1,226 @@
 use std::{
+    collections::HashMap,
+    io::{self, IsTerminal, Write},
     path::Path,
     sync::{Arc, Mutex},
     thread,
     time::Duration,
 };
 
+use crossterm::{
+    cursor::MoveToColumn,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::Print,
+    terminal::{self, Clear, ClearType},
+};
 use dunce::canonicalize;
 use futures::future::join_all;
 use log::{debug, error, info};
-use tokio::task;
+use tokio::{sync::mpsc, task};
 
 use database::{
-    actions::file::{get_file_by_id, get_random_files},
+    actions::{
+        file::{get_file_by_id, get_random_files},
+        metadata::{MetadataSummary, get_metadata_summary_by_files},
+    },
     connection::MainDbConnection,
 };
 use playback::{
-    player::{Playable, Player, PlayingItem},
+    player::{Playable, PlaybackState, Player, PlayerStatus, PlayingItem},
     strategies::AddMode,
 };
 
-async fn play_files(main_db: &MainDbConnection, canonicalized_path: &Path, file_ids: Vec<i32>) {
+/// How far the arrow keys seek, in seconds.
+const SEEK_STEP: f64 = 10.0;
+/// How much +/- change the volume, out of 1.
+const VOLUME_STEP: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Control {
+    TogglePause,
+    Next,
+    Previous,
+    /// Seconds to move, backwards when negative
+    Seek(f64),
+    Volume(f32),
+    Quit,
+}
+
+fn control_for_key(key: KeyEvent) -> Option<Control> {
+    // Windows reports the releases as well
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+
+    match key.code {
+        KeyCode::Char(' ') => Some(Control::TogglePause),
+        KeyCode::Char('n') => Some(Control::Next),
+        KeyCode::Char('p') => Some(Control::Previous),
+        KeyCode::Right => Some(Control::Seek(SEEK_STEP)),
+        KeyCode::Left => Some(Control::Seek(-SEEK_STEP)),
+        KeyCode::Char('+') | KeyCode::Char('=') => Some(Control::Volume(VOLUME_STEP)),
+        KeyCode::Char('-') => Some(Control::Volume(-VOLUME_STEP)),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Control::Quit),
+        // Raw mode swallows the signal
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Control::Quit),
+        _ => None,
+    }
+}
+
+/// Keeps the terminal in raw mode, restoring it when dropped.
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        println!();
+    }
+}
+
+/// Reads the keys on a thread of its own, until the controls are dropped.
+fn spawn_key_reader(sender: mpsc::UnboundedSender<Control>) {
+    thread::spawn(move || {
+        while !sender.is_closed() {
+            match event::poll(Duration::from_millis(100)) {
+                Ok(true) => {
+                    if let Ok(Event::Key(key)) = event::read()
+                        && let Some(control) = control_for_key(key)
+                        && sender.send(control).is_err()
+                    {
+                        break;
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!("Failed to read the keyboard: {e}");
+                    break;
+                }
+            }
+        }
+    });
+}
+
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn status_line(status: &PlayerStatus, summaries: &HashMap<i32, MetadataSummary>) -> String {
+    let state = match status.state {
+        PlaybackState::Playing => ">",
+        PlaybackState::Paused => "||",
+        PlaybackState::Stopped => "[]",
+    };
+    let summary = match &status.item {
+        Some(PlayingItem::InLibrary(id)) => summaries.get(id),
+        _ => None,
+    };
+    let (title, artist, duration) = match summary {
+        Some(summary) => (
+            if summary.title.is_empty() {
+                summary.file_name.as_str()
+            } else {
+                summary.title.as_str()
+            },
+            summary.artist.as_str(),
+            format_time(summary.duration),
+        ),
+        None => ("-", "-", "-".to_owned()),
+    };
+
+    format!(
+        "{state} {title} - {artist}  {} / {duration}  {:?}  vol {:.0}%",
+        format_time(status.position.as_secs_f64()),
+        status.playback_mode,
+        status.volume * 100.0
+    )
+}
+
+/// Redraws the status line in place, cut to the width of the terminal.
+fn draw(line: &str) -> io::Result<()> {
+    let width = terminal::size().map_or(80, |(width, _)| width as usize);
+    let line: String = line.chars().take(width.saturating_sub(1)).collect();
+
+    let mut stdout = io::stdout();
+    queue!(
+        stdout,
+        MoveToColumn(0),
+        Clear(ClearType::CurrentLine),
+        Print(line)
+    )?;
+    stdout.flush()
+}
+
+fn apply_control(player: &Mutex<Player>, status: &PlayerStatus, control: Control) {
+    let mut player = player.lock().unwrap();
+    match control {
+        Control::TogglePause => match status.state {
+            PlaybackState::Playing => player.pause(),
+            _ => player.play(),
+        },
+        Control::Next => player.next(),
+        Control::Previous => player.previous(),
+        Control::Seek(delta) => {
+            let position = (status.position.as_secs_f64() + delta).max(0.0);
+            player.seek(position * 1000.0);
+        }
+        Control::Volume(delta) => player.set_volume((status.volume + delta).clamp(0.0, 1.0)),
+        Control::Quit => {}
+    }
+}
+
+/// Controls the playback from the keyboard, showing what plays on a single
+/// line, until q is pressed or the playlist ends.
+async fn control_playback(
+    player: &Mutex<Player>,
+    summaries: &HashMap<i32, MetadataSummary>,
+) -> io::Result<()> {
+    let _raw_mode = RawModeGuard::enable()?;
+
+    let (sender, mut controls) = mpsc::unbounded_channel();
+    spawn_key_reader(sender);
+
+    let status_receiver = player.lock().unwrap().subscribe_status();
+    let mut status = player.lock().unwrap().get_status();
+    let mut started = false;
+
+    loop {
+        draw(&status_line(&status, summaries))?;
+
+        tokio::select! {
+            control = controls.recv() => match control {
+                Some(Control::Quit) | None => break,
+                Some(control) => apply_control(player, &status, control),
+            },
+            Ok(new_status) = status_receiver.recv() => {
+                status = new_status;
+                match status.state {
+                    PlaybackState::Playing => started = true,
+                    PlaybackState::Stopped if started => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    player.lock().unwrap().stop();
+    Ok(())
+}
+
+async fn play_files(
+    main_db: &MainDbConnection,
+    canonicalized_path: &Path,
+    file_ids: Vec<i32>,
+    no_tty: bool,
+) {
     let player = Player::new(None);
     let player = Arc::new(Mutex::new(player));
 
@@ -39,6 +240,19 @@ async fn play_files(main_db: &MainDbConnection, canonicalized_path: &Path, file_
         .filter_map(|file| file.flatten())
         .collect();
 
+    let interactive = !no_tty && io::stdin().is_terminal() && io::stdout().is_terminal();
+    let summaries: HashMap<i32, MetadataSummary> = if interactive {
+        match get_metadata_summary_by_files(main_db, files.clone()).await {
+            Ok(summaries) => summaries.into_iter().map(|x| (x.id, x)).collect(),
+            Err(e) => {
+                error!("Failed to get the metadata of the files: {e}");
+                HashMap::new()
+            }
+        }
+    } else {
+        HashMap::new()
+    };
+
     player.lock().unwrap().add_to_playlist(
         files
             .into_iter()
@@ -55,6 +269,13 @@ async fn play_files(main_db: &MainDbConnection, canonicalized_path: &Path, file_
 
     player.lock().unwrap().play();
 
+    if interactive {
+        if let Err(e) = control_playback(&player, &summaries).await {
+            error!("Failed to control the playback: {e}");
+        }
+        return;
+    }
+
     let status_receiver = player.lock().unwrap().subscribe_status();
 
     info!("Initializing event listeners");
@@ -75,11 +296,13 @@ async fn play_files(main_db: &MainDbConnection, canonicalized_path: &Path, file_
     thread::sleep(Duration::from_millis(30000));
 }
 
-pub async fn play_random(main_db: &MainDbConnection, canonicalized_path: &Path) {
+/// Plays the files, with keyboard controls when the terminal allows them
+/// and `no_tty` is not set.
+pub async fn play_random(main_db: &MainDbConnection, canonicalized_path: &Path, no_tty: bool) {
     match get_random_files(main_db, 30).await {
         Ok(files) => {
             let file_ids = files.into_iter().map(|file| file.id).collect();
-            play_files(main_db, canonicalized_path, file_ids).await;
+            play_files(main_db, canonicalized_path, file_ids, no_tty).await;
         }
         Err(e) => {
             error!("Failed to get random files: {e}");
@@ -87,6 +310,11 @@ pub async fn play_random(main_db: &MainDbConnection, canonicalized_path: &Path)
     }
 }
 
-pub async fn play_by_id(main_db: &MainDbConnection, canonicalized_path: &Path, id: i32) {
-    play_files(main_db, canonicalized_path, vec![id]).await;
+pub async fn play_by_id(
+    main_db: &MainDbConnection,
+    canonicalized_path: &Path,
+    id: i32,
+    no_tty: bool,
+) {
+    play_files(main_db, canonicalized_path, vec![id], no_tty).await;
 }
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use prettytable::{Table, format, row};
+use serde_json::json;
+
+use database::actions::file::get_files_by_ids;
+use database::actions::playlists::{
+    self, get_all_playlists, get_playlist_by_id, get_playlist_items,
+};
+use database::connection::MainDbConnection;
+use database::entities::{media_files, playlists as playlist_entity};
+
+use crate::m3u8::{PathStyle, write_m3u8};
+
+async fn find_playlist(main_db: &MainDbConnection, id: i32) -> Result<playlist_entity::Model> {
+    match get_playlist_by_id(main_db, id).await? {
+        Some(playlist) => Ok(playlist),
+        None => bail!("Playlist {id} does not exist"),
+    }
+}
+
+fn playlist_json(playlist: &playlist_entity::Model) -> serde_json::Value {
+    json!({
+        "id": playlist.id,
+        "name": playlist.name,
+        "group": playlist.group,
+    })
+}
+
+pub async fn list_playlists(main_db: &MainDbConnection, as_json: bool) -> Result<()> {
+    let playlists = get_all_playlists(main_db).await?;
+
+    if as_json {
+        let playlists: Vec<_> = playlists.iter().map(playlist_json).collect();
+        println!("{}", json!(playlists));
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["ID", "Name", "Group"]);
+    for playlist in playlists {
+        table.add_row(row![playlist.id, playlist.name, playlist.group]);
+    }
+    table.printstd();
+
+    Ok(())
+}
+
+pub async fn show_playlist(main_db: &MainDbConnection, id: i32, as_json: bool) -> Result<()> {
+    let playlist = find_playlist(main_db, id).await?;
+    let items = get_playlist_items(main_db, id).await?;
+
+    if as_json {
+        let mut value = playlist_json(&playlist);
+        value["items"] = items
+            .iter()
+            .map(|(item, file)| {
+                json!({
+                    "position": item.position,
+                    "file_id": file.id,
+                    "directory": file.directory,
+                    "file_name": file.file_name,
+                })
+            })
+            .collect();
+        println!("{value}");
+        return Ok(());
+    }
+
+    println!("{} ({})", playlist.name, playlist.group);
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["Position", "File ID", "File Path"]);
+    for (item, file) in items {
+        table.add_row(row![
+            item.position,
+            file.id,
+            Path::new(&file.directory).join(&file.file_name).display()
+        ]);
+    }
+    table.printstd();
+
+    Ok(())
+}
+
+pub async fn create_playlist(
+    main_db: &MainDbConnection,
+    node_id: &str,
+    name: &str,
+    group: &str,
+    as_json: bool,
+) -> Result<()> {
+    let playlist =
+        playlists::create_playlist(main_db, node_id, name.to_owned(), group.to_owned()).await?;
+
+    if as_json {
+        println!("{}", playlist_json(&playlist));
+    } else {
+        println!("Created playlist {} ({})", playlist.id, playlist.name);
+    }
+
+    Ok(())
+}
+
+pub async fn add_to_playlist(
+    main_db: &MainDbConnection,
+    node_id: &str,
+    id: i32,
+    file_ids: &[i32],
+) -> Result<()> {
+    find_playlist(main_db, id).await?;
+
+    let files: Vec<media_files::Model> = get_files_by_ids(main_db, file_ids).await?;
+    let missing: Vec<String> = file_ids
+        .iter()
+        .filter(|file_id| !files.iter().any(|file| file.id == **file_id))
+        .map(|file_id| file_id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        bail!("Files {} do not exist", missing.join(", "));
+    }
+
+    for file_id in file_ids {
+        playlists::add_item_to_playlist(main_db, node_id, id, *file_id, None).await?;
+    }
+
+    println!("Added {} files to playlist {id}", file_ids.len());
+
+    Ok(())
+}
+
+/// Removes a file from a playlist. Without a position, its first occurrence
+/// is removed.
+pub async fn remove_from_playlist(
+    main_db: &MainDbConnection,
+    node_id: &str,
+    id: i32,
+    file_id: i32,
+    position: Option<i32>,
+) -> Result<()> {
+    find_playlist(main_db, id).await?;
+
+    let position = match position {
+        Some(position) => position,
+        None => {
+            let items = get_playlist_items(main_db, id).await?;
+            match items.iter().find(|(_, file)| file.id == file_id) {
+                Some((item, _)) => item.position,
+                None => bail!("File {file_id} is not in playlist {id}"),
+            }
+        }
+    };
+
+    playlists::remove_item_from_playlist(main_db, node_id, id, file_id, position).await?;
+    println!("Removed file {file_id} at position {position} from playlist {id}");
+
+    Ok(())
+}
+
+pub async fn delete_playlist(main_db: &MainDbConnection, id: i32) -> Result<()> {
+    let playlist = find_playlist(main_db, id).await?;
+    playlists::remove_playlist(main_db, id).await?;
+    println!("Deleted playlist {id} ({})", playlist.name);
+
+    Ok(())
+}
+
+pub async fn export_playlist(
+    main_db: &MainDbConnection,
+    lib_path: &Path,
+    id: i32,
+    format: &str,
+    output: &Path,
+    style: PathStyle,
+) -> Result<()> {
+    if format != "m3u8" {
+        bail!("Unsupported format. The only supported format is 'm3u8'.");
+    }
+
+    find_playlist(main_db, id).await?;
+    let files: Vec<media_files::Model> = get_playlist_items(main_db, id)
+        .await?
+        .into_iter()
+        .map(|(_, file)| file)
+        .collect();
+
+    let output = write_m3u8(lib_path, output, &files, style)?;
+    println!("Playlist saved to M3U8 file: {}", output.display());
+
+    Ok(())
+}
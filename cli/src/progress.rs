@@ -0,0 +1,140 @@
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// How often the progress is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Width of the bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// A progress bar on stderr
+    Bar,
+    /// One JSON object per line on stdout
+    Json,
+    Quiet,
+}
+
+#[derive(Debug, Serialize)]
+struct ProgressLine<'a> {
+    task: &'a str,
+    done: usize,
+    total: usize,
+    rate: f64,
+    eta_secs: u64,
+}
+
+#[derive(Debug)]
+struct ProgressState {
+    reported_at: Instant,
+    /// Whether the bar is on screen and not followed by a new line yet
+    drawn: bool,
+}
+
+/// Reports the progress of a long task, throttled to a few updates a
+/// second. Can be shared with the workers of the task.
+#[derive(Debug)]
+pub struct ProgressReporter {
+    task: &'static str,
+    mode: ProgressMode,
+    started_at: Instant,
+    state: Mutex<ProgressState>,
+}
+
+pub fn format_eta(seconds: u64) -> String {
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+impl ProgressReporter {
+    pub fn new(task: &'static str, mode: ProgressMode) -> Self {
+        let now = Instant::now();
+
+        ProgressReporter {
+            task,
+            mode,
+            started_at: now,
+            state: Mutex::new(ProgressState {
+                reported_at: now,
+                drawn: false,
+            }),
+        }
+    }
+
+    pub fn report(&self, done: usize, total: usize) {
+        if self.mode == ProgressMode::Quiet {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if done < total && now.duration_since(state.reported_at) < PROGRESS_INTERVAL {
+            return;
+        }
+        state.reported_at = now;
+
+        let rate = done as f64 / now.duration_since(self.started_at).as_secs_f64().max(0.001);
+        let eta_secs = (total.saturating_sub(done) as f64 / rate.max(0.001)).round() as u64;
+
+        match self.mode {
+            ProgressMode::Bar => {
+                let ratio = if total == 0 {
+                    1.0
+                } else {
+                    done as f64 / total as f64
+                };
+                let filled = ((ratio * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+
+                eprint!(
+                    "\r{} [{}{}] {done}/{total} {:.0}% {rate:.1} files/s ETA {}  ",
+                    self.task,
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH - filled),
+                    ratio * 100.0,
+                    format_eta(eta_secs)
+                );
+                state.drawn = done < total;
+                if done >= total {
+                    eprintln!();
+                }
+            }
+            ProgressMode::Json => {
+                let line = ProgressLine {
+                    task: self.task,
+                    done,
+                    total,
+                    rate,
+                    eta_secs,
+                };
+                if let Ok(line) = serde_json::to_string(&line) {
+                    let mut stdout = std::io::stdout().lock();
+                    let _ = writeln!(stdout, "{line}");
+                    let _ = stdout.flush();
+                }
+            }
+            ProgressMode::Quiet => {}
+        }
+    }
+
+    /// Ends the bar line when the task stops before the end, so the next
+    /// output starts on its own line.
+    pub fn finish(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.drawn {
+            eprintln!();
+            state.drawn = false;
+        }
+    }
+}
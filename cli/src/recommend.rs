@@ -1,61 +1,216 @@
+use anyhow::{Result, anyhow, bail};
 use prettytable::{Table, format, row};
 use serde_json::json;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
+use database::actions::collection::CollectionQueryType;
 use database::actions::file::get_file_id_from_path;
 use database::actions::file::get_files_by_ids;
-use database::actions::recommendation::get_recommendation_by_file_id;
+use database::actions::mixes::query_mix_media_files;
+use database::actions::recommendation::{
+    get_recommendation_by_file_id, get_recommendation_by_file_ids,
+};
+use database::actions::search::{SearchHit, search_with_scores};
 use database::connection::{MainDbConnection, RecommendationDbConnection};
 
+use crate::m3u8::{PathStyle, write_m3u8};
+
+/// The most candidates listed when a query is ambiguous.
+const MAX_CANDIDATES: usize = 10;
+/// The most tracks of a collection used as seeds.
+const MAX_SEEDS: usize = 500;
+
 pub struct RecommendMusicOptions<'a> {
     pub canonicalized_path: &'a Path,
     pub path: &'a Path,
     pub item_id: Option<i32>,
     pub file_path: Option<&'a PathBuf>,
+    /// A search query the seeds are the best match of
+    pub query: Option<&'a str>,
+    /// Use the best match of an ambiguous query
+    pub first: bool,
+    /// Use the match of an ambiguous query with this ID
+    pub seed_id: Option<i64>,
+    /// Leave the seeds out of the recommendations
+    pub exclude_seeds: bool,
     pub num: usize,
     pub format: Option<&'a str>,
     pub output: Option<&'a PathBuf>,
+    /// Exports are written into the library, they fail when it is read-only.
+    pub read_only: bool,
+}
+
+/// Splits the type of collection off a query like `artist:four tet`.
+fn parse_query(query: &str) -> (Option<CollectionQueryType>, &str) {
+    if let Some((prefix, text)) = query.split_once(':')
+        && let Ok(collection_type) = prefix.trim().parse::<CollectionQueryType>()
+    {
+        return (Some(collection_type), text.trim());
+    }
+
+    (None, query.trim())
+}
+
+/// Picks the match the seeds come from, failing with the best candidates
+/// when the query is ambiguous and neither `first` nor `seed_id` tell which
+/// one to use.
+fn pick_candidate<'a>(
+    query: &str,
+    hits: &'a [SearchHit],
+    first: bool,
+    seed_id: Option<i64>,
+) -> Result<&'a SearchHit> {
+    if let Some(seed_id) = seed_id {
+        let mut matching = hits.iter().filter(|x| x.id == seed_id);
+        return match (matching.next(), matching.next()) {
+            (Some(hit), None) => Ok(hit),
+            (Some(_), Some(_)) => Err(anyhow!(
+                "Several matches of {query} have the ID {seed_id}, prefix the query with their type"
+            )),
+            _ => Err(anyhow!("No match of {query} has the ID {seed_id}")),
+        };
+    }
+
+    let (_, text) = parse_query(query);
+    let mut exact = hits.iter().filter(|x| x.name.eq_ignore_ascii_case(text));
+    match (hits, exact.next(), exact.next()) {
+        ([], _, _) => Err(anyhow!("Nothing matches {query}")),
+        ([hit], _, _) => Ok(hit),
+        ([hit, ..], _, _) if first => Ok(hit),
+        (_, Some(hit), None) => Ok(hit),
+        _ => {
+            let candidates: Vec<String> = hits
+                .iter()
+                .map(|x| format!("  {} {:0>5} {}", x.collection_type, x.id, x.name))
+                .collect();
+            Err(anyhow!(
+                "{query} matches several items, pass --first or --seed-id to pick one:\n{}",
+                candidates.join("\n")
+            ))
+        }
+    }
 }
 
+/// Resolves a search query into the tracks recommendations are based on,
+/// the tracks of the best matching collection.
+async fn resolve_seeds(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    query: &str,
+    first: bool,
+    seed_id: Option<i64>,
+) -> Result<Vec<i32>> {
+    let (collection_type, text) = parse_query(query);
+    let mut hits = search_with_scores(
+        main_db,
+        text,
+        collection_type.map(|x| vec![x]),
+        MAX_CANDIDATES,
+    )
+    .await?;
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(MAX_CANDIDATES);
+
+    let hit = pick_candidate(query, &hits, first, seed_id)?;
+    let (operator, parameter) = match hit.collection_type {
+        CollectionQueryType::Track => return Ok(vec![hit.id as i32]),
+        CollectionQueryType::Artist => ("lib::artist", hit.id.to_string()),
+        CollectionQueryType::Album => ("lib::album", hit.id.to_string()),
+        CollectionQueryType::Playlist => ("lib::playlist", hit.id.to_string()),
+        CollectionQueryType::Directory => ("lib::directory.deep", hit.name.clone()),
+        ref x => bail!("Recommendations cannot be based on a {x}"),
+    };
+
+    let files = query_mix_media_files(
+        main_db,
+        recommend_db,
+        vec![(operator.to_owned(), parameter)],
+        0,
+        MAX_SEEDS,
+    )
+    .await?;
+    if files.is_empty() {
+        bail!("The {} {} has no tracks", hit.collection_type, hit.name);
+    }
+
+    Ok(files.into_iter().map(|x| x.id).collect())
+}
+
+/// Prints or exports tracks similar to the seeds, a file given by its ID
+/// or path, or the tracks a search query resolves to. Returns whether
+/// recommendations were found.
 pub async fn recommend_music(
     main_db: &MainDbConnection,
     recommend_db: &RecommendationDbConnection,
     options: RecommendMusicOptions<'_>,
-) {
+) -> bool {
     let RecommendMusicOptions {
         canonicalized_path,
         path,
         item_id,
         file_path,
+        query,
+        first,
+        seed_id,
+        exclude_seeds,
         num,
         format,
         output,
+        read_only,
     } = options;
-    let file_id = if let Some(item_id) = item_id {
-        item_id
+
+    if read_only && format.is_some() {
+        eprintln!("The library is read-only, recommendations cannot be exported into it.");
+        return false;
+    }
+
+    let seeds = if let Some(item_id) = item_id {
+        vec![item_id]
     } else if let Some(file_path) = file_path {
         match get_file_id_from_path(main_db, path, file_path).await {
-            Ok(id) => id,
+            Ok(id) => vec![id],
             Err(e) => {
                 eprintln!("{e}");
-                return;
+                return false;
+            }
+        }
+    } else if let Some(query) = query {
+        match resolve_seeds(main_db, recommend_db, query, first, seed_id).await {
+            Ok(seeds) => seeds,
+            Err(e) => {
+                eprintln!("{e:#}");
+                return false;
             }
         }
     } else {
-        eprintln!("Either item_id or file_path must be provided.");
-        return;
+        eprintln!("Either item_id, file_path or query must be provided.");
+        return false;
     };
 
-    let recommendations: Vec<(u32, f32)> =
-        match get_recommendation_by_file_id(recommend_db, file_id, num) {
-            Ok(recommendations) => recommendations,
-            Err(e) => {
-                eprintln!("Failed to get recommendations: {e}");
-                return;
-            }
-        };
+    // Asks for more when the seeds are left out, they are usually the
+    // closest
+    let wanted = if exclude_seeds {
+        num + seeds.len()
+    } else {
+        num
+    };
+    let result = match seeds.as_slice() {
+        [file_id] => get_recommendation_by_file_id(recommend_db, *file_id, wanted),
+        _ => get_recommendation_by_file_ids(main_db, recommend_db, &seeds, wanted).await,
+    };
+    let mut recommendations: Vec<(u32, f32)> = match result {
+        Ok(recommendations) => recommendations,
+        Err(e) => {
+            eprintln!("Failed to get recommendations: {e}");
+            return false;
+        }
+    };
+    if exclude_seeds {
+        recommendations.retain(|(id, _)| !seeds.contains(&(*id as i32)));
+    }
+    recommendations.truncate(num);
 
     // Get file details of recommendations
     let ids: Vec<i32> = recommendations.iter().map(|(id, _)| *id as i32).collect();
@@ -72,7 +227,7 @@ pub async fn recommend_music(
             save_recommendations_as_json(canonicalized_path, output, &recommendations).await;
         }
         Some("m3u8") => {
-            save_recommendations_as_m3u8(canonicalized_path, output, path, &files).await;
+            save_recommendations_as_m3u8(canonicalized_path, output, &files).await;
         }
         Some(_) => {
             eprintln!("Unsupported format. Supported formats are 'json' and 'm3u8'.");
@@ -81,6 +236,8 @@ pub async fn recommend_music(
             display_recommendations_in_table(path, &recommendations, &files);
         }
     }
+
+    true
 }
 
 pub async fn save_recommendations_as_json(
@@ -128,8 +285,7 @@ pub async fn save_recommendations_as_json(
 pub async fn save_recommendations_as_m3u8(
     canonicalized_path: &Path,
     output: Option<&PathBuf>,
-    path: &Path,
-    files: &Vec<database::entities::media_files::Model>,
+    files: &[database::entities::media_files::Model],
 ) {
     let output_path = match output {
         Some(path) => path,
@@ -139,52 +295,18 @@ pub async fn save_recommendations_as_m3u8(
         }
     };
 
-    let corrected_path = check_and_correct_extension(&canonicalized_path.join(output_path), "m3u8");
-    if corrected_path != *output_path {
-        eprintln!("Warning: Output file extension corrected to .m3u8");
-    }
-
-    if let Some(parent) = corrected_path.parent()
-        && let Err(e) = fs::create_dir_all(parent)
-    {
-        eprintln!("Failed to create directories: {e}");
-        return;
-    }
-
-    let mut file = match File::create(&corrected_path) {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("Failed to create file: {e}");
-            return;
-        }
-    };
-
-    if let Err(e) = file.write_all("#EXTM3U\n".as_bytes()) {
-        eprintln!("Failed to write to file: {e}");
-        return;
-    }
-
-    for file_info in files {
-        let relative_path = path.join(&file_info.directory).join(&file_info.file_name);
-        let relative_to_output =
-            match pathdiff::diff_paths(&relative_path, corrected_path.parent().unwrap()) {
-                Some(path) => path,
-                None => {
-                    eprintln!("Failed to calculate relative path");
-                    return;
-                }
-            };
-
-        if let Err(e) = writeln!(file, "{}", relative_to_output.display()) {
-            eprintln!("Failed to write to file: {e}");
-            return;
-        }
+    match write_m3u8(
+        canonicalized_path,
+        &canonicalized_path.join(output_path),
+        files,
+        PathStyle::Relative,
+    ) {
+        Ok(corrected_path) => println!(
+            "Recommendations saved to M3U8 file: {}",
+            corrected_path.display()
+        ),
+        Err(e) => eprintln!("{e:#}"),
     }
-
-    println!(
-        "Recommendations saved to M3U8 file: {}",
-        corrected_path.to_str().unwrap()
-    );
 }
 
 pub fn display_recommendations_in_table(
@@ -0,0 +1,435 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use aes::Aes128;
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::URL_SAFE};
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, block_padding::Pkcs7};
+use directories::BaseDirs;
+use rpassword::prompt_password;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use uuid::Uuid;
+
+use scrobbling::libre_fm::ServiceEndpoint;
+use scrobbling::manager::{ScrobblingManager, ScrobblingService, ScrobblingServiceManager};
+
+type CredentialEncryptor = cbc::Encryptor<Aes128>;
+type CredentialDecryptor = cbc::Decryptor<Aes128>;
+
+/// The file the app keeps its settings in, named after its storage.
+const SETTINGS_FILE_NAME: &str = "rune.gs";
+/// The prefix of every key the app stores its settings under.
+const SETTINGS_EXTENSION_NAME: &str = "ci.not.rune";
+/// The names of the process of the app, on Linux, Windows and macOS.
+const APP_PROCESS_NAMES: [&str; 3] = ["rune", "rune.exe", "Rune"];
+const ENCRYPTION_KEY: &str = "encryption_key";
+const CREDENTIALS_KEY: &str = "login_credentials";
+
+/// Credentials read from the environment before prompting.
+const USERNAME_ENV: &str = "RUNE_SCROBBLE_USERNAME";
+const PASSWORD_ENV: &str = "RUNE_SCROBBLE_PASSWORD";
+const API_KEY_ENV: &str = "RUNE_SCROBBLE_API_KEY";
+const API_SECRET_ENV: &str = "RUNE_SCROBBLE_API_SECRET";
+
+pub struct ScrobbleOptions<'a> {
+    /// The directory of the settings of the app, the platform default if
+    /// unset
+    pub settings_dir: Option<&'a Path>,
+    /// The profile of the app the credentials belong to
+    pub profile: &'a str,
+}
+
+/// The credentials of one service, as the app stores them.
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredCredential {
+    #[serde(rename = "serviceId")]
+    service_id: String,
+    username: String,
+    password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_secret: Option<String>,
+    /// What is submitted to the service, everything when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scrobbling_enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    now_playing_enabled: Option<bool>,
+    /// The API of a Libre.fm compatible server, Libre.fm itself when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allow_insecure: Option<bool>,
+}
+
+impl StoredCredential {
+    fn endpoint(&self) -> Option<ServiceEndpoint> {
+        Some(ServiceEndpoint {
+            base_url: self.base_url.clone()?,
+            allow_insecure: self.allow_insecure.unwrap_or(false),
+        })
+    }
+}
+
+/// Where the app keeps its settings on this platform.
+fn default_settings_dir() -> Result<PathBuf> {
+    let dirs = BaseDirs::new().ok_or_else(|| anyhow!("Failed to locate the home directory"))?;
+
+    let path = if cfg!(target_os = "macos") {
+        dirs.home_dir()
+            .join("Library/Containers/ci.not.player/Data/Documents")
+    } else if cfg!(windows) {
+        dirs.data_dir().join("ci.not").join("rune")
+    } else {
+        dirs.data_dir().join(SETTINGS_EXTENSION_NAME)
+    };
+
+    Ok(path)
+}
+
+/// Fails while the app is running. It keeps its settings in memory and
+/// writes them whole, which would drop what is changed meanwhile.
+fn ensure_app_closed() -> Result<()> {
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, ProcessRefreshKind::new());
+
+    let running = APP_PROCESS_NAMES.into_iter().any(|name| {
+        system
+            .processes_by_exact_name(OsStr::new(name))
+            .next()
+            .is_some()
+    });
+    if running {
+        bail!("Rune is running, quit it before changing its settings");
+    }
+
+    Ok(())
+}
+
+/// Parses a service name, ignoring case and punctuation, so `lastfm` and
+/// `Last.fm` both name Last.fm. GNU FM servers are named `gnufm:<id>`.
+fn parse_service(name: &str) -> Result<ScrobblingService> {
+    if let Some((prefix, id)) = name.split_once(':')
+        && prefix.eq_ignore_ascii_case("gnufm")
+        && !id.is_empty()
+    {
+        return Ok(ScrobblingService::GnuFm(id.to_owned()));
+    }
+
+    let normalized: String = name
+        .chars()
+        .filter(|x| x.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    ScrobblingService::BUILT_IN
+        .into_iter()
+        .find(|x| x.to_string().to_lowercase() == normalized)
+        .ok_or_else(|| {
+            anyhow!("Invalid service {name}, expected lastfm, librefm, listenbrainz or gnufm:<id>")
+        })
+}
+
+/// The settings file of the app, read and written whole.
+struct AppSettings {
+    path: PathBuf,
+    profile: String,
+    values: Map<String, Value>,
+}
+
+impl AppSettings {
+    fn open(options: &ScrobbleOptions) -> Result<Self> {
+        let dir = match options.settings_dir {
+            Some(dir) => dir.to_path_buf(),
+            None => default_settings_dir()?,
+        };
+        let path = dir.join(SETTINGS_FILE_NAME);
+
+        let values = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read the settings {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid settings {}", path.display()))?
+        } else {
+            Map::new()
+        };
+
+        Ok(AppSettings {
+            path,
+            profile: options.profile.to_owned(),
+            values,
+        })
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{SETTINGS_EXTENSION_NAME}#{}:{name}", self.profile)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(&self.key(name)).and_then(Value::as_str)
+    }
+
+    fn set(&mut self, name: &str, value: String) {
+        self.values.insert(self.key(name), Value::String(value));
+    }
+
+    /// Writes the settings next to the file first and renames them over it,
+    /// so an interrupted write leaves the previous settings in place.
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string(&self.values)?;
+        let temp_path = self.path.with_extension("gs.tmp");
+
+        let written = fs::File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(content.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_path, &self.path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e)
+                .with_context(|| format!("Failed to write the settings {}", self.path.display()));
+        }
+
+        Ok(())
+    }
+
+    /// The key the credentials are encrypted with, generated as the app
+    /// does when missing.
+    fn encryption_key(&mut self) -> [u8; 16] {
+        let key = match self.get(ENCRYPTION_KEY) {
+            Some(key) if key.len() >= 16 => key.to_owned(),
+            _ => {
+                let key = Uuid::new_v4().to_string();
+                self.set(ENCRYPTION_KEY, key.clone());
+                key
+            }
+        };
+
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&key.as_bytes()[..16]);
+        bytes
+    }
+
+    fn credentials(&mut self) -> Result<Vec<StoredCredential>> {
+        let Some(encrypted) = self.get(CREDENTIALS_KEY).map(str::to_owned) else {
+            return Ok(Vec::new());
+        };
+
+        let key = self.encryption_key();
+        let data = URL_SAFE
+            .decode(encrypted)
+            .context("Invalid stored credentials")?;
+        let data = CredentialDecryptor::new(&key.into(), &[0; 16].into())
+            .decrypt_padded_vec_mut::<Pkcs7>(&data)
+            .map_err(|_| anyhow!("Failed to decrypt the stored credentials"))?;
+
+        serde_json::from_slice(&data).context("Invalid stored credentials")
+    }
+
+    fn set_credentials(&mut self, credentials: &[StoredCredential]) -> Result<()> {
+        let key = self.encryption_key();
+        let data = serde_json::to_vec(credentials)?;
+        let data = CredentialEncryptor::new(&key.into(), &[0; 16].into())
+            .encrypt_padded_vec_mut::<Pkcs7>(&data);
+
+        self.set(CREDENTIALS_KEY, URL_SAFE.encode(data));
+        Ok(())
+    }
+}
+
+fn prompt_line(prompt: &str) -> Result<String> {
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_owned())
+}
+
+/// Reads a value from the environment, or asks for it, without echoing it
+/// when secret.
+fn read_value(env_name: &str, prompt: &str, secret: bool) -> Result<String> {
+    if let Ok(value) = env::var(env_name)
+        && !value.is_empty()
+    {
+        return Ok(value);
+    }
+
+    let value = if secret {
+        prompt_password(prompt)?
+    } else {
+        prompt_line(prompt)?
+    };
+    if value.is_empty() {
+        bail!("{} is required", prompt.trim_end_matches([':', ' ']));
+    }
+
+    Ok(value)
+}
+
+async fn authenticate(credential: &StoredCredential) -> Result<()> {
+    let service = credential.service_id.clone().into();
+    let mut manager = ScrobblingManager::new(1, Duration::from_secs(1));
+    manager.set_endpoint(&service, credential.endpoint())?;
+    manager
+        .authenticate(
+            &service,
+            &credential.username,
+            &credential.password,
+            credential.api_key.clone(),
+            credential.api_secret.clone(),
+            false,
+        )
+        .await
+}
+
+/// Logs in to a scrobbling service and stores the credentials where the
+/// app reads them, the app logging in with them on its next start.
+///
+/// The credentials come from `RUNE_SCROBBLE_USERNAME`,
+/// `RUNE_SCROBBLE_PASSWORD`, `RUNE_SCROBBLE_API_KEY` and
+/// `RUNE_SCROBBLE_API_SECRET` when set, and are asked for otherwise.
+///
+/// Libre.fm and GNU FM servers are reached at `url` when set, which must use
+/// HTTPS unless `allow_insecure` is set.
+pub async fn scrobble_login(
+    service: &str,
+    username: Option<&str>,
+    url: Option<&str>,
+    allow_insecure: bool,
+    options: ScrobbleOptions<'_>,
+) -> Result<()> {
+    let service = parse_service(service)?;
+    if url.is_some() && !service.accepts_endpoint() {
+        bail!("{service} can't be reached at another URL");
+    }
+    if url.is_none() && matches!(service, ScrobblingService::GnuFm(_)) {
+        bail!("{service} requires the URL of its server");
+    }
+    ensure_app_closed()?;
+    let mut settings = AppSettings::open(&options)?;
+    let mut credentials = settings.credentials()?;
+
+    let username = match username {
+        Some(username) => username.to_owned(),
+        None => read_value(USERNAME_ENV, "Username: ", false)?,
+    };
+    let password = read_value(PASSWORD_ENV, "Password: ", true)?;
+    let (api_key, api_secret) = if service == ScrobblingService::LastFm {
+        (
+            Some(read_value(API_KEY_ENV, "API key: ", false)?),
+            Some(read_value(API_SECRET_ENV, "API secret: ", true)?),
+        )
+    } else {
+        (None, None)
+    };
+
+    // Logging in again keeps what is submitted to the service
+    let previous = credentials
+        .iter()
+        .find(|x| x.service_id == service.to_string());
+    let credential = StoredCredential {
+        service_id: service.to_string(),
+        username,
+        password,
+        api_key,
+        api_secret,
+        scrobbling_enabled: previous.and_then(|x| x.scrobbling_enabled),
+        now_playing_enabled: previous.and_then(|x| x.now_playing_enabled),
+        base_url: url.map(str::to_owned),
+        allow_insecure: url.map(|_| allow_insecure),
+    };
+    authenticate(&credential)
+        .await
+        .with_context(|| format!("Failed to log in to {service}"))?;
+
+    credentials.retain(|x| x.service_id != credential.service_id);
+    credentials.push(credential);
+    settings.set_credentials(&credentials)?;
+    settings.save()?;
+
+    println!("Logged in to {service}.");
+    Ok(())
+}
+
+/// Removes the stored credentials of a service.
+pub async fn scrobble_logout(service: &str, options: ScrobbleOptions<'_>) -> Result<()> {
+    let service = parse_service(service)?;
+    ensure_app_closed()?;
+    let mut settings = AppSettings::open(&options)?;
+    let mut credentials = settings.credentials()?;
+
+    let count = credentials.len();
+    credentials.retain(|x| x.service_id != service.to_string());
+    if credentials.len() == count {
+        println!("Not logged in to {service}.");
+        return Ok(());
+    }
+
+    settings.set_credentials(&credentials)?;
+    settings.save()?;
+
+    println!("Logged out of {service}.");
+    Ok(())
+}
+
+/// Prints the services with stored credentials, logging in to each when
+/// `check` is set.
+///
+/// Submissions and their queue only exist in the running app, and are not
+/// reported.
+pub async fn scrobble_status(check: bool, options: ScrobbleOptions<'_>) -> Result<()> {
+    let mut settings = AppSettings::open(&options)?;
+    let credentials = settings.credentials()?;
+
+    let mut services = ScrobblingService::BUILT_IN.to_vec();
+    services.extend(
+        credentials
+            .iter()
+            .filter_map(|x| x.service_id.parse().ok())
+            .filter(|x| matches!(x, ScrobblingService::GnuFm(_))),
+    );
+
+    for service in services {
+        let Some(credential) = credentials
+            .iter()
+            .find(|x| x.service_id == service.to_string())
+        else {
+            println!("{service}: not logged in");
+            continue;
+        };
+
+        let server = credential
+            .base_url
+            .as_ref()
+            .map(|x| format!(" on {x}"))
+            .unwrap_or_default();
+        if !check {
+            println!("{service}: logged in as {}{server}", credential.username);
+            continue;
+        }
+
+        match authenticate(credential).await {
+            Ok(()) => println!(
+                "{service}: logged in as {}{server}, verified",
+                credential.username
+            ),
+            Err(e) => println!(
+                "{service}: logged in as {}{server}, authentication failed: {e}",
+                credential.username
+            ),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use prettytable::{Table, format, row};
+use serde::Serialize;
+
+use database::actions::search::search_with_scores;
+use database::connection::MainDbConnection;
+
+use crate::output::{OutputFormat, print_csv, print_json};
+
+#[derive(Debug, Serialize)]
+struct SearchRecord {
+    collection_type: String,
+    id: i64,
+    name: String,
+    score: f64,
+}
+
+/// Prints the best matches of the query in each collection type. Returns
+/// whether anything matched.
+pub async fn search_library(
+    main_db: &MainDbConnection,
+    query: &str,
+    num: usize,
+    output: OutputFormat,
+) -> Result<bool> {
+    let records: Vec<SearchRecord> = search_with_scores(main_db, query, None, num)
+        .await?
+        .into_iter()
+        .map(|hit| SearchRecord {
+            collection_type: hit.collection_type.to_string(),
+            id: hit.id,
+            name: hit.name,
+            score: hit.score,
+        })
+        .collect();
+
+    match output {
+        OutputFormat::Table => {
+            if records.is_empty() {
+                println!("Nothing matches {query}.");
+            } else {
+                let mut table = Table::new();
+                table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+                table.set_titles(row!["Type", "ID", "Name", "Score"]);
+                for record in &records {
+                    table.add_row(row![
+                        record.collection_type,
+                        record.id,
+                        record.name,
+                        format!("{:.3}", record.score)
+                    ]);
+                }
+                table.printstd();
+            }
+        }
+        OutputFormat::Json => print_json(&records)?,
+        OutputFormat::Csv => print_csv(&records)?,
+    }
+
+    Ok(!records.is_empty())
+}
@@ -0,0 +1,147 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use log::{error, info};
+use tokio::signal::ctrl_c;
+
+use hub::server::runner::{ServeOptions, run_server};
+
+/// A log file shared by every writer of the logger, which can be reopened
+/// once a log rotation moved it away.
+#[derive(Debug, Clone)]
+pub struct LogFile {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+fn open_log_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl LogFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = open_log_file(path)
+            .with_context(|| format!("Failed to open the log file {}", path.display()))?;
+
+        Ok(LogFile {
+            path: path.to_path_buf(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Closes the file and opens the one now at its path.
+    pub fn reopen(&self) -> io::Result<()> {
+        let file = open_log_file(&self.path)?;
+        *self.file.lock().unwrap() = file;
+
+        Ok(())
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.lock().unwrap().flush()
+    }
+}
+
+/// Holds the pid file for as long as the server runs, removing it when
+/// dropped.
+struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    fn create(path: &Path) -> Result<Self> {
+        fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Failed to write the pid file {}", path.display()))?;
+
+        Ok(PidFile {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub struct ServeLibraryOptions<'a> {
+    pub lib_path: &'a str,
+    pub server: ServeOptions,
+    /// Reopened on SIGHUP when set
+    pub log_file: Option<LogFile>,
+    pub pid_file: Option<&'a Path>,
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM where it exists.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => error!("Failed to listen for SIGTERM: {e}"),
+        }
+    }
+
+    if let Err(e) = ctrl_c().await {
+        error!("Failed to listen for Ctrl-C: {e}");
+    }
+}
+
+/// Reopens the log file on every SIGHUP, as log rotation tools expect.
+#[cfg(unix)]
+fn reopen_on_hangup(log_file: LogFile) -> Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup()).context("Failed to listen for SIGHUP")?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match log_file.reopen() {
+                Ok(()) => info!("Reopened the log file"),
+                Err(e) => eprintln!("Failed to reopen the log file: {e}"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn reopen_on_hangup(_log_file: LogFile) -> Result<()> {
+    Ok(())
+}
+
+/// Runs the same server as `rune-server` on the library until Ctrl-C or
+/// SIGTERM, which shut it down gracefully.
+///
+/// The process stays in the foreground, a service manager or a wrapper
+/// detaches it, with the logs going to the log file and the pid to the pid
+/// file.
+pub async fn serve_library(options: ServeLibraryOptions<'_>) -> Result<()> {
+    if let Some(log_file) = options.log_file {
+        reopen_on_hangup(log_file)?;
+    }
+    let _pid_file = options.pid_file.map(PidFile::create).transpose()?;
+
+    run_server(options.lib_path, options.server, shutdown_signal()).await?;
+    info!("Server stopped");
+
+    Ok(())
+}
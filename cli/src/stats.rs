@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use anyhow::Result;
+use prettytable::{Table, format, row};
+use serde_json::json;
+
+use database::actions::library_stats::{
+    RankBy, RankedItem, get_library_size, get_library_stats, get_top_artists, get_top_tracks,
+};
+use database::connection::MainDbConnection;
+use fsio::FsIo;
+
+pub struct StatsOptions<'a> {
+    pub lib_path: &'a Path,
+    /// Number of top tracks and artists to list
+    pub top: u64,
+    pub by: RankBy,
+    /// Read the size of every file, which is slow on large libraries
+    pub with_size: bool,
+    pub json: bool,
+}
+
+fn format_duration(seconds: f64) -> String {
+    let total_seconds = seconds.round() as u64;
+
+    format!(
+        "{}:{:02}:{:02}",
+        total_seconds / 3600,
+        total_seconds / 60 % 60,
+        total_seconds % 60
+    )
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+fn display_ranking(title: &str, items: &[RankedItem]) {
+    println!("{title}");
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["#", "ID", "Name", "Plays", "Time"]);
+    for (index, item) in items.iter().enumerate() {
+        table.add_row(row![
+            index + 1,
+            item.id,
+            item.name,
+            item.plays,
+            format_duration(item.time)
+        ]);
+    }
+    table.printstd();
+}
+
+pub async fn library_stats(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: StatsOptions<'_>,
+) -> Result<()> {
+    let StatsOptions {
+        lib_path,
+        top,
+        by,
+        with_size,
+        json,
+    } = options;
+
+    let stats = get_library_stats(main_db).await?;
+    let size = if with_size {
+        Some(get_library_size(fsio, main_db, lib_path, 500).await?)
+    } else {
+        None
+    };
+    let top_tracks = get_top_tracks(main_db, by, top).await?;
+    let top_artists = get_top_artists(main_db, by, top).await?;
+
+    if json {
+        println!(
+            "{}",
+            json!({
+                "library": stats,
+                "size": size,
+                "top_tracks": top_tracks,
+                "top_artists": top_artists,
+            })
+        );
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.add_row(row!["Tracks", stats.tracks]);
+    table.add_row(row!["Albums", stats.albums]);
+    table.add_row(row!["Artists", stats.artists]);
+    table.add_row(row!["Duration", format_duration(stats.duration)]);
+    if let Some(size) = size {
+        table.add_row(row!["Size", format_size(size)]);
+    }
+    table.add_row(row!["Liked", stats.liked]);
+    table.add_row(row!["Unanalyzed", stats.unanalyzed]);
+    table.printstd();
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["Format", "Tracks", "Duration"]);
+    for format in &stats.formats {
+        table.add_row(row![
+            format.extension,
+            format.tracks,
+            format_duration(format.duration)
+        ]);
+    }
+    table.printstd();
+
+    if !top_tracks.is_empty() {
+        display_ranking(&format!("Top tracks by {by}"), &top_tracks);
+    }
+    if !top_artists.is_empty() {
+        display_ranking(&format!("Top artists by {by}"), &top_artists);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+use log::info;
+use rustls::ClientConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use uuid::Uuid;
+
+use ::discovery::client::CertValidator;
+use ::discovery::endpoint::ServerEndpoint;
+use ::discovery::url::decode_rnsrv_url;
+use ::sync::core::{RemoteDataSource, SyncDirection};
+use ::sync::hlc::SyncTaskContext;
+use ::sync::sync_scheduler::TableSyncResult;
+use database::connection::MainDbConnection;
+use database::sync::data_source::RemoteHttpDataSource;
+use database::sync::{SyncRunOptions, preview_sync, setup_and_run_sync_with_options};
+
+/// Exit code of syncs where some tables failed, connection and
+/// authentication failures exit with 1.
+pub const SYNC_PARTIAL_FAILURE_EXIT_CODE: i32 = 2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// Only apply the changes of the peer locally
+    Pull,
+    /// Only send the local changes to the peer
+    Push,
+    /// Exchange changes both ways, the latest change wins
+    #[default]
+    Bidirectional,
+}
+
+impl From<Direction> for SyncDirection {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Pull => SyncDirection::Pull,
+            Direction::Push => SyncDirection::Push,
+            Direction::Bidirectional => SyncDirection::Bidirectional,
+        }
+    }
+}
+
+pub struct SyncOptions<'a> {
+    /// The directory holding the trusted servers and the client certificate
+    pub config_path: &'a Path,
+    pub node_id: &'a str,
+    /// An rnsrv:// URL, a URL or a host with an optional port
+    pub peer: &'a str,
+    pub direction: Direction,
+    /// Only sync these tables, every table if empty
+    pub tables: &'a [String],
+    /// Only print the changes each side would send
+    pub dry_run: bool,
+}
+
+/// The base URLs of the peer, every host of an rnsrv:// URL in order.
+fn peer_base_urls(peer: &str) -> Result<Vec<String>> {
+    let specs = if peer.starts_with("rnsrv://") {
+        let url = decode_rnsrv_url(peer).map_err(|e| anyhow!("Invalid peer {peer}: {e}"))?;
+        if !url.is_supported() {
+            bail!("The peer speaks a newer protocol, update Rune");
+        }
+        url.host_specs()
+    } else {
+        vec![peer.to_owned()]
+    };
+
+    specs
+        .iter()
+        .map(|x| Ok(ServerEndpoint::parse(x)?.base_url()))
+        .collect()
+}
+
+/// A client trusting the servers trusted on this device, and presenting
+/// this device's certificate when one was generated or imported.
+async fn build_client(config_path: &Path) -> Result<reqwest::Client> {
+    let validator = Arc::new(
+        CertValidator::new(config_path)
+            .await
+            .context("Failed to load the trusted servers")?,
+    );
+    let builder = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(validator);
+
+    let cert_path = config_path.join("certificate.pem");
+    let key_path = config_path.join("private_key.pem");
+    let tls_config = if cert_path.exists() && key_path.exists() {
+        let certificate = CertificateDer::from_pem_file(&cert_path)
+            .context("Failed to read the client certificate")?;
+        let private_key = PrivateKeyDer::from_pem_file(&key_path)
+            .context("Failed to read the client private key")?;
+        builder
+            .with_client_auth_cert(vec![certificate], private_key)
+            .context("Invalid client certificate")?
+    } else {
+        info!("No client certificate found, connecting without one");
+        builder.with_no_client_auth()
+    };
+
+    Ok(reqwest::Client::builder()
+        .use_preconfigured_tls(tls_config)
+        .build()?)
+}
+
+/// Connects to the first host of the peer which answers.
+async fn connect_peer(client: reqwest::Client, peer: &str) -> Result<RemoteHttpDataSource> {
+    let mut last_error = None;
+    for base_url in peer_base_urls(peer)? {
+        let remote = RemoteHttpDataSource::with_client(&base_url, client.clone());
+        match remote.get_remote_node_id().await {
+            Ok(node_id) => {
+                info!("Connected to {base_url}, node {node_id}");
+                return Ok(remote);
+            }
+            Err(e) => {
+                info!("Failed to reach {base_url}: {e:#}");
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error
+        .unwrap_or_else(|| anyhow!("The peer has no host"))
+        .context(format!("Failed to connect to {peer}")))
+}
+
+/// Syncs the library with a peer, printing the result of every table as
+/// it completes. Returns whether every table succeeded, connection and
+/// authentication failures are errors.
+pub async fn sync_with_peer(main_db: &MainDbConnection, options: SyncOptions<'_>) -> Result<bool> {
+    let local_node_id = Uuid::parse_str(options.node_id).context("Invalid node id")?;
+    let client = build_client(options.config_path).await?;
+    let remote = connect_peer(client, options.peer).await?;
+
+    let run_options = SyncRunOptions {
+        direction: options.direction.into(),
+        tables: (!options.tables.is_empty()).then(|| options.tables.to_vec()),
+    };
+
+    if options.dry_run {
+        let previews = preview_sync(main_db, local_node_id, &remote, &run_options).await?;
+        for preview in &previews {
+            println!(
+                "{}: {} to pull, {} to push",
+                preview.table_name, preview.remote_changes, preview.local_changes
+            );
+        }
+        println!("Dry run, nothing was changed.");
+
+        return Ok(true);
+    }
+
+    let hlc_context = SyncTaskContext::new(local_node_id);
+    let results = setup_and_run_sync_with_options(
+        main_db,
+        local_node_id,
+        &remote,
+        &hlc_context,
+        run_options,
+        |result| match result {
+            TableSyncResult::Success(metadata) => {
+                println!("{}: synced", metadata.table_name)
+            }
+            TableSyncResult::Failure { table_name, error } => {
+                println!("{table_name}: failed, {error:#}")
+            }
+        },
+    )
+    .await?;
+
+    let failed = results.iter().filter(|x| !x.is_success()).count();
+    if failed > 0 {
+        eprintln!("{failed} of {} tables failed to sync", results.len());
+    }
+
+    Ok(failed == 0)
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+use prettytable::{Table, format, row};
+
+use database::actions::verify::{FileProblemKind, remove_media_files, verify_library_files};
+use database::connection::MainDbConnection;
+use fsio::FsIo;
+
+use crate::progress::{ProgressMode, ProgressReporter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyFix {
+    /// Remove the missing files from the library
+    RemoveMissing,
+}
+
+impl FromStr for VerifyFix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "missing=remove" => Ok(VerifyFix::RemoveMissing),
+            "missing=offline" => Err(anyhow!(
+                "Marking files offline is not supported, the library does not track availability"
+            )),
+            _ => Err(anyhow!("Invalid fix {s}, expected missing=remove")),
+        }
+    }
+}
+
+pub struct VerifyOptions<'a> {
+    pub lib_path: &'a Path,
+    pub hash: bool,
+    /// Save the problems as JSON to this file
+    pub output: Option<&'a PathBuf>,
+    pub fix: Option<VerifyFix>,
+}
+
+fn describe_problem(kind: &FileProblemKind) -> String {
+    match kind {
+        FileProblemKind::Missing => "missing".to_owned(),
+        FileProblemKind::Unreadable { error } => format!("unreadable: {error}"),
+        FileProblemKind::Modified => "modified since the last scan".to_owned(),
+        FileProblemKind::HashMismatch { expected, actual } => {
+            format!("content changed: {expected} -> {actual}")
+        }
+    }
+}
+
+pub async fn verify_files(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    options: VerifyOptions<'_>,
+) -> Result<()> {
+    let VerifyOptions {
+        lib_path,
+        hash,
+        output,
+        fix,
+    } = options;
+
+    let progress = ProgressReporter::new("Checking", ProgressMode::Bar);
+    let report = verify_library_files(fsio, main_db, lib_path, hash, 500, |checked, total| {
+        progress.report(checked, total)
+    })
+    .await;
+    progress.finish();
+    let report = report?;
+
+    if let Some(output) = output {
+        fs::write(output, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to save the report to {}", output.display()))?;
+    }
+
+    if report.problems.is_empty() {
+        println!("All {} files are fine.", report.checked);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(row!["ID", "File Path", "Problem"]);
+    for problem in &report.problems {
+        table.add_row(row![
+            problem.file_id,
+            problem.path.display(),
+            describe_problem(&problem.kind)
+        ]);
+    }
+    table.printstd();
+
+    let mut remaining = report.problems.len();
+    if fix == Some(VerifyFix::RemoveMissing) {
+        let missing = report.missing_file_ids();
+        remove_media_files(main_db, &missing).await?;
+        println!("Removed {} missing files from the library.", missing.len());
+        remaining -= missing.len();
+    }
+
+    if remaining > 0 {
+        bail!("{remaining} of {} files have problems", report.checked);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,360 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use log::{error, info};
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+use analysis::utils::computing_device::ComputingDevice;
+use database::actions::analysis::{analysis_audio_library, empty_progress_callback};
+use database::actions::cover_art::scan_cover_arts;
+use database::actions::file::{get_files_at_or_below, move_media_files};
+use database::actions::maintenance::checkpoint_main_db;
+use database::actions::metadata::scan_audio_files;
+use database::actions::recommendation::sync_recommendation;
+use database::actions::verify::remove_media_files;
+use database::connection::{MainDbConnection, RecommendationDbConnection};
+use fsio::{FileWatchEvent, FsIo, TRASH_DIR};
+use metadata::scanner::is_audio_file;
+
+/// Number of files scanned between two checks for a shutdown request.
+const SCAN_CHUNK_SIZE: usize = 16;
+
+pub struct WatchOptions<'a> {
+    pub lib_path: &'a Path,
+    pub node_id: &'a str,
+    /// Analyze the files added or changed, on this device
+    pub analyze: Option<ComputingDevice>,
+    /// Only log what would be done
+    pub dry_run: bool,
+    /// How long the library must stay quiet before the changes are handled,
+    /// so mass copies are handled at once
+    pub settle: Duration,
+}
+
+/// Changes collected while the library settles.
+#[derive(Debug, Default)]
+struct PendingChanges {
+    /// Files or directories created or modified
+    changed: BTreeSet<PathBuf>,
+    removed: BTreeSet<PathBuf>,
+    renamed: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PendingChanges {
+    fn push(&mut self, event: FileWatchEvent) {
+        match event {
+            FileWatchEvent::Created(path) | FileWatchEvent::Modified(path) => {
+                self.removed.remove(&path);
+                self.changed.insert(path);
+            }
+            FileWatchEvent::Removed(path) => {
+                self.changed.remove(&path);
+                self.removed.insert(path);
+            }
+            FileWatchEvent::Renamed { from, to } => {
+                if self.changed.remove(&from) {
+                    self.changed.insert(to);
+                } else {
+                    self.renamed.push((from, to));
+                }
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.changed.len() + self.removed.len() + self.renamed.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Path relative to the library root with `/` separators, like the paths
+/// stored in the database. `None` for paths the library ignores.
+fn to_library_path(lib_path: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(lib_path).ok()?;
+    if relative
+        .components()
+        .any(|x| x.as_os_str() == ".rune" || x.as_os_str() == TRASH_DIR)
+    {
+        return None;
+    }
+
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+fn is_ignored(lib_path: &Path, event: &FileWatchEvent) -> bool {
+    let path = match event {
+        FileWatchEvent::Created(path)
+        | FileWatchEvent::Modified(path)
+        | FileWatchEvent::Removed(path) => path,
+        FileWatchEvent::Renamed { to, .. } => to,
+    };
+
+    to_library_path(lib_path, path).is_none()
+}
+
+/// Audio files at or below the changed paths.
+fn collect_audio_files(fsio: &FsIo, paths: &BTreeSet<PathBuf>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let node = match fsio.metadata(path) {
+            Ok(node) => node,
+            // Already gone, the removal is handled with the next changes
+            Err(_) => continue,
+        };
+
+        if node.is_dir {
+            match fsio.walk_dir(path, true) {
+                Ok(nodes) => files.extend(
+                    nodes
+                        .into_iter()
+                        .filter(|x| x.is_file && is_audio_file(x))
+                        .map(|x| x.path),
+                ),
+                Err(e) => error!("Failed to walk {}: {e}", path.display()),
+            }
+        } else if is_audio_file(&node) {
+            files.push(node.path);
+        }
+    }
+
+    files
+}
+
+async fn apply_changes(
+    fsio: &Arc<FsIo>,
+    main_db: &MainDbConnection,
+    analysis_db: &RecommendationDbConnection,
+    options: &WatchOptions<'_>,
+    mut changes: PendingChanges,
+    cancel_token: &CancellationToken,
+) -> Result<()> {
+    let lib_path = options.lib_path;
+    let dry_run = options.dry_run;
+
+    for (from, to) in std::mem::take(&mut changes.renamed) {
+        let (Some(from_path), Some(to_path)) = (
+            to_library_path(lib_path, &from),
+            to_library_path(lib_path, &to),
+        ) else {
+            changes.changed.insert(to);
+            continue;
+        };
+
+        if dry_run {
+            info!("Would move {from_path} to {to_path}");
+            continue;
+        }
+
+        match move_media_files(main_db, &from_path, &to_path).await? {
+            // Moved into the library, or not scanned yet
+            0 => {
+                changes.changed.insert(to);
+            }
+            moved => info!("Moved {moved} files from {from_path} to {to_path}"),
+        }
+    }
+
+    for path in &changes.removed {
+        let Some(path) = to_library_path(lib_path, path) else {
+            continue;
+        };
+
+        let file_ids: Vec<i32> = get_files_at_or_below(main_db, &path)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect();
+        if file_ids.is_empty() {
+            continue;
+        }
+
+        if dry_run {
+            info!("Would remove {} files at {path}", file_ids.len());
+        } else {
+            remove_media_files(main_db, &file_ids).await?;
+            info!("Removed {} files at {path}", file_ids.len());
+        }
+    }
+
+    let files = collect_audio_files(fsio, &changes.changed);
+    let mut scanned = 0;
+    for (index, chunk) in files.chunks(SCAN_CHUNK_SIZE).enumerate() {
+        if cancel_token.is_cancelled() {
+            info!(
+                "Stopped before scanning {} files",
+                files.len() - index * SCAN_CHUNK_SIZE
+            );
+            return Ok(());
+        }
+
+        for file in chunk {
+            info!(
+                "{} {}",
+                if dry_run { "Would scan" } else { "Scanning" },
+                file.display()
+            );
+        }
+        if !dry_run {
+            scanned += scan_audio_files(fsio, main_db, options.node_id, lib_path, chunk)
+                .await?
+                .len();
+        }
+    }
+
+    if dry_run {
+        if !files.is_empty() {
+            info!(
+                "Would bake the cover art{} of the files scanned",
+                if options.analyze.is_some() {
+                    " and analyze"
+                } else {
+                    ""
+                }
+            );
+        }
+        return Ok(());
+    }
+    if scanned == 0 {
+        return Ok(());
+    }
+    info!("Added or updated {scanned} files");
+
+    let baked = scan_cover_arts(
+        fsio.clone(),
+        main_db,
+        lib_path,
+        options.node_id,
+        10,
+        |_now, _total| {},
+        Some(cancel_token.clone()),
+    )
+    .await?;
+    info!("Baked the cover art of {baked} files");
+
+    if let Some(computing_device) = options.analyze {
+        let analyzed = analysis_audio_library(
+            fsio.clone(),
+            main_db,
+            lib_path,
+            options.node_id,
+            15,
+            computing_device,
+            false,
+            empty_progress_callback,
+            Some(cancel_token.clone()),
+        )
+        .await?;
+        sync_recommendation(main_db, analysis_db).await?;
+        info!("Analyzed {analyzed} files");
+    }
+
+    Ok(())
+}
+
+/// Stops the watcher on Ctrl-C, or when the service manager asks to.
+fn cancel_on_shutdown(cancel_token: CancellationToken) {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => {}
+                        _ = terminate.recv() => {}
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to listen for SIGTERM: {e}");
+                    let _ = tokio::signal::ctrl_c().await;
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+
+        info!("Stopping, finishing the files in flight");
+        cancel_token.cancel();
+    });
+}
+
+/// Keeps the library up to date until Ctrl-C or SIGTERM, scanning the
+/// files added or changed and dropping the files removed.
+pub async fn watch_library(
+    fsio: Arc<FsIo>,
+    main_db: &MainDbConnection,
+    analysis_db: &RecommendationDbConnection,
+    options: WatchOptions<'_>,
+) -> Result<()> {
+    let cancel_token = CancellationToken::new();
+    cancel_on_shutdown(cancel_token.clone());
+
+    let mut watcher = fsio.watch(options.lib_path, true)?;
+    info!(
+        "Watching {}{}",
+        options.lib_path.display(),
+        if options.dry_run { " (dry run)" } else { "" }
+    );
+
+    let mut pending = PendingChanges::default();
+    loop {
+        let event = if pending.is_empty() {
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                event = watcher.recv() => event,
+            }
+        } else {
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                event = timeout(options.settle, watcher.recv()) => match event {
+                    Ok(event) => event,
+                    Err(_) => {
+                        let changes = std::mem::take(&mut pending);
+                        info!("Handling {} changes", changes.len());
+                        if let Err(e) = apply_changes(
+                            &fsio,
+                            main_db,
+                            analysis_db,
+                            &options,
+                            changes,
+                            &cancel_token,
+                        )
+                        .await
+                        {
+                            error!("Failed to handle the changes: {e:#}");
+                        }
+                        continue;
+                    }
+                },
+            }
+        };
+
+        match event {
+            Some(event) if is_ignored(options.lib_path, &event) => {}
+            Some(event) => pending.push(event),
+            None => bail!("The file system stopped reporting changes"),
+        }
+    }
+
+    if !pending.is_empty() {
+        info!(
+            "{} changes were not handled, the next scan picks them up",
+            pending.len()
+        );
+    }
+    if !options.dry_run {
+        checkpoint_main_db(main_db).await?;
+    }
+    info!("Stopped watching {}", options.lib_path.display());
+
+    Ok(())
+}
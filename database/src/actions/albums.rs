@@ -1,13 +1,13 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use sea_orm::prelude::*;
+use sea_orm::{QueryOrder, prelude::*};
 
 use crate::actions::collection::CollectionQuery;
-use crate::collection_query;
 use crate::connection::MainDbConnection;
 use crate::entities::{albums, media_file_albums};
+use crate::{collection_query, get_by_id};
 
 use super::collection::CollectionQueryType;
 use super::utils::CollectionDefinition;
@@ -22,6 +22,8 @@ impl CollectionDefinition for albums::Entity {
     }
 }
 
+get_by_id!(get_album_by_id, albums);
+
 collection_query!(
     albums,
     CollectionQueryType::Album,
@@ -29,3 +31,42 @@ collection_query!(
     media_file_albums,
     AlbumId
 );
+
+/// Media files of each album, ordered by their track number.
+pub async fn get_media_file_ids_by_album_ids(
+    main_db: &MainDbConnection,
+    album_ids: &[i32],
+) -> Result<HashMap<i32, Vec<i32>>> {
+    let rows = media_file_albums::Entity::find()
+        .filter(media_file_albums::Column::AlbumId.is_in(album_ids.to_vec()))
+        .order_by_asc(media_file_albums::Column::TrackNumber)
+        .order_by_asc(media_file_albums::Column::MediaFileId)
+        .all(main_db)
+        .await?;
+
+    let mut result: HashMap<i32, Vec<i32>> = HashMap::new();
+    for row in rows {
+        result
+            .entry(row.album_id)
+            .or_default()
+            .push(row.media_file_id);
+    }
+
+    Ok(result)
+}
+
+/// The album each of the given media files belongs to.
+pub async fn get_album_ids_by_media_file_ids(
+    main_db: &MainDbConnection,
+    media_file_ids: &[i32],
+) -> Result<HashMap<i32, i32>> {
+    let rows = media_file_albums::Entity::find()
+        .filter(media_file_albums::Column::MediaFileId.is_in(media_file_ids.to_vec()))
+        .all(main_db)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|x| (x.media_file_id, x.album_id))
+        .collect())
+}
@@ -11,6 +11,7 @@ use paste::paste;
 use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::prelude::ToPrimitive;
 use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
 use sea_orm::{ActiveValue, QueryOrder, QuerySelect};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use seq_macro::seq;
@@ -20,7 +21,7 @@ use analysis::analysis::{NormalizedAnalysisResult, analyze_audio, normalize_anal
 use analysis::utils::computing_device::ComputingDevice;
 use uuid::Uuid;
 
-use crate::entities::{media_analysis, media_files};
+use crate::entities::{media_analysis, media_analysis_failure, media_files};
 use crate::parallel_media_files_processing;
 
 pub fn empty_progress_callback(_processed: usize, _total: usize) {}
@@ -34,6 +35,8 @@ pub fn empty_progress_callback(_processed: usize, _total: usize) {}
 /// * `main_db` - A reference to the database connection.
 /// * `lib_path` - The root path for the audio files.
 /// * `batch_size` - The number of files to process in each batch.
+/// * `retry_failed` - Whether to analyze again the files which failed before. Files failed
+///   before are retried anyway once their content changed.
 /// * `progress_callback` - A callback function to report progress.
 /// * `cancel_token` - An optional cancellation token to support task cancellation.
 ///
@@ -47,6 +50,7 @@ pub async fn analysis_audio_library<F>(
     node_id: &str,
     batch_size: usize,
     computing_device: ComputingDevice,
+    retry_failed: bool,
     progress_callback: F,
     cancel_token: Option<CancellationToken>,
 ) -> Result<usize>
@@ -57,7 +61,7 @@ where
 
     info!("Starting audio library analysis with batch size: {batch_size}");
 
-    let existed_ids: Vec<i32> = media_analysis::Entity::find()
+    let mut existed_ids: Vec<i32> = media_analysis::Entity::find()
         .select_only()
         .column(media_analysis::Column::FileId)
         .distinct()
@@ -65,6 +69,12 @@ where
         .all(main_db)
         .await?;
 
+    if !retry_failed {
+        let failed_ids = get_failed_file_ids(main_db).await?;
+        info!("Skipping {} files which failed before", failed_ids.len());
+        existed_ids.extend(failed_ids);
+    }
+
     let cursor_query =
         media_files::Entity::find().filter(media_files::Column::Id.is_not_in(existed_ids));
 
@@ -104,14 +114,75 @@ where
                             Ok(_) => debug!("Finished analysis: {}", file.id),
                             Err(e) => error!("Failed to insert analysis result: {e}"),
                         }
+
+                        if let Err(e) = media_analysis_failure::Entity::delete_many()
+                            .filter(media_analysis_failure::Column::MediaFileId.eq(file.id))
+                            .exec(db)
+                            .await
+                        {
+                            error!("Failed to clear the analysis failure: {e}");
+                        }
                     };
                 }
-                Err(e) => error!("Failed to analyze track: {e}"),
+                Err(e) => {
+                    error!("Failed to analyze track: {e}");
+
+                    if let Err(e) = record_analysis_failure(db, &file, &e).await {
+                        error!("Failed to record the analysis failure: {e}");
+                    }
+                }
             }
         }
     )
 }
 
+/// IDs of the files whose analysis failed, and which did not change since.
+async fn get_failed_file_ids(main_db: &DatabaseConnection) -> Result<Vec<i32>> {
+    let failures = media_analysis_failure::Entity::find()
+        .find_also_related(media_files::Entity)
+        .all(main_db)
+        .await?;
+
+    Ok(failures
+        .into_iter()
+        .filter_map(|(failure, file)| match file {
+            Some(file) if file.file_hash == failure.file_hash => Some(failure.media_file_id),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Remembers why the analysis of a file failed, so the next analysis can
+/// skip it.
+async fn record_analysis_failure(
+    main_db: &DatabaseConnection,
+    file: &media_files::Model,
+    error: &anyhow::Error,
+) -> Result<()> {
+    let failure = media_analysis_failure::ActiveModel {
+        media_file_id: ActiveValue::Set(file.id),
+        file_hash: ActiveValue::Set(file.file_hash.clone()),
+        error: ActiveValue::Set(format!("{error:#}")),
+        failed_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+        ..Default::default()
+    };
+
+    media_analysis_failure::Entity::insert(failure)
+        .on_conflict(
+            OnConflict::column(media_analysis_failure::Column::MediaFileId)
+                .update_columns([
+                    media_analysis_failure::Column::FileHash,
+                    media_analysis_failure::Column::Error,
+                    media_analysis_failure::Column::FailedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
 /// Process a file if it has not been analyzed yet. Perform audio analysis and store the results
 /// in the database.
 ///
@@ -177,6 +248,10 @@ async fn insert_analysis_result(
         spectral_kurtosis: ActiveValue::Set(Decimal::from_f32(result.spectral_kurtosis)),
         perceptual_spread: ActiveValue::Set(Decimal::from_f32(result.raw.perceptual_spread)),
         perceptual_sharpness: ActiveValue::Set(Decimal::from_f32(result.raw.perceptual_sharpness)),
+        integrated_loudness: ActiveValue::Set(
+            result.raw.integrated_loudness.and_then(Decimal::from_f32),
+        ),
+        peak: ActiveValue::Set(Decimal::from_f32(result.raw.peak)),
         hlc_uuid: ActiveValue::Set(
             Uuid::new_v5(
                 &Uuid::NAMESPACE_OID,
@@ -400,6 +475,35 @@ pub async fn get_analyze_count(main_db: &DatabaseConnection) -> Result<u64> {
     Ok(media_analysis::Entity::find().count(main_db).await?)
 }
 
+/// How far the analysis of the library is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalysisSummary {
+    pub analyzed: usize,
+    /// Files not analyzed yet, failed files excluded
+    pub pending: usize,
+    /// Files whose analysis failed, and which did not change since
+    pub failed: usize,
+}
+
+pub async fn get_analysis_summary(main_db: &DatabaseConnection) -> Result<AnalysisSummary> {
+    let total = media_files::Entity::find().count(main_db).await? as usize;
+    let analyzed = media_analysis::Entity::find()
+        .select_only()
+        .column(media_analysis::Column::FileId)
+        .distinct()
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await?
+        .len();
+    let failed = get_failed_file_ids(main_db).await?.len();
+
+    Ok(AnalysisSummary {
+        analyzed,
+        pending: total.saturating_sub(analyzed + failed),
+        failed,
+    })
+}
+
 /// Computes the centralized analysis result from the database.
 ///
 /// This function retrieves analysis results based on specified file IDs,
@@ -1,8 +1,8 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use sea_orm::prelude::*;
+use sea_orm::{QueryOrder, prelude::*};
 
 use crate::actions::collection::{CollectionQuery, CollectionQueryType};
 use crate::collection_query;
@@ -28,3 +28,47 @@ collection_query!(
     media_file_artists,
     ArtistId
 );
+
+/// Media files of each artist.
+pub async fn get_media_file_ids_by_artist_ids(
+    main_db: &MainDbConnection,
+    artist_ids: &[i32],
+) -> Result<HashMap<i32, Vec<i32>>> {
+    let rows = media_file_artists::Entity::find()
+        .filter(media_file_artists::Column::ArtistId.is_in(artist_ids.to_vec()))
+        .order_by_asc(media_file_artists::Column::MediaFileId)
+        .all(main_db)
+        .await?;
+
+    let mut result: HashMap<i32, Vec<i32>> = HashMap::new();
+    for row in rows {
+        result
+            .entry(row.artist_id)
+            .or_default()
+            .push(row.media_file_id);
+    }
+
+    Ok(result)
+}
+
+/// The artists of each of the given media files.
+pub async fn get_artist_ids_by_media_file_ids(
+    main_db: &MainDbConnection,
+    media_file_ids: &[i32],
+) -> Result<HashMap<i32, Vec<i32>>> {
+    let rows = media_file_artists::Entity::find()
+        .filter(media_file_artists::Column::MediaFileId.is_in(media_file_ids.to_vec()))
+        .order_by_asc(media_file_artists::Column::Id)
+        .all(main_db)
+        .await?;
+
+    let mut result: HashMap<i32, Vec<i32>> = HashMap::new();
+    for row in rows {
+        result
+            .entry(row.media_file_id)
+            .or_default()
+            .push(row.artist_id);
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,130 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder, QuerySelect};
+
+use crate::entities::audit_log;
+
+use super::utils::DatabaseExecutor;
+
+/// A remote request that should be recorded in the audit log.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub request_type: String,
+    pub fingerprint: String,
+    pub alias: String,
+    pub parameters: String,
+    pub success: bool,
+}
+
+/// Filters applied when listing audit log entries.
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogFilter {
+    pub fingerprint: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// Insert a new audit log entry.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `entry` - The request being recorded.
+///
+/// # Returns
+/// * `Result<Model>` - The inserted audit log model or an error.
+pub async fn insert_audit_log<E>(main_db: &E, entry: AuditEntry) -> Result<audit_log::Model>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let new_entry = audit_log::ActiveModel {
+        date: ActiveValue::Set(Utc::now()),
+        request_type: ActiveValue::Set(entry.request_type),
+        fingerprint: ActiveValue::Set(entry.fingerprint),
+        alias: ActiveValue::Set(entry.alias),
+        parameters: ActiveValue::Set(entry.parameters),
+        success: ActiveValue::Set(entry.success),
+        ..Default::default()
+    };
+
+    Ok(new_entry.insert(main_db).await?)
+}
+
+/// List audit log entries with filtering and pagination, newest first.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `filter` - Restricts the entries by device and time range.
+/// * `cursor` - The page to fetch (0-based index).
+/// * `page_size` - The number of entries to retrieve per page.
+///
+/// # Returns
+/// * `Result<Vec<audit_log::Model>>` - A vector of audit log models or an error.
+pub async fn list_audit_log(
+    main_db: &DatabaseConnection,
+    filter: AuditLogFilter,
+    cursor: u64,
+    page_size: u64,
+) -> Result<Vec<audit_log::Model>> {
+    let mut query = audit_log::Entity::find();
+
+    if let Some(fingerprint) = filter.fingerprint {
+        query = query.filter(audit_log::Column::Fingerprint.eq(fingerprint));
+    }
+    if let Some(start) = filter.start {
+        query = query.filter(audit_log::Column::Date.gte(start));
+    }
+    if let Some(end) = filter.end {
+        query = query.filter(audit_log::Column::Date.lte(end));
+    }
+
+    let paginator = query
+        .order_by_desc(audit_log::Column::Id)
+        .paginate(main_db, page_size);
+
+    Ok(paginator.fetch_page(cursor).await?)
+}
+
+/// Remove audit log entries that are older than `max_age` or exceed `max_rows`.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `max_age` - Entries older than this are removed.
+/// * `max_rows` - Only the newest `max_rows` entries are kept.
+///
+/// # Returns
+/// * `Result<u64>` - The number of removed entries or an error.
+pub async fn prune_audit_log(
+    main_db: &DatabaseConnection,
+    max_age: Option<Duration>,
+    max_rows: Option<u64>,
+) -> Result<u64> {
+    let mut removed = 0;
+
+    if let Some(max_age) = max_age {
+        let threshold = Utc::now() - max_age;
+        removed += audit_log::Entity::delete_many()
+            .filter(audit_log::Column::Date.lt(threshold))
+            .exec(main_db)
+            .await?
+            .rows_affected;
+    }
+
+    if let Some(max_rows) = max_rows {
+        let boundary = audit_log::Entity::find()
+            .order_by_desc(audit_log::Column::Id)
+            .offset(max_rows)
+            .one(main_db)
+            .await?;
+
+        if let Some(boundary) = boundary {
+            removed += audit_log::Entity::delete_many()
+                .filter(audit_log::Column::Id.lte(boundary.id))
+                .exec(main_db)
+                .await?
+                .rows_affected;
+        }
+    }
+
+    Ok(removed)
+}
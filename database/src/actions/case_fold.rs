@@ -0,0 +1,283 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use caseless::default_case_fold_str;
+use log::{debug, info, warn};
+use sea_orm::{ActiveValue, DatabaseConnection, TransactionTrait, prelude::*};
+
+use ::fsio::FsIo;
+use ::metadata::describe::FileDescription;
+
+use crate::actions::{
+    collection::CollectionQueryType,
+    library_settings::{get_library_setting, set_library_flag},
+    logging::{LogLevel, insert_log},
+    search::remove_term,
+    stats::{mark_updated, stats_hlc_uuid},
+};
+use crate::connection::acquire_write_permit;
+use crate::entities::{media_file_playlists, media_file_stats, media_files, playback_queue};
+
+/// Name of the file written to a library to find out how it treats case.
+const CASE_PROBE_FILE: &str = ".rune-case-probe";
+
+/// The library setting remembering whether the library ignores case.
+pub const CASE_INSENSITIVE_SETTING: &str = "scan.case_insensitive";
+
+/// Key of a file on a case-insensitive file system. Full Unicode case
+/// folding is used, so `Straße` and `STRASSE` share a key.
+pub fn fold_path_key(directory: &str, file_name: &str) -> (String, String) {
+    (
+        default_case_fold_str(directory),
+        default_case_fold_str(file_name),
+    )
+}
+
+/// Tells whether the file system of a library ignores case. The library is
+/// probed on its first scan and the answer is kept in its settings.
+/// Libraries that can't be written to fall back to the default file system
+/// of the platform, and are probed again on the next scan.
+pub async fn is_case_insensitive(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+) -> bool {
+    match get_library_setting(main_db, CASE_INSENSITIVE_SETTING).await {
+        Ok(Some(value)) => return value == "true",
+        Ok(None) => {}
+        Err(e) => warn!("Unable to read the case sensitivity of the library: {e}"),
+    }
+
+    let Some(insensitive) = probe_case_insensitive(fsio, lib_path).await else {
+        return cfg!(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "windows"
+        ));
+    };
+
+    if let Err(e) = set_library_flag(main_db, CASE_INSENSITIVE_SETTING, insensitive).await {
+        warn!("Unable to save the case sensitivity of the library: {e}");
+    }
+
+    insensitive
+}
+
+/// Writes a probe file and looks it up in upper case, `None` when the
+/// library can't be written to.
+async fn probe_case_insensitive(fsio: &FsIo, lib_path: &Path) -> Option<bool> {
+    let probe = lib_path.join(CASE_PROBE_FILE);
+    if let Err(e) = fsio.write(&probe, &[]).await {
+        debug!("Unable to probe the case sensitivity of the library: {e}");
+        return None;
+    }
+
+    let insensitive = fsio
+        .exists(&lib_path.join(CASE_PROBE_FILE.to_uppercase()))
+        .unwrap_or(false);
+
+    if let Err(e) = fsio.remove_file(&probe).await {
+        warn!("Unable to remove the case probe {}: {e}", probe.display());
+    }
+
+    Some(insensitive)
+}
+
+/// Library files keyed by their folded path, so scans of case-insensitive
+/// libraries recognize files whose case changed on disk.
+#[derive(Debug, Default)]
+pub struct CaseFoldedFiles {
+    files: HashMap<(String, String), media_files::Model>,
+}
+
+impl CaseFoldedFiles {
+    /// Loads every file of the library. Files which fold to the same path
+    /// are the same file on disk, they are merged into the oldest one.
+    pub async fn load(main_db: &DatabaseConnection) -> Result<Self> {
+        let mut groups: HashMap<(String, String), Vec<media_files::Model>> = HashMap::new();
+        for file in media_files::Entity::find().all(main_db).await? {
+            groups
+                .entry(fold_path_key(&file.directory, &file.file_name))
+                .or_default()
+                .push(file);
+        }
+
+        let mut files = HashMap::new();
+        for (key, mut group) in groups {
+            group.sort_by_key(|x| x.id);
+            let mut group = group.into_iter();
+            let kept = group.next().unwrap();
+
+            for duplicate in group {
+                let reason = format!(
+                    "{}/{} and {}/{} are the same file on a case-insensitive file system, merging them",
+                    kept.directory, kept.file_name, duplicate.directory, duplicate.file_name
+                );
+                merge_files(main_db, &kept, &duplicate, reason)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to merge {}/{} into {}/{}",
+                            duplicate.directory,
+                            duplicate.file_name,
+                            kept.directory,
+                            kept.file_name
+                        )
+                    })?;
+            }
+
+            files.insert(key, kept);
+        }
+
+        Ok(Self { files })
+    }
+
+    /// Updates the path of the files whose case changed on disk, so the rest
+    /// of the scan finds them in place instead of adding them again.
+    pub async fn apply_renames(
+        &mut self,
+        main_db: &DatabaseConnection,
+        descriptions: &[Option<FileDescription>],
+    ) -> Result<usize> {
+        let mut renamed = 0;
+
+        for description in descriptions.iter().flatten() {
+            let key = fold_path_key(&description.directory, &description.file_name);
+            let Some(file) = self.files.get(&key) else {
+                continue;
+            };
+            if file.directory == description.directory && file.file_name == description.file_name {
+                continue;
+            }
+
+            info!(
+                "Case of {}/{} changed to {}/{}",
+                file.directory, file.file_name, description.directory, description.file_name
+            );
+
+            let mut active_model: media_files::ActiveModel = file.clone().into();
+            active_model.directory = ActiveValue::Set(description.directory.clone());
+            active_model.file_name = ActiveValue::Set(description.file_name.clone());
+            let file = active_model.update(main_db).await?;
+
+            self.files.insert(key, file);
+            renamed += 1;
+        }
+
+        Ok(renamed)
+    }
+}
+
+/// Moves what users did with `duplicate` to `kept`, then removes it. Play
+/// counts add up, a like on either file is kept, playlists and the queue
+/// point to `kept` from now on. `reason` is logged.
+pub(crate) async fn merge_files(
+    main_db: &DatabaseConnection,
+    kept: &media_files::Model,
+    duplicate: &media_files::Model,
+    reason: String,
+) -> Result<()> {
+    warn!("{reason}");
+
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+
+    let duplicate_stats = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(duplicate.id))
+        .one(&txn)
+        .await?;
+
+    if let Some(duplicate_stats) = duplicate_stats {
+        let kept_stats = media_file_stats::Entity::find()
+            .filter(media_file_stats::Column::MediaFileId.eq(kept.id))
+            .one(&txn)
+            .await?;
+
+        match kept_stats {
+            Some(kept_stats) => {
+                let mut active_model: media_file_stats::ActiveModel = kept_stats.clone().into();
+                active_model.liked = ActiveValue::Set(kept_stats.liked || duplicate_stats.liked);
+                active_model.skipped =
+                    ActiveValue::Set(kept_stats.skipped + duplicate_stats.skipped);
+                active_model.played_through =
+                    ActiveValue::Set(kept_stats.played_through + duplicate_stats.played_through);
+                active_model.total_listened_seconds = ActiveValue::Set(
+                    kept_stats.total_listened_seconds + duplicate_stats.total_listened_seconds,
+                );
+                // Timestamps are RFC 3339, which sort as strings
+                active_model.last_skipped_at = ActiveValue::Set(
+                    kept_stats
+                        .last_skipped_at
+                        .clone()
+                        .max(duplicate_stats.last_skipped_at.clone()),
+                );
+                active_model.first_played_at = ActiveValue::Set(
+                    [
+                        kept_stats.first_played_at.clone(),
+                        duplicate_stats.first_played_at.clone(),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .min(),
+                );
+                mark_updated(&mut active_model);
+                active_model.update(&txn).await?;
+
+                media_file_stats::Entity::delete_by_id(duplicate_stats.id)
+                    .exec(&txn)
+                    .await?;
+            }
+            None => {
+                let mut active_model: media_file_stats::ActiveModel = duplicate_stats.into();
+                active_model.media_file_id = ActiveValue::Set(kept.id);
+                active_model.hlc_uuid = ActiveValue::Set(stats_hlc_uuid(&kept.file_hash));
+                mark_updated(&mut active_model);
+                active_model.update(&txn).await?;
+            }
+        }
+    }
+
+    media_file_playlists::Entity::update_many()
+        .col_expr(
+            media_file_playlists::Column::MediaFileId,
+            Expr::value(kept.id),
+        )
+        .filter(media_file_playlists::Column::MediaFileId.eq(duplicate.id))
+        .exec(&txn)
+        .await?;
+
+    playback_queue::Entity::update_many()
+        .col_expr(playback_queue::Column::MediaFileId, Expr::value(kept.id))
+        .filter(playback_queue::Column::MediaFileId.eq(duplicate.id))
+        .exec(&txn)
+        .await?;
+
+    // The kept file is in the library since the duplicate was first seen
+    if duplicate.first_seen_at < kept.first_seen_at {
+        media_files::Entity::update_many()
+            .col_expr(
+                media_files::Column::FirstSeenAt,
+                Expr::value(duplicate.first_seen_at.clone()),
+            )
+            .filter(media_files::Column::Id.eq(kept.id))
+            .exec(&txn)
+            .await?;
+    }
+
+    media_files::Entity::delete_by_id(duplicate.id)
+        .exec(&txn)
+        .await?;
+    remove_term(&txn, CollectionQueryType::Track, duplicate.id).await?;
+
+    insert_log(
+        &txn,
+        LogLevel::Warning,
+        "actions::case_fold::merge_files".to_string(),
+        reason,
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
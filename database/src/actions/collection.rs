@@ -107,6 +107,9 @@ pub enum CollectionQueryListMode {
     Reverse,
     Random,
     Name,
+    /// The most recently added to the library first, collections being
+    /// added with their most recently added file
+    RecentlyAdded,
 }
 
 #[derive(Debug, Clone, Error)]
@@ -126,6 +129,7 @@ impl FromStr for CollectionQueryListMode {
             "reverse" => Ok(CollectionQueryListMode::Reverse),
             "newest" => Ok(CollectionQueryListMode::Reverse),
             "random" => Ok(CollectionQueryListMode::Random),
+            "recently_added" => Ok(CollectionQueryListMode::RecentlyAdded),
             _ => Err(ParseCollectionQueryListModeError::InvalidType),
         }
     }
@@ -308,6 +312,38 @@ macro_rules! collection_query {
                             .all(main_db)
                             .await
                     }
+                    CollectionQueryListMode::RecentlyAdded => {
+                        use sea_orm::{
+                            ColumnTrait, JoinType, QueryFilter, RelationTrait, sea_query::Expr,
+                        };
+                        use $crate::entities::media_files;
+
+                        let ids: Vec<i32> = $related_entity::Entity::find()
+                            .select_only()
+                            .column(<$related_entity::Column>::$relation_column_name)
+                            .join(
+                                JoinType::InnerJoin,
+                                $related_entity::Relation::MediaFiles.def(),
+                            )
+                            .group_by(<$related_entity::Column>::$relation_column_name)
+                            .order_by_desc(
+                                Expr::col((media_files::Entity, media_files::Column::FirstSeenAt))
+                                    .max(),
+                            )
+                            .limit(limit)
+                            .into_tuple()
+                            .all(main_db)
+                            .await?;
+
+                        $item_entity::Entity::find()
+                            .filter(<$item_entity::Column>::Id.is_in(ids.clone()))
+                            .all(main_db)
+                            .await
+                            .map(|mut collections| {
+                                collections.sort_by_key(|x| ids.iter().position(|id| *id == x.id));
+                                collections
+                            })
+                    }
                     CollectionQueryListMode::Random => {
                         let mut query: sea_orm::sea_query::SelectStatement =
                             $item_entity::Entity::find().as_query().to_owned();
@@ -0,0 +1,274 @@
+//! Duplicates found by their content, matching the landmarks of each track
+//! against the others without asking any service.
+//!
+//! Landmarks take 8 bytes each, about 10 per second of audio, so around
+//! 20 KB for a 4 minute track and 2 GB on disk for 100k tracks. Matching
+//! them all against each other would be quadratic, so only an eighth of the
+//! distinct hashes of each track goes into an in-memory inverted index, a
+//! `(hash, file_id)` pair of 8 bytes each: about 2 KB a track, 200 MB for
+//! 100k tracks. Tracks sharing a few indexed hashes are the candidates whose
+//! full landmarks are then aligned, loaded from the database pair by pair.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use fsio::FsIo;
+use log::{debug, info};
+use sea_orm::{
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+};
+use sync::hlc::SyncTaskContext;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use tag_editor::shazam::landmark::{
+    Landmark, align_landmarks, decode_landmarks, encode_landmarks, file_landmarks, is_indexed_hash,
+};
+
+use crate::connection::acquire_write_permit;
+use crate::entities::{media_file_content_match, media_file_landmark, media_files};
+use crate::parallel_media_files_processing;
+
+/// One in `2^INDEX_SAMPLE_BITS` hashes goes into the inverted index.
+const INDEX_SAMPLE_BITS: u32 = 3;
+/// Hashes shared by more files say nothing about any of them, like the
+/// landmarks of silence or of a test tone.
+const MAX_FILES_PER_HASH: usize = 32;
+/// Indexed hashes two files must share to be aligned.
+const MIN_SHARED_HASHES: usize = 3;
+/// Landmark rows read at once while building the index.
+const INDEX_PAGE_SIZE: u64 = 500;
+
+/// Computes the landmarks of the files which don't have them yet.
+pub async fn compute_file_landmarks<F>(
+    fsio: Arc<FsIo>,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    node_id: &str,
+    batch_size: usize,
+    progress_callback: F,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize) + Send + Sync + 'static,
+{
+    let progress_callback = Arc::new(progress_callback);
+
+    info!("Starting landmark computation with batch size: {batch_size}");
+
+    let existed_ids: Vec<i32> = media_file_landmark::Entity::find()
+        .select_only()
+        .column(media_file_landmark::Column::MediaFileId)
+        .into_tuple::<i32>()
+        .all(main_db)
+        .await
+        .context("Failed to query existing landmarks")?;
+
+    let cursor_query =
+        media_files::Entity::find().filter(media_files::Column::Id.is_not_in(existed_ids));
+
+    let lib_path = Arc::new(lib_path.to_path_buf());
+    let node_id = Arc::new(node_id.to_owned());
+    // Landmarks are local to this library and not synchronized, the macro
+    // only needs the context
+    let node_uuid = Uuid::from_str(&node_id).unwrap_or_default();
+    let hlc_context = Arc::new(SyncTaskContext::new(node_uuid));
+
+    parallel_media_files_processing!(
+        main_db,
+        batch_size,
+        progress_callback,
+        cancel_token,
+        cursor_query,
+        lib_path,
+        fsio,
+        node_id,
+        hlc_context,
+        move |fsio, file, lib_path, cancel_token| {
+            compute_single_landmarks(fsio, lib_path, file, cancel_token)
+        },
+        |db,
+         file: media_files::Model,
+         _node_id: Arc<String>,
+         _hlc_context: Arc<SyncTaskContext>,
+         landmarks_result: Result<Vec<Landmark>>| async move {
+            match landmarks_result {
+                Ok(landmarks) => {
+                    let model = media_file_landmark::ActiveModel {
+                        media_file_id: ActiveValue::Set(file.id),
+                        landmarks: ActiveValue::Set(encode_landmarks(&landmarks)),
+                        ..Default::default()
+                    };
+
+                    match media_file_landmark::Entity::insert(model).exec(db).await {
+                        Ok(_) => debug!("Inserted landmarks for file: {}", file.id),
+                        Err(e) => error!("Failed to insert landmarks: {e}"),
+                    }
+                }
+                Err(e) => error!("Failed to compute landmarks: {e:#?}"),
+            }
+        }
+    )
+}
+
+fn compute_single_landmarks(
+    fsio: &FsIo,
+    lib_path: &Path,
+    file: &media_files::Model,
+    cancel_token: Option<CancellationToken>,
+) -> Result<Vec<Landmark>> {
+    let file_path = lib_path.join(&file.directory).join(&file.file_name);
+
+    info!("Computing landmarks for: {}", file.file_name);
+
+    file_landmarks(fsio, &file_path, cancel_token)
+        .with_context(|| format!("compute landmarks for: {}", file_path.display()))
+}
+
+pub async fn get_landmark_count(main_db: &DatabaseConnection) -> Result<u64> {
+    Ok(media_file_landmark::Entity::find().count(main_db).await?)
+}
+
+async fn load_landmarks(main_db: &DatabaseConnection, file_id: i32) -> Result<Vec<Landmark>> {
+    let model = media_file_landmark::Entity::find()
+        .filter(media_file_landmark::Column::MediaFileId.eq(file_id))
+        .one(main_db)
+        .await?
+        .with_context(|| format!("No landmarks for file {file_id}"))?;
+
+    decode_landmarks(&model.landmarks)
+}
+
+/// Builds the inverted index, sorted by hash.
+async fn build_index(main_db: &DatabaseConnection) -> Result<Vec<(u32, i32)>> {
+    let mut index = Vec::new();
+    let mut pages = media_file_landmark::Entity::find()
+        .order_by_asc(media_file_landmark::Column::Id)
+        .paginate(main_db, INDEX_PAGE_SIZE);
+
+    while let Some(models) = pages.fetch_and_next().await? {
+        for model in models {
+            let mut hashes: Vec<u32> = decode_landmarks(&model.landmarks)?
+                .into_iter()
+                .map(|x| x.hash)
+                .filter(|&x| is_indexed_hash(x, INDEX_SAMPLE_BITS))
+                .collect();
+            hashes.sort_unstable();
+            hashes.dedup();
+
+            index.extend(hashes.into_iter().map(|x| (x, model.media_file_id)));
+        }
+    }
+
+    index.sort_unstable();
+    Ok(index)
+}
+
+/// Pairs of files sharing enough indexed hashes, the lower ID first.
+fn candidate_pairs(index: &[(u32, i32)]) -> Vec<(i32, i32)> {
+    let mut shared: HashMap<(i32, i32), usize> = HashMap::new();
+    for postings in index.chunk_by(|a, b| a.0 == b.0) {
+        if postings.len() > MAX_FILES_PER_HASH {
+            continue;
+        }
+
+        for (i, (_, file_id1)) in postings.iter().enumerate() {
+            for (_, file_id2) in &postings[i + 1..] {
+                *shared.entry((*file_id1, *file_id2)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<(i32, i32)> = shared
+        .into_iter()
+        .filter(|(_, count)| *count >= MIN_SHARED_HASHES)
+        .map(|(pair, _)| pair)
+        .collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Matches the landmarks of every file against the others and replaces the
+/// stored content matches with the pairs found. Landmarks must have been
+/// computed by [`compute_file_landmarks`] first. Returns the number of
+/// matching pairs.
+pub async fn match_content<F>(
+    main_db: &DatabaseConnection,
+    progress_callback: F,
+    cancel_token: Option<CancellationToken>,
+) -> Result<usize>
+where
+    F: Fn(usize, usize),
+{
+    let index = build_index(main_db).await?;
+    info!("Built a landmark index of {} hashes", index.len());
+
+    let candidates = candidate_pairs(&index);
+    drop(index);
+    info!("Aligning {} candidate pairs", candidates.len());
+
+    let mut matches = Vec::new();
+    // Candidates are sorted, the landmarks of the first file are reused
+    let mut current: Option<(i32, Vec<Landmark>)> = None;
+    for (done, &(file_id1, file_id2)) in candidates.iter().enumerate() {
+        if let Some(token) = &cancel_token
+            && token.is_cancelled()
+        {
+            info!("Content matching cancelled, the previous matches are kept");
+            return Ok(0);
+        }
+
+        if current.as_ref().is_none_or(|(id, _)| *id != file_id1) {
+            current = Some((file_id1, load_landmarks(main_db, file_id1).await?));
+        }
+        let Some((_, landmarks1)) = &current else {
+            continue;
+        };
+        let landmarks2 = load_landmarks(main_db, file_id2).await?;
+
+        if let Some(alignment) = align_landmarks(landmarks1, &landmarks2) {
+            debug!(
+                "Files {file_id1} and {file_id2} match at {:.2}s with a density of {:.3}",
+                alignment.offset, alignment.density
+            );
+            matches.push(media_file_content_match::ActiveModel {
+                file_id1: ActiveValue::Set(file_id1),
+                file_id2: ActiveValue::Set(file_id2),
+                offset: ActiveValue::Set(alignment.offset as f32),
+                density: ActiveValue::Set(alignment.density as f32),
+                ..Default::default()
+            });
+        }
+
+        progress_callback(done + 1, candidates.len());
+    }
+
+    let count = matches.len();
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+    media_file_content_match::Entity::delete_many()
+        .exec(&txn)
+        .await?;
+    for chunk in matches.chunks(500) {
+        media_file_content_match::Entity::insert_many(chunk.to_vec())
+            .exec(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+
+    info!("Found {count} pairs of files with matching content");
+    Ok(count)
+}
+
+pub async fn get_content_matches(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<media_file_content_match::Model>> {
+    media_file_content_match::Entity::find()
+        .all(main_db)
+        .await
+        .context("Failed to retrieve content matches")
+}
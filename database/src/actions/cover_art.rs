@@ -1,5 +1,6 @@
 use std::{
-    collections::HashMap,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
     env, fs,
     path::{Path, PathBuf},
     str::FromStr,
@@ -12,19 +13,25 @@ use log::{error, info};
 use once_cell::sync::Lazy;
 use sea_orm::{
     ActiveValue, ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait,
-    QueryFilter,
+    QueryFilter, QueryOrder, sea_query::Expr,
 };
 use tokio_util::sync::CancellationToken;
 
 use ::fsio::FsIo;
-use ::metadata::cover_art::{CoverArt, extract_cover_art_binary, get_primary_color};
+use ::metadata::cover_art::{
+    CoverArt, EmbedOptions, extract_cover_art_binary, get_primary_color, prepare_embedded_image,
+};
+use ::metadata::writer::{TagWriteOutcome, write_cover_art};
 use uuid::Uuid;
 
 use crate::{
-    entities::{media_cover_art, media_files},
+    entities::{albums, media_cover_art, media_file_albums, media_files},
     parallel_media_files_processing,
 };
 
+use super::albums::get_media_file_ids_by_album_ids;
+use super::file::get_files_by_ids;
+use super::metadata::set_file_last_modified;
 use super::utils::DatabaseExecutor;
 
 pub async fn get_magic_cover_art(
@@ -106,6 +113,7 @@ fn bake_cover_art_by_cover_arts(
         let path: PathBuf = COVER_TEMP_DIR.clone().join(hash);
 
         if !path.exists() {
+            fsio.ensure_space(&path, cover_art.binary.len() as u64)?;
             fs::write(path.clone(), cover_art.binary.clone())?;
         }
 
@@ -206,6 +214,46 @@ pub fn extract_cover_art_by_file_id(
     extract_cover_art_binary(fsio, Some(lib_path), &file_path)
 }
 
+/// Stores a cover art, or finds the one already stored with the same CRC,
+/// and returns its ID.
+pub async fn upsert_cover_art(
+    main_db: &DatabaseConnection,
+    cover_art: &CoverArt,
+    node_id: &str,
+) -> Result<i32> {
+    // Check if there is a file with the same CRC in the database
+    let existing_cover_art = media_cover_art::Entity::find()
+        .filter(media_cover_art::Column::FileHash.eq(cover_art.crc.clone()))
+        .one(main_db)
+        .await?;
+
+    if let Some(existing_cover_art) = existing_cover_art {
+        return Ok(existing_cover_art.id);
+    }
+
+    let new_cover_art = media_cover_art::ActiveModel {
+        id: ActiveValue::NotSet,
+        file_hash: ActiveValue::Set(cover_art.crc.clone()),
+        binary: ActiveValue::Set(cover_art.data.clone()),
+        primary_color: ActiveValue::Set(Some(cover_art.primary_color)),
+        hlc_uuid: ActiveValue::Set(
+            Uuid::new_v5(&Uuid::NAMESPACE_OID, cover_art.crc.as_bytes()).to_string(),
+        ),
+        created_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
+        updated_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
+        created_at_hlc_ver: ActiveValue::Set(0),
+        updated_at_hlc_ver: ActiveValue::Set(0),
+        created_at_hlc_nid: ActiveValue::Set(node_id.to_owned()),
+        updated_at_hlc_nid: ActiveValue::Set(node_id.to_owned()),
+    };
+
+    let insert_result = media_cover_art::Entity::insert(new_cover_art)
+        .exec(main_db)
+        .await?;
+
+    Ok(insert_result.last_insert_id)
+}
+
 pub async fn insert_extract_result(
     main_db: &DatabaseConnection,
     file: &media_files::Model,
@@ -222,52 +270,15 @@ pub async fn insert_extract_result(
     // The timestamp comparison in our query ensures we only process files that need updating
 
     if let Some(cover_art) = result {
-        // Check if there is a file with the same CRC in the database
-        let existing_cover_art = media_cover_art::Entity::find()
-            .filter(media_cover_art::Column::FileHash.eq(cover_art.crc.clone()))
-            .one(main_db)
-            .await?;
+        let cover_art_id = upsert_cover_art(main_db, &cover_art, node_id).await?;
 
-        if let Some(existing_cover_art) = existing_cover_art {
-            // If there is a file with the same CRC, update the file's cover_art_id
-            let mut file_active_model: media_files::ActiveModel = file.into();
-            file_active_model.cover_art_id = ActiveValue::Set(Some(existing_cover_art.id));
-            media_files::Entity::update(file_active_model)
-                .exec(main_db)
-                .await?;
-
-            Ok(())
-        } else {
-            // If there is no file with the same CRC, store the cover art in the database and update the file's cover_art_id
-            let new_cover_art = media_cover_art::ActiveModel {
-                id: ActiveValue::NotSet,
-                file_hash: ActiveValue::Set(cover_art.crc.clone()),
-                binary: ActiveValue::Set(cover_art.data.clone()),
-                primary_color: ActiveValue::Set(Some(cover_art.primary_color)),
-                hlc_uuid: ActiveValue::Set(
-                    Uuid::new_v5(&Uuid::NAMESPACE_OID, cover_art.crc.as_bytes()).to_string(),
-                ),
-                created_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
-                updated_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
-                created_at_hlc_ver: ActiveValue::Set(0),
-                updated_at_hlc_ver: ActiveValue::Set(0),
-                created_at_hlc_nid: ActiveValue::Set(node_id.to_owned()),
-                updated_at_hlc_nid: ActiveValue::Set(node_id.to_owned()),
-            };
-
-            let insert_result = media_cover_art::Entity::insert(new_cover_art)
-                .exec(main_db)
-                .await?;
-            let new_cover_art_id = insert_result.last_insert_id;
-
-            let mut file_active_model: media_files::ActiveModel = file.into();
-            file_active_model.cover_art_id = ActiveValue::Set(Some(new_cover_art_id));
-            media_files::Entity::update(file_active_model)
-                .exec(main_db)
-                .await?;
+        let mut file_active_model: media_files::ActiveModel = file.into();
+        file_active_model.cover_art_id = ActiveValue::Set(Some(cover_art_id));
+        media_files::Entity::update(file_active_model)
+            .exec(main_db)
+            .await?;
 
-            Ok(())
-        }
+        Ok(())
     } else {
         // update the file's cover_art_id to magic cover art (no cover art found)
         let mut file_active_model: media_files::ActiveModel = file.into();
@@ -572,3 +583,200 @@ pub async fn get_primary_color_by_cover_art_id(
         None => Err(anyhow::anyhow!("No primary color found")),
     }
 }
+
+async fn get_album_file_ids(main_db: &DatabaseConnection, album_id: i32) -> Result<Vec<i32>> {
+    Ok(get_media_file_ids_by_album_ids(main_db, &[album_id])
+        .await?
+        .remove(&album_id)
+        .unwrap_or_default())
+}
+
+/// The cover art of an album, the one most of its tracks share.
+pub async fn get_album_cover_art(
+    main_db: &DatabaseConnection,
+    album_id: i32,
+) -> Result<Option<media_cover_art::Model>> {
+    let magic_cover_art_id = get_magic_cover_art_id(main_db).await;
+    let files = media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(get_album_file_ids(main_db, album_id).await?))
+        .all(main_db)
+        .await?;
+
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for cover_art_id in files.iter().filter_map(|x| x.cover_art_id) {
+        if Some(cover_art_id) != magic_cover_art_id {
+            *counts.entry(cover_art_id).or_default() += 1;
+        }
+    }
+    let Some((cover_art_id, _)) = counts
+        .into_iter()
+        .max_by_key(|(id, count)| (*count, Reverse(*id)))
+    else {
+        return Ok(None);
+    };
+
+    Ok(media_cover_art::Entity::find_by_id(cover_art_id)
+        .one(main_db)
+        .await?)
+}
+
+/// Uses a cover art for every track of an album, returning the tracks.
+pub async fn set_album_cover_art(
+    main_db: &DatabaseConnection,
+    album_id: i32,
+    cover_art: &CoverArt,
+    node_id: &str,
+) -> Result<Vec<media_files::Model>> {
+    let file_ids = get_album_file_ids(main_db, album_id).await?;
+    if file_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let cover_art_id = upsert_cover_art(main_db, cover_art, node_id).await?;
+    media_files::Entity::update_many()
+        .col_expr(media_files::Column::CoverArtId, Expr::value(cover_art_id))
+        .filter(media_files::Column::Id.is_in(file_ids.clone()))
+        .exec(main_db)
+        .await?;
+
+    Ok(media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(file_ids))
+        .all(main_db)
+        .await?)
+}
+
+/// How embedding the cover arts of albums into their tracks went.
+#[derive(Debug, Default)]
+pub struct EmbedReport {
+    pub embedded: usize,
+    /// Files already holding the cover art
+    pub unchanged: usize,
+    /// Albums without a cover art to embed
+    pub without_cover_art: Vec<i32>,
+    /// Files of formats pictures can't be embedded into, with their format
+    pub unsupported: Vec<(i32, String)>,
+    /// Files the cover art could not be embedded into, with the error
+    pub failed: Vec<(i32, String)>,
+}
+
+/// Embeds the cover art of each album into the tags of its tracks, shrunk
+/// as `options` asks, recording the new modification time of each written
+/// file so the next scan doesn't take the write for an external change.
+/// `progress` is called with the files done and the total after each file.
+pub async fn embed_album_cover_art<F>(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    album_ids: &[i32],
+    options: &EmbedOptions,
+    progress: F,
+) -> Result<EmbedReport>
+where
+    F: Fn(usize, usize),
+{
+    let file_ids_by_album = get_media_file_ids_by_album_ids(main_db, album_ids).await?;
+    let total: usize = file_ids_by_album.values().map(Vec::len).sum();
+
+    let mut report = EmbedReport::default();
+    let mut done = 0;
+    for album_id in album_ids {
+        let Some(file_ids) = file_ids_by_album.get(album_id) else {
+            continue;
+        };
+        let files = get_files_by_ids(main_db, file_ids).await?;
+
+        let data = match get_album_cover_art(main_db, *album_id).await? {
+            Some(cover_art) => prepare_embedded_image(&cover_art.binary, options)
+                .with_context(|| format!("Failed to prepare the cover art of album {album_id}")),
+            None => {
+                report.without_cover_art.push(*album_id);
+                done += files.len();
+                progress(done, total);
+                continue;
+            }
+        };
+        let data = match data {
+            Ok(x) => x,
+            Err(e) => {
+                let error = format!("{e:#}");
+                report
+                    .failed
+                    .extend(files.iter().map(|file| (file.id, error.clone())));
+                done += files.len();
+                progress(done, total);
+                continue;
+            }
+        };
+
+        for file in &files {
+            let path = lib_path.join(&file.directory).join(&file.file_name);
+            let result = match write_cover_art(fsio, &path, &data).await {
+                Ok(TagWriteOutcome::Written { last_modified }) => {
+                    let result = set_file_last_modified(main_db, file.id, last_modified).await;
+                    if result.is_ok() {
+                        report.embedded += 1;
+                    }
+                    result
+                }
+                Ok(TagWriteOutcome::Unchanged) => {
+                    report.unchanged += 1;
+                    Ok(())
+                }
+                Ok(TagWriteOutcome::NotSupported { format }) => {
+                    report.unsupported.push((file.id, format));
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!(
+                    "Failed to embed the cover art into {}: {e:#}",
+                    path.display()
+                );
+                report.failed.push((file.id, format!("{e:#}")));
+            }
+
+            done += 1;
+            progress(done, total);
+        }
+    }
+
+    Ok(report)
+}
+
+/// The albums none of whose tracks has a cover art, tracks not scanned for
+/// cover arts yet included.
+pub async fn get_albums_without_cover_art(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<albums::Model>> {
+    let magic_cover_art_id = get_magic_cover_art_id(main_db).await;
+
+    let mut condition = Condition::all().add(media_files::Column::CoverArtId.is_not_null());
+    if let Some(id) = magic_cover_art_id {
+        condition = condition.add(media_files::Column::CoverArtId.ne(id));
+    }
+    let covered_file_ids: Vec<i32> = media_files::Entity::find()
+        .filter(condition)
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+
+    let mut covered_album_ids = HashSet::new();
+    for chunk in covered_file_ids.chunks(500) {
+        let links = media_file_albums::Entity::find()
+            .filter(media_file_albums::Column::MediaFileId.is_in(chunk.to_vec()))
+            .all(main_db)
+            .await?;
+        covered_album_ids.extend(links.into_iter().map(|x| x.album_id));
+    }
+
+    Ok(albums::Entity::find()
+        .order_by_asc(albums::Column::Id)
+        .all(main_db)
+        .await?
+        .into_iter()
+        .filter(|x| !covered_album_ids.contains(&x.id))
+        .collect())
+}
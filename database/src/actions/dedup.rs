@@ -0,0 +1,323 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow};
+use log::{info, warn};
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{DatabaseConnection, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use ::fsio::FsIo;
+
+use crate::actions::{
+    case_fold::merge_files, content_match::get_content_matches, file::get_files_by_ids,
+    fingerprint::group_file_pairs,
+};
+use crate::entities::{media_file_similarity, media_files};
+
+/// Extensions of lossless formats, preferred by [`KeepPreference::Lossless`].
+const LOSSLESS_EXTENSIONS: [&str; 6] = ["flac", "wav", "aiff", "aif", "ape", "wv"];
+
+/// Which file of a group of duplicates is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeepPreference {
+    /// The highest sample rate, like `mark_duplicate_files`
+    SampleRate,
+    /// A lossless file, then the highest sample rate
+    Lossless,
+    /// The highest bitrate, estimated from the size and duration
+    Bitrate,
+    /// The file added to the library first
+    Oldest,
+}
+
+impl FromStr for KeepPreference {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sample-rate" => Ok(KeepPreference::SampleRate),
+            "lossless" => Ok(KeepPreference::Lossless),
+            "bitrate" => Ok(KeepPreference::Bitrate),
+            "oldest" => Ok(KeepPreference::Oldest),
+            _ => Err(anyhow!(
+                "Invalid preference {s}, expected sample-rate, lossless, bitrate or oldest"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KeepPreference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            KeepPreference::SampleRate => "sample-rate",
+            KeepPreference::Lossless => "lossless",
+            KeepPreference::Bitrate => "bitrate",
+            KeepPreference::Oldest => "oldest",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A file of a group of duplicates, as it was when the plan was made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateFile {
+    pub file_id: i32,
+    /// Path relative to the library root
+    pub path: PathBuf,
+    pub extension: String,
+    pub sample_rate: i32,
+    /// `None` when the file can't be read or has no duration
+    pub bitrate_kbps: Option<u32>,
+}
+
+/// Why files were grouped as duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateLabel {
+    /// Their fingerprints are at least as similar as the threshold
+    Similarity,
+    /// Their landmarks align, see `content_match`
+    ContentMatch,
+}
+
+/// Two files of a group carrying the same recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentMatch {
+    pub file_id1: i32,
+    pub file_id2: i32,
+    /// How much later the shared part starts in the first file, in seconds
+    pub offset: f32,
+    /// The share of the landmarks of the shorter file which align
+    pub density: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub keep: i32,
+    pub files: Vec<DuplicateFile>,
+    #[serde(default)]
+    pub labels: Vec<DuplicateLabel>,
+    #[serde(default)]
+    pub content_matches: Vec<ContentMatch>,
+}
+
+/// Groups of duplicates with the file kept in each, which can be saved and
+/// applied later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupePlan {
+    pub threshold: f32,
+    pub prefer: KeepPreference,
+    pub groups: Vec<DuplicateGroup>,
+}
+
+impl DedupePlan {
+    /// Number of files the plan removes.
+    pub fn removal_count(&self) -> usize {
+        self.groups.iter().map(|group| group.files.len() - 1).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalMode {
+    /// Move the files to the recycle bin through [`FsIo`]
+    Trash,
+    Delete,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DedupeOutcome {
+    pub removed: usize,
+    /// Files which changed since the plan was made
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+fn estimate_bitrate(fsio: &FsIo, lib_path: &Path, file: &media_files::Model) -> Option<u32> {
+    let path = lib_path.join(&file.directory).join(&file.file_name);
+    let size = fsio.metadata(&path).ok()?.size;
+    let duration = file.duration.to_f64().filter(|x| *x > 0.0)?;
+
+    Some((size as f64 * 8.0 / duration / 1000.0).round() as u32)
+}
+
+fn pick_kept(files: &[DuplicateFile], prefer: KeepPreference) -> Option<i32> {
+    let is_lossless = |file: &DuplicateFile| {
+        LOSSLESS_EXTENSIONS.contains(&file.extension.to_lowercase().as_str())
+    };
+
+    // Ties go to the file added first
+    let kept = match prefer {
+        KeepPreference::SampleRate => files
+            .iter()
+            .max_by_key(|file| (file.sample_rate, -file.file_id)),
+        KeepPreference::Lossless => files
+            .iter()
+            .max_by_key(|file| (is_lossless(file), file.sample_rate, -file.file_id)),
+        KeepPreference::Bitrate => files
+            .iter()
+            .max_by_key(|file| (file.bitrate_kbps, -file.file_id)),
+        KeepPreference::Oldest => files.iter().min_by_key(|file| file.file_id),
+    };
+
+    kept.map(|file| file.file_id)
+}
+
+/// Groups the files whose fingerprints are at least `threshold` similar, or
+/// whose content matches, and picks the file kept in each group.
+/// Similarities must have been computed by `compare_all_pairs` first, and
+/// content matches by `match_content`.
+pub async fn plan_dedupe(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    threshold: f32,
+    prefer: KeepPreference,
+) -> Result<DedupePlan> {
+    let similarities = media_file_similarity::Entity::find()
+        .filter(media_file_similarity::Column::Similarity.gte(threshold))
+        .all(main_db)
+        .await
+        .context("Failed to retrieve file similarities")?;
+    let content_matches: Vec<ContentMatch> = get_content_matches(main_db)
+        .await?
+        .into_iter()
+        .map(|x| ContentMatch {
+            file_id1: x.file_id1,
+            file_id2: x.file_id2,
+            offset: x.offset,
+            density: x.density,
+        })
+        .collect();
+
+    let similar_pairs: Vec<(i32, i32)> = similarities
+        .iter()
+        .map(|x| (x.file_id1, x.file_id2))
+        .collect();
+    let pairs: Vec<(i32, i32)> = similar_pairs
+        .iter()
+        .copied()
+        .chain(content_matches.iter().map(|x| (x.file_id1, x.file_id2)))
+        .collect();
+
+    let mut groups = Vec::new();
+    for group in group_file_pairs(&pairs) {
+        let mut files: Vec<DuplicateFile> = get_files_by_ids(main_db, &group)
+            .await?
+            .iter()
+            .map(|file| DuplicateFile {
+                file_id: file.id,
+                path: Path::new(&file.directory).join(&file.file_name),
+                extension: file.extension.clone(),
+                sample_rate: file.sample_rate,
+                bitrate_kbps: estimate_bitrate(fsio, lib_path, file),
+            })
+            .collect();
+        files.sort_by_key(|file| file.file_id);
+
+        if files.len() < 2 {
+            continue;
+        }
+        let Some(keep) = pick_kept(&files, prefer) else {
+            continue;
+        };
+
+        let mut labels = Vec::new();
+        if similar_pairs.iter().any(|(x, _)| group.contains(x)) {
+            labels.push(DuplicateLabel::Similarity);
+        }
+        let group_matches: Vec<ContentMatch> = content_matches
+            .iter()
+            .filter(|x| group.contains(&x.file_id1))
+            .cloned()
+            .collect();
+        if !group_matches.is_empty() {
+            labels.push(DuplicateLabel::ContentMatch);
+        }
+
+        groups.push(DuplicateGroup {
+            keep,
+            files,
+            labels,
+            content_matches: group_matches,
+        });
+    }
+    groups.sort_by_key(|group| group.keep);
+
+    Ok(DedupePlan {
+        threshold,
+        prefer,
+        groups,
+    })
+}
+
+/// Removes the duplicates of a plan. Stats, playlists and the queue of each
+/// removed file move to the kept one. Files whose path changed since the
+/// plan was made are skipped.
+pub async fn apply_dedupe_plan(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    plan: &DedupePlan,
+    mode: RemovalMode,
+) -> Result<DedupeOutcome> {
+    let mut outcome = DedupeOutcome::default();
+
+    for group in &plan.groups {
+        let file_ids: Vec<i32> = group.files.iter().map(|file| file.file_id).collect();
+        let current = get_files_by_ids(main_db, &file_ids).await?;
+        let find_current = |file: &DuplicateFile| {
+            current.iter().find(|x| {
+                x.id == file.file_id && Path::new(&x.directory).join(&x.file_name) == file.path
+            })
+        };
+
+        let Some(kept) = group
+            .files
+            .iter()
+            .find(|file| file.file_id == group.keep)
+            .and_then(find_current)
+        else {
+            warn!("Kept file {} changed, skipping its group", group.keep);
+            outcome.skipped += group.files.len() - 1;
+            continue;
+        };
+
+        for file in group.files.iter().filter(|file| file.file_id != group.keep) {
+            let Some(duplicate) = find_current(file) else {
+                warn!("{} changed since the plan was made", file.path.display());
+                outcome.skipped += 1;
+                continue;
+            };
+
+            let path = lib_path.join(&file.path);
+            let removed = match mode {
+                RemovalMode::Trash => fsio.trash(&path).await,
+                RemovalMode::Delete => fsio.remove_file(&path).await,
+            };
+            if let Err(e) = removed {
+                warn!("Failed to remove {}: {e}", path.display());
+                outcome.failed += 1;
+                continue;
+            }
+
+            let reason = format!(
+                "Removed duplicate {} of {}/{}",
+                file.path.display(),
+                kept.directory,
+                kept.file_name
+            );
+            merge_files(main_db, kept, duplicate, reason).await?;
+            outcome.removed += 1;
+        }
+    }
+
+    info!(
+        "Removed {} duplicates, skipped {}, failed {}",
+        outcome.removed, outcome.skipped, outcome.failed
+    );
+
+    Ok(outcome)
+}
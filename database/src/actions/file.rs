@@ -6,12 +6,13 @@ use metadata::describe::FileDescription;
 use rust_decimal::prelude::ToPrimitive;
 use sea_orm::entity::prelude::*;
 use sea_orm::{
-    ColumnTrait, EntityTrait, FromQueryResult, Order, QueryFilter, QueryOrder, QuerySelect,
-    QueryTrait,
+    ActiveValue, ColumnTrait, EntityTrait, FromQueryResult, Order, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait, TransactionTrait,
 };
 
 use migration::{Func, SimpleExpr};
 
+use crate::connection::acquire_write_permit;
 use crate::entities::media_files;
 use crate::{get_by_id, get_by_ids, get_first_n};
 
@@ -146,6 +147,21 @@ pub async fn get_reverse_listed_media_files(
         .await
 }
 
+/// Media files, the most recently added to the library first.
+pub async fn get_recently_added_media_files(
+    main_db: &DatabaseConnection,
+    cursor: usize,
+    page_size: usize,
+) -> Result<Vec<media_files::Model>, sea_orm::DbErr> {
+    media_files::Entity::find()
+        .order_by(media_files::Column::FirstSeenAt, Order::Desc)
+        .order_by(media_files::Column::Id, Order::Desc)
+        .offset(cursor as u64)
+        .limit(page_size as u64)
+        .all(main_db)
+        .await
+}
+
 pub async fn get_file_ids_by_descriptions(
     db: &DatabaseConnection,
     descriptions: &[Option<FileDescription>],
@@ -200,3 +216,92 @@ pub async fn get_duration_by_file_id(
 pub async fn get_media_files_count(db: &DatabaseConnection) -> Result<u64, sea_orm::DbErr> {
     media_files::Entity::find().count(db).await
 }
+
+/// Maps HLC UUIDs of media files to their ids in this library, keeping the
+/// order of the input. Files that don't exist in the library are mapped to 0.
+pub async fn get_file_ids_by_hlc_uuids(
+    db: &DatabaseConnection,
+    hlc_uuids: &[String],
+) -> Result<Vec<i32>, DbErr> {
+    if hlc_uuids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let files = media_files::Entity::find()
+        .filter(media_files::Column::HlcUuid.is_in(hlc_uuids.iter().cloned()))
+        .all(db)
+        .await?;
+
+    let id_map: HashMap<String, i32> = files.into_iter().map(|x| (x.hlc_uuid, x.id)).collect();
+
+    Ok(hlc_uuids
+        .iter()
+        .map(|x| id_map.get(x).copied().unwrap_or(0))
+        .collect())
+}
+
+/// Splits a path relative to the library root, with `/` separators, into
+/// the directory and the file name stored in `media_files`.
+fn split_relative_path(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+/// Files at `path`, or below it when it is a directory. `path` is relative
+/// to the library root, with `/` separators.
+pub async fn get_files_at_or_below(
+    db: &DatabaseConnection,
+    path: &str,
+) -> Result<Vec<media_files::Model>> {
+    let (directory, file_name) = split_relative_path(path);
+    let prefix = format!("{path}/");
+
+    let files = media_files::Entity::find()
+        .filter(
+            sea_orm::Condition::any()
+                .add(
+                    media_files::Column::Directory
+                        .eq(directory)
+                        .and(media_files::Column::FileName.eq(file_name)),
+                )
+                .add(media_files::Column::Directory.eq(path))
+                .add(media_files::Column::Directory.starts_with(&prefix)),
+        )
+        .all(db)
+        .await?;
+
+    // `starts_with` treats `_` and `%` in the path as wildcards
+    Ok(files
+        .into_iter()
+        .filter(|x| {
+            (x.directory == directory && x.file_name == file_name)
+                || x.directory == path
+                || x.directory.starts_with(&prefix)
+        })
+        .collect())
+}
+
+/// Updates the paths of the files moved from `from` to `to`, a file or a
+/// directory relative to the library root. Returns the number of files
+/// moved.
+pub async fn move_media_files(db: &DatabaseConnection, from: &str, to: &str) -> Result<usize> {
+    let files = get_files_at_or_below(db, from).await?;
+    let (from_directory, from_file_name) = split_relative_path(from);
+    let (to_directory, to_file_name) = split_relative_path(to);
+
+    let _permit = acquire_write_permit().await;
+    let txn = db.begin().await?;
+    for file in &files {
+        let mut active_model: media_files::ActiveModel = file.clone().into();
+        if file.directory == from_directory && file.file_name == from_file_name {
+            active_model.directory = ActiveValue::Set(to_directory.to_owned());
+            active_model.file_name = ActiveValue::Set(to_file_name.to_owned());
+        } else {
+            active_model.directory =
+                ActiveValue::Set(format!("{to}{}", &file.directory[from.len()..]));
+        }
+        active_model.update(&txn).await?;
+    }
+    txn.commit().await?;
+
+    Ok(files.len())
+}
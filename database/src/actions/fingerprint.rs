@@ -484,19 +484,23 @@ where
     Ok(marked_count)
 }
 
-fn group_similar_files(similarities: &[media_file_similarity::Model]) -> Vec<Vec<i32>> {
+pub(crate) fn group_similar_files(similarities: &[media_file_similarity::Model]) -> Vec<Vec<i32>> {
+    let pairs: Vec<(i32, i32)> = similarities
+        .iter()
+        .map(|similarity| (similarity.file_id1, similarity.file_id2))
+        .collect();
+
+    group_file_pairs(&pairs)
+}
+
+/// Groups files linked by pairs, directly or through other files.
+pub(crate) fn group_file_pairs(pairs: &[(i32, i32)]) -> Vec<Vec<i32>> {
     let mut adjacency_list: HashMap<i32, Vec<i32>> = HashMap::new();
 
     // Build an adjacency list for our similarity graph
-    for similarity in similarities {
-        adjacency_list
-            .entry(similarity.file_id1)
-            .or_default()
-            .push(similarity.file_id2);
-        adjacency_list
-            .entry(similarity.file_id2)
-            .or_default()
-            .push(similarity.file_id1);
+    for &(file_id1, file_id2) in pairs {
+        adjacency_list.entry(file_id1).or_default().push(file_id2);
+        adjacency_list.entry(file_id2).or_default().push(file_id1);
     }
 
     // Use a set to track visited nodes during our search
@@ -11,6 +11,7 @@ use tokio_util::sync::CancellationToken;
 use crate::actions::collection::CollectionQueryType;
 use crate::actions::search::{add_term, remove_term};
 use crate::actions::utils::generate_group_name;
+use crate::connection::acquire_write_permit;
 use crate::entities::{
     albums, artists, genres, media_file_albums, media_file_artists, media_file_genres, media_files,
 };
@@ -54,6 +55,7 @@ pub async fn index_media_files(
 
     for summary in metadata_summaries {
         // Start a new transaction for each file to ensure individual processing atomicity.
+        let _permit = acquire_write_permit().await;
         let txn = match main_db.begin().await {
             Ok(txn) => txn,
             Err(e) => {
@@ -609,6 +611,7 @@ pub async fn cleanup_orphaned_records(db: &DatabaseConnection) -> Result<()> {
     info!("Starting cleanup of orphaned artists, albums, and genres");
 
     // Start a transaction to ensure atomicity of the cleanup process.
+    let _permit = acquire_write_permit().await;
     let txn = db.begin().await?;
 
     // 1. Query all artist IDs that are linked to media files through media_file_artists table.
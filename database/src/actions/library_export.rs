@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::entities::{
+    media_file_playlists, media_file_stats, media_files, mix_queries, mixes, playlists,
+};
+
+/// Version of the export layout, raised when a field changes meaning.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Rows read from the database at a time.
+const EXPORT_PAGE_SIZE: u64 = 500;
+
+/// The kinds of data an export can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportCategory {
+    /// The files of the library
+    Tracks,
+    /// Likes, ratings and play counts
+    Stats,
+    Playlists,
+    Mixes,
+}
+
+impl ExportCategory {
+    pub const ALL: [ExportCategory; 4] = [
+        ExportCategory::Tracks,
+        ExportCategory::Stats,
+        ExportCategory::Playlists,
+        ExportCategory::Mixes,
+    ];
+}
+
+impl FromStr for ExportCategory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tracks" => Ok(ExportCategory::Tracks),
+            "stats" => Ok(ExportCategory::Stats),
+            "playlists" => Ok(ExportCategory::Playlists),
+            "mixes" => Ok(ExportCategory::Mixes),
+            _ => Err(anyhow!(
+                "Invalid category {s}, expected tracks, stats, playlists or mixes"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExportCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ExportCategory::Tracks => "tracks",
+            ExportCategory::Stats => "stats",
+            ExportCategory::Playlists => "playlists",
+            ExportCategory::Mixes => "mixes",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Files are identified by their path relative to the library and their
+/// hash, ids are not stable across libraries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedTrack {
+    pub path: String,
+    pub file_hash: String,
+    /// Duration in seconds
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedStats {
+    pub path: String,
+    pub file_hash: String,
+    pub liked: bool,
+    pub rating: Option<i32>,
+    pub skipped: i32,
+    pub played_through: i32,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedPlaylistItem {
+    pub path: String,
+    pub file_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedPlaylist {
+    pub name: String,
+    pub group: String,
+    /// In playlist order
+    pub items: Vec<ExportedPlaylistItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedMixQuery {
+    pub operator: String,
+    pub parameter: String,
+    pub group: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedMix {
+    pub name: String,
+    pub group: String,
+    pub mode: Option<i32>,
+    pub locked: bool,
+    pub scriptlet_mode: bool,
+    pub queries: Vec<ExportedMixQuery>,
+}
+
+fn relative_path(file: &media_files::Model) -> String {
+    if file.directory.is_empty() {
+        file.file_name.clone()
+    } else {
+        format!("{}/{}", file.directory, file.file_name)
+    }
+}
+
+/// Writes the items of one array of the export, one item per line.
+struct SectionWriter<'a, W: Write> {
+    writer: &'a mut W,
+    written: usize,
+}
+
+impl<'a, W: Write> SectionWriter<'a, W> {
+    fn begin(writer: &'a mut W, name: &str) -> Result<Self> {
+        write!(writer, ",\n\"{name}\": [")?;
+
+        Ok(SectionWriter { writer, written: 0 })
+    }
+
+    fn item<T: Serialize>(&mut self, item: &T) -> Result<()> {
+        if self.written > 0 {
+            write!(self.writer, ",")?;
+        }
+        writeln!(self.writer)?;
+        serde_json::to_writer(&mut *self.writer, item)?;
+        self.written += 1;
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        write!(self.writer, "\n]")?;
+
+        Ok(())
+    }
+}
+
+async fn files_by_id(
+    main_db: &DatabaseConnection,
+    ids: Vec<i32>,
+) -> Result<HashMap<i32, media_files::Model>> {
+    Ok(media_files::Entity::find()
+        .filter(media_files::Column::Id.is_in(ids))
+        .all(main_db)
+        .await?
+        .into_iter()
+        .map(|file| (file.id, file))
+        .collect())
+}
+
+/// Streams the data of the library as JSON to a writer, page by page, so
+/// large libraries are never held in memory.
+///
+/// The output is an object with `version`, `exported_at` and one array per
+/// included category, named after it.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `writer` - Where the JSON is written, better buffered.
+/// * `categories` - The categories to export, in this order.
+/// * `progress_callback` - Called with the rows written and the rows to
+///   write.
+pub async fn export_library<W, F>(
+    main_db: &DatabaseConnection,
+    writer: &mut W,
+    categories: &[ExportCategory],
+    progress_callback: F,
+) -> Result<()>
+where
+    W: Write,
+    F: Fn(usize, usize),
+{
+    let mut total = 0;
+    for category in categories {
+        total += match category {
+            ExportCategory::Tracks => media_files::Entity::find().count(main_db).await?,
+            ExportCategory::Stats => media_file_stats::Entity::find().count(main_db).await?,
+            ExportCategory::Playlists => playlists::Entity::find().count(main_db).await?,
+            ExportCategory::Mixes => mixes::Entity::find().count(main_db).await?,
+        } as usize;
+    }
+    let mut done = 0;
+    progress_callback(done, total);
+
+    write!(
+        writer,
+        "{{\"version\": {EXPORT_VERSION},\n\"exported_at\": {}",
+        serde_json::to_string(&Utc::now().to_rfc3339())?
+    )?;
+
+    for category in categories {
+        let mut section = SectionWriter::begin(writer, &category.to_string())?;
+
+        match category {
+            ExportCategory::Tracks => {
+                let mut pages = media_files::Entity::find()
+                    .order_by_asc(media_files::Column::Id)
+                    .paginate(main_db, EXPORT_PAGE_SIZE);
+                while let Some(files) = pages.fetch_and_next().await? {
+                    for file in &files {
+                        section.item(&ExportedTrack {
+                            path: relative_path(file),
+                            file_hash: file.file_hash.clone(),
+                            duration: file.duration.to_f64().unwrap_or_default(),
+                        })?;
+                    }
+                    done += files.len();
+                    progress_callback(done, total);
+                }
+            }
+            ExportCategory::Stats => {
+                let mut pages = media_file_stats::Entity::find()
+                    .order_by_asc(media_file_stats::Column::Id)
+                    .paginate(main_db, EXPORT_PAGE_SIZE);
+                while let Some(stats) = pages.fetch_and_next().await? {
+                    let files =
+                        files_by_id(main_db, stats.iter().map(|x| x.media_file_id).collect())
+                            .await?;
+                    for stats in &stats {
+                        let Some(file) = files.get(&stats.media_file_id) else {
+                            continue;
+                        };
+                        section.item(&ExportedStats {
+                            path: relative_path(file),
+                            file_hash: file.file_hash.clone(),
+                            liked: stats.liked,
+                            rating: stats.rating,
+                            skipped: stats.skipped,
+                            played_through: stats.played_through,
+                            updated_at: stats.updated_at.clone(),
+                        })?;
+                    }
+                    done += stats.len();
+                    progress_callback(done, total);
+                }
+            }
+            ExportCategory::Playlists => {
+                let all_playlists = playlists::Entity::find()
+                    .order_by_asc(playlists::Column::Id)
+                    .all(main_db)
+                    .await?;
+                for playlist in all_playlists {
+                    let entries = media_file_playlists::Entity::find()
+                        .filter(media_file_playlists::Column::PlaylistId.eq(playlist.id))
+                        .order_by_asc(media_file_playlists::Column::Position)
+                        .all(main_db)
+                        .await?;
+                    let files =
+                        files_by_id(main_db, entries.iter().map(|x| x.media_file_id).collect())
+                            .await?;
+                    let items = entries
+                        .iter()
+                        .filter_map(|entry| files.get(&entry.media_file_id))
+                        .map(|file| ExportedPlaylistItem {
+                            path: relative_path(file),
+                            file_hash: file.file_hash.clone(),
+                        })
+                        .collect();
+
+                    section.item(&ExportedPlaylist {
+                        name: playlist.name,
+                        group: playlist.group,
+                        items,
+                    })?;
+                    done += 1;
+                    progress_callback(done, total);
+                }
+            }
+            ExportCategory::Mixes => {
+                let all_mixes = mixes::Entity::find()
+                    .order_by_asc(mixes::Column::Id)
+                    .all(main_db)
+                    .await?;
+                for mix in all_mixes {
+                    let queries = mix_queries::Entity::find()
+                        .filter(mix_queries::Column::MixId.eq(mix.id))
+                        .order_by_asc(mix_queries::Column::Id)
+                        .all(main_db)
+                        .await?
+                        .into_iter()
+                        .map(|query| ExportedMixQuery {
+                            operator: query.operator,
+                            parameter: query.parameter,
+                            group: query.group,
+                        })
+                        .collect();
+
+                    section.item(&ExportedMix {
+                        name: mix.name,
+                        group: mix.group,
+                        mode: mix.mode,
+                        locked: mix.locked,
+                        scriptlet_mode: mix.scriptlet_mode,
+                        queries,
+                    })?;
+                    done += 1;
+                    progress_callback(done, total);
+                }
+            }
+        }
+
+        section.end()?;
+    }
+
+    writeln!(writer, "\n}}")?;
+    writer.flush()?;
+
+    Ok(())
+}
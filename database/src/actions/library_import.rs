@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, TransactionTrait};
+
+use crate::connection::acquire_write_permit;
+use crate::entities::{media_files, playlists};
+
+use super::playlists::{create_playlist, insert_playlist_items};
+use super::stats::update_stats;
+use super::utils::DatabaseExecutor;
+
+/// The stats of a track as kept by another player.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedStats {
+    pub liked: bool,
+    /// From 1 to 5
+    pub rating: Option<i32>,
+    pub played_through: i32,
+    pub skipped: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedPlaylist {
+    pub name: String,
+    /// The IDs of the matched media files, in playlist order
+    pub media_file_ids: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LibraryImportSummary {
+    pub stats_updated: usize,
+    pub playlists_created: usize,
+    /// Playlists skipped because one with the same name is in the group
+    pub playlists_skipped: usize,
+    pub playlist_items: usize,
+}
+
+fn path_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => Some(c.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Find the media file of a path known by another player.
+///
+/// Paths inside the library are looked up directly. Other paths, from a
+/// library which moved since, are matched by file name, files sharing the
+/// name being told apart by the most parent directories in common.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `lib_path` - The root path of the library.
+/// * `path` - The absolute path of the file for the other player.
+///
+/// # Returns
+/// * `Result<Option<i32>>` - The ID of the media file, `None` if no file or
+///   several files match equally.
+pub async fn match_media_file<E>(main_db: &E, lib_path: &Path, path: &Path) -> Result<Option<i32>>
+where
+    E: DatabaseExecutor + ConnectionTrait,
+{
+    let Some(file_name) = path.file_name().map(|x| x.to_string_lossy().into_owned()) else {
+        return Ok(None);
+    };
+
+    if let Ok(relative_path) = path.strip_prefix(lib_path) {
+        let directory = relative_path
+            .parent()
+            .map(|x| path_components(x).join("/"))
+            .unwrap_or_default();
+
+        let file = media_files::Entity::find()
+            .filter(media_files::Column::Directory.eq(directory))
+            .filter(media_files::Column::FileName.eq(file_name.clone()))
+            .one(main_db)
+            .await?;
+
+        if let Some(file) = file {
+            return Ok(Some(file.id));
+        }
+    }
+
+    let candidates = media_files::Entity::find()
+        .filter(media_files::Column::FileName.eq(file_name))
+        .all(main_db)
+        .await?;
+
+    if candidates.len() <= 1 {
+        return Ok(candidates.first().map(|x| x.id));
+    }
+
+    let parents = path.parent().map(path_components).unwrap_or_default();
+    let scored: Vec<(i32, usize)> = candidates
+        .iter()
+        .map(|file| {
+            let directory: Vec<&str> = file
+                .directory
+                .split('/')
+                .filter(|x| !x.is_empty())
+                .collect();
+            let common = directory
+                .iter()
+                .rev()
+                .zip(parents.iter().rev())
+                .take_while(|(a, b)| **a == b.as_str())
+                .count();
+            (file.id, common)
+        })
+        .collect();
+
+    let best = scored.iter().map(|(_, common)| *common).max().unwrap_or(0);
+    let mut best_files = scored.iter().filter(|(_, common)| *common == best);
+
+    match (best_files.next(), best_files.next()) {
+        (Some((id, _)), None) => Ok(Some(*id)),
+        _ => Ok(None),
+    }
+}
+
+/// Merge the stats and playlists of another player into the library, in
+/// one transaction.
+///
+/// Stats are merged so importing twice changes nothing: likes are kept,
+/// counts take the highest value and ratings replace the existing ones.
+/// Playlists are created in `group`, those already there by name are
+/// skipped.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `node_id` - The id of the client that triggers the operation.
+/// * `stats` - The media file IDs with their imported stats.
+/// * `imported_playlists` - The playlists to create.
+/// * `group` - The group of the created playlists.
+pub async fn apply_library_import(
+    main_db: &DatabaseConnection,
+    node_id: &str,
+    stats: &[(i32, ImportedStats)],
+    imported_playlists: &[ImportedPlaylist],
+    group: &str,
+) -> Result<LibraryImportSummary> {
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+    let mut summary = LibraryImportSummary::default();
+
+    for (media_file_id, imported) in stats {
+        let updated = update_stats(&txn, *media_file_id, |stats| {
+            let liked = *stats.liked.as_ref();
+            let played_through = *stats.played_through.as_ref();
+            let skipped = *stats.skipped.as_ref();
+
+            stats.liked = ActiveValue::Set(liked || imported.liked);
+            stats.played_through = ActiveValue::Set(played_through.max(imported.played_through));
+            stats.skipped = ActiveValue::Set(skipped.max(imported.skipped));
+            if let Some(rating) = imported.rating {
+                stats.rating = ActiveValue::Set(Some(rating));
+            }
+        })
+        .await?;
+
+        if updated.is_some() {
+            summary.stats_updated += 1;
+        }
+    }
+
+    for playlist in imported_playlists {
+        let existing = playlists::Entity::find()
+            .filter(playlists::Column::Name.eq(playlist.name.clone()))
+            .filter(playlists::Column::Group.eq(group))
+            .one(&txn)
+            .await?;
+
+        if existing.is_some() {
+            summary.playlists_skipped += 1;
+            continue;
+        }
+
+        // Items are unique within a playlist
+        let mut seen = HashSet::new();
+        let media_file_ids: Vec<i32> = playlist
+            .media_file_ids
+            .iter()
+            .copied()
+            .filter(|id| seen.insert(*id))
+            .collect();
+
+        let created =
+            create_playlist(&txn, node_id, playlist.name.clone(), group.to_owned()).await?;
+        insert_playlist_items(&txn, node_id, created.id, &media_file_ids).await?;
+
+        summary.playlists_created += 1;
+        summary.playlist_items += media_file_ids.len();
+    }
+
+    txn.commit().await?;
+
+    Ok(summary)
+}
+
+/// The absolute path of a `file://` URL, as written by other players.
+pub fn path_from_file_url(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    // `file://localhost/...` and `file:///...` are the same
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+
+    let mut bytes = Vec::with_capacity(rest.len());
+    let mut input = rest.bytes();
+    while let Some(byte) = input.next() {
+        if byte == b'%' {
+            let high = input.next()?;
+            let low = input.next()?;
+            let hex = [high, low];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+
+    // Windows paths come as `/C:/...`
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_owned(),
+        _ => path,
+    };
+
+    Some(PathBuf::from(path))
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use chrono::Utc;
+use migration::OnConflict;
+use sea_orm::{ActiveValue, EntityTrait};
+
+use crate::entities::library_settings;
+
+use super::utils::DatabaseExecutor;
+
+/// Read a setting of the library.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `key` - The name of the setting.
+///
+/// # Returns
+/// * `Result<Option<String>>` - The value of the setting, `None` if it was never set.
+pub async fn get_library_setting<E>(main_db: &E, key: &str) -> Result<Option<String>>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    Ok(library_settings::Entity::find_by_id(key.to_owned())
+        .one(main_db)
+        .await?
+        .map(|x| x.value))
+}
+
+/// Write a setting of the library, replacing its previous value.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `key` - The name of the setting.
+/// * `value` - The new value of the setting.
+pub async fn set_library_setting<E>(main_db: &E, key: &str, value: &str) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    library_settings::Entity::insert(library_settings::ActiveModel {
+        key: ActiveValue::Set(key.to_owned()),
+        value: ActiveValue::Set(value.to_owned()),
+        updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+    })
+    .on_conflict(
+        OnConflict::column(library_settings::Column::Key)
+            .update_columns([
+                library_settings::Column::Value,
+                library_settings::Column::UpdatedAt,
+            ])
+            .to_owned(),
+    )
+    .exec_without_returning(main_db)
+    .await?;
+
+    Ok(())
+}
+
+/// Read a boolean setting of the library.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `key` - The name of the setting.
+/// * `default` - The value of the setting when it was never set.
+///
+/// # Returns
+/// * `Result<bool>` - The value of the setting.
+pub async fn get_library_flag<E>(main_db: &E, key: &str, default: bool) -> Result<bool>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    Ok(match get_library_setting(main_db, key).await? {
+        Some(value) => value == "true",
+        None => default,
+    })
+}
+
+/// Write a boolean setting of the library.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `key` - The name of the setting.
+/// * `value` - The new value of the setting.
+pub async fn set_library_flag<E>(main_db: &E, key: &str, value: bool) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    set_library_setting(main_db, key, if value { "true" } else { "false" }).await
+}
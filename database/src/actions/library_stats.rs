@@ -0,0 +1,226 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Statement,
+};
+use serde::Serialize;
+
+use ::fsio::FsIo;
+
+use crate::entities::{albums, artists, media_files};
+
+#[derive(Debug, Clone, Serialize, FromQueryResult)]
+pub struct FormatStats {
+    pub extension: String,
+    pub tracks: i64,
+    /// Total duration in seconds
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryStats {
+    pub tracks: u64,
+    pub albums: u64,
+    pub artists: u64,
+    /// Total duration in seconds
+    pub duration: f64,
+    pub unanalyzed: u64,
+    pub liked: u64,
+    pub formats: Vec<FormatStats>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Totals {
+    duration: f64,
+    unanalyzed: i64,
+    liked: i64,
+}
+
+/// Overview of the library. Everything is aggregated by SQLite, no row is
+/// loaded, so this stays fast on large libraries.
+pub async fn get_library_stats(main_db: &DatabaseConnection) -> Result<LibraryStats> {
+    let tracks = media_files::Entity::find().count(main_db).await?;
+    let albums = albums::Entity::find().count(main_db).await?;
+    let artists = artists::Entity::find().count(main_db).await?;
+
+    let totals = Totals::find_by_statement(Statement::from_string(
+        DbBackend::Sqlite,
+        r#"SELECT
+            (SELECT TOTAL(duration) FROM media_files) AS duration,
+            (SELECT COUNT(*) FROM media_files WHERE id NOT IN (SELECT file_id FROM media_analysis)) AS unanalyzed,
+            (SELECT COUNT(*) FROM media_file_stats WHERE liked) AS liked;"#,
+    ))
+    .one(main_db)
+    .await?
+    .ok_or_else(|| anyhow!("Failed to aggregate library statistics"))?;
+
+    let formats = FormatStats::find_by_statement(Statement::from_string(
+        DbBackend::Sqlite,
+        r#"SELECT LOWER(extension) AS extension, COUNT(*) AS tracks, TOTAL(duration) AS duration
+            FROM media_files GROUP BY LOWER(extension) ORDER BY tracks DESC;"#,
+    ))
+    .all(main_db)
+    .await?;
+
+    Ok(LibraryStats {
+        tracks,
+        albums,
+        artists,
+        duration: totals.duration,
+        unanalyzed: totals.unanalyzed as u64,
+        liked: totals.liked as u64,
+        formats,
+    })
+}
+
+/// Total size of the files of the library in bytes, read from the file
+/// system in pages of `page_size` files. Files which can't be read count as
+/// empty.
+pub async fn get_library_size(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    page_size: u64,
+) -> Result<u64> {
+    let mut size = 0;
+    let mut last_id = 0;
+
+    loop {
+        let page: Vec<(i32, String, String)> = media_files::Entity::find()
+            .select_only()
+            .column(media_files::Column::Id)
+            .column(media_files::Column::Directory)
+            .column(media_files::Column::FileName)
+            .filter(media_files::Column::Id.gt(last_id))
+            .order_by_asc(media_files::Column::Id)
+            .limit(page_size)
+            .into_tuple()
+            .all(main_db)
+            .await?;
+
+        let Some((id, _, _)) = page.last() else {
+            break;
+        };
+        last_id = *id;
+
+        for (_, directory, file_name) in &page {
+            let path = lib_path.join(directory).join(file_name);
+            size += fsio.metadata(&path).map(|node| node.size).unwrap_or(0);
+        }
+    }
+
+    Ok(size)
+}
+
+/// How tracks and artists are ranked, also the name of the column sorted
+/// by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankBy {
+    /// Times played through
+    Plays,
+    /// Time spent listening, times played through by duration
+    Time,
+}
+
+impl FromStr for RankBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plays" => Ok(RankBy::Plays),
+            "time" => Ok(RankBy::Time),
+            _ => Err(anyhow!("Invalid ranking {s}, expected plays or time")),
+        }
+    }
+}
+
+impl fmt::Display for RankBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            RankBy::Plays => "plays",
+            RankBy::Time => "time",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromQueryResult)]
+pub struct RankedItem {
+    pub id: i32,
+    pub name: String,
+    pub plays: i64,
+    /// Time spent listening in seconds
+    pub time: f64,
+}
+
+/// Most played tracks, named after their title or file name.
+pub async fn get_top_tracks(
+    main_db: &DatabaseConnection,
+    by: RankBy,
+    limit: u64,
+) -> Result<Vec<RankedItem>> {
+    let sql = format!(
+        r#"SELECT
+            media_files.id AS id,
+            COALESCE(
+                (SELECT meta_value FROM media_metadata
+                    WHERE file_id = media_files.id AND meta_key = 'track_title' LIMIT 1),
+                media_files.file_name
+            ) AS name,
+            CAST(TOTAL(media_file_stats.played_through) AS INTEGER) AS plays,
+            TOTAL(media_file_stats.played_through * media_files.duration) AS time
+        FROM media_file_stats
+        INNER JOIN media_files ON media_files.id = media_file_stats.media_file_id
+        WHERE media_file_stats.played_through > 0
+        GROUP BY media_files.id
+        ORDER BY {by} DESC
+        LIMIT ?;"#
+    );
+
+    let items = RankedItem::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        &sql,
+        [limit.into()],
+    ))
+    .all(main_db)
+    .await?;
+
+    Ok(items)
+}
+
+/// Most played artists, adding up the plays of their tracks.
+pub async fn get_top_artists(
+    main_db: &DatabaseConnection,
+    by: RankBy,
+    limit: u64,
+) -> Result<Vec<RankedItem>> {
+    let sql = format!(
+        r#"SELECT
+            artists.id AS id,
+            artists.name AS name,
+            CAST(TOTAL(media_file_stats.played_through) AS INTEGER) AS plays,
+            TOTAL(media_file_stats.played_through * media_files.duration) AS time
+        FROM media_file_stats
+        INNER JOIN media_files ON media_files.id = media_file_stats.media_file_id
+        INNER JOIN media_file_artists ON media_file_artists.media_file_id = media_files.id
+        INNER JOIN artists ON artists.id = media_file_artists.artist_id
+        WHERE media_file_stats.played_through > 0
+        GROUP BY artists.id
+        ORDER BY {by} DESC
+        LIMIT ?;"#
+    );
+
+    let items = RankedItem::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        &sql,
+        [limit.into()],
+    ))
+    .all(main_db)
+    .await?;
+
+    Ok(items)
+}
@@ -1,18 +1,28 @@
 use std::fmt;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
 
 use anyhow::{Result, anyhow};
-use chrono::Utc;
-use sea_orm::ActiveValue;
-use sea_orm::QueryOrder;
+use chrono::{DateTime, Duration, Utc};
 use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, Condition, QueryOrder, QuerySelect, Select};
 
 use crate::entities::log;
 
 use super::utils::DatabaseExecutor;
 
+/// Entries older than this are removed when the log is pruned.
+pub const MAX_LOG_AGE_DAYS: i64 = 30;
+/// Only the newest entries are kept when the log is pruned.
+pub const MAX_LOG_ROWS: u64 = 10_000;
+/// The log is pruned once every this many insertions.
+const PRUNE_INTERVAL: u64 = 100;
+
+static RECORDED_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info.severity());
+static INSERTED: AtomicU64 = AtomicU64::new(0);
+
 /// Enum representing log levels.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
     Info,
     Warning,
@@ -20,6 +30,85 @@ pub enum LogLevel {
     Debug,
 }
 
+impl LogLevel {
+    const ALL: [LogLevel; 4] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warning,
+        LogLevel::Error,
+    ];
+
+    /// Orders the levels from `Debug` to `Error`.
+    pub const fn severity(&self) -> u8 {
+        match self {
+            LogLevel::Debug => 0,
+            LogLevel::Info => 1,
+            LogLevel::Warning => 2,
+            LogLevel::Error => 3,
+        }
+    }
+
+    /// The levels at least as severe as this one.
+    pub fn and_above(&self) -> Vec<LogLevel> {
+        Self::ALL
+            .into_iter()
+            .filter(|x| x.severity() >= self.severity())
+            .collect()
+    }
+}
+
+/// Sets the least severe level written into the log table, `Info` by default.
+pub fn set_recorded_log_level(level: LogLevel) {
+    RECORDED_LEVEL.store(level.severity(), Ordering::Relaxed);
+}
+
+/// Whether entries of `level` are written into the log table.
+pub fn is_log_level_recorded(level: LogLevel) -> bool {
+    level.severity() >= RECORDED_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Filters applied when listing or exporting log entries.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Only entries at least as severe as this level
+    pub min_level: Option<LogLevel>,
+    /// Only entries whose domain starts with this prefix
+    pub domain: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    /// Only entries whose domain or detail contain this text
+    pub text: Option<String>,
+}
+
+impl LogFilter {
+    fn query(self) -> Select<log::Entity> {
+        let mut query = log::Entity::find();
+
+        if let Some(min_level) = self.min_level {
+            let levels = min_level.and_above().into_iter().map(|x| x.to_string());
+            query = query.filter(log::Column::Level.is_in(levels));
+        }
+        if let Some(domain) = self.domain.filter(|x| !x.is_empty()) {
+            query = query.filter(log::Column::Domain.starts_with(domain));
+        }
+        if let Some(start) = self.start {
+            query = query.filter(log::Column::Date.gte(start));
+        }
+        if let Some(end) = self.end {
+            query = query.filter(log::Column::Date.lte(end));
+        }
+        if let Some(text) = self.text.filter(|x| !x.is_empty()) {
+            query = query.filter(
+                Condition::any()
+                    .add(log::Column::Domain.contains(&text))
+                    .add(log::Column::Detail.contains(&text)),
+            );
+        }
+
+        query
+    }
+}
+
 impl FromStr for LogLevel {
     type Err = anyhow::Error;
 
@@ -46,7 +135,10 @@ impl fmt::Display for LogLevel {
     }
 }
 
-/// Insert a new log entry.
+/// Insert a new log entry, unless its level is below the recorded one.
+///
+/// The log is pruned to `MAX_LOG_AGE_DAYS` and `MAX_LOG_ROWS` once every
+/// few insertions.
 ///
 /// # Arguments
 /// * `main_db` - A reference to the database connection.
@@ -55,16 +147,21 @@ impl fmt::Display for LogLevel {
 /// * `detail` - The detailed log message.
 ///
 /// # Returns
-/// * `Result<Model>` - The inserted log model or an error.
+/// * `Result<Option<Model>>` - The inserted log model, `None` if the entry was
+///   not recorded, or an error.
 pub async fn insert_log<E>(
     main_db: &E,
     level: LogLevel,
     domain: String,
     detail: String,
-) -> Result<log::Model>
+) -> Result<Option<log::Model>>
 where
     E: DatabaseExecutor + sea_orm::ConnectionTrait,
 {
+    if !is_log_level_recorded(level) {
+        return Ok(None);
+    }
+
     let new_log = log::ActiveModel {
         date: ActiveValue::Set(Utc::now()),
         level: ActiveValue::Set(level.to_string()),
@@ -74,7 +171,18 @@ where
     };
 
     let inserted_log = new_log.insert(main_db).await?;
-    Ok(inserted_log)
+
+    let inserted = INSERTED.fetch_add(1, Ordering::Relaxed) + 1;
+    if inserted.is_multiple_of(PRUNE_INTERVAL) {
+        prune_logs(
+            main_db,
+            Some(Duration::days(MAX_LOG_AGE_DAYS)),
+            Some(MAX_LOG_ROWS),
+        )
+        .await?;
+    }
+
+    Ok(Some(inserted_log))
 }
 
 /// Clear all log entries.
@@ -107,10 +215,11 @@ pub async fn delete_log(main_db: &DatabaseConnection, log_id: i32) -> Result<()>
     Ok(())
 }
 
-/// List log entries with pagination.
+/// List log entries with filtering and pagination, newest first.
 ///
 /// # Arguments
 /// * `main_db` - A reference to the database connection.
+/// * `filter` - Restricts the entries by level, domain, time range and text.
 /// * `cursor` - The starting point for pagination (0-based index).
 /// * `page_size` - The number of logs to retrieve per page.
 ///
@@ -118,13 +227,85 @@ pub async fn delete_log(main_db: &DatabaseConnection, log_id: i32) -> Result<()>
 /// * `Result<Vec<log::Model>>` - A vector of log models or an error.
 pub async fn list_log(
     main_db: &DatabaseConnection,
+    filter: LogFilter,
     cursor: u64,
     page_size: u64,
 ) -> Result<Vec<log::Model>> {
-    let paginator = log::Entity::find()
+    let paginator = filter
+        .query()
         .order_by_desc(log::Column::Date)
+        .order_by_desc(log::Column::Id)
         .paginate(main_db, page_size);
 
     let logs = paginator.fetch_page(cursor).await?;
     Ok(logs)
 }
+
+/// List every log entry matching a filter, oldest first.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `filter` - Restricts the entries by level, domain, time range and text.
+///
+/// # Returns
+/// * `Result<Vec<log::Model>>` - A vector of log models or an error.
+pub async fn select_logs(
+    main_db: &DatabaseConnection,
+    filter: LogFilter,
+) -> Result<Vec<log::Model>> {
+    let logs = filter
+        .query()
+        .order_by_asc(log::Column::Date)
+        .order_by_asc(log::Column::Id)
+        .all(main_db)
+        .await?;
+
+    Ok(logs)
+}
+
+/// Remove log entries that are older than `max_age` or exceed `max_rows`.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `max_age` - Entries older than this are removed.
+/// * `max_rows` - Only the newest `max_rows` entries are kept.
+///
+/// # Returns
+/// * `Result<u64>` - The number of removed entries or an error.
+pub async fn prune_logs<E>(
+    main_db: &E,
+    max_age: Option<Duration>,
+    max_rows: Option<u64>,
+) -> Result<u64>
+where
+    E: sea_orm::ConnectionTrait,
+{
+    let mut removed = 0;
+
+    if let Some(max_age) = max_age {
+        let threshold = Utc::now() - max_age;
+        removed += log::Entity::delete_many()
+            .filter(log::Column::Date.lt(threshold))
+            .exec(main_db)
+            .await?
+            .rows_affected;
+    }
+
+    if let Some(max_rows) = max_rows {
+        let boundary = log::Entity::find()
+            .order_by_desc(log::Column::Id)
+            .offset(max_rows)
+            .one(main_db)
+            .await?;
+
+        if let Some(boundary) = boundary {
+            removed += log::Entity::delete_many()
+                .filter(log::Column::Id.lte(boundary.id))
+                .exec(main_db)
+                .await?
+                .rows_affected;
+        }
+    }
+
+    Ok(removed)
+}
@@ -0,0 +1,142 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{QuerySelect, prelude::*};
+
+use playback::loudness::TrackLoudness;
+
+use crate::entities::{media_analysis, media_file_albums, media_files};
+
+/// The loudness and the peak of an analyzed track, with its duration.
+#[derive(Debug, Clone, Copy)]
+struct MeasuredTrack {
+    loudness: f64,
+    peak: f64,
+    duration: f64,
+}
+
+/// The loudness of a group of tracks played one after the other: the mean
+/// of their power weighted by their duration, and their highest peak.
+pub fn aggregate_loudness(tracks: &[(f64, f64, f64)]) -> Option<(f64, f64)> {
+    let total_duration: f64 = tracks.iter().map(|(_, _, duration)| duration).sum();
+    if total_duration <= 0.0 {
+        return None;
+    }
+
+    let power: f64 = tracks
+        .iter()
+        .map(|(loudness, _, duration)| 10f64.powf((loudness + 0.691) / 10.0) * duration)
+        .sum::<f64>()
+        / total_duration;
+    let peak = tracks.iter().map(|(_, peak, _)| *peak).fold(0.0, f64::max);
+
+    Some((-0.691 + 10.0 * power.log10(), peak))
+}
+
+async fn get_measured_tracks(
+    main_db: &DatabaseConnection,
+    file_ids: Vec<i32>,
+) -> Result<HashMap<i32, MeasuredTrack>> {
+    let rows: Vec<(i32, Option<Decimal>, Option<Decimal>, Decimal)> =
+        media_analysis::Entity::find()
+            .select_only()
+            .column(media_analysis::Column::FileId)
+            .column(media_analysis::Column::IntegratedLoudness)
+            .column(media_analysis::Column::Peak)
+            .column(media_files::Column::Duration)
+            .inner_join(media_files::Entity)
+            .filter(media_analysis::Column::FileId.is_in(file_ids))
+            .filter(media_analysis::Column::IntegratedLoudness.is_not_null())
+            .into_tuple()
+            .all(main_db)
+            .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(file_id, loudness, peak, duration)| {
+            Some((
+                file_id,
+                MeasuredTrack {
+                    loudness: loudness?.to_f64()?,
+                    peak: peak.and_then(|x| x.to_f64()).unwrap_or_default(),
+                    duration: duration.to_f64().unwrap_or_default(),
+                },
+            ))
+        })
+        .collect())
+}
+
+/// The loudness of the given files, measured when they were analyzed, and
+/// of their album out of its analyzed tracks.
+///
+/// Files analyzed before loudness was measured are left out.
+pub async fn get_track_loudness(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, TrackLoudness>> {
+    let album_of_file: HashMap<i32, i32> = media_file_albums::Entity::find()
+        .select_only()
+        .column(media_file_albums::Column::MediaFileId)
+        .column(media_file_albums::Column::AlbumId)
+        .filter(media_file_albums::Column::MediaFileId.is_in(file_ids.to_vec()))
+        .into_tuple::<(i32, i32)>()
+        .all(main_db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let album_ids: HashSet<i32> = album_of_file.values().copied().collect();
+    let album_tracks: Vec<(i32, i32)> = media_file_albums::Entity::find()
+        .select_only()
+        .column(media_file_albums::Column::MediaFileId)
+        .column(media_file_albums::Column::AlbumId)
+        .filter(media_file_albums::Column::AlbumId.is_in(album_ids))
+        .into_tuple()
+        .all(main_db)
+        .await?;
+
+    let measured = get_measured_tracks(
+        main_db,
+        file_ids
+            .iter()
+            .copied()
+            .chain(album_tracks.iter().map(|(file_id, _)| *file_id))
+            .collect(),
+    )
+    .await?;
+
+    let mut albums: HashMap<i32, Vec<(f64, f64, f64)>> = HashMap::new();
+    for (file_id, album_id) in album_tracks {
+        if let Some(track) = measured.get(&file_id) {
+            albums
+                .entry(album_id)
+                .or_default()
+                .push((track.loudness, track.peak, track.duration));
+        }
+    }
+    let albums: HashMap<i32, (f64, f64)> = albums
+        .into_iter()
+        .filter_map(|(album_id, tracks)| Some((album_id, aggregate_loudness(&tracks)?)))
+        .collect();
+
+    Ok(file_ids
+        .iter()
+        .filter_map(|file_id| {
+            let track = measured.get(file_id)?;
+            let album = album_of_file
+                .get(file_id)
+                .and_then(|album_id| albums.get(album_id));
+
+            Some((
+                *file_id,
+                TrackLoudness {
+                    track_loudness: track.loudness as f32,
+                    track_peak: track.peak as f32,
+                    album_loudness: album.map(|(loudness, _)| *loudness as f32),
+                    album_peak: album.map(|(_, peak)| *peak as f32),
+                },
+            ))
+        })
+        .collect())
+}
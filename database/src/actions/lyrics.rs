@@ -0,0 +1,181 @@
+//! Lyrics indexed for the full-text search. Lyrics make the index much
+//! larger, so they are only indexed once enabled for the library.
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+use log::warn;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait};
+
+use ::lyric::{lrc::parse_lrc, parser::parse_audio_lyrics};
+
+use crate::connection::acquire_write_permit;
+use crate::entities::{media_files, media_metadata};
+
+use super::{
+    library_settings::{get_library_flag, set_library_flag},
+    search::{add_lyrics_term, clear_lyrics_terms, remove_lyrics_term},
+    utils::DatabaseExecutor,
+};
+
+/// The library setting telling whether lyrics are indexed.
+pub const INDEX_LYRICS_SETTING: &str = "search.index_lyrics";
+
+/// The metadata field embedded lyrics are read into.
+const LYRICS_META_KEY: &str = "lyrics";
+
+/// Time tags of LRC lines, `[01:02.03]`, and of their words, `<01:02.03>`.
+static TIME_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"[\[<]\d+:\d+(?:[.:]\d+)?[\]>]").unwrap());
+
+pub async fn is_lyrics_indexing_enabled<E>(main_db: &E) -> Result<bool>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    get_library_flag(main_db, INDEX_LYRICS_SETTING, false).await
+}
+
+/// Turns the indexing of lyrics on or off. Lyrics indexed before are kept
+/// until [`rebuild_lyrics_index`] runs.
+pub async fn set_lyrics_indexing_enabled<E>(main_db: &E, enabled: bool) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    set_library_flag(main_db, INDEX_LYRICS_SETTING, enabled).await
+}
+
+/// Joins the lines of lyrics without their time tags, skipping the empty
+/// ones.
+fn join_lines(lines: impl Iterator<Item = String>) -> String {
+    lines
+        .map(|x| TIME_TAG.replace_all(&x, "").trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The text of lyrics, without the ID tags and the time tags of LRC.
+///
+/// # Arguments
+/// * `content` - Lyrics in LRC or as plain text.
+///
+/// # Returns
+/// * `String` - One line of lyrics per line, empty lines removed.
+pub fn lyrics_text(content: &str) -> String {
+    match parse_lrc(content) {
+        Ok(lyric) if !lyric.lyrics.is_empty() => {
+            join_lines(lyric.lyrics.into_iter().map(|x| x.text))
+        }
+        _ => join_lines(content.lines().map(str::to_owned)),
+    }
+}
+
+/// The text of the lyrics of a media file, embedded ones first, like the
+/// player picks them.
+///
+/// # Arguments
+/// * `path` - The path of the media file, sidecar lyrics are found next to it.
+/// * `embedded` - The lyrics embedded in the media file, if any.
+///
+/// # Returns
+/// * `Option<String>` - The text of the lyrics, `None` if the file has none.
+pub fn read_lyrics_text(path: &Path, embedded: Option<&str>) -> Option<String> {
+    let text = match embedded {
+        Some(embedded) => lyrics_text(embedded),
+        None => match parse_audio_lyrics(path.to_path_buf())? {
+            Ok(lyric) => join_lines(lyric.lyrics.into_iter().map(|x| x.text)),
+            Err(e) => {
+                warn!("Unable to parse the lyrics of {path:?}: {e:?}");
+                return None;
+            }
+        },
+    };
+
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// Indexes the lyrics of a media file if lyrics are indexed in the library,
+/// removing the ones indexed before when the file has none anymore.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `file_id` - The ID of the media file.
+/// * `path` - The path of the media file.
+/// * `metadata` - The metadata read from the media file.
+///
+/// # Returns
+/// * `Result<bool>` - Whether lyrics were indexed.
+pub async fn index_file_lyrics<E>(
+    main_db: &E,
+    file_id: i32,
+    path: &Path,
+    metadata: &[(String, String)],
+) -> Result<bool>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    if !is_lyrics_indexing_enabled(main_db).await? {
+        return Ok(false);
+    }
+
+    let embedded = metadata
+        .iter()
+        .find(|(key, _)| key == LYRICS_META_KEY)
+        .map(|(_, value)| value.as_str());
+
+    match read_lyrics_text(path, embedded) {
+        Some(text) => {
+            add_lyrics_term(main_db, file_id, &text).await?;
+            Ok(true)
+        }
+        None => {
+            remove_lyrics_term(main_db, file_id).await?;
+            Ok(false)
+        }
+    }
+}
+
+/// Removes every indexed lyric, then indexes the lyrics of every media file
+/// again if lyrics are indexed in the library, so turning the setting on
+/// or off takes effect.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `lib_path` - The root path of the library.
+///
+/// # Returns
+/// * `Result<usize>` - The number of media files whose lyrics were indexed.
+pub async fn rebuild_lyrics_index(main_db: &DatabaseConnection, lib_path: &Path) -> Result<usize> {
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+    clear_lyrics_terms(&txn).await?;
+
+    if !is_lyrics_indexing_enabled(&txn).await? {
+        txn.commit().await?;
+        return Ok(0);
+    }
+
+    let mut embedded: HashMap<i32, String> = media_metadata::Entity::find()
+        .filter(media_metadata::Column::MetaKey.eq(LYRICS_META_KEY))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|x| (x.file_id, x.meta_value))
+        .collect();
+
+    let mut indexed = 0;
+    for file in media_files::Entity::find().all(&txn).await? {
+        let path = lib_path.join(&file.directory).join(&file.file_name);
+        let lyrics = embedded.remove(&file.id);
+
+        if let Some(text) = read_lyrics_text(&path, lyrics.as_deref()) {
+            add_lyrics_term(&txn, file.id, &text).await?;
+            indexed += 1;
+        }
+    }
+
+    txn.commit().await?;
+
+    Ok(indexed)
+}
@@ -0,0 +1,214 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
+use heed::CompactionOption;
+use log::info;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, FromQueryResult, Statement};
+use serde::{Deserialize, Serialize};
+
+use crate::connection::{MAIN_DB_FILE, RECOMMENDATION_DB_FILE, RecommendationDbConnection};
+
+/// Name of the manifest written along with the databases of a backup.
+pub const BACKUP_MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, FromQueryResult)]
+struct DatabaseSize {
+    size: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct IntegrityCheckRow {
+    integrity_check: String,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct ForeignKeyCheckRow {
+    table: String,
+    rowid: Option<i64>,
+    parent: String,
+}
+
+async fn get_main_db_size(main_db: &DatabaseConnection) -> Result<u64> {
+    let size = DatabaseSize::find_by_statement(Statement::from_string(
+        DbBackend::Sqlite,
+        "SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size();",
+    ))
+    .one(main_db)
+    .await?
+    .ok_or_else(|| anyhow!("Failed to read the size of the database"))?;
+
+    Ok(size.size as u64)
+}
+
+/// Writes the write-ahead log of the main database back to it and
+/// truncates the log.
+pub async fn checkpoint_main_db(main_db: &DatabaseConnection) -> Result<()> {
+    main_db
+        .execute_unprepared("PRAGMA wal_checkpoint(TRUNCATE);")
+        .await
+        .context("Failed to checkpoint the main database")?;
+
+    Ok(())
+}
+
+/// Size of the main database in bytes before and after vacuuming it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VacuumOutcome {
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// Rebuilds the main database to reclaim the space of deleted rows, then
+/// truncates its write-ahead log. The recommendation database can't be
+/// compacted in place, backing it up compacts the copy.
+pub async fn vacuum_main_db(main_db: &DatabaseConnection) -> Result<VacuumOutcome> {
+    let size_before = get_main_db_size(main_db).await?;
+
+    main_db
+        .execute_unprepared("VACUUM;")
+        .await
+        .context("Failed to vacuum the main database")?;
+    checkpoint_main_db(main_db).await?;
+
+    let size_after = get_main_db_size(main_db).await?;
+    info!("Vacuumed the main database from {size_before} to {size_after} bytes");
+
+    Ok(VacuumOutcome {
+        size_before,
+        size_after,
+    })
+}
+
+/// Checks the structure of the main database and that every row points to
+/// rows which exist, then that every node of the recommendation database
+/// can be decoded. Returns the problems found, empty if there are none.
+pub async fn verify_databases(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+
+    let integrity = IntegrityCheckRow::find_by_statement(Statement::from_string(
+        DbBackend::Sqlite,
+        "SELECT integrity_check FROM pragma_integrity_check();",
+    ))
+    .all(main_db)
+    .await
+    .context("Failed to check the integrity of the main database")?;
+    problems.extend(
+        integrity
+            .into_iter()
+            .map(|row| row.integrity_check)
+            .filter(|x| x != "ok")
+            .map(|x| format!("main: {x}")),
+    );
+
+    let foreign_keys = ForeignKeyCheckRow::find_by_statement(Statement::from_string(
+        DbBackend::Sqlite,
+        r#"SELECT "table", rowid, parent FROM pragma_foreign_key_check();"#,
+    ))
+    .all(main_db)
+    .await
+    .context("Failed to check the foreign keys of the main database")?;
+    problems.extend(foreign_keys.into_iter().map(|row| {
+        let rowid = row
+            .rowid
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| "?".to_owned());
+        format!(
+            "main: row {rowid} of {} points to a missing row of {}",
+            row.table, row.parent
+        )
+    }));
+
+    let rtxn = recommend_db.env.read_txn()?;
+    let mut nodes = 0;
+    for entry in recommend_db.db.iter(&rtxn)? {
+        match entry {
+            Ok(_) => nodes += 1,
+            Err(e) => problems.push(format!("recommendation: {e}")),
+        }
+    }
+    info!(
+        "Verified the databases, {nodes} recommendation nodes, {} problems",
+        problems.len()
+    );
+
+    Ok(problems)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Written along with the databases, restoring a backup is copying its
+/// files back to the database directory of the library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub created_at: String,
+    /// The library the databases belong to
+    pub library: PathBuf,
+    pub files: Vec<BackupFile>,
+}
+
+/// Copies both databases of a library into a new directory below
+/// `output_dir` while they are in use, along with a manifest. Returns the
+/// directory of the backup.
+pub async fn backup_databases(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    lib_path: &Path,
+    output_dir: &Path,
+) -> Result<(PathBuf, BackupManifest)> {
+    let now = Utc::now();
+    let backup_dir = output_dir.join(format!("rune-backup-{}", now.format("%Y%m%d-%H%M%S")));
+    if backup_dir.exists() {
+        bail!("{} already exists", backup_dir.display());
+    }
+    fs::create_dir_all(&backup_dir)
+        .with_context(|| format!("Failed to create {}", backup_dir.display()))?;
+
+    let main_path = backup_dir.join(MAIN_DB_FILE);
+    main_db
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "VACUUM INTO ?;",
+            [main_path.to_string_lossy().to_string().into()],
+        ))
+        .await
+        .context("Failed to back up the main database")?;
+
+    let recommendation_path = backup_dir.join(RECOMMENDATION_DB_FILE);
+    recommend_db
+        .env
+        .copy_to_path(&recommendation_path, CompactionOption::Enabled)
+        .context("Failed to back up the recommendation database")?;
+
+    let mut files = Vec::new();
+    for path in [&main_path, &recommendation_path] {
+        files.push(BackupFile {
+            name: path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            size: fs::metadata(path)?.len(),
+        });
+    }
+
+    let manifest = BackupManifest {
+        created_at: now.to_rfc3339(),
+        library: lib_path.to_path_buf(),
+        files,
+    };
+    fs::write(
+        backup_dir.join(BACKUP_MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    info!("Backed up the databases to {}", backup_dir.display());
+
+    Ok((backup_dir, manifest))
+}
@@ -5,13 +5,15 @@ use std::{
 
 use anyhow::{Context, Result, bail};
 use chrono::Utc;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use sea_orm::{
-    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, TransactionTrait,
+    ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
     entity::prelude::*,
+    sea_query::{Expr, Func},
 };
 use tokio_util::sync::CancellationToken;
 
@@ -19,19 +21,23 @@ use ::fsio::{FsIo, FsNode};
 use ::metadata::{
     describe::{FileDescription, describe_file},
     reader::get_metadata,
-    scanner::AudioScanner,
+    scanner::{AudioScanner, is_audio_file, is_trashed},
 };
 
 use crate::actions::{
+    case_fold::{CaseFoldedFiles, is_case_insensitive},
     collection::CollectionQueryType,
     cover_art::remove_cover_art_by_file_id,
     file::get_file_ids_by_descriptions,
     index::{index_media_files, perform_library_maintenance},
     logging::{LogLevel, insert_log},
-    search::{add_term, remove_term},
+    lyrics::index_file_lyrics,
+    search::{add_term, remove_lyrics_term, remove_term},
 };
+use crate::connection::acquire_write_permit;
 use crate::entities::{
-    albums, artists, media_file_albums, media_file_artists, media_files, media_metadata,
+    albums, artists, media_file_albums, media_file_artists, media_file_stats, media_files,
+    media_metadata,
 };
 
 use super::cover_art::get_magic_cover_art_id;
@@ -137,6 +143,7 @@ pub async fn sync_file_descriptions(
     debug!("Starting to process multiple files");
 
     // Start a transaction
+    let _permit = acquire_write_permit().await;
     let txn = main_db.begin().await?;
     let mut search_term: Option<(i32, String)> = None;
 
@@ -453,6 +460,7 @@ pub async fn process_files(
     debug!("Starting to process multiple files");
 
     // Start a transaction
+    let _permit = acquire_write_permit().await;
     let txn = main_db.begin().await?;
 
     for description in descriptions.iter_mut() {
@@ -629,6 +637,23 @@ where
     Ok(())
 }
 
+/// Records the modification time of a file the app wrote to, so the scanner
+/// doesn't take the write for an external change.
+pub async fn set_file_last_modified<E>(db: &E, file_id: i32, last_modified: String) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    media_files::Entity::update_many()
+        .col_expr(
+            media_files::Column::LastModified,
+            Expr::value(last_modified),
+        )
+        .filter(media_files::Column::Id.eq(file_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
 pub async fn update_file_metadata<E>(
     fsio: &FsIo,
     db: &E,
@@ -740,6 +765,8 @@ where
         return Err(e);
     }
 
+    index_file_lyrics(db, existing_file.id, &metadata.path, &metadata.metadata).await?;
+
     Ok(())
 }
 
@@ -844,6 +871,7 @@ where
             Decimal::from_f64(duration_in_seconds).expect("Unable to convert track duration"),
         ),
         last_modified: ActiveValue::Set(description.last_modified.clone()),
+        first_seen_at: ActiveValue::Set(Utc::now().to_rfc3339()),
         hlc_uuid: ActiveValue::Set(
             Uuid::new_v5(&Uuid::NAMESPACE_OID, new_hash.as_bytes()).to_string(),
         ),
@@ -901,6 +929,8 @@ where
             .with_context(|| format!("Failed to insert new metadata: {}", description.file_name))?;
     }
 
+    index_file_lyrics(main_db, file_id, &metadata.path, &metadata.metadata).await?;
+
     Ok(())
 }
 
@@ -918,7 +948,8 @@ async fn clean_up_database(main_db: &DatabaseConnection, root_path: &Path) -> Re
                 .exec(main_db)
                 .await?;
 
-            remove_term(main_db, CollectionQueryType::Track, db_file.id).await?
+            remove_term(main_db, CollectionQueryType::Track, db_file.id).await?;
+            remove_lyrics_term(main_db, db_file.id).await?;
         }
     }
 
@@ -942,7 +973,7 @@ where
     F: Fn(usize) + Send + Sync,
 {
     let root_path_str = lib_path.to_str().expect("Invalid UTF-8 sequence in path");
-    let mut scanner = AudioScanner::new(fsio, &root_path_str)?;
+    let mut scanner = AudioScanner::new(fsio, &root_path_str, cancel_token.clone())?;
 
     info!("Starting audio library scan with last-modified pre-filtering");
 
@@ -950,6 +981,24 @@ where
     let mut total_files_scanned = 0;
     let mut skipped_files = 0;
 
+    // Files whose case changed are only the same file when the file system
+    // ignores case
+    let mut case_folded = if is_case_insensitive(fsio, main_db, lib_path).await {
+        info!("The library is on a case-insensitive file system");
+        match CaseFoldedFiles::load(main_db)
+            .await
+            .with_context(|| "Unable to load files for case-insensitive matching")
+        {
+            Ok(x) => Some(x),
+            Err(e) => {
+                error!("{e:#?}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     // Read audio files in larger batches for better performance
     const BATCH_SIZE: usize = 48;
 
@@ -963,7 +1012,7 @@ where
         }
 
         debug!("Reading metadata for the next {} files", BATCH_SIZE);
-        let files = scanner.read_files(BATCH_SIZE);
+        let files = scanner.read_files(BATCH_SIZE).await;
         total_files_scanned += files.len();
 
         if files.is_empty() {
@@ -977,6 +1026,18 @@ where
             .map(|result| result.ok())
             .collect();
 
+        if let Some(case_folded) = case_folded.as_mut() {
+            match case_folded
+                .apply_renames(main_db, &descriptions)
+                .await
+                .with_context(|| "Unable to apply case-only renames")
+            {
+                Ok(0) => {}
+                Ok(renamed) => info!("Updated {} files whose case changed", renamed),
+                Err(e) => error!("{e:#?}"),
+            }
+        }
+
         // Pre-filtering: Skip files that haven't been modified
         if !force {
             // Collect file keys for batch database query
@@ -1048,6 +1109,14 @@ where
         progress_callback(total_files_scanned);
     }
 
+    // The walk also ends early when the scan is cancelled
+    if let Some(ref token) = cancel_token
+        && token.is_cancelled()
+    {
+        info!("Scan cancelled.");
+        return Ok(processed_files);
+    }
+
     if skipped_files > 0 {
         info!("Pre-filtering skipped {} unchanged files", skipped_files);
     }
@@ -1073,6 +1142,57 @@ where
     Ok(processed_files)
 }
 
+/// Scans some files of the library, like [`scan_audio_library`] does for
+/// every file, skipping the files which are not audio or did not change.
+/// Returns the IDs of the files added or updated.
+pub async fn scan_audio_files(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    node_id: &str,
+    lib_path: &Path,
+    paths: &[PathBuf],
+) -> Result<Vec<i32>> {
+    let descriptions: Vec<Option<FileDescription>> = paths
+        .iter()
+        .filter_map(|path| match fsio.metadata(path) {
+            Ok(node) => Some(node),
+            Err(e) => {
+                warn!("Failed to read {}: {e}", path.display());
+                None
+            }
+        })
+        .filter(|node| node.is_file && is_audio_file(node) && !is_trashed(node))
+        .map(
+            |node| match describe_file(&node, &Some(lib_path.to_path_buf())) {
+                Ok(description) => Some(description),
+                Err(e) => {
+                    warn!("Skipping {}: {e}", node.path.display());
+                    None
+                }
+            },
+        )
+        .collect();
+
+    let file_keys: Vec<(String, String)> = descriptions
+        .iter()
+        .flatten()
+        .map(|x| (x.directory.clone(), x.file_name.clone()))
+        .collect();
+    let existing_modified_map = get_existing_files_modified(main_db, file_keys).await?;
+    let (mut descriptions, _) = filter_modified_files(descriptions, &existing_modified_map);
+
+    if !descriptions.iter().any(|x| x.is_some()) {
+        return Ok(vec![]);
+    }
+
+    sync_file_descriptions(fsio, main_db, node_id, &mut descriptions, false).await?;
+
+    let file_ids = get_file_ids_by_descriptions(main_db, &descriptions).await?;
+    index_media_files(main_db, node_id, file_ids.clone(), None).await?;
+
+    Ok(file_ids)
+}
+
 static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
 
 pub fn extract_number(s: &str) -> Option<i32> {
@@ -1092,6 +1212,19 @@ pub struct MetadataSummary {
     pub duration: f64,
     pub cover_art_id: Option<i32>,
     pub file_hash: String,
+    /// When the file was added to the library (RFC 3339)
+    pub first_seen_at: String,
+    pub skipped: i32,
+    pub played_through: i32,
+    /// When the file was last skipped (RFC 3339)
+    pub last_skipped_at: Option<String>,
+    /// Time spent listening to the file, in seconds
+    pub total_listened_seconds: f64,
+    /// Where the listening of the file stopped, in seconds, `None` if it
+    /// was listened to the end
+    pub last_position_seconds: Option<f64>,
+    /// When the file was first listened to (RFC 3339)
+    pub first_played_at: Option<String>,
 }
 
 pub async fn get_metadata_summary_by_files(
@@ -1117,6 +1250,14 @@ pub async fn get_metadata_summary_by_files(
         .all(db)
         .await?;
 
+    let stats_map: HashMap<i32, media_file_stats::Model> = media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.is_in(file_ids.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|x| (x.media_file_id, x))
+        .collect();
+
     // Create a map for metadata entries
     let mut metadata_map: HashMap<i32, HashMap<String, String>> = HashMap::new();
     for entry in metadata_entries {
@@ -1147,6 +1288,7 @@ pub async fn get_metadata_summary_by_files(
             .unwrap_or(0);
 
         let track_number = parsed_disk_number * 1000 + parsed_track_number;
+        let stats = stats_map.get(&file_id);
 
         let summary = MetadataSummary {
             id: file_id,
@@ -1171,6 +1313,13 @@ pub async fn get_metadata_summary_by_files(
                 cover_art_id
             },
             file_hash: file.file_hash.clone(),
+            first_seen_at: file.first_seen_at.clone(),
+            skipped: stats.map(|x| x.skipped).unwrap_or_default(),
+            played_through: stats.map(|x| x.played_through).unwrap_or_default(),
+            last_skipped_at: stats.and_then(|x| x.last_skipped_at.clone()),
+            total_listened_seconds: stats.map(|x| x.total_listened_seconds).unwrap_or_default(),
+            last_position_seconds: stats.and_then(|x| x.last_position_seconds),
+            first_played_at: stats.and_then(|x| x.first_played_at.clone()),
         };
 
         results.push(summary);
@@ -1215,6 +1364,42 @@ pub async fn get_metadata_summary_by_file_id(
         .ok_or_else(|| anyhow::anyhow!("Metadata summary not found for file ID: {}", file_id))
 }
 
+/// Find the files of a track by its artist and title, ignoring case.
+///
+/// # Arguments
+/// * `db` - A reference to the database connection.
+/// * `artist` - The artist of the track.
+/// * `title` - The title of the track.
+///
+/// # Returns
+/// * `Result<Vec<MetadataSummary>>` - The files of the track, none when it is
+///   not part of the library.
+pub async fn get_metadata_summary_by_track(
+    db: &DatabaseConnection,
+    artist: &str,
+    title: &str,
+) -> Result<Vec<MetadataSummary>> {
+    let file_ids: Vec<i32> = media_metadata::Entity::find()
+        .select_only()
+        .column(media_metadata::Column::FileId)
+        .filter(media_metadata::Column::MetaKey.eq("track_title"))
+        .filter(
+            Expr::expr(Func::lower(Expr::col(media_metadata::Column::MetaValue)))
+                .eq(Func::lower(Expr::val(title.trim()))),
+        )
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let artist = artist.trim().to_lowercase();
+    let summaries = get_metadata_summary_by_file_ids(db, file_ids).await?;
+
+    Ok(summaries
+        .into_iter()
+        .filter(|x| x.artist.trim().to_lowercase() == artist)
+        .collect())
+}
+
 pub async fn get_parsed_file_by_id(
     db: &DatabaseConnection,
     file_id: i32,
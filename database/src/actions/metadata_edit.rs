@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use chrono::Utc;
+use log::info;
+use regex::Regex;
+use sea_orm::{ActiveValue, DatabaseConnection, TransactionTrait, prelude::*};
+
+use ::fsio::FsIo;
+use ::metadata::writer::{TagWriteOutcome, write_tags};
+
+use crate::actions::{
+    collection::CollectionQueryType, file::get_files_by_ids, index::index_media_files,
+    metadata::set_file_last_modified, search::add_term,
+};
+use crate::connection::acquire_write_permit;
+use crate::entities::{media_files, media_metadata};
+
+/// Metadata keys which can be edited, as stored in `media_metadata`.
+pub const EDITABLE_KEYS: [&str; 10] = [
+    "track_title",
+    "artist",
+    "album",
+    "album_artist",
+    "genre",
+    "track_number",
+    "disc_number",
+    "date",
+    "sort_artist",
+    "sort_album_artist",
+];
+
+/// A metadata value which changes with an edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataChange {
+    pub file_id: i32,
+    pub key: String,
+    pub old_value: Option<String>,
+    pub new_value: String,
+}
+
+/// Get the editable metadata of files, keyed by file ID.
+pub async fn get_editable_metadata(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<HashMap<i32, HashMap<String, String>>> {
+    let entries = media_metadata::Entity::find()
+        .filter(media_metadata::Column::FileId.is_in(file_ids.to_vec()))
+        .filter(media_metadata::Column::MetaKey.is_in(EDITABLE_KEYS))
+        .all(main_db)
+        .await?;
+
+    let mut metadata: HashMap<i32, HashMap<String, String>> = HashMap::new();
+    for entry in entries {
+        metadata
+            .entry(entry.file_id)
+            .or_default()
+            .insert(entry.meta_key, entry.meta_value);
+    }
+
+    Ok(metadata)
+}
+
+/// Compares the requested values with the current metadata of each file.
+/// Values which are already set are left out of the result.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `edits` - The key-value pairs to set, for each file ID.
+///
+/// # Returns
+/// * `Result<Vec<MetadataChange>>` - The changes to apply, or an error if a
+///   file doesn't exist or a key can't be edited.
+pub async fn diff_metadata(
+    main_db: &DatabaseConnection,
+    edits: &[(i32, Vec<(String, String)>)],
+) -> Result<Vec<MetadataChange>> {
+    let file_ids: Vec<i32> = edits.iter().map(|(file_id, _)| *file_id).collect();
+    let files = get_files_by_ids(main_db, &file_ids).await?;
+    for file_id in &file_ids {
+        if !files.iter().any(|file| file.id == *file_id) {
+            bail!("File {file_id} does not exist");
+        }
+    }
+
+    let current = get_editable_metadata(main_db, &file_ids).await?;
+    let empty = HashMap::new();
+
+    let mut changes = Vec::new();
+    for (file_id, fields) in edits {
+        let metadata = current.get(file_id).unwrap_or(&empty);
+
+        for (key, value) in fields {
+            if !EDITABLE_KEYS.contains(&key.as_str()) {
+                bail!("Metadata field {key} can't be edited");
+            }
+
+            let old_value = metadata.get(key).cloned();
+            if old_value.as_ref() == Some(value) {
+                continue;
+            }
+
+            changes.push(MetadataChange {
+                file_id: *file_id,
+                key: key.clone(),
+                old_value,
+                new_value: value.clone(),
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Writes metadata changes in one transaction, then indexes the changed
+/// files again so artists and albums follow the new values.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `node_id` - The id of the client that triggers the operation.
+/// * `changes` - The changes, as returned by `diff_metadata`.
+///
+/// # Returns
+/// * `Result<()>` - An empty result or an error.
+pub async fn apply_metadata_changes(
+    main_db: &DatabaseConnection,
+    node_id: &str,
+    changes: &[MetadataChange],
+) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let mut file_ids: Vec<i32> = changes.iter().map(|change| change.file_id).collect();
+    file_ids.sort_unstable();
+    file_ids.dedup();
+
+    let files: HashMap<i32, media_files::Model> = get_files_by_ids(main_db, &file_ids)
+        .await?
+        .into_iter()
+        .map(|file| (file.id, file))
+        .collect();
+
+    let permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+
+    for change in changes {
+        let Some(file) = files.get(&change.file_id) else {
+            bail!("File {} does not exist", change.file_id);
+        };
+
+        let existing = media_metadata::Entity::find()
+            .filter(media_metadata::Column::FileId.eq(change.file_id))
+            .filter(media_metadata::Column::MetaKey.eq(change.key.as_str()))
+            .one(&txn)
+            .await?;
+
+        match existing {
+            Some(existing) => {
+                let ver = existing.updated_at_hlc_ver;
+                let mut active_model: media_metadata::ActiveModel = existing.into();
+                active_model.meta_value = ActiveValue::Set(change.new_value.clone());
+                active_model.updated_at_hlc_ts = ActiveValue::Set(Utc::now().to_rfc3339());
+                active_model.updated_at_hlc_ver = ActiveValue::Set(ver + 1);
+                active_model.updated_at_hlc_nid = ActiveValue::Set(node_id.to_owned());
+                active_model.update(&txn).await?;
+            }
+            None => {
+                media_metadata::ActiveModel {
+                    file_id: ActiveValue::Set(change.file_id),
+                    meta_key: ActiveValue::Set(change.key.clone()),
+                    meta_value: ActiveValue::Set(change.new_value.clone()),
+                    hlc_uuid: ActiveValue::Set(
+                        Uuid::new_v5(
+                            &Uuid::NAMESPACE_OID,
+                            format!("RUNE_METADATA::{}::{}", file.file_hash, change.key).as_bytes(),
+                        )
+                        .to_string(),
+                    ),
+                    created_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
+                    updated_at_hlc_ts: ActiveValue::Set(Utc::now().to_rfc3339()),
+                    created_at_hlc_ver: ActiveValue::Set(0),
+                    updated_at_hlc_ver: ActiveValue::Set(0),
+                    created_at_hlc_nid: ActiveValue::Set(node_id.to_owned()),
+                    updated_at_hlc_nid: ActiveValue::Set(node_id.to_owned()),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await?;
+            }
+        }
+
+        if change.key == "track_title" {
+            add_term(
+                &txn,
+                CollectionQueryType::Track,
+                change.file_id,
+                &change.new_value,
+            )
+            .await?;
+        }
+    }
+
+    txn.commit().await?;
+    // Indexing takes a permit for each file
+    drop(permit);
+
+    info!(
+        "Edited {} metadata values of {} files",
+        changes.len(),
+        file_ids.len()
+    );
+
+    index_media_files(main_db, node_id, file_ids, None).await
+}
+
+/// Key of a placeholder in a file name pattern. Short names follow what
+/// people write in patterns, the others are metadata keys.
+pub(crate) fn placeholder_key(name: &str) -> Option<&'static str> {
+    match name {
+        "title" => Some("track_title"),
+        "track" => Some("track_number"),
+        "disc" => Some("disc_number"),
+        "year" => Some("date"),
+        _ => EDITABLE_KEYS.iter().find(|key| **key == name).copied(),
+    }
+}
+
+/// Parses patterns like `{artist} - {title}` into a regex matching file
+/// names without their extension. Patterns with slashes, like
+/// `{artist}/{year} - {album}/{track} - {title}`, match the last directories
+/// of the path too.
+pub struct FileNamePattern {
+    regex: Regex,
+    keys: Vec<&'static str>,
+    /// The number of path components the pattern matches
+    depth: usize,
+}
+
+impl FileNamePattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut expression = String::from("^");
+        let mut keys = Vec::new();
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                bail!("Unclosed placeholder in {pattern}");
+            };
+            let name = &rest[start + 1..start + end];
+            let Some(key) = placeholder_key(name) else {
+                bail!("Unknown placeholder {{{name}}} in {pattern}");
+            };
+            if keys.contains(&key) {
+                bail!("Placeholder {{{name}}} is used twice in {pattern}");
+            }
+
+            expression.push_str(&regex::escape(&rest[..start]));
+            expression.push_str("([^/]+?)");
+            keys.push(key);
+            rest = &rest[start + end + 1..];
+        }
+        expression.push_str(&regex::escape(rest));
+        expression.push('$');
+
+        if keys.is_empty() {
+            bail!("No placeholder in {pattern}");
+        }
+
+        Ok(Self {
+            regex: Regex::new(&expression)?,
+            keys,
+            depth: pattern.matches('/').count() + 1,
+        })
+    }
+
+    /// Extracts metadata from a file name, or from a path with `/` as the
+    /// separator for patterns with directories. Returns `None` if it doesn't
+    /// match the pattern.
+    pub fn parse(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let components: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+        if components.len() < self.depth {
+            return None;
+        }
+        let (file_name, directories) = components[components.len() - self.depth..].split_last()?;
+
+        let stem = match file_name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => stem,
+            _ => file_name,
+        };
+        let mut subject = directories.join("/");
+        if !subject.is_empty() {
+            subject.push('/');
+        }
+        subject.push_str(stem);
+        let captures = self.regex.captures(&subject)?;
+
+        Some(
+            self.keys
+                .iter()
+                .zip(captures.iter().skip(1))
+                .filter_map(|(key, value)| {
+                    let value = value?.as_str().trim();
+                    (!value.is_empty()).then(|| (key.to_string(), value.to_owned()))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// What a pattern inferred from the path of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InferredMetadata {
+    pub file_id: i32,
+    /// The path of the file in the library, with `/` as the separator
+    pub path: String,
+    /// The parsed values, `None` if the path doesn't match the pattern
+    pub fields: Option<Vec<(String, String)>>,
+}
+
+/// Parses the paths of files with a pattern, without changing anything, so
+/// the results can be reviewed before they are applied.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `file_ids` - The files to parse the paths of.
+/// * `pattern` - The pattern to parse them with.
+///
+/// # Returns
+/// * `Result<Vec<InferredMetadata>>` - What was parsed for each file, in the
+///   order of `file_ids`, or an error if a file doesn't exist.
+pub async fn infer_metadata_from_paths(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+    pattern: &FileNamePattern,
+) -> Result<Vec<InferredMetadata>> {
+    let files = get_files_by_ids(main_db, file_ids).await?;
+
+    let mut inferred = Vec::with_capacity(file_ids.len());
+    for file_id in file_ids {
+        let Some(file) = files.iter().find(|file| file.id == *file_id) else {
+            bail!("File {file_id} does not exist");
+        };
+        let path = Path::new(&file.directory)
+            .join(&file.file_name)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        inferred.push(InferredMetadata {
+            file_id: *file_id,
+            fields: pattern.parse(&path),
+            path,
+        });
+    }
+
+    Ok(inferred)
+}
+
+/// How writing metadata changes to the tags of the files went.
+#[derive(Debug, Default)]
+pub struct TagWriteReport {
+    pub written: usize,
+    /// Files of formats tags can't be written to, with their format
+    pub unsupported: Vec<(i32, String)>,
+    /// Files tags could not be written to, with the error
+    pub failed: Vec<(i32, String)>,
+}
+
+/// Writes applied metadata changes to the tags of the files, recording the
+/// new modification time of each written file so the next scan doesn't take
+/// the write for an external change. A failing file doesn't stop the others.
+pub async fn write_changes_to_tags(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    changes: &[MetadataChange],
+) -> Result<TagWriteReport> {
+    let mut file_ids: Vec<i32> = changes.iter().map(|change| change.file_id).collect();
+    file_ids.sort_unstable();
+    file_ids.dedup();
+    let files = get_files_by_ids(main_db, &file_ids).await?;
+
+    let mut report = TagWriteReport::default();
+    for file in &files {
+        let fields: Vec<(String, String)> = changes
+            .iter()
+            .filter(|change| change.file_id == file.id)
+            .map(|change| (change.key.clone(), change.new_value.clone()))
+            .collect();
+
+        let path = lib_path.join(&file.directory).join(&file.file_name);
+        let result = match write_tags(fsio, &path, &fields).await {
+            Ok(TagWriteOutcome::Written { last_modified }) => {
+                set_file_last_modified(main_db, file.id, last_modified).await
+            }
+            Ok(TagWriteOutcome::Unchanged) => Ok(()),
+            Ok(TagWriteOutcome::NotSupported { format }) => {
+                report.unsupported.push((file.id, format));
+                continue;
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => report.written += 1,
+            Err(e) => report.failed.push((file.id, format!("{e:#}"))),
+        }
+    }
+
+    Ok(report)
+}
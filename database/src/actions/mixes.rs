@@ -19,7 +19,7 @@ use crate::actions::analysis::get_analyze_count;
 use crate::actions::analysis::get_percentile_analysis_result;
 use crate::actions::cover_art::get_magic_cover_art_id;
 use crate::actions::playback_queue::list_playback_queue;
-use crate::connection::{MainDbConnection, RecommendationDbConnection};
+use crate::connection::{MainDbConnection, RecommendationDbConnection, acquire_write_permit};
 use crate::entities::media_file_fingerprint;
 use crate::entities::media_file_genres;
 use crate::entities::{
@@ -144,7 +144,8 @@ impl CollectionQuery for mixes::Model {
             CollectionQueryListMode::Forward => {
                 mixes::Entity::find().limit(limit).all(main_db).await
             }
-            CollectionQueryListMode::Reverse => {
+            // Mixes have no files of their own
+            CollectionQueryListMode::Reverse | CollectionQueryListMode::RecentlyAdded => {
                 mixes::Entity::find()
                     .order_by_desc(mixes::Column::Id)
                     .limit(limit)
@@ -305,6 +306,7 @@ pub async fn replace_mix_queries(
 ) -> Result<()> {
     use mix_queries::Entity as MixQueryEntity;
 
+    let _permit = acquire_write_permit().await;
     let txn = main_db.begin().await?;
 
     // Get all existing records for the current mix_id
@@ -430,9 +432,12 @@ enum QueryOperator {
     SortDuration(bool),
     SortPlayedthrough(bool),
     SortSkipped(bool),
+    SortMostSkipped(bool),
     FilterLiked(bool),
+    FilterInProgress(bool),
     FilterWithCoverArt(bool),
     FilterAnalyzed(bool),
+    FilterAddedWithin(u32),
     PipeLimit(u64),
     PipeRecommend(i32),
     Unknown(String),
@@ -595,15 +600,24 @@ fn parse_query(query: &(String, String)) -> QueryOperator {
         "sort::skipped" => parse_parameter::<bool>(parameter, operator)
             .map(QueryOperator::SortSkipped)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "sort::most_skipped" => parse_parameter::<bool>(parameter, operator)
+            .map(QueryOperator::SortMostSkipped)
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
         "filter::liked" => parse_parameter::<bool>(parameter, operator)
             .map(QueryOperator::FilterLiked)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "filter::in_progress" => parse_parameter::<bool>(parameter, operator)
+            .map(QueryOperator::FilterInProgress)
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
         "filter::analyzed" => parse_parameter::<bool>(parameter, operator)
             .map(QueryOperator::FilterAnalyzed)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
         "filter::with_cover_art" => parse_parameter::<bool>(parameter, operator)
             .map(QueryOperator::FilterWithCoverArt)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
+        "filter::added_within" => parse_parameter::<u32>(parameter, operator)
+            .map(QueryOperator::FilterAddedWithin)
+            .unwrap_or(QueryOperator::Unknown(operator.clone())),
         "pipe::limit" => parse_parameter::<u64>(parameter, operator)
             .map(QueryOperator::PipeLimit)
             .unwrap_or(QueryOperator::Unknown(operator.clone())),
@@ -616,14 +630,12 @@ fn parse_query(query: &(String, String)) -> QueryOperator {
 
 fn apply_join_filter(
     query: Select<media_files::Entity>,
-    filter_liked: Option<bool>,
+    join_stats: bool,
     filter_analyzed: Option<bool>,
     sort_track_number: Option<bool>,
-    sort_playedthrough_asc: Option<bool>,
-    sort_skipped_asc: Option<bool>,
 ) -> Select<media_files::Entity> {
     let mut _query = query;
-    if filter_liked.is_some() || sort_playedthrough_asc.is_some() || sort_skipped_asc.is_some() {
+    if join_stats {
         _query = _query
             .join(
                 JoinType::LeftJoin,
@@ -666,20 +678,52 @@ macro_rules! apply_sorting_macro {
 
 // Macro to handle subquery filters
 macro_rules! add_subquery_filter {
-    ($or_condition:expr, $ids:expr, $entity:ty, $column:expr, $file_column:expr) => {
+    ($sources:expr, $operator:expr, $ids:expr, $entity:ty, $column:expr, $file_column:expr) => {
         if !$ids.is_empty() {
+            let label = format_stage($operator, &$ids);
             let subquery = <$entity>::find()
                 .select_only()
                 .filter($column.is_in($ids))
                 .column($file_column)
                 .into_query();
 
-            $or_condition =
-                $or_condition.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
+            $sources.push((
+                label,
+                Expr::cust("\"media_files\".\"id\"")
+                    .in_subquery(subquery)
+                    .into_condition(),
+            ));
         }
     };
 }
 
+/// How many tracks a stage of a mix query matched. Sources are counted
+/// alone, filters and pipes count what is left after them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixQueryStage {
+    pub stage: String,
+    pub matched: u64,
+}
+
+fn format_stage<T: ToString>(operator: &str, parameters: &[T]) -> String {
+    let parameters: Vec<String> = parameters.iter().map(|x| x.to_string()).collect();
+    format!("{operator}({})", parameters.join(", "))
+}
+
+async fn count_stage(
+    main_db: &DatabaseConnection,
+    stages: &mut Option<&mut Vec<MixQueryStage>>,
+    stage: String,
+    query: &Select<media_files::Entity>,
+) -> Result<()> {
+    if let Some(stages) = stages {
+        let matched = query.clone().count(main_db).await?;
+        stages.push(MixQueryStage { stage, matched });
+    }
+
+    Ok(())
+}
+
 fn sort_media_files(
     mut media_files: Vec<media_files::Model>,
     track_ids: &[i32],
@@ -711,6 +755,39 @@ pub async fn query_mix_media_files(
     queries: Vec<(String, String)>,
     cursor: usize,
     page_size: usize,
+) -> Result<Vec<media_files::Model>> {
+    query_mix_media_files_by_stage(main_db, recommend_db, queries, cursor, page_size, None).await
+}
+
+/// Like [`query_mix_media_files`] from the start, also returning how many
+/// tracks each stage of the query matched. Every stage costs a query.
+pub async fn explain_mix_media_files(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    queries: Vec<(String, String)>,
+    page_size: usize,
+) -> Result<(Vec<media_files::Model>, Vec<MixQueryStage>)> {
+    let mut stages = Vec::new();
+    let files = query_mix_media_files_by_stage(
+        main_db,
+        recommend_db,
+        queries,
+        0,
+        page_size,
+        Some(&mut stages),
+    )
+    .await?;
+
+    Ok((files, stages))
+}
+
+async fn query_mix_media_files_by_stage(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    queries: Vec<(String, String)>,
+    cursor: usize,
+    page_size: usize,
+    mut stages: Option<&mut Vec<MixQueryStage>>,
 ) -> Result<Vec<media_files::Model>> {
     let mut all: bool = false;
 
@@ -729,10 +806,13 @@ pub async fn query_mix_media_files(
     let mut sort_duration_asc: Option<bool> = None;
     let mut sort_playedthrough_asc: Option<bool> = None;
     let mut sort_skipped_asc: Option<bool> = None;
+    let mut sort_most_skipped: Option<bool> = None;
 
     let mut filter_liked: Option<bool> = None;
+    let mut filter_in_progress: Option<bool> = None;
     let mut filter_cover_art: Option<bool> = None;
     let mut filter_analyzed: Option<bool> = None;
+    let mut filter_added_within: Option<u32> = None;
     let mut pipe_limit: Option<u64> = None;
     let mut pipe_recommend: Option<i32> = None;
 
@@ -753,9 +833,12 @@ pub async fn query_mix_media_files(
             QueryOperator::SortDuration(asc) => sort_duration_asc = Some(asc),
             QueryOperator::SortPlayedthrough(asc) => sort_playedthrough_asc = Some(asc),
             QueryOperator::SortSkipped(asc) => sort_skipped_asc = Some(asc),
+            QueryOperator::SortMostSkipped(most) => sort_most_skipped = Some(most),
             QueryOperator::FilterLiked(liked) => filter_liked = Some(liked),
+            QueryOperator::FilterInProgress(in_progress) => filter_in_progress = Some(in_progress),
             QueryOperator::FilterWithCoverArt(cover_art) => filter_cover_art = Some(cover_art),
             QueryOperator::FilterAnalyzed(analyzed) => filter_analyzed = Some(analyzed),
+            QueryOperator::FilterAddedWithin(days) => filter_added_within = Some(days),
             QueryOperator::PipeLimit(limit) => pipe_limit = Some(limit),
             QueryOperator::PipeRecommend(recommend) => pipe_recommend = Some(recommend),
             QueryOperator::Unknown(op) => warn!("Unknown operator: {op}"),
@@ -766,7 +849,15 @@ pub async fn query_mix_media_files(
         return Ok([].to_vec());
     }
 
-    if pipe_recommend.is_some() && get_analyze_count(main_db).await? < 1 {
+    if let Some(recommend_group) = pipe_recommend
+        && get_analyze_count(main_db).await? < 1
+    {
+        if let Some(stages) = stages.as_mut() {
+            stages.push(MixQueryStage {
+                stage: format_stage("pipe::recommend", &[recommend_group]),
+                matched: 0,
+            });
+        }
         return Ok([].to_vec());
     }
 
@@ -800,13 +891,15 @@ pub async fn query_mix_media_files(
                 .add(media_file_fingerprint::Column::IsDuplicated.is_null())
                 .add(media_file_fingerprint::Column::IsDuplicated.ne(1)),
         );
+    count_stage(main_db, &mut stages, "library".to_owned(), &query).await?;
 
-    // Create an OR condition to hold all the subconditions
-    let mut or_condition = Condition::any();
+    // The sources of the tracks, along with the operators they come from
+    let mut sources: Vec<(String, Condition)> = vec![];
 
     // Filter by artist_ids if provided
     add_subquery_filter!(
-        or_condition,
+        sources,
+        "lib::artist",
         artist_ids,
         media_file_artists::Entity,
         media_file_artists::Column::ArtistId,
@@ -815,7 +908,8 @@ pub async fn query_mix_media_files(
 
     // Filter by album_ids if provided
     add_subquery_filter!(
-        or_condition,
+        sources,
+        "lib::album",
         album_ids,
         media_file_albums::Entity,
         media_file_albums::Column::AlbumId,
@@ -824,7 +918,8 @@ pub async fn query_mix_media_files(
 
     // Filter by genres_ids if provided
     add_subquery_filter!(
-        or_condition,
+        sources,
+        "lib::genre",
         genre_ids,
         media_file_genres::Entity,
         media_file_genres::Column::GenreId,
@@ -833,7 +928,8 @@ pub async fn query_mix_media_files(
 
     // Filter by playlist_ids if provided
     add_subquery_filter!(
-        or_condition,
+        sources,
+        "lib::playlist",
         playlist_ids.clone(),
         media_file_playlists::Entity,
         media_file_playlists::Column::PlaylistId,
@@ -848,11 +944,17 @@ pub async fn query_mix_media_files(
             .column(media_files::Column::Id)
             .into_query();
 
-        or_condition = or_condition.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
+        sources.push((
+            format_stage("lib::track", &track_ids),
+            Expr::cust("\"media_files\".\"id\"")
+                .in_subquery(subquery)
+                .into_condition(),
+        ));
     }
 
     // Filter by directories if provided
     if !directories_deep.is_empty() {
+        let label = format_stage("lib::directory.deep", &directories_deep);
         let mut dir_conditions = Condition::any();
         for dir in directories_deep {
             let dir = dir.strip_prefix('/').unwrap_or(&dir);
@@ -863,18 +965,19 @@ pub async fn query_mix_media_files(
                     .or(Expr::col(media_files::Column::Directory).like(format!("{dir}/%"))),
             );
         }
-        or_condition = or_condition.add(dir_conditions);
+        sources.push((label, dir_conditions));
     }
 
     // Filter by directories if provided
     if !directories_shallow.is_empty() {
+        let label = format_stage("lib::directory.shallow", &directories_shallow);
         let mut dir_conditions = Condition::any();
         for dir in directories_shallow {
             let dir = dir.strip_prefix('/').unwrap_or(&dir);
 
             dir_conditions = dir_conditions.add(Expr::col(media_files::Column::Directory).eq(dir));
         }
-        or_condition = or_condition.add(dir_conditions);
+        sources.push((label, dir_conditions));
     }
 
     // Filter by random tracks if provided
@@ -886,7 +989,12 @@ pub async fn query_mix_media_files(
             .column(media_files::Column::Id)
             .into_query();
 
-        or_condition = or_condition.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
+        sources.push((
+            format_stage("lib::random", &random_count),
+            Expr::cust("\"media_files\".\"id\"")
+                .in_subquery(subquery)
+                .into_condition(),
+        ));
     }
 
     if let Some(queue_enabled) = playback_queue
@@ -900,69 +1008,132 @@ pub async fn query_mix_media_files(
             .column(media_files::Column::Id)
             .into_query();
 
-        or_condition = or_condition.add(Expr::cust("\"media_files\".\"id\"").in_subquery(subquery));
+        sources.push((
+            "lib::queue(true)".to_owned(),
+            Expr::cust("\"media_files\".\"id\"")
+                .in_subquery(subquery)
+                .into_condition(),
+        ));
+    }
+
+    // Create an OR condition to hold all the subconditions
+    let mut or_condition = Condition::any();
+    for (stage, condition) in sources {
+        count_stage(
+            main_db,
+            &mut stages,
+            stage,
+            &query.clone().filter(condition.clone()),
+        )
+        .await?;
+        or_condition = or_condition.add(condition);
+    }
+
+    if all {
+        count_stage(main_db, &mut stages, "lib::all(true)".to_owned(), &query).await?;
+    } else {
+        query = query.filter(or_condition);
+        count_stage(main_db, &mut stages, "any source".to_owned(), &query).await?;
     }
 
-    let has_liked = filter_liked.is_some();
-    let has_cover_art = filter_cover_art.is_some();
-    let has_analyzed = filter_analyzed.is_some();
+    let mut filters: Vec<(String, Condition)> = vec![];
 
-    if has_liked || has_cover_art || has_analyzed {
-        let mut filter = Condition::all();
+    if let Some(liked) = filter_liked {
+        filters.push((
+            format_stage("filter::liked", &[liked]),
+            media_file_stats::Column::Liked.eq(liked).into_condition(),
+        ));
+    }
 
-        if !all {
-            filter = filter.add(or_condition);
-        }
+    if let Some(in_progress) = filter_in_progress {
+        // Files listened to the end have no position left
+        let condition = if in_progress {
+            media_file_stats::Column::LastPositionSeconds
+                .gt(0.0)
+                .into_condition()
+        } else {
+            Condition::any()
+                .add(media_file_stats::Column::LastPositionSeconds.is_null())
+                .add(media_file_stats::Column::LastPositionSeconds.lte(0.0))
+        };
+        filters.push((
+            format_stage("filter::in_progress", &[in_progress]),
+            condition,
+        ));
+    }
 
-        if let Some(liked) = filter_liked {
-            filter = filter.add(media_file_stats::Column::Liked.eq(liked));
-        }
+    if let Some(analyzed) = filter_analyzed {
+        let condition = if analyzed {
+            media_analysis::Column::Id.is_not_null()
+        } else {
+            media_analysis::Column::Id.is_null()
+        };
+        filters.push((
+            format_stage("filter::analyzed", &[analyzed]),
+            condition.into_condition(),
+        ));
+    }
+
+    if let Some(cover_art) = filter_cover_art {
+        let magic_cover_art_id = get_magic_cover_art_id(main_db).await;
+
+        let condition = if cover_art {
+            let mut condition = Condition::all();
+            condition = condition.add(media_files::Column::CoverArtId.is_not_null());
 
-        if let Some(analyzed) = filter_analyzed {
-            if analyzed {
-                filter = filter.add(media_analysis::Column::Id.is_not_null());
-            } else {
-                filter = filter.add(media_analysis::Column::Id.is_null());
+            if let Some(magic_cover_art_id) = magic_cover_art_id {
+                condition = condition.add(media_files::Column::CoverArtId.ne(magic_cover_art_id));
             }
-        }
+            condition
+        } else {
+            let mut condition = Condition::any();
+            condition = condition.add(media_files::Column::CoverArtId.is_null());
 
-        if let Some(cover_art) = filter_cover_art {
-            let magic_cover_art_id = get_magic_cover_art_id(main_db).await;
-
-            if cover_art {
-                let mut condition = Condition::all();
-                condition = condition.add(media_files::Column::CoverArtId.is_not_null());
-
-                if let Some(magic_cover_art_id) = magic_cover_art_id {
-                    condition =
-                        condition.add(media_files::Column::CoverArtId.ne(magic_cover_art_id));
-                }
-                filter = filter.add(condition);
-            } else {
-                let mut condition = Condition::any();
-                condition = condition.add(media_files::Column::CoverArtId.is_null());
-
-                if let Some(magic_cover_art_id) = magic_cover_art_id {
-                    condition =
-                        condition.add(media_files::Column::CoverArtId.eq(magic_cover_art_id));
-                }
-                filter = filter.add(condition);
+            if let Some(magic_cover_art_id) = magic_cover_art_id {
+                condition = condition.add(media_files::Column::CoverArtId.eq(magic_cover_art_id));
             }
-        }
+            condition
+        };
+        filters.push((
+            format_stage("filter::with_cover_art", &[cover_art]),
+            condition,
+        ));
+    }
 
-        query = query.filter(filter);
-    } else if !all {
-        query = query.filter(or_condition);
+    if let Some(days) = filter_added_within {
+        // RFC 3339 timestamps in UTC sort like the time they stand for
+        let since = (Utc::now() - chrono::Duration::days(days.into())).to_rfc3339();
+        filters.push((
+            format_stage("filter::added_within", &[days]),
+            media_files::Column::FirstSeenAt.gte(since).into_condition(),
+        ));
+    }
+
+    for (stage, condition) in filters {
+        query = query.filter(condition);
+
+        if stages.is_some() {
+            // The filters need the joins to be counted
+            let joined = apply_join_filter(
+                query.clone(),
+                filter_liked.is_some() || filter_in_progress.is_some(),
+                filter_analyzed,
+                None,
+            );
+            count_stage(main_db, &mut stages, stage, &joined).await?;
+        }
     }
 
     // Join with media_file_stats table for sorting by playedthrough and skipped, and filtering by liked
     query = apply_join_filter(
         query,
-        filter_liked,
+        filter_liked.is_some()
+            || filter_in_progress.is_some()
+            || sort_playedthrough_asc.is_some()
+            || sort_skipped_asc.is_some()
+            || sort_most_skipped.is_some(),
         filter_analyzed,
         sort_track_number_asc,
-        sort_playedthrough_asc,
-        sort_skipped_asc,
     );
 
     if only_one_playlist {
@@ -1010,6 +1181,13 @@ pub async fn query_mix_media_files(
             );
         }
 
+        if let Some(most) = sort_most_skipped {
+            let order = if most { Order::Desc } else { Order::Asc };
+            query = query
+                .order_by(media_file_stats::Column::Skipped, order.clone())
+                .order_by(media_file_stats::Column::LastSkippedAt, order);
+        }
+
         if let Some(query_limit) = pipe_limit {
             query = query.limit(query_limit);
         }
@@ -1023,6 +1201,15 @@ pub async fn query_mix_media_files(
             .await
             .with_context(|| "Failed to query file ids for recommendation")?;
 
+        if let Some(stages) = stages.as_mut()
+            && let Some(query_limit) = pipe_limit
+        {
+            stages.push(MixQueryStage {
+                stage: format_stage("pipe::limit", &[query_limit]),
+                matched: candidate_file_ids.len() as u64,
+            });
+        }
+
         if candidate_file_ids.is_empty() {
             return Ok([].to_vec());
         }
@@ -1069,6 +1256,13 @@ pub async fn query_mix_media_files(
             .filter_map(|id| file_map.get(&id).cloned())
             .collect::<Vec<_>>();
 
+        if let Some(stages) = stages.as_mut() {
+            stages.push(MixQueryStage {
+                stage: format_stage("pipe::recommend", &[recommend_group]),
+                matched: files_by_recommendation.len() as u64,
+            });
+        }
+
         let sorted_files = sort_media_files(files_by_recommendation, &track_ids);
 
         return Ok(sorted_files);
@@ -1113,6 +1307,24 @@ pub async fn query_mix_media_files(
         );
     }
 
+    // Ties are broken by the time of the last skip
+    if let Some(most) = sort_most_skipped {
+        let order = if most { Order::Desc } else { Order::Asc };
+        query = query
+            .order_by(media_file_stats::Column::Skipped, order.clone())
+            .order_by(media_file_stats::Column::LastSkippedAt, order);
+    }
+
+    if let Some(stages) = stages.as_mut()
+        && let Some(limit) = pipe_limit
+    {
+        let matched = stages.last().map(|x| x.matched).unwrap_or_default();
+        stages.push(MixQueryStage {
+            stage: format_stage("pipe::limit", &[limit]),
+            matched: matched.min(limit),
+        });
+    }
+
     if let Some(limit) = pipe_limit
         && cursor as u64 >= limit
     {
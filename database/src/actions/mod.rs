@@ -1,20 +1,39 @@
 pub mod albums;
 pub mod analysis;
 pub mod artists;
+pub mod audit_log;
+pub mod case_fold;
 pub mod collection;
+pub mod content_match;
 pub mod cover_art;
+pub mod dedup;
 pub mod directory;
 pub mod file;
 pub mod fingerprint;
 pub mod genres;
 pub mod index;
 pub mod library;
+pub mod library_export;
+pub mod library_import;
+pub mod library_settings;
+pub mod library_stats;
 pub mod logging;
+pub mod loudness;
+pub mod lyrics;
+pub mod maintenance;
 pub mod metadata;
+pub mod metadata_edit;
 pub mod mixes;
+pub mod musicbrainz;
+pub mod organize;
 pub mod playback_queue;
 pub mod playlists;
 pub mod recommendation;
+pub mod schema;
+pub mod scrobble_history;
+pub mod scrobble_queue;
 pub mod search;
 pub mod stats;
+pub mod tag_normalize;
 pub mod utils;
+pub mod verify;
@@ -0,0 +1,273 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, bail};
+use chrono::Utc;
+use log::info;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{ActiveValue, DatabaseConnection, TransactionTrait, prelude::*};
+
+use ::tag_editor::music_brainz::release::{MusicBrainzRelease, ReleaseTrack};
+
+use crate::actions::{
+    file::get_files_by_ids,
+    metadata_edit::{MetadataChange, apply_metadata_changes, diff_metadata, get_editable_metadata},
+};
+use crate::connection::acquire_write_permit;
+use crate::entities::media_files;
+
+/// The lowest score a local track and a release track are mapped at.
+const MIN_MAPPING_SCORE: f64 = 0.5;
+/// Durations this close, in seconds, count as equal.
+const DURATION_TOLERANCE: f64 = 2.0;
+/// Durations this far apart, in seconds, don't count at all.
+const DURATION_LIMIT: f64 = 20.0;
+
+/// What a local track is matched with a release track by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTrack {
+    pub file_id: i32,
+    pub title: Option<String>,
+    pub disc_number: Option<u32>,
+    pub track_number: Option<u32>,
+    /// In seconds
+    pub duration: Option<f64>,
+}
+
+/// A local track and the index of the release track it's mapped to, `None`
+/// when no release track is close enough.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackMapping {
+    pub file_id: i32,
+    pub track_index: Option<usize>,
+    /// From 0 to 1, 0 for unmapped tracks
+    pub score: f64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReleaseApplyOutcome {
+    pub changes: Vec<MetadataChange>,
+    /// Tracks which were left untouched
+    pub unmapped_file_ids: Vec<i32>,
+}
+
+/// Parses numbers like `3` or `3/12`.
+fn parse_number(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Reads what the tracks are matched by, in the order of `file_ids`.
+pub async fn get_local_tracks(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+) -> Result<Vec<LocalTrack>> {
+    let files = get_files_by_ids(main_db, file_ids).await?;
+    let metadata = get_editable_metadata(main_db, file_ids).await?;
+    let empty = HashMap::new();
+
+    let mut tracks = Vec::with_capacity(file_ids.len());
+    for file_id in file_ids {
+        let Some(file) = files.iter().find(|file| file.id == *file_id) else {
+            bail!("File {file_id} does not exist");
+        };
+        let metadata = metadata.get(file_id).unwrap_or(&empty);
+
+        tracks.push(LocalTrack {
+            file_id: *file_id,
+            title: metadata.get("track_title").cloned(),
+            disc_number: metadata.get("disc_number").and_then(|x| parse_number(x)),
+            track_number: metadata.get("track_number").and_then(|x| parse_number(x)),
+            duration: file.duration.to_f64().filter(|x| *x > 0.0),
+        });
+    }
+
+    Ok(tracks)
+}
+
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|x| x.is_alphanumeric() || x.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// How likely a local track is the release track, from its position, its
+/// duration and its title.
+fn mapping_score(local: &LocalTrack, track: &ReleaseTrack) -> f64 {
+    let position = match (local.disc_number, local.track_number) {
+        (Some(disc), Some(number)) if disc == track.disc_number => {
+            if number == track.track_number {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        (None, Some(number)) if number == track.track_number => 0.8,
+        _ => 0.0,
+    };
+
+    let duration = match (local.duration, track.length) {
+        (Some(a), Some(b)) => {
+            let difference = (a - b).abs();
+            ((DURATION_LIMIT - difference) / (DURATION_LIMIT - DURATION_TOLERANCE)).clamp(0.0, 1.0)
+        }
+        // Unknown either way
+        _ => 0.5,
+    };
+
+    let title = match &local.title {
+        Some(title) => {
+            strsim::normalized_levenshtein(&normalize_title(title), &normalize_title(&track.title))
+        }
+        None => 0.0,
+    };
+
+    0.3 * position + 0.3 * duration + 0.4 * title
+}
+
+/// Proposes which release track each local track is, best matches first,
+/// each release track being used once. The result follows the order of
+/// `local`.
+pub fn propose_mapping(local: &[LocalTrack], release: &MusicBrainzRelease) -> Vec<TrackMapping> {
+    let mut scores: Vec<(f64, usize, usize)> = Vec::new();
+    for (local_index, local_track) in local.iter().enumerate() {
+        for (track_index, track) in release.tracks.iter().enumerate() {
+            let score = mapping_score(local_track, track);
+            if score >= MIN_MAPPING_SCORE {
+                scores.push((score, local_index, track_index));
+            }
+        }
+    }
+    scores.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut mapping: Vec<TrackMapping> = local
+        .iter()
+        .map(|x| TrackMapping {
+            file_id: x.file_id,
+            track_index: None,
+            score: 0.0,
+        })
+        .collect();
+    let mut used_tracks = HashSet::new();
+    for (score, local_index, track_index) in scores {
+        if mapping[local_index].track_index.is_some() || used_tracks.contains(&track_index) {
+            continue;
+        }
+
+        used_tracks.insert(track_index);
+        mapping[local_index].track_index = Some(track_index);
+        mapping[local_index].score = score;
+    }
+
+    mapping
+}
+
+/// The metadata a release gives one of its tracks.
+pub fn release_track_fields(
+    release: &MusicBrainzRelease,
+    track: &ReleaseTrack,
+) -> Vec<(String, String)> {
+    let mut fields = vec![
+        ("track_title".to_owned(), track.title.clone()),
+        ("artist".to_owned(), track.artist.clone()),
+        ("album".to_owned(), release.title.clone()),
+        ("album_artist".to_owned(), release.artist.clone()),
+        (
+            "track_number".to_owned(),
+            format!("{}/{}", track.track_number, track.track_count),
+        ),
+        (
+            "disc_number".to_owned(),
+            format!("{}/{}", track.disc_number, track.disc_count),
+        ),
+    ];
+    if let Some(year) = release.year() {
+        fields.push(("date".to_owned(), year.to_owned()));
+    }
+
+    fields
+}
+
+/// Applies a reviewed mapping: mapped tracks take the metadata and the
+/// MusicBrainz IDs of their release track, unmapped ones are left untouched.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `node_id` - The id of the client that triggers the operation.
+/// * `release` - The release the mapping was made for.
+/// * `mapping` - The release track index of each file, if any.
+///
+/// # Returns
+/// * `Result<ReleaseApplyOutcome>` - The metadata changes and the unmapped
+///   tracks, or an error if the mapping doesn't fit the release.
+pub async fn apply_release_mapping(
+    main_db: &DatabaseConnection,
+    node_id: &str,
+    release: &MusicBrainzRelease,
+    mapping: &[(i32, Option<usize>)],
+) -> Result<ReleaseApplyOutcome> {
+    let mut outcome = ReleaseApplyOutcome::default();
+    let mut used_tracks = HashSet::new();
+    let mut mapped = Vec::new();
+    for (file_id, track_index) in mapping {
+        let Some(track_index) = track_index else {
+            outcome.unmapped_file_ids.push(*file_id);
+            continue;
+        };
+        let Some(track) = release.tracks.get(*track_index) else {
+            bail!("Release {} has no track {track_index}", release.id);
+        };
+        if !used_tracks.insert(*track_index) {
+            bail!(
+                "Track {track_index} of release {} is mapped twice",
+                release.id
+            );
+        }
+
+        mapped.push((*file_id, track));
+    }
+
+    let edits: Vec<(i32, Vec<(String, String)>)> = mapped
+        .iter()
+        .map(|(file_id, track)| (*file_id, release_track_fields(release, track)))
+        .collect();
+    outcome.changes = diff_metadata(main_db, &edits).await?;
+    apply_metadata_changes(main_db, node_id, &outcome.changes).await?;
+
+    let file_ids: Vec<i32> = mapped.iter().map(|(file_id, _)| *file_id).collect();
+    let files = get_files_by_ids(main_db, &file_ids).await?;
+
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+    for file in files {
+        let Some((_, track)) = mapped.iter().find(|(file_id, _)| *file_id == file.id) else {
+            continue;
+        };
+
+        let ver = file.updated_at_hlc_ver;
+        let mut active_model: media_files::ActiveModel = file.into();
+        active_model.musicbrainz_recording_id = ActiveValue::Set(Some(track.recording_id.clone()));
+        active_model.musicbrainz_track_id = ActiveValue::Set(Some(track.id.clone()));
+        active_model.musicbrainz_release_id = ActiveValue::Set(Some(release.id.clone()));
+        active_model.musicbrainz_release_group_id =
+            ActiveValue::Set(release.release_group_id.clone());
+        active_model.musicbrainz_artist_ids = ActiveValue::Set(Some(track.artist_ids.join(";")));
+        active_model.updated_at_hlc_ts = ActiveValue::Set(Utc::now().to_rfc3339());
+        active_model.updated_at_hlc_ver = ActiveValue::Set(ver + 1);
+        active_model.updated_at_hlc_nid = ActiveValue::Set(node_id.to_owned());
+        active_model.update(&txn).await?;
+    }
+    txn.commit().await?;
+
+    info!(
+        "Applied release {} to {} tracks, {} left unmapped",
+        release.id,
+        mapped.len(),
+        outcome.unmapped_file_ids.len()
+    );
+
+    Ok(outcome)
+}
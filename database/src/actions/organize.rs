@@ -0,0 +1,487 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use caseless::default_case_fold_str;
+use log::{info, warn};
+use sea_orm::{ActiveValue, DatabaseConnection, TransactionTrait, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use ::fsio::FsIo;
+
+use crate::actions::{
+    file::get_files_by_ids,
+    metadata_edit::{get_editable_metadata, placeholder_key},
+};
+use crate::connection::acquire_write_permit;
+use crate::entities::media_files;
+
+/// Names longer than this are cut, leaving room for a collision suffix
+/// below the 255 bytes most file systems allow.
+const MAX_COMPONENT_LENGTH: usize = 240;
+
+/// Names Windows reserves for devices, whatever the extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    /// A metadata key, `year` or `ext`, zero-padded to `width`
+    Field {
+        key: &'static str,
+        width: usize,
+    },
+}
+
+/// Parses templates like `{album_artist}/{year} - {album}/{track:02} {title}.{ext}`
+/// into the path of a file relative to the library root. `.{ext}` is
+/// appended when the template doesn't place the extension.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    template: String,
+    components: Vec<Vec<Segment>>,
+}
+
+impl PathTemplate {
+    pub fn new(template: &str) -> Result<Self> {
+        if template.starts_with('/') || template.contains('\\') {
+            bail!("{template} must be relative to the library, with / separators");
+        }
+
+        let mut components = Vec::new();
+        for component in template.split('/') {
+            if component.is_empty() || component == "." || component == ".." {
+                bail!("{template} has an empty, . or .. component, it can't leave the library");
+            }
+            components.push(parse_component(template, component)?);
+        }
+
+        let has_ext = components.last().is_some_and(|x| {
+            x.iter()
+                .any(|segment| matches!(segment, Segment::Field { key: "ext", .. }))
+        });
+        if !has_ext && let Some(last) = components.last_mut() {
+            last.push(Segment::Text(".".to_owned()));
+            last.push(Segment::Field {
+                key: "ext",
+                width: 0,
+            });
+        }
+
+        Ok(Self {
+            template: template.to_owned(),
+            components,
+        })
+    }
+
+    /// The path of a file, relative to the library root, from its metadata.
+    /// Missing values are replaced, so every file gets a path.
+    pub fn render(&self, file: &media_files::Model, metadata: &HashMap<String, String>) -> PathBuf {
+        let mut path = PathBuf::new();
+        for component in &self.components {
+            let mut name = String::new();
+            for segment in component {
+                match segment {
+                    Segment::Text(text) => name.push_str(text),
+                    Segment::Field { key, width } => {
+                        name.push_str(&field_value(file, metadata, key, *width))
+                    }
+                }
+            }
+            path.push(sanitize_component(&name));
+        }
+
+        path
+    }
+}
+
+impl std::fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+fn parse_component(template: &str, component: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = component;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            bail!("Unclosed placeholder in {template}");
+        };
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_owned()));
+        }
+
+        let placeholder = &rest[start + 1..start + end];
+        let (name, width) = match placeholder.split_once(':') {
+            Some((name, format)) => {
+                let width = format
+                    .strip_prefix('0')
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .with_context(|| {
+                        format!("Invalid format {{{placeholder}}} in {template}, expected :0N")
+                    })?;
+                (name, width)
+            }
+            None => (placeholder, 0),
+        };
+        let key = match name {
+            "ext" => "ext",
+            "year" => "year",
+            _ => placeholder_key(name)
+                .with_context(|| format!("Unknown placeholder {{{name}}} in {template}"))?,
+        };
+
+        segments.push(Segment::Field { key, width });
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_owned()));
+    }
+
+    Ok(segments)
+}
+
+fn field_value(
+    file: &media_files::Model,
+    metadata: &HashMap<String, String>,
+    key: &str,
+    width: usize,
+) -> String {
+    let get = |key: &str| {
+        metadata
+            .get(key)
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+    };
+
+    let value = match key {
+        "ext" => file.extension.to_lowercase(),
+        "year" => match get("date") {
+            Some(date) => date.chars().take(4).collect(),
+            None => "Unknown Year".to_owned(),
+        },
+        // `3/12` is the third track of twelve
+        "track_number" | "disc_number" => get(key)
+            .and_then(|x| x.split('/').next())
+            .map(|x| x.trim().to_owned())
+            .unwrap_or_else(|| "0".to_owned()),
+        "track_title" => match get(key) {
+            Some(title) => title.to_owned(),
+            None => match file.file_name.rsplit_once('.') {
+                Some((stem, _)) if !stem.is_empty() => stem.to_owned(),
+                _ => file.file_name.clone(),
+            },
+        },
+        "album_artist" => get(key)
+            .or_else(|| get("artist"))
+            .unwrap_or("Unknown Artist")
+            .to_owned(),
+        "artist" => get(key).unwrap_or("Unknown Artist").to_owned(),
+        "album" => get(key).unwrap_or("Unknown Album").to_owned(),
+        "genre" => get(key).unwrap_or("Unknown Genre").to_owned(),
+        _ => get(key).unwrap_or("Unknown").to_owned(),
+    };
+
+    if width > 0 && value.chars().all(|x| x.is_ascii_digit()) {
+        format!("{value:0>width$}")
+    } else {
+        value
+    }
+}
+
+/// Replaces what the platform doesn't allow in a file name. Backslashes are
+/// replaced everywhere, the scanner reads them as separators.
+pub fn sanitize_component(name: &str) -> String {
+    let strict = cfg!(any(windows, target_os = "android"));
+    let illegal = |x: char| {
+        x == '/'
+            || x == '\\'
+            || x.is_control()
+            || (strict && matches!(x, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+            || (cfg!(target_os = "macos") && x == ':')
+    };
+
+    let mut name: String = name
+        .trim()
+        .chars()
+        .map(|x| if illegal(x) { '_' } else { x })
+        .collect();
+    if strict {
+        // Windows drops trailing dots and spaces
+        let len = name.trim_end_matches(['.', ' ']).len();
+        name.truncate(len);
+
+        let stem = name.split('.').next().unwrap_or_default();
+        if RESERVED_NAMES.iter().any(|x| x.eq_ignore_ascii_case(stem)) {
+            let len = stem.len();
+            name.insert(len, '_');
+        }
+    }
+
+    if name.len() > MAX_COMPONENT_LENGTH {
+        // Cut the stem, the extension is needed to play the file
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() && ext.len() < 16 => (stem, Some(ext)),
+            _ => (name.as_str(), None),
+        };
+        let mut end = MAX_COMPONENT_LENGTH - ext.map_or(0, |x| x.len() + 1);
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        name = match ext {
+            Some(ext) => format!("{}.{ext}", stem[..end].trim_end()),
+            None => stem[..end].trim_end().to_owned(),
+        };
+    }
+
+    if name.is_empty() || name == "." || name == ".." {
+        return "_".to_owned();
+    }
+
+    name
+}
+
+/// Whether `path` is relative and stays below the directory it's joined to.
+fn is_inside_library(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path.components().all(|x| matches!(x, Component::Normal(_)))
+}
+
+/// Adds ` (2)`, ` (3)`... to the stem of a file name.
+fn numbered(path: &Path, number: usize) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem} ({number}).{ext}"),
+        _ => format!("{file_name} ({number})"),
+    };
+
+    path.with_file_name(file_name)
+}
+
+fn fold_path(path: &Path) -> String {
+    default_case_fold_str(&path.to_string_lossy())
+}
+
+/// A file to move, with paths relative to the library root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMove {
+    pub file_id: i32,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// The moves organizing files after a template, which can be saved and
+/// applied later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrganizePlan {
+    pub template: String,
+    pub moves: Vec<FileMove>,
+    /// Files already where the template puts them
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OrganizeOutcome {
+    pub moved: usize,
+    /// Files which changed since the plan was made, or whose destination
+    /// was taken in the meantime
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Plans where a template puts files, without touching them. Destinations
+/// taken by another file, on disk or in the plan, get a numbered suffix.
+/// Case is ignored when comparing paths, in case the file system does.
+pub async fn plan_organize(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    file_ids: &[i32],
+    template: &PathTemplate,
+) -> Result<OrganizePlan> {
+    let files = get_files_by_ids(main_db, file_ids).await?;
+    let metadata = get_editable_metadata(main_db, file_ids).await?;
+    let empty = HashMap::new();
+
+    let mut taken = HashSet::new();
+    let mut moves = Vec::new();
+    let mut unchanged = 0;
+    for file_id in file_ids {
+        let Some(file) = files.iter().find(|file| file.id == *file_id) else {
+            bail!("File {file_id} does not exist");
+        };
+
+        let from = Path::new(&file.directory).join(&file.file_name);
+        let to = template.render(file, metadata.get(file_id).unwrap_or(&empty));
+        if to == from {
+            taken.insert(fold_path(&to));
+            unchanged += 1;
+            continue;
+        }
+
+        let mut destination = to.clone();
+        let mut number = 1;
+        loop {
+            let key = fold_path(&destination);
+            // A file can take its own path with another case
+            let is_self = key == fold_path(&from);
+            if !taken.contains(&key) && (is_self || !fsio.exists(&lib_path.join(&destination))?) {
+                taken.insert(key);
+                break;
+            }
+            number += 1;
+            destination = numbered(&to, number);
+        }
+
+        moves.push(FileMove {
+            file_id: *file_id,
+            from,
+            to: destination,
+        });
+    }
+
+    Ok(OrganizePlan {
+        template: template.to_string(),
+        moves,
+        unchanged,
+    })
+}
+
+/// Moves the files of a plan. The path of each file is updated in a
+/// transaction committed once the file is moved, so a failed move leaves
+/// both as they were. Directories emptied by the moves are removed.
+pub async fn apply_organize_plan(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    plan: &OrganizePlan,
+) -> Result<OrganizeOutcome> {
+    let mut outcome = OrganizeOutcome::default();
+
+    let file_ids: Vec<i32> = plan.moves.iter().map(|x| x.file_id).collect();
+    let files: HashMap<i32, media_files::Model> = get_files_by_ids(main_db, &file_ids)
+        .await?
+        .into_iter()
+        .map(|file| (file.id, file))
+        .collect();
+
+    let mut emptied = HashSet::new();
+    for file_move in &plan.moves {
+        if !is_inside_library(&file_move.from) || !is_inside_library(&file_move.to) {
+            bail!(
+                "Refusing to move {} to {}, outside of the library",
+                file_move.from.display(),
+                file_move.to.display()
+            );
+        }
+
+        let Some(file) = files
+            .get(&file_move.file_id)
+            .filter(|x| Path::new(&x.directory).join(&x.file_name) == file_move.from)
+        else {
+            warn!(
+                "{} changed since the plan was made",
+                file_move.from.display()
+            );
+            outcome.skipped += 1;
+            continue;
+        };
+
+        let from = lib_path.join(&file_move.from);
+        let to = lib_path.join(&file_move.to);
+        let is_self = fold_path(&file_move.from) == fold_path(&file_move.to);
+        if !is_self && fsio.exists(&to)? {
+            warn!("{} was taken since the plan was made", to.display());
+            outcome.skipped += 1;
+            continue;
+        }
+
+        let directory = file_move
+            .to
+            .parent()
+            .map(|x| x.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let Some(file_name) = file_move.to.file_name().and_then(|x| x.to_str()) else {
+            bail!("Invalid file name: {}", file_move.to.display());
+        };
+
+        let _permit = acquire_write_permit().await;
+        let txn = main_db.begin().await?;
+        let mut active_model: media_files::ActiveModel = file.clone().into();
+        active_model.directory = ActiveValue::Set(directory);
+        active_model.file_name = ActiveValue::Set(file_name.to_owned());
+        active_model.update(&txn).await?;
+
+        let moved: Result<()> = async {
+            if let Some(parent) = to.parent() {
+                fsio.create_dir_all(parent)?;
+            }
+            fsio.move_file(&from, &to).await?;
+
+            Ok(())
+        }
+        .await;
+        if let Err(e) = moved {
+            warn!(
+                "Failed to move {} to {}: {e:#}",
+                from.display(),
+                to.display()
+            );
+            txn.rollback().await?;
+            outcome.failed += 1;
+            continue;
+        }
+
+        if let Err(e) = txn.commit().await {
+            // Put the file back where the library expects it
+            if let Err(e) = fsio.move_file(&to, &from).await {
+                warn!("Failed to move {} back: {e}", to.display());
+            }
+            return Err(e)
+                .with_context(|| format!("Failed to record the move of {}", from.display()));
+        }
+
+        if let Some(parent) = from.parent() {
+            emptied.insert(parent.to_path_buf());
+        }
+        outcome.moved += 1;
+    }
+
+    for directory in emptied {
+        remove_empty_directories(fsio, lib_path, &directory).await;
+    }
+
+    info!(
+        "Organized files with {}: moved {}, skipped {}, failed {}",
+        plan.template, outcome.moved, outcome.skipped, outcome.failed
+    );
+
+    Ok(outcome)
+}
+
+/// Removes `directory` and its parents while they are empty, up to the
+/// library root.
+async fn remove_empty_directories(fsio: &FsIo, lib_path: &Path, directory: &Path) {
+    let mut directory = directory.to_path_buf();
+    while directory.starts_with(lib_path) && directory != lib_path {
+        match fsio.read_dir(&directory).await {
+            Ok(entries) if entries.is_empty() => {}
+            _ => return,
+        }
+        if let Err(e) = fsio.remove_dir_all(&directory).await {
+            warn!("Failed to remove {}: {e}", directory.display());
+            return;
+        }
+        if !directory.pop() {
+            return;
+        }
+    }
+}
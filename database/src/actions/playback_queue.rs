@@ -2,6 +2,7 @@ use anyhow::Result;
 use sea_orm::{EntityTrait, QueryOrder, Set};
 use sea_orm::{TransactionTrait, prelude::*};
 
+use crate::connection::acquire_write_permit;
 use crate::entities::playback_queue;
 
 pub async fn replace_playback_queue(
@@ -10,6 +11,7 @@ pub async fn replace_playback_queue(
 ) -> Result<()> {
     use playback_queue::Entity as PlaybackQueueEntity;
 
+    let _permit = acquire_write_permit().await;
     let txn = main_db.begin().await?;
 
     PlaybackQueueEntity::delete_many().exec(&txn).await?;
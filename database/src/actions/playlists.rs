@@ -12,7 +12,7 @@ use tokio::fs::read_to_string;
 
 use crate::actions::collection::CollectionQuery;
 use crate::actions::search::{add_term, remove_term};
-use crate::connection::MainDbConnection;
+use crate::connection::{MainDbConnection, acquire_write_permit};
 use crate::entities::{media_file_playlists, media_files, playlists};
 use crate::{collection_query, get_by_id};
 
@@ -113,6 +113,33 @@ pub async fn get_all_playlists(db: &DatabaseConnection) -> Result<Vec<playlists:
     Ok(playlist)
 }
 
+/// Get the files of a playlist in playback order.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `playlist_id` - The ID of the playlist.
+///
+/// # Returns
+/// * `Result<Vec<(media_file_playlists::Model, media_files::Model)>>` - The
+///   playlist items with their files, or an error.
+pub async fn get_playlist_items(
+    main_db: &DatabaseConnection,
+    playlist_id: i32,
+) -> Result<Vec<(media_file_playlists::Model, media_files::Model)>> {
+    let items = media_file_playlists::Entity::find()
+        .filter(media_file_playlists::Column::PlaylistId.eq(playlist_id))
+        .order_by_asc(media_file_playlists::Column::Position)
+        .find_also_related(media_files::Entity)
+        .all(main_db)
+        .await?;
+
+    // Items of files removed from the library are skipped
+    Ok(items
+        .into_iter()
+        .filter_map(|(item, file)| file.map(|file| (item, file)))
+        .collect())
+}
+
 /// Update an existing playlist.
 ///
 /// # Arguments
@@ -133,6 +160,8 @@ pub async fn update_playlist(
 ) -> Result<playlists::Model> {
     use playlists::Entity as PlaylistEntity;
 
+    let _permit = acquire_write_permit().await;
+
     // Find the playlist by ID
     let playlist = PlaylistEntity::find_by_id(playlist_id).one(main_db).await?;
 
@@ -182,6 +211,8 @@ pub async fn remove_playlist(main_db: &DatabaseConnection, playlist_id: i32) ->
     use media_file_playlists::Entity as MediaFilePlaylistEntity;
     use playlists::Entity as PlaylistEntity;
 
+    let _permit = acquire_write_permit().await;
+
     // Check if the playlist exists
     let playlist = PlaylistEntity::find_by_id(playlist_id).one(main_db).await?;
     if playlist.is_none() {
@@ -226,6 +257,8 @@ pub async fn add_item_to_playlist(
     use media_file_playlists::Entity as MediaFilePlaylistEntity;
     use playlists::Entity as PlaylistEntity;
 
+    let _permit = acquire_write_permit().await;
+
     // Determine the position to insert the item
     let position = match position {
         Some(pos) => pos,
@@ -287,6 +320,8 @@ pub async fn reorder_playlist_item_position(
 ) -> Result<()> {
     use media_file_playlists::Entity as MediaFilePlaylistEntity;
 
+    let _permit = acquire_write_permit().await;
+
     // Find the media file playlist item
     let item = MediaFilePlaylistEntity::find()
         .filter(media_file_playlists::Column::PlaylistId.eq(playlist_id))
@@ -443,19 +478,26 @@ where
     })
 }
 
-pub async fn import_m3u8_to_playlist<E>(
+/// Append media files to a playlist in one statement, in the given order.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `node_id` - The id of the client that triggers the operation.
+/// * `playlist_id` - The ID of the playlist to append to.
+/// * `media_file_ids` - The IDs of the media files to append.
+///
+/// # Returns
+/// * `Result<()>` - An empty result or an error.
+pub async fn insert_playlist_items<E>(
     main_db: &E,
     node_id: &str,
     playlist_id: i32,
-    playlist_path: &Path,
-) -> Result<PlaylistImportResult>
+    media_file_ids: &[i32],
+) -> Result<()>
 where
     E: DatabaseExecutor + sea_orm::ConnectionTrait,
 {
-    let import_result = parse_m3u8_playlist(main_db, playlist_path).await?;
-
-    let models: Vec<media_file_playlists::ActiveModel> = import_result
-        .matched_ids
+    let models: Vec<media_file_playlists::ActiveModel> = media_file_ids
         .iter()
         .enumerate()
         .map(
@@ -487,6 +529,22 @@ where
             .await?;
     }
 
+    Ok(())
+}
+
+pub async fn import_m3u8_to_playlist<E>(
+    main_db: &E,
+    node_id: &str,
+    playlist_id: i32,
+    playlist_path: &Path,
+) -> Result<PlaylistImportResult>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    let import_result = parse_m3u8_playlist(main_db, playlist_path).await?;
+
+    insert_playlist_items(main_db, node_id, playlist_id, &import_result.matched_ids).await?;
+
     Ok(import_result)
 }
 
@@ -497,6 +555,7 @@ pub async fn create_m3u8_playlist(
     group: String,
     m3u8_path: &Path,
 ) -> Result<(playlists::Model, PlaylistImportResult)> {
+    let _permit = acquire_write_permit().await;
     let txn = main_db.begin().await?;
 
     // Create the playlist
@@ -542,6 +601,7 @@ pub async fn remove_item_from_playlist(
     use media_file_playlists::Entity as MediaFilePlaylistEntity;
     use playlists::Entity as PlaylistEntity;
 
+    let _permit = acquire_write_permit().await;
     let txn = main_db.begin().await?;
 
     info!("Removing item {media_file_id}(pos: {position}) from playlist {playlist_id}");
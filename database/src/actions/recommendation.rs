@@ -13,7 +13,7 @@ use crate::actions::analysis::AggregatedAnalysisResult;
 use crate::connection::{MainDbConnection, RecommendationDbConnection};
 use crate::entities::{media_analysis, media_files};
 
-use super::analysis::get_percentile_analysis_result;
+use super::analysis::{get_centralized_analysis_result, get_percentile_analysis_result};
 
 /// Get recommendations for a given item.
 ///
@@ -91,6 +91,40 @@ pub fn get_recommendation_by_parameter(
     }
 }
 
+/// Get recommendations for a group of items, around the center of their
+/// analysis results.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `recommend_db` - The tuple containing the LMDB environment and the Arroy database.
+/// * `file_ids` - The IDs of the items, those without analysis are ignored.
+/// * `n` - The number of recommendations to retrieve, the items themselves
+///   may be among them.
+///
+/// # Returns
+/// * `Result<Vec<(u32, f32)>>` - A vector of recommended item IDs and their distances.
+pub async fn get_recommendation_by_file_ids(
+    main_db: &MainDbConnection,
+    recommend_db: &RecommendationDbConnection,
+    file_ids: &[i32],
+    n: usize,
+) -> Result<Vec<(u32, f32)>> {
+    let analyzed = media_analysis::Entity::find()
+        .filter(media_analysis::Column::FileId.is_in(file_ids.to_vec()))
+        .count(main_db)
+        .await?;
+    if analyzed == 0 {
+        bail!("None of the given items has been analyzed");
+    }
+
+    let virtual_point: [f32; 61] = get_centralized_analysis_result(main_db, file_ids.to_vec())
+        .await
+        .with_context(|| "Failed to query centralized data")?
+        .into();
+
+    get_recommendation_by_parameter(recommend_db, virtual_point, n)
+}
+
 /// Sync the recommendation database with the analysis data.
 ///
 /// # Arguments
@@ -0,0 +1,191 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use log::info;
+use sea_orm::{
+    ConnectionTrait, DbBackend, FromQueryResult, SqlxSqliteConnector, Statement,
+    sqlx::{SqlitePool, sqlite::SqliteConnectOptions},
+};
+
+use ::migration::{MigrationName, Migrator, MigratorTrait};
+
+use crate::connection::MainDbConnection;
+
+/// The table the migrator records the applied migrations in.
+const MIGRATION_TABLE: &str = "seaql_migrations";
+
+#[derive(Debug, FromQueryResult)]
+struct AppliedMigrationRow {
+    version: String,
+}
+
+/// The migrations applied to a main database, compared to the ones this
+/// build knows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaStatus {
+    /// The newest applied migration, `None` for an empty database
+    pub version: Option<String>,
+    /// The newest migration of this build
+    pub latest_version: String,
+    /// Migrations this build applies when opening the library
+    pub pending: Vec<String>,
+    /// Applied migrations this build doesn't know, left by a newer version
+    pub unknown: Vec<String>,
+}
+
+impl SchemaStatus {
+    /// Whether a newer version migrated the library, which this build
+    /// cannot open.
+    pub fn is_newer(&self) -> bool {
+        !self.unknown.is_empty()
+    }
+}
+
+/// The names of the migrations of this build, oldest first.
+pub fn migration_names() -> Vec<String> {
+    Migrator::migrations()
+        .iter()
+        .map(|x| x.name().to_owned())
+        .collect()
+}
+
+async fn applied_migrations<C: ConnectionTrait>(db: &C) -> Result<Vec<String>> {
+    let exists = db
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?;",
+            [MIGRATION_TABLE.into()],
+        ))
+        .await?
+        .is_some();
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let rows = AppliedMigrationRow::find_by_statement(Statement::from_string(
+        DbBackend::Sqlite,
+        format!(
+            "SELECT version FROM {} ORDER BY version;",
+            table.to_string()
+        ),
+    ))
+    .all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(|x| x.version).collect())
+}
+
+/// Reads the migrations applied to `db` without applying any.
+pub async fn schema_status<C: ConnectionTrait>(db: &C) -> Result<SchemaStatus> {
+    let known = migration_names();
+    let applied = applied_migrations(db)
+        .await
+        .context("Failed to read the applied migrations")?;
+
+    Ok(SchemaStatus {
+        version: applied.last().cloned(),
+        latest_version: known.last().cloned().unwrap_or_default(),
+        pending: known
+            .iter()
+            .filter(|x| !applied.contains(x))
+            .cloned()
+            .collect(),
+        unknown: applied
+            .iter()
+            .filter(|x| !known.contains(x))
+            .cloned()
+            .collect(),
+    })
+}
+
+/// Reads the schema of the main database at `path` without migrating it,
+/// so the user can be warned before it is. `None` if there is no database.
+pub async fn inspect_main_db_schema(path: &Path) -> Result<Option<SchemaStatus>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let pool = SqlitePool::connect_with(options)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(pool);
+
+    let status = schema_status(&db).await;
+    db.close().await?;
+
+    status.map(Some)
+}
+
+/// Finds the migration `target` names, either fully or by a prefix like
+/// `m20251018_000031`.
+pub fn resolve_migration(target: &str) -> Result<String> {
+    let known = migration_names();
+    if known.iter().any(|x| x == target) {
+        return Ok(target.to_owned());
+    }
+
+    let matches: Vec<_> = known.iter().filter(|x| x.starts_with(target)).collect();
+    match matches.as_slice() {
+        [name] => Ok((*name).clone()),
+        [] => bail!("Unknown migration: {target}"),
+        _ => bail!("Ambiguous migration {target}, it matches {matches:?}"),
+    }
+}
+
+/// Migrations applied and reverted to bring a database to a version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrateOutcome {
+    pub from: Option<String>,
+    pub to: String,
+    pub applied: u32,
+    pub reverted: u32,
+}
+
+/// Applies or reverts migrations until `target` is the newest applied one.
+///
+/// # Arguments
+/// * `main_db` - A main database, opened without migrating it.
+/// * `target` - The name of the migration, or a unique prefix of it.
+/// * `node_id` - The node recorded by the migrations adding rows.
+pub async fn migrate_main_db(
+    main_db: &MainDbConnection,
+    target: &str,
+    node_id: &str,
+) -> Result<MigrateOutcome> {
+    let target = resolve_migration(target)?;
+    let status = schema_status(main_db).await?;
+    if status.is_newer() {
+        bail!(
+            "The library was migrated by a newer version of Rune ({}), use it to roll back",
+            status.unknown.join(", ")
+        );
+    }
+
+    let known = migration_names();
+    let applied = (known.len() - status.pending.len()) as u32;
+    let wanted = known.iter().position(|x| *x == target).unwrap_or_default() as u32 + 1;
+
+    let _ = migration::initialize_node_id(node_id.to_owned());
+    let mut outcome = MigrateOutcome {
+        from: status.version,
+        to: target,
+        applied: 0,
+        reverted: 0,
+    };
+
+    if wanted > applied {
+        outcome.applied = wanted - applied;
+        Migrator::up(main_db, Some(outcome.applied)).await?;
+    } else if wanted < applied {
+        outcome.reverted = applied - wanted;
+        Migrator::down(main_db, Some(outcome.reverted)).await?;
+    }
+
+    info!(
+        "Migrated the main database to {}: {} applied, {} reverted",
+        outcome.to, outcome.applied, outcome.reverted
+    );
+
+    Ok(outcome)
+}
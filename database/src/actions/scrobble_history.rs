@@ -0,0 +1,159 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder};
+
+use crate::entities::scrobble_history;
+
+/// Separates the services in the `accepted_by` column.
+const SERVICE_SEPARATOR: char = ',';
+
+/// A track which was listened to long enough to be scrobbled.
+#[derive(Debug, Clone)]
+pub struct ScrobbleHistoryEntry {
+    /// The file in the library, if the track is part of it
+    pub media_file_id: Option<i32>,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    /// When the track was listened to (UNIX timestamp)
+    pub timestamp: i64,
+}
+
+/// Filters applied when listing the scrobble history.
+#[derive(Debug, Clone, Default)]
+pub struct ScrobbleHistoryFilter {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// The services which accepted a scrobble, in the order they did.
+pub fn accepted_services(entry: &scrobble_history::Model) -> Vec<String> {
+    entry
+        .accepted_by
+        .split(SERVICE_SEPARATOR)
+        .filter(|x| !x.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Insert a scrobble into the history, accepted by no service yet.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `entry` - The scrobble being recorded.
+///
+/// # Returns
+/// * `Result<Model>` - The inserted scrobble or an error.
+pub async fn insert_scrobble_history(
+    main_db: &DatabaseConnection,
+    entry: ScrobbleHistoryEntry,
+) -> Result<scrobble_history::Model> {
+    let new_entry = scrobble_history::ActiveModel {
+        media_file_id: ActiveValue::Set(entry.media_file_id),
+        artist: ActiveValue::Set(entry.artist),
+        title: ActiveValue::Set(entry.title),
+        album: ActiveValue::Set(entry.album),
+        timestamp: ActiveValue::Set(entry.timestamp),
+        accepted_by: ActiveValue::Set(String::new()),
+        ..Default::default()
+    };
+
+    Ok(new_entry.insert(main_db).await?)
+}
+
+/// Record that a service accepted a scrobble of the history.
+///
+/// The scrobble is found by the time it was listened to and the track, as
+/// the services know it.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `timestamp` - When the track was listened to (UNIX timestamp).
+/// * `artist` - The artist of the track.
+/// * `title` - The title of the track.
+/// * `service` - The service which accepted the scrobble.
+///
+/// # Returns
+/// * `Result<bool>` - Whether the scrobble was found in the history.
+pub async fn mark_scrobble_accepted(
+    main_db: &DatabaseConnection,
+    timestamp: i64,
+    artist: &str,
+    title: &str,
+    service: &str,
+) -> Result<bool> {
+    let entry = scrobble_history::Entity::find()
+        .filter(scrobble_history::Column::Timestamp.eq(timestamp))
+        .filter(scrobble_history::Column::Artist.eq(artist))
+        .filter(scrobble_history::Column::Title.eq(title))
+        .order_by_desc(scrobble_history::Column::Id)
+        .one(main_db)
+        .await?;
+
+    let Some(entry) = entry else {
+        return Ok(false);
+    };
+
+    let mut services = accepted_services(&entry);
+    if services.iter().any(|x| x == service) {
+        return Ok(true);
+    }
+    services.push(service.to_owned());
+
+    let mut active: scrobble_history::ActiveModel = entry.into();
+    active.accepted_by = ActiveValue::Set(services.join(&SERVICE_SEPARATOR.to_string()));
+    active.update(main_db).await?;
+
+    Ok(true)
+}
+
+/// List the scrobble history with filtering and pagination, newest first.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `filter` - Restricts the scrobbles by time range.
+/// * `cursor` - The page to fetch (0-based index).
+/// * `page_size` - The number of scrobbles to retrieve per page.
+///
+/// # Returns
+/// * `Result<Vec<scrobble_history::Model>>` - A vector of scrobbles or an error.
+pub async fn list_scrobble_history(
+    main_db: &DatabaseConnection,
+    filter: ScrobbleHistoryFilter,
+    cursor: u64,
+    page_size: u64,
+) -> Result<Vec<scrobble_history::Model>> {
+    let mut query = scrobble_history::Entity::find();
+
+    if let Some(start) = filter.start {
+        query = query.filter(scrobble_history::Column::Timestamp.gte(start.timestamp()));
+    }
+    if let Some(end) = filter.end {
+        query = query.filter(scrobble_history::Column::Timestamp.lte(end.timestamp()));
+    }
+
+    let paginator = query
+        .order_by_desc(scrobble_history::Column::Timestamp)
+        .order_by_desc(scrobble_history::Column::Id)
+        .paginate(main_db, page_size);
+
+    Ok(paginator.fetch_page(cursor).await?)
+}
+
+/// Remove the scrobbles listened to more than `max_age` ago.
+///
+/// # Returns
+/// * `Result<u64>` - The number of removed scrobbles or an error.
+pub async fn prune_scrobble_history(
+    main_db: &DatabaseConnection,
+    max_age: Duration,
+) -> Result<u64> {
+    let threshold = (Utc::now() - max_age).timestamp();
+
+    Ok(scrobble_history::Entity::delete_many()
+        .filter(scrobble_history::Column::Timestamp.lt(threshold))
+        .exec(main_db)
+        .await?
+        .rows_affected)
+}
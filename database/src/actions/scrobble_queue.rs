@@ -0,0 +1,184 @@
+use anyhow::Result;
+use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, QueryOrder, QuerySelect, TransactionTrait};
+
+use crate::connection::acquire_write_permit;
+use crate::entities::scrobble_queue;
+
+/// Queued entries submitting a listen.
+const SCROBBLE_ACTION: &str = "scrobble";
+/// Queued entries marking a track as loved, or no longer loved.
+const LOVE_ACTION: &str = "love";
+const UNLOVE_ACTION: &str = "unlove";
+
+/// Whether a queued love marks the track as loved, rather than no longer
+/// loved.
+pub fn is_love(entry: &scrobble_queue::Model) -> bool {
+    entry.action == LOVE_ACTION
+}
+
+/// Queue a scrobble which could not be submitted to a service.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `service` - The service the scrobble is for.
+/// * `track` - The serialized track.
+/// * `timestamp` - When the track was listened to (UNIX timestamp).
+///
+/// # Returns
+/// * `Result<Model>` - The queued scrobble or an error.
+pub async fn enqueue_scrobble(
+    main_db: &DatabaseConnection,
+    service: &str,
+    track: String,
+    timestamp: i64,
+) -> Result<scrobble_queue::Model> {
+    let new_entry = scrobble_queue::ActiveModel {
+        service: ActiveValue::Set(service.to_owned()),
+        track: ActiveValue::Set(track),
+        timestamp: ActiveValue::Set(timestamp),
+        attempts: ActiveValue::Set(0),
+        action: ActiveValue::Set(SCROBBLE_ACTION.to_owned()),
+        ..Default::default()
+    };
+
+    Ok(new_entry.insert(main_db).await?)
+}
+
+/// Queue a love, or an unlove, of a track which could not be submitted to a
+/// service. Only the latest one queued for the track is kept.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `service` - The service the love is for.
+/// * `track` - The serialized track.
+/// * `loved` - Whether the track is loved, or no longer.
+/// * `timestamp` - When the track was loved (UNIX timestamp).
+///
+/// # Returns
+/// * `Result<Model>` - The queued love or an error.
+pub async fn enqueue_love(
+    main_db: &DatabaseConnection,
+    service: &str,
+    track: String,
+    loved: bool,
+    timestamp: i64,
+) -> Result<scrobble_queue::Model> {
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+
+    scrobble_queue::Entity::delete_many()
+        .filter(scrobble_queue::Column::Service.eq(service))
+        .filter(scrobble_queue::Column::Action.is_in([LOVE_ACTION, UNLOVE_ACTION]))
+        .filter(scrobble_queue::Column::Track.eq(track.as_str()))
+        .exec(&txn)
+        .await?;
+
+    let new_entry = scrobble_queue::ActiveModel {
+        service: ActiveValue::Set(service.to_owned()),
+        track: ActiveValue::Set(track),
+        timestamp: ActiveValue::Set(timestamp),
+        attempts: ActiveValue::Set(0),
+        action: ActiveValue::Set(if loved { LOVE_ACTION } else { UNLOVE_ACTION }.to_owned()),
+        ..Default::default()
+    };
+    let inserted = new_entry.insert(&txn).await?;
+
+    txn.commit().await?;
+
+    Ok(inserted)
+}
+
+/// List the oldest queued scrobbles of a service.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `service` - The service the scrobbles are for.
+/// * `limit` - The maximum number of scrobbles to return.
+///
+/// # Returns
+/// * `Result<Vec<Model>>` - The scrobbles, in the order they were listened to.
+pub async fn list_queued_scrobbles(
+    main_db: &DatabaseConnection,
+    service: &str,
+    limit: u64,
+) -> Result<Vec<scrobble_queue::Model>> {
+    Ok(scrobble_queue::Entity::find()
+        .filter(scrobble_queue::Column::Service.eq(service))
+        .filter(scrobble_queue::Column::Action.eq(SCROBBLE_ACTION))
+        .order_by_asc(scrobble_queue::Column::Timestamp)
+        .order_by_asc(scrobble_queue::Column::Id)
+        .limit(limit)
+        .all(main_db)
+        .await?)
+}
+
+/// List the oldest queued loves and unloves of a service.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `service` - The service the loves are for.
+/// * `limit` - The maximum number of loves to return.
+///
+/// # Returns
+/// * `Result<Vec<Model>>` - The loves, in the order they were made.
+pub async fn list_queued_loves(
+    main_db: &DatabaseConnection,
+    service: &str,
+    limit: u64,
+) -> Result<Vec<scrobble_queue::Model>> {
+    Ok(scrobble_queue::Entity::find()
+        .filter(scrobble_queue::Column::Service.eq(service))
+        .filter(scrobble_queue::Column::Action.is_in([LOVE_ACTION, UNLOVE_ACTION]))
+        .order_by_asc(scrobble_queue::Column::Id)
+        .limit(limit)
+        .all(main_db)
+        .await?)
+}
+
+/// Count the queued scrobbles of a service, leaving out the loves.
+pub async fn count_queued_scrobbles(main_db: &DatabaseConnection, service: &str) -> Result<u64> {
+    Ok(scrobble_queue::Entity::find()
+        .filter(scrobble_queue::Column::Service.eq(service))
+        .filter(scrobble_queue::Column::Action.eq(SCROBBLE_ACTION))
+        .count(main_db)
+        .await?)
+}
+
+/// Record that a service refused the given scrobbles.
+pub async fn increase_scrobble_attempts(main_db: &DatabaseConnection, ids: &[i32]) -> Result<()> {
+    scrobble_queue::Entity::update_many()
+        .col_expr(
+            scrobble_queue::Column::Attempts,
+            Expr::col(scrobble_queue::Column::Attempts).add(1),
+        )
+        .filter(scrobble_queue::Column::Id.is_in(ids.to_vec()))
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// Remove the given scrobbles from the queue.
+///
+/// # Returns
+/// * `Result<u64>` - The number of removed scrobbles or an error.
+pub async fn remove_queued_scrobbles(main_db: &DatabaseConnection, ids: &[i32]) -> Result<u64> {
+    Ok(scrobble_queue::Entity::delete_many()
+        .filter(scrobble_queue::Column::Id.is_in(ids.to_vec()))
+        .exec(main_db)
+        .await?
+        .rows_affected)
+}
+
+/// Remove every queued scrobble and love of a service.
+///
+/// # Returns
+/// * `Result<u64>` - The number of removed scrobbles or an error.
+pub async fn clear_scrobble_queue(main_db: &DatabaseConnection, service: &str) -> Result<u64> {
+    Ok(scrobble_queue::Entity::delete_many()
+        .filter(scrobble_queue::Column::Service.eq(service))
+        .exec(main_db)
+        .await?
+        .rows_affected)
+}
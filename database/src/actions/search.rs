@@ -142,3 +142,205 @@ pub async fn search_for(
 
     Ok(results)
 }
+
+#[derive(Debug, FromQueryResult)]
+struct RankedSearchResult {
+    key: String,
+    doc: String,
+    rank: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub collection_type: CollectionQueryType,
+    pub id: i64,
+    /// The indexed name which matched
+    pub name: String,
+    /// The relevance of the match, higher is better
+    pub score: f64,
+}
+
+/// Like [`search_for`], but keeps the names and the relevance of the
+/// matches, best matches first within each collection type.
+pub async fn search_with_scores(
+    main_db: &DatabaseConnection,
+    query_str: &str,
+    search_fields: Option<Vec<CollectionQueryType>>,
+    n: usize,
+) -> Result<Vec<SearchHit>> {
+    let mut results = Vec::new();
+
+    if query_str.is_empty() {
+        return Ok(results);
+    }
+
+    let query_str = deunicode(query_str);
+    for collection_type in [
+        CollectionQueryType::Track,
+        CollectionQueryType::Artist,
+        CollectionQueryType::Album,
+        CollectionQueryType::Directory,
+        CollectionQueryType::Playlist,
+    ] {
+        if let Some(ref search_fields) = search_fields
+            && !search_fields.contains(&collection_type)
+        {
+            continue;
+        }
+
+        let top_docs = RankedSearchResult::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            r#"SELECT key, doc, rank FROM search_index WHERE doc MATCH ? AND entry_type = ? ORDER BY rank LIMIT ?;"#,
+            [
+                format!("\"{}\"", query_str.replace("\"", "\"\"")).into(),
+                collection_type.to_string().into(),
+                (n * 2).to_string().into(),
+            ],
+        ))
+        .all(main_db)
+        .await?;
+
+        let mut hits: Vec<SearchHit> = Vec::new();
+        for item in top_docs {
+            let Ok(id) = item.key.parse::<i64>() else {
+                warn!("Invalid document ID found!");
+                continue;
+            };
+            // Every name is indexed twice, as is and transliterated
+            if hits.iter().any(|hit| hit.id == id) || hits.len() >= n {
+                continue;
+            }
+
+            hits.push(SearchHit {
+                collection_type: collection_type.clone(),
+                id,
+                name: item.doc,
+                // FTS5 ranks better matches with lower, negative values
+                score: -item.rank,
+            });
+        }
+        results.extend(hits);
+    }
+
+    Ok(results)
+}
+
+/// The entry type of the lyrics in the search index, next to the names of
+/// the collections.
+pub const LYRIC_ENTRY_TYPE: &str = "lyric";
+
+/// Marks the start of a match in the snippets of [`search_lyrics`].
+pub const SNIPPET_MATCH_START: &str = "<b>";
+/// Marks the end of a match in the snippets of [`search_lyrics`].
+pub const SNIPPET_MATCH_END: &str = "</b>";
+
+/// The number of tokens around the match in the snippets of
+/// [`search_lyrics`].
+const SNIPPET_TOKENS: i32 = 12;
+
+pub async fn remove_lyrics_term<E>(main_db: &E, file_id: i32) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    search_index::Entity::delete_many()
+        .filter(search_index::Column::Key.eq(file_id.to_string()))
+        .filter(search_index::Column::EntryType.eq(LYRIC_ENTRY_TYPE))
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// Indexes the lyrics of a media file, replacing the ones indexed before.
+/// Unlike names, lyrics are only indexed as written, not transliterated,
+/// to keep the index small.
+pub async fn add_lyrics_term<E>(main_db: &E, file_id: i32, lyrics: &str) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    remove_lyrics_term(main_db, file_id).await?;
+
+    search_index::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            r#"INSERT INTO search_index (id, key, entry_type, doc) VALUES ('', ?, ?, ?);"#,
+            [
+                file_id.to_string().into(),
+                LYRIC_ENTRY_TYPE.into(),
+                lyrics.into(),
+            ],
+        ))
+        .all(main_db)
+        .await?;
+
+    Ok(())
+}
+
+/// Removes every lyric from the search index.
+pub async fn clear_lyrics_terms<E>(main_db: &E) -> Result<()>
+where
+    E: DatabaseExecutor + sea_orm::ConnectionTrait,
+{
+    search_index::Entity::delete_many()
+        .filter(search_index::Column::EntryType.eq(LYRIC_ENTRY_TYPE))
+        .exec(main_db)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, FromQueryResult)]
+struct LyricSearchResult {
+    key: String,
+    snippet: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyricSearchHit {
+    pub file_id: i64,
+    /// The lines around the match, the matched words wrapped in
+    /// [`SNIPPET_MATCH_START`] and [`SNIPPET_MATCH_END`]
+    pub snippet: String,
+}
+
+/// Searches the indexed lyrics, best matches first. Lyrics are tokenized
+/// like the names of the collections are.
+pub async fn search_lyrics(
+    main_db: &DatabaseConnection,
+    query_str: &str,
+    n: usize,
+) -> Result<Vec<LyricSearchHit>> {
+    if query_str.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let top_docs = LyricSearchResult::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        r#"SELECT key, snippet(search_index, 3, ?, ?, '…', ?) AS snippet FROM search_index WHERE doc MATCH ? AND entry_type = ? ORDER BY rank LIMIT ?;"#,
+        [
+            SNIPPET_MATCH_START.into(),
+            SNIPPET_MATCH_END.into(),
+            SNIPPET_TOKENS.into(),
+            format!("\"{}\"", query_str.replace("\"", "\"\"")).into(),
+            LYRIC_ENTRY_TYPE.into(),
+            (n as i64).into(),
+        ],
+    ))
+    .all(main_db)
+    .await?;
+
+    let mut hits = Vec::new();
+    for item in top_docs {
+        let Ok(file_id) = item.key.parse::<i64>() else {
+            warn!("Invalid document ID found!");
+            continue;
+        };
+
+        hits.push(LyricSearchHit {
+            file_id,
+            snippet: item.snippet,
+        });
+    }
+
+    Ok(hits)
+}
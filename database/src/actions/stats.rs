@@ -1,32 +1,52 @@
-use anyhow::Result;
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow, bail};
 use chrono::Utc;
-use sea_orm::ActiveValue;
 use sea_orm::prelude::*;
+use sea_orm::{ActiveValue, JoinType, QueryOrder, QuerySelect, TransactionTrait};
 
+use crate::connection::acquire_write_permit;
 use crate::entities::media_file_stats;
 use crate::entities::media_files;
 
-/// Set the liked status of a media file.
-///
-/// # Arguments
-/// * `main_db` - A reference to the database connection.
-/// * `media_file_id` - The ID of the media file to update.
-/// * `liked` - The new liked status.
-///
-/// # Returns
-/// * `Result<Model>` - The updated media file stats model or an error.
-pub async fn set_liked(
-    main_db: &DatabaseConnection,
+use super::utils::DatabaseExecutor;
+
+/// The sync ID of the stats of a file, the same on every device.
+pub(crate) fn stats_hlc_uuid(file_hash: &str) -> String {
+    Uuid::new_v5(
+        &Uuid::NAMESPACE_OID,
+        format!("RUNE_STATS::{file_hash}").as_bytes(),
+    )
+    .to_string()
+}
+
+/// Stamp the stats as updated now, by this node.
+pub(crate) fn mark_updated(stats: &mut media_file_stats::ActiveModel) {
+    let now = Utc::now().to_rfc3339();
+
+    stats.updated_at = ActiveValue::Set(now.clone());
+    stats.updated_at_hlc_ts = ActiveValue::Set(now);
+    stats.updated_at_hlc_ver = ActiveValue::Set(0);
+    stats.updated_at_hlc_nid = ActiveValue::Set(migration::get_node_id().to_owned());
+}
+
+/// Update the stats of a media file, creating them first if the file has
+/// none. Returns `None` if the media file does not exist.
+pub(crate) async fn update_stats<E, F>(
+    main_db: &E,
     media_file_id: i32,
-    liked: bool,
-) -> Result<Option<media_file_stats::Model>> {
-    let media_file = media_files::Entity::find_by_id(media_file_id)
+    update: F,
+) -> Result<Option<media_file_stats::Model>>
+where
+    E: DatabaseExecutor + ConnectionTrait,
+    F: FnOnce(&mut media_file_stats::ActiveModel),
+{
+    let Some(media_file) = media_files::Entity::find_by_id(media_file_id)
         .one(main_db)
-        .await?;
-
-    if media_file.is_none() {
+        .await?
+    else {
         return Ok(None);
-    }
+    };
 
     // Find the media file stats by media file ID
     let stats = media_file_stats::Entity::find()
@@ -37,22 +57,32 @@ pub async fn set_liked(
     let updated_stats = if let Some(stats) = stats {
         let mut active_model: media_file_stats::ActiveModel = stats.into();
 
-        // Update the liked status
-        active_model.liked = ActiveValue::Set(liked);
-        active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
+        update(&mut active_model);
+        mark_updated(&mut active_model);
 
         // Update the media file stats in the database
         active_model.update(main_db).await?
     } else {
         // Create a new media file stats record
-        let new_stats = media_file_stats::ActiveModel {
+        let now = Utc::now().to_rfc3339();
+        let node_id = migration::get_node_id();
+        let mut new_stats = media_file_stats::ActiveModel {
             media_file_id: ActiveValue::Set(media_file_id),
-            liked: ActiveValue::Set(liked),
+            liked: ActiveValue::Set(false),
             skipped: ActiveValue::Set(0),
             played_through: ActiveValue::Set(0),
-            updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
+            last_skipped_at: ActiveValue::Set(None),
+            total_listened_seconds: ActiveValue::Set(0.),
+            last_position_seconds: ActiveValue::Set(None),
+            first_played_at: ActiveValue::Set(None),
+            hlc_uuid: ActiveValue::Set(stats_hlc_uuid(&media_file.file_hash)),
+            created_at_hlc_ts: ActiveValue::Set(now),
+            created_at_hlc_ver: ActiveValue::Set(0),
+            created_at_hlc_nid: ActiveValue::Set(node_id.to_owned()),
             ..Default::default()
         };
+        update(&mut new_stats);
+        mark_updated(&mut new_stats);
 
         new_stats.insert(main_db).await?
     };
@@ -60,6 +90,87 @@ pub async fn set_liked(
     Ok(Some(updated_stats))
 }
 
+/// Set the liked status of a media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+/// * `liked` - The new liked status.
+///
+/// # Returns
+/// * `Result<Model>` - The updated media file stats model or an error.
+pub async fn set_liked(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    liked: bool,
+) -> Result<Option<media_file_stats::Model>> {
+    update_stats(main_db, media_file_id, |stats| {
+        stats.liked = ActiveValue::Set(liked)
+    })
+    .await
+}
+
+/// Set the liked status of several media files at once.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_ids` - The IDs of the media files to update.
+/// * `liked` - The new liked status.
+///
+/// # Returns
+/// * `Result<Vec<i32>>` - The IDs of the media files which do not exist, the
+///   other files are updated.
+pub async fn set_liked_many(
+    main_db: &DatabaseConnection,
+    media_file_ids: &[i32],
+    liked: bool,
+) -> Result<Vec<i32>> {
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+    let mut missing = Vec::new();
+
+    for media_file_id in media_file_ids {
+        let updated = update_stats(&txn, *media_file_id, |stats| {
+            stats.liked = ActiveValue::Set(liked)
+        })
+        .await?;
+
+        if updated.is_none() {
+            missing.push(*media_file_id);
+        }
+    }
+
+    txn.commit().await?;
+
+    Ok(missing)
+}
+
+/// Set the rating of a media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+/// * `rating` - The new rating, from 1 to 5, or `None` to clear it.
+///
+/// # Returns
+/// * `Result<Model>` - The updated media file stats model or an error.
+pub async fn set_rating(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    rating: Option<i32>,
+) -> Result<Option<media_file_stats::Model>> {
+    if let Some(rating) = rating
+        && !(1..=5).contains(&rating)
+    {
+        bail!("Invalid rating {rating}, expected 1 to 5");
+    }
+
+    update_stats(main_db, media_file_id, |stats| {
+        stats.rating = ActiveValue::Set(rating)
+    })
+    .await
+}
+
 /// Get the liked status of a media file.
 ///
 /// # Arguments
@@ -83,53 +194,77 @@ pub async fn get_liked(main_db: &DatabaseConnection, media_file_id: i32) -> Resu
     }
 }
 
-/// Increase the skipped count of a media file.
+/// Get the media files liked among the given ones.
 ///
 /// # Arguments
 /// * `main_db` - A reference to the database connection.
-/// * `media_file_id` - The ID of the media file to update.
+/// * `media_file_ids` - The IDs of the media files to check.
 ///
 /// # Returns
-/// * `Result<Model>` - The updated media file stats model or an error.
-pub async fn increase_skipped(
+/// * `Result<HashMap<i32, String>>` - The liked media files, with the time
+///   their stats were last updated, or an error.
+pub async fn get_liked_media_files(
     main_db: &DatabaseConnection,
-    media_file_id: i32,
-) -> Result<media_file_stats::Model> {
+    media_file_ids: &[i32],
+) -> Result<HashMap<i32, String>> {
     use media_file_stats::Entity as MediaFileStatsEntity;
 
-    // Find the media file stats by media file ID
     let stats = MediaFileStatsEntity::find()
-        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
-        .one(main_db)
+        .filter(media_file_stats::Column::MediaFileId.is_in(media_file_ids.to_vec()))
+        .filter(media_file_stats::Column::Liked.eq(true))
+        .all(main_db)
         .await?;
 
-    let updated_stats = if let Some(stats) = stats {
-        let mut active_model: media_file_stats::ActiveModel = stats.clone().into();
-
-        // Increase the skipped count
-        active_model.skipped = ActiveValue::Set(stats.skipped + 1);
-        active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
-
-        // Update the media file stats in the database
-        active_model.update(main_db).await?
-    } else {
-        // Create a new media file stats record
-        let new_stats = media_file_stats::ActiveModel {
-            media_file_id: ActiveValue::Set(media_file_id),
-            liked: ActiveValue::Set(false),
-            skipped: ActiveValue::Set(1),
-            played_through: ActiveValue::Set(0),
-            updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
-            ..Default::default()
-        };
+    Ok(stats
+        .into_iter()
+        .map(|x| (x.media_file_id, x.updated_at))
+        .collect())
+}
 
-        new_stats.insert(main_db).await?
-    };
+/// Get every liked media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+///
+/// # Returns
+/// * `Result<Vec<Model>>` - The liked media files, by ID, or an error.
+pub async fn get_all_liked_media_files(
+    main_db: &DatabaseConnection,
+) -> Result<Vec<media_files::Model>> {
+    Ok(media_files::Entity::find()
+        .join(
+            JoinType::InnerJoin,
+            media_file_stats::Relation::MediaFiles.def().rev(),
+        )
+        .filter(media_file_stats::Column::Liked.eq(true))
+        .order_by_asc(media_files::Column::Id)
+        .all(main_db)
+        .await?)
+}
 
-    Ok(updated_stats)
+/// Increase the skipped count of a media file, recording when it was
+/// skipped.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+///
+/// # Returns
+/// * `Result<Model>` - The updated media file stats model or an error.
+pub async fn increase_skipped(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+) -> Result<media_file_stats::Model> {
+    update_stats(main_db, media_file_id, |stats| {
+        stats.skipped = ActiveValue::Set(stats.skipped.as_ref() + 1);
+        stats.last_skipped_at = ActiveValue::Set(Some(Utc::now().to_rfc3339()));
+    })
+    .await?
+    .ok_or_else(|| anyhow!("Media file {media_file_id} not found"))
 }
 
-/// Increase the played through count of a media file.
+/// Increase the played through count of a media file. A file played to the
+/// end has no position to resume from.
 ///
 /// # Arguments
 /// * `main_db` - A reference to the database connection.
@@ -141,36 +276,47 @@ pub async fn increase_played_through(
     main_db: &DatabaseConnection,
     media_file_id: i32,
 ) -> Result<media_file_stats::Model> {
-    use media_file_stats::Entity as MediaFileStatsEntity;
-
-    // Find the media file stats by media file ID
-    let stats = MediaFileStatsEntity::find()
-        .filter(media_file_stats::Column::MediaFileId.eq(media_file_id))
-        .one(main_db)
-        .await?;
-
-    let updated_stats = if let Some(stats) = stats {
-        let mut active_model: media_file_stats::ActiveModel = stats.clone().into();
-
-        // Increase the played through count
-        active_model.played_through = ActiveValue::Set(stats.played_through + 1);
-        active_model.updated_at = ActiveValue::Set(Utc::now().to_rfc3339());
-
-        // Update the media file stats in the database
-        active_model.update(main_db).await?
-    } else {
-        // Create a new media file stats record
-        let new_stats = media_file_stats::ActiveModel {
-            media_file_id: ActiveValue::Set(media_file_id),
-            liked: ActiveValue::Set(false),
-            skipped: ActiveValue::Set(0),
-            played_through: ActiveValue::Set(1),
-            updated_at: ActiveValue::Set(Utc::now().to_rfc3339()),
-            ..Default::default()
-        };
+    update_stats(main_db, media_file_id, |stats| {
+        stats.played_through = ActiveValue::Set(stats.played_through.as_ref() + 1);
+        stats.last_position_seconds = ActiveValue::Set(None);
+        if stats.first_played_at.as_ref().is_none() {
+            stats.first_played_at = ActiveValue::Set(Some(Utc::now().to_rfc3339()));
+        }
+    })
+    .await?
+    .ok_or_else(|| anyhow!("Media file {media_file_id} not found"))
+}
 
-        new_stats.insert(main_db).await?
-    };
+/// Record a stretch of listening to a media file.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `media_file_id` - The ID of the media file to update.
+/// * `position` - Where the listening stopped, in seconds, `None` if the
+///   file was listened to the end.
+/// * `listened` - The time spent listening, in seconds, seeks and pauses
+///   left out.
+///
+/// # Returns
+/// * `Result<Option<Model>>` - The updated media file stats model, `None` if
+///   the media file does not exist, or an error.
+pub async fn record_listening(
+    main_db: &DatabaseConnection,
+    media_file_id: i32,
+    position: Option<f64>,
+    listened: f64,
+) -> Result<Option<media_file_stats::Model>> {
+    if listened < 0. || position.is_some_and(|x| x < 0.) {
+        bail!("Invalid listening of {listened}s up to {position:?}s");
+    }
 
-    Ok(updated_stats)
+    update_stats(main_db, media_file_id, |stats| {
+        stats.total_listened_seconds =
+            ActiveValue::Set(stats.total_listened_seconds.as_ref() + listened);
+        stats.last_position_seconds = ActiveValue::Set(position.filter(|x| *x > 0.));
+        if listened > 0. && stats.first_played_at.as_ref().is_none() {
+            stats.first_played_at = ActiveValue::Set(Some(Utc::now().to_rfc3339()));
+        }
+    })
+    .await
 }
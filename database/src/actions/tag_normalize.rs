@@ -0,0 +1,323 @@
+//! Normalization of messy tags: stray whitespace, the many spellings of
+//! "featuring", shouting titles, sort names and mojibake. Each rule can be
+//! turned off, and the changes go through the same path as manual edits so
+//! they can be previewed before they are applied.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sea_orm::{DatabaseConnection, QueryOrder, QuerySelect, prelude::*};
+
+use crate::actions::{
+    file::get_files_by_ids,
+    metadata_edit::{EDITABLE_KEYS, MetadataChange, get_editable_metadata},
+};
+use crate::entities::media_files;
+
+/// Words title casing keeps lowercase unless they start a value.
+pub const DEFAULT_TITLE_CASE_EXCEPTIONS: [&str; 18] = [
+    "a", "an", "and", "as", "at", "but", "by", "feat.", "for", "in", "nor", "of", "on", "or",
+    "the", "to", "vs.", "with",
+];
+
+/// Files whose metadata is read at once.
+const NORMALIZE_BATCH_SIZE: usize = 500;
+
+/// Fields the featuring notation is unified in.
+const FEATURING_KEYS: [&str; 3] = ["track_title", "artist", "album_artist"];
+/// Fields which are title cased. Names are left alone.
+const TITLE_CASE_KEYS: [&str; 2] = ["track_title", "album"];
+/// Name fields and the sort fields derived from them.
+const SORT_KEYS: [(&str, &str); 2] = [
+    ("artist", "sort_artist"),
+    ("album_artist", "sort_album_artist"),
+];
+
+static FEATURING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(^|[\s(\[])(?:featuring|feat\.?|ft\.?)(\s)").expect("Invalid regex")
+});
+
+/// The rules to apply, each of them can be turned off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizationRules {
+    /// Remove whitespace around values
+    pub trim_whitespace: bool,
+    /// Turn runs of whitespace into a single space
+    pub collapse_spaces: bool,
+    /// Write `ft.`, `Feat` or `featuring` as `feat.`
+    pub unify_featuring: bool,
+    /// Title case track titles and albums
+    pub title_case: bool,
+    /// Words title casing writes as they are spelled here, like `of` or
+    /// `AC/DC`
+    pub title_case_exceptions: Vec<String>,
+    /// Set the sort names of artists starting with `The ` to the name
+    /// without it, when they have none
+    pub sort_names: bool,
+    /// Decode again values UTF-8 was read from as Latin-1, like `BeyoncÃ©`
+    pub fix_mojibake: bool,
+}
+
+impl Default for NormalizationRules {
+    fn default() -> Self {
+        Self {
+            trim_whitespace: true,
+            collapse_spaces: true,
+            unify_featuring: true,
+            title_case: true,
+            title_case_exceptions: DEFAULT_TITLE_CASE_EXCEPTIONS
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            sort_names: true,
+            fix_mojibake: true,
+        }
+    }
+}
+
+/// The bytes of the Windows-1252 characters which have no Latin-1 code
+/// point, as decoders mistaking UTF-8 for it produce them.
+fn windows_1252_byte(c: char) -> Option<u8> {
+    let byte = match c {
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8a,
+        '‹' => 0x8b,
+        'Œ' => 0x8c,
+        'Ž' => 0x8e,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9a,
+        '›' => 0x9b,
+        'œ' => 0x9c,
+        'ž' => 0x9e,
+        'Ÿ' => 0x9f,
+        _ => return None,
+    };
+
+    Some(byte)
+}
+
+/// Reads a value back as the UTF-8 it was before being decoded as Latin-1.
+/// Values which don't turn into valid UTF-8 that way, like `Café`, were
+/// not mis-decoded and are left as they are.
+pub fn fix_mojibake(value: &str) -> Option<String> {
+    let bytes = value
+        .chars()
+        .map(|c| match u8::try_from(c) {
+            Ok(byte) => Some(byte),
+            Err(_) => windows_1252_byte(c),
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    if bytes.is_ascii() {
+        return None;
+    }
+
+    String::from_utf8(bytes).ok().filter(|x| x != value)
+}
+
+pub fn unify_featuring(value: &str) -> String {
+    FEATURING.replace_all(value, "${1}feat.${2}").into_owned()
+}
+
+/// Capitalizes the first letter of a word, skipping leading punctuation.
+fn capitalize(word: &str) -> String {
+    let Some(index) = word.find(char::is_alphanumeric) else {
+        return word.to_owned();
+    };
+    let (prefix, rest) = word.split_at(index);
+    let mut chars = rest.chars();
+    let first = chars.next().map(|x| x.to_uppercase().to_string());
+
+    format!("{prefix}{}{}", first.unwrap_or_default(), chars.as_str())
+}
+
+/// Finds the exception a word is, the punctuation around it aside, like
+/// `(feat.` or `of,`. Returns the word written as the exception.
+fn find_exception(word: &str, exceptions: &[String], capitalized: bool) -> Option<String> {
+    let core = word.trim_start_matches(|x: char| !x.is_alphanumeric());
+    let prefix = &word[..word.len() - core.len()];
+    let stripped = core.trim_end_matches(|x: char| !x.is_alphanumeric());
+
+    [core, stripped].into_iter().find_map(|candidate| {
+        let exception = exceptions
+            .iter()
+            .find(|x| x.to_lowercase() == candidate.to_lowercase())?;
+        let exception = if capitalized {
+            capitalize(exception)
+        } else {
+            exception.clone()
+        };
+        Some(format!("{prefix}{exception}{}", &core[candidate.len()..]))
+    })
+}
+
+/// Title cases a value. Words of `exceptions` are written as spelled there,
+/// lowercase ones being capitalized at the start and the end of the value.
+/// Values in all caps are lowercased first, other words keep their inner
+/// capitals, like `iPhone` or `II`.
+pub fn title_case(value: &str, exceptions: &[String]) -> String {
+    let shouting = value.chars().any(char::is_alphabetic)
+        && !value.chars().any(char::is_lowercase)
+        && value.chars().filter(|x| x.is_alphabetic()).count() > 1;
+    let value = if shouting {
+        value.to_lowercase()
+    } else {
+        value.to_owned()
+    };
+
+    let words: Vec<&str> = value.split(' ').collect();
+    words
+        .iter()
+        .enumerate()
+        .map(|(index, word)| {
+            let capitalized = index == 0 || index == words.len() - 1;
+            if let Some(word) = find_exception(word, exceptions, capitalized) {
+                word
+            } else if word.chars().skip(1).any(char::is_uppercase) {
+                word.to_string()
+            } else {
+                capitalize(word)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The name sorted without its leading `The `, if it has one.
+pub fn sort_name(value: &str) -> Option<String> {
+    let prefix = value.get(..4)?;
+    let rest = value[4..].trim_start();
+    (prefix.eq_ignore_ascii_case("the ") && !rest.is_empty()).then(|| rest.to_owned())
+}
+
+/// Normalizes the metadata of a file. Returns the fields which change with
+/// their new value, in the order of `EDITABLE_KEYS`.
+pub fn normalize_fields(
+    fields: &HashMap<String, String>,
+    rules: &NormalizationRules,
+) -> Vec<(String, String)> {
+    let mut normalized: HashMap<&str, String> = HashMap::new();
+    for (key, value) in fields {
+        let mut value = value.clone();
+
+        if rules.fix_mojibake
+            && let Some(fixed) = fix_mojibake(&value)
+        {
+            value = fixed;
+        }
+        if rules.trim_whitespace {
+            value = value.trim().to_owned();
+        }
+        if rules.collapse_spaces {
+            value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if rules.unify_featuring && FEATURING_KEYS.contains(&key.as_str()) {
+            value = unify_featuring(&value);
+        }
+        if rules.title_case && TITLE_CASE_KEYS.contains(&key.as_str()) {
+            value = title_case(&value, &rules.title_case_exceptions);
+        }
+
+        normalized.insert(key.as_str(), value);
+    }
+
+    if rules.sort_names {
+        for (name_key, sort_key) in SORT_KEYS {
+            if fields.contains_key(sort_key) {
+                continue;
+            }
+            if let Some(sort) = normalized.get(name_key).and_then(|x| sort_name(x)) {
+                normalized.insert(sort_key, sort);
+            }
+        }
+    }
+
+    EDITABLE_KEYS
+        .iter()
+        .filter_map(|key| {
+            let value = normalized.remove(key)?;
+            // Values normalized away are kept rather than emptied
+            if value.is_empty() || fields.get(*key) == Some(&value) {
+                return None;
+            }
+
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Computes what normalizing the metadata of files would change, without
+/// changing anything, so the changes can be reviewed before they are applied
+/// with `apply_metadata_changes`.
+///
+/// # Arguments
+/// * `main_db` - A reference to the database connection.
+/// * `file_ids` - The files to normalize, the whole library if empty.
+/// * `rules` - The rules to apply.
+///
+/// # Returns
+/// * `Result<Vec<MetadataChange>>` - The changes, or an error if a file
+///   doesn't exist.
+pub async fn normalize_metadata(
+    main_db: &DatabaseConnection,
+    file_ids: &[i32],
+    rules: &NormalizationRules,
+) -> Result<Vec<MetadataChange>> {
+    let file_ids = if file_ids.is_empty() {
+        media_files::Entity::find()
+            .select_only()
+            .column(media_files::Column::Id)
+            .order_by_asc(media_files::Column::Id)
+            .into_tuple::<i32>()
+            .all(main_db)
+            .await?
+    } else {
+        let files = get_files_by_ids(main_db, file_ids).await?;
+        for file_id in file_ids {
+            if !files.iter().any(|file| file.id == *file_id) {
+                bail!("File {file_id} does not exist");
+            }
+        }
+
+        file_ids.to_vec()
+    };
+
+    let mut changes = Vec::new();
+    for chunk in file_ids.chunks(NORMALIZE_BATCH_SIZE) {
+        let mut metadata = get_editable_metadata(main_db, chunk).await?;
+
+        for file_id in chunk {
+            let Some(fields) = metadata.remove(file_id) else {
+                continue;
+            };
+
+            for (key, new_value) in normalize_fields(&fields, rules) {
+                changes.push(MetadataChange {
+                    file_id: *file_id,
+                    old_value: fields.get(&key).cloned(),
+                    key,
+                    new_value,
+                });
+            }
+        }
+    }
+
+    Ok(changes)
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use deunicode::deunicode;
 use sea_orm::prelude::*;
-use sea_orm::{DatabaseConnection, DatabaseTransaction, EntityTrait};
+use sea_orm::{DatabaseConnection, DatabaseTransaction, EntityTrait, Statement};
 
 pub trait DatabaseExecutor: Send + Sync {}
 
@@ -358,3 +358,29 @@ macro_rules! parallel_media_files_processing {
         Ok(total_tasks)
     }};
 }
+
+/// Returns the size of the SQLite database behind the connection in bytes.
+pub async fn get_database_size(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let row = db
+        .query_one(Statement::from_string(
+            db.get_database_backend(),
+            "SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size()",
+        ))
+        .await?;
+
+    match row {
+        Some(row) => Ok(row.try_get::<i64>("", "size")?.max(0) as u64),
+        None => Ok(0),
+    }
+}
+
+/// Moves the write-ahead log back into the database file and truncates it.
+pub async fn checkpoint_database(db: &DatabaseConnection) -> Result<(), DbErr> {
+    db.execute(Statement::from_string(
+        db.get_database_backend(),
+        "PRAGMA wal_checkpoint(TRUNCATE)",
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use log::info;
+use sea_orm::{TransactionTrait, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use ::fsio::{FileIoError, FsIo};
+use ::metadata::describe::describe_file;
+
+use crate::actions::collection::CollectionQueryType;
+use crate::actions::search::remove_term;
+use crate::connection::acquire_write_permit;
+use crate::entities::media_files;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum FileProblemKind {
+    Missing,
+    /// The file exists but can't be read
+    Unreadable {
+        error: String,
+    },
+    /// The modification time differs from the one recorded by the last scan
+    Modified,
+    /// The content differs from the hash recorded by the last scan
+    HashMismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileProblem {
+    pub file_id: i32,
+    /// Path relative to the library root
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub kind: FileProblemKind,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub problems: Vec<FileProblem>,
+}
+
+impl VerifyReport {
+    pub fn missing_file_ids(&self) -> Vec<i32> {
+        self.problems
+            .iter()
+            .filter(|problem| problem.kind == FileProblemKind::Missing)
+            .map(|problem| problem.file_id)
+            .collect()
+    }
+}
+
+fn check_file(
+    fsio: &FsIo,
+    lib_path: &Path,
+    file: &media_files::Model,
+    hash: bool,
+) -> Option<FileProblemKind> {
+    let path = lib_path.join(&file.directory).join(&file.file_name);
+
+    let node = match fsio.metadata(&path) {
+        Ok(node) => node,
+        Err(FileIoError::PathNotFound(_)) => return Some(FileProblemKind::Missing),
+        Err(e) => {
+            return Some(FileProblemKind::Unreadable {
+                error: e.to_string(),
+            });
+        }
+    };
+
+    let modified = node
+        .modified
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_secs().to_string());
+    if !hash {
+        return match modified {
+            Some(modified) if modified != file.last_modified => Some(FileProblemKind::Modified),
+            _ => None,
+        };
+    }
+
+    let actual = describe_file(&node, &Some(lib_path.to_path_buf()))
+        .and_then(|mut description| description.get_crc(fsio));
+    match actual {
+        Ok(actual) if actual != file.file_hash => Some(FileProblemKind::HashMismatch {
+            expected: file.file_hash.clone(),
+            actual,
+        }),
+        Ok(_) => None,
+        Err(e) => Some(FileProblemKind::Unreadable {
+            error: format!("{e:#}"),
+        }),
+    }
+}
+
+/// Checks every file of the library against the disk, in pages of
+/// `page_size` files. Files are compared by modification time, or by
+/// content with `hash`, which reads every file. `progress` receives the
+/// number of files checked and the total.
+pub async fn verify_library_files<F>(
+    fsio: &FsIo,
+    main_db: &DatabaseConnection,
+    lib_path: &Path,
+    hash: bool,
+    page_size: u64,
+    mut progress: F,
+) -> Result<VerifyReport>
+where
+    F: FnMut(usize, usize),
+{
+    let total = media_files::Entity::find().count(main_db).await? as usize;
+    let mut report = VerifyReport::default();
+    let mut last_id = 0;
+
+    loop {
+        let page = media_files::Entity::find()
+            .filter(media_files::Column::Id.gt(last_id))
+            .order_by_asc(media_files::Column::Id)
+            .limit(page_size)
+            .all(main_db)
+            .await?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        for file in &page {
+            if let Some(kind) = check_file(fsio, lib_path, file, hash) {
+                report.problems.push(FileProblem {
+                    file_id: file.id,
+                    path: Path::new(&file.directory).join(&file.file_name),
+                    kind,
+                });
+            }
+
+            report.checked += 1;
+            progress(report.checked, total);
+        }
+    }
+
+    info!(
+        "Verified {} files, {} problems",
+        report.checked,
+        report.problems.len()
+    );
+
+    Ok(report)
+}
+
+/// Removes files from the library, like a scan does once they are gone
+/// from the disk.
+pub async fn remove_media_files(main_db: &DatabaseConnection, file_ids: &[i32]) -> Result<()> {
+    let _permit = acquire_write_permit().await;
+    let txn = main_db.begin().await?;
+
+    for file_id in file_ids {
+        media_files::Entity::delete_by_id(*file_id)
+            .exec(&txn)
+            .await?;
+        remove_term(&txn, CollectionQueryType::Track, *file_id).await?;
+    }
+
+    txn.commit().await?;
+
+    Ok(())
+}
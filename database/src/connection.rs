@@ -1,7 +1,9 @@
 use std::{
-    fs,
+    collections::BTreeMap,
+    fs::{self, TryLockError},
     path::{Path, PathBuf},
     str::FromStr,
+    time::{Duration, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result};
@@ -11,12 +13,17 @@ use arroy::{
     internals::{KeyCodec, NodeCodec},
 };
 use heed::{Env, EnvFlags, EnvOpenOptions};
-use log::info;
+use log::{debug, info, warn};
 use sea_orm::{
-    Database, SqlxSqliteConnector,
-    sqlx::{SqlitePool, sqlite::SqliteConnectOptions},
+    ConnectionTrait, Database, SqlxSqliteConnector, Statement,
+    sqlx::{
+        SqlitePool,
+        sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
+    },
 };
+use serde::{Deserialize, Serialize};
 use tempfile::tempdir;
+use tokio::sync::{Mutex, MutexGuard};
 use uuid::Uuid;
 #[cfg(windows)]
 use windows::Win32::Storage::FileSystem::{FILE_ATTRIBUTE_HIDDEN, SetFileAttributesW};
@@ -34,19 +41,49 @@ pub enum StorageMode {
     Redirected(Uuid),
 }
 
+pub(crate) const MAIN_DB_FILE: &str = ".0.db";
+pub(crate) const RECOMMENDATION_DB_FILE: &str = ".analysis.db";
+
+/// Name of the file locked by the processes using the databases of a
+/// library.
+const LIBRARY_LOCK_FILE: &str = ".lock";
+
+/// Name of the file written to find out whether a library can be written to.
+const WRITE_PROBE_FILE: &str = ".rune-write-probe";
+
+/// The write probe is retried before a library is taken for read-only, so
+/// a share that is slow to answer doesn't move its databases.
+const WRITE_PROBE_ATTEMPTS: u32 = 3;
+const WRITE_PROBE_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Name of the file recording that the databases of a library were moved
+/// to the per-user directory.
+const RELOCATION_FILE: &str = ".relocated";
+
+/// How long a connection waits for another one to release the database
+/// before failing with `database is locked`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Write transactions on the main database wait here for their turn, in the
+/// order they asked for it.
+static WRITE_QUEUE: Mutex<()> = Mutex::const_new(());
+
 pub struct StorageInfo {
     pub state: LibraryState,
     pub rune_dir: PathBuf,
     pub db_dir: PathBuf,
+    /// The library cannot be written to, its databases are kept in the
+    /// per-user directory instead of `.rune`.
+    pub read_only: bool,
 }
 
 impl StorageInfo {
     pub fn get_main_db_path(&self) -> PathBuf {
-        self.db_dir.join(".0.db")
+        self.db_dir.join(MAIN_DB_FILE)
     }
 
     pub fn get_recommendation_db_path(&self) -> PathBuf {
-        self.db_dir.join(".analysis.db")
+        self.db_dir.join(RECOMMENDATION_DB_FILE)
     }
 }
 
@@ -129,28 +166,295 @@ pub fn create_redirect(lib_path: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn get_storage_info(lib_path: &str, db_path: Option<&str>) -> Result<StorageInfo> {
+/// Tells whether files can be created at the root of a library, by writing
+/// a probe file through `fsio`.
+pub async fn probe_library_writable(fsio: &FsIo, lib_path: &str) -> bool {
+    let probe = Path::new(lib_path).join(WRITE_PROBE_FILE);
+
+    for attempt in 1..=WRITE_PROBE_ATTEMPTS {
+        match fsio.write(&probe, &[]).await {
+            Ok(()) => {
+                if let Err(e) = fsio.remove_file(&probe).await {
+                    warn!("Failed to remove the write probe {}: {e}", probe.display());
+                }
+                return true;
+            }
+            Err(e) if attempt < WRITE_PROBE_ATTEMPTS => {
+                debug!("Failed to write the probe of {lib_path}, retrying: {e}");
+                tokio::time::sleep(WRITE_PROBE_RETRY_DELAY).await;
+            }
+            Err(e) => info!("The library {lib_path} is read-only: {e}"),
+        }
+    }
+
+    false
+}
+
+/// Directory of the databases of a read-only library below the per-user
+/// directory, named after the path of the library.
+fn read_only_storage_id(lib_path: &str) -> Uuid {
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, lib_path.as_bytes())
+}
+
+/// The database files of a library, which follow it when it is relocated.
+fn database_files() -> [String; 3] {
+    [
+        MAIN_DB_FILE.to_owned(),
+        format!("{MAIN_DB_FILE}-wal"),
+        RECOMMENDATION_DB_FILE.to_owned(),
+    ]
+}
+
+/// Size and modification time (in milliseconds) of the database files in
+/// `dir`, to tell whether they changed.
+fn stamp_database_files(dir: &Path) -> BTreeMap<String, (u64, u64)> {
+    database_files()
+        .into_iter()
+        .filter_map(|name| {
+            let metadata = fs::metadata(dir.join(&name)).ok()?;
+            let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+            Some((name, (metadata.len(), modified.as_millis() as u64)))
+        })
+        .collect()
+}
+
+/// Written next to the databases of a library that was found read-only.
+#[derive(Debug, Serialize, Deserialize)]
+struct Relocation {
+    /// The database files left in `.rune` when the library was relocated
+    portable_files: BTreeMap<String, (u64, u64)>,
+}
+
+/// Tells whether the databases of a library are kept in the per-user
+/// directory below `db_path` because the library can't be written to.
+///
+/// The decision is recorded there, so the databases are moved back to
+/// `.rune` once the library is writable again. They are left in place if
+/// the databases in `.rune` changed in the meantime, or if another process
+/// still uses them.
+pub async fn resolve_library_read_only(
+    fsio: &FsIo,
+    lib_path: &str,
+    db_path: Option<&str>,
+) -> Result<bool> {
+    let writable = probe_library_writable(fsio, lib_path).await;
+
+    // Redirected libraries keep their databases in the per-user directory
+    // whether they are writable or not
+    let redirected = matches!(
+        check_library_state(lib_path)?,
+        LibraryState::Initialized(StorageMode::Redirected(_))
+    );
+    let Some(db_path) = db_path.filter(|_| !redirected) else {
+        return Ok(!writable);
+    };
+
+    let rune_dir: PathBuf = [lib_path, ".rune"].iter().collect();
+    let relocated_dir = PathBuf::from(db_path).join(read_only_storage_id(lib_path).to_string());
+    let relocation_path = relocated_dir.join(RELOCATION_FILE);
+
+    if !writable {
+        if !relocation_path.exists() {
+            info!(
+                "Keeping the databases of {lib_path} in {}",
+                relocated_dir.display()
+            );
+            let relocation = Relocation {
+                portable_files: stamp_database_files(&rune_dir),
+            };
+            fs::create_dir_all(&relocated_dir)?;
+            fs::write(&relocation_path, serde_json::to_vec(&relocation)?)
+                .with_context(|| format!("Failed to write {}", relocation_path.display()))?;
+        }
+
+        return Ok(true);
+    }
+
+    if !relocation_path.exists() {
+        return Ok(false);
+    }
+
+    restore_relocated_databases(lib_path, &rune_dir, &relocated_dir)
+}
+
+/// Moves the databases of a library that is writable again back to
+/// `.rune`. Returns whether the relocated databases are still to be used.
+fn restore_relocated_databases(
+    lib_path: &str,
+    rune_dir: &Path,
+    relocated_dir: &Path,
+) -> Result<bool> {
+    let relocation_path = relocated_dir.join(RELOCATION_FILE);
+    let relocation: Relocation = serde_json::from_slice(&fs::read(&relocation_path)?)
+        .with_context(|| format!("Invalid relocation record {}", relocation_path.display()))?;
+
+    if stamp_database_files(rune_dir) != relocation.portable_files {
+        warn!(
+            "The databases of {lib_path} changed while it was read-only, using them and leaving the copies in {}",
+            relocated_dir.display()
+        );
+        fs::remove_file(&relocation_path)?;
+        return Ok(false);
+    }
+
+    let relocated_info = StorageInfo {
+        state: check_library_state(lib_path)?,
+        rune_dir: rune_dir.to_path_buf(),
+        db_dir: relocated_dir.to_path_buf(),
+        read_only: true,
+    };
+    let Some(_lock) = lock_library(&relocated_info, LockMode::Exclusive, false)? else {
+        info!("The databases of {lib_path} are in use, moving them back later");
+        return Ok(true);
+    };
+
+    info!("The library {lib_path} is writable again, moving its databases back");
+    if !rune_dir.exists() {
+        fs::create_dir_all(rune_dir)?;
+        #[cfg(windows)]
+        set_hidden_attribute(rune_dir)?;
+    }
+
+    // The log of the databases left behind doesn't belong to the relocated
+    // copy
+    if relocated_dir.join(MAIN_DB_FILE).exists() {
+        for name in [format!("{MAIN_DB_FILE}-wal"), format!("{MAIN_DB_FILE}-shm")] {
+            let stale = rune_dir.join(name);
+            if stale.exists() {
+                fs::remove_file(&stale)
+                    .with_context(|| format!("Failed to remove {}", stale.display()))?;
+            }
+        }
+    }
+
+    for name in database_files() {
+        let source = relocated_dir.join(&name);
+        if !source.exists() {
+            continue;
+        }
+
+        let target = rune_dir.join(&name);
+        fs::copy(&source, &target)
+            .with_context(|| format!("Failed to copy {}", source.display()))?;
+        fs::remove_file(&source)?;
+    }
+
+    let shared_memory = relocated_dir.join(format!("{MAIN_DB_FILE}-shm"));
+    if shared_memory.exists() {
+        fs::remove_file(&shared_memory)?;
+    }
+
+    // Removed last, the copies of an interrupted move are left in place and
+    // reported on the next start
+    fs::remove_file(&relocation_path)?;
+
+    Ok(false)
+}
+
+pub fn get_storage_info(
+    lib_path: &str,
+    db_path: Option<&str>,
+    read_only: bool,
+) -> Result<StorageInfo> {
     let rune_dir: PathBuf = [lib_path, ".rune"].iter().collect();
     let state = check_library_state(lib_path)?;
 
     let db_dir = match &state {
-        LibraryState::Uninitialized => rune_dir.clone(),
-        LibraryState::Initialized(mode) => match mode {
-            StorageMode::Portable => rune_dir.clone(),
-            StorageMode::Redirected(uuid) => {
-                let db_path = db_path.context("db_path is required for redirected storage")?;
-                PathBuf::from(db_path).join(uuid.to_string())
-            }
-        },
+        LibraryState::Initialized(StorageMode::Redirected(uuid)) => {
+            let db_path = db_path.context("db_path is required for redirected storage")?;
+            PathBuf::from(db_path).join(uuid.to_string())
+        }
+        _ if read_only => {
+            let db_path = db_path.context("db_path is required for read-only libraries")?;
+            PathBuf::from(db_path).join(read_only_storage_id(lib_path).to_string())
+        }
+        _ => rune_dir.clone(),
     };
 
     Ok(StorageInfo {
         state,
         rune_dir,
         db_dir,
+        read_only,
     })
 }
 
+/// Copies the databases of a portable library which became read-only to
+/// the per-user directory the first time it is opened that way, so its
+/// history is kept.
+fn adopt_portable_databases(storage_info: &StorageInfo) -> Result<()> {
+    if !storage_info.read_only
+        || storage_info.state != LibraryState::Initialized(StorageMode::Portable)
+    {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&storage_info.db_dir)?;
+
+    // The write-ahead log can't be checkpointed on a read-only volume, it
+    // is copied along with the main database
+    let main_wal = format!("{MAIN_DB_FILE}-wal");
+    for name in [MAIN_DB_FILE, main_wal.as_str(), RECOMMENDATION_DB_FILE] {
+        let source = storage_info.rune_dir.join(name);
+        let target = storage_info.db_dir.join(name);
+        if source.exists() && !target.exists() {
+            info!("Copying {} to {}", source.display(), target.display());
+            fs::copy(&source, &target)
+                .with_context(|| format!("Failed to copy {}", source.display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Taken by every process using the library
+    Shared,
+    /// Taken by maintenance tasks which need the databases to themselves
+    Exclusive,
+}
+
+/// Lock on the databases of a library, released when dropped.
+#[derive(Debug)]
+pub struct LibraryLock {
+    _file: fs::File,
+}
+
+/// Locks the databases of a library. Returns `None` if another process
+/// holds a conflicting lock, or waits for it to be released if `wait` is
+/// set.
+pub fn lock_library(
+    storage_info: &StorageInfo,
+    mode: LockMode,
+    wait: bool,
+) -> Result<Option<LibraryLock>> {
+    fs::create_dir_all(&storage_info.db_dir)?;
+
+    let path = storage_info.db_dir.join(LIBRARY_LOCK_FILE);
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let locked = match (mode, wait) {
+        (LockMode::Shared, false) => file.try_lock_shared(),
+        (LockMode::Exclusive, false) => file.try_lock(),
+        (LockMode::Shared, true) => file.lock_shared().map_err(TryLockError::Error),
+        (LockMode::Exclusive, true) => file.lock().map_err(TryLockError::Error),
+    };
+
+    match locked {
+        Ok(()) => Ok(Some(LibraryLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+        }
+    }
+}
+
 pub type MainDbConnection = sea_orm::DatabaseConnection;
 
 pub async fn connect_main_db(
@@ -159,7 +463,22 @@ pub async fn connect_main_db(
     db_path: Option<&str>,
     node_id: &str,
 ) -> Result<MainDbConnection> {
-    let storage_info = get_storage_info(lib_path, db_path)?;
+    let db = open_main_db(fsio, lib_path, db_path).await?;
+    initialize_db(&db, node_id).await?;
+
+    Ok(db)
+}
+
+/// Opens the main database without applying the pending migrations, for
+/// maintenance tasks which migrate it themselves.
+pub async fn open_main_db(
+    fsio: &FsIo,
+    lib_path: &str,
+    db_path: Option<&str>,
+) -> Result<MainDbConnection> {
+    let read_only = resolve_library_read_only(fsio, lib_path, db_path).await?;
+    let storage_info = get_storage_info(lib_path, db_path, read_only)?;
+    adopt_portable_databases(&storage_info)?;
     let db_path = storage_info.get_main_db_path();
 
     if !storage_info.db_dir.exists() {
@@ -172,17 +491,71 @@ pub async fn connect_main_db(
         fsio.canonicalize_path(&db_path.path)?.to_string_lossy()
     );
 
-    let connection_options = SqliteConnectOptions::from_str(&db_url)?;
+    // Readers don't block the writer in WAL mode, and every connection of
+    // the pool waits for the others instead of failing right away
+    let connection_options = SqliteConnectOptions::from_str(&db_url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(BUSY_TIMEOUT)
+        .foreign_keys(true);
 
     let pool = SqlitePool::connect_with(connection_options).await?;
 
     info!("Initializing main database: {}", { db_url });
 
-    let db = SqlxSqliteConnector::from_sqlx_sqlite_pool(pool);
+    Ok(SqlxSqliteConnector::from_sqlx_sqlite_pool(pool))
+}
 
-    initialize_db(&db, node_id).await?;
+/// The turn of a write transaction on the main database, the next one starts
+/// once it is dropped.
+pub type WritePermit = MutexGuard<'static, ()>;
+
+/// Waits for the turn of a write transaction on the main database.
+///
+/// SQLite has a single writer, and a connection waiting on `busy_timeout`
+/// may never get its turn while a scan commits batch after batch. Writers
+/// taking a permit are served first come, first served instead. Keep the
+/// permit until the transaction is committed or rolled back, and drop it
+/// before starting another write.
+pub async fn acquire_write_permit() -> WritePermit {
+    WRITE_QUEUE.lock().await
+}
 
-    Ok(db)
+/// The sizes of the database files of a library, in bytes, 0 for the files
+/// which don't exist.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatabaseFileSizes {
+    pub main_bytes: u64,
+    /// The write-ahead log, not checkpointed into the main database yet
+    pub main_wal_bytes: u64,
+    pub recommendation_bytes: u64,
+}
+
+/// Reads the sizes of the database files next to the main database.
+pub async fn database_file_sizes(main_db: &MainDbConnection) -> Result<DatabaseFileSizes> {
+    let row = main_db
+        .query_one(Statement::from_string(
+            main_db.get_database_backend(),
+            "PRAGMA database_list",
+        ))
+        .await?
+        .context("The main database is not attached")?;
+    let file: String = row.try_get("", "file")?;
+
+    // In-memory databases have no file
+    if file.is_empty() {
+        return Ok(DatabaseFileSizes::default());
+    }
+
+    let size = |path: &Path| fs::metadata(path).map(|x| x.len()).unwrap_or_default();
+    let main_path = PathBuf::from(&file);
+    let db_dir = main_path.parent().unwrap_or(Path::new(""));
+
+    Ok(DatabaseFileSizes {
+        main_bytes: size(&main_path),
+        main_wal_bytes: size(Path::new(&format!("{file}-wal"))),
+        recommendation_bytes: size(&db_dir.join(RECOMMENDATION_DB_FILE)),
+    })
 }
 
 pub async fn initialize_db(conn: &sea_orm::DatabaseConnection, node_id: &str) -> Result<()> {
@@ -216,7 +589,9 @@ pub async fn connect_recommendation_db(
     lib_path: &str,
     db_path: Option<&str>,
 ) -> Result<RecommendationDbConnection> {
-    let storage_info = get_storage_info(lib_path, db_path)?;
+    let read_only = resolve_library_read_only(fsio, lib_path, db_path).await?;
+    let storage_info = get_storage_info(lib_path, db_path, read_only)?;
+    adopt_portable_databases(&storage_info)?;
     let analysis_path = storage_info.get_recommendation_db_path();
 
     if !storage_info.db_dir.exists() {
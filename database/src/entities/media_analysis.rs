@@ -69,6 +69,8 @@ pub struct Model {
     pub mfcc10: Option<Decimal>,
     pub mfcc11: Option<Decimal>,
     pub mfcc12: Option<Decimal>,
+    pub integrated_loudness: Option<Decimal>,
+    pub peak: Option<Decimal>,
     pub hlc_uuid: String,
     #[sea_orm(column_type = "Text")]
     pub created_at_hlc_ts: String,
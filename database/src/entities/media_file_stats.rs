@@ -1,8 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "media_file_stats")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -13,6 +14,26 @@ pub struct Model {
     pub played_through: i32,
     #[sea_orm(column_type = "Text")]
     pub updated_at: String,
+    pub rating: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_skipped_at: Option<String>,
+    #[sea_orm(column_type = "Double")]
+    pub total_listened_seconds: f64,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub last_position_seconds: Option<f64>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub first_played_at: Option<String>,
+    pub hlc_uuid: String,
+    #[sea_orm(column_type = "Text")]
+    pub created_at_hlc_ts: String,
+    pub created_at_hlc_ver: i32,
+    #[sea_orm(column_type = "Text")]
+    pub created_at_hlc_nid: String,
+    #[sea_orm(column_type = "Text")]
+    pub updated_at_hlc_ts: String,
+    pub updated_at_hlc_ver: i32,
+    #[sea_orm(column_type = "Text")]
+    pub updated_at_hlc_nid: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
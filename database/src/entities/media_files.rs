@@ -21,6 +21,14 @@ pub struct Model {
     pub cover_art_id: Option<i32>,
     pub sample_rate: i32,
     pub duration: Decimal,
+    pub musicbrainz_recording_id: Option<String>,
+    pub musicbrainz_track_id: Option<String>,
+    pub musicbrainz_release_id: Option<String>,
+    pub musicbrainz_release_group_id: Option<String>,
+    pub musicbrainz_artist_ids: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    #[serde(default)]
+    pub first_seen_at: String,
     pub hlc_uuid: String,
     #[sea_orm(column_type = "Text")]
     pub created_at_hlc_ts: String,
@@ -4,14 +4,19 @@ pub mod prelude;
 
 pub mod albums;
 pub mod artists;
+pub mod audit_log;
 pub mod genres;
+pub mod library_settings;
 pub mod log;
 pub mod media_analysis;
+pub mod media_analysis_failure;
 pub mod media_cover_art;
 pub mod media_file_albums;
 pub mod media_file_artists;
+pub mod media_file_content_match;
 pub mod media_file_fingerprint;
 pub mod media_file_genres;
+pub mod media_file_landmark;
 pub mod media_file_playlists;
 pub mod media_file_similarity;
 pub mod media_file_stats;
@@ -21,5 +26,7 @@ pub mod mix_queries;
 pub mod mixes;
 pub mod playback_queue;
 pub mod playlists;
+pub mod scrobble_history;
+pub mod scrobble_queue;
 pub mod search_index;
 pub mod sync_record;
@@ -2,14 +2,19 @@
 
 pub use super::albums::Entity as Albums;
 pub use super::artists::Entity as Artists;
+pub use super::audit_log::Entity as AuditLog;
 pub use super::genres::Entity as Genres;
+pub use super::library_settings::Entity as LibrarySettings;
 pub use super::log::Entity as Log;
 pub use super::media_analysis::Entity as MediaAnalysis;
+pub use super::media_analysis_failure::Entity as MediaAnalysisFailure;
 pub use super::media_cover_art::Entity as MediaCoverArt;
 pub use super::media_file_albums::Entity as MediaFileAlbums;
 pub use super::media_file_artists::Entity as MediaFileArtists;
+pub use super::media_file_content_match::Entity as MediaFileContentMatch;
 pub use super::media_file_fingerprint::Entity as MediaFileFingerprint;
 pub use super::media_file_genres::Entity as MediaFileGenres;
+pub use super::media_file_landmark::Entity as MediaFileLandmark;
 pub use super::media_file_playlists::Entity as MediaFilePlaylists;
 pub use super::media_file_similarity::Entity as MediaFileSimilarity;
 pub use super::media_file_stats::Entity as MediaFileStats;
@@ -19,4 +24,6 @@ pub use super::mix_queries::Entity as MixQueries;
 pub use super::mixes::Entity as Mixes;
 pub use super::playback_queue::Entity as PlaybackQueue;
 pub use super::playlists::Entity as Playlists;
+pub use super::scrobble_history::Entity as ScrobbleHistory;
+pub use super::scrobble_queue::Entity as ScrobbleQueue;
 pub use super::search_index::Entity as SearchIndex;
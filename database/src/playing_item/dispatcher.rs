@@ -5,6 +5,7 @@ use std::{
 
 use anyhow::Result;
 use sea_orm::DatabaseConnection;
+use tokio_util::sync::CancellationToken;
 
 use ::fsio::FsIo;
 use ::playback::player::PlayingItem;
@@ -26,7 +27,18 @@ impl PlayingItemActionDispatcher {
         Self {
             in_library_processor: Box::new(LibraryItemProcessor),
             independent_file_processor: Box::new(IndependentFileProcessor),
-            online_file_processor: Box::new(OnlineFileProcessor),
+            online_file_processor: Box::new(OnlineFileProcessor::default()),
+        }
+    }
+
+    /// A dispatcher whose requests to remote libraries are aborted once
+    /// `cancel_token` is cancelled, like when the library is closed.
+    pub fn with_cancel_token(cancel_token: CancellationToken) -> Self {
+        Self {
+            online_file_processor: Box::new(OnlineFileProcessor {
+                cancel_token: Some(cancel_token),
+            }),
+            ..Self::new()
         }
     }
 
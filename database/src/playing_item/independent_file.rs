@@ -156,6 +156,7 @@ impl PlayingFileMetadataProvider for IndependentFileProcessor {
                 let color_file: PathBuf = COVER_TEMP_DIR.clone().join(color_file_name);
 
                 if !image_file.exists() {
+                    fsio.ensure_space(&image_file, cover_art.data.len() as u64)?;
                     fs::write(image_file.clone(), cover_art.data)?;
                 }
 
@@ -12,12 +12,13 @@ use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use ::discovery::{client::CertValidator, config::get_config_dir};
+use ::discovery::{client::CertValidator, config::get_config_dir, endpoint::ServerEndpoint};
 use ::fsio::FsIo;
+use ::http_request::{Bytes, ClientConfig};
 use ::http_request::{
-    BodyExt, Empty, Request, StatusCode, Uri, create_https_client, send_http_request,
+    CancellationToken, ConnectionPool, DownloadOptions, Empty, Request, RequestOptions, StatusCode,
+    Uri, download_to, send_request_with_options,
 };
-use ::http_request::{Bytes, ClientConfig};
 use ::metadata::{
     cover_art::extract_cover_art_from_stream, streaming::get_metadata_and_codec_from_stream,
 };
@@ -25,6 +26,9 @@ use ::playback::{player::PlayingItem, stream_utils::create_stream_media_source_f
 
 use super::{MediaFileHandle, PlayingFileMetadataProvider, PlayingItemMetadataSummary};
 
+/// Cover arts larger than this aren't downloaded.
+const MAX_COVER_ART_SIZE: u64 = 32 * 1024 * 1024;
+
 pub fn extract_online_file_urls(items: &[PlayingItem]) -> Vec<(PlayingItem, String)> {
     items
         .iter()
@@ -73,35 +77,35 @@ pub async fn get_media_metadata(
     host: &str,
     config: Arc<ClientConfig>,
     file_id: i64,
+    options: &RequestOptions,
 ) -> Result<MediaMetadataResponse> {
+    let endpoint = ServerEndpoint::parse(host)?;
     let uri = Uri::builder()
         .scheme("https")
-        .authority(format!("{host}:7863"))
-        .path_and_query(format!("/media/{file_id}/metadata"))
+        .authority(endpoint.authority())
+        .path_and_query(endpoint.path(&format!("/media/{file_id}/metadata")))
         .build()
         .context("Invalid URL format")?;
 
-    let mut sender = create_https_client(host.to_owned(), 7863, config)
-        .await
-        .context("Failed to create HTTPS client")?;
-
     let req = Request::builder()
         .uri(uri)
         .header("Accept", "application/json")
         .body(Empty::<Bytes>::new())
         .context("Failed to build request")?;
 
-    let res = send_http_request(&mut sender, req)
-        .await
-        .context("Failed to execute request")?;
+    let res = send_request_with_options(
+        ConnectionPool::shared(),
+        &endpoint.host,
+        endpoint.api_port,
+        config,
+        req,
+        options,
+    )
+    .await
+    .context("Failed to execute request")?;
 
     let status = res.status();
-    let body = res
-        .into_body()
-        .collect()
-        .await
-        .context("Failed to read response body")?
-        .to_bytes();
+    let body = res.into_body();
 
     if status != StatusCode::OK {
         let error_message = String::from_utf8_lossy(&body);
@@ -118,53 +122,71 @@ pub async fn get_media_metadata(
     Ok(response)
 }
 
-pub async fn get_cover_art(host: &str, config: Arc<ClientConfig>, file_id: i64) -> Result<Bytes> {
+/// Downloads the cover art of a remote file to `path`.
+pub async fn download_cover_art(
+    fsio: &FsIo,
+    host: &str,
+    config: Arc<ClientConfig>,
+    file_id: i64,
+    path: &Path,
+    options: &RequestOptions,
+) -> Result<()> {
+    let endpoint = ServerEndpoint::parse(host)?;
     let uri = Uri::builder()
         .scheme("https")
-        .authority(format!("{host}:7863"))
-        .path_and_query(format!("/media/{file_id}/cover"))
+        .authority(endpoint.authority())
+        .path_and_query(endpoint.path(&format!("/media/{file_id}/cover")))
         .build()
         .context("Invalid URL format")?;
 
-    let mut sender = create_https_client(host.to_owned(), 7863, config)
-        .await
-        .context("Failed to create HTTPS client")?;
-
     let req = Request::builder()
         .uri(uri)
         .body(Empty::<Bytes>::new())
         .context("Failed to build request")?;
 
-    let res = send_http_request(&mut sender, req)
-        .await
-        .context("Failed to execute request")?;
+    let options = DownloadOptions {
+        request: options.clone(),
+        max_size: Some(MAX_COVER_ART_SIZE),
+        ..Default::default()
+    };
+    download_to(
+        fsio,
+        ConnectionPool::shared(),
+        config,
+        req,
+        path,
+        &options,
+        |_, _| {},
+    )
+    .await
+    .context("Failed to download cover art")?;
+
+    Ok(())
+}
 
-    let status = res.status();
-    let body = res
-        .into_body()
-        .collect()
-        .await
-        .context("Failed to read response body")?
-        .to_bytes();
+/// Fetches what it knows of the files of remote libraries from their
+/// servers. The fetches in flight are aborted once the cancellation token,
+/// if any, is cancelled.
+#[derive(Default)]
+pub struct OnlineFileProcessor {
+    pub cancel_token: Option<CancellationToken>,
+}
 
-    if status != StatusCode::OK {
-        let error_message = String::from_utf8_lossy(&body);
-        return Err(anyhow::anyhow!(
-            "Get cover art failed with status code {}: {}",
-            status,
-            error_message
-        ));
+impl OnlineFileProcessor {
+    fn request_options(&self) -> RequestOptions {
+        let options = RequestOptions::idempotent();
+        match &self.cancel_token {
+            Some(cancel_token) => options.with_cancel_token(cancel_token.clone()),
+            None => options,
+        }
     }
-
-    Ok(body)
 }
 
-pub struct OnlineFileProcessor;
-
 async fn get_summary_for_online_in_library_file(
     item: &PlayingItem,
     host: &str,
     file_id: i32,
+    options: &RequestOptions,
 ) -> Result<PlayingItemMetadataSummary> {
     let config_path = get_config_dir()?;
     let cert_validator = Arc::new(
@@ -174,7 +196,7 @@ async fn get_summary_for_online_in_library_file(
     );
     let client_config = Arc::new(cert_validator.into_client_config());
 
-    let metadata = get_media_metadata(host, client_config, file_id as i64).await?;
+    let metadata = get_media_metadata(host, client_config, file_id as i64, options).await?;
     Ok(PlayingItemMetadataSummary {
         item: item.clone(),
         title: metadata.file.title,
@@ -244,14 +266,17 @@ impl PlayingFileMetadataProvider for OnlineFileProcessor {
         _main_db: &DatabaseConnection,
         items: &[PlayingItem],
     ) -> Result<Vec<PlayingItemMetadataSummary>> {
+        let options = self.request_options();
         let futures = items.iter().filter_map(|item| {
             if let PlayingItem::Online(url, online_file_opt) = item {
+                let options = &options;
                 Some(async move {
                     let result = if let Some(online_file) = online_file_opt {
                         get_summary_for_online_in_library_file(
                             item,
                             &online_file.host,
                             online_file.id,
+                            options,
                         )
                         .await
                     } else {
@@ -290,6 +315,7 @@ impl PlayingFileMetadataProvider for OnlineFileProcessor {
                 .with_context(|| "Failed to create the cert validator")?,
         );
         let client_config = Arc::new(cert_validator.into_client_config());
+        let options = self.request_options();
 
         let futures = items.iter().filter_map(|item| {
             if let PlayingItem::Online(url, online_file_opt) = item {
@@ -299,29 +325,37 @@ impl PlayingFileMetadataProvider for OnlineFileProcessor {
                 let online_file_opt_clone = online_file_opt.clone();
 
                 let client_config = Arc::clone(&client_config);
+                let options = &options;
                 Some(async move {
-                    let cover_art_data = if let Some(online_file) = online_file_opt_clone {
-                        get_cover_art(&online_file.host, client_config, online_file.id as i64)
-                            .await
-                            .map(|bytes| bytes.to_vec())
+                    let cache_dir = lib_path.join("cache").join("covers");
+                    if !fsio.exists(&cache_dir).unwrap_or(false) {
+                        fsio.create_dir_all(&cache_dir)?;
+                    }
+                    let file_path = cache_dir.join(format!("{}.jpg", Uuid::new_v4()));
+
+                    let saved = if let Some(online_file) = online_file_opt_clone {
+                        download_cover_art(
+                            fsio,
+                            &online_file.host,
+                            client_config,
+                            online_file.id as i64,
+                            &file_path,
+                            options,
+                        )
+                        .await
                     } else {
                         let source = create_stream_media_source_from_url(&url_clone).await?;
-                        extract_cover_art_from_stream(source, "")
-                            .await
-                            .map(|cover_art| cover_art.data)
+                        match extract_cover_art_from_stream(source, "").await {
+                            Ok(cover_art) => fsio
+                                .write(&file_path, &cover_art.data)
+                                .await
+                                .map_err(Into::into),
+                            Err(e) => Err(e),
+                        }
                     };
 
-                    match cover_art_data {
-                        Ok(data) => {
-                            let file_name = format!("{}.jpg", Uuid::new_v4());
-                            let cache_dir = lib_path.join("cache").join("covers");
-                            if !fsio.exists(&cache_dir).unwrap_or(false) {
-                                fsio.create_dir_all(&cache_dir)?;
-                            }
-                            let file_path = cache_dir.join(file_name);
-                            fsio.write(&file_path, &data).await?;
-                            Ok((item_clone, file_path.to_string_lossy().to_string()))
-                        }
+                    match saved {
+                        Ok(()) => Ok((item_clone, file_path.to_string_lossy().to_string())),
                         Err(e) => Err(anyhow!(
                             "Failed to get cover art for {:?}: {}",
                             item_clone,
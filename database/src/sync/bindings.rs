@@ -10,7 +10,8 @@ use sync::{
 
 use crate::entities::{
     albums, artists, genres, media_cover_art, media_file_albums, media_file_artists,
-    media_file_fingerprint, media_file_genres, media_file_similarity, media_files,
+    media_file_fingerprint, media_file_genres, media_file_similarity, media_file_stats,
+    media_files,
 };
 
 // Albums
@@ -50,7 +51,9 @@ impl_hlc_model_for_entity!(
     media_files::Column::HlcUuid,
     media_files::Column::UpdatedAtHlcTs,
     media_files::Column::UpdatedAtHlcVer,
-    media_files::Column::UpdatedAtHlcNid
+    media_files::Column::UpdatedAtHlcNid,
+    // Each device keeps the time it first saw the file
+    immutable = [media_files::Column::FirstSeenAt]
 );
 
 // MediaFileAlbums
@@ -103,6 +106,16 @@ impl_hlc_model_for_entity!(
     media_file_similarity::Column::UpdatedAtHlcNid
 );
 
+// MediaFileStats
+impl_hlc_record_for_model!(media_file_stats::Model);
+impl_hlc_model_for_entity!(
+    media_file_stats::Entity,
+    media_file_stats::Column::HlcUuid,
+    media_file_stats::Column::UpdatedAtHlcTs,
+    media_file_stats::Column::UpdatedAtHlcVer,
+    media_file_stats::Column::UpdatedAtHlcNid
+);
+
 // MediaCoverArt
 impl_hlc_record_for_model!(media_cover_art::Model);
 impl_hlc_model_for_entity!(
@@ -123,3 +136,4 @@ impl_primary_key_from_str_for_i32_pk!(media_file_genres::PrimaryKey, i32);
 impl_primary_key_from_str_for_i32_pk!(media_cover_art::PrimaryKey, i32);
 impl_primary_key_from_str_for_i32_pk!(media_file_fingerprint::PrimaryKey, i32);
 impl_primary_key_from_str_for_i32_pk!(media_file_similarity::PrimaryKey, i32);
+impl_primary_key_from_str_for_i32_pk!(media_file_stats::PrimaryKey, i32);
@@ -19,9 +19,11 @@ use sync::chunking::{break_data_chunk, generate_data_chunks};
 use uuid::Uuid;
 
 use crate::{
+    connection::acquire_write_permit,
     entities::{
         albums, artists, genres, media_cover_art, media_file_albums, media_file_artists,
-        media_file_fingerprint, media_file_genres, media_file_similarity, media_files, sync_record,
+        media_file_fingerprint, media_file_genres, media_file_similarity, media_file_stats,
+        media_files, sync_record,
     },
     sync::utils::parse_hlc,
 };
@@ -200,6 +202,15 @@ pub async fn get_remote_chunks_handler(
             )
             .await?
         }
+        "media_file_stats" => {
+            generate_data_chunks::<media_file_stats::Entity, _>(
+                db,
+                &options,
+                after_hlc,
+                Some(fk_resolver),
+            )
+            .await?
+        }
         _ => {
             return Err(AppError(anyhow!(
                 "Unsupported table name for chunks: {}",
@@ -314,6 +325,15 @@ pub async fn get_remote_sub_chunks_handler(
             )
             .await?
         }
+        "media_file_stats" => {
+            break_data_chunk::<media_file_stats::Entity, _>(
+                db,
+                &payload.parent_chunk,
+                payload.sub_chunk_size,
+                Some(fk_resolver),
+            )
+            .await?
+        }
         _ => {
             return Err(AppError(anyhow!(
                 "Unsupported table name for sub_chunks: {}",
@@ -480,6 +500,15 @@ pub async fn get_remote_records_in_hlc_range_handler(
             )
             .await?,
         )?,
+        "media_file_stats" => serde_json::to_value(
+            fetch_records_with_fk_payloads::<media_file_stats::Entity, _>(
+                db,
+                &start_hlc,
+                &end_hlc,
+                fk_resolver,
+            )
+            .await?,
+        )?,
         _ => {
             return Err(AppError(anyhow!(
                 "Unsupported table name for records: {}",
@@ -570,6 +599,10 @@ where
                     for pk_col in E::PrimaryKey::iter() {
                         active_model.reset(pk_col.into_column());
                     }
+                    // Immutable columns keep the value the record was created with.
+                    for column in E::immutable_columns() {
+                        active_model.not_set(column);
+                    }
 
                     // Use `update_many` filtered by the logical unique_id.
                     // This correctly applies only the `Set` fields from `active_model`.
@@ -641,6 +674,7 @@ pub async fn apply_remote_changes_handler(
     let db = &state.db;
     let fk_resolver = state.fk_resolver.as_ref();
 
+    let _permit = acquire_write_permit().await;
     let txn = db.begin().await.context("Failed to begin transaction")?;
     debug!("Transaction started for apply_remote_changes on table {table_name}");
 
@@ -716,6 +750,15 @@ pub async fn apply_remote_changes_handler(
             )
             .await?
         }
+        "media_file_stats" => {
+            process_entity_changes::<media_file_stats::Entity, _>(
+                &txn,
+                &body,
+                fk_resolver,
+                &table_name,
+            )
+            .await?
+        }
         _ => {
             txn.rollback()
                 .await
@@ -20,9 +20,15 @@ use crate::sync::chunking::{ApplyChangesPayload, GetRemoteSubChunksPayload};
 
 impl RemoteHttpDataSource {
     pub fn new(base_url: &str) -> Self {
+        Self::with_client(base_url, reqwest::Client::new())
+    }
+
+    /// Uses a preconfigured client, e.g. one trusting the peer's
+    /// certificate and presenting this device's one.
+    pub fn with_client(base_url: &str, client: reqwest::Client) -> Self {
         Self {
-            base_url: base_url.to_string(),
-            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client,
         }
     }
 
@@ -21,7 +21,8 @@ use ::sync::{
 
 use crate::entities::{
     albums, artists, genres, media_cover_art, media_file_albums, media_file_artists,
-    media_file_fingerprint, media_file_genres, media_file_similarity, media_files,
+    media_file_fingerprint, media_file_genres, media_file_similarity, media_file_stats,
+    media_files,
 };
 
 /// Foreign key resolver implementation for the Rune.
@@ -651,6 +652,18 @@ impl_junction_table_fk_ops!(
     ]
 );
 
+impl_junction_table_fk_ops!(
+    media_file_stats::Model,
+    media_file_stats::ActiveModel,
+    "media_file_stats",
+    [(
+        media_file_id,
+        media_file_stats::Column::MediaFileId,
+        media_files::Entity,
+        media_files::Column::Id
+    )]
+);
+
 #[async_trait]
 impl ModelWithForeignKeyOps for media_files::Model {
     async fn extract_model_fk_sync_ids<E: DatabaseExecutor>(&self, db: &E) -> Result<FkPayload> {
@@ -3,12 +3,14 @@ use std::sync::Arc;
 
 use foreign_keys::RuneForeignKeyResolver;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityName, EntityTrait, QueryFilter, Set,
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityName, EntityTrait, PaginatorTrait,
+    QueryFilter, Set,
 };
+use serde::{Deserialize, Serialize};
 use sync::{
     chunking::ChunkingOptions,
     core::{RemoteDataSource, SyncContext, SyncDirection, SyncTableMetadata},
-    hlc::{HLC, SyncTaskContext},
+    hlc::{HLC, HLCModel, HLCRecord, SyncTaskContext},
     sync_scheduler::{SyncScheduler, TableSyncJob, TableSyncResult},
 };
 use uuid::Uuid;
@@ -23,19 +25,61 @@ pub mod data_source;
 pub mod foreign_keys;
 pub mod utils;
 
+/// Which part of the database a sync run covers.
+#[derive(Debug, Clone)]
+pub struct SyncRunOptions {
+    pub direction: SyncDirection,
+    /// Only sync these tables, every table if `None`
+    pub tables: Option<Vec<String>>,
+}
+
+impl Default for SyncRunOptions {
+    fn default() -> Self {
+        Self {
+            direction: SyncDirection::Bidirectional,
+            tables: None,
+        }
+    }
+}
+
 pub async fn setup_and_run_sync<'s, RDS: RemoteDataSource + Debug + Send + Sync + 'static>(
     db: &'s DatabaseConnection,
     local_node_id: Uuid,
     remote_data_source_ref: &'s RDS,
     hlc_task_context_ref: &'s SyncTaskContext,
 ) -> anyhow::Result<Vec<TableSyncResult>> {
+    setup_and_run_sync_with_options(
+        db,
+        local_node_id,
+        remote_data_source_ref,
+        hlc_task_context_ref,
+        SyncRunOptions::default(),
+        |_| {},
+    )
+    .await
+}
+
+/// Runs a sync with the given direction and tables, calling `on_result`
+/// with the result of every table as soon as it completes.
+pub async fn setup_and_run_sync_with_options<'s, RDS, F>(
+    db: &'s DatabaseConnection,
+    local_node_id: Uuid,
+    remote_data_source_ref: &'s RDS,
+    hlc_task_context_ref: &'s SyncTaskContext,
+    options: SyncRunOptions,
+    on_result: F,
+) -> anyhow::Result<Vec<TableSyncResult>>
+where
+    RDS: RemoteDataSource + Debug + Send + Sync + 'static,
+    F: FnMut(&TableSyncResult),
+{
     let sync_context = SyncContext::<'s, RDS> {
         // R in SyncContext is RDS
         db,
         local_node_id,
         remote_source: remote_data_source_ref,
         chunking_options: ChunkingOptions::default(local_node_id),
-        sync_direction: SyncDirection::Bidirectional,
+        sync_direction: options.direction,
         hlc_context: hlc_task_context_ref,
     };
 
@@ -59,7 +103,7 @@ pub async fn setup_and_run_sync<'s, RDS: RemoteDataSource + Debug + Send + Sync
 
     // Define the sync order based on table dependencies (topological sort).
     // Parent tables must be synced before their dependent child tables.
-    let jobs: Vec<TableSyncJob<RDS>> = vec![
+    let mut jobs: Vec<TableSyncJob<RDS>> = vec![
         // Phase 1: Parent/Independent tables
         // These tables do not have foreign keys to other synced tables, or are parents.
         TableSyncJob::new::<entities::media_cover_art::Entity, _>(
@@ -137,10 +181,22 @@ pub async fn setup_and_run_sync<'s, RDS: RemoteDataSource + Debug + Send + Sync
             .await,
             fk_resolver.clone(),
         ),
+        TableSyncJob::new::<entities::media_file_stats::Entity, _>(
+            entities::media_file_stats::Entity.table_name().to_string(),
+            initial_meta(entities::media_file_stats::Entity.table_name().to_string()).await,
+            fk_resolver.clone(),
+        ),
     ];
 
+    if let Some(tables) = &options.tables {
+        check_table_names(tables, jobs.iter().map(|x| x.table_name.as_str()))?;
+        jobs.retain(|x| tables.contains(&x.table_name));
+    }
+
     let scheduler = SyncScheduler::new();
-    let results = scheduler.run_plan(&sync_context, jobs).await;
+    let results = scheduler
+        .run_plan_with_callback(&sync_context, jobs, on_result)
+        .await;
 
     for result in &results {
         if let TableSyncResult::Success(metadata) = result {
@@ -166,3 +222,128 @@ pub async fn setup_and_run_sync<'s, RDS: RemoteDataSource + Debug + Send + Sync
 
     Ok(results)
 }
+
+fn check_table_names<'a>(
+    tables: &[String],
+    known: impl Iterator<Item = &'a str> + Clone,
+) -> anyhow::Result<()> {
+    if let Some(unknown) = tables
+        .iter()
+        .find(|table| !known.clone().any(|x| x == table.as_str()))
+    {
+        anyhow::bail!(
+            "Unknown table {unknown}, expected one of {}",
+            known.collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// The changes a sync of one table would exchange.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSyncPreview {
+    pub table_name: String,
+    /// Records changed on the remote side since the last sync
+    pub remote_changes: u64,
+    /// Records changed locally since the last sync
+    pub local_changes: u64,
+}
+
+async fn preview_table<E, RDS>(
+    db: &DatabaseConnection,
+    local_node_id: Uuid,
+    remote: &RDS,
+    direction: SyncDirection,
+) -> anyhow::Result<TableSyncPreview>
+where
+    E: HLCModel + EntityTrait + Default + Send + Sync,
+    E::Model: HLCRecord + Send + Sync + for<'de> Deserialize<'de> + Serialize,
+    RDS: RemoteDataSource + Debug + Send + Sync + 'static,
+{
+    let table_name = E::default().table_name().to_string();
+    let last_sync_hlc = get_local_last_sync_hlc(db, &table_name, local_node_id)
+        .await?
+        .unwrap_or(HLC {
+            timestamp_ms: 0,
+            version: 0,
+            node_id: local_node_id,
+        });
+
+    let remote_changes = if direction == SyncDirection::Push {
+        0
+    } else {
+        remote
+            .get_remote_chunks::<E>(&table_name, Some(&last_sync_hlc))
+            .await?
+            .iter()
+            .map(|x| x.count)
+            .sum()
+    };
+    let local_changes = if direction == SyncDirection::Pull {
+        0
+    } else {
+        E::find().filter(E::gt(&last_sync_hlc)?).count(db).await?
+    };
+
+    Ok(TableSyncPreview {
+        table_name,
+        remote_changes,
+        local_changes,
+    })
+}
+
+/// Counts the records each side changed since the last sync, without
+/// changing anything. Records changed on both sides are counted twice, so
+/// the counts are an upper bound of the operations a sync would apply.
+pub async fn preview_sync<RDS: RemoteDataSource + Debug + Send + Sync + 'static>(
+    db: &DatabaseConnection,
+    local_node_id: Uuid,
+    remote: &RDS,
+    options: &SyncRunOptions,
+) -> anyhow::Result<Vec<TableSyncPreview>> {
+    macro_rules! preview_tables {
+        ($($entity:ident),* $(,)?) => {{
+            let known = [$(entities::$entity::Entity.table_name().to_string()),*];
+            if let Some(tables) = &options.tables {
+                check_table_names(tables, known.iter().map(String::as_str))?;
+            }
+
+            let mut previews = Vec::new();
+            $(
+                let selected = options.tables.as_ref().is_none_or(|tables| {
+                    tables
+                        .iter()
+                        .any(|x| x == entities::$entity::Entity.table_name())
+                });
+                if selected {
+                    previews.push(
+                        preview_table::<entities::$entity::Entity, RDS>(
+                            db,
+                            local_node_id,
+                            remote,
+                            options.direction,
+                        )
+                        .await?,
+                    );
+                }
+            )*
+            previews
+        }};
+    }
+
+    // Same tables, in the same order, as `setup_and_run_sync_with_options`
+    Ok(preview_tables!(
+        media_cover_art,
+        artists,
+        genres,
+        albums,
+        media_files,
+        media_file_albums,
+        media_file_artists,
+        media_file_genres,
+        media_file_fingerprint,
+        media_file_similarity,
+        media_file_stats,
+    ))
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter};
+
+use ::database::{
+    actions::{
+        case_fold::{
+            CASE_INSENSITIVE_SETTING, CaseFoldedFiles, fold_path_key, is_case_insensitive,
+        },
+        library_settings::{get_library_setting, set_library_flag},
+        playback_queue::{list_playback_queue, replace_playback_queue},
+        playlists::{add_item_to_playlist, create_playlist, get_playlist_items},
+        stats::{increase_played_through, set_liked},
+    },
+    connection::connect_main_db,
+    entities::{media_file_stats, media_files},
+};
+use ::fsio::FsIo;
+use ::metadata::describe::describe_file;
+
+mod common;
+use common::{SeedFile, seed_media_file};
+
+async fn stats_of(main_db: &DatabaseConnection, file_id: i32) -> Result<media_file_stats::Model> {
+    Ok(media_file_stats::Entity::find()
+        .filter(media_file_stats::Column::MediaFileId.eq(file_id))
+        .one(main_db)
+        .await?
+        .unwrap())
+}
+
+#[test]
+fn paths_are_fully_case_folded() {
+    assert_eq!(
+        fold_path_key("Straße", "Straße.flac"),
+        fold_path_key("STRASSE", "STRASSE.FLAC")
+    );
+    assert_ne!(
+        fold_path_key("Straße", "Straße.flac"),
+        fold_path_key("Strasse", "Strase.flac")
+    );
+}
+
+#[tokio::test]
+async fn case_only_renames_keep_the_file() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path.to_str().unwrap(), None, "test").await?;
+
+    let song = seed_media_file(&main_db, "Song.flac", SeedFile::default()).await?;
+    increase_played_through(&main_db, song.id).await?;
+    set_liked(&main_db, song.id, true).await?;
+
+    // The file as the scan finds it after its case changed on disk
+    std::fs::create_dir_all(lib_path.join("music"))?;
+    std::fs::write(lib_path.join("music/song.flac"), b"audio")?;
+    let node = fsio.metadata(&lib_path.join("music/song.flac"))?;
+    let description = describe_file(&node, &Some(lib_path.to_path_buf()))?;
+
+    let mut case_folded = CaseFoldedFiles::load(&main_db).await?;
+    let renamed = case_folded
+        .apply_renames(&main_db, &[Some(description)])
+        .await?;
+    assert_eq!(renamed, 1);
+
+    let files = media_files::Entity::find().all(&main_db).await?;
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].id, song.id);
+    assert_eq!(files[0].directory, "music");
+    assert_eq!(files[0].file_name, "song.flac");
+    assert_eq!(files[0].file_hash, song.file_hash);
+
+    let stats = stats_of(&main_db, song.id).await?;
+    assert_eq!(stats.played_through, 1);
+    assert!(stats.liked);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn files_folding_to_the_same_path_are_merged() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+
+    let kept = seed_media_file(&main_db, "Song.flac", SeedFile::default()).await?;
+    let upper = SeedFile {
+        directory: "MUSIC",
+        ..Default::default()
+    };
+    let duplicate = seed_media_file(&main_db, "SONG.flac", upper).await?;
+    let other = seed_media_file(&main_db, "Other.flac", SeedFile::default()).await?;
+
+    increase_played_through(&main_db, kept.id).await?;
+    increase_played_through(&main_db, duplicate.id).await?;
+    increase_played_through(&main_db, duplicate.id).await?;
+    set_liked(&main_db, duplicate.id, true).await?;
+
+    let playlist = create_playlist(&main_db, "test", "Favorites".to_owned(), "".to_owned()).await?;
+    add_item_to_playlist(&main_db, "test", playlist.id, duplicate.id, None).await?;
+    add_item_to_playlist(&main_db, "test", playlist.id, other.id, None).await?;
+    replace_playback_queue(&main_db, vec![other.id, duplicate.id]).await?;
+
+    CaseFoldedFiles::load(&main_db).await?;
+
+    let mut ids: Vec<i32> = media_files::Entity::find()
+        .all(&main_db)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, [kept.id, other.id]);
+
+    let stats = stats_of(&main_db, kept.id).await?;
+    assert_eq!(stats.played_through, 3);
+    assert!(stats.liked);
+    assert_eq!(
+        media_file_stats::Entity::find()
+            .filter(media_file_stats::Column::MediaFileId.eq(duplicate.id))
+            .count(&main_db)
+            .await?,
+        0
+    );
+
+    let items: Vec<i32> = get_playlist_items(&main_db, playlist.id)
+        .await?
+        .into_iter()
+        .map(|(_, file)| file.id)
+        .collect();
+    assert_eq!(items, [kept.id, other.id]);
+    assert_eq!(list_playback_queue(&main_db).await?, [other.id, kept.id]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn case_sensitivity_is_probed_once() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path.to_str().unwrap(), None, "test").await?;
+
+    assert_eq!(
+        get_library_setting(&main_db, CASE_INSENSITIVE_SETTING).await?,
+        None
+    );
+
+    let insensitive = is_case_insensitive(&fsio, &main_db, lib_path).await;
+    assert_eq!(
+        get_library_setting(&main_db, CASE_INSENSITIVE_SETTING).await?,
+        Some(insensitive.to_string())
+    );
+    assert!(!lib_path.join(".rune-case-probe").exists());
+
+    // Later scans trust the settings instead of probing again
+    set_library_flag(&main_db, CASE_INSENSITIVE_SETTING, !insensitive).await?;
+    assert_eq!(
+        is_case_insensitive(&fsio, &main_db, lib_path).await,
+        !insensitive
+    );
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+#![allow(dead_code)]
+
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, DatabaseConnection, prelude::Decimal};
+
+use ::database::entities::media_files;
+
+/// The parts of a seeded media file the tests care about.
+#[derive(Debug, Clone, Copy)]
+pub struct SeedFile<'a> {
+    pub directory: &'a str,
+    pub extension: &'a str,
+    /// Duration in seconds.
+    pub duration: i64,
+}
+
+impl Default for SeedFile<'_> {
+    fn default() -> Self {
+        Self {
+            directory: "Music",
+            extension: "flac",
+            duration: 180,
+        }
+    }
+}
+
+/// Builds a media file named `file_name`, whose hash and HLC uuid derive
+/// from the name. Callers may override more fields before inserting it.
+pub fn media_file(file_name: &str, seed: SeedFile) -> media_files::ActiveModel {
+    let now = Utc::now().to_rfc3339();
+
+    media_files::ActiveModel {
+        file_name: Set(file_name.to_owned()),
+        directory: Set(seed.directory.to_owned()),
+        extension: Set(seed.extension.to_owned()),
+        file_hash: Set(format!("{file_name}_hash")),
+        last_modified: Set(now.clone()),
+        cover_art_id: Set(None),
+        sample_rate: Set(44100),
+        duration: Set(Decimal::new(seed.duration, 0)),
+        musicbrainz_recording_id: Set(None),
+        musicbrainz_track_id: Set(None),
+        musicbrainz_release_id: Set(None),
+        musicbrainz_release_group_id: Set(None),
+        musicbrainz_artist_ids: Set(None),
+        hlc_uuid: Set(format!("{file_name}_uuid")),
+        created_at_hlc_ts: Set(now.clone()),
+        created_at_hlc_ver: Set(0),
+        created_at_hlc_nid: Set("test".to_owned()),
+        updated_at_hlc_ts: Set(now),
+        updated_at_hlc_ver: Set(0),
+        updated_at_hlc_nid: Set("test".to_owned()),
+        ..Default::default()
+    }
+}
+
+/// Inserts a media file built by [`media_file`].
+pub async fn seed_media_file(
+    db: &DatabaseConnection,
+    file_name: &str,
+    seed: SeedFile<'_>,
+) -> Result<media_files::Model> {
+    Ok(media_file(file_name, seed).insert(db).await?)
+}
@@ -0,0 +1,150 @@
+use std::f64::consts::TAU;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use sea_orm::{EntityTrait, PaginatorTrait};
+
+use ::database::{
+    actions::{
+        metadata::{empty_progress_callback, scan_audio_library},
+        playlists::{
+            add_item_to_playlist, create_playlist, get_playlist_items,
+            reorder_playlist_item_position, update_playlist,
+        },
+    },
+    connection::connect_main_db,
+    entities::media_files,
+};
+use ::fsio::FsIo;
+
+const SAMPLE_RATE: u32 = 8000;
+
+/// A tenth of a second of a tone, different for every index so every file
+/// has its own hash.
+fn write_tone(path: &Path, index: usize) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let frequency = 200.0 + index as f64 * 10.0;
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for i in 0..SAMPLE_RATE / 10 {
+        let sample = (TAU * frequency * i as f64 / SAMPLE_RATE as f64).sin() * 0.5;
+        writer.write_sample((sample * i16::MAX as f64) as i16)?;
+    }
+    writer.finalize()?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn playlist_edits_are_not_locked_out_by_a_scan() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().canonicalize()?;
+    let fsio = Arc::new(FsIo::new());
+
+    fs::create_dir(lib_path.join("Seed"))?;
+    write_tone(&lib_path.join("Seed/seed.wav"), 0)?;
+
+    let main_db = Arc::new(connect_main_db(&fsio, lib_path.to_str().unwrap(), None, "test").await?);
+    scan_audio_library(
+        &fsio,
+        &main_db,
+        "test",
+        &lib_path,
+        false,
+        false,
+        empty_progress_callback,
+        None,
+    )
+    .await?;
+    let seed_id = media_files::Entity::find()
+        .one(main_db.as_ref())
+        .await?
+        .unwrap()
+        .id;
+
+    // Several batches of new files for the scan to write
+    const ALBUMS: usize = 4;
+    const TRACKS: usize = 40;
+    for album in 0..ALBUMS {
+        let directory = lib_path.join(format!("Album {album}"));
+        fs::create_dir(&directory)?;
+        for track in 0..TRACKS {
+            write_tone(
+                &directory.join(format!("{track:02}.wav")),
+                1 + album * TRACKS + track,
+            )?;
+        }
+    }
+
+    let playlist = create_playlist(
+        main_db.as_ref(),
+        "test",
+        "Stress".to_owned(),
+        "Tests".to_owned(),
+    )
+    .await?;
+
+    let scan = {
+        let fsio = Arc::clone(&fsio);
+        let main_db = Arc::clone(&main_db);
+        let lib_path = lib_path.clone();
+        tokio::spawn(async move {
+            scan_audio_library(
+                &fsio,
+                &main_db,
+                "test",
+                &lib_path,
+                true,
+                false,
+                empty_progress_callback,
+                None,
+            )
+            .await
+        })
+    };
+
+    let edits = (0..50)
+        .map(|i| {
+            let main_db = Arc::clone(&main_db);
+            tokio::spawn(async move {
+                add_item_to_playlist(&main_db, "test", playlist.id, seed_id, Some(i)).await?;
+                reorder_playlist_item_position(&main_db, "test", playlist.id, seed_id, i).await?;
+                update_playlist(
+                    &main_db,
+                    "test",
+                    playlist.id,
+                    Some(format!("Stress {i}")),
+                    None,
+                )
+                .await?;
+
+                anyhow::Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut failures = Vec::new();
+    for edit in edits {
+        if let Err(e) = edit.await? {
+            failures.push(format!("{e:#}"));
+        }
+    }
+    assert!(failures.is_empty(), "{failures:#?}");
+
+    scan.await??;
+
+    // No batch of the scan was given up either
+    assert_eq!(
+        media_files::Entity::find().count(main_db.as_ref()).await?,
+        (1 + ALBUMS * TRACKS) as u64
+    );
+    assert_eq!(get_playlist_items(&main_db, playlist.id).await?.len(), 50);
+
+    Ok(())
+}
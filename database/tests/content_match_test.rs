@@ -0,0 +1,149 @@
+use std::f64::consts::TAU;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use ::database::{
+    actions::{
+        content_match::{compute_file_landmarks, get_content_matches, match_content},
+        dedup::{DuplicateLabel, KeepPreference, plan_dedupe},
+        file::get_files_at_or_below,
+        metadata::{empty_progress_callback, scan_audio_library},
+    },
+    connection::connect_main_db,
+};
+use ::fsio::FsIo;
+
+/// A minute of chords changing at an uneven pace, the same for a seed
+/// whatever the sample rate.
+fn song(seed: u64, sample_rate: u32) -> Vec<f64> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) as f64 / (1u64 << 31) as f64
+    };
+
+    // Start times and frequencies, in seconds and Hz
+    let mut notes = Vec::new();
+    let mut start = 0.0;
+    while start < 60.0 {
+        notes.push((start, [300.0 + next() * 2700.0, 300.0 + next() * 2700.0]));
+        start += 0.15 + next() * 0.3;
+    }
+
+    let mut note = 0;
+    (0..60 * sample_rate as usize)
+        .map(|i| {
+            let time = i as f64 / sample_rate as f64;
+            while notes.get(note + 1).is_some_and(|x| x.0 <= time) {
+                note += 1;
+            }
+
+            let (start, frequencies) = notes[note];
+            let t = time - start;
+            let envelope = (t / 0.01).min(1.0) * (-3.0 * t).exp();
+            let sample: f64 = frequencies.iter().map(|f| (TAU * f * t).sin()).sum();
+            sample * envelope * 0.4
+        })
+        .collect()
+}
+
+fn write_wav(path: &Path, samples: &[f64], sample_rate: u32, bits_per_sample: u16) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        match bits_per_sample {
+            8 => writer.write_sample((sample * i8::MAX as f64) as i8)?,
+            _ => writer.write_sample((sample * i16::MAX as f64) as i16)?,
+        }
+    }
+    writer.finalize()?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reencoded_and_trimmed_copies_match() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().canonicalize()?;
+    let fsio = Arc::new(FsIo::new());
+
+    let songs_path = lib_path.join("Songs");
+    std::fs::create_dir(&songs_path)?;
+    write_wav(&songs_path.join("original.wav"), &song(1, 44100), 44100, 16)?;
+    // Half the sample rate and the bit depth, the first 2 seconds cut
+    let copy = song(1, 22050);
+    write_wav(&songs_path.join("copy.wav"), &copy[2 * 22050..], 22050, 8)?;
+    write_wav(&songs_path.join("other.wav"), &song(2, 44100), 44100, 16)?;
+
+    let main_db = connect_main_db(&fsio, lib_path.to_str().unwrap(), None, "test").await?;
+    scan_audio_library(
+        &fsio,
+        &main_db,
+        "test",
+        &lib_path,
+        true,
+        false,
+        empty_progress_callback,
+        None,
+    )
+    .await?;
+    let files = get_files_at_or_below(&main_db, "Songs").await?;
+    let file_id = |name: &str| files.iter().find(|x| x.file_name == name).unwrap().id;
+    let (original, copy, other) = (
+        file_id("original.wav"),
+        file_id("copy.wav"),
+        file_id("other.wav"),
+    );
+
+    let computed = compute_file_landmarks(
+        Arc::clone(&fsio),
+        &main_db,
+        &lib_path,
+        "",
+        2,
+        empty_progress_callback,
+        None,
+    )
+    .await?;
+    assert_eq!(computed, 3);
+
+    assert_eq!(
+        match_content(&main_db, empty_progress_callback, None).await?,
+        1
+    );
+    let matches = get_content_matches(&main_db).await?;
+    let content_match = &matches[0];
+    assert_eq!(
+        (content_match.file_id1, content_match.file_id2),
+        (original.min(copy), original.max(copy))
+    );
+    // The content comes 2 seconds later in the original
+    let offset = if content_match.file_id1 == original {
+        content_match.offset
+    } else {
+        -content_match.offset
+    };
+    assert!((offset - 2.0).abs() < 0.05, "Unexpected offset {offset}");
+    assert!(content_match.density > 0.05);
+
+    let plan = plan_dedupe(&fsio, &main_db, &lib_path, 0.9, KeepPreference::Lossless).await?;
+    assert_eq!(plan.groups.len(), 1);
+    let group = &plan.groups[0];
+    assert_eq!(group.labels, [DuplicateLabel::ContentMatch]);
+    let file_ids: Vec<i32> = group.files.iter().map(|x| x.file_id).collect();
+    assert!(file_ids.contains(&original) && file_ids.contains(&copy));
+    assert!(!file_ids.contains(&other));
+    // The higher sample rate
+    assert_eq!(group.keep, original);
+
+    Ok(())
+}
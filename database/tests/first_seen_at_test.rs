@@ -0,0 +1,66 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, DatabaseConnection};
+
+use ::database::{
+    actions::{
+        file::get_recently_added_media_files, metadata::get_metadata_summary_by_file_id,
+        mixes::query_mix_media_files,
+    },
+    connection::{connect_main_db, connect_recommendation_db},
+    entities::media_files,
+};
+use ::fsio::FsIo;
+
+mod common;
+use common::{SeedFile, media_file};
+
+async fn seed_media_file(
+    db: &DatabaseConnection,
+    file_name: &str,
+    days_ago: i64,
+) -> Result<media_files::Model> {
+    let mut file = media_file(file_name, SeedFile::default());
+    // Ripped long before it was added
+    file.last_modified = Set("2001-01-01T00:00:00+00:00".to_owned());
+    file.first_seen_at = Set((Utc::now() - Duration::days(days_ago)).to_rfc3339());
+
+    Ok(file.insert(db).await?)
+}
+
+#[tokio::test]
+async fn files_are_ordered_and_filtered_by_when_they_were_added() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+    let recommend_db = connect_recommendation_db(&fsio, lib_path, None).await?;
+
+    let old = seed_media_file(&main_db, "old", 90).await?;
+    let newest = seed_media_file(&main_db, "newest", 1).await?;
+    let recent = seed_media_file(&main_db, "recent", 10).await?;
+
+    let summary = get_metadata_summary_by_file_id(&main_db, old.id).await?;
+    assert_eq!(summary.first_seen_at, old.first_seen_at);
+
+    let ids: Vec<i32> = get_recently_added_media_files(&main_db, 0, 10)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    assert_eq!(ids, [newest.id, recent.id, old.id]);
+
+    let queries = vec![
+        ("lib::all".to_owned(), "true".to_owned()),
+        ("filter::added_within".to_owned(), "30".to_owned()),
+    ];
+    let mut ids: Vec<i32> = query_mix_media_files(&main_db, &recommend_db, queries, 0, 100)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, [newest.id, recent.id]);
+
+    Ok(())
+}
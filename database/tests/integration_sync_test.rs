@@ -280,6 +280,12 @@ async fn seed_media_file(
         cover_art_id: Set(cover_art_id),
         sample_rate: Set(44100),
         duration: Set(Decimal::new(180, 0)),
+        musicbrainz_recording_id: Set(None),
+        musicbrainz_track_id: Set(None),
+        musicbrainz_release_id: Set(None),
+        musicbrainz_release_group_id: Set(None),
+        musicbrainz_artist_ids: Set(None),
+        first_seen_at: Set(Utc::now().to_rfc3339()),
         hlc_uuid: Set(Uuid::new_v4().to_string()),
         created_at_hlc_ts: Set(hlc.to_rfc3339()?),
         created_at_hlc_ver: Set(hlc.version as i32),
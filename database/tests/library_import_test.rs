@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use ::database::actions::library_import::path_from_file_url;
+
+#[test]
+fn file_url_decodes_escapes() {
+    assert_eq!(
+        path_from_file_url("file://localhost/Users/me/Music/Four%20Tet/01%20Angel%20Echoes.m4a"),
+        Some(PathBuf::from(
+            "/Users/me/Music/Four Tet/01 Angel Echoes.m4a"
+        ))
+    );
+    assert_eq!(
+        path_from_file_url("file:///home/me/M%C3%BAsica/a.flac"),
+        Some(PathBuf::from("/home/me/Música/a.flac"))
+    );
+}
+
+#[test]
+fn file_url_keeps_windows_drives() {
+    assert_eq!(
+        path_from_file_url("file://localhost/C:/Music/a.mp3"),
+        Some(PathBuf::from("C:/Music/a.mp3"))
+    );
+}
+
+#[test]
+fn file_url_rejects_other_urls() {
+    assert_eq!(path_from_file_url("http://example.com/a.mp3"), None);
+    assert_eq!(path_from_file_url("file:///a%2.mp3"), None);
+}
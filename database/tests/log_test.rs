@@ -0,0 +1,108 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, PaginatorTrait};
+
+use ::database::{
+    actions::logging::{
+        LogFilter, LogLevel, insert_log, list_log, prune_logs, select_logs, set_recorded_log_level,
+    },
+    connection::connect_main_db,
+    entities::log,
+};
+use ::fsio::FsIo;
+
+#[tokio::test]
+async fn logs_are_filtered_pruned_and_thresholded() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+
+    let entries = [
+        (LogLevel::Info, "scanner", "Scanned /music/a.flac"),
+        (LogLevel::Warning, "scrobbler::LastFm", "Rate limited"),
+        (LogLevel::Error, "scrobbler::ListenBrainz", "Token rejected"),
+    ];
+    for (level, domain, detail) in entries {
+        insert_log(&main_db, level, domain.to_owned(), detail.to_owned()).await?;
+    }
+
+    // Below the recorded level by default
+    let skipped = insert_log(
+        &main_db,
+        LogLevel::Debug,
+        "scanner".to_owned(),
+        "noise".to_owned(),
+    )
+    .await?;
+    assert!(skipped.is_none());
+
+    let details =
+        |logs: Vec<log::Model>| -> Vec<String> { logs.into_iter().map(|x| x.detail).collect() };
+
+    let all = list_log(&main_db, LogFilter::default(), 0, 10).await?;
+    assert_eq!(
+        details(all),
+        ["Token rejected", "Rate limited", "Scanned /music/a.flac"]
+    );
+
+    let filter = LogFilter {
+        min_level: Some(LogLevel::Warning),
+        ..Default::default()
+    };
+    assert_eq!(
+        details(list_log(&main_db, filter, 0, 10).await?),
+        ["Token rejected", "Rate limited"]
+    );
+
+    let filter = LogFilter {
+        domain: Some("scrobbler::".to_owned()),
+        text: Some("token".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(
+        details(select_logs(&main_db, filter).await?),
+        ["Token rejected"]
+    );
+
+    let filter = LogFilter {
+        start: Some(Utc::now() - Duration::hours(1)),
+        end: Some(Utc::now() - Duration::minutes(30)),
+        ..Default::default()
+    };
+    assert!(select_logs(&main_db, filter).await?.is_empty());
+
+    set_recorded_log_level(LogLevel::Debug);
+    let recorded = insert_log(
+        &main_db,
+        LogLevel::Debug,
+        "scanner".to_owned(),
+        "trace".to_owned(),
+    )
+    .await?;
+    assert!(recorded.is_some());
+    set_recorded_log_level(LogLevel::Info);
+
+    log::ActiveModel {
+        date: ActiveValue::Set(Utc::now() - Duration::days(60)),
+        level: ActiveValue::Set(LogLevel::Error.to_string()),
+        domain: ActiveValue::Set("scanner".to_owned()),
+        detail: ActiveValue::Set("stale".to_owned()),
+        ..Default::default()
+    }
+    .insert(&main_db)
+    .await?;
+
+    assert_eq!(
+        prune_logs(&main_db, Some(Duration::days(30)), None).await?,
+        1
+    );
+    assert_eq!(prune_logs(&main_db, None, Some(2)).await?, 2);
+    assert_eq!(
+        details(select_logs(&main_db, LogFilter::default()).await?),
+        ["Token rejected", "trace"]
+    );
+    assert_eq!(log::Entity::find().count(&main_db).await?, 2);
+
+    Ok(())
+}
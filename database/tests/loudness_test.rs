@@ -0,0 +1,167 @@
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, DatabaseConnection, prelude::Decimal};
+
+use ::database::{
+    actions::loudness::{aggregate_loudness, get_track_loudness},
+    connection::connect_main_db,
+    entities::{albums, media_analysis, media_file_albums, media_files},
+};
+use ::fsio::FsIo;
+
+mod common;
+use common::SeedFile;
+
+async fn seed_media_file(
+    db: &DatabaseConnection,
+    file_name: &str,
+    duration: i64,
+) -> Result<media_files::Model> {
+    let seed = SeedFile {
+        duration,
+        ..Default::default()
+    };
+    common::seed_media_file(db, file_name, seed).await
+}
+
+async fn seed_analysis(
+    db: &DatabaseConnection,
+    file: &media_files::Model,
+    loudness: Option<Decimal>,
+    peak: Option<Decimal>,
+) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+
+    media_analysis::ActiveModel {
+        file_id: Set(file.id),
+        integrated_loudness: Set(loudness),
+        peak: Set(peak),
+        hlc_uuid: Set(format!("{}_analysis", file.file_name)),
+        created_at_hlc_ts: Set(now.clone()),
+        created_at_hlc_ver: Set(0),
+        created_at_hlc_nid: Set("test".to_owned()),
+        updated_at_hlc_ts: Set(now),
+        updated_at_hlc_ver: Set(0),
+        updated_at_hlc_nid: Set("test".to_owned()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(())
+}
+
+async fn seed_album(
+    db: &DatabaseConnection,
+    name: &str,
+    files: &[&media_files::Model],
+) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+
+    let album = albums::ActiveModel {
+        name: Set(name.to_owned()),
+        group: Set(name.to_owned()),
+        hlc_uuid: Set(format!("{name}_uuid")),
+        created_at_hlc_ts: Set(now.clone()),
+        created_at_hlc_ver: Set(0),
+        created_at_hlc_nid: Set("test".to_owned()),
+        updated_at_hlc_ts: Set(now.clone()),
+        updated_at_hlc_ver: Set(0),
+        updated_at_hlc_nid: Set("test".to_owned()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    for (i, file) in files.iter().enumerate() {
+        media_file_albums::ActiveModel {
+            media_file_id: Set(file.id),
+            album_id: Set(album.id),
+            track_number: Set(Some(i as i32 + 1)),
+            hlc_uuid: Set(format!("{name}_{i}_uuid")),
+            created_at_hlc_ts: Set(now.clone()),
+            created_at_hlc_ver: Set(0),
+            created_at_hlc_nid: Set("test".to_owned()),
+            updated_at_hlc_ts: Set(now.clone()),
+            updated_at_hlc_ver: Set(0),
+            updated_at_hlc_nid: Set("test".to_owned()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn album_loudness_is_weighted_by_duration() {
+    // The same loudness everywhere gives it back
+    let (loudness, peak) = aggregate_loudness(&[(-14.0, 0.5, 100.0), (-14.0, 0.9, 300.0)]).unwrap();
+    assert!((loudness + 14.0).abs() < 1e-9);
+    assert_eq!(peak, 0.9);
+
+    // A track 10 dB quieter adds a tenth of the power of the other one
+    let (loudness, _) = aggregate_loudness(&[(-10.0, 1.0, 100.0), (-20.0, 1.0, 100.0)]).unwrap();
+    let expected = -10.0 + 10.0 * (1.1f64 / 2.0).log10();
+    assert!((loudness - expected).abs() < 1e-9);
+
+    assert_eq!(aggregate_loudness(&[]), None);
+}
+
+#[tokio::test]
+async fn track_loudness_includes_album_loudness() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+
+    let loud = seed_media_file(&main_db, "loud", 200).await?;
+    let quiet = seed_media_file(&main_db, "quiet", 200).await?;
+    let single = seed_media_file(&main_db, "single", 180).await?;
+    let legacy = seed_media_file(&main_db, "legacy", 180).await?;
+
+    seed_analysis(
+        &main_db,
+        &loud,
+        Some(Decimal::new(-80, 1)),
+        Some(Decimal::new(98, 2)),
+    )
+    .await?;
+    seed_analysis(
+        &main_db,
+        &quiet,
+        Some(Decimal::new(-180, 1)),
+        Some(Decimal::new(40, 2)),
+    )
+    .await?;
+    seed_analysis(
+        &main_db,
+        &single,
+        Some(Decimal::new(-120, 1)),
+        Some(Decimal::new(70, 2)),
+    )
+    .await?;
+    // Analyzed before loudness was measured
+    seed_analysis(&main_db, &legacy, None, None).await?;
+    seed_album(&main_db, "Album", &[&loud, &quiet]).await?;
+
+    let loudness = get_track_loudness(&main_db, &[quiet.id, single.id, legacy.id]).await?;
+    assert_eq!(loudness.len(), 2);
+    assert!(!loudness.contains_key(&legacy.id));
+
+    // The loud track of the album counts even though it was not requested
+    let quiet = &loudness[&quiet.id];
+    assert_eq!(quiet.track_loudness, -18.0);
+    assert_eq!(quiet.track_peak, 0.4);
+    let album_loudness = quiet.album_loudness.unwrap();
+    assert!(album_loudness > -18.0 && album_loudness < -8.0);
+    assert_eq!(quiet.album_peak, Some(0.98));
+
+    let single = &loudness[&single.id];
+    assert_eq!(single.track_loudness, -12.0);
+    assert_eq!(single.album_loudness, None);
+    assert_eq!(single.album_peak, None);
+
+    Ok(())
+}
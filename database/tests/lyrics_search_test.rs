@@ -0,0 +1,111 @@
+use anyhow::Result;
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, DatabaseConnection};
+
+use ::database::{
+    actions::{
+        lyrics::{
+            index_file_lyrics, is_lyrics_indexing_enabled, lyrics_text, rebuild_lyrics_index,
+            set_lyrics_indexing_enabled,
+        },
+        search::search_lyrics,
+    },
+    connection::connect_main_db,
+    entities::{media_files, media_metadata},
+};
+use ::fsio::FsIo;
+
+mod common;
+use common::SeedFile;
+
+const LRC: &str = "[ar:Someone]\n[ti:A Song]\n\n[00:01.00]Walking down the river\n\
+                   [00:05.50]<00:05.50>Under <00:06.00>the <00:06.50>moon\n\
+                   [00:09.00][00:20.00]Singing all night";
+
+async fn seed_media_file(
+    db: &DatabaseConnection,
+    file_name: &str,
+    lyrics: Option<&str>,
+) -> Result<media_files::Model> {
+    let now = Utc::now().to_rfc3339();
+    let file = common::seed_media_file(db, file_name, SeedFile::default()).await?;
+
+    if let Some(lyrics) = lyrics {
+        media_metadata::ActiveModel {
+            file_id: Set(file.id),
+            meta_key: Set("lyrics".to_owned()),
+            meta_value: Set(lyrics.to_owned()),
+            hlc_uuid: Set(format!("{file_name}_lyrics_uuid")),
+            created_at_hlc_ts: Set(now.clone()),
+            created_at_hlc_ver: Set(0),
+            created_at_hlc_nid: Set("test".to_owned()),
+            updated_at_hlc_ts: Set(now),
+            updated_at_hlc_ver: Set(0),
+            updated_at_hlc_nid: Set("test".to_owned()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(file)
+}
+
+#[test]
+fn time_tags_are_stripped_from_lyrics() {
+    assert_eq!(
+        lyrics_text(LRC),
+        "Walking down the river\nUnder the moon\nSinging all night"
+    );
+    assert_eq!(
+        lyrics_text("Plain lyrics\n\n  without time tags  "),
+        "Plain lyrics\nwithout time tags"
+    );
+}
+
+#[tokio::test]
+async fn lyrics_are_indexed_once_enabled() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path.to_str().unwrap(), None, "test").await?;
+
+    let song = seed_media_file(&main_db, "song", Some(LRC)).await?;
+    seed_media_file(&main_db, "instrumental", None).await?;
+
+    // Off by default, lyrics are left out of the index
+    assert!(!is_lyrics_indexing_enabled(&main_db).await?);
+    let metadata = [("lyrics".to_owned(), LRC.to_owned())];
+    let path = lib_path.join("Music/song");
+    assert!(!index_file_lyrics(&main_db, song.id, &path, &metadata).await?);
+    assert!(search_lyrics(&main_db, "river", 10).await?.is_empty());
+
+    set_lyrics_indexing_enabled(&main_db, true).await?;
+    assert_eq!(rebuild_lyrics_index(&main_db, lib_path).await?, 1);
+
+    let hits = search_lyrics(&main_db, "river", 10).await?;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].file_id, song.id as i64);
+    assert!(
+        hits[0].snippet.contains("<b>river</b>"),
+        "{}",
+        hits[0].snippet
+    );
+    // Time tags are not part of the indexed text
+    assert!(search_lyrics(&main_db, "00", 10).await?.is_empty());
+
+    // Indexing again replaces the lyrics of the file
+    assert!(index_file_lyrics(&main_db, song.id, &path, &metadata).await?);
+    assert_eq!(search_lyrics(&main_db, "moon", 10).await?.len(), 1);
+    assert!(!index_file_lyrics(&main_db, song.id, &path, &[]).await?);
+    assert!(search_lyrics(&main_db, "moon", 10).await?.is_empty());
+
+    // Rebuilding picks up the setting change
+    assert_eq!(rebuild_lyrics_index(&main_db, lib_path).await?, 1);
+    set_lyrics_indexing_enabled(&main_db, false).await?;
+    assert_eq!(search_lyrics(&main_db, "moon", 10).await?.len(), 1);
+    assert_eq!(rebuild_lyrics_index(&main_db, lib_path).await?, 0);
+    assert!(search_lyrics(&main_db, "moon", 10).await?.is_empty());
+
+    Ok(())
+}
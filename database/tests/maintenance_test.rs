@@ -0,0 +1,59 @@
+use std::fs;
+
+use anyhow::Result;
+
+use ::database::{
+    actions::maintenance::{
+        BACKUP_MANIFEST_FILE, BackupManifest, backup_databases, vacuum_main_db, verify_databases,
+    },
+    connection::{
+        LockMode, connect_main_db, connect_recommendation_db, get_storage_info, lock_library,
+    },
+};
+use ::fsio::FsIo;
+
+#[test]
+fn maintenance_waits_for_the_library_to_be_closed() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let storage_info = get_storage_info(lib_dir.path().to_str().unwrap(), None, false)?;
+
+    let app = lock_library(&storage_info, LockMode::Shared, false)?;
+    assert!(app.is_some());
+    assert!(lock_library(&storage_info, LockMode::Shared, false)?.is_some());
+    assert!(lock_library(&storage_info, LockMode::Exclusive, false)?.is_none());
+
+    drop(app);
+    let maintenance = lock_library(&storage_info, LockMode::Exclusive, false)?;
+    assert!(maintenance.is_some());
+    assert!(lock_library(&storage_info, LockMode::Shared, false)?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn maintenance_of_a_new_library() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let backup_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+    let recommend_db = connect_recommendation_db(&fsio, lib_path, None).await?;
+
+    vacuum_main_db(&main_db).await?;
+    assert!(verify_databases(&main_db, &recommend_db).await?.is_empty());
+
+    let (path, manifest) =
+        backup_databases(&main_db, &recommend_db, lib_dir.path(), backup_dir.path()).await?;
+    assert!(path.starts_with(backup_dir.path()));
+    assert_eq!(manifest.files.len(), 2);
+    for file in &manifest.files {
+        assert_eq!(fs::metadata(path.join(&file.name))?.len(), file.size);
+    }
+
+    let saved: BackupManifest =
+        serde_json::from_str(&fs::read_to_string(path.join(BACKUP_MANIFEST_FILE))?)?;
+    assert_eq!(saved, manifest);
+
+    Ok(())
+}
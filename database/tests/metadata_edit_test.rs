@@ -0,0 +1,83 @@
+use ::database::actions::metadata_edit::FileNamePattern;
+
+fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+    values
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn file_name_pattern_extracts_fields() {
+    let pattern = FileNamePattern::new("{track} {artist} - {title}").unwrap();
+
+    assert_eq!(
+        pattern.parse("07 Boards of Canada - Music Is Math.flac"),
+        Some(pairs(&[
+            ("track_number", "07"),
+            ("artist", "Boards of Canada"),
+            ("track_title", "Music Is Math"),
+        ]))
+    );
+    assert_eq!(pattern.parse("Music Is Math.flac"), None);
+}
+
+#[test]
+fn file_name_pattern_keeps_dots_in_values() {
+    let pattern = FileNamePattern::new("{artist} - {title}").unwrap();
+
+    assert_eq!(
+        pattern.parse("Mr. Oizo - Flat Beat.mp3"),
+        Some(pairs(&[
+            ("artist", "Mr. Oizo"),
+            ("track_title", "Flat Beat")
+        ]))
+    );
+}
+
+#[test]
+fn file_name_pattern_rejects_invalid_patterns() {
+    assert!(FileNamePattern::new("{artist} - {title").is_err());
+    assert!(FileNamePattern::new("{mood} - {title}").is_err());
+    assert!(FileNamePattern::new("{title} ({title})").is_err());
+    assert!(FileNamePattern::new("no placeholder").is_err());
+}
+
+#[test]
+fn path_pattern_extracts_fields_from_directories() {
+    let pattern = FileNamePattern::new("{artist}/{year} - {album}/{track} - {title}").unwrap();
+
+    assert_eq!(
+        pattern.parse(
+            "Music/Boards of Canada/1998 - Music Has the Right to Children/07 - Aquarius.flac"
+        ),
+        Some(pairs(&[
+            ("artist", "Boards of Canada"),
+            ("date", "1998"),
+            ("album", "Music Has the Right to Children"),
+            ("track_number", "07"),
+            ("track_title", "Aquarius"),
+        ]))
+    );
+    // Too few directories
+    assert_eq!(pattern.parse("1998 - Album/07 - Aquarius.flac"), None);
+}
+
+#[test]
+fn path_pattern_placeholders_stay_in_their_component() {
+    let pattern = FileNamePattern::new("{album}/{title}").unwrap();
+
+    assert_eq!(
+        pattern.parse("Artist/Vol. 1/Intro.mp3"),
+        Some(pairs(&[("album", "Vol. 1"), ("track_title", "Intro")]))
+    );
+
+    let pattern = FileNamePattern::new("{artist} - {title}").unwrap();
+    assert_eq!(
+        pattern.parse("Various - Hits/Mr. Oizo - Flat Beat.mp3"),
+        Some(pairs(&[
+            ("artist", "Mr. Oizo"),
+            ("track_title", "Flat Beat")
+        ]))
+    );
+}
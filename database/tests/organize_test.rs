@@ -0,0 +1,118 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use ::database::{
+    actions::{
+        file::get_files_at_or_below,
+        metadata::{empty_progress_callback, scan_audio_library},
+        metadata_edit::{apply_metadata_changes, diff_metadata},
+        organize::{PathTemplate, apply_organize_plan, plan_organize, sanitize_component},
+    },
+    connection::connect_main_db,
+    playing_item::dispatcher::PlayingItemActionDispatcher,
+};
+use ::fsio::FsIo;
+use ::playback::player::PlayingItem;
+
+fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+    values
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn templates_cannot_leave_the_library() {
+    assert!(PathTemplate::new("{artist}/{title}").is_ok());
+    assert!(PathTemplate::new("/{artist}/{title}").is_err());
+    assert!(PathTemplate::new("../{artist}/{title}").is_err());
+    assert!(PathTemplate::new("{artist}//{title}").is_err());
+    assert!(PathTemplate::new("{artist}\\{title}").is_err());
+    assert!(PathTemplate::new("{mood}/{title}").is_err());
+    assert!(PathTemplate::new("{track:2} {title}").is_err());
+}
+
+#[test]
+fn values_cannot_add_components() {
+    assert_eq!(sanitize_component("AC/DC"), "AC_DC");
+    assert_eq!(sanitize_component("Back\\Slash"), "Back_Slash");
+    assert_eq!(sanitize_component(".."), "_");
+    assert_eq!(sanitize_component("  "), "_");
+}
+
+#[tokio::test]
+async fn moved_files_can_still_be_played() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().canonicalize()?;
+    let fsio = FsIo::new();
+
+    let asset = Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets/startup_0.ogg");
+    fs::create_dir(lib_path.join("Incoming"))?;
+    fs::copy(&asset, lib_path.join("Incoming/startup.ogg"))?;
+
+    let main_db = connect_main_db(&fsio, lib_path.to_str().unwrap(), None, "test").await?;
+    scan_audio_library(
+        &fsio,
+        &main_db,
+        "test",
+        &lib_path,
+        true,
+        false,
+        empty_progress_callback,
+        None,
+    )
+    .await?;
+    let file_id = get_files_at_or_below(&main_db, "Incoming").await?[0].id;
+
+    let edits = vec![(
+        file_id,
+        pairs(&[
+            ("artist", "Rune"),
+            ("album", "Startup"),
+            ("track_title", "Chime"),
+            ("track_number", "1/2"),
+        ]),
+    )];
+    let changes = diff_metadata(&main_db, &edits).await?;
+    apply_metadata_changes(&main_db, "test", &changes).await?;
+
+    // Taken by a file the library doesn't know about
+    fs::create_dir_all(lib_path.join("Rune/Startup"))?;
+    fs::write(lib_path.join("Rune/Startup/01 Chime.ogg"), b"")?;
+
+    let template = PathTemplate::new("{album_artist}/{album}/{track:02} {title}")?;
+    let plan = plan_organize(&fsio, &main_db, &lib_path, &[file_id], &template).await?;
+    assert_eq!(plan.moves.len(), 1);
+    assert_eq!(plan.moves[0].from, PathBuf::from("Incoming/startup.ogg"));
+    assert_eq!(
+        plan.moves[0].to,
+        PathBuf::from("Rune/Startup/01 Chime (2).ogg")
+    );
+    // Planning moves nothing
+    assert!(lib_path.join("Incoming/startup.ogg").exists());
+
+    let outcome = apply_organize_plan(&fsio, &main_db, &lib_path, &plan).await?;
+    assert_eq!(outcome.moved, 1);
+    assert!(!lib_path.join("Incoming").exists());
+    assert!(lib_path.join("Rune/Startup/01 Chime (2).ogg").exists());
+
+    // Applying the plan again finds the file moved already
+    let outcome = apply_organize_plan(&fsio, &main_db, &lib_path, &plan).await?;
+    assert_eq!((outcome.moved, outcome.skipped), (0, 1));
+
+    let item = PlayingItem::InLibrary(file_id);
+    let paths = PlayingItemActionDispatcher::new()
+        .get_file_path(&fsio, &lib_path, &main_db, std::slice::from_ref(&item))
+        .await?;
+    let path = &paths[&item];
+    assert!(path.ends_with("Rune/Startup/01 Chime (2).ogg"));
+
+    // Decoded the way the player does
+    let decoder = rodio::Decoder::new(BufReader::new(File::open(path)?))?;
+    assert!(decoder.take(1024).count() > 0);
+
+    Ok(())
+}
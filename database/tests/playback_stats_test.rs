@@ -0,0 +1,127 @@
+use anyhow::Result;
+use sea_orm::DatabaseConnection;
+
+use ::database::{
+    actions::{
+        metadata::get_metadata_summary_by_file_id,
+        mixes::query_mix_media_files,
+        stats::{increase_played_through, increase_skipped, record_listening},
+    },
+    connection::{RecommendationDbConnection, connect_main_db, connect_recommendation_db},
+};
+use ::fsio::FsIo;
+
+mod common;
+use common::{SeedFile, seed_media_file};
+
+const AUDIOBOOK: SeedFile = SeedFile {
+    directory: "Audiobooks",
+    extension: "m4b",
+    duration: 3600,
+};
+
+async fn mix_file_ids(
+    main_db: &DatabaseConnection,
+    recommend_db: &RecommendationDbConnection,
+    queries: &[(&str, &str)],
+) -> Result<Vec<i32>> {
+    let queries = queries
+        .iter()
+        .map(|(operator, parameter)| (operator.to_string(), parameter.to_string()))
+        .collect();
+    let files = query_mix_media_files(main_db, recommend_db, queries, 0, 100).await?;
+
+    Ok(files.into_iter().map(|x| x.id).collect())
+}
+
+#[tokio::test]
+async fn listening_and_skips_are_recorded() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+    let recommend_db = connect_recommendation_db(&fsio, lib_path, None).await?;
+
+    let book = seed_media_file(&main_db, "book", AUDIOBOOK).await?;
+    let song = seed_media_file(&main_db, "song", AUDIOBOOK).await?;
+
+    let stats = record_listening(&main_db, book.id, Some(600.), 590.)
+        .await?
+        .unwrap();
+    assert_eq!(stats.total_listened_seconds, 590.);
+    assert_eq!(stats.last_position_seconds, Some(600.));
+    assert!(stats.first_played_at.is_some());
+    assert!(!stats.hlc_uuid.is_empty());
+    assert_eq!(stats.updated_at_hlc_nid, "test");
+
+    let first_played_at = stats.first_played_at.clone();
+    let stats = record_listening(&main_db, book.id, Some(900.), 300.)
+        .await?
+        .unwrap();
+    assert_eq!(stats.total_listened_seconds, 890.);
+    assert_eq!(stats.first_played_at, first_played_at);
+
+    increase_skipped(&main_db, song.id).await?;
+    let stats = increase_skipped(&main_db, song.id).await?;
+    assert_eq!(stats.skipped, 2);
+    assert!(stats.last_skipped_at.is_some());
+    assert!(stats.first_played_at.is_none());
+
+    assert!(record_listening(&main_db, -1, None, 1.).await?.is_none());
+    assert!(
+        record_listening(&main_db, book.id, None, -1.)
+            .await
+            .is_err()
+    );
+
+    let summary = get_metadata_summary_by_file_id(&main_db, book.id).await?;
+    assert_eq!(summary.total_listened_seconds, 890.);
+    assert_eq!(summary.last_position_seconds, Some(900.));
+    let summary = get_metadata_summary_by_file_id(&main_db, song.id).await?;
+    assert_eq!(summary.skipped, 2);
+    assert!(summary.last_skipped_at.is_some());
+
+    let all = ("lib::all", "true");
+    assert_eq!(
+        mix_file_ids(
+            &main_db,
+            &recommend_db,
+            &[all, ("filter::in_progress", "true")]
+        )
+        .await?,
+        [book.id]
+    );
+    assert_eq!(
+        mix_file_ids(
+            &main_db,
+            &recommend_db,
+            &[all, ("sort::most_skipped", "true")]
+        )
+        .await?,
+        [song.id, book.id]
+    );
+
+    // Played to the end, nothing is left to resume
+    let stats = increase_played_through(&main_db, book.id).await?;
+    assert_eq!(stats.played_through, 1);
+    assert_eq!(stats.last_position_seconds, None);
+    assert!(
+        mix_file_ids(
+            &main_db,
+            &recommend_db,
+            &[all, ("filter::in_progress", "true")]
+        )
+        .await?
+        .is_empty()
+    );
+    let mut not_in_progress = mix_file_ids(
+        &main_db,
+        &recommend_db,
+        &[all, ("filter::in_progress", "false")],
+    )
+    .await?;
+    not_in_progress.sort();
+    assert_eq!(not_in_progress, [book.id, song.id]);
+
+    Ok(())
+}
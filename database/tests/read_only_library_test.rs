@@ -0,0 +1,206 @@
+#![cfg(unix)]
+
+use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+use anyhow::Result;
+use sea_orm::{EntityTrait, PaginatorTrait};
+
+use ::database::{
+    actions::logging::{LogLevel, insert_log},
+    connection::{
+        connect_main_db, get_storage_info, probe_library_writable, resolve_library_read_only,
+    },
+    entities::log,
+};
+use ::fsio::FsIo;
+
+fn set_read_only(path: &Path, read_only: bool) -> Result<()> {
+    let mode = if read_only { 0o555 } else { 0o755 };
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+/// Makes the library read-only, returning `false` when permissions are not
+/// enforced, e.g. when the tests run as root.
+async fn make_read_only(fsio: &FsIo, lib_path: &Path) -> Result<bool> {
+    set_read_only(&lib_path.join(".rune"), true)?;
+    set_read_only(lib_path, true)?;
+
+    if probe_library_writable(fsio, lib_path.to_str().unwrap()).await {
+        restore_writable(lib_path)?;
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+fn restore_writable(lib_path: &Path) -> Result<()> {
+    set_read_only(lib_path, false)?;
+    set_read_only(&lib_path.join(".rune"), false)
+}
+
+#[tokio::test]
+async fn writable_library_keeps_its_database_inside() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let config_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+
+    assert!(probe_library_writable(&fsio, lib_path).await);
+    assert!(!lib_dir.path().join(".rune-write-probe").exists());
+
+    let storage_info = get_storage_info(lib_path, config_dir.path().to_str(), false)?;
+    assert_eq!(storage_info.db_dir, lib_dir.path().join(".rune"));
+    assert!(!storage_info.read_only);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn library_becoming_read_only_keeps_its_history() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let config_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let config_path = config_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+
+    let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+    insert_log(
+        &main_db,
+        LogLevel::Info,
+        "test".to_string(),
+        "kept".to_string(),
+    )
+    .await?;
+    main_db.close().await?;
+    assert!(lib_dir.path().join(".rune/.0.db").exists());
+
+    if !make_read_only(&fsio, lib_dir.path()).await? {
+        eprintln!("Permissions are not enforced, skipping");
+        return Ok(());
+    }
+
+    let result = async {
+        let storage_info = get_storage_info(lib_path, Some(config_path), true)?;
+        let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+
+        assert!(storage_info.db_dir.starts_with(config_dir.path()));
+        assert!(storage_info.get_main_db_path().exists());
+        assert_eq!(log::Entity::find().count(&main_db).await?, 1);
+
+        // The copy is writable and is used from now on
+        insert_log(
+            &main_db,
+            LogLevel::Info,
+            "test".to_string(),
+            "new".to_string(),
+        )
+        .await?;
+        main_db.close().await?;
+
+        let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+        assert_eq!(log::Entity::find().count(&main_db).await?, 2);
+
+        anyhow::Ok(())
+    }
+    .await;
+
+    restore_writable(lib_dir.path())?;
+    result
+}
+
+#[tokio::test]
+async fn library_writable_again_gets_its_databases_back() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let config_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let config_path = config_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+
+    let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+    main_db.close().await?;
+
+    if !make_read_only(&fsio, lib_dir.path()).await? {
+        eprintln!("Permissions are not enforced, skipping");
+        return Ok(());
+    }
+
+    let result = async {
+        assert!(resolve_library_read_only(&fsio, lib_path, Some(config_path)).await?);
+
+        let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+        insert_log(
+            &main_db,
+            LogLevel::Info,
+            "test".to_string(),
+            "read-only".to_string(),
+        )
+        .await?;
+        main_db.close().await?;
+
+        anyhow::Ok(get_storage_info(lib_path, Some(config_path), true)?)
+    }
+    .await;
+
+    restore_writable(lib_dir.path())?;
+    let relocated = result?;
+
+    assert!(!resolve_library_read_only(&fsio, lib_path, Some(config_path)).await?);
+    assert!(!relocated.get_main_db_path().exists());
+
+    let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+    assert_eq!(log::Entity::find().count(&main_db).await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn databases_changed_while_read_only_are_kept() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let config_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let config_path = config_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+
+    let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+    main_db.close().await?;
+
+    if !make_read_only(&fsio, lib_dir.path()).await? {
+        eprintln!("Permissions are not enforced, skipping");
+        return Ok(());
+    }
+
+    let result = async {
+        let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+        main_db.close().await?;
+
+        anyhow::Ok(get_storage_info(lib_path, Some(config_path), true)?)
+    }
+    .await;
+
+    restore_writable(lib_dir.path())?;
+    let relocated = result?;
+
+    // Another device wrote to the library while this one couldn't
+    let main_db = sea_orm::Database::connect(format!(
+        "sqlite:{}?mode=rw",
+        lib_dir.path().join(".rune/.0.db").display()
+    ))
+    .await?;
+    insert_log(
+        &main_db,
+        LogLevel::Info,
+        "test".to_string(),
+        "elsewhere".to_string(),
+    )
+    .await?;
+    main_db.close().await?;
+
+    assert!(!resolve_library_read_only(&fsio, lib_path, Some(config_path)).await?);
+    assert!(relocated.get_main_db_path().exists());
+
+    let main_db = connect_main_db(&fsio, lib_path, Some(config_path), "test").await?;
+    assert_eq!(log::Entity::find().count(&main_db).await?, 1);
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+
+use ::database::{
+    actions::schema::{
+        inspect_main_db_schema, migrate_main_db, migration_names, resolve_migration, schema_status,
+    },
+    connection::{connect_main_db, get_storage_info},
+};
+use ::fsio::FsIo;
+
+#[tokio::test]
+async fn every_migration_rolls_back_and_forward() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+
+    let names = migration_names();
+    let status = schema_status(&main_db).await?;
+    assert_eq!(status.version.as_ref(), names.last());
+    assert!(status.pending.is_empty());
+    assert!(!status.is_newer());
+
+    // Reverting the HLC columns restores the timestamps of the seeded mixes
+    let outcome = migrate_main_db(&main_db, &names[0], "test").await?;
+    assert_eq!(outcome.reverted as usize, names.len() - 1);
+    let status = schema_status(&main_db).await?;
+    assert_eq!(status.version.as_ref(), Some(&names[0]));
+    assert_eq!(status.pending.len(), names.len() - 1);
+
+    let outcome = migrate_main_db(&main_db, &status.latest_version, "test").await?;
+    assert_eq!(outcome.applied as usize, names.len() - 1);
+    assert!(schema_status(&main_db).await?.pending.is_empty());
+    main_db.close().await?;
+
+    let storage_info = get_storage_info(lib_path, None, false)?;
+    let inspected = inspect_main_db_schema(&storage_info.get_main_db_path()).await?;
+    assert_eq!(inspected.map(|x| x.version), Some(names.last().cloned()));
+
+    Ok(())
+}
+
+#[test]
+fn migrations_are_found_by_prefix() -> Result<()> {
+    let names = migration_names();
+    assert_eq!(resolve_migration("m20230701_000001")?, names[0]);
+    assert_eq!(resolve_migration(&names[1])?, names[1]);
+    assert!(resolve_migration("m2023").is_err());
+    assert!(resolve_migration("m1999").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+use ::database::{
+    actions::scrobble_history::{
+        ScrobbleHistoryEntry, ScrobbleHistoryFilter, accepted_services, insert_scrobble_history,
+        list_scrobble_history, mark_scrobble_accepted, prune_scrobble_history,
+    },
+    connection::connect_main_db,
+};
+use ::fsio::FsIo;
+
+fn entry(title: &str, timestamp: i64) -> ScrobbleHistoryEntry {
+    ScrobbleHistoryEntry {
+        media_file_id: None,
+        artist: "Artist".to_owned(),
+        title: title.to_owned(),
+        album: None,
+        timestamp,
+    }
+}
+
+#[tokio::test]
+async fn scrobble_history_records_accepting_services() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+
+    let now = Utc::now().timestamp();
+    insert_scrobble_history(&main_db, entry("Old", now - 3600)).await?;
+    insert_scrobble_history(&main_db, entry("Recent", now - 60)).await?;
+    insert_scrobble_history(&main_db, entry("Current", now)).await?;
+
+    assert!(mark_scrobble_accepted(&main_db, now - 60, "Artist", "Recent", "LastFm").await?);
+    assert!(mark_scrobble_accepted(&main_db, now - 60, "Artist", "Recent", "ListenBrainz").await?);
+    // Submitting a queued scrobble again doesn't list the service twice
+    assert!(mark_scrobble_accepted(&main_db, now - 60, "Artist", "Recent", "LastFm").await?);
+    assert!(!mark_scrobble_accepted(&main_db, now, "Artist", "Missing", "LastFm").await?);
+
+    let history = list_scrobble_history(&main_db, ScrobbleHistoryFilter::default(), 0, 10).await?;
+    let titles: Vec<_> = history.iter().map(|x| x.title.as_str()).collect();
+    assert_eq!(titles, ["Current", "Recent", "Old"]);
+    assert_eq!(accepted_services(&history[1]), ["LastFm", "ListenBrainz"]);
+    assert!(accepted_services(&history[0]).is_empty());
+
+    let filter = ScrobbleHistoryFilter {
+        start: DateTime::from_timestamp(now - 120, 0),
+        end: DateTime::from_timestamp(now - 30, 0),
+    };
+    let history = list_scrobble_history(&main_db, filter, 0, 10).await?;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].title, "Recent");
+
+    let page = list_scrobble_history(&main_db, ScrobbleHistoryFilter::default(), 1, 2).await?;
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].title, "Old");
+
+    assert_eq!(
+        prune_scrobble_history(&main_db, Duration::minutes(30)).await?,
+        1
+    );
+    let history = list_scrobble_history(&main_db, ScrobbleHistoryFilter::default(), 0, 10).await?;
+    assert_eq!(history.len(), 2);
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+
+use ::database::{
+    actions::scrobble_queue::{
+        clear_scrobble_queue, count_queued_scrobbles, enqueue_love, enqueue_scrobble,
+        increase_scrobble_attempts, is_love, list_queued_loves, list_queued_scrobbles,
+        remove_queued_scrobbles,
+    },
+    connection::connect_main_db,
+};
+use ::fsio::FsIo;
+
+#[tokio::test]
+async fn queued_scrobbles_are_listed_in_listening_order() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+
+    let later = enqueue_scrobble(&main_db, "LastFm", "later".to_owned(), 200).await?;
+    let earlier = enqueue_scrobble(&main_db, "LastFm", "earlier".to_owned(), 100).await?;
+    enqueue_scrobble(&main_db, "ListenBrainz", "other".to_owned(), 150).await?;
+
+    let queued = list_queued_scrobbles(&main_db, "LastFm", 10).await?;
+    let tracks: Vec<_> = queued.iter().map(|x| x.track.as_str()).collect();
+    assert_eq!(tracks, ["earlier", "later"]);
+    assert_eq!(list_queued_scrobbles(&main_db, "LastFm", 1).await?.len(), 1);
+
+    increase_scrobble_attempts(&main_db, &[earlier.id]).await?;
+    let queued = list_queued_scrobbles(&main_db, "LastFm", 10).await?;
+    assert_eq!(queued[0].attempts, 1);
+    assert_eq!(queued[1].attempts, 0);
+
+    assert_eq!(remove_queued_scrobbles(&main_db, &[later.id]).await?, 1);
+    assert_eq!(count_queued_scrobbles(&main_db, "LastFm").await?, 1);
+    assert_eq!(count_queued_scrobbles(&main_db, "ListenBrainz").await?, 1);
+
+    assert_eq!(clear_scrobble_queue(&main_db, "LastFm").await?, 1);
+    assert_eq!(count_queued_scrobbles(&main_db, "LastFm").await?, 0);
+    assert_eq!(count_queued_scrobbles(&main_db, "ListenBrainz").await?, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn only_the_latest_love_of_a_track_is_queued() -> Result<()> {
+    let lib_dir = tempfile::tempdir()?;
+    let lib_path = lib_dir.path().to_str().unwrap();
+    let fsio = FsIo::new();
+    let main_db = connect_main_db(&fsio, lib_path, None, "test").await?;
+
+    enqueue_scrobble(&main_db, "LastFm", "listened".to_owned(), 100).await?;
+    enqueue_love(&main_db, "LastFm", "track".to_owned(), true, 110).await?;
+    enqueue_love(&main_db, "LastFm", "other".to_owned(), true, 120).await?;
+    enqueue_love(&main_db, "LastFm", "track".to_owned(), false, 130).await?;
+
+    let loves = list_queued_loves(&main_db, "LastFm", 10).await?;
+    let tracks: Vec<_> = loves
+        .iter()
+        .map(|x| (x.track.as_str(), is_love(x)))
+        .collect();
+    assert_eq!(tracks, [("other", true), ("track", false)]);
+
+    // Loves are not listens
+    let queued = list_queued_scrobbles(&main_db, "LastFm", 10).await?;
+    assert_eq!(queued.len(), 1);
+    assert_eq!(count_queued_scrobbles(&main_db, "LastFm").await?, 1);
+
+    assert_eq!(clear_scrobble_queue(&main_db, "LastFm").await?, 3);
+    assert!(list_queued_loves(&main_db, "LastFm", 10).await?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use ::database::actions::tag_normalize::{
+    DEFAULT_TITLE_CASE_EXCEPTIONS, NormalizationRules, fix_mojibake, normalize_fields, sort_name,
+    title_case, unify_featuring,
+};
+
+fn fields(values: &[(&str, &str)]) -> HashMap<String, String> {
+    values
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn exceptions() -> Vec<String> {
+    DEFAULT_TITLE_CASE_EXCEPTIONS
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+}
+
+#[test]
+fn mojibake_is_decoded_again() {
+    assert_eq!(fix_mojibake("BeyoncÃ©").as_deref(), Some("Beyoncé"));
+    // Windows-1252 punctuation
+    assert_eq!(fix_mojibake("Donâ€™t Stop").as_deref(), Some("Don’t Stop"));
+    assert_eq!(fix_mojibake("Café"), None);
+    assert_eq!(fix_mojibake("東京"), None);
+    assert_eq!(fix_mojibake("Plain"), None);
+}
+
+#[test]
+fn featuring_notation_is_unified() {
+    assert_eq!(unify_featuring("Artist ft. Guest"), "Artist feat. Guest");
+    assert_eq!(
+        unify_featuring("Song (Featuring Guest)"),
+        "Song (feat. Guest)"
+    );
+    assert_eq!(unify_featuring("Artist Feat Guest"), "Artist feat. Guest");
+    assert_eq!(unify_featuring("Left Behind"), "Left Behind");
+    assert_eq!(unify_featuring("Daft Punk"), "Daft Punk");
+}
+
+#[test]
+fn title_case_keeps_exceptions_and_inner_capitals() {
+    assert_eq!(
+        title_case("THE DARK SIDE OF THE MOON", &exceptions()),
+        "The Dark Side of the Moon"
+    );
+    assert_eq!(
+        title_case("songs for an iPod (feat. guest)", &exceptions()),
+        "Songs for an iPod (feat. Guest)"
+    );
+    assert_eq!(
+        title_case("what are you looking at", &exceptions()),
+        "What Are You Looking At"
+    );
+    assert_eq!(title_case("Part II", &exceptions()), "Part II");
+
+    let mut exceptions = exceptions();
+    exceptions.push("AC/DC".to_owned());
+    assert_eq!(title_case("BEST OF AC/DC", &exceptions), "Best of AC/DC");
+}
+
+#[test]
+fn sort_name_drops_the_article() {
+    assert_eq!(sort_name("The Beatles").as_deref(), Some("Beatles"));
+    assert_eq!(sort_name("the the").as_deref(), Some("the"));
+    assert_eq!(sort_name("The"), None);
+    assert_eq!(sort_name("Theatre of Tragedy"), None);
+}
+
+#[test]
+fn normalization_returns_changed_fields_only() {
+    let current = fields(&[
+        ("track_title", "  HELLO   WORLD "),
+        ("artist", "The Band ft. Guest"),
+        ("album", "Already Fine"),
+        ("genre", "Rock "),
+    ]);
+
+    assert_eq!(
+        normalize_fields(&current, &NormalizationRules::default()),
+        vec![
+            ("track_title".to_owned(), "Hello World".to_owned()),
+            ("artist".to_owned(), "The Band feat. Guest".to_owned()),
+            ("genre".to_owned(), "Rock".to_owned()),
+            ("sort_artist".to_owned(), "Band feat. Guest".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn normalization_rules_can_be_turned_off() {
+    let current = fields(&[
+        ("track_title", "LOUD  SONG"),
+        ("artist", "The Band"),
+        ("album_artist", "The Band"),
+        ("sort_album_artist", "Band, The"),
+    ]);
+    let rules = NormalizationRules {
+        title_case: false,
+        ..Default::default()
+    };
+
+    // Sort names already set are kept
+    assert_eq!(
+        normalize_fields(&current, &rules),
+        vec![
+            ("track_title".to_owned(), "LOUD SONG".to_owned()),
+            ("sort_artist".to_owned(), "Band".to_owned()),
+        ]
+    );
+
+    let rules = NormalizationRules {
+        trim_whitespace: false,
+        collapse_spaces: false,
+        unify_featuring: false,
+        title_case: false,
+        title_case_exceptions: Vec::new(),
+        sort_names: false,
+        fix_mojibake: false,
+    };
+    assert!(normalize_fields(&current, &rules).is_empty());
+}
@@ -134,7 +134,10 @@ async fn main() -> anyhow::Result<()> {
         device_type: Some(DeviceType::Headless),
         fingerprint,
         api_port,
+        ws_port: None,
+        path_prefix: String::new(),
         protocol: "http".to_string(),
+        endpoints: vec![],
     };
 
     let discovery = DeviceDiscovery::new().await?;
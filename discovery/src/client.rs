@@ -3,14 +3,17 @@ use std::{
     io::Error as IoError,
     path::Path,
     sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
 };
 
 use anyhow::{Result, anyhow, bail};
-use futures::{FutureExt, future::select_all};
+use dashmap::DashMap;
+use futures::{StreamExt, stream::FuturesUnordered};
 use http_body_util::Empty;
-use hyper::{Uri, body::Bytes, http::Error as HttpError};
+use hyper::{body::Bytes, http::Error as HttpError};
 use hyper_util::rt::TokioIo;
 use log::warn;
+use once_cell::sync::Lazy;
 use pem::Pem;
 use rustls::{
     Error as RustlsError, RootCertStore, SignatureScheme,
@@ -24,14 +27,12 @@ use rustls::{
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::{
-    net::TcpStream,
-    sync::{broadcast, oneshot},
-};
+use tokio::{net::TcpStream, sync::broadcast, time::timeout};
 use tokio_rustls::TlsConnector;
 use webpki_roots::TLS_SERVER_ROOTS;
 use x509_parser::parse_x509_certificate;
 
+use crate::endpoint::{DEFAULT_API_PORT, ServerEndpoint};
 use crate::persistent::{PersistenceError, PersistentDataManager};
 use crate::{ssl::calculate_base85_fingerprint, utils::server_name_to_string};
 
@@ -103,6 +104,14 @@ pub struct CertValidator {
 pub struct FingerprintReport {
     /// HashMap mapping certificate fingerprints to a list of trusted hostnames.
     pub entries: HashMap<String, Vec<String>>, // fingerprint -> list of hosts
+    /// Fingerprints that were explicitly untrusted, so they are not trusted again
+    /// by accident when importing a trust bundle.
+    #[serde(default)]
+    pub revoked: Vec<String>,
+    /// Expiry (UNIX timestamp) of the last certificate presented for each
+    /// trusted fingerprint, so stale entries are not handed to other devices.
+    #[serde(default)]
+    pub expires_at: HashMap<String, i64>,
 }
 
 impl CertValidator {
@@ -215,6 +224,9 @@ impl CertValidator {
                     hosts.dedup(); // Remove duplicate entries
                 }
 
+                // Trusting a fingerprint again lifts a previous revocation
+                report.revoked.retain(|x| x != &fingerprint);
+
                 Ok::<_, CertValidatorError>((report, ())) // Return updated report and success result
             })
             .await?;
@@ -269,11 +281,54 @@ impl CertValidator {
             .update(|mut report| async move {
                 // Update operation on persistent storage
                 report.entries.remove(fingerprint); // Remove the entry for the given fingerprint
+                report.expires_at.remove(fingerprint);
+
+                // Remember the revocation so imports do not trust it again silently
+                if !report.revoked.iter().any(|x| x == fingerprint) {
+                    report.revoked.push(fingerprint.to_string());
+                }
+
                 Ok((report, ())) // Return updated report and success result
             })
             .await
     }
 
+    /// Lists the fingerprints that were removed from the trust store.
+    ///
+    /// # Returns
+    /// `Vec<String>` - A vector of revoked certificate fingerprints.
+    pub async fn list_revoked_fingerprints(&self) -> Vec<String> {
+        self.storage.read().await.revoked.clone()
+    }
+
+    /// Remembers when the certificate presented for `fingerprint` expires.
+    ///
+    /// The trust store is only written when the expiry changed, so this is
+    /// cheap to call on every handshake.
+    pub async fn record_certificate_expiry(
+        &self,
+        fingerprint: &str,
+        expires_at: i64,
+    ) -> Result<(), CertValidatorError> {
+        if self.storage.read().await.expires_at.get(fingerprint) == Some(&expires_at) {
+            return Ok(());
+        }
+
+        self.storage
+            .update(|mut report| async move {
+                report
+                    .expires_at
+                    .insert(fingerprint.to_string(), expires_at);
+                Ok((report, ()))
+            })
+            .await
+    }
+
+    /// Lists the known certificate expiry (UNIX timestamp) of trusted fingerprints.
+    pub async fn list_certificate_expiry(&self) -> HashMap<String, i64> {
+        self.storage.read().await.expires_at.clone()
+    }
+
     /// Retrieves the list of trusted hostnames associated with a given certificate fingerprint.
     ///
     /// This method looks up a certificate fingerprint in the persistent storage and returns the list of hostnames
@@ -459,6 +514,20 @@ impl ServerCertVerifier for CertValidator {
             .unwrap_or(false); // Default to false if fingerprint not found
 
         if is_trusted {
+            // Remember the expiry for trust bundle exports without blocking the handshake
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                let validator = self.clone();
+                let expires_at = cert.validity().not_after.timestamp();
+                handle.spawn(async move {
+                    if let Err(e) = validator
+                        .record_certificate_expiry(&fingerprint, expires_at)
+                        .await
+                    {
+                        warn!("Failed to record the certificate expiry of {fingerprint}: {e}");
+                    }
+                });
+            }
+
             Ok(ServerCertVerified::assertion()) // Certificate is trusted based on fingerprint
         } else {
             warn!(
@@ -546,7 +615,7 @@ pub async fn fetch_server_certificate(url: &str) -> Result<ServerCert, CertValid
         .host()
         .ok_or(CertValidatorError::InvalidServerName)? // Extract host from Uri
         .to_string();
-    let port = uri.port_u16().unwrap_or(7863); // Extract port from Uri, default to 7863 if not specified
+    let port = uri.port_u16().unwrap_or(DEFAULT_API_PORT); // Extract port from Uri, fall back to the default port if not specified
 
     let cert_info = Arc::new(Mutex::new(None)); // Arc Mutex to share certificate info across threads
     let verifier = TempCertVerifier {
@@ -760,33 +829,183 @@ pub async fn trust_server(
 /// - TCP connection fails.
 /// - TLS handshake fails.
 pub async fn try_connect(host: &str, config: ClientConfig) -> Result<String> {
-    let uri = host.parse::<Uri>()?; // Parse host string to Uri
-    let host_str = uri.host().unwrap().to_string(); // Extract host string from Uri
+    handshake(host, Arc::new(config)).await?; // Establish TLS connection
+
+    Ok(host.to_string()) // Return host string on successful connection
+}
+
+/// Connects to `host` and completes a TLS handshake, returning the fingerprint of the
+/// certificate the server presented.
+///
+/// The certificate is checked by the verifier of `config` during the handshake, so with a
+/// config built from a [`CertValidator`] only trusted fingerprints are ever returned.
+async fn handshake(host: &str, config: Arc<ClientConfig>) -> Result<String> {
+    let endpoint = ServerEndpoint::parse(host)?; // Parse the host specification
+    let host_str = endpoint.host.clone(); // Extract host string from the endpoint
     let sni = ServerName::try_from(host_str.clone())?; // Create ServerName for SNI
 
-    let connector = TlsConnector::from(Arc::new(config)); // Create TLS connector from ClientConfig
-    let tcp = TcpStream::connect((host_str.as_str(), uri.port_u16().unwrap_or(7863))).await?; // Connect to host via TCP
-    let _ = connector.connect(sni, tcp).await?; // Establish TLS connection
+    let connector = TlsConnector::from(config); // Create TLS connector from ClientConfig
+    let tcp = TcpStream::connect((host_str.as_str(), endpoint.api_port)).await?; // Connect to host via TCP
+    let tls = connector.connect(sni, tcp).await?; // Establish TLS connection
 
-    Ok(host.to_string()) // Return host string on successful connection
+    let certificate = tls
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|x| x.first())
+        .ok_or_else(|| anyhow!("Server presented no certificate"))?;
+    let (_, raw_cert) = parse_x509_certificate(certificate.as_ref())
+        .map_err(|e| anyhow!("Failed to parse server certificate: {e}"))?;
+
+    calculate_base85_fingerprint(raw_cert.public_key().raw)
+}
+
+/// How long a single host probe may take before the host is considered unreachable.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The host that won the last selection for each server fingerprint.
+static STICKY_HOSTS: Lazy<DashMap<String, String>> = Lazy::new(DashMap::new);
+
+/// Outcome of probing a single host.
+#[derive(Debug, Clone)]
+pub struct HostProbe {
+    pub host: String,
+    /// Time until the TLS handshake completed, `None` if it failed.
+    pub latency: Option<Duration>,
+    /// Fingerprint of the certificate presented by the host.
+    pub fingerprint: Option<String>,
+    pub error: Option<String>,
 }
 
-/// Selects the best host from a list of candidates by connecting to all of them in parallel
-/// and returning the first one that successfully establishes a TLS connection and validates
-/// the certificate.
+/// Outcome of [`probe_best_host`].
+#[derive(Debug, Clone, Default)]
+pub struct HostSelection {
+    /// Every probe that ran, in the order they completed.
+    pub probes: Vec<HostProbe>,
+    /// Index of the selected probe in `probes`.
+    pub selected: Option<usize>,
+    /// Whether the host remembered from a previous selection was reused.
+    pub sticky: bool,
+}
+
+impl HostSelection {
+    pub fn host(&self) -> Option<&str> {
+        self.selected.map(|x| self.probes[x].host.as_str())
+    }
+
+    /// Converts the selection into the selected host, or an error describing every
+    /// failed probe.
+    pub fn into_host(self) -> Result<String> {
+        if let Some(host) = self.host() {
+            return Ok(host.to_string());
+        }
+
+        let errors = self
+            .probes
+            .iter()
+            .map(|x| {
+                format!(
+                    "{}: {}",
+                    x.host,
+                    x.error.as_deref().unwrap_or("unknown error")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        Err(anyhow!("All connection attempts failed: {errors}"))
+    }
+}
+
+/// Probes a host with a TLS handshake bounded by `limit`.
+async fn probe_host(host: String, config: Arc<ClientConfig>, limit: Duration) -> HostProbe {
+    let start = Instant::now();
+
+    let result = match timeout(limit, handshake(&host, config)).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("Timed out after {}ms", limit.as_millis())),
+    };
+
+    match result {
+        Ok(fingerprint) => HostProbe {
+            host,
+            latency: Some(start.elapsed()),
+            fingerprint: Some(fingerprint),
+            error: None,
+        },
+        Err(e) => HostProbe {
+            host,
+            latency: None,
+            fingerprint: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Selects the host with the fastest TLS handshake among `hosts`.
 ///
-/// This function attempts to connect to all provided hosts simultaneously and returns as soon
-/// as one host successfully connects. It cancels all remaining connection attempts once a
-/// successful connection is established.
+/// The host that won the previous selection for the same server is tried first. If it
+/// fails, or presents another certificate than the one it won with, every host is probed
+/// in parallel, each bounded by [`PROBE_TIMEOUT`], and the first successful handshake wins.
+/// The certificate fingerprint is validated during each handshake, so an impostor can
+/// never win the race.
+pub async fn probe_best_host(hosts: Vec<String>, config: Arc<ClientConfig>) -> HostSelection {
+    let mut selection = HostSelection::default();
+
+    let sticky = STICKY_HOSTS
+        .iter()
+        .find(|x| hosts.contains(x.value()))
+        .map(|x| (x.key().clone(), x.value().clone()));
+
+    if let Some((fingerprint, host)) = sticky {
+        let probe = probe_host(host.clone(), config.clone(), PROBE_TIMEOUT).await;
+
+        if probe.fingerprint.as_deref() == Some(fingerprint.as_str()) {
+            selection.probes.push(probe);
+            selection.selected = Some(0);
+            selection.sticky = true;
+            return selection;
+        }
+
+        warn!("Remembered host {host} failed, probing every candidate");
+        STICKY_HOSTS.remove(&fingerprint);
+        selection.probes.push(probe);
+    }
+
+    let mut probes: FuturesUnordered<_> = hosts
+        .into_iter()
+        .map(|host| probe_host(host, config.clone(), PROBE_TIMEOUT))
+        .collect();
+
+    // Handshakes run concurrently, so the first one to succeed is the fastest
+    while let Some(probe) = probes.next().await {
+        let succeeded = probe.fingerprint.is_some();
+        selection.probes.push(probe);
+
+        if succeeded {
+            let index = selection.probes.len() - 1;
+            let winner = &selection.probes[index];
+            if let Some(fingerprint) = &winner.fingerprint {
+                STICKY_HOSTS.insert(fingerprint.clone(), winner.host.clone());
+            }
+            selection.selected = Some(index);
+            break;
+        }
+    }
+
+    selection
+}
+
+/// Selects the best host from a list of candidates, see [`probe_best_host`].
 ///
 /// # Arguments
 /// * `hosts` - A vector of host URLs to try connecting to.
 /// * `config` - The rustls `ClientConfig` to use for establishing the TLS connections.
 ///
 /// # Returns
-/// `Result<String, anyhow::Error>` - A `Result` containing the URL of the first host that
-///                                   successfully connected, or an `anyhow::Error` if all
-///                                   connection attempts failed.
+/// `Result<String, anyhow::Error>` - A `Result` containing the URL of the host with the
+///                                   fastest successful handshake, or an `anyhow::Error` if
+///                                   all connection attempts failed.
 ///
 /// # Errors
 /// Returns `anyhow::Error` if:
@@ -797,62 +1016,7 @@ pub async fn select_best_host(hosts: Vec<String>, config: Arc<ClientConfig>) ->
         return Err(anyhow!("No hosts provided"));
     }
 
-    // Create a channel for cancellation
-    let (tx, rx) = oneshot::channel();
-    let cancel_signal = rx.shared();
-
-    // Create a future for each host that can be cancelled
-    let mut connection_tasks = Vec::with_capacity(hosts.len());
-
-    for host in hosts {
-        let config_clone = config.clone();
-        let cancel_clone = cancel_signal.clone();
-
-        // Create a future that will try to connect to the host and can be cancelled
-        let task = async move {
-            let host_clone = host.clone();
-
-            // Create a cancellable connection future
-            let connection_future = async move {
-                match try_connect(&host_clone, (*config_clone).clone()).await {
-                    Ok(_) => Ok(host_clone),
-                    Err(e) => Err(anyhow!("Failed to connect to {}: {}", host_clone, e)),
-                }
-            };
-
-            // Race the connection future against the cancellation signal
-            match futures::future::select(connection_future.boxed(), cancel_clone).await {
-                futures::future::Either::Left((result, _)) => result,
-                futures::future::Either::Right((_, _)) => {
-                    // Connection was cancelled, return a dummy error
-                    Err(anyhow!("Connection to {} was cancelled", host))
-                }
-            }
-        };
-
-        connection_tasks.push(task.boxed());
-    }
-
-    // Use select_all to race all connection futures
-    let (result, _, remaining_tasks) = select_all(connection_tasks).await;
-
-    // Cancel all remaining tasks
-    let _ = tx.send(());
-
-    // Drop remaining futures to free resources
-    drop(remaining_tasks);
-
-    // Return the result of the first completed future
-    match result {
-        Ok(host) => Ok(host),
-        Err(e) => {
-            // If all tasks failed, aggregate the errors
-            Err(anyhow!(
-                "All connection attempts failed. First error: {}",
-                e
-            ))
-        }
-    }
+    probe_best_host(hosts, config).await.into_host()
 }
 
 /// Utility function to test multiple hosts and measure their response times.
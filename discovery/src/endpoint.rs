@@ -0,0 +1,238 @@
+use std::net::IpAddr;
+
+use anyhow::{Result, anyhow};
+use hyper::Uri;
+
+use crate::utils::DeviceInfo;
+
+/// Port the combined HTTP/WebSocket server listens on unless configured otherwise.
+pub const DEFAULT_API_PORT: u16 = 7863;
+
+/// Location of the HTTP and WebSocket endpoints of a Rune server.
+///
+/// A server may listen for WebSocket connections on a different port than
+/// the one serving files, and may be mounted below a path prefix when it
+/// sits behind a reverse proxy. Every client-side URL should be built from
+/// this type instead of assuming the default layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerEndpoint {
+    /// Host name or IP address, without brackets for IPv6.
+    pub host: String,
+    /// Port serving the HTTP API and the files.
+    pub api_port: u16,
+    /// Port accepting WebSocket connections.
+    pub ws_port: u16,
+    /// Normalized path prefix, either empty or starting with `/` without a
+    /// trailing slash.
+    pub path_prefix: String,
+}
+
+impl ServerEndpoint {
+    /// Creates an endpoint on `host` with the default layout.
+    pub fn new(host: &str) -> Self {
+        ServerEndpoint {
+            host: host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            api_port: DEFAULT_API_PORT,
+            ws_port: DEFAULT_API_PORT,
+            path_prefix: String::new(),
+        }
+    }
+
+    /// Parses a host specification.
+    ///
+    /// Accepts a bare host or IP address (`192.168.1.2`, `fe80::1`), a host
+    /// with a port (`example.com:8000`, `[fe80::1]:8000`) or a full URL
+    /// with a path prefix (`https://example.com/rune/`). Missing parts fall
+    /// back to the default layout.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+
+        if let Ok(ip) = spec.parse::<IpAddr>() {
+            return Ok(ServerEndpoint::new(&ip.to_string()));
+        }
+
+        let url = if spec.contains("://") {
+            spec.to_string()
+        } else {
+            format!("https://{spec}")
+        };
+        let uri = url
+            .parse::<Uri>()
+            .map_err(|e| anyhow!("Invalid server address {spec}: {e}"))?;
+        let host = uri
+            .host()
+            .filter(|x| !x.is_empty())
+            .ok_or_else(|| anyhow!("Missing host in server address {spec}"))?;
+        let port = uri.port_u16().unwrap_or(DEFAULT_API_PORT);
+
+        Ok(ServerEndpoint {
+            api_port: port,
+            ws_port: port,
+            path_prefix: normalize_path_prefix(uri.path()),
+            ..ServerEndpoint::new(host)
+        })
+    }
+
+    /// Applies the ports and path prefix a server advertises about itself.
+    pub fn with_device_info(self, device_info: &DeviceInfo) -> Self {
+        self.with_advertised(
+            device_info.api_port,
+            device_info.ws_port,
+            &device_info.path_prefix,
+        )
+    }
+
+    /// Applies advertised values, a missing `ws_port` means the WebSocket
+    /// endpoint shares the API port.
+    pub fn with_advertised(self, api_port: u16, ws_port: Option<u16>, path_prefix: &str) -> Self {
+        ServerEndpoint {
+            api_port,
+            ws_port: ws_port.unwrap_or(api_port),
+            path_prefix: normalize_path_prefix(path_prefix),
+            ..self
+        }
+    }
+
+    fn host_for_url(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        }
+    }
+
+    /// The `host:port` authority of the HTTP API.
+    pub fn authority(&self) -> String {
+        format!("{}:{}", self.host_for_url(), self.api_port)
+    }
+
+    /// Prepends the path prefix to an absolute path.
+    pub fn path(&self, path: &str) -> String {
+        format!("{}{}", self.path_prefix, path)
+    }
+
+    /// The base URL of the HTTP API, without a trailing slash.
+    pub fn base_url(&self) -> String {
+        format!("https://{}{}", self.authority(), self.path_prefix)
+    }
+
+    /// Builds the URL of an HTTP API path, `path` must start with `/`.
+    pub fn http_url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url(), path)
+    }
+
+    /// The URL of the WebSocket endpoint.
+    pub fn ws_url(&self) -> String {
+        format!(
+            "wss://{}:{}{}/ws",
+            self.host_for_url(),
+            self.ws_port,
+            self.path_prefix
+        )
+    }
+
+    /// A host specification that [`ServerEndpoint::parse`] turns back into
+    /// this endpoint, the WebSocket port aside.
+    pub fn to_spec(&self) -> String {
+        if self.api_port == DEFAULT_API_PORT && self.path_prefix.is_empty() {
+            self.host.clone()
+        } else if self.path_prefix.is_empty() {
+            self.authority()
+        } else {
+            self.base_url()
+        }
+    }
+}
+
+/// Normalizes a path prefix to either an empty string or `/segment[/...]`.
+pub fn normalize_path_prefix(prefix: &str) -> String {
+    let trimmed = prefix.trim().trim_matches('/');
+
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{trimmed}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        let endpoint = ServerEndpoint::parse("192.168.1.2").unwrap();
+
+        assert_eq!(endpoint.http_url("/ping"), "https://192.168.1.2:7863/ping");
+        assert_eq!(endpoint.ws_url(), "wss://192.168.1.2:7863/ws");
+        assert_eq!(endpoint.to_spec(), "192.168.1.2");
+    }
+
+    #[test]
+    fn test_custom_port_and_prefix() {
+        let endpoint = ServerEndpoint::parse("https://music.example.com:8443/rune/").unwrap();
+
+        assert_eq!(endpoint.host, "music.example.com");
+        assert_eq!(endpoint.api_port, 8443);
+        assert_eq!(endpoint.path_prefix, "/rune");
+        assert_eq!(
+            endpoint.http_url("/files/cache/1.png"),
+            "https://music.example.com:8443/rune/files/cache/1.png"
+        );
+        assert_eq!(endpoint.ws_url(), "wss://music.example.com:8443/rune/ws");
+        assert_eq!(
+            ServerEndpoint::parse(&endpoint.to_spec()).unwrap(),
+            endpoint
+        );
+    }
+
+    #[test]
+    fn test_host_with_port() {
+        let endpoint = ServerEndpoint::parse("10.0.0.5:9000").unwrap();
+
+        assert_eq!(endpoint.authority(), "10.0.0.5:9000");
+        assert_eq!(endpoint.ws_url(), "wss://10.0.0.5:9000/ws");
+        assert_eq!(endpoint.to_spec(), "10.0.0.5:9000");
+    }
+
+    #[test]
+    fn test_ipv6() {
+        let bare = ServerEndpoint::parse("fe80::1").unwrap();
+        assert_eq!(bare.http_url("/ping"), "https://[fe80::1]:7863/ping");
+
+        let with_port = ServerEndpoint::parse("[fe80::1]:8000").unwrap();
+        assert_eq!(with_port.host, "fe80::1");
+        assert_eq!(with_port.ws_url(), "wss://[fe80::1]:8000/ws");
+    }
+
+    #[test]
+    fn test_split_websocket_port() {
+        let endpoint = ServerEndpoint::parse("192.168.1.2")
+            .unwrap()
+            .with_advertised(8000, Some(8001), "rune");
+
+        assert_eq!(
+            endpoint.http_url("/ping"),
+            "https://192.168.1.2:8000/rune/ping"
+        );
+        assert_eq!(endpoint.ws_url(), "wss://192.168.1.2:8001/rune/ws");
+    }
+
+    #[test]
+    fn test_normalize_path_prefix() {
+        assert_eq!(normalize_path_prefix(""), "");
+        assert_eq!(normalize_path_prefix("/"), "");
+        assert_eq!(normalize_path_prefix("rune"), "/rune");
+        assert_eq!(normalize_path_prefix("/rune/"), "/rune");
+        assert_eq!(normalize_path_prefix("/apps/rune"), "/apps/rune");
+    }
+
+    #[test]
+    fn test_invalid_spec() {
+        assert!(ServerEndpoint::parse("").is_err());
+        assert!(ServerEndpoint::parse("https://").is_err());
+    }
+}
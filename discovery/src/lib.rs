@@ -2,10 +2,12 @@ use utils::DeviceInfo;
 
 pub mod client;
 pub mod config;
+pub mod endpoint;
 pub mod persistent;
 pub mod protocol;
 pub mod server;
 pub mod ssl;
+pub mod trust_bundle;
 pub mod url;
 pub mod utils;
 
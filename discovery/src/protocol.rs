@@ -36,6 +36,7 @@ use tokio::{net::UdpSocket, sync::Mutex, task::JoinHandle};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
+    endpoint::{DEFAULT_API_PORT, normalize_path_prefix},
     persistent::PersistentDataManager,
     utils::{DeviceInfo, DeviceType},
 };
@@ -59,6 +60,19 @@ pub struct DiscoveredDevice {
     pub last_seen: DateTime<Utc>,
     /// List of IP addresses from which the device's announcements have been observed.
     pub ips: Vec<IpAddr>,
+    /// Port of the HTTP API advertised by the device.
+    #[serde(default = "default_api_port")]
+    pub api_port: u16,
+    /// Port of the WebSocket endpoint if it differs from `api_port`.
+    #[serde(default)]
+    pub ws_port: Option<u16>,
+    /// Path prefix the device's server is mounted at.
+    #[serde(default)]
+    pub path_prefix: String,
+}
+
+fn default_api_port() -> u16 {
+    DEFAULT_API_PORT
 }
 
 /// Multicast group address for device discovery.
@@ -402,7 +416,10 @@ impl DiscoveryService {
                     "deviceType": device_info.device_type,
                     "fingerprint": device_info.fingerprint,
                     "api_port": device_info.api_port,
+                    "wsPort": device_info.ws_port,
+                    "pathPrefix": device_info.path_prefix,
                     "protocol": device_info.protocol,
+                    "endpoints": device_info.endpoints,
                     "announce": true
                 });
                 if let Err(e) = Self::send_announcement(&sockets, &announcement).await {
@@ -588,6 +605,17 @@ impl DiscoveryService {
             device_type: serde_json::from_value(announcement["deviceType"].clone())?, // Deserialize device type
             ips: vec![addr.ip()],  // Record sender IP address
             last_seen: Utc::now(), // Update last seen timestamp to now
+            api_port: announcement["api_port"]
+                .as_u64()
+                .and_then(|x| u16::try_from(x).ok())
+                .unwrap_or(DEFAULT_API_PORT),
+            ws_port: announcement["wsPort"]
+                .as_u64()
+                .and_then(|x| u16::try_from(x).ok()),
+            path_prefix: announcement["pathPrefix"]
+                .as_str()
+                .map(normalize_path_prefix)
+                .unwrap_or_default(),
         };
 
         // Update device state or insert new device if not already known
@@ -596,6 +624,9 @@ impl DiscoveryService {
                 existing.ips.push(addr.ip()); // Add new IP if not already listed
             }
             existing.last_seen = Utc::now(); // Update last seen timestamp
+            existing.api_port = device.api_port;
+            existing.ws_port = device.ws_port;
+            existing.path_prefix = device.path_prefix;
         } else {
             devices.insert(fingerprint, device); // Insert new device into device states
         }
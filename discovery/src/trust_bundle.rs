@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit,
+    aead::{Aead, AeadCore, OsRng, generic_array::GenericArray},
+};
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::Argon2;
+use chrono::Utc;
+use log::warn;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use x509_parser::parse_x509_certificate;
+
+use crate::client::{CertValidator, parse_certificate};
+
+/// Leading bytes of an encrypted trust bundle file.
+const MAGIC: &[u8; 8] = b"RUNETRST";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The client certificate of the exporting device, so another controller
+/// can act as the same client on servers that already trust it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientIdentity {
+    pub alias: String,
+    pub certificate: String,
+    pub private_key: String,
+}
+
+/// A portable copy of the trust store of a device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustBundle {
+    /// Creation time (UNIX timestamp)
+    pub created_at: i64,
+    /// Certificate fingerprint to trusted hosts.
+    pub entries: BTreeMap<String, Vec<String>>,
+    /// Fingerprints the exporting device untrusted.
+    pub revoked: Vec<String>,
+    /// Expiry (UNIX timestamp) of the certificates behind `entries`, when
+    /// the exporting device has seen them.
+    #[serde(default)]
+    pub expires_at: BTreeMap<String, i64>,
+    pub identity: Option<ClientIdentity>,
+}
+
+/// A host already trusted for another fingerprint than the imported one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustConflict {
+    pub host: String,
+    pub existing_fingerprint: String,
+    pub imported_fingerprint: String,
+}
+
+/// Outcome of merging a [`TrustBundle`] into the local trust store.
+#[derive(Debug, Clone, Default)]
+pub struct TrustImportReport {
+    /// Fingerprints trusted for at least one new host.
+    pub imported: Vec<String>,
+    /// Hosts that were skipped because they resolve to another fingerprint.
+    pub conflicts: Vec<TrustConflict>,
+    /// Fingerprints that were skipped because either device revoked them.
+    pub revoked: Vec<String>,
+    /// Certificates of the bundle that are no longer valid and were skipped.
+    pub expired: Vec<String>,
+    /// The client identity of the bundle that should be installed, if any.
+    pub identity: Option<ClientIdentity>,
+}
+
+impl TrustBundle {
+    /// Snapshots the trust store of `validator`.
+    pub async fn from_validator(
+        validator: &CertValidator,
+        identity: Option<ClientIdentity>,
+    ) -> Self {
+        let mut entries: BTreeMap<_, _> = validator
+            .clone_trusted_entries()
+            .await
+            .into_iter()
+            .collect();
+        entries.retain(|_, hosts| !hosts.is_empty());
+
+        let expires_at = validator
+            .list_certificate_expiry()
+            .await
+            .into_iter()
+            .filter(|(fingerprint, _)| entries.contains_key(fingerprint))
+            .collect();
+
+        TrustBundle {
+            created_at: Utc::now().timestamp(),
+            entries,
+            revoked: validator.list_revoked_fingerprints().await,
+            expires_at,
+            identity,
+        }
+    }
+
+    /// Serializes and encrypts the bundle with a key derived from `password`.
+    pub fn encrypt(&self, password: &str) -> Result<Vec<u8>> {
+        if password.is_empty() {
+            bail!("A password is required to export the trust store");
+        }
+
+        let plaintext = serde_json::to_vec(self).context("Failed to serialize trust bundle")?;
+
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let cipher = derive_cipher(password, &salt)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt trust bundle"))?;
+
+        let mut data =
+            Vec::with_capacity(MAGIC.len() + 1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.push(FORMAT_VERSION);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        Ok(data)
+    }
+
+    /// Decrypts a bundle produced by [`TrustBundle::encrypt`].
+    pub fn decrypt(data: &[u8], password: &str) -> Result<Self> {
+        let header_len = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+        if data.len() < header_len || &data[..MAGIC.len()] != MAGIC {
+            bail!("Not a trust bundle");
+        }
+
+        let version = data[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            bail!("Unsupported trust bundle version {version}");
+        }
+
+        let salt = &data[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+        let nonce = GenericArray::from_slice(&data[MAGIC.len() + 1 + SALT_LEN..header_len]);
+
+        let plaintext = derive_cipher(password, salt)?
+            .decrypt(nonce, &data[header_len..])
+            .map_err(|_| anyhow!("Wrong password or corrupted trust bundle"))?;
+
+        serde_json::from_slice(&plaintext).context("Failed to parse trust bundle")
+    }
+
+    /// Merges the bundle into the trust store of `validator`.
+    ///
+    /// Nothing is overwritten: hosts already trusted for another fingerprint
+    /// are reported as conflicts, and fingerprints revoked on either device
+    /// or expired certificates are reported instead of being imported.
+    pub async fn import_into(
+        self,
+        validator: &CertValidator,
+        import_identity: bool,
+    ) -> Result<TrustImportReport> {
+        let mut report = TrustImportReport::default();
+        let local_revoked = validator.list_revoked_fingerprints().await;
+        let now = Utc::now().timestamp();
+
+        for (fingerprint, hosts) in self.entries {
+            if local_revoked.contains(&fingerprint) || self.revoked.contains(&fingerprint) {
+                warn!("Skipping revoked fingerprint {fingerprint}");
+                report.revoked.push(fingerprint);
+                continue;
+            }
+
+            let expires_at = self.expires_at.get(&fingerprint).copied();
+            if expires_at.is_some_and(|x| x <= now) {
+                warn!("Skipping the expired certificate {fingerprint}");
+                report.expired.push(fingerprint);
+                continue;
+            }
+
+            let mut accepted = Vec::new();
+            for host in hosts {
+                let existing = validator.find_fingerprints_by_host(&host).await;
+
+                match existing.into_iter().find(|x| x != &fingerprint) {
+                    Some(existing_fingerprint) => report.conflicts.push(TrustConflict {
+                        host,
+                        existing_fingerprint,
+                        imported_fingerprint: fingerprint.clone(),
+                    }),
+                    None => accepted.push(host),
+                }
+            }
+
+            if accepted.is_empty() {
+                continue;
+            }
+
+            validator
+                .add_trusted_domains(&accepted, &fingerprint)
+                .await?;
+            if let Some(expires_at) = expires_at {
+                validator
+                    .record_certificate_expiry(&fingerprint, expires_at)
+                    .await?;
+            }
+            report.imported.push(fingerprint);
+        }
+
+        if import_identity && let Some(identity) = self.identity {
+            let (_, fingerprint) = parse_certificate(&identity.certificate)
+                .context("Invalid client certificate in trust bundle")?;
+
+            if is_certificate_expired(&identity.certificate)? {
+                warn!("Skipping the expired client certificate {fingerprint}");
+                report.expired.push(fingerprint);
+            } else {
+                report.identity = Some(identity);
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+fn derive_cipher(password: &str, salt: &[u8]) -> Result<Aes256Gcm> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive the bundle key: {e}"))?;
+
+    Aes256Gcm::new_from_slice(&key).map_err(|e| anyhow!("Invalid bundle key: {e}"))
+}
+
+/// Checks whether a PEM encoded certificate is outside of its validity period.
+pub fn is_certificate_expired(certificate: &str) -> Result<bool> {
+    let pem = pem::parse(certificate).context("Failed to parse certificate PEM")?;
+    let (_, cert) = parse_x509_certificate(pem.contents())
+        .map_err(|e| anyhow!("Failed to parse certificate: {e}"))?;
+
+    Ok(!cert.validity().is_valid())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rune-trust-bundle-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn bundle() -> TrustBundle {
+        TrustBundle {
+            created_at: 1_700_000_000,
+            entries: BTreeMap::from([("fp-a".to_owned(), vec!["10.0.0.2".to_owned()])]),
+            revoked: vec!["fp-old".to_owned()],
+            expires_at: BTreeMap::new(),
+            identity: None,
+        }
+    }
+
+    #[test]
+    fn bundles_survive_a_round_trip() {
+        let data = bundle().encrypt("secret").unwrap();
+        assert!(data.starts_with(MAGIC));
+
+        let decrypted = TrustBundle::decrypt(&data, "secret").unwrap();
+        assert_eq!(decrypted.created_at, 1_700_000_000);
+        assert_eq!(decrypted.entries, bundle().entries);
+        assert_eq!(decrypted.revoked, ["fp-old"]);
+        assert!(decrypted.identity.is_none());
+    }
+
+    #[test]
+    fn wrong_passwords_are_rejected() {
+        let data = bundle().encrypt("secret").unwrap();
+
+        assert!(TrustBundle::decrypt(&data, "Secret").is_err());
+        assert!(TrustBundle::decrypt(&data, "").is_err());
+        assert!(bundle().encrypt("").is_err());
+    }
+
+    #[test]
+    fn tampered_bundles_are_rejected() {
+        let data = bundle().encrypt("secret").unwrap();
+
+        assert!(TrustBundle::decrypt(&data[..data.len() - 1], "secret").is_err());
+        assert!(TrustBundle::decrypt(&data[..MAGIC.len() + 1 + SALT_LEN], "secret").is_err());
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(TrustBundle::decrypt(&tampered, "secret").is_err());
+
+        let mut tampered = data.clone();
+        tampered[MAGIC.len() + 1] ^= 1;
+        assert!(TrustBundle::decrypt(&tampered, "secret").is_err());
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        let data = bundle().encrypt("secret").unwrap();
+
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        let error = TrustBundle::decrypt(&bad_magic, "secret").unwrap_err();
+        assert_eq!(error.to_string(), "Not a trust bundle");
+
+        let mut bad_version = data.clone();
+        bad_version[MAGIC.len()] = FORMAT_VERSION + 1;
+        let error = TrustBundle::decrypt(&bad_version, "secret").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Unsupported trust bundle version {}", FORMAT_VERSION + 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn conflicting_hosts_are_not_overwritten() -> Result<()> {
+        let dir = temp_dir("conflicts");
+        let validator = CertValidator::new(&dir).await?;
+        validator
+            .add_trusted_domains(["10.0.0.2"], "fp-local")
+            .await?;
+
+        let mut bundle = bundle();
+        bundle.entries.insert(
+            "fp-a".to_owned(),
+            vec!["10.0.0.2".to_owned(), "10.0.0.3".to_owned()],
+        );
+        bundle
+            .entries
+            .insert("fp-b".to_owned(), vec!["10.0.0.2".to_owned()]);
+        let report = bundle.import_into(&validator, false).await?;
+
+        assert_eq!(report.imported, ["fp-a"]);
+        assert_eq!(
+            report.conflicts,
+            ["fp-a", "fp-b"].map(|x| TrustConflict {
+                host: "10.0.0.2".to_owned(),
+                existing_fingerprint: "fp-local".to_owned(),
+                imported_fingerprint: x.to_owned(),
+            })
+        );
+        assert_eq!(
+            validator.get_hosts_for_fingerprint("fp-a").await,
+            ["10.0.0.3"]
+        );
+        assert!(validator.get_hosts_for_fingerprint("fp-b").await.is_empty());
+        assert_eq!(
+            validator.get_hosts_for_fingerprint("fp-local").await,
+            ["10.0.0.2"]
+        );
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fingerprints_revoked_on_either_device_are_skipped() -> Result<()> {
+        let dir = temp_dir("revoked");
+        let validator = CertValidator::new(&dir).await?;
+        validator.add_trusted_domains(["10.0.0.4"], "fp-b").await?;
+        validator.remove_fingerprint("fp-b").await?;
+
+        let mut bundle = bundle();
+        bundle
+            .entries
+            .insert("fp-b".to_owned(), vec!["10.0.0.4".to_owned()]);
+        bundle
+            .entries
+            .insert("fp-old".to_owned(), vec!["10.0.0.5".to_owned()]);
+        let report = bundle.import_into(&validator, false).await?;
+
+        assert_eq!(report.imported, ["fp-a"]);
+        assert_eq!(report.revoked, ["fp-b", "fp-old"]);
+        assert!(validator.get_hosts_for_fingerprint("fp-b").await.is_empty());
+        assert!(
+            validator
+                .get_hosts_for_fingerprint("fp-old")
+                .await
+                .is_empty()
+        );
+        assert_eq!(validator.list_revoked_fingerprints().await, ["fp-b"]);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_certificates_are_skipped() -> Result<()> {
+        let dir = temp_dir("expired");
+        let validator = CertValidator::new(&dir).await?;
+        let now = Utc::now().timestamp();
+
+        let mut bundle = bundle();
+        bundle
+            .entries
+            .insert("fp-c".to_owned(), vec!["10.0.0.6".to_owned()]);
+        bundle.expires_at.insert("fp-a".to_owned(), now - 60);
+        bundle.expires_at.insert("fp-c".to_owned(), now + 3600);
+        let report = bundle.import_into(&validator, false).await?;
+
+        assert_eq!(report.imported, ["fp-c"]);
+        assert_eq!(report.expired, ["fp-a"]);
+        assert!(validator.get_hosts_for_fingerprint("fp-a").await.is_empty());
+        assert_eq!(
+            validator.list_certificate_expiry().await,
+            HashMap::from([("fp-c".to_owned(), now + 3600)])
+        );
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
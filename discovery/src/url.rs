@@ -1,20 +1,35 @@
 use std::error::Error;
 use std::fmt;
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::from_utf8;
 
 use log::error;
 
+use crate::endpoint::{DEFAULT_API_PORT, ServerEndpoint};
+
+/// Protocol version assumed for URLs that do not carry one.
+pub const RNSRV_PROTOCOL_VERSION: u32 = 1;
+
+/// Length of a base-36 encoded IPv4 address.
+const IPV4_ENCODED_LEN: usize = 7;
+/// Length of a base-36 encoded IPv6 address.
+const IPV6_ENCODED_LEN: usize = 25;
+
 /// Represents errors that can occur during IPv4 address encoding.
 #[derive(Debug)]
 pub enum EncodeError {
     /// Indicates that the provided string is not a valid IPv4 address.
     InvalidIPv4,
+    /// Indicates that the provided string is neither an IPv4 nor an IPv6 address.
+    InvalidAddress,
 }
 
 impl fmt::Display for EncodeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Invalid IPv4 address")
+        match self {
+            Self::InvalidIPv4 => write!(f, "Invalid IPv4 address"),
+            Self::InvalidAddress => write!(f, "Invalid IP address"),
+        }
     }
 }
 
@@ -160,7 +175,11 @@ pub enum UrlError {
     InvalidProtocol,
     /// Indicates that the format of the URL after the protocol prefix is invalid.
     InvalidFormat,
-    /// Wraps a `DecodeError` that occurred during IPv4 address decoding within the URL.
+    /// Indicates that a host entry carries a port that is not a valid non-zero `u16`.
+    InvalidPort,
+    /// Indicates that the protocol version field is missing its number or is zero.
+    InvalidVersion,
+    /// Wraps a `DecodeError` that occurred during IP address decoding within the URL.
     DecodeError(DecodeError),
 }
 
@@ -175,6 +194,8 @@ impl std::fmt::Display for UrlError {
         match self {
             Self::InvalidProtocol => write!(f, "Invalid URL protocol: expected 'rnsrv://'"),
             Self::InvalidFormat => write!(f, "Invalid URL format after protocol prefix"),
+            Self::InvalidPort => write!(f, "Invalid port in URL"),
+            Self::InvalidVersion => write!(f, "Invalid protocol version in URL"),
             Self::DecodeError(e) => write!(f, "IP address decode error: {e}"),
         }
     }
@@ -189,92 +210,455 @@ impl std::error::Error for UrlError {
     }
 }
 
-/// Encodes a slice of IPv4 address strings into a RuneScape server URL format.
+/// Encodes an IPv6 address string into a 25-character base-36 encoded string.
+///
+/// This is the IPv6 counterpart of [`encode_ipv4`], 25 base-36 digits being
+/// the fewest that can hold every 128-bit value.
 ///
-/// This function takes a slice of IPv4 address strings, encodes each IP address using `encode_ipv4`,
-/// and concatenates them into a URL string with the "rnsrv://" protocol prefix.
+/// # Errors
+///
+/// * `EncodeError::InvalidAddress`: If the input string `ip` is not a valid IPv6 address.
+pub fn encode_ipv6(ip: &str) -> Result<String, EncodeError> {
+    let ipv6: Ipv6Addr = ip
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .map_err(|_| EncodeError::InvalidAddress)?;
+    let mut n = ipv6.to_bits();
+    let mut chars = Vec::with_capacity(IPV6_ENCODED_LEN);
+
+    for _ in 0..IPV6_ENCODED_LEN {
+        let rem = (n % 36) as u8;
+        chars.push(match rem {
+            0..=9 => b'0' + rem,
+            _ => b'a' + rem - 10,
+        });
+        n /= 36;
+    }
+
+    chars.reverse();
+    Ok(String::from_utf8(chars).unwrap())
+}
+
+/// Decodes a 25-character base-36 encoded string back into an IPv6 address string.
+///
+/// # Errors
+///
+/// * `DecodeError::InvalidLength`: If the input string `s` is not exactly 25 characters long.
+/// * `DecodeError::InvalidCharacter`: If the input string `s` contains non base-36 characters.
+/// * `DecodeError::Overflow`: If the decoded value exceeds the 128-bit range.
+pub fn decode_ipv6(s: &str) -> Result<String, DecodeError> {
+    if s.len() != IPV6_ENCODED_LEN {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    let mut num: u128 = 0;
+    for c in s.chars() {
+        let d = c.to_digit(36).ok_or(DecodeError::InvalidCharacter)?;
+        num = num
+            .checked_mul(36)
+            .and_then(|n| n.checked_add(d as u128))
+            .ok_or(DecodeError::Overflow)?;
+    }
+
+    Ok(Ipv6Addr::from_bits(num).to_string())
+}
+
+/// A single host of a Rune server URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RnsrvHost {
+    pub ip: IpAddr,
+    /// Port of the HTTP API, [`DEFAULT_API_PORT`] when the URL omits it.
+    pub port: u16,
+}
+
+impl RnsrvHost {
+    /// Creates a host listening on the default port.
+    pub fn new(ip: IpAddr) -> Self {
+        RnsrvHost {
+            ip,
+            port: DEFAULT_API_PORT,
+        }
+    }
+
+    /// A host specification accepted by [`ServerEndpoint::parse`].
+    pub fn to_spec(&self) -> String {
+        ServerEndpoint {
+            api_port: self.port,
+            ws_port: self.port,
+            ..ServerEndpoint::new(&self.ip.to_string())
+        }
+        .to_spec()
+    }
+
+    fn encode(&self) -> String {
+        let mut encoded = match self.ip {
+            // Encoding a valid address can not fail.
+            IpAddr::V4(ip) => encode_ipv4(&ip.to_string()).unwrap(),
+            IpAddr::V6(ip) => encode_ipv6(&ip.to_string()).unwrap(),
+        };
+
+        if self.port != DEFAULT_API_PORT {
+            encoded.push('.');
+            encoded.push_str(&self.port.to_string());
+        }
+
+        encoded
+    }
+
+    fn decode(entry: &str) -> Result<Self, UrlError> {
+        let (address, port) = match entry.split_once('.') {
+            Some((address, port)) => {
+                // Only plain decimal digits are accepted, so every port has a
+                // single representation.
+                if port.is_empty() || !port.bytes().all(|x| x.is_ascii_digit()) {
+                    return Err(UrlError::InvalidPort);
+                }
+
+                let port = port.parse::<u16>().map_err(|_| UrlError::InvalidPort)?;
+                if port == 0 {
+                    return Err(UrlError::InvalidPort);
+                }
+                (address, port)
+            }
+            None => (entry, DEFAULT_API_PORT),
+        };
+
+        let ip = match address.len() {
+            IPV4_ENCODED_LEN => decode_ipv4(address)?,
+            IPV6_ENCODED_LEN => decode_ipv6(address)?,
+            _ => return Err(DecodeError::InvalidLength.into()),
+        };
+
+        Ok(RnsrvHost {
+            // The decoders only produce valid addresses.
+            ip: ip.parse().unwrap(),
+            port,
+        })
+    }
+}
+
+/// A decoded Rune server URL.
+///
+/// Two layouts exist. The original one is `rnsrv://` followed by the
+/// 7-character encoding of every IPv4 address, implying the default port and
+/// protocol version 1. The extended one is `rnsrv://v<version>-<entry>-...`,
+/// where every entry is an encoded IPv4 or IPv6 address optionally followed
+/// by `.<port>`. Only URL-unreserved characters are used so the URL survives
+/// being embedded anywhere.
+///
+/// [`RnsrvUrl::encode`] falls back to the original layout whenever it can
+/// express the URL, so older clients keep understanding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RnsrvUrl {
+    pub version: u32,
+    pub hosts: Vec<RnsrvHost>,
+}
+
+impl RnsrvUrl {
+    /// Creates a URL for the current protocol version.
+    pub fn new(hosts: Vec<RnsrvHost>) -> Self {
+        RnsrvUrl {
+            version: RNSRV_PROTOCOL_VERSION,
+            hosts,
+        }
+    }
+
+    /// Whether this build speaks the protocol version of the URL.
+    pub fn is_supported(&self) -> bool {
+        self.version <= RNSRV_PROTOCOL_VERSION
+    }
+
+    /// Host specifications accepted by [`ServerEndpoint::parse`], in URL order.
+    pub fn host_specs(&self) -> Vec<String> {
+        self.hosts.iter().map(RnsrvHost::to_spec).collect()
+    }
+
+    pub fn encode(&self) -> String {
+        let legacy = self.version == RNSRV_PROTOCOL_VERSION
+            && !self.hosts.is_empty()
+            && self
+                .hosts
+                .iter()
+                .all(|x| x.ip.is_ipv4() && x.port == DEFAULT_API_PORT);
+
+        if legacy {
+            return format!(
+                "rnsrv://{}",
+                self.hosts.iter().map(RnsrvHost::encode).collect::<String>()
+            );
+        }
+
+        let mut buffer = format!("rnsrv://v{}", self.version);
+        for host in &self.hosts {
+            buffer.push('-');
+            buffer.push_str(&host.encode());
+        }
+
+        buffer
+    }
+}
+
+impl fmt::Display for RnsrvUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.encode())
+    }
+}
+
+/// Encodes a slice of IP address strings into a Rune server URL.
+///
+/// Every address is assumed to serve on the default port, use [`RnsrvUrl`]
+/// directly to encode other ports.
 ///
 /// # Arguments
 ///
-/// * `ips` - A slice of string slices, where each string slice represents an IPv4 address in
-///   dotted decimal notation.
+/// * `ips` - A slice of string slices, where each string slice represents an IPv4 or IPv6 address.
 ///
 /// # Returns
 ///
-/// * `Result<String, EncodeError>` - On success, returns `Ok` containing the RuneScape server URL string.
-///   On failure, returns `Err` containing an `EncodeError` if any of the IPv4 addresses fail to encode.
+/// * `Result<String, EncodeError>` - On success, returns `Ok` containing the Rune server URL string.
+///   On failure, returns `Err` containing an `EncodeError` if any of the addresses fail to parse.
 ///
 /// # Errors
 ///
-/// * `EncodeError::InvalidIPv4`: If any of the IPv4 addresses in the input slice are invalid.
-///   This error is propagated from the `encode_ipv4` function.
+/// * `EncodeError::InvalidAddress`: If any of the addresses in the input slice are invalid.
 pub fn encode_rnsrv_url(ips: &[&str]) -> Result<String, EncodeError> {
-    // Initialize a String buffer to build the URL efficiently.
-    // Pre-allocate capacity to avoid reallocations, assuming 7 characters per IP and 8 for "rnsrv://".
-    let mut buffer = String::with_capacity(7 * ips.len() + 8);
-    // Push the "rnsrv://" protocol prefix to the buffer.
-    buffer.push_str("rnsrv://");
-
-    // Iterate through each IPv4 address string in the input slice.
-    for ip in ips {
-        // Encode each IPv4 address using `encode_ipv4` and append the result to the buffer.
-        // Propagate any `EncodeError` from `encode_ipv4`.
-        buffer.push_str(&encode_ipv4(ip)?);
-    }
+    let hosts = ips
+        .iter()
+        .map(|ip| {
+            ip.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .map(RnsrvHost::new)
+                .map_err(|_| EncodeError::InvalidAddress)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    // Return the built URL string.
-    Ok(buffer)
+    Ok(RnsrvUrl::new(hosts).encode())
 }
 
-/// Decodes a RuneScape server URL string back into a vector of IPv4 address strings.
+/// Decodes a Rune server URL string back into its hosts and protocol version.
 ///
-/// This function takes a RuneScape server URL string, verifies the "rnsrv://" protocol prefix,
-/// and decodes the encoded IPv4 addresses within the URL using `decode_ipv4`.
+/// Both the original and the extended layout described on [`RnsrvUrl`] are
+/// accepted.
 ///
 /// # Arguments
 ///
-/// * `url` - A string slice representing the RuneScape server URL string.
+/// * `url` - A string slice representing the Rune server URL string.
 ///
 /// # Returns
 ///
-/// * `Result<Vec<String>, UrlError>` - On success, returns `Ok` containing a vector of IPv4 address strings.
+/// * `Result<RnsrvUrl, UrlError>` - On success, returns `Ok` containing the decoded URL.
 ///   On failure, returns `Err` containing a `UrlError`.
 ///
 /// # Errors
 ///
 /// * `UrlError::InvalidProtocol`: If the input URL does not start with "rnsrv://".
-/// * `UrlError::InvalidFormat`: If the part of the URL after "rnsrv://" is not a valid sequence
-///   of 7-character encoded IPv4 addresses (i.e., length is not a multiple of 7).
-/// * `UrlError::DecodeError`: If any of the 7-character chunks within the URL fail to decode
-///   into a valid IPv4 address. This wraps a `DecodeError` from the `decode_ipv4` function.
-pub fn decode_rnsrv_url(url: &str) -> Result<Vec<String>, UrlError> {
+/// * `UrlError::InvalidFormat`: If the part of the URL after "rnsrv://" is neither a sequence
+///   of 7-character encoded IPv4 addresses nor a well formed extended URL.
+/// * `UrlError::InvalidVersion`: If the version field of an extended URL is not a positive number.
+/// * `UrlError::InvalidPort`: If a host entry carries an invalid port.
+/// * `UrlError::DecodeError`: If any of the encoded addresses fail to decode.
+pub fn decode_rnsrv_url(url: &str) -> Result<RnsrvUrl, UrlError> {
     // Check if the URL starts with the "rnsrv://" protocol prefix.
-    // If not, return an `InvalidProtocol` error.
-    if !url.starts_with("rnsrv://") {
+    let Some(encoded) = url.strip_prefix("rnsrv://") else {
         error!("Unable to parse: {url}");
         return Err(UrlError::InvalidProtocol);
+    };
+
+    // The extended layout always starts with the version field. An encoded
+    // IPv4 address never starts with `v`, its first digit is at most `1`.
+    if let Some(extended) = encoded.strip_prefix('v') {
+        let mut parts = extended.split('-');
+
+        // `split` always yields at least one part.
+        let version = parts.next().unwrap();
+        if version.is_empty() || !version.bytes().all(|x| x.is_ascii_digit()) {
+            return Err(UrlError::InvalidVersion);
+        }
+        let version = version
+            .parse::<u32>()
+            .ok()
+            .filter(|x| *x > 0)
+            .ok_or(UrlError::InvalidVersion)?;
+
+        let hosts = parts
+            .map(|entry| {
+                if entry.is_empty() {
+                    Err(UrlError::InvalidFormat)
+                } else {
+                    RnsrvHost::decode(entry)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        return Ok(RnsrvUrl { version, hosts });
     }
 
-    // Extract the encoded part of the URL, which is after the "rnsrv://" prefix.
-    let encoded = &url[8..];
-    // Check if the length of the encoded part is a multiple of 7.
-    // If not, the format is invalid, return `InvalidFormat` error.
-    if !encoded.len().is_multiple_of(7) {
+    // Otherwise the URL is a plain sequence of 7-character IPv4 addresses.
+    if !encoded.len().is_multiple_of(IPV4_ENCODED_LEN) {
         return Err(UrlError::InvalidFormat);
     }
 
-    // Initialize a vector to store the decoded IPv4 address strings.
-    let mut ips = Vec::with_capacity(encoded.len() / 7);
-    // Iterate over the encoded string in chunks of 7 characters.
-    for chunk in encoded.as_bytes().chunks_exact(7) {
-        // Convert each 7-character chunk to a string slice.
-        // This should be safe as we are expecting base-36 ASCII characters.
+    let mut hosts = Vec::with_capacity(encoded.len() / IPV4_ENCODED_LEN);
+    for chunk in encoded.as_bytes().chunks_exact(IPV4_ENCODED_LEN) {
         let s = from_utf8(chunk).map_err(|_| UrlError::InvalidFormat)?;
-        // Decode each 7-character chunk using `decode_ipv4` and push the result to the `ips` vector.
-        // Propagate any `DecodeError` from `decode_ipv4` by converting it to `UrlError::DecodeError`.
-        ips.push(decode_ipv4(s)?);
+        hosts.push(RnsrvHost::new(IpAddr::V4(decode_ipv4(s)?.parse().unwrap())));
+    }
+
+    Ok(RnsrvUrl {
+        version: RNSRV_PROTOCOL_VERSION,
+        hosts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn random_host(rng: &mut StdRng) -> RnsrvHost {
+        let ip = if rng.gen_bool(0.5) {
+            IpAddr::V4(Ipv4Addr::from_bits(rng.r#gen()))
+        } else {
+            IpAddr::V6(Ipv6Addr::from_bits(rng.r#gen()))
+        };
+        let port = if rng.gen_bool(0.5) {
+            DEFAULT_API_PORT
+        } else {
+            rng.gen_range(1..=u16::MAX)
+        };
+
+        RnsrvHost { ip, port }
+    }
+
+    #[test]
+    fn test_ipv4_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0x7863);
+
+        for _ in 0..1000 {
+            let ip = Ipv4Addr::from_bits(rng.r#gen()).to_string();
+            assert_eq!(decode_ipv4(&encode_ipv4(&ip).unwrap()).unwrap(), ip);
+        }
+    }
+
+    #[test]
+    fn test_ipv6_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0x7863);
+
+        for bits in [0, 1, u128::MAX] {
+            let ip = Ipv6Addr::from_bits(bits).to_string();
+            assert_eq!(decode_ipv6(&encode_ipv6(&ip).unwrap()).unwrap(), ip);
+        }
+
+        for _ in 0..1000 {
+            let ip = Ipv6Addr::from_bits(rng.r#gen()).to_string();
+            assert_eq!(decode_ipv6(&encode_ipv6(&ip).unwrap()).unwrap(), ip);
+        }
+    }
+
+    #[test]
+    fn test_url_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0x7863);
+
+        for _ in 0..1000 {
+            let hosts = (0..rng.gen_range(1..6))
+                .map(|_| random_host(&mut rng))
+                .collect();
+            let url = RnsrvUrl {
+                version: rng.gen_range(1..4),
+                hosts,
+            };
+
+            assert_eq!(decode_rnsrv_url(&url.encode()).unwrap(), url);
+        }
+    }
+
+    #[test]
+    fn test_legacy_url_compatibility() {
+        let legacy = encode_rnsrv_url(&["192.168.1.2", "10.0.0.5"]).unwrap();
+        assert_eq!(legacy.len(), 8 + 2 * IPV4_ENCODED_LEN);
+
+        let url = decode_rnsrv_url(&legacy).unwrap();
+        assert_eq!(url.version, RNSRV_PROTOCOL_VERSION);
+        assert_eq!(url.host_specs(), vec!["192.168.1.2", "10.0.0.5"]);
     }
 
-    // Return the vector of decoded IPv4 address strings.
-    Ok(ips)
+    #[test]
+    fn test_mixed_hosts() {
+        let url = RnsrvUrl::new(vec![
+            RnsrvHost::new("192.168.1.2".parse().unwrap()),
+            RnsrvHost {
+                ip: "10.0.0.5".parse().unwrap(),
+                port: 9000,
+            },
+            RnsrvHost {
+                ip: "fe80::1".parse().unwrap(),
+                port: 8000,
+            },
+            RnsrvHost::new("::1".parse().unwrap()),
+        ]);
+
+        let encoded = url.encode();
+        assert!(encoded.starts_with("rnsrv://v1-"));
+
+        let decoded = decode_rnsrv_url(&encoded).unwrap();
+        assert_eq!(decoded, url);
+        assert_eq!(
+            decoded.host_specs(),
+            vec!["192.168.1.2", "10.0.0.5:9000", "[fe80::1]:8000", "::1"]
+        );
+        for spec in decoded.host_specs() {
+            assert!(ServerEndpoint::parse(&spec).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let url = RnsrvUrl {
+            version: RNSRV_PROTOCOL_VERSION + 1,
+            hosts: vec![RnsrvHost::new("192.168.1.2".parse().unwrap())],
+        };
+
+        let decoded = decode_rnsrv_url(&url.encode()).unwrap();
+        assert_eq!(decoded.version, RNSRV_PROTOCOL_VERSION + 1);
+        assert!(!decoded.is_supported());
+    }
+
+    #[test]
+    fn test_invalid_urls() {
+        assert!(matches!(
+            decode_rnsrv_url("https://example.com"),
+            Err(UrlError::InvalidProtocol)
+        ));
+        assert!(matches!(
+            decode_rnsrv_url("rnsrv://abc"),
+            Err(UrlError::InvalidFormat)
+        ));
+        assert!(matches!(
+            decode_rnsrv_url("rnsrv://v-1a2b3c4"),
+            Err(UrlError::InvalidVersion)
+        ));
+        assert!(matches!(
+            decode_rnsrv_url("rnsrv://v0-1a2b3c4"),
+            Err(UrlError::InvalidVersion)
+        ));
+        assert!(matches!(
+            decode_rnsrv_url("rnsrv://v1-1a2b3c4.0"),
+            Err(UrlError::InvalidPort)
+        ));
+        assert!(matches!(
+            decode_rnsrv_url("rnsrv://v1-1a2b3c4.70000"),
+            Err(UrlError::InvalidPort)
+        ));
+        assert!(matches!(
+            decode_rnsrv_url("rnsrv://v1-1a2b3c4--1a2b3c4"),
+            Err(UrlError::InvalidFormat)
+        ));
+        assert!(matches!(
+            decode_rnsrv_url("rnsrv://v1-1a2b"),
+            Err(UrlError::DecodeError(DecodeError::InvalidLength))
+        ));
+    }
 }
@@ -1,5 +1,10 @@
-use std::{fmt, str::FromStr};
+use std::{
+    fmt,
+    net::{IpAddr as StdIpAddr, SocketAddr, SocketAddrV6},
+    str::FromStr,
+};
 
+use anyhow::{Result, bail};
 use chrono::{DateTime, Utc};
 use rustls::{
     Error as RustlsError,
@@ -70,7 +75,16 @@ pub struct DeviceInfo {
     pub device_type: Option<DeviceType>,
     pub fingerprint: String,
     pub api_port: u16,
+    /// Port accepting WebSocket connections when it differs from `api_port`.
+    #[serde(default)]
+    pub ws_port: Option<u16>,
+    /// Path the server is mounted at, e.g. `/rune` behind a reverse proxy.
+    #[serde(default)]
+    pub path_prefix: String,
     pub protocol: String,
+    /// Addresses the server is currently accepting connections on.
+    #[serde(default)]
+    pub endpoints: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -170,3 +184,36 @@ pub fn server_name_to_string(x: &ServerName<'_>) -> Result<String, RustlsError>
 
     Ok(result)
 }
+
+/// Returns the addresses currently assigned to the network interface with the given name,
+/// on `port`. Link-local IPv6 addresses are scoped to the interface, they can't be bound
+/// otherwise.
+pub fn interface_addresses(name: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    let Some(interface) = netdev::get_interfaces()
+        .into_iter()
+        .find(|x| x.name == name || x.friendly_name.as_deref() == Some(name))
+    else {
+        bail!("Network interface not found: {name}");
+    };
+
+    let addresses: Vec<SocketAddr> = interface
+        .ipv4
+        .iter()
+        .map(|x| SocketAddr::new(StdIpAddr::V4(x.addr()), port))
+        .chain(interface.ipv6.iter().map(|x| {
+            let ip = x.addr();
+            let scope_id = if ip.is_unicast_link_local() {
+                interface.index
+            } else {
+                0
+            };
+            SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id))
+        }))
+        .collect();
+
+    if addresses.is_empty() {
+        bail!("Network interface has no address assigned: {name}");
+    }
+
+    Ok(addresses)
+}
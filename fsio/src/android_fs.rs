@@ -1,19 +1,273 @@
 use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
     fs,
     os::unix::prelude::AsRawFd,
     path::{Component, Path, PathBuf},
     sync::{Arc, Mutex},
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 
 use async_trait::async_trait;
 use ndk_saf::{from_tree_url, open_content_url, AndroidFile, AndroidFileOps};
 use rusqlite::{params, Connection};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    copy::{check_cancelled, copy_stream},
+    space::{fd_space, volume_space},
+    walk::stream_walk,
+    watch::AbortOnDrop,
+    DiskSpace, FallbackTrash, FileIo, FileIoError, FileStream, FileWatchEvent, FileWatchHandle,
+    FsNode, FsNodeStream, ProgressFn,
+};
+
+/// SAF has no change notifications, watched trees are listed again at
+/// this interval and compared with the previous listing.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the stats gathered by a directory listing are trusted.
+const STAT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Columns returned for every child by a directory listing.
+#[derive(Debug, Clone)]
+struct DocumentStat {
+    filename: String,
+    url: String,
+    is_dir: bool,
+    size: u64,
+    last_modified: i64,
+}
+
+impl DocumentStat {
+    fn from_file(file: &AndroidFile) -> Self {
+        Self {
+            filename: file.filename.clone(),
+            url: file.url.clone(),
+            is_dir: file.is_dir,
+            size: file.size as u64,
+            last_modified: file.last_modified,
+        }
+    }
+
+    /// SAF only exposes the modification time of documents, in milliseconds.
+    fn node(&self, path: PathBuf) -> FsNode {
+        let modified = (self.last_modified > 0)
+            .then(|| UNIX_EPOCH + Duration::from_millis(self.last_modified as u64));
+        FsNode {
+            filename: self.filename.clone(),
+            raw_path: path.to_str().unwrap_or_default().to_string(),
+            path,
+            is_dir: self.is_dir,
+            is_file: !self.is_dir,
+            size: self.size,
+            modified,
+            created: None,
+            accessed: None,
+            readonly: false,
+        }
+    }
+}
+
+/// Stats of the documents seen by recent directory listings, so checking a
+/// file found by a walk does not cost one SAF query per file. Every walk
+/// starts with an empty cache.
+#[derive(Debug, Clone, Default)]
+struct StatCache {
+    entries: Arc<Mutex<HashMap<PathBuf, (Instant, DocumentStat)>>>,
+}
+
+impl StatCache {
+    fn get(&self, path: &Path) -> Option<DocumentStat> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(path)
+            .filter(|(cached_at, _)| cached_at.elapsed() < STAT_CACHE_TTL)
+            .map(|(_, stat)| stat.clone())
+    }
+
+    fn insert(&self, path: PathBuf, stat: DocumentStat) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(path, (Instant::now(), stat));
+    }
+
+    /// Drops `path` and everything cached below it.
+    fn forget(&self, path: &Path) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|x, _| !x.starts_with(path));
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// Lists the children of a directory with a single SAF query, recording
+/// their stats and refreshing their rows in the path cache.
+fn list_children(
+    url: &str,
+    path: &Path,
+    db: &Mutex<Connection>,
+    stats: &StatCache,
+) -> Result<Vec<(PathBuf, DocumentStat)>, FileIoError> {
+    let dir = from_tree_url(url).map_err(|e| FileIoError::Saf(e.to_string()))?;
+    let children: Vec<_> = dir
+        .list_files()
+        .map_err(|e| FileIoError::Saf(e.to_string()))?
+        .iter()
+        .map(|file| (path.join(&file.filename), DocumentStat::from_file(file)))
+        .collect();
+
+    let mut conn = db.lock().unwrap();
+    let tx = conn
+        .transaction()
+        .map_err(|e| FileIoError::Database(e.to_string()))?;
+    tx.execute(
+        "DELETE FROM fs_cache WHERE parent = ?1",
+        params![path.to_str().unwrap()],
+    )
+    .map_err(|e| FileIoError::Database(e.to_string()))?;
+    for (child_path, stat) in &children {
+        tx.execute(
+            "INSERT OR REPLACE INTO fs_cache (path, content_url, parent) VALUES (?1, ?2, ?3)",
+            params![
+                child_path.to_str().unwrap(),
+                stat.url,
+                path.to_str().unwrap()
+            ],
+        )
+        .map_err(|e| FileIoError::Database(e.to_string()))?;
+    }
+    tx.commit()
+        .map_err(|e| FileIoError::Database(e.to_string()))?;
+
+    for (child_path, stat) in &children {
+        stats.insert(child_path.clone(), stat.clone());
+    }
 
-use super::{FileIo, FileIoError, FileStream, FsNode};
+    Ok(children)
+}
+
+/// Walks a tree one directory listing at a time, so the first nodes are
+/// available before the whole tree has been listed.
+struct SafWalk {
+    db: Arc<Mutex<Connection>>,
+    stats: StatCache,
+    /// Directories left to list, with their content URL
+    directories: Vec<(PathBuf, String)>,
+    pending: VecDeque<(PathBuf, DocumentStat)>,
+}
+
+impl Iterator for SafWalk {
+    type Item = Result<FsNode, FileIoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((path, stat)) = self.pending.pop_front() {
+                if stat.is_dir {
+                    self.directories.push((path.clone(), stat.url.clone()));
+                }
+                return Some(Ok(stat.node(path)));
+            }
+
+            let (path, url) = self.directories.pop()?;
+            match list_children(&url, &path, &self.db, &self.stats) {
+                Ok(children) => self.pending.extend(children),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SnapshotEntry {
+    url: String,
+    is_dir: bool,
+    size: u64,
+    last_modified: i64,
+}
+
+type Snapshot = BTreeMap<PathBuf, SnapshotEntry>;
+
+fn take_snapshot(
+    file: AndroidFile,
+    current_path: &Path,
+    recursive: bool,
+    snapshot: &mut Snapshot,
+) -> Result<(), FileIoError> {
+    let files = file
+        .list_files()
+        .map_err(|e| FileIoError::Saf(e.to_string()))?;
+    for f in files {
+        let new_path = current_path.join(&f.filename);
+        snapshot.insert(
+            new_path.clone(),
+            SnapshotEntry {
+                url: f.url.clone(),
+                is_dir: f.is_dir,
+                size: f.size as u64,
+                last_modified: f.last_modified,
+            },
+        );
+
+        if recursive && f.is_dir {
+            take_snapshot(f, &new_path, recursive, snapshot)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reports the differences between two listings and applies them to the
+/// path cache.
+fn diff_snapshots(
+    previous: &Snapshot,
+    current: &Snapshot,
+    db: &Mutex<Connection>,
+    sender: &UnboundedSender<FileWatchEvent>,
+) -> Result<(), FileIoError> {
+    let mut events = Vec::new();
+    let conn = db.lock().unwrap();
+
+    for (path, entry) in current {
+        match previous.get(path) {
+            None => {
+                let parent = path.parent().unwrap_or(Path::new(""));
+                conn.execute(
+                    "INSERT OR REPLACE INTO fs_cache (path, content_url, parent) VALUES (?1, ?2, ?3)",
+                    params![path.to_str().unwrap(), entry.url, parent.to_str().unwrap()],
+                )
+                .map_err(|e| FileIoError::Database(e.to_string()))?;
+                events.push(FileWatchEvent::Created(path.clone()));
+            }
+            Some(old)
+                if !entry.is_dir
+                    && (old.size != entry.size || old.last_modified != entry.last_modified) =>
+            {
+                events.push(FileWatchEvent::Modified(path.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for path in previous.keys().filter(|x| !current.contains_key(*x)) {
+        conn.execute(
+            "DELETE FROM fs_cache WHERE path = ?1",
+            params![path.to_str().unwrap()],
+        )
+        .map_err(|e| FileIoError::Database(e.to_string()))?;
+        events.push(FileWatchEvent::Removed(path.clone()));
+    }
+
+    for event in events {
+        let _ = sender.send(event);
+    }
+    Ok(())
+}
 
 pub(crate) struct AndroidFsIo {
     db: Arc<Mutex<Connection>>,
     root_uri: String,
+    stats: StatCache,
 }
 
 impl AndroidFsIo {
@@ -43,6 +297,7 @@ impl AndroidFsIo {
         let instance = Self {
             db: Arc::new(Mutex::new(db)),
             root_uri: root_uri.to_string(),
+            stats: StatCache::default(),
         };
 
         instance.refresh_cache()?;
@@ -139,6 +394,11 @@ impl AndroidFsIo {
     }
 
     fn get_uri(&self, path: &Path) -> Result<String, FileIoError> {
+        // The root is not part of the cache
+        if path.as_os_str().is_empty() {
+            return Ok(self.root_uri.clone());
+        }
+
         let conn = self.db.lock().unwrap();
         let mut stmt = conn
             .prepare("SELECT content_url FROM fs_cache WHERE path = ?1")
@@ -163,6 +423,154 @@ impl AndroidFsIo {
         let uri = self.get_uri(path)?;
         from_tree_url(&uri).map_err(|e| FileIoError::Saf(e.to_string()))
     }
+
+    /// Stats of `path`, from the last listing of its directory if it is
+    /// recent enough.
+    fn stat(&self, path: &Path) -> Result<DocumentStat, FileIoError> {
+        if let Some(stat) = self.stats.get(path) {
+            return Ok(stat);
+        }
+
+        let stat = DocumentStat::from_file(&self.get_android_file(path)?);
+        self.stats.insert(path.to_path_buf(), stat.clone());
+        Ok(stat)
+    }
+
+    /// Creates an empty document at `path`, its parent has to exist.
+    fn create_document(&self, path: &Path) -> Result<AndroidFile, FileIoError> {
+        let parent = path.parent().unwrap_or_else(|| Path::new(""));
+        let name = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .ok_or(FileIoError::InvalidPath)?;
+
+        let file = self
+            .get_android_file(parent)?
+            .create_file("application/octet-stream", name)
+            .map_err(|e| FileIoError::Saf(e.to_string()))?;
+        self.stats.forget(path);
+
+        let conn = self.db.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO fs_cache (path, content_url, parent) VALUES (?1, ?2, ?3)",
+            params![path.to_str().unwrap(), file.url, parent.to_str().unwrap()],
+        )
+        .map_err(|e| FileIoError::Database(e.to_string()))?;
+
+        Ok(file)
+    }
+
+    /// Walks `path` with one SAF query per directory, starting a new scan
+    /// run for the stat cache.
+    /// A file on the volume of `path`: its closest existing ancestor if it
+    /// is a file, otherwise the first file listed in that directory.
+    fn space_document(&self, path: &Path) -> Result<AndroidFile, FileIoError> {
+        for ancestor in path.ancestors() {
+            let Ok(stat) = self.stat(ancestor) else {
+                continue;
+            };
+            if !stat.is_dir {
+                return from_tree_url(&stat.url).map_err(|e| FileIoError::Saf(e.to_string()));
+            }
+
+            let children = list_children(&stat.url, ancestor, &self.db, &self.stats)?;
+            return match children.into_iter().find(|(_, x)| !x.is_dir) {
+                Some((_, stat)) => {
+                    from_tree_url(&stat.url).map_err(|e| FileIoError::Saf(e.to_string()))
+                }
+                None => Err(FileIoError::NotSupported(format!(
+                    "disk space of {}, it holds no file",
+                    ancestor.display()
+                ))),
+            };
+        }
+
+        Err(FileIoError::PathNotFound(
+            path.to_string_lossy().to_string(),
+        ))
+    }
+
+    fn walk_entries(&self, path: &Path) -> Result<SafWalk, FileIoError> {
+        self.stats.clear();
+        Ok(SafWalk {
+            db: self.db.clone(),
+            stats: self.stats.clone(),
+            directories: vec![(path.to_path_buf(), self.get_uri(path)?)],
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Copies a document through content streams, replacing `to` and
+    /// removing the partial copy if anything fails.
+    fn copy_document(
+        &self,
+        from: &Path,
+        to: &Path,
+        progress: Option<&ProgressFn>,
+        cancel_token: Option<&CancellationToken>,
+    ) -> Result<u64, FileIoError> {
+        check_cancelled(cancel_token)?;
+
+        let source = self.get_android_file(from)?;
+        if source.is_dir {
+            return Err(FileIoError::NotSupported(
+                "copying directories on Android".to_string(),
+            ));
+        }
+
+        if self.exists(to)? {
+            self.get_android_file(to)?
+                .remove_file()
+                .map_err(|e| FileIoError::Saf(e.to_string()))?;
+            self.stats.forget(to);
+        }
+
+        let target = self.create_document(to)?;
+
+        let copied = (|| {
+            let mut reader = source
+                .open("r")
+                .map_err(|e| FileIoError::Saf(e.to_string()))?;
+            let mut writer = target
+                .open("w")
+                .map_err(|e| FileIoError::Saf(e.to_string()))?;
+            let copied = copy_stream(&mut reader, &mut writer, progress, cancel_token)?;
+            writer.sync_all()?;
+            Ok::<_, FileIoError>(copied)
+        })();
+
+        copied.or_else(|e| {
+            let _ = target.remove_file();
+            self.stats.forget(to);
+            let conn = self.db.lock().unwrap();
+            conn.execute(
+                "DELETE FROM fs_cache WHERE path = ?1",
+                params![to.to_str().unwrap()],
+            )
+            .map_err(|e| FileIoError::Database(e.to_string()))?;
+            Err(e)
+        })
+    }
+
+    /// Moves a document by copying it then removing it, ndk-saf does not
+    /// expose `DocumentsContract.moveDocument`.
+    fn move_document(&self, from: &Path, to: &Path) -> Result<(), FileIoError> {
+        self.copy_document(from, to, None, None)?;
+
+        self.get_android_file(from)?
+            .remove_file()
+            .map_err(|e| FileIoError::Saf(e.to_string()))?;
+        self.stats.forget(from);
+
+        let conn = self.db.lock().unwrap();
+        conn.execute(
+            "DELETE FROM fs_cache WHERE path = ?1",
+            params![from.to_str().unwrap()],
+        )
+        .map_err(|e| FileIoError::Database(e.to_string()))?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -207,6 +615,9 @@ impl FileIo for AndroidFsIo {
 
     async fn write(&self, path: &Path, contents: &[u8]) -> Result<(), FileIoError> {
         use std::io::Write;
+        if !self.exists(path)? {
+            self.create_document(path)?;
+        }
         let mut file = self.open_async(path, "w").await?;
         file.write_all(contents)?;
         Ok(())
@@ -222,6 +633,7 @@ impl FileIo for AndroidFsIo {
             .create_directory(name)
             .map_err(|e| FileIoError::Saf(e.to_string()))?;
         let new_path = parent.join(name);
+        self.stats.forget(&new_path);
 
         let conn = self.db.lock().unwrap();
         conn.execute(
@@ -258,40 +670,18 @@ impl FileIo for AndroidFsIo {
     }
 
     async fn read_dir(&self, path: &Path) -> Result<Vec<FsNode>, FileIoError> {
-        let conn = self.db.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT path FROM fs_cache WHERE parent = ?1")
-            .map_err(|e| FileIoError::Database(e.to_string()))?;
-        let mut rows = stmt
-            .query(params![path.to_str().unwrap()])
-            .map_err(|e| FileIoError::Database(e.to_string()))?;
-
-        let mut nodes = Vec::new();
-        while let Some(row) = rows
-            .next()
-            .map_err(|e| FileIoError::Database(e.to_string()))?
-        {
-            let path_str: String = row
-                .get(0)
-                .map_err(|e| FileIoError::Database(e.to_string()))?;
-            let path = PathBuf::from(path_str);
-            let file = self.get_android_file(&path)?;
-            nodes.push(FsNode {
-                filename: file.filename,
-                raw_path: path.to_str().unwrap_or_default().to_string(),
-                path,
-                is_dir: file.is_dir,
-                is_file: !file.is_dir,
-                size: file.size as u64,
-            });
-        }
-        Ok(nodes)
+        let children = list_children(&self.get_uri(path)?, path, &self.db, &self.stats)?;
+        Ok(children
+            .into_iter()
+            .map(|(path, stat)| stat.node(path))
+            .collect())
     }
 
     async fn remove_file(&self, path: &Path) -> Result<(), FileIoError> {
         let file = self.get_android_file(path)?;
         file.remove_file()
             .map_err(|e| FileIoError::Saf(e.to_string()))?;
+        self.stats.forget(path);
 
         let conn = self.db.lock().unwrap();
         conn.execute(
@@ -306,6 +696,7 @@ impl FileIo for AndroidFsIo {
         let file = self.get_android_file(path)?;
         file.remove_file()
             .map_err(|e| FileIoError::Saf(e.to_string()))?;
+        self.stats.forget(path);
 
         let conn = self.db.lock().unwrap();
         conn.execute(
@@ -316,49 +707,56 @@ impl FileIo for AndroidFsIo {
         Ok(())
     }
 
+    async fn trash(&self, path: &Path) -> Result<(), FileIoError> {
+        FallbackTrash::new(self, Path::new("")).put(path).await?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), FileIoError> {
+        self.move_document(from, to)
+    }
+
+    async fn move_file(&self, from: &Path, to: &Path) -> Result<(), FileIoError> {
+        self.move_document(from, to)
+    }
+
+    async fn copy_file(
+        &self,
+        from: &Path,
+        to: &Path,
+        progress: Option<ProgressFn>,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<u64, FileIoError> {
+        self.copy_document(from, to, progress.as_ref(), cancel_token.as_ref())
+    }
+
     fn walk_dir(&self, path: &Path, _follow_links: bool) -> Result<Vec<FsNode>, FileIoError> {
-        let conn = self.db.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT path FROM fs_cache WHERE path LIKE ?1")
-            .map_err(|e| FileIoError::Database(e.to_string()))?;
-        let mut rows = stmt
-            .query(params![format!("{}%", path.to_str().unwrap())])
-            .map_err(|e| FileIoError::Database(e.to_string()))?;
+        self.walk_entries(path)?.collect()
+    }
 
-        let mut nodes = Vec::new();
-        while let Some(row) = rows
-            .next()
-            .map_err(|e| FileIoError::Database(e.to_string()))?
-        {
-            let path_str: String = row
-                .get(0)
-                .map_err(|e| FileIoError::Database(e.to_string()))?;
-            let path = PathBuf::from(path_str);
-            let file = self.get_android_file(&path)?;
-            nodes.push(FsNode {
-                filename: file.filename,
-                raw_path: path.to_str().unwrap_or_default().to_string(),
-                path,
-                is_dir: file.is_dir,
-                is_file: !file.is_dir,
-                size: file.size as u64,
-            });
-        }
-        Ok(nodes)
+    fn walk_dir_stream(
+        &self,
+        path: &Path,
+        _follow_links: bool,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<FsNodeStream, FileIoError> {
+        Ok(stream_walk(self.walk_entries(path)?, cancel_token))
     }
 
     fn exists(&self, path: &Path) -> Result<bool, FileIoError> {
-        Ok(self.get_uri(path).is_ok())
+        Ok(self.stats.get(path).is_some() || self.get_uri(path).is_ok())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FsNode, FileIoError> {
+        Ok(self.stat(path)?.node(path.to_path_buf()))
     }
 
     async fn is_file(&self, path: &Path) -> Result<bool, FileIoError> {
-        let file = self.get_android_file(path)?;
-        Ok(!file.is_dir)
+        Ok(!self.stat(path)?.is_dir)
     }
 
     async fn is_dir(&self, path: &Path) -> Result<bool, FileIoError> {
-        let file = self.get_android_file(path)?;
-        Ok(file.is_dir)
+        Ok(self.stat(path)?.is_dir)
     }
 
     fn canonicalize_path(&self, path: &Path) -> Result<PathBuf, FileIoError> {
@@ -386,30 +784,16 @@ impl FileIo for AndroidFsIo {
     }
 
     fn canonicalize(&self, path: &Path) -> Result<FsNode, FileIoError> {
-        let file = self.get_android_file(path)?;
+        let stat = self.stat(path)?;
         let path = self.canonicalize_path(path)?;
-        Ok(FsNode {
-            filename: file.filename,
-            raw_path: path.to_str().unwrap_or_default().to_string(),
-            path,
-            is_dir: file.is_dir,
-            is_file: !file.is_dir,
-            size: file.size as u64,
-        })
+        Ok(stat.node(path))
     }
 
     fn canonicalize_str(&self, path: &str) -> Result<FsNode, FileIoError> {
         if path.contains(':') {
             let file = from_tree_url(path).map_err(|e| FileIoError::Saf(e.to_string()))?;
             let canon_path = self.canonicalize_path_str(path)?;
-            Ok(FsNode {
-                filename: file.filename,
-                raw_path: canon_path.to_str().unwrap_or_default().to_string(),
-                path: canon_path,
-                is_dir: file.is_dir,
-                is_file: !file.is_dir,
-                size: file.size as u64,
-            })
+            Ok(DocumentStat::from_file(&file).node(canon_path))
         } else {
             self.canonicalize(Path::new(path))
         }
@@ -431,4 +815,65 @@ impl FileIo for AndroidFsIo {
         }
         self.canonicalize(path)
     }
+
+    fn watch(&self, path: &Path, recursive: bool) -> Result<FileWatchHandle, FileIoError> {
+        let url = self.get_uri(path)?;
+        let path = path.to_path_buf();
+        let db = self.db.clone();
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let poller = tokio::spawn(async move {
+            let mut previous: Option<Snapshot> = None;
+            loop {
+                let url = url.clone();
+                let base = path.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    let file = from_tree_url(&url).map_err(|e| FileIoError::Saf(e.to_string()))?;
+                    let mut snapshot = Snapshot::new();
+                    take_snapshot(file, &base, recursive, &mut snapshot)?;
+                    Ok::<_, FileIoError>(snapshot)
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(current)) => {
+                        if let Some(previous) = &previous {
+                            if let Err(e) = diff_snapshots(previous, &current, &db, &sender) {
+                                log::warn!("Failed to update the cache of watched files: {}", e);
+                            }
+                        }
+                        previous = Some(current);
+                    }
+                    Ok(Err(e)) => log::warn!("Failed to list watched files: {}", e),
+                    Err(e) => log::error!("Watch polling task failed: {}", e),
+                }
+
+                if sender.is_closed() {
+                    break;
+                }
+                tokio::time::sleep(WATCH_POLL_INTERVAL).await;
+            }
+        });
+
+        Ok(FileWatchHandle::new(receiver, AbortOnDrop(poller)))
+    }
+
+    fn disk_space(&self, path: &Path) -> Result<DiskSpace, FileIoError> {
+        // Private storage of the app, outside of the tree
+        if path.is_absolute() {
+            let existing = path
+                .ancestors()
+                .find(|x| x.exists())
+                .ok_or_else(|| FileIoError::PathNotFound(path.to_string_lossy().to_string()))?;
+            return volume_space(existing).map_err(FileIoError::Io);
+        }
+
+        // SAF cannot query a volume, its space is read from the descriptor
+        // of a document stored on it
+        let std_file = self
+            .space_document(path)?
+            .open("r")
+            .map_err(|e| FileIoError::Saf(e.to_string()))?;
+        fd_space(std_file.as_raw_fd()).map_err(FileIoError::Io)
+    }
 }
@@ -0,0 +1,52 @@
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::FileIoError;
+
+/// Receives the number of bytes copied so far.
+pub type ProgressFn = Arc<dyn Fn(u64) + Send + Sync>;
+
+pub(crate) const COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+pub(crate) fn check_cancelled(cancel_token: Option<&CancellationToken>) -> Result<(), FileIoError> {
+    match cancel_token {
+        Some(token) if token.is_cancelled() => Err(FileIoError::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+/// Copies `reader` into `writer` one chunk at a time, reporting the
+/// progress and checking for cancellation after every chunk.
+pub(crate) fn copy_stream(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    progress: Option<&ProgressFn>,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<u64, FileIoError> {
+    let mut buffer = vec![0; COPY_CHUNK_SIZE];
+    let mut copied = 0;
+
+    loop {
+        check_cancelled(cancel_token)?;
+
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        writer.write_all(&buffer[..read])?;
+
+        copied += read as u64;
+        if let Some(progress) = progress {
+            progress(copied);
+        }
+    }
+
+    writer.flush()?;
+    Ok(copied)
+}
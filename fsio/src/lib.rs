@@ -2,11 +2,13 @@ use std::{
     io::{Read, Seek, Write},
     path::{Path, PathBuf},
     sync::Arc,
+    time::SystemTime,
 };
 
 use anyhow::Result;
 use async_trait::async_trait;
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Error, Debug)]
 pub enum FileIoError {
@@ -22,6 +24,16 @@ pub enum FileIoError {
     Database(String),
     #[error("Android SAF error: {0}")]
     Saf(String),
+    #[error("watch error: {0}")]
+    Watch(String),
+    #[error("operation cancelled")]
+    Cancelled,
+    #[error("not enough space for {path}: {available} bytes available, {required} required")]
+    InsufficientSpace {
+        path: String,
+        available: u64,
+        required: u64,
+    },
     #[error("operation not supported: {0}")]
     NotSupported(String),
     #[error("unknown error")]
@@ -30,12 +42,18 @@ pub enum FileIoError {
 
 #[derive(Debug, Clone)]
 pub struct FsNode {
+    /// Lossy when the name is not valid UTF-8, `path` keeps it as it is.
     pub filename: String,
     pub path: PathBuf,
     pub raw_path: String,
     pub is_dir: bool,
     pub is_file: bool,
     pub size: u64,
+    /// Timestamps are `None` when the backend or the platform cannot tell.
+    pub modified: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub readonly: bool,
 }
 
 pub trait FileStream: Read + Write + Seek + Send + Sync {}
@@ -59,8 +77,65 @@ pub trait FileIo: Send + Sync {
     async fn read_dir(&self, path: &Path) -> Result<Vec<FsNode>, FileIoError>;
     async fn remove_file(&self, path: &Path) -> Result<(), FileIoError>;
     async fn remove_dir_all(&self, path: &Path) -> Result<(), FileIoError>;
+    /// Moves `path` to the recycle bin of the platform, or to the
+    /// [`FallbackTrash`] at the root of the library on Android. Use this
+    /// instead of [`FileIo::remove_file`] unless permanent deletion was
+    /// explicitly asked for.
+    async fn trash(&self, path: &Path) -> Result<(), FileIoError>;
+    /// Renames `from` to `to`, replacing `to` if it is a file.
+    ///
+    /// - Std: atomic, fails with [`std::io::ErrorKind::CrossesDevices`] when
+    ///   both paths are not on the same file system.
+    /// - Android: not atomic, SAF documents are copied then removed, and only
+    ///   files can be renamed.
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), FileIoError>;
+    /// Moves `from` to `to`, replacing `to` if it is a file.
+    ///
+    /// - Std: atomic on the same file system. Across file systems the files
+    ///   are copied and synced to disk before the source is removed, a failed
+    ///   copy removes what was copied and leaves the source untouched.
+    /// - Android: same as [`FileIo::rename`].
+    async fn move_file(&self, from: &Path, to: &Path) -> Result<(), FileIoError>;
+    /// Copies the file `from` to `to` in chunks and returns the number of
+    /// bytes copied. `progress` receives the bytes copied so far, and a
+    /// cancelled copy removes the partial file and fails with
+    /// [`FileIoError::Cancelled`].
+    ///
+    /// - Std: clones the file when the file system supports it, otherwise
+    ///   copies in the kernel or through a buffer. The modification time is
+    ///   preserved.
+    /// - Android: copies through content streams, the modification time is
+    ///   not preserved.
+    async fn copy_file(
+        &self,
+        from: &Path,
+        to: &Path,
+        progress: Option<ProgressFn>,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<u64, FileIoError>;
+    /// Lists `path` and everything below it, skipping broken links and the
+    /// entries that cannot be read.
+    ///
+    /// When `follow_links` is set, links are followed unless they point
+    /// inside the walked tree, whose files are reported under their real
+    /// path. Files outside the tree are reported under the first link path
+    /// reaching them. A file or directory reached twice, through links or
+    /// hard links, is reported once, and directories are not walked again,
+    /// which also breaks link loops. Otherwise links are reported as they
+    /// are. SAF has no links, the Android backend ignores the flag.
     fn walk_dir(&self, path: &Path, follow_links: bool) -> Result<Vec<FsNode>, FileIoError>;
+    /// Walks `path` like [`FileIo::walk_dir`], yielding nodes as they are
+    /// found instead of collecting the whole tree. Must be called within a
+    /// Tokio runtime.
+    fn walk_dir_stream(
+        &self,
+        path: &Path,
+        follow_links: bool,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<FsNodeStream, FileIoError>;
     fn exists(&self, path: &Path) -> Result<bool, FileIoError>;
+    /// Reads the node of `path` without canonicalizing it.
+    fn metadata(&self, path: &Path) -> Result<FsNode, FileIoError>;
     async fn is_file(&self, path: &Path) -> Result<bool, FileIoError>;
     async fn is_dir(&self, path: &Path) -> Result<bool, FileIoError>;
     fn canonicalize_path(&self, path: &Path) -> Result<PathBuf, FileIoError>;
@@ -69,6 +144,32 @@ pub trait FileIo: Send + Sync {
     fn canonicalize_str(&self, path: &str) -> Result<FsNode, FileIoError>;
     async fn ensure_file(&self, path: &Path) -> Result<FsNode, FileIoError>;
     async fn ensure_directory(&self, path: &Path) -> Result<FsNode, FileIoError>;
+    /// Watches `path` for changes, must be called within a Tokio runtime.
+    fn watch(&self, path: &Path, recursive: bool) -> Result<FileWatchHandle, FileIoError>;
+    /// Space of the volume holding `path`, which does not need to exist
+    /// yet.
+    fn disk_space(&self, path: &Path) -> Result<DiskSpace, FileIoError>;
+    /// Fails with [`FileIoError::InsufficientSpace`] when writing `size`
+    /// bytes to `path` would leave less than [`free_space_floor`] on its
+    /// volume. Backends which cannot tell the free space let the write go.
+    fn ensure_space(&self, path: &Path, size: u64) -> Result<(), FileIoError> {
+        let space = match self.disk_space(path) {
+            Ok(space) => space,
+            Err(FileIoError::NotSupported(_)) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let required = size.saturating_add(free_space_floor());
+        if space.available < required {
+            return Err(FileIoError::InsufficientSpace {
+                path: path.to_string_lossy().to_string(),
+                available: space.available,
+                required,
+            });
+        }
+
+        Ok(())
+    }
 }
 
 pub struct FsIo {
@@ -133,3 +234,18 @@ use android_fs::AndroidFsIo;
 
 mod noop_fs;
 use noop_fs::NoOpFsIo;
+
+mod copy;
+pub use copy::ProgressFn;
+
+mod space;
+pub use space::{free_space_floor, set_free_space_floor, DiskSpace, DEFAULT_FREE_SPACE_FLOOR};
+
+mod trash;
+pub use trash::{FallbackTrash, TrashEntry, TRASH_DIR};
+
+mod walk;
+pub use walk::{FsNodeStream, WALK_BUFFER};
+
+mod watch;
+pub use watch::{FileWatchEvent, FileWatchHandle, WATCH_DEBOUNCE};
@@ -4,8 +4,12 @@ use std::{
 };
 
 use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
-use crate::{FileIo, FileIoError, FileStream, FsNode};
+use crate::{
+    DiskSpace, FileIo, FileIoError, FileStream, FileWatchHandle, FsNode, FsNodeStream, ProgressFn,
+};
 
 pub struct NoOpFsIo;
 
@@ -115,10 +119,41 @@ impl FileIo for NoOpFsIo {
         Ok(())
     }
 
+    async fn trash(&self, _path: &Path) -> Result<(), FileIoError> {
+        Ok(())
+    }
+
+    async fn rename(&self, _from: &Path, _to: &Path) -> Result<(), FileIoError> {
+        Ok(())
+    }
+
+    async fn move_file(&self, _from: &Path, _to: &Path) -> Result<(), FileIoError> {
+        Ok(())
+    }
+
+    async fn copy_file(
+        &self,
+        _from: &Path,
+        _to: &Path,
+        _progress: Option<ProgressFn>,
+        _cancel_token: Option<CancellationToken>,
+    ) -> Result<u64, FileIoError> {
+        Ok(0)
+    }
+
     fn walk_dir(&self, _path: &Path, _follow_links: bool) -> Result<Vec<FsNode>, FileIoError> {
         Ok(Vec::new())
     }
 
+    fn walk_dir_stream(
+        &self,
+        _path: &Path,
+        _follow_links: bool,
+        _cancel_token: Option<CancellationToken>,
+    ) -> Result<FsNodeStream, FileIoError> {
+        Ok(Box::pin(futures::stream::empty()))
+    }
+
     fn exists(&self, _path: &Path) -> Result<bool, FileIoError> {
         Ok(false)
     }
@@ -139,14 +174,22 @@ impl FileIo for NoOpFsIo {
         Ok(PathBuf::from(path))
     }
 
+    fn metadata(&self, path: &Path) -> Result<FsNode, FileIoError> {
+        self.canonicalize(path)
+    }
+
     fn canonicalize(&self, path: &Path) -> Result<FsNode, FileIoError> {
         Ok(FsNode {
-            filename: path.file_name().unwrap().to_str().unwrap().to_string(),
+            filename: path.file_name().unwrap().to_string_lossy().into_owned(),
             raw_path: path.to_str().unwrap_or_default().to_string(),
             path: path.to_path_buf(),
             is_dir: false,
             is_file: false,
             size: 0,
+            modified: None,
+            created: None,
+            accessed: None,
+            readonly: false,
         })
     }
 
@@ -163,6 +206,10 @@ impl FileIo for NoOpFsIo {
             is_dir: false,
             is_file: false,
             size: 0,
+            modified: None,
+            created: None,
+            accessed: None,
+            readonly: false,
         })
     }
 
@@ -173,4 +220,14 @@ impl FileIo for NoOpFsIo {
     async fn ensure_directory(&self, path: &Path) -> Result<FsNode, FileIoError> {
         self.canonicalize(path)
     }
+
+    fn watch(&self, _path: &Path, _recursive: bool) -> Result<FileWatchHandle, FileIoError> {
+        // Keep the sender alive so the stream stays open without events
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Ok(FileWatchHandle::new(receiver, sender))
+    }
+
+    fn disk_space(&self, _path: &Path) -> Result<DiskSpace, FileIoError> {
+        Err(FileIoError::NotSupported("disk space".to_string()))
+    }
 }
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Size of the volume holding a path, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub total: u64,
+    /// Space the current user can write to, which may be less than the free
+    /// space of the volume.
+    pub available: u64,
+}
+
+/// Space left free on a volume unless configured otherwise.
+pub const DEFAULT_FREE_SPACE_FLOOR: u64 = 256 * 1024 * 1024;
+
+static FREE_SPACE_FLOOR: AtomicU64 = AtomicU64::new(DEFAULT_FREE_SPACE_FLOOR);
+
+/// Changes the space caches and downloads must leave free on a volume.
+pub fn set_free_space_floor(bytes: u64) {
+    FREE_SPACE_FLOOR.store(bytes, Ordering::Relaxed);
+}
+
+pub fn free_space_floor() -> u64 {
+    FREE_SPACE_FLOOR.load(Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+pub(crate) fn volume_space(path: &std::path::Path) -> std::io::Result<DiskSpace> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL terminated and `stat` is only read on success
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+
+    Ok(space_from_statvfs(&stat))
+}
+
+#[cfg(target_os = "android")]
+pub(crate) fn fd_space(fd: std::os::unix::io::RawFd) -> std::io::Result<DiskSpace> {
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `stat` is only read on success
+    if unsafe { libc::fstatvfs(fd, stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+
+    Ok(space_from_statvfs(&stat))
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn space_from_statvfs(stat: &libc::statvfs) -> DiskSpace {
+    // The field types differ between platforms
+    let fragment = stat.f_frsize as u64;
+    DiskSpace {
+        total: stat.f_blocks as u64 * fragment,
+        available: stat.f_bavail as u64 * fragment,
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn volume_space(path: &std::path::Path) -> std::io::Result<DiskSpace> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::{core::PCWSTR, Win32::Storage::FileSystem::GetDiskFreeSpaceExW};
+
+    // The API takes a directory
+    let path = if path.is_file() {
+        path.parent().unwrap_or(path)
+    } else {
+        path
+    };
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0;
+    let mut total = 0;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR(wide.as_ptr()),
+            Some(&mut available),
+            Some(&mut total),
+            None,
+        )
+    }
+    .map_err(std::io::Error::other)?;
+
+    Ok(DiskSpace { total, available })
+}
@@ -1,10 +1,31 @@
-use std::path::{Path, PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fs::File,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
 
 use async_trait::async_trait;
-use tokio::fs;
+use log::debug;
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecursiveMode, Watcher,
+};
+use tokio::{
+    fs,
+    sync::mpsc::{self, UnboundedSender},
+};
+use tokio_util::sync::CancellationToken;
 use walkdir::WalkDir;
 
-use super::{FileIo, FileIoError, FileStream, FsNode};
+use super::{
+    copy::{check_cancelled, copy_stream},
+    space::volume_space,
+    walk::stream_walk,
+    DiskSpace, FileIo, FileIoError, FileStream, FileWatchEvent, FileWatchHandle, FsNode,
+    FsNodeStream, ProgressFn,
+};
 
 pub(crate) struct StdFsIo;
 
@@ -14,6 +35,416 @@ impl StdFsIo {
     }
 }
 
+fn node_from_metadata(filename: String, path: PathBuf, metadata: &std::fs::Metadata) -> FsNode {
+    FsNode {
+        filename,
+        raw_path: path.to_str().unwrap_or_default().to_string(),
+        path,
+        is_dir: metadata.is_dir(),
+        is_file: metadata.is_file(),
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        created: metadata.created().ok(),
+        accessed: metadata.accessed().ok(),
+        readonly: metadata.permissions().readonly(),
+    }
+}
+
+/// Length from which Windows needs the extended-length prefix, directories
+/// are limited to `MAX_PATH` minus room for an 8.3 file name.
+#[cfg(windows)]
+const LONG_PATH_THRESHOLD: usize = 248;
+
+/// Path to hand to the OS, long paths get the `\\?\` extended-length prefix
+/// on Windows so they are not cut at `MAX_PATH`. Verbatim paths skip all
+/// normalization, so the path is made absolute with backslashes first.
+#[cfg(windows)]
+fn io_path(path: &Path) -> Cow<'_, Path> {
+    let Some(raw) = path.to_str() else {
+        return Cow::Borrowed(path);
+    };
+    if raw.len() < LONG_PATH_THRESHOLD || raw.starts_with(r"\\?\") {
+        return Cow::Borrowed(path);
+    }
+
+    let Ok(absolute) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    let Some(absolute) = absolute.to_str() else {
+        return Cow::Borrowed(path);
+    };
+    if absolute.starts_with(r"\\?\") {
+        return Cow::Owned(PathBuf::from(absolute));
+    }
+    match absolute.strip_prefix(r"\\") {
+        Some(unc) => Cow::Owned(PathBuf::from(format!(r"\\?\UNC\{unc}"))),
+        None => Cow::Owned(PathBuf::from(format!(r"\\?\{absolute}"))),
+    }
+}
+
+#[cfg(not(windows))]
+fn io_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// Display form of a path, without the extended-length prefix, as reported
+/// in [`FsNode`] and stored in the database. Only drive and UNC paths are
+/// stripped, other verbatim paths have no plain form.
+#[cfg(windows)]
+fn display_path(path: PathBuf) -> PathBuf {
+    let Some(raw) = path.to_str() else {
+        return path;
+    };
+    if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{unc}"));
+    }
+    match raw.strip_prefix(r"\\?\") {
+        Some(rest) if rest.as_bytes().get(1) == Some(&b':') => PathBuf::from(rest),
+        _ => path,
+    }
+}
+
+#[cfg(not(windows))]
+fn display_path(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// Identity of a node, to notice when links lead to it twice.
+#[cfg(unix)]
+type NodeId = (u64, u64);
+#[cfg(not(unix))]
+type NodeId = PathBuf;
+
+#[cfg(unix)]
+fn node_id(_path: &Path, metadata: &std::fs::Metadata) -> Option<NodeId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn node_id(path: &Path, _metadata: &std::fs::Metadata) -> Option<NodeId> {
+    dunce::canonicalize(path).ok().map(display_path)
+}
+
+/// Walks a directory tree following the symlink policy of
+/// [`FileIo::walk_dir`].
+struct StdWalk {
+    inner: walkdir::IntoIter,
+    /// Canonical root of the walk, only set when links are followed.
+    root: Option<PathBuf>,
+    visited: HashSet<NodeId>,
+}
+
+impl StdWalk {
+    fn new(path: &Path, follow_links: bool) -> Self {
+        let path = io_path(path);
+        let root = follow_links.then(|| {
+            dunce::canonicalize(&path)
+                .map(display_path)
+                .unwrap_or(display_path(path.to_path_buf()))
+        });
+        Self {
+            inner: WalkDir::new(&path).follow_links(follow_links).into_iter(),
+            root,
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Whether `entry` should be skipped, along with its children.
+    fn skip(&mut self, entry: &walkdir::DirEntry) -> bool {
+        let Some(root) = &self.root else {
+            return false;
+        };
+
+        if entry.depth() > 0 && entry.path_is_symlink() {
+            match dunce::canonicalize(entry.path()).map(display_path) {
+                // The target is walked on its own, report it only once
+                Ok(target) if target.starts_with(root) => {
+                    debug!(
+                        "Skipping {}, it links to {} in the walked tree",
+                        entry.path().display(),
+                        target.display()
+                    );
+                    return true;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    debug!("Skipping broken link {}: {}", entry.path().display(), e);
+                    return true;
+                }
+            }
+        }
+
+        // Files too, two links may lead to the same file outside the tree
+        if entry.file_type().is_dir() || entry.file_type().is_file() {
+            let id = entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| node_id(entry.path(), &metadata));
+            if let Some(id) = id {
+                if !self.visited.insert(id) {
+                    debug!("Skipping {}, already walked", entry.path().display());
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+impl Iterator for StdWalk {
+    type Item = Result<FsNode, FileIoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.inner.next()? {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // Broken links, link loops and unreadable entries
+                    debug!("Skipping an entry of the walk: {}", e);
+                    continue;
+                }
+            };
+
+            if self.skip(&entry) {
+                if entry.file_type().is_dir() {
+                    self.inner.skip_current_dir();
+                }
+                continue;
+            }
+
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => return Some(Err(FileIoError::Io(e.into()))),
+            };
+            return Some(Ok(node_from_metadata(
+                entry.file_name().to_string_lossy().into_owned(),
+                display_path(entry.path().to_path_buf()),
+                &metadata,
+            )));
+        }
+    }
+}
+
+/// Maps the errors of path operations to the matching [`FileIoError`].
+fn map_io_error(e: io::Error, path: &Path) -> FileIoError {
+    let path = display_path(path.to_path_buf());
+    match e.kind() {
+        ErrorKind::NotFound => FileIoError::PathNotFound(path.to_string_lossy().to_string()),
+        ErrorKind::PermissionDenied => {
+            FileIoError::PermissionDenied(path.to_string_lossy().to_string())
+        }
+        _ => FileIoError::Io(e),
+    }
+}
+
+/// Moves `from` to `to` with `rename`, copying then removing the source
+/// when `rename` reports that both paths are on different file systems.
+fn move_path(
+    from: &Path,
+    to: &Path,
+    rename: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> Result<(), FileIoError> {
+    match rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_path(from, to) {
+                // Leave nothing half copied behind, the source is still intact
+                let _ = if from.is_dir() {
+                    std::fs::remove_dir_all(to)
+                } else {
+                    std::fs::remove_file(to)
+                };
+                return Err(map_io_error(e, from));
+            }
+
+            if from.is_dir() {
+                std::fs::remove_dir_all(from)
+            } else {
+                std::fs::remove_file(from)
+            }
+            .map_err(|e| map_io_error(e, from))
+        }
+        Err(e) => Err(map_io_error(e, from)),
+    }
+}
+
+/// Copies a file or a directory tree, syncing every file to disk.
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(from).unwrap();
+        let target = if relative.as_os_str().is_empty() {
+            to.to_path_buf()
+        } else {
+            to.join(relative)
+        };
+
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+            std::fs::File::open(&target)?.sync_all()?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies a single file, removing the partial copy if anything fails.
+fn copy_file_blocking(
+    from: &Path,
+    to: &Path,
+    progress: Option<&ProgressFn>,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<u64, FileIoError> {
+    check_cancelled(cancel_token)?;
+
+    let mut source = File::open(from).map_err(|e| map_io_error(e, from))?;
+    let metadata = source.metadata()?;
+    let mut target = File::create(to).map_err(|e| map_io_error(e, to))?;
+
+    let result = copy_contents(
+        &mut source,
+        &mut target,
+        metadata.len(),
+        progress,
+        cancel_token,
+    )
+    .and_then(|copied| {
+        target.sync_all()?;
+        if let Ok(modified) = metadata.modified() {
+            target.set_modified(modified)?;
+        }
+        Ok(copied)
+    });
+
+    if result.is_err() {
+        drop(target);
+        let _ = std::fs::remove_file(to);
+    }
+    result
+}
+
+/// Clones the file if the file system supports reflinks, otherwise copies
+/// it in the kernel with `copy_file_range`, falling back to a buffered copy
+/// when neither is available.
+#[cfg(target_os = "linux")]
+fn copy_contents(
+    source: &mut File,
+    target: &mut File,
+    len: u64,
+    progress: Option<&ProgressFn>,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<u64, FileIoError> {
+    use std::os::fd::AsRawFd;
+
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+
+    // SAFETY: both descriptors stay open for the duration of the call
+    if unsafe { libc::ioctl(target.as_raw_fd(), FICLONE as _, source.as_raw_fd()) } == 0 {
+        if let Some(progress) = progress {
+            progress(len);
+        }
+        return Ok(len);
+    }
+
+    let mut copied = 0;
+    loop {
+        check_cancelled(cancel_token)?;
+
+        // SAFETY: both descriptors stay open for the duration of the call,
+        // null offsets use and advance the file positions
+        let result = unsafe {
+            libc::copy_file_range(
+                source.as_raw_fd(),
+                std::ptr::null_mut(),
+                target.as_raw_fd(),
+                std::ptr::null_mut(),
+                crate::copy::COPY_CHUNK_SIZE,
+                0,
+            )
+        };
+
+        if result < 0 {
+            let e = io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                // Unsupported for these files, nothing was written yet
+                Some(
+                    libc::ENOSYS | libc::EXDEV | libc::EINVAL | libc::EOPNOTSUPP | libc::EPERM,
+                ) if copied == 0 => {
+                    return copy_stream(source, target, progress, cancel_token);
+                }
+                _ => return Err(e.into()),
+            }
+        }
+
+        if result == 0 {
+            break;
+        }
+
+        copied += result as u64;
+        if let Some(progress) = progress {
+            progress(copied);
+        }
+    }
+
+    Ok(copied)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_contents(
+    source: &mut File,
+    target: &mut File,
+    _len: u64,
+    progress: Option<&ProgressFn>,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<u64, FileIoError> {
+    copy_stream(source, target, progress, cancel_token)
+}
+
+/// Translates a notify event into the events of the watch API, access and
+/// unclassified events are dropped.
+fn forward_event(event: Event, sender: &UnboundedSender<FileWatchEvent>) {
+    let mut paths = event.paths.into_iter().map(display_path);
+    let events = match event.kind {
+        EventKind::Create(_) => paths.map(FileWatchEvent::Created).collect(),
+        EventKind::Remove(_) => paths.map(FileWatchEvent::Removed).collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            match (paths.next(), paths.next()) {
+                (Some(from), Some(to)) => vec![FileWatchEvent::Renamed { from, to }],
+                _ => Vec::new(),
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            paths.map(FileWatchEvent::Removed).collect()
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            paths.map(FileWatchEvent::Created).collect()
+        }
+        // Platforms that cannot tell both ends of a rename apart
+        EventKind::Modify(ModifyKind::Name(_)) => paths
+            .map(|path| {
+                if path.exists() {
+                    FileWatchEvent::Created(path)
+                } else {
+                    FileWatchEvent::Removed(path)
+                }
+            })
+            .collect(),
+        EventKind::Modify(_) => paths.map(FileWatchEvent::Modified).collect(),
+        _ => Vec::new(),
+    };
+
+    for event in events {
+        // The handle is gone, the watcher is about to be dropped
+        if sender.send(event).is_err() {
+            return;
+        }
+    }
+}
+
 #[async_trait]
 impl FileIo for StdFsIo {
     fn name(&self) -> &'static str {
@@ -31,7 +462,7 @@ impl FileIo for StdFsIo {
             options.create(true);
         }
 
-        let file = options.open(path)?;
+        let file = options.open(io_path(path))?;
         Ok(Box::new(file))
     }
 
@@ -50,129 +481,175 @@ impl FileIo for StdFsIo {
             options.create(true);
         }
 
-        let file = options.open(path).await?;
+        let file = options.open(io_path(path)).await?;
         Ok(Box::new(file.into_std().await))
     }
 
     fn read(&self, path: &Path) -> Result<Vec<u8>, FileIoError> {
-        std::fs::read(path).map_err(FileIoError::Io)
+        std::fs::read(io_path(path)).map_err(FileIoError::Io)
     }
 
     fn read_to_string(&self, path: &Path) -> Result<String, FileIoError> {
-        std::fs::read_to_string(path).map_err(FileIoError::Io)
+        std::fs::read_to_string(io_path(path)).map_err(FileIoError::Io)
     }
 
     async fn write(&self, path: &Path, contents: &[u8]) -> Result<(), FileIoError> {
-        fs::write(path, contents).await.map_err(FileIoError::Io)
+        fs::write(io_path(path), contents)
+            .await
+            .map_err(FileIoError::Io)
     }
 
     async fn write_string(&self, path: &Path, contents: &str) -> Result<(), FileIoError> {
-        fs::write(path, contents).await.map_err(FileIoError::Io)
+        fs::write(io_path(path), contents)
+            .await
+            .map_err(FileIoError::Io)
     }
 
     async fn create_dir(&self, parent: &Path, name: &str) -> Result<PathBuf, FileIoError> {
         let new_path = parent.join(name);
-        fs::create_dir(&new_path).await?;
+        fs::create_dir(io_path(&new_path)).await?;
         Ok(new_path)
     }
 
     fn create_dir_all(&self, path: &Path) -> Result<(), FileIoError> {
-        std::fs::create_dir_all(path).map_err(FileIoError::Io)
+        std::fs::create_dir_all(io_path(path)).map_err(FileIoError::Io)
     }
 
     async fn read_dir(&self, path: &Path) -> Result<Vec<FsNode>, FileIoError> {
-        let mut entries = fs::read_dir(path).await?;
+        let mut entries = fs::read_dir(io_path(path)).await?;
         let mut nodes = Vec::new();
         while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
+            let path = display_path(entry.path());
             let metadata = entry.metadata().await?;
-            nodes.push(FsNode {
-                filename: entry.file_name().to_str().unwrap().to_string(),
-                raw_path: path.to_str().unwrap_or_default().to_string(),
+            nodes.push(node_from_metadata(
+                entry.file_name().to_string_lossy().into_owned(),
                 path,
-                is_dir: metadata.is_dir(),
-                is_file: metadata.is_file(),
-                size: metadata.len(),
-            });
+                &metadata,
+            ));
         }
         Ok(nodes)
     }
 
     async fn remove_file(&self, path: &Path) -> Result<(), FileIoError> {
-        fs::remove_file(path).await.map_err(FileIoError::Io)
+        fs::remove_file(io_path(path))
+            .await
+            .map_err(FileIoError::Io)
     }
 
     async fn remove_dir_all(&self, path: &Path) -> Result<(), FileIoError> {
-        fs::remove_dir_all(path).await.map_err(FileIoError::Io)
+        fs::remove_dir_all(io_path(path))
+            .await
+            .map_err(FileIoError::Io)
+    }
+
+    async fn trash(&self, path: &Path) -> Result<(), FileIoError> {
+        if !io_path(path).exists() {
+            return Err(FileIoError::PathNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+
+        let path = io_path(path).into_owned();
+        tokio::task::spawn_blocking(move || trash::delete(&path))
+            .await
+            .map_err(|e| FileIoError::Io(io::Error::other(e)))?
+            .map_err(|e| FileIoError::Io(io::Error::other(e)))
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<(), FileIoError> {
+        fs::rename(io_path(from), io_path(to))
+            .await
+            .map_err(|e| map_io_error(e, from))
+    }
+
+    async fn move_file(&self, from: &Path, to: &Path) -> Result<(), FileIoError> {
+        let from = io_path(from).into_owned();
+        let to = io_path(to).into_owned();
+        tokio::task::spawn_blocking(move || move_path(&from, &to, |a, b| std::fs::rename(a, b)))
+            .await
+            .map_err(|e| FileIoError::Io(io::Error::other(e)))?
+    }
+
+    async fn copy_file(
+        &self,
+        from: &Path,
+        to: &Path,
+        progress: Option<ProgressFn>,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<u64, FileIoError> {
+        let from = io_path(from).into_owned();
+        let to = io_path(to).into_owned();
+        tokio::task::spawn_blocking(move || {
+            copy_file_blocking(&from, &to, progress.as_ref(), cancel_token.as_ref())
+        })
+        .await
+        .map_err(|e| FileIoError::Io(io::Error::other(e)))?
     }
 
     fn walk_dir(&self, path: &Path, follow_links: bool) -> Result<Vec<FsNode>, FileIoError> {
-        let path = path.to_path_buf();
-        WalkDir::new(path)
-            .follow_links(follow_links)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .map(|entry| {
-                let path = entry.path().to_path_buf();
-                let metadata = entry.metadata().map_err(|e| FileIoError::Io(e.into()))?;
-                Ok(FsNode {
-                    filename: entry.file_name().to_str().unwrap().to_string(),
-                    raw_path: path.to_str().unwrap_or_default().to_string(),
-                    path,
-                    is_dir: metadata.is_dir(),
-                    is_file: metadata.is_file(),
-                    size: metadata.len(),
-                })
-            })
-            .collect::<Result<Vec<_>, _>>()
+        StdWalk::new(path, follow_links).collect()
+    }
+
+    fn walk_dir_stream(
+        &self,
+        path: &Path,
+        follow_links: bool,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<FsNodeStream, FileIoError> {
+        Ok(stream_walk(StdWalk::new(path, follow_links), cancel_token))
     }
 
     fn exists(&self, path: &Path) -> Result<bool, FileIoError> {
-        Ok(std::fs::exists(path)?)
+        Ok(std::fs::exists(io_path(path))?)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FsNode, FileIoError> {
+        let metadata = std::fs::metadata(io_path(path)).map_err(|e| map_io_error(e, path))?;
+        let filename = path
+            .file_name()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(node_from_metadata(filename, path.to_path_buf(), &metadata))
     }
 
     async fn is_file(&self, path: &Path) -> Result<bool, FileIoError> {
-        let metadata = fs::metadata(path).await?;
+        let metadata = fs::metadata(io_path(path)).await?;
         Ok(metadata.is_file())
     }
 
     async fn is_dir(&self, path: &Path) -> Result<bool, FileIoError> {
-        let metadata = fs::metadata(path).await?;
+        let metadata = fs::metadata(io_path(path)).await?;
         Ok(metadata.is_dir())
     }
 
     fn canonicalize_path(&self, path: &Path) -> Result<PathBuf, FileIoError> {
-        dunce::canonicalize(path).map_err(FileIoError::Io)
+        dunce::canonicalize(io_path(path))
+            .map(display_path)
+            .map_err(FileIoError::Io)
     }
 
     fn canonicalize_path_str(&self, path: &str) -> Result<PathBuf, FileIoError> {
-        dunce::canonicalize(path).map_err(FileIoError::Io)
+        self.canonicalize_path(Path::new(path))
     }
 
     fn canonicalize(&self, path: &Path) -> Result<FsNode, FileIoError> {
         let path = self.canonicalize_path(path)?;
-        let metadata = std::fs::metadata(&path)?;
-        Ok(FsNode {
-            filename: path.file_name().unwrap().to_str().unwrap().to_string(),
-            raw_path: path.to_str().unwrap_or_default().to_string(),
+        let metadata = std::fs::metadata(io_path(&path))?;
+        Ok(node_from_metadata(
+            path.file_name().unwrap().to_string_lossy().into_owned(),
             path,
-            is_dir: metadata.is_dir(),
-            is_file: metadata.is_file(),
-            size: metadata.len(),
-        })
+            &metadata,
+        ))
     }
 
     fn canonicalize_str(&self, path: &str) -> Result<FsNode, FileIoError> {
         let path = self.canonicalize_path_str(path)?;
-        let metadata = std::fs::metadata(&path)?;
-        Ok(FsNode {
-            filename: path.file_name().unwrap().to_str().unwrap().to_string(),
-            raw_path: path.to_str().unwrap_or_default().to_string(),
+        let metadata = std::fs::metadata(io_path(&path))?;
+        Ok(node_from_metadata(
+            path.file_name().unwrap().to_string_lossy().into_owned(),
             path,
-            is_dir: metadata.is_dir(),
-            is_file: metadata.is_file(),
-            size: metadata.len(),
-        })
+            &metadata,
+        ))
     }
 
     async fn ensure_file(&self, path: &Path) -> Result<FsNode, FileIoError> {
@@ -191,4 +668,405 @@ impl FileIo for StdFsIo {
         }
         self.canonicalize(path)
     }
+
+    fn watch(&self, path: &Path, recursive: bool) -> Result<FileWatchHandle, FileIoError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+                Ok(event) => forward_event(event, &sender),
+                Err(e) => log::warn!("File watcher error: {}", e),
+            })
+            .map_err(|e| FileIoError::Watch(e.to_string()))?;
+
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(&io_path(path), mode)
+            .map_err(|e| FileIoError::Watch(e.to_string()))?;
+
+        Ok(FileWatchHandle::new(receiver, watcher))
+    }
+
+    fn disk_space(&self, path: &Path) -> Result<DiskSpace, FileIoError> {
+        // Files and directories about to be created are on the volume of
+        // their closest existing ancestor
+        let existing = path
+            .ancestors()
+            .map(|x| {
+                if x.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    x
+                }
+            })
+            .find(|x| io_path(x).exists())
+            .ok_or_else(|| FileIoError::PathNotFound(path.to_string_lossy().to_string()))?;
+
+        volume_space(&io_path(existing)).map_err(|e| map_io_error(e, path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn cross_device(_: &Path, _: &Path) -> io::Result<()> {
+        Err(io::Error::from(ErrorKind::CrossesDevices))
+    }
+
+    #[tokio::test]
+    async fn rename_replaces_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from.txt");
+        let to = dir.path().join("to.txt");
+        std::fs::write(&from, "new").unwrap();
+        std::fs::write(&to, "old").unwrap();
+
+        StdFsIo::new().rename(&from, &to).await.unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+    }
+
+    #[tokio::test]
+    async fn rename_missing_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = StdFsIo::new()
+            .rename(&dir.path().join("missing"), &dir.path().join("to"))
+            .await;
+
+        assert!(matches!(result, Err(FileIoError::PathNotFound(_))));
+    }
+
+    #[test]
+    fn move_file_across_file_systems() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("track.flac");
+        let to = dir.path().join("moved.flac");
+        std::fs::write(&from, b"audio").unwrap();
+
+        move_path(&from, &to, cross_device).unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(std::fs::read(&to).unwrap(), b"audio");
+    }
+
+    #[test]
+    fn move_directory_across_file_systems() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("album");
+        let to = dir.path().join("moved");
+        std::fs::create_dir_all(from.join("disc 1")).unwrap();
+        std::fs::write(from.join("cover.jpg"), b"cover").unwrap();
+        std::fs::write(from.join("disc 1/01.flac"), b"audio").unwrap();
+
+        move_path(&from, &to, cross_device).unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(std::fs::read(to.join("cover.jpg")).unwrap(), b"cover");
+        assert_eq!(std::fs::read(to.join("disc 1/01.flac")).unwrap(), b"audio");
+    }
+
+    #[tokio::test]
+    async fn copy_file_reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("track.flac");
+        let to = dir.path().join("copy.flac");
+        let contents = vec![7u8; crate::copy::COPY_CHUNK_SIZE * 2 + 1];
+        std::fs::write(&from, &contents).unwrap();
+
+        let reported = Arc::new(AtomicU64::new(0));
+        let progress: ProgressFn = {
+            let reported = reported.clone();
+            Arc::new(move |copied| reported.store(copied, Ordering::SeqCst))
+        };
+
+        let copied = StdFsIo::new()
+            .copy_file(&from, &to, Some(progress), None)
+            .await
+            .unwrap();
+
+        assert_eq!(copied, contents.len() as u64);
+        assert_eq!(reported.load(Ordering::SeqCst), copied);
+        assert_eq!(std::fs::read(&to).unwrap(), contents);
+        assert_eq!(
+            std::fs::metadata(&to).unwrap().modified().unwrap(),
+            std::fs::metadata(&from).unwrap().modified().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_copy_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("track.flac");
+        let to = dir.path().join("copy.flac");
+        std::fs::write(&from, b"audio").unwrap();
+
+        let cancel_token = CancellationToken::new();
+        cancel_token.cancel();
+
+        let result = StdFsIo::new()
+            .copy_file(&from, &to, None, Some(cancel_token))
+            .await;
+
+        assert!(matches!(result, Err(FileIoError::Cancelled)));
+        assert!(!to.exists());
+    }
+
+    #[tokio::test]
+    async fn walk_dir_stream_matches_walk_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("album")).unwrap();
+        std::fs::write(dir.path().join("album/01.flac"), b"audio").unwrap();
+        std::fs::write(dir.path().join("album/02.flac"), b"audio").unwrap();
+
+        let fsio = StdFsIo::new();
+        let mut expected: Vec<_> = fsio
+            .walk_dir(dir.path(), false)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.path)
+            .collect();
+        let mut streamed: Vec<_> = fsio
+            .walk_dir_stream(dir.path(), false, None)
+            .unwrap()
+            .map(|x| x.unwrap().path)
+            .collect()
+            .await;
+
+        expected.sort();
+        streamed.sort();
+        assert_eq!(streamed, expected);
+    }
+
+    #[tokio::test]
+    async fn cancelled_walk_ends_the_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("01.flac"), b"audio").unwrap();
+
+        let cancel_token = CancellationToken::new();
+        cancel_token.cancel();
+        let nodes: Vec<_> = StdFsIo::new()
+            .walk_dir_stream(dir.path(), false, Some(cancel_token))
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(nodes.len(), 1);
+        assert!(matches!(nodes[0], Err(FileIoError::Cancelled)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_dir_follows_links_once() {
+        use std::os::unix::fs::symlink;
+
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("shared.flac"), b"audio").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dunce::canonicalize(dir.path()).unwrap();
+        std::fs::create_dir_all(root.join("album")).unwrap();
+        std::fs::write(root.join("album/01.flac"), b"audio").unwrap();
+        // A loop back to the root, a second way into the album, a link
+        // leaving the tree and a broken link
+        symlink(&root, root.join("album/loop")).unwrap();
+        symlink(root.join("album"), root.join("album link")).unwrap();
+        symlink(root.join("album/01.flac"), root.join("01 link.flac")).unwrap();
+        symlink(outside.path(), root.join("shared")).unwrap();
+        symlink(root.join("missing"), root.join("broken")).unwrap();
+        // Two more ways to the file outside the tree
+        let shared = outside.path().join("shared.flac");
+        symlink(&shared, root.join("shared link.flac")).unwrap();
+        symlink(&shared, root.join("album/shared.flac")).unwrap();
+
+        let mut files: Vec<_> = StdFsIo::new()
+            .walk_dir(&root, true)
+            .unwrap()
+            .into_iter()
+            .filter(|x| x.is_file)
+            .map(|x| x.path.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        files.sort();
+
+        // The file outside the tree is reported once, under whichever link
+        // the walk reaches first
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0], PathBuf::from("album/01.flac"));
+        assert!([
+            PathBuf::from("album/shared.flac"),
+            PathBuf::from("shared link.flac"),
+            PathBuf::from("shared/shared.flac"),
+        ]
+        .contains(&files[1]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_dir_reports_links_without_following() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("album")).unwrap();
+        std::fs::write(dir.path().join("album/01.flac"), b"audio").unwrap();
+        symlink(dir.path(), dir.path().join("album/loop")).unwrap();
+
+        let nodes = StdFsIo::new().walk_dir(dir.path(), false).unwrap();
+
+        assert_eq!(nodes.len(), 4);
+        assert!(nodes
+            .iter()
+            .any(|x| x.path.ends_with("album/loop") && !x.is_dir && !x.is_file));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_dir_keeps_names_that_are_not_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let name = OsStr::from_bytes(b"caf\xe9.flac");
+        std::fs::write(dir.path().join(name), b"audio").unwrap();
+
+        let nodes = StdFsIo::new().walk_dir(dir.path(), false).unwrap();
+        let node = nodes.iter().find(|x| x.is_file).unwrap();
+
+        assert_eq!(node.filename, "caf\u{fffd}.flac");
+        assert_eq!(node.path, dir.path().join(name));
+    }
+
+    #[test]
+    fn metadata_reads_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("track.flac");
+        std::fs::write(&path, b"audio").unwrap();
+
+        let node = StdFsIo::new().metadata(&path).unwrap();
+
+        assert_eq!(node.filename, "track.flac");
+        assert_eq!(node.size, 5);
+        assert_eq!(
+            node.modified,
+            std::fs::metadata(&path).unwrap().modified().ok()
+        );
+        assert!(!node.readonly);
+    }
+
+    #[test]
+    fn failed_move_keeps_the_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("track.flac");
+        let to = dir.path().join("missing/moved.flac");
+        std::fs::write(&from, b"audio").unwrap();
+
+        assert!(move_path(&from, &to, cross_device).is_err());
+        assert_eq!(std::fs::read(&from).unwrap(), b"audio");
+        assert!(!to.exists());
+    }
+
+    /// A directory below `root` whose path is over 300 characters long.
+    #[cfg(windows)]
+    fn long_dir(root: &Path) -> PathBuf {
+        let segment = "a".repeat(60);
+        let path = (0..5).fold(root.to_path_buf(), |path, _| path.join(&segment));
+        assert!(path.as_os_str().len() > 300);
+        path
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_keep_their_display_form() {
+        let path = PathBuf::from(r"C:\Music").join("a".repeat(300));
+        let extended = io_path(&path);
+
+        assert!(extended.to_str().unwrap().starts_with(r"\\?\C:\Music"));
+        assert_eq!(display_path(extended.into_owned()), path);
+        assert_eq!(
+            display_path(PathBuf::from(r"\\?\UNC\server\share\a")),
+            PathBuf::from(r"\\server\share\a")
+        );
+        assert_eq!(io_path(Path::new(r"C:\Music")), Path::new(r"C:\Music"));
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn long_paths_round_trip_from_scan_to_playback() {
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fsio = StdFsIo::new();
+        let root = fsio.canonicalize_path(dir.path()).unwrap();
+        let album = long_dir(&root);
+        fsio.create_dir_all(&album).unwrap();
+        fsio.write(&album.join("01.flac"), b"audio").await.unwrap();
+
+        // Scanning reports the file in display form
+        let nodes = fsio.walk_dir(&root, false).unwrap();
+        let node = nodes.iter().find(|x| x.filename == "01.flac").unwrap();
+        assert!(!node.raw_path.starts_with(r"\\?\"));
+        assert_eq!(node.path, album.join("01.flac"));
+
+        // The library stores the directory relative to the root with
+        // forward slashes, playback joins it back
+        let directory = node
+            .path
+            .strip_prefix(&root)
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .replace('\\', "/");
+        let resolved = fsio
+            .canonicalize_path(&root.join(directory).join("01.flac"))
+            .unwrap();
+        assert_eq!(resolved, node.path);
+
+        let mut contents = Vec::new();
+        fsio.open(&resolved, "r")
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"audio");
+        assert_eq!(fsio.metadata(&resolved).unwrap().size, 5);
+    }
+
+    #[test]
+    fn disk_space_of_missing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let fsio = StdFsIo::new();
+
+        let space = fsio.disk_space(dir.path()).unwrap();
+        assert!(space.total > 0);
+        assert!(space.available <= space.total);
+        assert_eq!(
+            fsio.disk_space(&dir.path().join("missing/cover.jpg"))
+                .unwrap()
+                .total,
+            space.total
+        );
+    }
+
+    #[test]
+    fn ensure_space_refuses_writes_past_the_floor() {
+        let dir = tempfile::tempdir().unwrap();
+        let fsio = StdFsIo::new();
+        let available = fsio.disk_space(dir.path()).unwrap().available;
+
+        assert!(matches!(
+            fsio.ensure_space(dir.path(), available),
+            Err(FileIoError::InsufficientSpace { .. })
+        ));
+    }
 }
@@ -0,0 +1,240 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FileIo, FileIoError};
+
+/// Name of the holding folder, created at the root of the library.
+pub const TRASH_DIR: &str = ".rune-trash";
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub original_path: PathBuf,
+    /// Name of the file inside the holding folder
+    pub stored_name: String,
+    /// Trash time (UNIX timestamp)
+    pub trashed_at: u64,
+}
+
+fn manifest_error(e: serde_json::Error) -> FileIoError {
+    FileIoError::Io(std::io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Holding folder standing in for the recycle bin on backends without one.
+///
+/// Trashed files are moved into the folder and recorded in a manifest with
+/// their original path, so they can be restored or purged later.
+pub struct FallbackTrash<'a> {
+    fsio: &'a dyn FileIo,
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl<'a> FallbackTrash<'a> {
+    pub fn new(fsio: &'a dyn FileIo, root: &Path) -> Self {
+        Self {
+            fsio,
+            root: root.to_path_buf(),
+            dir: root.join(TRASH_DIR),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.dir.join(MANIFEST_FILE)
+    }
+
+    pub async fn list(&self) -> Result<Vec<TrashEntry>, FileIoError> {
+        let manifest_path = self.manifest_path();
+        if !self.fsio.exists(&manifest_path)? {
+            return Ok(Vec::new());
+        }
+
+        let content = self.fsio.read_to_string(&manifest_path)?;
+        serde_json::from_str(&content).map_err(manifest_error)
+    }
+
+    async fn save(&self, entries: &[TrashEntry]) -> Result<(), FileIoError> {
+        let content = serde_json::to_string_pretty(entries).map_err(manifest_error)?;
+        self.fsio
+            .write_string(&self.manifest_path(), &content)
+            .await
+    }
+
+    /// Moves `path` into the holding folder.
+    pub async fn put(&self, path: &Path) -> Result<TrashEntry, FileIoError> {
+        if !self.fsio.exists(path)? {
+            return Err(FileIoError::PathNotFound(
+                path.to_string_lossy().to_string(),
+            ));
+        }
+        let file_name = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .ok_or(FileIoError::InvalidPath)?;
+
+        if !self.fsio.exists(&self.dir)? {
+            self.fsio.create_dir(&self.root, TRASH_DIR).await?;
+        }
+
+        let mut entries = self.list().await?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut stamp = now.as_nanos();
+        while entries.iter().any(|x| x.id == format!("{stamp:x}")) {
+            stamp += 1;
+        }
+        let id = format!("{stamp:x}");
+
+        let entry = TrashEntry {
+            stored_name: format!("{id}-{file_name}"),
+            id,
+            original_path: path.to_path_buf(),
+            trashed_at: now.as_secs(),
+        };
+
+        let stored_path = self.dir.join(&entry.stored_name);
+        self.fsio.move_file(path, &stored_path).await?;
+
+        entries.push(entry.clone());
+        if let Err(e) = self.save(&entries).await {
+            // Unrecorded files could never be restored, put it back
+            let _ = self.fsio.move_file(&stored_path, path).await;
+            return Err(e);
+        }
+
+        Ok(entry)
+    }
+
+    /// Moves a trashed file back to its original path, which must be free.
+    pub async fn restore(&self, id: &str) -> Result<PathBuf, FileIoError> {
+        let mut entries = self.list().await?;
+        let index = entries
+            .iter()
+            .position(|x| x.id == id)
+            .ok_or_else(|| FileIoError::PathNotFound(format!("trash entry {id}")))?;
+        let entry = &entries[index];
+
+        if self.fsio.exists(&entry.original_path)? {
+            return Err(FileIoError::Io(std::io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists", entry.original_path.display()),
+            )));
+        }
+
+        if let Some(parent) = entry.original_path.parent() {
+            self.fsio.ensure_directory(parent).await?;
+        }
+        self.fsio
+            .move_file(&self.dir.join(&entry.stored_name), &entry.original_path)
+            .await?;
+
+        let entry = entries.remove(index);
+        self.save(&entries).await?;
+
+        Ok(entry.original_path)
+    }
+
+    /// Deletes a trashed file permanently.
+    pub async fn purge(&self, id: &str) -> Result<(), FileIoError> {
+        let mut entries = self.list().await?;
+        let index = entries
+            .iter()
+            .position(|x| x.id == id)
+            .ok_or_else(|| FileIoError::PathNotFound(format!("trash entry {id}")))?;
+
+        let entry = entries.remove(index);
+        let stored_path = self.dir.join(&entry.stored_name);
+        if self.fsio.exists(&stored_path)? {
+            self.fsio.remove_file(&stored_path).await?;
+        }
+
+        self.save(&entries).await
+    }
+
+    /// Deletes every trashed file permanently, returning how many were
+    /// purged.
+    pub async fn purge_all(&self) -> Result<usize, FileIoError> {
+        let entries = self.list().await?;
+        for entry in &entries {
+            let stored_path = self.dir.join(&entry.stored_name);
+            if self.fsio.exists(&stored_path)? {
+                self.fsio.remove_file(&stored_path).await?;
+            }
+        }
+
+        self.save(&[]).await?;
+        Ok(entries.len())
+    }
+}
+
+#[cfg(all(test, not(target_os = "android")))]
+mod tests {
+    use super::*;
+    use crate::FsIo;
+
+    #[tokio::test]
+    async fn manifest_round_trips_original_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("Artist/Album (Deluxe)/01 - Ünïcode.flac");
+        std::fs::create_dir_all(original.parent().unwrap()).unwrap();
+        std::fs::write(&original, b"audio").unwrap();
+
+        let fsio = FsIo::new();
+        let trash = FallbackTrash::new(&*fsio, dir.path());
+
+        let entry = trash.put(&original).await.unwrap();
+        assert!(!original.exists());
+        assert_eq!(trash.list().await.unwrap(), vec![entry.clone()]);
+
+        // A fresh instance only has the manifest to go by
+        let trash = FallbackTrash::new(&*fsio, dir.path());
+        assert_eq!(trash.restore(&entry.id).await.unwrap(), original);
+        assert_eq!(std::fs::read(&original).unwrap(), b"audio");
+        assert!(trash.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn purge_removes_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("01.flac");
+        std::fs::write(&original, b"audio").unwrap();
+
+        let fsio = FsIo::new();
+        let trash = FallbackTrash::new(&*fsio, dir.path());
+        let entry = trash.put(&original).await.unwrap();
+
+        trash.purge(&entry.id).await.unwrap();
+
+        assert!(!trash.dir().join(&entry.stored_name).exists());
+        assert!(trash.list().await.unwrap().is_empty());
+        assert!(trash.restore(&entry.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn restore_keeps_newer_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("01.flac");
+        std::fs::write(&original, b"old").unwrap();
+
+        let fsio = FsIo::new();
+        let trash = FallbackTrash::new(&*fsio, dir.path());
+        let entry = trash.put(&original).await.unwrap();
+        std::fs::write(&original, b"new").unwrap();
+
+        assert!(trash.restore(&entry.id).await.is_err());
+        assert_eq!(std::fs::read(&original).unwrap(), b"new");
+        assert_eq!(trash.list().await.unwrap().len(), 1);
+    }
+}
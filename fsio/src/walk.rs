@@ -0,0 +1,42 @@
+use std::pin::Pin;
+
+use futures::{stream, Stream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::{FileIoError, FsNode};
+
+/// Nodes buffered ahead of the consumer of a walk.
+pub const WALK_BUFFER: usize = 256;
+
+pub type FsNodeStream = Pin<Box<dyn Stream<Item = Result<FsNode, FileIoError>> + Send>>;
+
+/// Drives a blocking walk on a dedicated thread, buffering at most
+/// [`WALK_BUFFER`] nodes. The walk stops when the stream is dropped or the
+/// token is cancelled, in which case the stream ends with
+/// [`FileIoError::Cancelled`].
+///
+/// Must be called within a Tokio runtime.
+pub(crate) fn stream_walk<I>(walk: I, cancel_token: Option<CancellationToken>) -> FsNodeStream
+where
+    I: Iterator<Item = Result<FsNode, FileIoError>> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(WALK_BUFFER);
+
+    tokio::task::spawn_blocking(move || {
+        for node in walk {
+            if cancel_token.as_ref().is_some_and(|x| x.is_cancelled()) {
+                let _ = sender.blocking_send(Err(FileIoError::Cancelled));
+                return;
+            }
+
+            if sender.blocking_send(node).is_err() {
+                return;
+            }
+        }
+    });
+
+    Box::pin(stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|node| (node, receiver))
+    }))
+}
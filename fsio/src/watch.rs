@@ -0,0 +1,377 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::Stream;
+use tokio::{
+    sync::mpsc::{self, Receiver, Sender, UnboundedReceiver},
+    task::JoinHandle,
+    time::{Instant, timeout},
+};
+
+/// How long the watcher waits for the file system to settle before
+/// reporting the changes it collected.
+pub const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Longest a change waits to be reported while events keep coming, like
+/// during a large copy into a watched folder.
+pub const WATCH_MAX_LATENCY: Duration = Duration::from_secs(5);
+
+const WATCH_BUFFER: usize = 256;
+
+/// A change reported by [`crate::FileIo::watch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileWatchEvent {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+/// Stream of the changes below a watched path, watching stops when the
+/// handle is dropped.
+pub struct FileWatchHandle {
+    events: Receiver<FileWatchEvent>,
+    debouncer: JoinHandle<()>,
+    _guard: Box<dyn Send>,
+}
+
+impl FileWatchHandle {
+    /// Debounces the `raw` events of a backend. `guard` is whatever keeps
+    /// the backend watching, it is dropped along with the handle.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub(crate) fn new(raw: UnboundedReceiver<FileWatchEvent>, guard: impl Send + 'static) -> Self {
+        let (sender, events) = mpsc::channel(WATCH_BUFFER);
+        let debouncer = tokio::spawn(debounce(raw, sender, WATCH_DEBOUNCE, WATCH_MAX_LATENCY));
+
+        Self {
+            events,
+            debouncer,
+            _guard: Box::new(guard),
+        }
+    }
+
+    /// Waits for the next change, `None` once the backend stopped watching.
+    pub async fn recv(&mut self) -> Option<FileWatchEvent> {
+        self.events.recv().await
+    }
+}
+
+impl Stream for FileWatchHandle {
+    type Item = FileWatchEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl Drop for FileWatchHandle {
+    fn drop(&mut self) {
+        self.debouncer.abort();
+    }
+}
+
+/// Aborts a background task when dropped, used as the guard of watchers
+/// driven by a task.
+#[cfg(target_os = "android")]
+pub(crate) struct AbortOnDrop(pub JoinHandle<()>);
+
+#[cfg(target_os = "android")]
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// What the coalescer knows about a path during a burst.
+#[derive(Debug)]
+struct PathState {
+    existed: bool,
+    exists: bool,
+    modified: bool,
+    /// Path the current content had before the burst, `None` for content
+    /// created during the burst.
+    content_from: Option<PathBuf>,
+}
+
+/// Merges the events of a burst, so a file written in several chunks is
+/// reported once, a file created then removed is not reported at all and
+/// the separate halves of a rename reported by some platforms add up to a
+/// single rename.
+#[derive(Debug, Default)]
+struct Coalescer {
+    order: Vec<PathBuf>,
+    states: HashMap<PathBuf, PathState>,
+}
+
+impl Coalescer {
+    /// State of `path`, assuming it existed before the burst unless the
+    /// first event about it is a creation.
+    fn state(&mut self, path: &Path, existed: bool) -> &mut PathState {
+        if !self.states.contains_key(path) {
+            self.order.push(path.to_path_buf());
+            self.states.insert(
+                path.to_path_buf(),
+                PathState {
+                    existed,
+                    exists: existed,
+                    modified: false,
+                    content_from: existed.then(|| path.to_path_buf()),
+                },
+            );
+        }
+        self.states.get_mut(path).unwrap()
+    }
+
+    fn push(&mut self, event: FileWatchEvent) {
+        match event {
+            FileWatchEvent::Created(path) => {
+                let state = self.state(&path, false);
+                state.exists = true;
+                state.content_from = None;
+            }
+            FileWatchEvent::Modified(path) => {
+                self.state(&path, true).modified = true;
+            }
+            // The content is kept in case the removal is the first half of
+            // a rename
+            FileWatchEvent::Removed(path) => {
+                self.state(&path, true).exists = false;
+            }
+            FileWatchEvent::Renamed { from, to } => {
+                let from_state = self.state(&from, true);
+                from_state.exists = false;
+                let content_from = from_state.content_from.take();
+
+                let to_state = self.state(&to, false);
+                to_state.exists = true;
+                to_state.modified = false;
+                to_state.content_from = content_from;
+            }
+        }
+    }
+
+    fn drain(&mut self) -> Vec<FileWatchEvent> {
+        let moved: HashSet<PathBuf> = self
+            .states
+            .iter()
+            .filter(|(_, state)| state.exists)
+            .filter_map(|(path, state)| state.content_from.clone().filter(|x| x != path))
+            .collect();
+
+        let mut events = Vec::new();
+        for path in self.order.drain(..) {
+            let state = self.states.remove(&path).unwrap();
+            match (state.existed, state.exists, state.content_from) {
+                (_, true, Some(from)) if from != path => {
+                    events.push(FileWatchEvent::Renamed {
+                        from,
+                        to: path.clone(),
+                    });
+                    if state.modified {
+                        events.push(FileWatchEvent::Modified(path));
+                    }
+                }
+                (true, true, Some(_)) if !state.modified => {}
+                (true, true, _) => events.push(FileWatchEvent::Modified(path)),
+                (false, true, _) => events.push(FileWatchEvent::Created(path)),
+                (true, false, _) if !moved.contains(&path) => {
+                    events.push(FileWatchEvent::Removed(path))
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Collects bursts of raw events and forwards them once no event arrived
+/// for `delay`, or `max_latency` after the first event of a burst that
+/// doesn't settle. Shared by all backends so they report changes alike.
+async fn debounce(
+    mut raw: UnboundedReceiver<FileWatchEvent>,
+    output: Sender<FileWatchEvent>,
+    delay: Duration,
+    max_latency: Duration,
+) {
+    let mut coalescer = Coalescer::default();
+
+    while let Some(event) = raw.recv().await {
+        coalescer.push(event);
+        let deadline = Instant::now() + max_latency;
+
+        let mut closed = false;
+        loop {
+            let wait = delay.min(deadline.saturating_duration_since(Instant::now()));
+            if wait.is_zero() {
+                break;
+            }
+
+            match timeout(wait, raw.recv()).await {
+                Ok(Some(event)) => coalescer.push(event),
+                Ok(None) => {
+                    closed = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        for event in coalescer.drain() {
+            if output.send(event).await.is_err() {
+                return;
+            }
+        }
+
+        if closed {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(events: Vec<FileWatchEvent>) -> Vec<FileWatchEvent> {
+        let mut coalescer = Coalescer::default();
+        for event in events {
+            coalescer.push(event);
+        }
+        coalescer.drain()
+    }
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(name)
+    }
+
+    #[test]
+    fn chunked_writes_are_reported_once() {
+        assert_eq!(
+            drain(vec![
+                FileWatchEvent::Modified(path("a.flac")),
+                FileWatchEvent::Modified(path("a.flac")),
+                FileWatchEvent::Modified(path("a.flac")),
+            ]),
+            [FileWatchEvent::Modified(path("a.flac"))]
+        );
+        assert_eq!(
+            drain(vec![
+                FileWatchEvent::Created(path("a.flac")),
+                FileWatchEvent::Modified(path("a.flac")),
+            ]),
+            [FileWatchEvent::Created(path("a.flac"))]
+        );
+    }
+
+    #[test]
+    fn files_created_then_removed_are_not_reported() {
+        assert_eq!(
+            drain(vec![
+                FileWatchEvent::Created(path("a.part")),
+                FileWatchEvent::Modified(path("a.part")),
+                FileWatchEvent::Removed(path("a.part")),
+            ]),
+            []
+        );
+        // A file removed then created again was replaced
+        assert_eq!(
+            drain(vec![
+                FileWatchEvent::Removed(path("a.flac")),
+                FileWatchEvent::Created(path("a.flac")),
+            ]),
+            [FileWatchEvent::Modified(path("a.flac"))]
+        );
+    }
+
+    #[test]
+    fn halves_of_a_rename_add_up() {
+        let renamed = |from: &str, to: &str| FileWatchEvent::Renamed {
+            from: path(from),
+            to: path(to),
+        };
+
+        assert_eq!(
+            drain(vec![renamed("a.flac", "b.flac")]),
+            [renamed("a.flac", "b.flac")]
+        );
+        assert_eq!(
+            drain(vec![
+                FileWatchEvent::Removed(path("a.flac")),
+                renamed("a.flac", "b.flac"),
+            ]),
+            [renamed("a.flac", "b.flac")]
+        );
+        // Renamed twice during the burst
+        assert_eq!(
+            drain(vec![
+                renamed("a.flac", "tmp.flac"),
+                renamed("tmp.flac", "b.flac")
+            ]),
+            [renamed("a.flac", "b.flac")]
+        );
+    }
+
+    #[test]
+    fn renamed_files_keep_their_changes() {
+        assert_eq!(
+            drain(vec![
+                FileWatchEvent::Renamed {
+                    from: path("a.flac"),
+                    to: path("b.flac"),
+                },
+                FileWatchEvent::Modified(path("b.flac")),
+            ]),
+            [
+                FileWatchEvent::Renamed {
+                    from: path("a.flac"),
+                    to: path("b.flac"),
+                },
+                FileWatchEvent::Modified(path("b.flac")),
+            ]
+        );
+        // A file created then renamed is only created
+        assert_eq!(
+            drain(vec![
+                FileWatchEvent::Created(path("a.part")),
+                FileWatchEvent::Renamed {
+                    from: path("a.part"),
+                    to: path("a.flac"),
+                },
+            ]),
+            [FileWatchEvent::Created(path("a.flac"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn bursts_that_never_settle_are_flushed() {
+        let (raw_sender, raw) = mpsc::unbounded_channel();
+        let (sender, mut events) = mpsc::channel(WATCH_BUFFER);
+        tokio::spawn(debounce(
+            raw,
+            sender,
+            Duration::from_millis(100),
+            Duration::from_millis(300),
+        ));
+
+        // Events keep coming faster than the debounce delay
+        let writer = tokio::spawn(async move {
+            for i in 0.. {
+                let event = FileWatchEvent::Created(PathBuf::from(format!("{i}.flac")));
+                if raw_sender.send(event).is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        let event = timeout(Duration::from_secs(2), events.recv()).await;
+        writer.abort();
+        assert!(matches!(event, Ok(Some(FileWatchEvent::Created(_)))));
+    }
+}
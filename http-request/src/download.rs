@@ -0,0 +1,445 @@
+//! Streaming responses to files.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use fsio::FsIo;
+use http_body::Body;
+use http_body_util::BodyExt;
+use hyper::header::{
+    CONTENT_LENGTH, CONTENT_RANGE, ETAG, HeaderName, HeaderValue, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use hyper::http::request::Parts;
+use hyper::{Request, StatusCode, body::Bytes};
+use rustls::ClientConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::options::{
+    RequestError, RequestOptions, is_transient, is_transient_status, rebuild_request,
+};
+use crate::pool::{ConnectionPool, send_pooled_request_with_timeout};
+use crate::redirect::{Redirects, host_and_port};
+
+const PART_EXTENSION: &str = ".part";
+const STATE_EXTENSION: &str = ".part.json";
+const WANT_DIGEST: HeaderName = HeaderName::from_static("want-digest");
+const DIGEST: HeaderName = HeaderName::from_static("digest");
+
+/// How a response is downloaded.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Limits, retries and cancellation of the download. `timeout` limits
+    /// the wait for the response and for each piece of its body, not the
+    /// whole download, and retries go on from what was already downloaded
+    pub request: RequestOptions,
+    /// Size the file can't exceed
+    pub max_size: Option<u64>,
+    /// SHA-256 the file must have. Without it, the file is checked against
+    /// the digest the server reports, if it reports one
+    pub sha256: Option<[u8; 32]>,
+    /// Whether to go on from the partial file an interrupted download left
+    /// behind, instead of starting over
+    pub resume: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            request: RequestOptions::idempotent(),
+            max_size: None,
+            sha256: None,
+            resume: false,
+        }
+    }
+}
+
+/// Why a download failed, for the failures which aren't a [`RequestError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadError {
+    /// The server answered with another status than the file
+    Status(StatusCode),
+    /// The file is larger than `max_size`
+    TooLarge { limit: u64 },
+    /// The file doesn't have the SHA-256 it should have
+    DigestMismatch,
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DownloadError::Status(status) => write!(f, "Download failed with status {status}"),
+            DownloadError::TooLarge { limit } => {
+                write!(f, "The file is larger than {limit} bytes")
+            }
+            DownloadError::DigestMismatch => {
+                write!(f, "The downloaded file doesn't match its digest")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl DownloadError {
+    /// The failure behind an error returned by a download, if it is one.
+    pub fn of(error: &anyhow::Error) -> Option<&Self> {
+        error.downcast_ref()
+    }
+}
+
+/// State of a partial download, stored next to the partial file so the
+/// download can go on after a failure or a restart.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct PartialDownload {
+    url: String,
+    /// Size of the complete file, 0 when the server didn't tell
+    total: u64,
+    /// `ETag` or `Last-Modified` of the remote file, whichever is available
+    validator: Option<String>,
+    /// SHA-256 of the complete file reported by the server, in base64
+    digest: Option<String>,
+}
+
+/// A body read to the end into the partial file.
+struct Completed {
+    hasher: Sha256,
+    size: u64,
+    digest: Option<String>,
+}
+
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()?))
+}
+
+fn parse_digest(value: &str) -> Option<String> {
+    value.split(',').find_map(|x| {
+        let (algorithm, digest) = x.trim().split_once('=')?;
+        algorithm
+            .eq_ignore_ascii_case("sha-256")
+            .then(|| digest.to_owned())
+    })
+}
+
+async fn read_state(fsio: &FsIo, state_path: &Path) -> Option<PartialDownload> {
+    let mut content = Vec::new();
+    let mut file = fsio.open_async(state_path, "r").await.ok()?;
+    file.read_to_end(&mut content).ok()?;
+
+    serde_json::from_slice(&content).ok()
+}
+
+/// The URL an interrupted download into `path` was fetching, if it left a
+/// partial file behind.
+pub async fn partial_download_url(fsio: &FsIo, path: &Path) -> Option<String> {
+    read_state(fsio, &with_extension(path, STATE_EXTENSION))
+        .await
+        .map(|x| x.url)
+}
+
+/// Loads the state of an interrupted download of `url`, along with the
+/// hasher and the size of the data downloaded so far.
+async fn load_partial(
+    fsio: &FsIo,
+    url: &str,
+    part_path: &Path,
+    state_path: &Path,
+) -> Option<(PartialDownload, Sha256, u64)> {
+    let state = read_state(fsio, state_path).await?;
+    if state.url != url {
+        return None;
+    }
+
+    let mut file = fsio.open_async(part_path, "r").await.ok()?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher).ok()?;
+
+    Some((state, hasher, size))
+}
+
+async fn remove_partial(fsio: &FsIo, part_path: &Path, state_path: &Path) {
+    let _ = fsio.remove_file(part_path).await;
+    let _ = fsio.remove_file(state_path).await;
+}
+
+/// Makes one attempt at downloading into the partial file, going on from
+/// it when `resume` is set. Returns `None` when the partial file turned out
+/// to be unusable and was removed, so the download has to start over.
+#[allow(clippy::too_many_arguments)]
+async fn attempt<B, F>(
+    fsio: &FsIo,
+    pool: &ConnectionPool,
+    config: &Arc<ClientConfig>,
+    (parts, body): (&Parts, &B),
+    (part_path, state_path): (&Path, &Path),
+    options: &DownloadOptions,
+    resume: bool,
+    on_progress: &F,
+) -> Result<Option<Completed>>
+where
+    B: Body<Data = Bytes> + Clone + Default + Send + 'static,
+    B::Error: Into<anyhow::Error>,
+    F: Fn(u64, u64),
+{
+    let url = parts.uri.to_string();
+    let partial = match resume {
+        true => load_partial(fsio, &url, part_path, state_path).await,
+        false => None,
+    };
+    let (mut state, mut hasher, offset) = partial.unwrap_or_else(|| {
+        let state = PartialDownload {
+            url,
+            ..Default::default()
+        };
+        (state, Sha256::new(), 0)
+    });
+
+    let mut req = rebuild_request(parts, body);
+    let headers = req.headers_mut();
+    if offset > 0 {
+        headers.insert(RANGE, HeaderValue::from_str(&format!("bytes={offset}-"))?);
+        if let Some(validator) = &state.validator {
+            headers.insert(IF_RANGE, HeaderValue::from_str(validator)?);
+        }
+    }
+    if options.sha256.is_none() {
+        headers.insert(WANT_DIGEST, HeaderValue::from_static("sha-256"));
+    }
+
+    let (host, port) = host_and_port(&parts.uri)?;
+    let timeout = options.request.timeout;
+    let mut redirects = Redirects::new(&host, port, req, options.request.max_redirects);
+    let response = loop {
+        let request = send_pooled_request_with_timeout(
+            pool,
+            &redirects.host,
+            redirects.port,
+            Arc::clone(config),
+            rebuild_request(&redirects.parts, &redirects.body),
+            options.request.connect_timeout,
+        );
+        let response = tokio::time::timeout(timeout, request)
+            .await
+            .unwrap_or_else(|_| Err(RequestError::Timeout(timeout).into()))?;
+        if !redirects.follow(&response)? {
+            break response;
+        }
+    };
+
+    let header = |name: HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .map(str::to_owned)
+    };
+    let validator = header(ETAG).or_else(|| header(LAST_MODIFIED));
+
+    let (offset, total) = match response.status() {
+        StatusCode::PARTIAL_CONTENT if offset > 0 => {
+            let (start, total) = header(CONTENT_RANGE)
+                .and_then(|x| parse_content_range(&x))
+                .ok_or_else(|| anyhow!("Invalid Content-Range in response"))?;
+            let changed = (state.total != 0 && total != state.total)
+                || (state.validator.is_some() && validator != state.validator);
+            if start != offset || changed {
+                remove_partial(fsio, part_path, state_path).await;
+                return Ok(None);
+            }
+            (offset, total)
+        }
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+            remove_partial(fsio, part_path, state_path).await;
+            return Ok(None);
+        }
+        // Sent without a range, or as a whole because the file changed
+        StatusCode::OK => {
+            hasher = Sha256::new();
+            state.digest = None;
+            let total = header(CONTENT_LENGTH).and_then(|x| x.parse().ok());
+            (0, total.unwrap_or(0))
+        }
+        status => return Err(DownloadError::Status(status).into()),
+    };
+
+    if let Some(limit) = options.max_size
+        && total > limit
+    {
+        return Err(DownloadError::TooLarge { limit }.into());
+    }
+    fsio.ensure_space(part_path, total.saturating_sub(offset))?;
+
+    state.total = total;
+    state.validator = validator.or(state.validator);
+    state.digest = header(DIGEST)
+        .and_then(|x| parse_digest(&x))
+        .or(state.digest);
+    fsio.write(state_path, &serde_json::to_vec(&state)?)
+        .await
+        .context("Failed to save the download state")?;
+
+    let mode = if offset == 0 { "wt" } else { "wa" };
+    let mut file = fsio
+        .open_async(part_path, mode)
+        .await
+        .context("Failed to open the partial file")?;
+
+    let mut size = offset;
+    on_progress(size, total);
+
+    let mut body = pin!(response.into_body());
+    while let Some(frame) = tokio::time::timeout(timeout, body.frame())
+        .await
+        .map_err(|_| RequestError::Timeout(timeout))?
+    {
+        let Ok(data) = frame.context("Failed to read response body")?.into_data() else {
+            continue;
+        };
+
+        size += data.len() as u64;
+        if let Some(limit) = options.max_size
+            && size > limit
+        {
+            return Err(DownloadError::TooLarge { limit }.into());
+        }
+
+        file.write_all(&data)
+            .context("Failed to write the partial file")?;
+        hasher.update(&data);
+        on_progress(size, total);
+    }
+    file.flush().context("Failed to write the partial file")?;
+
+    if total != 0 && size != total {
+        bail!("The download stopped after {size} of {total} bytes");
+    }
+
+    Ok(Some(Completed {
+        hasher,
+        size,
+        digest: state.digest,
+    }))
+}
+
+/// Whether a failed attempt at a download might succeed if made again.
+fn is_retryable(error: &anyhow::Error) -> bool {
+    match DownloadError::of(error) {
+        Some(DownloadError::Status(status)) => is_transient_status(*status),
+        Some(_) => false,
+        None => is_transient(error),
+    }
+}
+
+/// Downloads the response to a request into `path`, returning the size of
+/// the file. `on_progress` receives the bytes downloaded so far and the size
+/// of the file, 0 when the server doesn't tell.
+///
+/// The body is streamed to a partial file next to `path`, which replaces
+/// `path` once the file is complete and checked against the SHA-256 of
+/// `options`, or else the digest reported by the server. The partial file
+/// of a failed or cancelled download is kept for a later download to go on
+/// from, unless the file is too large or doesn't match its digest.
+///
+/// Redirects are followed as by `send_request_with_options`. Timeouts and
+/// cancellations fail with a [`RequestError`], the other failures of the
+/// download itself with a [`DownloadError`].
+pub async fn download_to<B, F>(
+    fsio: &FsIo,
+    pool: &ConnectionPool,
+    config: Arc<ClientConfig>,
+    req: Request<B>,
+    path: &Path,
+    options: &DownloadOptions,
+    on_progress: F,
+) -> Result<u64>
+where
+    B: Body<Data = Bytes> + Clone + Default + Send + 'static,
+    B::Error: Into<anyhow::Error>,
+    F: Fn(u64, u64),
+{
+    let part_path = with_extension(path, PART_EXTENSION);
+    let state_path = with_extension(path, STATE_EXTENSION);
+    let (parts, body) = req.into_parts();
+    let cancel_token = options.request.cancel_token.clone().unwrap_or_default();
+    let retries = if options.request.idempotent {
+        options.request.retries
+    } else {
+        0
+    };
+
+    let mut resume = options.resume;
+    let mut tries = 0;
+    let completed = loop {
+        let result = tokio::select! {
+            result = attempt(
+                fsio,
+                pool,
+                &config,
+                (&parts, &body),
+                (&part_path, &state_path),
+                options,
+                resume,
+                &on_progress,
+            ) => result,
+            _ = cancel_token.cancelled() => return Err(RequestError::Cancelled.into()),
+        };
+
+        let error = match result {
+            Ok(Some(completed)) => break completed,
+            Ok(None) => {
+                resume = false;
+                continue;
+            }
+            Err(e) => e,
+        };
+        if matches!(
+            DownloadError::of(&error),
+            Some(DownloadError::TooLarge { .. })
+        ) {
+            remove_partial(fsio, &part_path, &state_path).await;
+        }
+        if !is_retryable(&error) || tries >= retries {
+            return Err(error);
+        }
+
+        // Retries go on from what the failed attempt downloaded
+        tries += 1;
+        resume = true;
+        tokio::select! {
+            _ = tokio::time::sleep(options.request.backoff(tries)) => {}
+            _ = cancel_token.cancelled() => return Err(RequestError::Cancelled.into()),
+        }
+    };
+
+    let expected = options.sha256.map(|x| x.to_vec()).or_else(|| {
+        // Digests which can't be decoded match no file
+        let digest = completed.digest.as_deref()?;
+        Some(STANDARD.decode(digest).unwrap_or_default())
+    });
+    if let Some(expected) = expected
+        && completed.hasher.finalize().as_slice() != expected
+    {
+        remove_partial(fsio, &part_path, &state_path).await;
+        return Err(DownloadError::DigestMismatch.into());
+    }
+
+    fsio.rename(&part_path, path)
+        .await
+        .context("Failed to move the downloaded file into place")?;
+    let _ = fsio.remove_file(&state_path).await;
+
+    Ok(completed.size)
+}
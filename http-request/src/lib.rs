@@ -2,25 +2,36 @@ use std::sync::Arc;
 
 use anyhow::{Context, Result, anyhow};
 use http_body_util::combinators::UnsyncBoxBody;
-use hyper::{Response, body::Incoming};
+use hyper::body::Incoming;
 use hyper_util::rt::TokioIo;
 use rustls::pki_types::ServerName;
-use tokio::net::TcpStream;
 use tokio_rustls::TlsConnector;
 
+pub mod download;
+pub mod options;
+pub mod pool;
+pub mod proxy;
+pub mod redirect;
+
+pub use download::{DownloadError, DownloadOptions, download_to, partial_download_url};
 pub use http_body_util::{BodyExt, Empty, Full};
-pub use hyper::{Method, Request, StatusCode, Uri, body::Bytes};
+pub use hyper::{Method, Request, Response, StatusCode, Uri, body::Bytes};
+pub use options::{RequestError, RequestOptions, send_request_with_options};
+pub use pool::{ConnectionPool, PoolStats, PooledBody, send_pooled_request};
+pub use proxy::{ProxyConfig, ProxyKind, ProxyMode, proxy_for, set_proxy_mode};
+pub use redirect::RedirectChain;
 pub use rustls::ClientConfig;
+pub use tokio_util::sync::CancellationToken;
 
+/// Opens an HTTP/1.1 connection over TLS to `host:port`, tunneled through
+/// the proxy if one applies to the host.
 pub async fn create_https_client(
     host: String,
     port: u16,
     config: Arc<ClientConfig>,
 ) -> Result<hyper::client::conn::http1::SendRequest<UnsyncBoxBody<Bytes, anyhow::Error>>> {
     let host = host.to_string();
-    let tcp_stream = TcpStream::connect((host.clone(), port))
-        .await
-        .with_context(|| format!("Failed to connect to {host}:{port}"))?;
+    let tcp_stream = proxy::connect(&host, port).await?;
 
     let server_name =
         ServerName::try_from(host.clone()).map_err(|_| anyhow!("Invalid server name: {}", host))?;
@@ -44,6 +55,9 @@ pub async fn create_https_client(
     Ok(sender)
 }
 
+/// Sends a request over a connection opened with `create_https_client`.
+/// Nothing limits how long it takes, see `send_request_with_options` for
+/// timeouts, retries and cancellation.
 pub async fn send_http_request<B>(
     sender: &mut hyper::client::conn::http1::SendRequest<UnsyncBoxBody<Bytes, anyhow::Error>>,
     req: Request<B>,
@@ -0,0 +1,299 @@
+//! Timeouts, retries and cancellation of requests.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use http_body::Body;
+use http_body_util::BodyExt;
+use hyper::http::request::Parts;
+use hyper::{Request, Response, StatusCode, Uri, body::Bytes};
+use rustls::ClientConfig;
+use tokio_util::sync::CancellationToken;
+
+use crate::pool::{ConnectionPool, send_pooled_request_with_timeout};
+use crate::redirect::{DEFAULT_MAX_REDIRECTS, Redirects};
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_RETRIES: u32 = 2;
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(250);
+/// The longest wait between two attempts.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(10);
+
+/// How a request is made.
+#[derive(Debug, Clone)]
+pub struct RequestOptions {
+    /// Limit for opening a connection, the TLS handshake included
+    pub connect_timeout: Duration,
+    /// Limit for each attempt, from connecting to reading the whole body
+    pub timeout: Duration,
+    /// Attempts made after the first one fails, when the request is
+    /// idempotent
+    pub retries: u32,
+    /// The wait before the first retry, doubled before each of the next ones
+    pub retry_backoff: Duration,
+    /// Whether the request can be made again without side effects. Other
+    /// requests are never retried
+    pub idempotent: bool,
+    /// Aborts the request, and the retries still to come, once cancelled
+    pub cancel_token: Option<CancellationToken>,
+    /// Redirects followed before giving up, at most `MAX_REDIRECTS`. With
+    /// 0, redirects are returned as they are
+    pub max_redirects: usize,
+}
+
+impl Default for RequestOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            idempotent: false,
+            cancel_token: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+        }
+    }
+}
+
+impl RequestOptions {
+    /// The default options of requests which can be retried, like `GET`.
+    pub fn idempotent() -> Self {
+        Self {
+            idempotent: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_cancel_token(mut self, cancel_token: CancellationToken) -> Self {
+        self.cancel_token = Some(cancel_token);
+        self
+    }
+
+    /// The wait before retry `attempt`, counted from 1. The wait is drawn
+    /// between half and all of the backoff, so clients failing together
+    /// don't retry together.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .retry_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(MAX_RETRY_BACKOFF);
+        let jitter = (RandomState::new().hash_one(attempt) % 1000) as f64 / 1000.;
+
+        backoff.mul_f64(0.5 + jitter / 2.)
+    }
+}
+
+/// Why a request failed, for the failures callers tell users apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestError {
+    /// The host didn't accept the connection in time
+    ConnectTimeout { host: String, port: u16 },
+    /// Nothing listens on the port, or a firewall refused the connection
+    ConnectionRefused { host: String, port: u16 },
+    /// The response didn't arrive in time
+    Timeout(Duration),
+    /// The request was cancelled through its token
+    Cancelled,
+    /// The request was redirected more times than allowed
+    TooManyRedirects(usize),
+    /// The request was redirected to a URI it already went through
+    RedirectLoop(Uri),
+    /// The request was redirected to a URI which isn't HTTPS
+    InsecureRedirect(Uri),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::ConnectTimeout { host, port } => {
+                write!(f, "Timed out connecting to {host}:{port}")
+            }
+            RequestError::ConnectionRefused { host, port } => {
+                write!(f, "Connection refused by {host}:{port}")
+            }
+            RequestError::Timeout(limit) => {
+                write!(f, "No response within {} seconds", limit.as_secs_f64())
+            }
+            RequestError::Cancelled => write!(f, "Request cancelled"),
+            RequestError::TooManyRedirects(limit) => {
+                write!(f, "Redirected more than {limit} times")
+            }
+            RequestError::RedirectLoop(uri) => write!(f, "Redirect loop through {uri}"),
+            RequestError::InsecureRedirect(uri) => {
+                write!(f, "Refused to follow a redirect to {uri}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+impl RequestError {
+    /// The failure behind an error returned by a request, if it is one.
+    pub fn of(error: &anyhow::Error) -> Option<&Self> {
+        error.downcast_ref()
+    }
+}
+
+/// Whether a failed attempt might succeed if made again.
+pub(crate) fn is_transient(error: &anyhow::Error) -> bool {
+    match RequestError::of(error) {
+        Some(
+            RequestError::ConnectTimeout { .. }
+            | RequestError::ConnectionRefused { .. }
+            | RequestError::Timeout(_),
+        ) => true,
+        Some(_) => false,
+        None => error
+            .chain()
+            .any(|x| x.is::<hyper::Error>() || x.is::<std::io::Error>()),
+    }
+}
+
+/// Statuses of servers which are briefly unavailable.
+pub(crate) fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// A new request made of the parts and body of another one.
+pub(crate) fn rebuild_request<B: Clone>(parts: &Parts, body: &B) -> Request<B> {
+    let mut req = Request::new(body.clone());
+    *req.method_mut() = parts.method.clone();
+    *req.uri_mut() = parts.uri.clone();
+    *req.version_mut() = parts.version;
+    *req.headers_mut() = parts.headers.clone();
+    req
+}
+
+async fn attempt<B>(
+    pool: &ConnectionPool,
+    host: &str,
+    port: u16,
+    config: Arc<ClientConfig>,
+    req: Request<B>,
+    options: &RequestOptions,
+) -> Result<Response<Bytes>>
+where
+    B: Body<Data = Bytes> + Clone + Send + 'static,
+    B::Error: Into<anyhow::Error>,
+{
+    let request = async {
+        let res = send_pooled_request_with_timeout(
+            pool,
+            host,
+            port,
+            config,
+            req,
+            options.connect_timeout,
+        )
+        .await?;
+        let (parts, body) = res.into_parts();
+        let body = body
+            .collect()
+            .await
+            .context("Failed to read response body")?
+            .to_bytes();
+
+        Ok(Response::from_parts(parts, body))
+    };
+
+    tokio::time::timeout(options.timeout, request)
+        .await
+        .unwrap_or_else(|_| Err(RequestError::Timeout(options.timeout).into()))
+}
+
+/// Sends a request, retrying it within the limits of `options`.
+async fn send_with_retries<B>(
+    pool: &ConnectionPool,
+    host: &str,
+    port: u16,
+    config: &Arc<ClientConfig>,
+    (parts, body): (&Parts, &B),
+    options: &RequestOptions,
+) -> Result<Response<Bytes>>
+where
+    B: Body<Data = Bytes> + Clone + Send + 'static,
+    B::Error: Into<anyhow::Error>,
+{
+    let cancel_token = options.cancel_token.clone().unwrap_or_default();
+    let retries = if options.idempotent {
+        options.retries
+    } else {
+        0
+    };
+
+    let mut tries = 0;
+    loop {
+        let req = rebuild_request(parts, body);
+        let result = tokio::select! {
+            result = attempt(pool, host, port, Arc::clone(config), req, options) => result,
+            _ = cancel_token.cancelled() => return Err(RequestError::Cancelled.into()),
+        };
+
+        let transient = match &result {
+            Ok(response) => is_transient_status(response.status()),
+            Err(e) => is_transient(e),
+        };
+        if !transient || tries >= retries {
+            return result;
+        }
+
+        tries += 1;
+        tokio::select! {
+            _ = tokio::time::sleep(options.backoff(tries)) => {}
+            _ = cancel_token.cancelled() => return Err(RequestError::Cancelled.into()),
+        }
+    }
+}
+
+/// Sends a request over a pooled connection and reads its whole body,
+/// within the limits of `options`. Idempotent requests failing because of
+/// the network, a timeout or a briefly unavailable server are made again,
+/// waiting longer before each retry.
+///
+/// Redirects are followed up to `max_redirects`, to HTTPS URIs only, and
+/// the credentials of the request are dropped once it leaves the host.
+/// The URIs the response was reached through are in its
+/// [`RedirectChain`](crate::RedirectChain).
+///
+/// Timeouts, refused connections, cancellations and redirects which can't
+/// be followed fail with a [`RequestError`], which [`RequestError::of`]
+/// finds back.
+pub async fn send_request_with_options<B>(
+    pool: &ConnectionPool,
+    host: &str,
+    port: u16,
+    config: Arc<ClientConfig>,
+    req: Request<B>,
+    options: &RequestOptions,
+) -> Result<Response<Bytes>>
+where
+    B: Body<Data = Bytes> + Clone + Default + Send + 'static,
+    B::Error: Into<anyhow::Error>,
+{
+    let mut redirects = Redirects::new(host, port, req, options.max_redirects);
+
+    loop {
+        let mut response = send_with_retries(
+            pool,
+            &redirects.host,
+            redirects.port,
+            &config,
+            (&redirects.parts, &redirects.body),
+            options,
+        )
+        .await?;
+        if !redirects.follow(&response)? {
+            response.extensions_mut().insert(redirects.into_chain());
+            return Ok(response);
+        }
+    }
+}
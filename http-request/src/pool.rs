@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use http_body::{Body, Frame, SizeHint};
+use http_body_util::BodyExt;
+use http_body_util::combinators::UnsyncBoxBody;
+use hyper::client::conn::http1::SendRequest;
+use hyper::{Request, Response, body::Bytes, body::Incoming};
+use rustls::ClientConfig;
+
+use crate::create_https_client;
+use crate::options::{DEFAULT_CONNECT_TIMEOUT, RequestError};
+use crate::proxy::{ProxyConfig, proxy_for};
+
+/// How long an idle connection is kept by default.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Idle connections kept for each host.
+const MAX_IDLE_PER_HOST: usize = 8;
+
+type Sender = SendRequest<UnsyncBoxBody<Bytes, anyhow::Error>>;
+
+static SHARED_POOL: LazyLock<ConnectionPool> = LazyLock::new(ConnectionPool::default);
+
+/// Connections are shared by requests to the same host and port made with
+/// the same TLS configuration. Configurations are told apart by identity,
+/// callers share connections by sharing the `Arc<ClientConfig>`. The proxy
+/// is part of the key, so connections opened before the proxy settings
+/// changed aren't reused.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    host: String,
+    port: u16,
+    config: usize,
+    proxy: Option<ProxyConfig>,
+}
+
+impl PoolKey {
+    fn new(host: &str, port: u16, config: &Arc<ClientConfig>) -> Self {
+        Self {
+            host: host.to_owned(),
+            port,
+            config: Arc::as_ptr(config) as usize,
+            proxy: proxy_for(host),
+        }
+    }
+}
+
+struct IdleConnection {
+    sender: Sender,
+    idle_since: Instant,
+    // Keeps the configuration alive, so its address isn't reused by another
+    // one while its connections are pooled
+    _config: Arc<ClientConfig>,
+}
+
+/// How many connections a pool opened, and how many times it reused one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub opened: usize,
+    pub reused: usize,
+}
+
+struct PoolInner {
+    idle_timeout: Duration,
+    idle: Mutex<HashMap<PoolKey, Vec<IdleConnection>>>,
+    opened: AtomicUsize,
+    reused: AtomicUsize,
+}
+
+/// A pool of HTTP/1.1 keep-alive connections. A connection goes back to the
+/// pool once the body of its response has been read to the end, and is
+/// closed after staying idle for the idle timeout.
+#[derive(Clone)]
+pub struct ConnectionPool {
+    inner: Arc<PoolInner>,
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+impl ConnectionPool {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                idle_timeout,
+                idle: Mutex::new(HashMap::new()),
+                opened: AtomicUsize::new(0),
+                reused: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// The pool shared by the whole process.
+    pub fn shared() -> &'static ConnectionPool {
+        &SHARED_POOL
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            opened: self.inner.opened.load(Ordering::Relaxed),
+            reused: self.inner.reused.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The number of idle connections, the expired ones left out.
+    pub fn idle_count(&self) -> usize {
+        let mut idle = self.inner.idle.lock().unwrap();
+        self.evict_expired(&mut idle);
+        idle.values().map(Vec::len).sum()
+    }
+
+    fn evict_expired(&self, idle: &mut HashMap<PoolKey, Vec<IdleConnection>>) {
+        let timeout = self.inner.idle_timeout;
+        idle.retain(|_, connections| {
+            connections.retain(|x| x.idle_since.elapsed() < timeout && !x.sender.is_closed());
+            !connections.is_empty()
+        });
+    }
+
+    /// Takes the most recently used idle connection which is still open.
+    fn take_idle(&self, key: &PoolKey) -> Option<Sender> {
+        let mut idle = self.inner.idle.lock().unwrap();
+        self.evict_expired(&mut idle);
+        let connections = idle.get_mut(key)?;
+        let connection = connections.pop()?;
+        if connections.is_empty() {
+            idle.remove(key);
+        }
+
+        Some(connection.sender)
+    }
+
+    fn put_idle(&self, key: PoolKey, sender: Sender, config: Arc<ClientConfig>) {
+        if sender.is_closed() {
+            return;
+        }
+
+        let mut idle = self.inner.idle.lock().unwrap();
+        self.evict_expired(&mut idle);
+        let connections = idle.entry(key).or_default();
+        if connections.len() >= MAX_IDLE_PER_HOST {
+            connections.remove(0);
+        }
+        connections.push(IdleConnection {
+            sender,
+            idle_since: Instant::now(),
+            _config: config,
+        });
+    }
+
+    /// Reuses an idle connection to the host, or opens a new one. Returns
+    /// whether the connection was reused.
+    async fn connection(
+        &self,
+        key: &PoolKey,
+        config: &Arc<ClientConfig>,
+        connect_timeout: Duration,
+    ) -> Result<(Sender, bool)> {
+        while let Some(mut sender) = self.take_idle(key) {
+            // Idle connections were read to the end, they are ready at once
+            // unless the server closed them
+            if sender.ready().await.is_ok() {
+                self.inner.reused.fetch_add(1, Ordering::Relaxed);
+                return Ok((sender, true));
+            }
+        }
+
+        Ok((self.open(key, config, connect_timeout).await?, false))
+    }
+
+    /// Opens a connection, failing with a `RequestError` when it times out
+    /// or is refused.
+    async fn open(
+        &self,
+        key: &PoolKey,
+        config: &Arc<ClientConfig>,
+        connect_timeout: Duration,
+    ) -> Result<Sender> {
+        let connect = create_https_client(key.host.clone(), key.port, Arc::clone(config));
+        let sender = match tokio::time::timeout(connect_timeout, connect).await {
+            Ok(Ok(sender)) => sender,
+            Ok(Err(e)) if is_connection_refused(&e) => {
+                return Err(e.context(RequestError::ConnectionRefused {
+                    host: key.host.clone(),
+                    port: key.port,
+                }));
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(_) => {
+                return Err(RequestError::ConnectTimeout {
+                    host: key.host.clone(),
+                    port: key.port,
+                }
+                .into());
+            }
+        };
+
+        self.inner.opened.fetch_add(1, Ordering::Relaxed);
+        Ok(sender)
+    }
+}
+
+/// The body of a pooled response. The connection goes back to the pool
+/// once the body has been read to the end.
+pub struct PooledBody {
+    inner: Incoming,
+    release: Option<Release>,
+}
+
+struct Release {
+    pool: ConnectionPool,
+    key: PoolKey,
+    sender: Sender,
+    config: Arc<ClientConfig>,
+}
+
+impl Release {
+    fn release(self) {
+        self.pool.put_idle(self.key, self.sender, self.config);
+    }
+}
+
+impl Body for PooledBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_frame(cx);
+
+        match &result {
+            Poll::Ready(None) => {
+                if let Some(release) = this.release.take() {
+                    release.release();
+                }
+            }
+            // The connection is in an unknown state
+            Poll::Ready(Some(Err(_))) => this.release = None,
+            _ => {}
+        }
+
+        result
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+fn is_connection_refused(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|x| x.downcast_ref::<std::io::Error>())
+        .any(|x| x.kind() == std::io::ErrorKind::ConnectionRefused)
+}
+
+/// Whether a request failed because the connection was closed by the
+/// server while idle, rather than because of the request.
+fn is_stale_connection_error(error: &hyper::Error) -> bool {
+    error.is_canceled() || error.is_closed() || error.is_incomplete_message()
+}
+
+/// Sends a request over a pooled connection to `host:port`. A request which
+/// fails because a reused connection went stale is sent again, once, over a
+/// new connection, which is why the body must be `Clone`.
+///
+/// Only opening the connection is limited in time, see
+/// `send_request_with_options` for requests which can't be left hanging.
+pub async fn send_pooled_request<B>(
+    pool: &ConnectionPool,
+    host: &str,
+    port: u16,
+    config: Arc<ClientConfig>,
+    req: Request<B>,
+) -> Result<Response<PooledBody>>
+where
+    B: Body<Data = Bytes> + Clone + Send + 'static,
+    B::Error: Into<anyhow::Error>,
+{
+    send_pooled_request_with_timeout(pool, host, port, config, req, DEFAULT_CONNECT_TIMEOUT).await
+}
+
+pub(crate) async fn send_pooled_request_with_timeout<B>(
+    pool: &ConnectionPool,
+    host: &str,
+    port: u16,
+    config: Arc<ClientConfig>,
+    req: Request<B>,
+    connect_timeout: Duration,
+) -> Result<Response<PooledBody>>
+where
+    B: Body<Data = Bytes> + Clone + Send + 'static,
+    B::Error: Into<anyhow::Error>,
+{
+    let key = PoolKey::new(host, port, &config);
+    let (parts, body) = req.into_parts();
+    let build = || {
+        let mut req = Request::new(body.clone().map_err(|e| anyhow::anyhow!(e)).boxed_unsync());
+        *req.method_mut() = parts.method.clone();
+        *req.uri_mut() = parts.uri.clone();
+        *req.version_mut() = parts.version;
+        *req.headers_mut() = parts.headers.clone();
+        req
+    };
+
+    let (mut sender, reused) = pool.connection(&key, &config, connect_timeout).await?;
+    let response = match sender.send_request(build()).await {
+        Ok(response) => response,
+        Err(e) if reused && is_stale_connection_error(&e) => {
+            let mut fresh = pool.open(&key, &config, connect_timeout).await?;
+            let response = fresh
+                .send_request(build())
+                .await
+                .context("Failed to send request")?;
+            sender = fresh;
+            response
+        }
+        Err(e) => return Err(e).context("Failed to send request"),
+    };
+
+    let (parts, inner) = response.into_parts();
+    let release = Release {
+        pool: pool.clone(),
+        key,
+        sender,
+        config,
+    };
+    let release = if inner.is_end_stream() {
+        release.release();
+        None
+    } else {
+        Some(release)
+    };
+
+    Ok(Response::from_parts(parts, PooledBody { inner, release }))
+}
@@ -0,0 +1,201 @@
+//! Following redirects.
+
+use anyhow::{Context, Result, anyhow};
+use hyper::header::{
+    AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HeaderMap, LOCATION, PROXY_AUTHORIZATION,
+    TRANSFER_ENCODING,
+};
+use hyper::http::request::Parts;
+use hyper::{Method, Request, Response, StatusCode, Uri};
+
+use crate::options::RequestError;
+
+pub const DEFAULT_MAX_REDIRECTS: usize = 5;
+/// Redirects followed at most, whatever the options of the request say.
+pub const MAX_REDIRECTS: usize = 20;
+
+/// The URIs a request went through, from the one requested to the one which
+/// answered, found in the extensions of the responses of
+/// `send_request_with_options`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedirectChain(pub Vec<Uri>);
+
+impl RedirectChain {
+    /// The URIs a response was reached through, empty for responses which
+    /// didn't record them.
+    pub fn of<B>(response: &Response<B>) -> &[Uri] {
+        response
+            .extensions()
+            .get::<RedirectChain>()
+            .map(|x| x.0.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The number of redirects followed.
+    pub fn hops(&self) -> usize {
+        self.0.len().saturating_sub(1)
+    }
+}
+
+pub(crate) fn is_redirect(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+/// Resolves the `Location` of a redirect against the URI which answered it.
+pub(crate) fn resolve_location(base: &Uri, location: &str) -> Result<Uri> {
+    // Fragments stay on the client
+    let location = location.split('#').next().unwrap_or_default().trim();
+    if location.is_empty() {
+        return Err(anyhow!("Empty redirect location"));
+    }
+
+    let scheme = base.scheme_str().unwrap_or("https");
+    let authority = base
+        .authority()
+        .map(|x| x.as_str())
+        .ok_or_else(|| anyhow!("Redirect from {base} which has no host"))?;
+
+    let resolved = if location.contains("://") {
+        location.to_owned()
+    } else if let Some(rest) = location.strip_prefix("//") {
+        format!("{scheme}://{rest}")
+    } else if location.starts_with('/') {
+        format!("{scheme}://{authority}{location}")
+    } else if location.starts_with('?') {
+        format!("{scheme}://{authority}{}{location}", base.path())
+    } else {
+        let directory = match base.path().rfind('/') {
+            Some(index) => &base.path()[..=index],
+            None => "/",
+        };
+        format!("{scheme}://{authority}{directory}{location}")
+    };
+
+    resolved
+        .parse()
+        .with_context(|| format!("Invalid redirect location: {location}"))
+}
+
+/// The host and port a URI is reached at.
+pub(crate) fn host_and_port(uri: &Uri) -> Result<(String, u16)> {
+    let host = uri
+        .host()
+        .ok_or_else(|| anyhow!("No host in {uri}"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+
+    Ok((host.to_owned(), uri.port_u16().unwrap_or(443)))
+}
+
+/// Removes the credentials of a request, before it follows a redirect to
+/// another host.
+pub(crate) fn strip_credentials(headers: &mut HeaderMap) {
+    for name in [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+        headers.remove(name);
+    }
+}
+
+/// Whether following the redirect turns the request into a `GET` without a
+/// body, as browsers do for `303`, and for `301` and `302` answering a
+/// `POST`.
+pub(crate) fn redirects_to_get(status: StatusCode, method: &Method) -> bool {
+    match status {
+        StatusCode::SEE_OTHER => *method != Method::HEAD && *method != Method::GET,
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => *method == Method::POST,
+        _ => false,
+    }
+}
+
+/// Drops the headers describing a body which is no longer sent.
+pub(crate) fn strip_body_headers(headers: &mut HeaderMap) {
+    for name in [CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING] {
+        headers.remove(name);
+    }
+}
+
+/// A request on its way through redirects: where it goes next, and where it
+/// went so far.
+pub(crate) struct Redirects<B> {
+    pub parts: Parts,
+    pub body: B,
+    pub host: String,
+    pub port: u16,
+    max_redirects: usize,
+    chain: Vec<Uri>,
+    visited: Vec<(Method, Uri)>,
+}
+
+impl<B: Default> Redirects<B> {
+    pub fn new(host: &str, port: u16, req: Request<B>, max_redirects: usize) -> Self {
+        let (parts, body) = req.into_parts();
+
+        Self {
+            chain: vec![parts.uri.clone()],
+            visited: vec![(parts.method.clone(), parts.uri.clone())],
+            parts,
+            body,
+            host: host.to_owned(),
+            port,
+            max_redirects: max_redirects.min(MAX_REDIRECTS),
+        }
+    }
+
+    /// Points the request to the target of `response` if it is a redirect
+    /// to follow, and returns whether it was.
+    pub fn follow<T>(&mut self, response: &Response<T>) -> Result<bool> {
+        let status = response.status();
+        if self.max_redirects == 0 || !is_redirect(status) {
+            return Ok(false);
+        }
+        if self.chain.len() > self.max_redirects {
+            return Err(RequestError::TooManyRedirects(self.max_redirects).into());
+        }
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .ok_or_else(|| anyhow!("Redirect from {} without a location", self.parts.uri))?
+            .to_str()
+            .context("Invalid redirect location")?;
+        let uri = resolve_location(&self.parts.uri, location)?;
+        if uri.scheme_str() != Some("https") {
+            return Err(RequestError::InsecureRedirect(uri).into());
+        }
+
+        if redirects_to_get(status, &self.parts.method) {
+            self.parts.method = Method::GET;
+            self.body = B::default();
+            strip_body_headers(&mut self.parts.headers);
+        }
+        if self
+            .visited
+            .contains(&(self.parts.method.clone(), uri.clone()))
+        {
+            return Err(RequestError::RedirectLoop(uri).into());
+        }
+
+        let (host, port) = host_and_port(&uri)?;
+        if host != self.host || port != self.port {
+            strip_credentials(&mut self.parts.headers);
+        }
+
+        (self.host, self.port) = (host, port);
+        self.parts.uri = uri.clone();
+        self.chain.push(uri.clone());
+        self.visited.push((self.parts.method.clone(), uri));
+
+        Ok(true)
+    }
+
+    /// The URIs the request went through.
+    pub fn into_chain(self) -> RedirectChain {
+        RedirectChain(self.chain)
+    }
+}
@@ -0,0 +1,340 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use fsio::FsIo;
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{RootCertStore, ServerConfig};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+use http_request::{
+    Bytes, ClientConfig, ConnectionPool, DownloadError, DownloadOptions, Empty, Request,
+    RequestOptions, Uri, download_to,
+};
+
+const SIZE: usize = 256 * 1024;
+const ETAG: &str = "\"v1\"";
+
+struct TestServer {
+    port: u16,
+    /// The start of the range of each request, 0 for the requests without
+    /// one
+    ranges: Arc<Mutex<Vec<u64>>>,
+    client_config: Arc<ClientConfig>,
+}
+
+fn content() -> Vec<u8> {
+    (0..SIZE).map(|x| (x % 251) as u8).collect()
+}
+
+fn digest(data: &[u8]) -> String {
+    STANDARD.encode(Sha256::digest(data))
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines()
+        .filter_map(|x| x.split_once(':'))
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+/// Starts a TLS server serving the same content on every path, with a
+/// wrong digest on `/bad-digest`, and cut after half of it on the first
+/// request for `/cut`.
+async fn start_server() -> Result<TestServer> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()])?;
+    let cert = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let server_config = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.clone()], key)?;
+    let mut roots = RootCertStore::empty();
+    roots.add(cert)?;
+    let client_config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let acceptor = TlsAcceptor::from(Arc::new(server_config));
+    let ranges = Arc::new(Mutex::new(Vec::new()));
+    let cuts = Arc::new(AtomicUsize::new(0));
+
+    let recorded = Arc::clone(&ranges);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            let recorded = Arc::clone(&recorded);
+            let cuts = Arc::clone(&cuts);
+
+            tokio::spawn(async move {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+
+                let content = content();
+                let mut buffer = Vec::new();
+                let mut chunk = [0; 1024];
+                loop {
+                    while !buffer.windows(4).any(|x| x == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let end = buffer.windows(4).position(|x| x == b"\r\n\r\n").unwrap() + 4;
+                    let head = String::from_utf8_lossy(&buffer[..end]).into_owned();
+                    buffer.drain(..end);
+
+                    let target = head.split_whitespace().nth(1).unwrap_or_default();
+                    let path = target
+                        .parse::<Uri>()
+                        .map(|x| x.path().to_owned())
+                        .unwrap_or_default();
+                    let start = header(&head, "range")
+                        .and_then(|x| x.strip_prefix("bytes="))
+                        .and_then(|x| x.trim_end_matches('-').parse::<usize>().ok())
+                        .filter(|_| header(&head, "if-range").is_none_or(|x| x == ETAG))
+                        .unwrap_or(0);
+                    recorded.lock().unwrap().push(start as u64);
+
+                    let reported = match path.as_str() {
+                        "/bad-digest" => digest(b"something else"),
+                        _ => digest(&content),
+                    };
+                    let status = match start {
+                        0 => "200 OK".to_owned(),
+                        _ => format!(
+                            "206 Partial Content\r\ncontent-range: bytes {start}-{}/{SIZE}",
+                            SIZE - 1
+                        ),
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {status}\r\netag: {ETAG}\r\ndigest: sha-256={reported}\r\n\
+                         content-length: {}\r\n\r\n",
+                        SIZE - start
+                    );
+                    let mut body = &content[start..];
+                    let cut = path == "/cut" && cuts.fetch_add(1, Ordering::SeqCst) == 0;
+                    if cut {
+                        body = &body[..SIZE / 2];
+                    }
+
+                    if stream.write_all(head.as_bytes()).await.is_err()
+                        || stream.write_all(body).await.is_err()
+                    {
+                        return;
+                    }
+                    let _ = stream.flush().await;
+                    if cut {
+                        let _ = stream.shutdown().await;
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(TestServer {
+        port,
+        ranges,
+        client_config: Arc::new(client_config),
+    })
+}
+
+async fn download(
+    server: &TestServer,
+    path: &str,
+    destination: &Path,
+    options: &DownloadOptions,
+    on_progress: impl Fn(u64, u64),
+) -> Result<u64> {
+    let uri: Uri = format!("https://localhost:{}{path}", server.port).parse()?;
+    let req = Request::builder().uri(uri).body(Empty::<Bytes>::new())?;
+
+    download_to(
+        &FsIo::new(),
+        &ConnectionPool::default(),
+        Arc::clone(&server.client_config),
+        req,
+        destination,
+        options,
+        on_progress,
+    )
+    .await
+}
+
+fn quick_options() -> DownloadOptions {
+    DownloadOptions {
+        request: RequestOptions {
+            retries: 0,
+            ..RequestOptions::idempotent()
+        },
+        ..Default::default()
+    }
+}
+
+fn leftovers(dir: &Path) -> Result<Vec<String>> {
+    let mut names = std::fs::read_dir(dir)?
+        .map(|x| Ok(x?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>>>()?;
+    names.sort();
+
+    Ok(names)
+}
+
+#[tokio::test]
+async fn downloads_are_streamed_to_the_destination() -> Result<()> {
+    let server = start_server().await?;
+    let dir = tempfile::tempdir()?;
+    let destination = dir.path().join("track.flac");
+
+    let progress = Mutex::new(Vec::new());
+    let size = download(
+        &server,
+        "/file",
+        &destination,
+        &quick_options(),
+        |x, total| progress.lock().unwrap().push((x, total)),
+    )
+    .await?;
+
+    assert_eq!(size, SIZE as u64);
+    assert_eq!(std::fs::read(&destination)?, content());
+    assert_eq!(leftovers(dir.path())?, ["track.flac"]);
+
+    let progress = progress.into_inner().unwrap();
+    assert!(progress.is_sorted(), "Progress should only go forward");
+    assert_eq!(progress.last(), Some(&(SIZE as u64, SIZE as u64)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn files_are_checked_against_their_digest() -> Result<()> {
+    let server = start_server().await?;
+    let dir = tempfile::tempdir()?;
+    let destination = dir.path().join("track.flac");
+
+    let options = DownloadOptions {
+        sha256: Some(Sha256::digest(content()).into()),
+        ..quick_options()
+    };
+    download(&server, "/file", &destination, &options, |_, _| {}).await?;
+    assert_eq!(std::fs::read(&destination)?, content());
+    std::fs::remove_file(&destination)?;
+
+    let options = DownloadOptions {
+        sha256: Some([0; 32]),
+        ..quick_options()
+    };
+    let error = download(&server, "/file", &destination, &options, |_, _| {})
+        .await
+        .unwrap_err();
+    assert_eq!(
+        DownloadError::of(&error),
+        Some(&DownloadError::DigestMismatch)
+    );
+    assert!(leftovers(dir.path())?.is_empty());
+
+    // Without an expected digest, the one of the server is checked
+    let error = download(
+        &server,
+        "/bad-digest",
+        &destination,
+        &quick_options(),
+        |_, _| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        DownloadError::of(&error),
+        Some(&DownloadError::DigestMismatch)
+    );
+    assert!(leftovers(dir.path())?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn downloads_are_limited_in_size() -> Result<()> {
+    let server = start_server().await?;
+    let dir = tempfile::tempdir()?;
+    let destination = dir.path().join("track.flac");
+
+    let options = DownloadOptions {
+        max_size: Some(SIZE as u64 - 1),
+        ..quick_options()
+    };
+    let error = download(&server, "/file", &destination, &options, |_, _| {})
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        DownloadError::of(&error),
+        Some(&DownloadError::TooLarge {
+            limit: SIZE as u64 - 1
+        })
+    );
+    assert!(leftovers(dir.path())?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn interrupted_downloads_can_be_resumed() -> Result<()> {
+    let server = start_server().await?;
+    let dir = tempfile::tempdir()?;
+    let destination = dir.path().join("track.flac");
+
+    download(&server, "/cut", &destination, &quick_options(), |_, _| {})
+        .await
+        .unwrap_err();
+    assert_eq!(
+        leftovers(dir.path())?,
+        ["track.flac.part", "track.flac.part.json"],
+        "The partial file should be kept"
+    );
+
+    let options = DownloadOptions {
+        resume: true,
+        ..quick_options()
+    };
+    download(&server, "/cut", &destination, &options, |_, _| {}).await?;
+
+    assert_eq!(std::fs::read(&destination)?, content());
+    assert_eq!(leftovers(dir.path())?, ["track.flac"]);
+    assert_eq!(*server.ranges.lock().unwrap(), [0, SIZE as u64 / 2]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn retries_go_on_from_what_was_downloaded() -> Result<()> {
+    let server = start_server().await?;
+    let dir = tempfile::tempdir()?;
+    let destination = dir.path().join("track.flac");
+
+    let options = DownloadOptions {
+        request: RequestOptions {
+            retries: 1,
+            ..RequestOptions::idempotent()
+        },
+        ..Default::default()
+    };
+    download(&server, "/cut", &destination, &options, |_, _| {}).await?;
+
+    assert_eq!(std::fs::read(&destination)?, content());
+    assert_eq!(*server.ranges.lock().unwrap(), [0, SIZE as u64 / 2]);
+
+    Ok(())
+}
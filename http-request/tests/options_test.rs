@@ -0,0 +1,271 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{RootCertStore, ServerConfig};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+use http_request::{
+    Bytes, CancellationToken, ClientConfig, ConnectionPool, Empty, Request, RequestError,
+    RequestOptions, Response, StatusCode, Uri, send_request_with_options,
+};
+
+/// How the server answers a request.
+#[derive(Clone, Copy)]
+struct Reply {
+    delay: Duration,
+    status: u16,
+}
+
+const OK: Reply = Reply {
+    delay: Duration::ZERO,
+    status: 200,
+};
+const UNAVAILABLE: Reply = Reply {
+    delay: Duration::ZERO,
+    status: 503,
+};
+const STALLED: Reply = Reply {
+    delay: Duration::from_secs(60),
+    status: 200,
+};
+
+struct TestServer {
+    port: u16,
+    requests: Arc<AtomicUsize>,
+    client_config: Arc<ClientConfig>,
+}
+
+fn tls_configs() -> Result<(ServerConfig, ClientConfig)> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()])?;
+    let cert = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let server_config = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.clone()], key)?;
+    let mut roots = RootCertStore::empty();
+    roots.add(cert)?;
+    let client_config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok((server_config, client_config))
+}
+
+/// Starts a TLS server answering the n-th request it receives with the n-th
+/// reply, and the requests after the last reply with the last one.
+async fn start_server(replies: Vec<Reply>) -> Result<TestServer> {
+    let (server_config, client_config) = tls_configs()?;
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let acceptor = TlsAcceptor::from(Arc::new(server_config));
+    let requests = Arc::new(AtomicUsize::new(0));
+
+    let counter = Arc::clone(&requests);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            let counter = Arc::clone(&counter);
+            let replies = replies.clone();
+
+            tokio::spawn(async move {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+
+                let mut buffer = Vec::new();
+                let mut chunk = [0; 1024];
+                loop {
+                    while !buffer.windows(4).any(|x| x == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let end = buffer.windows(4).position(|x| x == b"\r\n\r\n").unwrap() + 4;
+                    buffer.drain(..end);
+
+                    let index = counter.fetch_add(1, Ordering::SeqCst);
+                    let reply = replies[index.min(replies.len() - 1)];
+                    tokio::time::sleep(reply.delay).await;
+
+                    let response = format!(
+                        "HTTP/1.1 {} Status\r\ncontent-length: 2\r\n\r\nok",
+                        reply.status
+                    );
+                    if stream.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    let _ = stream.flush().await;
+                }
+            });
+        }
+    });
+
+    Ok(TestServer {
+        port,
+        requests,
+        client_config: Arc::new(client_config),
+    })
+}
+
+async fn get(
+    port: u16,
+    client_config: Arc<ClientConfig>,
+    options: &RequestOptions,
+) -> Result<Response<Bytes>> {
+    let uri: Uri = format!("https://localhost:{port}/").parse()?;
+    let req = Request::builder().uri(uri).body(Empty::<Bytes>::new())?;
+
+    send_request_with_options(
+        &ConnectionPool::default(),
+        "localhost",
+        port,
+        client_config,
+        req,
+        options,
+    )
+    .await
+}
+
+fn quick_options() -> RequestOptions {
+    RequestOptions {
+        connect_timeout: Duration::from_millis(200),
+        timeout: Duration::from_millis(300),
+        retry_backoff: Duration::from_millis(10),
+        ..RequestOptions::idempotent()
+    }
+}
+
+#[tokio::test]
+async fn stalled_responses_time_out() -> Result<()> {
+    let server = start_server(vec![STALLED]).await?;
+    let options = RequestOptions {
+        retries: 0,
+        ..quick_options()
+    };
+
+    let started = Instant::now();
+    let error = get(server.port, Arc::clone(&server.client_config), &options)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        RequestError::of(&error),
+        Some(&RequestError::Timeout(options.timeout))
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stalled_handshakes_are_connect_timeouts() -> Result<()> {
+    // Connections wait in the backlog, the TLS handshake never happens
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let (_, client_config) = tls_configs()?;
+
+    let error = get(port, Arc::new(client_config), &quick_options())
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        RequestError::of(&error),
+        Some(&RequestError::ConnectTimeout {
+            host: "localhost".to_owned(),
+            port
+        })
+    );
+    drop(listener);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn refused_connections_are_told_apart() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    drop(listener);
+    let (_, client_config) = tls_configs()?;
+
+    let error = get(port, Arc::new(client_config), &quick_options())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        RequestError::of(&error),
+        Some(RequestError::ConnectionRefused { .. })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn idempotent_requests_are_retried() -> Result<()> {
+    let server = start_server(vec![UNAVAILABLE, STALLED, OK]).await?;
+
+    let res = get(
+        server.port,
+        Arc::clone(&server.client_config),
+        &quick_options(),
+    )
+    .await?;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.body().as_ref(), b"ok");
+    assert_eq!(server.requests.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn other_requests_are_not_retried() -> Result<()> {
+    let server = start_server(vec![UNAVAILABLE, OK]).await?;
+    let options = RequestOptions {
+        idempotent: false,
+        ..quick_options()
+    };
+
+    let res = get(server.port, Arc::clone(&server.client_config), &options).await?;
+
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(server.requests.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancelled_requests_stop_at_once() -> Result<()> {
+    let server = start_server(vec![STALLED]).await?;
+    let cancel_token = CancellationToken::new();
+    let options = RequestOptions {
+        timeout: Duration::from_secs(60),
+        ..quick_options()
+    }
+    .with_cancel_token(cancel_token.clone());
+
+    let canceller = cancel_token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        canceller.cancel();
+    });
+
+    let started = Instant::now();
+    let error = get(server.port, Arc::clone(&server.client_config), &options)
+        .await
+        .unwrap_err();
+
+    assert_eq!(RequestError::of(&error), Some(&RequestError::Cancelled));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{RootCertStore, ServerConfig};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+use http_request::{
+    BodyExt, Bytes, ClientConfig, ConnectionPool, Empty, PoolStats, Request, StatusCode, Uri,
+    send_pooled_request,
+};
+
+const BODY: &str = "hello";
+
+/// A TLS server answering every request with the same body, counting the
+/// connections it accepts.
+struct TestServer {
+    port: u16,
+    accepted: Arc<AtomicUsize>,
+    client_config: Arc<ClientConfig>,
+}
+
+/// Starts a server closing each connection after `requests_per_connection`
+/// requests, without telling the client, like a server whose keep-alive
+/// timeout expired.
+async fn start_server(requests_per_connection: Option<usize>) -> Result<TestServer> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()])?;
+    let cert = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let server_config = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.clone()], key)?;
+    let mut roots = RootCertStore::empty();
+    roots.add(cert)?;
+    let client_config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let acceptor = TlsAcceptor::from(Arc::new(server_config));
+    let accepted = Arc::new(AtomicUsize::new(0));
+
+    let counter = Arc::clone(&accepted);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+
+                let mut served = 0;
+                let mut buffer = Vec::new();
+                let mut chunk = [0; 1024];
+                loop {
+                    // Requests have no body, their head ends with a blank line
+                    while !buffer.windows(4).any(|x| x == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let end = buffer.windows(4).position(|x| x == b"\r\n\r\n").unwrap() + 4;
+                    buffer.drain(..end);
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{BODY}",
+                        BODY.len()
+                    );
+                    if stream.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    let _ = stream.flush().await;
+
+                    served += 1;
+                    if requests_per_connection.is_some_and(|x| served >= x) {
+                        let _ = stream.shutdown().await;
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(TestServer {
+        port,
+        accepted,
+        client_config: Arc::new(client_config),
+    })
+}
+
+async fn get(pool: &ConnectionPool, server: &TestServer, config: Arc<ClientConfig>) -> Result<()> {
+    let uri: Uri = format!("https://localhost:{}/", server.port).parse()?;
+    let req = Request::builder().uri(uri).body(Empty::<Bytes>::new())?;
+
+    let res = send_pooled_request(pool, "localhost", server.port, config, req).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.into_body().collect().await?.to_bytes();
+    assert_eq!(body, BODY.as_bytes());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sequential_requests_reuse_the_connection() -> Result<()> {
+    let server = start_server(None).await?;
+    let pool = ConnectionPool::default();
+
+    for _ in 0..3 {
+        get(&pool, &server, Arc::clone(&server.client_config)).await?;
+    }
+
+    assert_eq!(server.accepted.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        pool.stats(),
+        PoolStats {
+            opened: 1,
+            reused: 2
+        }
+    );
+    assert_eq!(pool.idle_count(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn idle_connections_expire() -> Result<()> {
+    let server = start_server(None).await?;
+    let pool = ConnectionPool::new(Duration::from_millis(100));
+
+    get(&pool, &server, Arc::clone(&server.client_config)).await?;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(pool.idle_count(), 0);
+
+    get(&pool, &server, Arc::clone(&server.client_config)).await?;
+    assert_eq!(server.accepted.load(Ordering::SeqCst), 2);
+    assert_eq!(pool.stats().reused, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn connections_are_not_shared_between_configs() -> Result<()> {
+    let server = start_server(None).await?;
+    let pool = ConnectionPool::default();
+    let other_config = Arc::new(server.client_config.as_ref().clone());
+
+    get(&pool, &server, Arc::clone(&server.client_config)).await?;
+    get(&pool, &server, other_config).await?;
+
+    assert_eq!(server.accepted.load(Ordering::SeqCst), 2);
+    assert_eq!(pool.stats().opened, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn connections_closed_by_the_server_are_replaced() -> Result<()> {
+    let server = start_server(Some(1)).await?;
+    let pool = ConnectionPool::default();
+
+    for _ in 0..3 {
+        get(&pool, &server, Arc::clone(&server.client_config)).await?;
+        // Let the client see the connection close
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    assert_eq!(server.accepted.load(Ordering::SeqCst), 3);
+    assert_eq!(pool.stats().opened, 3);
+
+    Ok(())
+}
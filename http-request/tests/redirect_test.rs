@@ -0,0 +1,289 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{RootCertStore, ServerConfig};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+use http_request::{
+    Bytes, ClientConfig, ConnectionPool, Empty, Full, Method, RedirectChain, Request, RequestError,
+    RequestOptions, Response, StatusCode, Uri, send_request_with_options,
+};
+
+struct TestServer {
+    port: u16,
+    client_config: Arc<ClientConfig>,
+}
+
+/// The answer to a request for `path`: a redirect, or the method and the
+/// credentials the request came with.
+fn route(path: &str, head: &str, port: u16) -> (u16, Option<String>, String) {
+    let redirect = |status, location: String| (status, Some(location), String::new());
+
+    match path {
+        "/relative" => redirect(302, "final".to_owned()),
+        "/absolute" => redirect(301, format!("https://localhost:{port}/final")),
+        "/loop-a" => redirect(302, "/loop-b".to_owned()),
+        "/loop-b" => redirect(302, "/loop-a".to_owned()),
+        "/insecure" => redirect(302, format!("http://localhost:{port}/final")),
+        "/other-host" => redirect(307, format!("https://127.0.0.1:{port}/final")),
+        "/same-host" => redirect(307, "/final".to_owned()),
+        "/see-other" => redirect(303, "/final".to_owned()),
+        _ if path.starts_with("/chain/") => {
+            let hops: usize = path["/chain/".len()..].parse().unwrap();
+            match hops {
+                0 => redirect(302, "/final".to_owned()),
+                hops => redirect(302, format!("/chain/{}", hops - 1)),
+            }
+        }
+        _ => {
+            let method = head.split_whitespace().next().unwrap_or_default();
+            let authorized = head.to_lowercase().contains("\r\nauthorization:");
+            (200, None, format!("{method} {authorized}"))
+        }
+    }
+}
+
+async fn start_server() -> Result<TestServer> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certified =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_owned(), "127.0.0.1".to_owned()])?;
+    let cert = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let server_config = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.clone()], key)?;
+    let mut roots = RootCertStore::empty();
+    roots.add(cert)?;
+    let client_config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let acceptor = TlsAcceptor::from(Arc::new(server_config));
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+
+                let mut buffer = Vec::new();
+                let mut chunk = [0; 1024];
+                loop {
+                    while !buffer.windows(4).any(|x| x == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let end = buffer.windows(4).position(|x| x == b"\r\n\r\n").unwrap() + 4;
+                    let head = String::from_utf8_lossy(&buffer[..end]).into_owned();
+                    buffer.drain(..end);
+
+                    // Requests have absolute URIs
+                    let target = head.split_whitespace().nth(1).unwrap_or_default();
+                    let path = target
+                        .parse::<Uri>()
+                        .map(|x| x.path().to_owned())
+                        .unwrap_or_default();
+                    let length = head
+                        .lines()
+                        .filter_map(|x| x.split_once(':'))
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, value)| value.trim().parse().ok())
+                        .unwrap_or(0);
+                    while buffer.len() < length {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    buffer.drain(..length);
+
+                    let (status, location, body) = route(&path, &head, port);
+                    let location = location
+                        .map(|x| format!("location: {x}\r\n"))
+                        .unwrap_or_default();
+                    let response = format!(
+                        "HTTP/1.1 {status} Status\r\n{location}content-length: {}\r\n\r\n{body}",
+                        body.len()
+                    );
+                    if stream.write_all(response.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    let _ = stream.flush().await;
+                }
+            });
+        }
+    });
+
+    Ok(TestServer {
+        port,
+        client_config: Arc::new(client_config),
+    })
+}
+
+async fn get(server: &TestServer, path: &str, options: &RequestOptions) -> Result<Response<Bytes>> {
+    let uri: Uri = format!("https://localhost:{}{path}", server.port).parse()?;
+    let req = Request::builder()
+        .uri(uri)
+        .header("authorization", "Bearer secret")
+        .body(Empty::<Bytes>::new())?;
+
+    send_request_with_options(
+        &ConnectionPool::default(),
+        "localhost",
+        server.port,
+        Arc::clone(&server.client_config),
+        req,
+        options,
+    )
+    .await
+}
+
+fn paths(response: &Response<Bytes>) -> Vec<String> {
+    RedirectChain::of(response)
+        .iter()
+        .map(|x| x.path().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn redirects_are_followed() -> Result<()> {
+    let server = start_server().await?;
+    let options = RequestOptions::idempotent();
+
+    let res = get(&server, "/relative", &options).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.body().as_ref(), b"GET true");
+    assert_eq!(paths(&res), ["/relative", "/final"]);
+
+    let res = get(&server, "/absolute", &options).await?;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(paths(&res), ["/absolute", "/final"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn credentials_stay_on_their_host() -> Result<()> {
+    let server = start_server().await?;
+    let options = RequestOptions::idempotent();
+
+    let res = get(&server, "/same-host", &options).await?;
+    assert_eq!(res.body().as_ref(), b"GET true");
+
+    let res = get(&server, "/other-host", &options).await?;
+    assert_eq!(res.body().as_ref(), b"GET false");
+    assert_eq!(
+        RedirectChain::of(&res)[1].host(),
+        Some("127.0.0.1"),
+        "The redirect should reach the other host"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn see_other_turns_posts_into_gets() -> Result<()> {
+    let server = start_server().await?;
+    let uri: Uri = format!("https://localhost:{}/see-other", server.port).parse()?;
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("content-type", "text/plain")
+        .body(Full::new(Bytes::from_static(b"body")))?;
+
+    let res = send_request_with_options(
+        &ConnectionPool::default(),
+        "localhost",
+        server.port,
+        Arc::clone(&server.client_config),
+        req,
+        &RequestOptions::default(),
+    )
+    .await?;
+
+    assert_eq!(res.body().as_ref(), b"GET false");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsafe_redirects_are_refused() -> Result<()> {
+    let server = start_server().await?;
+    let options = RequestOptions::idempotent();
+
+    let error = get(&server, "/loop-a", &options).await.unwrap_err();
+    assert!(matches!(
+        RequestError::of(&error),
+        Some(RequestError::RedirectLoop(uri)) if uri.path() == "/loop-a"
+    ));
+
+    let error = get(&server, "/insecure", &options).await.unwrap_err();
+    assert!(matches!(
+        RequestError::of(&error),
+        Some(RequestError::InsecureRedirect(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn redirects_are_limited() -> Result<()> {
+    let server = start_server().await?;
+    let options = RequestOptions {
+        max_redirects: 3,
+        ..RequestOptions::idempotent()
+    };
+
+    let res = get(&server, "/chain/2", &options).await?;
+    assert_eq!(paths(&res), ["/chain/2", "/chain/1", "/chain/0", "/final"]);
+
+    let error = get(&server, "/chain/5", &options).await.unwrap_err();
+    assert_eq!(
+        RequestError::of(&error),
+        Some(&RequestError::TooManyRedirects(3))
+    );
+
+    // The hard cap applies whatever the options say
+    let options = RequestOptions {
+        max_redirects: usize::MAX,
+        ..RequestOptions::idempotent()
+    };
+    let error = get(&server, "/chain/100", &options).await.unwrap_err();
+    assert_eq!(
+        RequestError::of(&error),
+        Some(&RequestError::TooManyRedirects(
+            http_request::redirect::MAX_REDIRECTS
+        ))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn redirects_can_be_returned_as_they_are() -> Result<()> {
+    let server = start_server().await?;
+    let options = RequestOptions {
+        max_redirects: 0,
+        ..RequestOptions::idempotent()
+    };
+
+    let res = get(&server, "/relative", &options).await?;
+    assert_eq!(res.status(), StatusCode::FOUND);
+    assert_eq!(res.headers()["location"], "final");
+    assert_eq!(paths(&res), ["/relative"]);
+
+    Ok(())
+}
@@ -48,6 +48,33 @@ const kMiddleClickActionKey = 'middle_click_action';
 /// under specific conditions.
 const kAdaptiveSwitchingKey = 'adaptive_switching';
 
+/// This key is used to enable or disable gapless playback, where the next
+/// track is decoded ahead of time and starts right after the current one,
+/// without silence in between, as live albums and classical recordings need.
+const kGaplessPlaybackKey = 'gapless_playback';
+
+/// This key is used to store how many seconds two tracks overlap while the
+/// outgoing one fades out and the incoming one fades in, 0 turning
+/// crossfading off.
+const kCrossfadeKey = 'crossfade';
+
+/// This key is used to store how the loudness of tracks is normalized during
+/// playback: "off", "track" to play every track at the same loudness, or
+/// "album" to normalize albums as a whole.
+const kLoudnessNormalizationKey = 'loudness_normalization';
+
+/// This key is used to store the loudness, in LUFS, which normalized tracks
+/// are played at.
+const kLoudnessTargetKey = 'loudness_target';
+
+/// This key is used to store the id of the audio device playback happens on,
+/// the default device being used when it is missing.
+const kAudioDeviceKey = 'audio_device';
+
+/// This key is used to store how many files from remote libraries are
+/// downloaded at the same time.
+const kMaxConcurrentDownloadsKey = 'max_concurrent_downloads';
+
 /// This key is used to store the user's preference for the color mode of the
 /// application. This can include options such as "system", "dark", or "light".
 const kColorModeKey = 'color_mode';
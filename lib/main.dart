@@ -28,6 +28,11 @@ import 'utils/update_color_mode.dart';
 import 'utils/theme_color_manager.dart';
 import 'utils/storage_key_manager.dart';
 import 'utils/api/set_adaptive_switching_enabled.dart';
+import 'utils/api/set_gapless_enabled.dart';
+import 'utils/api/set_crossfade.dart';
+import 'utils/api/set_loudness_normalization.dart';
+import 'utils/api/set_audio_device.dart';
+import 'utils/api/set_max_concurrent_downloads.dart';
 import 'utils/api/operate_playback_with_mix_query.dart';
 import 'utils/file_storage/mac_secure_manager.dart';
 import 'utils/macos_window_control_button_manager.dart';
@@ -227,6 +232,11 @@ void main(List<String> arguments) async {
   }
 
   setAdaptiveSwitchingEnabled();
+  setGaplessEnabled();
+  setCrossfade();
+  setLoudnessNormalization();
+  setAudioDevice();
+  setMaxConcurrentDownloads();
 
   mainLoop(licenseProvider, linuxCustomWindowControls);
   if (isDesktop && !Platform.isMacOS) {
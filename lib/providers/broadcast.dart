@@ -172,6 +172,11 @@ class BroadcastProvider extends ChangeNotifier {
     StartServerRequest(
       interface: interface,
       alias: _deviceAlias!,
+      bindAddresses: const [],
+      strict: false,
+      port: 0,
+      wsPort: 0,
+      pathPrefix: '',
     ).sendSignalToRust();
   }
 
@@ -401,7 +401,7 @@ class LibraryManagerProvider with ChangeNotifier {
   }
 
   Future<void> cancelTask(String path, CancelTaskType type) async {
-    CancelTaskRequest(path: path, rType: type).sendSignalToRust();
+    CancelTaskRequest(path: path, rType: type, taskId: null).sendSignalToRust();
   }
 
   @override
@@ -18,6 +18,10 @@ extension LoginRequestItemJson on LoginRequestItem {
       'password': password,
       if (apiKey != "") 'api_key': apiKey,
       if (apiSecret != "") 'api_secret': apiSecret,
+      if (scrobblingEnabled != null) 'scrobbling_enabled': scrobblingEnabled,
+      if (nowPlayingEnabled != null) 'now_playing_enabled': nowPlayingEnabled,
+      if (baseUrl != null) 'base_url': baseUrl,
+      if (allowInsecure != null) 'allow_insecure': allowInsecure,
     };
   }
 }
@@ -29,6 +33,10 @@ LoginRequestItem itemFromMap(Map<String, dynamic> json) {
     password: json['password'] as String,
     apiKey: json['api_key'] as String?,
     apiSecret: json['api_secret'] as String?,
+    scrobblingEnabled: json['scrobbling_enabled'] as bool?,
+    nowPlayingEnabled: json['now_playing_enabled'] as bool?,
+    baseUrl: json['base_url'] as String?,
+    allowInsecure: json['allow_insecure'] as bool?,
   );
 }
 
@@ -37,12 +45,24 @@ class ServiceStatus {
   final bool isAvailable;
   final bool hasCredentials;
   final String error;
+  final bool scrobblingEnabled;
+  final bool nowPlayingEnabled;
+  final bool needsReauthentication;
+  // When the requests started failing, null while they succeed
+  final DateTime? degradedSince;
+  // The server of a Libre.fm compatible service, null for the default one
+  final String? baseUrl;
 
   ServiceStatus({
     required this.serviceId,
     required this.isAvailable,
     required this.hasCredentials,
     required this.error,
+    required this.scrobblingEnabled,
+    required this.nowPlayingEnabled,
+    required this.needsReauthentication,
+    required this.degradedSince,
+    required this.baseUrl,
   });
 
   @override
@@ -53,7 +73,12 @@ class ServiceStatus {
         other.serviceId == serviceId &&
         other.isAvailable == isAvailable &&
         other.hasCredentials == hasCredentials &&
-        other.error == error;
+        other.error == error &&
+        other.scrobblingEnabled == scrobblingEnabled &&
+        other.nowPlayingEnabled == nowPlayingEnabled &&
+        other.needsReauthentication == needsReauthentication &&
+        other.degradedSince == degradedSince &&
+        other.baseUrl == baseUrl;
   }
 
   @override
@@ -61,7 +86,12 @@ class ServiceStatus {
     return serviceId.hashCode ^
         isAvailable.hashCode ^
         hasCredentials.hashCode ^
-        error.hashCode;
+        error.hashCode ^
+        scrobblingEnabled.hashCode ^
+        nowPlayingEnabled.hashCode ^
+        needsReauthentication.hashCode ^
+        degradedSince.hashCode ^
+        baseUrl.hashCode;
   }
 }
 
@@ -157,7 +187,19 @@ class ScrobbleProvider with ChangeNotifier {
     int existingIndex = storedCredentials
         .indexWhere((item) => item.serviceId == credentials.serviceId);
     if (existingIndex != -1) {
-      storedCredentials[existingIndex] = credentials;
+      // Logging in again keeps what is submitted to the service
+      final existing = storedCredentials[existingIndex];
+      storedCredentials[existingIndex] = LoginRequestItem(
+        serviceId: credentials.serviceId,
+        username: credentials.username,
+        password: credentials.password,
+        apiKey: credentials.apiKey,
+        apiSecret: credentials.apiSecret,
+        scrobblingEnabled: existing.scrobblingEnabled,
+        nowPlayingEnabled: existing.nowPlayingEnabled,
+        baseUrl: credentials.baseUrl,
+        allowInsecure: credentials.allowInsecure,
+      );
     } else {
       storedCredentials.add(credentials);
     }
@@ -182,6 +224,40 @@ class ScrobbleProvider with ChangeNotifier {
     await _settingsManager.setValue(credentialsKey, encryptedData);
   }
 
+  Future<void> setServiceFlags(
+    String serviceId, {
+    required bool scrobblingEnabled,
+    required bool nowPlayingEnabled,
+  }) async {
+    SetScrobbleServiceFlagsRequest(
+      serviceId: serviceId,
+      scrobblingEnabled: scrobblingEnabled,
+      nowPlayingEnabled: nowPlayingEnabled,
+    ).sendSignalToRust();
+
+    // The flags are stored with the credentials, to apply on the next login
+    List<LoginRequestItem> storedCredentials = await _getStoredCredentials();
+    storedCredentials = storedCredentials
+        .map((item) => item.serviceId == serviceId
+            ? LoginRequestItem(
+                serviceId: item.serviceId,
+                username: item.username,
+                password: item.password,
+                apiKey: item.apiKey,
+                apiSecret: item.apiSecret,
+                scrobblingEnabled: scrobblingEnabled,
+                nowPlayingEnabled: nowPlayingEnabled,
+                baseUrl: item.baseUrl,
+                allowInsecure: item.allowInsecure,
+              )
+            : item)
+        .toList();
+
+    String encryptedData = _encrypt(
+        jsonEncode(storedCredentials.map((item) => item.toMap()).toList()));
+    await _settingsManager.setValue(credentialsKey, encryptedData);
+  }
+
   Future<void> _handleStatusUpdate(
       RustSignalPack<ScrobbleServiceStatusUpdated> signal) async {
     List<LoginRequestItem> storedCredentials = await _getStoredCredentials();
@@ -193,7 +269,16 @@ class ScrobbleProvider with ChangeNotifier {
               serviceId: status.serviceId,
               isAvailable: status.isAvailable,
               hasCredentials: credentialServiceIds.contains(status.serviceId),
-              error: status.error ?? "",
+              // Failures after logging in are shown as well
+              error: status.error ?? status.errorDetail ?? "",
+              scrobblingEnabled: status.scrobblingEnabled,
+              nowPlayingEnabled: status.nowPlayingEnabled,
+              needsReauthentication: status.needsReauthentication,
+              degradedSince: status.degradedSince == null
+                  ? null
+                  : DateTime.fromMillisecondsSinceEpoch(
+                      status.degradedSince! * 1000),
+              baseUrl: status.baseUrl,
             ))
         .toList();
 
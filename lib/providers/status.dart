@@ -24,6 +24,7 @@ class PlaybackStatusState {
   int? playbackMode;
   String? coverArtPath;
   String? libPath;
+  String? unavailableAudioDevice;
 
   PlaybackStatusState({
     this.state = "Stopped",
@@ -39,6 +40,7 @@ class PlaybackStatusState {
     this.playbackMode,
     this.coverArtPath,
     this.libPath,
+    this.unavailableAudioDevice,
   });
 
   PlaybackStatusState.from(PlaybackStatusState other)
@@ -54,7 +56,8 @@ class PlaybackStatusState {
         item = other.item,
         playbackMode = other.playbackMode,
         coverArtPath = other.coverArtPath,
-        libPath = other.libPath;
+        libPath = other.libPath,
+        unavailableAudioDevice = other.unavailableAudioDevice;
 
   // Update from PlaybackStatus (machine generated)
   void updateFrom(PlaybackStatus newStatus) {
@@ -71,6 +74,7 @@ class PlaybackStatusState {
     playbackMode = newStatus.playbackMode;
     coverArtPath = newStatus.coverArtPath;
     libPath = newStatus.libPath;
+    unavailableAudioDevice = newStatus.unavailableAudioDevice;
   }
 
   @override
@@ -90,7 +94,8 @@ class PlaybackStatusState {
         item == other.item &&
         playbackMode == other.playbackMode &&
         coverArtPath == other.coverArtPath &&
-        libPath == other.libPath;
+        libPath == other.libPath &&
+        unavailableAudioDevice == other.unavailableAudioDevice;
   }
 
   @override
@@ -108,6 +113,7 @@ class PlaybackStatusState {
         playbackMode,
         coverArtPath,
         libPath,
+        unavailableAudioDevice,
       );
 }
 
@@ -155,6 +161,8 @@ class PlaybackStatusProvider with ChangeNotifier {
       final bool isNewTrack = _playbackStatusState.item != newStatus.item;
 
       _playbackStatusState.state = newStatus.state;
+      _playbackStatusState.unavailableAudioDevice =
+          newStatus.unavailableAudioDevice;
 
       if (newStatus.state != "Stopped" ||
           _playbackStatusState.libPath != newStatus.libPath) {
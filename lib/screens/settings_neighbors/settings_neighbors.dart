@@ -18,6 +18,7 @@ import 'widgets/settings_tile_title.dart';
 import 'widgets/search_remote_device_setting_button.dart';
 import 'widgets/add_neighbor_manually_setting_button.dart';
 import 'widgets/edit_device_information_setting_button.dart';
+import 'widgets/max_concurrent_downloads_setting.dart';
 
 class SettingsNeighborsPage extends StatefulWidget {
   const SettingsNeighborsPage({super.key});
@@ -69,6 +70,7 @@ class _SettingsNeighborsPageState extends State<SettingsNeighborsPage> {
                     navigateIfFailed: true,
                   ),
                   const EditDeviceInformationSettingButton(),
+                  const MaxConcurrentDownloadsSetting(),
                   const SizedBox(height: 2),
                   SizedBox(
                     width: double.maxFinite,
@@ -0,0 +1,63 @@
+import 'package:fluent_ui/fluent_ui.dart';
+
+import '../../../utils/l10n.dart';
+import '../../../utils/api/set_max_concurrent_downloads.dart';
+import '../../../widgets/settings/settings_box_combo_box.dart';
+import '../../../constants/configurations.dart';
+import '../../../constants/settings_manager.dart';
+
+const _maxConcurrentDownloadsOptions = [1, 2, 3, 4, 5];
+
+class MaxConcurrentDownloadsSetting extends StatefulWidget {
+  const MaxConcurrentDownloadsSetting({super.key});
+
+  @override
+  MaxConcurrentDownloadsSettingState createState() =>
+      MaxConcurrentDownloadsSettingState();
+}
+
+class MaxConcurrentDownloadsSettingState
+    extends State<MaxConcurrentDownloadsSetting> {
+  int maxConcurrentDownloads = 3;
+
+  @override
+  void initState() {
+    super.initState();
+    _loadMaxConcurrentDownloads();
+  }
+
+  Future<void> _loadMaxConcurrentDownloads() async {
+    final storedMaxConcurrentDownloads =
+        await $settingsManager.getValue<int>(kMaxConcurrentDownloadsKey);
+    setState(() {
+      maxConcurrentDownloads = storedMaxConcurrentDownloads ?? 3;
+    });
+  }
+
+  Future<void> _updateMaxConcurrentDownloads(int newLimit) async {
+    setState(() {
+      maxConcurrentDownloads = newLimit;
+    });
+    await $settingsManager.setValue(kMaxConcurrentDownloadsKey, newLimit);
+    setMaxConcurrentDownloads();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    final s = S.of(context);
+
+    return SettingsBoxComboBox(
+      title: s.maxConcurrentDownloads,
+      subtitle: s.maxConcurrentDownloadsSubtitle,
+      value: maxConcurrentDownloads,
+      items: _maxConcurrentDownloadsOptions
+          .map((x) => SettingsBoxComboBoxItem(value: x, title: x.toString()))
+          .toList(),
+      onChanged: (newValue) {
+        if (newValue != null) {
+          _updateMaxConcurrentDownloads(newValue);
+        }
+      },
+    );
+  }
+}
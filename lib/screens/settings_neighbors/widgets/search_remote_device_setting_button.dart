@@ -109,7 +109,7 @@ class SearchRemoteDeviceSettingButton extends StatelessWidget {
         if (!context.mounted) return;
         libraryPath.addLibraryPath(
           context,
-          '@RR|${encodeRnSrvUrl(device.ips)}',
+          '@RR|${encodeRnSrvUrl(device.ips, port: device.apiPort)}',
           device.alias,
         );
       },
@@ -9,6 +9,11 @@ import '../../widgets/navigation_bar/page_content_frame.dart';
 import 'widgets/queue_mode_setting.dart';
 import 'widgets/playback_mode_setting.dart';
 import 'widgets/adaptive_switching_setting.dart';
+import 'widgets/gapless_playback_setting.dart';
+import 'widgets/crossfade_setting.dart';
+import 'widgets/audio_device_setting.dart';
+import 'widgets/loudness_target_setting.dart';
+import 'widgets/loudness_normalization_setting.dart';
 import 'widgets/middle_click_action_setting.dart';
 
 class SettingsPlayback extends StatefulWidget {
@@ -35,6 +40,11 @@ class _SettingsPlaybackState extends State<SettingsPlayback> {
                 MiddleClickActionSetting(),
                 PlaybackModeSetting(),
                 AdaptiveSwitchingSetting(),
+                GaplessPlaybackSetting(),
+                CrossfadeSetting(),
+                LoudnessNormalizationSetting(),
+                LoudnessTargetSetting(),
+                AudioDeviceSetting(),
                 Padding(
                   padding:
                       EdgeInsets.only(top: 8, bottom: 2, left: 6, right: 6),
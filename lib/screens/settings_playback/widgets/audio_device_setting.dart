@@ -0,0 +1,89 @@
+import 'package:provider/provider.dart';
+import 'package:fluent_ui/fluent_ui.dart';
+
+import '../../../utils/l10n.dart';
+import '../../../utils/api/set_audio_device.dart';
+import '../../../utils/api/list_audio_devices.dart';
+import '../../../widgets/settings/settings_box_combo_box.dart';
+import '../../../providers/status.dart';
+import '../../../bindings/bindings.dart';
+import '../../../constants/configurations.dart';
+import '../../../constants/settings_manager.dart';
+
+class AudioDeviceSetting extends StatefulWidget {
+  const AudioDeviceSetting({super.key});
+
+  @override
+  AudioDeviceSettingState createState() => AudioDeviceSettingState();
+}
+
+class AudioDeviceSettingState extends State<AudioDeviceSetting> {
+  // An empty id stands for the default device
+  String audioDevice = "";
+  List<AudioDevice> devices = [];
+
+  @override
+  void initState() {
+    super.initState();
+    _loadAudioDevice();
+  }
+
+  Future<void> _loadAudioDevice() async {
+    final storedAudioDevice =
+        await $settingsManager.getValue<String>(kAudioDeviceKey);
+    final availableDevices = await listAudioDevices();
+    if (!mounted) return;
+
+    setState(() {
+      audioDevice = storedAudioDevice ?? "";
+      devices = availableDevices;
+    });
+  }
+
+  Future<void> _updateAudioDevice(String newId) async {
+    setState(() {
+      audioDevice = newId;
+    });
+    if (newId.isEmpty) {
+      await $settingsManager.removeValue(kAudioDeviceKey);
+    } else {
+      await $settingsManager.setValue(kAudioDeviceKey, newId);
+    }
+    setAudioDevice();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    final s = S.of(context);
+    final unavailableDevice = context.select<PlaybackStatusProvider, String?>(
+      (x) => x.playbackStatus.unavailableAudioDevice,
+    );
+
+    return SettingsBoxComboBox(
+      title: s.audioDevice,
+      subtitle: unavailableDevice == null
+          ? s.audioDeviceSubtitle
+          : s.audioDeviceUnavailable(unavailableDevice),
+      value: audioDevice,
+      items: [
+        SettingsBoxComboBoxItem(value: "", title: s.defaultAudioDevice),
+        // The chosen device stays listed while it is unplugged
+        if (audioDevice.isNotEmpty &&
+            devices.every((x) => x.id != audioDevice))
+          SettingsBoxComboBoxItem(value: audioDevice, title: audioDevice),
+        // Ids are the device names, numbered when several devices share one
+        ...devices.map(
+          (x) => SettingsBoxComboBoxItem(
+            value: x.id,
+            title: x.isDefault ? s.defaultAudioDeviceName(x.id) : x.id,
+          ),
+        ),
+      ],
+      onChanged: (newValue) {
+        if (newValue != null) {
+          _updateAudioDevice(newValue);
+        }
+      },
+    );
+  }
+}
@@ -0,0 +1,66 @@
+import 'package:fluent_ui/fluent_ui.dart';
+
+import '../../../utils/l10n.dart';
+import '../../../utils/api/set_crossfade.dart';
+import '../../../widgets/settings/settings_box_combo_box.dart';
+import '../../../constants/configurations.dart';
+import '../../../constants/settings_manager.dart';
+
+const _crossfadeOptions = [2, 4, 6, 8, 10, 12];
+
+class CrossfadeSetting extends StatefulWidget {
+  const CrossfadeSetting({super.key});
+
+  @override
+  CrossfadeSettingState createState() => CrossfadeSettingState();
+}
+
+class CrossfadeSettingState extends State<CrossfadeSetting> {
+  int crossfade = 0;
+
+  @override
+  void initState() {
+    super.initState();
+    _loadCrossfade();
+  }
+
+  Future<void> _loadCrossfade() async {
+    final storedCrossfade = await $settingsManager.getValue<int>(kCrossfadeKey);
+    setState(() {
+      crossfade = storedCrossfade ?? 0;
+    });
+  }
+
+  Future<void> _updateCrossfade(int newSeconds) async {
+    setState(() {
+      crossfade = newSeconds;
+    });
+    await $settingsManager.setValue(kCrossfadeKey, newSeconds);
+    setCrossfade();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    final s = S.of(context);
+
+    return SettingsBoxComboBox(
+      title: s.crossfade,
+      subtitle: s.crossfadeSubtitle,
+      value: crossfade,
+      items: [
+        SettingsBoxComboBoxItem(value: 0, title: s.crossfadeOff),
+        ..._crossfadeOptions.map(
+          (x) => SettingsBoxComboBoxItem(
+            value: x,
+            title: s.crossfadeSeconds(x),
+          ),
+        ),
+      ],
+      onChanged: (newValue) {
+        if (newValue != null) {
+          _updateCrossfade(newValue);
+        }
+      },
+    );
+  }
+}
@@ -0,0 +1,52 @@
+import 'package:fluent_ui/fluent_ui.dart';
+
+import '../../../utils/l10n.dart';
+import '../../../utils/api/set_gapless_enabled.dart';
+import '../../../widgets/settings/settings_box_toggle.dart';
+import '../../../constants/configurations.dart';
+import '../../../constants/settings_manager.dart';
+
+class GaplessPlaybackSetting extends StatefulWidget {
+  const GaplessPlaybackSetting({super.key});
+
+  @override
+  GaplessPlaybackSettingState createState() => GaplessPlaybackSettingState();
+}
+
+class GaplessPlaybackSettingState extends State<GaplessPlaybackSetting> {
+  bool gaplessPlayback = false;
+
+  @override
+  void initState() {
+    super.initState();
+    _loadGaplessPlayback();
+  }
+
+  Future<void> _loadGaplessPlayback() async {
+    final storedGaplessPlayback =
+        await $settingsManager.getValue<bool>(kGaplessPlaybackKey);
+    setState(() {
+      gaplessPlayback = storedGaplessPlayback ?? false;
+    });
+  }
+
+  Future<void> _updateGaplessPlayback(bool newSetting) async {
+    setState(() {
+      gaplessPlayback = newSetting;
+    });
+    await $settingsManager.setValue(kGaplessPlaybackKey, newSetting);
+    setGaplessEnabled();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    final s = S.of(context);
+
+    return SettingsBoxToggle(
+      title: s.gaplessPlayback,
+      subtitle: s.gaplessPlaybackSubtitle,
+      value: gaplessPlayback,
+      onChanged: _updateGaplessPlayback,
+    );
+  }
+}
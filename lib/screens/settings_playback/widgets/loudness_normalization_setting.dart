@@ -0,0 +1,66 @@
+import 'package:fluent_ui/fluent_ui.dart';
+
+import '../../../utils/l10n.dart';
+import '../../../utils/api/set_loudness_normalization.dart';
+import '../../../widgets/settings/settings_box_combo_box.dart';
+import '../../../constants/configurations.dart';
+import '../../../constants/settings_manager.dart';
+
+class LoudnessNormalizationSetting extends StatefulWidget {
+  const LoudnessNormalizationSetting({super.key});
+
+  @override
+  LoudnessNormalizationSettingState createState() =>
+      LoudnessNormalizationSettingState();
+}
+
+class LoudnessNormalizationSettingState
+    extends State<LoudnessNormalizationSetting> {
+  String loudnessNormalization = "off";
+
+  @override
+  void initState() {
+    super.initState();
+    _loadLoudnessNormalization();
+  }
+
+  Future<void> _loadLoudnessNormalization() async {
+    final storedLoudnessNormalization =
+        await $settingsManager.getValue<String>(kLoudnessNormalizationKey);
+    setState(() {
+      loudnessNormalization = storedLoudnessNormalization ?? "off";
+    });
+  }
+
+  Future<void> _updateLoudnessNormalization(String newMode) async {
+    setState(() {
+      loudnessNormalization = newMode;
+    });
+    await $settingsManager.setValue(kLoudnessNormalizationKey, newMode);
+    setLoudnessNormalization();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    final s = S.of(context);
+
+    return SettingsBoxComboBox(
+      title: s.loudnessNormalization,
+      subtitle: s.loudnessNormalizationSubtitle,
+      value: loudnessNormalization,
+      items: [
+        SettingsBoxComboBoxItem(
+          value: "off",
+          title: s.loudnessNormalizationOff,
+        ),
+        SettingsBoxComboBoxItem(value: "track", title: s.trackGain),
+        SettingsBoxComboBoxItem(value: "album", title: s.albumGain),
+      ],
+      onChanged: (newValue) {
+        if (newValue != null) {
+          _updateLoudnessNormalization(newValue);
+        }
+      },
+    );
+  }
+}
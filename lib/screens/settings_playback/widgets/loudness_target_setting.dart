@@ -0,0 +1,61 @@
+import 'package:fluent_ui/fluent_ui.dart';
+
+import '../../../utils/l10n.dart';
+import '../../../utils/api/set_loudness_normalization.dart';
+import '../../../widgets/settings/settings_box_combo_box.dart';
+import '../../../constants/configurations.dart';
+import '../../../constants/settings_manager.dart';
+
+const _loudnessTargets = [-23, -18, -16, -14];
+
+class LoudnessTargetSetting extends StatefulWidget {
+  const LoudnessTargetSetting({super.key});
+
+  @override
+  LoudnessTargetSettingState createState() => LoudnessTargetSettingState();
+}
+
+class LoudnessTargetSettingState extends State<LoudnessTargetSetting> {
+  int loudnessTarget = -18;
+
+  @override
+  void initState() {
+    super.initState();
+    _loadLoudnessTarget();
+  }
+
+  Future<void> _loadLoudnessTarget() async {
+    final storedLoudnessTarget =
+        await $settingsManager.getValue<int>(kLoudnessTargetKey);
+    setState(() {
+      loudnessTarget = storedLoudnessTarget ?? -18;
+    });
+  }
+
+  Future<void> _updateLoudnessTarget(int newTarget) async {
+    setState(() {
+      loudnessTarget = newTarget;
+    });
+    await $settingsManager.setValue(kLoudnessTargetKey, newTarget);
+    setLoudnessNormalization();
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    final s = S.of(context);
+
+    return SettingsBoxComboBox(
+      title: s.loudnessTarget,
+      subtitle: s.loudnessTargetSubtitle,
+      value: loudnessTarget,
+      items: _loudnessTargets
+          .map((x) => SettingsBoxComboBoxItem(value: x, title: s.lufs(x)))
+          .toList(),
+      onChanged: (newValue) {
+        if (newValue != null) {
+          _updateLoudnessTarget(newValue);
+        }
+      },
+    );
+  }
+}
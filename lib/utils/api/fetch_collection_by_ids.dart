@@ -4,12 +4,14 @@ import '../../bindings/bindings.dart';
 
 Future<List<Collection>> fetchCollectionByIds(
   CollectionType collectionType,
-  List<int> ids,
-) async {
+  List<int> ids, {
+  bool bypassCache = false,
+}) async {
   final request = FetchCollectionByIdsRequest(
     ids: ids,
     collectionType: collectionType,
     bakeCoverArts: true,
+    bypassCache: bypassCache,
   );
   request.sendSignalToRust(); // GENERATED
 
@@ -3,10 +3,13 @@ import 'dart:async';
 import '../../bindings/bindings.dart';
 
 Future<List<CollectionGroupSummary>> fetchCollectionGroupSummary(
-  CollectionType collectionType,
-) async {
-  final request =
-      FetchCollectionGroupSummaryRequest(collectionType: collectionType);
+  CollectionType collectionType, {
+  bool bypassCache = false,
+}) async {
+  final request = FetchCollectionGroupSummaryRequest(
+    collectionType: collectionType,
+    bypassCache: bypassCache,
+  );
   request.sendSignalToRust(); // GENERATED
 
   return (await CollectionGroupSummaryResponse.rustSignalStream.first)
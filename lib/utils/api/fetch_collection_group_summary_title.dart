@@ -1,9 +1,13 @@
 import '../../bindings/bindings.dart';
 
 Future<List<String>> fetchCollectionGroupSummaryTitle(
-    CollectionType collectionType) async {
-  final fetchGroupsRequest =
-      FetchCollectionGroupSummaryRequest(collectionType: collectionType);
+  CollectionType collectionType, {
+  bool bypassCache = false,
+}) async {
+  final fetchGroupsRequest = FetchCollectionGroupSummaryRequest(
+    collectionType: collectionType,
+    bypassCache: bypassCache,
+  );
   fetchGroupsRequest.sendSignalToRust(); // GENERATED
 
   // Listen for the response from Rust
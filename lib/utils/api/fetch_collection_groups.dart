@@ -2,12 +2,14 @@ import '../../bindings/bindings.dart';
 
 Future<List<CollectionGroup>> fetchCollectionGroups(
   CollectionType collectionType,
-  List<String> groupTitles,
-) async {
+  List<String> groupTitles, {
+  bool bypassCache = false,
+}) async {
   final fetchGroupsRequest = FetchCollectionGroupsRequest(
     collectionType: collectionType,
     groupTitles: groupTitles,
     bakeCoverArts: true,
+    bypassCache: bypassCache,
   );
   fetchGroupsRequest.sendSignalToRust(); // GENERATED
 
@@ -5,11 +5,13 @@ import '../../bindings/bindings.dart';
 
 Future<List<InternalMediaFile>> fetchMediaFileByIds(
   List<int> ids,
-  bool bakeCoverArts,
-) async {
+  bool bakeCoverArts, {
+  bool bypassCache = false,
+}) async {
   final request = FetchMediaFileByIdsRequest(
     ids: ids,
     bakeCoverArts: true,
+    bypassCache: bypassCache,
   );
   request.sendSignalToRust(); // GENERATED
 
@@ -3,12 +3,14 @@ import '../../widgets/track_list/utils/internal_media_file.dart';
 
 Future<List<InternalMediaFile>> fetchMediaFiles(
   int cursor,
-  int pageSize,
-) async {
+  int pageSize, {
+  bool bypassCache = false,
+}) async {
   final fetchMediaFiles = FetchMediaFilesRequest(
     cursor: cursor,
     pageSize: pageSize,
     bakeCoverArts: true,
+    bypassCache: bypassCache,
   );
   fetchMediaFiles.sendSignalToRust(); // GENERATED
 
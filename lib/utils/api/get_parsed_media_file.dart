@@ -1,7 +1,13 @@
 import '../../bindings/bindings.dart';
 
-Future<FetchParsedMediaFileResponse> getParsedMediaFile(int fileId) async {
-  final fetchRequest = FetchParsedMediaFileRequest(id: fileId);
+Future<FetchParsedMediaFileResponse> getParsedMediaFile(
+  int fileId, {
+  bool bypassCache = false,
+}) async {
+  final fetchRequest = FetchParsedMediaFileRequest(
+    id: fileId,
+    bypassCache: bypassCache,
+  );
   fetchRequest.sendSignalToRust(); // GENERATED
 
   final rustSignal = await FetchParsedMediaFileResponse.rustSignalStream.first;
@@ -0,0 +1,9 @@
+import '../../bindings/bindings.dart';
+
+Future<List<AudioDevice>> listAudioDevices() async {
+  ListAudioDevicesRequest().sendSignalToRust();
+
+  return (await ListAudioDevicesResponse.rustSignalStream.first)
+      .message
+      .devices;
+}
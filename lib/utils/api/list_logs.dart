@@ -2,6 +2,13 @@ import '../../bindings/bindings.dart';
 
 Future<List<LogDetail>> listLogs(int cursor, int pageSize) async {
   final listRequest = ListLogRequest(
+    query: LogQuery(
+      minLevel: null,
+      domain: null,
+      startTime: null,
+      endTime: null,
+      text: null,
+    ),
     cursor: cursor,
     pageSize: pageSize,
   );
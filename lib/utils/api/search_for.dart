@@ -7,7 +7,8 @@ Future<SearchForResponse> searchFor(String query) async {
     query = '$query*';
   }
 
-  final searchRequest = SearchForRequest(queryStr: query, n: 30, fields: []);
+  final searchRequest = SearchForRequest(
+      queryStr: query, n: 30, fields: [], includeLyrics: false);
   searchRequest.sendSignalToRust();
 
   return (await SearchForResponse.rustSignalStream.first).message;
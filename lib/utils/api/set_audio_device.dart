@@ -0,0 +1,10 @@
+import '../../bindings/bindings.dart';
+import '../../constants/configurations.dart';
+
+import '../settings_manager.dart';
+
+void setAudioDevice() async {
+  final id = await SettingsManager().getValue<String?>(kAudioDeviceKey);
+
+  SetAudioDeviceRequest(id: id).sendSignalToRust();
+}
@@ -0,0 +1,10 @@
+import '../../bindings/bindings.dart';
+import '../../constants/configurations.dart';
+
+import '../settings_manager.dart';
+
+void setCrossfade() async {
+  final seconds = await SettingsManager().getValue<int?>(kCrossfadeKey) ?? 0;
+
+  SetCrossfadeRequest(seconds: seconds.toDouble()).sendSignalToRust();
+}
@@ -0,0 +1,11 @@
+import '../../bindings/bindings.dart';
+import '../../constants/configurations.dart';
+
+import '../settings_manager.dart';
+
+void setGaplessEnabled() async {
+  final enabled =
+      await SettingsManager().getValue<bool?>(kGaplessPlaybackKey) == true;
+
+  SetGaplessEnabledRequest(enabled: enabled).sendSignalToRust();
+}
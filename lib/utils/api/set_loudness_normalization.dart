@@ -0,0 +1,19 @@
+import '../../bindings/bindings.dart';
+import '../../constants/configurations.dart';
+
+import '../settings_manager.dart';
+
+void setLoudnessNormalization() async {
+  final settingsManager = SettingsManager();
+  final mode =
+      await settingsManager.getValue<String?>(kLoudnessNormalizationKey) ??
+          'off';
+  final targetLoudness =
+      await settingsManager.getValue<int?>(kLoudnessTargetKey) ?? -18;
+
+  SetLoudnessNormalizationRequest(
+    enabled: mode != 'off',
+    targetLoudness: targetLoudness.toDouble(),
+    albumGain: mode == 'album',
+  ).sendSignalToRust();
+}
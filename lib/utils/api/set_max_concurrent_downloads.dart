@@ -0,0 +1,11 @@
+import '../../bindings/bindings.dart';
+import '../../constants/configurations.dart';
+
+import '../settings_manager.dart';
+
+void setMaxConcurrentDownloads() async {
+  final limit =
+      await SettingsManager().getValue<int?>(kMaxConcurrentDownloadsKey) ?? 3;
+
+  SetMaxConcurrentDownloadsRequest(limit: limit).sendSignalToRust();
+}
@@ -202,8 +202,8 @@ class _MixEditorState extends State<MixEditor> {
 }
 
 Future<Map<String, List<int>>> searchFor(String query, String field) async {
-  final searchRequest =
-      SearchForRequest(queryStr: query, fields: [field], n: 30);
+  final searchRequest = SearchForRequest(
+      queryStr: query, fields: [field], n: 30, includeLyrics: false);
   searchRequest.sendSignalToRust();
 
   final message = (await SearchForResponse.rustSignalStream.first).message;
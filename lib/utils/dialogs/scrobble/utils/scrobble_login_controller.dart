@@ -22,6 +22,10 @@ class ScrobbleLoginController {
       password: passwordController.text,
       apiKey: apiKeyController.text,
       apiSecret: apiSecretController.text,
+      scrobblingEnabled: null,
+      nowPlayingEnabled: null,
+      baseUrl: null,
+      allowInsecure: null,
     );
   }
 }
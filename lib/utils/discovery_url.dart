@@ -41,20 +41,43 @@ String _decodeIPv4(String s) {
   return octets.join('.');
 }
 
-String encodeRnSrvUrl(List<String> ips) {
-  final buffer = StringBuffer('rnsrv://');
+const int defaultApiPort = 7863;
+const int rnSrvProtocolVersion = 1;
+
+/// Encodes the IPv4 addresses of a server. Servers on the default port use
+/// the compact legacy layout, others the `rnsrv://v<version>-<ip>.<port>`
+/// layout understood by the hub.
+String encodeRnSrvUrl(List<String> ips, {int port = defaultApiPort}) {
+  if (port == defaultApiPort) {
+    final buffer = StringBuffer('rnsrv://');
+    for (final ip in ips) {
+      buffer.write(_encodeIPv4(ip));
+    }
+    return buffer.toString();
+  }
+
+  final buffer = StringBuffer('rnsrv://v$rnSrvProtocolVersion');
   for (final ip in ips) {
-    buffer.write(_encodeIPv4(ip));
+    buffer.write('-${_encodeIPv4(ip)}.$port');
   }
   return buffer.toString();
 }
 
+/// Decodes the IPv4 addresses of a server URL, ports are not returned.
 List<String> decodeRnSrvUrl(String url) {
   if (!url.startsWith('rnsrv://')) {
     throw FormatException('Invalid runep URL');
   }
 
-  final encoded = url.substring(7);
+  final encoded = url.substring(8);
+  if (encoded.startsWith('v')) {
+    return encoded
+        .split('-')
+        .skip(1)
+        .map((entry) => _decodeIPv4(entry.split('.').first))
+        .toList();
+  }
+
   if (encoded.length % 7 != 0) {
     throw FormatException('Invalid encoded IP sequence');
   }
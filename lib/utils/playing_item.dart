@@ -55,6 +55,9 @@ class PlayingItem {
         str.endsWith(')')) {
       final path = str.substring(28, str.length - 1);
       return PlayingItem.independentFile(path);
+    } else if (str.startsWith('PlayingItem::Online(')) {
+      // Streamed items are only played by remote output devices
+      return PlayingItem.unknown();
     } else if (str == 'PlayingItem::Unknown()') {
       return PlayingItem.unknown();
     }
@@ -80,16 +83,19 @@ extension PlayingItemExtension on PlayingItem {
       return PlayingItemRequest(
         inLibrary: InLibraryPlayingItem(fileId: inLibrary!),
         independentFile: null,
+        online: null,
       );
     } else if (independentFile != null) {
       return PlayingItemRequest(
         inLibrary: null,
         independentFile: IndependentFilePlayingItem(rawPath: independentFile!),
+        online: null,
       );
     } else {
       return PlayingItemRequest(
         inLibrary: null,
         independentFile: null,
+        online: null,
       );
     }
   }
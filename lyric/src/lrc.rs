@@ -70,18 +70,10 @@ pub fn parse_lrc(content: &str) -> Result<LyricFile> {
                 // Parse enhanced format word-level time tags
                 let word_time_tags = if remaining_content.contains('<') {
                     parse_enhanced_lrc(remaining_content).unwrap_or_else(|_| {
-                        vec![(
-                            start_time.clone(),
-                            DUMMY_END_TIME,
-                            text.to_string(),
-                        )]
+                        vec![(start_time.clone(), DUMMY_END_TIME, text.to_string())]
                     })
                 } else {
-                    vec![(
-                        start_time.clone(),
-                        DUMMY_END_TIME,
-                        text.to_string(),
-                    )]
+                    vec![(start_time.clone(), DUMMY_END_TIME, text.to_string())]
                 };
 
                 lrc.lyrics.push(LyricLine {
@@ -136,6 +128,49 @@ fn parse_enhanced_lrc(content: &str) -> Result<Vec<(TimeTag, TimeTag, String)>>
     Ok(word_time_tags)
 }
 
+fn format_time_tag(tag: &TimeTag, open: char, close: char) -> String {
+    format!(
+        "{open}{:02}:{:02}.{:02}{close}",
+        tag.minutes,
+        tag.seconds,
+        tag.milliseconds / 10
+    )
+}
+
+/// Serializes lyrics to LRC. Word time tags are written in the enhanced
+/// format when the text of a line doesn't carry them already.
+pub fn write_lrc(lyric: &LyricFile) -> String {
+    let mut content = String::new();
+
+    let mut keys: Vec<&String> = lyric.metadata.keys().collect();
+    keys.sort();
+    for key in keys {
+        content.push_str(&format!("[{key}:{}]\n", lyric.metadata[key]));
+    }
+
+    for line in &lyric.lyrics {
+        content.push_str(&format_time_tag(&line.start_time, '[', ']'));
+        content.push_str(match line.voice_type {
+            VoiceType::Male => "M: ",
+            VoiceType::Female => "F: ",
+            VoiceType::Duet => "D: ",
+            VoiceType::Default => "",
+        });
+
+        if line.word_time_tags.len() > 1 && !line.text.contains('<') {
+            for (start_time, _, word) in &line.word_time_tags {
+                content.push_str(&format_time_tag(start_time, '<', '>'));
+                content.push_str(word);
+            }
+        } else {
+            content.push_str(&line.text);
+        }
+        content.push('\n');
+    }
+
+    content
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,4 +224,23 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_lrc_writer_round_trip() -> Result<()> {
+        let lrc_content = r#"[ar:Chubby Checker]
+[ti:Let's Twist Again]
+[00:12.00]First line
+[00:15.30]F: Female line
+[00:24.00]<00:24.00>Word <00:24.50>by <00:25.00>word
+"#;
+
+        let lrc_file = parse_lrc(lrc_content)?;
+        let written = write_lrc(&lrc_file);
+        assert_eq!(written, lrc_content);
+
+        let reparsed = parse_lrc(&written)?;
+        assert_eq!(reparsed.lyrics, lrc_file.lyrics);
+
+        Ok(())
+    }
 }
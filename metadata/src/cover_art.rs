@@ -1,7 +1,11 @@
+use std::fmt;
+use std::io::Cursor;
 use std::path::Path;
+use std::str::FromStr;
 
-use anyhow::{Context, Result};
-use image::{GenericImageView, ImageBuffer, Pixel};
+use anyhow::{Context, Result, anyhow, bail};
+use image::codecs::jpeg::JpegEncoder;
+use image::{GenericImageView, ImageBuffer, ImageFormat, Pixel};
 use lofty::file::TaggedFileExt;
 use log::{error, info};
 use palette_extract::{Color, get_palette_rgb};
@@ -235,25 +239,132 @@ fn fallback_to_external_cover(fsio: &FsIo, lib_path: &Path, file_path: &Path) ->
 fn process_external_cover(fsio: &FsIo, cover_path: &Path) -> Option<CoverArt> {
     let cover_data = fsio.read(cover_path).ok()?;
 
+    cover_art_from_image(cover_data).ok()
+}
+
+/// Builds a cover art from the data of an image file, hashed as the cover
+/// arts read from the tracks so identical images are stored once.
+pub fn cover_art_from_image(cover_data: Vec<u8>) -> Result<CoverArt> {
     if cover_data.is_empty() {
-        return None;
+        bail!("The image is empty");
     }
 
-    let rgb_sequence = decode_image(&cover_data).ok()?;
+    let rgb_sequence = decode_image(&cover_data).context("Failed to decode the image")?;
 
     // Calculate the CRC
     let crc = media_crc32(&rgb_sequence, 0, 0, rgb_sequence.len());
     let primary_color = get_palette_rgb(&rgb_sequence)[0];
 
     if crc == 0 {
-        return None;
+        bail!("Invalid CRC for cover art");
     }
 
     let crc_string = format!("{crc:08x}");
 
-    Some(CoverArt {
+    Ok(CoverArt {
         crc: crc_string,
         data: cover_data,
         primary_color: color_to_int(&primary_color),
     })
 }
+
+/// The usual file extension of the image format of a cover art.
+pub fn cover_art_extension(data: &[u8]) -> Option<&'static str> {
+    image::guess_format(data)
+        .ok()
+        .and_then(|format| format.extensions_str().first().copied())
+}
+
+/// The quality JPEG covers are encoded at before being embedded.
+const EMBED_JPEG_QUALITY: u8 = 90;
+
+/// The image format cover arts are embedded into the tracks as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbedFormat {
+    Jpeg,
+    Png,
+}
+
+impl FromStr for EmbedFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(EmbedFormat::Jpeg),
+            "png" => Ok(EmbedFormat::Png),
+            _ => Err(anyhow!("Invalid image format {s}, expected jpeg or png")),
+        }
+    }
+}
+
+impl fmt::Display for EmbedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            EmbedFormat::Jpeg => "jpeg",
+            EmbedFormat::Png => "png",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// How a cover art is shrunk before being embedded into the tracks, so a
+/// large scan doesn't grow every file of an album by megabytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbedOptions {
+    /// The longest side of the embedded image, in pixels
+    pub max_dimension: u32,
+    pub format: EmbedFormat,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        Self {
+            max_dimension: 1000,
+            format: EmbedFormat::Jpeg,
+        }
+    }
+}
+
+/// Prepares an image to be embedded into the tracks. Images already small
+/// enough and in the right format are kept as they are, so embedding them
+/// twice gives the same bytes and is skipped.
+pub fn prepare_embedded_image(data: &[u8], options: &EmbedOptions) -> Result<Vec<u8>> {
+    if options.max_dimension == 0 {
+        bail!("The maximum dimension of embedded images must be positive");
+    }
+
+    let format = match options.format {
+        EmbedFormat::Jpeg => ImageFormat::Jpeg,
+        EmbedFormat::Png => ImageFormat::Png,
+    };
+    let image = image::load_from_memory(data).context("Failed to decode the image")?;
+    let fits = image.width() <= options.max_dimension && image.height() <= options.max_dimension;
+    if fits && image::guess_format(data).ok() == Some(format) {
+        return Ok(data.to_vec());
+    }
+
+    let image = if fits {
+        image
+    } else {
+        image.resize(
+            options.max_dimension,
+            options.max_dimension,
+            image::imageops::FilterType::Lanczos3,
+        )
+    };
+
+    let mut encoded = Cursor::new(Vec::new());
+    match options.format {
+        EmbedFormat::Jpeg => {
+            // JPEG has no alpha channel
+            JpegEncoder::new_with_quality(&mut encoded, EMBED_JPEG_QUALITY)
+                .encode_image(&image.into_rgb8())
+                .context("Failed to encode the image")?;
+        }
+        EmbedFormat::Png => image
+            .write_to(&mut encoded, ImageFormat::Png)
+            .context("Failed to encode the image")?,
+    }
+
+    Ok(encoded.into_inner())
+}
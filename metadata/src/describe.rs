@@ -125,9 +125,12 @@ pub fn describe_file(fs_node: &FsNode, lib_path: &Option<PathBuf>) -> Result<Fil
         .map(String::from)
         .unwrap_or_else(|| String::from(""));
 
-    // Get last modified time
-    let metadata = file_path.metadata()?;
-    let last_modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+    // Get last modified time, only stat the file if the walk could not tell
+    let last_modified = match fs_node.modified {
+        Some(x) => x,
+        None => file_path.metadata()?.modified()?,
+    };
+    let last_modified = last_modified.duration_since(UNIX_EPOCH)?.as_secs();
     let last_modified = format!("{last_modified}");
 
     Ok(FileDescription {
@@ -1,7 +1,10 @@
-use fsio::{FsIo, FsNode};
+use fsio::{FsIo, FsNode, FsNodeStream};
+use futures::StreamExt;
+use log::warn;
 use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
 
-fn is_audio_file(entry: &FsNode) -> bool {
+pub fn is_audio_file(entry: &FsNode) -> bool {
     if let Some(ext) = entry.path.extension() {
         matches!(
             ext.to_str().unwrap_or("").to_lowercase().as_str(),
@@ -12,42 +15,59 @@ fn is_audio_file(entry: &FsNode) -> bool {
     }
 }
 
-fn scan_audio_files<P: AsRef<Path>>(
-    fsio: &FsIo,
-    path: &P,
-) -> Result<impl Iterator<Item = FsNode> + Send, fsio::FileIoError> {
-    let files = fsio.walk_dir(path.as_ref(), true)?;
-
-    Ok(files.into_iter().filter(is_audio_file))
+/// Files waiting in the fallback trash are no longer part of the library.
+pub fn is_trashed(entry: &FsNode) -> bool {
+    entry
+        .path
+        .components()
+        .any(|x| x.as_os_str() == fsio::TRASH_DIR)
 }
 
-pub struct AudioScanner<'a> {
+pub struct AudioScanner {
     root_path: PathBuf,
-    iterator: Box<dyn Iterator<Item = FsNode> + Send + 'a>,
+    stream: FsNodeStream,
     ended: bool,
 }
 
-impl<'a> AudioScanner<'a> {
-    pub fn new<P: AsRef<Path> + Send + 'a>(
-        fsio: &'a FsIo,
-        path: &'a P,
+impl AudioScanner {
+    /// Starts walking `path`, files are read from the file system as they
+    /// are requested so the first batch is available right away.
+    pub fn new<P: AsRef<Path>>(
+        fsio: &FsIo,
+        path: &P,
+        cancel_token: Option<CancellationToken>,
     ) -> Result<Self, fsio::FileIoError> {
-        let iterator = scan_audio_files(fsio, path)?;
+        let stream = fsio.walk_dir_stream(path.as_ref(), true, cancel_token)?;
         Ok(AudioScanner {
             root_path: path.as_ref().to_path_buf(),
-            iterator: Box::new(iterator),
+            stream,
             ended: false,
         })
     }
 
-    pub fn read_files(&mut self, count: usize) -> Vec<FsNode> {
+    pub async fn read_files(&mut self, count: usize) -> Vec<FsNode> {
         let mut files = Vec::new();
-        for _ in 0..count {
-            if let Some(file) = self.iterator.next() {
-                files.push(file);
-            } else {
-                self.ended = true;
-                break;
+        while files.len() < count {
+            match self.stream.next().await {
+                Some(Ok(file)) => {
+                    if !is_audio_file(&file) || is_trashed(&file) {
+                        continue;
+                    }
+                    // The library stores paths as text, they must survive the round trip
+                    if file.path.to_str().is_none() {
+                        warn!(
+                            "Skipping {}, its path is not valid UTF-8",
+                            file.path.display()
+                        );
+                        continue;
+                    }
+                    files.push(file);
+                }
+                Some(Err(fsio::FileIoError::Cancelled)) | None => {
+                    self.ended = true;
+                    break;
+                }
+                Some(Err(e)) => warn!("Failed to read a file while scanning: {e}"),
             }
         }
         files
@@ -0,0 +1,253 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::error::LoftyError;
+use lofty::file::{AudioFile, FileType, TaggedFileExt};
+use lofty::flac::FlacFile;
+use lofty::mp4::Mp4File;
+use lofty::mpeg::MpegFile;
+use lofty::ogg::{OpusFile, VorbisFile};
+use lofty::picture::{Picture, PictureType};
+use lofty::probe::Probe;
+use lofty::tag::{ItemKey, MergeTag, SplitTag, Tag, TagExt};
+
+use ::fsio::FsIo;
+
+use crate::describe::describe_file;
+
+/// What writing to the tags of a file did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagWriteOutcome {
+    /// The tags were written. `last_modified` is the new modification time
+    /// of the file, as the scanner records it.
+    Written { last_modified: String },
+    /// The file already holds what would be written, the file is untouched
+    Unchanged,
+    /// Tags are not written to files of this format, the file is untouched
+    NotSupported { format: String },
+}
+
+fn item_key(key: &str) -> Result<ItemKey> {
+    let item_key = match key {
+        "track_title" => ItemKey::TrackTitle,
+        "artist" => ItemKey::TrackArtist,
+        "album" => ItemKey::AlbumTitle,
+        "album_artist" => ItemKey::AlbumArtist,
+        "genre" => ItemKey::Genre,
+        "track_number" => ItemKey::TrackNumber,
+        "disc_number" => ItemKey::DiscNumber,
+        "date" => ItemKey::RecordingDate,
+        "sort_artist" => ItemKey::TrackArtistSortOrder,
+        "sort_album_artist" => ItemKey::AlbumArtistSortOrder,
+        _ => bail!("Metadata field {key} can't be written to files"),
+    };
+
+    Ok(item_key)
+}
+
+/// Writes metadata into the tags of a file, using the metadata keys of the
+/// reader. A tag of the preferred type of the format is created if the file
+/// has none.
+pub async fn write_tags<P: AsRef<Path>>(
+    fsio: &FsIo,
+    file_path: &P,
+    fields: &[(String, String)],
+) -> Result<TagWriteOutcome> {
+    // Checked before touching the file, so no field is half written
+    let fields = fields
+        .iter()
+        .map(|(key, value)| Ok((item_key(key)?, value.clone())))
+        .collect::<Result<Vec<_>>>()?;
+
+    edit_primary_tag(fsio, file_path.as_ref(), |tag| {
+        for (key, value) in fields {
+            tag.insert_text(key, value);
+        }
+
+        Ok(())
+    })
+    .await
+}
+
+/// Embeds an image into the tags of a file as its front cover, replacing
+/// the previous one. Files whose front cover is already this image are left
+/// untouched.
+pub async fn write_cover_art<P: AsRef<Path>>(
+    fsio: &FsIo,
+    file_path: &P,
+    data: &[u8],
+) -> Result<TagWriteOutcome> {
+    let mut picture =
+        Picture::from_reader(&mut &data[..]).context("The image format can't be embedded")?;
+    picture.set_pic_type(PictureType::CoverFront);
+
+    let canonical_path = fsio.canonicalize_path(file_path.as_ref())?;
+    if front_cover(&canonical_path).is_some_and(|x| x == data) {
+        return Ok(TagWriteOutcome::Unchanged);
+    }
+
+    edit_primary_tag(fsio, file_path.as_ref(), |tag| {
+        tag.remove_picture_type(PictureType::CoverFront);
+        tag.push_picture(picture);
+
+        Ok(())
+    })
+    .await
+}
+
+/// The data of the front cover embedded into a file, if it can be read.
+fn front_cover(file_path: &Path) -> Option<Vec<u8>> {
+    let tagged_file = Probe::open(file_path)
+        .ok()?
+        .options(ParseOptions::new().read_properties(false))
+        .read()
+        .ok()?;
+
+    tagged_file.tags().iter().find_map(|tag| {
+        tag.pictures()
+            .iter()
+            .find(|x| x.pic_type() == PictureType::CoverFront)
+            .map(|x| x.data().to_vec())
+    })
+}
+
+/// The file tags are written to before it replaces the original, next to
+/// it so the rename stays on the same file system.
+fn temp_path(file_path: &Path) -> Result<PathBuf> {
+    let file_name = file_path
+        .file_name()
+        .and_then(|x| x.to_str())
+        .with_context(|| format!("Invalid file name: {}", file_path.display()))?;
+
+    Ok(file_path.with_file_name(format!(".{file_name}.{}.tagging", std::process::id())))
+}
+
+/// Edits the primary tag of a file on a copy, which replaces the original
+/// once written and synced to disk, so an interrupted write never leaves a
+/// broken file behind.
+async fn edit_primary_tag<F>(fsio: &FsIo, file_path: &Path, edit: F) -> Result<TagWriteOutcome>
+where
+    F: FnOnce(&mut Tag) -> Result<()>,
+{
+    let file_path = fsio.canonicalize_path(file_path)?;
+
+    let file_type = Probe::open(&file_path)
+        .with_context(|| format!("Failed to read {}", file_path.display()))?
+        .guess_file_type()
+        .with_context(|| format!("Failed to read {}", file_path.display()))?
+        .file_type();
+    let file_type = match file_type {
+        Some(
+            x @ (FileType::Mpeg
+            | FileType::Flac
+            | FileType::Vorbis
+            | FileType::Opus
+            | FileType::Mp4),
+        ) => x,
+        x => {
+            let format = match x {
+                Some(x) => format!("{x:?}"),
+                None => file_path
+                    .extension()
+                    .map(|x| x.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+            return Ok(TagWriteOutcome::NotSupported { format });
+        }
+    };
+
+    let temp_path = temp_path(&file_path)?;
+    fsio.copy_file(&file_path, &temp_path, None, None)
+        .await
+        .with_context(|| format!("Failed to copy {}", file_path.display()))?;
+
+    let result = write_primary_tag(&temp_path, file_type, edit);
+    let result = match result {
+        Ok(()) => fsio
+            .rename(&temp_path, &file_path)
+            .await
+            .map_err(Into::into),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        let _ = fsio.remove_file(&temp_path).await;
+        return Err(e).with_context(|| format!("Failed to write tags to {}", file_path.display()));
+    }
+
+    let node = fsio.metadata(&file_path)?;
+    let description = describe_file(&node, &None)?;
+
+    Ok(TagWriteOutcome::Written {
+        last_modified: description.last_modified,
+    })
+}
+
+/// Edits the tag of `file_path` in place, then syncs it to disk.
+fn write_primary_tag<F>(file_path: &Path, file_type: FileType, edit: F) -> Result<()>
+where
+    F: FnOnce(&mut Tag) -> Result<()>,
+{
+    let mut file = OpenOptions::new().read(true).write(true).open(file_path)?;
+    // Properties aren't needed, and files too damaged to play can be fixed
+    let options = ParseOptions::new().read_properties(false);
+
+    match file_type {
+        FileType::Mpeg => {
+            let mpeg = MpegFile::read_from(&mut file, options)?;
+            let tag = mpeg.id3v2().cloned().unwrap_or_default();
+            edit_tag(&mut file, tag, &[], edit)?;
+        }
+        FileType::Flac => {
+            let flac = FlacFile::read_from(&mut file, options)?;
+            let tag = flac.vorbis_comments().cloned().unwrap_or_default();
+            // FLAC keeps the pictures in blocks of their own
+            let pictures: Vec<Picture> = flac.pictures().iter().map(|(x, _)| x.clone()).collect();
+            edit_tag(&mut file, tag, &pictures, edit)?;
+        }
+        FileType::Vorbis => {
+            let vorbis = VorbisFile::read_from(&mut file, options)?;
+            let tag = vorbis.vorbis_comments().clone();
+            edit_tag(&mut file, tag, &[], edit)?;
+        }
+        FileType::Opus => {
+            let opus = OpusFile::read_from(&mut file, options)?;
+            let tag = opus.vorbis_comments().clone();
+            edit_tag(&mut file, tag, &[], edit)?;
+        }
+        FileType::Mp4 => {
+            let mp4 = Mp4File::read_from(&mut file, options)?;
+            let tag = mp4.ilst().cloned().unwrap_or_default();
+            edit_tag(&mut file, tag, &[], edit)?;
+        }
+        x => bail!("Tags can't be written to {x:?} files"),
+    }
+
+    file.flush()?;
+    file.sync_all()?;
+
+    Ok(())
+}
+
+/// Applies `edit` to the fields of `tag` the generic tag can hold, leaving
+/// the frames it can't as they are, and saves the tag into `file`.
+fn edit_tag<T, F>(file: &mut File, tag: T, pictures: &[Picture], edit: F) -> Result<()>
+where
+    T: SplitTag + TagExt<Err = LoftyError>,
+    F: FnOnce(&mut Tag) -> Result<()>,
+{
+    let (remainder, mut generic) = tag.split_tag();
+    for picture in pictures {
+        generic.push_picture(picture.clone());
+    }
+
+    edit(&mut generic)?;
+
+    let tag = remainder.merge_tag(generic);
+    file.rewind()?;
+    tag.save_to(file, WriteOptions::default())?;
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use lofty::config::ParseOptions;
+use lofty::file::TaggedFileExt;
+use lofty::probe::Probe;
+use lofty::tag::Accessor;
+
+use ::fsio::FsIo;
+use ::metadata::cover_art::{EmbedFormat, EmbedOptions, prepare_embedded_image};
+use ::metadata::writer::{TagWriteOutcome, write_cover_art, write_tags};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Writes a title and an artist to a copy of a fixture, then checks they can
+/// be read back, that the fields the writer doesn't know survived, and that
+/// no temporary file was left behind.
+async fn assert_round_trip(name: &str, preserved: &[u8]) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join(name);
+    std::fs::copy(fixture(name), &path)?;
+
+    let fsio = FsIo::new();
+    let fields = vec![
+        ("track_title".to_owned(), "Written title".to_owned()),
+        ("artist".to_owned(), "Written artist".to_owned()),
+    ];
+    let outcome = write_tags(&fsio, &path, &fields).await?;
+    let TagWriteOutcome::Written { last_modified } = outcome else {
+        panic!("Tags were not written to {name}: {outcome:?}");
+    };
+    assert!(!last_modified.is_empty());
+
+    let tagged_file = Probe::open(&path)?
+        .options(ParseOptions::new().read_properties(false))
+        .read()?;
+    let tag = tagged_file
+        .primary_tag()
+        .with_context(|| format!("No tag in {name}"))?;
+    assert_eq!(tag.title().as_deref(), Some("Written title"));
+    assert_eq!(tag.artist().as_deref(), Some("Written artist"));
+
+    let data = std::fs::read(&path)?;
+    assert!(
+        data.windows(preserved.len()).any(|x| x == preserved),
+        "Unrelated fields of {name} were dropped"
+    );
+
+    let entries: Vec<_> = std::fs::read_dir(dir.path())?
+        .map(|x| x.map(|x| x.file_name()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(entries, [name]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn id3v2_tags_are_written() -> Result<()> {
+    assert_round_trip("tagged.mp3", b"keep me").await
+}
+
+#[tokio::test]
+async fn flac_vorbis_comments_are_written() -> Result<()> {
+    assert_round_trip("tagged.flac", b"keep me").await
+}
+
+#[tokio::test]
+async fn ogg_vorbis_comments_are_written() -> Result<()> {
+    assert_round_trip("tagged.ogg", b"keep me").await
+}
+
+#[tokio::test]
+async fn mp4_atoms_are_written() -> Result<()> {
+    assert_round_trip("tagged.m4a", b"keep me").await
+}
+
+#[tokio::test]
+async fn unsupported_files_are_left_untouched() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("untagged.wav");
+    std::fs::copy(fixture("untagged.wav"), &path)?;
+
+    let fsio = FsIo::new();
+    let fields = vec![("artist".to_owned(), "Written artist".to_owned())];
+    let outcome = write_tags(&fsio, &path, &fields).await?;
+    assert!(matches!(outcome, TagWriteOutcome::NotSupported { .. }));
+    assert_eq!(
+        std::fs::read(&path)?,
+        std::fs::read(fixture("untagged.wav"))?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_fields_are_rejected_before_writing() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("tagged.mp3");
+    std::fs::copy(fixture("tagged.mp3"), &path)?;
+
+    let fsio = FsIo::new();
+    let fields = vec![
+        ("artist".to_owned(), "Written artist".to_owned()),
+        ("mood".to_owned(), "Calm".to_owned()),
+    ];
+    assert!(write_tags(&fsio, &path, &fields).await.is_err());
+    assert_eq!(std::fs::read(&path)?, std::fs::read(fixture("tagged.mp3"))?);
+
+    Ok(())
+}
+
+/// A PNG too large to be embedded as it is.
+fn large_png() -> Result<Vec<u8>> {
+    let image = image::RgbImage::from_fn(1600, 800, |x, y| image::Rgb([x as u8, y as u8, 128]));
+    let mut data = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut data, image::ImageFormat::Png)?;
+
+    Ok(data.into_inner())
+}
+
+#[test]
+fn embedded_images_are_shrunk() -> Result<()> {
+    let options = EmbedOptions {
+        max_dimension: 500,
+        format: EmbedFormat::Jpeg,
+    };
+    let data = prepare_embedded_image(&large_png()?, &options)?;
+    assert_eq!(image::guess_format(&data)?, image::ImageFormat::Jpeg);
+    let image = image::load_from_memory(&data)?;
+    assert_eq!((image.width(), image.height()), (500, 250));
+
+    // Already small enough and in the right format
+    assert_eq!(prepare_embedded_image(&data, &options)?, data);
+
+    Ok(())
+}
+
+/// Embeds a cover into a copy of a fixture twice, the second write finding
+/// the image already there.
+async fn assert_cover_embedded(name: &str) -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join(name);
+    std::fs::copy(fixture(name), &path)?;
+
+    let fsio = FsIo::new();
+    let data = prepare_embedded_image(&large_png()?, &EmbedOptions::default())?;
+    let outcome = write_cover_art(&fsio, &path, &data).await?;
+    assert!(
+        matches!(outcome, TagWriteOutcome::Written { .. }),
+        "The cover was not embedded into {name}: {outcome:?}"
+    );
+
+    let written = std::fs::read(&path)?;
+    let outcome = write_cover_art(&fsio, &path, &data).await?;
+    assert_eq!(outcome, TagWriteOutcome::Unchanged);
+    assert_eq!(std::fs::read(&path)?, written);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn covers_are_embedded_into_mp3_files() -> Result<()> {
+    assert_cover_embedded("tagged.mp3").await
+}
+
+#[tokio::test]
+async fn covers_are_embedded_into_flac_files() -> Result<()> {
+    assert_cover_embedded("tagged.flac").await
+}
+
+#[tokio::test]
+async fn covers_are_embedded_into_m4a_files() -> Result<()> {
+    assert_cover_embedded("tagged.m4a").await
+}
@@ -37,6 +37,19 @@ mod m20250410_000025_add_hlc_columns;
 mod m20250529_000026_create_sync_record_table;
 mod m20251010_000027_add_index_cover_art_file_hash;
 mod m20251010_000028_add_index_media_files_cover_art_id;
+mod m20251016_000029_create_audit_log_table;
+mod m20251017_000030_create_media_analysis_failure_table;
+mod m20251018_000031_add_column_rating;
+mod m20251019_000032_create_scrobble_queue_table;
+mod m20251020_000033_create_scrobble_history_table;
+mod m20251021_000034_add_column_scrobble_queue_action;
+mod m20251022_000035_create_media_file_landmark_table;
+mod m20251022_000036_create_media_file_content_match_table;
+mod m20251023_000037_add_columns_musicbrainz_ids;
+mod m20251024_000038_add_columns_playback_stats;
+mod m20251025_000039_create_library_settings_table;
+mod m20251026_000040_add_column_first_seen_at;
+mod m20251027_000041_add_columns_loudness;
 
 pub struct Migrator;
 
@@ -70,6 +83,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20250529_000026_create_sync_record_table::Migration),
             Box::new(m20251010_000027_add_index_cover_art_file_hash::Migration),
             Box::new(m20251010_000028_add_index_media_files_cover_art_id::Migration),
+            Box::new(m20251016_000029_create_audit_log_table::Migration),
+            Box::new(m20251017_000030_create_media_analysis_failure_table::Migration),
+            Box::new(m20251018_000031_add_column_rating::Migration),
+            Box::new(m20251019_000032_create_scrobble_queue_table::Migration),
+            Box::new(m20251020_000033_create_scrobble_history_table::Migration),
+            Box::new(m20251021_000034_add_column_scrobble_queue_action::Migration),
+            Box::new(m20251022_000035_create_media_file_landmark_table::Migration),
+            Box::new(m20251022_000036_create_media_file_content_match_table::Migration),
+            Box::new(m20251023_000037_add_columns_musicbrainz_ids::Migration),
+            Box::new(m20251024_000038_add_columns_playback_stats::Migration),
+            Box::new(m20251025_000039_create_library_settings_table::Migration),
+            Box::new(m20251026_000040_add_column_first_seen_at::Migration),
+            Box::new(m20251027_000041_add_columns_loudness::Migration),
         ]
     }
 }
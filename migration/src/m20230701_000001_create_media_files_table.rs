@@ -68,4 +68,10 @@ pub enum MediaFiles {
     CoverArtId,
     SampleRate,
     Duration,
+    MusicbrainzRecordingId,
+    MusicbrainzTrackId,
+    MusicbrainzReleaseId,
+    MusicbrainzReleaseGroupId,
+    MusicbrainzArtistIds,
+    FirstSeenAt,
 }
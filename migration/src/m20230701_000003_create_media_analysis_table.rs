@@ -172,4 +172,6 @@ pub enum MediaAnalysis {
     Mfcc10,
     Mfcc11,
     Mfcc12,
+    IntegratedLoudness,
+    Peak,
 }
@@ -70,4 +70,9 @@ pub enum MediaFileStats {
     Skipped,
     PlayedThrough,
     UpdatedAt,
+    Rating,
+    LastSkippedAt,
+    TotalListenedSeconds,
+    LastPositionSeconds,
+    FirstPlayedAt,
 }
@@ -1068,14 +1068,7 @@ impl Migration {
     where
         T: Iden + Copy + 'static,
     {
-        manager
-            .alter_table(
-                Table::alter()
-                    .table(table)
-                    .drop_column(CommonColumns::HlcUuid)
-                    .to_owned(),
-            )
-            .await?;
+        Self::drop_hlc_uuid(manager, table).await?;
 
         if include_ts {
             manager
@@ -1143,15 +1136,63 @@ impl Migration {
     where
         T: Iden + Copy + 'static,
     {
+        #[derive(Iden)]
+        enum OldTimestampColumns {
+            CreatedAt,
+            UpdatedAt,
+        }
+
+        // SQLite only adds NOT NULL columns with a default, the timestamps
+        // are restored from the HLC ones right after
+        let default_timestamp_value =
+            Value::String(Some(Box::new("1970-01-01T00:00:00Z".to_string())));
+
         manager
             .alter_table(
                 Table::alter()
                     .table(table)
-                    .drop_column(CommonColumns::HlcUuid)
+                    .add_column(
+                        ColumnDef::new(OldTimestampColumns::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(default_timestamp_value.clone()),
+                    )
                     .to_owned(),
             )
             .await?;
 
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(table)
+                    .add_column(
+                        ColumnDef::new(OldTimestampColumns::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(default_timestamp_value),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(table)
+                    .value(
+                        OldTimestampColumns::CreatedAt,
+                        Expr::col(CommonColumns::CreatedAtHlcTs),
+                    )
+                    .value(
+                        OldTimestampColumns::UpdatedAt,
+                        Expr::col(CommonColumns::UpdatedAtHlcTs),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Self::drop_hlc_uuid(manager, table).await?;
+
         manager
             .alter_table(
                 Table::alter()
@@ -1206,11 +1247,19 @@ impl Migration {
             )
             .await?;
 
+        Ok(())
+    }
+
+    /// Drops the `hlc_uuid` column along with its index, which SQLite
+    /// refuses to drop columns from.
+    async fn drop_hlc_uuid<'a, T>(manager: &'a SchemaManager<'a>, table: T) -> Result<(), DbErr>
+    where
+        T: Iden + Copy + 'static,
+    {
         manager
-            .alter_table(
-                Table::alter()
-                    .table(table)
-                    .add_column(ColumnDef::new(Mixes::CreatedAt).timestamp().not_null())
+            .drop_index(
+                Index::drop()
+                    .name(format!("idx_{}_hlc_uuid", table.to_string()))
                     .to_owned(),
             )
             .await?;
@@ -1219,11 +1268,9 @@ impl Migration {
             .alter_table(
                 Table::alter()
                     .table(table)
-                    .add_column(ColumnDef::new(Mixes::UpdatedAt).timestamp().not_null())
+                    .drop_column(CommonColumns::HlcUuid)
                     .to_owned(),
             )
-            .await?;
-
-        Ok(())
+            .await
     }
 }
@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251016_000029_create_audit_log_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditLog::Date).timestamp().not_null())
+                    .col(ColumnDef::new(AuditLog::RequestType).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Fingerprint).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Alias).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Parameters).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Success).boolean().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_fingerprint_date")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::Fingerprint)
+                    .col(AuditLog::Date)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum AuditLog {
+    Table,
+    Id,
+    Date,
+    RequestType,
+    Fingerprint,
+    Alias,
+    Parameters,
+    Success,
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251017_000030_create_media_analysis_failure_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaAnalysisFailure::Table)
+                    .col(
+                        ColumnDef::new(MediaAnalysisFailure::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaAnalysisFailure::MediaFileId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaAnalysisFailure::FileHash)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaAnalysisFailure::Error)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaAnalysisFailure::FailedAt)
+                            .string()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_analysis_failure_media_file_id")
+                            .from(
+                                MediaAnalysisFailure::Table,
+                                MediaAnalysisFailure::MediaFileId,
+                            )
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaAnalysisFailure::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MediaAnalysisFailure {
+    Table,
+    Id,
+    MediaFileId,
+    FileHash,
+    Error,
+    FailedAt,
+}
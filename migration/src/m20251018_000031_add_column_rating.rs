@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230912_000015_create_media_file_stats_table::MediaFileStats;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251018_000031_add_column_rating"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .add_column(ColumnDef::new(MediaFileStats::Rating).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFileStats::Table)
+                    .drop_column(MediaFileStats::Rating)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251019_000032_create_scrobble_queue_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScrobbleQueue::Table)
+                    .col(
+                        ColumnDef::new(ScrobbleQueue::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ScrobbleQueue::Service).string().not_null())
+                    .col(ColumnDef::new(ScrobbleQueue::Track).string().not_null())
+                    .col(
+                        ColumnDef::new(ScrobbleQueue::Timestamp)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScrobbleQueue::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_scrobble_queue_service_timestamp")
+                    .table(ScrobbleQueue::Table)
+                    .col(ScrobbleQueue::Service)
+                    .col(ScrobbleQueue::Timestamp)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ScrobbleQueue::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ScrobbleQueue {
+    Table,
+    Id,
+    Service,
+    Track,
+    Timestamp,
+    Attempts,
+    Action,
+}
@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251020_000033_create_scrobble_history_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScrobbleHistory::Table)
+                    .col(
+                        ColumnDef::new(ScrobbleHistory::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ScrobbleHistory::MediaFileId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(ScrobbleHistory::Artist).string().not_null())
+                    .col(ColumnDef::new(ScrobbleHistory::Title).string().not_null())
+                    .col(ColumnDef::new(ScrobbleHistory::Album).string().null())
+                    .col(
+                        ColumnDef::new(ScrobbleHistory::Timestamp)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ScrobbleHistory::AcceptedBy)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_scrobble_history_timestamp")
+                    .table(ScrobbleHistory::Table)
+                    .col(ScrobbleHistory::Timestamp)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ScrobbleHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ScrobbleHistory {
+    Table,
+    Id,
+    MediaFileId,
+    Artist,
+    Title,
+    Album,
+    Timestamp,
+    AcceptedBy,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20251019_000032_create_scrobble_queue_table::ScrobbleQueue;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251021_000034_add_column_scrobble_queue_action"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ScrobbleQueue::Table)
+                    .add_column(
+                        ColumnDef::new(ScrobbleQueue::Action)
+                            .string()
+                            .not_null()
+                            .default("scrobble"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ScrobbleQueue::Table)
+                    .drop_column(ScrobbleQueue::Action)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251022_000036_create_media_file_content_match_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaFileContentMatch::Table)
+                    .col(
+                        ColumnDef::new(MediaFileContentMatch::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaFileContentMatch::FileId1)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaFileContentMatch::FileId2)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaFileContentMatch::Offset)
+                            .float()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MediaFileContentMatch::Density)
+                            .float()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_file_content_match_file_id1")
+                            .from(MediaFileContentMatch::Table, MediaFileContentMatch::FileId1)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_file_content_match_file_id2")
+                            .from(MediaFileContentMatch::Table, MediaFileContentMatch::FileId2)
+                            .to(MediaFiles::Table, MediaFiles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_content_match_file_ids")
+                            .col(MediaFileContentMatch::FileId1)
+                            .col(MediaFileContentMatch::FileId2)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MediaFileContentMatch::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MediaFileContentMatch {
+    Table,
+    Id,
+    FileId1,
+    FileId2,
+    Offset,
+    Density,
+}
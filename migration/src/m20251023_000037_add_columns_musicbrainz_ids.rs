@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251023_000037_add_columns_musicbrainz_ids"
+    }
+}
+
+const COLUMNS: [MediaFiles; 5] = [
+    MediaFiles::MusicbrainzRecordingId,
+    MediaFiles::MusicbrainzTrackId,
+    MediaFiles::MusicbrainzReleaseId,
+    MediaFiles::MusicbrainzReleaseGroupId,
+    MediaFiles::MusicbrainzArtistIds,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite adds one column at a time
+        for column in COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaFiles::Table)
+                        .add_column(ColumnDef::new(column).string().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaFiles::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
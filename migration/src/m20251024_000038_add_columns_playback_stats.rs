@@ -0,0 +1,187 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{FromQueryResult, Statement, prelude::Uuid},
+};
+
+use crate::m20230912_000015_create_media_file_stats_table::MediaFileStats;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251024_000038_add_columns_playback_stats"
+    }
+}
+
+#[derive(Iden)]
+enum HlcColumns {
+    HlcUuid,
+    CreatedAtHlcTs,
+    CreatedAtHlcVer,
+    CreatedAtHlcNid,
+    UpdatedAtHlcTs,
+    UpdatedAtHlcVer,
+    UpdatedAtHlcNid,
+}
+
+const HLC_UUID_INDEX: &str = "idx_media_file_stats_hlc_uuid";
+
+fn columns() -> Vec<ColumnDef> {
+    let default_timestamp_value = Value::String(Some(Box::new("1970-01-01T00:00:00Z".to_string())));
+
+    vec![
+        ColumnDef::new(MediaFileStats::LastSkippedAt)
+            .text()
+            .null()
+            .to_owned(),
+        ColumnDef::new(MediaFileStats::TotalListenedSeconds)
+            .double()
+            .not_null()
+            .default(0.0)
+            .to_owned(),
+        ColumnDef::new(MediaFileStats::LastPositionSeconds)
+            .double()
+            .null()
+            .to_owned(),
+        ColumnDef::new(MediaFileStats::FirstPlayedAt)
+            .text()
+            .null()
+            .to_owned(),
+        ColumnDef::new(HlcColumns::HlcUuid)
+            .string()
+            .not_null()
+            .default("")
+            .to_owned(),
+        ColumnDef::new(HlcColumns::CreatedAtHlcTs)
+            .timestamp_with_time_zone()
+            .not_null()
+            .default(default_timestamp_value.clone())
+            .to_owned(),
+        ColumnDef::new(HlcColumns::CreatedAtHlcVer)
+            .integer()
+            .not_null()
+            .default(0)
+            .to_owned(),
+        ColumnDef::new(HlcColumns::CreatedAtHlcNid)
+            .text()
+            .not_null()
+            .default("")
+            .to_owned(),
+        ColumnDef::new(HlcColumns::UpdatedAtHlcTs)
+            .timestamp_with_time_zone()
+            .not_null()
+            .default(default_timestamp_value)
+            .to_owned(),
+        ColumnDef::new(HlcColumns::UpdatedAtHlcVer)
+            .integer()
+            .not_null()
+            .default(0)
+            .to_owned(),
+        ColumnDef::new(HlcColumns::UpdatedAtHlcNid)
+            .text()
+            .not_null()
+            .default("")
+            .to_owned(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite adds one column at a time
+        for column in columns() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaFileStats::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // The stats were last written when they were last updated
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(MediaFileStats::Table)
+                    .value(
+                        HlcColumns::CreatedAtHlcTs,
+                        Expr::col(MediaFileStats::UpdatedAt),
+                    )
+                    .value(
+                        HlcColumns::UpdatedAtHlcTs,
+                        Expr::col(MediaFileStats::UpdatedAt),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every device derives the same UUID for the stats of a file
+        let db = manager.get_connection();
+        let node_id = crate::get_node_id();
+
+        #[derive(Debug, FromQueryResult)]
+        struct StatsRow {
+            id: i32,
+            file_hash: String,
+        }
+        let rows: Vec<StatsRow> = StatsRow::find_by_statement(Statement::from_string(
+            db.get_database_backend(),
+            "SELECT T1.id, T2.file_hash FROM media_file_stats AS T1 JOIN media_files AS T2 ON T1.media_file_id = T2.id"
+                .to_string(),
+        ))
+        .all(db)
+        .await?;
+        for row in rows {
+            let uuid = Uuid::new_v5(
+                &Uuid::NAMESPACE_OID,
+                format!("RUNE_STATS::{}", row.file_hash).as_bytes(),
+            )
+            .to_string();
+            manager
+                .exec_stmt(
+                    Query::update()
+                        .table(MediaFileStats::Table)
+                        .values([
+                            (HlcColumns::HlcUuid, uuid.into()),
+                            (HlcColumns::CreatedAtHlcNid, node_id.into()),
+                            (HlcColumns::UpdatedAtHlcNid, node_id.into()),
+                        ])
+                        .and_where(Expr::col(MediaFileStats::Id).eq(row.id))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(HLC_UUID_INDEX)
+                    .table(MediaFileStats::Table)
+                    .col(HlcColumns::HlcUuid)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The indexed column can't be dropped before its index
+        manager
+            .drop_index(Index::drop().name(HLC_UUID_INDEX).to_owned())
+            .await?;
+
+        for column in columns() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaFileStats::Table)
+                        .drop_column(Alias::new(column.get_column_name()))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
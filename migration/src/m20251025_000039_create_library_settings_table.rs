@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251025_000039_create_library_settings_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LibrarySettings::Table)
+                    .col(
+                        ColumnDef::new(LibrarySettings::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LibrarySettings::Value).text().not_null())
+                    .col(
+                        ColumnDef::new(LibrarySettings::UpdatedAt)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LibrarySettings::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum LibrarySettings {
+    Table,
+    Key,
+    Value,
+    UpdatedAt,
+}
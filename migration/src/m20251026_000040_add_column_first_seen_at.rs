@@ -0,0 +1,84 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000001_create_media_files_table::MediaFiles;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251026_000040_add_column_first_seen_at"
+    }
+}
+
+#[derive(Iden)]
+enum HlcColumns {
+    CreatedAtHlcTs,
+}
+
+const FIRST_SEEN_AT_INDEX: &str = "idx_media_files_first_seen_at";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFiles::Table)
+                    .add_column(
+                        ColumnDef::new(MediaFiles::FirstSeenAt)
+                            .text()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Files were first seen when their record was created, unless it
+        // predates the HLC columns and only has their default
+        let now = chrono::Utc::now().to_rfc3339();
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(MediaFiles::Table)
+                    .value(
+                        MediaFiles::FirstSeenAt,
+                        Expr::case(
+                            Expr::col(HlcColumns::CreatedAtHlcTs)
+                                .eq("")
+                                .or(Expr::col(HlcColumns::CreatedAtHlcTs).like("1970-01-01%")),
+                            now,
+                        )
+                        .finally(Expr::col(HlcColumns::CreatedAtHlcTs)),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(FIRST_SEEN_AT_INDEX)
+                    .table(MediaFiles::Table)
+                    .col(MediaFiles::FirstSeenAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The indexed column can't be dropped before its index
+        manager
+            .drop_index(Index::drop().name(FIRST_SEEN_AT_INDEX).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MediaFiles::Table)
+                    .drop_column(MediaFiles::FirstSeenAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230701_000003_create_media_analysis_table::MediaAnalysis;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251027_000041_add_columns_loudness"
+    }
+}
+
+fn columns() -> Vec<ColumnDef> {
+    vec![
+        ColumnDef::new(MediaAnalysis::IntegratedLoudness)
+            .double()
+            .null()
+            .to_owned(),
+        ColumnDef::new(MediaAnalysis::Peak)
+            .double()
+            .null()
+            .to_owned(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite adds one column at a time
+        for column in columns() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaAnalysis::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in columns() {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(MediaAnalysis::Table)
+                        .drop_column(Alias::new(column.get_column_name()))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ mod gui_request;
 
 use std::sync::Arc;
 use std::sync::OnceLock;
+use std::time::Instant;
 
 use fsio::FsIo;
 use log::{error, info};
@@ -22,6 +23,7 @@ use ::playback::sfx_player::SfxPlayer;
 use ::scrobbling::manager::ScrobblingManager;
 
 use crate::Signal;
+use crate::backends::remote::output::RemoteOutputManager;
 use crate::listen_local_gui_event;
 use crate::messages::*;
 use crate::server::ServerManager;
@@ -29,9 +31,11 @@ use crate::utils::Broadcaster;
 use crate::utils::DatabaseConnections;
 use crate::utils::GlobalParams;
 use crate::utils::ParamsExtractor;
-use crate::utils::TaskTokens;
 use crate::utils::nid::get_or_create_node_id;
 use crate::utils::player::initialize_local_player;
+use crate::utils::scrobble_history::DatabaseScrobbleHistory;
+use crate::utils::scrobble_queue::DatabaseScrobbleQueue;
+use crate::utils::task_registry::TaskRegistry;
 
 pub async fn local_player_loop(
     fsio: Arc<FsIo>,
@@ -50,6 +54,8 @@ pub async fn local_player_loop(
 
         let recommend_db: Arc<RecommendationDbConnection> = db_connections.recommend_db;
 
+        let library_lock = db_connections.library_lock;
+
         let lib_path: Arc<String> = Arc::new(lib_path);
         let config_path: Arc<String> = Arc::new(config_path);
 
@@ -61,7 +67,7 @@ pub async fn local_player_loop(
         );
 
         let main_cancel_token = CancellationToken::new();
-        let task_tokens: Arc<Mutex<TaskTokens>> = Arc::new(Mutex::new(TaskTokens::default()));
+        let task_registry = Arc::new(TaskRegistry::new());
 
         info!("Initializing player");
         let player = Player::new(Some(main_cancel_token.clone()));
@@ -83,14 +89,27 @@ pub async fn local_player_loop(
         tokio::spawn(initialize_local_player(
             fsio.clone(),
             lib_path.clone(),
+            config_path.clone(),
             main_db.clone(),
             player.clone(),
             scrobbler.clone(),
             broadcaster.clone(),
             cert_validator.clone(),
             permission_manager.clone(),
+            (*main_cancel_token).clone(),
         ));
 
+        scrobbler
+            .lock()
+            .await
+            .set_history(Arc::new(DatabaseScrobbleHistory::new(main_db.clone())));
+        let scrobble_queue_token = (*main_cancel_token).clone();
+        ScrobblingManager::start_queue(
+            scrobbler.clone(),
+            Arc::new(DatabaseScrobbleQueue::new(main_db.clone())),
+            async move { scrobble_queue_token.cancelled().await },
+        );
+
         info!("Initializing UI events");
         let global_params = GlobalParams {
             fsio,
@@ -99,8 +118,9 @@ pub async fn local_player_loop(
             node_id,
             main_db,
             recommend_db,
+            library_lock: Some(library_lock),
             main_token: Arc::clone(&main_cancel_token),
-            task_tokens,
+            task_registry,
             player,
             sfx_player,
             scrobbler,
@@ -109,7 +129,9 @@ pub async fn local_player_loop(
             cert_validator,
             permission_manager,
             server_manager: OnceLock::new(),
+            remote_output: Arc::new(RemoteOutputManager::default()),
             running_mode: crate::utils::RunningMode::Client,
+            started_at: Instant::now(),
         };
 
         let global_params = Arc::new(global_params);
@@ -0,0 +1,405 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::messages::*;
+
+/// What the response of a cacheable request is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CachedData {
+    /// Albums, artists, genres, playlists and mixes
+    Collections,
+    /// Media files and their parsed metadata
+    MediaFiles,
+}
+
+const COLLECTIONS: &[CachedData] = &[CachedData::Collections];
+const LIBRARY: &[CachedData] = &[CachedData::Collections, CachedData::MediaFiles];
+
+/// Server broadcasts and the cached data they make stale.
+const INVALIDATING_BROADCASTS: &[(&str, &[CachedData])] = &[
+    ("ScanAudioLibraryResponse", LIBRARY),
+    ("AnalyzeAudioLibraryResponse", LIBRARY),
+    ("SetMediaLibraryPathResponse", LIBRARY),
+    ("PlaylistUpdate", COLLECTIONS),
+];
+
+/// Outgoing requests and the cached data they make stale. Requests that
+/// are not listed leave every cached response in place: likes, ratings,
+/// playback and settings are not part of any of them.
+const INVALIDATING_REQUESTS: &[(&str, &[CachedData])] = &[
+    ("DeduplicateAudioLibraryRequest", LIBRARY),
+    ("ApplyPathMetadataRequest", LIBRARY),
+    ("ApplyTagNormalizationRequest", LIBRARY),
+    ("OrganizeFilesRequest", LIBRARY),
+    ("ApplyIdentificationRequest", LIBRARY),
+    ("ApplyReleaseMappingRequest", LIBRARY),
+    ("SetAlbumCoverArtRequest", LIBRARY),
+    ("CreatePlaylistRequest", COLLECTIONS),
+    ("CreateM3u8PlaylistRequest", COLLECTIONS),
+    ("UpdatePlaylistRequest", COLLECTIONS),
+    ("RemovePlaylistRequest", COLLECTIONS),
+    ("AddItemToPlaylistRequest", COLLECTIONS),
+    ("ReorderPlaylistItemPositionRequest", COLLECTIONS),
+    ("RemoveItemFromPlaylistRequest", COLLECTIONS),
+    ("CreateMixRequest", COLLECTIONS),
+    ("UpdateMixRequest", COLLECTIONS),
+    ("RemoveMixRequest", COLLECTIONS),
+    ("AddItemToMixRequest", COLLECTIONS),
+];
+
+fn invalidated_data(
+    table: &[(&str, &'static [CachedData])],
+    message_type: &str,
+) -> &'static [CachedData] {
+    table
+        .iter()
+        .find(|(name, _)| *name == message_type)
+        .map(|(_, data)| *data)
+        .unwrap_or_default()
+}
+
+/// Requests whose responses can be served from the cache.
+pub trait CacheableRequest: Serialize + for<'a> Deserialize<'a> {
+    /// Clears the `bypass_cache` flag and returns its previous value, so
+    /// the remaining fields can be used as the cache key.
+    fn take_bypass_cache(&mut self) -> bool;
+}
+
+macro_rules! implement_cacheable_request {
+    ($($request:ty),*) => {
+        $(
+            impl CacheableRequest for $request {
+                fn take_bypass_cache(&mut self) -> bool {
+                    std::mem::take(&mut self.bypass_cache)
+                }
+            }
+        )*
+    };
+}
+
+implement_cacheable_request!(
+    FetchCollectionGroupSummaryRequest,
+    FetchCollectionGroupsRequest,
+    FetchCollectionByIdsRequest,
+    FetchMediaFilesRequest,
+    FetchMediaFileByIdsRequest,
+    FetchParsedMediaFileRequest
+);
+
+fn normalize_request<T: CacheableRequest>(payload: &[u8]) -> Result<(bool, Vec<u8>)> {
+    let mut request: T =
+        rinf::deserialize(payload).map_err(|e| anyhow!("Deserialization failed: {e}"))?;
+    let bypass = request.take_bypass_cache();
+    let payload = rinf::serialize(&request).map_err(|e| anyhow!("Serialization failed: {e}"))?;
+
+    Ok((bypass, payload))
+}
+
+/// Identifies a cacheable request by its type and encoded parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    request_type: String,
+    payload: Vec<u8>,
+}
+
+impl CacheKey {
+    /// Builds the cache key of an encoded request, along with whether the
+    /// caller asked to bypass the cache. Returns `None` if responses of the
+    /// request type are never cached.
+    pub fn from_request(request_type: &str, payload: &[u8]) -> Option<(Self, bool)> {
+        let normalized = match request_type {
+            "FetchCollectionGroupSummaryRequest" => {
+                normalize_request::<FetchCollectionGroupSummaryRequest>(payload)
+            }
+            "FetchCollectionGroupsRequest" => {
+                normalize_request::<FetchCollectionGroupsRequest>(payload)
+            }
+            "FetchCollectionByIdsRequest" => {
+                normalize_request::<FetchCollectionByIdsRequest>(payload)
+            }
+            "FetchMediaFilesRequest" => normalize_request::<FetchMediaFilesRequest>(payload),
+            "FetchMediaFileByIdsRequest" => {
+                normalize_request::<FetchMediaFileByIdsRequest>(payload)
+            }
+            "FetchParsedMediaFileRequest" => {
+                normalize_request::<FetchParsedMediaFileRequest>(payload)
+            }
+            _ => return None,
+        };
+
+        let (bypass, payload) = normalized.ok()?;
+        Some((
+            CacheKey {
+                request_type: request_type.to_string(),
+                payload,
+            },
+            bypass,
+        ))
+    }
+
+    fn size(&self) -> usize {
+        self.request_type.len() + self.payload.len()
+    }
+
+    fn data(&self) -> CachedData {
+        match self.request_type.as_str() {
+            "FetchMediaFilesRequest"
+            | "FetchMediaFileByIdsRequest"
+            | "FetchParsedMediaFileRequest" => CachedData::MediaFiles,
+            _ => CachedData::Collections,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCacheConfig {
+    pub max_entries: usize,
+    pub max_bytes: usize,
+    /// Cached responses older than this are fetched again even if no
+    /// invalidating broadcast has been received.
+    pub ttl: Duration,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 512,
+            max_bytes: 32 * 1024 * 1024,
+            ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response_type: String,
+    payload: Vec<u8>,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Requests sent to the server whose responses should be cached.
+    pending: HashMap<Uuid, CacheKey>,
+    total_bytes: usize,
+    tick: u64,
+}
+
+impl CacheState {
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= key.size() + entry.payload.len();
+        }
+    }
+}
+
+/// LRU cache of remote responses, bounded by entry count and total size.
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    config: ResponseCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl ResponseCache {
+    pub fn new(config: ResponseCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Returns the cached response type and payload if they are still fresh.
+    pub fn lookup(&self, key: &CacheKey) -> Option<(String, Vec<u8>)> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        let expired = match state.entries.get_mut(key) {
+            Some(entry) if entry.inserted_at.elapsed() < self.config.ttl => {
+                entry.last_used = tick;
+                return Some((entry.response_type.clone(), entry.payload.clone()));
+            }
+            Some(_) => true,
+            None => false,
+        };
+
+        if expired {
+            state.remove(key);
+        }
+
+        None
+    }
+
+    /// Remembers that the response to the given request should be cached.
+    pub fn track(&self, request_id: Uuid, key: CacheKey) {
+        self.state.lock().unwrap().pending.insert(request_id, key);
+    }
+
+    /// Stores the response of a tracked request, evicting the least recently
+    /// used entries if the cache grows over its limits.
+    pub fn complete(&self, request_id: &Uuid, response_type: &str, payload: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        let Some(key) = state.pending.remove(request_id) else {
+            return;
+        };
+
+        let size = key.size() + payload.len();
+        if size > self.config.max_bytes || self.config.max_entries == 0 {
+            return;
+        }
+
+        state.remove(&key);
+        state.tick += 1;
+        let entry = CacheEntry {
+            response_type: response_type.to_string(),
+            payload: payload.to_vec(),
+            inserted_at: Instant::now(),
+            last_used: state.tick,
+        };
+        state.entries.insert(key, entry);
+        state.total_bytes += size;
+
+        while state.entries.len() > self.config.max_entries
+            || state.total_bytes > self.config.max_bytes
+        {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            state.remove(&oldest);
+        }
+    }
+
+    /// Drops the cached responses the received message reports a change of.
+    pub fn invalidate_on_broadcast(&self, message_type: &str) {
+        self.invalidate(invalidated_data(INVALIDATING_BROADCASTS, message_type));
+    }
+
+    /// Drops the cached responses the outgoing request may change.
+    pub fn invalidate_on_request(&self, request_type: &str) {
+        self.invalidate(invalidated_data(INVALIDATING_REQUESTS, request_type));
+    }
+
+    /// Drops the cached responses made of `data`, along with the pending
+    /// ones, since they may predate the change.
+    fn invalidate(&self, data: &[CachedData]) {
+        if data.is_empty() {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let stale: Vec<CacheKey> = state
+            .entries
+            .keys()
+            .filter(|key| data.contains(&key.data()))
+            .cloned()
+            .collect();
+        for key in &stale {
+            state.remove(key);
+        }
+        state.pending.retain(|_, key| !data.contains(&key.data()));
+    }
+
+    /// Drops every cached response. Responses of requests that are still in
+    /// flight are not cached either, since they may predate the change.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.pending.clear();
+        state.total_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::protocol::SERVED_REQUEST_TYPES;
+
+    fn key(request_type: &str) -> CacheKey {
+        CacheKey {
+            request_type: request_type.to_owned(),
+            payload: vec![1, 2, 3],
+        }
+    }
+
+    fn cache_responses(cache: &ResponseCache, request_types: &[&str]) {
+        for request_type in request_types {
+            let request_id = Uuid::new_v4();
+            cache.track(request_id, key(request_type));
+            cache.complete(&request_id, "Response", &[4, 5]);
+        }
+    }
+
+    const REQUESTS: &[&str] = &["FetchCollectionGroupsRequest", "FetchMediaFilesRequest"];
+
+    #[test]
+    fn playlist_changes_keep_media_files() {
+        let cache = ResponseCache::default();
+        cache_responses(&cache, REQUESTS);
+
+        cache.invalidate_on_request("AddItemToPlaylistRequest");
+        assert!(cache.lookup(&key("FetchCollectionGroupsRequest")).is_none());
+        assert!(cache.lookup(&key("FetchMediaFilesRequest")).is_some());
+    }
+
+    #[test]
+    fn metadata_changes_drop_everything() {
+        let cache = ResponseCache::default();
+        cache_responses(&cache, REQUESTS);
+
+        cache.invalidate_on_request("ApplyTagNormalizationRequest");
+        assert!(cache.lookup(&key("FetchCollectionGroupsRequest")).is_none());
+        assert!(cache.lookup(&key("FetchMediaFilesRequest")).is_none());
+        assert_eq!(cache.state.lock().unwrap().total_bytes, 0);
+    }
+
+    #[test]
+    fn unlisted_requests_keep_the_cache() {
+        let cache = ResponseCache::default();
+        cache_responses(&cache, REQUESTS);
+
+        cache.invalidate_on_request("SetLikedRequest");
+        cache.invalidate_on_request("PlayRequest");
+        cache.invalidate_on_broadcast("PlaybackStatus");
+        assert!(cache.lookup(&key("FetchCollectionGroupsRequest")).is_some());
+        assert!(cache.lookup(&key("FetchMediaFilesRequest")).is_some());
+    }
+
+    #[test]
+    fn pending_responses_of_stale_data_are_not_cached() {
+        let cache = ResponseCache::default();
+        let playlists = Uuid::new_v4();
+        let files = Uuid::new_v4();
+        cache.track(playlists, key("FetchCollectionGroupsRequest"));
+        cache.track(files, key("FetchMediaFilesRequest"));
+
+        cache.invalidate_on_broadcast("PlaylistUpdate");
+        cache.complete(&playlists, "Response", &[4, 5]);
+        cache.complete(&files, "Response", &[4, 5]);
+        assert!(cache.lookup(&key("FetchCollectionGroupsRequest")).is_none());
+        assert!(cache.lookup(&key("FetchMediaFilesRequest")).is_some());
+    }
+
+    #[test]
+    fn listed_requests_are_served() {
+        for (request_type, _) in INVALIDATING_REQUESTS {
+            assert!(
+                SERVED_REQUEST_TYPES.contains(request_type),
+                "{request_type} is never sent to a server"
+            );
+        }
+    }
+}
@@ -1,20 +1,27 @@
 #[macro_use]
 mod remote_request;
+pub mod cache;
+pub mod output;
 
 use std::{
     collections::HashMap,
     sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result, bail};
 use futures::{SinkExt, StreamExt};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use rinf::{DartSignal, RustSignal};
 use rustls::ClientConfig;
 use serde::Deserialize;
-use tokio::sync::{Mutex, RwLock};
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, RwLock},
+};
 use tokio_tungstenite::{
-    Connector, connect_async_tls_with_config, tungstenite::protocol::Message as TungsteniteMessage,
+    Connector, MaybeTlsStream, WebSocketStream, connect_async_tls_with_config,
+    tungstenite::protocol::Message as TungsteniteMessage,
 };
 use tokio_util::sync::CancellationToken;
 use urlencoding::encode;
@@ -22,7 +29,7 @@ use uuid::Uuid;
 
 use ::database::connection::{connect_fake_main_db, connect_fake_recommendation_db};
 use ::discovery::{
-    client::{CertValidator, select_best_host},
+    client::{CertValidator, probe_best_host},
     protocol::DiscoveryService,
     server::PermissionManager,
     url::decode_rnsrv_url,
@@ -32,13 +39,22 @@ use ::playback::{player::MockPlayer, sfx_player::SfxPlayer};
 use ::scrobbling::manager::MockScrobblingManager;
 
 use crate::{
-    Signal, forward_event_to_remote, implement_rinf_dart_signal_trait,
+    Signal,
+    backends::remote::{
+        cache::{CacheKey, ResponseCache},
+        output::RemoteOutputManager,
+    },
+    forward_event_to_remote, implement_rinf_dart_signal_trait,
     messages::*,
     register_remote_handlers,
-    server::{api::check_fingerprint, generate_or_load_certificates},
+    server::{
+        api::{check_fingerprint, resolve_endpoint},
+        generate_or_load_certificates,
+        protocol::{PROTOCOL_PARAM, PROTOCOL_VERSION, PeerCapabilities, unsupported_request},
+    },
     utils::{
         GlobalParams, LocalGuiBroadcaster, ParamsExtractor, RinfRustSignal, RunningMode,
-        TaskTokens, nid::get_or_create_node_id,
+        nid::get_or_create_node_id, task_registry::TaskRegistry,
     },
 };
 
@@ -86,11 +102,96 @@ pub fn decode_message(payload: &[u8]) -> Option<(String, Vec<u8>, Uuid)> {
     Some((msg_type, msg_payload, request_id))
 }
 
+/// Delay before the first attempt to reconnect to a server that went away.
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound of the exponential reconnect backoff.
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Connects to `url` until it succeeds, waiting longer after every failed
+/// attempt. Returns `None` if `cancel_token` is cancelled first.
+async fn reconnect(
+    url: &str,
+    config: Arc<ClientConfig>,
+    cancel_token: &CancellationToken,
+) -> Option<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let mut delay = RECONNECT_INITIAL_DELAY;
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => return None,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        match connect_async_tls_with_config(
+            url,
+            None,
+            false,
+            Some(Connector::Rustls(Arc::clone(&config))),
+        )
+        .await
+        {
+            Ok((ws_stream, _)) => return Some(ws_stream),
+            Err(e) => {
+                delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+                warn!("Failed to reconnect, retrying in {delay:?}: {e}");
+            }
+        }
+    }
+}
+
+/// Asks the server which requests it answers. Servers speaking the legacy
+/// protocol never answer, every request is then sent to them.
+async fn request_capabilities<S>(write: &Mutex<S>) -> Result<()>
+where
+    S: futures::Sink<TungsteniteMessage> + Unpin,
+    S::Error: std::error::Error + Send + Sync + 'static,
+{
+    let payload = rinf::serialize(&ServerCapabilitiesRequest {})
+        .map_err(|e| anyhow::anyhow!("Serialization failed: {e}"))?;
+    let message = encode_message("ServerCapabilitiesRequest", &payload, None);
+    write
+        .lock()
+        .await
+        .send(TungsteniteMessage::Binary(message.into()))
+        .await?;
+
+    Ok(())
+}
+
+/// Remembers the requests the server answers, from its capabilities.
+fn update_capabilities(capabilities: &SharedCapabilities, payload: &[u8]) {
+    let response = match rinf::deserialize::<ServerCapabilitiesResponse>(payload) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to decode the capabilities of the server: {e}");
+            return;
+        }
+    };
+
+    let peer = PeerCapabilities::from_response(&response);
+    if !peer.is_identical() {
+        let missing = peer.missing();
+        info!(
+            "The server speaks protocol version {} (this client speaks {PROTOCOL_VERSION})",
+            peer.protocol_version
+        );
+        if !missing.is_empty() {
+            warn!("Requests the server doesn't answer: {}", missing.join(", "));
+        }
+    }
+
+    *capabilities.write().unwrap() = Some(peer);
+}
+
 type MessageHandler = Box<dyn Fn(Vec<u8>) + Send + Sync>;
 type HandlerMap = Arc<Mutex<HashMap<String, MessageHandler>>>;
+/// The capabilities of the server, `None` until it told them.
+type SharedCapabilities = Arc<std::sync::RwLock<Option<PeerCapabilities>>>;
 
 pub struct WebSocketDartBridge {
     handlers: HandlerMap,
+    response_cache: Arc<ResponseCache>,
+    capabilities: SharedCapabilities,
 }
 
 impl Default for WebSocketDartBridge {
@@ -103,6 +204,8 @@ impl WebSocketDartBridge {
     pub fn new() -> Self {
         WebSocketDartBridge {
             handlers: Arc::new(Mutex::new(HashMap::new())),
+            response_cache: Arc::new(ResponseCache::default()),
+            capabilities: Arc::default(),
         }
     }
 
@@ -148,9 +251,10 @@ impl WebSocketDartBridge {
         config: Arc<ClientConfig>,
         fingerprint: &str,
     ) -> Result<()> {
+        let endpoint = resolve_endpoint(host, Arc::clone(&config)).await?;
         let url = format!(
-            "wss://{}:7863/ws?fingerprint={}&host={}",
-            host,
+            "{}?fingerprint={}&host={}&{PROTOCOL_PARAM}={PROTOCOL_VERSION}",
+            endpoint.ws_url(),
             encode(fingerprint),
             encode(host)
         );
@@ -161,7 +265,7 @@ impl WebSocketDartBridge {
             url.clone(),
             None,
             false,
-            Some(Connector::Rustls(config)),
+            Some(Connector::Rustls(Arc::clone(&config))),
         )
         .await
         {
@@ -171,6 +275,10 @@ impl WebSocketDartBridge {
                 let (write, mut read) = ws_stream.split();
                 let write = Arc::new(Mutex::new(write));
 
+                if let Err(e) = request_capabilities(&write).await {
+                    error!("Failed to request the capabilities of the server: {e}");
+                }
+
                 let cancel_token: CancellationToken = CancellationToken::new();
 
                 let sfx_player = SfxPlayer::new(Some(cancel_token.clone()));
@@ -218,47 +326,96 @@ impl WebSocketDartBridge {
 
                 for_all_non_local_requests3!(
                     forward_event_to_remote,
-                    self_arc,
+                    (
+                        Arc::clone(&self.handlers),
+                        Arc::clone(&self.response_cache),
+                        Arc::clone(&self.capabilities)
+                    ),
                     cancel_token.clone(),
                     write.clone()
                 );
 
                 let handlers = self.handlers.clone();
+                let response_cache = Arc::clone(&self.response_cache);
+                let capabilities = Arc::clone(&self.capabilities);
                 let write_clone = Arc::clone(&write);
                 let cancel_token_clone = Arc::clone(&cancel_token);
                 let message_loop = || async move {
-                    loop {
-                        tokio::select! {
-                            message = read.next() => {
-                                match message {
-                                    Some(Ok(msg)) => {
-                                        if let TungsteniteMessage::Binary(payload) = msg
-                                            && let Some((msg_type, msg_payload, _request_id)) = decode_message(&payload) {
-                                                debug!("Received message with type: {msg_type}");
-                                                if let Some(handler) = handlers.lock().await.get(&msg_type) {
-                                                    handler(msg_payload);
-                                                } else {
-                                                    error!("No handler registered for message type while receiving response: {msg_type}");
+                    'connection: loop {
+                        let mut server_shutting_down = false;
+
+                        loop {
+                            tokio::select! {
+                                message = read.next() => {
+                                    match message {
+                                        Some(Ok(msg)) => {
+                                            if let TungsteniteMessage::Binary(payload) = msg
+                                                && let Some((msg_type, msg_payload, request_id)) = decode_message(&payload) {
+                                                    debug!("Received message with type: {msg_type}");
+                                                    if msg_type == "ServerShuttingDown" {
+                                                        server_shutting_down = true;
+                                                    }
+                                                    if msg_type == "ServerCapabilitiesResponse" {
+                                                        update_capabilities(&capabilities, &msg_payload);
+                                                    }
+                                                    response_cache.invalidate_on_broadcast(&msg_type);
+                                                    response_cache.complete(&request_id, &msg_type, &msg_payload);
+                                                    if let Some(handler) = handlers.lock().await.get(&msg_type) {
+                                                        handler(msg_payload);
+                                                    } else {
+                                                        error!("No handler registered for message type while receiving response: {msg_type}");
+                                                    }
                                                 }
+                                        }
+                                        Some(Err(e)) => {
+                                            if !server_shutting_down {
+                                                error!("Error receiving message: {e}");
                                             }
+                                            break;
+                                        }
+                                        None => break,
                                     }
-                                    Some(Err(e)) => {
-                                        error!("Error receiving message: {e}");
-                                        break;
-                                    }
-                                    None => break,
                                 }
-                            }
-                            _ = cancel_token_clone.cancelled() => {
-                                info!("Received cancel signal, closing connection");
-                                let mut write = write_clone.lock().await;
-                                if let Err(e) = write.close().await {
-                                    error!("Error closing websocket connection: {e}");
+                                _ = cancel_token_clone.cancelled() => {
+                                    info!("Received cancel signal, closing connection");
+                                    let mut write = write_clone.lock().await;
+                                    if let Err(e) = write.close().await {
+                                        error!("Error closing websocket connection: {e}");
+                                    }
+                                    break 'connection;
                                 }
-                                break;
                             }
                         }
+
+                        // Cached responses belong to this connection only
+                        response_cache.clear();
+
+                        if server_shutting_down {
+                            info!("Server is shutting down, reconnecting");
+                        } else {
+                            warn!("Connection lost, reconnecting");
+                        }
+
+                        let Some(ws_stream) =
+                            reconnect(&url, Arc::clone(&config), &cancel_token_clone).await
+                        else {
+                            break;
+                        };
+
+                        info!("WebSocket connection re-established");
+                        let (new_write, new_read) = ws_stream.split();
+                        *write_clone.lock().await = new_write;
+                        read = new_read;
+
+                        // The server may have been updated in between
+                        *capabilities.write().unwrap() = None;
+                        if let Err(e) = request_capabilities(&write_clone).await {
+                            error!("Failed to request the capabilities of the server: {e}");
+                        }
                     }
+
+                    // Cached responses belong to this connection and library only
+                    response_cache.clear();
                 };
 
                 tokio::spawn(message_loop());
@@ -279,12 +436,9 @@ impl WebSocketDartBridge {
                     node_id: Arc::new(node_id),
                     main_db: Arc::new(connect_fake_main_db().await?),
                     recommend_db: Arc::new(connect_fake_recommendation_db()?),
+                    library_lock: None,
                     main_token: Arc::clone(&cancel_token),
-                    task_tokens: Arc::new(Mutex::new(TaskTokens {
-                        scan_token: None,
-                        analyze_token: None,
-                        deduplicate_token: None,
-                    })),
+                    task_registry: Arc::new(TaskRegistry::new()),
                     player: Arc::new(Mutex::new(MockPlayer {})),
                     sfx_player,
                     scrobbler: Arc::new(Mutex::new(MockScrobblingManager::new())),
@@ -293,7 +447,9 @@ impl WebSocketDartBridge {
                     cert_validator,
                     permission_manager,
                     server_manager: OnceLock::new(),
+                    remote_output: Arc::new(RemoteOutputManager::default()),
                     running_mode: RunningMode::Server,
+                    started_at: Instant::now(),
                 };
 
                 let global_params = Arc::new(global_params);
@@ -332,9 +488,34 @@ pub async fn server_player_loop(
     );
     let client_config = cert_validator.clone().into_client_config();
 
-    let hosts = decode_rnsrv_url(url).map_err(|e| anyhow::anyhow!(e.to_string()))?;
-    let host = select_best_host(hosts, Arc::new(client_config))
-        .await
+    let server_url = decode_rnsrv_url(url).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    if !server_url.is_supported() {
+        bail!(
+            "The server speaks protocol version {}, please update Rune",
+            server_url.version
+        );
+    }
+
+    let selection = probe_best_host(server_url.host_specs(), Arc::new(client_config)).await;
+
+    RemoteConnectionStatus {
+        connected: selection.host().is_some(),
+        host: selection.host().unwrap_or_default().to_string(),
+        sticky: selection.sticky,
+        probes: selection
+            .probes
+            .iter()
+            .map(|x| HostProbeResult {
+                host: x.host.clone(),
+                latency_ms: x.latency.map(|x| x.as_millis() as u32),
+                error: x.error.clone(),
+            })
+            .collect(),
+    }
+    .send_signal_to_dart();
+
+    let host = selection
+        .into_host()
         .with_context(|| "Failed to select the best host")?;
 
     let client_config = Arc::new(cert_validator.clone().into_client_config());
@@ -367,11 +548,15 @@ pub async fn server_player_loop(
             SetMediaLibraryPathResponse,
             AnalyzeAudioLibraryProgress,
             AnalyzeAudioLibraryResponse,
+            SetAlbumCoverArtProgress,
             PlaybackStatus,
             ScrobbleServiceStatusUpdated,
             CrashResponse,
+            PermissionDeniedResponse,
+            UnsupportedRequestResponse,
             RealtimeFFT,
-            PlaylistUpdate
+            PlaylistUpdate,
+            ServerShuttingDown
         );
 
         bridge
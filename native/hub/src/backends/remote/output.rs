@@ -0,0 +1,362 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, anyhow, bail};
+use futures::{SinkExt, StreamExt, stream::SplitSink};
+use log::{debug, error, info, warn};
+use rustls::ClientConfig;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, RwLock, oneshot},
+    time::timeout,
+};
+use tokio_tungstenite::{
+    Connector, MaybeTlsStream, WebSocketStream, connect_async_tls_with_config,
+    tungstenite::protocol::Message as TungsteniteMessage,
+};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+use urlencoding::encode;
+use uuid::Uuid;
+
+use ::database::{
+    actions::file::get_files_by_ids, connection::MainDbConnection, playing_item::MediaFileHandle,
+};
+use ::discovery::endpoint::ServerEndpoint;
+use ::playback::player::PlayingItem;
+
+use crate::{
+    backends::remote::{RinfDartSignal, decode_message, encode_message},
+    messages::*,
+    server::{
+        api::resolve_endpoint,
+        protocol::{PROTOCOL_PARAM, PROTOCOL_VERSION},
+    },
+    utils::Broadcaster,
+};
+
+/// How long to wait for the target device to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+type WebSocketWriter = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, TungsteniteMessage>;
+type PendingRequests = Arc<std::sync::Mutex<HashMap<Uuid, oneshot::Sender<(String, Vec<u8>)>>>>;
+
+/// A connection to a trusted device that outputs the audio on behalf of
+/// this instance. Playback requests are forwarded to the device, and its
+/// playback broadcasts are mirrored to the local UI.
+pub struct RemoteOutput {
+    host: String,
+    stream_host: String,
+    write: Arc<Mutex<WebSocketWriter>>,
+    pending: PendingRequests,
+    cancel_token: CancellationToken,
+}
+
+impl RemoteOutput {
+    pub async fn connect(
+        host: &str,
+        config: Arc<ClientConfig>,
+        fingerprint: &str,
+        stream_host: &str,
+        broadcaster: Arc<dyn Broadcaster>,
+    ) -> Result<Self> {
+        let endpoint = resolve_endpoint(host, Arc::clone(&config)).await?;
+        let url = format!(
+            "{}?fingerprint={}&host={}&{PROTOCOL_PARAM}={PROTOCOL_VERSION}",
+            endpoint.ws_url(),
+            encode(fingerprint),
+            encode(host)
+        );
+
+        info!("Connecting to the remote output {host}");
+
+        let (ws_stream, _) =
+            connect_async_tls_with_config(url, None, false, Some(Connector::Rustls(config)))
+                .await
+                .with_context(|| format!("Failed to connect to {host}"))?;
+
+        let (write, mut read) = ws_stream.split();
+        let write = Arc::new(Mutex::new(write));
+        let pending: PendingRequests = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let cancel_token = CancellationToken::new();
+
+        let write_clone = Arc::clone(&write);
+        let pending_clone = Arc::clone(&pending);
+        let cancel_token_clone = cancel_token.clone();
+        let host_clone = host.to_string();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    message = read.next() => {
+                        match message {
+                            Some(Ok(TungsteniteMessage::Binary(payload))) => {
+                                if let Some((msg_type, msg_payload, request_id)) = decode_message(&payload) {
+                                    let sender = pending_clone.lock().unwrap().remove(&request_id);
+                                    match sender {
+                                        Some(sender) => {
+                                            let _ = sender.send((msg_type, msg_payload));
+                                        }
+                                        None => mirror_broadcast(&*broadcaster, &msg_type, &msg_payload),
+                                    }
+                                }
+                            }
+                            Some(Ok(_)) => {}
+                            Some(Err(e)) => {
+                                error!("Error receiving message from the remote output {host_clone}: {e}");
+                                break;
+                            }
+                            None => break,
+                        }
+                    }
+                    _ = cancel_token_clone.cancelled() => {
+                        if let Err(e) = write_clone.lock().await.close().await {
+                            error!("Error closing the remote output connection: {e}");
+                        }
+                        break;
+                    }
+                }
+            }
+
+            // Requests still waiting are answered with an error by dropping the senders
+            pending_clone.lock().unwrap().clear();
+            cancel_token_clone.cancel();
+            info!("Remote output {host_clone} disconnected");
+        });
+
+        Ok(RemoteOutput {
+            host: host.to_string(),
+            stream_host: stream_host.to_string(),
+            write,
+            pending,
+            cancel_token,
+        })
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.cancel_token.is_cancelled()
+    }
+
+    async fn write_request<T>(&self, request: &T, request_id: Uuid) -> Result<()>
+    where
+        T: RinfDartSignal + Serialize,
+    {
+        if self.is_closed() {
+            bail!("The remote output {} is disconnected", self.host);
+        }
+
+        let payload = rinf::serialize(request).map_err(|e| anyhow!("{e}"))?;
+        let message = encode_message(&request.name(), &payload, Some(request_id));
+
+        self.write
+            .lock()
+            .await
+            .send(TungsteniteMessage::Binary(message.into()))
+            .await
+            .with_context(|| format!("Failed to send {} to {}", request.name(), self.host))
+    }
+
+    /// Forwards a request without waiting for the target to handle it.
+    pub async fn send<T>(&self, request: &T) -> Result<()>
+    where
+        T: RinfDartSignal + Serialize,
+    {
+        self.write_request(request, Uuid::new_v4()).await
+    }
+
+    /// Forwards a request and waits for the response of the target.
+    pub async fn request<T, R>(&self, request: &T) -> Result<R>
+    where
+        T: RinfDartSignal + Serialize,
+        R: for<'a> Deserialize<'a>,
+    {
+        let request_id = Uuid::new_v4();
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(request_id, sender);
+
+        if let Err(e) = self.write_request(request, request_id).await {
+            self.pending.lock().unwrap().remove(&request_id);
+            return Err(e);
+        }
+
+        let (response_type, payload) = match timeout(REQUEST_TIMEOUT, receiver).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => bail!("The remote output {} is disconnected", self.host),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&request_id);
+                bail!("{} timed out on {}", request.name(), self.host);
+            }
+        };
+
+        if response_type == "CrashResponse" {
+            let crash: CrashResponse = rinf::deserialize(&payload).map_err(|e| anyhow!("{e}"))?;
+            bail!(
+                "{} failed on {}: {}",
+                request.name(),
+                self.host,
+                crash.detail
+            );
+        }
+        if response_type == "PermissionDeniedResponse" {
+            let denied: PermissionDeniedResponse =
+                rinf::deserialize(&payload).map_err(|e| anyhow!("{e}"))?;
+            bail!(
+                "{} was refused by {}: {}",
+                request.name(),
+                self.host,
+                denied.detail
+            );
+        }
+        if response_type == "UnsupportedRequestResponse" {
+            let unsupported: UnsupportedRequestResponse =
+                rinf::deserialize(&payload).map_err(|e| anyhow!("{e}"))?;
+            bail!("{} on {}", unsupported.detail, self.host);
+        }
+
+        rinf::deserialize(&payload).map_err(|e| anyhow!("Deserialization failed: {e}"))
+    }
+
+    /// Maps tracks of this library to items the target can play. Tracks the
+    /// target has in its synced library are referenced by the ids of the
+    /// target, other library tracks are streamed from this device. Files
+    /// outside of the library can't be streamed and are mapped to `None`.
+    pub async fn resolve_tracks(
+        &self,
+        main_db: &MainDbConnection,
+        tracks: &[MediaFileHandle],
+    ) -> Result<Vec<Option<PlayingItemRequest>>> {
+        let local_ids: Vec<i32> = tracks
+            .iter()
+            .filter_map(|x| match x.item {
+                PlayingItem::InLibrary(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+
+        let files = get_files_by_ids(main_db, &local_ids)
+            .await
+            .with_context(|| "Failed to query tracks to resolve")?;
+        let hlc_uuids: Vec<String> = files.iter().map(|x| x.hlc_uuid.clone()).collect();
+
+        let remote_ids: Vec<i32> = if hlc_uuids.is_empty() {
+            vec![]
+        } else {
+            self.request::<_, FetchMediaFileIdsByHlcUuidsResponse>(
+                &FetchMediaFileIdsByHlcUuidsRequest {
+                    hlc_uuids: hlc_uuids.clone(),
+                },
+            )
+            .await?
+            .file_ids
+        };
+
+        let id_map: HashMap<i32, i32> = files
+            .iter()
+            .zip(remote_ids)
+            .filter(|(_, remote_id)| *remote_id != 0)
+            .map(|(file, remote_id)| (file.id, remote_id))
+            .collect();
+
+        tracks
+            .iter()
+            .map(|track| {
+                let item = match &track.item {
+                    PlayingItem::InLibrary(id) => match id_map.get(id) {
+                        Some(remote_id) => PlayingItem::InLibrary(*remote_id),
+                        None => PlayingItem::Online(
+                            self.library_file_url(&track.directory, &track.file_name)?,
+                            None,
+                        ),
+                    },
+                    PlayingItem::Online(url, _) => PlayingItem::Online(url.clone(), None),
+                    PlayingItem::IndependentFile(path) => {
+                        warn!("Skipping {path}, files outside of the library can't be streamed");
+                        return Ok(None);
+                    }
+                    PlayingItem::Unknown => return Ok(None),
+                };
+
+                Ok(Some(item.into()))
+            })
+            .collect()
+    }
+
+    fn library_file_url(&self, directory: &str, file_name: &str) -> Result<String> {
+        let endpoint = ServerEndpoint::parse(&self.stream_host)?;
+        let mut url = Url::parse(&endpoint.http_url("/"))
+            .with_context(|| format!("Invalid stream host: {}", self.stream_host))?;
+
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid stream host: {}", self.stream_host))?
+            .pop_if_empty()
+            .extend(["files", "library"])
+            .extend(directory.split('/').filter(|x| !x.is_empty()))
+            .push(file_name);
+
+        Ok(url.to_string())
+    }
+
+    /// Stops the playback on the target and closes the connection.
+    pub async fn close(&self) {
+        if self.is_closed() {
+            return;
+        }
+
+        if let Err(e) = self.send(&PauseRequest {}).await {
+            warn!("Failed to stop the playback on {}: {e:#}", self.host);
+        }
+
+        self.cancel_token.cancel();
+    }
+}
+
+fn mirror_broadcast(broadcaster: &dyn Broadcaster, msg_type: &str, payload: &[u8]) {
+    let result = match msg_type {
+        "PlaybackStatus" => {
+            rinf::deserialize::<PlaybackStatus>(payload).map(|x| broadcaster.broadcast(&x))
+        }
+        "PlaylistUpdate" => {
+            rinf::deserialize::<PlaylistUpdate>(payload).map(|x| broadcaster.broadcast(&x))
+        }
+        _ => {
+            debug!("Ignoring {msg_type} from the remote output");
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        error!("Failed to decode {msg_type} from the remote output: {e}");
+    }
+}
+
+/// Holds the remote output playback is currently forwarded to, if any.
+#[derive(Default)]
+pub struct RemoteOutputManager {
+    output: RwLock<Option<Arc<RemoteOutput>>>,
+}
+
+impl RemoteOutputManager {
+    /// Returns the remote output if it is still connected.
+    pub async fn active(&self) -> Option<Arc<RemoteOutput>> {
+        self.output
+            .read()
+            .await
+            .as_ref()
+            .filter(|x| !x.is_closed())
+            .cloned()
+    }
+
+    /// Replaces the current remote output, stopping the previous one.
+    /// Passing `None` switches back to the local output.
+    pub async fn replace(&self, output: Option<RemoteOutput>) {
+        let previous = std::mem::replace(&mut *self.output.write().await, output.map(Arc::new));
+
+        if let Some(previous) = previous {
+            info!("Stopping the remote output {}", previous.host());
+            previous.close().await;
+        }
+    }
+}
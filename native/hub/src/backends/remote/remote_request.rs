@@ -50,6 +50,7 @@ macro_rules! forward_event_to_remote {
             paste::paste! {
                 let [<cancel_token_ $request:snake>] = Arc::clone(&$cancel_token);
                 let write_clone = Arc::clone(&$write);
+                let (handlers_clone, cache_clone, capabilities_clone) = $bridge;
                 let [<handle_event_ $request:snake>] = || async move {
                     let receiver = <$request>::get_dart_signal_receiver();
                     loop {
@@ -70,7 +71,40 @@ macro_rules! forward_event_to_remote {
                                 };
 
                                 let type_name = dart_signal.message.name();
-                                let encoded_message = encode_message(&type_name, &payload, Some(Uuid::new_v4()));
+                                let request_id = Uuid::new_v4();
+
+                                // Requests the server doesn't answer would never get a response
+                                let unsupported_by = capabilities_clone
+                                    .read()
+                                    .unwrap()
+                                    .as_ref()
+                                    .filter(|x| !x.supports(&type_name))
+                                    .map(|x| x.protocol_version);
+                                if let Some(protocol_version) = unsupported_by {
+                                    warn!("Not sending {type_name}, which the server doesn't answer");
+                                    unsupported_request(&type_name, protocol_version).send_signal_to_dart();
+                                    continue;
+                                }
+
+                                // Serve the response from the cache if possible
+                                match CacheKey::from_request(&type_name, &payload) {
+                                    Some((key, bypass)) => {
+                                        if !bypass
+                                            && let Some((response_type, response)) = cache_clone.lookup(&key)
+                                        {
+                                            debug!("Serving {type_name} from the response cache");
+                                            if let Some(handler) = handlers_clone.lock().await.get(&response_type) {
+                                                handler(response);
+                                            }
+                                            continue;
+                                        }
+
+                                        cache_clone.track(request_id, key);
+                                    }
+                                    None => cache_clone.invalidate_on_request(&type_name),
+                                }
+
+                                let encoded_message = encode_message(&type_name, &payload, Some(request_id));
 
                                 // Send the message
                                 let result = write_clone.lock().await
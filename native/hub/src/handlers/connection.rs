@@ -1,7 +1,10 @@
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
 
-use database::connection::{LibraryState, check_library_state};
+use database::{
+    actions::schema::inspect_main_db_schema,
+    connection::{LibraryState, StorageMode, check_library_state, get_storage_info},
+};
 
 use crate::{
     Session, Signal,
@@ -9,6 +12,15 @@ use crate::{
     utils::{GlobalParams, ParamsExtractor},
 };
 
+/// The schema of the main database of a portable library, the databases of
+/// redirected ones are only found once the library is opened.
+async fn portable_library_schema(lib_path: &str) -> Result<Option<SchemaInfo>> {
+    let storage_info = get_storage_info(lib_path, None, false)?;
+    let status = inspect_main_db_schema(&storage_info.get_main_db_path()).await?;
+
+    Ok(status.map(Into::into))
+}
+
 impl ParamsExtractor for TestLibraryInitializedRequest {
     type Params = ();
 
@@ -37,12 +49,22 @@ impl Signal for TestLibraryInitializedRequest {
                     success: true,
                     error: None,
                     not_ready: true,
+                    schema: None,
                 },
-                LibraryState::Initialized(_) => TestLibraryInitializedResponse {
+                LibraryState::Initialized(mode) => TestLibraryInitializedResponse {
                     path: media_library_path.clone(),
                     success: true,
                     error: None,
                     not_ready: false,
+                    schema: match mode {
+                        StorageMode::Portable => portable_library_schema(&media_library_path)
+                            .await
+                            .unwrap_or_else(|e| {
+                                warn!("Failed to read the schema of the library: {e:#}");
+                                None
+                            }),
+                        StorageMode::Redirected(_) => None,
+                    },
                 },
             },
             Err(e) => TestLibraryInitializedResponse {
@@ -50,6 +72,7 @@ impl Signal for TestLibraryInitializedRequest {
                 success: false,
                 error: Some(format!("{e:#?}")),
                 not_ready: false,
+                schema: None,
             },
         };
 
@@ -1,20 +1,22 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use futures::future::join_all;
 
 use tokio::task;
 
 use ::database::{
-    connection::{MainDbConnection, RecommendationDbConnection},
+    actions::cover_art::{embed_album_cover_art, set_album_cover_art},
+    connection::{MainDbConnection, RecommendationDbConnection, probe_library_writable},
     playing_item::dispatcher::PlayingItemActionDispatcher,
 };
 use ::fsio::FsIo;
+use ::metadata::cover_art::{EmbedFormat, EmbedOptions, cover_art_from_image};
 
 use crate::{
     Session, Signal,
     messages::*,
-    utils::{GlobalParams, ParamsExtractor, query_cover_arts},
+    utils::{Broadcaster, GlobalParams, ParamsExtractor, query_cover_arts},
 };
 
 impl ParamsExtractor for GetCoverArtIdsByMixQueriesRequest {
@@ -133,3 +135,120 @@ impl Signal for GetPrimaryColorByTrackIdRequest {
         }
     }
 }
+
+impl From<EmbedImageFormat> for EmbedFormat {
+    fn from(format: EmbedImageFormat) -> Self {
+        match format {
+            EmbedImageFormat::Jpeg => EmbedFormat::Jpeg,
+            EmbedImageFormat::Png => EmbedFormat::Png,
+        }
+    }
+}
+
+impl ParamsExtractor for SetAlbumCoverArtRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<String>,
+        Arc<dyn Broadcaster>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+            Arc::clone(&all_params.broadcaster),
+        )
+    }
+}
+
+impl Signal for SetAlbumCoverArtRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<String>,
+        Arc<dyn Broadcaster>,
+    );
+    type Response = SetAlbumCoverArtResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, node_id, broadcaster): Self::Params,
+        session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let mut response = SetAlbumCoverArtResponse {
+            updated_count: 0,
+            embedded_count: 0,
+            unchanged_count: 0,
+            unsupported_file_ids: Vec::new(),
+            failed_file_ids: Vec::new(),
+            success: true,
+            error: String::new(),
+        };
+
+        let result = async {
+            if request.embed && !probe_library_writable(&fsio, &lib_path).await {
+                bail!("The library is read-only, the cover art cannot be embedded into its files");
+            }
+
+            if let Some(image_path) = &request.image_path {
+                // Remote clients can't read the files of this device
+                if session.is_some() {
+                    bail!("Only the local client can set a cover art from an image file");
+                }
+
+                let data = tokio::fs::read(image_path)
+                    .await
+                    .with_context(|| format!("Failed to read {image_path}"))?;
+                let cover_art = cover_art_from_image(data)
+                    .with_context(|| format!("{image_path} is not a usable image"))?;
+                for album_id in &request.album_ids {
+                    let files =
+                        set_album_cover_art(&main_db, *album_id, &cover_art, &node_id).await?;
+                    response.updated_count += i32::try_from(files.len())?;
+                }
+            }
+
+            if request.embed {
+                let options = EmbedOptions {
+                    max_dimension: request.max_dimension,
+                    format: request.format.into(),
+                };
+                let report = embed_album_cover_art(
+                    &fsio,
+                    &main_db,
+                    Path::new(lib_path.as_str()),
+                    &request.album_ids,
+                    &options,
+                    |progress, total| {
+                        broadcaster.broadcast(&SetAlbumCoverArtProgress {
+                            progress: progress.try_into().unwrap_or(i32::MAX),
+                            total: total.try_into().unwrap_or(i32::MAX),
+                        });
+                    },
+                )
+                .await?;
+                response.embedded_count = report.embedded.try_into()?;
+                response.unchanged_count = report.unchanged.try_into()?;
+                response.unsupported_file_ids =
+                    report.unsupported.into_iter().map(|(id, _)| id).collect();
+                response.failed_file_ids = report.failed.into_iter().map(|(id, _)| id).collect();
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            response.success = false;
+            response.error = format!("{e:#}");
+        }
+
+        Ok(Some(response))
+    }
+}
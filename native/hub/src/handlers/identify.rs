@@ -0,0 +1,198 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+
+use ::database::{
+    actions::{
+        file::get_files_by_ids,
+        metadata_edit::{apply_metadata_changes, diff_metadata, write_changes_to_tags},
+    },
+    connection::{MainDbConnection, probe_library_writable},
+};
+use ::fsio::FsIo;
+use ::tag_editor::shazam::recognition::{
+    RecognizedTrack, centered_excerpt, pcm_excerpt, recognize,
+};
+
+use crate::{
+    Session, Signal, TaskRegistry,
+    messages::*,
+    utils::{GlobalParams, ParamsExtractor},
+};
+
+impl From<RecognizedTrack> for IdentifiedTrack {
+    fn from(track: RecognizedTrack) -> Self {
+        IdentifiedTrack {
+            title: track.title,
+            artist: track.artist,
+            album: track.album,
+            date: track.date,
+            offset: track.offset,
+        }
+    }
+}
+
+impl IdentifiedTrack {
+    fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            ("track_title".to_owned(), self.title.clone()),
+            ("artist".to_owned(), self.artist.clone()),
+        ];
+        if let Some(album) = &self.album {
+            fields.push(("album".to_owned(), album.clone()));
+        }
+        if let Some(date) = &self.date {
+            fields.push(("date".to_owned(), date.clone()));
+        }
+
+        fields
+    }
+}
+
+impl ParamsExtractor for IdentifyAudioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<TaskRegistry>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.task_registry),
+        )
+    }
+}
+
+impl Signal for IdentifyAudioRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<TaskRegistry>,
+    );
+    type Response = IdentifyAudioResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, task_registry): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        // One identification at a time, the service being rate-limited
+        task_registry.cancel_kind(CancelTaskType::IdentifyAudio);
+        let task = task_registry.register(CancelTaskType::IdentifyAudio, &lib_path);
+        let token = task.token().clone();
+
+        let result = async {
+            let samples = match (request.file_id, &request.snippet) {
+                (Some(file_id), None) => {
+                    let Some(file) = get_files_by_ids(&main_db, &[file_id]).await?.pop() else {
+                        bail!("File {file_id} does not exist");
+                    };
+                    let path = Path::new(lib_path.as_str())
+                        .join(&file.directory)
+                        .join(&file.file_name);
+                    let fsio = Arc::clone(&fsio);
+                    let token = token.clone();
+                    tokio::task::spawn_blocking(move || centered_excerpt(&fsio, &path, Some(token)))
+                        .await??
+                }
+                (None, Some(snippet)) => pcm_excerpt(
+                    &snippet.samples,
+                    snippet.sample_rate,
+                    snippet.channels.try_into()?,
+                )?,
+                _ => bail!("Either a file or a snippet must be identified"),
+            };
+
+            recognize(&samples, &token).await
+        }
+        .await;
+        task.finish(&result);
+
+        Ok(Some(match result {
+            Ok(tracks) => IdentifyAudioResponse {
+                file_id: request.file_id,
+                matches: tracks.into_iter().map(Into::into).collect(),
+                success: true,
+                error: String::new(),
+            },
+            Err(e) => IdentifyAudioResponse {
+                file_id: request.file_id,
+                matches: Vec::new(),
+                success: false,
+                error: format!("{e:#}"),
+            },
+        }))
+    }
+}
+
+impl ParamsExtractor for ApplyIdentificationRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for ApplyIdentificationRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+    type Response = ApplyIdentificationResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let mut response = ApplyIdentificationResponse {
+            file_id: request.file_id,
+            applied_count: 0,
+            written: false,
+            success: true,
+            error: String::new(),
+        };
+
+        let result = async {
+            if request.write_tags && !probe_library_writable(&fsio, &lib_path).await {
+                bail!("The library is read-only, tags cannot be written to its files");
+            }
+
+            let edits = vec![(request.file_id, request.track.fields())];
+            let changes = diff_metadata(&main_db, &edits).await?;
+            apply_metadata_changes(&main_db, &node_id, &changes).await?;
+            response.applied_count = changes.len().try_into()?;
+
+            if request.write_tags && !changes.is_empty() {
+                let report =
+                    write_changes_to_tags(&fsio, &main_db, Path::new(lib_path.as_str()), &changes)
+                        .await?;
+                if let Some((_, error)) = report.failed.first() {
+                    bail!("{error}");
+                }
+                response.written = report.written > 0;
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            response.success = false;
+            response.error = format!("{e:#}");
+        }
+
+        Ok(Some(response))
+    }
+}
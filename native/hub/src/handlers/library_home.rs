@@ -10,7 +10,10 @@ use ::database::{
         collection::{
             CollectionQuery, CollectionQueryListMode, CollectionQueryType, UnifiedCollection,
         },
-        file::{get_media_files, get_random_files, get_reverse_listed_media_files},
+        file::{
+            get_media_files, get_random_files, get_recently_added_media_files,
+            get_reverse_listed_media_files,
+        },
         metadata::{MetadataSummary, get_metadata_summary_by_files},
         mixes::query_mix_media_files,
     },
@@ -139,6 +142,9 @@ impl ComplexQuery for NaiveTrackComplexQuery {
                 get_reverse_listed_media_files(main_db, 0, 25).await
             }
             CollectionQueryListMode::Random => get_random_files(main_db, 25).await,
+            CollectionQueryListMode::RecentlyAdded => {
+                get_recently_added_media_files(main_db, 0, 25).await
+            }
         }?;
 
         build_track_collections(main_db, tracks).await
@@ -2,7 +2,6 @@ use std::{path::Path, sync::Arc};
 
 use anyhow::{Context, Result};
 use log::{debug, info, warn};
-use tokio::{sync::Mutex, task};
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
@@ -10,6 +9,7 @@ use ::analysis::utils::computing_device::ComputingDevice;
 use ::database::{
     actions::{
         analysis::analysis_audio_library,
+        content_match::{compute_file_landmarks, match_content},
         cover_art::scan_cover_arts,
         fingerprint::{
             Configuration, compare_all_pairs, compute_file_fingerprints, mark_duplicate_files,
@@ -22,30 +22,31 @@ use ::database::{
 use ::fsio::FsIo;
 
 use crate::{
-    Session, Signal, TaskTokens,
+    Session, Signal, TaskRegistry,
     messages::*,
+    server::metrics::ServerMetrics,
     utils::{Broadcaster, GlobalParams, ParamsExtractor, determine_batch_size},
 };
 
 impl ParamsExtractor for CloseLibraryRequest {
-    type Params = (Arc<String>, Arc<CancellationToken>, Arc<Mutex<TaskTokens>>);
+    type Params = (Arc<String>, Arc<CancellationToken>, Arc<TaskRegistry>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.lib_path),
             Arc::clone(&all_params.main_token),
-            Arc::clone(&all_params.task_tokens),
+            Arc::clone(&all_params.task_registry),
         )
     }
 }
 
 impl Signal for CloseLibraryRequest {
-    type Params = (Arc<String>, Arc<CancellationToken>, Arc<Mutex<TaskTokens>>);
+    type Params = (Arc<String>, Arc<CancellationToken>, Arc<TaskRegistry>);
     type Response = CloseLibraryResponse;
 
     async fn handle(
         &self,
-        (lib_path, main_token, task_tokens): Self::Params,
+        (lib_path, main_token, task_registry): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -61,13 +62,8 @@ impl Signal for CloseLibraryRequest {
             return Ok(None);
         }
 
-        let mut tokens = task_tokens.lock().await;
-        if let Some(token) = tokens.scan_token.take() {
-            token.cancel();
-        }
-        if let Some(token) = tokens.analyze_token.take() {
-            token.cancel();
-        }
+        task_registry.cancel_kind(CancelTaskType::ScanAudioLibrary);
+        task_registry.cancel_kind(CancelTaskType::AnalyzeAudioLibrary);
 
         main_token.cancel();
 
@@ -82,8 +78,9 @@ impl ParamsExtractor for ScanAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
-        Arc<Mutex<TaskTokens>>,
+        Arc<TaskRegistry>,
         Arc<dyn Broadcaster>,
+        Option<Arc<ServerMetrics>>,
     );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
@@ -91,8 +88,12 @@ impl ParamsExtractor for ScanAudioLibraryRequest {
             Arc::clone(&all_params.fsio),
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
-            Arc::clone(&all_params.task_tokens),
+            Arc::clone(&all_params.task_registry),
             Arc::clone(&all_params.broadcaster),
+            all_params
+                .server_manager
+                .get()
+                .map(|x| Arc::clone(&x.metrics)),
         )
     }
 }
@@ -102,27 +103,25 @@ impl Signal for ScanAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
-        Arc<Mutex<TaskTokens>>,
+        Arc<TaskRegistry>,
         Arc<dyn Broadcaster>,
+        Option<Arc<ServerMetrics>>,
     );
     type Response = ();
 
     async fn handle(
         &self,
-        (fsio, main_db, node_id, task_tokens, broadcaster): Self::Params,
+        (fsio, main_db, node_id, task_registry, broadcaster, server_metrics): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<()>> {
-        let mut tokens = task_tokens.lock().await;
         // If there is scanning task
-        if let Some(token) = tokens.scan_token.take() {
-            token.cancel();
-        }
+        task_registry.cancel_kind(CancelTaskType::ScanAudioLibrary);
 
-        // Create a new cancel token
-        let new_token = CancellationToken::new();
-        tokens.scan_token = Some(new_token.clone());
-        drop(tokens); // Release the lock
+        // Register the new task, with its own cancel token
+        let task = task_registry.register(CancelTaskType::ScanAudioLibrary, &dart_signal.path);
+        let new_token = task.token().clone();
+        let tasks = task_registry.tracker();
 
         // Clone all the data we need before spawning the task
         let request_path = dart_signal.path.clone();
@@ -131,7 +130,11 @@ impl Signal for ScanAudioLibraryRequest {
         let node_id_clone = Arc::clone(&node_id);
         let broadcaster_clone = Arc::clone(&broadcaster);
 
-        task::spawn_blocking(move || {
+        if let Some(metrics) = &server_metrics {
+            metrics.set_task_running("scan", true);
+        }
+
+        tasks.spawn_blocking(move || {
             let runtime = tokio::runtime::Runtime::new().unwrap();
             runtime.block_on(async move {
                 let result: Result<()> = async {
@@ -143,6 +146,7 @@ impl Signal for ScanAudioLibraryRequest {
                         true,
                         request_force,
                         |progress| {
+                            task.set_progress(progress, 0);
                             broadcaster_clone.broadcast(&ScanAudioLibraryProgress {
                                 task: ScanTaskType::IndexFiles,
                                 path: request_path.clone(),
@@ -168,6 +172,7 @@ impl Signal for ScanAudioLibraryRequest {
                     let batch_size = determine_batch_size(0.75);
                     let cloned_broadcaster = Arc::clone(&broadcaster_clone);
                     let path_for_closure = request_path.clone();
+                    let progress_task = task.clone();
 
                     scan_cover_arts(
                         fsio,
@@ -176,6 +181,7 @@ impl Signal for ScanAudioLibraryRequest {
                         &node_id_clone,
                         batch_size,
                         move |now, total| {
+                            progress_task.set_progress(now, total);
                             cloned_broadcaster.broadcast(&ScanAudioLibraryProgress {
                                 task: ScanTaskType::ScanCoverArts,
                                 path: path_for_closure.clone(),
@@ -195,6 +201,11 @@ impl Signal for ScanAudioLibraryRequest {
                     Ok(())
                 }
                 .await;
+                task.finish(&result);
+
+                if let Some(metrics) = &server_metrics {
+                    metrics.set_task_running("scan", false);
+                }
 
                 result?;
                 Ok::<(), anyhow::Error>(())
@@ -220,8 +231,9 @@ impl ParamsExtractor for AnalyzeAudioLibraryRequest {
         Arc<MainDbConnection>,
         Arc<String>,
         Arc<RecommendationDbConnection>,
-        Arc<Mutex<TaskTokens>>,
+        Arc<TaskRegistry>,
         Arc<dyn Broadcaster>,
+        Option<Arc<ServerMetrics>>,
     );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
@@ -230,8 +242,12 @@ impl ParamsExtractor for AnalyzeAudioLibraryRequest {
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
             Arc::clone(&all_params.recommend_db),
-            Arc::clone(&all_params.task_tokens),
+            Arc::clone(&all_params.task_registry),
             Arc::clone(&all_params.broadcaster),
+            all_params
+                .server_manager
+                .get()
+                .map(|x| Arc::clone(&x.metrics)),
         )
     }
 }
@@ -242,28 +258,33 @@ impl Signal for AnalyzeAudioLibraryRequest {
         Arc<MainDbConnection>,
         Arc<String>,
         Arc<RecommendationDbConnection>,
-        Arc<Mutex<TaskTokens>>,
+        Arc<TaskRegistry>,
         Arc<dyn Broadcaster>,
+        Option<Arc<ServerMetrics>>,
     );
     type Response = ();
 
     async fn handle(
         &self,
-        (fsio, main_db, node_id, recommend_db, task_tokens, broadcaster): Self::Params,
+        (
+            fsio,
+            main_db,
+            node_id,
+            recommend_db,
+            task_registry,
+            broadcaster,
+            server_metrics,
+        ): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
-        let mut tokens = task_tokens.lock().await;
-        if let Some(token) = tokens.scan_token.take() {
-            token.cancel();
-        }
-
-        let new_token = CancellationToken::new();
-        tokens.analyze_token = Some(new_token.clone());
-        drop(tokens);
+        task_registry.cancel_kind(CancelTaskType::ScanAudioLibrary);
 
         // Clone the data from dart_signal before spawning the task
         let request = dart_signal;
+        let task = task_registry.register(CancelTaskType::AnalyzeAudioLibrary, &request.path);
+        let new_token = task.token().clone();
+        let tasks = task_registry.tracker();
         debug!("Analyzing media files: {request:#?}");
 
         let request_path = request.path.clone();
@@ -271,19 +292,26 @@ impl Signal for AnalyzeAudioLibraryRequest {
         let batch_size = determine_batch_size(request.workload_factor);
         let computing_device = request.computing_device;
 
-        task::spawn_blocking(move || {
+        if let Some(metrics) = &server_metrics {
+            metrics.set_task_running("analyze", true);
+        }
+
+        tasks.spawn_blocking(move || {
             let runtime = tokio::runtime::Runtime::new().unwrap();
             runtime.block_on(async move {
                 let cloned_broadcaster = Arc::clone(&broadcaster);
+                let progress_task = task.clone();
                 let result = async {
                     let total_files = analysis_audio_library(
-                        fsio,
+                        Arc::clone(&fsio),
                         &main_db,
                         Path::new(&request_path),
                         &node_id,
                         batch_size,
                         computing_device.into(),
+                        true,
                         move |progress, total| {
+                            progress_task.set_progress(progress, total);
                             cloned_broadcaster.broadcast(&AnalyzeAudioLibraryProgress {
                                 path: closure_request_path.clone(),
                                 progress: progress.try_into().unwrap(),
@@ -299,6 +327,19 @@ impl Signal for AnalyzeAudioLibraryRequest {
                         .await
                         .with_context(|| "Recommendation synchronization failed")?;
 
+                    // Landmarks for matching duplicates by their content
+                    compute_file_landmarks(
+                        fsio,
+                        &main_db,
+                        Path::new(&request_path),
+                        &node_id,
+                        batch_size,
+                        |_, _| {},
+                        Some(new_token.clone()),
+                    )
+                    .await
+                    .with_context(|| "Landmark computation failed")?;
+
                     broadcaster.broadcast(&AnalyzeAudioLibraryResponse {
                         path: request_path.clone(),
                         total: total_files as i32,
@@ -307,6 +348,11 @@ impl Signal for AnalyzeAudioLibraryRequest {
                     Ok::<(), anyhow::Error>(())
                 }
                 .await;
+                task.finish(&result);
+
+                if let Some(metrics) = &server_metrics {
+                    metrics.set_task_running("analyze", false);
+                }
 
                 if let Err(e) = result {
                     eprintln!("Error: {e:?}");
@@ -323,7 +369,7 @@ impl ParamsExtractor for DeduplicateAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
-        Arc<Mutex<TaskTokens>>,
+        Arc<TaskRegistry>,
         Arc<dyn Broadcaster>,
     );
 
@@ -332,7 +378,7 @@ impl ParamsExtractor for DeduplicateAudioLibraryRequest {
             Arc::clone(&all_params.fsio),
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.node_id),
-            Arc::clone(&all_params.task_tokens),
+            Arc::clone(&all_params.task_registry),
             Arc::clone(&all_params.broadcaster),
         )
     }
@@ -343,38 +389,34 @@ impl Signal for DeduplicateAudioLibraryRequest {
         Arc<FsIo>,
         Arc<MainDbConnection>,
         Arc<String>,
-        Arc<Mutex<TaskTokens>>,
+        Arc<TaskRegistry>,
         Arc<dyn Broadcaster>,
     );
     type Response = ();
 
     async fn handle(
         &self,
-        (fsio, main_db, node_id, task_tokens, broadcaster): Self::Params,
+        (fsio, main_db, node_id, task_registry, broadcaster): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
-        let mut tokens = task_tokens.lock().await;
-        if let Some(token) = tokens.deduplicate_token.take() {
-            token.cancel();
-        }
-
-        let new_token = CancellationToken::new();
-        tokens.deduplicate_token = Some(new_token.clone());
-        drop(tokens);
+        task_registry.cancel_kind(CancelTaskType::DeduplicateAudioLibrary);
 
         let request = dart_signal;
+        let task = task_registry.register(CancelTaskType::DeduplicateAudioLibrary, &request.path);
+        let new_token = task.token().clone();
+        let tasks = task_registry.tracker();
         let request_path = Arc::new(request.path.clone());
         let batch_size = determine_batch_size(request.workload_factor);
         let config = Configuration::default();
         let similarity_threshold = request.similarity_threshold;
 
         let request_path_clone = request_path.clone();
-        task::spawn_blocking(move || {
+        tasks.spawn_blocking(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
 
             let request_path_clone = request_path_clone.clone();
-            rt.block_on(async {
+            let result = rt.block_on(async {
                 let uuid_node_id = match Uuid::parse_str(&node_id) {
                     Ok(id) => id,
                     Err(e) => {
@@ -390,20 +432,22 @@ impl Signal for DeduplicateAudioLibraryRequest {
                 };
                 let node_id = uuid_node_id.to_string();
 
-                // Stage 1: Compute fingerprints (0% - 33%)
+                // Stage 1: Compute fingerprints (0% - 25%)
                 let broadcaster_clone = Arc::clone(&broadcaster);
                 let progress_path = request_path_clone.to_string();
+                let progress_task = task.clone();
 
                 let request_path_clone = request_path_clone.to_string();
                 compute_file_fingerprints(
-                    fsio,
+                    Arc::clone(&fsio),
                     &main_db,
                     Path::new(&request_path_clone),
                     &node_id,
                     batch_size,
                     move |cur, total| {
-                        let progress = cur as f32 / total as f32 * 0.33;
+                        let progress = cur as f32 / total as f32 * 0.25;
 
+                        progress_task.set_progress((progress * 100.0) as usize, 100);
                         broadcaster_clone.broadcast(&DeduplicateAudioLibraryProgress {
                             path: progress_path.clone(),
                             progress: (progress * 100.0) as i32,
@@ -416,17 +460,19 @@ impl Signal for DeduplicateAudioLibraryRequest {
 
                 info!(" Compute fingerprints completed.");
 
-                // Stage 2: Compare all pairs (33% - 66%)
+                // Stage 2: Compare all pairs (25% - 50%)
                 let broadcaster_clone = Arc::clone(&broadcaster);
                 let progress_path = request_path_clone.to_string();
+                let progress_task = task.clone();
 
                 compare_all_pairs(
                     &main_db,
                     &node_id,
                     batch_size,
                     move |cur, total| {
-                        let progress = 0.33 + cur as f32 / total as f32 * 0.33;
+                        let progress = 0.25 + cur as f32 / total as f32 * 0.25;
 
+                        progress_task.set_progress((progress * 100.0) as usize, 100);
                         broadcaster_clone.broadcast(&DeduplicateAudioLibraryProgress {
                             path: progress_path.clone(),
                             progress: (progress * 100.0) as i32,
@@ -441,14 +487,66 @@ impl Signal for DeduplicateAudioLibraryRequest {
 
                 info!("Comparing fingerprints completed.");
 
-                // Stage 3: Mark duplicates (66% - 100%)
+                // Stage 3: Match the content of the files (50% - 75%), the
+                // matches being reported apart from the similarities
+                let broadcaster_clone = Arc::clone(&broadcaster);
+                let progress_path = request_path_clone.to_string();
+                let progress_task = task.clone();
+
+                compute_file_landmarks(
+                    fsio,
+                    &main_db,
+                    Path::new(&request_path_clone),
+                    &node_id,
+                    batch_size,
+                    move |cur, total| {
+                        let progress = 0.5 + cur as f32 / total as f32 * 0.15;
+
+                        progress_task.set_progress((progress * 100.0) as usize, 100);
+                        broadcaster_clone.broadcast(&DeduplicateAudioLibraryProgress {
+                            path: progress_path.clone(),
+                            progress: (progress * 100.0) as i32,
+                            total: 100,
+                        });
+                    },
+                    Some(new_token.clone()),
+                )
+                .await?;
+
+                if !new_token.is_cancelled() {
+                    let broadcaster_clone = Arc::clone(&broadcaster);
+                    let progress_path = request_path_clone.to_string();
+                    let progress_task = task.clone();
+
+                    match_content(
+                        &main_db,
+                        move |cur, total| {
+                            let progress = 0.65 + cur as f32 / total as f32 * 0.1;
+
+                            progress_task.set_progress((progress * 100.0) as usize, 100);
+                            broadcaster_clone.broadcast(&DeduplicateAudioLibraryProgress {
+                                path: progress_path.clone(),
+                                progress: (progress * 100.0) as i32,
+                                total: 100,
+                            });
+                        },
+                        Some(new_token.clone()),
+                    )
+                    .await?;
+                }
+
+                info!("Matching content completed.");
+
+                // Stage 4: Mark duplicates (75% - 100%)
                 if !new_token.is_cancelled() {
                     let broadcaster_clone = Arc::clone(&broadcaster);
                     let progress_path = request_path_clone.to_string();
+                    let progress_task = task.clone();
 
                     mark_duplicate_files(&main_db, similarity_threshold, move |cur, total| {
-                        let progress = 0.66 + cur as f32 / total as f32 * 0.34;
+                        let progress = 0.75 + cur as f32 / total as f32 * 0.25;
 
+                        progress_task.set_progress((progress * 100.0) as usize, 100);
                         broadcaster_clone.broadcast(&DeduplicateAudioLibraryProgress {
                             path: progress_path.clone(),
                             progress: (progress * 100.0) as i32,
@@ -464,7 +562,10 @@ impl Signal for DeduplicateAudioLibraryRequest {
                 });
 
                 Ok::<(), anyhow::Error>(())
-            })
+            });
+            task.finish(&result);
+
+            result
         });
 
         Ok(Some(()))
@@ -472,52 +573,65 @@ impl Signal for DeduplicateAudioLibraryRequest {
 }
 
 impl ParamsExtractor for CancelTaskRequest {
-    type Params = (Arc<Mutex<TaskTokens>>,);
+    type Params = (Arc<TaskRegistry>,);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.task_tokens),)
+        (Arc::clone(&all_params.task_registry),)
     }
 }
 
 impl Signal for CancelTaskRequest {
-    type Params = (Arc<Mutex<TaskTokens>>,);
+    type Params = (Arc<TaskRegistry>,);
     type Response = CancelTaskResponse;
 
     async fn handle(
         &self,
-        (task_tokens,): Self::Params,
+        (task_registry,): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
         let request = dart_signal;
-        let mut tokens = task_tokens.lock().await;
-
-        let success = match request.r#type {
-            CancelTaskType::AnalyzeAudioLibrary => {
-                if let Some(token) = tokens.analyze_token.take() {
-                    warn!("Cancelling analyze task");
-                    token.cancel();
-                    true
-                } else {
-                    false
-                }
+
+        let success = match &request.task_id {
+            Some(task_id) => {
+                warn!("Cancelling task {task_id}");
+                task_registry.cancel(task_id)
             }
-            CancelTaskType::ScanAudioLibrary => {
-                if let Some(token) = tokens.scan_token.take() {
-                    warn!("Cancelling scan task");
-                    token.cancel();
-                    true
-                } else {
-                    false
-                }
+            None => {
+                warn!("Cancelling {:?} tasks", request.r#type);
+                task_registry.cancel_kind(request.r#type)
             }
-            _ => false,
         };
 
         Ok(Some(CancelTaskResponse {
             path: request.path.clone(),
             r#type: request.r#type,
+            task_id: request.task_id.clone(),
             success,
         }))
     }
 }
+
+impl ParamsExtractor for ListTasksRequest {
+    type Params = (Arc<TaskRegistry>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.task_registry),)
+    }
+}
+
+impl Signal for ListTasksRequest {
+    type Params = (Arc<TaskRegistry>,);
+    type Response = ListTasksResponse;
+
+    async fn handle(
+        &self,
+        (task_registry,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        Ok(Some(ListTasksResponse {
+            tasks: task_registry.list(),
+        }))
+    }
+}
@@ -1,18 +1,45 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use anyhow::{Context, Result};
+use chrono::DateTime;
+use log::info;
 
 use ::database::{
-    actions::logging::{clear_logs, delete_log, list_log},
+    actions::{
+        audit_log::{AuditLogFilter, list_audit_log},
+        logging::{LogFilter, clear_logs, delete_log, list_log, select_logs},
+    },
     connection::MainDbConnection,
 };
+use ::fsio::FsIo;
 
 use crate::{
     Session, Signal,
     messages::*,
-    utils::{GlobalParams, ParamsExtractor},
+    utils::{
+        GlobalParams, ParamsExtractor,
+        log_export::logs_to_ndjson,
+        log_filter::{current_log_filter, set_log_filter},
+    },
 };
 
+fn log_filter(query: &LogQuery) -> Result<LogFilter> {
+    Ok(LogFilter {
+        min_level: query
+            .min_level
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .with_context(|| format!("Invalid log level: {:?}", query.min_level))?,
+        domain: query.domain.clone(),
+        start: query
+            .start_time
+            .and_then(|x| DateTime::from_timestamp(x, 0)),
+        end: query.end_time.and_then(|x| DateTime::from_timestamp(x, 0)),
+        text: query.text.clone(),
+    })
+}
+
 impl ParamsExtractor for ListLogRequest {
     type Params = (Arc<MainDbConnection>,);
 
@@ -35,6 +62,7 @@ impl Signal for ListLogRequest {
 
         let result = list_log(
             &main_db,
+            log_filter(&request.query)?,
             request.cursor.try_into()?,
             request.page_size.try_into()?,
         )
@@ -61,6 +89,62 @@ impl Signal for ListLogRequest {
     }
 }
 
+impl ParamsExtractor for ExportLogsRequest {
+    type Params = (Arc<MainDbConnection>, Arc<FsIo>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.fsio),
+        )
+    }
+}
+
+impl Signal for ExportLogsRequest {
+    type Params = (Arc<MainDbConnection>, Arc<FsIo>);
+    type Response = ExportLogsResponse;
+
+    async fn handle(
+        &self,
+        (main_db, fsio): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let result = async {
+            let logs = select_logs(&main_db, log_filter(&request.query)?)
+                .await
+                .context("Failed to select logs")?;
+            fsio.write_string(
+                Path::new(&request.path),
+                &logs_to_ndjson(&logs, request.redact_paths),
+            )
+            .await
+            .with_context(|| format!("Failed to write logs to {}", request.path))?;
+
+            Ok::<_, anyhow::Error>(logs.len())
+        }
+        .await;
+
+        Ok(Some(match result {
+            Ok(count) => {
+                info!("Exported {count} log entries to {}", request.path);
+                ExportLogsResponse {
+                    success: true,
+                    exported_count: count as u32,
+                    error: None,
+                }
+            }
+            Err(e) => ExportLogsResponse {
+                success: false,
+                exported_count: 0,
+                error: Some(format!("{e:#}")),
+            },
+        }))
+    }
+}
+
 impl ParamsExtractor for ClearLogRequest {
     type Params = (Arc<MainDbConnection>,);
 
@@ -115,3 +199,93 @@ impl Signal for RemoveLogRequest {
         }))
     }
 }
+
+impl ParamsExtractor for ListAuditLogRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for ListAuditLogRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = ListAuditLogResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let filter = AuditLogFilter {
+            fingerprint: request.fingerprint.clone(),
+            start: request
+                .start_time
+                .and_then(|x| DateTime::from_timestamp(x, 0)),
+            end: request
+                .end_time
+                .and_then(|x| DateTime::from_timestamp(x, 0)),
+        };
+
+        let result = list_audit_log(
+            &main_db,
+            filter,
+            request.cursor.try_into()?,
+            request.page_size.try_into()?,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to list audit log: cursor={}, page_size={}",
+                request.cursor, request.page_size
+            )
+        })?;
+
+        Ok(Some(ListAuditLogResponse {
+            result: result
+                .into_iter()
+                .map(|x| AuditLogDetail {
+                    id: x.id,
+                    request_type: x.request_type,
+                    fingerprint: x.fingerprint,
+                    alias: x.alias,
+                    parameters: x.parameters,
+                    success: x.success,
+                    date: x.date.timestamp(),
+                })
+                .collect(),
+        }))
+    }
+}
+
+impl ParamsExtractor for SetLogLevelRequest {
+    type Params = ();
+
+    fn extract_params(&self, _: &GlobalParams) -> Self::Params {}
+}
+
+impl Signal for SetLogLevelRequest {
+    type Params = ();
+    type Response = SetLogLevelResponse;
+
+    async fn handle(
+        &self,
+        _: Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let result = set_log_filter(&dart_signal.filter);
+        if result.is_ok() {
+            info!("Log filter changed to {}", dart_signal.filter.trim());
+        }
+
+        Ok(Some(SetLogLevelResponse {
+            success: result.is_ok(),
+            error: result.err().map(|e| format!("{e:#}")),
+            filter: current_log_filter(),
+        }))
+    }
+}
@@ -6,7 +6,11 @@ use ::database::{
     connection::MainDbConnection, playing_item::dispatcher::PlayingItemActionDispatcher,
 };
 use ::fsio::FsIo;
-use ::lyric::{lrc::parse_lrc, parser::parse_audio_lyrics};
+use ::lyric::{
+    lrc::parse_lrc,
+    parser::parse_audio_lyrics,
+    types::{LyricFile, LyricLine, TimeTag, VoiceType},
+};
 use ::metadata::reader::get_lyrics;
 use ::playback::player::PlayingItem;
 
@@ -16,6 +20,32 @@ use crate::{
     utils::{GlobalParams, ParamsExtractor},
 };
 
+/// Embedded lyrics without any time tag, every line starting at zero so
+/// they are shown unsynced.
+fn parse_plain_lyrics(content: &str) -> LyricFile {
+    let zero = TimeTag {
+        minutes: 0,
+        seconds: 0,
+        milliseconds: 0,
+    };
+
+    let mut lyric = LyricFile::new();
+    lyric.lyrics = content
+        .lines()
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .map(|text| LyricLine {
+            start_time: zero.clone(),
+            end_time: zero.clone(),
+            voice_type: VoiceType::Default,
+            word_time_tags: vec![(zero.clone(), zero.clone(), text.clone())],
+            text,
+        })
+        .collect();
+
+    lyric
+}
+
 impl ParamsExtractor for GetLyricByTrackIdRequest {
     type Params = (Arc<FsIo>, Arc<String>, Arc<MainDbConnection>);
 
@@ -60,7 +90,13 @@ impl Signal for GetLyricByTrackIdRequest {
                     let build_in_lyric = get_lyrics(path).unwrap_or_default();
 
                     let lyrics = match build_in_lyric {
-                        Some(x) => Some(parse_lrc(&x)),
+                        Some(x) => Some(parse_lrc(&x).map(|lyric| {
+                            if lyric.lyrics.is_empty() {
+                                parse_plain_lyrics(&x)
+                            } else {
+                                lyric
+                            }
+                        })),
                         None => parse_audio_lyrics(path.to_path_buf()),
                     };
 
@@ -1,18 +1,32 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
+use log::warn;
 use sea_orm::DatabaseConnection;
+use tokio::sync::Mutex;
 
 use ::database::{
     actions::{
         cover_art::{bake_cover_art_by_file_ids, bake_cover_art_by_media_files},
-        file::{get_files_by_ids, get_media_files, get_media_files_count, list_files},
+        file::{
+            get_file_ids_by_hlc_uuids, get_files_by_ids, get_media_files, get_media_files_count,
+            list_files,
+        },
         metadata::{get_metadata_summary_by_files, get_parsed_file_by_id},
+        metadata_edit::{
+            self, FileNamePattern, apply_metadata_changes, diff_metadata,
+            infer_metadata_from_paths, write_changes_to_tags,
+        },
+        organize::{self, OrganizePlan, PathTemplate, apply_organize_plan, plan_organize},
+        tag_normalize::{DEFAULT_TITLE_CASE_EXCEPTIONS, NormalizationRules, normalize_metadata},
     },
-    connection::MainDbConnection,
+    connection::{MainDbConnection, probe_library_writable},
+    playing_item::dispatcher::PlayingItemActionDispatcher,
 };
 use ::fsio::FsIo;
+use ::playback::player::{Playable, PlayingItem};
 
 use crate::{
     Session, Signal,
@@ -246,3 +260,447 @@ impl Signal for GetMediaFilesCountRequest {
         }))
     }
 }
+
+impl ParamsExtractor for FetchMediaFileIdsByHlcUuidsRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for FetchMediaFileIdsByHlcUuidsRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = FetchMediaFileIdsByHlcUuidsResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let file_ids = get_file_ids_by_hlc_uuids(&main_db, &dart_signal.hlc_uuids)
+            .await
+            .with_context(|| "Failed to get media file ids by HLC UUIDs")?;
+
+        Ok(Some(FetchMediaFileIdsByHlcUuidsResponse { file_ids }))
+    }
+}
+
+fn inferred_metadata_to_messages(
+    inferred: &[metadata_edit::InferredMetadata],
+) -> Vec<InferredMetadata> {
+    inferred
+        .iter()
+        .map(|x| InferredMetadata {
+            file_id: x.file_id,
+            path: x.path.clone(),
+            matched: x.fields.is_some(),
+            fields: x
+                .fields
+                .iter()
+                .flatten()
+                .map(|(key, value)| MetadataField {
+                    key: key.clone(),
+                    value: value.clone(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+impl ParamsExtractor for PreviewPathMetadataRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for PreviewPathMetadataRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = PreviewPathMetadataResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let result = async {
+            let pattern = FileNamePattern::new(&dart_signal.pattern)?;
+            infer_metadata_from_paths(&main_db, &dart_signal.file_ids, &pattern).await
+        }
+        .await;
+
+        Ok(Some(match result {
+            Ok(inferred) => PreviewPathMetadataResponse {
+                results: inferred_metadata_to_messages(&inferred),
+                success: true,
+                error: String::new(),
+            },
+            Err(e) => PreviewPathMetadataResponse {
+                results: Vec::new(),
+                success: false,
+                error: format!("{e:#}"),
+            },
+        }))
+    }
+}
+
+impl ParamsExtractor for ApplyPathMetadataRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for ApplyPathMetadataRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+    type Response = ApplyPathMetadataResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let mut response = ApplyPathMetadataResponse {
+            results: Vec::new(),
+            applied_count: 0,
+            written_count: 0,
+            unsupported_file_ids: Vec::new(),
+            failed_file_ids: Vec::new(),
+            success: true,
+            error: String::new(),
+        };
+
+        let result = async {
+            if request.write_tags && !probe_library_writable(&fsio, &lib_path).await {
+                bail!("The library is read-only, tags cannot be written to its files");
+            }
+
+            let pattern = FileNamePattern::new(&request.pattern)?;
+            let inferred = infer_metadata_from_paths(&main_db, &request.file_ids, &pattern).await?;
+            response.results = inferred_metadata_to_messages(&inferred);
+
+            // Files whose path doesn't match are left as they are
+            let edits: Vec<(i32, Vec<(String, String)>)> = inferred
+                .into_iter()
+                .filter_map(|x| Some((x.file_id, x.fields?)))
+                .collect();
+            let changes = diff_metadata(&main_db, &edits).await?;
+            apply_metadata_changes(&main_db, &node_id, &changes)
+                .await
+                .with_context(|| "Failed to apply the inferred metadata")?;
+            response.applied_count = changes.len().try_into()?;
+
+            if request.write_tags && !changes.is_empty() {
+                let report =
+                    write_changes_to_tags(&fsio, &main_db, Path::new(lib_path.as_str()), &changes)
+                        .await?;
+                response.written_count = report.written.try_into()?;
+                response.unsupported_file_ids =
+                    report.unsupported.into_iter().map(|(id, _)| id).collect();
+                response.failed_file_ids = report.failed.into_iter().map(|(id, _)| id).collect();
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            response.success = false;
+            response.error = format!("{e:#}");
+        }
+
+        Ok(Some(response))
+    }
+}
+
+impl From<&TagNormalizationRules> for NormalizationRules {
+    fn from(rules: &TagNormalizationRules) -> Self {
+        NormalizationRules {
+            trim_whitespace: rules.trim_whitespace,
+            collapse_spaces: rules.collapse_spaces,
+            unify_featuring: rules.unify_featuring,
+            title_case: rules.title_case,
+            title_case_exceptions: if rules.title_case_exceptions.is_empty() {
+                DEFAULT_TITLE_CASE_EXCEPTIONS
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect()
+            } else {
+                rules.title_case_exceptions.clone()
+            },
+            sort_names: rules.sort_names,
+            fix_mojibake: rules.fix_mojibake,
+        }
+    }
+}
+
+impl ParamsExtractor for PreviewTagNormalizationRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for PreviewTagNormalizationRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = PreviewTagNormalizationResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let rules = NormalizationRules::from(&dart_signal.rules);
+
+        Ok(Some(
+            match normalize_metadata(&main_db, &dart_signal.file_ids, &rules).await {
+                Ok(changes) => PreviewTagNormalizationResponse {
+                    changes: changes
+                        .into_iter()
+                        .map(|x| MetadataFieldChange {
+                            file_id: x.file_id,
+                            key: x.key,
+                            old_value: x.old_value,
+                            new_value: x.new_value,
+                        })
+                        .collect(),
+                    success: true,
+                    error: String::new(),
+                },
+                Err(e) => PreviewTagNormalizationResponse {
+                    changes: Vec::new(),
+                    success: false,
+                    error: format!("{e:#}"),
+                },
+            },
+        ))
+    }
+}
+
+impl ParamsExtractor for ApplyTagNormalizationRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for ApplyTagNormalizationRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+    type Response = ApplyTagNormalizationResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let mut response = ApplyTagNormalizationResponse {
+            applied_count: 0,
+            written_count: 0,
+            unsupported_file_ids: Vec::new(),
+            failed_file_ids: Vec::new(),
+            success: true,
+            error: String::new(),
+        };
+
+        let result = async {
+            if request.write_tags && !probe_library_writable(&fsio, &lib_path).await {
+                bail!("The library is read-only, tags cannot be written to its files");
+            }
+
+            let rules = NormalizationRules::from(&request.rules);
+            let changes = normalize_metadata(&main_db, &request.file_ids, &rules).await?;
+            apply_metadata_changes(&main_db, &node_id, &changes)
+                .await
+                .with_context(|| "Failed to apply the normalized metadata")?;
+            response.applied_count = changes.len().try_into()?;
+
+            if request.write_tags && !changes.is_empty() {
+                let report =
+                    write_changes_to_tags(&fsio, &main_db, Path::new(lib_path.as_str()), &changes)
+                        .await?;
+                response.written_count = report.written.try_into()?;
+                response.unsupported_file_ids =
+                    report.unsupported.into_iter().map(|(id, _)| id).collect();
+                response.failed_file_ids = report.failed.into_iter().map(|(id, _)| id).collect();
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            response.success = false;
+            response.error = format!("{e:#}");
+        }
+
+        Ok(Some(response))
+    }
+}
+
+impl ParamsExtractor for PreviewOrganizeFilesRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for PreviewOrganizeFilesRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>);
+    type Response = PreviewOrganizeFilesResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let result = async {
+            let template = PathTemplate::new(&dart_signal.template)?;
+            plan_organize(
+                &fsio,
+                &main_db,
+                Path::new(lib_path.as_str()),
+                &dart_signal.file_ids,
+                &template,
+            )
+            .await
+        }
+        .await;
+
+        Ok(Some(match result {
+            Ok(plan) => PreviewOrganizeFilesResponse {
+                moves: plan
+                    .moves
+                    .into_iter()
+                    .map(|x| FileMove {
+                        file_id: x.file_id,
+                        from: x.from.to_string_lossy().replace('\\', "/"),
+                        to: x.to.to_string_lossy().replace('\\', "/"),
+                    })
+                    .collect(),
+                unchanged_count: plan.unchanged.try_into()?,
+                success: true,
+                error: String::new(),
+            },
+            Err(e) => PreviewOrganizeFilesResponse {
+                moves: Vec::new(),
+                unchanged_count: 0,
+                success: false,
+                error: format!("{e:#}"),
+            },
+        }))
+    }
+}
+
+impl ParamsExtractor for OrganizeFilesRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for OrganizeFilesRequest {
+    type Params = (
+        Arc<FsIo>,
+        Arc<MainDbConnection>,
+        Arc<String>,
+        Arc<Mutex<dyn Playable>>,
+    );
+    type Response = OrganizeFilesResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, player): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        // The moves come from a preview, they are checked again while applied
+        let plan = OrganizePlan {
+            template: dart_signal.template.clone(),
+            moves: dart_signal
+                .moves
+                .iter()
+                .map(|x| organize::FileMove {
+                    file_id: x.file_id,
+                    from: x.from.clone().into(),
+                    to: x.to.clone().into(),
+                })
+                .collect(),
+            unchanged: 0,
+        };
+
+        let outcome =
+            match apply_organize_plan(&fsio, &main_db, Path::new(lib_path.as_str()), &plan).await {
+                Ok(x) => x,
+                Err(e) => {
+                    return Ok(Some(OrganizeFilesResponse {
+                        moved_count: 0,
+                        skipped_count: 0,
+                        failed_count: 0,
+                        success: false,
+                        error: format!("{e:#}"),
+                    }));
+                }
+            };
+
+        // The queue refers to files by id, but the player holds their paths
+        let player = player.lock().await;
+        let moved_items: Vec<PlayingItem> = player
+            .get_playlist()
+            .into_iter()
+            .filter(|item| {
+                plan.moves
+                    .iter()
+                    .any(|x| *item == PlayingItem::InLibrary(x.file_id))
+            })
+            .collect();
+        if !moved_items.is_empty() {
+            match PlayingItemActionDispatcher::new()
+                .get_file_path(&fsio, &lib_path.as_str(), &main_db, &moved_items)
+                .await
+            {
+                Ok(paths) => player.relocate_playlist_items(paths.into_iter().collect()),
+                Err(e) => warn!("Failed to relocate the moved files in the queue: {e:#}"),
+            }
+        }
+
+        Ok(Some(OrganizeFilesResponse {
+            moved_count: outcome.moved.try_into()?,
+            skipped_count: outcome.skipped.try_into()?,
+            failed_count: outcome.failed.try_into()?,
+            success: true,
+            error: String::new(),
+        }))
+    }
+}
@@ -3,6 +3,7 @@ mod collection;
 mod connection;
 mod cover_art;
 mod directory;
+mod identify;
 mod library_home;
 mod library_manage;
 mod license;
@@ -10,6 +11,7 @@ mod logging;
 mod lyric;
 mod media_file;
 mod mix;
+mod musicbrainz;
 mod neighbors;
 mod playback;
 mod playlist;
@@ -0,0 +1,196 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+
+use ::database::{
+    actions::{
+        albums::get_media_file_ids_by_album_ids,
+        metadata_edit::write_changes_to_tags,
+        musicbrainz::{apply_release_mapping, get_local_tracks, propose_mapping},
+    },
+    connection::{MainDbConnection, probe_library_writable},
+};
+use ::fsio::FsIo;
+use ::tag_editor::music_brainz::release::{
+    MusicBrainzRelease, ReleaseTrack, fetch_release, fetch_release_of_group,
+};
+
+use crate::{
+    Session, Signal,
+    messages::*,
+    utils::{GlobalParams, ParamsExtractor},
+};
+
+impl From<&ReleaseTrack> for MusicBrainzReleaseTrack {
+    fn from(track: &ReleaseTrack) -> Self {
+        MusicBrainzReleaseTrack {
+            disc_number: track.disc_number,
+            track_number: track.track_number,
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            length: track.length,
+        }
+    }
+}
+
+impl ParamsExtractor for PreviewReleaseMappingRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for PreviewReleaseMappingRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = PreviewReleaseMappingResponse;
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let mut response = PreviewReleaseMappingResponse {
+            album_id: request.album_id,
+            release_id: String::new(),
+            title: String::new(),
+            artist: String::new(),
+            date: None,
+            tracks: Vec::new(),
+            mappings: Vec::new(),
+            unmapped_file_ids: Vec::new(),
+            success: true,
+            error: String::new(),
+        };
+
+        let result = async {
+            let file_ids = get_media_file_ids_by_album_ids(&main_db, &[request.album_id])
+                .await?
+                .remove(&request.album_id)
+                .unwrap_or_default();
+            if file_ids.is_empty() {
+                bail!("Album {} has no track", request.album_id);
+            }
+            let local_tracks = get_local_tracks(&main_db, &file_ids).await?;
+
+            let release: MusicBrainzRelease = if request.is_release_group {
+                fetch_release_of_group(&request.musicbrainz_id, file_ids.len()).await?
+            } else {
+                fetch_release(&request.musicbrainz_id).await?
+            };
+
+            for (mapping, local_track) in propose_mapping(&local_tracks, &release)
+                .into_iter()
+                .zip(&local_tracks)
+            {
+                if mapping.track_index.is_none() {
+                    response.unmapped_file_ids.push(mapping.file_id);
+                }
+                response.mappings.push(ProposedTrackMapping {
+                    mapping: ReleaseTrackMapping {
+                        file_id: mapping.file_id,
+                        track_index: mapping.track_index.map(|x| x as u32),
+                    },
+                    local_title: local_track.title.clone(),
+                    score: mapping.score,
+                });
+            }
+
+            response.tracks = release.tracks.iter().map(Into::into).collect();
+            response.release_id = release.id;
+            response.title = release.title;
+            response.artist = release.artist;
+            response.date = release.date;
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            response.success = false;
+            response.error = format!("{e:#}");
+        }
+
+        Ok(Some(response))
+    }
+}
+
+impl ParamsExtractor for ApplyReleaseMappingRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.node_id),
+        )
+    }
+}
+
+impl Signal for ApplyReleaseMappingRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<String>, Arc<String>);
+    type Response = ApplyReleaseMappingResponse;
+    async fn handle(
+        &self,
+        (fsio, main_db, lib_path, node_id): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+        let mut response = ApplyReleaseMappingResponse {
+            release_id: request.release_id.clone(),
+            applied_count: 0,
+            unmapped_file_ids: Vec::new(),
+            written_count: 0,
+            unsupported_file_ids: Vec::new(),
+            failed_file_ids: Vec::new(),
+            success: true,
+            error: String::new(),
+        };
+
+        let result = async {
+            if request.write_tags && !probe_library_writable(&fsio, &lib_path).await {
+                bail!("The library is read-only, tags cannot be written to its files");
+            }
+
+            // Fetched again rather than trusting the client with the metadata
+            let release = fetch_release(&request.release_id).await?;
+            let mapping: Vec<(i32, Option<usize>)> = request
+                .mappings
+                .iter()
+                .map(|x| (x.file_id, x.track_index.map(|x| x as usize)))
+                .collect();
+
+            let outcome = apply_release_mapping(&main_db, &node_id, &release, &mapping).await?;
+            response.applied_count = outcome.changes.len().try_into()?;
+            response.unmapped_file_ids = outcome.unmapped_file_ids;
+
+            if request.write_tags && !outcome.changes.is_empty() {
+                let report = write_changes_to_tags(
+                    &fsio,
+                    &main_db,
+                    Path::new(lib_path.as_str()),
+                    &outcome.changes,
+                )
+                .await?;
+                response.written_count = report.written.try_into()?;
+                response.unsupported_file_ids =
+                    report.unsupported.into_iter().map(|(id, _)| id).collect();
+                response.failed_file_ids = report.failed.into_iter().map(|(id, _)| id).collect();
+            }
+
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            response.success = false;
+            response.error = format!("{e:#}");
+        }
+
+        Ok(Some(response))
+    }
+}
@@ -1,7 +1,7 @@
 use std::{fs, future::Future, path::Path, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result, anyhow};
-use log::info;
+use log::{error, info};
 use tokio::sync::RwLock;
 use url::Url;
 
@@ -14,34 +14,50 @@ use ::discovery::{
     url::decode_rnsrv_url,
     utils::{DeviceInfo, DeviceType},
 };
-use ::http_request::{BodyExt, Bytes, Empty, Request, Uri, create_https_client, send_http_request};
+use ::fsio::FsIo;
 
 use crate::server::{
     ServerManager,
     api::{check_fingerprint, register_device},
     generate_or_load_certificates, get_or_generate_alias,
+    utils::{
+        bind::{DEFAULT_SERVER_PORT, ServerLayout, resolve_bind_addresses},
+        trust::{export_trust_bundle, import_trust_bundle},
+    },
+};
+use crate::utils::{
+    Broadcaster, GlobalParams, ParamsExtractor,
+    download::{DownloadConfig, download_file, set_max_concurrent_downloads},
 };
-use crate::utils::{GlobalParams, ParamsExtractor};
 use crate::{Session, Signal, messages::*};
 
 impl ParamsExtractor for StartBroadcastRequest {
-    type Params = (Arc<DiscoveryService>, Arc<String>);
+    type Params = (
+        Arc<DiscoveryService>,
+        Arc<String>,
+        Option<Arc<ServerManager>>,
+    );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.device_scanner),
             Arc::clone(&all_params.config_path),
+            all_params.server_manager.get().cloned(),
         )
     }
 }
 
 impl Signal for StartBroadcastRequest {
-    type Params = (Arc<DiscoveryService>, Arc<String>);
+    type Params = (
+        Arc<DiscoveryService>,
+        Arc<String>,
+        Option<Arc<ServerManager>>,
+    );
     type Response = ();
 
     async fn handle(
         &self,
-        (scanner, config_path): Self::Params,
+        (scanner, config_path, server_manager): Self::Params,
         _session: Option<Session>,
         request: &Self,
     ) -> anyhow::Result<Option<Self::Response>> {
@@ -54,17 +70,35 @@ impl Signal for StartBroadcastRequest {
             request.alias, fingerprint
         );
 
+        let mut device_info = DeviceInfo {
+            alias: request.alias.clone(),
+            device_model: Some("RuneAudio".to_string()),
+            version: "Technical Preview".to_owned(),
+            device_type: Some(DeviceType::Desktop),
+            fingerprint: fingerprint.clone(),
+            api_port: DEFAULT_SERVER_PORT,
+            ws_port: None,
+            path_prefix: String::new(),
+            protocol: "http".to_owned(),
+            endpoints: vec![],
+        };
+
+        if let Some(server_manager) = server_manager {
+            device_info.endpoints = server_manager
+                .get_addresses()
+                .await
+                .iter()
+                .map(|x| x.to_string())
+                .collect();
+            server_manager
+                .get_layout()
+                .await
+                .advertise(&mut device_info);
+        }
+
         scanner
             .start_announcements(
-                DeviceInfo {
-                    alias: request.alias.clone(),
-                    device_model: Some("RuneAudio".to_string()),
-                    version: "Technical Preview".to_owned(),
-                    device_type: Some(DeviceType::Desktop),
-                    fingerprint: fingerprint.clone(),
-                    api_port: 7863,
-                    protocol: "http".to_owned(),
-                },
+                device_info,
                 Duration::from_secs(request.duration_seconds.into()),
                 None,
             )
@@ -180,6 +214,9 @@ impl Signal for GetDiscoveredDeviceRequest {
                 device_type: x.device_type.to_string(),
                 last_seen_unix_epoch: x.last_seen.timestamp(),
                 ips: x.ips.into_iter().map(|ip| ip.to_string()).collect(),
+                api_port: x.api_port.into(),
+                ws_port: x.ws_port.unwrap_or_default().into(),
+                path_prefix: x.path_prefix,
             })
             .collect();
 
@@ -189,6 +226,16 @@ impl Signal for GetDiscoveredDeviceRequest {
     }
 }
 
+/// Converts a port from a request, where 0 stands for the default.
+fn parse_port(port: u32) -> Result<Option<u16>, String> {
+    match port {
+        0 => Ok(None),
+        x => u16::try_from(x)
+            .map(Some)
+            .map_err(|_| format!("Invalid port: {x}")),
+    }
+}
+
 impl ParamsExtractor for StartServerRequest {
     type Params = (Arc<String>, Arc<ServerManager>);
 
@@ -218,10 +265,32 @@ impl Signal for StartServerRequest {
         request: &Self,
     ) -> impl Future<Output = Result<Option<Self::Response>>> + Send {
         async move {
-            let ip: std::net::IpAddr = request.interface.parse().map_err(|e| {
-                anyhow::anyhow!("Invalid interface address '{}': {}", request.interface, e)
-            })?;
-            let addr = std::net::SocketAddr::new(ip, 7863);
+            let (port, ws_port) = match (parse_port(request.port), parse_port(request.ws_port)) {
+                (Ok(port), Ok(ws_port)) => (port.unwrap_or(DEFAULT_SERVER_PORT), ws_port),
+                (Err(e), _) | (_, Err(e)) => {
+                    return Ok(Some(StartServerResponse {
+                        success: false,
+                        error: e,
+                        endpoints: vec![],
+                        bind_errors: vec![],
+                    }));
+                }
+            };
+            let layout = ServerLayout::new(port, ws_port, &request.path_prefix);
+
+            let specs: Vec<String> = std::iter::once(request.interface.clone())
+                .chain(request.bind_addresses.iter().cloned())
+                .collect();
+            let (addrs, mut bind_errors) = resolve_bind_addresses(&specs, layout.port);
+
+            if request.strict && !bind_errors.is_empty() {
+                return Ok(Some(StartServerResponse {
+                    success: false,
+                    error: bind_errors.join("\n"),
+                    endpoints: vec![],
+                    bind_errors,
+                }));
+            }
 
             let device_info = DeviceInfo {
                 alias: request.alias.clone(),
@@ -234,20 +303,41 @@ impl Signal for StartServerRequest {
                 )
                 .await?
                 .0,
-                api_port: 7863,
+                api_port: layout.port,
+                ws_port: layout.ws_port,
+                path_prefix: layout.path_prefix.clone(),
                 protocol: "http".to_owned(),
+                endpoints: vec![],
             };
 
             let discovery_params = DiscoveryParams { device_info };
 
-            match server_manager.start(addr, discovery_params).await {
-                Ok(_) => Ok(Some(StartServerResponse {
-                    success: true,
-                    error: String::new(),
-                })),
+            server_manager.set_layout(layout).await;
+
+            match server_manager
+                .start(addrs, discovery_params, request.strict)
+                .await
+            {
+                Ok(report) => {
+                    bind_errors.extend(
+                        report
+                            .failures
+                            .into_iter()
+                            .map(|(addr, e)| format!("Failed to bind {addr}: {e}")),
+                    );
+
+                    Ok(Some(StartServerResponse {
+                        success: true,
+                        error: String::new(),
+                        endpoints: report.endpoints.iter().map(|x| x.to_string()).collect(),
+                        bind_errors,
+                    }))
+                }
                 Err(e) => Ok(Some(StartServerResponse {
                     success: false,
                     error: format!("{e:#?}"),
+                    endpoints: vec![],
+                    bind_errors,
                 })),
             }
         }
@@ -275,9 +365,31 @@ impl Signal for StopServerRequest {
     async fn handle(
         &self,
         server_manager: Self::Params,
-        _session: Option<Session>,
+        session: Option<Session>,
         _: &Self,
     ) -> Result<Option<Self::Response>> {
+        // Stopping waits for the requests in flight, this one included, and
+        // closes the connection it came from, so remote clients are answered
+        // first and the server stops once the response is sent
+        if session.is_some() {
+            if !server_manager.is_running() {
+                return Ok(Some(StopServerResponse {
+                    success: false,
+                    error: "Server not running".into(),
+                }));
+            }
+
+            tokio::spawn(async move {
+                if let Err(e) = server_manager.stop().await {
+                    error!("Failed to stop the server: {e:#?}");
+                }
+            });
+            return Ok(Some(StopServerResponse {
+                success: true,
+                error: "".into(),
+            }));
+        }
+
         match server_manager.stop().await {
             Ok(_) => Ok(Some(StopServerResponse {
                 success: true,
@@ -292,20 +404,23 @@ impl Signal for StopServerRequest {
 }
 
 impl ParamsExtractor for ListClientsRequest {
-    type Params = Arc<RwLock<PermissionManager>>;
+    type Params = (Arc<RwLock<PermissionManager>>, Option<Arc<ServerManager>>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        Arc::clone(&all_params.permission_manager)
+        (
+            Arc::clone(&all_params.permission_manager),
+            all_params.server_manager.get().cloned(),
+        )
     }
 }
 
 impl Signal for ListClientsRequest {
-    type Params = Arc<RwLock<PermissionManager>>;
+    type Params = (Arc<RwLock<PermissionManager>>, Option<Arc<ServerManager>>);
     type Response = ListClientsResponse;
 
     async fn handle(
         &self,
-        permission_manager: Self::Params,
+        (permission_manager, server_manager): Self::Params,
         _session: Option<Session>,
         _: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -313,15 +428,51 @@ impl Signal for ListClientsRequest {
 
         let converted_users = users
             .into_iter()
-            .map(|u| ClientSummary {
-                alias: u.alias,
-                fingerprint: u.fingerprint,
-                device_model: u.device_model,
-                status: match u.status {
-                    UserStatus::Approved => ClientStatus::Approved,
-                    UserStatus::Pending => ClientStatus::Pending,
-                    UserStatus::Blocked => ClientStatus::Blocked,
-                },
+            .map(|u| {
+                let (throttled_messages, dropped_messages) = server_manager
+                    .as_ref()
+                    .map(|x| x.connection_metrics.snapshot(&u.fingerprint))
+                    .unwrap_or_default();
+
+                let connections = server_manager
+                    .as_ref()
+                    .map(|x| x.connection_registry.snapshot(&u.fingerprint))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|c| ClientConnectionInfo {
+                        remote_address: c.remote_addr.to_string(),
+                        protocol_version: c.protocol_version,
+                        protocol_features: c.protocol_features,
+                        connected_at: c.connected_at,
+                        last_activity: c.last_activity,
+                        request_count: c.request_count,
+                        bytes_sent: c.bytes_sent,
+                        bytes_received: c.bytes_received,
+                        broadcast_subscriptions: c
+                            .broadcasts
+                            .into_iter()
+                            .map(|(message_type, counter)| BroadcastSubscription {
+                                message_type,
+                                message_count: counter.messages,
+                                bytes: counter.bytes,
+                            })
+                            .collect(),
+                    })
+                    .collect();
+
+                ClientSummary {
+                    alias: u.alias,
+                    fingerprint: u.fingerprint,
+                    device_model: u.device_model,
+                    status: match u.status {
+                        UserStatus::Approved => ClientStatus::Approved,
+                        UserStatus::Pending => ClientStatus::Pending,
+                        UserStatus::Blocked => ClientStatus::Blocked,
+                    },
+                    throttled_messages,
+                    dropped_messages,
+                    connections,
+                }
             })
             .collect();
 
@@ -416,7 +567,13 @@ impl Signal for ServerAvailabilityTestRequest {
         let validator = Arc::new(validator.read().await.clone());
 
         let hosts = match decode_rnsrv_url(&req.url) {
-            Ok(x) => x,
+            Ok(x) if !x.is_supported() => {
+                return Ok(Some(ServerAvailabilityTestResponse {
+                    success: false,
+                    error: format!("Unsupported server protocol version {}", x.version),
+                }));
+            }
+            Ok(x) => x.host_specs(),
             Err(e) => {
                 return Ok(Some(ServerAvailabilityTestResponse {
                     success: false,
@@ -484,6 +641,225 @@ impl Signal for UpdateClientStatusRequest {
     }
 }
 
+impl ParamsExtractor for AuthenticateAdminRequest {
+    type Params = Option<Arc<ServerManager>>;
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        all_params.server_manager.get().cloned()
+    }
+}
+
+impl Signal for AuthenticateAdminRequest {
+    type Params = Option<Arc<ServerManager>>;
+    type Response = AuthenticateAdminResponse;
+
+    async fn handle(
+        &self,
+        server_manager: Self::Params,
+        session: Option<Session>,
+        request: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let result = async {
+            let session = session.ok_or_else(|| anyhow!("Only remote sessions authenticate"))?;
+            let server_manager =
+                server_manager.ok_or_else(|| anyhow!("Server is not initialized"))?;
+
+            let token = server_manager
+                .admin_auth
+                .authenticate(&session.fingerprint, &request.password)
+                .await?;
+            *session.admin_token.lock().unwrap() = Some(token.token.clone());
+
+            Ok::<_, anyhow::Error>(token)
+        }
+        .await;
+
+        Ok(Some(match result {
+            Ok(token) => AuthenticateAdminResponse {
+                success: true,
+                token: token.token,
+                expires_at: token.expires_at,
+                error: String::new(),
+            },
+            Err(e) => AuthenticateAdminResponse {
+                success: false,
+                token: String::new(),
+                expires_at: 0,
+                error: e.to_string(),
+            },
+        }))
+    }
+}
+
+impl ParamsExtractor for RefreshAdminTokenRequest {
+    type Params = Option<Arc<ServerManager>>;
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        all_params.server_manager.get().cloned()
+    }
+}
+
+impl Signal for RefreshAdminTokenRequest {
+    type Params = Option<Arc<ServerManager>>;
+    type Response = RefreshAdminTokenResponse;
+
+    async fn handle(
+        &self,
+        server_manager: Self::Params,
+        session: Option<Session>,
+        request: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let result = async {
+            let session = session.ok_or_else(|| anyhow!("Only remote sessions authenticate"))?;
+            let server_manager =
+                server_manager.ok_or_else(|| anyhow!("Server is not initialized"))?;
+
+            let current = if request.token.is_empty() {
+                session
+                    .admin_token
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .ok_or_else(|| anyhow!("No admin token to refresh"))?
+            } else {
+                request.token.clone()
+            };
+
+            let token = server_manager
+                .admin_auth
+                .refresh(&session.fingerprint, &current)
+                .await?;
+            *session.admin_token.lock().unwrap() = Some(token.token.clone());
+
+            Ok::<_, anyhow::Error>(token)
+        }
+        .await;
+
+        Ok(Some(match result {
+            Ok(token) => RefreshAdminTokenResponse {
+                success: true,
+                token: token.token,
+                expires_at: token.expires_at,
+                error: String::new(),
+            },
+            Err(e) => RefreshAdminTokenResponse {
+                success: false,
+                token: String::new(),
+                expires_at: 0,
+                error: e.to_string(),
+            },
+        }))
+    }
+}
+
+impl ParamsExtractor for ExportTrustBundleRequest {
+    type Params = (Arc<String>, Arc<RwLock<CertValidator>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.config_path),
+            Arc::clone(&all_params.cert_validator),
+        )
+    }
+}
+
+impl Signal for ExportTrustBundleRequest {
+    type Params = (Arc<String>, Arc<RwLock<CertValidator>>);
+    type Response = ExportTrustBundleResponse;
+
+    async fn handle(
+        &self,
+        (config_path, validator): Self::Params,
+        _session: Option<Session>,
+        req: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let validator = validator.read().await.clone();
+
+        match export_trust_bundle(
+            Path::new(&*config_path),
+            &validator,
+            Path::new(&req.path),
+            &req.password,
+            req.include_identity,
+        )
+        .await
+        {
+            Ok(count) => Ok(Some(ExportTrustBundleResponse {
+                success: true,
+                exported_count: count as u32,
+                error: String::new(),
+            })),
+            Err(e) => Ok(Some(ExportTrustBundleResponse {
+                success: false,
+                exported_count: 0,
+                error: format!("{e:#?}"),
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for ImportTrustBundleRequest {
+    type Params = (Arc<String>, Arc<RwLock<CertValidator>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.config_path),
+            Arc::clone(&all_params.cert_validator),
+        )
+    }
+}
+
+impl Signal for ImportTrustBundleRequest {
+    type Params = (Arc<String>, Arc<RwLock<CertValidator>>);
+    type Response = ImportTrustBundleResponse;
+
+    async fn handle(
+        &self,
+        (config_path, validator): Self::Params,
+        _session: Option<Session>,
+        req: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let validator = validator.read().await.clone();
+
+        match import_trust_bundle(
+            Path::new(&*config_path),
+            &validator,
+            Path::new(&req.path),
+            &req.password,
+            req.import_identity,
+        )
+        .await
+        {
+            Ok(report) => Ok(Some(ImportTrustBundleResponse {
+                success: true,
+                imported_fingerprints: report.imported,
+                conflicts: report
+                    .conflicts
+                    .into_iter()
+                    .map(|x| TrustBundleConflict {
+                        host: x.host,
+                        existing_fingerprint: x.existing_fingerprint,
+                        imported_fingerprint: x.imported_fingerprint,
+                    })
+                    .collect(),
+                revoked_fingerprints: report.revoked,
+                expired_fingerprints: report.expired,
+                identity_imported: report.identity.is_some(),
+                error: String::new(),
+            })),
+            Err(e) => Ok(Some(ImportTrustBundleResponse {
+                success: false,
+                imported_fingerprints: vec![],
+                conflicts: vec![],
+                revoked_fingerprints: vec![],
+                expired_fingerprints: vec![],
+                identity_imported: false,
+                error: format!("{e:#?}"),
+            })),
+        }
+    }
+}
+
 impl ParamsExtractor for EditHostsRequest {
     type Params = Arc<RwLock<CertValidator>>;
 
@@ -818,20 +1194,24 @@ impl Signal for FetchServerCertificateRequest {
 }
 
 impl ParamsExtractor for FetchRemoteFileRequest {
-    type Params = Arc<RwLock<CertValidator>>;
+    type Params = (Arc<FsIo>, Arc<RwLock<CertValidator>>, Arc<dyn Broadcaster>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        Arc::clone(&all_params.cert_validator)
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.cert_validator),
+            Arc::clone(&all_params.broadcaster),
+        )
     }
 }
 
 impl Signal for FetchRemoteFileRequest {
-    type Params = Arc<RwLock<CertValidator>>;
+    type Params = (Arc<FsIo>, Arc<RwLock<CertValidator>>, Arc<dyn Broadcaster>);
     type Response = FetchRemoteFileResponse;
 
     async fn handle(
         &self,
-        validator: Self::Params,
+        (fsio, validator, broadcaster): Self::Params,
         _session: Option<Session>,
         req: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -849,26 +1229,20 @@ impl Signal for FetchRemoteFileRequest {
             }
         };
 
-        // Extract hostname and port
-        let host = match url.host_str() {
-            Some(host) => host.to_string(),
-            None => {
-                return Ok(Some(FetchRemoteFileResponse {
-                    success: false,
-                    local_path: String::new(),
-                    error: "Missing host in URL".to_string(),
-                }));
-            }
-        };
-
-        let port = url.port().unwrap_or(7863);
+        if url.host_str().is_none() {
+            return Ok(Some(FetchRemoteFileResponse {
+                success: false,
+                local_path: String::new(),
+                error: "Missing host in URL".to_string(),
+            }));
+        }
 
         // Extract the file name from the URL path
-        let path = url.path();
-        let file_name = Path::new(path)
+        let file_name = Path::new(url.path())
             .file_name()
             .and_then(|name| name.to_str())
-            .unwrap_or_default();
+            .unwrap_or_default()
+            .to_string();
 
         if file_name.is_empty() {
             return Ok(Some(FetchRemoteFileResponse {
@@ -888,7 +1262,7 @@ impl Signal for FetchRemoteFileRequest {
         }
 
         // Create the local file path
-        let local_path = COVER_TEMP_DIR.clone().join(file_name);
+        let local_path = COVER_TEMP_DIR.clone().join(&file_name);
 
         // Check if the file already exists locally
         if local_path.exists() {
@@ -899,100 +1273,59 @@ impl Signal for FetchRemoteFileRequest {
             }));
         }
 
-        // Create an HTTPS client with the custom TLS configuration
-        let mut sender = match create_https_client(
-            host.clone(),
-            port,
-            validator.clone().into_client_config().into(),
+        let result = download_file(
+            &fsio,
+            &url,
+            &local_path,
+            validator.into_client_config().into(),
+            &DownloadConfig::default(),
+            |downloaded_bytes, total_bytes| {
+                broadcaster.broadcast(&FetchRemoteFileProgress {
+                    url: req.url.clone(),
+                    file_name: file_name.clone(),
+                    downloaded_bytes,
+                    total_bytes,
+                });
+            },
         )
-        .await
-        {
-            Ok(sender) => sender,
-            Err(e) => {
-                return Ok(Some(FetchRemoteFileResponse {
-                    success: false,
-                    local_path: String::new(),
-                    error: format!("Failed to create HTTPS client: {e}"),
-                }));
-            }
-        };
-
-        // Build the request URI
-        let uri = match Uri::builder()
-            .scheme("https")
-            .authority(format!("{host}:{port}"))
-            .path_and_query(path)
-            .build()
-        {
-            Ok(uri) => uri,
-            Err(e) => {
-                return Ok(Some(FetchRemoteFileResponse {
-                    success: false,
-                    local_path: String::new(),
-                    error: format!("Failed to build request URI: {e}"),
-                }));
-            }
-        };
-
-        // Build the HTTP request
-        let req = match Request::builder().uri(uri).body(Empty::<Bytes>::new()) {
-            Ok(req) => req,
-            Err(e) => {
-                return Ok(Some(FetchRemoteFileResponse {
-                    success: false,
-                    local_path: String::new(),
-                    error: format!("Failed to build HTTP request: {e}"),
-                }));
-            }
-        };
-
-        // Send the request
-        let res = match send_http_request(&mut sender, req).await {
-            Ok(res) => res,
-            Err(e) => {
-                return Ok(Some(FetchRemoteFileResponse {
-                    success: false,
-                    local_path: String::new(),
-                    error: format!("Failed to send HTTP request: {e}"),
-                }));
-            }
-        };
+        .await;
 
-        // Check the response status
-        if !res.status().is_success() {
-            return Ok(Some(FetchRemoteFileResponse {
+        match result {
+            Ok(_) => Ok(Some(FetchRemoteFileResponse {
+                success: true,
+                local_path: local_path.to_str().unwrap_or_default().to_string(),
+                error: String::new(),
+            })),
+            Err(e) => Ok(Some(FetchRemoteFileResponse {
                 success: false,
                 local_path: String::new(),
-                error: format!("HTTP request failed with status: {}", res.status()),
-            }));
+                error: format!("{e:#}"),
+            })),
         }
+    }
+}
 
-        // Read the response body
-        let body = match res.into_body().collect().await {
-            Ok(body) => body.to_bytes(),
-            Err(e) => {
-                return Ok(Some(FetchRemoteFileResponse {
-                    success: false,
-                    local_path: String::new(),
-                    error: format!("Failed to read response body: {e}"),
-                }));
-            }
-        };
+impl ParamsExtractor for SetMaxConcurrentDownloadsRequest {
+    type Params = ();
 
-        // Write the file to disk
-        if let Err(e) = fs::write(&local_path, &body) {
-            return Ok(Some(FetchRemoteFileResponse {
-                success: false,
-                local_path: String::new(),
-                error: format!("Failed to write file to disk: {e}"),
-            }));
-        }
+    fn extract_params(&self, _: &GlobalParams) -> Self::Params {}
+}
 
-        // Return the successful response with the local file path
-        Ok(Some(FetchRemoteFileResponse {
-            success: true,
-            local_path: local_path.to_str().unwrap_or_default().to_string(),
-            error: String::new(),
-        }))
+impl Signal for SetMaxConcurrentDownloadsRequest {
+    type Params = ();
+    type Response = ();
+
+    async fn handle(
+        &self,
+        _: Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        set_max_concurrent_downloads(dart_signal.limit as usize);
+        info!(
+            "Concurrent downloads limited to {}",
+            dart_signal.limit.max(1)
+        );
+        Ok(Some(()))
     }
 }
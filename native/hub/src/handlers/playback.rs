@@ -1,23 +1,38 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc, time::Duration};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use fsio::FsIo;
-use tokio::sync::Mutex;
+use log::{info, warn};
+use serde::Serialize;
+use tokio::sync::{Mutex, RwLock};
 
 use ::database::{
-    actions::{mixes::query_mix_media_files, stats::increase_skipped},
+    actions::{
+        loudness::get_track_loudness, mixes::query_mix_media_files, stats::increase_skipped,
+    },
     connection::{MainDbConnection, RecommendationDbConnection},
-    playing_item::dispatcher::PlayingItemActionDispatcher,
+    playing_item::{MediaFileHandle, dispatcher::PlayingItemActionDispatcher},
 };
+use ::discovery::client::{CertValidator, select_best_host};
 use ::playback::{
+    loudness::{LoudnessNormalization, TrackLoudness},
+    output_stream::list_output_devices,
     player::{Playable, PlayingItem},
     strategies::AddMode,
 };
 
 use crate::{
     Session, Signal,
+    backends::remote::{
+        RinfDartSignal,
+        output::{RemoteOutput, RemoteOutputManager},
+    },
     messages::*,
-    utils::{GlobalParams, ParamsExtractor, files_to_playback_request, find_nearest_index},
+    server::{api::check_fingerprint, generate_or_load_certificates},
+    utils::{
+        Broadcaster, GlobalParams, ParamsExtractor, files_to_playback_request, find_nearest_index,
+        player::fetch_playlist_items,
+    },
 };
 
 impl From<PlayingItem> for PlayingItemRequest {
@@ -26,15 +41,22 @@ impl From<PlayingItem> for PlayingItemRequest {
             PlayingItem::InLibrary(x) => PlayingItemRequest {
                 in_library: Some(InLibraryPlayingItem { file_id: x }),
                 independent_file: None,
+                online: None,
             },
             PlayingItem::IndependentFile(path_str) => PlayingItemRequest {
                 in_library: None,
                 independent_file: Some(IndependentFilePlayingItem { raw_path: path_str }),
+                online: None,
+            },
+            PlayingItem::Online(url, _) => PlayingItemRequest {
+                in_library: None,
+                independent_file: None,
+                online: Some(OnlinePlayingItem { url }),
             },
-            PlayingItem::Online(_, _) => todo!(),
             PlayingItem::Unknown => PlayingItemRequest {
                 in_library: None,
                 independent_file: None,
+                online: None,
             },
         }
     }
@@ -54,28 +76,119 @@ impl From<PlayingItemRequest> for PlayingItem {
             return PlayingItem::IndependentFile(independent_file.raw_path);
         }
 
+        if let Some(online) = x.online
+            && !online.url.is_empty()
+        {
+            return PlayingItem::Online(online.url, None);
+        }
+
         PlayingItem::Unknown
     }
 }
 
+/// Forwards a playback request to the remote output, if playback is cast to
+/// another device. Only requests of the local UI are forwarded, so devices
+/// casting to each other can't bounce requests back and forth.
+async fn forward_to_remote_output<T>(
+    remote_output: &RemoteOutputManager,
+    session: &Option<Session>,
+    request: &T,
+) -> Result<bool>
+where
+    T: RinfDartSignal + Serialize,
+{
+    if session.is_some() {
+        return Ok(false);
+    }
+
+    match remote_output.active().await {
+        Some(output) => {
+            output.send(request).await?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Plays tracks resolved from this library on the remote output. Mix queries
+/// are resolved locally, so they keep referring to this library.
+async fn operate_remote_playback(
+    output: &RemoteOutput,
+    main_db: &MainDbConnection,
+    request: &OperatePlaybackWithMixQueryRequest,
+    tracks: &[MediaFileHandle],
+) -> Result<OperatePlaybackWithMixQueryResponse> {
+    let items: Vec<PlayingItem> = tracks.iter().map(|x| x.item.clone()).collect();
+
+    let nearest_index = if request.hint_position < 0 {
+        0
+    } else {
+        let hint_position: usize = request.hint_position.try_into()?;
+        find_nearest_index(&items, hint_position, |x| {
+            if let Some(initial_item) = &request.initial_playback_item {
+                *x == PlayingItem::from(initial_item.clone())
+            } else {
+                false
+            }
+        })
+        .unwrap_or(hint_position)
+    };
+
+    let resolved = output.resolve_tracks(main_db, tracks).await?;
+    if !resolved.is_empty() && resolved.iter().all(Option::is_none) {
+        bail!("None of the tracks can be played on {}", output.host());
+    }
+
+    // Tracks the target can't play are skipped, shift the index accordingly
+    let hint_position = resolved
+        .iter()
+        .take(nearest_index)
+        .filter(|x| x.is_some())
+        .count();
+
+    output
+        .request::<_, OperatePlaybackWithMixQueryResponse>(&OperatePlaybackWithMixQueryRequest {
+            queries: vec![],
+            playback_mode: request.playback_mode,
+            hint_position: hint_position.try_into()?,
+            initial_playback_item: None,
+            instantly_play: request.instantly_play,
+            operate_mode: request.operate_mode,
+            fallback_playing_items: resolved.into_iter().flatten().collect(),
+        })
+        .await
+        .with_context(|| format!("Failed to operate playback on {}", output.host()))?;
+
+    Ok(OperatePlaybackWithMixQueryResponse {
+        playing_items: items.into_iter().map(|x| x.into()).collect(),
+    })
+}
+
 impl ParamsExtractor for LoadRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for LoadRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = ();
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         let volume = dart_signal.index;
         player.lock().await.load(volume as usize);
         Ok(Some(()))
@@ -83,72 +196,99 @@ impl Signal for LoadRequest {
 }
 
 impl ParamsExtractor for PlayRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for PlayRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = ();
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
-        _: &Self,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
+        dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         player.lock().await.play();
         Ok(Some(()))
     }
 }
 
 impl ParamsExtractor for PauseRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for PauseRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = ();
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
-        _: &Self,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
+        dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         player.lock().await.pause();
         Ok(Some(()))
     }
 }
 
 impl ParamsExtractor for NextRequest {
-    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
+    );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
         )
     }
 }
 
 impl Signal for NextRequest {
-    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
+    );
     type Response = ();
 
     async fn handle(
         &self,
-        (main_db, player): Self::Params,
-        _session: Option<Session>,
-        _: &Self,
+        (main_db, player, remote_output): Self::Params,
+        session: Option<Session>,
+        dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         let item = player.lock().await.get_status().item;
 
         if let Some(PlayingItem::InLibrary(file_id)) = item {
@@ -163,26 +303,39 @@ impl Signal for NextRequest {
 }
 
 impl ParamsExtractor for PreviousRequest {
-    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
+    );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
         )
     }
 }
 
 impl Signal for PreviousRequest {
-    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
+    );
     type Response = ();
 
     async fn handle(
         &self,
-        (main_db, player): Self::Params,
-        _session: Option<Session>,
-        _: &Self,
+        (main_db, player, remote_output): Self::Params,
+        session: Option<Session>,
+        dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         let item = player.lock().await.get_status().item;
 
         if let Some(PlayingItem::InLibrary(file_id)) = item {
@@ -197,23 +350,30 @@ impl Signal for PreviousRequest {
 }
 
 impl ParamsExtractor for SetPlaybackModeRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for SetPlaybackModeRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = ();
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         let mode = dart_signal.mode;
         player.lock().await.set_playback_mode(mode.into());
         Ok(Some(()))
@@ -221,26 +381,39 @@ impl Signal for SetPlaybackModeRequest {
 }
 
 impl ParamsExtractor for SwitchRequest {
-    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
+    );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
         (
             Arc::clone(&all_params.main_db),
             Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
         )
     }
 }
 
 impl Signal for SwitchRequest {
-    type Params = (Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
+    );
     type Response = ();
 
     async fn handle(
         &self,
-        (main_db, player): Self::Params,
-        _session: Option<Session>,
+        (main_db, player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         if let Some(PlayingItem::InLibrary(file_id)) = player.lock().await.get_status().item {
             increase_skipped(&main_db, file_id)
                 .await
@@ -257,46 +430,60 @@ impl Signal for SwitchRequest {
 }
 
 impl ParamsExtractor for SeekRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for SeekRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = ();
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         player.lock().await.seek(dart_signal.position_seconds);
         Ok(Some(()))
     }
 }
 
 impl ParamsExtractor for RemoveRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for RemoveRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = ();
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         player
             .lock()
             .await
@@ -306,47 +493,95 @@ impl Signal for RemoveRequest {
 }
 
 impl ParamsExtractor for VolumeRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for VolumeRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = VolumeResponse;
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
         let volume = dart_signal.volume;
-        player.lock().await.set_volume(volume);
+        if !forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            player.lock().await.set_volume(volume);
+        }
         Ok(Some(VolumeResponse { volume }))
     }
 }
 
+impl ParamsExtractor for RelativeVolumeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
+    }
+}
+
+impl Signal for RelativeVolumeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
+    type Response = RelativeVolumeResponse;
+
+    async fn handle(
+        &self,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        // The volume of the remote output isn't known here, so there is
+        // nothing to respond with
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(None);
+        }
+
+        let mut player = player.lock().await;
+        let volume = (player.get_status().volume + dart_signal.delta).clamp(0.0, 1.0);
+        if dart_signal.delta != 0.0 {
+            player.set_volume(volume);
+        }
+        Ok(Some(RelativeVolumeResponse { volume }))
+    }
+}
+
 impl ParamsExtractor for MovePlaylistItemRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.player),)
+        (
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
+        )
     }
 }
 
 impl Signal for MovePlaylistItemRequest {
-    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Params = (Arc<Mutex<dyn Playable>>, Arc<RemoteOutputManager>);
     type Response = ();
 
     async fn handle(
         &self,
-        (player,): Self::Params,
-        _session: Option<Session>,
+        (player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
+        if forward_to_remote_output(&remote_output, &session, dart_signal).await? {
+            return Ok(Some(()));
+        }
+
         let request = dart_signal;
         let old_index = request.old_index;
         let new_index = request.new_index;
@@ -359,6 +594,40 @@ impl Signal for MovePlaylistItemRequest {
     }
 }
 
+impl ParamsExtractor for FetchPlaybackQueueRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.player),
+        )
+    }
+}
+
+impl Signal for FetchPlaybackQueueRequest {
+    type Params = (Arc<FsIo>, Arc<MainDbConnection>, Arc<Mutex<dyn Playable>>);
+    type Response = FetchPlaybackQueueResponse;
+
+    async fn handle(
+        &self,
+        (fsio, main_db, player): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let status = player.lock().await.get_status();
+        let items = fetch_playlist_items(&fsio, &main_db, &status.playlist)
+            .await
+            .with_context(|| "Failed to fetch the playback queue")?;
+
+        Ok(Some(FetchPlaybackQueueResponse {
+            items,
+            index: status.index.map(|x| x as u32),
+        }))
+    }
+}
+
 impl ParamsExtractor for SetRealtimeFFTEnabledRequest {
     type Params = (Arc<Mutex<dyn Playable>>,);
 
@@ -407,6 +676,169 @@ impl Signal for SetAdaptiveSwitchingEnabledRequest {
     }
 }
 
+impl ParamsExtractor for SetGaplessEnabledRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetGaplessEnabledRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let enabled = dart_signal.enabled;
+        player.lock().await.set_gapless_enabled(enabled);
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for SetCrossfadeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetCrossfadeRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let duration = Duration::try_from_secs_f32(dart_signal.seconds).unwrap_or_default();
+        player.lock().await.set_crossfade(duration);
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for SetLoudnessNormalizationRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetLoudnessNormalizationRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        if !dart_signal.target_loudness.is_finite() {
+            bail!("Invalid target loudness: {}", dart_signal.target_loudness);
+        }
+
+        let normalization = dart_signal.enabled.then_some(LoudnessNormalization {
+            target_loudness: dart_signal.target_loudness,
+            album_gain: dart_signal.album_gain,
+        });
+        player
+            .lock()
+            .await
+            .set_loudness_normalization(normalization);
+        Ok(Some(()))
+    }
+}
+
+impl ParamsExtractor for ListAudioDevicesRequest {
+    type Params = ();
+
+    fn extract_params(&self, _: &GlobalParams) -> Self::Params {}
+}
+
+impl Signal for ListAudioDevicesRequest {
+    type Params = ();
+    type Response = ListAudioDevicesResponse;
+
+    async fn handle(
+        &self,
+        _: Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let devices = list_output_devices()?
+            .into_iter()
+            .map(|x| AudioDevice {
+                id: x.id,
+                name: x.name,
+                is_default: x.is_default,
+            })
+            .collect();
+
+        Ok(Some(ListAudioDevicesResponse { devices }))
+    }
+}
+
+impl ParamsExtractor for SetAudioDeviceRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.player),)
+    }
+}
+
+impl Signal for SetAudioDeviceRequest {
+    type Params = (Arc<Mutex<dyn Playable>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (player,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        player
+            .lock()
+            .await
+            .set_output_device(dart_signal.id.clone());
+        Ok(Some(()))
+    }
+}
+
+/// The loudness of the library tracks about to be played, an unavailable
+/// loudness only leaving them unnormalized.
+async fn fetch_track_loudness(
+    main_db: &MainDbConnection,
+    tracks: &[MediaFileHandle],
+) -> Vec<(PlayingItem, TrackLoudness)> {
+    let file_ids: Vec<i32> = tracks
+        .iter()
+        .filter_map(|x| match x.item {
+            PlayingItem::InLibrary(file_id) => Some(file_id),
+            _ => None,
+        })
+        .collect();
+
+    match get_track_loudness(main_db, &file_ids).await {
+        Ok(loudness) => loudness
+            .into_iter()
+            .map(|(file_id, loudness)| (PlayingItem::InLibrary(file_id), loudness))
+            .collect(),
+        Err(e) => {
+            warn!("Failed to get the loudness of the tracks: {e:#}");
+            Vec::new()
+        }
+    }
+}
+
 impl ParamsExtractor for OperatePlaybackWithMixQueryRequest {
     type Params = (
         Arc<FsIo>,
@@ -414,6 +846,7 @@ impl ParamsExtractor for OperatePlaybackWithMixQueryRequest {
         Arc<RecommendationDbConnection>,
         Arc<String>,
         Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
     );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
@@ -423,6 +856,7 @@ impl ParamsExtractor for OperatePlaybackWithMixQueryRequest {
             Arc::clone(&all_params.recommend_db),
             Arc::clone(&all_params.lib_path),
             Arc::clone(&all_params.player),
+            Arc::clone(&all_params.remote_output),
         )
     }
 }
@@ -434,13 +868,14 @@ impl Signal for OperatePlaybackWithMixQueryRequest {
         Arc<RecommendationDbConnection>,
         Arc<String>,
         Arc<Mutex<dyn Playable>>,
+        Arc<RemoteOutputManager>,
     );
     type Response = OperatePlaybackWithMixQueryResponse;
 
     async fn handle(
         &self,
-        (fsio, main_db, recommend_db, lib_path, player): Self::Params,
-        _session: Option<Session>,
+        (fsio, main_db, recommend_db, lib_path, player, remote_output): Self::Params,
+        session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
         let request = dart_signal;
@@ -476,6 +911,15 @@ impl Signal for OperatePlaybackWithMixQueryRequest {
             .collect()
         };
 
+        if session.is_none()
+            && let Some(output) = remote_output.active().await
+        {
+            return operate_remote_playback(&output, &main_db, request, &tracks)
+                .await
+                .map(Some);
+        }
+
+        let loudness = fetch_track_loudness(&main_db, &tracks).await;
         let mut player = player.lock().await;
 
         let operate_mode = request.operate_mode;
@@ -483,6 +927,7 @@ impl Signal for OperatePlaybackWithMixQueryRequest {
         if operate_mode == PlaylistOperateMode::Replace {
             player.clear_playlist();
         }
+        player.set_track_loudness(loudness);
 
         let add_mode = if operate_mode == PlaylistOperateMode::PlayNext {
             AddMode::PlayNext
@@ -558,3 +1003,119 @@ impl Signal for OperatePlaybackWithMixQueryRequest {
         }))
     }
 }
+
+impl ParamsExtractor for StartRemoteOutputRequest {
+    type Params = (
+        Arc<String>,
+        Arc<RwLock<CertValidator>>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<dyn Broadcaster>,
+        Arc<RemoteOutputManager>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.config_path),
+            Arc::clone(&all_params.cert_validator),
+            Arc::clone(&all_params.player),
+            Arc::clone(&all_params.broadcaster),
+            Arc::clone(&all_params.remote_output),
+        )
+    }
+}
+
+impl Signal for StartRemoteOutputRequest {
+    type Params = (
+        Arc<String>,
+        Arc<RwLock<CertValidator>>,
+        Arc<Mutex<dyn Playable>>,
+        Arc<dyn Broadcaster>,
+        Arc<RemoteOutputManager>,
+    );
+    type Response = StartRemoteOutputResponse;
+
+    async fn handle(
+        &self,
+        (config_path, validator, player, broadcaster, remote_output): Self::Params,
+        _session: Option<Session>,
+        req: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let validator = Arc::new(validator.read().await.clone());
+        let client_config = Arc::new(validator.into_client_config());
+
+        let output = async {
+            let (fingerprint, _, _) =
+                generate_or_load_certificates(Path::new(&*config_path), &req.alias).await?;
+
+            let host = select_best_host(req.hosts.clone(), client_config.clone())
+                .await
+                .with_context(|| "Failed to select the best host")?;
+
+            let result = check_fingerprint(&host, client_config.clone(), &fingerprint)
+                .await
+                .with_context(|| "Failed to check fingerprint")?;
+
+            if !result.is_trusted {
+                bail!("This device is not trusted by {host}");
+            }
+
+            RemoteOutput::connect(
+                &host,
+                client_config.clone(),
+                &fingerprint,
+                &req.stream_host,
+                broadcaster,
+            )
+            .await
+        }
+        .await;
+
+        match output {
+            Ok(output) => {
+                let connected_host = output.host().to_string();
+                info!("Forwarding playback to {connected_host}");
+
+                remote_output.replace(Some(output)).await;
+                player.lock().await.pause();
+
+                Ok(Some(StartRemoteOutputResponse {
+                    success: true,
+                    error: String::new(),
+                    connected_host,
+                }))
+            }
+            Err(e) => Ok(Some(StartRemoteOutputResponse {
+                success: false,
+                error: format!("{e:#}"),
+                connected_host: String::new(),
+            })),
+        }
+    }
+}
+
+impl ParamsExtractor for StopRemoteOutputRequest {
+    type Params = (Arc<RemoteOutputManager>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.remote_output),)
+    }
+}
+
+impl Signal for StopRemoteOutputRequest {
+    type Params = (Arc<RemoteOutputManager>,);
+    type Response = StopRemoteOutputResponse;
+
+    async fn handle(
+        &self,
+        (remote_output,): Self::Params,
+        _session: Option<Session>,
+        _: &Self,
+    ) -> Result<Option<Self::Response>> {
+        remote_output.replace(None).await;
+
+        Ok(Some(StopRemoteOutputResponse {
+            success: true,
+            error: String::new(),
+        }))
+    }
+}
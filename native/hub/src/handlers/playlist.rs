@@ -9,7 +9,7 @@ use ::database::actions::playlists::{
     add_item_to_playlist, create_m3u8_playlist, create_playlist, get_all_playlists,
     get_playlist_by_id, remove_playlist, reorder_playlist_item_position, update_playlist,
 };
-use ::database::connection::MainDbConnection;
+use ::database::connection::{MainDbConnection, acquire_write_permit};
 
 use crate::utils::{GlobalParams, ParamsExtractor};
 use crate::{Session, Signal, messages::*};
@@ -73,6 +73,7 @@ impl Signal for CreatePlaylistRequest {
         let name = &request.name;
         let group = &request.group;
 
+        let _permit = acquire_write_permit().await;
         let txn = main_db.begin().await?;
         let playlist = create_playlist(&txn, &node_id, name.clone(), group.clone())
             .await
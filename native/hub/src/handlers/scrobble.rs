@@ -1,17 +1,41 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use log::info;
 use scrobbling::manager::ScrobblingServiceManager;
 use tokio::sync::Mutex;
 
-use ::scrobbling::manager::{ScrobblingCredential, ScrobblingManager};
+use ::database::actions::metadata::get_metadata_summary_by_track;
+use ::database::actions::scrobble_history::{
+    ScrobbleHistoryFilter, accepted_services, list_scrobble_history,
+};
+use ::database::actions::stats::set_liked_many;
+use ::database::connection::MainDbConnection;
+use ::scrobbling::error::ScrobblingApiError;
+use ::scrobbling::last_fm::LastFmClient;
+use ::scrobbling::libre_fm::ServiceEndpoint;
+use ::scrobbling::manager::{ScrobblingCredential, ScrobblingManager, ServiceFlags};
 
 use crate::{
     Session, Signal,
     messages::*,
-    utils::{GlobalParams, ParamsExtractor},
+    utils::{
+        GlobalParams, ParamsExtractor,
+        scrobble_rules::{ScrobbleRules, ScrobbleRulesStore},
+    },
 };
 
+/// The server a login request is made to, when not the default one.
+fn login_endpoint(request: &LoginRequestItem) -> Option<ServiceEndpoint> {
+    let base_url = request.base_url.as_ref().filter(|x| !x.trim().is_empty())?;
+
+    Some(ServiceEndpoint {
+        base_url: base_url.trim().to_owned(),
+        allow_insecure: request.allow_insecure.unwrap_or(false),
+    })
+}
+
 impl ParamsExtractor for AuthenticateSingleServiceRequest {
     type Params = (Arc<Mutex<dyn ScrobblingServiceManager>>,);
 
@@ -33,18 +57,23 @@ impl Signal for AuthenticateSingleServiceRequest {
         let request = &dart_signal.request;
 
         if let Some(request) = request {
-            let result = scrobbler
-                .lock()
-                .await
-                .authenticate(
-                    &request.service_id.clone().into(),
-                    &request.username,
-                    &request.password,
-                    request.api_key.clone(),
-                    request.api_secret.clone(),
-                    false,
-                )
-                .await;
+            let service = request.service_id.clone().into();
+            let mut scrobbler = scrobbler.lock().await;
+            let result = match scrobbler.set_endpoint(&service, login_endpoint(request)) {
+                Ok(()) => {
+                    scrobbler
+                        .authenticate(
+                            &service,
+                            &request.username,
+                            &request.password,
+                            request.api_key.clone(),
+                            request.api_secret.clone(),
+                            false,
+                        )
+                        .await
+                }
+                Err(e) => Err(e),
+            };
 
             let response = match result {
                 Ok(_) => AuthenticateSingleServiceResponse {
@@ -94,6 +123,11 @@ impl Signal for AuthenticateMultipleServiceRequest {
                     password: x.password.clone(),
                     api_key: x.api_key.clone(),
                     api_secret: x.api_secret.clone(),
+                    flags: ServiceFlags {
+                        scrobbling: x.scrobbling_enabled.unwrap_or(true),
+                        now_playing: x.now_playing_enabled.unwrap_or(true),
+                    },
+                    endpoint: login_endpoint(x),
                 })
                 .collect(),
         );
@@ -129,3 +163,333 @@ impl Signal for LogoutSingleServiceRequest {
         Ok(None)
     }
 }
+
+impl ParamsExtractor for SetScrobbleServiceFlagsRequest {
+    type Params = (Arc<Mutex<dyn ScrobblingServiceManager>>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.scrobbler),)
+    }
+}
+
+impl Signal for SetScrobbleServiceFlagsRequest {
+    type Params = (Arc<Mutex<dyn ScrobblingServiceManager>>,);
+    type Response = ();
+
+    async fn handle(
+        &self,
+        (scrobbler,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        scrobbler
+            .lock()
+            .await
+            .set_service_flags(
+                dart_signal.service_id.clone().into(),
+                ServiceFlags {
+                    scrobbling: dart_signal.scrobbling_enabled,
+                    now_playing: dart_signal.now_playing_enabled,
+                },
+            )
+            .await;
+
+        Ok(None)
+    }
+}
+
+impl From<ScrobbleRules> for ScrobbleRulesItem {
+    fn from(x: ScrobbleRules) -> Self {
+        Self {
+            min_played_fraction: x.min_played_fraction,
+            min_played_seconds: x.min_played_seconds,
+            min_track_seconds: x.min_track_seconds,
+            scrobble_repeats: x.scrobble_repeats,
+            history_retention_days: x.history_retention_days,
+        }
+    }
+}
+
+impl From<ScrobbleRulesItem> for ScrobbleRules {
+    fn from(x: ScrobbleRulesItem) -> Self {
+        Self {
+            min_played_fraction: x.min_played_fraction,
+            min_played_seconds: x.min_played_seconds,
+            min_track_seconds: x.min_track_seconds,
+            scrobble_repeats: x.scrobble_repeats,
+            history_retention_days: x.history_retention_days,
+        }
+    }
+}
+
+impl ParamsExtractor for FetchScrobbleRulesRequest {
+    type Params = (Arc<String>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.config_path),)
+    }
+}
+
+impl Signal for FetchScrobbleRulesRequest {
+    type Params = (Arc<String>,);
+    type Response = FetchScrobbleRulesResponse;
+
+    async fn handle(
+        &self,
+        (config_path,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let rules = ScrobbleRulesStore::new(Path::new(&*config_path))
+            .load()
+            .await?;
+
+        Ok(Some(FetchScrobbleRulesResponse {
+            rules: rules.into(),
+        }))
+    }
+}
+
+impl ParamsExtractor for UpdateScrobbleRulesRequest {
+    type Params = (Arc<String>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.config_path),)
+    }
+}
+
+impl Signal for UpdateScrobbleRulesRequest {
+    type Params = (Arc<String>,);
+    type Response = UpdateScrobbleRulesResponse;
+
+    async fn handle(
+        &self,
+        (config_path,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let rules: ScrobbleRules = dart_signal.rules.clone().into();
+        let result = ScrobbleRulesStore::new(Path::new(&*config_path))
+            .save(&rules)
+            .await;
+
+        let response = match result {
+            Ok(_) => UpdateScrobbleRulesResponse {
+                success: true,
+                error: None,
+            },
+            Err(e) => UpdateScrobbleRulesResponse {
+                success: false,
+                error: Some(format!("{e:#}")),
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
+
+impl ParamsExtractor for ListScrobbleHistoryRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for ListScrobbleHistoryRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = ListScrobbleHistoryResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        let filter = ScrobbleHistoryFilter {
+            start: request
+                .start_time
+                .and_then(|x| DateTime::from_timestamp(x, 0)),
+            end: request
+                .end_time
+                .and_then(|x| DateTime::from_timestamp(x, 0)),
+        };
+
+        let result = list_scrobble_history(
+            &main_db,
+            filter,
+            request.cursor.try_into()?,
+            request.page_size.try_into()?,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to list scrobble history: cursor={}, page_size={}",
+                request.cursor, request.page_size
+            )
+        })?;
+
+        Ok(Some(ListScrobbleHistoryResponse {
+            result: result
+                .into_iter()
+                .map(|x| ScrobbleHistoryItem {
+                    accepted_by: accepted_services(&x),
+                    id: x.id,
+                    media_file_id: x.media_file_id,
+                    artist: x.artist,
+                    title: x.title,
+                    album: x.album,
+                    timestamp: x.timestamp,
+                })
+                .collect(),
+        }))
+    }
+}
+
+/// Files this close to the length Last.fm knows of a loved track match it.
+const LOVED_TRACK_DURATION_TOLERANCE: f64 = 10.0;
+
+/// Likes the files matching the tracks loved on Last.fm, by artist and
+/// title, then by length. Tracks matching no file or several are reported
+/// rather than guessed.
+async fn import_loved_tracks(
+    main_db: &MainDbConnection,
+    client: &LastFmClient,
+) -> Result<ImportLovedTracksResponse> {
+    let username = client.username().await?;
+
+    let mut loved = 0;
+    let mut matched = Vec::new();
+    let mut mismatches = Vec::new();
+    let mut page = 1;
+    loop {
+        let loved_tracks = client.loved_tracks(&username, page).await?;
+
+        for track in loved_tracks.tracks {
+            loved += 1;
+
+            let candidates =
+                get_metadata_summary_by_track(main_db, &track.artist, &track.title).await?;
+            let reason = if candidates.is_empty() {
+                Some("not_in_library")
+            } else {
+                let duration = match client.track_duration(&track.artist, &track.title).await {
+                    Ok(x) => x,
+                    // Last.fm doesn't know the track under this name anymore
+                    Err(e)
+                        if matches!(
+                            ScrobblingApiError::of(&e),
+                            Some(ScrobblingApiError::Rejected(_))
+                        ) =>
+                    {
+                        None
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                let fitting: Vec<i32> = candidates
+                    .iter()
+                    .filter(|x| {
+                        duration.is_none_or(|duration| {
+                            (x.duration - duration.as_secs_f64()).abs()
+                                <= LOVED_TRACK_DURATION_TOLERANCE
+                        })
+                    })
+                    .map(|x| x.id)
+                    .collect();
+
+                match fitting.as_slice() {
+                    [id] => {
+                        matched.push(*id);
+                        None
+                    }
+                    [] => Some("duration_mismatch"),
+                    _ => Some("ambiguous"),
+                }
+            };
+
+            if let Some(reason) = reason {
+                mismatches.push(LovedTrackMismatch {
+                    artist: track.artist,
+                    title: track.title,
+                    reason: reason.to_owned(),
+                    candidates: candidates.iter().map(|x| x.id).collect(),
+                });
+            }
+        }
+
+        if page >= loved_tracks.total_pages {
+            break;
+        }
+        page += 1;
+    }
+
+    matched.sort_unstable();
+    matched.dedup();
+    let missing = set_liked_many(main_db, &matched, true).await?;
+
+    info!(
+        "Imported {loved} loved tracks from Last.fm, {} unmatched",
+        mismatches.len()
+    );
+
+    Ok(ImportLovedTracksResponse {
+        success: true,
+        error: None,
+        loved,
+        liked: (matched.len() - missing.len()) as u32,
+        mismatches,
+    })
+}
+
+impl ParamsExtractor for ImportLovedTracksRequest {
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn ScrobblingServiceManager>>,
+    );
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.scrobbler),
+        )
+    }
+}
+
+impl Signal for ImportLovedTracksRequest {
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn ScrobblingServiceManager>>,
+    );
+    type Response = ImportLovedTracksResponse;
+
+    async fn handle(
+        &self,
+        (main_db, scrobbler): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        // Not held while the loved tracks are fetched
+        let client = scrobbler.lock().await.lastfm_client();
+
+        let result = match client {
+            Some(client) => import_loved_tracks(&main_db, &client).await,
+            None => Err(anyhow::anyhow!("Not logged in to Last.fm")),
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => ImportLovedTracksResponse {
+                success: false,
+                error: Some(format!("{e:#}")),
+                loved: 0,
+                liked: 0,
+                mismatches: Vec::new(),
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
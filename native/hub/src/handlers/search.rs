@@ -1,10 +1,14 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use anyhow::{Context, Result};
+use log::info;
 
 use ::database::actions::collection::CollectionQueryType;
+use ::database::actions::lyrics::{
+    is_lyrics_indexing_enabled, rebuild_lyrics_index, set_lyrics_indexing_enabled,
+};
 use ::database::actions::search::convert_to_collection_types;
-use ::database::actions::search::search_for;
+use ::database::actions::search::{search_for, search_lyrics};
 use ::database::connection::MainDbConnection;
 
 use crate::{
@@ -65,11 +69,125 @@ impl Signal for SearchForRequest {
             }
         }
 
+        let lyrics = if request.include_lyrics {
+            search_lyrics(&main_db, query_str, n)
+                .await
+                .with_context(|| format!("Lyric search failed: query_str={query_str}, n={n}"))?
+                .into_iter()
+                .map(|x| LyricSearchHit {
+                    track_id: x.file_id as i32,
+                    snippet: x.snippet,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
         Ok(Some(SearchForResponse {
             artists,
             albums,
             playlists,
             tracks,
+            lyrics,
+        }))
+    }
+}
+
+impl ParamsExtractor for SetLyricsIndexingRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SetLyricsIndexingRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SetLyricsIndexingResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let result = set_lyrics_indexing_enabled(main_db.as_ref(), dart_signal.enabled).await;
+        if result.is_ok() {
+            info!("Lyrics indexing set to {}", dart_signal.enabled);
+        }
+
+        Ok(Some(SetLyricsIndexingResponse {
+            enabled: is_lyrics_indexing_enabled(main_db.as_ref()).await?,
+            success: result.is_ok(),
+            error: result.err().map(|e| format!("{e:#}")),
+        }))
+    }
+}
+
+impl ParamsExtractor for GetLyricsIndexingRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for GetLyricsIndexingRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = GetLyricsIndexingResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        Ok(Some(GetLyricsIndexingResponse {
+            enabled: is_lyrics_indexing_enabled(main_db.as_ref()).await?,
+        }))
+    }
+}
+
+impl ParamsExtractor for RebuildLyricsIndexRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.lib_path),
+        )
+    }
+}
+
+impl Signal for RebuildLyricsIndexRequest {
+    type Params = (Arc<MainDbConnection>, Arc<String>);
+    type Response = RebuildLyricsIndexResponse;
+
+    async fn handle(
+        &self,
+        (main_db, lib_path): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let enabled = is_lyrics_indexing_enabled(main_db.as_ref()).await?;
+        let result = rebuild_lyrics_index(&main_db, Path::new(lib_path.as_str())).await;
+        if let Ok(indexed) = result {
+            info!("Rebuilt the lyrics index, {indexed} tracks indexed");
+        }
+
+        Ok(Some(match result {
+            Ok(indexed) => RebuildLyricsIndexResponse {
+                enabled,
+                indexed: indexed as i32,
+                success: true,
+                error: None,
+            },
+            Err(e) => RebuildLyricsIndexResponse {
+                enabled,
+                indexed: 0,
+                success: false,
+                error: Some(format!("{e:#}")),
+            },
         }))
     }
 }
@@ -1,12 +1,18 @@
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
+use log::error;
+use tokio::sync::Mutex;
 
 use ::database::{
-    actions::stats::{get_liked, set_liked},
+    actions::{
+        metadata::get_metadata_summary_by_file_id,
+        stats::{get_liked, set_liked, set_rating},
+    },
     connection::MainDbConnection,
 };
 use ::playback::player::PlayingItem;
+use ::scrobbling::{ScrobblingTrack, manager::ScrobblingServiceManager};
 
 use crate::{
     Session, Signal,
@@ -15,20 +21,29 @@ use crate::{
 };
 
 impl ParamsExtractor for SetLikedRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn ScrobblingServiceManager>>,
+    );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.main_db),)
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.scrobbler),
+        )
     }
 }
 
 impl Signal for SetLikedRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<Mutex<dyn ScrobblingServiceManager>>,
+    );
     type Response = SetLikedResponse;
 
     async fn handle(
         &self,
-        (main_db,): Self::Params,
+        (main_db, scrobbler): Self::Params,
         _session: Option<Session>,
         dart_signal: &Self,
     ) -> Result<Option<Self::Response>> {
@@ -39,7 +54,7 @@ impl Signal for SetLikedRequest {
 
             let response = match parsed_item {
                 PlayingItem::InLibrary(file_id) => {
-                    set_liked(&main_db, file_id, request.liked)
+                    let stats = set_liked(&main_db, file_id, request.liked)
                         .await
                         .with_context(|| {
                             format!(
@@ -48,6 +63,24 @@ impl Signal for SetLikedRequest {
                             )
                         })?;
 
+                    // Mirrored as a love on the scrobbling services
+                    if stats.is_some() {
+                        match get_metadata_summary_by_file_id(&main_db, file_id).await {
+                            Ok(metadata) => scrobbler.lock().await.set_loved(
+                                ScrobblingTrack {
+                                    artist: metadata.artist,
+                                    track: metadata.title,
+                                    album: Some(metadata.album),
+                                    album_artist: None,
+                                    duration: None,
+                                    timestamp: None,
+                                },
+                                request.liked,
+                            ),
+                            Err(e) => error!("Failed to love file {file_id}: {e:#}"),
+                        }
+                    }
+
                     SetLikedResponse {
                         item: item.clone(),
                         liked: request.liked,
@@ -125,3 +158,58 @@ impl Signal for GetLikedRequest {
         Ok(None)
     }
 }
+
+impl ParamsExtractor for SetRatingRequest {
+    type Params = (Arc<MainDbConnection>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.main_db),)
+    }
+}
+
+impl Signal for SetRatingRequest {
+    type Params = (Arc<MainDbConnection>,);
+    type Response = SetRatingResponse;
+
+    async fn handle(
+        &self,
+        (main_db,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let request = dart_signal;
+
+        if let Some(item) = &request.item {
+            let parsed_item: PlayingItem = item.clone().into();
+
+            let response = match parsed_item {
+                PlayingItem::InLibrary(file_id) => {
+                    let stats = set_rating(&main_db, file_id, request.rating)
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "Failed to set rating: file_id={}, rating={:?}",
+                                file_id, request.rating
+                            )
+                        })?;
+
+                    SetRatingResponse {
+                        item: item.clone(),
+                        rating: stats.as_ref().and_then(|x| x.rating),
+                        success: stats.is_some(),
+                    }
+                }
+                // Only files of the library have stats
+                _ => SetRatingResponse {
+                    item: item.clone(),
+                    rating: None,
+                    success: false,
+                },
+            };
+
+            return Ok(Some(response));
+        }
+
+        Ok(None)
+    }
+}
@@ -1,36 +1,120 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc, time::Instant};
 
 use anyhow::Result;
+use log::warn;
 use sysinfo::System;
 use sysinfo::Users;
+use tokio::task;
 
-use ::database::connection::MainDbConnection;
+use ::analysis::utils::computing_device::gpu_adapter_info;
+use ::database::{
+    actions::schema::{SchemaStatus, schema_status},
+    connection::{MainDbConnection, database_file_sizes},
+};
+use ::fsio::FsIo;
+use ::playback::output_stream::{audio_host_name, output_device_name};
 
 use crate::{
     Session, Signal,
     messages::*,
-    utils::{GlobalParams, ParamsExtractor},
+    server::protocol::server_capabilities,
+    utils::{
+        GlobalParams, ParamsExtractor, RunningMode,
+        log_file::{log_dir, log_dir_size},
+        log_filter::current_log_filter,
+        proxy_settings::{ProxyProtocol, ProxySettings, ProxySettingsStore, ProxySource},
+    },
 };
 
+#[cfg(target_os = "macos")]
+fn bundle_id() -> String {
+    crate::apple_bridge::get_bundle_id()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn bundle_id() -> String {
+    String::new()
+}
+
+fn volume_space(fsio: &FsIo, path: &str) -> Option<VolumeSpace> {
+    let space = fsio.disk_space(Path::new(path)).ok()?;
+    Some(VolumeSpace {
+        total_bytes: space.total,
+        available_bytes: space.available,
+    })
+}
+
+impl From<SchemaStatus> for SchemaInfo {
+    fn from(x: SchemaStatus) -> Self {
+        Self {
+            version: x.version,
+            latest_version: x.latest_version,
+            pending_migrations: x.pending.len() as u32,
+            unknown_migrations: x.unknown,
+        }
+    }
+}
+
 impl ParamsExtractor for SystemInfoRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<FsIo>,
+        Arc<String>,
+        Arc<String>,
+        Arc<String>,
+        RunningMode,
+        Instant,
+    );
 
     fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
-        (Arc::clone(&all_params.main_db),)
+        (
+            Arc::clone(&all_params.main_db),
+            Arc::clone(&all_params.fsio),
+            Arc::clone(&all_params.lib_path),
+            Arc::clone(&all_params.config_path),
+            Arc::clone(&all_params.node_id),
+            all_params.running_mode,
+            all_params.started_at,
+        )
     }
 }
 
 impl Signal for SystemInfoRequest {
-    type Params = (Arc<MainDbConnection>,);
+    type Params = (
+        Arc<MainDbConnection>,
+        Arc<FsIo>,
+        Arc<String>,
+        Arc<String>,
+        Arc<String>,
+        RunningMode,
+        Instant,
+    );
     type Response = SystemInfoResponse;
 
     async fn handle(
         &self,
-        (_main_db,): Self::Params,
+        (main_db, fsio, lib_path, config_path, node_id, running_mode, started_at): Self::Params,
         _session: Option<Session>,
         _: &Self,
     ) -> Result<Option<Self::Response>> {
         let users = Users::new_with_refreshed_list();
+        let schema = match schema_status(&*main_db).await {
+            Ok(status) => Some(status.into()),
+            Err(e) => {
+                warn!("Failed to read the schema of the library: {e:#}");
+                None
+            }
+        };
+        let database_sizes = database_file_sizes(&main_db).await.unwrap_or_else(|e| {
+            warn!("Failed to read the size of the databases: {e:#}");
+            Default::default()
+        });
+        // Looking up the adapter blocks the first time
+        let gpu_adapter = task::spawn_blocking(gpu_adapter_info)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
 
         Ok(Some(SystemInfoResponse {
             build_date: option_env!("VERGEN_BUILD_DATE")
@@ -43,11 +127,167 @@ impl Signal for SystemInfoRequest {
             build_rustc_semver: option_env!("VERGEN_RUSTC_SEMVER")
                 .unwrap_or_default()
                 .to_owned(),
+            hub_version: env!("CARGO_PKG_VERSION").to_owned(),
             system_name: System::name().unwrap_or_default(),
             system_kernel_version: System::kernel_version().unwrap_or_default(),
             system_os_version: System::os_version().unwrap_or_default(),
             system_host_name: System::host_name().unwrap_or_default(),
             users: users.into_iter().map(|x| x.name().to_owned()).collect(),
+            library_space: volume_space(&fsio, &lib_path),
+            config_space: volume_space(&fsio, &config_path),
+            log_filter: current_log_filter(),
+            log_directory: log_dir().map(|x| x.to_string_lossy().into_owned()),
+            log_directory_bytes: log_dir().map(log_dir_size).unwrap_or_default(),
+            schema,
+            os: std::env::consts::OS.to_owned(),
+            arch: std::env::consts::ARCH.to_owned(),
+            bundle_id: bundle_id(),
+            audio_host: audio_host_name(),
+            audio_device: output_device_name(),
+            gpu_adapter: gpu_adapter.name,
+            gpu_driver: gpu_adapter.driver,
+            gpu_backend: gpu_adapter.backend,
+            database_bytes: database_sizes.main_bytes,
+            database_wal_bytes: database_sizes.main_wal_bytes,
+            recommendation_database_bytes: database_sizes.recommendation_bytes,
+            node_id: node_id.to_string(),
+            running_mode: match running_mode {
+                RunningMode::Client => "local",
+                RunningMode::Server => "server",
+            }
+            .to_owned(),
+            uptime_seconds: started_at.elapsed().as_secs(),
+        }))
+    }
+}
+
+impl ParamsExtractor for ServerCapabilitiesRequest {
+    type Params = ();
+
+    fn extract_params(&self, _: &GlobalParams) -> Self::Params {}
+}
+
+impl Signal for ServerCapabilitiesRequest {
+    type Params = ();
+    type Response = ServerCapabilitiesResponse;
+
+    async fn handle(
+        &self,
+        _: Self::Params,
+        _session: Option<Session>,
+        _: &Self,
+    ) -> Result<Option<Self::Response>> {
+        Ok(Some(server_capabilities()))
+    }
+}
+
+impl From<ProxySettings> for ProxySettingsItem {
+    fn from(x: ProxySettings) -> Self {
+        Self {
+            source: match x.source {
+                ProxySource::Environment => ProxySourceItem::Environment,
+                ProxySource::Disabled => ProxySourceItem::Disabled,
+                ProxySource::Manual => ProxySourceItem::Manual,
+            },
+            protocol: match x.protocol {
+                ProxyProtocol::Http => ProxyProtocolItem::Http,
+                ProxyProtocol::Socks5 => ProxyProtocolItem::Socks5,
+            },
+            host: x.host,
+            port: x.port,
+            username: x.username,
+            password: x.password,
+            no_proxy: x.no_proxy,
+        }
+    }
+}
+
+impl From<ProxySettingsItem> for ProxySettings {
+    fn from(x: ProxySettingsItem) -> Self {
+        Self {
+            source: match x.source {
+                ProxySourceItem::Environment => ProxySource::Environment,
+                ProxySourceItem::Disabled => ProxySource::Disabled,
+                ProxySourceItem::Manual => ProxySource::Manual,
+            },
+            protocol: match x.protocol {
+                ProxyProtocolItem::Http => ProxyProtocol::Http,
+                ProxyProtocolItem::Socks5 => ProxyProtocol::Socks5,
+            },
+            host: x.host,
+            port: x.port,
+            username: x.username,
+            password: x.password,
+            no_proxy: x.no_proxy,
+        }
+    }
+}
+
+impl ParamsExtractor for FetchProxySettingsRequest {
+    type Params = (Arc<String>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.config_path),)
+    }
+}
+
+impl Signal for FetchProxySettingsRequest {
+    type Params = (Arc<String>,);
+    type Response = FetchProxySettingsResponse;
+
+    async fn handle(
+        &self,
+        (config_path,): Self::Params,
+        _session: Option<Session>,
+        _dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let settings = ProxySettingsStore::new(Path::new(&*config_path))
+            .load()
+            .await?;
+
+        Ok(Some(FetchProxySettingsResponse {
+            settings: settings.into(),
         }))
     }
 }
+
+impl ParamsExtractor for UpdateProxySettingsRequest {
+    type Params = (Arc<String>,);
+
+    fn extract_params(&self, all_params: &GlobalParams) -> Self::Params {
+        (Arc::clone(&all_params.config_path),)
+    }
+}
+
+impl Signal for UpdateProxySettingsRequest {
+    type Params = (Arc<String>,);
+    type Response = UpdateProxySettingsResponse;
+
+    async fn handle(
+        &self,
+        (config_path,): Self::Params,
+        _session: Option<Session>,
+        dart_signal: &Self,
+    ) -> Result<Option<Self::Response>> {
+        let settings: ProxySettings = dart_signal.settings.clone().into();
+        let result = ProxySettingsStore::new(Path::new(&*config_path))
+            .save(&settings)
+            .await;
+
+        let response = match result {
+            Ok(_) => {
+                settings.apply();
+                UpdateProxySettingsResponse {
+                    success: true,
+                    error: None,
+                }
+            }
+            Err(e) => UpdateProxySettingsResponse {
+                success: false,
+                error: Some(format!("{e:#}")),
+            },
+        };
+
+        Ok(Some(response))
+    }
+}
@@ -21,13 +21,20 @@ pub use tokio;
 
 use ::scrobbling::manager::ScrobblingManager;
 
-use utils::{TaskTokens, receive_media_library_path};
+use utils::{receive_media_library_path, task_registry::TaskRegistry};
 
-use crate::utils::init_logging;
+use crate::utils::{
+    init_logging,
+    log_file::{log_dir, open_log_file},
+    log_filter::{FILE_LOG_FILTER, register_log_filter},
+};
 
 pub struct Session {
     pub fingerprint: String,
     pub host: String,
+    /// Admin token held by the connection, set once the client
+    /// authenticated with the root password.
+    pub admin_token: Arc<std::sync::Mutex<Option<String>>>,
 }
 
 pub trait Signal: Sized {
@@ -55,20 +62,33 @@ async fn main() {
     let scrobbler = ScrobblingManager::new(10, Duration::new(5, 0));
     let scrobbler = Arc::new(Mutex::new(scrobbler));
 
-    let _guard = if enable_log {
-        let file_filter = EnvFilter::new("debug");
-        let now = chrono::Local::now();
-        let file_name = format!("{}.rune.log", now.format("%Y-%m-%d_%H-%M-%S"));
-        let file_appender = tracing_appender::rolling::never(".", file_name);
-        let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+    let log_file = if enable_log {
+        match open_log_file() {
+            Ok(x) => Some(x),
+            Err(e) => {
+                eprintln!("Failed to open the log file, logging to stdout: {e:#}");
+                None
+            }
+        }
+    } else {
+        None
+    };
 
-        tracing_subscriber::fmt()
-            .with_env_filter(file_filter)
+    let _guard = if let Some(log_file) = log_file {
+        let (non_blocking, guard) = tracing_appender::non_blocking(log_file);
+
+        let builder = tracing_subscriber::fmt()
             .with_writer(non_blocking)
             .with_timer(fmt::time::ChronoLocal::rfc_3339())
-            .init();
-
-        info!("Logging is enabled");
+            .with_env_filter(EnvFilter::new(FILE_LOG_FILTER))
+            .with_filter_reloading();
+        let handle = builder.reload_handle();
+        builder.init();
+        register_log_filter(handle, FILE_LOG_FILTER);
+
+        if let Some(dir) = log_dir() {
+            info!("Logging is enabled, writing into {dir:?}");
+        }
         Some(guard)
     } else {
         init_logging();
@@ -1,3 +1,304 @@
 use requests::define_request_types;
 
-define_request_types!();
+// The requests the hub answers, `Request => Response` or `Request` alone for
+// requests without a response, each with the scope of `server::scope::Scope`
+// it requires. `local_only` requests are only answered for the local GUI,
+// never forwarded to a server. Every name must be a signal of
+// `crate::messages`, requests implementing `Default` for the generated tests
+// to send them. The protocol version is bumped whenever a request
+// changes in a way its name doesn't tell, peers without one speak version 1.
+define_request_types! {
+    #![protocol_version = 7]
+
+    // Library
+    #[scope(local_only)]
+    TestLibraryInitializedRequest => TestLibraryInitializedResponse,
+    #[scope(local_only)]
+    CloseLibraryRequest => CloseLibraryResponse,
+    #[scope(library_write)]
+    CancelTaskRequest => CancelTaskResponse,
+    #[scope(read)]
+    ListTasksRequest => ListTasksResponse,
+    #[scope(library_write)]
+    ScanAudioLibraryRequest,
+    #[scope(library_write)]
+    AnalyzeAudioLibraryRequest,
+    #[scope(admin)]
+    DeduplicateAudioLibraryRequest,
+
+    // Playback
+    #[scope(playback)]
+    VolumeRequest => VolumeResponse,
+    #[scope(playback)]
+    RelativeVolumeRequest => RelativeVolumeResponse,
+    #[scope(playback)]
+    LoadRequest,
+    #[scope(playback)]
+    PlayRequest,
+    #[scope(playback)]
+    PauseRequest,
+    #[scope(playback)]
+    NextRequest,
+    #[scope(playback)]
+    PreviousRequest,
+    #[scope(playback)]
+    SwitchRequest,
+    #[scope(playback)]
+    SeekRequest,
+    #[scope(playback)]
+    RemoveRequest,
+    #[scope(playback)]
+    SetPlaybackModeRequest,
+    #[scope(playback)]
+    MovePlaylistItemRequest,
+    #[scope(read)]
+    FetchPlaybackQueueRequest => FetchPlaybackQueueResponse,
+    #[scope(playback)]
+    SetRealtimeFFTEnabledRequest,
+    #[scope(playback)]
+    SetAdaptiveSwitchingEnabledRequest,
+    #[scope(playback)]
+    SetGaplessEnabledRequest,
+    #[scope(playback)]
+    SetCrossfadeRequest,
+    #[scope(playback)]
+    SetLoudnessNormalizationRequest,
+    #[scope(playback)]
+    ListAudioDevicesRequest => ListAudioDevicesResponse,
+    #[scope(playback)]
+    SetAudioDeviceRequest,
+
+    // SFX
+    #[scope(local_only)]
+    SfxPlayRequest,
+
+    // Analyze
+    #[scope(read)]
+    IfAnalyzeExistsRequest => IfAnalyzeExistsResponse,
+    #[scope(read)]
+    GetAnalyzeCountRequest => GetAnalyzeCountResponse,
+
+    // Media File
+    #[scope(read)]
+    FetchMediaFilesRequest => FetchMediaFilesResponse,
+    #[scope(read)]
+    FetchMediaFileByIdsRequest => FetchMediaFileByIdsResponse,
+    #[scope(read)]
+    FetchParsedMediaFileRequest => FetchParsedMediaFileResponse,
+    #[scope(read)]
+    SearchMediaFileSummaryRequest => SearchMediaFileSummaryResponse,
+    #[scope(read)]
+    FetchMediaFileIdsByHlcUuidsRequest => FetchMediaFileIdsByHlcUuidsResponse,
+    #[scope(read)]
+    PreviewPathMetadataRequest => PreviewPathMetadataResponse,
+    #[scope(library_write)]
+    ApplyPathMetadataRequest => ApplyPathMetadataResponse,
+    #[scope(read)]
+    PreviewTagNormalizationRequest => PreviewTagNormalizationResponse,
+    #[scope(library_write)]
+    ApplyTagNormalizationRequest => ApplyTagNormalizationResponse,
+    #[scope(read)]
+    PreviewOrganizeFilesRequest => PreviewOrganizeFilesResponse,
+    #[scope(library_write)]
+    OrganizeFilesRequest => OrganizeFilesResponse,
+    #[scope(library_write)]
+    IdentifyAudioRequest => IdentifyAudioResponse,
+    #[scope(library_write)]
+    ApplyIdentificationRequest => ApplyIdentificationResponse,
+    #[scope(read)]
+    PreviewReleaseMappingRequest => PreviewReleaseMappingResponse,
+    #[scope(library_write)]
+    ApplyReleaseMappingRequest => ApplyReleaseMappingResponse,
+
+    // Lyric
+    #[scope(read)]
+    GetMediaFilesCountRequest => GetMediaFilesCountResponse,
+    #[scope(read)]
+    GetLyricByTrackIdRequest => GetLyricByTrackIdResponse,
+
+    // Collection
+    #[scope(read)]
+    FetchCollectionGroupSummaryRequest => CollectionGroupSummaryResponse,
+    #[scope(read)]
+    FetchCollectionGroupsRequest => FetchCollectionGroupsResponse,
+    #[scope(read)]
+    FetchCollectionByIdsRequest => FetchCollectionByIdsResponse,
+    #[scope(read)]
+    SearchCollectionSummaryRequest => SearchCollectionSummaryResponse,
+
+    // Cover Art
+    #[scope(read)]
+    GetCoverArtIdsByMixQueriesRequest => GetCoverArtIdsByMixQueriesResponse,
+    #[scope(library_write)]
+    SetAlbumCoverArtRequest => SetAlbumCoverArtResponse,
+    #[scope(read)]
+    GetPrimaryColorByTrackIdRequest => GetPrimaryColorByTrackIdResponse,
+
+    // Playlist
+    #[scope(read)]
+    FetchAllPlaylistsRequest => FetchAllPlaylistsResponse,
+    #[scope(library_write)]
+    CreatePlaylistRequest => CreatePlaylistResponse,
+    #[scope(library_write)]
+    CreateM3u8PlaylistRequest => CreateM3u8PlaylistResponse,
+    #[scope(library_write)]
+    UpdatePlaylistRequest => UpdatePlaylistResponse,
+    #[scope(library_write)]
+    RemovePlaylistRequest => RemovePlaylistResponse,
+    #[scope(library_write)]
+    AddItemToPlaylistRequest => AddItemToPlaylistResponse,
+    #[scope(library_write)]
+    ReorderPlaylistItemPositionRequest => ReorderPlaylistItemPositionResponse,
+    #[scope(library_write)]
+    RemoveItemFromPlaylistRequest => RemoveItemFromPlaylistResponse,
+    #[scope(read)]
+    GetPlaylistByIdRequest => GetPlaylistByIdResponse,
+
+    // Mix
+    #[scope(read)]
+    FetchAllMixesRequest => FetchAllMixesResponse,
+    #[scope(library_write)]
+    CreateMixRequest => CreateMixResponse,
+    #[scope(library_write)]
+    UpdateMixRequest => UpdateMixResponse,
+    #[scope(library_write)]
+    RemoveMixRequest => RemoveMixResponse,
+    #[scope(library_write)]
+    AddItemToMixRequest => AddItemToMixResponse,
+    #[scope(read)]
+    GetMixByIdRequest => GetMixByIdResponse,
+    #[scope(read)]
+    MixQueryRequest => MixQueryResponse,
+    #[scope(read)]
+    FetchMixQueriesRequest => FetchMixQueriesResponse,
+    #[scope(playback)]
+    OperatePlaybackWithMixQueryRequest => OperatePlaybackWithMixQueryResponse,
+
+    // Like
+    #[scope(library_write)]
+    SetLikedRequest => SetLikedResponse,
+    #[scope(read)]
+    GetLikedRequest => GetLikedResponse,
+    #[scope(library_write)]
+    SetRatingRequest => SetRatingResponse,
+
+    // Query and Search
+    #[scope(read)]
+    ComplexQueryRequest => ComplexQueryResponse,
+    #[scope(read)]
+    SearchForRequest => SearchForResponse,
+    #[scope(read)]
+    GetLyricsIndexingRequest => GetLyricsIndexingResponse,
+    #[scope(admin)]
+    SetLyricsIndexingRequest => SetLyricsIndexingResponse,
+    #[scope(admin)]
+    RebuildLyricsIndexRequest => RebuildLyricsIndexResponse,
+
+    // Directory
+    #[scope(read)]
+    FetchDirectoryTreeRequest => FetchDirectoryTreeResponse,
+
+    // Scrobbler
+    #[scope(library_write)]
+    AuthenticateSingleServiceRequest => AuthenticateSingleServiceResponse,
+    #[scope(library_write)]
+    AuthenticateMultipleServiceRequest,
+    #[scope(library_write)]
+    LogoutSingleServiceRequest,
+    #[scope(library_write)]
+    SetScrobbleServiceFlagsRequest,
+    #[scope(read)]
+    FetchScrobbleRulesRequest => FetchScrobbleRulesResponse,
+    #[scope(library_write)]
+    UpdateScrobbleRulesRequest => UpdateScrobbleRulesResponse,
+    #[scope(read)]
+    ListScrobbleHistoryRequest => ListScrobbleHistoryResponse,
+    #[scope(library_write)]
+    ImportLovedTracksRequest => ImportLovedTracksResponse,
+
+    // Log
+    #[scope(read)]
+    ListLogRequest => ListLogResponse,
+    #[scope(local_only)]
+    ExportLogsRequest => ExportLogsResponse,
+    #[scope(admin)]
+    ClearLogRequest => ClearLogResponse,
+    #[scope(admin)]
+    RemoveLogRequest => RemoveLogResponse,
+    #[scope(admin)]
+    ListAuditLogRequest => ListAuditLogResponse,
+    #[scope(admin)]
+    SetLogLevelRequest => SetLogLevelResponse,
+
+    // System
+    #[scope(read)]
+    SystemInfoRequest => SystemInfoResponse,
+    #[scope(read)]
+    ServerCapabilitiesRequest => ServerCapabilitiesResponse,
+    #[scope(local_only)]
+    FetchProxySettingsRequest => FetchProxySettingsResponse,
+    #[scope(local_only)]
+    UpdateProxySettingsRequest => UpdateProxySettingsResponse,
+
+    // License
+    #[scope(admin)]
+    RegisterLicenseRequest => RegisterLicenseResponse,
+    #[scope(read)]
+    ValidateLicenseRequest => ValidateLicenseResponse,
+
+    // Neighbors
+    #[scope(local_only)]
+    StartBroadcastRequest,
+    #[scope(local_only)]
+    StopBroadcastRequest,
+    #[scope(local_only)]
+    StartListeningRequest,
+    #[scope(local_only)]
+    StopListeningRequest,
+    #[scope(read)]
+    GetDiscoveredDeviceRequest => GetDiscoveredDeviceResponse,
+    #[scope(admin)]
+    StartServerRequest => StartServerResponse,
+    #[scope(admin)]
+    StopServerRequest => StopServerResponse,
+    #[scope(admin)]
+    ListClientsRequest => ListClientsResponse,
+    #[scope(read)]
+    AuthenticateAdminRequest => AuthenticateAdminResponse,
+    #[scope(read)]
+    RefreshAdminTokenRequest => RefreshAdminTokenResponse,
+    #[scope(local_only)]
+    GetSslCertificateFingerprintRequest => GetSslCertificateFingerprintResponse,
+    #[scope(local_only)]
+    AddTrustedServerRequest => AddTrustedServerResponse,
+    #[scope(local_only)]
+    RemoveTrustedClientRequest => RemoveTrustedClientResponse,
+    #[scope(admin)]
+    UpdateClientStatusRequest => UpdateClientStatusResponse,
+    #[scope(local_only)]
+    ExportTrustBundleRequest => ExportTrustBundleResponse,
+    #[scope(local_only)]
+    ImportTrustBundleRequest => ImportTrustBundleResponse,
+    #[scope(local_only)]
+    EditHostsRequest => EditHostsResponse,
+    #[scope(local_only)]
+    RemoveTrustedServerRequest => RemoveTrustedServerResponse,
+    #[scope(local_only)]
+    ServerAvailabilityTestRequest => ServerAvailabilityTestResponse,
+    #[scope(local_only)]
+    RegisterDeviceOnServerRequest => RegisterDeviceOnServerResponse,
+    #[scope(local_only)]
+    CheckDeviceOnServerRequest => CheckDeviceOnServerResponse,
+    #[scope(local_only)]
+    ConnectRequest => ConnectResponse,
+    #[scope(local_only)]
+    FetchServerCertificateRequest => FetchServerCertificateResponse,
+    #[scope(local_only)]
+    FetchRemoteFileRequest => FetchRemoteFileResponse,
+    #[scope(local_only)]
+    SetMaxConcurrentDownloadsRequest,
+    #[scope(local_only)]
+    StartRemoteOutputRequest => StartRemoteOutputResponse,
+    #[scope(local_only)]
+    StopRemoteOutputRequest => StopRemoteOutputResponse,
+}
@@ -1,7 +1,7 @@
 use rinf::{DartSignal, RustSignal};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct IfAnalyzeExistsRequest {
     pub file_id: i32,
 }
@@ -12,7 +12,7 @@ pub struct IfAnalyzeExistsResponse {
     pub exists: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetAnalyzeCountRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
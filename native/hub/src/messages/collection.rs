@@ -5,8 +5,9 @@ use serde::{Deserialize, Serialize};
 
 use super::mix::MixQuery;
 
-#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum CollectionType {
+    #[default]
     Album,
     Artist,
     Playlist,
@@ -16,9 +17,10 @@ pub enum CollectionType {
     Directory,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchCollectionGroupSummaryRequest {
     pub collection_type: CollectionType,
+    pub bypass_cache: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, SignalPiece)]
@@ -33,11 +35,12 @@ pub struct CollectionGroupSummaryResponse {
     pub groups: Vec<CollectionGroupSummary>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchCollectionGroupsRequest {
     pub collection_type: CollectionType,
     pub bake_cover_arts: bool,
     pub group_titles: Vec<String>,
+    pub bypass_cache: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, SignalPiece)]
@@ -61,11 +64,12 @@ pub struct FetchCollectionGroupsResponse {
     pub groups: Vec<CollectionGroup>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchCollectionByIdsRequest {
     pub collection_type: CollectionType,
     pub bake_cover_arts: bool,
     pub ids: Vec<i32>,
+    pub bypass_cache: bool,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -74,7 +78,7 @@ pub struct FetchCollectionByIdsResponse {
     pub result: Vec<Collection>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SearchCollectionSummaryRequest {
     pub collection_type: Option<CollectionType>,
     pub bake_cover_arts: Option<bool>,
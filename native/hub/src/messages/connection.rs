@@ -1,13 +1,16 @@
 use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+use super::system::SchemaInfo;
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum LibraryInitializeMode {
+    #[default]
     Portable,
     Redirected,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct TestLibraryInitializedRequest {
     pub path: String,
 }
@@ -18,15 +21,19 @@ pub struct TestLibraryInitializedResponse {
     pub success: bool,
     pub error: Option<String>,
     pub not_ready: bool,
+    /// The schema of a portable library, read before it is migrated so the
+    /// user can be warned first
+    pub schema: Option<SchemaInfo>,
 }
 
-#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum OperationDestination {
+    #[default]
     Local,
     Remote,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SetMediaLibraryPathRequest {
     pub path: String,
     pub db_path: String,
@@ -43,4 +50,24 @@ pub struct SetMediaLibraryPathResponse {
     pub success: bool,
     pub error: Option<String>,
     pub not_ready: bool,
+    /// The library cannot be written to, its databases are kept in the
+    /// per-user directory and features writing into it are disabled.
+    pub read_only: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct HostProbeResult {
+    pub host: String,
+    /// Time until the TLS handshake completed, absent if it failed.
+    pub latency_ms: Option<u32>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RemoteConnectionStatus {
+    pub connected: bool,
+    pub host: String,
+    /// Whether the host remembered from the previous connection was reused.
+    pub sticky: bool,
+    pub probes: Vec<HostProbeResult>,
 }
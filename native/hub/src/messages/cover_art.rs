@@ -4,13 +4,13 @@ use serde::{Deserialize, Serialize};
 use super::mix::MixQuery;
 use super::playback::PlayingItemRequest;
 
-#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
 pub struct GetCoverArtIdsByMixQueriesRequestUnit {
     pub id: i32,
     pub queries: Vec<MixQuery>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetCoverArtIdsByMixQueriesRequest {
     pub requests: Vec<GetCoverArtIdsByMixQueriesRequestUnit>,
     pub n: i32,
@@ -27,7 +27,7 @@ pub struct GetCoverArtIdsByMixQueriesResponse {
     pub result: Vec<GetCoverArtIdsByMixQueriesResponseUnit>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetPrimaryColorByTrackIdRequest {
     pub item: Option<PlayingItemRequest>,
 }
@@ -37,3 +37,41 @@ pub struct GetPrimaryColorByTrackIdResponse {
     pub item: PlayingItemRequest,
     pub primary_color: Option<i32>,
 }
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum EmbedImageFormat {
+    #[default]
+    Jpeg,
+    Png,
+}
+
+/// Sets the cover art of albums, optionally embedding it into the tags of
+/// their tracks. Without an image, the stored cover art of each album is
+/// embedded as it is.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetAlbumCoverArtRequest {
+    pub album_ids: Vec<i32>,
+    /// An image file on the device running the library, local clients only
+    pub image_path: Option<String>,
+    pub embed: bool,
+    /// The longest side of the embedded image, in pixels
+    pub max_dimension: u32,
+    pub format: EmbedImageFormat,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetAlbumCoverArtProgress {
+    pub progress: i32,
+    pub total: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetAlbumCoverArtResponse {
+    pub updated_count: i32,
+    pub embedded_count: i32,
+    pub unchanged_count: i32,
+    pub unsupported_file_ids: Vec<i32>,
+    pub failed_file_ids: Vec<i32>,
+    pub success: bool,
+    pub error: String,
+}
@@ -8,7 +8,7 @@ pub struct DirectoryTreeResponse {
     pub children: Vec<DirectoryTreeResponse>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchDirectoryTreeRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -0,0 +1,57 @@
+use rinf::{DartSignal, RustSignal, SignalPiece};
+use serde::{Deserialize, Serialize};
+
+/// Interleaved 16-bit PCM recorded by the client, like a microphone take.
+#[derive(Clone, Debug, Serialize, Deserialize, SignalPiece, Default)]
+pub struct PcmSnippet {
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+    pub channels: u32,
+}
+
+/// Identifies a file of the library from the excerpt in its middle, or a
+/// snippet recorded by the client. The previous identification still
+/// running is cancelled.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct IdentifyAudioRequest {
+    pub file_id: Option<i32>,
+    pub snippet: Option<PcmSnippet>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SignalPiece, Default)]
+pub struct IdentifiedTrack {
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub date: Option<String>,
+    /// Where the excerpt starts in the track, in seconds
+    pub offset: f64,
+}
+
+/// The matches, best first. No match is a successful response without
+/// matches.
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct IdentifyAudioResponse {
+    pub file_id: Option<i32>,
+    pub matches: Vec<IdentifiedTrack>,
+    pub success: bool,
+    pub error: String,
+}
+
+/// Writes an identification into the metadata of a file, and into its tags
+/// when asked to.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ApplyIdentificationRequest {
+    pub file_id: i32,
+    pub track: IdentifiedTrack,
+    pub write_tags: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ApplyIdentificationResponse {
+    pub file_id: i32,
+    pub applied_count: i32,
+    pub written: bool,
+    pub success: bool,
+    pub error: String,
+}
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use super::collection::CollectionType;
 use super::mix::MixQuery;
 
-#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
 pub struct ComplexQuery {
     pub id: String,
     pub title: String,
@@ -14,7 +14,7 @@ pub struct ComplexQuery {
     pub parameter: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ComplexQueryRequest {
     pub queries: Vec<ComplexQuery>,
 }
@@ -1,7 +1,7 @@
 use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct CloseLibraryRequest {
     pub path: String,
 }
@@ -11,7 +11,7 @@ pub struct CloseLibraryResponse {
     pub path: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ScanAudioLibraryRequest {
     pub path: String,
     pub force: bool,
@@ -37,13 +37,14 @@ pub struct ScanAudioLibraryResponse {
     pub progress: i32,
 }
 
-#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum ComputingDeviceRequest {
+    #[default]
     Cpu,
     Gpu,
 }
 
-#[derive(Debug, Serialize, Deserialize, DartSignal)]
+#[derive(Debug, Serialize, Deserialize, DartSignal, Default)]
 pub struct AnalyzeAudioLibraryRequest {
     pub path: String,
     pub computing_device: ComputingDeviceRequest,
@@ -63,7 +64,7 @@ pub struct AnalyzeAudioLibraryResponse {
     pub total: i32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct DeduplicateAudioLibraryRequest {
     pub path: String,
     pub similarity_threshold: f32,
@@ -82,22 +83,61 @@ pub struct DeduplicateAudioLibraryResponse {
     pub path: String,
 }
 
-#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum CancelTaskType {
+    #[default]
     AnalyzeAudioLibrary,
     ScanAudioLibrary,
     DeduplicateAudioLibrary,
+    IdentifyAudio,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+/// Cancels the task with `task_id`, or every running task of `type` when no
+/// id is given.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct CancelTaskRequest {
     pub path: String,
     pub r#type: CancelTaskType,
+    pub task_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, RustSignal)]
 pub struct CancelTaskResponse {
     pub path: String,
     pub r#type: CancelTaskType,
+    pub task_id: Option<String>,
     pub success: bool,
 }
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Debug)]
+pub struct TaskSummary {
+    pub id: String,
+    pub kind: CancelTaskType,
+    pub path: String,
+    pub progress: i64,
+    pub total: i64,
+    pub state: TaskState,
+    pub error: Option<String>,
+    /// How long the task ran, or has been running
+    pub elapsed_seconds: u64,
+    /// `None` while the task is running
+    pub finished_seconds_ago: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ListTasksRequest {}
+
+/// The running tasks and the ones finished in the last minutes, oldest
+/// first.
+#[derive(Serialize, Deserialize, RustSignal)]
+pub struct ListTasksResponse {
+    pub tasks: Vec<TaskSummary>,
+}
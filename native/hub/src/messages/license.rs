@@ -1,7 +1,7 @@
 use rinf::{DartSignal, RustSignal};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RegisterLicenseRequest {
     pub path: String,
 }
@@ -14,7 +14,7 @@ pub struct RegisterLicenseResponse {
     pub error: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ValidateLicenseRequest {
     pub license: Option<String>,
 }
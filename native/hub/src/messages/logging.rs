@@ -10,8 +10,22 @@ pub struct LogDetail {
     pub date: i64,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+/// Restricts the listed or exported log entries, every field is optional.
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
+pub struct LogQuery {
+    /// The least severe level, like `warning`
+    pub min_level: Option<String>,
+    /// A prefix of the domain, like `scrobbler::`
+    pub domain: Option<String>,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    /// Matched against the domain and the detail
+    pub text: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ListLogRequest {
+    pub query: LogQuery,
     pub cursor: i32,
     pub page_size: i32,
 }
@@ -21,7 +35,24 @@ pub struct ListLogResponse {
     pub result: Vec<LogDetail>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+/// Writes the matching log entries to `path` as NDJSON, one object per line,
+/// to be attached to bug reports.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ExportLogsRequest {
+    pub query: LogQuery,
+    pub path: String,
+    /// Replaces the file paths found in the entries with `<path>`
+    pub redact_paths: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ExportLogsResponse {
+    pub success: bool,
+    pub exported_count: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ClearLogRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -29,7 +60,7 @@ pub struct ClearLogResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RemoveLogRequest {
     pub id: i32,
 }
@@ -39,3 +70,43 @@ pub struct RemoveLogResponse {
     pub id: i32,
     pub success: bool,
 }
+
+/// Replaces the filter of the logs until the hub exits, in the syntax of
+/// `RUST_LOG`, like `info,hub=debug`.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetLogLevelRequest {
+    pub filter: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetLogLevelResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    /// The active filter, unchanged when the new one was rejected
+    pub filter: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct AuditLogDetail {
+    pub id: i32,
+    pub request_type: String,
+    pub fingerprint: String,
+    pub alias: String,
+    pub parameters: String,
+    pub success: bool,
+    pub date: i64,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ListAuditLogRequest {
+    pub fingerprint: Option<String>,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub cursor: i32,
+    pub page_size: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ListAuditLogResponse {
+    pub result: Vec<AuditLogDetail>,
+}
@@ -17,7 +17,7 @@ pub struct LyricContentLineSection {
     pub content: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetLyricByTrackIdRequest {
     pub item: Option<PlayingItemRequest>,
 }
@@ -5,11 +5,12 @@ use std::collections::HashMap;
 use super::album::Album;
 use super::artist::Artist;
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchMediaFilesRequest {
     pub cursor: i32,
     pub page_size: i32,
     pub bake_cover_arts: bool,
+    pub bypass_cache: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, SignalPiece, Debug)]
@@ -24,9 +25,10 @@ pub struct MediaFile {
     pub track_number: i32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchParsedMediaFileRequest {
     pub id: i32,
+    pub bypass_cache: bool,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -42,10 +44,11 @@ pub struct FetchMediaFilesResponse {
     pub cover_art_map: HashMap<i32, String>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchMediaFileByIdsRequest {
     pub ids: Vec<i32>,
     pub bake_cover_arts: bool,
+    pub bypass_cache: bool,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -61,7 +64,7 @@ pub struct MediaFileSummary {
     pub cover_art_id: i32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SearchMediaFileSummaryRequest {
     pub n: i32,
 }
@@ -71,10 +74,173 @@ pub struct SearchMediaFileSummaryResponse {
     pub result: Vec<MediaFileSummary>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetMediaFilesCountRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
 pub struct GetMediaFilesCountResponse {
     pub count: i32,
 }
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct FetchMediaFileIdsByHlcUuidsRequest {
+    pub hlc_uuids: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchMediaFileIdsByHlcUuidsResponse {
+    pub file_ids: Vec<i32>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct MetadataField {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct InferredMetadata {
+    pub file_id: i32,
+    pub path: String,
+    /// Whether the path matched the pattern, `fields` is empty otherwise
+    pub matched: bool,
+    pub fields: Vec<MetadataField>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct PreviewPathMetadataRequest {
+    pub file_ids: Vec<i32>,
+    /// e.g. `{artist}/{year} - {album}/{track} - {title}`
+    pub pattern: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct PreviewPathMetadataResponse {
+    pub results: Vec<InferredMetadata>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ApplyPathMetadataRequest {
+    pub file_ids: Vec<i32>,
+    pub pattern: String,
+    /// Also write the new values into the tags of the files
+    pub write_tags: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ApplyPathMetadataResponse {
+    pub results: Vec<InferredMetadata>,
+    /// The number of metadata values which changed
+    pub applied_count: i32,
+    pub written_count: i32,
+    /// Files of formats tags can't be written to
+    pub unsupported_file_ids: Vec<i32>,
+    /// Files tags could not be written to
+    pub failed_file_ids: Vec<i32>,
+    pub success: bool,
+    pub error: String,
+}
+
+/// A file to move, with paths relative to the library root.
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
+pub struct FileMove {
+    pub file_id: i32,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct PreviewOrganizeFilesRequest {
+    pub file_ids: Vec<i32>,
+    /// e.g. `{album_artist}/{year} - {album}/{disc}-{track:02} {title}.{ext}`
+    pub template: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct PreviewOrganizeFilesResponse {
+    pub moves: Vec<FileMove>,
+    /// Files already where the template puts them
+    pub unchanged_count: i32,
+    pub success: bool,
+    pub error: String,
+}
+
+/// Applies the moves of a preview. Files which changed since, or whose
+/// destination was taken, are skipped.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct OrganizeFilesRequest {
+    pub template: String,
+    pub moves: Vec<FileMove>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct OrganizeFilesResponse {
+    pub moved_count: i32,
+    pub skipped_count: i32,
+    pub failed_count: i32,
+    pub success: bool,
+    pub error: String,
+}
+
+/// The tag normalization rules to apply, each of them can be turned off.
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
+pub struct TagNormalizationRules {
+    pub trim_whitespace: bool,
+    pub collapse_spaces: bool,
+    /// Write `ft.`, `Feat` or `featuring` as `feat.`
+    pub unify_featuring: bool,
+    /// Title case track titles and albums
+    pub title_case: bool,
+    /// Words title casing writes as spelled here, the defaults if empty
+    pub title_case_exceptions: Vec<String>,
+    /// Sort artists starting with `The ` without it
+    pub sort_names: bool,
+    pub fix_mojibake: bool,
+}
+
+/// A field of a file before and after an edit.
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct MetadataFieldChange {
+    pub file_id: i32,
+    pub key: String,
+    pub old_value: Option<String>,
+    pub new_value: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct PreviewTagNormalizationRequest {
+    /// The files to normalize, the whole library if empty
+    pub file_ids: Vec<i32>,
+    pub rules: TagNormalizationRules,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct PreviewTagNormalizationResponse {
+    pub changes: Vec<MetadataFieldChange>,
+    pub success: bool,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ApplyTagNormalizationRequest {
+    /// The files to normalize, the whole library if empty
+    pub file_ids: Vec<i32>,
+    pub rules: TagNormalizationRules,
+    /// Also write the new values into the tags of the files
+    pub write_tags: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ApplyTagNormalizationResponse {
+    /// The number of metadata values which changed
+    pub applied_count: i32,
+    pub written_count: i32,
+    /// Files of formats tags can't be written to
+    pub unsupported_file_ids: Vec<i32>,
+    /// Files tags could not be written to
+    pub failed_file_ids: Vec<i32>,
+    pub success: bool,
+    pub error: String,
+}
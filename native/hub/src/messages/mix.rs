@@ -4,13 +4,13 @@ use std::collections::HashMap;
 
 use super::media_file::MediaFile;
 
-#[derive(Debug, Serialize, Deserialize, Clone, SignalPiece)]
+#[derive(Debug, Serialize, Deserialize, Clone, SignalPiece, Default)]
 pub struct MixQuery {
     pub operator: String,
     pub parameter: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct MixQueryRequest {
     pub queries: Vec<MixQuery>,
     pub cursor: i32,
@@ -33,7 +33,7 @@ pub struct Mix {
     pub mode: i32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchAllMixesRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -41,7 +41,7 @@ pub struct FetchAllMixesResponse {
     pub mixes: Vec<Mix>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct CreateMixRequest {
     pub name: String,
     pub group: String,
@@ -55,7 +55,7 @@ pub struct CreateMixResponse {
     pub mix: Mix,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct UpdateMixRequest {
     pub mix_id: i32,
     pub name: String,
@@ -70,7 +70,7 @@ pub struct UpdateMixResponse {
     pub mix: Mix,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RemoveMixRequest {
     pub mix_id: i32,
 }
@@ -81,7 +81,7 @@ pub struct RemoveMixResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct AddItemToMixRequest {
     pub mix_id: i32,
     pub operator: String,
@@ -93,7 +93,7 @@ pub struct AddItemToMixResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetMixByIdRequest {
     pub mix_id: i32,
 }
@@ -103,7 +103,7 @@ pub struct GetMixByIdResponse {
     pub mix: Mix,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchMixQueriesRequest {
     pub mix_id: i32,
 }
@@ -5,6 +5,7 @@ mod collection;
 mod connection;
 mod cover_art;
 mod directory;
+mod identify;
 mod library_home;
 mod library_manage;
 mod license;
@@ -12,6 +13,7 @@ mod logging;
 mod lyric;
 mod media_file;
 mod mix;
+mod musicbrainz;
 mod neighbors;
 mod playback;
 mod playlist;
@@ -28,6 +30,7 @@ pub use collection::*;
 pub use connection::*;
 pub use cover_art::*;
 pub use directory::*;
+pub use identify::*;
 pub use library_home::*;
 pub use library_manage::*;
 pub use license::*;
@@ -35,6 +38,7 @@ pub use logging::*;
 pub use lyric::*;
 pub use media_file::*;
 pub use mix::*;
+pub use musicbrainz::*;
 pub use neighbors::*;
 pub use playback::*;
 pub use playlist::*;
@@ -0,0 +1,77 @@
+use rinf::{DartSignal, RustSignal, SignalPiece};
+use serde::{Deserialize, Serialize};
+
+/// Fetches a MusicBrainz release, or the release of a release group closest
+/// to the album, and proposes which release track each track of the album
+/// is. Nothing changes until the mapping is applied.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct PreviewReleaseMappingRequest {
+    pub album_id: i32,
+    /// A release MBID, or a release group MBID
+    pub musicbrainz_id: String,
+    pub is_release_group: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SignalPiece)]
+pub struct MusicBrainzReleaseTrack {
+    pub disc_number: u32,
+    pub track_number: u32,
+    pub title: String,
+    /// The artist credit with its join phrases
+    pub artist: String,
+    /// In seconds
+    pub length: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SignalPiece, Default)]
+pub struct ReleaseTrackMapping {
+    pub file_id: i32,
+    /// The index of the release track, `None` leaves the file untouched
+    pub track_index: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SignalPiece)]
+pub struct ProposedTrackMapping {
+    pub mapping: ReleaseTrackMapping,
+    pub local_title: Option<String>,
+    /// How close the tracks are, from 0 to 1
+    pub score: f64,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct PreviewReleaseMappingResponse {
+    pub album_id: i32,
+    pub release_id: String,
+    pub title: String,
+    pub artist: String,
+    pub date: Option<String>,
+    pub tracks: Vec<MusicBrainzReleaseTrack>,
+    /// In the order of the album
+    pub mappings: Vec<ProposedTrackMapping>,
+    /// Tracks no release track is close to
+    pub unmapped_file_ids: Vec<i32>,
+    pub success: bool,
+    pub error: String,
+}
+
+/// Applies a mapping the client reviewed: titles, artists, disc and track
+/// numbers, the release year and the MusicBrainz IDs of the mapped tracks.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ApplyReleaseMappingRequest {
+    pub release_id: String,
+    pub mappings: Vec<ReleaseTrackMapping>,
+    pub write_tags: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ApplyReleaseMappingResponse {
+    pub release_id: String,
+    pub applied_count: i32,
+    pub unmapped_file_ids: Vec<i32>,
+    pub written_count: i32,
+    /// Files of formats tags can't be written to
+    pub unsupported_file_ids: Vec<i32>,
+    pub failed_file_ids: Vec<i32>,
+    pub success: bool,
+    pub error: String,
+}
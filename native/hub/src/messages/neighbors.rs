@@ -1,9 +1,10 @@
 use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
 pub enum ClientStatus {
     Approved,
+    #[default]
     Pending,
     Blocked,
 }
@@ -14,29 +15,52 @@ pub struct ClientSummary {
     pub fingerprint: String,
     pub device_model: String,
     pub status: ClientStatus,
+    pub throttled_messages: u64,
+    pub dropped_messages: u64,
+    pub connections: Vec<ClientConnectionInfo>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct BroadcastSubscription {
+    pub message_type: String,
+    pub message_count: u64,
+    pub bytes: u64,
 }
 
 #[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct ClientConnectionInfo {
+    pub remote_address: String,
+    pub protocol_version: u32,
+    pub protocol_features: Vec<String>,
+    pub connected_at: i64,
+    pub last_activity: i64,
+    pub request_count: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub broadcast_subscriptions: Vec<BroadcastSubscription>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
 pub struct TrustedServerCertificate {
     pub fingerprint: String,
     pub hosts: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct StartBroadcastRequest {
     pub duration_seconds: u32,
     pub alias: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct StopBroadcastRequest {}
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct StartListeningRequest {
     pub alias: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct StopListeningRequest {}
 
 #[derive(Deserialize, Serialize, SignalPiece, RustSignal)]
@@ -47,9 +71,12 @@ pub struct DiscoveredDeviceMessage {
     pub fingerprint: String,
     pub last_seen_unix_epoch: i64,
     pub ips: Vec<String>,
+    pub api_port: u32,
+    pub ws_port: u32,
+    pub path_prefix: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetDiscoveredDeviceRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -57,19 +84,31 @@ pub struct GetDiscoveredDeviceResponse {
     pub devices: Vec<DiscoveredDeviceMessage>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct StartServerRequest {
     pub interface: String,
     pub alias: String,
+    /// Additional socket addresses, IP addresses or interface names to listen on.
+    pub bind_addresses: Vec<String>,
+    /// Refuse to start if any of the addresses cannot be bound.
+    pub strict: bool,
+    /// Port used for addresses given without one, 0 for the default port.
+    pub port: u32,
+    /// Separate port for WebSocket connections, 0 to share `port`.
+    pub ws_port: u32,
+    /// Path every route is served below, e.g. `/rune` behind a reverse proxy.
+    pub path_prefix: String,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
 pub struct StartServerResponse {
     pub success: bool,
     pub error: String,
+    pub endpoints: Vec<String>,
+    pub bind_errors: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct StopServerRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -78,7 +117,14 @@ pub struct StopServerResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+/// Sent to every connected client when the server starts shutting down.
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ServerShuttingDown {
+    /// Longest time, in seconds, before the server closes the connections.
+    pub countdown_secs: u32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ListClientsRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -93,7 +139,7 @@ pub struct IncommingClientPermissionNotification {
     pub user: ClientSummary,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetSslCertificateFingerprintRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -101,7 +147,7 @@ pub struct GetSslCertificateFingerprintResponse {
     pub fingerprint: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RemoveTrustedClientRequest {
     pub fingerprint: String,
 }
@@ -112,7 +158,7 @@ pub struct RemoveTrustedClientResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct UpdateClientStatusRequest {
     pub fingerprint: String,
     pub status: ClientStatus,
@@ -124,7 +170,7 @@ pub struct UpdateClientStatusResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct EditHostsRequest {
     pub fingerprint: String,
     pub hosts: Vec<String>,
@@ -136,7 +182,7 @@ pub struct EditHostsResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct AddTrustedServerRequest {
     pub certificate: Option<TrustedServerCertificate>,
 }
@@ -147,7 +193,7 @@ pub struct AddTrustedServerResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RemoveTrustedServerRequest {
     pub fingerprint: String,
 }
@@ -158,7 +204,7 @@ pub struct RemoveTrustedServerResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ServerAvailabilityTestRequest {
     pub url: String,
 }
@@ -169,7 +215,7 @@ pub struct ServerAvailabilityTestResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RegisterDeviceOnServerRequest {
     pub alias: String,
     pub hosts: Vec<String>,
@@ -181,7 +227,7 @@ pub struct RegisterDeviceOnServerResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct CheckDeviceOnServerRequest {
     pub alias: String,
     pub hosts: Vec<String>,
@@ -199,7 +245,7 @@ pub struct TrustListUpdated {
     pub certificates: Vec<TrustedServerCertificate>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ConnectRequest {
     pub hosts: Vec<String>,
 }
@@ -211,7 +257,7 @@ pub struct ConnectResponse {
     pub connected_host: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchServerCertificateRequest {
     pub url: String,
 }
@@ -223,7 +269,7 @@ pub struct FetchServerCertificateResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchRemoteFileRequest {
     pub url: String,
 }
@@ -234,3 +280,87 @@ pub struct FetchRemoteFileResponse {
     pub local_path: String,
     pub error: String,
 }
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchRemoteFileProgress {
+    pub url: String,
+    pub file_name: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Changes how many remote files are downloaded in parallel, at least one.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetMaxConcurrentDownloadsRequest {
+    pub limit: u32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct AuthenticateAdminRequest {
+    pub password: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct AuthenticateAdminResponse {
+    pub success: bool,
+    pub token: String,
+    /// Expiration time of the token (UNIX timestamp)
+    pub expires_at: u64,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct RefreshAdminTokenRequest {
+    /// Token to refresh, the one held by the connection is used when empty.
+    pub token: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RefreshAdminTokenResponse {
+    pub success: bool,
+    pub token: String,
+    /// Expiration time of the token (UNIX timestamp)
+    pub expires_at: u64,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ExportTrustBundleRequest {
+    pub path: String,
+    pub password: String,
+    /// Also export the client certificate of this device.
+    pub include_identity: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ExportTrustBundleResponse {
+    pub success: bool,
+    pub exported_count: u32,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ImportTrustBundleRequest {
+    pub path: String,
+    pub password: String,
+    /// Replace the client certificate of this device with the exported one.
+    pub import_identity: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct TrustBundleConflict {
+    pub host: String,
+    pub existing_fingerprint: String,
+    pub imported_fingerprint: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ImportTrustBundleResponse {
+    pub success: bool,
+    pub imported_fingerprints: Vec<String>,
+    pub conflicts: Vec<TrustBundleConflict>,
+    pub revoked_fingerprints: Vec<String>,
+    pub expired_fingerprints: Vec<String>,
+    pub identity_imported: bool,
+    pub error: String,
+}
@@ -18,46 +18,49 @@ pub struct PlaybackStatus {
     pub ready: bool,
     pub cover_art_path: Option<String>,
     pub lib_path: String,
+    /// The id of the chosen audio device while it can't be opened and the
+    /// default one plays instead
+    pub unavailable_audio_device: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct LoadRequest {
     pub index: i32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct PlayRequest {}
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct PauseRequest {}
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct NextRequest {}
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct PreviousRequest {}
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SetPlaybackModeRequest {
     pub mode: u32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SwitchRequest {
     pub index: u32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SeekRequest {
     pub position_seconds: f64,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RemoveRequest {
     pub index: u32,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct MovePlaylistItemRequest {
     pub old_index: u32,
     pub new_index: u32,
@@ -77,22 +80,75 @@ pub struct PlaylistUpdate {
     pub items: Vec<PlaylistItem>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct FetchPlaybackQueueRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchPlaybackQueueResponse {
+    pub items: Vec<PlaylistItem>,
+    /// Position of the current item in `items`.
+    pub index: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SetRealtimeFFTEnabledRequest {
     pub enabled: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SetAdaptiveSwitchingEnabledRequest {
     pub enabled: bool,
 }
 
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetGaplessEnabledRequest {
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetCrossfadeRequest {
+    /// The overlap between two tracks, 0 turns crossfading off
+    pub seconds: f32,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetLoudnessNormalizationRequest {
+    pub enabled: bool,
+    /// In LUFS
+    pub target_loudness: f32,
+    /// Normalizes albums as a whole, keeping the differences between their
+    /// tracks
+    pub album_gain: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Debug)]
+pub struct AudioDevice {
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ListAudioDevicesRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ListAudioDevicesResponse {
+    pub devices: Vec<AudioDevice>,
+}
+
+/// Moves playback to another audio device, resuming the current track where
+/// it was, `None` picking the default device.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetAudioDeviceRequest {
+    pub id: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, RustSignal)]
 pub struct RealtimeFFT {
     pub value: Vec<f32>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct VolumeRequest {
     pub volume: f32,
 }
@@ -102,30 +158,48 @@ pub struct VolumeResponse {
     pub volume: f32,
 }
 
-#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq)]
+/// Changes the volume by `delta`, a change of 0 reading the current volume.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct RelativeVolumeRequest {
+    pub delta: f32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RelativeVolumeResponse {
+    pub volume: f32,
+}
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum PlaylistOperateMode {
+    #[default]
     AppendToEnd,
     PlayNext,
     Replace,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece, Default)]
 pub struct InLibraryPlayingItem {
     pub file_id: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece)]
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece, Default)]
 pub struct IndependentFilePlayingItem {
     pub raw_path: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, SignalPiece)]
+#[derive(Debug, Clone, Serialize, Deserialize, SignalPiece, Default)]
+pub struct OnlinePlayingItem {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, SignalPiece, Default)]
 pub struct PlayingItemRequest {
     pub in_library: Option<InLibraryPlayingItem>,
     pub independent_file: Option<IndependentFilePlayingItem>,
+    pub online: Option<OnlinePlayingItem>,
 }
 
-#[derive(Debug, Serialize, Deserialize, DartSignal)]
+#[derive(Debug, Serialize, Deserialize, DartSignal, Default)]
 pub struct OperatePlaybackWithMixQueryRequest {
     pub queries: Vec<MixQuery>,
     pub playback_mode: u32,
@@ -140,3 +214,30 @@ pub struct OperatePlaybackWithMixQueryRequest {
 pub struct OperatePlaybackWithMixQueryResponse {
     pub playing_items: Vec<PlayingItemRequest>,
 }
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct StartRemoteOutputRequest {
+    /// Alias of the local certificate used to authenticate on the target.
+    pub alias: String,
+    /// Hosts of the trusted device that should output the audio.
+    pub hosts: Vec<String>,
+    /// Address the target uses to stream files that are not in its library
+    /// from this device, may include a port and a path prefix.
+    pub stream_host: String,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StartRemoteOutputResponse {
+    pub success: bool,
+    pub error: String,
+    pub connected_host: String,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct StopRemoteOutputRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct StopRemoteOutputResponse {
+    pub success: bool,
+    pub error: String,
+}
@@ -14,7 +14,7 @@ pub struct PlaylistsGroup {
     pub playlists: Vec<Playlist>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct FetchAllPlaylistsRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -22,7 +22,7 @@ pub struct FetchAllPlaylistsResponse {
     pub playlists: Vec<Playlist>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct CreatePlaylistRequest {
     pub name: String,
     pub group: String,
@@ -33,7 +33,7 @@ pub struct CreatePlaylistResponse {
     pub playlist: Playlist,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct UpdatePlaylistRequest {
     pub playlist_id: i32,
     pub name: String,
@@ -45,7 +45,7 @@ pub struct UpdatePlaylistResponse {
     pub playlist: Playlist,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RemovePlaylistRequest {
     pub playlist_id: i32,
 }
@@ -56,7 +56,7 @@ pub struct RemovePlaylistResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct AddItemToPlaylistRequest {
     pub playlist_id: i32,
     pub media_file_id: i32,
@@ -68,7 +68,7 @@ pub struct AddItemToPlaylistResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct ReorderPlaylistItemPositionRequest {
     pub playlist_id: i32,
     pub media_file_id: i32,
@@ -80,7 +80,7 @@ pub struct ReorderPlaylistItemPositionResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetPlaylistByIdRequest {
     pub playlist_id: i32,
 }
@@ -96,7 +96,7 @@ pub struct PlaylistSummary {
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct CreateM3u8PlaylistRequest {
     pub name: String,
     pub group: String,
@@ -112,7 +112,7 @@ pub struct CreateM3u8PlaylistResponse {
     pub error: String,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct RemoveItemFromPlaylistRequest {
     pub playlist_id: i32,
     pub media_file_id: i32,
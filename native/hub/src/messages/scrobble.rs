@@ -1,16 +1,23 @@
 use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
 pub struct LoginRequestItem {
     pub service_id: String,
     pub username: String,
     pub password: String,
     pub api_key: Option<String>,
     pub api_secret: Option<String>,
+    /// Unset means enabled, for credentials stored before the flags
+    pub scrobbling_enabled: Option<bool>,
+    pub now_playing_enabled: Option<bool>,
+    /// The API of a Libre.fm compatible server, Libre.fm itself when unset
+    pub base_url: Option<String>,
+    /// Allows a server URL using plain HTTP
+    pub allow_insecure: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct AuthenticateSingleServiceRequest {
     pub request: Option<LoginRequestItem>,
 }
@@ -21,7 +28,7 @@ pub struct AuthenticateSingleServiceResponse {
     pub error: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct AuthenticateMultipleServiceRequest {
     pub requests: Vec<LoginRequestItem>,
 }
@@ -31,6 +38,18 @@ pub struct ScrobbleServiceStatus {
     pub service_id: String,
     pub is_available: bool,
     pub error: Option<String>,
+    /// Scrobbles waiting for the service to be reachable
+    pub queued: u64,
+    pub scrobbling_enabled: bool,
+    pub now_playing_enabled: bool,
+    /// The last failure of a request, until one succeeds
+    pub error_detail: Option<String>,
+    /// When the requests started failing (UNIX timestamp)
+    pub degraded_since: Option<i64>,
+    /// The service refused the session, logging in again is required
+    pub needs_reauthentication: bool,
+    /// The API of the server reached, when not the default one
+    pub base_url: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -38,7 +57,98 @@ pub struct ScrobbleServiceStatusUpdated {
     pub services: Vec<ScrobbleServiceStatus>,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct LogoutSingleServiceRequest {
     pub service_id: String,
 }
+
+/// Stops or resumes the submissions to a service without logging out.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetScrobbleServiceFlagsRequest {
+    pub service_id: String,
+    pub scrobbling_enabled: bool,
+    pub now_playing_enabled: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
+pub struct ScrobbleRulesItem {
+    pub min_played_fraction: f64,
+    pub min_played_seconds: u32,
+    pub min_track_seconds: u32,
+    pub scrobble_repeats: bool,
+    /// Days the local scrobble history is kept, forever when unset
+    pub history_retention_days: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct FetchScrobbleRulesRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchScrobbleRulesResponse {
+    pub rules: ScrobbleRulesItem,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct UpdateScrobbleRulesRequest {
+    pub rules: ScrobbleRulesItem,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct UpdateScrobbleRulesResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct ScrobbleHistoryItem {
+    pub id: i32,
+    /// The file in the library, if the track is part of it
+    pub media_file_id: Option<i32>,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    /// When the track was listened to (UNIX timestamp)
+    pub timestamp: i64,
+    /// The services which accepted the scrobble, none when no service is
+    /// logged in to
+    pub accepted_by: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ListScrobbleHistoryRequest {
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub cursor: i32,
+    pub page_size: i32,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ListScrobbleHistoryResponse {
+    pub result: Vec<ScrobbleHistoryItem>,
+}
+
+/// Likes the files of the library matching the tracks loved on Last.fm.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ImportLovedTracksRequest {}
+
+/// A loved track which could not be matched to a single file.
+#[derive(Clone, Serialize, Deserialize, SignalPiece)]
+pub struct LovedTrackMismatch {
+    pub artist: String,
+    pub title: String,
+    /// `not_in_library`, `duration_mismatch` or `ambiguous`
+    pub reason: String,
+    /// The files with the same artist and title
+    pub candidates: Vec<i32>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct ImportLovedTracksResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    /// Tracks loved on Last.fm
+    pub loved: u32,
+    /// Files marked as liked
+    pub liked: u32,
+    pub mismatches: Vec<LovedTrackMismatch>,
+}
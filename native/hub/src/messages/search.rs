@@ -1,11 +1,21 @@
-use rinf::{DartSignal, RustSignal};
+use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SearchForRequest {
     pub query_str: String,
     pub fields: Vec<String>,
     pub n: i32,
+    /// Also search the lyrics, if they are indexed in the library
+    pub include_lyrics: bool,
+}
+
+#[derive(Deserialize, Serialize, SignalPiece, Clone, Debug)]
+pub struct LyricSearchHit {
+    pub track_id: i32,
+    /// The lyrics around the match, the matched words wrapped in `<b>` and
+    /// `</b>`
+    pub snippet: String,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -14,4 +24,42 @@ pub struct SearchForResponse {
     pub albums: Vec<i32>,
     pub playlists: Vec<i32>,
     pub tracks: Vec<i32>,
+    /// Tracks whose lyrics match, empty unless requested
+    pub lyrics: Vec<LyricSearchHit>,
+}
+
+/// Turns the indexing of lyrics of the library on or off. The change takes
+/// effect for indexed lyrics once `RebuildLyricsIndexRequest` runs.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetLyricsIndexingRequest {
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetLyricsIndexingResponse {
+    pub enabled: bool,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct GetLyricsIndexingRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct GetLyricsIndexingResponse {
+    pub enabled: bool,
+}
+
+/// Indexes the lyrics of the library again following its setting, removing
+/// them from the index when their indexing is off.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct RebuildLyricsIndexRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct RebuildLyricsIndexResponse {
+    pub enabled: bool,
+    /// The number of tracks whose lyrics were indexed
+    pub indexed: i32,
+    pub success: bool,
+    pub error: Option<String>,
 }
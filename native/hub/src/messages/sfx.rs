@@ -1,7 +1,7 @@
 use rinf::DartSignal;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SfxPlayRequest {
     pub path: String,
 }
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use super::playback::PlayingItemRequest;
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SetLikedRequest {
     pub item: Option<PlayingItemRequest>,
     pub liked: bool,
@@ -16,7 +16,7 @@ pub struct SetLikedResponse {
     pub success: bool,
 }
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct GetLikedRequest {
     pub item: Option<PlayingItemRequest>,
 }
@@ -26,3 +26,17 @@ pub struct GetLikedResponse {
     pub item: PlayingItemRequest,
     pub liked: bool,
 }
+
+/// Rates a media file from 1 to 5, `None` clearing the rating.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct SetRatingRequest {
+    pub item: Option<PlayingItemRequest>,
+    pub rating: Option<i32>,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct SetRatingResponse {
+    pub item: PlayingItemRequest,
+    pub rating: Option<i32>,
+    pub success: bool,
+}
@@ -1,7 +1,7 @@
-use rinf::{DartSignal, RustSignal};
+use rinf::{DartSignal, RustSignal, SignalPiece};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, DartSignal)]
+#[derive(Serialize, Deserialize, DartSignal, Default)]
 pub struct SystemInfoRequest {}
 
 #[derive(Deserialize, Serialize, RustSignal)]
@@ -10,14 +10,152 @@ pub struct SystemInfoResponse {
     pub build_sha: String,
     pub build_commit_timestamp: String,
     pub build_rustc_semver: String,
+    /// The version of the hub crate
+    pub hub_version: String,
     pub system_name: String,
     pub system_kernel_version: String,
     pub system_os_version: String,
     pub system_host_name: String,
     pub users: Vec<String>,
+    /// `None` when the space of the volume cannot be read.
+    pub library_space: Option<VolumeSpace>,
+    pub config_space: Option<VolumeSpace>,
+    /// The filter of the logs, `None` when it cannot be changed
+    pub log_filter: Option<String>,
+    /// Where the log files are written, `None` unless logging to files
+    pub log_directory: Option<String>,
+    /// The size of the log files, rotated ones included
+    pub log_directory_bytes: u64,
+    /// `None` when the applied migrations cannot be read
+    pub schema: Option<SchemaInfo>,
+    /// The operating system and the CPU architecture the hub was built for,
+    /// like `linux` and `x86_64`
+    pub os: String,
+    pub arch: String,
+    /// The bundle identifier of the app, empty outside of macOS
+    pub bundle_id: String,
+    /// The audio API playback goes through, like WASAPI or ALSA
+    pub audio_host: String,
+    /// The device playback goes to, empty before anything was played
+    pub audio_device: String,
+    /// The GPU adapter the analysis runs on, empty without any
+    pub gpu_adapter: String,
+    pub gpu_driver: String,
+    pub gpu_backend: String,
+    /// The sizes of the database files of the library, 0 when unknown
+    pub database_bytes: u64,
+    pub database_wal_bytes: u64,
+    pub recommendation_database_bytes: u64,
+    pub node_id: String,
+    /// `local` when the app hosts the library itself, `server` when a
+    /// server answers
+    pub running_mode: String,
+    pub uptime_seconds: u64,
+}
+
+/// The migrations applied to the main database of a library.
+#[derive(Clone, Deserialize, Serialize, SignalPiece, Debug, Default)]
+pub struct SchemaInfo {
+    /// The newest applied migration, `None` for a new library
+    pub version: Option<String>,
+    /// The newest migration of this build
+    pub latest_version: String,
+    /// Migrations applied when the library is opened, after which older
+    /// versions of Rune cannot open it
+    pub pending_migrations: u32,
+    /// Applied migrations this build doesn't know, the library was opened
+    /// by a newer version and cannot be opened by this one
+    pub unknown_migrations: Vec<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize, SignalPiece, Debug)]
+pub struct VolumeSpace {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
 }
 
 #[derive(Deserialize, Serialize, RustSignal)]
 pub struct CrashResponse {
     pub detail: String,
 }
+
+/// Sent instead of the response of a request the session may not send.
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct PermissionDeniedResponse {
+    pub request: String,
+    /// The scope the request requires, as named in the request manifest
+    pub scope: String,
+    pub detail: String,
+}
+
+/// Asks which protocol the server speaks, sent by clients once connected.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct ServerCapabilitiesRequest {}
+
+#[derive(Clone, Deserialize, Serialize, RustSignal)]
+pub struct ServerCapabilitiesResponse {
+    pub protocol_version: u32,
+    /// The hash of the protocol, in hexadecimal
+    pub protocol_hash: String,
+    /// Every request the server answers, the UI hides the features needing
+    /// the other ones
+    pub requests: Vec<String>,
+}
+
+/// Sent instead of the response of a request the server doesn't answer.
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct UnsupportedRequestResponse {
+    pub request: String,
+    /// The protocol version of the peer which doesn't answer the request
+    pub protocol_version: u32,
+    pub detail: String,
+}
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ProxySourceItem {
+    /// The `HTTPS_PROXY` and `ALL_PROXY` environment variables
+    #[default]
+    Environment,
+    Disabled,
+    Manual,
+}
+
+#[derive(Serialize, Deserialize, SignalPiece, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ProxyProtocolItem {
+    /// Tunnels through `CONNECT` requests
+    #[default]
+    Http,
+    Socks5,
+}
+
+#[derive(Clone, Serialize, Deserialize, SignalPiece, Default)]
+pub struct ProxySettingsItem {
+    pub source: ProxySourceItem,
+    pub protocol: ProxyProtocolItem,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hosts reached directly, devices on the local network always are
+    pub no_proxy: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct FetchProxySettingsRequest {}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct FetchProxySettingsResponse {
+    pub settings: ProxySettingsItem,
+}
+
+/// Saves the proxy settings, which apply to the requests made from then on.
+#[derive(Serialize, Deserialize, DartSignal, Default)]
+pub struct UpdateProxySettingsRequest {
+    pub settings: ProxySettingsItem,
+}
+
+#[derive(Deserialize, Serialize, RustSignal)]
+pub struct UpdateProxySettingsResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
@@ -0,0 +1,542 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::SaltString};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use log::{info, warn};
+use rand::{RngCore, thread_rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    Session,
+    server::scope::{PermissionDenied, Scope},
+    utils::GlobalParams,
+};
+
+/// How long an admin token stays valid after being issued or refreshed.
+pub const ADMIN_TOKEN_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Failed attempts allowed before a client gets locked out.
+const MAX_FAILED_ATTEMPTS: u32 = 5;
+/// Lockout after the first excess failure, doubled for every further one.
+const LOCKOUT_BASE: Duration = Duration::from_secs(30);
+const LOCKOUT_MAX: Duration = Duration::from_secs(15 * 60);
+/// Failures of a client are forgotten this long after its last one. Never
+/// shorter than [`LOCKOUT_MAX`], so no running lockout is dropped.
+const FAILURE_MEMORY: Duration = Duration::from_secs(60 * 60);
+/// Clients whose failures are remembered at once, the oldest are dropped
+/// first.
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+const PASSWORD_FILE: &str = "root_password.hash";
+
+/// Hashes a password with argon2 using a random salt.
+pub fn hash_password(password: &str) -> Result<String> {
+    let mut salt = [0u8; 16];
+    thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).map_err(|e| anyhow!("Failed to encode salt: {e}"))?;
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|x| x.to_string())
+        .map_err(|e| anyhow!("Failed to hash password: {e}"))
+}
+
+/// Verifies a password against an argon2 hash, or a bcrypt hash written by
+/// older versions.
+pub fn verify_password(password: &str, stored_hash: &str) -> Result<bool> {
+    let stored_hash = stored_hash.trim();
+
+    if is_legacy_hash(stored_hash) {
+        return bcrypt::verify(password, stored_hash).context("Failed to verify password");
+    }
+
+    let parsed =
+        PasswordHash::new(stored_hash).map_err(|e| anyhow!("Invalid password hash: {e}"))?;
+
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok())
+}
+
+fn is_legacy_hash(stored_hash: &str) -> bool {
+    stored_hash.starts_with("$2")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AdminClaims {
+    /// Fingerprint of the client the token was issued to.
+    sub: String,
+    /// Digest of the password hash the token was issued under, changing the
+    /// password invalidates every token.
+    pwd: String,
+    /// Expiration time (UNIX timestamp)
+    exp: u64,
+    /// Issued at time (UNIX timestamp)
+    iat: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminToken {
+    pub token: String,
+    /// Expiration time (UNIX timestamp)
+    pub expires_at: u64,
+}
+
+#[derive(Debug)]
+struct FailedAttempts {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Issues and validates the short-lived tokens that grant a remote client
+/// admin access after it proved knowledge of the root password.
+pub struct AdminAuth {
+    password_path: PathBuf,
+    secret: Vec<u8>,
+    failures: Mutex<HashMap<String, FailedAttempts>>,
+}
+
+impl std::fmt::Debug for AdminAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminAuth")
+            .field("password_path", &self.password_path)
+            .finish()
+    }
+}
+
+impl AdminAuth {
+    /// Admin tokens are signed with a key derived from `jwt_secret`, so they
+    /// are never accepted where a panel token is expected and vice versa.
+    pub fn new(config_path: &Path, jwt_secret: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"rune-admin-token");
+        hasher.update(jwt_secret);
+
+        Self {
+            password_path: config_path.join(PASSWORD_FILE),
+            secret: hasher.finalize().to_vec(),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks the root password on behalf of `client`, which is locked out
+    /// for a while after too many failed attempts.
+    ///
+    /// Lockouts are keyed by `client` alone. The panel keys them by address,
+    /// so behind a reverse proxy every client shares one, and failures from
+    /// any of them lock out all the others.
+    ///
+    /// Hashes written by older versions are upgraded to argon2 on success.
+    pub async fn check_password(&self, client: &str, password: &str) -> Result<()> {
+        // Counted as a failure until the password is verified, so parallel
+        // guesses can't all get past the lockout
+        self.reserve_attempt(client)?;
+
+        if !self.password_path.exists() {
+            bail!("Root password not initialized");
+        }
+
+        let stored_hash = tokio::fs::read_to_string(&self.password_path)
+            .await
+            .context("Failed to read password hash")?;
+
+        let verified = {
+            let password = password.to_owned();
+            let stored_hash = stored_hash.clone();
+            tokio::task::spawn_blocking(move || verify_password(&password, &stored_hash))
+                .await
+                .context("Failed to verify password")??
+        };
+        if !verified {
+            bail!("Invalid password");
+        }
+
+        self.failures.lock().unwrap().remove(client);
+
+        if is_legacy_hash(stored_hash.trim()) {
+            info!("Upgrading the root password hash to argon2");
+            let password = password.to_owned();
+            let hash = tokio::task::spawn_blocking(move || hash_password(&password))
+                .await
+                .context("Failed to hash password")??;
+            tokio::fs::write(&self.password_path, hash)
+                .await
+                .context("Failed to write password hash")?;
+        }
+
+        Ok(())
+    }
+
+    /// Exchanges the root password for a token bound to `fingerprint`.
+    pub async fn authenticate(&self, fingerprint: &str, password: &str) -> Result<AdminToken> {
+        self.check_password(fingerprint, password).await?;
+        info!("Issued an admin token to {fingerprint}");
+        self.issue(fingerprint).await
+    }
+
+    /// Exchanges a valid token for a new one with a fresh TTL.
+    pub async fn refresh(&self, fingerprint: &str, token: &str) -> Result<AdminToken> {
+        self.validate(fingerprint, token).await?;
+        self.issue(fingerprint).await
+    }
+
+    /// Checks that `token` is unexpired, was issued to `fingerprint` and
+    /// predates no password change.
+    pub async fn validate(&self, fingerprint: &str, token: &str) -> Result<()> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.leeway = 0;
+
+        let claims =
+            decode::<AdminClaims>(token, &DecodingKey::from_secret(&self.secret), &validation)
+                .map_err(|e| anyhow!("Invalid admin token: {e}"))?
+                .claims;
+
+        if claims.sub != fingerprint {
+            warn!(
+                "Rejected an admin token issued to {} from {fingerprint}",
+                claims.sub
+            );
+            bail!("Admin token was issued to another device");
+        }
+
+        if claims.pwd != self.password_digest().await? {
+            bail!("Admin token was revoked by a password change");
+        }
+
+        Ok(())
+    }
+
+    async fn issue(&self, fingerprint: &str) -> Result<AdminToken> {
+        let iat = unix_now();
+        let claims = AdminClaims {
+            sub: fingerprint.to_owned(),
+            pwd: self.password_digest().await?,
+            exp: iat + ADMIN_TOKEN_TTL.as_secs(),
+            iat,
+        };
+
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(&self.secret),
+        )?;
+
+        Ok(AdminToken {
+            token,
+            expires_at: claims.exp,
+        })
+    }
+
+    /// Short digest of the stored password hash, read on every call so a
+    /// password changed from the command line takes effect immediately.
+    async fn password_digest(&self) -> Result<String> {
+        let stored_hash = tokio::fs::read_to_string(&self.password_path)
+            .await
+            .context("Failed to read password hash")?;
+
+        let digest = Sha256::digest(stored_hash.trim().as_bytes());
+        Ok(digest[..8].iter().map(|x| format!("{x:02x}")).collect())
+    }
+
+    /// Counts an attempt of `client` as failed unless it is locked out.
+    fn reserve_attempt(&self, client: &str) -> Result<()> {
+        let mut failures = self.failures.lock().unwrap();
+        check_lockout(&failures, client)?;
+        record_failure(&mut failures, client);
+        Ok(())
+    }
+}
+
+fn check_lockout(failures: &HashMap<String, FailedAttempts>, client: &str) -> Result<()> {
+    if let Some(locked_until) = failures.get(client).and_then(|x| x.locked_until) {
+        let now = Instant::now();
+        if locked_until > now {
+            bail!(
+                "Too many failed attempts, retry in {}s",
+                (locked_until - now).as_secs() + 1
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn record_failure(failures: &mut HashMap<String, FailedAttempts>, client: &str) {
+    let now = Instant::now();
+
+    failures.retain(|_, x| now.duration_since(x.last_failure) < FAILURE_MEMORY);
+    if failures.len() >= MAX_TRACKED_CLIENTS && !failures.contains_key(client) {
+        let oldest = failures
+            .iter()
+            .min_by_key(|(_, x)| x.last_failure)
+            .map(|(client, _)| client.clone());
+        if let Some(oldest) = oldest {
+            failures.remove(&oldest);
+        }
+    }
+
+    let entry = failures
+        .entry(client.to_owned())
+        .or_insert_with(|| FailedAttempts {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+    entry.count += 1;
+    entry.last_failure = now;
+
+    if entry.count >= MAX_FAILED_ATTEMPTS {
+        let exponent = (entry.count - MAX_FAILED_ATTEMPTS).min(16);
+        let lockout = LOCKOUT_BASE.saturating_mul(1 << exponent).min(LOCKOUT_MAX);
+        entry.locked_until = Some(now + lockout);
+        warn!(
+            "Locked out {client} for {}s after {} failed password attempts",
+            lockout.as_secs(),
+            entry.count
+        );
+    }
+}
+
+/// Rejects the requests of remote sessions lacking the scope of `request`.
+/// Approved devices hold every scope but admin, which takes a valid admin
+/// token. Requests from the local user are never restricted.
+pub async fn authorize_request(
+    global_params: &GlobalParams,
+    request: &str,
+    scope: Scope,
+    session: Option<&Session>,
+) -> Result<(), PermissionDenied> {
+    let Some(session) = session else {
+        return Ok(());
+    };
+    let deny = |reason: &str| PermissionDenied {
+        request: request.to_owned(),
+        scope,
+        reason: reason.to_owned(),
+    };
+
+    match scope {
+        Scope::Read | Scope::Playback | Scope::LibraryWrite => Ok(()),
+        Scope::LocalOnly => Err(deny("only the local user may send it")),
+        Scope::Admin => {
+            let server_manager = global_params
+                .server_manager
+                .get()
+                .ok_or_else(|| deny("the server is not initialized"))?;
+
+            let token = session
+                .admin_token
+                .lock()
+                .unwrap()
+                .clone()
+                .ok_or_else(|| deny("admin authentication is required"))?;
+
+            server_manager
+                .admin_auth
+                .validate(&session.fingerprint, &token)
+                .await
+                .map_err(|e| deny(&e.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FINGERPRINT: &str = "AA:BB:CC";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rune-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn admin_auth(name: &str, password: &str) -> (AdminAuth, PathBuf) {
+        let dir = temp_dir(name);
+        std::fs::write(dir.join(PASSWORD_FILE), hash_password(password).unwrap()).unwrap();
+        (AdminAuth::new(&dir, b"secret"), dir)
+    }
+
+    fn fail(auth: &AdminAuth, client: &str) {
+        record_failure(&mut auth.failures.lock().unwrap(), client);
+    }
+
+    fn is_locked(auth: &AdminAuth, client: &str) -> bool {
+        check_lockout(&auth.failures.lock().unwrap(), client).is_err()
+    }
+
+    fn locked_for(auth: &AdminAuth, client: &str) -> Option<Duration> {
+        auth.failures.lock().unwrap()[client]
+            .locked_until
+            .map(|x| x.saturating_duration_since(Instant::now()))
+    }
+
+    #[test]
+    fn passwords_are_hashed_with_argon2() {
+        let hash = hash_password("hunter2").unwrap();
+
+        assert!(hash.starts_with("$argon2"));
+        assert_ne!(hash, hash_password("hunter2").unwrap());
+        assert!(verify_password("hunter2", &hash).unwrap());
+        assert!(!verify_password("hunter3", &hash).unwrap());
+    }
+
+    #[tokio::test]
+    async fn legacy_hashes_are_upgraded_on_success() {
+        let dir = temp_dir("admin-upgrade");
+        let path = dir.join(PASSWORD_FILE);
+        std::fs::write(&path, bcrypt::hash("hunter2", 4).unwrap()).unwrap();
+        let auth = AdminAuth::new(&dir, b"secret");
+
+        assert!(auth.check_password(FINGERPRINT, "hunter3").await.is_err());
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("$2"));
+
+        auth.check_password(FINGERPRINT, "hunter2").await.unwrap();
+        let upgraded = std::fs::read_to_string(&path).unwrap();
+        assert!(upgraded.starts_with("$argon2"));
+        assert!(verify_password("hunter2", &upgraded).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn tokens_are_bound_to_the_fingerprint() {
+        let (auth, dir) = admin_auth("admin-binding", "hunter2");
+        let token = auth.authenticate(FINGERPRINT, "hunter2").await.unwrap();
+
+        auth.validate(FINGERPRINT, &token.token).await.unwrap();
+        assert!(auth.validate("DD:EE:FF", &token.token).await.is_err());
+        assert!(auth.refresh("DD:EE:FF", &token.token).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn password_changes_revoke_tokens() {
+        let (auth, dir) = admin_auth("admin-revocation", "hunter2");
+        let token = auth.authenticate(FINGERPRINT, "hunter2").await.unwrap();
+
+        std::fs::write(dir.join(PASSWORD_FILE), hash_password("hunter3").unwrap()).unwrap();
+
+        assert!(auth.validate(FINGERPRINT, &token.token).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_are_rejected_without_leeway() {
+        let (auth, dir) = admin_auth("admin-expiry", "hunter2");
+        // Expired a second ago, within the default leeway of the decoder
+        let iat = unix_now() - ADMIN_TOKEN_TTL.as_secs() - 1;
+        let claims = AdminClaims {
+            sub: FINGERPRINT.to_owned(),
+            pwd: auth.password_digest().await.unwrap(),
+            exp: iat + ADMIN_TOKEN_TTL.as_secs(),
+            iat,
+        };
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(&auth.secret),
+        )
+        .unwrap();
+
+        assert!(auth.validate(FINGERPRINT, &token).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lockouts_escalate_with_every_failure() {
+        let auth = AdminAuth::new(Path::new("unused"), b"secret");
+
+        for _ in 1..MAX_FAILED_ATTEMPTS {
+            fail(&auth, FINGERPRINT);
+        }
+        assert_eq!(locked_for(&auth, FINGERPRINT), None);
+        assert!(!is_locked(&auth, FINGERPRINT));
+
+        fail(&auth, FINGERPRINT);
+        let first = locked_for(&auth, FINGERPRINT).unwrap();
+        assert!(first <= LOCKOUT_BASE && first > LOCKOUT_BASE / 2);
+        assert!(is_locked(&auth, FINGERPRINT));
+        // Other clients are not affected
+        assert!(!is_locked(&auth, "DD:EE:FF"));
+
+        fail(&auth, FINGERPRINT);
+        let second = locked_for(&auth, FINGERPRINT).unwrap();
+        assert!(second > LOCKOUT_BASE && second <= LOCKOUT_BASE * 2);
+
+        for _ in 0..16 {
+            fail(&auth, FINGERPRINT);
+        }
+        assert!(locked_for(&auth, FINGERPRINT).unwrap() <= LOCKOUT_MAX);
+    }
+
+    #[tokio::test]
+    async fn attempts_count_before_the_password_is_verified() {
+        let (auth, dir) = admin_auth("admin-reservation", "hunter2");
+
+        // Guesses in flight hold their attempt, the next one is refused
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            auth.reserve_attempt(FINGERPRINT).unwrap();
+        }
+        assert!(auth.reserve_attempt(FINGERPRINT).is_err());
+        assert!(auth.check_password(FINGERPRINT, "hunter2").await.is_err());
+
+        // A success releases the attempts of the client
+        auth.reserve_attempt("DD:EE:FF").unwrap();
+        auth.check_password("DD:EE:FF", "hunter2").await.unwrap();
+        assert!(!auth.failures.lock().unwrap().contains_key("DD:EE:FF"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stale_failures_are_forgotten() {
+        let auth = AdminAuth::new(Path::new("unused"), b"secret");
+        let Some(long_ago) = Instant::now().checked_sub(FAILURE_MEMORY) else {
+            return;
+        };
+
+        for i in 0..MAX_TRACKED_CLIENTS {
+            fail(&auth, &format!("client {i}"));
+        }
+        {
+            let mut failures = auth.failures.lock().unwrap();
+            failures.get_mut("client 0").unwrap().last_failure = long_ago;
+            failures.get_mut("client 1").unwrap().last_failure = long_ago + Duration::from_secs(1);
+        }
+
+        fail(&auth, FINGERPRINT);
+        {
+            let failures = auth.failures.lock().unwrap();
+            assert_eq!(failures.len(), MAX_TRACKED_CLIENTS);
+            assert!(!failures.contains_key("client 0"));
+        }
+
+        // Past the cap, the client whose last failure is the oldest goes
+        fail(&auth, "another client");
+        let failures = auth.failures.lock().unwrap();
+        assert_eq!(failures.len(), MAX_TRACKED_CLIENTS);
+        assert!(!failures.contains_key("client 1"));
+        assert!(failures.contains_key(FINGERPRINT));
+    }
+}
@@ -5,43 +5,44 @@ use serde::{Deserialize, Serialize};
 use urlencoding::encode;
 
 use ::http_request::{
-    BodyExt, Bytes, ClientConfig, Empty, Full, Method, Request, StatusCode, Uri,
-    create_https_client, send_http_request,
+    Bytes, ClientConfig, ConnectionPool, Empty, Full, Method, Request, RequestOptions, StatusCode,
+    Uri, send_request_with_options,
 };
 
+use ::discovery::endpoint::ServerEndpoint;
+
 use super::utils::device::SanitizedDeviceInfo;
 
 pub async fn fetch_device_info(
     host: &str,
     config: Arc<ClientConfig>,
 ) -> Result<SanitizedDeviceInfo> {
+    let endpoint = ServerEndpoint::parse(host)?;
     let uri = Uri::builder()
         .scheme("https")
-        .authority(format!("{host}:7863"))
-        .path_and_query("/device-info")
+        .authority(endpoint.authority())
+        .path_and_query(endpoint.path("/device-info"))
         .build()
         .context("Invalid URL format")?;
 
-    let mut sender = create_https_client(host.to_owned(), 7863, config)
-        .await
-        .context("Failed to create HTTPS client")?;
-
     let req = Request::builder()
         .uri(uri)
         .header("Accept", "application/json")
         .body(Empty::<Bytes>::new())
         .context("Failed to build request")?;
 
-    let res = send_http_request(&mut sender, req)
-        .await
-        .context("Failed to execute request")?;
+    let res = send_request_with_options(
+        ConnectionPool::shared(),
+        &endpoint.host,
+        endpoint.api_port,
+        config,
+        req,
+        &RequestOptions::idempotent(),
+    )
+    .await
+    .context("Failed to execute request")?;
 
-    let body = res
-        .into_body()
-        .collect()
-        .await
-        .context("Failed to read response body")?
-        .to_bytes();
+    let body = res.into_body();
 
     let device_info: SanitizedDeviceInfo =
         serde_json::from_slice(&body).context("Failed to parse device info")?;
@@ -49,6 +50,25 @@ pub async fn fetch_device_info(
     Ok(device_info)
 }
 
+/// Resolves the endpoint of `host`, refining it with the ports and path
+/// prefix the server advertises.
+///
+/// Falls back to the layout parsed from `host` when the server can not be
+/// reached or does not advertise its layout.
+pub async fn resolve_endpoint(host: &str, config: Arc<ClientConfig>) -> Result<ServerEndpoint> {
+    let endpoint = ServerEndpoint::parse(host)?;
+
+    match fetch_device_info(host, config).await {
+        Ok(SanitizedDeviceInfo {
+            api_port: Some(api_port),
+            ws_port,
+            path_prefix,
+            ..
+        }) => Ok(endpoint.with_advertised(api_port, ws_port, &path_prefix)),
+        _ => Ok(endpoint),
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct RegisterRequest {
     public_key: String,
@@ -67,17 +87,14 @@ pub async fn register_device(
     device_model: String,
     device_type: String,
 ) -> Result<()> {
+    let endpoint = ServerEndpoint::parse(host)?;
     let uri = Uri::builder()
         .scheme("https")
-        .authority(format!("{host}:7863"))
-        .path_and_query("/register")
+        .authority(endpoint.authority())
+        .path_and_query(endpoint.path("/register"))
         .build()
         .context("Invalid URL format")?;
 
-    let mut sender = create_https_client(host.to_owned(), 7863, config)
-        .await
-        .context("Failed to create HTTPS client")?;
-
     let register_request = RegisterRequest {
         public_key,
         fingerprint,
@@ -96,12 +113,19 @@ pub async fn register_device(
         .body(Full::new(Bytes::from(json_body)))
         .context("Failed to build request")?;
 
-    let response = send_http_request(&mut sender, req)
-        .await
-        .context("Failed to execute request")?;
+    let response = send_request_with_options(
+        ConnectionPool::shared(),
+        &endpoint.host,
+        endpoint.api_port,
+        config,
+        req,
+        &RequestOptions::default(),
+    )
+    .await
+    .context("Failed to execute request")?;
 
     let status = response.status();
-    let error_body = response.into_body().collect().await?.to_bytes();
+    let error_body = response.into_body();
 
     if status != StatusCode::CREATED {
         let error_message = String::from_utf8_lossy(&error_body);
@@ -127,37 +151,36 @@ pub async fn check_fingerprint(
     config: Arc<ClientConfig>,
     fingerprint: &str,
 ) -> Result<CheckFingerprintResponse> {
+    let endpoint = ServerEndpoint::parse(host)?;
     let uri = Uri::builder()
         .scheme("https")
-        .authority(format!("{host}:7863"))
-        .path_and_query(format!(
+        .authority(endpoint.authority())
+        .path_and_query(endpoint.path(&format!(
             "/check-fingerprint?fingerprint={}",
             encode(fingerprint)
-        ))
+        )))
         .build()
         .context("Invalid URL format")?;
 
-    let mut sender = create_https_client(host.to_owned(), 7863, config)
-        .await
-        .context("Failed to create HTTPS client")?;
-
     let req = Request::builder()
         .uri(uri)
         .header("Accept", "application/json")
         .body(Empty::<Bytes>::new())
         .context("Failed to build request")?;
 
-    let res = send_http_request(&mut sender, req)
-        .await
-        .context("Failed to execute request")?;
+    let res = send_request_with_options(
+        ConnectionPool::shared(),
+        &endpoint.host,
+        endpoint.api_port,
+        config,
+        req,
+        &RequestOptions::idempotent(),
+    )
+    .await
+    .context("Failed to execute request")?;
 
     let status = res.status();
-    let body = res
-        .into_body()
-        .collect()
-        .await
-        .context("Failed to read response body")?
-        .to_bytes();
+    let body = res.into_body();
 
     if status != StatusCode::OK {
         let error_message = String::from_utf8_lossy(&body);
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use rand::{
+    distributions::{Alphanumeric, DistString},
+    thread_rng,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+const TOKEN_FILE: &str = "api_tokens.json";
+const TOKEN_PREFIX: &str = "rune_";
+const TOKEN_LENGTH: usize = 40;
+
+/// A token granting access to the HTTP API, only its digest is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiTokenRecord {
+    pub id: String,
+    pub name: String,
+    digest: String,
+    /// Creation time (UNIX timestamp)
+    pub created_at: i64,
+}
+
+/// Persists the API tokens of the server in the configuration directory.
+///
+/// The file is read on every call, so tokens created or revoked from the
+/// command line take effect on a running server immediately.
+#[derive(Debug)]
+pub struct ApiTokenStore {
+    path: PathBuf,
+}
+
+impl ApiTokenStore {
+    pub fn new(config_path: &Path) -> Self {
+        Self {
+            path: config_path.join(TOKEN_FILE),
+        }
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiTokenRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = tokio::fs::read_to_string(&self.path)
+            .await
+            .context("Failed to read API tokens")?;
+        serde_json::from_str(&content).context("Failed to parse API tokens")
+    }
+
+    async fn save(&self, records: &[ApiTokenRecord]) -> Result<()> {
+        let content = serde_json::to_string_pretty(records)?;
+        tokio::fs::write(&self.path, content)
+            .await
+            .context("Failed to write API tokens")
+    }
+
+    /// Creates a token named `name`, the returned secret is never shown again.
+    pub async fn create(&self, name: &str) -> Result<(ApiTokenRecord, String)> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("API token name cannot be empty");
+        }
+
+        let mut records = self.list().await?;
+        if records.iter().any(|x| x.name == name) {
+            bail!("An API token named {name} already exists");
+        }
+
+        let token = format!(
+            "{TOKEN_PREFIX}{}",
+            Alphanumeric.sample_string(&mut thread_rng(), TOKEN_LENGTH)
+        );
+        let record = ApiTokenRecord {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_owned(),
+            digest: digest(&token),
+            created_at: Utc::now().timestamp(),
+        };
+
+        records.push(record.clone());
+        self.save(&records).await?;
+
+        Ok((record, token))
+    }
+
+    /// Revokes the token with the given id or name.
+    pub async fn revoke(&self, id_or_name: &str) -> Result<ApiTokenRecord> {
+        let mut records = self.list().await?;
+        let Some(index) = records
+            .iter()
+            .position(|x| x.id == id_or_name || x.name == id_or_name)
+        else {
+            bail!("API token {id_or_name} not found");
+        };
+
+        let record = records.remove(index);
+        self.save(&records).await?;
+
+        Ok(record)
+    }
+
+    /// Finds the record of `token`, if it was issued and not revoked.
+    pub async fn verify(&self, token: &str) -> Result<Option<ApiTokenRecord>> {
+        if !token.starts_with(TOKEN_PREFIX) {
+            return Ok(None);
+        }
+
+        let digest = digest(token);
+        Ok(self.list().await?.into_iter().find(|x| x.digest == digest))
+    }
+}
+
+fn digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect()
+}
@@ -0,0 +1,182 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use chrono::Duration;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::Value;
+
+use ::database::actions::audit_log::{AuditEntry, insert_audit_log, prune_audit_log};
+
+use crate::utils::GlobalParams;
+
+/// Request name prefixes that never change the state of the server.
+const READ_ONLY_PREFIXES: &[&str] = &[
+    "Fetch",
+    "Get",
+    "List",
+    "Search",
+    "If",
+    "Check",
+    "Test",
+    "Validate",
+    "Preview",
+    "SystemInfo",
+    "MixQuery",
+    "ComplexQuery",
+    "ServerAvailabilityTest",
+];
+
+/// The audit log is pruned once every this many insertions.
+const PRUNE_INTERVAL: u64 = 100;
+
+const MAX_PARAMETER_LENGTH: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    /// Also record read-only requests, useful for debugging.
+    pub verbose: bool,
+    pub max_age: Option<Duration>,
+    pub max_rows: Option<u64>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            verbose: false,
+            max_age: Some(Duration::days(90)),
+            max_rows: Some(10_000),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditLogger {
+    verbose: AtomicBool,
+    max_age: Option<Duration>,
+    max_rows: Option<u64>,
+    inserted: AtomicU64,
+}
+
+impl Default for AuditLogger {
+    fn default() -> Self {
+        Self::new(AuditConfig::default())
+    }
+}
+
+impl AuditLogger {
+    pub fn new(config: AuditConfig) -> Self {
+        Self {
+            verbose: AtomicBool::new(config.verbose),
+            max_age: config.max_age,
+            max_rows: config.max_rows,
+            inserted: AtomicU64::new(0),
+        }
+    }
+
+    pub fn set_verbose(&self, verbose: bool) {
+        self.verbose.store(verbose, Ordering::SeqCst);
+    }
+
+    pub fn should_record(&self, request_type: &str) -> bool {
+        self.verbose.load(Ordering::SeqCst) || !is_read_only_request(request_type)
+    }
+}
+
+pub fn is_read_only_request(request_type: &str) -> bool {
+    READ_ONLY_PREFIXES
+        .iter()
+        .any(|prefix| request_type.starts_with(prefix))
+}
+
+/// Keeps identifiers and other short scalar fields of a request, so the
+/// audit log shows what was targeted without storing whole payloads.
+pub fn summarize_parameters<T: Serialize>(request: &T) -> String {
+    let Ok(Value::Object(fields)) = serde_json::to_value(request) else {
+        return String::new();
+    };
+
+    let summary: serde_json::Map<String, Value> = fields
+        .into_iter()
+        .filter(|(key, value)| match value {
+            Value::Bool(_) | Value::Number(_) => true,
+            Value::String(x) => x.len() <= 64,
+            Value::Array(items) => {
+                key.ends_with("ids") && items.iter().all(|item| item.is_number())
+            }
+            _ => false,
+        })
+        .collect();
+
+    let mut summary = Value::Object(summary).to_string();
+    if summary.len() > MAX_PARAMETER_LENGTH {
+        let mut end = MAX_PARAMETER_LENGTH;
+        while !summary.is_char_boundary(end) {
+            end -= 1;
+        }
+        summary.truncate(end);
+        summary.push('…');
+    }
+
+    summary
+}
+
+/// Records a remote request into the audit log without blocking the caller.
+pub fn record_request<T: Serialize>(
+    global_params: &GlobalParams,
+    request_type: &str,
+    request: &T,
+    fingerprint: Option<String>,
+    success: bool,
+) {
+    let Some(fingerprint) = fingerprint else {
+        return;
+    };
+    let Some(server_manager) = global_params.server_manager.get() else {
+        return;
+    };
+
+    let logger = Arc::clone(&server_manager.audit_logger);
+    if !logger.should_record(request_type) {
+        return;
+    }
+
+    let request_type = request_type.to_string();
+    let parameters = summarize_parameters(request);
+    let main_db = Arc::clone(&global_params.main_db);
+    let permission_manager = Arc::clone(&global_params.permission_manager);
+
+    tokio::spawn(async move {
+        let alias = permission_manager
+            .read()
+            .await
+            .verify_by_fingerprint(&fingerprint)
+            .await
+            .map(|user| user.alias)
+            .unwrap_or_default();
+
+        let entry = AuditEntry {
+            request_type,
+            fingerprint,
+            alias,
+            parameters,
+            success,
+        };
+
+        if let Err(e) = insert_audit_log(&*main_db, entry).await {
+            error!("Failed to record audit log: {e:#?}");
+            return;
+        }
+
+        let inserted = logger.inserted.fetch_add(1, Ordering::Relaxed) + 1;
+        if inserted.is_multiple_of(PRUNE_INTERVAL) {
+            match prune_audit_log(&main_db, logger.max_age, logger.max_rows).await {
+                Ok(0) => {}
+                Ok(removed) => info!("Pruned {removed} audit log entries"),
+                Err(e) => error!("Failed to prune audit log: {e:#?}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,16 @@
+use anyhow::Result;
+
+use ::playback::output_stream::list_output_devices;
+
+pub fn handle_audio_devices() -> Result<()> {
+    let devices = list_output_devices()?;
+    if devices.is_empty() {
+        println!("No audio devices");
+    }
+
+    for device in devices {
+        let default = if device.is_default { "  (default)" } else { "" };
+        println!("{}{default}", device.id);
+    }
+    Ok(())
+}
@@ -2,19 +2,29 @@ use anyhow::Result;
 use log::{error, info};
 use rpassword::prompt_password;
 
-use hub::server::update_root_password;
+use hub::server::{change_root_password, update_root_password};
 
 use ::discovery::config::get_config_dir;
 
 pub async fn handle_chpwd() -> Result<()> {
     let config_dir = get_config_dir()?;
+    let initialized = config_dir.join("root_password.hash").exists();
+
+    let current = if initialized {
+        Some(prompt_password("Enter current password: ")?)
+    } else {
+        None
+    };
 
     loop {
         let pwd = prompt_password("Enter new password: ")?;
         let confirm = prompt_password("Confirm password: ")?;
 
         if pwd == confirm {
-            update_root_password(&config_dir, &pwd).await?;
+            match &current {
+                Some(current) => change_root_password(&config_dir, current, &pwd).await?,
+                None => update_root_password(&config_dir, &pwd).await?,
+            }
             info!("Password updated successfully");
             return Ok(());
         }
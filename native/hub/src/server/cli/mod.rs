@@ -1,4 +1,6 @@
+pub mod audio_devices;
 pub mod broadcast;
 pub mod chpwd;
 pub mod permission;
 pub mod server;
+pub mod token;
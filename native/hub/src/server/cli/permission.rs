@@ -1,7 +1,10 @@
 use anyhow::Result;
 use log::info;
 
-use hub::server::utils::permission::{parse_status, print_permission_table, validate_index};
+use hub::server::{
+    subsonic::credentials::SubsonicCredentialStore,
+    utils::permission::{parse_status, print_permission_table, validate_index},
+};
 
 use crate::PermissionAction;
 
@@ -31,6 +34,23 @@ pub async fn handle_permission(action: PermissionAction) -> Result<()> {
             pm.remove_user(&user.fingerprint).await?;
             info!("User deleted successfully");
         }
+        PermissionAction::Subsonic { index, revoke } => {
+            let users = pm.list_users().await;
+            validate_index(index, users.len())?;
+            let user = &users[index - 1];
+            let store = SubsonicCredentialStore::new(config_path);
+
+            if revoke {
+                match store.revoke(&user.fingerprint).await? {
+                    Some(credential) => info!("Subsonic login {} revoked", credential.username),
+                    None => info!("User has no Subsonic login"),
+                }
+            } else {
+                let credential = store.issue(&user.alias, &user.fingerprint).await?;
+                println!("Username: {}", credential.username);
+                println!("Password: {}", credential.password);
+            }
+        }
     }
     Ok(())
 }
@@ -1,28 +1,41 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::net::SocketAddr;
 
 use anyhow::Result;
+use log::error;
 use tokio::signal::ctrl_c;
 
-use hub::server::{ServerManager, utils::device::load_device_info};
-
-use crate::initialize_global_params;
-
-use ::discovery::{DiscoveryParams, config::get_config_dir};
-
-pub async fn handle_server(addr: String, lib_path: String) -> Result<()> {
-    let config_path = get_config_dir()?;
-    let device_info = load_device_info(config_path).await?;
-    let global_params = initialize_global_params(&lib_path, config_path.to_str().unwrap()).await?;
-
-    let server_manager = Arc::new(ServerManager::new(global_params).await?);
-    let socket_addr: SocketAddr = addr.parse()?;
-
-    server_manager
-        .clone()
-        .start(socket_addr, DiscoveryParams { device_info })
-        .await?;
-
-    ctrl_c().await?;
-    server_manager.stop().await?;
-    Ok(())
+use hub::server::{
+    limits::ConnectionLimits,
+    runner::{ServeOptions, run_server},
+    utils::bind::ServerLayout,
+};
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_server(
+    addr: Vec<String>,
+    layout: ServerLayout,
+    strict_bind: bool,
+    lib_path: String,
+    limits: ConnectionLimits,
+    audit_verbose: bool,
+    metrics_addr: Option<SocketAddr>,
+    audio_device: Option<String>,
+) -> Result<()> {
+    let options = ServeOptions {
+        addr,
+        layout,
+        strict_bind,
+        limits,
+        audit_verbose,
+        metrics_addr,
+        broadcast: false,
+        audio_device,
+    };
+
+    run_server(&lib_path, options, async {
+        if let Err(e) = ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {e}");
+        }
+    })
+    .await
 }
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::info;
+
+use hub::server::api_tokens::ApiTokenStore;
+
+use crate::TokenAction;
+
+use ::discovery::config::get_config_dir;
+
+pub async fn handle_token(action: TokenAction) -> Result<()> {
+    let config_path = get_config_dir()?;
+    let store = ApiTokenStore::new(config_path);
+
+    match action {
+        TokenAction::Ls => {
+            let records = store.list().await?;
+            if records.is_empty() {
+                println!("No API tokens");
+            }
+
+            for record in records {
+                let created_at = DateTime::<Utc>::from_timestamp(record.created_at, 0)
+                    .map(|x| x.to_rfc3339())
+                    .unwrap_or_default();
+                println!("{}  {}  {}", record.id, record.name, created_at);
+            }
+        }
+        TokenAction::Create { name } => {
+            let (record, token) = store.create(&name).await?;
+            info!("API token {} created", record.name);
+            println!("{token}");
+            println!("Store this token now, it will not be shown again");
+        }
+        TokenAction::Revoke { token } => {
+            let record = store.revoke(&token).await?;
+            info!("API token {} revoked", record.name);
+        }
+    }
+    Ok(())
+}
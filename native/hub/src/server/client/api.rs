@@ -25,7 +25,6 @@ pub async fn build_query(
     id: i32,
     connection: &WSConnection,
 ) -> Result<Vec<(String, String)>> {
-
     if collection_type == CollectionType::Mix {
         let queries = fetch_mix_queries_by_mix_id(id, connection).await?;
         Ok(queries
@@ -67,17 +66,37 @@ pub fn path_to_collection_type(path: &Path) -> Option<CollectionType> {
         "Tracks" => Some(CollectionType::Track),
         "Genres" => Some(CollectionType::Genre),
         _ => {
-            log::warn!("path_to_collection_type: Unknown collection type '{}' from path {:?}", component_str, path);
+            log::warn!(
+                "path_to_collection_type: Unknown collection type '{}' from path {:?}",
+                component_str,
+                path
+            );
             None
         }
     }
 }
 
+/// The root directory of the virtual file system holding a collection type
+pub fn collection_type_to_root_dir(collection_type: CollectionType) -> Option<&'static str> {
+    match collection_type {
+        CollectionType::Album => Some("Albums"),
+        CollectionType::Artist => Some("Artists"),
+        CollectionType::Playlist => Some("Playlists"),
+        CollectionType::Mix => Some("Mixes"),
+        CollectionType::Track => Some("Tracks"),
+        CollectionType::Genre => Some("Genres"),
+        CollectionType::Directory => None,
+    }
+}
+
 pub async fn fetch_collection_group_summary(
     collection_type: CollectionType,
     connection: &WSConnection,
 ) -> Result<CollectionGroupSummaryResponse> {
-    let request = FetchCollectionGroupSummaryRequest { collection_type };
+    let request = FetchCollectionGroupSummaryRequest {
+        collection_type,
+        bypass_cache: false,
+    };
 
     connection
         .request("FetchCollectionGroupSummaryRequest", request)
@@ -93,6 +112,7 @@ pub async fn fetch_collection_groups(
         collection_type,
         bake_cover_arts: false,
         group_titles,
+        bypass_cache: false,
     };
 
     connection
@@ -100,6 +120,54 @@ pub async fn fetch_collection_groups(
         .await
 }
 
+pub async fn fetch_collections_by_ids(
+    collection_type: CollectionType,
+    ids: Vec<i32>,
+    connection: &WSConnection,
+) -> Result<FetchCollectionByIdsResponse> {
+    let request = FetchCollectionByIdsRequest {
+        collection_type,
+        bake_cover_arts: false,
+        ids,
+        bypass_cache: false,
+    };
+
+    connection
+        .request("FetchCollectionByIdsRequest", request)
+        .await
+}
+
+pub async fn fetch_media_files_by_ids(
+    ids: Vec<i32>,
+    connection: &WSConnection,
+) -> Result<FetchMediaFileByIdsResponse> {
+    let request = FetchMediaFileByIdsRequest {
+        ids,
+        bake_cover_arts: false,
+        bypass_cache: false,
+    };
+
+    connection
+        .request("FetchMediaFileByIdsRequest", request)
+        .await
+}
+
+pub async fn send_search_request(
+    query_str: String,
+    fields: Vec<String>,
+    n: i32,
+    connection: &WSConnection,
+) -> Result<SearchForResponse> {
+    let request = SearchForRequest {
+        query_str,
+        fields,
+        n,
+        include_lyrics: false,
+    };
+
+    connection.request("SearchForRequest", request).await
+}
+
 impl From<OperateMode> for PlaylistOperateMode {
     fn from(mode: OperateMode) -> Self {
         match mode {
@@ -157,6 +225,28 @@ pub async fn send_mix_query_request(
     connection.request("MixQueryRequest", request).await
 }
 
+pub async fn fetch_all_mixes(connection: &WSConnection) -> Result<FetchAllMixesResponse> {
+    let request = FetchAllMixesRequest {};
+
+    connection.request("FetchAllMixesRequest", request).await
+}
+
+pub async fn fetch_mix_queries(
+    mix_id: i32,
+    connection: &WSConnection,
+) -> Result<Vec<(String, String)>> {
+    let request = FetchMixQueriesRequest { mix_id };
+
+    let response: FetchMixQueriesResponse = connection
+        .request("FetchMixQueriesRequest", request)
+        .await?;
+    Ok(response
+        .result
+        .into_iter()
+        .map(|x| (x.operator, x.parameter))
+        .collect())
+}
+
 pub async fn send_play_request(connection: &WSConnection) -> Result<()> {
     let request = PlayRequest {};
 
@@ -181,6 +271,72 @@ pub async fn send_previous_request(connection: &WSConnection) -> Result<()> {
     connection.request_simple("PreviousRequest", request).await
 }
 
+pub async fn fetch_playback_queue(connection: &WSConnection) -> Result<FetchPlaybackQueueResponse> {
+    let request = FetchPlaybackQueueRequest {};
+
+    connection
+        .request("FetchPlaybackQueueRequest", request)
+        .await
+}
+
+pub async fn fetch_lyrics(
+    item: PlayingItemRequest,
+    connection: &WSConnection,
+) -> Result<GetLyricByTrackIdResponse> {
+    let request = GetLyricByTrackIdRequest { item: Some(item) };
+
+    connection
+        .request("GetLyricByTrackIdRequest", request)
+        .await
+}
+
+pub async fn send_set_liked_request(
+    item: PlayingItemRequest,
+    liked: bool,
+    connection: &WSConnection,
+) -> Result<SetLikedResponse> {
+    let request = SetLikedRequest {
+        item: Some(item),
+        liked,
+    };
+
+    connection.request("SetLikedRequest", request).await
+}
+
+pub async fn send_set_rating_request(
+    item: PlayingItemRequest,
+    rating: Option<i32>,
+    connection: &WSConnection,
+) -> Result<SetRatingResponse> {
+    let request = SetRatingRequest {
+        item: Some(item),
+        rating,
+    };
+
+    connection.request("SetRatingRequest", request).await
+}
+
+pub async fn send_seek_request(position_seconds: f64, connection: &WSConnection) -> Result<()> {
+    let request = SeekRequest { position_seconds };
+
+    connection.request_simple("SeekRequest", request).await
+}
+
+pub async fn send_volume_request(volume: f32, connection: &WSConnection) -> Result<f32> {
+    let request = VolumeRequest { volume };
+
+    let response: VolumeResponse = connection.request("VolumeRequest", request).await?;
+    Ok(response.volume)
+}
+
+pub async fn send_relative_volume_request(delta: f32, connection: &WSConnection) -> Result<f32> {
+    let request = RelativeVolumeRequest { delta };
+
+    let response: RelativeVolumeResponse =
+        connection.request("RelativeVolumeRequest", request).await?;
+    Ok(response.volume)
+}
+
 pub async fn send_set_playback_mode_request(
     playback_mode: PlaybackMode,
     connection: &WSConnection,
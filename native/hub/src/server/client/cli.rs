@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
 #[derive(Clone, Debug)]
@@ -66,6 +68,196 @@ impl From<OperateMode> for i32 {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VolumeChange {
+    /// A volume from 0 to 1
+    Absolute(f32),
+    /// A step, as a fraction of the full volume
+    Relative(f32),
+}
+
+impl std::str::FromStr for VolumeChange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with(['+', '-']) {
+            let step: f32 = s.parse().map_err(|_| format!("Invalid volume step: {s}"))?;
+            return Ok(VolumeChange::Relative(step / 100.0));
+        }
+
+        match s.parse::<f32>() {
+            Ok(volume) if (0.0..=1.0).contains(&volume) => Ok(VolumeChange::Absolute(volume)),
+            _ => Err(format!("Invalid volume: {s}, expected a value from 0 to 1")),
+        }
+    }
+}
+
+/// Parses a time as bare seconds, `mm:ss` or `hh:mm:ss`, the last field
+/// allowing a fraction.
+pub fn parse_time(s: &str) -> Result<f64, String> {
+    let invalid = || format!("Invalid time: {s}, expected seconds, mm:ss or hh:mm:ss");
+
+    let fields: Vec<&str> = s.split(':').collect();
+    if fields.len() > 3 || fields.iter().any(|x| x.is_empty()) {
+        return Err(invalid());
+    }
+
+    let (last, leading) = fields.split_last().ok_or_else(invalid)?;
+    let seconds: f64 = last.parse().map_err(|_| invalid())?;
+    if !seconds.is_finite() || seconds < 0.0 || (!leading.is_empty() && seconds >= 60.0) {
+        return Err(invalid());
+    }
+
+    let mut total = 0.0;
+    for (i, field) in leading.iter().enumerate() {
+        if !field.chars().all(|x| x.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let value: u64 = field.parse().map_err(|_| invalid())?;
+        // Minutes following hours are below 60 as well
+        if i > 0 && value >= 60 {
+            return Err(invalid());
+        }
+        total = total * 60.0 + value as f64;
+    }
+
+    Ok(total * 60.0 + seconds)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SeekPosition {
+    /// A position from the start of the track, in seconds
+    Absolute(f64),
+    /// A step from the current position, in seconds
+    Relative(f64),
+}
+
+impl std::str::FromStr for SeekPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(step) = s.strip_prefix('+') {
+            return parse_time(step).map(SeekPosition::Relative);
+        }
+        if let Some(step) = s.strip_prefix('-') {
+            return parse_time(step).map(|x| SeekPosition::Relative(-x));
+        }
+
+        parse_time(s).map(SeekPosition::Absolute)
+    }
+}
+
+/// Operators a mix query can use
+pub const MIX_OPERATORS: &[&str] = &[
+    "lib::all",
+    "lib::artist",
+    "lib::album",
+    "lib::genre",
+    "lib::playlist",
+    "lib::track",
+    "lib::random",
+    "lib::queue",
+    "lib::directory.deep",
+    "lib::directory.shallow",
+    "sort::track_number",
+    "sort::last_modified",
+    "sort::duration",
+    "sort::playedthrough",
+    "sort::skipped",
+    "sort::most_skipped",
+    "filter::liked",
+    "filter::in_progress",
+    "filter::analyzed",
+    "filter::with_cover_art",
+    "filter::added_within",
+    "pipe::limit",
+    "pipe::recommend",
+];
+
+/// Parses a mix query, `operator=parameter` pairs separated by `;`, the
+/// `operator(parameter)` form of the command line being accepted as well.
+pub fn parse_mix_query(s: &str) -> Result<Vec<(String, String)>, String> {
+    let mut queries = Vec::new();
+
+    for part in s.split(';').map(str::trim).filter(|x| !x.is_empty()) {
+        let (operator, parameter) = if let Some((operator, parameter)) = part.split_once('=') {
+            (operator, parameter)
+        } else if let Some(parameter) = part.strip_suffix(')')
+            && let Some((operator, parameter)) = parameter.split_once('(')
+        {
+            (operator, parameter)
+        } else {
+            return Err(format!(
+                "Invalid query: {part}, expected operator=parameter"
+            ));
+        };
+
+        let operator = operator.trim();
+        if !MIX_OPERATORS.contains(&operator) {
+            return Err(format!("Unknown operator: {operator}"));
+        }
+        queries.push((operator.to_string(), parameter.trim().to_string()));
+    }
+
+    if queries.is_empty() {
+        return Err("The mix query is empty".to_string());
+    }
+
+    Ok(queries)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MixSource {
+    /// The ID of a saved mix
+    Saved(i32),
+    Query(Vec<(String, String)>),
+}
+
+impl std::str::FromStr for MixSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().parse::<i32>() {
+            Ok(id) => Ok(MixSource::Saved(id)),
+            Err(_) => parse_mix_query(s).map(MixSource::Query),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchType {
+    Artists,
+    Albums,
+    Tracks,
+    Playlists,
+}
+
+impl std::str::FromStr for SearchType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "artist" | "artists" => Ok(SearchType::Artists),
+            "album" | "albums" => Ok(SearchType::Albums),
+            "track" | "tracks" => Ok(SearchType::Tracks),
+            "playlist" | "playlists" => Ok(SearchType::Playlists),
+            _ => Err(format!("Unknown search type: {s}")),
+        }
+    }
+}
+
+impl SearchType {
+    /// The field name the server searches for this type
+    pub fn field(self) -> &'static str {
+        match self {
+            SearchType::Artists => "artist",
+            SearchType::Albums => "album",
+            SearchType::Tracks => "track",
+            SearchType::Playlists => "playlist",
+        }
+    }
+}
+
 #[derive(Debug, Parser)]
 pub enum ReplCommand {
     /// List contents of current directory
@@ -88,6 +280,18 @@ pub enum ReplCommand {
         /// Directory to change to
         path: String,
     },
+    /// Search the library, `cd @N` and `opq @N` then use the Nth result
+    Search {
+        /// Words to search for
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+        /// Only search one type (artists, albums, tracks, playlists)
+        #[arg(short = 't', long = "type")]
+        search_type: Option<SearchType>,
+        /// Maximum number of results per type
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: i32,
+    },
     /// Operate playback with mix query
     Opq {
         /// Path to create query from
@@ -126,6 +330,86 @@ pub enum ReplCommand {
     Next,
     /// Go back to the previous track
     Previous,
+    /// Seek in the current track: `seek 1:23`, `seek 90`, `seek +30`, `seek -10`
+    Seek {
+        /// Seconds, mm:ss or hh:mm:ss, relative when starting with + or -
+        #[arg(allow_hyphen_values = true)]
+        position: SeekPosition,
+    },
+    /// Show the track being played
+    Np {
+        /// Keep the line updated until Enter is pressed
+        #[arg(short = 'f', long)]
+        follow: bool,
+    },
+    /// Show the play queue
+    Queue {
+        /// Page to show, the one holding the current track by default
+        #[arg(short = 'p', long)]
+        page: Option<usize>,
+        /// Number of tracks per page
+        #[arg(long, default_value_t = 20)]
+        page_size: usize,
+    },
+    /// Show the lyrics of the track being played
+    Lyrics {
+        /// Print each line as it is sung, until a key is pressed
+        #[arg(short = 's', long)]
+        sync: bool,
+    },
+    /// Like the track being played, or the given one
+    Like {
+        /// Track ID, or `@N` for the Nth track of the current directory or
+        /// else of the last search
+        track: Option<String>,
+    },
+    /// Unlike the track being played, or the given one
+    Unlike {
+        /// Track ID, or `@N` for the Nth track of the current directory or
+        /// else of the last search
+        track: Option<String>,
+    },
+    /// Rate the track being played, or the given one, 0 clearing the rating
+    Rate {
+        /// Rating from 0 to 5
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=5))]
+        rating: u8,
+        /// Track ID, or `@N` for the Nth track of the current directory or
+        /// else of the last search
+        track: Option<String>,
+    },
+    /// Evaluate a mix: `mix "lib::artist=123;filter::liked=true"`, or `mix <id>`
+    Mix {
+        /// Mix query, or the ID of a saved mix
+        mix: MixSource,
+        /// Replace the play queue with the tracks and play them
+        #[arg(long)]
+        play: bool,
+        /// Maximum number of tracks to print
+        #[arg(short = 'n', long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// List saved mixes
+    Mixes,
+    /// Download a track, or every track of an album or another collection
+    Get {
+        /// Path, track ID, or `@N` for the Nth entry of the current directory
+        /// or else of the last search
+        target: String,
+        /// Directory to download into, the current one by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Show or change the volume: `vol`, `vol 0.35`, `vol +5`, `vol -5`
+    Vol {
+        /// Volume from 0 to 1, or a step in percent starting with + or -
+        #[arg(allow_hyphen_values = true)]
+        volume: Option<VolumeChange>,
+    },
+    /// Mute the playback
+    Mute,
+    /// Restore the volume from before `mute`
+    Unmute,
     /// Set playback mode
     SetMode {
         /// Playback mode (sequential, repeatone, repeatall, shuffle)
@@ -139,9 +423,12 @@ pub enum ReplCommand {
 
 impl ReplCommand {
     pub fn parse(input: &str) -> Result<Self, clap::Error> {
-        let input_vec: Vec<String> = std::iter::once("".to_string())
-            .chain(shlex::split(input).unwrap_or_default())
-            .collect();
+        Self::parse_args(shlex::split(input).unwrap_or_default())
+    }
+
+    /// Parses a command already split into arguments
+    pub fn parse_args(args: Vec<String>) -> Result<Self, clap::Error> {
+        let input_vec: Vec<String> = std::iter::once("".to_string()).chain(args).collect();
 
         let args = input_vec.iter().map(|s| s.as_str());
 
@@ -217,6 +504,22 @@ pub enum RemoteCmd {
     Register { host: String },
     /// Printing the certification summary of this device
     SelfInfo,
+    /// Export the trusted devices to a password-protected bundle
+    Export {
+        /// Path of the bundle to write
+        path: PathBuf,
+        /// Also export the client certificate of this device
+        #[arg(long)]
+        include_identity: bool,
+    },
+    /// Import trusted devices from a password-protected bundle
+    Import {
+        /// Path of the bundle to read
+        path: PathBuf,
+        /// Replace the client certificate of this device with the exported one
+        #[arg(long)]
+        identity: bool,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -224,6 +527,9 @@ pub enum RemoteCmd {
 pub enum Cli {
     /// Interactive REPL mode
     Repl(ReplArgs),
+    /// Run a single REPL command and exit: 1 if it failed, 2 if it is
+    /// invalid, 3 if the server can't be reached in time
+    RemoteExec(RemoteExecArgs),
     /// Device discovery and management
     #[command(subcommand)]
     Discovery(DiscoveryCmd),
@@ -232,9 +538,113 @@ pub enum Cli {
     Remote(RemoteCmd),
 }
 
+#[derive(Debug, clap::Args)]
+pub struct RemoteExecArgs {
+    /// Service URL
+    #[arg(help = "The URL of the service, e.g., example.com:7863 or 192.168.1.1:8963/rune")]
+    pub service_url: String,
+    /// Seconds to wait for the server before giving up
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+    /// The command, as typed in the REPL
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    pub command: Vec<String>,
+}
+
 #[derive(Debug, clap::Args)]
 pub struct ReplArgs {
     /// Service URL
-    #[arg(help = "The URL of the service, e.g., example.com:7863 or 192.168.1.1:8963")]
+    #[arg(help = "The URL of the service, e.g., example.com:7863 or 192.168.1.1:8963/rune")]
     pub service_url: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bare_seconds() {
+        assert_eq!(parse_time("90"), Ok(90.0));
+        assert_eq!(parse_time("0"), Ok(0.0));
+        assert_eq!(parse_time("12.5"), Ok(12.5));
+    }
+
+    #[test]
+    fn parses_minutes_and_seconds() {
+        assert_eq!(parse_time("1:23"), Ok(83.0));
+        assert_eq!(parse_time("90:00"), Ok(5400.0));
+        assert_eq!(parse_time("0:05.5"), Ok(5.5));
+    }
+
+    #[test]
+    fn parses_hours_minutes_and_seconds() {
+        assert_eq!(parse_time("1:23:45"), Ok(5025.0));
+        assert_eq!(parse_time("0:00:01"), Ok(1.0));
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        for time in [
+            "", ":", "1:", ":30", "1:60", "1:60:00", "1:2:3:4", "abc", "1:ab", "-5", "1.5:00",
+            "inf", "NaN",
+        ] {
+            assert!(parse_time(time).is_err(), "{time} should be rejected");
+        }
+    }
+
+    #[test]
+    fn parses_mix_queries() {
+        assert_eq!(
+            parse_mix_query("lib::artist=123;filter::liked=true"),
+            Ok(vec![
+                ("lib::artist".to_string(), "123".to_string()),
+                ("filter::liked".to_string(), "true".to_string()),
+            ])
+        );
+        assert_eq!(
+            parse_mix_query(" lib::album(4) ; pipe::limit = 10 ;"),
+            Ok(vec![
+                ("lib::album".to_string(), "4".to_string()),
+                ("pipe::limit".to_string(), "10".to_string()),
+            ])
+        );
+        assert_eq!(
+            parse_mix_query("lib::directory.deep=/a=b"),
+            Ok(vec![(
+                "lib::directory.deep".to_string(),
+                "/a=b".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_mix_queries() {
+        for query in ["", ";", "lib::artist", "lib::nothing=1", "lib::album(4"] {
+            assert!(
+                parse_mix_query(query).is_err(),
+                "{query} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_mix_sources() {
+        assert_eq!("12".parse(), Ok(MixSource::Saved(12)));
+        assert_eq!(
+            "lib::all=true".parse(),
+            Ok(MixSource::Query(vec![(
+                "lib::all".to_string(),
+                "true".to_string()
+            )]))
+        );
+    }
+
+    #[test]
+    fn parses_seek_positions() {
+        assert_eq!("1:30".parse(), Ok(SeekPosition::Absolute(90.0)));
+        assert_eq!("+30".parse(), Ok(SeekPosition::Relative(30.0)));
+        assert_eq!("-10".parse(), Ok(SeekPosition::Relative(-10.0)));
+        assert_eq!("-1:00".parse(), Ok(SeekPosition::Relative(-60.0)));
+        assert!("+-5".parse::<SeekPosition>().is_err());
+    }
+}
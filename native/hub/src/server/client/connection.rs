@@ -1,37 +1,102 @@
 use std::sync::Arc;
 use std::{collections::HashMap, process::exit};
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use futures::{SinkExt, StreamExt};
 use log::error;
 use rinf::{DartSignal, RustSignal};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{Notify, RwLock, mpsc, oneshot};
 use tokio_tungstenite::{connect_async, tungstenite};
 use tungstenite::Message;
 use uuid::Uuid;
 
 use hub::backends::remote::{decode_message, encode_message};
+use hub::messages::{
+    CrashResponse, PermissionDeniedResponse, PlaybackStatus, PlaylistItem, PlaylistUpdate,
+    RelativeVolumeResponse, UnsupportedRequestResponse, VolumeResponse,
+};
+use hub::server::protocol::with_protocol_version;
+
+/// The latest state the server broadcasted, kept for the commands showing
+/// the playback
+#[derive(Default)]
+pub struct PlaybackState {
+    pub status: Option<PlaybackStatus>,
+    pub queue: Option<Vec<PlaylistItem>>,
+    /// Only known once a volume change went through this connection
+    pub volume: Option<f32>,
+}
+
+impl PlaybackState {
+    fn update(&mut self, type_name: &str, payload: &[u8]) {
+        let result = match type_name {
+            "PlaybackStatus" => {
+                rinf::deserialize::<PlaybackStatus>(payload).map(|x| self.status = Some(x))
+            }
+            "PlaylistUpdate" => {
+                rinf::deserialize::<PlaylistUpdate>(payload).map(|x| self.queue = Some(x.items))
+            }
+            "VolumeResponse" => {
+                rinf::deserialize::<VolumeResponse>(payload).map(|x| self.volume = Some(x.volume))
+            }
+            "RelativeVolumeResponse" => rinf::deserialize::<RelativeVolumeResponse>(payload)
+                .map(|x| self.volume = Some(x.volume)),
+            _ => return,
+        };
+
+        if let Err(e) = result {
+            error!("Failed to decode {type_name}: {e}");
+        }
+    }
+}
+
+/// Where the type name and the payload of each response are sent, by the
+/// ID of the request
+type ResponseChannels = Arc<RwLock<HashMap<Uuid, mpsc::Sender<(String, Vec<u8>)>>>>;
+
+/// What the writer task sends to the server
+#[derive(Debug)]
+enum Outgoing {
+    Message(String, Vec<u8>, Uuid),
+    /// Answered once every message queued before it was written
+    Flush(oneshot::Sender<()>),
+}
 
 pub struct WSConnection {
-    tx: mpsc::Sender<(String, Vec<u8>, Uuid)>,
-    response_channels: Arc<RwLock<HashMap<Uuid, mpsc::Sender<Vec<u8>>>>>,
+    /// The WebSocket URL of the service
+    pub url: String,
+    tx: mpsc::Sender<Outgoing>,
+    response_channels: ResponseChannels,
+    pub playback: Arc<RwLock<PlaybackState>>,
+    /// Notified whenever a new playback status arrived
+    pub status_changed: Arc<Notify>,
 }
 
 impl WSConnection {
     pub async fn connect(url: String) -> Result<Self> {
-        let (ws_stream, _) = connect_async(url).await?;
+        let (ws_stream, _) = connect_async(with_protocol_version(&url)).await?;
         let (write, mut read) = ws_stream.split();
 
-        let (tx, mut rx) = mpsc::channel::<(String, Vec<u8>, Uuid)>(32);
-        let response_channels =
-            Arc::new(RwLock::new(HashMap::<Uuid, mpsc::Sender<Vec<u8>>>::new()));
+        let (tx, mut rx) = mpsc::channel::<Outgoing>(32);
+        let response_channels: ResponseChannels = Arc::default();
         let response_channels_clone = response_channels.clone();
+        let playback = Arc::new(RwLock::new(PlaybackState::default()));
+        let playback_clone = playback.clone();
+        let status_changed = Arc::new(Notify::new());
+        let status_changed_clone = status_changed.clone();
 
         // Handle outgoing messages
         tokio::spawn(async move {
             let mut write = write;
-            while let Some((type_name, payload, uuid)) = rx.recv().await {
+            while let Some(outgoing) = rx.recv().await {
+                let (type_name, payload, uuid) = match outgoing {
+                    Outgoing::Message(type_name, payload, uuid) => (type_name, payload, uuid),
+                    Outgoing::Flush(done) => {
+                        let _ = done.send(());
+                        continue;
+                    }
+                };
                 let message = encode_message(&type_name, &payload, Some(uuid));
                 if let Err(e) = write.send(Message::Binary(message.into())).await {
                     eprintln!("Failed to send message: {e}");
@@ -45,10 +110,15 @@ impl WSConnection {
             while let Some(msg) = read.next().await {
                 match msg {
                     Ok(Message::Binary(payload)) => {
-                        if let Some((_type_name, payload, uuid)) = decode_message(&payload) {
+                        if let Some((type_name, payload, uuid)) = decode_message(&payload) {
+                            playback_clone.write().await.update(&type_name, &payload);
+                            if type_name == "PlaybackStatus" {
+                                status_changed_clone.notify_waiters();
+                            }
+
                             let channels = response_channels_clone.read().await;
                             if let Some(channel) = channels.get(&uuid) {
-                                let _ = channel.send(payload).await;
+                                let _ = channel.send((type_name, payload)).await;
                             }
                         }
                     }
@@ -63,8 +133,11 @@ impl WSConnection {
         });
 
         Ok(Self {
+            url,
             tx,
             response_channels,
+            playback,
+            status_changed,
         })
     }
 
@@ -82,20 +155,33 @@ impl WSConnection {
         }
 
         let payload = rinf::serialize(&request).with_context(|| "Failed to serialize request")?;
-        self.tx.send((type_name.to_string(), payload, uuid)).await?;
+        self.tx
+            .send(Outgoing::Message(type_name.to_string(), payload, uuid))
+            .await?;
 
-        let response = response_rx
-            .recv()
-            .await
-            .ok_or_else(|| anyhow!("No response received"))?;
-        let decoded = rinf::deserialize::<U>(&response[..])?;
+        let response = response_rx.recv().await;
 
         {
             let mut channels = self.response_channels.write().await;
             channels.remove(&uuid);
         }
 
-        Ok(decoded)
+        let (response_type, payload) = response.ok_or_else(|| anyhow!("No response received"))?;
+        // Errors of the server are shown as they are
+        if response_type == "CrashResponse" {
+            let crash = rinf::deserialize::<CrashResponse>(&payload)?;
+            bail!("{}", crash.detail);
+        }
+        if response_type == "PermissionDeniedResponse" {
+            let denied = rinf::deserialize::<PermissionDeniedResponse>(&payload)?;
+            bail!("{}", denied.detail);
+        }
+        if response_type == "UnsupportedRequestResponse" {
+            let unsupported = rinf::deserialize::<UnsupportedRequestResponse>(&payload)?;
+            bail!("{}", unsupported.detail);
+        }
+
+        Ok(rinf::deserialize::<U>(&payload[..])?)
     }
 
     pub async fn request_simple<T: DartSignal + Serialize>(
@@ -112,8 +198,19 @@ impl WSConnection {
         }
 
         let payload = rinf::serialize(&request).with_context(|| "Failed to serialize request")?;
-        self.tx.send((type_name.to_string(), payload, uuid)).await?;
+        self.tx
+            .send(Outgoing::Message(type_name.to_string(), payload, uuid))
+            .await?;
 
         Ok(())
     }
+
+    /// Waits until every request sent so far was written to the server
+    pub async fn flush(&self) -> Result<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.tx.send(Outgoing::Flush(done_tx)).await?;
+        done_rx
+            .await
+            .map_err(|_| anyhow!("The connection to the server was closed"))
+    }
 }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::{Result, anyhow};
 use colored::Colorize;
@@ -8,8 +9,8 @@ use colored::Colorize;
 use hub::messages::*;
 
 use crate::api::{
-    build_query, fetch_collection_group_summary, fetch_collection_groups, path_to_collection_type,
-    send_mix_query_request,
+    build_query, collection_type_to_root_dir, fetch_collection_group_summary,
+    fetch_collection_groups, path_to_collection_type, send_mix_query_request,
 };
 use crate::connection::WSConnection;
 
@@ -24,6 +25,15 @@ pub struct VirtualEntry {
 pub struct CacheEntry {
     pub entries: Vec<VirtualEntry>,
     pub collection_type: CollectionType,
+    /// When the entries were listed, to refresh stale ones
+    pub fetched_at: Instant,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub collection_type: CollectionType,
+    pub id: i32,
+    pub name: String,
 }
 
 pub struct VirtualFS {
@@ -31,6 +41,8 @@ pub struct VirtualFS {
     pub root_dirs: Vec<String>,
     pub cache: HashMap<PathBuf, CacheEntry>,
     pub connection: Arc<WSConnection>,
+    /// Results of the last search, referenced as `@1`, `@2`...
+    pub search_results: Vec<SearchResult>,
 }
 
 impl VirtualFS {
@@ -48,6 +60,7 @@ impl VirtualFS {
             root_dirs,
             cache: HashMap::new(),
             connection,
+            search_results: Vec::new(),
         }
     }
 
@@ -62,6 +75,7 @@ impl VirtualFS {
             CacheEntry {
                 entries,
                 collection_type,
+                fetched_at: Instant::now(),
             },
         );
     }
@@ -127,6 +141,94 @@ impl VirtualFS {
         Ok(None)
     }
 
+    /// The result of the last search a `@N` reference points to, `None` if
+    /// `reference` is a plain path
+    pub fn search_result(&self, reference: &str) -> Result<Option<SearchResult>> {
+        let Some(index) = reference.strip_prefix('@') else {
+            return Ok(None);
+        };
+        let index: usize = index
+            .parse()
+            .map_err(|_| anyhow!("Invalid search result reference: {reference}"))?;
+
+        self.search_results
+            .get(index.wrapping_sub(1))
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| anyhow!("No search result {reference}, run search first"))
+    }
+
+    /// The track a `@N` reference points to in the listing of the current
+    /// directory, ordered by name as `ls` shows it. `None` if `reference`
+    /// is not a reference or the directory lists no tracks.
+    pub fn listing_entry(&self, reference: &str) -> Result<Option<VirtualEntry>> {
+        let Some(index) = reference.strip_prefix('@') else {
+            return Ok(None);
+        };
+        let Some(cache_entry) = self.cache.get(&self.current_path) else {
+            return Ok(None);
+        };
+        if cache_entry.entries.iter().all(|x| x.is_directory) {
+            return Ok(None);
+        }
+
+        let index: usize = index
+            .parse()
+            .map_err(|_| anyhow!("Invalid listing reference: {reference}"))?;
+        let mut entries = cache_entry.entries.clone();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        entries
+            .into_iter()
+            .nth(index.wrapping_sub(1))
+            .map(Some)
+            .ok_or_else(|| anyhow!("No entry {reference} in the current directory"))
+    }
+
+    /// The path of a collection, caching its group so the collection can
+    /// be listed and queried once entered
+    pub async fn locate_collection(
+        &mut self,
+        collection_type: CollectionType,
+        id: i32,
+    ) -> Result<PathBuf> {
+        let root_dir = collection_type_to_root_dir(collection_type)
+            .ok_or_else(|| anyhow!("Invalid collection type: {}", collection_type.as_str()))?;
+        let (path, entry) = self
+            .find_entry_by_id_and_type(id, collection_type)
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "ID {} not found in {} collection",
+                    id,
+                    collection_type.as_str()
+                )
+            })?;
+        let group_title = path
+            .parent()
+            .and_then(|x| x.file_name())
+            .and_then(|x| x.to_str())
+            .ok_or_else(|| anyhow!("Invalid path structure"))?
+            .to_string();
+
+        let group_path = PathBuf::from("/").join(root_dir).join(&group_title);
+        let response =
+            fetch_collection_groups(collection_type, vec![group_title], &self.connection).await?;
+        let entries = response
+            .groups
+            .into_iter()
+            .flat_map(|group| group.collections)
+            .map(|collection| VirtualEntry {
+                name: collection.name,
+                id: Some(collection.id),
+                is_directory: true,
+            })
+            .collect();
+        self.cache_entries(group_path.clone(), entries, collection_type);
+
+        Ok(group_path.join(entry.name))
+    }
+
     fn get_collection_type_from_current_path(&self) -> Option<CollectionType> {
         if self.current_path == PathBuf::from("/") {
             None
@@ -348,7 +450,13 @@ impl VirtualFS {
     }
 
     pub async fn list_current_dir(&mut self) -> Result<Vec<VirtualEntry>> {
-        if self.current_path == Path::new("/") {
+        let path = self.current_path.clone();
+        self.list_dir(&path).await
+    }
+
+    /// Lists a directory of the library, caching its entries
+    pub async fn list_dir(&mut self, path: &Path) -> Result<Vec<VirtualEntry>> {
+        if path == Path::new("/") {
             return Ok(self
                 .root_dirs
                 .iter()
@@ -361,11 +469,9 @@ impl VirtualFS {
         }
 
         let collection_type =
-            path_to_collection_type(&self.current_path).ok_or_else(|| anyhow!("Invalid path"))?;
+            path_to_collection_type(path).ok_or_else(|| anyhow!("Invalid path"))?;
 
-        let entries = if self.current_path.components().count() == 2
-            && self.current_path.ends_with("Tracks")
-        {
+        let entries = if path.components().count() == 2 && path.ends_with("Tracks") {
             // Special handling for /Tracks directory - list files directly
             let query = vec![("lib::directory.deep".to_string(), "/".to_string())];
             let mix_response = send_mix_query_request(query, &self.connection).await?;
@@ -380,7 +486,7 @@ impl VirtualFS {
                 })
                 .collect::<Vec<_>>())
         } else {
-            match self.current_path.components().count() {
+            match path.components().count() {
                 // If we're at the root of a collection type (e.g., /Artists)
                 2 => {
                     // Skip group listing for Tracks
@@ -404,8 +510,7 @@ impl VirtualFS {
                 }
                 // If we're in a group (e.g., /Artists/:Group)
                 3 => {
-                    let group_title = self
-                        .current_path
+                    let group_title = path
                         .components()
                         .next_back()
                         .unwrap()
@@ -432,7 +537,7 @@ impl VirtualFS {
                         .collect::<Vec<_>>())
                 }
                 4 => {
-                    let queries = self.path_to_query(&self.current_path).await?;
+                    let queries = self.path_to_query(path).await?;
                     let mix_response = send_mix_query_request(queries, &self.connection).await?;
 
                     Ok(mix_response
@@ -449,10 +554,10 @@ impl VirtualFS {
             }
         };
 
-        if let Some(collection_type) = path_to_collection_type(&self.current_path)
+        if let Some(collection_type) = path_to_collection_type(path)
             && let Ok(ref entries) = entries
         {
-            self.cache_entries(self.current_path.clone(), entries.clone(), collection_type);
+            self.cache_entries(path.to_path_buf(), entries.clone(), collection_type);
         }
 
         entries
@@ -1,39 +1,67 @@
 use std::{
     borrow::Cow::{self, Borrowed, Owned},
+    collections::HashSet,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex, mpsc},
+    time::Duration,
 };
 
+use clap::CommandFactory;
+use clean_path::Clean;
+use radix_trie::{Trie, TrieCommon};
 use rustyline::{
-    completion::FilenameCompleter,
+    Context,
+    completion::{Completer, Pair},
     highlight::{Highlighter, MatchingBracketHighlighter},
     hint::Hinter,
     history::SearchDirection,
     validate::MatchingBracketValidator,
 };
-use rustyline_derive::{Completer, Helper, Validator};
-use tokio::sync::RwLock;
+use rustyline_derive::{Helper, Validator};
+use tokio::{runtime::Handle, sync::RwLock};
 
-use crate::fs::VirtualFS;
+use hub::messages::CollectionType;
 
-#[derive(Helper, Completer, Validator)]
+use crate::{
+    cli::{MIX_OPERATORS, ReplCommand},
+    fs::{VirtualEntry, VirtualFS},
+};
+
+/// How long completion waits for a directory missing from the cache, its
+/// entries being completed on a later Tab once listed
+const COMPLETION_TIMEOUT: Duration = Duration::from_millis(300);
+/// Listings older than this are refreshed in the background, the stale
+/// entries being completed meanwhile
+const LISTING_TTL: Duration = Duration::from_secs(60);
+
+/// Commands taking a path of the library
+const PATH_COMMANDS: &[&str] = &["cd", "opq", "get"];
+/// Commands taking a path of the library made of IDs
+const ID_PATH_COMMANDS: &[&str] = &["cdi", "opqi"];
+/// Commands taking a track
+const TRACK_COMMANDS: &[&str] = &["like", "unlike", "rate"];
+
+#[derive(Helper, Validator)]
 pub struct DIYHinter {
-    #[rustyline(Completer)]
-    completer: FilenameCompleter,
     highlighter: MatchingBracketHighlighter,
     #[rustyline(Validator)]
     validator: MatchingBracketValidator,
     colored_prompt: String,
+    /// Operators hinted while typing a mix query
+    mix_operators: Trie<String, ()>,
+    /// Directories being listed for completion
+    pending_listings: Arc<Mutex<HashSet<PathBuf>>>,
     pub fs: Arc<RwLock<VirtualFS>>,
 }
 
 impl DIYHinter {
     pub fn new(fs: Arc<RwLock<VirtualFS>>) -> Self {
         Self {
-            completer: FilenameCompleter::new(),
             highlighter: MatchingBracketHighlighter::new(),
             validator: MatchingBracketValidator::new(),
             colored_prompt: String::new(),
+            mix_operators: MIX_OPERATORS.iter().map(|x| (x.to_string(), ())).collect(),
+            pending_listings: Default::default(),
             fs,
         }
     }
@@ -62,6 +90,34 @@ impl DIYHinter {
         None
     }
 
+    // Complete the name of a command while the first word is being typed
+    fn find_matching_command(&self, partial_input: &str) -> Option<String> {
+        ReplCommand::command()
+            .get_subcommands()
+            .map(|x| x.get_name())
+            .find(|x| x.len() > partial_input.len() && x.starts_with(partial_input))
+            .map(|x| x[partial_input.len()..].to_string())
+    }
+
+    // Complete the operator being typed in the query of `mix`
+    fn find_matching_operator(&self, line: &str) -> Option<String> {
+        let query = line.strip_prefix("mix ")?;
+        let partial = query
+            .rsplit([';', ' ', '"', '\''])
+            .next()
+            .unwrap_or_default();
+        if partial.is_empty() || partial.contains(['=', '(']) {
+            return None;
+        }
+
+        self.mix_operators
+            .get_raw_descendant(partial)?
+            .keys()
+            .filter(|x| x.len() > partial.len() && x.starts_with(partial))
+            .min()
+            .map(|x| x[partial.len()..].to_string())
+    }
+
     // Parse the input line to extract current directory path and partial input
     fn parse_input(&self, line: &str) -> Option<(PathBuf, String)> {
         // Get last component from input as partial text
@@ -88,6 +144,242 @@ impl DIYHinter {
     }
 }
 
+/// Splits the line before the cursor into the words before the one being
+/// typed, unquoted, and where that word starts with its text unquoted.
+fn split_current_word(line: &str) -> (Vec<String>, usize, String) {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if start.is_none() && !c.is_whitespace() {
+            start = Some(i);
+        }
+
+        match (quote, c) {
+            _ if escaped => {
+                word.push(c);
+                escaped = false;
+            }
+            (None | Some('"'), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if start.take().is_some() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            (None, c) => word.push(c),
+        }
+    }
+
+    (words, start.unwrap_or(line.len()), word)
+}
+
+/// Quotes a completed word the way the command line is split
+fn quote_word(word: &str) -> String {
+    shlex::try_quote(word)
+        .map(|x| x.into_owned())
+        .unwrap_or_else(|_| word.to_string())
+}
+
+impl DIYHinter {
+    /// The entries of a directory: cached ones, refreshed in the background
+    /// once stale, or else listed for a brief while
+    fn listing(&self, dir: &Path) -> Vec<VirtualEntry> {
+        let cached = match self.fs.try_read() {
+            Ok(fs) if dir == Path::new("/") => {
+                return fs
+                    .root_dirs
+                    .iter()
+                    .map(|name| VirtualEntry {
+                        name: name.clone(),
+                        id: None,
+                        is_directory: true,
+                    })
+                    .collect();
+            }
+            Ok(fs) => fs
+                .cache
+                .get(dir)
+                .map(|x| (x.entries.clone(), x.fetched_at.elapsed() > LISTING_TTL)),
+            // A command is using the file system, don't wait for it
+            Err(_) => return Vec::new(),
+        };
+
+        match cached {
+            Some((entries, stale)) => {
+                if stale {
+                    self.fetch_listing(dir);
+                }
+                entries
+            }
+            None => self
+                .fetch_listing(dir)
+                .and_then(|x| x.recv_timeout(COMPLETION_TIMEOUT).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Lists a directory in the background, caching its entries. `None` if
+    /// the directory is already being listed.
+    fn fetch_listing(&self, dir: &Path) -> Option<mpsc::Receiver<Vec<VirtualEntry>>> {
+        let handle = Handle::try_current().ok()?;
+        if !self
+            .pending_listings
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf())
+        {
+            return None;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let fs = self.fs.clone();
+        let pending_listings = self.pending_listings.clone();
+        let dir = dir.to_path_buf();
+        handle.spawn(async move {
+            let entries = fs.write().await.list_dir(&dir).await;
+            pending_listings.lock().unwrap().remove(&dir);
+            if let Ok(entries) = entries {
+                let _ = tx.send(entries);
+            }
+        });
+
+        Some(rx)
+    }
+
+    fn complete_command(&self, partial: &str) -> Vec<Pair> {
+        ReplCommand::command()
+            .get_subcommands()
+            .map(|x| x.get_name())
+            .filter(|x| x.starts_with(partial))
+            .map(|x| Pair {
+                display: x.to_string(),
+                replacement: format!("{x} "),
+            })
+            .collect()
+    }
+
+    fn complete_search_results(&self, partial: &str, tracks_only: bool) -> Vec<Pair> {
+        let Ok(fs) = self.fs.try_read() else {
+            return Vec::new();
+        };
+
+        fs.search_results
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| !tracks_only || x.collection_type == CollectionType::Track)
+            .map(|(i, x)| (format!("@{}", i + 1), x))
+            .filter(|(reference, _)| reference.starts_with(partial))
+            .map(|(reference, x)| Pair {
+                display: format!("{reference} {}", x.name),
+                replacement: reference,
+            })
+            .collect()
+    }
+
+    fn complete_path(&self, partial: &str, ids: bool) -> Vec<Pair> {
+        if partial.starts_with('@') {
+            return self.complete_search_results(partial, false);
+        }
+
+        let Ok(current_path) = self.fs.try_read().map(|x| x.current_path.clone()) else {
+            return Vec::new();
+        };
+        let (dir_part, base) = match partial.rfind('/') {
+            Some(i) => partial.split_at(i + 1),
+            None => ("", partial),
+        };
+        let dir = if dir_part.starts_with('/') {
+            PathBuf::from(dir_part)
+        } else {
+            current_path.join(dir_part)
+        };
+
+        self.listing(&dir.clean())
+            .into_iter()
+            .filter_map(|entry| {
+                let (name, display) = match (ids, entry.id) {
+                    (true, Some(id)) => (id.to_string(), format!("{id} {}", entry.name)),
+                    _ => (entry.name.clone(), entry.name),
+                };
+                if !name.starts_with(base) {
+                    return None;
+                }
+
+                let suffix = if entry.is_directory { "/" } else { "" };
+                Some(Pair {
+                    display: format!("{display}{suffix}"),
+                    replacement: quote_word(&format!("{dir_part}{name}{suffix}")),
+                })
+            })
+            .collect()
+    }
+
+    /// Completes `@N` references and IDs of the tracks of the current
+    /// directory, or else `@N` references of the tracks found by the last
+    /// search, as `like` and `rate` resolve them
+    fn complete_track(&self, partial: &str) -> Vec<Pair> {
+        let Ok(current_path) = self.fs.try_read().map(|x| x.current_path.clone()) else {
+            return Vec::new();
+        };
+        let mut entries = self.listing(&current_path);
+        if entries.iter().all(|x| x.is_directory) {
+            return self.complete_search_results(partial, true);
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        entries
+            .into_iter()
+            .enumerate()
+            .filter(|(_, x)| !x.is_directory)
+            .filter_map(|(i, x)| {
+                let reference = format!("@{}", i + 1);
+                let id = x.id?.to_string();
+                let replacement = if partial.starts_with('@') || partial.is_empty() {
+                    reference.clone()
+                } else {
+                    id.clone()
+                };
+                replacement.starts_with(partial).then(|| Pair {
+                    display: format!("{reference} {id} {}", x.name),
+                    replacement,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Completer for DIYHinter {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (words, start, partial) = split_current_word(&line[..pos]);
+        if partial.starts_with('-') {
+            return Ok((start, Vec::new()));
+        }
+
+        let candidates = match words.first().map(String::as_str) {
+            None => self.complete_command(&partial),
+            Some(x) if PATH_COMMANDS.contains(&x) => self.complete_path(&partial, false),
+            Some(x) if ID_PATH_COMMANDS.contains(&x) => self.complete_path(&partial, true),
+            Some(x) if TRACK_COMMANDS.contains(&x) => self.complete_track(&partial),
+            Some(_) => Vec::new(),
+        };
+
+        Ok((start, candidates))
+    }
+}
+
 impl Hinter for DIYHinter {
     type Hint = String;
 
@@ -114,6 +406,15 @@ impl Hinter for DIYHinter {
             return Some(sr.entry.chars().skip(char_pos).collect());
         }
 
+        // Then command names, as long as no argument was typed
+        if !line.contains(' ') {
+            return self.find_matching_command(line);
+        }
+
+        if line.starts_with("mix ") {
+            return self.find_matching_operator(line);
+        }
+
         // If no history match, try filesystem-based completion
         if let Some((current_path, partial)) = self.parse_input(line) {
             return self.find_matching_entry(&current_path, &partial);
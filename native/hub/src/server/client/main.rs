@@ -8,28 +8,42 @@ pub mod repl;
 pub mod utils;
 pub mod verify;
 
-use std::{process::exit, sync::Arc};
+use std::{
+    process::exit,
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
 
 use anyhow::{Context, Result, anyhow, bail};
 use clap::Parser;
 use log::{error, info};
 use regex::Regex;
+use rpassword::prompt_password;
 use rustls::crypto::ring::default_provider;
 use tokio::{
     signal::ctrl_c,
     sync::{Mutex, RwLock},
+    time::{Instant, timeout_at},
 };
 use tracing_subscriber::EnvFilter;
 
-use ::discovery::{client::CertValidator, config::get_config_dir, protocol::DiscoveryService};
+use ::discovery::{
+    client::CertValidator,
+    config::get_config_dir,
+    endpoint::{DEFAULT_API_PORT, ServerEndpoint, normalize_path_prefix},
+    protocol::DiscoveryService,
+    url::decode_rnsrv_url,
+};
+
+use hub::server::utils::trust::{export_trust_bundle, import_trust_bundle};
 
-use cli::{Cli, DiscoveryCmd, RemoteCmd, ReplCommand};
+use cli::{Cli, DiscoveryCmd, RemoteCmd, RemoteExecArgs, ReplCommand};
 use connection::WSConnection;
 use editor::{EditorConfig, create_editor};
 use fs::VirtualFS;
 use utils::{
     AppState, get_fingerprint_by_index, print_certificate_table, print_device_details,
-    print_device_table,
+    print_device_table, print_trust_import_report,
 };
 use verify::{inspect_host, print_device_information, register_current_device, verify_servers};
 
@@ -45,11 +59,33 @@ async fn main() -> Result<()> {
 
     match cli {
         Cli::Repl(args) => repl_mode(&args.service_url).await,
+        Cli::RemoteExec(args) => remote_exec(args).await,
         Cli::Discovery(cmd) => handle_discovery_command(cmd).await,
         Cli::Remote(cmd) => handle_remote_command(cmd).await,
     }
 }
 
+/// Exit codes of `remote-exec`, invalid commands exiting with 2 as clap
+/// does
+const EXIT_COMMAND_FAILED: i32 = 1;
+const EXIT_UNREACHABLE: i32 = 3;
+
+/// Connects to the service, ready to run commands
+async fn connect(service_url: &str) -> Result<Arc<AppState>> {
+    let connection = Arc::new(WSConnection::connect(service_url.to_string()).await?);
+    let fs = Arc::new(RwLock::new(VirtualFS::new(connection)));
+
+    let config_dir = get_config_dir()?;
+    Ok(Arc::new(AppState {
+        fs,
+        validator: CertValidator::new(config_dir.join("certs")).await?,
+        discovery: Arc::new(Mutex::new(None)),
+        config_dir: config_dir.clone(),
+        muted_volume: Mutex::new(None),
+        failed: AtomicBool::new(false),
+    }))
+}
+
 async fn repl_mode(service_url: &str) -> Result<()> {
     let service_url = match validate_and_format_url(service_url) {
         Ok(x) => x,
@@ -64,25 +100,16 @@ async fn repl_mode(service_url: &str) -> Result<()> {
     println!("\nType 'help' to see available commands");
 
     let config = EditorConfig::default();
-    let connection = match WSConnection::connect(service_url.clone()).await {
+    let state = match connect(&service_url).await {
         Ok(x) => x,
         Err(e) => {
             error!("{e}");
             return Ok(());
         }
     };
-    let connection = Arc::new(connection);
-    let fs = Arc::new(RwLock::new(VirtualFS::new(connection)));
+    let fs = state.fs.clone();
     let mut editor = create_editor(config, fs.clone())?;
 
-    let config_dir = get_config_dir()?;
-    let state: Arc<AppState> = Arc::new(AppState {
-        fs: fs.clone(),
-        validator: CertValidator::new(config_dir.join("certs")).await?,
-        discovery: Arc::new(Mutex::new(None)),
-        config_dir: config_dir.clone(),
-    });
-
     loop {
         let state = state.clone();
         let current_dir = {
@@ -131,6 +158,46 @@ async fn repl_mode(service_url: &str) -> Result<()> {
     Ok(())
 }
 
+/// Runs a single command without the REPL, exiting with a code telling
+/// whether it succeeded
+async fn remote_exec(args: RemoteExecArgs) -> Result<()> {
+    let command = ReplCommand::parse_args(args.command).unwrap_or_else(|e| e.exit());
+    let service_url = validate_and_format_url(&args.service_url)?;
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+
+    let state = match timeout_at(deadline, connect(&service_url)).await {
+        Ok(Ok(x)) => x,
+        Ok(Err(e)) => {
+            eprintln!("Failed to connect to {service_url}: {e}");
+            exit(EXIT_UNREACHABLE);
+        }
+        Err(_) => {
+            eprintln!("Timed out connecting to {service_url}");
+            exit(EXIT_UNREACHABLE);
+        }
+    };
+
+    let result = timeout_at(deadline, async {
+        handle_repl_command(command, state.clone()).await?;
+        // Requests without a response are only queued until written
+        state.fs.read().await.connection.flush().await
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) if !state.has_failed() => Ok(()),
+        Ok(Ok(())) => exit(EXIT_COMMAND_FAILED),
+        Ok(Err(e)) => {
+            eprintln!("{e}");
+            exit(EXIT_COMMAND_FAILED);
+        }
+        Err(_) => {
+            eprintln!("Timed out waiting for {service_url}");
+            exit(EXIT_UNREACHABLE);
+        }
+    }
+}
+
 async fn handle_repl_command(command: ReplCommand, state: Arc<AppState>) -> Result<bool> {
     use ReplCommand::*;
 
@@ -138,6 +205,11 @@ async fn handle_repl_command(command: ReplCommand, state: Arc<AppState>) -> Resu
         Ls { long } => repl::handle_ls(state, long).await,
         Pwd => repl::handle_pwd(state).await,
         Cd { path, id } => repl::handle_cd(state, path, id).await,
+        Search {
+            query,
+            search_type,
+            limit,
+        } => repl::handle_search(state, query, search_type, limit).await,
         Opq {
             path,
             playback_mode,
@@ -150,6 +222,19 @@ async fn handle_repl_command(command: ReplCommand, state: Arc<AppState>) -> Resu
         Next => repl::handle_next(state).await,
         Previous => repl::handle_previous(state).await,
         SetMode { mode } => repl::handle_setmode(state, mode).await,
+        Like { track } => repl::handle_like(state, track, true).await,
+        Unlike { track } => repl::handle_like(state, track, false).await,
+        Rate { rating, track } => repl::handle_rate(state, rating, track).await,
+        Mix { mix, play, limit } => repl::handle_mix(state, mix, play, limit).await,
+        Mixes => repl::handle_mixes(state).await,
+        Get { target, output } => repl::handle_get(state, target, output).await,
+        Vol { volume } => repl::handle_vol(state, volume).await,
+        Mute => repl::handle_mute(state).await,
+        Unmute => repl::handle_unmute(state).await,
+        Seek { position } => repl::handle_seek(state, position).await,
+        Np { follow } => repl::handle_np(state, follow).await,
+        Lyrics { sync } => repl::handle_lyrics(state, sync).await,
+        Queue { page, page_size } => repl::handle_queue(state, page, page_size).await,
         Quit => Ok(false),
         Exit => Ok(false),
         // Handle aliases (should never reach here due to parse conversion)
@@ -271,6 +356,30 @@ async fn handle_remote_command(cmd: RemoteCmd) -> Result<()> {
         }
 
         RemoteCmd::SelfInfo => print_device_information().await,
+
+        RemoteCmd::Export {
+            path,
+            include_identity,
+        } => {
+            let password = prompt_password("Bundle password: ")?;
+            if password != prompt_password("Confirm password: ")? {
+                bail!("Passwords do not match");
+            }
+
+            let count =
+                export_trust_bundle(config_dir, &validator, &path, &password, include_identity)
+                    .await?;
+            info!("Exported {count} trusted devices to {}", path.display());
+            Ok(())
+        }
+
+        RemoteCmd::Import { path, identity } => {
+            let password = prompt_password("Bundle password: ")?;
+            let report =
+                import_trust_bundle(config_dir, &validator, &path, &password, identity).await?;
+            print_trust_import_report(&report);
+            Ok(())
+        }
     }
 }
 
@@ -289,11 +398,30 @@ fn setup_logging() -> Result<()> {
 }
 
 fn validate_and_format_url(input: &str) -> Result<String> {
-    let re = Regex::new(r"^(?P<host>[^:]+)(:(?P<port>\d+))?$").unwrap();
+    if input.starts_with("rnsrv://") {
+        let url = decode_rnsrv_url(input).map_err(|e| anyhow!("Invalid server URL: {e}"))?;
+        if !url.is_supported() {
+            bail!("Unsupported server protocol version {}", url.version);
+        }
+
+        let host = url
+            .hosts
+            .first()
+            .ok_or_else(|| anyhow!("Server URL contains no host"))?;
+        let endpoint = ServerEndpoint::parse(&host.to_spec())?;
+
+        return Ok(format!("ws://{}/ws", endpoint.authority()));
+    }
+
+    let re = Regex::new(r"^(?P<host>[^:/]+)(:(?P<port>\d+))?(?P<prefix>/.*)?$").unwrap();
 
     if let Some(caps) = re.captures(input) {
         let host = caps.name("host").unwrap().as_str();
-        let port = caps.name("port").map_or("7863", |m| m.as_str());
+        let port = caps
+            .name("port")
+            .map_or(Ok(DEFAULT_API_PORT), |m| m.as_str().parse::<u16>())
+            .map_err(|_| anyhow!("Invalid port: must be between 0 and 65535"))?;
+        let prefix = normalize_path_prefix(caps.name("prefix").map_or("", |m| m.as_str()));
 
         if !is_valid_host(host) {
             return Err(anyhow!(
@@ -301,7 +429,7 @@ fn validate_and_format_url(input: &str) -> Result<String> {
             ));
         }
 
-        Ok(format!("ws://{host}:{port}/ws"))
+        Ok(format!("ws://{host}:{port}{prefix}/ws"))
     } else {
         Err(anyhow!("Invalid URL format"))
     }
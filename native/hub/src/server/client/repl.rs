@@ -1,35 +1,63 @@
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
 use clean_path::Clean;
 use colored::*;
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use unicode_width::UnicodeWidthStr;
+use url::Url;
+
+use fsio::FsIo;
+use hub::messages::{
+    CollectionType, InLibraryPlayingItem, LyricContentLine, MediaFile, PlaybackStatus,
+    PlayingItemRequest, PlaylistItem,
+};
+use hub::utils::download::{DownloadConfig, download_file, partial_download_url};
 
 use crate::api::{
-    operate_playback_with_mix_query_request, send_next_request, send_pause_request,
-    send_play_request, send_previous_request, send_set_playback_mode_request,
+    build_collection_query, fetch_all_mixes, fetch_collections_by_ids, fetch_lyrics,
+    fetch_media_files_by_ids, fetch_mix_queries, fetch_playback_queue,
+    operate_playback_with_mix_query_request, send_mix_query_request, send_next_request,
+    send_pause_request, send_play_request, send_previous_request, send_relative_volume_request,
+    send_search_request, send_seek_request, send_set_liked_request, send_set_playback_mode_request,
+    send_set_rating_request, send_volume_request,
 };
-use crate::fs::VirtualEntry;
+use crate::cli::{MixSource, OperateMode, PlaybackMode, SearchType, SeekPosition, VolumeChange};
+use crate::connection::WSConnection;
+use crate::fs::{SearchResult, VirtualEntry, VirtualFS};
 use crate::utils::AppState;
 
 pub async fn handle_ls(state: Arc<AppState>, long: bool) -> Result<bool> {
     let mut fs = state.fs.write().await;
     match fs.list_current_dir().await {
         Ok(entries) => {
+            let mut entries = entries;
+            // `@N` references count entries in this order
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+
             if long {
-                for entry in entries {
+                for (i, entry) in entries.into_iter().enumerate() {
                     let entry_type = if entry.is_directory { "DIR" } else { "FILE" };
                     let id_str = entry.id.map(|id| format!(" [{id}]")).unwrap_or_default();
-                    println!("{:<4} {}{}", entry_type, id_str, entry.name);
+                    println!(
+                        "{:<5} {:<4}{} {}",
+                        format!("@{}", i + 1),
+                        entry_type,
+                        id_str,
+                        entry.name
+                    );
                 }
             } else {
-                let mut entries = entries;
-                entries.sort_by(|a, b| a.name.cmp(&b.name));
                 print_entries_grid(entries);
             }
         }
-        Err(e) => eprintln!("Error listing directory: {e}"),
+        Err(e) => state.fail(format!("Error listing directory: {e}")),
     }
     Ok(true)
 }
@@ -81,6 +109,30 @@ pub async fn handle_pwd(state: Arc<AppState>) -> Result<bool> {
 
 pub async fn handle_cd(state: Arc<AppState>, path: String, id: bool) -> Result<bool> {
     let mut fs = state.fs.write().await;
+
+    match fs.search_result(&path) {
+        Ok(Some(result)) => {
+            if result.collection_type == CollectionType::Track {
+                println!("{} is a track, use `opq {path}` to play it", result.name);
+                return Ok(true);
+            }
+
+            match fs
+                .locate_collection(result.collection_type, result.id)
+                .await
+            {
+                Ok(new_path) => fs.current_path = new_path,
+                Err(e) => state.fail(format!("Error locating {path}: {e}")),
+            }
+            return Ok(true);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            state.fail(e);
+            return Ok(true);
+        }
+    }
+
     let new_path = if id {
         fs.resolve_path_with_ids(&path).await?
     } else {
@@ -93,11 +145,11 @@ pub async fn handle_cd(state: Arc<AppState>, path: String, id: bool) -> Result<b
             Ok(true)
         }
         Ok(false) => {
-            println!("Directory not found: {path}");
+            state.fail(format!("Directory not found: {path}"));
             Ok(true)
         }
         Err(e) => {
-            println!("Error validating path: {e}");
+            state.fail(format!("Error validating path: {e}"));
             Ok(true)
         }
     }
@@ -121,15 +173,24 @@ pub async fn handle_opq(
     id: bool,
 ) -> Result<bool> {
     let mut fs = state.fs.write().await;
-    let mut path_obj = fs.current_path.join(&path).clean();
 
-    if id {
-        path_obj = fs
-            .resolve_path_with_ids(&path_obj.to_string_lossy())
-            .await?;
-    }
+    let queries = match fs.search_result(&path) {
+        Ok(Some(result)) => search_result_to_query(&result),
+        Ok(None) => {
+            let mut path_obj = fs.current_path.join(&path).clean();
+
+            if id {
+                path_obj = fs
+                    .resolve_path_with_ids(&path_obj.to_string_lossy())
+                    .await?;
+            }
 
-    match fs.path_to_query(&path_obj).await {
+            fs.path_to_query(&path_obj).await
+        }
+        Err(e) => Err(e),
+    };
+
+    match queries {
         Ok(queries) => match operate_playback_with_mix_query_request(
             queries,
             playback_mode,
@@ -140,9 +201,108 @@ pub async fn handle_opq(
         .await
         {
             Ok(_) => println!("Successfully updated playback queue"),
-            Err(e) => eprintln!("Failed to update playback queue: {e}"),
+            Err(e) => state.fail(format!("Failed to update playback queue: {e}")),
         },
-        Err(e) => eprintln!("Error creating query from path: {e}"),
+        Err(e) => state.fail(format!("Error creating query from path: {e}")),
+    }
+    Ok(true)
+}
+
+fn search_result_to_query(result: &SearchResult) -> Result<Vec<(String, String)>> {
+    if result.collection_type == CollectionType::Track {
+        Ok(vec![("lib::track".to_string(), result.id.to_string())])
+    } else {
+        build_collection_query(result.collection_type, result.id)
+    }
+}
+
+/// The names of the results of a search, keyed by ID
+async fn fetch_result_names(
+    collection_type: CollectionType,
+    ids: Vec<i32>,
+    connection: &WSConnection,
+) -> Result<HashMap<i32, String>> {
+    Ok(if collection_type == CollectionType::Track {
+        fetch_media_files_by_ids(ids, connection)
+            .await?
+            .media_files
+            .into_iter()
+            .map(|file| (file.id, file.title))
+            .collect()
+    } else {
+        fetch_collections_by_ids(collection_type, ids, connection)
+            .await?
+            .result
+            .into_iter()
+            .map(|collection| (collection.id, collection.name))
+            .collect()
+    })
+}
+
+async fn search(
+    fs: &mut VirtualFS,
+    query: String,
+    search_type: Option<SearchType>,
+    limit: i32,
+) -> Result<()> {
+    let fields = search_type
+        .map(|x| vec![x.field().to_string()])
+        .unwrap_or_default();
+    let response = send_search_request(query, fields, limit, &fs.connection).await?;
+
+    let groups = [
+        ("Artists", CollectionType::Artist, response.artists),
+        ("Albums", CollectionType::Album, response.albums),
+        ("Tracks", CollectionType::Track, response.tracks),
+        ("Playlists", CollectionType::Playlist, response.playlists),
+    ];
+
+    let mut results = Vec::new();
+    for (title, collection_type, ids) in groups {
+        if ids.is_empty() {
+            continue;
+        }
+
+        let mut names = fetch_result_names(collection_type, ids.clone(), &fs.connection).await?;
+
+        println!("{}", title.yellow().bold());
+        // Keep the order of the search, best matches first
+        for id in ids {
+            let Some(name) = names.remove(&id) else {
+                continue;
+            };
+
+            println!(
+                "    {} {} {}",
+                format!("@{}", results.len() + 1).red().bold(),
+                format!("[{id}]").yellow(),
+                name
+            );
+            results.push(SearchResult {
+                collection_type,
+                id,
+                name,
+            });
+        }
+    }
+
+    if results.is_empty() {
+        println!("No results");
+    }
+    fs.search_results = results;
+
+    Ok(())
+}
+
+pub async fn handle_search(
+    state: Arc<AppState>,
+    query: Vec<String>,
+    search_type: Option<SearchType>,
+    limit: i32,
+) -> Result<bool> {
+    let mut fs = state.fs.write().await;
+    if let Err(e) = search(&mut fs, query.join(" "), search_type, limit).await {
+        state.fail(format!("Error searching: {e}"));
     }
     Ok(true)
 }
@@ -176,3 +336,901 @@ pub async fn handle_setmode(state: Arc<AppState>, mode: crate::cli::PlaybackMode
     send_set_playback_mode_request(mode, &fs.connection).await?;
     Ok(true)
 }
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn playback_mode_name(mode: u32) -> &'static str {
+    match mode {
+        0 => "sequential",
+        1 => "repeat one",
+        2 => "repeat all",
+        3 => "shuffle",
+        _ => "unknown",
+    }
+}
+
+fn format_volume(volume: Option<f32>) -> String {
+    volume
+        .map(|x| format!("{:.0}%", x * 100.0))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// A single line summary of the playback, for `np --follow`
+fn now_playing_line(status: &PlaybackStatus, volume: Option<f32>) -> String {
+    format!(
+        "[{}] {} - {} ({}) {}/{} | {} | volume {}",
+        status.state,
+        status.title.as_deref().unwrap_or("Unknown Title"),
+        status.artist.as_deref().unwrap_or("Unknown Artist"),
+        status.album.as_deref().unwrap_or("Unknown Album"),
+        format_duration(status.progress_seconds as f64),
+        format_duration(status.duration),
+        playback_mode_name(status.playback_mode),
+        format_volume(volume),
+    )
+}
+
+fn print_now_playing(status: &PlaybackStatus, volume: Option<f32>) {
+    println!(
+        "{:<10} {}",
+        "Title:".yellow().bold(),
+        status.title.as_deref().unwrap_or("Unknown Title").cyan()
+    );
+    println!(
+        "{:<10} {}",
+        "Artist:".yellow().bold(),
+        status.artist.as_deref().unwrap_or("Unknown Artist")
+    );
+    println!(
+        "{:<10} {}",
+        "Album:".yellow().bold(),
+        status.album.as_deref().unwrap_or("Unknown Album")
+    );
+    println!(
+        "{:<10} {} / {}",
+        "Position:".yellow().bold(),
+        format_duration(status.progress_seconds as f64),
+        format_duration(status.duration)
+    );
+    println!("{:<10} {}", "State:".yellow().bold(), status.state);
+    println!(
+        "{:<10} {}",
+        "Mode:".yellow().bold(),
+        playback_mode_name(status.playback_mode)
+    );
+    println!(
+        "{:<10} {}",
+        "Volume:".yellow().bold(),
+        format_volume(volume)
+    );
+}
+
+/// Redraws the playback line until Enter is pressed
+async fn follow_now_playing(connection: &WSConnection) -> Result<()> {
+    let mut enter = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)
+    });
+    let mut ticker = tokio::time::interval(Duration::from_millis(250));
+
+    println!("{}", "Press Enter to stop following".bright_black());
+    loop {
+        tokio::select! {
+            _ = &mut enter => break,
+            _ = ticker.tick() => {
+                let playback = connection.playback.read().await;
+                let line = match &playback.status {
+                    Some(status) => now_playing_line(status, playback.volume),
+                    None => "Waiting for the playback status".to_string(),
+                };
+                let width = term_size::dimensions().map(|(w, _)| w).unwrap_or(80);
+                let line: String = line.chars().take(width.saturating_sub(1)).collect();
+
+                print!("\r\x1b[2K{line}");
+                std::io::stdout().flush()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// How long `seek` waits for the status confirming the new position
+const SEEK_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub async fn handle_seek(state: Arc<AppState>, position: SeekPosition) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+
+    let (progress, duration) = {
+        let playback = connection.playback.read().await;
+        match &playback.status {
+            Some(status) => (Some(status.progress_seconds as f64), Some(status.duration)),
+            None => (None, None),
+        }
+    };
+
+    let target = match (position, progress) {
+        (SeekPosition::Absolute(x), _) => x,
+        (SeekPosition::Relative(x), Some(progress)) => progress + x,
+        (SeekPosition::Relative(_), None) => {
+            println!("No playback status received yet, only absolute positions can be used");
+            return Ok(true);
+        }
+    };
+
+    let clamped = match duration {
+        Some(duration) if duration > 0.0 => target.clamp(0.0, duration),
+        _ => target.max(0.0),
+    };
+    if clamped != target {
+        let note = if target < 0.0 {
+            "Before the start of the track, seeking to 0:00".to_string()
+        } else {
+            format!(
+                "Past the end of the track, seeking to {}",
+                format_duration(clamped)
+            )
+        };
+        println!("{}", note.bright_black());
+    }
+
+    // Registered before the request so the status following it isn't missed
+    let status_changed = connection.status_changed.clone();
+    let notified = status_changed.notified();
+
+    if let Err(e) = send_seek_request(clamped, &connection).await {
+        state.fail(format!("Failed to seek: {e}"));
+        return Ok(true);
+    }
+
+    match tokio::time::timeout(SEEK_CONFIRMATION_TIMEOUT, notified).await {
+        Ok(()) => {
+            let playback = connection.playback.read().await;
+            if let Some(status) = &playback.status {
+                println!(
+                    "Position: {}/{}",
+                    format_duration(status.progress_seconds as f64),
+                    format_duration(status.duration)
+                );
+            }
+        }
+        Err(_) => println!(
+            "Seek requested to {}, no status confirmed it yet",
+            format_duration(clamped)
+        ),
+    }
+    Ok(true)
+}
+
+pub async fn handle_np(state: Arc<AppState>, follow: bool) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+
+    if follow {
+        follow_now_playing(&connection).await?;
+        return Ok(true);
+    }
+
+    let playback = connection.playback.read().await;
+    match &playback.status {
+        Some(status) => print_now_playing(status, playback.volume),
+        None => println!("No playback status received yet, the server reports it while playing"),
+    }
+    Ok(true)
+}
+
+/// The play queue and the index of the current track in it
+async fn playback_queue(connection: &WSConnection) -> Result<(Vec<PlaylistItem>, Option<usize>)> {
+    let retained = {
+        let playback = connection.playback.read().await;
+        playback.queue.clone().map(|queue| {
+            let index = playback
+                .status
+                .as_ref()
+                .and_then(|x| x.index)
+                .map(|x| x as usize);
+            (queue, index)
+        })
+    };
+    if let Some(x) = retained {
+        return Ok(x);
+    }
+
+    // Nothing was broadcasted since the connection opened, ask for the queue
+    let response = fetch_playback_queue(connection).await?;
+    connection.playback.write().await.queue = Some(response.items.clone());
+    Ok((response.items, response.index.map(|x| x as usize)))
+}
+
+pub async fn handle_queue(
+    state: Arc<AppState>,
+    page: Option<usize>,
+    page_size: usize,
+) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+
+    let (queue, index) = match playback_queue(&connection).await {
+        Ok(x) => x,
+        Err(e) => {
+            state.fail(format!("Error fetching the play queue: {e}"));
+            return Ok(true);
+        }
+    };
+
+    if queue.is_empty() {
+        println!("The play queue is empty");
+        return Ok(true);
+    }
+
+    let page_size = page_size.max(1);
+    let pages = queue.len().div_ceil(page_size);
+    let page = page
+        .unwrap_or_else(|| index.map(|x| x / page_size + 1).unwrap_or(1))
+        .clamp(1, pages);
+
+    let start = (page - 1) * page_size;
+    for (i, item) in queue.iter().enumerate().skip(start).take(page_size) {
+        let marker = if Some(i) == index { "→" } else { " " };
+        let line = format!(
+            "{} {:>4} {} - {} ({})",
+            marker,
+            i + 1,
+            item.title,
+            item.artist,
+            format_duration(item.duration)
+        );
+        if Some(i) == index {
+            println!("{}", line.green().bold());
+        } else {
+            println!("{line}");
+        }
+    }
+    println!(
+        "{}",
+        format!("Page {page}/{pages}, {} tracks", queue.len()).bright_black()
+    );
+
+    Ok(true)
+}
+
+pub async fn handle_vol(state: Arc<AppState>, volume: Option<VolumeChange>) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+
+    let result = match volume {
+        None => send_relative_volume_request(0.0, &connection).await,
+        Some(VolumeChange::Absolute(volume)) => send_volume_request(volume, &connection).await,
+        Some(VolumeChange::Relative(delta)) => {
+            send_relative_volume_request(delta, &connection).await
+        }
+    };
+
+    match result {
+        Ok(volume) => {
+            // Changing the volume by hand forgets the one `unmute` restores
+            if volume > 0.0 {
+                *state.muted_volume.lock().await = None;
+            }
+            println!("Volume: {}", format_volume(Some(volume)));
+        }
+        Err(e) => state.fail(format!("Failed to change the volume: {e}")),
+    }
+    Ok(true)
+}
+
+pub async fn handle_mute(state: Arc<AppState>) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+    let mut muted_volume = state.muted_volume.lock().await;
+
+    if muted_volume.is_some() {
+        println!("Already muted");
+        return Ok(true);
+    }
+
+    let volume = match send_relative_volume_request(0.0, &connection).await {
+        Ok(x) => x,
+        Err(e) => {
+            state.fail(format!("Failed to read the volume: {e}"));
+            return Ok(true);
+        }
+    };
+
+    match send_volume_request(0.0, &connection).await {
+        Ok(x) => {
+            *muted_volume = Some(volume);
+            println!("Muted, volume: {}", format_volume(Some(x)));
+        }
+        Err(e) => state.fail(format!("Failed to mute: {e}")),
+    }
+    Ok(true)
+}
+
+pub async fn handle_unmute(state: Arc<AppState>) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+    let mut muted_volume = state.muted_volume.lock().await;
+
+    let Some(volume) = *muted_volume else {
+        println!("Not muted");
+        return Ok(true);
+    };
+
+    match send_volume_request(volume, &connection).await {
+        Ok(x) => {
+            *muted_volume = None;
+            println!("Unmuted, volume: {}", format_volume(Some(x)));
+        }
+        Err(e) => state.fail(format!("Failed to unmute: {e}")),
+    }
+    Ok(true)
+}
+
+/// The lyrics of the track at `index` in the play queue
+struct TrackLyrics {
+    index: usize,
+    title: String,
+    artist: String,
+    lines: Vec<LyricContentLine>,
+}
+
+impl TrackLyrics {
+    /// Plain-text lyrics come with every line starting at zero
+    fn is_synced(&self) -> bool {
+        self.lines.iter().any(|x| x.start_time > 0)
+    }
+}
+
+fn lyric_line_text(line: &LyricContentLine) -> String {
+    line.sections
+        .iter()
+        .map(|x| x.content.as_str())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+async fn current_track_lyrics(connection: &WSConnection) -> Result<Option<TrackLyrics>> {
+    let (queue, index) = playback_queue(connection).await?;
+    let Some((index, item)) = index.and_then(|i| queue.into_iter().nth(i).map(|x| (i, x))) else {
+        return Ok(None);
+    };
+
+    let response = fetch_lyrics(item.item, connection).await?;
+    Ok(Some(TrackLyrics {
+        index,
+        title: item.title,
+        artist: item.artist,
+        lines: response.lines,
+    }))
+}
+
+fn print_lyrics_header(lyrics: &TrackLyrics, line_end: &str) {
+    print!(
+        "{}{line_end}",
+        format!("{} - {}", lyrics.title, lyrics.artist).bold()
+    );
+    if lyrics.lines.is_empty() {
+        print!("{}{line_end}", "No lyrics for this track".bright_black());
+    }
+}
+
+/// Keeps the terminal in raw mode, so single key presses can be read, until
+/// dropped
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enable() -> Result<Self> {
+        enable_raw_mode()?;
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Blocks until a key is pressed, or until `stop` is set so no key press
+/// meant for the editor is swallowed afterwards
+fn wait_for_key(stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match event::poll(Duration::from_millis(100)) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => return,
+                Ok(_) => {}
+                Err(_) => return,
+            },
+            Ok(false) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+/// Prints the lines of the lyrics as the playback reaches them, following
+/// the next tracks, until a key is pressed
+async fn follow_lyrics(connection: &WSConnection, mut lyrics: TrackLyrics) -> Result<()> {
+    println!("{}", "Press any key to stop following".bright_black());
+
+    // The editor is not reading while a command runs, so the terminal is
+    // ours until the guard restores it
+    let _raw_mode = RawModeGuard::enable()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut key = tokio::task::spawn_blocking({
+        let stop = stop.clone();
+        move || wait_for_key(stop)
+    });
+    let mut ticker = tokio::time::interval(Duration::from_millis(100));
+    let mut current_line: Option<usize> = None;
+
+    let result = loop {
+        tokio::select! {
+            _ = &mut key => break Ok(()),
+            _ = ticker.tick() => {
+                let (progress, index) = {
+                    let playback = connection.playback.read().await;
+                    match &playback.status {
+                        Some(status) => (status.progress_seconds, status.index),
+                        None => continue,
+                    }
+                };
+
+                if let Some(index) = index
+                    && index as usize != lyrics.index
+                {
+                    match current_track_lyrics(connection).await {
+                        Ok(Some(x)) => {
+                            lyrics = x;
+                            current_line = None;
+                            print!("\r\n");
+                            print_lyrics_header(&lyrics, "\r\n");
+                        }
+                        Ok(None) => {
+                            lyrics.index = index as usize;
+                            lyrics.lines.clear();
+                        }
+                        Err(e) => break Err(e),
+                    }
+                }
+
+                let position = (progress * 1000.0) as i32;
+                let line = lyrics.lines.iter().rposition(|x| x.start_time <= position);
+                if line != current_line {
+                    if let Some(line) = line {
+                        let text = lyric_line_text(&lyrics.lines[line]);
+                        print!("{}\r\n", text.green().bold());
+                    }
+                    current_line = line;
+                }
+                if let Err(e) = std::io::stdout().flush() {
+                    break Err(e.into());
+                }
+            }
+        }
+    };
+
+    stop.store(true, Ordering::Relaxed);
+    result
+}
+
+pub async fn handle_lyrics(state: Arc<AppState>, sync: bool) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+
+    let lyrics = match current_track_lyrics(&connection).await {
+        Ok(Some(x)) => x,
+        Ok(None) => {
+            println!("Nothing is playing");
+            return Ok(true);
+        }
+        Err(e) => {
+            state.fail(format!("Error fetching the lyrics: {e}"));
+            return Ok(true);
+        }
+    };
+
+    if sync && lyrics.is_synced() {
+        if let Err(e) = follow_lyrics(&connection, lyrics).await {
+            state.fail(format!("Error following the lyrics: {e}"));
+        }
+        return Ok(true);
+    }
+
+    print_lyrics_header(&lyrics, "\n");
+    if sync && !lyrics.lines.is_empty() {
+        println!("{}", "The lyrics are not synced".bright_black());
+    }
+    for line in &lyrics.lines {
+        println!("{}", lyric_line_text(line));
+    }
+    Ok(true)
+}
+
+fn in_library_item(file_id: i32) -> PlayingItemRequest {
+    PlayingItemRequest {
+        in_library: Some(InLibraryPlayingItem { file_id }),
+        independent_file: None,
+        online: None,
+    }
+}
+
+/// The item a `like` or `rate` command targets and its name: the track
+/// being played, a track ID, or a `@N` reference
+async fn resolve_track(
+    state: &AppState,
+    track: Option<String>,
+) -> Result<(PlayingItemRequest, String)> {
+    let fs = state.fs.read().await;
+
+    let Some(track) = track else {
+        let (queue, index) = playback_queue(&fs.connection).await?;
+        return index
+            .and_then(|i| queue.into_iter().nth(i))
+            .map(|x| (x.item, x.title))
+            .ok_or_else(|| anyhow!("Nothing is playing"));
+    };
+
+    if let Some(entry) = fs.listing_entry(&track)? {
+        return match (entry.is_directory, entry.id) {
+            (false, Some(id)) => Ok((in_library_item(id), entry.name)),
+            _ => Err(anyhow!("{} is not a track", entry.name)),
+        };
+    }
+    if let Some(result) = fs.search_result(&track)? {
+        if result.collection_type != CollectionType::Track {
+            bail!("{} is not a track", result.name);
+        }
+        return Ok((in_library_item(result.id), result.name));
+    }
+
+    let id: i32 = track
+        .parse()
+        .map_err(|_| anyhow!("Invalid track: {track}, expected an ID or @N"))?;
+    let name = fetch_media_files_by_ids(vec![id], &fs.connection)
+        .await?
+        .media_files
+        .into_iter()
+        .next()
+        .map(|x| x.title)
+        .ok_or_else(|| anyhow!("Track {id} not found"))?;
+
+    Ok((in_library_item(id), name))
+}
+
+pub async fn handle_like(state: Arc<AppState>, track: Option<String>, liked: bool) -> Result<bool> {
+    let (item, name) = match resolve_track(&state, track).await {
+        Ok(x) => x,
+        Err(e) => {
+            state.fail(e);
+            return Ok(true);
+        }
+    };
+
+    let connection = state.fs.read().await.connection.clone();
+    match send_set_liked_request(item, liked, &connection).await {
+        Ok(response) if response.success => {
+            let status = if response.liked { "Liked" } else { "Unliked" };
+            println!("{status}: {name}");
+        }
+        Ok(_) => state.fail(format!("{name} is not in the library and can't be liked")),
+        Err(e) => state.fail(format!("Failed to update {name}: {e}")),
+    }
+    Ok(true)
+}
+
+pub async fn handle_rate(state: Arc<AppState>, rating: u8, track: Option<String>) -> Result<bool> {
+    let (item, name) = match resolve_track(&state, track).await {
+        Ok(x) => x,
+        Err(e) => {
+            state.fail(e);
+            return Ok(true);
+        }
+    };
+
+    let rating = (rating > 0).then_some(rating as i32);
+    let connection = state.fs.read().await.connection.clone();
+    match send_set_rating_request(item, rating, &connection).await {
+        Ok(response) if response.success => match response.rating {
+            Some(rating) => println!("Rated {name}: {}", "★".repeat(rating as usize)),
+            None => println!("Cleared the rating of {name}"),
+        },
+        Ok(_) => state.fail(format!("{name} is not in the library and can't be rated")),
+        Err(e) => state.fail(format!("Failed to rate {name}: {e}")),
+    }
+    Ok(true)
+}
+
+/// Built-in mixes are named with a leading zero-width space
+fn mix_display_name(name: &str) -> &str {
+    name.trim_start_matches('\u{200B}')
+}
+
+pub async fn handle_mixes(state: Arc<AppState>) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+
+    match fetch_all_mixes(&connection).await {
+        Ok(response) if response.mixes.is_empty() => println!("No saved mixes"),
+        Ok(response) => {
+            for mix in response.mixes {
+                println!(
+                    "{} {} {}",
+                    format!("[{}]", mix.id).yellow(),
+                    mix_display_name(&mix.name),
+                    format!("({})", mix_display_name(&mix.group)).bright_black()
+                );
+            }
+        }
+        Err(e) => state.fail(format!("Error fetching the mixes: {e}")),
+    }
+    Ok(true)
+}
+
+async fn run_mix(
+    connection: &WSConnection,
+    mix: MixSource,
+    play: bool,
+    limit: usize,
+) -> Result<()> {
+    let queries = match mix {
+        MixSource::Saved(id) => fetch_mix_queries(id, connection).await?,
+        MixSource::Query(queries) => queries,
+    };
+
+    let response = send_mix_query_request(queries.clone(), connection).await?;
+    if response.files.is_empty() {
+        println!("The mix has no tracks");
+        return Ok(());
+    }
+
+    for file in response.files.iter().take(limit) {
+        println!(
+            "{} {} - {} ({})",
+            format!("[{}]", file.id).yellow(),
+            file.title,
+            file.artist,
+            format_duration(file.duration)
+        );
+    }
+    if response.files.len() > limit {
+        println!(
+            "{}",
+            format!("... and {} more tracks", response.files.len() - limit).bright_black()
+        );
+    }
+
+    if play {
+        operate_playback_with_mix_query_request(
+            queries,
+            PlaybackMode::NoChange,
+            true,
+            OperateMode::Replace,
+            connection,
+        )
+        .await?;
+        println!("Playing {} tracks", response.files.len());
+    }
+
+    Ok(())
+}
+
+pub async fn handle_mix(
+    state: Arc<AppState>,
+    mix: MixSource,
+    play: bool,
+    limit: usize,
+) -> Result<bool> {
+    let connection = state.fs.read().await.connection.clone();
+
+    if let Err(e) = run_mix(&connection, mix, play, limit).await {
+        state.fail(format!("Error running the mix: {e}"));
+    }
+    Ok(true)
+}
+
+/// Width of the progress bar of downloads, in characters
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// The tracks a `get` command targets, with the name of the collection
+/// they belong to when it targets one
+async fn resolve_download(
+    fs: &mut VirtualFS,
+    target: &str,
+) -> Result<(Vec<MediaFile>, Option<String>)> {
+    if let Some(entry) = fs.listing_entry(target)? {
+        return match (entry.is_directory, entry.id) {
+            (false, Some(id)) => Ok((media_files_by_id(id, &fs.connection).await?, None)),
+            _ => {
+                let path = fs.current_path.join(&entry.name);
+                path_files(fs, &path).await
+            }
+        };
+    }
+    if let Some(result) = fs.search_result(target)? {
+        if result.collection_type == CollectionType::Track {
+            return Ok((media_files_by_id(result.id, &fs.connection).await?, None));
+        }
+        let queries = build_collection_query(result.collection_type, result.id)?;
+        let files = send_mix_query_request(queries, &fs.connection).await?.files;
+        return Ok((files, Some(result.name)));
+    }
+    if let Ok(id) = target.parse::<i32>() {
+        return Ok((media_files_by_id(id, &fs.connection).await?, None));
+    }
+
+    let path = fs.current_path.join(target).clean();
+    path_files(fs, &path).await
+}
+
+async fn media_files_by_id(id: i32, connection: &WSConnection) -> Result<Vec<MediaFile>> {
+    let files = fetch_media_files_by_ids(vec![id], connection)
+        .await?
+        .media_files;
+    if files.is_empty() {
+        bail!("Track {id} not found");
+    }
+
+    Ok(files)
+}
+
+/// The tracks of a collection of the library, or the track, at a path
+async fn path_files(fs: &mut VirtualFS, path: &Path) -> Result<(Vec<MediaFile>, Option<String>)> {
+    let depth = path.components().count();
+    let is_track = depth == 5 || (depth == 3 && path.starts_with("/Tracks"));
+    if depth != 4 && !is_track {
+        bail!("{} is not a track nor a collection", path.display());
+    }
+
+    // Entries are found in the listing of their parent
+    if let Some(parent) = path.parent()
+        && !fs.cache.contains_key(parent)
+    {
+        fs.list_dir(parent).await?;
+    }
+
+    let queries = fs.path_to_query(path).await?;
+    let files = send_mix_query_request(queries, &fs.connection).await?.files;
+    let collection = path
+        .file_name()
+        .filter(|_| !is_track)
+        .map(|x| x.to_string_lossy().into_owned());
+
+    Ok((files, collection))
+}
+
+/// Where the server serves a media file, next to its WebSocket endpoint
+fn media_file_url(service_url: &str, file_id: i32) -> Result<Url> {
+    let mut url = Url::parse(service_url)?;
+    url.set_scheme("https")
+        .map_err(|_| anyhow!("Invalid service URL: {service_url}"))?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("Invalid service URL: {service_url}"))?
+        .pop()
+        .extend(["media", "file", &file_id.to_string()]);
+
+    Ok(url)
+}
+
+/// Makes a name safe to use as a file name on every platform
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|x| match x {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            x if x.is_control() => '_',
+            x => x,
+        })
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+/// The file a track is downloaded to: the one an interrupted download of it
+/// left behind, or else the first free one, numbered on collisions
+async fn download_destination(fsio: &FsIo, dir: &Path, file_name: &str, url: &Url) -> PathBuf {
+    let name = Path::new(file_name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name.extension().map(|x| x.to_string_lossy());
+
+    for i in 0.. {
+        let candidate = match (i, &extension) {
+            (0, _) => dir.join(file_name),
+            (i, Some(extension)) => dir.join(format!("{stem} ({i}).{extension}")),
+            (i, None) => dir.join(format!("{stem} ({i})")),
+        };
+
+        match partial_download_url(fsio, &candidate).await {
+            Some(x) if x == url.as_str() => return candidate,
+            Some(_) => {}
+            None if !candidate.exists() => return candidate,
+            None => {}
+        }
+    }
+
+    unreachable!()
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+fn print_progress(name: &str, downloaded: u64, total: u64) {
+    let ratio = if total > 0 {
+        (downloaded as f64 / total as f64).min(1.0)
+    } else {
+        0.0
+    };
+    let filled = (ratio * PROGRESS_BAR_WIDTH as f64) as usize;
+
+    eprint!(
+        "\r[{}{}] {:>3}% {} / {} {}",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled),
+        (ratio * 100.0) as u32,
+        format_size(downloaded),
+        format_size(total),
+        name
+    );
+    let _ = std::io::stderr().flush();
+}
+
+async fn run_get(state: &AppState, target: &str, output: Option<PathBuf>) -> Result<()> {
+    let (files, collection) = {
+        let mut fs = state.fs.write().await;
+        resolve_download(&mut fs, target).await?
+    };
+    if files.is_empty() {
+        bail!("{target} has no tracks");
+    }
+
+    let mut dir = output.unwrap_or_else(|| PathBuf::from("."));
+    if let Some(collection) = collection {
+        dir.push(sanitize_file_name(&collection));
+    }
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let connection = state.fs.read().await.connection.clone();
+    let client_config = Arc::new(Arc::new(state.validator.clone()).into_client_config());
+    let fsio = FsIo::new();
+
+    for file in &files {
+        let url = media_file_url(&connection.url, file.id)?;
+        // The path is the one of the server, whatever its platform
+        let file_name = file.path.rsplit(['/', '\\']).next().unwrap_or_default();
+        let destination =
+            download_destination(&fsio, &dir, &sanitize_file_name(file_name), &url).await;
+
+        let progressed = AtomicBool::new(false);
+        let result = download_file(
+            &fsio,
+            &url,
+            &destination,
+            client_config.clone(),
+            &DownloadConfig::default(),
+            |downloaded, total| {
+                progressed.store(true, Ordering::Relaxed);
+                print_progress(&file.title, downloaded, total);
+            },
+        )
+        .await;
+        if progressed.load(Ordering::Relaxed) {
+            eprintln!();
+        }
+
+        // The download is checked against the digest the server sends
+        match result {
+            Ok(()) => println!("Downloaded {} to {}", file.title, destination.display()),
+            Err(e) => state.fail(format!("Failed to download {}: {e:#}", file.title)),
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn handle_get(
+    state: Arc<AppState>,
+    target: String,
+    output: Option<PathBuf>,
+) -> Result<bool> {
+    if let Err(e) = run_get(&state, &target, output).await {
+        state.fail(format!("Failed to download {target}: {e}"));
+    }
+    Ok(true)
+}
@@ -1,4 +1,11 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    fmt::Display,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 use anyhow::Result;
 use colored::Colorize;
@@ -8,6 +15,7 @@ use tokio::sync::{Mutex, RwLock};
 use discovery::{
     client::CertValidator,
     protocol::{DiscoveredDevice, DiscoveryService},
+    trust_bundle::TrustImportReport,
 };
 
 use crate::fs::VirtualFS;
@@ -17,6 +25,23 @@ pub struct AppState {
     pub validator: CertValidator,
     pub discovery: Arc<Mutex<Option<DiscoveryService>>>,
     pub config_dir: PathBuf,
+    /// The volume to restore on `unmute`, set while muted
+    pub muted_volume: Mutex<Option<f32>>,
+    /// Whether a command reported an error, for the exit code of
+    /// `remote-exec`
+    pub failed: AtomicBool,
+}
+
+impl AppState {
+    /// Prints the error of a command, which the REPL survives
+    pub fn fail(&self, message: impl Display) {
+        eprintln!("{message}");
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
 }
 
 pub fn print_device_table(devices: &[DiscoveredDevice]) {
@@ -118,3 +143,46 @@ pub fn get_fingerprint_by_index(validator: &CertValidator, index: usize) -> Resu
 
     Ok(fingerprints.get(index - 1).map(|s| s.to_string()))
 }
+
+pub fn print_trust_import_report(report: &TrustImportReport) {
+    let short = |fp: &str| fp.chars().take(8).collect::<String>().magenta();
+
+    println!("{}", "Imported:".green().bold());
+    for fp in &report.imported {
+        println!("    {}", short(fp));
+    }
+
+    if !report.conflicts.is_empty() {
+        println!("{}", "Conflicts (skipped):".yellow().bold());
+        for conflict in &report.conflicts {
+            println!(
+                "    {} is trusted as {}, bundle has {}",
+                conflict.host.white(),
+                short(&conflict.existing_fingerprint),
+                short(&conflict.imported_fingerprint)
+            );
+        }
+    }
+
+    if !report.revoked.is_empty() {
+        println!("{}", "Revoked (skipped):".red().bold());
+        for fp in &report.revoked {
+            println!("    {}", short(fp));
+        }
+    }
+
+    if !report.expired.is_empty() {
+        println!("{}", "Expired (skipped):".red().bold());
+        for fp in &report.expired {
+            println!("    {}", short(fp));
+        }
+    }
+
+    if let Some(identity) = &report.identity {
+        println!(
+            "{} {}",
+            "Client certificate installed:".green().bold(),
+            identity.alias.cyan()
+        );
+    }
+}
@@ -9,6 +9,7 @@ use rustls::ClientConfig;
 use ::discovery::{
     client::{fetch_server_certificate, parse_certificate},
     config::get_config_dir,
+    endpoint::ServerEndpoint,
 };
 
 use hub::server::{
@@ -34,7 +35,10 @@ impl fmt::Display for VerificationResult {
 }
 
 async fn verify_single_host(host: String, expected_fp: String) -> (String, VerificationResult) {
-    let url = format!("https://{host}:7863/ping");
+    let url = match ServerEndpoint::parse(&host) {
+        Ok(endpoint) => endpoint.http_url("/ping"),
+        Err(e) => return (host, VerificationResult::Error(e.to_string())),
+    };
     info!("Connecting to {url}");
 
     let result =
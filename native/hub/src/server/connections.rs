@@ -0,0 +1,173 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+    },
+};
+
+use chrono::Utc;
+
+/// Number and size of broadcast messages of a single type delivered to a connection.
+#[derive(Debug, Clone, Default)]
+pub struct BroadcastCounter {
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+/// Traffic statistics of a single WebSocket connection.
+#[derive(Debug)]
+pub struct ConnectionStats {
+    pub id: u64,
+    pub fingerprint: String,
+    pub remote_addr: SocketAddr,
+    pub protocol_version: u32,
+    pub protocol_features: Vec<String>,
+    /// UNIX timestamp of the moment the connection was established.
+    pub connected_at: i64,
+    last_activity: AtomicI64,
+    request_count: AtomicU64,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    broadcasts: Mutex<HashMap<String, BroadcastCounter>>,
+}
+
+/// Point-in-time copy of [`ConnectionStats`].
+#[derive(Debug, Clone)]
+pub struct ConnectionSnapshot {
+    pub fingerprint: String,
+    pub remote_addr: SocketAddr,
+    pub protocol_version: u32,
+    pub protocol_features: Vec<String>,
+    pub connected_at: i64,
+    pub last_activity: i64,
+    pub request_count: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub broadcasts: Vec<(String, BroadcastCounter)>,
+}
+
+impl ConnectionStats {
+    pub fn record_request(&self, bytes: usize) {
+        self.request_count.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        self.touch();
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_broadcast(&self, message_type: &str, bytes: usize) {
+        let mut broadcasts = self.broadcasts.lock().unwrap();
+        let counter = broadcasts.entry(message_type.to_string()).or_default();
+        counter.messages += 1;
+        counter.bytes += bytes as u64;
+    }
+
+    pub fn touch(&self) {
+        self.last_activity
+            .store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ConnectionSnapshot {
+        let mut broadcasts: Vec<_> = self
+            .broadcasts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        broadcasts.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes));
+
+        ConnectionSnapshot {
+            fingerprint: self.fingerprint.clone(),
+            remote_addr: self.remote_addr,
+            protocol_version: self.protocol_version,
+            protocol_features: self.protocol_features.clone(),
+            connected_at: self.connected_at,
+            last_activity: self.last_activity.load(Ordering::Relaxed),
+            request_count: self.request_count.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            broadcasts,
+        }
+    }
+}
+
+/// Tracks all open WebSocket connections of the server.
+#[derive(Debug, Default)]
+pub struct ConnectionRegistry {
+    next_id: AtomicU64,
+    connections: Mutex<HashMap<u64, Arc<ConnectionStats>>>,
+}
+
+impl ConnectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &self,
+        fingerprint: &str,
+        remote_addr: SocketAddr,
+        protocol_version: u32,
+        protocol_features: Vec<String>,
+    ) -> Arc<ConnectionStats> {
+        let now = Utc::now().timestamp();
+        let stats = Arc::new(ConnectionStats {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            fingerprint: fingerprint.to_string(),
+            remote_addr,
+            protocol_version,
+            protocol_features,
+            connected_at: now,
+            last_activity: AtomicI64::new(now),
+            request_count: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            broadcasts: Mutex::new(HashMap::new()),
+        });
+
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(stats.id, Arc::clone(&stats));
+
+        stats
+    }
+
+    pub fn unregister(&self, id: u64) {
+        self.connections.lock().unwrap().remove(&id);
+    }
+
+    /// Returns the number of open connections and of distinct clients.
+    pub fn counts(&self) -> (usize, usize) {
+        let connections = self.connections.lock().unwrap();
+        let clients: HashSet<&str> = connections
+            .values()
+            .map(|x| x.fingerprint.as_str())
+            .collect();
+
+        (connections.len(), clients.len())
+    }
+
+    /// Returns a snapshot of every open connection of the given client.
+    pub fn snapshot(&self, fingerprint: &str) -> Vec<ConnectionSnapshot> {
+        self.connections
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|x| x.fingerprint == fingerprint)
+            .map(|x| x.snapshot())
+            .collect()
+    }
+}
+
+/// Reads the message type from an encoded message without decoding the payload.
+pub fn peek_message_type(payload: &[u8]) -> Option<&str> {
+    let type_len = *payload.first()? as usize;
+    std::str::from_utf8(payload.get(1..1 + type_len)?).ok()
+}
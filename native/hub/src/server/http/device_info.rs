@@ -15,6 +15,9 @@ fn sanitize_device_info(original: &DeviceInfo) -> SanitizedDeviceInfo {
             Some(x) => x.to_string(),
             None => "Unknown".to_string(),
         },
+        api_port: Some(original.api_port),
+        ws_port: original.ws_port,
+        path_prefix: original.path_prefix.clone(),
     }
 }
 
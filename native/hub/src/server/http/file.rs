@@ -1,23 +1,65 @@
-use std::sync::Arc;
+use std::{fs::File, io, sync::Arc};
 
 use axum::{
     body::Body,
     extract::{Path, State},
-    http::{Request, StatusCode},
+    http::{HeaderMap, HeaderValue, Request, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use base64::{Engine, engine::general_purpose::STANDARD};
+use fsio::FsIo;
+use sha2::{Digest, Sha256};
 use tower::ServiceExt;
 use tower_http::services::ServeDir;
 
 use crate::server::ServerState;
 
+/// Request headers passed through to the file service, so clients can fetch
+/// files in ranges and resume interrupted downloads.
+const FORWARDED_HEADERS: &[header::HeaderName] = &[
+    header::RANGE,
+    header::IF_RANGE,
+    header::IF_MODIFIED_SINCE,
+    header::IF_UNMODIFIED_SINCE,
+];
+
+fn wants_sha256_digest(headers: &HeaderMap) -> bool {
+    headers
+        .get_all("want-digest")
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .any(|x| {
+            x.split(';')
+                .next()
+                .is_some_and(|x| x.trim().eq_ignore_ascii_case("sha-256"))
+        })
+}
+
+fn sha256_digest(path: &std::path::Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(STANDARD.encode(hasher.finalize()))
+}
+
+/// Path of a media file relative to the library, with `/` separators.
+pub fn library_path(directory: &str, file_name: &str) -> String {
+    let directory = directory.replace('\\', "/");
+    let directory = directory.trim_matches('/');
+    if directory.is_empty() {
+        file_name.to_owned()
+    } else {
+        format!("{directory}/{file_name}")
+    }
+}
+
 pub async fn file_handler(
     Path(file_path): Path<String>,
     State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     let lib_path = &state.app_state.lib_path;
     let cover_temp_dir = &state.app_state.cover_temp_dir;
-    let fsio = state.fsio.clone();
 
     // Parse the request path, splitting it into prefix and actual file path
     let path_parts: Vec<&str> = file_path.splitn(2, '/').collect();
@@ -34,6 +76,17 @@ pub async fn file_handler(
         _ => return StatusCode::NOT_FOUND.into_response(),
     };
 
+    serve_file(&state.fsio, root_dir, relative_path, &headers).await
+}
+
+/// Serves `relative_path` below `root_dir`, honoring range and conditional
+/// request headers, and refusing paths that escape `root_dir`.
+pub async fn serve_file(
+    fsio: &FsIo,
+    root_dir: &std::path::Path,
+    relative_path: &str,
+    headers: &HeaderMap,
+) -> Response {
     // Construct the full file path and normalize it
     let requested_path = root_dir.join(relative_path);
     let canonical_path = match fsio.canonicalize_path(&requested_path) {
@@ -54,14 +107,33 @@ pub async fn file_handler(
 
     // Serve the file using ServeDir
     let service = ServeDir::new(root_dir);
-    let request = Request::builder()
-        .uri(format!("/{}", relative_path.to_string_lossy()))
-        .body(axum::body::Body::empty())
-        .unwrap();
+    let mut request = Request::builder().uri(format!("/{}", relative_path.to_string_lossy()));
+    for name in FORWARDED_HEADERS {
+        for value in headers.get_all(name) {
+            request = request.header(name, value);
+        }
+    }
+    let request = request.body(axum::body::Body::empty()).unwrap();
+
+    // The digest always covers the complete file, even for range requests
+    let digest = if wants_sha256_digest(headers) {
+        let path = canonical_path.clone();
+        match tokio::task::spawn_blocking(move || sha256_digest(&path)).await {
+            Ok(Ok(digest)) => Some(digest),
+            _ => None,
+        }
+    } else {
+        None
+    };
 
     match service.oneshot(request).await {
         Ok(response) => {
-            let (parts, body) = response.into_parts();
+            let (mut parts, body) = response.into_parts();
+            if let Some(digest) = digest
+                && let Ok(value) = HeaderValue::from_str(&format!("sha-256={digest}"))
+            {
+                parts.headers.insert("digest", value);
+            }
             let boxed_body = Body::new(body);
             Response::from_parts(parts, boxed_body)
         }
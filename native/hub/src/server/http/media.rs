@@ -6,15 +6,25 @@ use axum::{
     http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use fsio::FsIo;
 use mimetype_detector::detect_file;
 use serde::Serialize;
 
 use crate::{
     messages::{Album, Artist, MediaFile},
-    server::{ServerManager, ServerState},
+    server::{
+        ServerManager, ServerState,
+        http::file::{library_path, serve_file},
+    },
     utils::parse_media_files,
 };
-use database::actions::{cover_art::bake_cover_art_by_file_ids, metadata::get_parsed_file_by_id};
+use database::{
+    actions::{
+        cover_art::bake_cover_art_by_file_ids, file::get_file_by_id,
+        metadata::get_parsed_file_by_id,
+    },
+    connection::MainDbConnection,
+};
 
 #[derive(Serialize)]
 pub struct MediaMetadataResponse {
@@ -78,6 +88,37 @@ pub async fn get_media_metadata_handler(
     }))
 }
 
+/// Serves the original file of a media file, in ranges when asked, so
+/// clients can download tracks without knowing where the library lives.
+pub async fn get_media_file_handler(
+    State(server_state): State<Arc<ServerState>>,
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+    AxumPath(file_id): AxumPath<i64>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let file_id_i32 = file_id
+        .try_into()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "File ID out of range".to_string()))?;
+
+    let file = get_file_by_id(&server_manager.global_params.main_db, file_id_i32)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Media file not found for file_id: {file_id}"),
+            )
+        })?;
+
+    Ok(serve_file(
+        &server_state.fsio,
+        &server_state.app_state.lib_path,
+        &library_path(&file.directory, &file.file_name),
+        &headers,
+    )
+    .await)
+}
+
 pub async fn get_cover_art_handler(
     State(server_state): State<Arc<ServerState>>,
     Extension(server_manager): Extension<Arc<ServerManager>>,
@@ -86,16 +127,27 @@ pub async fn get_cover_art_handler(
     let file_id_i32 = file_id
         .try_into()
         .map_err(|_| (StatusCode::BAD_REQUEST, "File ID out of range".to_string()))?;
-    let cover_art_map = bake_cover_art_by_file_ids(
+
+    cover_art_response(
         &server_state.fsio,
         &server_manager.global_params.main_db,
-        vec![file_id_i32],
+        file_id_i32,
     )
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+}
+
+/// Bakes the cover art of a media file and responds with its content.
+pub async fn cover_art_response(
+    fsio: &FsIo,
+    main_db: &MainDbConnection,
+    file_id: i32,
+) -> Result<Response, (StatusCode, String)> {
+    let cover_art_map = bake_cover_art_by_file_ids(fsio, main_db, vec![file_id])
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let cover_path = cover_art_map
-        .get(&file_id_i32)
+        .get(&file_id)
         .cloned()
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Cover art not found".to_string()))?;
 
@@ -0,0 +1,107 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use axum::{
+    extract::{Extension, State},
+    http::header,
+    response::IntoResponse,
+};
+use log::warn;
+
+use ::database::actions::utils::get_database_size;
+use ::playback::player::PlaybackState;
+
+use crate::server::{ServerManager, ServerState, metrics::MetricsWriter};
+
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+pub async fn metrics_handler(
+    State(state): State<Arc<ServerState>>,
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+) -> impl IntoResponse {
+    let global_params = &server_manager.global_params;
+    let mut out = MetricsWriter::new();
+
+    let (connections, clients) = state.connection_registry.counts();
+    out.gauge(
+        "rune_connected_clients",
+        "Clients with at least one open WebSocket connection.",
+        clients as f64,
+    );
+    out.gauge(
+        "rune_websocket_connections",
+        "Open WebSocket connections.",
+        connections as f64,
+    );
+
+    server_manager.metrics.render(&mut out);
+
+    match get_database_size(&global_params.main_db).await {
+        Ok(size) => out.gauge(
+            "rune_database_size_bytes",
+            "Size of the library database.",
+            size as f64,
+        ),
+        Err(e) => warn!("Failed to get the database size: {e}"),
+    }
+
+    let cover_temp_dir = state.app_state.cover_temp_dir.clone();
+    match tokio::task::spawn_blocking(move || directory_size(&cover_temp_dir)).await {
+        Ok(Ok(size)) => out.gauge(
+            "rune_cover_cache_size_bytes",
+            "Size of the cover art cache.",
+            size as f64,
+        ),
+        Ok(Err(e)) => warn!("Failed to get the cover cache size: {e}"),
+        Err(e) => warn!("Failed to get the cover cache size: {e}"),
+    }
+
+    let status = global_params.player.lock().await.get_status();
+
+    out.header(
+        "rune_playback_state",
+        "gauge",
+        "Current playback state of the player.",
+    );
+    for (state, label) in [
+        (PlaybackState::Playing, "playing"),
+        (PlaybackState::Paused, "paused"),
+        (PlaybackState::Stopped, "stopped"),
+    ] {
+        let value = if status.state == state { 1.0 } else { 0.0 };
+        out.sample("rune_playback_state", &[("state", label)], value);
+    }
+    out.gauge(
+        "rune_playback_position_seconds",
+        "Position in the current track.",
+        status.position.as_secs_f64(),
+    );
+    out.gauge(
+        "rune_playback_volume",
+        "Volume of the player.",
+        status.volume as f64,
+    );
+    out.gauge(
+        "rune_playlist_length",
+        "Number of items in the playback queue.",
+        status.playlist.len() as f64,
+    );
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        out.finish(),
+    )
+}
@@ -3,7 +3,9 @@ pub mod device_info;
 pub mod file;
 pub mod list;
 pub mod media;
+pub mod metrics;
 pub mod panel_alias;
+pub mod panel_api_tokens;
 pub mod panel_auth_middleware;
 pub mod panel_broadcast;
 pub mod panel_delete_user;
@@ -13,4 +15,5 @@ pub mod panel_self;
 pub mod panel_status;
 pub mod ping;
 pub mod register;
+pub mod rest;
 pub mod websocket;
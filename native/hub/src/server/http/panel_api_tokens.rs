@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use axum::{Extension, Json, extract::Path, http::StatusCode, response::IntoResponse};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::server::{ServerManager, api_tokens::ApiTokenRecord};
+
+use super::register::AppError;
+
+#[derive(Serialize)]
+pub struct ApiTokenSummary {
+    id: String,
+    name: String,
+    created_at: i64,
+}
+
+impl From<ApiTokenRecord> for ApiTokenSummary {
+    fn from(x: ApiTokenRecord) -> Self {
+        Self {
+            id: x.id,
+            name: x.name,
+            created_at: x.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiTokenRequest {
+    name: String,
+}
+
+#[derive(Serialize)]
+pub struct CreateApiTokenResponse {
+    #[serde(flatten)]
+    summary: ApiTokenSummary,
+    /// The secret itself, only returned once.
+    token: String,
+}
+
+pub async fn list_api_tokens_handler(
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+) -> Result<Json<Vec<ApiTokenSummary>>, AppError> {
+    let records = server_manager
+        .api_tokens
+        .list()
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Json(records.into_iter().map(Into::into).collect()))
+}
+
+pub async fn create_api_token_handler(
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+    Json(request): Json<CreateApiTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let (record, token) = server_manager
+        .api_tokens
+        .create(&request.name)
+        .await
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    info!("Created API token {}", record.name);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiTokenResponse {
+            summary: record.into(),
+            token,
+        }),
+    ))
+}
+
+pub async fn revoke_api_token_handler(
+    Path(id): Path<String>,
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let record = server_manager
+        .api_tokens
+        .revoke(&id)
+        .await
+        .map_err(|e| AppError::NotFound(e.to_string()))?;
+
+    info!("Revoked API token {}", record.name);
+
+    Ok(Json(json!({
+        "success": true,
+        "message": format!("API token {} revoked successfully", record.name)
+    })))
+}
@@ -1,7 +1,6 @@
-use std::{path::Path, sync::Arc};
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::{Extension, Json};
-use bcrypt::verify;
+use axum::{Extension, Json, extract::ConnectInfo};
 use serde::Deserialize;
 
 use crate::server::ServerManager;
@@ -19,28 +18,17 @@ pub struct LoginResponse {
 }
 
 pub async fn login_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Extension(server_manager): Extension<Arc<ServerManager>>,
     Json(request): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, AppError> {
-    let config_path = Path::new(&*server_manager.global_params.config_path);
-    let password_path = config_path.join("root_password.hash");
-
-    if !password_path.exists() {
-        return Err(AppError::Unauthorized(
-            "Root password not initialized".into(),
-        ));
-    }
-
-    let stored_hash = tokio::fs::read_to_string(&password_path)
+    // Behind a reverse proxy every client has the address of the proxy, and
+    // shares its lockout
+    server_manager
+        .admin_auth
+        .check_password(&addr.ip().to_string(), &request.password)
         .await
-        .map_err(|_| AppError::Internal("Failed to read password hash".into()))?;
-
-    let valid = verify(&request.password, &stored_hash)
-        .map_err(|_| AppError::Internal("Password verification failed".into()))?;
-
-    if !valid {
-        return Err(AppError::Unauthorized("Invalid password".into()));
-    }
+        .map_err(|e| AppError::Unauthorized(e.to_string()))?;
 
     let token = server_manager
         .generate_jwt_token(None)
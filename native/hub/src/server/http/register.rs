@@ -79,6 +79,9 @@ pub enum AppError {
     Internal(String),
     NotFound(String),
     Unauthorized(String),
+    BadRequest(String),
+    Forbidden(String),
+    Unavailable(String),
 }
 
 impl From<PermissionError> for AppError {
@@ -106,6 +109,9 @@ impl IntoResponse for AppError {
             AppError::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
             AppError::NotFound(e) => (StatusCode::NOT_FOUND, e),
             AppError::Unauthorized(e) => (StatusCode::UNAUTHORIZED, e),
+            AppError::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
+            AppError::Forbidden(e) => (StatusCode::FORBIDDEN, e),
+            AppError::Unavailable(e) => (StatusCode::SERVICE_UNAVAILABLE, e),
         };
 
         let body = Json(ErrorResponse { message });
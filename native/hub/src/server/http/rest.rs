@@ -0,0 +1,364 @@
+use std::{sync::Arc, time::Instant};
+
+use axum::{
+    Extension, Json, Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+};
+use log::{debug, warn};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value, json};
+
+use ::discovery::{endpoint::ServerEndpoint, server::UserStatus};
+
+use crate::{
+    Session,
+    messages::*,
+    server::{ServerManager, ServerState},
+};
+
+use super::register::AppError;
+
+/// Path the HTTP API is mounted at, below the path prefix of the server.
+pub const REST_PREFIX: &str = "/api/v1";
+
+/// Header carrying the public key or fingerprint of a registered device,
+/// the same credential WebSocket clients send in their `auth` parameter.
+pub const DEVICE_AUTH_HEADER: &str = "X-Rune-Auth";
+
+/// A route of the HTTP API, mapped onto a WebSocket request.
+#[derive(Debug)]
+pub struct RestRoute {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub request: &'static str,
+    /// Response of the request, routes without one answer with no content.
+    pub response: Option<&'static str>,
+}
+
+macro_rules! rest_response {
+    () => {
+        ()
+    };
+    ($response:ident) => {
+        $response
+    };
+}
+
+macro_rules! rest_response_name {
+    () => {
+        None
+    };
+    ($response:ident) => {
+        Some(stringify!($response))
+    };
+}
+
+/// Declares the route table and the router serving it, so the OpenAPI
+/// document can never drift from the routes actually served.
+macro_rules! rest_routes {
+    ($($method:ident $path:literal => $request:ident $(-> $response:ident)?, $summary:literal;)*) => {
+        pub const REST_ROUTES: &[RestRoute] = &[
+            $(RestRoute {
+                method: stringify!($method),
+                path: $path,
+                summary: $summary,
+                request: stringify!($request),
+                response: rest_response_name!($($response)?),
+            },)*
+        ];
+
+        /// Routes of the HTTP API, to be nested at [`REST_PREFIX`].
+        pub fn rest_router() -> Router<Arc<ServerState>> {
+            Router::new()
+                $(.route($path, $method(dispatch::<$request, rest_response!($($response)?)>)))*
+                .route("/openapi.json", get(openapi_handler))
+        }
+    };
+}
+
+rest_routes! {
+    post "/playback/play" => PlayRequest, "Resume playback";
+    post "/playback/pause" => PauseRequest, "Pause playback";
+    post "/playback/next" => NextRequest, "Skip to the next item of the queue";
+    post "/playback/previous" => PreviousRequest, "Go back to the previous item of the queue";
+    post "/playback/seek" => SeekRequest, "Seek within the current item";
+    put "/playback/volume" => VolumeRequest -> VolumeResponse, "Set the volume";
+    post "/playback/volume/relative" => RelativeVolumeRequest -> RelativeVolumeResponse, "Change the volume by a step";
+    put "/playback/mode" => SetPlaybackModeRequest, "Set the playback mode";
+    get "/queue" => FetchPlaybackQueueRequest -> FetchPlaybackQueueResponse, "List the items of the queue";
+    post "/queue/switch" => SwitchRequest, "Play the item at the given position of the queue";
+    put "/queue/move" => MovePlaylistItemRequest, "Move an item of the queue";
+    delete "/queue" => RemoveRequest, "Remove the item at the given position of the queue";
+    post "/search" => SearchForRequest -> SearchForResponse, "Search the library";
+    post "/media-files/by-ids" => FetchMediaFileByIdsRequest -> FetchMediaFileByIdsResponse, "Fetch media files by id";
+    get "/playlists" => FetchAllPlaylistsRequest -> FetchAllPlaylistsResponse, "List all playlists";
+    post "/playlists" => CreatePlaylistRequest -> CreatePlaylistResponse, "Create a playlist";
+    put "/playlists" => UpdatePlaylistRequest -> UpdatePlaylistResponse, "Rename or regroup a playlist";
+    delete "/playlists" => RemovePlaylistRequest -> RemovePlaylistResponse, "Remove a playlist";
+    get "/playlists/by-id" => GetPlaylistByIdRequest -> GetPlaylistByIdResponse, "Fetch a playlist";
+    post "/playlists/items" => AddItemToPlaylistRequest -> AddItemToPlaylistResponse, "Add a media file to a playlist";
+    put "/playlists/items" => ReorderPlaylistItemPositionRequest -> ReorderPlaylistItemPositionResponse, "Move a media file within a playlist";
+    delete "/playlists/items" => RemoveItemFromPlaylistRequest -> RemoveItemFromPlaylistResponse, "Remove a media file from a playlist";
+}
+
+/// Requests of `GET` and `DELETE` routes are read from the query string,
+/// the others from a JSON body.
+fn reads_query(method: &str) -> bool {
+    matches!(method, "get" | "delete")
+}
+
+fn request_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Resolves the fingerprint the request is made on behalf of, either a
+/// device registered on the server or an API token.
+async fn authenticate(
+    state: &ServerState,
+    server_manager: &ServerManager,
+    headers: &HeaderMap,
+) -> Result<String, AppError> {
+    if let Some(authorization) = headers.get(header::AUTHORIZATION) {
+        let token = authorization
+            .to_str()
+            .ok()
+            .and_then(|x| x.strip_prefix("Bearer "))
+            .ok_or_else(|| AppError::Unauthorized("Invalid token format".into()))?;
+
+        let record = server_manager
+            .api_tokens
+            .verify(token)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("Invalid API token".into()))?;
+
+        return Ok(format!("api-token:{}", record.id));
+    }
+
+    let Some(auth_key) = headers
+        .get(DEVICE_AUTH_HEADER)
+        .and_then(|x| x.to_str().ok())
+    else {
+        return Err(AppError::Unauthorized("Missing credentials".into()));
+    };
+
+    let permission_manager = state.permission_manager.read().await;
+    let user = match permission_manager.verify_by_public_key(auth_key).await {
+        Some(user) => Some(user),
+        None => permission_manager.verify_by_fingerprint(auth_key).await,
+    };
+
+    match user.map(|x| (x.status, x.fingerprint)) {
+        Some((UserStatus::Approved, fingerprint)) => Ok(fingerprint),
+        Some((UserStatus::Blocked, _)) => Err(AppError::Forbidden("Device is blocked".into())),
+        Some((UserStatus::Pending, _)) => {
+            Err(AppError::Unauthorized("Device is pending approval".into()))
+        }
+        None => Err(AppError::Unauthorized("Unknown device".into())),
+    }
+}
+
+/// Converts a JSON request into the message `Req`, runs it through the
+/// WebSocket handler of the same name and converts its response back.
+async fn dispatch<Req, Resp>(
+    State(state): State<Arc<ServerState>>,
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError>
+where
+    Req: Serialize + DeserializeOwned + Send + 'static,
+    Resp: Serialize + DeserializeOwned + Send + 'static,
+{
+    let name = request_name::<Req>();
+    let fingerprint = authenticate(&state, &server_manager, &headers).await?;
+
+    let request: Req = if reads_query(&method.as_str().to_ascii_lowercase()) {
+        Query::<Req>::try_from_uri(&uri)
+            .map_err(|e| AppError::BadRequest(e.body_text()))?
+            .0
+    } else if body.is_empty() {
+        serde_json::from_str("{}").map_err(|e| AppError::BadRequest(e.to_string()))?
+    } else {
+        serde_json::from_slice(&body).map_err(|e| AppError::BadRequest(e.to_string()))?
+    };
+
+    let payload = rinf::serialize(&request).map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let host = headers
+        .get(header::HOST)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| ServerEndpoint::parse(x).ok())
+        .map(|x| x.host)
+        .unwrap_or_else(|| "127.0.0.1".to_owned());
+    let session = Session {
+        fingerprint,
+        host: state.layout.endpoint(&host).base_url(),
+        admin_token: Arc::new(std::sync::Mutex::new(None)),
+    };
+
+    let Some(_in_flight) = state.shutdown.track() else {
+        return Err(AppError::Unavailable("Server is shutting down".into()));
+    };
+
+    debug!("[{}] REST request: {name}", session.fingerprint);
+    let started_at = Instant::now();
+    let (response_type, response) = state
+        .websocket_service
+        .handle_message(name, payload, Some(session))
+        .await
+        .ok_or_else(|| AppError::NotFound(format!("{name} is not served by this server")))?;
+    let response = response.map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let succeeded = response_type != "CrashResponse" && response_type != "PermissionDeniedResponse";
+    state
+        .metrics
+        .record_request(name, started_at.elapsed(), succeeded);
+
+    match response_type.as_str() {
+        "" => Ok(StatusCode::NO_CONTENT.into_response()),
+        "CrashResponse" => {
+            let crash = rinf::deserialize::<CrashResponse>(&response)
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            warn!("REST request {name} failed: {}", crash.detail);
+            Err(AppError::Internal(crash.detail))
+        }
+        "PermissionDeniedResponse" => {
+            let denied = rinf::deserialize::<PermissionDeniedResponse>(&response)
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            Err(AppError::Forbidden(denied.detail))
+        }
+        _ => {
+            let response = rinf::deserialize::<Resp>(&response)
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            Ok(Json(response).into_response())
+        }
+    }
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+fn json_response(description: &str, schema: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema_ref(schema) } },
+    })
+}
+
+/// Builds the OpenAPI document of the HTTP API from [`REST_ROUTES`].
+///
+/// Messages are described by name only, their fields match the Rust
+/// structs in `messages` with snake_case names.
+pub fn openapi_document(path_prefix: &str) -> Value {
+    let mut paths = Map::new();
+    let mut schemas = Map::new();
+
+    schemas.insert(
+        "Error".to_owned(),
+        json!({
+            "type": "object",
+            "properties": { "message": { "type": "string" } },
+            "required": ["message"],
+        }),
+    );
+
+    for route in REST_ROUTES {
+        let mut operation = json!({
+            "operationId": route.request,
+            "summary": route.summary,
+            "responses": {
+                "400": json_response("Malformed request", "Error"),
+                "401": json_response("Missing or invalid credentials", "Error"),
+                "403": json_response("Device is blocked", "Error"),
+                "500": json_response("Request failed", "Error"),
+            },
+        });
+
+        if reads_query(route.method) {
+            operation["parameters"] = json!([{
+                "name": "request",
+                "in": "query",
+                "style": "form",
+                "explode": true,
+                "schema": schema_ref(route.request),
+            }]);
+        } else {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema_ref(route.request) } },
+            });
+        }
+
+        match route.response {
+            Some(response) => {
+                operation["responses"]["200"] = json_response(response, response);
+                schemas.insert(
+                    response.to_owned(),
+                    json!({ "type": "object", "title": response }),
+                );
+            }
+            None => {
+                operation["responses"]["204"] = json!({ "description": "Request accepted" });
+            }
+        }
+
+        schemas.insert(
+            route.request.to_owned(),
+            json!({ "type": "object", "title": route.request }),
+        );
+
+        let path = format!("{path_prefix}{REST_PREFIX}{}", route.path);
+        let item = paths.entry(path).or_insert_with(|| json!({}));
+        item[route.method] = operation;
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Rune HTTP API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "apiToken": { "type": "http", "scheme": "bearer" },
+                "device": { "type": "apiKey", "in": "header", "name": DEVICE_AUTH_HEADER },
+            },
+        },
+        "security": [{ "apiToken": [] }, { "device": [] }],
+    })
+}
+
+pub async fn openapi_handler(State(state): State<Arc<ServerState>>) -> Json<Value> {
+    Json(openapi_document(&state.layout.path_prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::protocol::SERVED_REQUEST_TYPES;
+
+    #[test]
+    fn every_route_maps_to_a_served_request() {
+        for route in REST_ROUTES {
+            assert!(
+                SERVED_REQUEST_TYPES.contains(&route.request),
+                "{} {} maps to {}, which is never served",
+                route.method,
+                route.path,
+                route.request
+            );
+        }
+    }
+}
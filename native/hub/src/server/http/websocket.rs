@@ -1,9 +1,9 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Instant};
 
 use axum::{
     extract::{
         ConnectInfo, Query, State,
-        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+        ws::{CloseFrame, Message as WsMessage, WebSocket, WebSocketUpgrade, close_code},
     },
     http::StatusCode,
     response::{IntoResponse, Response},
@@ -15,7 +15,16 @@ use tokio::sync::mpsc;
 use crate::{
     Session,
     backends::remote::{decode_message, encode_message},
-    server::ServerState,
+    messages::CrashResponse,
+    server::{
+        ServerState,
+        connections::peek_message_type,
+        limits::TokenBucket,
+        protocol::{
+            LEGACY_PROTOCOL_VERSION, PROTOCOL_VERSION, peer_protocol_version, unsupported_request,
+        },
+    },
+    utils::RinfRustSignal,
 };
 use discovery::server::{User, UserStatus};
 
@@ -25,6 +34,10 @@ pub async fn websocket_handler(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<Arc<ServerState>>,
 ) -> Response {
+    if state.shutdown.is_shutting_down() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
     let auth_key = params
         .get("auth")
         .or_else(|| params.get("public_key"))
@@ -74,8 +87,23 @@ pub async fn websocket_handler(
     match auth_result {
         Ok(user) => {
             info!("Connection authorized for {} @ {}", user.alias, addr);
-            let host = format!("https://{host}:7863");
-            ws.on_upgrade(move |socket| handle_socket(socket, state, user, host))
+            let host = state.layout.endpoint(&host).base_url();
+            let protocol_version = peer_protocol_version(&params);
+            let features = params
+                .get("features")
+                .map(|x| {
+                    x.split(',')
+                        .map(|x| x.trim().to_string())
+                        .filter(|x| !x.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let max_frame_size = state.connection_limits.max_frame_size;
+            ws.max_frame_size(max_frame_size)
+                .max_message_size(max_frame_size)
+                .on_upgrade(move |socket| {
+                    handle_socket(socket, state, user, host, addr, protocol_version, features)
+                })
         }
         Err(code) => {
             warn!(
@@ -91,38 +119,135 @@ pub async fn websocket_handler(
     }
 }
 
-pub async fn handle_socket(socket: WebSocket, state: Arc<ServerState>, user: User, host: String) {
+pub async fn handle_socket(
+    socket: WebSocket,
+    state: Arc<ServerState>,
+    user: User,
+    host: String,
+    addr: SocketAddr,
+    protocol_version: u32,
+    features: Vec<String>,
+) {
     let (mut sender, mut receiver) = socket.split();
     let mut broadcast_rx = state.websocket_service.broadcast_tx.subscribe();
     let (tx, mut rx) = mpsc::channel(32);
 
     let alias = user.alias.clone();
     let fingerprint = user.fingerprint.clone();
+    let connection_registry = Arc::clone(&state.connection_registry);
+    let stats = connection_registry.register(&fingerprint, addr, protocol_version, features);
+    let server_metrics = Arc::clone(&state.metrics);
+    let shutdown = Arc::clone(&state.shutdown);
 
-    info!("[{alias}] WebSocket connection established");
+    info!("[{alias}] WebSocket connection established (protocol version {protocol_version})");
 
     // Clone alias for send_task
     let send_task_alias = alias.clone();
+    let send_task_stats = stats.clone();
     let send_task = tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
+            let len = match &msg {
+                WsMessage::Binary(x) => x.len(),
+                WsMessage::Text(x) => x.len(),
+                _ => 0,
+            };
+
             if let Err(e) = sender.send(msg).await {
                 error!("[{send_task_alias}] Failed to send message: {e}");
                 break;
             }
+
+            send_task_stats.record_sent(len);
         }
     });
 
     // Clone alias for incoming task
     let incoming_tx = tx.clone();
     let incoming_alias = alias.clone();
+    let limits = state.connection_limits.clone();
+    let metrics = state.connection_metrics.get_or_create(&fingerprint);
+    let mut bucket = TokenBucket::new(limits.for_status(&user.status));
+    let incoming_stats = stats.clone();
+    let admin_token = Arc::new(std::sync::Mutex::new(None));
     let incoming = async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            if let WsMessage::Binary(payload) = msg
-                && let Some((msg_type, msg_payload, uuid)) = decode_message(&payload)
-            {
+        let mut violations: u32 = 0;
+
+        while let Some(msg) = receiver.next().await {
+            let msg = match msg {
+                Ok(msg) => msg,
+                Err(e) => {
+                    // Oversized frames are rejected by the protocol layer and surface here
+                    warn!("[{incoming_alias}] Failed to receive message: {e}");
+                    metrics.record_dropped();
+                    close_connection(&incoming_tx, "Message rejected").await;
+                    break;
+                }
+            };
+
+            let WsMessage::Binary(payload) = msg else {
+                incoming_stats.touch();
+                continue;
+            };
+
+            incoming_stats.record_request(payload.len());
+
+            if payload.len() > limits.max_frame_size {
+                warn!(
+                    "[{incoming_alias}] Dropped a message of {} bytes",
+                    payload.len()
+                );
+                metrics.record_dropped();
+                close_connection(&incoming_tx, "Message too large").await;
+                break;
+            }
+
+            if bucket.try_acquire() {
+                violations = 0;
+            } else {
+                violations += 1;
+                metrics.record_throttled();
+
+                if violations > limits.max_violations {
+                    warn!("[{incoming_alias}] Rate limit exceeded, closing connection");
+                    metrics.record_dropped();
+                    close_connection(&incoming_tx, "Rate limit exceeded").await;
+                    break;
+                }
+
+                debug!("[{incoming_alias}] Throttling message ({violations} violations)");
+                tokio::time::sleep(limits.throttle_delay).await;
+            }
+
+            if let Some((msg_type, msg_payload, uuid)) = decode_message(&payload) {
                 debug!("[{incoming_alias}] Received: {msg_type}");
 
-                if let Some((resp_type, response)) = state
+                // Held until the response is queued, so the connection only
+                // closes once it is sent
+                let Some(_in_flight) = state.shutdown.track() else {
+                    debug!("[{incoming_alias}] Refused {msg_type} while shutting down");
+                    let refused = CrashResponse {
+                        detail: format!("{msg_type} refused, the server is shutting down"),
+                    };
+                    let response = match refused.encode_to_vec() {
+                        Ok(response) => response,
+                        Err(e) => {
+                            error!("[{incoming_alias}] Failed to encode the refusal: {e}");
+                            continue;
+                        }
+                    };
+
+                    let response_payload = encode_message(&refused.name(), &response, Some(uuid));
+                    if let Err(e) = incoming_tx
+                        .send(WsMessage::Binary(response_payload.into()))
+                        .await
+                    {
+                        error!("[{incoming_alias}] Failed to queue response: {e}");
+                    }
+                    continue;
+                };
+
+                let started_at = Instant::now();
+                let handled = state
                     .websocket_service
                     .handle_message(
                         &msg_type,
@@ -130,10 +255,32 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<ServerState>, user: Use
                         Some(Session {
                             fingerprint: fingerprint.to_owned(),
                             host: host.to_owned(),
+                            admin_token: Arc::clone(&admin_token),
                         }),
                     )
-                    .await
-                {
+                    .await;
+
+                // Legacy peers have no handler for the answer and keep waiting
+                // for the response as they always did
+                let handled = match handled {
+                    None if protocol_version > LEGACY_PROTOCOL_VERSION => {
+                        warn!("[{incoming_alias}] Received the unsupported request {msg_type}");
+                        let unsupported = unsupported_request(&msg_type, PROTOCOL_VERSION);
+                        Some((unsupported.name(), unsupported.encode_to_vec()))
+                    }
+                    handled => handled,
+                };
+
+                if let Some((resp_type, response)) = handled {
+                    state.metrics.record_request(
+                        &msg_type,
+                        started_at.elapsed(),
+                        response.is_ok()
+                            && resp_type != "CrashResponse"
+                            && resp_type != "PermissionDeniedResponse"
+                            && resp_type != "UnsupportedRequestResponse",
+                    );
+
                     let response = match response {
                         Ok(response) => response,
                         Err(e) => {
@@ -160,8 +307,14 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<ServerState>, user: Use
     // Clone alias for outgoing task
     let broadcast_tx = tx.clone();
     let outgoing_alias = alias.clone();
+    let outgoing_stats = stats.clone();
     let outgoing = async move {
         while let Ok(msg) = broadcast_rx.recv().await {
+            if let Some(message_type) = peek_message_type(&msg) {
+                outgoing_stats.record_broadcast(message_type, msg.len());
+                server_metrics.record_broadcast(message_type);
+            }
+
             if let Err(e) = broadcast_tx.send(WsMessage::Binary(msg.into())).await {
                 error!("[{outgoing_alias}] Failed to queue broadcast: {e}");
                 break;
@@ -171,16 +324,41 @@ pub async fn handle_socket(socket: WebSocket, state: Arc<ServerState>, user: Use
         drop(broadcast_tx);
     };
 
-    // Drop the original tx as we've cloned it for both tasks
+    let shutdown_tx = tx.clone();
+
+    // Drop the original tx as we've cloned it for all tasks
     drop(tx);
 
     // Run tasks concurrently
     tokio::select! {
         _ = incoming => {},
         _ = outgoing => {},
+        _ = shutdown.closing() => {
+            let frame = CloseFrame {
+                code: close_code::AWAY,
+                reason: "Server shutting down".into(),
+            };
+
+            if let Err(e) = shutdown_tx.send(WsMessage::Close(Some(frame))).await {
+                error!("[{alias}] Failed to queue close frame: {e}");
+            }
+        },
     };
+    drop(shutdown_tx);
 
     // Wait for the send task to complete
     let _ = send_task.await;
+    connection_registry.unregister(stats.id);
     info!("[{alias}] WebSocket connection closed");
 }
+
+async fn close_connection(tx: &mpsc::Sender<WsMessage>, reason: &str) {
+    let frame = CloseFrame {
+        code: close_code::POLICY,
+        reason: reason.into(),
+    };
+
+    if let Err(e) = tx.send(WsMessage::Close(Some(frame))).await {
+        error!("Failed to queue close frame: {e}");
+    }
+}
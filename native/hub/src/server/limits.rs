@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use discovery::server::UserStatus;
+
+/// Token bucket parameters applied to the messages of a single connection.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    /// Sustained number of requests accepted per second.
+    pub requests_per_second: f64,
+    /// Number of requests that can be accepted in a burst.
+    pub burst: u32,
+}
+
+/// Limits enforced by the WebSocket handler before a message is dispatched.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimits {
+    pub approved: RateLimitConfig,
+    pub pending: RateLimitConfig,
+    /// Largest binary frame or message accepted from a client, in bytes.
+    pub max_frame_size: usize,
+    /// How long a throttled message is delayed before it is handled.
+    pub throttle_delay: Duration,
+    /// Consecutive throttled messages tolerated before the connection is closed.
+    pub max_violations: u32,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            approved: RateLimitConfig {
+                requests_per_second: 50.0,
+                burst: 100,
+            },
+            pending: RateLimitConfig {
+                requests_per_second: 2.0,
+                burst: 5,
+            },
+            max_frame_size: 16 * 1024 * 1024,
+            throttle_delay: Duration::from_millis(250),
+            max_violations: 20,
+        }
+    }
+}
+
+impl ConnectionLimits {
+    pub fn for_status(&self, status: &UserStatus) -> RateLimitConfig {
+        match status {
+            UserStatus::Approved => self.approved,
+            UserStatus::Pending | UserStatus::Blocked => self.pending,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_second: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let capacity = f64::from(config.burst.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_second: config.requests_per_second.max(0.0),
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token from the bucket, returning `false` if none is available.
+    pub fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Throttling counters of a single client, shared between its connections.
+#[derive(Debug, Default)]
+pub struct ConnectionMetrics {
+    pub throttled_messages: AtomicU64,
+    pub dropped_messages: AtomicU64,
+}
+
+impl ConnectionMetrics {
+    pub fn record_throttled(&self) {
+        self.throttled_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped(&self) {
+        self.dropped_messages.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Connection metrics indexed by client fingerprint.
+#[derive(Debug, Default)]
+pub struct ConnectionMetricsRegistry {
+    clients: Mutex<HashMap<String, Arc<ConnectionMetrics>>>,
+}
+
+impl ConnectionMetricsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_create(&self, fingerprint: &str) -> Arc<ConnectionMetrics> {
+        let mut clients = self.clients.lock().unwrap();
+        clients.entry(fingerprint.to_string()).or_default().clone()
+    }
+
+    /// Returns `(throttled, dropped)` for the given client.
+    pub fn snapshot(&self, fingerprint: &str) -> (u64, u64) {
+        let clients = self.clients.lock().unwrap();
+        match clients.get(fingerprint) {
+            Some(metrics) => (
+                metrics.throttled_messages.load(Ordering::Relaxed),
+                metrics.dropped_messages.load(Ordering::Relaxed),
+            ),
+            None => (0, 0),
+        }
+    }
+}
@@ -1,37 +1,25 @@
 mod cli;
 
-#[cfg(target_os = "android")]
-use std::path::Path;
-use std::{
-    sync::{Arc, OnceLock},
-    time::Duration,
-};
+use std::net::SocketAddr;
 
 use anyhow::{Result, bail};
 use clap::{Parser, Subcommand};
-use log::info;
 use rustls::crypto::ring::default_provider;
-use tokio::sync::{Mutex, RwLock};
-use tokio_util::sync::CancellationToken;
 use tracing_subscriber::EnvFilter;
 
 use cli::{
-    broadcast::handle_broadcast, chpwd::handle_chpwd, permission::handle_permission,
-    server::handle_server,
+    audio_devices::handle_audio_devices, broadcast::handle_broadcast, chpwd::handle_chpwd,
+    permission::handle_permission, server::handle_server, token::handle_token,
 };
-use hub::{
-    server::{ServerManager, WebSocketService},
-    utils::{
-        GlobalParams, RunningMode, TaskTokens, initialize_databases, nid::get_or_create_node_id,
-        player::initialize_local_player,
-    },
+use hub::server::{
+    http::rest::openapi_document,
+    limits::{ConnectionLimits, RateLimitConfig},
+    utils::bind::{DEFAULT_SERVER_PORT, ServerLayout},
 };
+use hub::utils::log_filter::{DEFAULT_SERVER_LOG_FILTER, register_log_filter};
 
-use ::database::connection::{MainDbConnection, RecommendationDbConnection};
-use ::discovery::{client::CertValidator, protocol::DiscoveryService, server::PermissionManager};
-use ::fsio::FsIo;
-use ::playback::{player::Player, sfx_player::SfxPlayer};
-use ::scrobbling::manager::ScrobblingManager;
+use ::discovery::endpoint::normalize_path_prefix;
+use ::fsio::{DEFAULT_FREE_SPACE_FLOOR, set_free_space_floor};
 
 #[derive(Parser)]
 #[command(name = "Rune", author = "Rune Developers", version)]
@@ -44,20 +32,67 @@ struct Cli {
 enum Commands {
     /// Start the server
     Server {
-        #[arg(short, long, default_value = "127.0.0.1:7863")]
-        addr: String,
+        /// Address, IP or interface name to listen on, can be repeated
+        #[arg(short, long, default_value = "127.0.0.1")]
+        addr: Vec<String>,
+        /// Port used for addresses given without one
+        #[arg(short, long, default_value_t = DEFAULT_SERVER_PORT)]
+        port: u16,
+        /// Accept WebSocket connections on a separate port
+        #[arg(long)]
+        ws_port: Option<u16>,
+        /// Serve every route below this path, e.g. `/rune` behind a reverse proxy
+        #[arg(long, default_value = "")]
+        path_prefix: String,
+        /// Refuse to start if any of the addresses cannot be bound
+        #[arg(long)]
+        strict_bind: bool,
         #[arg(required = true, index = 1)]
         lib_path: String,
+        /// Requests per second accepted from each approved client
+        #[arg(long, default_value_t = 50.0)]
+        rate_limit: f64,
+        /// Number of requests an approved client can send in a burst
+        #[arg(long, default_value_t = 100)]
+        rate_burst: u32,
+        /// Largest WebSocket message accepted from a client, in bytes
+        #[arg(long, default_value_t = 16 * 1024 * 1024)]
+        max_frame_size: usize,
+        /// Record read-only requests in the audit log as well
+        #[arg(long)]
+        audit_verbose: bool,
+        /// Serve Prometheus metrics over plain HTTP on this address, disabled by default
+        #[arg(long)]
+        metrics_addr: Option<SocketAddr>,
+        /// Space in bytes that caches and downloads leave free on a volume
+        #[arg(long, default_value_t = DEFAULT_FREE_SPACE_FLOOR)]
+        min_free_space: u64,
+        /// Id of the audio device to play on, as listed by `audio-devices`
+        #[arg(long)]
+        audio_device: Option<String>,
     },
     /// Initialize or change root password
     Chpwd,
     /// Broadcast presence to the local network
     Broadcast,
+    /// List the audio devices the server can play on
+    AudioDevices,
     /// Manage device permissions
     Permission {
         #[command(subcommand)]
         action: PermissionAction,
     },
+    /// Manage the tokens accepted by the HTTP API
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+    /// Print the OpenAPI document of the HTTP API
+    Openapi {
+        /// Path prefix the server is mounted at
+        #[arg(long, default_value = "")]
+        path_prefix: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -79,6 +114,33 @@ enum PermissionAction {
         #[arg(value_name = "INDEX")]
         index: usize,
     },
+    /// Issue a Subsonic login for a user, named after its alias
+    Subsonic {
+        /// User index number
+        #[arg(value_name = "INDEX")]
+        index: usize,
+        /// Remove the login instead of issuing a new password
+        #[arg(long)]
+        revoke: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenAction {
+    /// List all API tokens
+    Ls,
+    /// Create an API token and print it
+    Create {
+        /// Name describing what the token is used for
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+    /// Revoke an API token
+    Revoke {
+        /// Id or name of the token
+        #[arg(value_name = "TOKEN")]
+        token: String,
+    },
 }
 
 #[tokio::main]
@@ -92,97 +154,63 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Server { addr, lib_path } => handle_server(addr, lib_path).await?,
+        Commands::Server {
+            addr,
+            port,
+            ws_port,
+            path_prefix,
+            strict_bind,
+            lib_path,
+            rate_limit,
+            rate_burst,
+            max_frame_size,
+            audit_verbose,
+            metrics_addr,
+            min_free_space,
+            audio_device,
+        } => {
+            set_free_space_floor(min_free_space);
+
+            let limits = ConnectionLimits {
+                approved: RateLimitConfig {
+                    requests_per_second: rate_limit,
+                    burst: rate_burst,
+                },
+                max_frame_size,
+                ..Default::default()
+            };
+            handle_server(
+                addr,
+                ServerLayout::new(port, ws_port, &path_prefix),
+                strict_bind,
+                lib_path,
+                limits,
+                audit_verbose,
+                metrics_addr,
+                audio_device,
+            )
+            .await?
+        }
         Commands::Chpwd => handle_chpwd().await?,
         Commands::Broadcast => handle_broadcast().await?,
+        Commands::AudioDevices => handle_audio_devices()?,
         Commands::Permission { action } => handle_permission(action).await?,
+        Commands::Token { action } => handle_token(action).await?,
+        Commands::Openapi { path_prefix } => {
+            let document = openapi_document(&normalize_path_prefix(&path_prefix));
+            println!("{}", serde_json::to_string_pretty(&document)?);
+        }
     }
 
     Ok(())
 }
 
 fn setup_logging() {
-    let filter = EnvFilter::new(
-        "symphonia_format_ogg=off,symphonia_core=off,symphonia_bundle_mp3::demuxer=off,\
-         tantivy::directory=off,tantivy::indexer=off,sea_orm_migration::migrator=off,info",
-    );
-    tracing_subscriber::fmt().with_env_filter(filter).init();
-}
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(DEFAULT_SERVER_LOG_FILTER))
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    builder.init();
 
-async fn initialize_global_params(lib_path: &str, config_path: &str) -> Result<Arc<GlobalParams>> {
-    #[cfg(not(target_os = "android"))]
-    let fsio = Arc::new(FsIo::new());
-    #[cfg(target_os = "android")]
-    let fsio = Arc::new(FsIo::new(Path::new(".rune/.android-fs.db"), &lib_path)?);
-
-    let db_path = format!("{lib_path}/.rune");
-    let node_id = Arc::new(get_or_create_node_id(&fsio, config_path).await?.to_string());
-
-    let db_connections = initialize_databases(&fsio, lib_path, Some(&db_path), &node_id).await?;
-
-    let main_db: Arc<MainDbConnection> = db_connections.main_db;
-    let recommend_db: Arc<RecommendationDbConnection> = db_connections.recommend_db;
-    let lib_path: Arc<String> = Arc::new(lib_path.to_string());
-    let config_path: Arc<String> = Arc::new(config_path.to_string());
-
-    let main_cancel_token = CancellationToken::new();
-    let task_tokens: Arc<Mutex<TaskTokens>> = Arc::new(Mutex::new(TaskTokens::default()));
-
-    info!("Initializing player");
-    let player = Player::new(Some(main_cancel_token.clone()));
-    let player: Arc<Mutex<Player>> = Arc::new(Mutex::new(player));
-
-    let sfx_player = SfxPlayer::new(Some(main_cancel_token.clone()));
-    let sfx_player: Arc<Mutex<SfxPlayer>> = Arc::new(Mutex::new(sfx_player));
-
-    let main_cancel_token = Arc::new(main_cancel_token);
-
-    let scrobbler = ScrobblingManager::new(10, Duration::new(5, 0));
-    let scrobbler = Arc::new(Mutex::new(scrobbler));
-
-    let broadcaster = Arc::new(WebSocketService::new());
-    let device_scanner = Arc::new(DiscoveryService::without_store());
-
-    let permission_manager = Arc::new(RwLock::new(PermissionManager::new(config_path.as_str())?));
-    let cert_validator = Arc::new(RwLock::new(CertValidator::new(config_path.as_str()).await?));
-
-    info!("Initializing Player events");
-    tokio::spawn(initialize_local_player(
-        fsio.clone(),
-        lib_path.clone(),
-        main_db.clone(),
-        player.clone(),
-        scrobbler.clone(),
-        broadcaster.clone(),
-        cert_validator.clone(),
-        permission_manager.clone(),
-    ));
-
-    let global_params = Arc::new(GlobalParams {
-        fsio,
-        lib_path,
-        config_path,
-        node_id,
-        main_db,
-        recommend_db,
-        main_token: main_cancel_token,
-        task_tokens,
-        player,
-        sfx_player,
-        scrobbler,
-        broadcaster,
-        device_scanner,
-        cert_validator,
-        permission_manager,
-        server_manager: OnceLock::new(),
-        running_mode: RunningMode::Server,
-    });
-
-    let server_manager = Arc::new(ServerManager::new(global_params.clone()).await?);
-    global_params
-        .server_manager
-        .set(server_manager.clone())
-        .expect("Failed to set server manager in global params");
-
-    Ok(global_params)
+    register_log_filter(handle, DEFAULT_SERVER_LOG_FILTER);
 }
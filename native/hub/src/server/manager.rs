@@ -15,9 +15,9 @@ use axum::{
 };
 use axum_server::{Handle, tls_rustls::RustlsConfig};
 use base64::{Engine as _, engine::general_purpose::URL_SAFE};
-use database::actions::cover_art::COVER_TEMP_DIR;
+use database::actions::{cover_art::COVER_TEMP_DIR, utils::checkpoint_database};
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
-use log::{error, info};
+use log::{error, info, warn};
 use rand::{
     RngCore,
     distributions::{Alphanumeric, DistString},
@@ -28,6 +28,7 @@ use tokio::{
     fs::{read_to_string, write},
     sync::{Mutex, RwLock},
     task::JoinHandle,
+    time::timeout,
 };
 use tower_governor::{
     GovernorLayer, governor::GovernorConfigBuilder, key_extractor::PeerIpKeyExtractor,
@@ -41,13 +42,21 @@ use crate::{
     messages::*,
     server::{
         AppState, ServerState, WebSocketService,
+        admin::{AdminAuth, hash_password, verify_password},
+        api_tokens::ApiTokenStore,
+        audit::AuditLogger,
+        connections::ConnectionRegistry,
         http::{
             check_fingerprint::check_fingerprint_handler,
             device_info::device_info_handler,
             file::file_handler,
             list::list_users_handler,
-            media::{get_cover_art_handler, get_media_metadata_handler},
+            media::{get_cover_art_handler, get_media_file_handler, get_media_metadata_handler},
+            metrics::metrics_handler,
             panel_alias::update_alias_handler,
+            panel_api_tokens::{
+                create_api_token_handler, list_api_tokens_handler, revoke_api_token_handler,
+            },
             panel_auth_middleware::auth_middleware,
             panel_broadcast::toggle_broadcast_handler,
             panel_delete_user::delete_user_handler,
@@ -57,10 +66,16 @@ use crate::{
             panel_status::update_user_status_handler,
             ping::ping_handler,
             register::register_handler,
+            rest::{REST_PREFIX, rest_router},
             websocket::websocket_handler,
         },
+        limits::{ConnectionLimits, ConnectionMetricsRegistry},
+        metrics::ServerMetrics,
+        shutdown::ShutdownCoordinator,
+        subsonic::{SUBSONIC_PREFIX, credentials::SubsonicCredentialStore, subsonic_router},
+        utils::bind::ServerLayout,
     },
-    utils::{GlobalParams, ParamsExtractor, RinfRustSignal},
+    utils::{Broadcaster, GlobalParams, ParamsExtractor, RinfRustSignal},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -85,17 +100,42 @@ impl JwtClaims {
     }
 }
 
+/// How long in-flight requests may run once a shutdown began.
+const REQUEST_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long library tasks may take to acknowledge their cancellation.
+const TASK_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long connections may take to close after being asked to.
+const CONNECTION_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of binding the server to a list of addresses.
+#[derive(Debug, Default)]
+pub struct ServerStartReport {
+    pub endpoints: Vec<SocketAddr>,
+    pub failures: Vec<(SocketAddr, String)>,
+}
+
 #[derive(Debug)]
 pub struct ServerManager {
     pub global_params: Arc<GlobalParams>,
-    server_handle: Mutex<Option<JoinHandle<()>>>,
-    addr: Mutex<Option<SocketAddr>>,
+    server_handles: Mutex<Vec<JoinHandle<()>>>,
+    addrs: Mutex<Vec<SocketAddr>>,
     is_running: std::sync::atomic::AtomicBool,
     shutdown_handle: Mutex<Option<Handle>>,
+    shutdown: Mutex<Option<(Arc<ShutdownCoordinator>, Arc<WebSocketService>)>>,
     certificate: String,
     private_key: String,
     pub jwt_secret: Vec<u8>,
+    pub admin_auth: Arc<AdminAuth>,
+    pub api_tokens: Arc<ApiTokenStore>,
+    pub subsonic_credentials: Arc<SubsonicCredentialStore>,
     pub fsio: Arc<FsIo>,
+    connection_limits: Mutex<ConnectionLimits>,
+    pub connection_metrics: Arc<ConnectionMetricsRegistry>,
+    pub connection_registry: Arc<ConnectionRegistry>,
+    pub audit_logger: Arc<AuditLogger>,
+    pub metrics: Arc<ServerMetrics>,
+    metrics_addr: Mutex<Option<SocketAddr>>,
+    layout: Mutex<ServerLayout>,
 }
 
 impl ServerManager {
@@ -112,6 +152,9 @@ impl ServerManager {
         let jwt_secret = get_or_generate_jwt_secret(config_path)
             .await
             .context("Failed to initialize JWT secret")?;
+        let admin_auth = Arc::new(AdminAuth::new(config_path, &jwt_secret));
+        let api_tokens = Arc::new(ApiTokenStore::new(config_path));
+        let subsonic_credentials = Arc::new(SubsonicCredentialStore::new(config_path));
 
         #[cfg(not(target_os = "android"))]
         let fsio = Arc::new(FsIo::new());
@@ -123,22 +166,60 @@ impl ServerManager {
 
         Ok(Self {
             global_params,
-            server_handle: Mutex::new(None),
-            addr: Mutex::new(None),
+            server_handles: Mutex::new(Vec::new()),
+            addrs: Mutex::new(Vec::new()),
             is_running: AtomicBool::new(false),
             shutdown_handle: Mutex::new(None),
+            shutdown: Mutex::new(None),
             certificate,
             private_key,
             jwt_secret,
+            admin_auth,
+            api_tokens,
+            subsonic_credentials,
             fsio,
+            connection_limits: Mutex::new(ConnectionLimits::default()),
+            connection_metrics: Arc::new(ConnectionMetricsRegistry::new()),
+            connection_registry: Arc::new(ConnectionRegistry::new()),
+            audit_logger: Arc::new(AuditLogger::default()),
+            metrics: Arc::new(ServerMetrics::new()),
+            metrics_addr: Mutex::new(None),
+            layout: Mutex::new(ServerLayout::default()),
         })
     }
 
+    /// Replaces the limits applied to WebSocket connections, takes effect on the next start.
+    pub async fn set_connection_limits(&self, limits: ConnectionLimits) {
+        *self.connection_limits.lock().await = limits;
+    }
+
+    /// Serves the metrics endpoint over plain HTTP on `addr`, takes effect on
+    /// the next start. The endpoint is disabled when no address is set.
+    pub async fn set_metrics_address(&self, addr: Option<SocketAddr>) {
+        *self.metrics_addr.lock().await = addr;
+    }
+
+    /// Replaces the ports and path prefix of the server, takes effect on the next start.
+    pub async fn set_layout(&self, layout: ServerLayout) {
+        *self.layout.lock().await = layout;
+    }
+
+    pub async fn get_layout(&self) -> ServerLayout {
+        self.layout.lock().await.clone()
+    }
+
+    /// Starts an acceptor for every address in `addrs`.
+    ///
+    /// Addresses that fail to bind are reported in the returned
+    /// [`ServerStartReport`]; unless `strict` is set the server keeps running
+    /// on the remaining ones. When the layout splits the WebSocket port, an
+    /// extra acceptor is started on that port for every bound address.
     pub async fn start(
         self: Arc<Self>,
-        addr: SocketAddr,
+        addrs: Vec<SocketAddr>,
         discovery_params: DiscoveryParams,
-    ) -> Result<()>
+        strict: bool,
+    ) -> Result<ServerStartReport>
     where
         Self: Send,
     {
@@ -146,6 +227,71 @@ impl ServerManager {
             return Err(anyhow::anyhow!("Server already running"));
         }
 
+        if addrs.is_empty() {
+            return Err(anyhow::anyhow!("No address to bind the server to"));
+        }
+
+        let mut report = ServerStartReport::default();
+        let mut listeners = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            match bind_listener(addr) {
+                Ok(listener) => {
+                    report.endpoints.push(addr);
+                    listeners.push((addr, listener));
+                }
+                Err(e) => {
+                    error!("Failed to bind {addr}: {e}");
+                    report.failures.push((addr, e.to_string()));
+                }
+            }
+        }
+
+        if listeners.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Failed to bind any address: {}",
+                format_bind_failures(&report.failures)
+            ));
+        }
+
+        let layout = self.layout.lock().await.clone();
+
+        let mut ws_listeners = Vec::new();
+        if let Some(ws_port) = layout.ws_port {
+            let ws_addrs: Vec<_> = report
+                .endpoints
+                .iter()
+                .map(|x| SocketAddr::new(x.ip(), ws_port))
+                .collect();
+
+            for addr in ws_addrs {
+                match bind_listener(addr) {
+                    Ok(listener) => ws_listeners.push((addr, listener)),
+                    Err(e) => {
+                        error!("Failed to bind {addr}: {e}");
+                        report.failures.push((addr, e.to_string()));
+                    }
+                }
+            }
+
+            if ws_listeners.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Failed to bind any WebSocket address: {}",
+                    format_bind_failures(&report.failures)
+                ));
+            }
+        }
+
+        if strict && !report.failures.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Failed to bind some addresses: {}",
+                format_bind_failures(&report.failures)
+            ));
+        }
+
+        let mut device_info = discovery_params.device_info;
+        device_info.endpoints = report.endpoints.iter().map(|x| x.to_string()).collect();
+        layout.advertise(&mut device_info);
+
         let websocket_service = Arc::new(WebSocketService::new());
 
         for_all_request_pairs2!(
@@ -162,11 +308,18 @@ impl ServerManager {
         let server_state = Arc::new(ServerState {
             app_state: app_state.clone(),
             websocket_service: websocket_service.clone(),
-            discovery_device_info: Arc::new(RwLock::new(discovery_params.device_info)),
+            discovery_device_info: Arc::new(RwLock::new(device_info)),
             permission_manager: self.global_params.permission_manager.clone(),
             device_scanner: self.global_params.device_scanner.clone(),
             fsio: Arc::clone(&self.fsio),
+            connection_limits: Arc::new(self.connection_limits.lock().await.clone()),
+            connection_metrics: Arc::clone(&self.connection_metrics),
+            connection_registry: Arc::clone(&self.connection_registry),
+            metrics: Arc::clone(&self.metrics),
+            shutdown: Arc::new(ShutdownCoordinator::new()),
+            layout: Arc::new(layout.clone()),
         });
+        let shutdown = Arc::clone(&server_state.shutdown);
 
         let governor_conf = GovernorConfigBuilder::default()
             .per_second(60)
@@ -190,6 +343,11 @@ impl ServerManager {
                 "/panel/users/{fingerprint}/status",
                 put(update_user_status_handler),
             )
+            .route(
+                "/panel/api-tokens",
+                get(list_api_tokens_handler).post(create_api_token_handler),
+            )
+            .route("/panel/api-tokens/{id}", delete(revoke_api_token_handler))
             .layer(middleware::from_fn(auth_middleware))
             .layer(Extension(self.clone()))
             .with_state(server_state.clone());
@@ -200,20 +358,39 @@ impl ServerManager {
                 config: governor_conf.into(),
             });
 
-        let app = Router::new()
+        let routes = Router::new()
             .merge(register_route)
             .merge(auth_routes)
             .merge(protected_routes)
             .route("/ping", get(ping_handler))
-            .route("/ws", get(websocket_handler))
             .route("/check-fingerprint", get(check_fingerprint_handler))
             .route("/files/{*file_path}", get(file_handler))
             .route("/device-info", get(device_info_handler))
             .route("/media/metadata/:id", get(get_media_metadata_handler))
             .route("/media/cover/:id", get(get_cover_art_handler))
-            .with_state(server_state)
+            .route("/media/file/{id}", get(get_media_file_handler))
+            .nest(REST_PREFIX, rest_router())
+            .nest(SUBSONIC_PREFIX, subsonic_router());
+
+        // WebSocket connections get their own acceptors when the port is split
+        let routes = match layout.ws_port {
+            Some(_) => routes,
+            None => routes.route("/ws", get(websocket_handler)),
+        };
+
+        let app = with_path_prefix(routes, &layout.path_prefix)
+            .with_state(server_state.clone())
             .layer(Extension(self.clone()));
 
+        let ws_app = with_path_prefix(
+            Router::new()
+                .route("/ping", get(ping_handler))
+                .route("/ws", get(websocket_handler)),
+            &layout.path_prefix,
+        )
+        .with_state(server_state.clone())
+        .layer(Extension(self.clone()));
+
         info!(
             "Library files path: {}",
             app_state.lib_path.to_string_lossy()
@@ -235,24 +412,64 @@ impl ServerManager {
             .context("Failed to create TLS configuration")?
         };
 
-        let server_handle = tokio::spawn(async move {
-            info!("Starting secure HTTPS/WSS server on {addr}");
-            let server = axum_server::bind_rustls(addr, tls_config)
-                .handle(handle)
-                .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+        let app = app.into_make_service_with_connect_info::<SocketAddr>();
+        let ws_app = ws_app.into_make_service_with_connect_info::<SocketAddr>();
+        let acceptors = listeners
+            .into_iter()
+            .map(|(addr, listener)| (addr, listener, app.clone()))
+            .chain(
+                ws_listeners
+                    .into_iter()
+                    .map(|(addr, listener)| (addr, listener, ws_app.clone())),
+            );
+
+        let mut server_handles = Vec::new();
+        for (addr, listener, app) in acceptors {
+            let server = axum_server::from_tcp_rustls(listener, tls_config.clone())
+                .handle(handle.clone())
+                .serve(app);
+
+            server_handles.push(tokio::spawn(async move {
+                info!("Starting secure HTTPS/WSS server on {addr}");
+                match server.await {
+                    Ok(_) => info!("Server on {addr} stopped gracefully"),
+                    Err(e) => error!("Server error on {addr}: {e}"),
+                }
+            }));
+        }
 
-            match server.await {
-                Ok(_) => info!("Server stopped gracefully"),
-                Err(e) => error!("Server error: {e}"),
+        let metrics_addr = *self.metrics_addr.lock().await;
+        if let Some(addr) = metrics_addr {
+            match bind_listener(addr) {
+                Ok(listener) => {
+                    let metrics_app = Router::new()
+                        .route("/metrics", get(metrics_handler))
+                        .with_state(server_state)
+                        .layer(Extension(self.clone()));
+
+                    let server = axum_server::from_tcp(listener)
+                        .handle(handle.clone())
+                        .serve(metrics_app.into_make_service());
+
+                    server_handles.push(tokio::spawn(async move {
+                        info!("Starting metrics endpoint on http://{addr}/metrics");
+                        match server.await {
+                            Ok(_) => info!("Metrics endpoint on {addr} stopped gracefully"),
+                            Err(e) => error!("Metrics endpoint error on {addr}: {e}"),
+                        }
+                    }));
+                }
+                Err(e) => error!("Failed to bind the metrics endpoint to {addr}: {e}"),
             }
-        });
+        }
 
-        *self.server_handle.lock().await = Some(server_handle);
-        *self.addr.lock().await = Some(addr);
+        *self.server_handles.lock().await = server_handles;
+        *self.addrs.lock().await = report.endpoints.clone();
         *self.shutdown_handle.lock().await = Some(shutdown_handle);
+        *self.shutdown.lock().await = Some((shutdown, websocket_service));
         self.is_running.store(true, Ordering::SeqCst);
 
-        Ok(())
+        Ok(report)
     }
 
     pub async fn stop(&self) -> Result<()> {
@@ -260,27 +477,79 @@ impl ServerManager {
             return Err(anyhow::anyhow!("Server not running"));
         }
 
+        let shutdown = self.shutdown.lock().await.take();
+        if let Some((coordinator, websocket_service)) = &shutdown {
+            info!("Shutting down the server");
+            coordinator.begin();
+            websocket_service.broadcast(&ServerShuttingDown {
+                countdown_secs: REQUEST_DRAIN_TIMEOUT.as_secs() as u32,
+            });
+
+            if !coordinator.drain(REQUEST_DRAIN_TIMEOUT).await {
+                warn!("Some requests did not complete before the shutdown timeout");
+            }
+        }
+
+        self.drain_library_tasks().await;
+
+        if let Some((coordinator, _)) = &shutdown {
+            coordinator.close_connections();
+        }
+
         if let Some(handle) = self.shutdown_handle.lock().await.as_ref() {
-            handle.shutdown();
+            handle.graceful_shutdown(Some(CONNECTION_CLOSE_TIMEOUT));
         }
 
-        if let Some(handle) = self.server_handle.lock().await.take() {
+        let server_handles = std::mem::take(&mut *self.server_handles.lock().await);
+        for handle in server_handles {
             handle.await?;
         }
 
-        *self.addr.lock().await = None;
+        self.checkpoint_databases().await;
+
+        self.addrs.lock().await.clear();
         *self.shutdown_handle.lock().await = None;
         self.is_running.store(false, Ordering::SeqCst);
 
         Ok(())
     }
 
+    /// Cancels the running library tasks and waits for them to stop.
+    async fn drain_library_tasks(&self) {
+        let task_registry = &self.global_params.task_registry;
+        task_registry.cancel_all();
+        let tasks = task_registry.tracker();
+
+        tasks.close();
+        if timeout(TASK_DRAIN_TIMEOUT, tasks.wait()).await.is_err() {
+            warn!(
+                "{} library tasks did not stop before the shutdown timeout",
+                tasks.len()
+            );
+        }
+        tasks.reopen();
+    }
+
+    async fn checkpoint_databases(&self) {
+        if let Err(e) = checkpoint_database(&self.global_params.main_db).await {
+            error!("Failed to checkpoint the library database: {e}");
+        }
+
+        if let Err(e) = self.global_params.recommend_db.env.force_sync() {
+            error!("Failed to sync the recommendation database: {e}");
+        }
+    }
+
     pub fn is_running(&self) -> bool {
         self.is_running.load(Ordering::SeqCst)
     }
 
     pub async fn get_address(&self) -> Option<SocketAddr> {
-        *self.addr.lock().await
+        self.addrs.lock().await.first().copied()
+    }
+
+    pub async fn get_addresses(&self) -> Vec<SocketAddr> {
+        self.addrs.lock().await.clone()
     }
 
     pub fn generate_jwt_token(&self, validity: Option<Duration>) -> Result<String> {
@@ -308,6 +577,32 @@ impl ServerManager {
     }
 }
 
+fn bind_listener(addr: SocketAddr) -> std::io::Result<std::net::TcpListener> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Mounts `router` below `path_prefix`, which must be normalized.
+fn with_path_prefix<S>(router: Router<S>, path_prefix: &str) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if path_prefix.is_empty() {
+        router
+    } else {
+        Router::new().nest(path_prefix, router)
+    }
+}
+
+fn format_bind_failures(failures: &[(SocketAddr, String)]) -> String {
+    failures
+        .iter()
+        .map(|(addr, e)| format!("{addr} ({e})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 pub async fn get_or_generate_alias(config_path: &Path) -> Result<String> {
     info!("Generating certificate in: {config_path:?}");
 
@@ -378,6 +673,29 @@ pub async fn generate_or_load_certificates<P: AsRef<Path>>(
     }
 }
 
+/// Replaces the alias and client certificate of this device, for example
+/// with the identity of another controller imported from a trust bundle.
+pub async fn install_client_identity<P: AsRef<Path>>(
+    config_path: P,
+    alias: &str,
+    certificate: &str,
+    private_key: &str,
+) -> Result<()> {
+    let config_path: &Path = config_path.as_ref();
+
+    parse_certificate(certificate).context("Invalid client certificate")?;
+
+    tokio::fs::write(config_path.join("certificate.pem"), certificate)
+        .await
+        .context("Failed to save certificate")?;
+    tokio::fs::write(config_path.join("private_key.pem"), private_key)
+        .await
+        .context("Failed to save private key")?;
+    update_alias(config_path, alias).await?;
+
+    Ok(())
+}
+
 async fn get_or_generate_jwt_secret<P: AsRef<Path>>(config_path: P) -> Result<Vec<u8>> {
     let config_path: &Path = config_path.as_ref();
 
@@ -412,7 +730,35 @@ pub async fn update_root_password<P: AsRef<Path>>(config_path: P, password: &str
         return Err(anyhow::anyhow!("Root password already initialized"));
     }
 
-    let hash = bcrypt::hash(password, bcrypt::DEFAULT_COST).context("Failed to hash password")?;
+    let hash = hash_password(password)?;
+
+    tokio::fs::write(&password_path, &hash)
+        .await
+        .context("Failed to write password hash")?;
+
+    Ok(())
+}
+
+/// Replaces the root password after checking the current one, every admin
+/// token issued under the previous password stops being accepted.
+pub async fn change_root_password<P: AsRef<Path>>(
+    config_path: P,
+    current_password: &str,
+    new_password: &str,
+) -> Result<()> {
+    let config_path: &Path = config_path.as_ref();
+
+    let password_path = config_path.join("root_password.hash");
+
+    let stored_hash = tokio::fs::read_to_string(&password_path)
+        .await
+        .context("Root password not initialized")?;
+
+    if !verify_password(current_password, &stored_hash)? {
+        return Err(anyhow::anyhow!("Invalid password"));
+    }
+
+    let hash = hash_password(new_password)?;
 
     tokio::fs::write(&password_path, &hash)
         .await
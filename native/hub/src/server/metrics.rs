@@ -0,0 +1,231 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Upper bounds of the request latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RequestMetrics {
+    handled: u64,
+    failed: u64,
+    latency: Histogram,
+}
+
+/// Server-wide counters exposed on the metrics endpoint.
+#[derive(Debug, Default)]
+pub struct ServerMetrics {
+    requests: Mutex<HashMap<String, RequestMetrics>>,
+    broadcasts: Mutex<HashMap<String, u64>>,
+    tasks: Mutex<HashMap<String, bool>>,
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_request(&self, request_type: &str, latency: Duration, success: bool) {
+        let mut requests = self.requests.lock().unwrap();
+        let metrics = requests.entry(request_type.to_string()).or_default();
+        metrics.handled += 1;
+        if !success {
+            metrics.failed += 1;
+        }
+        metrics.latency.observe(latency.as_secs_f64());
+    }
+
+    pub fn record_broadcast(&self, message_type: &str) {
+        let mut broadcasts = self.broadcasts.lock().unwrap();
+        *broadcasts.entry(message_type.to_string()).or_default() += 1;
+    }
+
+    /// Marks a long running library task, like scanning or analysis, as
+    /// running or idle.
+    pub fn set_task_running(&self, task: &str, running: bool) {
+        self.tasks.lock().unwrap().insert(task.to_string(), running);
+    }
+
+    /// Renders the counters in the Prometheus text exposition format.
+    pub fn render(&self, out: &mut MetricsWriter) {
+        let requests: BTreeMap<_, _> = self
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        out.header(
+            "rune_requests_total",
+            "counter",
+            "Remote requests handled, by request type.",
+        );
+        for (request_type, metrics) in &requests {
+            out.sample(
+                "rune_requests_total",
+                &[("type", request_type)],
+                metrics.handled as f64,
+            );
+        }
+
+        out.header(
+            "rune_request_failures_total",
+            "counter",
+            "Remote requests that failed, by request type.",
+        );
+        for (request_type, metrics) in &requests {
+            out.sample(
+                "rune_request_failures_total",
+                &[("type", request_type)],
+                metrics.failed as f64,
+            );
+        }
+
+        out.header(
+            "rune_request_duration_seconds",
+            "histogram",
+            "Time spent handling remote requests, by request type.",
+        );
+        for (request_type, metrics) in &requests {
+            let histogram = &metrics.latency;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                out.sample(
+                    "rune_request_duration_seconds_bucket",
+                    &[("type", request_type), ("le", &bound.to_string())],
+                    count as f64,
+                );
+            }
+            out.sample(
+                "rune_request_duration_seconds_bucket",
+                &[("type", request_type), ("le", "+Inf")],
+                histogram.count as f64,
+            );
+            out.sample(
+                "rune_request_duration_seconds_sum",
+                &[("type", request_type)],
+                histogram.sum,
+            );
+            out.sample(
+                "rune_request_duration_seconds_count",
+                &[("type", request_type)],
+                histogram.count as f64,
+            );
+        }
+
+        let broadcasts: BTreeMap<_, _> = self
+            .broadcasts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect();
+
+        out.header(
+            "rune_broadcast_messages_total",
+            "counter",
+            "Broadcast messages delivered to clients, by message type.",
+        );
+        for (message_type, count) in &broadcasts {
+            out.sample(
+                "rune_broadcast_messages_total",
+                &[("type", message_type)],
+                *count as f64,
+            );
+        }
+
+        let tasks: BTreeMap<_, _> = self
+            .tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect();
+
+        out.header(
+            "rune_task_running",
+            "gauge",
+            "Whether a library task is running.",
+        );
+        for (task, running) in &tasks {
+            out.sample(
+                "rune_task_running",
+                &[("task", task)],
+                if *running { 1.0 } else { 0.0 },
+            );
+        }
+    }
+}
+
+/// Builds a response in the Prometheus text exposition format.
+#[derive(Debug, Default)]
+pub struct MetricsWriter {
+    buffer: String,
+}
+
+impl MetricsWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn header(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.buffer, "# HELP {name} {help}");
+        let _ = writeln!(self.buffer, "# TYPE {name} {kind}");
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.buffer.push_str(name);
+
+        if !labels.is_empty() {
+            let labels = labels
+                .iter()
+                .map(|(key, value)| format!("{key}=\"{}\"", escape_label_value(value)))
+                .collect::<Vec<_>>()
+                .join(",");
+            let _ = write!(self.buffer, "{{{labels}}}");
+        }
+
+        let _ = writeln!(self.buffer, " {value}");
+    }
+
+    /// Writes a metric that has a single unlabeled sample.
+    pub fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        self.header(name, "gauge", help);
+        self.sample(name, &[], value);
+    }
+
+    pub fn finish(self) -> String {
+        self.buffer
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
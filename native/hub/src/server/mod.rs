@@ -1,13 +1,25 @@
 #[macro_use]
 mod server_request;
+pub mod admin;
 pub mod api;
+pub mod api_tokens;
+pub mod audit;
+pub mod connections;
 pub mod http;
+pub mod limits;
 mod manager;
+pub mod metrics;
+pub mod protocol;
+pub mod runner;
+pub mod scope;
+pub mod shutdown;
+pub mod subsonic;
 pub mod utils;
 
 use fsio::FsIo;
 pub use manager::{
-    ServerManager, generate_or_load_certificates, get_or_generate_alias, update_root_password,
+    ServerManager, change_root_password, generate_or_load_certificates, get_or_generate_alias,
+    install_client_identity, update_root_password,
 };
 
 use std::{collections::HashMap, future::Future, path::PathBuf, pin::Pin, sync::Arc};
@@ -21,6 +33,13 @@ use ::discovery::{protocol::DiscoveryService, server::PermissionManager, utils::
 use crate::{
     Session,
     backends::remote::encode_message,
+    server::{
+        connections::ConnectionRegistry,
+        limits::{ConnectionLimits, ConnectionMetricsRegistry},
+        metrics::ServerMetrics,
+        shutdown::ShutdownCoordinator,
+        utils::bind::ServerLayout,
+    },
     utils::{Broadcaster, RinfRustSignal},
 };
 
@@ -45,6 +64,12 @@ pub struct ServerState {
     pub permission_manager: Arc<RwLock<PermissionManager>>,
     pub device_scanner: Arc<DiscoveryService>,
     pub fsio: Arc<FsIo>,
+    pub connection_limits: Arc<ConnectionLimits>,
+    pub connection_metrics: Arc<ConnectionMetricsRegistry>,
+    pub connection_registry: Arc<ConnectionRegistry>,
+    pub metrics: Arc<ServerMetrics>,
+    pub shutdown: Arc<ShutdownCoordinator>,
+    pub layout: Arc<ServerLayout>,
 }
 
 pub struct WebSocketService {
@@ -109,6 +134,14 @@ impl Broadcaster for WebSocketService {
     }
 }
 
+impl std::fmt::Debug for WebSocketService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketService")
+            .field("receivers", &self.broadcast_tx.receiver_count())
+            .finish()
+    }
+}
+
 impl Clone for WebSocketService {
     fn clone(&self) -> Self {
         WebSocketService {
@@ -0,0 +1,142 @@
+//! The protocol spoken over WebSocket connections, which peers announce when
+//! connecting so clients find the requests a server cannot answer up front.
+
+use std::collections::{HashMap, HashSet};
+
+pub use crate::macros::{PROTOCOL_HASH, PROTOCOL_VERSION, SERVED_REQUEST_TYPES};
+use crate::messages::{ServerCapabilitiesResponse, UnsupportedRequestResponse};
+
+/// The query parameter of WebSocket URLs carrying the protocol version of
+/// the client.
+pub const PROTOCOL_PARAM: &str = "protocol";
+
+/// The version of the peers predating the negotiation, which send none.
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+
+/// The protocol version announced in the query of a connection.
+pub fn peer_protocol_version(params: &HashMap<String, String>) -> u32 {
+    params
+        .get(PROTOCOL_PARAM)
+        .and_then(|x| x.trim().parse().ok())
+        .unwrap_or(LEGACY_PROTOCOL_VERSION)
+}
+
+/// Adds the protocol version of this build to the query of a WebSocket URL.
+pub fn with_protocol_version(url: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}{PROTOCOL_PARAM}={PROTOCOL_VERSION}")
+}
+
+pub fn server_capabilities() -> ServerCapabilitiesResponse {
+    ServerCapabilitiesResponse {
+        protocol_version: PROTOCOL_VERSION,
+        protocol_hash: format!("{PROTOCOL_HASH:016x}"),
+        requests: SERVED_REQUEST_TYPES.iter().map(|x| x.to_string()).collect(),
+    }
+}
+
+/// The answer to a request a server speaking `protocol_version` has no
+/// handler for.
+pub fn unsupported_request(request: &str, protocol_version: u32) -> UnsupportedRequestResponse {
+    UnsupportedRequestResponse {
+        request: request.to_owned(),
+        protocol_version,
+        detail: format!(
+            "{request} is not supported by the server, which speaks protocol version \
+             {protocol_version}"
+        ),
+    }
+}
+
+/// The requests a server answers, as told by its capabilities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCapabilities {
+    pub protocol_version: u32,
+    /// `None` when the hash sent by the peer cannot be read
+    pub protocol_hash: Option<u64>,
+    requests: HashSet<String>,
+}
+
+impl PeerCapabilities {
+    pub fn from_response(response: &ServerCapabilitiesResponse) -> Self {
+        Self {
+            protocol_version: response.protocol_version,
+            protocol_hash: u64::from_str_radix(&response.protocol_hash, 16).ok(),
+            requests: response.requests.iter().cloned().collect(),
+        }
+    }
+
+    /// Whether the peer speaks exactly the protocol of this build.
+    pub fn is_identical(&self) -> bool {
+        self.protocol_version == PROTOCOL_VERSION && self.protocol_hash == Some(PROTOCOL_HASH)
+    }
+
+    pub fn supports(&self, request: &str) -> bool {
+        self.requests.contains(request)
+    }
+
+    /// The requests this build sends which the peer doesn't answer.
+    pub fn missing(&self) -> Vec<&'static str> {
+        SERVED_REQUEST_TYPES
+            .iter()
+            .copied()
+            .filter(|x| !self.supports(x))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peers_without_a_version_speak_the_legacy_protocol() {
+        let mut params = HashMap::new();
+        assert_eq!(peer_protocol_version(&params), LEGACY_PROTOCOL_VERSION);
+
+        params.insert(PROTOCOL_PARAM.to_owned(), "invalid".to_owned());
+        assert_eq!(peer_protocol_version(&params), LEGACY_PROTOCOL_VERSION);
+
+        params.insert(PROTOCOL_PARAM.to_owned(), PROTOCOL_VERSION.to_string());
+        assert_eq!(peer_protocol_version(&params), PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn the_version_is_added_to_the_query() {
+        assert_eq!(
+            with_protocol_version("wss://host/ws"),
+            format!("wss://host/ws?protocol={PROTOCOL_VERSION}")
+        );
+        assert_eq!(
+            with_protocol_version("wss://host/ws?fingerprint=x"),
+            format!("wss://host/ws?fingerprint=x&protocol={PROTOCOL_VERSION}")
+        );
+    }
+
+    #[test]
+    fn served_requests_are_sorted_and_exclude_local_ones() {
+        assert!(SERVED_REQUEST_TYPES.is_sorted());
+        assert!(SERVED_REQUEST_TYPES.contains(&"ServerCapabilitiesRequest"));
+        assert!(!SERVED_REQUEST_TYPES.contains(&"ConnectRequest"));
+    }
+
+    #[test]
+    fn capabilities_of_older_servers_tell_the_missing_requests() {
+        let capabilities = PeerCapabilities::from_response(&server_capabilities());
+        assert!(capabilities.is_identical());
+        assert!(capabilities.missing().is_empty());
+
+        let mut response = server_capabilities();
+        response.protocol_version = LEGACY_PROTOCOL_VERSION;
+        response.protocol_hash = "not a hash".to_owned();
+        response
+            .requests
+            .retain(|x| x != "ServerCapabilitiesRequest");
+
+        let capabilities = PeerCapabilities::from_response(&response);
+        assert!(!capabilities.is_identical());
+        assert!(!capabilities.supports("ServerCapabilitiesRequest"));
+        assert!(capabilities.supports("SystemInfoRequest"));
+        assert_eq!(capabilities.missing(), ["ServerCapabilitiesRequest"]);
+    }
+}
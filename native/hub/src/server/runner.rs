@@ -0,0 +1,239 @@
+#[cfg(target_os = "android")]
+use std::path::Path;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, bail};
+use log::{info, warn};
+use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
+
+use ::database::connection::{
+    MainDbConnection, RecommendationDbConnection, resolve_library_read_only,
+};
+use ::discovery::{
+    DiscoveryParams, client::CertValidator, config::get_config_dir, protocol::DiscoveryService,
+    server::PermissionManager,
+};
+use ::fsio::FsIo;
+use ::playback::{player::Player, sfx_player::SfxPlayer};
+use ::scrobbling::manager::ScrobblingManager;
+
+use crate::{
+    backends::remote::output::RemoteOutputManager,
+    server::{
+        ServerManager, WebSocketService,
+        limits::ConnectionLimits,
+        utils::{
+            bind::{ServerLayout, resolve_bind_addresses},
+            device::load_device_info,
+        },
+    },
+    utils::{
+        GlobalParams, RunningMode, initialize_databases, nid::get_or_create_node_id,
+        player::initialize_local_player, scrobble_history::DatabaseScrobbleHistory,
+        scrobble_queue::DatabaseScrobbleQueue, task_registry::TaskRegistry,
+    },
+};
+
+/// How often the presence of the server is announced when broadcasting.
+const BROADCAST_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Everything the standalone server needs to start.
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Addresses, IPs or interface names to listen on
+    pub addr: Vec<String>,
+    pub layout: ServerLayout,
+    /// Refuse to start if any of the addresses cannot be bound
+    pub strict_bind: bool,
+    pub limits: ConnectionLimits,
+    /// Record read-only requests in the audit log as well
+    pub audit_verbose: bool,
+    /// Serve Prometheus metrics over plain HTTP on this address
+    pub metrics_addr: Option<SocketAddr>,
+    /// Announce the server on the local network while it runs
+    pub broadcast: bool,
+    /// The id of the audio device to play on, the default one when `None`
+    pub audio_device: Option<String>,
+}
+
+pub async fn initialize_server_params(
+    lib_path: &str,
+    config_path: &str,
+) -> Result<Arc<GlobalParams>> {
+    #[cfg(not(target_os = "android"))]
+    let fsio = Arc::new(FsIo::new());
+    #[cfg(target_os = "android")]
+    let fsio = Arc::new(FsIo::new(Path::new(".rune/.android-fs.db"), &lib_path)?);
+
+    // Read-only libraries keep their databases in the config directory
+    let db_path = if resolve_library_read_only(&fsio, lib_path, Some(config_path)).await? {
+        config_path.to_string()
+    } else {
+        format!("{lib_path}/.rune")
+    };
+    let node_id = Arc::new(get_or_create_node_id(&fsio, config_path).await?.to_string());
+
+    let db_connections = initialize_databases(&fsio, lib_path, Some(&db_path), &node_id).await?;
+
+    let main_db: Arc<MainDbConnection> = db_connections.main_db;
+    let recommend_db: Arc<RecommendationDbConnection> = db_connections.recommend_db;
+    let library_lock = db_connections.library_lock;
+    let lib_path: Arc<String> = Arc::new(lib_path.to_string());
+    let config_path: Arc<String> = Arc::new(config_path.to_string());
+
+    let main_cancel_token = CancellationToken::new();
+    let task_registry = Arc::new(TaskRegistry::new());
+
+    info!("Initializing player");
+    let player = Player::new(Some(main_cancel_token.clone()));
+    let player: Arc<Mutex<Player>> = Arc::new(Mutex::new(player));
+
+    let sfx_player = SfxPlayer::new(Some(main_cancel_token.clone()));
+    let sfx_player: Arc<Mutex<SfxPlayer>> = Arc::new(Mutex::new(sfx_player));
+
+    let main_cancel_token = Arc::new(main_cancel_token);
+
+    let scrobbler = ScrobblingManager::new(10, Duration::new(5, 0));
+    let scrobbler = Arc::new(Mutex::new(scrobbler));
+
+    let broadcaster = Arc::new(WebSocketService::new());
+    let device_scanner = Arc::new(DiscoveryService::without_store());
+
+    let permission_manager = Arc::new(RwLock::new(PermissionManager::new(config_path.as_str())?));
+    let cert_validator = Arc::new(RwLock::new(CertValidator::new(config_path.as_str()).await?));
+
+    info!("Initializing Player events");
+    tokio::spawn(initialize_local_player(
+        fsio.clone(),
+        lib_path.clone(),
+        config_path.clone(),
+        main_db.clone(),
+        player.clone(),
+        scrobbler.clone(),
+        broadcaster.clone(),
+        cert_validator.clone(),
+        permission_manager.clone(),
+        (*main_cancel_token).clone(),
+    ));
+
+    scrobbler
+        .lock()
+        .await
+        .set_history(Arc::new(DatabaseScrobbleHistory::new(main_db.clone())));
+    let scrobble_queue_token = (*main_cancel_token).clone();
+    ScrobblingManager::start_queue(
+        scrobbler.clone(),
+        Arc::new(DatabaseScrobbleQueue::new(main_db.clone())),
+        async move { scrobble_queue_token.cancelled().await },
+    );
+
+    let global_params = Arc::new(GlobalParams {
+        fsio,
+        lib_path,
+        config_path,
+        node_id,
+        main_db,
+        recommend_db,
+        library_lock: Some(library_lock),
+        main_token: main_cancel_token,
+        task_registry,
+        player,
+        sfx_player,
+        scrobbler,
+        broadcaster,
+        device_scanner,
+        cert_validator,
+        permission_manager,
+        server_manager: OnceLock::new(),
+        remote_output: Arc::new(RemoteOutputManager::default()),
+        running_mode: RunningMode::Server,
+        started_at: Instant::now(),
+    });
+
+    let server_manager = Arc::new(ServerManager::new(global_params.clone()).await?);
+    global_params
+        .server_manager
+        .set(server_manager.clone())
+        .expect("Failed to set server manager in global params");
+
+    Ok(global_params)
+}
+
+/// Runs the server on a library until `shutdown` resolves, then shuts it
+/// down gracefully.
+pub async fn run_server<F>(lib_path: &str, options: ServeOptions, shutdown: F) -> Result<()>
+where
+    F: Future<Output = ()>,
+{
+    let config_path = get_config_dir()?;
+    let device_info = load_device_info(config_path).await?;
+    let global_params = initialize_server_params(lib_path, config_path.to_str().unwrap()).await?;
+    if options.audio_device.is_some() {
+        global_params
+            .player
+            .lock()
+            .await
+            .set_output_device(options.audio_device.clone());
+    }
+
+    let server_manager = match global_params.server_manager.get() {
+        Some(x) => Arc::clone(x),
+        None => Arc::new(ServerManager::new(global_params).await?),
+    };
+    let (socket_addrs, errors) = resolve_bind_addresses(&options.addr, options.layout.port);
+    for error in &errors {
+        warn!("{error}");
+    }
+    if options.strict_bind && !errors.is_empty() {
+        bail!("Failed to resolve some bind addresses");
+    }
+
+    server_manager.set_connection_limits(options.limits).await;
+    server_manager
+        .audit_logger
+        .set_verbose(options.audit_verbose);
+    server_manager
+        .set_metrics_address(options.metrics_addr)
+        .await;
+    server_manager.set_layout(options.layout.clone()).await;
+
+    let report = server_manager
+        .clone()
+        .start(
+            socket_addrs,
+            DiscoveryParams {
+                device_info: device_info.clone(),
+            },
+            options.strict_bind,
+        )
+        .await?;
+
+    for (addr, error) in &report.failures {
+        warn!("Failed to bind {addr}: {error}");
+    }
+
+    let device_scanner = Arc::clone(&server_manager.global_params.device_scanner);
+    if options.broadcast {
+        let mut device_info = device_info;
+        device_info.endpoints = report.endpoints.iter().map(|x| x.to_string()).collect();
+        options.layout.advertise(&mut device_info);
+
+        device_scanner
+            .start_announcements(device_info, BROADCAST_INTERVAL, None)
+            .await?;
+    }
+
+    shutdown.await;
+
+    if options.broadcast {
+        device_scanner.stop_announcements().await;
+    }
+    server_manager.stop().await?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! The permissions requests require, declared in the request manifest of
+//! `macros.rs`.
+
+use std::fmt;
+
+pub use crate::macros::{REQUEST_TYPES, declared_scope, required_scope};
+
+/// What a request may do, which the session sending it must be allowed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Reads the library or the state of the server
+    Read,
+    /// Controls the playback
+    Playback,
+    /// Changes the library or the settings of the services
+    LibraryWrite,
+    /// Manages the server, requires an admin token
+    Admin,
+    /// Only answered for the local user, never over the network
+    LocalOnly,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Playback => "playback",
+            Scope::LibraryWrite => "library_write",
+            Scope::Admin => "admin",
+            Scope::LocalOnly => "local_only",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The scope of a request type, implemented for every request of the
+/// manifest.
+pub trait RequestScope {
+    const SCOPE: Scope;
+}
+
+/// A request refused to a session lacking its scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionDenied {
+    pub request: String,
+    pub scope: Scope,
+    pub reason: String,
+}
+
+impl fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requires the {} scope: {}",
+            self.request, self.scope, self.reason
+        )
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::*;
+
+    #[test]
+    fn every_request_declares_its_scope() {
+        let undeclared: Vec<_> = REQUEST_TYPES
+            .iter()
+            .filter(|x| declared_scope(x).is_none())
+            .collect();
+
+        assert!(
+            undeclared.is_empty(),
+            "Requests without a scope in the manifest: {undeclared:?}"
+        );
+    }
+
+    #[test]
+    fn unknown_requests_require_admin() {
+        assert_eq!(declared_scope("UnknownRequest"), None);
+        assert_eq!(required_scope("UnknownRequest"), Scope::Admin);
+    }
+
+    #[test]
+    fn scope_constants_match_the_lookup() {
+        assert_eq!(
+            required_scope("FetchMediaFilesRequest"),
+            FetchMediaFilesRequest::SCOPE
+        );
+        assert_eq!(ClearLogRequest::SCOPE, Scope::Admin);
+        assert_eq!(ConnectRequest::SCOPE, Scope::LocalOnly);
+    }
+}
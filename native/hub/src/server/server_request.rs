@@ -40,8 +40,42 @@ macro_rules! register_single_handler {
                         }
                     };
 
+                    let fingerprint = session.as_ref().map(|x| x.fingerprint.clone());
+                    if let Err(e) = $crate::server::admin::authorize_request(
+                        &global_params,
+                        stringify!($request),
+                        <$request as $crate::server::scope::RequestScope>::SCOPE,
+                        session.as_ref(),
+                    ).await {
+                        warn!("Refused {}: {e}", stringify!($request));
+                        $crate::server::audit::record_request(
+                            &global_params,
+                            stringify!($request),
+                            &request,
+                            fingerprint,
+                            false,
+                        );
+                        return (
+                            "PermissionDeniedResponse".to_owned(),
+                            rinf::serialize(&PermissionDeniedResponse {
+                                request: e.request.clone(),
+                                scope: e.scope.to_string(),
+                                detail: e.to_string(),
+                            }).map_err(|e| anyhow::Error::new(e))
+                        );
+                    }
+
                     let params = request.extract_params(&global_params);
-                    match request.handle(params, session, &request).await {
+                    let result = request.handle(params, session, &request).await;
+                    $crate::server::audit::record_request(
+                        &global_params,
+                        stringify!($request),
+                        &request,
+                        fingerprint,
+                        result.is_ok(),
+                    );
+
+                    match result {
                         Ok(_response) => {
                             handle_server_response!(_response, $with_response)
                         }
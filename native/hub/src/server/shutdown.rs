@@ -0,0 +1,151 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{sync::Notify, time::timeout};
+use tokio_util::sync::CancellationToken;
+
+/// Coordinates a graceful shutdown of the server.
+///
+/// Once [`ShutdownCoordinator::begin`] is called no new connection or request
+/// is accepted, while the requests already being handled are allowed to
+/// finish before the connections are closed.
+#[derive(Debug, Default)]
+pub struct ShutdownCoordinator {
+    shutting_down: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+    close_token: CancellationToken,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Registers a request as in flight, returns `None` if the server is
+    /// shutting down and the request should be refused.
+    pub fn track(self: &Arc<Self>) -> Option<InFlightGuard> {
+        // Counted before checking, so a drain which already read zero is
+        // sure to see the shutdown here; a refused request gives its count
+        // back when the guard drops
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard {
+            coordinator: Arc::clone(self),
+        };
+
+        if self.is_shutting_down() {
+            return None;
+        }
+        Some(guard)
+    }
+
+    /// Waits until every in-flight request completed, returns `false` if
+    /// `limit` elapsed first.
+    pub async fn drain(&self, limit: Duration) -> bool {
+        timeout(limit, async {
+            loop {
+                let notified = self.idle.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                if self.in_flight.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    /// Asks every open connection to close.
+    pub fn close_connections(&self) {
+        self.close_token.cancel();
+    }
+
+    /// Resolves once the connections are asked to close.
+    pub async fn closing(&self) {
+        self.close_token.cancelled().await
+    }
+}
+
+/// Marks a request as in flight until dropped.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    coordinator: Arc<ShutdownCoordinator>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.coordinator.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.coordinator.idle.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_refused_once_shutting_down() {
+        let coordinator = Arc::new(ShutdownCoordinator::new());
+        let guard = coordinator.track();
+        assert!(guard.is_some());
+        assert_eq!(coordinator.in_flight.load(Ordering::SeqCst), 1);
+
+        coordinator.begin();
+        assert!(coordinator.track().is_none());
+        assert_eq!(coordinator.in_flight.load(Ordering::SeqCst), 1);
+
+        drop(guard);
+        assert_eq!(coordinator.in_flight.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_the_requests_in_flight() {
+        let coordinator = Arc::new(ShutdownCoordinator::new());
+        assert!(coordinator.drain(Duration::from_millis(10)).await);
+
+        let guard = coordinator.track();
+        coordinator.begin();
+        assert!(!coordinator.drain(Duration::from_millis(10)).await);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(guard);
+        });
+        assert!(coordinator.drain(Duration::from_secs(5)).await);
+    }
+
+    #[tokio::test]
+    async fn connections_are_asked_to_close() {
+        let coordinator = ShutdownCoordinator::new();
+        assert!(
+            timeout(Duration::from_millis(10), coordinator.closing())
+                .await
+                .is_err()
+        );
+
+        coordinator.close_connections();
+        assert!(
+            timeout(Duration::from_millis(10), coordinator.closing())
+                .await
+                .is_ok()
+        );
+    }
+}
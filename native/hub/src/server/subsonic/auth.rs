@@ -0,0 +1,162 @@
+use md5::{Digest, Md5};
+
+use ::discovery::server::UserStatus;
+
+use crate::server::{ServerManager, ServerState};
+
+use super::{SubsonicParams, response::SubsonicError};
+
+fn md5_hex(input: &str) -> String {
+    Md5::digest(input.as_bytes())
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect()
+}
+
+/// Decodes a password sent as `p`, either in clear text or hex encoded
+/// with the `enc:` prefix.
+fn decode_password(password: &str) -> Option<String> {
+    let Some(hex) = password.strip_prefix("enc:") else {
+        return Some(password.to_owned());
+    };
+
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Compares two secrets in a time that does not depend on where they
+/// differ, so a token can't be guessed byte by byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Checks the token (`t` and `s`) or the password (`p`) of a request
+/// against the password of the credential.
+fn verify_password(password: &str, params: &SubsonicParams) -> Result<bool, SubsonicError> {
+    match (params.get("t"), params.get("s"), params.get("p")) {
+        (Some(token), Some(salt), _) => {
+            let expected = md5_hex(&format!("{password}{salt}"));
+            Ok(constant_time_eq(
+                expected.as_bytes(),
+                token.to_ascii_lowercase().as_bytes(),
+            ))
+        }
+        (_, _, Some(given)) => Ok(decode_password(given)
+            .is_some_and(|x| constant_time_eq(x.as_bytes(), password.as_bytes()))),
+        (Some(_), None, None) => Err(SubsonicError::missing_parameter("s")),
+        _ => Err(SubsonicError::missing_parameter("t")),
+    }
+}
+
+/// Checks the credentials of a Subsonic request and resolves the
+/// fingerprint of the device they belong to.
+///
+/// Both the token (`t` and `s`) and the password (`p`) schemes are
+/// accepted, the device must also be approved in the permission manager.
+pub async fn authenticate(
+    state: &ServerState,
+    server_manager: &ServerManager,
+    params: &SubsonicParams,
+) -> Result<String, SubsonicError> {
+    let username = params.require("u")?;
+
+    let credential = server_manager
+        .subsonic_credentials
+        .find(username)
+        .await?
+        .ok_or_else(SubsonicError::wrong_credentials)?;
+
+    if !verify_password(&credential.password, params)? {
+        return Err(SubsonicError::wrong_credentials());
+    }
+
+    let permission_manager = state.permission_manager.read().await;
+    match permission_manager
+        .verify_by_fingerprint(&credential.fingerprint)
+        .await
+        .map(|x| x.status)
+    {
+        Some(UserStatus::Approved) => Ok(credential.fingerprint),
+        Some(UserStatus::Blocked) => Err(SubsonicError::not_authorized("Device is blocked")),
+        Some(UserStatus::Pending) => {
+            Err(SubsonicError::not_authorized("Device is pending approval"))
+        }
+        None => Err(SubsonicError::wrong_credentials()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> SubsonicParams {
+        SubsonicParams(
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn tokens_are_salted_md5_of_the_password() {
+        // The example of the Subsonic API documentation
+        assert_eq!(md5_hex("sesamec19b2d"), "26719a1196d2a940705a59634eb18eab");
+
+        let token = params(&[("t", "26719a1196d2a940705a59634eb18eab"), ("s", "c19b2d")]);
+        assert!(verify_password("sesame", &token).unwrap());
+        assert!(!verify_password("sesam", &token).unwrap());
+
+        let uppercase = params(&[("t", "26719A1196D2A940705A59634EB18EAB"), ("s", "c19b2d")]);
+        assert!(verify_password("sesame", &uppercase).unwrap());
+
+        let other_salt = params(&[("t", "26719a1196d2a940705a59634eb18eab"), ("s", "c19b2e")]);
+        assert!(!verify_password("sesame", &other_salt).unwrap());
+    }
+
+    #[test]
+    fn passwords_may_be_hex_encoded() {
+        assert_eq!(decode_password("sesame").as_deref(), Some("sesame"));
+        assert_eq!(
+            decode_password("enc:736573616d65").as_deref(),
+            Some("sesame")
+        );
+        assert_eq!(
+            decode_password("enc:736573616D65").as_deref(),
+            Some("sesame")
+        );
+        assert_eq!(decode_password("enc:").as_deref(), Some(""));
+        assert_eq!(decode_password("enc:73657"), None);
+        assert_eq!(decode_password("enc:7g"), None);
+        assert_eq!(decode_password("enc:ff"), None);
+
+        assert!(verify_password("sesame", &params(&[("p", "enc:736573616d65")])).unwrap());
+        assert!(verify_password("sesame", &params(&[("p", "sesame")])).unwrap());
+        assert!(!verify_password("sesame", &params(&[("p", "enc:73657361")])).unwrap());
+    }
+
+    #[test]
+    fn missing_credentials_are_reported() {
+        let error = verify_password("sesame", &params(&[])).unwrap_err();
+        assert_eq!(error.code, 10);
+        assert_eq!(error.message, "Required parameter is missing: t");
+
+        let error = verify_password("sesame", &params(&[("t", "26719a")])).unwrap_err();
+        assert_eq!(error.message, "Required parameter is missing: s");
+    }
+
+    #[test]
+    fn secrets_are_compared_fully() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
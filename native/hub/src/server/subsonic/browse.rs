@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use sea_orm::{EntityTrait, QueryOrder};
+use serde_json::{Value, json};
+
+use database::{
+    actions::{
+        albums::{get_album_ids_by_media_file_ids, get_media_file_ids_by_album_ids},
+        artists::{get_artist_ids_by_media_file_ids, get_media_file_ids_by_artist_ids},
+        collection::{CollectionQuery, CollectionQueryListMode, CollectionQueryType},
+        file::get_ordered_files_by_ids,
+        metadata::{get_metadata_summary_by_file_ids, get_metadata_summary_by_files},
+        search::search_for,
+        stats::get_liked_media_files,
+    },
+    connection::MainDbConnection,
+    entities::{albums, artists},
+};
+
+use crate::server::http::file::library_path;
+
+use super::{
+    SubsonicContext,
+    response::{SubsonicError, SubsonicReply},
+};
+
+const MAX_LIST_SIZE: u64 = 500;
+
+/// An item of the library, as identified by Subsonic clients.
+#[derive(Debug, Clone, Copy)]
+pub enum SubsonicId {
+    Song(i32),
+    Album(i32),
+    Artist(i32),
+}
+
+impl SubsonicId {
+    pub fn parse(id: &str) -> Result<Self, SubsonicError> {
+        let invalid = || SubsonicError::not_found(format!("Invalid id: {id}"));
+
+        if let Some(x) = id.strip_prefix("al-") {
+            x.parse().map(Self::Album).map_err(|_| invalid())
+        } else if let Some(x) = id.strip_prefix("ar-") {
+            x.parse().map(Self::Artist).map_err(|_| invalid())
+        } else {
+            id.parse().map(Self::Song).map_err(|_| invalid())
+        }
+    }
+}
+
+fn album_id(id: i32) -> String {
+    format!("al-{id}")
+}
+
+fn artist_id(id: i32) -> String {
+    format!("ar-{id}")
+}
+
+fn main_db(context: &SubsonicContext) -> &MainDbConnection {
+    &context.server_manager.global_params.main_db
+}
+
+fn content_type(suffix: &str) -> &'static str {
+    match suffix {
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "m4a" | "mp4" | "alac" => "audio/mp4",
+        "aac" => "audio/aac",
+        "wav" => "audio/wav",
+        "aiff" | "aif" => "audio/aiff",
+        "wma" => "audio/x-ms-wma",
+        "ape" => "audio/ape",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Describes media files as Subsonic songs, in the order of `file_ids`.
+async fn songs(main_db: &MainDbConnection, file_ids: &[i32]) -> Result<Vec<Value>, SubsonicError> {
+    let files = get_ordered_files_by_ids(main_db, file_ids).await?;
+    let summaries = get_metadata_summary_by_files(main_db, files.clone()).await?;
+    let album_ids = get_album_ids_by_media_file_ids(main_db, file_ids).await?;
+    let artist_ids = get_artist_ids_by_media_file_ids(main_db, file_ids).await?;
+    let liked = get_liked_media_files(main_db, file_ids).await?;
+
+    let mut result = Vec::with_capacity(files.len());
+    for (file, summary) in files.iter().zip(summaries) {
+        let suffix = file.extension.to_lowercase();
+        let mut song = json!({
+            "id": file.id.to_string(),
+            "isDir": false,
+            "title": summary.title,
+            "album": summary.album,
+            "artist": summary.artist,
+            "track": summary.track_number % 1000,
+            "discNumber": summary.track_number / 1000,
+            "duration": summary.duration.round() as i64,
+            "suffix": suffix,
+            "contentType": content_type(&suffix),
+            "path": library_path(&file.directory, &file.file_name),
+            "type": "music",
+            "mediaType": "song",
+        });
+
+        if !summary.genre.is_empty() {
+            song["genre"] = json!(summary.genre);
+        }
+        if summary.cover_art_id.is_some() {
+            song["coverArt"] = json!(file.id.to_string());
+        }
+        if let Some(album) = album_ids.get(&file.id) {
+            song["parent"] = json!(album_id(*album));
+            song["albumId"] = json!(album_id(*album));
+        }
+        if let Some(artist) = artist_ids.get(&file.id).and_then(|x| x.first()) {
+            song["artistId"] = json!(artist_id(*artist));
+        }
+        if let Some(starred) = liked.get(&file.id) {
+            song["starred"] = json!(starred);
+        }
+
+        result.push(song);
+    }
+
+    Ok(result)
+}
+
+/// Describes albums as Subsonic `AlbumID3` entries, credited to the
+/// artist of their first track.
+async fn album_entries(
+    main_db: &MainDbConnection,
+    albums: &[albums::Model],
+) -> Result<Vec<Value>, SubsonicError> {
+    let album_ids: Vec<i32> = albums.iter().map(|x| x.id).collect();
+    let tracks = get_media_file_ids_by_album_ids(main_db, &album_ids).await?;
+
+    let file_ids: Vec<i32> = tracks.values().flatten().copied().collect();
+    let durations: HashMap<i32, f64> = get_metadata_summary_by_file_ids(main_db, file_ids)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x.duration))
+        .collect();
+
+    let first_tracks: Vec<i32> = tracks.values().filter_map(|x| x.first().copied()).collect();
+    let track_artists = get_artist_ids_by_media_file_ids(main_db, &first_tracks).await?;
+    let artist_ids: Vec<i32> = track_artists.values().flatten().copied().collect();
+    let artist_names: HashMap<i32, String> = artists::Model::get_by_ids(main_db, &artist_ids)
+        .await?
+        .into_iter()
+        .map(|x| (x.id, x.name))
+        .collect();
+
+    Ok(albums
+        .iter()
+        .map(|album| {
+            let tracks = tracks.get(&album.id).map(Vec::as_slice).unwrap_or_default();
+            let duration: f64 = tracks.iter().filter_map(|x| durations.get(x)).sum();
+
+            let mut entry = json!({
+                "id": album_id(album.id),
+                "name": album.name,
+                "coverArt": album_id(album.id),
+                "songCount": tracks.len(),
+                "duration": duration.round() as i64,
+                "created": album.created_at_hlc_ts,
+            });
+
+            if let Some(artist) = tracks
+                .first()
+                .and_then(|x| track_artists.get(x))
+                .and_then(|x| x.first())
+                && let Some(name) = artist_names.get(artist)
+            {
+                entry["artist"] = json!(name);
+                entry["artistId"] = json!(artist_id(*artist));
+            }
+
+            entry
+        })
+        .collect())
+}
+
+/// Describes artists as Subsonic `ArtistID3` entries.
+async fn artist_entries(
+    main_db: &MainDbConnection,
+    artists: &[artists::Model],
+) -> Result<Vec<Value>, SubsonicError> {
+    let artist_ids: Vec<i32> = artists.iter().map(|x| x.id).collect();
+    let tracks = get_media_file_ids_by_artist_ids(main_db, &artist_ids).await?;
+
+    let file_ids: Vec<i32> = tracks.values().flatten().copied().collect();
+    let file_albums = get_album_ids_by_media_file_ids(main_db, &file_ids).await?;
+
+    Ok(artists
+        .iter()
+        .map(|artist| {
+            let album_count = tracks
+                .get(&artist.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|x| file_albums.get(x))
+                .collect::<HashSet<_>>()
+                .len();
+
+            json!({
+                "id": artist_id(artist.id),
+                "name": artist.name,
+                "coverArt": artist_id(artist.id),
+                "albumCount": album_count,
+            })
+        })
+        .collect())
+}
+
+/// Loads the models of `ids`, in the order of `ids`.
+async fn ordered<T: CollectionQuery>(
+    main_db: &MainDbConnection,
+    ids: &[i32],
+) -> Result<Vec<T>, SubsonicError> {
+    let mut items = T::get_by_ids(main_db, ids).await?;
+    items.sort_by_key(|x| ids.iter().position(|id| *id == x.id()));
+    Ok(items)
+}
+
+pub async fn get_music_folders(_context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    Ok(SubsonicReply::with(
+        "musicFolders",
+        json!({ "musicFolder": [{ "id": 1, "name": "Library" }] }),
+    ))
+}
+
+pub async fn get_artists(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let main_db = main_db(context);
+    let artists = artists::Entity::find()
+        .order_by_asc(artists::Column::Name)
+        .all(main_db)
+        .await?;
+
+    let mut index: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for (artist, entry) in artists.iter().zip(artist_entries(main_db, &artists).await?) {
+        let letter = match artist.name.chars().next() {
+            Some(c) if c.is_alphabetic() => c.to_uppercase().to_string(),
+            _ => "#".to_owned(),
+        };
+        index.entry(letter).or_default().push(entry);
+    }
+
+    let index: Vec<Value> = index
+        .into_iter()
+        .map(|(name, artists)| json!({ "name": name, "artist": artists }))
+        .collect();
+
+    Ok(SubsonicReply::with(
+        "artists",
+        json!({ "ignoredArticles": "", "index": index }),
+    ))
+}
+
+pub async fn get_artist(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let main_db = main_db(context);
+    let SubsonicId::Artist(id) = SubsonicId::parse(context.params.require("id")?)? else {
+        return Err(SubsonicError::not_found("Not an artist id"));
+    };
+
+    let artist = artists::Entity::find_by_id(id)
+        .one(main_db)
+        .await?
+        .ok_or_else(|| SubsonicError::not_found(format!("Artist {id} not found")))?;
+
+    let file_ids = get_media_file_ids_by_artist_ids(main_db, &[id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+    let file_albums = get_album_ids_by_media_file_ids(main_db, &file_ids).await?;
+
+    let mut album_ids = Vec::new();
+    for file_id in &file_ids {
+        if let Some(album) = file_albums.get(file_id)
+            && !album_ids.contains(album)
+        {
+            album_ids.push(*album);
+        }
+    }
+
+    let albums = ordered::<albums::Model>(main_db, &album_ids).await?;
+    let mut entry = artist_entries(main_db, &[artist])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    entry["album"] = json!(album_entries(main_db, &albums).await?);
+
+    Ok(SubsonicReply::with("artist", entry))
+}
+
+pub async fn get_album(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let main_db = main_db(context);
+    let SubsonicId::Album(id) = SubsonicId::parse(context.params.require("id")?)? else {
+        return Err(SubsonicError::not_found("Not an album id"));
+    };
+
+    let album = albums::Entity::find_by_id(id)
+        .one(main_db)
+        .await?
+        .ok_or_else(|| SubsonicError::not_found(format!("Album {id} not found")))?;
+
+    let file_ids = get_media_file_ids_by_album_ids(main_db, &[id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+
+    let mut entry = album_entries(main_db, &[album])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    entry["song"] = json!(songs(main_db, &file_ids).await?);
+
+    Ok(SubsonicReply::with("album", entry))
+}
+
+pub async fn get_song(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let SubsonicId::Song(id) = SubsonicId::parse(context.params.require("id")?)? else {
+        return Err(SubsonicError::not_found("Not a song id"));
+    };
+
+    let song = songs(main_db(context), &[id])
+        .await?
+        .pop()
+        .ok_or_else(|| SubsonicError::not_found(format!("Song {id} not found")))?;
+
+    Ok(SubsonicReply::with("song", song))
+}
+
+pub async fn get_album_list2(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let main_db = main_db(context);
+    let list_type = context.params.require("type")?;
+    let size = context.params.number("size", 10u64)?.min(MAX_LIST_SIZE);
+    let offset = context.params.number("offset", 0u64)?;
+
+    let mode = match list_type {
+        "random" => Some(CollectionQueryListMode::Random),
+        "newest" => Some(CollectionQueryListMode::RecentlyAdded),
+        "alphabeticalByName" => Some(CollectionQueryListMode::Name),
+        // Play history, ratings and release dates are not tracked per album
+        "alphabeticalByArtist"
+        | "frequent"
+        | "recent"
+        | "starred"
+        | "highest"
+        | "byYear"
+        | "byGenre" => None,
+        _ => {
+            return Err(SubsonicError::generic(format!(
+                "Unknown album list type: {list_type}"
+            )));
+        }
+    };
+
+    let albums = match mode {
+        Some(CollectionQueryListMode::Random) => {
+            albums::Model::list(main_db, size, CollectionQueryListMode::Random).await?
+        }
+        Some(mode) => albums::Model::list(main_db, size + offset, mode)
+            .await?
+            .into_iter()
+            .skip(offset as usize)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(SubsonicReply::with(
+        "albumList2",
+        json!({ "album": album_entries(main_db, &albums).await? }),
+    ))
+}
+
+pub async fn search3(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let main_db = main_db(context);
+    let query = context.params.require("query")?.trim_matches('"');
+
+    let window = |kind: &str| -> Result<(usize, usize), SubsonicError> {
+        let count = context.params.number(&format!("{kind}Count"), 20usize)?;
+        let offset = context.params.number(&format!("{kind}Offset"), 0usize)?;
+        Ok((count.min(MAX_LIST_SIZE as usize), offset))
+    };
+    let (artist_count, artist_offset) = window("artist")?;
+    let (album_count, album_offset) = window("album")?;
+    let (song_count, song_offset) = window("song")?;
+
+    let n = [
+        artist_count + artist_offset,
+        album_count + album_offset,
+        song_count + song_offset,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or_default();
+
+    let results = search_for(
+        main_db,
+        query,
+        Some(vec![
+            CollectionQueryType::Artist,
+            CollectionQueryType::Album,
+            CollectionQueryType::Track,
+        ]),
+        n,
+    )
+    .await?;
+
+    let ids = |kind: CollectionQueryType, count: usize, offset: usize| -> Vec<i32> {
+        results
+            .get(&kind)
+            .into_iter()
+            .flatten()
+            .filter_map(|x| i32::try_from(*x).ok())
+            .skip(offset)
+            .take(count)
+            .collect()
+    };
+
+    let artists = ordered::<artists::Model>(
+        main_db,
+        &ids(CollectionQueryType::Artist, artist_count, artist_offset),
+    )
+    .await?;
+    let albums = ordered::<albums::Model>(
+        main_db,
+        &ids(CollectionQueryType::Album, album_count, album_offset),
+    )
+    .await?;
+    let song_ids = ids(CollectionQueryType::Track, song_count, song_offset);
+
+    Ok(SubsonicReply::with(
+        "searchResult3",
+        json!({
+            "artist": artist_entries(main_db, &artists).await?,
+            "album": album_entries(main_db, &albums).await?,
+            "song": songs(main_db, &song_ids).await?,
+        }),
+    ))
+}
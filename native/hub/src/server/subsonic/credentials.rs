@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use rand::{
+    distributions::{Alphanumeric, DistString},
+    thread_rng,
+};
+use serde::{Deserialize, Serialize};
+
+const CREDENTIAL_FILE: &str = "subsonic_credentials.json";
+const PASSWORD_LENGTH: usize = 24;
+
+/// Subsonic login of a device registered in the permission manager.
+///
+/// The token scheme of Subsonic hashes the password with a salt chosen by
+/// the client, so the password has to be kept in clear text. It is random
+/// and only grants what the device it is bound to is allowed to do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsonicCredential {
+    pub username: String,
+    pub fingerprint: String,
+    pub password: String,
+    /// Creation time (UNIX timestamp)
+    pub created_at: i64,
+}
+
+/// Persists the Subsonic logins of the server in the configuration directory.
+///
+/// Like the API tokens, the file is read on every call so logins issued
+/// from the command line apply to a running server.
+#[derive(Debug)]
+pub struct SubsonicCredentialStore {
+    path: PathBuf,
+}
+
+impl SubsonicCredentialStore {
+    pub fn new(config_path: &Path) -> Self {
+        Self {
+            path: config_path.join(CREDENTIAL_FILE),
+        }
+    }
+
+    pub async fn list(&self) -> Result<Vec<SubsonicCredential>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = tokio::fs::read_to_string(&self.path)
+            .await
+            .context("Failed to read Subsonic credentials")?;
+        serde_json::from_str(&content).context("Failed to parse Subsonic credentials")
+    }
+
+    async fn save(&self, credentials: &[SubsonicCredential]) -> Result<()> {
+        let content = serde_json::to_string_pretty(credentials)?;
+        tokio::fs::write(&self.path, content)
+            .await
+            .context("Failed to write Subsonic credentials")
+    }
+
+    /// Issues a new password for the device `fingerprint`, replacing the
+    /// previous login of the device.
+    pub async fn issue(&self, username: &str, fingerprint: &str) -> Result<SubsonicCredential> {
+        let username = username.trim();
+        if username.is_empty() {
+            bail!("Subsonic username cannot be empty");
+        }
+
+        let mut credentials = self.list().await?;
+        credentials.retain(|x| x.fingerprint != fingerprint);
+        if credentials.iter().any(|x| x.username == username) {
+            bail!("Subsonic username {username} is already taken by another device");
+        }
+
+        let credential = SubsonicCredential {
+            username: username.to_owned(),
+            fingerprint: fingerprint.to_owned(),
+            password: Alphanumeric.sample_string(&mut thread_rng(), PASSWORD_LENGTH),
+            created_at: Utc::now().timestamp(),
+        };
+
+        credentials.push(credential.clone());
+        self.save(&credentials).await?;
+
+        Ok(credential)
+    }
+
+    /// Removes the login of the device `fingerprint`, if any.
+    pub async fn revoke(&self, fingerprint: &str) -> Result<Option<SubsonicCredential>> {
+        let mut credentials = self.list().await?;
+        let Some(index) = credentials
+            .iter()
+            .position(|x| x.fingerprint == fingerprint)
+        else {
+            return Ok(None);
+        };
+
+        let credential = credentials.remove(index);
+        self.save(&credentials).await?;
+
+        Ok(Some(credential))
+    }
+
+    pub async fn find(&self, username: &str) -> Result<Option<SubsonicCredential>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|x| x.username == username))
+    }
+}
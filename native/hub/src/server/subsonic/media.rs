@@ -0,0 +1,121 @@
+use axum::{http::StatusCode, response::IntoResponse};
+
+use database::actions::{
+    albums::get_media_file_ids_by_album_ids,
+    artists::get_media_file_ids_by_artist_ids,
+    file::get_file_by_id,
+    stats::{increase_played_through, set_liked},
+};
+
+use crate::server::http::{
+    file::{library_path, serve_file},
+    media::cover_art_response,
+};
+
+use super::{
+    SubsonicContext,
+    browse::SubsonicId,
+    response::{SubsonicError, SubsonicReply},
+};
+
+fn song_ids(context: &SubsonicContext) -> Result<Vec<i32>, SubsonicError> {
+    context
+        .params
+        .get_all("id")
+        .into_iter()
+        .map(|x| match SubsonicId::parse(x)? {
+            SubsonicId::Song(id) => Ok(id),
+            _ => Err(SubsonicError::not_found(format!("Not a song id: {x}"))),
+        })
+        .collect()
+}
+
+/// Serves the original file of a song, transcoding is not supported so
+/// `format` and `maxBitRate` are ignored.
+pub async fn stream(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let SubsonicId::Song(id) = SubsonicId::parse(context.params.require("id")?)? else {
+        return Err(SubsonicError::not_found("Not a song id"));
+    };
+
+    let file = get_file_by_id(&context.server_manager.global_params.main_db, id)
+        .await?
+        .ok_or_else(|| SubsonicError::not_found(format!("Song {id} not found")))?;
+
+    let response = serve_file(
+        &context.state.fsio,
+        &context.state.app_state.lib_path,
+        &library_path(&file.directory, &file.file_name),
+        &context.headers,
+    )
+    .await;
+
+    Ok(SubsonicReply::Raw(response))
+}
+
+/// Serves the cover art of a song, or of the first track of an album or
+/// an artist. The `size` parameter is ignored, covers are served as baked.
+pub async fn get_cover_art(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let main_db = &context.server_manager.global_params.main_db;
+
+    let file_id = match SubsonicId::parse(context.params.require("id")?)? {
+        SubsonicId::Song(id) => Some(id),
+        SubsonicId::Album(id) => get_media_file_ids_by_album_ids(main_db, &[id])
+            .await?
+            .get(&id)
+            .and_then(|x| x.first().copied()),
+        SubsonicId::Artist(id) => get_media_file_ids_by_artist_ids(main_db, &[id])
+            .await?
+            .get(&id)
+            .and_then(|x| x.first().copied()),
+    };
+
+    let Some(file_id) = file_id else {
+        return Err(SubsonicError::not_found("Cover art not found"));
+    };
+
+    let response = match cover_art_response(&context.state.fsio, main_db, file_id).await {
+        Ok(response) => response,
+        Err((StatusCode::NOT_FOUND, e)) => return Err(SubsonicError::not_found(e)),
+        Err((status, e)) => (status, e).into_response(),
+    };
+
+    Ok(SubsonicReply::Raw(response))
+}
+
+/// Likes or unlikes songs, albums and artists have no liked status.
+pub async fn star(context: &SubsonicContext, liked: bool) -> Result<SubsonicReply, SubsonicError> {
+    if context.params.get("albumId").is_some() || context.params.get("artistId").is_some() {
+        return Err(SubsonicError::generic(
+            "Only songs can be starred on this server",
+        ));
+    }
+
+    let main_db = &context.server_manager.global_params.main_db;
+    for id in song_ids(context)? {
+        set_liked(main_db, id, liked)
+            .await?
+            .ok_or_else(|| SubsonicError::not_found(format!("Song {id} not found")))?;
+    }
+
+    Ok(SubsonicReply::empty())
+}
+
+/// Counts songs as played through, "now playing" notifications are
+/// accepted and ignored.
+pub async fn scrobble(context: &SubsonicContext) -> Result<SubsonicReply, SubsonicError> {
+    let ids = song_ids(context)?;
+    if ids.is_empty() {
+        return Err(SubsonicError::missing_parameter("id"));
+    }
+
+    if context.params.get("submission") == Some("false") {
+        return Ok(SubsonicReply::empty());
+    }
+
+    let main_db = &context.server_manager.global_params.main_db;
+    for id in ids {
+        increase_played_through(main_db, id).await?;
+    }
+
+    Ok(SubsonicReply::empty())
+}
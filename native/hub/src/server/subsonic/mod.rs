@@ -0,0 +1,176 @@
+pub mod auth;
+mod browse;
+pub mod credentials;
+mod media;
+pub mod response;
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, Uri},
+    response::Response,
+    routing::get,
+};
+use log::{debug, warn};
+use serde_json::json;
+
+use crate::{
+    Session,
+    server::{ServerManager, ServerState, admin::authorize_request, scope::Scope},
+};
+
+use self::{
+    auth::authenticate,
+    response::{SubsonicError, SubsonicFormat, SubsonicReply, render},
+};
+
+/// Path the Subsonic API is mounted at, below the path prefix of the server.
+pub const SUBSONIC_PREFIX: &str = "/rest";
+
+/// Parameters of a Subsonic request, merged from the query string and
+/// the form body. Parameters such as `id` may be repeated.
+#[derive(Debug, Default)]
+pub struct SubsonicParams(Vec<(String, String)>);
+
+impl SubsonicParams {
+    fn parse(uri: &Uri, body: &[u8]) -> Self {
+        let query = uri.query().unwrap_or_default().as_bytes();
+        Self(
+            url::form_urlencoded::parse(query)
+                .chain(url::form_urlencoded::parse(body))
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    pub fn require(&self, name: &str) -> Result<&str, SubsonicError> {
+        self.get(name)
+            .ok_or_else(|| SubsonicError::missing_parameter(name))
+    }
+
+    /// Reads a numeric parameter, falling back to `default` when absent.
+    pub fn number<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, SubsonicError> {
+        match self.get(name) {
+            Some(x) => x
+                .parse()
+                .map_err(|_| SubsonicError::generic(format!("Invalid value for {name}: {x}"))),
+            None => Ok(default),
+        }
+    }
+}
+
+/// Everything an endpoint needs to answer a request.
+pub struct SubsonicContext {
+    pub state: Arc<ServerState>,
+    pub server_manager: Arc<ServerManager>,
+    pub headers: HeaderMap,
+    pub params: SubsonicParams,
+}
+
+/// Routes of the Subsonic API, to be nested at [`SUBSONIC_PREFIX`].
+///
+/// Endpoints are translated into the database actions the WebSocket
+/// handlers use. Albums are identified as `al-<id>`, artists as `ar-<id>`
+/// and songs by the id of their media file.
+pub fn subsonic_router() -> Router<Arc<ServerState>> {
+    Router::new().route("/{endpoint}", get(subsonic_handler).post(subsonic_handler))
+}
+
+async fn subsonic_handler(
+    State(state): State<Arc<ServerState>>,
+    Extension(server_manager): Extension<Arc<ServerManager>>,
+    Path(endpoint): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let params = SubsonicParams::parse(&uri, &body);
+    let format = SubsonicFormat::parse(params.get("f"));
+    let endpoint = endpoint.strip_suffix(".view").unwrap_or(&endpoint);
+
+    let context = SubsonicContext {
+        state,
+        server_manager,
+        headers,
+        params,
+    };
+
+    render(format, dispatch(&context, endpoint).await)
+}
+
+/// Scope an endpoint takes, checked like the scope of the requests sent
+/// over the WebSocket.
+fn endpoint_scope(endpoint: &str) -> Scope {
+    match endpoint {
+        "star" | "unstar" | "scrobble" => Scope::LibraryWrite,
+        _ => Scope::Read,
+    }
+}
+
+async fn dispatch(
+    context: &SubsonicContext,
+    endpoint: &str,
+) -> Result<SubsonicReply, SubsonicError> {
+    // Clients probe the extensions before logging in
+    if endpoint == "getOpenSubsonicExtensions" {
+        return Ok(SubsonicReply::with("openSubsonicExtensions", json!([])));
+    }
+
+    let fingerprint =
+        authenticate(&context.state, &context.server_manager, &context.params).await?;
+    debug!("[{fingerprint}] Subsonic request: {endpoint}");
+
+    // Only the device is checked, the host is never used to build URLs here
+    let session = Session {
+        fingerprint,
+        host: String::new(),
+        admin_token: Arc::new(std::sync::Mutex::new(None)),
+    };
+    if let Err(e) = authorize_request(
+        &context.server_manager.global_params,
+        endpoint,
+        endpoint_scope(endpoint),
+        Some(&session),
+    )
+    .await
+    {
+        warn!("Refused Subsonic request {endpoint}: {e}");
+        return Err(SubsonicError::not_authorized(e.to_string()));
+    }
+
+    match endpoint {
+        "ping" => Ok(SubsonicReply::empty()),
+        "getLicense" => Ok(SubsonicReply::with("license", json!({ "valid": true }))),
+        "getMusicFolders" => browse::get_music_folders(context).await,
+        "getArtists" => browse::get_artists(context).await,
+        "getArtist" => browse::get_artist(context).await,
+        "getAlbum" => browse::get_album(context).await,
+        "getSong" => browse::get_song(context).await,
+        "getAlbumList2" => browse::get_album_list2(context).await,
+        "search3" => browse::search3(context).await,
+        "stream" | "download" => media::stream(context).await,
+        "getCoverArt" => media::get_cover_art(context).await,
+        "star" => media::star(context, true).await,
+        "unstar" => media::star(context, false).await,
+        "scrobble" => media::scrobble(context).await,
+        _ => Err(SubsonicError::not_implemented(endpoint)),
+    }
+}
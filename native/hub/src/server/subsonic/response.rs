@@ -0,0 +1,328 @@
+use std::fmt::Write;
+
+use axum::{
+    Json,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value, json};
+
+/// Version of the Subsonic API implemented by the server.
+pub const SUBSONIC_API_VERSION: &str = "1.16.1";
+
+const SUBSONIC_XMLNS: &str = "http://subsonic.org/restapi";
+
+/// Error reported in the body of a Subsonic response, with the codes
+/// defined by the Subsonic API.
+#[derive(Debug)]
+pub struct SubsonicError {
+    pub code: u32,
+    pub message: String,
+}
+
+impl SubsonicError {
+    pub fn generic(message: impl Into<String>) -> Self {
+        Self {
+            code: 0,
+            message: message.into(),
+        }
+    }
+
+    pub fn missing_parameter(name: &str) -> Self {
+        Self {
+            code: 10,
+            message: format!("Required parameter is missing: {name}"),
+        }
+    }
+
+    pub fn wrong_credentials() -> Self {
+        Self {
+            code: 40,
+            message: "Wrong username or password".to_owned(),
+        }
+    }
+
+    pub fn not_authorized(message: impl Into<String>) -> Self {
+        Self {
+            code: 50,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            code: 70,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_implemented(endpoint: &str) -> Self {
+        Self::generic(format!("{endpoint} is not implemented by this server"))
+    }
+}
+
+impl From<anyhow::Error> for SubsonicError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::generic(e.to_string())
+    }
+}
+
+impl From<sea_orm::DbErr> for SubsonicError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        Self::generic(e.to_string())
+    }
+}
+
+/// What an endpoint answers with.
+pub enum SubsonicReply {
+    /// Elements merged into the `subsonic-response` element.
+    Payload(Map<String, Value>),
+    /// Binary content, such as an audio stream or a cover art.
+    Raw(Response),
+}
+
+impl SubsonicReply {
+    pub fn empty() -> Self {
+        Self::Payload(Map::new())
+    }
+
+    pub fn with(name: &str, value: Value) -> Self {
+        let mut payload = Map::new();
+        payload.insert(name.to_owned(), value);
+        Self::Payload(payload)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubsonicFormat {
+    Xml,
+    Json,
+}
+
+impl SubsonicFormat {
+    /// Reads the `f` parameter, `jsonp` is answered with plain JSON.
+    pub fn parse(f: Option<&str>) -> Self {
+        match f {
+            Some("json") | Some("jsonp") => Self::Json,
+            _ => Self::Xml,
+        }
+    }
+}
+
+fn envelope(status: &str, payload: Map<String, Value>) -> Value {
+    let mut response = Map::new();
+    response.insert("status".to_owned(), json!(status));
+    response.insert("version".to_owned(), json!(SUBSONIC_API_VERSION));
+    response.insert("type".to_owned(), json!("rune"));
+    response.insert("serverVersion".to_owned(), json!(env!("CARGO_PKG_VERSION")));
+    response.insert("openSubsonic".to_owned(), json!(true));
+    response.extend(payload);
+
+    json!({ "subsonic-response": response })
+}
+
+/// Renders the outcome of an endpoint in the format asked by the client.
+///
+/// Errors are still answered with `200 OK`, as Subsonic clients expect.
+pub fn render(format: SubsonicFormat, result: Result<SubsonicReply, SubsonicError>) -> Response {
+    let document = match result {
+        Ok(SubsonicReply::Raw(response)) => return response,
+        Ok(SubsonicReply::Payload(payload)) => envelope("ok", payload),
+        Err(e) => {
+            let mut payload = Map::new();
+            payload.insert(
+                "error".to_owned(),
+                json!({ "code": e.code, "message": e.message }),
+            );
+            envelope("failed", payload)
+        }
+    };
+
+    match format {
+        SubsonicFormat::Json => Json(document).into_response(),
+        SubsonicFormat::Xml => (
+            [(header::CONTENT_TYPE, "text/xml; charset=utf-8")],
+            to_xml(&document["subsonic-response"]),
+        )
+            .into_response(),
+    }
+}
+
+fn escape_xml(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(x) => Some(x.clone()),
+        Value::Number(x) => Some(x.to_string()),
+        Value::Bool(x) => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+/// Writes `value` as the element `name`: scalar fields become attributes,
+/// objects child elements and arrays repeated child elements.
+fn write_element(output: &mut String, name: &str, value: &Value, extra_attributes: &str) {
+    let _ = write!(output, "<{name}{extra_attributes}");
+
+    let Value::Object(fields) = value else {
+        match scalar_text(value) {
+            Some(text) => {
+                let _ = write!(output, ">{}</{name}>", escape_xml(&text));
+            }
+            None => output.push_str("/>"),
+        }
+        return;
+    };
+
+    for (key, field) in fields {
+        if let Some(text) = scalar_text(field) {
+            let _ = write!(output, " {key}=\"{}\"", escape_xml(&text));
+        }
+    }
+
+    let mut children = String::new();
+    for (key, field) in fields {
+        match field {
+            Value::Object(_) => write_element(&mut children, key, field, ""),
+            Value::Array(items) => {
+                for item in items {
+                    write_element(&mut children, key, item, "");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if children.is_empty() {
+        output.push_str("/>");
+    } else {
+        let _ = write!(output, ">{children}</{name}>");
+    }
+}
+
+fn to_xml(response: &Value) -> String {
+    let mut output = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    write_element(
+        &mut output,
+        "subsonic-response",
+        response,
+        &format!(" xmlns=\"{SUBSONIC_XMLNS}\""),
+    );
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::to_bytes, http::StatusCode};
+
+    use super::*;
+
+    async fn body(response: Response) -> String {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn error_codes_follow_the_subsonic_api() {
+        assert_eq!(SubsonicError::generic("Oops").code, 0);
+        assert_eq!(SubsonicError::missing_parameter("id").code, 10);
+        assert_eq!(SubsonicError::wrong_credentials().code, 40);
+        assert_eq!(SubsonicError::not_authorized("Blocked").code, 50);
+        assert_eq!(SubsonicError::not_found("Song 1 not found").code, 70);
+        assert_eq!(SubsonicError::not_implemented("getPodcasts").code, 0);
+    }
+
+    #[test]
+    fn formats_default_to_xml() {
+        assert_eq!(SubsonicFormat::parse(None), SubsonicFormat::Xml);
+        assert_eq!(SubsonicFormat::parse(Some("xml")), SubsonicFormat::Xml);
+        assert_eq!(SubsonicFormat::parse(Some("json")), SubsonicFormat::Json);
+        assert_eq!(SubsonicFormat::parse(Some("jsonp")), SubsonicFormat::Json);
+    }
+
+    #[tokio::test]
+    async fn json_replies_are_wrapped_in_the_envelope() {
+        let response = render(
+            SubsonicFormat::Json,
+            Ok(SubsonicReply::with("license", json!({ "valid": true }))),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let document: Value = serde_json::from_str(&body(response).await).unwrap();
+        let reply = &document["subsonic-response"];
+        assert_eq!(reply["status"], "ok");
+        assert_eq!(reply["version"], SUBSONIC_API_VERSION);
+        assert_eq!(reply["type"], "rune");
+        assert_eq!(reply["openSubsonic"], true);
+        assert_eq!(reply["license"]["valid"], true);
+        assert!(reply.get("error").is_none());
+    }
+
+    #[tokio::test]
+    async fn errors_are_answered_with_ok_status() {
+        let response = render(
+            SubsonicFormat::Json,
+            Err(SubsonicError::wrong_credentials()),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let document: Value = serde_json::from_str(&body(response).await).unwrap();
+        let reply = &document["subsonic-response"];
+        assert_eq!(reply["status"], "failed");
+        assert_eq!(reply["error"]["code"], 40);
+        assert_eq!(reply["error"]["message"], "Wrong username or password");
+    }
+
+    #[tokio::test]
+    async fn xml_errors_are_escaped_attributes() {
+        let response = render(
+            SubsonicFormat::Xml,
+            Err(SubsonicError::not_found("Song <1> not found")),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/xml; charset=utf-8"
+        );
+
+        let xml = body(response).await;
+        assert!(xml.starts_with(
+            r#"<?xml version="1.0" encoding="UTF-8"?><subsonic-response xmlns="http://subsonic.org/restapi" "#
+        ));
+        assert!(xml.contains(r#" status="failed""#));
+        assert!(xml.contains(&format!(r#" version="{SUBSONIC_API_VERSION}""#)));
+        assert!(xml.ends_with(
+            r#"><error code="70" message="Song &lt;1&gt; not found"/></subsonic-response>"#
+        ));
+    }
+
+    #[test]
+    fn arrays_become_repeated_elements() {
+        let response = json!({
+            "artists": { "index": [{ "name": "A" }, { "name": "B & C" }] }
+        });
+
+        assert_eq!(
+            to_xml(&response),
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<subsonic-response xmlns="http://subsonic.org/restapi">"#,
+                r#"<artists><index name="A"/><index name="B &amp; C"/></artists>"#,
+                r#"</subsonic-response>"#
+            )
+        );
+    }
+}
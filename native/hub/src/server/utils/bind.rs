@@ -0,0 +1,104 @@
+use std::{
+    collections::HashSet,
+    net::{IpAddr, SocketAddr},
+};
+
+use discovery::{
+    endpoint::{DEFAULT_API_PORT, ServerEndpoint, normalize_path_prefix},
+    utils::{DeviceInfo, interface_addresses},
+};
+
+pub const DEFAULT_SERVER_PORT: u16 = DEFAULT_API_PORT;
+
+/// Ports and URL layout of the combined HTTP/WebSocket server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerLayout {
+    /// Port used for bind addresses given without one, advertised to clients.
+    pub port: u16,
+    /// Separate port for WebSocket connections, `None` when they share `port`.
+    pub ws_port: Option<u16>,
+    /// Normalized path prefix every route is mounted below.
+    pub path_prefix: String,
+}
+
+impl Default for ServerLayout {
+    fn default() -> Self {
+        ServerLayout {
+            port: DEFAULT_SERVER_PORT,
+            ws_port: None,
+            path_prefix: String::new(),
+        }
+    }
+}
+
+impl ServerLayout {
+    pub fn new(port: u16, ws_port: Option<u16>, path_prefix: &str) -> Self {
+        ServerLayout {
+            port,
+            ws_port: ws_port.filter(|x| *x != port),
+            path_prefix: normalize_path_prefix(path_prefix),
+        }
+    }
+
+    /// The endpoint clients reach this server at through `host`.
+    pub fn endpoint(&self, host: &str) -> ServerEndpoint {
+        ServerEndpoint::new(host).with_advertised(self.port, self.ws_port, &self.path_prefix)
+    }
+
+    /// Copies the layout into the information announced to other devices.
+    pub fn advertise(&self, device_info: &mut DeviceInfo) {
+        device_info.api_port = self.port;
+        device_info.ws_port = self.ws_port;
+        device_info.path_prefix = self.path_prefix.clone();
+    }
+}
+
+/// Resolves bind specifications into socket addresses.
+///
+/// Each specification can be a socket address (`192.168.1.2:7863`), a bare
+/// IP address, or a network interface name optionally followed by a port
+/// (`wg0`, `eth0:7863`). Interface names expand to every address currently
+/// assigned to the interface, link-local IPv6 ones scoped to it.
+///
+/// Returns the resolved addresses along with a description of every
+/// specification that could not be resolved.
+pub fn resolve_bind_addresses(
+    specs: &[String],
+    default_port: u16,
+) -> (Vec<SocketAddr>, Vec<String>) {
+    let mut addresses = Vec::new();
+    let mut errors = Vec::new();
+
+    for spec in specs.iter().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        if let Ok(addr) = spec.parse::<SocketAddr>() {
+            addresses.push(addr);
+            continue;
+        }
+
+        if let Ok(ip) = spec.trim_matches(['[', ']']).parse::<IpAddr>() {
+            addresses.push(SocketAddr::new(ip, default_port));
+            continue;
+        }
+
+        let (name, port) = match spec.rsplit_once(':') {
+            Some((name, port)) => match port.parse::<u16>() {
+                Ok(port) => (name, port),
+                Err(_) => {
+                    errors.push(format!("Invalid port in bind address: {spec}"));
+                    continue;
+                }
+            },
+            None => (spec, default_port),
+        };
+
+        match interface_addresses(name, port) {
+            Ok(found) => addresses.extend(found),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    let mut seen = HashSet::new();
+    addresses.retain(|x| seen.insert(*x));
+
+    (addresses, errors)
+}
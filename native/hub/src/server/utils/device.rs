@@ -5,7 +5,9 @@ use anyhow::Result;
 use discovery::utils::{DeviceInfo, DeviceType};
 use serde::{Deserialize, Serialize};
 
-use crate::server::{generate_or_load_certificates, get_or_generate_alias};
+use crate::server::{
+    generate_or_load_certificates, get_or_generate_alias, utils::bind::DEFAULT_SERVER_PORT,
+};
 
 pub async fn load_device_info(config_path: &Path) -> Result<DeviceInfo> {
     let certificate_id = get_or_generate_alias(config_path).await?;
@@ -17,8 +19,11 @@ pub async fn load_device_info(config_path: &Path) -> Result<DeviceInfo> {
         version: "Technical Preview".to_owned(),
         device_type: Some(DeviceType::Desktop),
         fingerprint: fingerprint.clone(),
-        api_port: 7863,
+        api_port: DEFAULT_SERVER_PORT,
+        ws_port: None,
+        path_prefix: String::new(),
         protocol: "http".to_owned(),
+        endpoints: vec![],
     })
 }
 
@@ -29,4 +34,11 @@ pub struct SanitizedDeviceInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device_model: Option<String>,
     pub device_type: String,
+    /// Port of the HTTP API, absent when talking to older servers.
+    #[serde(default)]
+    pub api_port: Option<u16>,
+    #[serde(default)]
+    pub ws_port: Option<u16>,
+    #[serde(default)]
+    pub path_prefix: String,
 }
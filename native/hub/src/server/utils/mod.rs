@@ -1,2 +1,4 @@
+pub mod bind;
 pub mod device;
 pub mod permission;
+pub mod trust;
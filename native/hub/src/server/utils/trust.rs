@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::info;
+
+use ::discovery::{
+    client::CertValidator,
+    trust_bundle::{ClientIdentity, TrustBundle, TrustImportReport},
+};
+
+use crate::server::{
+    generate_or_load_certificates, get_or_generate_alias, install_client_identity,
+};
+
+/// Writes the trust store of this device to an encrypted bundle at `path`,
+/// returns the number of exported fingerprints.
+pub async fn export_trust_bundle(
+    config_path: &Path,
+    validator: &CertValidator,
+    path: &Path,
+    password: &str,
+    include_identity: bool,
+) -> Result<usize> {
+    let identity = if include_identity {
+        let alias = get_or_generate_alias(config_path).await?;
+        let (_, certificate, private_key) = generate_or_load_certificates(config_path, &alias)
+            .await
+            .context("Failed to load client certificates")?;
+
+        Some(ClientIdentity {
+            alias,
+            certificate,
+            private_key,
+        })
+    } else {
+        None
+    };
+
+    let bundle = TrustBundle::from_validator(validator, identity).await;
+    let data = bundle.encrypt(password)?;
+
+    tokio::fs::write(path, data)
+        .await
+        .with_context(|| format!("Failed to write trust bundle to {}", path.display()))?;
+
+    info!(
+        "Exported {} trusted fingerprints to {}",
+        bundle.entries.len(),
+        path.display()
+    );
+
+    Ok(bundle.entries.len())
+}
+
+/// Merges an encrypted bundle into the trust store of this device, and
+/// installs the client certificate it carries when `import_identity` is set.
+pub async fn import_trust_bundle(
+    config_path: &Path,
+    validator: &CertValidator,
+    path: &Path,
+    password: &str,
+    import_identity: bool,
+) -> Result<TrustImportReport> {
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read trust bundle from {}", path.display()))?;
+
+    let report = TrustBundle::decrypt(&data, password)?
+        .import_into(validator, import_identity)
+        .await?;
+
+    if let Some(identity) = &report.identity {
+        install_client_identity(
+            config_path,
+            &identity.alias,
+            &identity.certificate,
+            &identity.private_key,
+        )
+        .await?;
+        info!("Installed the client certificate of {}", identity.alias);
+    }
+
+    Ok(report)
+}
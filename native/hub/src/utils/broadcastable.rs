@@ -18,3 +18,8 @@ implement_rinf_rust_signal_trait!(
 implement_rinf_rust_signal_trait!(PlaylistUpdate);
 implement_rinf_rust_signal_trait!(TrustListUpdated);
 implement_rinf_rust_signal_trait!(IncommingClientPermissionNotification);
+implement_rinf_rust_signal_trait!(FetchRemoteFileProgress);
+implement_rinf_rust_signal_trait!(SetAlbumCoverArtProgress);
+implement_rinf_rust_signal_trait!(ServerShuttingDown);
+implement_rinf_rust_signal_trait!(PermissionDeniedResponse);
+implement_rinf_rust_signal_trait!(UnsupportedRequestResponse);
@@ -0,0 +1,224 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use lazy_static::lazy_static;
+use tokio::sync::Semaphore;
+use url::Url;
+
+use ::fsio::FsIo;
+use ::http_request::{
+    Bytes, ClientConfig, ConnectionPool, DownloadOptions, Empty, Request, RequestOptions, Uri,
+    download_to,
+};
+
+pub use ::http_request::partial_download_url;
+
+use crate::server::utils::bind::DEFAULT_SERVER_PORT;
+
+const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+lazy_static! {
+    static ref DOWNLOAD_LIMITER: DownloadLimiter =
+        DownloadLimiter::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS);
+}
+
+/// Caps the number of remote files downloaded at the same time.
+struct DownloadLimiter {
+    semaphore: Semaphore,
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    limit: usize,
+    /// Permits held by running downloads which are dropped instead of
+    /// returned, after the limit was lowered below the running downloads
+    excess: usize,
+}
+
+/// A download slot, given back to the limiter when dropped.
+struct DownloadPermit<'a> {
+    limiter: &'a DownloadLimiter,
+}
+
+impl Drop for DownloadPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.release();
+    }
+}
+
+impl DownloadLimiter {
+    fn new(limit: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(limit),
+            state: Mutex::new(LimiterState { limit, excess: 0 }),
+        }
+    }
+
+    async fn acquire(&self) -> Result<DownloadPermit<'_>> {
+        self.semaphore
+            .acquire()
+            .await
+            .with_context(|| "Download limiter closed")?
+            .forget();
+
+        Ok(DownloadPermit { limiter: self })
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.excess > 0 {
+            state.excess -= 1;
+        } else {
+            self.semaphore.add_permits(1);
+        }
+    }
+
+    fn set_limit(&self, limit: usize) {
+        let limit = limit.max(1);
+        let mut state = self.state.lock().unwrap();
+
+        if limit > state.limit {
+            let added = limit - state.limit;
+            let reclaimed = added.min(state.excess);
+            state.excess -= reclaimed;
+            self.semaphore.add_permits(added - reclaimed);
+        } else {
+            // Permits held by running downloads are dropped once they are
+            // released
+            let removed = state.limit - limit;
+            let forgotten = self.semaphore.forget_permits(removed);
+            state.excess += removed - forgotten;
+        }
+        state.limit = limit;
+    }
+}
+
+/// Changes how many remote files can be downloaded in parallel.
+pub fn set_max_concurrent_downloads(limit: usize) {
+    DOWNLOAD_LIMITER.set_limit(limit);
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadConfig {
+    /// Attempts made after a failure before the download is abandoned, each
+    /// going on from what was already downloaded.
+    pub max_retries: u32,
+    /// The wait before the first retry, doubled before each of the next ones.
+    pub retry_delay: Duration,
+    /// Limit for the wait for the response and for each piece of its body.
+    pub timeout: Duration,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            retry_delay: Duration::from_secs(1),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Downloads a remote file into `destination`.
+///
+/// Data is written to a temporary file next to the destination. If a
+/// previous attempt left a partial file behind, the download resumes from
+/// it. The complete file is checked against the digest reported by the
+/// server before it is moved into place. A download which would leave less
+/// than the free space floor on the volume stops, keeping the partial file.
+pub async fn download_file<F>(
+    fsio: &FsIo,
+    url: &Url,
+    destination: &Path,
+    client_config: Arc<ClientConfig>,
+    config: &DownloadConfig,
+    on_progress: F,
+) -> Result<()>
+where
+    F: Fn(u64, u64),
+{
+    let _permit = DOWNLOAD_LIMITER.acquire().await?;
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("Missing host in URL"))?
+        .to_string();
+    let port = url.port().unwrap_or(DEFAULT_SERVER_PORT);
+
+    let uri = Uri::builder()
+        .scheme("https")
+        .authority(format!("{host}:{port}"))
+        .path_and_query(url.path())
+        .build()
+        .with_context(|| "Failed to build request URI")?;
+    let req = Request::builder()
+        .uri(uri)
+        .body(Empty::<Bytes>::new())
+        .with_context(|| "Failed to build HTTP request")?;
+
+    let options = DownloadOptions {
+        request: RequestOptions {
+            timeout: config.timeout,
+            retries: config.max_retries,
+            retry_backoff: config.retry_delay,
+            ..RequestOptions::idempotent()
+        },
+        resume: true,
+        ..Default::default()
+    };
+    download_to(
+        fsio,
+        ConnectionPool::shared(),
+        client_config,
+        req,
+        destination,
+        &options,
+        on_progress,
+    )
+    .await
+    .with_context(|| format!("Failed to download {url}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lowering_the_limit_applies_once_downloads_finish() {
+        let limiter = DownloadLimiter::new(3);
+        let permits = vec![
+            limiter.acquire().await.unwrap(),
+            limiter.acquire().await.unwrap(),
+            limiter.acquire().await.unwrap(),
+        ];
+
+        limiter.set_limit(1);
+        drop(permits);
+        assert_eq!(limiter.semaphore.available_permits(), 1);
+
+        limiter.set_limit(2);
+        assert_eq!(limiter.semaphore.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn raising_the_limit_cancels_the_pending_excess() {
+        let limiter = DownloadLimiter::new(2);
+        let first = limiter.acquire().await.unwrap();
+        let second = limiter.acquire().await.unwrap();
+
+        limiter.set_limit(1);
+        limiter.set_limit(3);
+        assert_eq!(limiter.semaphore.available_permits(), 1);
+
+        drop(first);
+        drop(second);
+        assert_eq!(limiter.semaphore.available_permits(), 3);
+    }
+}
@@ -0,0 +1,96 @@
+//! Log entries written as NDJSON, one object per line, to be attached to
+//! bug reports.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::json;
+
+use ::database::entities::log;
+
+/// Stands in for the file paths of redacted entries.
+pub const REDACTED_PATH: &str = "<path>";
+
+/// Absolute paths and file URIs, preceded by anything that cannot be part of
+/// a path so relative fragments like `1/2` or URLs are left alone.
+static PATH_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?P<lead>^|[^\w.~:/\\-])(?:(?:file|content)://|[A-Za-z]:[\\/]|\\\\|~?/)[^\s"'<>|]+"#,
+    )
+    .unwrap()
+});
+
+/// Replaces the file paths found in `text` with `REDACTED_PATH`.
+pub fn redact_paths(text: &str) -> String {
+    PATH_PATTERN
+        .replace_all(text, format!("${{lead}}{REDACTED_PATH}"))
+        .into_owned()
+}
+
+/// Writes `entries` as NDJSON, redacting the file paths if asked to.
+pub fn logs_to_ndjson(entries: &[log::Model], redact: bool) -> String {
+    let redact = |x: &str| {
+        if redact {
+            redact_paths(x)
+        } else {
+            x.to_owned()
+        }
+    };
+
+    entries
+        .iter()
+        .map(|x| {
+            let line = json!({
+                "id": x.id,
+                "date": x.date.to_rfc3339(),
+                "level": x.level,
+                "domain": redact(&x.domain),
+                "detail": redact(&x.detail),
+            });
+            format!("{line}\n")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+
+    #[test]
+    fn paths_are_redacted() {
+        assert_eq!(
+            redact_paths("Failed to read /home/user/Music/a b.flac: denied"),
+            "Failed to read <path> b.flac: denied"
+        );
+        assert_eq!(
+            redact_paths(r#"path: "C:\\Users\\user\\a.flac", uri: content://media/1"#),
+            r#"path: "<path>", uri: <path>"#
+        );
+        assert_eq!(
+            redact_paths("Retry 1/2 for https://example.com/api"),
+            "Retry 1/2 for https://example.com/api"
+        );
+    }
+
+    #[test]
+    fn entries_are_written_one_per_line() {
+        let entry = log::Model {
+            id: 1,
+            date: DateTime::from_timestamp(0, 0).unwrap(),
+            level: "ERROR".to_owned(),
+            domain: "scanner".to_owned(),
+            detail: "Broken file ~/Music/a.flac".to_owned(),
+        };
+
+        let ndjson = logs_to_ndjson(&[entry.clone(), entry], true);
+        let lines: Vec<_> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["level"], "ERROR");
+        assert_eq!(line["detail"], "Broken file <path>");
+        assert_eq!(line["date"], "1970-01-01T00:00:00+00:00");
+    }
+}
@@ -0,0 +1,254 @@
+//! The log files written with `--enable-log`, kept in the `logs` directory
+//! of the configuration, rotated by size and compressed once rotated.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result};
+use flate2::{Compression, write::GzEncoder};
+
+use ::discovery::config::get_config_dir;
+
+pub const LOG_DIR_NAME: &str = "logs";
+/// Size after which the log file is rotated.
+pub const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// Rotated log files kept, the oldest ones are removed first.
+pub const MAX_ROTATED_LOG_FILES: usize = 5;
+
+/// Suffix of the log files previous versions wrote into the working
+/// directory.
+const STRAY_LOG_SUFFIX: &str = ".rune.log";
+
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The directory log files are written into, `None` unless `--enable-log`
+/// was given.
+pub fn log_dir() -> Option<&'static Path> {
+    LOG_DIR.get().map(|x| x.as_path())
+}
+
+/// Opens a new log file in the `logs` directory of the configuration,
+/// moving the log files of previous versions into it first.
+pub fn open_log_file() -> Result<RotatingLogFile> {
+    let dir = get_config_dir()?.join(LOG_DIR_NAME);
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create the log directory {dir:?}"))?;
+
+    migrate_stray_logs(Path::new("."), &dir)?;
+    let writer = RotatingLogFile::open(&dir, MAX_LOG_FILE_SIZE, MAX_ROTATED_LOG_FILES)?;
+    let _ = LOG_DIR.set(dir);
+
+    Ok(writer)
+}
+
+fn log_file_name() -> String {
+    format!(
+        "rune.{}.log",
+        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S%.3f")
+    )
+}
+
+/// Writes `source` compressed into `target`, then removes `source`.
+fn compress_into(source: &Path, target: &Path) -> io::Result<()> {
+    let mut input = BufReader::new(File::open(source)?);
+    let mut encoder = GzEncoder::new(File::create(target)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+
+    fs::remove_file(source)
+}
+
+/// Compresses a rotated log file next to it, as `.log.gz`.
+fn compress_log_file(path: &Path) -> io::Result<()> {
+    let mut target = path.as_os_str().to_owned();
+    target.push(".gz");
+    compress_into(path, Path::new(&target))
+}
+
+/// Removes the oldest rotated log files, keeping `max_files` of them.
+fn prune_log_files(dir: &Path, max_files: usize) -> io::Result<()> {
+    let mut rotated: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter(|x| x.extension().is_some_and(|x| x == "gz"))
+        .collect();
+    // Names start with the time the file was created
+    rotated.sort();
+
+    let excess = rotated.len().saturating_sub(max_files);
+    for path in &rotated[..excess] {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Moves the `*.rune.log` files previous versions left in `from` into the
+/// log directory, compressed and named like rotated log files.
+fn migrate_stray_logs(from: &Path, dir: &Path) -> Result<()> {
+    let Ok(entries) = fs::read_dir(from) else {
+        return Ok(());
+    };
+
+    for entry in entries.filter_map(|x| x.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(created_at) = name.strip_suffix(STRAY_LOG_SUFFIX) else {
+            continue;
+        };
+
+        let target = dir.join(format!("rune.{created_at}.log.gz"));
+        compress_into(&entry.path(), &target)
+            .with_context(|| format!("Failed to move the log file {name}"))?;
+    }
+
+    // Files of runs that ended before rotating theirs
+    for entry in fs::read_dir(dir)?.filter_map(|x| x.ok()) {
+        if entry.path().extension().is_some_and(|x| x == "log") {
+            compress_log_file(&entry.path())?;
+        }
+    }
+
+    prune_log_files(dir, MAX_ROTATED_LOG_FILES)?;
+
+    Ok(())
+}
+
+/// The size of the files of the log directory.
+pub fn log_dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|x| x.ok()?.metadata().ok())
+        .filter(|x| x.is_file())
+        .map(|x| x.len())
+        .sum()
+}
+
+/// A log file which starts a new file once `max_size` bytes were written
+/// into it, compressing the previous one.
+pub struct RotatingLogFile {
+    dir: PathBuf,
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl RotatingLogFile {
+    pub fn open(dir: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let path = dir.join(log_file_name());
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            path,
+            file,
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let path = self.dir.join(log_file_name());
+        if path == self.path {
+            return Ok(());
+        }
+
+        // The previous file is closed before it is compressed
+        self.file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.size = 0;
+        let previous = std::mem::replace(&mut self.path, path);
+
+        compress_log_file(&previous)?;
+        prune_log_files(&self.dir, self.max_files)
+    }
+}
+
+impl Write for RotatingLogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0
+            && self.size + buf.len() as u64 > self.max_size
+            && let Err(e) = self.rotate()
+        {
+            // Logging it would write into this file again
+            eprintln!("Failed to rotate the log file: {e}");
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rune-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn rotated_files_are_compressed_and_bounded() {
+        let dir = temp_dir("log-rotation");
+        let mut file = RotatingLogFile::open(&dir, 64, 2).unwrap();
+
+        for i in 0..8 {
+            let line = format!("{i}: {}\n", "x".repeat(40));
+            file.write_all(line.as_bytes()).unwrap();
+            // Rotated files are named after the millisecond they start at
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let names = names(&dir);
+        let current: Vec<_> = names.iter().filter(|x| x.ends_with(".log")).collect();
+        let rotated: Vec<_> = names.iter().filter(|x| x.ends_with(".log.gz")).collect();
+        assert_eq!(current.len(), 1, "{names:?}");
+        assert_eq!(rotated.len(), 2, "{names:?}");
+        assert!(log_dir_size(&dir) > 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stray_log_files_are_moved_into_the_log_directory() {
+        let from = temp_dir("stray-logs");
+        let dir = from.join(LOG_DIR_NAME);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(from.join("2024-01-01_10-00-00.rune.log"), "old log").unwrap();
+        fs::write(from.join("notes.txt"), "not a log").unwrap();
+
+        migrate_stray_logs(&from, &dir).unwrap();
+
+        assert_eq!(names(&from), [LOG_DIR_NAME, "notes.txt"]);
+        assert_eq!(names(&dir), ["rune.2024-01-01_10-00-00.log.gz"]);
+
+        fs::remove_dir_all(&from).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! The filter of the logs, which can be changed while the hub runs to
+//! capture a debug trace without restarting it.
+
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{Context, Result, anyhow, bail};
+use log::warn;
+use tracing_subscriber::{EnvFilter, reload};
+
+/// The filter of the stdout and logcat logs.
+pub const DEFAULT_LOG_FILTER: &str = "symphonia_format_ogg=off,symphonia_core=off,\
+     symphonia_bundle_mp3::demuxer=off,sea_orm_migration::migrator=off,info";
+
+/// The filter of the server logs, which also silences the indexer.
+pub const DEFAULT_SERVER_LOG_FILTER: &str = "symphonia_format_ogg=off,symphonia_core=off,\
+     symphonia_bundle_mp3::demuxer=off,tantivy::directory=off,tantivy::indexer=off,\
+     sea_orm_migration::migrator=off,info";
+
+/// The filter of the log file written with `--enable-log`.
+pub const FILE_LOG_FILTER: &str = "debug";
+
+type ReloadFn = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+struct LogFilter {
+    reload: ReloadFn,
+    current: Mutex<String>,
+}
+
+static LOG_FILTER: OnceLock<LogFilter> = OnceLock::new();
+
+/// Makes the filter behind `handle`, initialized from `filter`, the one
+/// `set_log_filter` changes. Only the first registered filter is kept.
+pub fn register_log_filter<S: 'static>(handle: reload::Handle<EnvFilter, S>, filter: &str) {
+    let registered = LOG_FILTER.set(LogFilter {
+        reload: Box::new(move |x| handle.reload(x)),
+        current: Mutex::new(filter.to_owned()),
+    });
+
+    if registered.is_err() {
+        warn!("A log filter is already registered, ignoring the new one");
+    }
+}
+
+/// Replaces the filter of the logs, leaving the active one untouched if
+/// `filter` is invalid.
+pub fn set_log_filter(filter: &str) -> Result<()> {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        bail!("The log filter is empty");
+    }
+
+    let parsed =
+        EnvFilter::try_new(filter).with_context(|| format!("Invalid log filter: {filter}"))?;
+    let log_filter = LOG_FILTER
+        .get()
+        .ok_or_else(|| anyhow!("The log filter cannot be changed in this process"))?;
+
+    (log_filter.reload)(parsed).context("Failed to reload the log filter")?;
+    *log_filter.current.lock().unwrap() = filter.to_owned();
+
+    Ok(())
+}
+
+/// The active filter, `None` if it cannot be changed.
+pub fn current_log_filter() -> Option<String> {
+    LOG_FILTER.get().map(|x| x.current.lock().unwrap().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::Registry;
+
+    use super::*;
+
+    #[test]
+    fn invalid_filters_leave_the_active_one() {
+        let (_layer, handle) = reload::Layer::<_, Registry>::new(EnvFilter::new("info"));
+        register_log_filter(handle.clone(), "info");
+
+        set_log_filter(" info,hub=debug ").unwrap();
+        assert_eq!(current_log_filter().as_deref(), Some("info,hub=debug"));
+
+        assert!(set_log_filter("hub=loudest").is_err());
+        assert!(set_log_filter("  ").is_err());
+        assert_eq!(current_log_filter().as_deref(), Some("info,hub=debug"));
+        let active = handle.with_current(|x| x.to_string()).unwrap();
+        assert!(active.contains("hub=debug"), "Active filter: {active}");
+    }
+}
@@ -1,18 +1,29 @@
 pub mod broadcastable;
+pub mod download;
+pub mod log_export;
+pub mod log_file;
+pub mod log_filter;
 pub mod nid;
 pub mod player;
+pub mod proxy_settings;
+pub mod scrobble_history;
+pub mod scrobble_queue;
+pub mod scrobble_rules;
+pub mod task_registry;
 
 use std::{
     collections::HashMap,
     fmt::Debug,
     path::{Path, PathBuf},
     sync::{Arc, OnceLock},
+    time::Instant,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use fsio::FsIo;
 use log::{error, info};
 use nid::get_or_create_node_id;
+use proxy_settings::ProxySettingsStore;
 use rinf::DartSignal;
 use scrobbling::manager::ScrobblingServiceManager;
 use tokio::sync::{Mutex, RwLock};
@@ -24,8 +35,9 @@ use ::database::{
         mixes::query_mix_media_files,
     },
     connection::{
-        LibraryState, MainDbConnection, RecommendationDbConnection, check_library_state,
-        connect_main_db, connect_recommendation_db, create_redirect,
+        LibraryLock, LibraryState, LockMode, MainDbConnection, RecommendationDbConnection,
+        check_library_state, connect_main_db, connect_recommendation_db, create_redirect,
+        get_storage_info, lock_library, probe_library_writable, resolve_library_read_only,
     },
     entities::media_files,
     playing_item::MediaFileHandle,
@@ -37,13 +49,17 @@ use ::playback::{
 };
 use ::scrobbling::manager::ScrobblingManager;
 
-use crate::backends::{local::local_player_loop, remote::server_player_loop};
+use crate::backends::{
+    local::local_player_loop,
+    remote::{output::RemoteOutputManager, server_player_loop},
+};
 use crate::messages::*;
 use crate::server::ServerManager;
+use crate::utils::log_filter::{DEFAULT_LOG_FILTER, register_log_filter};
+use crate::utils::task_registry::TaskRegistry;
 
 #[cfg(target_os = "android")]
 use tracing_logcat::{LogcatMakeWriter, LogcatTag};
-#[cfg(not(target_os = "android"))]
 use tracing_subscriber::EnvFilter;
 #[cfg(target_os = "android")]
 use tracing_subscriber::fmt::format::Format;
@@ -51,6 +67,8 @@ use tracing_subscriber::fmt::format::Format;
 pub struct DatabaseConnections {
     pub main_db: Arc<MainDbConnection>,
     pub recommend_db: Arc<RecommendationDbConnection>,
+    /// Keeps maintenance tasks away while the library is open
+    pub library_lock: Arc<LibraryLock>,
 }
 
 pub async fn initialize_databases(
@@ -61,6 +79,12 @@ pub async fn initialize_databases(
 ) -> Result<DatabaseConnections> {
     info!("Initializing databases");
 
+    let read_only = resolve_library_read_only(fsio, path, db_path).await?;
+    let storage_info = get_storage_info(path, db_path, read_only)?;
+    let Some(library_lock) = lock_library(&storage_info, LockMode::Shared, false)? else {
+        bail!("The library is being maintained by another process, try again later");
+    };
+
     let main_db = connect_main_db(fsio, path, db_path, node_id)
         .await
         .with_context(|| "Failed to connect to main DB")?;
@@ -72,16 +96,10 @@ pub async fn initialize_databases(
     Ok(DatabaseConnections {
         main_db: Arc::new(main_db),
         recommend_db: Arc::new(recommend_db),
+        library_lock: Arc::new(library_lock),
     })
 }
 
-#[derive(Debug, Default)]
-pub struct TaskTokens {
-    pub scan_token: Option<CancellationToken>,
-    pub analyze_token: Option<CancellationToken>,
-    pub deduplicate_token: Option<CancellationToken>,
-}
-
 #[derive(Debug, Clone, Copy)]
 pub enum RunningMode {
     Server,
@@ -95,8 +113,10 @@ pub struct GlobalParams {
     pub node_id: Arc<String>,
     pub main_db: Arc<MainDbConnection>,
     pub recommend_db: Arc<RecommendationDbConnection>,
+    /// `None` when the library is not on this device
+    pub library_lock: Option<Arc<LibraryLock>>,
     pub main_token: Arc<CancellationToken>,
-    pub task_tokens: Arc<Mutex<TaskTokens>>,
+    pub task_registry: Arc<TaskRegistry>,
     pub player: Arc<Mutex<dyn Playable>>,
     pub sfx_player: Arc<Mutex<SfxPlayer>>,
     pub scrobbler: Arc<Mutex<dyn ScrobblingServiceManager>>,
@@ -105,7 +125,10 @@ pub struct GlobalParams {
     pub cert_validator: Arc<RwLock<CertValidator>>,
     pub permission_manager: Arc<RwLock<PermissionManager>>,
     pub server_manager: OnceLock<Arc<ServerManager>>,
+    pub remote_output: Arc<RemoteOutputManager>,
     pub running_mode: RunningMode,
+    /// When the backend was started, for its uptime
+    pub started_at: Instant,
 }
 
 impl Debug for GlobalParams {
@@ -167,12 +190,21 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
             )?);
             let node_id = get_or_create_node_id(&fsio, config_path).await?.to_string();
 
+            match ProxySettingsStore::new(Path::new(config_path)).load().await {
+                Ok(settings) => settings.apply(),
+                Err(e) => error!("Failed to load proxy settings: {e:#}"),
+            }
+
             match &dart_signal.message.hosted_on {
                 OperationDestination::Local => {
                     let database_path = dart_signal.message.db_path;
                     let database_mode = dart_signal.message.mode;
                     info!("Received path: {media_library_path}");
 
+                    // Read-only libraries keep their databases in the
+                    // per-user directory, there is nothing to initialize
+                    let read_only = !probe_library_writable(&fsio, media_library_path).await;
+
                     let library_test = match check_library_state(media_library_path) {
                         Ok(x) => x,
                         Err(e) => {
@@ -182,12 +214,13 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                                 success: false,
                                 error: Some(format!("{e:#?}")),
                                 not_ready: false,
+                                read_only,
                             });
                             continue;
                         }
                     };
 
-                    if database_mode.is_none() {
+                    if database_mode.is_none() && !read_only {
                         match &library_test {
                             LibraryState::Uninitialized => {
                                 broadcaster.broadcast(&SetMediaLibraryPathResponse {
@@ -195,6 +228,7 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                                     success: true,
                                     error: None,
                                     not_ready: true,
+                                    read_only,
                                 });
                                 continue;
                             }
@@ -202,7 +236,8 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                         }
                     }
 
-                    if let Some(mode) = database_mode
+                    if !read_only
+                        && let Some(mode) = database_mode
                         && mode == LibraryInitializeMode::Redirected
                         && let Err(e) = create_redirect(media_library_path)
                     {
@@ -211,6 +246,7 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                             success: false,
                             error: Some(format!("{e:#?}")),
                             not_ready: false,
+                            read_only,
                         });
                         continue;
                     }
@@ -231,6 +267,7 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                                 success: true,
                                 error: None,
                                 not_ready: false,
+                                read_only,
                             });
 
                             // Clone the Arc for this iteration
@@ -255,6 +292,7 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                                 success: false,
                                 error: Some(format!("{e:#?}")),
                                 not_ready: false,
+                                read_only,
                             });
                         }
                     }
@@ -268,6 +306,7 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                                 success: true,
                                 error: None,
                                 not_ready: false,
+                                read_only: false,
                             });
                         }
                         Err(e) => {
@@ -277,6 +316,7 @@ pub async fn receive_media_library_path(scrobbler: Arc<Mutex<ScrobblingManager>>
                                 success: false,
                                 error: Some(format!("{e:#?}")),
                                 not_ready: false,
+                                read_only: false,
                             });
                         }
                     }
@@ -389,7 +429,9 @@ pub fn process_cover_art_path(
                         .and_then(|name| name.to_str())
                         .unwrap_or_default();
 
-                    // Construct the URL using the host and cache prefix
+                    // The host is the base URL of the session, it already carries
+                    // the configured port and path prefix
+                    let host = host.trim_end_matches('/');
                     format!("{host}/files/cache/{file_name}")
                 } else {
                     // No host available, return the original path
@@ -527,13 +569,13 @@ where
 
 #[cfg(not(target_os = "android"))]
 pub fn init_logging() {
-    let stdout_filter = EnvFilter::new(
-        "symphonia_format_ogg=off,symphonia_core=off,symphonia_bundle_mp3::demuxer=off,sea_orm_migration::migrator=off,info",
-    );
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(DEFAULT_LOG_FILTER))
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    builder.init();
 
-    tracing_subscriber::fmt()
-        .with_env_filter(stdout_filter)
-        .init();
+    register_log_filter(handle, DEFAULT_LOG_FILTER);
 }
 
 #[cfg(target_os = "android")]
@@ -541,9 +583,14 @@ pub fn init_logging() {
     let tag = LogcatTag::Fixed(env!("CARGO_PKG_NAME").to_owned());
     let writer = LogcatMakeWriter::new(tag).expect("Failed to initialize logcat writer");
 
-    tracing_subscriber::fmt()
+    let builder = tracing_subscriber::fmt()
         .event_format(Format::default().with_level(false).without_time())
         .with_writer(writer)
         .with_ansi(false)
-        .init();
+        .with_env_filter(EnvFilter::new(DEFAULT_LOG_FILTER))
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    builder.init();
+
+    register_log_filter(handle, DEFAULT_LOG_FILTER);
 }
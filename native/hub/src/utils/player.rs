@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    path::Path,
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
@@ -12,10 +13,14 @@ use tokio::{
     sync::{Mutex, RwLock},
     task,
 };
+use tokio_util::sync::CancellationToken;
 
 use ::database::{
     actions::{
-        logging::insert_log, playback_queue::replace_playback_queue, stats::increase_played_through,
+        logging::insert_log,
+        playback_queue::replace_playback_queue,
+        scrobble_history::{ScrobbleHistoryEntry, insert_scrobble_history, prune_scrobble_history},
+        stats::{increase_played_through, record_listening},
     },
     connection::MainDbConnection,
     playing_item::{
@@ -35,6 +40,9 @@ use ::scrobbling::{ScrobblingTrack, manager::ScrobblingServiceManager};
 
 use crate::messages::*;
 use crate::utils::Broadcaster;
+use crate::utils::scrobble_rules::{
+    ListeningProgress, PlaytimeTracker, ScrobbleRules, ScrobbleRulesStore,
+};
 
 pub fn metadata_summary_to_scrobbling_track(
     metadata: &PlayingItemMetadataSummary,
@@ -54,16 +62,75 @@ pub fn metadata_summary_to_scrobbling_track(
     }
 }
 
+/// Keeps a scrobble in the local history, whether a service is logged in to
+/// or not, dropping the entries older than the retention of the rules.
+async fn record_scrobble_history(
+    main_db: &MainDbConnection,
+    item: Option<&PlayingItem>,
+    track: &ScrobblingTrack,
+    rules: &ScrobbleRules,
+) -> Result<()> {
+    let media_file_id = match item {
+        Some(PlayingItem::InLibrary(id)) => Some(*id),
+        _ => None,
+    };
+
+    insert_scrobble_history(
+        main_db,
+        ScrobbleHistoryEntry {
+            media_file_id,
+            artist: track.artist.clone(),
+            title: track.track.clone(),
+            album: track.album.clone().filter(|x| !x.is_empty()),
+            timestamp: track.listened_at() as i64,
+        },
+    )
+    .await?;
+
+    if let Some(days) = rules.history_retention_days {
+        let removed = prune_scrobble_history(main_db, chrono::Duration::days(days.into())).await?;
+        if removed > 0 {
+            info!("Pruned {removed} scrobble history entries");
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the listening to a track of the library into its stats.
+async fn record_listening_progress(
+    main_db: &MainDbConnection,
+    progress: &ListeningProgress,
+) -> Result<()> {
+    let media_file_id = match &progress.item {
+        PlayingItem::InLibrary(id) => *id,
+        PlayingItem::Online(_, Some(online_file)) => online_file.id,
+        _ => return Ok(()),
+    };
+
+    record_listening(
+        main_db,
+        media_file_id,
+        progress.position.map(|x| x.as_secs_f64()),
+        progress.listened.as_secs_f64(),
+    )
+    .await?;
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn initialize_local_player(
     fsio: Arc<FsIo>,
     lib_path: Arc<String>,
+    config_path: Arc<String>,
     main_db: Arc<MainDbConnection>,
     player: Arc<Mutex<dyn Playable>>,
     scrobbler: Arc<Mutex<dyn ScrobblingServiceManager>>,
     broadcaster: Arc<dyn Broadcaster>,
     cert_validator: Arc<RwLock<CertValidator>>,
     permission_manager: Arc<RwLock<PermissionManager>>,
+    cancel_token: CancellationToken,
 ) -> Result<()> {
     let status_receiver = player.lock().await.subscribe_status();
     let played_through_receiver = player.lock().await.subscribe_played_through();
@@ -87,10 +154,11 @@ pub async fn initialize_local_player(
     let manager = Arc::new(Mutex::new(MediaControlManager::new()?));
 
     let os_controller_receiver = manager.lock().await.subscribe_controller_events();
-    let dispatcher = Arc::new(Mutex::new(PlayingItemActionDispatcher::new()));
-    let dispatcher_for_played_through = Arc::clone(&dispatcher);
+    let dispatcher = Arc::new(Mutex::new(PlayingItemActionDispatcher::with_cancel_token(
+        cancel_token.clone(),
+    )));
 
-    let scrobber_for_played_through = Arc::clone(&scrobbler);
+    let scrobble_rules_store = ScrobbleRulesStore::new(Path::new(config_path.as_str()));
 
     let scrobber_error_receiver = scrobbler.lock().await.subscribe_error();
     let scrobber_status_receiver = scrobbler.lock().await.subscribe_login_status();
@@ -103,6 +171,12 @@ pub async fn initialize_local_player(
     let broadcaster_for_certificate = Arc::clone(&broadcaster);
     let broadcaster_for_permission_manager = Arc::clone(&broadcaster);
 
+    let cancel_token_for_os_controller = cancel_token.clone();
+    // The scrobbler outlives the library, unlike the player closing its
+    // channels once terminated
+    let cancel_token_for_scrobbler = cancel_token.clone();
+    let cancel_token_for_scrobble_log = cancel_token.clone();
+
     manager.lock().await.initialize()?;
 
     info!("Initializing event listeners");
@@ -112,8 +186,21 @@ pub async fn initialize_local_player(
         let mut cached_meta: Option<PlayingItemMetadataSummary> = None;
         let mut cached_cover_art: Option<String> = None;
         let mut last_status_item: Option<PlayingItem> = None;
+        let mut playtime = PlaytimeTracker::default();
+        let mut scrobble_rules = scrobble_rules_store.load().await.unwrap_or_else(|e| {
+            error!("{e:#}");
+            Default::default()
+        });
+
+        loop {
+            let status = tokio::select! {
+                status = status_receiver.recv() => match status {
+                    Ok(status) => status,
+                    Err(_) => break,
+                },
+                _ = cancel_token.cancelled() => break,
+            };
 
-        while let Ok(status) = status_receiver.recv().await {
             debug!("Player status updated: {status:?}");
 
             let item = status.item.clone();
@@ -200,6 +287,33 @@ pub async fn initialize_local_player(
                 }
             };
 
+            // The rules are read again for every pass, so edits apply from
+            // the next track on
+            if playtime.observe(&status, meta.duration) {
+                match scrobble_rules_store.load().await {
+                    Ok(rules) => scrobble_rules = rules,
+                    Err(e) => error!("{e:#}"),
+                }
+            }
+            if let Some(progress) = playtime.take_progress()
+                && let Err(e) = record_listening_progress(&main_db, &progress).await
+            {
+                error!("Failed to record the listening progress: {e:#}");
+            }
+            if let Some(started_at) = playtime.take_scrobble(&scrobble_rules, meta.duration) {
+                let mut track = metadata_summary_to_scrobbling_track(&meta);
+                track.timestamp = Some(started_at);
+
+                // Recorded first, for the services accepting it to find it
+                if let Err(e) =
+                    record_scrobble_history(&main_db, status.item.as_ref(), &track, &scrobble_rules)
+                        .await
+                {
+                    error!("Failed to record scrobble history: {e:#}");
+                }
+                scrobbler.lock().await.scrobble_all(track);
+            }
+
             let position = status.position;
             let duration = meta.duration;
             let progress_percentage = if duration == 0. {
@@ -222,6 +336,7 @@ pub async fn initialize_local_player(
                 ready: status.ready,
                 cover_art_path: cached_cover_art.clone(),
                 lib_path: lib_path.as_str().to_string(),
+                unavailable_audio_device: status.unavailable_output_device,
             };
 
             if let Err(e) =
@@ -237,6 +352,10 @@ pub async fn initialize_local_player(
 
             broadcaster_for_main.broadcast(&formated_status);
         }
+
+        // Dropping the last handle unregisters the OS media controls
+        info!("Releasing OS media controls");
+        drop(manager);
     });
 
     task::spawn(async move {
@@ -255,8 +374,6 @@ pub async fn initialize_local_player(
 
     task::spawn(async move {
         let main_db = Arc::clone(&main_db_for_played_throudh);
-        let dispatcher = Arc::clone(&dispatcher_for_played_through);
-        let scrobbler = Arc::clone(&scrobber_for_played_through);
 
         while let Ok(item) = played_through_receiver.recv().await {
             match &item {
@@ -280,28 +397,21 @@ pub async fn initialize_local_player(
                 PlayingItem::Online(_, None) => {}
                 PlayingItem::Unknown => {}
             }
-
-            let metadata = dispatcher
-                .lock()
-                .await
-                .get_metadata_summary(&fsio, &main_db, [item].as_ref())
-                .await;
-
-            if let Ok(metadata) = metadata {
-                if metadata.is_empty() {
-                    continue;
-                }
-
-                let metadata: PlayingItemMetadataSummary = metadata[0].clone();
-                let track: ScrobblingTrack = metadata_summary_to_scrobbling_track(&metadata);
-
-                scrobbler.lock().await.scrobble_all(track);
-            }
         }
     });
 
     task::spawn(async move {
-        while let Ok(value) = scrobber_status_receiver.recv().await {
+        let cancel_token = cancel_token_for_scrobbler;
+
+        loop {
+            let value = tokio::select! {
+                value = scrobber_status_receiver.recv() => match value {
+                    Ok(value) => value,
+                    Err(_) => break,
+                },
+                _ = cancel_token.cancelled() => break,
+            };
+
             broadcaster_for_scrobbler.broadcast(&ScrobbleServiceStatusUpdated {
                 services: value
                     .into_iter()
@@ -309,6 +419,13 @@ pub async fn initialize_local_player(
                         service_id: x.service.to_string(),
                         is_available: x.is_available,
                         error: x.error_message,
+                        queued: x.queued,
+                        scrobbling_enabled: x.flags.scrobbling,
+                        now_playing_enabled: x.flags.now_playing,
+                        error_detail: x.health.error_detail,
+                        degraded_since: x.health.degraded_since.map(|x| x as i64),
+                        needs_reauthentication: x.health.needs_reauthentication,
+                        base_url: x.endpoint.map(|x| x.base_url),
                     })
                     .collect(),
             });
@@ -323,8 +440,17 @@ pub async fn initialize_local_player(
 
     task::spawn(async move {
         let main_db = Arc::clone(&main_db_for_scrobble_log);
+        let cancel_token = cancel_token_for_scrobble_log;
+
+        loop {
+            let error = tokio::select! {
+                error = scrobber_error_receiver.recv() => match error {
+                    Ok(error) => error,
+                    Err(_) => break,
+                },
+                _ = cancel_token.cancelled() => break,
+            };
 
-        while let Ok(error) = scrobber_error_receiver.recv().await {
             error!(
                 "Scrobbler received error: {:?}::{:?}: {:#?}",
                 error.service, error.action, error.error
@@ -381,8 +507,17 @@ pub async fn initialize_local_player(
 
     task::spawn(async move {
         let player = Arc::clone(&player);
+        let cancel_token = cancel_token_for_os_controller;
+
+        loop {
+            let value = tokio::select! {
+                value = os_controller_receiver.recv() => match value {
+                    Ok(value) => value,
+                    Err(_) => break,
+                },
+                _ = cancel_token.cancelled() => break,
+            };
 
-        while let Ok(value) = os_controller_receiver.recv().await {
             if let Err(e) = handle_media_control_event(&player, value)
                 .await
                 .with_context(|| "Unable to handle control event")
@@ -424,6 +559,9 @@ pub async fn initialize_local_player(
                         discovery::server::UserStatus::Pending => ClientStatus::Pending,
                         discovery::server::UserStatus::Blocked => ClientStatus::Blocked,
                     },
+                    throttled_messages: 0,
+                    dropped_messages: 0,
+                    connections: vec![],
                 },
             });
         }
@@ -438,42 +576,43 @@ pub async fn send_playlist_update(
     playlist: &PlaylistStatus,
     broadcaster: &dyn Broadcaster,
 ) {
-    let items: Vec<PlayingItem> = playlist.items.clone();
-
-    let dispatcher = PlayingItemActionDispatcher::new();
-
-    match dispatcher
-        .get_metadata_summary(&fsio, db, &items.clone())
-        .await
-    {
-        Ok(summaries) => {
-            // Create a HashMap to store summaries by their id
-            let summary_map: HashMap<PlayingItem, _> = summaries
-                .into_iter()
-                .map(|summary| (summary.item.clone(), summary))
-                .collect();
-
-            // Reorder items according to file_ids
-            let items: Vec<PlaylistItem> = items
-                .into_iter()
-                .filter_map(|id| summary_map.get(&id))
-                .map(|summary| PlaylistItem {
-                    item: summary.item.clone().into(),
-                    artist: summary.artist.clone(),
-                    album: summary.album.clone(),
-                    title: summary.title.clone(),
-                    duration: summary.duration,
-                })
-                .collect();
-
-            broadcaster.broadcast(&PlaylistUpdate { items });
-        }
+    match fetch_playlist_items(&fsio, db, &playlist.items).await {
+        Ok(items) => broadcaster.broadcast(&PlaylistUpdate { items }),
         Err(e) => {
             error!("Error happened while updating playlist: {e:?}")
         }
     }
 }
 
+/// Resolves the metadata of the queued `items`, keeping their order.
+pub async fn fetch_playlist_items(
+    fsio: &FsIo,
+    db: &DatabaseConnection,
+    items: &[PlayingItem],
+) -> Result<Vec<PlaylistItem>> {
+    let dispatcher = PlayingItemActionDispatcher::new();
+    let summaries = dispatcher.get_metadata_summary(fsio, db, items).await?;
+
+    // Create a HashMap to store summaries by their id
+    let summary_map: HashMap<PlayingItem, _> = summaries
+        .into_iter()
+        .map(|summary| (summary.item.clone(), summary))
+        .collect();
+
+    // Reorder items according to file_ids
+    Ok(items
+        .iter()
+        .filter_map(|id| summary_map.get(id))
+        .map(|summary| PlaylistItem {
+            item: summary.item.clone().into(),
+            artist: summary.artist.clone(),
+            album: summary.album.clone(),
+            title: summary.title.clone(),
+            duration: summary.duration,
+        })
+        .collect())
+}
+
 async fn update_media_controls_metadata(
     manager: Arc<Mutex<MediaControlManager>>,
     status: &PlayingItemMetadataSummary,
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use ::http_request::{ProxyConfig, ProxyKind, ProxyMode, set_proxy_mode};
+
+const SETTINGS_FILE: &str = "proxy.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxySource {
+    /// The `HTTPS_PROXY` and `ALL_PROXY` environment variables
+    #[default]
+    Environment,
+    Disabled,
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyProtocol {
+    #[default]
+    Http,
+    Socks5,
+}
+
+/// The proxy outbound requests go through. Devices on the local network are
+/// always reached directly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub source: ProxySource,
+    /// The manual proxy, unused with the other sources
+    pub protocol: ProxyProtocol,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hosts reached directly: names, which match their subdomains too, IP
+    /// addresses or CIDR ranges
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxySettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.source != ProxySource::Manual {
+            return Ok(());
+        }
+        if self.host.trim().is_empty() {
+            bail!("The proxy host is missing");
+        }
+        if self.port == 0 {
+            bail!("The proxy port is missing");
+        }
+
+        Ok(())
+    }
+
+    pub fn mode(&self) -> ProxyMode {
+        match self.source {
+            ProxySource::Environment => ProxyMode::Environment,
+            ProxySource::Disabled => ProxyMode::Disabled,
+            ProxySource::Manual => ProxyMode::Manual(ProxyConfig {
+                kind: match self.protocol {
+                    ProxyProtocol::Http => ProxyKind::Http,
+                    ProxyProtocol::Socks5 => ProxyKind::Socks5,
+                },
+                host: self.host.trim().to_owned(),
+                port: self.port,
+                username: self.username.clone().filter(|x| !x.is_empty()),
+                password: self.password.clone(),
+                no_proxy: self
+                    .no_proxy
+                    .iter()
+                    .map(|x| x.trim().to_lowercase())
+                    .filter(|x| !x.is_empty())
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Makes the outbound requests of the process go through the proxy.
+    pub fn apply(&self) {
+        set_proxy_mode(self.mode());
+    }
+}
+
+/// Persists the proxy settings in the configuration directory.
+#[derive(Debug)]
+pub struct ProxySettingsStore {
+    path: PathBuf,
+}
+
+impl ProxySettingsStore {
+    pub fn new(config_path: &Path) -> Self {
+        Self {
+            path: config_path.join(SETTINGS_FILE),
+        }
+    }
+
+    pub async fn load(&self) -> Result<ProxySettings> {
+        if !self.path.exists() {
+            return Ok(ProxySettings::default());
+        }
+
+        let content = tokio::fs::read_to_string(&self.path)
+            .await
+            .context("Failed to read proxy settings")?;
+        serde_json::from_str(&content).context("Failed to parse proxy settings")
+    }
+
+    pub async fn save(&self, settings: &ProxySettings) -> Result<()> {
+        settings.validate()?;
+
+        let content = serde_json::to_string_pretty(settings)?;
+        tokio::fs::write(&self.path, content)
+            .await
+            .context("Failed to write proxy settings")
+    }
+}
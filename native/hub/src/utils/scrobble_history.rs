@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use log::warn;
+
+use ::database::actions::scrobble_history::mark_scrobble_accepted;
+use ::database::connection::MainDbConnection;
+use ::scrobbling::ScrobblingTrack;
+use ::scrobbling::history::ScrobbleHistory;
+use ::scrobbling::manager::ScrobblingService;
+
+/// Records the services accepting the scrobbles in the history kept in the
+/// main database of the library.
+pub struct DatabaseScrobbleHistory {
+    main_db: Arc<MainDbConnection>,
+}
+
+impl DatabaseScrobbleHistory {
+    pub fn new(main_db: Arc<MainDbConnection>) -> Self {
+        Self { main_db }
+    }
+}
+
+#[async_trait]
+impl ScrobbleHistory for DatabaseScrobbleHistory {
+    async fn accepted(&self, service: &ScrobblingService, track: &ScrobblingTrack) -> Result<()> {
+        let found = mark_scrobble_accepted(
+            &self.main_db,
+            track.listened_at() as i64,
+            &track.artist,
+            &track.track,
+            &service.to_string(),
+        )
+        .await?;
+
+        // Scrobbles queued before the history existed, or already pruned
+        if !found {
+            warn!("Scrobble accepted by {service} is missing from the history");
+        }
+
+        Ok(())
+    }
+}
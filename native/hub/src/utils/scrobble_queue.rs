@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use log::warn;
+
+use ::database::actions::scrobble_queue::{
+    clear_scrobble_queue, count_queued_scrobbles, enqueue_love, enqueue_scrobble,
+    increase_scrobble_attempts, is_love, list_queued_loves, list_queued_scrobbles,
+    remove_queued_scrobbles,
+};
+use ::database::connection::MainDbConnection;
+use ::scrobbling::ScrobblingTrack;
+use ::scrobbling::manager::ScrobblingService;
+use ::scrobbling::queue::{QueuedLove, QueuedScrobble, ScrobbleQueue};
+
+/// Keeps the scrobbles and loves which could not be submitted in the main
+/// database of the library.
+pub struct DatabaseScrobbleQueue {
+    main_db: Arc<MainDbConnection>,
+}
+
+impl DatabaseScrobbleQueue {
+    pub fn new(main_db: Arc<MainDbConnection>) -> Self {
+        Self { main_db }
+    }
+}
+
+#[async_trait]
+impl ScrobbleQueue for DatabaseScrobbleQueue {
+    async fn push(&self, service: &ScrobblingService, track: &ScrobblingTrack) -> Result<()> {
+        let timestamp = track.listened_at() as i64;
+        let track = serde_json::to_string(track)?;
+        enqueue_scrobble(&self.main_db, &service.to_string(), track, timestamp).await?;
+
+        Ok(())
+    }
+
+    async fn push_love(
+        &self,
+        service: &ScrobblingService,
+        track: &ScrobblingTrack,
+        loved: bool,
+    ) -> Result<()> {
+        let timestamp = track.listened_at() as i64;
+        // Only what identifies the track, for the loves of the same track to
+        // replace each other
+        let track = serde_json::to_string(&ScrobblingTrack {
+            artist: track.artist.clone(),
+            track: track.track.clone(),
+            album: None,
+            album_artist: None,
+            duration: None,
+            timestamp: None,
+        })?;
+        enqueue_love(&self.main_db, &service.to_string(), track, loved, timestamp).await?;
+
+        Ok(())
+    }
+
+    async fn peek(&self, service: &ScrobblingService, limit: usize) -> Result<Vec<QueuedScrobble>> {
+        let entries =
+            list_queued_scrobbles(&self.main_db, &service.to_string(), limit as u64).await?;
+
+        let mut queued = Vec::with_capacity(entries.len());
+        let mut corrupted = Vec::new();
+        for entry in entries {
+            match serde_json::from_str::<ScrobblingTrack>(&entry.track) {
+                Ok(mut track) => {
+                    track.timestamp = Some(entry.timestamp as u64);
+                    queued.push(QueuedScrobble {
+                        id: entry.id,
+                        track,
+                        attempts: entry.attempts.max(0) as u32,
+                    });
+                }
+                Err(e) => {
+                    warn!("Dropping corrupted queued scrobble {}: {e}", entry.id);
+                    corrupted.push(entry.id);
+                }
+            }
+        }
+
+        // Kept, they would hold their place at the head of the queue forever
+        if !corrupted.is_empty() {
+            remove_queued_scrobbles(&self.main_db, &corrupted).await?;
+        }
+
+        Ok(queued)
+    }
+
+    async fn peek_loves(
+        &self,
+        service: &ScrobblingService,
+        limit: usize,
+    ) -> Result<Vec<QueuedLove>> {
+        let entries = list_queued_loves(&self.main_db, &service.to_string(), limit as u64).await?;
+
+        let mut queued = Vec::with_capacity(entries.len());
+        let mut corrupted = Vec::new();
+        for entry in entries {
+            match serde_json::from_str::<ScrobblingTrack>(&entry.track) {
+                Ok(track) => queued.push(QueuedLove {
+                    id: entry.id,
+                    track,
+                    loved: is_love(&entry),
+                    attempts: entry.attempts.max(0) as u32,
+                }),
+                Err(e) => {
+                    warn!("Dropping corrupted queued love {}: {e}", entry.id);
+                    corrupted.push(entry.id);
+                }
+            }
+        }
+
+        if !corrupted.is_empty() {
+            remove_queued_scrobbles(&self.main_db, &corrupted).await?;
+        }
+
+        Ok(queued)
+    }
+
+    async fn remove(&self, ids: &[i32]) -> Result<()> {
+        remove_queued_scrobbles(&self.main_db, ids).await?;
+
+        Ok(())
+    }
+
+    async fn record_attempt(&self, ids: &[i32]) -> Result<()> {
+        increase_scrobble_attempts(&self.main_db, ids).await
+    }
+
+    async fn clear(&self, service: &ScrobblingService) -> Result<()> {
+        clear_scrobble_queue(&self.main_db, &service.to_string()).await?;
+
+        Ok(())
+    }
+
+    async fn depth(&self, service: &ScrobblingService) -> Result<u64> {
+        count_queued_scrobbles(&self.main_db, &service.to_string()).await
+    }
+}
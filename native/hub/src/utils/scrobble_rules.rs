@@ -0,0 +1,230 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use ::playback::player::{PlaybackState, PlayerStatus, PlayingItem};
+
+const RULES_FILE: &str = "scrobble_rules.json";
+
+/// Progress steps longer than this are seeks, not listening.
+const MAX_PROGRESS_STEP: Duration = Duration::from_secs(5);
+/// Jumping back this close to the start of a track starts another pass.
+const RESTART_POSITION: Duration = Duration::from_secs(2);
+/// Stopping this close to the end of a track listens to it to the end.
+const END_MARGIN: Duration = Duration::from_secs(5);
+
+/// What counts as a scrobble, for every service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrobbleRules {
+    /// Fraction of the track to listen to, between 0 and 1
+    pub min_played_fraction: f64,
+    /// Listening time which is enough whatever the length of the track
+    pub min_played_seconds: u32,
+    /// Tracks shorter than this never scrobble
+    pub min_track_seconds: u32,
+    /// Whether playing the same track again right away scrobbles again
+    pub scrobble_repeats: bool,
+    /// Days the local scrobble history is kept, forever when unset
+    pub history_retention_days: Option<u32>,
+}
+
+impl Default for ScrobbleRules {
+    fn default() -> Self {
+        Self {
+            min_played_fraction: 0.5,
+            min_played_seconds: 4 * 60,
+            min_track_seconds: 30,
+            scrobble_repeats: true,
+            history_retention_days: None,
+        }
+    }
+}
+
+impl ScrobbleRules {
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.min_played_fraction) {
+            bail!("The played fraction must be between 0 and 1");
+        }
+        if self.history_retention_days == Some(0) {
+            bail!("The scrobble history must be kept for at least a day");
+        }
+
+        Ok(())
+    }
+
+    /// Whether listening to a track of `duration` seconds for `played`
+    /// counts as a scrobble.
+    pub fn is_eligible(&self, duration: f64, played: Duration) -> bool {
+        // Tracks without a known length can't be judged
+        if duration <= 0. || duration < self.min_track_seconds as f64 {
+            return false;
+        }
+
+        let played = played.as_secs_f64();
+        played >= duration * self.min_played_fraction || played >= self.min_played_seconds as f64
+    }
+}
+
+/// Persists the scrobble rules in the configuration directory.
+///
+/// The file is read at the start of every pass over a track, so edits apply
+/// from the next track on.
+#[derive(Debug)]
+pub struct ScrobbleRulesStore {
+    path: PathBuf,
+}
+
+impl ScrobbleRulesStore {
+    pub fn new(config_path: &Path) -> Self {
+        Self {
+            path: config_path.join(RULES_FILE),
+        }
+    }
+
+    pub async fn load(&self) -> Result<ScrobbleRules> {
+        if !self.path.exists() {
+            return Ok(ScrobbleRules::default());
+        }
+
+        let content = tokio::fs::read_to_string(&self.path)
+            .await
+            .context("Failed to read scrobble rules")?;
+        serde_json::from_str(&content).context("Failed to parse scrobble rules")
+    }
+
+    pub async fn save(&self, rules: &ScrobbleRules) -> Result<()> {
+        rules.validate()?;
+
+        let content = serde_json::to_string_pretty(rules)?;
+        tokio::fs::write(&self.path, content)
+            .await
+            .context("Failed to write scrobble rules")
+    }
+}
+
+/// Listening to a track which is not in its stats yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListeningProgress {
+    pub item: PlayingItem,
+    /// Where the listening stopped, `None` at the end of the track
+    pub position: Option<Duration>,
+    /// Time listened since the progress was last taken
+    pub listened: Duration,
+}
+
+/// Accumulates the time actually spent listening to the playing track from
+/// the status updates of the player, leaving out pauses and seeks.
+#[derive(Debug, Default)]
+pub struct PlaytimeTracker {
+    item: Option<PlayingItem>,
+    /// Duration of the track in seconds
+    duration: f64,
+    position: Duration,
+    played: Duration,
+    /// Listening time already handed out as progress
+    recorded: Duration,
+    playing: bool,
+    progress: Option<ListeningProgress>,
+    /// When the pass started (UNIX timestamp)
+    started_at: u64,
+    /// The pass plays the same track as the previous one
+    repeat: bool,
+    scrobbled: bool,
+}
+
+impl PlaytimeTracker {
+    /// Follows a status update, `duration` being the length of the track
+    /// in seconds. Returns whether a new pass over a track started.
+    pub fn observe(&mut self, status: &PlayerStatus, duration: f64) -> bool {
+        let playing = status.state == PlaybackState::Playing;
+        let paused = self.playing && !playing;
+        self.playing = playing;
+
+        // Stopping keeps the pass, playing the track again restarts it
+        let Some(item) = &status.item else {
+            if paused {
+                self.progress = self.take_listened();
+            }
+            return false;
+        };
+        let position = status.position;
+
+        let same_item = self.item.as_ref() == Some(item);
+        let restarted = same_item && position < self.position && position < RESTART_POSITION;
+        if same_item && !restarted {
+            let step = position.saturating_sub(self.position);
+            if playing && step <= MAX_PROGRESS_STEP {
+                self.played += step;
+            }
+            self.position = position;
+            self.duration = duration;
+
+            if paused {
+                self.progress = self.take_listened();
+            }
+            return false;
+        }
+
+        let finished = self.take_listened();
+        *self = Self {
+            item: Some(item.clone()),
+            duration,
+            position,
+            played: Duration::ZERO,
+            recorded: Duration::ZERO,
+            playing,
+            progress: finished,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            repeat: same_item,
+            scrobbled: false,
+        };
+
+        true
+    }
+
+    /// Whether the current pass just became a scrobble. Returns when the
+    /// pass started, once per pass.
+    pub fn take_scrobble(&mut self, rules: &ScrobbleRules, duration: f64) -> Option<u64> {
+        if self.item.is_none() || self.scrobbled {
+            return None;
+        }
+        if self.repeat && !rules.scrobble_repeats {
+            return None;
+        }
+        if !rules.is_eligible(duration, self.played) {
+            return None;
+        }
+
+        self.scrobbled = true;
+        Some(self.started_at)
+    }
+
+    /// The listening to write into the stats of its track, once a pass
+    /// ended or paused.
+    pub fn take_progress(&mut self) -> Option<ListeningProgress> {
+        self.progress.take()
+    }
+
+    fn take_listened(&mut self) -> Option<ListeningProgress> {
+        let item = self.item.clone()?;
+        let listened = self.played.saturating_sub(self.recorded);
+        self.recorded = self.played;
+
+        let finished = self.duration > 0.
+            && self.position.as_secs_f64() + END_MARGIN.as_secs_f64() >= self.duration;
+
+        Some(ListeningProgress {
+            item,
+            position: (!finished).then_some(self.position),
+            listened,
+        })
+    }
+}
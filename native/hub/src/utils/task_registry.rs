@@ -0,0 +1,294 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use uuid::Uuid;
+
+use crate::messages::{CancelTaskType, TaskState, TaskSummary};
+
+/// How long finished tasks are kept for the UI to show their results.
+const FINISHED_RETENTION: Duration = Duration::from_secs(300);
+
+/// The most finished tasks kept, the oldest ones being dropped first.
+const MAX_FINISHED_TASKS: usize = 32;
+
+#[derive(Debug)]
+struct TaskEntry {
+    id: String,
+    kind: CancelTaskType,
+    path: String,
+    progress: i64,
+    total: i64,
+    state: TaskState,
+    error: Option<String>,
+    started_at: Instant,
+    finished_at: Option<Instant>,
+    token: CancellationToken,
+}
+
+impl TaskEntry {
+    fn is_running(&self) -> bool {
+        self.finished_at.is_none()
+    }
+
+    fn summary(&self, now: Instant) -> TaskSummary {
+        TaskSummary {
+            id: self.id.clone(),
+            kind: self.kind,
+            path: self.path.clone(),
+            progress: self.progress,
+            total: self.total,
+            state: self.state,
+            error: self.error.clone(),
+            elapsed_seconds: self
+                .finished_at
+                .unwrap_or(now)
+                .duration_since(self.started_at)
+                .as_secs(),
+            finished_seconds_ago: self.finished_at.map(|x| now.duration_since(x).as_secs()),
+        }
+    }
+}
+
+/// The long running library tasks, scans, analyses, deduplications and
+/// identifications, each with its own id and cancellation token, so they can
+/// be listed and cancelled one by one. Shared by the local GUI and the
+/// remote handlers through the `GlobalParams`.
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    entries: Arc<Mutex<Vec<TaskEntry>>>,
+    /// Tracks the running library tasks so they can be awaited on shutdown.
+    tracker: TaskTracker,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new running task.
+    ///
+    /// # Arguments
+    /// * `kind` - What the task does.
+    /// * `path` - The library path the task works on.
+    ///
+    /// # Returns
+    /// * `TaskHandle` - The handle to report the progress and the result of
+    ///   the task, and to find out whether it was cancelled.
+    pub fn register(&self, kind: CancelTaskType, path: &str) -> TaskHandle {
+        let id = Uuid::new_v4().to_string();
+        let token = CancellationToken::new();
+
+        let mut entries = self.entries.lock().unwrap();
+        prune_finished(&mut entries, Instant::now());
+        entries.push(TaskEntry {
+            id: id.clone(),
+            kind,
+            path: path.to_owned(),
+            progress: 0,
+            total: 0,
+            state: TaskState::Running,
+            error: None,
+            started_at: Instant::now(),
+            finished_at: None,
+            token: token.clone(),
+        });
+
+        TaskHandle {
+            id,
+            token,
+            entries: Arc::clone(&self.entries),
+        }
+    }
+
+    /// Cancels a running task.
+    ///
+    /// # Returns
+    /// * `bool` - Whether a running task had this id.
+    pub fn cancel(&self, id: &str) -> bool {
+        let entries = self.entries.lock().unwrap();
+        match entries.iter().find(|x| x.id == id && x.is_running()) {
+            Some(entry) => {
+                entry.token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancels every running task of a kind.
+    ///
+    /// # Returns
+    /// * `bool` - Whether any task was cancelled.
+    pub fn cancel_kind(&self, kind: CancelTaskType) -> bool {
+        let entries = self.entries.lock().unwrap();
+        let mut cancelled = false;
+        for entry in entries.iter().filter(|x| x.kind == kind && x.is_running()) {
+            entry.token.cancel();
+            cancelled = true;
+        }
+
+        cancelled
+    }
+
+    /// Cancels every running task.
+    pub fn cancel_all(&self) {
+        for entry in self.entries.lock().unwrap().iter() {
+            entry.token.cancel();
+        }
+    }
+
+    /// The running tasks and the recently finished ones, oldest first.
+    pub fn list(&self) -> Vec<TaskSummary> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        prune_finished(&mut entries, now);
+
+        entries.iter().map(|x| x.summary(now)).collect()
+    }
+
+    pub fn tracker(&self) -> TaskTracker {
+        self.tracker.clone()
+    }
+}
+
+/// Drops the tasks finished for too long, and the oldest finished ones past
+/// the limit.
+fn prune_finished(entries: &mut Vec<TaskEntry>, now: Instant) {
+    entries.retain(|x| {
+        x.finished_at
+            .is_none_or(|finished_at| now.duration_since(finished_at) < FINISHED_RETENTION)
+    });
+
+    let mut excess = entries
+        .iter()
+        .filter(|x| !x.is_running())
+        .count()
+        .saturating_sub(MAX_FINISHED_TASKS);
+    entries.retain(|x| {
+        if excess > 0 && !x.is_running() {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+/// A task of the [`TaskRegistry`], for the task itself to report to.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    id: String,
+    token: CancellationToken,
+    entries: Arc<Mutex<Vec<TaskEntry>>>,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    fn update(&self, f: impl FnOnce(&mut TaskEntry)) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.iter_mut().find(|x| x.id == self.id) {
+            f(entry);
+        }
+    }
+
+    pub fn set_progress(&self, progress: usize, total: usize) {
+        self.update(|entry| {
+            entry.progress = progress as i64;
+            entry.total = total as i64;
+        });
+    }
+
+    /// Marks the task as finished, cancelled if its token was cancelled on
+    /// the way.
+    pub fn finish<T>(&self, result: &Result<T>) {
+        let cancelled = self.token.is_cancelled();
+        self.update(|entry| {
+            entry.finished_at = Some(Instant::now());
+            match result {
+                Err(e) => {
+                    entry.state = TaskState::Failed;
+                    entry.error = Some(format!("{e:#}"));
+                }
+                Ok(_) if cancelled => entry.state = TaskState::Cancelled,
+                Ok(_) => entry.state = TaskState::Completed,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn tasks_are_cancelled_by_id_or_by_kind() {
+        let registry = TaskRegistry::new();
+        let first = registry.register(CancelTaskType::ScanAudioLibrary, "/a");
+        let second = registry.register(CancelTaskType::ScanAudioLibrary, "/b");
+        let analysis = registry.register(CancelTaskType::AnalyzeAudioLibrary, "/a");
+
+        assert!(registry.cancel(first.id()));
+        assert!(first.token().is_cancelled());
+        assert!(!second.token().is_cancelled());
+        assert!(!registry.cancel("unknown"));
+
+        assert!(registry.cancel_kind(CancelTaskType::ScanAudioLibrary));
+        assert!(second.token().is_cancelled());
+        assert!(!analysis.token().is_cancelled());
+        assert!(!registry.cancel_kind(CancelTaskType::IdentifyAudio));
+    }
+
+    #[test]
+    fn finished_tasks_keep_their_result() {
+        let registry = TaskRegistry::new();
+        let scan = registry.register(CancelTaskType::ScanAudioLibrary, "/a");
+        let analysis = registry.register(CancelTaskType::AnalyzeAudioLibrary, "/a");
+        let identify = registry.register(CancelTaskType::IdentifyAudio, "/a");
+
+        scan.set_progress(3, 10);
+        registry.cancel(scan.id());
+        scan.finish(&Ok(()));
+        analysis.finish::<()>(&Err(anyhow!("No GPU")));
+
+        let tasks = registry.list();
+        assert_eq!(tasks.len(), 3);
+        assert_eq!((tasks[0].progress, tasks[0].total), (3, 10));
+        assert_eq!(tasks[0].state, TaskState::Cancelled);
+        assert_eq!(tasks[1].state, TaskState::Failed);
+        assert_eq!(tasks[1].error.as_deref(), Some("No GPU"));
+        assert_eq!(tasks[2].state, TaskState::Running);
+        assert!(tasks[2].finished_seconds_ago.is_none());
+
+        // Finished tasks can't be cancelled anymore
+        assert!(!registry.cancel(scan.id()));
+        assert!(registry.cancel(identify.id()));
+    }
+
+    #[test]
+    fn only_the_latest_finished_tasks_are_kept() {
+        let registry = TaskRegistry::new();
+        let running = registry.register(CancelTaskType::DeduplicateAudioLibrary, "/a");
+        for _ in 0..MAX_FINISHED_TASKS + 5 {
+            registry
+                .register(CancelTaskType::ScanAudioLibrary, "/a")
+                .finish(&Ok(()));
+        }
+
+        let tasks = registry.list();
+        assert_eq!(tasks.len(), MAX_FINISHED_TASKS + 1);
+        assert_eq!(tasks[0].id, running.id());
+    }
+}
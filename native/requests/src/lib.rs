@@ -1,464 +1,438 @@
+use std::collections::HashSet;
+
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, ExprLit, Ident, Lit, Token, parse_macro_input};
+
+/// The permission a request requires, `Admin` when the manifest declares
+/// none.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Read,
+    Playback,
+    LibraryWrite,
+    Admin,
+    LocalOnly,
+}
 
+const SCOPE_NAMES: &[(&str, Scope)] = &[
+    ("read", Scope::Read),
+    ("playback", Scope::Playback),
+    ("library_write", Scope::LibraryWrite),
+    ("admin", Scope::Admin),
+    ("local_only", Scope::LocalOnly),
+];
+
+impl Scope {
+    fn parse(attr: &Attribute) -> syn::Result<Self> {
+        let ident: Ident = attr.parse_args()?;
+        SCOPE_NAMES
+            .iter()
+            .find(|(name, _)| ident == name)
+            .map(|(_, scope)| *scope)
+            .ok_or_else(|| {
+                let names: Vec<_> = SCOPE_NAMES.iter().map(|(name, _)| *name).collect();
+                syn::Error::new(
+                    ident.span(),
+                    format!("Unknown scope, expected one of {}", names.join(", ")),
+                )
+            })
+    }
+
+    fn variant(self) -> proc_macro2::TokenStream {
+        let variant = match self {
+            Scope::Read => quote!(Read),
+            Scope::Playback => quote!(Playback),
+            Scope::LibraryWrite => quote!(LibraryWrite),
+            Scope::Admin => quote!(Admin),
+            Scope::LocalOnly => quote!(LocalOnly),
+        };
+        quote!(crate::server::scope::Scope::#variant)
+    }
+}
+
+/// An entry of the manifest, `Request => Response` or `Request` for
+/// requests without a response, after its flags.
 struct RequestResponse {
-    request: String,
-    response: Option<String>,
-    local_only: bool,
+    request: Ident,
+    response: Option<Ident>,
+    scope: Option<Scope>,
+}
+
+impl RequestResponse {
+    /// Local only requests are never forwarded to servers.
+    fn local_only(&self) -> bool {
+        self.scope == Some(Scope::LocalOnly)
+    }
+}
+
+impl Parse for RequestResponse {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let request = input.parse()?;
+        let response = if input.peek(Token![=>]) {
+            input.parse::<Token![=>]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        let mut scope = None;
+        for attr in attrs {
+            if !attr.path().is_ident("scope") {
+                return Err(syn::Error::new_spanned(
+                    attr.path(),
+                    "Unknown request flag, expected `scope(...)`",
+                ));
+            }
+            if scope.is_some() {
+                return Err(syn::Error::new_spanned(attr, "Duplicated request scope"));
+            }
+            scope = Some(Scope::parse(&attr)?);
+        }
+
+        Ok(Self {
+            request,
+            response,
+            scope,
+        })
+    }
+}
+
+/// Reads the `#![protocol_version = N]` header of the manifest.
+fn parse_protocol_version(attrs: &[Attribute], span: proc_macro2::Span) -> syn::Result<u32> {
+    let mut version = None;
+    for attr in attrs {
+        if !attr.path().is_ident("protocol_version") {
+            return Err(syn::Error::new_spanned(
+                attr.path(),
+                "Unknown manifest flag, expected `protocol_version = N`",
+            ));
+        }
+        if version.is_some() {
+            return Err(syn::Error::new_spanned(attr, "Duplicated protocol version"));
+        }
+
+        let value = &attr.meta.require_name_value()?.value;
+        let Expr::Lit(ExprLit {
+            lit: Lit::Int(value),
+            ..
+        }) = value
+        else {
+            return Err(syn::Error::new_spanned(value, "Expected a version number"));
+        };
+        version = Some(value.base10_parse()?);
+    }
+
+    version.ok_or_else(|| {
+        syn::Error::new(
+            span,
+            "The manifest should start with `#![protocol_version = N]`",
+        )
+    })
+}
+
+/// `FetchMediaFilesRequest` as `fetch_media_files_request`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// FNV-1a, stable across builds and platforms unlike the hashers of `std`.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, x| {
+        (hash ^ u64::from(*x)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The request types, as listed in `native/hub/src/macros.rs`.
+struct Manifest {
+    protocol_version: u32,
+    entries: Vec<RequestResponse>,
+}
+
+impl Parse for Manifest {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_inner)?;
+        let protocol_version = parse_protocol_version(&attrs, input.span())?;
+        let entries = Punctuated::<RequestResponse, Token![,]>::parse_terminated(input)?;
+
+        Ok(Self {
+            protocol_version,
+            entries: entries.into_iter().collect(),
+        })
+    }
+}
+
+impl Manifest {
+    /// Checks every name follows the naming of messages and is listed once,
+    /// reporting every mistake at once.
+    fn validate(&self) -> syn::Result<()> {
+        let mut errors: Option<syn::Error> = None;
+        let mut report = |ident: &Ident, message: String| {
+            let error = syn::Error::new(ident.span(), message);
+            match errors.as_mut() {
+                Some(errors) => errors.combine(error),
+                None => errors = Some(error),
+            }
+        };
+
+        let mut listed = HashSet::new();
+        for entry in &self.entries {
+            let names = std::iter::once((&entry.request, "Request"))
+                .chain(entry.response.iter().map(|x| (x, "Response")));
+            for (ident, suffix) in names {
+                let name = ident.to_string();
+                if !name.ends_with(suffix) || name == suffix {
+                    report(ident, format!("`{name}` should be named `...{suffix}`"));
+                }
+                if !listed.insert(name.clone()) {
+                    report(ident, format!("`{name}` is already listed"));
+                }
+            }
+        }
+
+        errors.map_or(Ok(()), Err)
+    }
+
+    /// Asserts every request is a Dart signal and every response a Rust
+    /// signal of `crate::messages`, failing at the entry otherwise.
+    fn type_checks(&self) -> proc_macro2::TokenStream {
+        let checks = self.entries.iter().flat_map(|entry| {
+            let request = &entry.request;
+            let request = quote_spanned! {request.span()=>
+                request::<crate::messages::#request>();
+            };
+            let response = entry.response.as_ref().map(|response| {
+                quote_spanned! {response.span()=>
+                    response::<crate::messages::#response>();
+                }
+            });
+            std::iter::once(request).chain(response)
+        });
+
+        quote! {
+            const _: fn() = || {
+                fn request<T: ::rinf::DartSignal>() {}
+                fn response<T: ::rinf::RustSignal>() {}
+                #(#checks)*
+            };
+        }
+    }
+
+    /// The scope of every request, as a constant of each type and a lookup
+    /// by name.
+    fn scopes(&self) -> proc_macro2::TokenStream {
+        let requests: Vec<_> = self.entries.iter().map(|x| &x.request).collect();
+        let names: Vec<_> = requests.iter().map(|x| x.to_string()).collect();
+        let declared: Vec<_> = self
+            .entries
+            .iter()
+            .map(|x| match x.scope {
+                Some(scope) => {
+                    let scope = scope.variant();
+                    quote!(Some(#scope))
+                }
+                None => quote!(None),
+            })
+            .collect();
+        let required: Vec<_> = self
+            .entries
+            .iter()
+            .map(|x| x.scope.unwrap_or(Scope::Admin).variant())
+            .collect();
+
+        quote! {
+            #(
+                impl crate::server::scope::RequestScope for crate::messages::#requests {
+                    const SCOPE: crate::server::scope::Scope = #required;
+                }
+            )*
+
+            /// The name of every request type.
+            pub const REQUEST_TYPES: &[&str] = &[#(#names),*];
+
+            /// The scope a request type declares in the manifest, `None` for
+            /// the ones declaring none and unknown ones.
+            pub fn declared_scope(msg_type: &str) -> Option<crate::server::scope::Scope> {
+                match msg_type {
+                    #(#names => #declared,)*
+                    _ => None,
+                }
+            }
+
+            /// The scope required to send a request type, `Admin` unless it
+            /// declares another one.
+            pub fn required_scope(msg_type: &str) -> crate::server::scope::Scope {
+                declared_scope(msg_type).unwrap_or(crate::server::scope::Scope::Admin)
+            }
+        }
+    }
+
+    /// The version of the protocol and the requests a server answers, which
+    /// peers compare to find the requests they cannot send.
+    fn protocol(&self) -> proc_macro2::TokenStream {
+        let mut served: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|x| !x.local_only())
+            .map(|x| x.request.to_string())
+            .collect();
+        served.sort();
+
+        let version = self.protocol_version;
+        let hash = fnv1a(format!("{version}\n{}", served.join("\n")).as_bytes());
+
+        quote! {
+            /// The version of the protocol, bumped whenever requests change
+            /// in ways their names don't tell.
+            pub const PROTOCOL_VERSION: u32 = #version;
+
+            /// A hash of the protocol version and of the requests served,
+            /// equal between peers speaking the same protocol.
+            pub const PROTOCOL_HASH: u64 = #hash;
+
+            /// The name of every request a server answers, sorted.
+            pub const SERVED_REQUEST_TYPES: &[&str] = &[#(#served),*];
+        }
+    }
+
+    /// A test for every request, sending its default value through the
+    /// framing of WebSocket messages to a handler registered for it, and a
+    /// test of the lists the `for_all_*` macros expand to. Expanded after
+    /// the macros it uses.
+    fn tests(&self) -> proc_macro2::TokenStream {
+        let tests = self.entries.iter().map(|entry| {
+            let request = &entry.request;
+            let name = request.to_string();
+            let test = Ident::new(
+                &format!("{}_is_framed_and_dispatched", snake_case(&name)),
+                request.span(),
+            );
+            quote! {
+                #[test]
+                fn #test() {
+                    round_trip::<crate::messages::#request>(#name);
+                }
+            }
+        });
+
+        quote! {
+            #[cfg(test)]
+            mod request_manifest_tests {
+                use std::collections::HashSet;
+
+                use crate::{
+                    backends::remote::{decode_message, encode_message},
+                    server::{WebSocketService, scope::REQUEST_TYPES},
+                };
+
+                fn round_trip<T>(name: &str)
+                where
+                    T: Default + ::serde::Serialize + for<'a> ::serde::Deserialize<'a>,
+                {
+                    let payload = ::rinf::serialize(&T::default()).expect("Failed to serialize");
+                    let message = encode_message(name, &payload, None);
+                    let (msg_type, msg_payload, _) =
+                        decode_message(&message).expect("Failed to decode the framing");
+                    assert_eq!(msg_type, name);
+                    assert_eq!(msg_payload, payload);
+
+                    let decoded: T =
+                        ::rinf::deserialize(&msg_payload).expect("Failed to deserialize");
+                    assert_eq!(::rinf::serialize(&decoded).unwrap(), payload);
+
+                    let runtime = ::tokio::runtime::Builder::new_current_thread()
+                        .build()
+                        .unwrap();
+                    let response = runtime.block_on(async {
+                        let service = WebSocketService::new();
+                        service
+                            .register_handler(name, |payload, _| async move {
+                                ("StubResponse".to_owned(), Ok(payload))
+                            })
+                            .await;
+                        service.handle_message(&msg_type, msg_payload, None).await
+                    });
+
+                    let (response_type, response) = response.expect("No handler was dispatched");
+                    assert_eq!(response_type, "StubResponse");
+                    assert_eq!(response.unwrap(), payload);
+                }
+
+                #(#tests)*
+
+                macro_rules! request_names {
+                    ($names:expr $(, $request:ident)*) => {
+                        $($names.push(stringify!($request));)*
+                    };
+                }
+
+                macro_rules! request_name {
+                    (($request:ident, $response:ident)) => {
+                        stringify!($request)
+                    };
+                    ($request:ident) => {
+                        stringify!($request)
+                    };
+                }
+
+                macro_rules! request_pair_names {
+                    ($names:expr, $unused:expr $(, $entry:tt)*) => {
+                        $($names.push(request_name!($entry));)*
+                    };
+                }
+
+                macro_rules! all_request_names {
+                    ($($request:ident),*) => {
+                        vec![$(stringify!($request)),*]
+                    };
+                }
+
+                #[test]
+                fn local_and_forwarded_requests_split_the_manifest() {
+                    let mut local: Vec<&str> = Vec::new();
+                    for_all_local_only_request_pairs2!(request_pair_names, local, ());
+                    let mut forwarded: Vec<&str> = Vec::new();
+                    for_all_non_local_requests!(request_names, forwarded);
+                    let all: Vec<&str> = for_all_requests0!(all_request_names);
+
+                    let local: HashSet<_> = local.into_iter().collect();
+                    let forwarded: HashSet<_> = forwarded.into_iter().collect();
+                    let both: Vec<_> = local.intersection(&forwarded).collect();
+                    assert!(both.is_empty(), "Both local and forwarded: {both:?}");
+
+                    let covered: HashSet<_> = local.union(&forwarded).copied().collect();
+                    let all: HashSet<_> = all.into_iter().collect();
+                    assert_eq!(covered, all);
+                    assert_eq!(all, REQUEST_TYPES.iter().copied().collect::<HashSet<_>>());
+                }
+            }
+        }
+    }
 }
 
+/// Defines the `for_all_*` macros from the manifest of request types given
+/// as input.
 #[proc_macro]
-pub fn define_request_types(_input: TokenStream) -> TokenStream {
-    let types = vec![
-        // Library
-        RequestResponse {
-            request: "TestLibraryInitializedRequest".to_string(),
-            response: Some("TestLibraryInitializedResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "CloseLibraryRequest".to_string(),
-            response: Some("CloseLibraryResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "CancelTaskRequest".to_string(),
-            response: Some("CancelTaskResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "ScanAudioLibraryRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "AnalyzeAudioLibraryRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "DeduplicateAudioLibraryRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        // Playback
-        RequestResponse {
-            request: "VolumeRequest".to_string(),
-            response: Some("VolumeResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "LoadRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "PlayRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "PauseRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "NextRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "PreviousRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SwitchRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SeekRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "RemoveRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SetPlaybackModeRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "MovePlaylistItemRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SetRealtimeFFTEnabledRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SetAdaptiveSwitchingEnabledRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        // SFX
-        RequestResponse {
-            request: "SfxPlayRequest".to_string(),
-            response: None,
-            local_only: true,
-        },
-        // Analyze
-        RequestResponse {
-            request: "IfAnalyzeExistsRequest".to_string(),
-            response: Some("IfAnalyzeExistsResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "GetAnalyzeCountRequest".to_string(),
-            response: Some("GetAnalyzeCountResponse".to_string()),
-            local_only: false,
-        },
-        // Media File
-        RequestResponse {
-            request: "FetchMediaFilesRequest".to_string(),
-            response: Some("FetchMediaFilesResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "FetchMediaFileByIdsRequest".to_string(),
-            response: Some("FetchMediaFileByIdsResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "FetchParsedMediaFileRequest".to_string(),
-            response: Some("FetchParsedMediaFileResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SearchMediaFileSummaryRequest".to_string(),
-            response: Some("SearchMediaFileSummaryResponse".to_string()),
-            local_only: false,
-        },
-        // Lyric
-        RequestResponse {
-            request: "GetMediaFilesCountRequest".to_string(),
-            response: Some("GetMediaFilesCountResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "GetLyricByTrackIdRequest".to_string(),
-            response: Some("GetLyricByTrackIdResponse".to_string()),
-            local_only: false,
-        },
-        // Collection
-        RequestResponse {
-            request: "FetchCollectionGroupSummaryRequest".to_string(),
-            response: Some("CollectionGroupSummaryResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "FetchCollectionGroupsRequest".to_string(),
-            response: Some("FetchCollectionGroupsResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "FetchCollectionByIdsRequest".to_string(),
-            response: Some("FetchCollectionByIdsResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SearchCollectionSummaryRequest".to_string(),
-            response: Some("SearchCollectionSummaryResponse".to_string()),
-            local_only: false,
-        },
-        // Cover Art
-        RequestResponse {
-            request: "GetCoverArtIdsByMixQueriesRequest".to_string(),
-            response: Some("GetCoverArtIdsByMixQueriesResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "GetPrimaryColorByTrackIdRequest".to_string(),
-            response: Some("GetPrimaryColorByTrackIdResponse".to_string()),
-            local_only: false,
-        },
-        // Playlist
-        RequestResponse {
-            request: "FetchAllPlaylistsRequest".to_string(),
-            response: Some("FetchAllPlaylistsResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "CreatePlaylistRequest".to_string(),
-            response: Some("CreatePlaylistResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "CreateM3u8PlaylistRequest".to_string(),
-            response: Some("CreateM3u8PlaylistResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "UpdatePlaylistRequest".to_string(),
-            response: Some("UpdatePlaylistResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "RemovePlaylistRequest".to_string(),
-            response: Some("RemovePlaylistResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "AddItemToPlaylistRequest".to_string(),
-            response: Some("AddItemToPlaylistResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "ReorderPlaylistItemPositionRequest".to_string(),
-            response: Some("ReorderPlaylistItemPositionResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "GetPlaylistByIdRequest".to_string(),
-            response: Some("GetPlaylistByIdResponse".to_string()),
-            local_only: false,
-        },
-        // Mix
-        RequestResponse {
-            request: "FetchAllMixesRequest".to_string(),
-            response: Some("FetchAllMixesResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "CreateMixRequest".to_string(),
-            response: Some("CreateMixResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "UpdateMixRequest".to_string(),
-            response: Some("UpdateMixResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "RemoveMixRequest".to_string(),
-            response: Some("RemoveMixResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "AddItemToMixRequest".to_string(),
-            response: Some("AddItemToMixResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "GetMixByIdRequest".to_string(),
-            response: Some("GetMixByIdResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "MixQueryRequest".to_string(),
-            response: Some("MixQueryResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "FetchMixQueriesRequest".to_string(),
-            response: Some("FetchMixQueriesResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "OperatePlaybackWithMixQueryRequest".to_string(),
-            response: Some("OperatePlaybackWithMixQueryResponse".to_string()),
-            local_only: false,
-        },
-        // Like
-        RequestResponse {
-            request: "SetLikedRequest".to_string(),
-            response: Some("SetLikedResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "GetLikedRequest".to_string(),
-            response: Some("GetLikedResponse".to_string()),
-            local_only: false,
-        },
-        // Query and Search
-        RequestResponse {
-            request: "ComplexQueryRequest".to_string(),
-            response: Some("ComplexQueryResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "SearchForRequest".to_string(),
-            response: Some("SearchForResponse".to_string()),
-            local_only: false,
-        },
-        // Directory
-        RequestResponse {
-            request: "FetchDirectoryTreeRequest".to_string(),
-            response: Some("FetchDirectoryTreeResponse".to_string()),
-            local_only: false,
-        },
-        // Scrobbler
-        RequestResponse {
-            request: "AuthenticateSingleServiceRequest".to_string(),
-            response: Some("AuthenticateSingleServiceResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "AuthenticateMultipleServiceRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        RequestResponse {
-            request: "LogoutSingleServiceRequest".to_string(),
-            response: None,
-            local_only: false,
-        },
-        // Log
-        RequestResponse {
-            request: "ListLogRequest".to_string(),
-            response: Some("ListLogResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "ClearLogRequest".to_string(),
-            response: Some("ClearLogResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "RemoveLogRequest".to_string(),
-            response: Some("RemoveLogResponse".to_string()),
-            local_only: false,
-        },
-        // System
-        RequestResponse {
-            request: "SystemInfoRequest".to_string(),
-            response: Some("SystemInfoResponse".to_string()),
-            local_only: false,
-        },
-        // License
-        RequestResponse {
-            request: "RegisterLicenseRequest".to_string(),
-            response: Some("RegisterLicenseResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "ValidateLicenseRequest".to_string(),
-            response: Some("ValidateLicenseResponse".to_string()),
-            local_only: false,
-        },
-        // Neighbors
-        RequestResponse {
-            request: "StartBroadcastRequest".to_string(),
-            response: None,
-            local_only: true,
-        },
-        RequestResponse {
-            request: "StopBroadcastRequest".to_string(),
-            response: None,
-            local_only: true,
-        },
-        RequestResponse {
-            request: "StartListeningRequest".to_string(),
-            response: None,
-            local_only: true,
-        },
-        RequestResponse {
-            request: "StopListeningRequest".to_string(),
-            response: None,
-            local_only: true,
-        },
-        RequestResponse {
-            request: "GetDiscoveredDeviceRequest".to_string(),
-            response: Some("GetDiscoveredDeviceResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "StartServerRequest".to_string(),
-            response: Some("StartServerResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "StopServerRequest".to_string(),
-            response: Some("StopServerResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "ListClientsRequest".to_string(),
-            response: Some("ListClientsResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "GetSslCertificateFingerprintRequest".to_string(),
-            response: Some("GetSslCertificateFingerprintResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "AddTrustedServerRequest".to_string(),
-            response: Some("AddTrustedServerResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "RemoveTrustedClientRequest".to_string(),
-            response: Some("RemoveTrustedClientResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "UpdateClientStatusRequest".to_string(),
-            response: Some("UpdateClientStatusResponse".to_string()),
-            local_only: false,
-        },
-        RequestResponse {
-            request: "EditHostsRequest".to_string(),
-            response: Some("EditHostsResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "RemoveTrustedServerRequest".to_string(),
-            response: Some("RemoveTrustedServerResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "ServerAvailabilityTestRequest".to_string(),
-            response: Some("ServerAvailabilityTestResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "RegisterDeviceOnServerRequest".to_string(),
-            response: Some("RegisterDeviceOnServerResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "CheckDeviceOnServerRequest".to_string(),
-            response: Some("CheckDeviceOnServerResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "ConnectRequest".to_string(),
-            response: Some("ConnectResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "FetchServerCertificateRequest".to_string(),
-            response: Some("FetchServerCertificateResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "FetchRemoteFileRequest".to_string(),
-            response: Some("FetchRemoteFileResponse".to_string()),
-            local_only: true,
-        },
-        RequestResponse {
-            request: "RemoveItemFromPlaylistRequest".to_string(),
-            response: Some("RemoveItemFromPlaylistResponse".to_string()),
-            local_only: true,
-        },
-    ];
+pub fn define_request_types(input: TokenStream) -> TokenStream {
+    let manifest = parse_macro_input!(input as Manifest);
+    if let Err(e) = manifest.validate() {
+        return e.to_compile_error().into();
+    }
+    let type_checks = manifest.type_checks();
+    let scopes = manifest.scopes();
+    let protocol = manifest.protocol();
+    let tests = manifest.tests();
+    let types = manifest.entries;
 
     let (with_response, without_response): (Vec<_>, Vec<_>) =
         types.iter().partition(|t| t.response.is_some());
@@ -466,8 +440,8 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
     let response_pairs: Vec<_> = with_response
         .iter()
         .map(|t| {
-            let req_ident = syn::parse_str::<syn::Ident>(&t.request).unwrap();
-            let resp_ident = syn::parse_str::<syn::Ident>(t.response.as_ref().unwrap()).unwrap();
+            let req_ident = &t.request;
+            let resp_ident = t.response.as_ref().unwrap();
             quote! { (#req_ident, #resp_ident) }
         })
         .collect();
@@ -475,52 +449,54 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
     let request_only: Vec<_> = without_response
         .iter()
         .map(|t| {
-            let ident = syn::parse_str::<syn::Ident>(&t.request).unwrap();
+            let ident = &t.request;
             quote! { #ident }
         })
         .collect();
 
     let all_responses: Vec<_> = with_response
         .iter()
-        .map(|t| syn::parse_str::<syn::Ident>(t.response.as_ref().unwrap()).unwrap())
+        .map(|t| t.response.as_ref().unwrap())
         .collect();
 
     let all_requests: Vec<_> = with_response
         .iter()
-        .map(|t| syn::parse_str::<syn::Ident>(&t.request).unwrap())
-        .chain(
-            without_response
-                .iter()
-                .map(|t| syn::parse_str::<syn::Ident>(&t.request).unwrap()),
-        )
+        .map(|t| &t.request)
+        .chain(without_response.iter().map(|t| &t.request))
         .collect();
 
     let non_local_requests: Vec<_> = types
         .iter()
-        .filter(|t| !t.local_only)
-        .map(|t| syn::parse_str::<syn::Ident>(&t.request).unwrap())
+        .filter(|t| !t.local_only())
+        .map(|t| &t.request)
         .collect();
 
     let local_response_pairs: Vec<_> = with_response
         .iter()
-        .filter(|t| t.local_only)
+        .filter(|t| t.local_only())
         .map(|t| {
-            let req_ident = syn::parse_str::<syn::Ident>(&t.request).unwrap();
-            let resp_ident = syn::parse_str::<syn::Ident>(t.response.as_ref().unwrap()).unwrap();
+            let req_ident = &t.request;
+            let resp_ident = t.response.as_ref().unwrap();
             quote! { (#req_ident, #resp_ident) }
         })
         .collect();
 
     let local_request_only: Vec<_> = without_response
         .iter()
-        .filter(|t| t.local_only)
+        .filter(|t| t.local_only())
         .map(|t| {
-            let ident = syn::parse_str::<syn::Ident>(&t.request).unwrap();
+            let ident = &t.request;
             quote! { #ident }
         })
         .collect();
 
     let expanded = quote! {
+        #type_checks
+
+        #scopes
+
+        #protocol
+
         #[macro_export]
         macro_rules! for_all_request_pairs {
             ($m:tt, $params:expr) => {
@@ -604,6 +580,8 @@ pub fn define_request_types(_input: TokenStream) -> TokenStream {
                 $m!($param1, $param2, $param3, #(#non_local_requests),*);
             };
         }
+
+        #tests
     };
 
     TokenStream::from(expanded)
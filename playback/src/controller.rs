@@ -17,9 +17,15 @@ use tokio::sync::Mutex;
 use crate::dummy_souvlaki::{MediaControlEvent, MediaControls, PlatformConfig, SeekDirection};
 
 #[cfg(not(target_os = "android"))]
-use souvlaki::{MediaControlEvent, MediaControls, PlatformConfig, SeekDirection};
+use souvlaki::{MediaControlEvent, PlatformConfig, SeekDirection};
 
-use simple_channel::{SimpleChannel, SimpleReceiver, SimpleSender};
+#[cfg(not(any(target_os = "android", target_os = "linux")))]
+use souvlaki::MediaControls;
+
+#[cfg(target_os = "linux")]
+use crate::mpris::MediaControls;
+
+use simple_channel::{Overflow, SimpleChannel, SimpleReceiver, SimpleSender};
 
 use crate::player::{Playable, PlaybackState};
 
@@ -63,7 +69,7 @@ impl MediaControlManager {
         };
 
         let config = PlatformConfig {
-            dbus_name: "rune_player",
+            dbus_name: "rune",
             display_name: "Rune",
             hwnd,
         };
@@ -73,7 +79,8 @@ impl MediaControlManager {
             Err(e) => bail!(Error::msg(format!("{e:?}"))),
         };
 
-        let (event_sender, _) = SimpleChannel::channel(32);
+        // Events are commands of the user, they are never dropped
+        let (event_sender, _) = SimpleChannel::channel(32, Overflow::Wait);
 
         Ok(Self {
             controls,
@@ -90,7 +97,9 @@ impl MediaControlManager {
         let request = self.controls.attach(move |event: MediaControlEvent| {
             let event_sender = event_sender.clone();
             thread::spawn(move || {
-                event_sender.send(event);
+                if event_sender.send(event).is_err() {
+                    debug!("No one receives media control events");
+                }
             });
         });
 
@@ -150,9 +159,26 @@ pub async fn handle_media_control_event(
                 .await
                 .seek(current_position.as_millis() as f64 + seek_seconds * 1000.0);
         }
+        MediaControlEvent::SeekBy(direction, offset) => {
+            let offset_ms = match direction {
+                SeekDirection::Forward => offset.as_millis() as f64,
+                SeekDirection::Backward => -(offset.as_millis() as f64),
+            };
+
+            let current_position = player.lock().await.get_status().position;
+
+            player
+                .lock()
+                .await
+                .seek((current_position.as_millis() as f64 + offset_ms).max(0.0));
+        }
         MediaControlEvent::SetPosition(position) => {
             player.lock().await.seek(position.0.as_millis() as f64)
         }
+        MediaControlEvent::SetVolume(volume) => player
+            .lock()
+            .await
+            .set_volume(volume.clamp(0.0, 1.0) as f32),
         _ => debug!("Unhandled media control event: {event:?}"),
     }
 
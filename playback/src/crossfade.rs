@@ -0,0 +1,179 @@
+use std::{
+    fs::File,
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source, source::SeekError};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    probe::Hint,
+};
+
+/// The gain of a [`Fade`] source, ramped from the player thread while the
+/// source plays on the audio thread.
+#[derive(Debug, Default)]
+pub struct FadeControl {
+    pending: AtomicBool,
+    /// The gain to reach, and how long it takes
+    request: Mutex<Option<(f32, Duration)>>,
+}
+
+impl FadeControl {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Ramps the gain linearly from its current value to `target`.
+    pub fn ramp_to(&self, target: f32, duration: Duration) {
+        if let Ok(mut request) = self.request.lock() {
+            *request = Some((target, duration));
+            self.pending.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// A source whose gain follows the ramps of its [`FadeControl`], sample by
+/// sample, so two tracks can be crossfaded without steps in their volume.
+pub struct Fade<S: Source>
+where
+    S::Item: Sample,
+{
+    inner: S,
+    control: Arc<FadeControl>,
+    gain: f32,
+    step: f32,
+    target: f32,
+    remaining: u64,
+}
+
+impl<S: Source> Fade<S>
+where
+    S::Item: Sample,
+{
+    pub fn new(inner: S, control: Arc<FadeControl>, gain: f32) -> Self {
+        Self {
+            inner,
+            control,
+            gain,
+            step: 0.0,
+            target: gain,
+            remaining: 0,
+        }
+    }
+
+    fn start_pending_ramp(&mut self) {
+        let Some((target, duration)) = self
+            .control
+            .request
+            .lock()
+            .ok()
+            .and_then(|mut request| request.take())
+        else {
+            return;
+        };
+
+        let samples = (duration.as_secs_f64()
+            * self.inner.sample_rate() as f64
+            * self.inner.channels() as f64) as u64;
+        self.target = target;
+        if samples == 0 {
+            self.gain = target;
+            self.remaining = 0;
+        } else {
+            self.step = (target - self.gain) / samples as f32;
+            self.remaining = samples;
+        }
+    }
+}
+
+impl<S: Source> Iterator for Fade<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.control.pending.swap(false, Ordering::Acquire) {
+            self.start_pending_ramp();
+        }
+
+        let sample = self.inner.next()?;
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.gain = if self.remaining == 0 {
+                self.target
+            } else {
+                self.gain + self.step
+            };
+        }
+
+        Some(sample.amplify(self.gain))
+    }
+}
+
+impl<S: Source> Source for Fade<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
+
+fn album_tag(revision: &MetadataRevision) -> Option<String> {
+    revision
+        .tags()
+        .iter()
+        .find(|x| x.std_key == Some(StandardTagKey::Album))
+        .map(|x| x.value.to_string())
+}
+
+/// The album tag of a media file, `None` if it has none or can't be read.
+pub fn read_album(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|x| x.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+
+    if let Some(album) = probed.format.metadata().current().and_then(album_tag) {
+        return Some(album);
+    }
+    probed
+        .metadata
+        .get()
+        .and_then(|x| x.current().and_then(album_tag))
+}
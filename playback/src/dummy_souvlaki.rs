@@ -31,7 +31,9 @@ pub enum MediaControlEvent {
     Previous,
     Stop,
     Seek(SeekDirection),
+    SeekBy(SeekDirection, Duration),
     SetPosition(MediaPosition),
+    SetVolume(f64),
 }
 
 #[derive(Clone, Debug)]
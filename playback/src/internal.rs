@@ -1,15 +1,19 @@
 use std::{
+    collections::HashMap,
     fmt::Debug,
     fs::File,
     io::BufReader,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
     time::Duration,
 };
 
 use anyhow::{Context, Result, anyhow, bail};
 use log::{debug, error, info, warn};
-use rodio::{Decoder, PlayError, Sink, Source, source::SeekError};
+use rodio::{Decoder, PlayError, Sink, Source, cpal, source::SeekError};
 use stream_download::{StreamDownload, storage::temp::TempStorageProvider};
 use tokio::{
     sync::mpsc,
@@ -18,9 +22,12 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 
 use crate::buffered::{RuneBuffered, rune_buffered};
+use crate::crossfade::{Fade, FadeControl, read_album};
+use crate::loudness::{LoudnessNormalization, TrackLoudness};
 use crate::output_stream::{RuneOutputStream, RuneOutputStreamHandle};
 use crate::player::PlayingItem;
 use crate::realtime_fft::RealTimeFFT;
+use crate::retractable::Retractable;
 use crate::shared_source::SharedSource;
 use crate::strategies::{
     AddMode, PlaybackStrategy, RepeatAllStrategy, RepeatOneStrategy, SequentialStrategy,
@@ -98,6 +105,13 @@ impl Source for AnySource {
     }
 }
 
+/// How long before the end of a track, or before its crossfade, the next one
+/// starts being decoded.
+const GAPLESS_PREFETCH_LEAD: Duration = Duration::from_secs(5);
+
+/// The longest overlap between two crossfaded tracks.
+const MAX_CROSSFADE: Duration = Duration::from_secs(12);
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlaybackMode {
     Sequential,
@@ -140,6 +154,19 @@ pub enum PlayerCommand {
         index: usize,
         path: PathBuf,
         play: bool,
+        /// Where the track starts, not at its beginning when it resumes on
+        /// another output stream
+        position: Duration,
+    },
+    /// The next track, decoded ahead of time for gapless playback or a
+    /// crossfade
+    PrefetchComplete {
+        result: Box<Result<AnySource>>,
+        item: PlayingItem,
+        index: usize,
+        path: PathBuf,
+        /// Whether it is on the same album as the current track
+        same_album: bool,
     },
     Play,
     Pause,
@@ -156,6 +183,10 @@ pub enum PlayerCommand {
         index: usize,
     },
     ClearPlaylist,
+    /// Points the items of the playlist to files which moved
+    RelocatePlaylistItems {
+        paths: Vec<(PlayingItem, std::path::PathBuf)>,
+    },
     MovePlayListItem {
         old_index: usize,
         new_index: usize,
@@ -164,6 +195,13 @@ pub enum PlayerCommand {
     SetVolume(f32),
     SetRealtimeFFTEnabled(bool),
     SetAdaptiveSwitchingEnabled(bool),
+    SetGaplessEnabled(bool),
+    SetCrossfade(Duration),
+    SetLoudnessNormalization(Option<LoudnessNormalization>),
+    SetTrackLoudness(Vec<(PlayingItem, TrackLoudness)>),
+    /// Moves playback to the output device with the given id, `None` for the
+    /// default device
+    SetOutputDevice(Option<String>),
 }
 
 #[derive(Debug, Clone)]
@@ -215,6 +253,9 @@ pub enum PlayerEvent {
     PlaylistUpdated(Vec<PlayingItem>),
     RealtimeFFT(Vec<f32>),
     Log(InternalLog),
+    /// The chosen output device can't be opened and the default one plays
+    /// instead, `None` once the chosen one plays again
+    OutputDeviceUnavailable(Option<String>),
 }
 
 #[derive(Debug, Clone)]
@@ -223,6 +264,32 @@ pub struct PlaylistItem {
     pub path: PathBuf,
 }
 
+#[derive(Debug)]
+enum PrefetchState {
+    Decoding,
+    /// Queued in the sink after the current track
+    Queued {
+        retracted: Arc<AtomicBool>,
+        fade: Arc<FadeControl>,
+        format: (u32, u16),
+        duration: Option<Duration>,
+    },
+    /// Decoded, waiting for the crossfade to start
+    Ready(AnySource),
+    /// The track can't be spliced, it is loaded once the current one ends
+    Declined,
+}
+
+/// The track following the current one, for gapless playback or a
+/// crossfade.
+#[derive(Debug)]
+struct Prefetch {
+    item: PlayingItem,
+    index: usize,
+    path: PathBuf,
+    state: PrefetchState,
+}
+
 #[derive(Debug, PartialEq)]
 enum InternalPlaybackState {
     Playing,
@@ -281,16 +348,37 @@ pub(crate) struct PlayerInternal {
     current_track_path: Option<PathBuf>,
     sink: Option<Sink>,
     _stream: Option<RuneOutputStream>,
+    stream_handle: Option<RuneOutputStreamHandle>,
     state: InternalPlaybackState,
     debounce_timer: Option<Instant>,
     cancellation_token: CancellationToken,
     playback_mode: PlaybackMode,
     playback_strategy: Box<dyn PlaybackStrategy>,
     volume: f32,
-    stream_error_sender: mpsc::UnboundedSender<String>,
-    stream_error_receiver: mpsc::UnboundedReceiver<String>,
+    stream_error_sender: mpsc::UnboundedSender<cpal::StreamError>,
+    stream_error_receiver: mpsc::UnboundedReceiver<cpal::StreamError>,
     stream_retry_count: usize,
     adaptive_switching: bool,
+    gapless: bool,
+    /// The sample rate and the channels of the current track
+    current_format: Option<(u32, u16)>,
+    current_duration: Option<Duration>,
+    prefetch: Option<Prefetch>,
+    crossfade: Duration,
+    /// The gain of the current track
+    current_fade: Option<Arc<FadeControl>>,
+    /// The sink of the previous track while it fades out, dropped once the
+    /// crossfade is over
+    fading_out: Option<(Sink, Instant)>,
+    /// Set when the user seeks, the current track then ends without a
+    /// crossfade
+    crossfade_skipped: bool,
+    loudness_normalization: Option<LoudnessNormalization>,
+    track_loudness: HashMap<PlayingItem, TrackLoudness>,
+    /// The id of the chosen output device, `None` for the default one
+    output_device: Option<String>,
+    /// The chosen output device while the default one plays instead
+    unavailable_output_device: Option<String>,
 }
 
 impl PlayerInternal {
@@ -311,6 +399,7 @@ impl PlayerInternal {
             current_track_path: None,
             sink: None,
             _stream: None,
+            stream_handle: None,
             realtime_fft: Arc::new(Mutex::new(RealTimeFFT::new(512))),
             state: InternalPlaybackState::Stopped,
             debounce_timer: None,
@@ -323,6 +412,18 @@ impl PlayerInternal {
             stream_error_receiver,
             stream_retry_count: 0,
             adaptive_switching: false,
+            gapless: false,
+            current_format: None,
+            current_duration: None,
+            prefetch: None,
+            crossfade: Duration::ZERO,
+            current_fade: None,
+            fading_out: None,
+            crossfade_skipped: false,
+            loudness_normalization: None,
+            track_loudness: HashMap::new(),
+            output_device: None,
+            unavailable_output_device: None,
         }
     }
 
@@ -348,11 +449,11 @@ impl PlayerInternal {
                     debug!("Received command: {cmd:?}");
                     match cmd {
                         PlayerCommand::Load { index } => self.load(Some(index), false, true)?,
-                        PlayerCommand::LoadComplete { result, item, index, path, play } => {
+                        PlayerCommand::LoadComplete { result, item, index, path, play, position } => {
                             self.state = InternalPlaybackState::Stopped;
                             match *result {
                                 Ok(source) => {
-                                    self.setup_sink(source, item, index, path, play)?;
+                                    self.setup_sink(source, item, index, path, play, position)?;
                                 }
                                 Err(e) => {
                                     error!("Failed to load track: {e:?}");
@@ -360,6 +461,9 @@ impl PlayerInternal {
                                 }
                             }
                         },
+                        PlayerCommand::PrefetchComplete { result, item, index, path, same_album } => {
+                            self.queue_prefetched(*result, item, index, path, same_album);
+                        },
                         PlayerCommand::Play => self.play()?,
                         PlayerCommand::Pause => self.pause()?,
                         PlayerCommand::Stop => self.stop()?,
@@ -368,17 +472,25 @@ impl PlayerInternal {
                         PlayerCommand::Switch(index) => self.switch(index)?,
                         PlayerCommand::Seek(position) => self.seek(position)?,
                         PlayerCommand::AddToPlaylist { tracks, mode } => {
-                            self.add_to_playlist(tracks, mode);
+                            self.add_to_playlist(tracks, mode)?;
                         },
                         PlayerCommand::RemoveFromPlaylist { index } => self.remove_from_playlist(index)?,
                         PlayerCommand::ClearPlaylist => self.clear_playlist()?,
+                        PlayerCommand::RelocatePlaylistItems { paths } => self.relocate_playlist_items(paths)?,
                         PlayerCommand::MovePlayListItem {old_index, new_index} => {
-                            self.move_playlist_item(old_index, new_index);
+                            self.move_playlist_item(old_index, new_index)?;
                         },
                         PlayerCommand::SetPlaybackMode(mode) => self.set_playback_mode(mode)?,
                         PlayerCommand::SetVolume(volume) => self.set_volume(volume)?,
                         PlayerCommand::SetRealtimeFFTEnabled(enabled) => self.set_realtime_fft_enabled(enabled)?,
                         PlayerCommand::SetAdaptiveSwitchingEnabled(enabled) => self.set_adaptive_switching(enabled)?,
+                        PlayerCommand::SetGaplessEnabled(enabled) => self.set_gapless(enabled)?,
+                        PlayerCommand::SetCrossfade(duration) => self.set_crossfade(duration)?,
+                        PlayerCommand::SetLoudnessNormalization(normalization) => {
+                            self.set_loudness_normalization(normalization)?;
+                        },
+                        PlayerCommand::SetTrackLoudness(loudness) => self.track_loudness.extend(loudness),
+                        PlayerCommand::SetOutputDevice(device) => self.set_output_device(device)?,
                     };
                 },
                 Ok(fft_data) = fft_receiver.recv() => {
@@ -389,6 +501,9 @@ impl PlayerInternal {
                 _ = progress_interval.tick() => {
                     if self.state != InternalPlaybackState::Stopped {
                         self.send_progress()?;
+                        self.prefetch_next();
+                        self.start_crossfade()?;
+                        self.finish_crossfade();
                     }
                 },
                 _ = async {
@@ -402,7 +517,14 @@ impl PlayerInternal {
                     self.debounce_timer = None;
                     self.send_playlist_updated()?;
                 },
-                Some(error_message) = self.stream_error_receiver.recv() => {
+                Some(error) = self.stream_error_receiver.recv() => {
+                    if matches!(error, cpal::StreamError::DeviceNotAvailable) {
+                        warn!("Output device disappeared, reopening the output stream");
+                        self.reopen_output_stream()?;
+                        continue;
+                    }
+
+                    let error_message = error.to_string();
                     self.stop()?;
                     error!("Received error message: {error_message}");
 
@@ -437,12 +559,25 @@ impl PlayerInternal {
     }
 
     fn load(&mut self, index: Option<usize>, play: bool, mapped: bool) -> Result<()> {
+        self.load_at(index, play, mapped, Duration::ZERO)
+    }
+
+    /// Loads a track, starting it at `position`.
+    fn load_at(
+        &mut self,
+        index: Option<usize>,
+        play: bool,
+        mapped: bool,
+        position: Duration,
+    ) -> Result<()> {
         if self.state == InternalPlaybackState::Loading {
             warn!("Already loading a track, ignoring new load request.");
             return Ok(());
         }
 
         if let Some(index) = index {
+            self.discard_prefetch()?;
+            self.fading_out = None;
             self.state = InternalPlaybackState::Loading;
 
             let mapped_index = if mapped {
@@ -461,26 +596,7 @@ impl PlayerInternal {
             let commands_sender = self.commands_sender.clone();
 
             tokio::spawn(async move {
-                let source_result = async {
-                    match &item.item {
-                        PlayingItem::IndependentFile(_) | PlayingItem::InLibrary(_) => {
-                            let file = File::open(item.path.clone())
-                                .with_context(|| format!("Failed to open file: {:?}", item.path))?;
-                            let decoder = Decoder::new(BufReader::new(file))?;
-                            Ok(AnySource::Local(rune_buffered(decoder)))
-                        }
-                        PlayingItem::Online(url, _) => {
-                            info!("Downloading from url: {url}");
-                            let reader = crate::stream_utils::create_stream_from_url(url).await?;
-                            let decoder = Decoder::new(reader)?;
-                            Ok(AnySource::Online(rune_buffered(decoder)))
-                        }
-                        PlayingItem::Unknown => {
-                            bail!("Cannot load unknown item");
-                        }
-                    }
-                }
-                .await;
+                let source_result = open_source(&item).await;
 
                 let cmd = PlayerCommand::LoadComplete {
                     result: Box::new(source_result),
@@ -488,6 +604,7 @@ impl PlayerInternal {
                     index,
                     path: item.path,
                     play,
+                    position,
                 };
                 if commands_sender.send(cmd).is_err() {
                     error!("Failed to send LoadComplete command");
@@ -504,55 +621,31 @@ impl PlayerInternal {
         index: usize,
         path: PathBuf,
         play: bool,
+        position: Duration,
     ) -> Result<()> {
-        let source = SharedSource::new(source);
-        let source_for_fft = Arc::clone(&source.inner);
-
-        let (stream, stream_handle) = RuneOutputStream::try_default_with_callback({
-            let error_sender = self.stream_error_sender.clone();
-            move |error| {
-                let _ = error_sender.send(error.to_string());
-            }
-        })
-        .context("Failed to create output stream")?;
+        let (stream, stream_handle) = self.open_output_stream()?;
         let sink = try_new_sink(&stream_handle).context("Failed to create sink")?;
 
-        let (fft_tx, mut fft_rx) = mpsc::unbounded_channel();
-
-        let realtime_fft = Arc::clone(&self.realtime_fft);
-        let fft_enabled = Arc::clone(&self.fft_enabled);
-        tokio::spawn(async move {
-            while let Some(data) = fft_rx.recv().await {
-                if let Ok(enabled) = fft_enabled.lock()
-                    && *enabled
-                    && let Ok(fft) = realtime_fft.lock()
-                {
-                    fft.add_data(data);
-                }
-            }
-        });
-
         sink.set_volume(self.volume);
-        sink.append(source.periodic_access(
-            Duration::from_millis(12),
-            move |_sample: &mut SharedSource<_>| {
-                if let Ok(guard) = source_for_fft.lock() {
-                    let data: Option<Vec<i16>> = guard.current_samples();
-                    if let Some(data) = data
-                        && fft_tx.send(data).is_err()
-                    {
-                        error!("Failed to send FFT data");
-                    }
-                }
-            },
-        ));
+        self.current_format = Some((source.sample_rate(), source.channels()));
+        self.current_duration = source.total_duration();
+        let (_, fade) = self.append_to_sink(&sink, source, &item, 1.0);
+        self.current_fade = Some(fade);
+        self.crossfade_skipped = false;
 
         if !play {
             sink.pause();
         }
+        if !position.is_zero() {
+            if let Err(e) = sink.try_seek(position) {
+                warn!("Failed to resume the track at {position:?}: {e:?}");
+            }
+        }
+        let position = sink.get_pos();
 
         self.sink = Some(sink);
         self._stream = Some(stream);
+        self.stream_handle = Some(stream_handle);
         self.current_track_index = Some(index);
         self.current_item = Some(item.clone());
         self.current_track_path = Some(path.clone());
@@ -565,7 +658,7 @@ impl PlayerInternal {
                     index,
                     path,
                     playback_mode: self.playback_mode,
-                    position: Duration::new(0, 0),
+                    position,
                 })
                 .context("Failed to send Playing event")?;
             self.state = InternalPlaybackState::Playing;
@@ -576,7 +669,7 @@ impl PlayerInternal {
                     index,
                     path,
                     playback_mode: self.playback_mode,
-                    position: Duration::new(0, 0),
+                    position,
                 })
                 .context("Failed to send Playing event")?;
             self.state = InternalPlaybackState::Stopped;
@@ -585,6 +678,383 @@ impl PlayerInternal {
         Ok(())
     }
 
+    /// Appends a track to the sink at the given fade gain, normalizing its
+    /// loudness and feeding the realtime FFT while it plays.
+    ///
+    /// Returns the flag which retracts the track from the sink, and the
+    /// control fading it.
+    fn append_to_sink(
+        &self,
+        sink: &Sink,
+        source: AnySource,
+        item: &PlayingItem,
+        gain: f32,
+    ) -> (Arc<AtomicBool>, Arc<FadeControl>) {
+        let normalization_gain = self.normalization_gain(item);
+        let source = SharedSource::new(source);
+        let source_for_fft = Arc::clone(&source.inner);
+
+        let (fft_tx, mut fft_rx) = mpsc::unbounded_channel();
+
+        let realtime_fft = Arc::clone(&self.realtime_fft);
+        let fft_enabled = Arc::clone(&self.fft_enabled);
+        tokio::spawn(async move {
+            while let Some(data) = fft_rx.recv().await {
+                if let Ok(enabled) = fft_enabled.lock()
+                    && *enabled
+                    && let Ok(fft) = realtime_fft.lock()
+                {
+                    fft.add_data(data);
+                }
+            }
+        });
+
+        let retracted = Arc::new(AtomicBool::new(false));
+        let fade = FadeControl::new();
+        sink.append(Retractable::new(
+            Fade::new(
+                source
+                    .periodic_access(
+                        Duration::from_millis(12),
+                        move |_sample: &mut SharedSource<_>| {
+                            if let Ok(guard) = source_for_fft.lock() {
+                                let data: Option<Vec<i16>> = guard.current_samples();
+                                if let Some(data) = data
+                                    && fft_tx.send(data).is_err()
+                                {
+                                    error!("Failed to send FFT data");
+                                }
+                            }
+                        },
+                    )
+                    .amplify(normalization_gain),
+                Arc::clone(&fade),
+                gain,
+            ),
+            Arc::clone(&retracted),
+        ));
+
+        (retracted, fade)
+    }
+
+    /// Starts decoding the next track shortly before the current one ends,
+    /// for it to be queued right after it or crossfaded with it.
+    fn prefetch_next(&mut self) {
+        if (!self.gapless && self.crossfade.is_zero())
+            || self.prefetch.is_some()
+            || self.state != InternalPlaybackState::Playing
+        {
+            return;
+        }
+
+        let (Some(sink), Some(index), Some(duration)) =
+            (&self.sink, self.current_track_index, self.current_duration)
+        else {
+            return;
+        };
+        // Nothing but the current track may be queued
+        if sink.len() != 1
+            || duration.saturating_sub(sink.get_pos()) > GAPLESS_PREFETCH_LEAD + self.crossfade
+        {
+            return;
+        }
+
+        let Some(next_index) = self.playback_strategy.next(index, self.playlist.len()) else {
+            return;
+        };
+        let Some(item) = self
+            .playlist
+            .get(self.get_mapped_track_index(next_index))
+            .cloned()
+        else {
+            return;
+        };
+
+        debug!("Prefetching the next track: {:?}", item.path);
+        self.prefetch = Some(Prefetch {
+            item: item.item.clone(),
+            index: next_index,
+            path: item.path.clone(),
+            state: PrefetchState::Decoding,
+        });
+
+        // Gapless playback is preferred over crossfading within an album
+        let current = match (&self.current_item, &self.current_track_path) {
+            (Some(item), Some(path)) if self.gapless && !self.crossfade.is_zero() => {
+                Some(PlaylistItem {
+                    item: item.clone(),
+                    path: path.clone(),
+                })
+            }
+            _ => None,
+        };
+
+        let commands_sender = self.commands_sender.clone();
+        tokio::spawn(async move {
+            let result = open_source(&item).await;
+            let same_album = match current {
+                Some(current) => is_same_album(&current, &item).await,
+                None => false,
+            };
+
+            let cmd = PlayerCommand::PrefetchComplete {
+                result: Box::new(result),
+                item: item.item,
+                index: next_index,
+                path: item.path,
+                same_album,
+            };
+            if commands_sender.send(cmd).is_err() {
+                error!("Failed to send PrefetchComplete command");
+            }
+        });
+    }
+
+    /// Keeps the prefetched track for the crossfade, or queues it after the
+    /// current one if their PCM streams can be spliced, the track being
+    /// loaded once the current one ends otherwise.
+    fn queue_prefetched(
+        &mut self,
+        result: Result<AnySource>,
+        item: PlayingItem,
+        index: usize,
+        path: PathBuf,
+        same_album: bool,
+    ) {
+        let Some(prefetch) = &self.prefetch else {
+            return;
+        };
+        if !matches!(prefetch.state, PrefetchState::Decoding)
+            || prefetch.index != index
+            || prefetch.item != item
+            || prefetch.path != path
+        {
+            debug!("Dropping the outdated prefetched track {path:?}");
+            return;
+        }
+
+        let state = match result {
+            Err(e) => {
+                warn!("Failed to prefetch {path:?}: {e:?}");
+                PrefetchState::Declined
+            }
+            Ok(source)
+                if !self.crossfade.is_zero()
+                    && !self.crossfade_skipped
+                    && !(self.gapless && same_album) =>
+            {
+                debug!("Prefetched {path:?} for the crossfade");
+                PrefetchState::Ready(source)
+            }
+            Ok(source) => {
+                let format = (source.sample_rate(), source.channels());
+
+                match &self.sink {
+                    Some(sink)
+                        if self.gapless
+                            && sink.len() == 1
+                            && self.current_format == Some(format) =>
+                    {
+                        let duration = source.total_duration();
+                        let (retracted, fade) = self.append_to_sink(sink, source, &item, 1.0);
+                        info!("Queued {path:?} for gapless playback");
+
+                        PrefetchState::Queued {
+                            retracted,
+                            fade,
+                            format,
+                            duration,
+                        }
+                    }
+                    _ => {
+                        info!(
+                            "{path:?} can't be spliced after the current track, \
+                            it will be loaded once the track ends"
+                        );
+                        PrefetchState::Declined
+                    }
+                }
+            }
+        };
+
+        if let Some(prefetch) = &mut self.prefetch {
+            prefetch.state = state;
+        }
+    }
+
+    /// Moves on to the queued track once the sink started playing it.
+    fn advance_to_prefetched(&mut self) -> Result<()> {
+        let Some(sink) = &self.sink else {
+            return Ok(());
+        };
+        if sink.len() > 1
+            || !matches!(
+                self.prefetch,
+                Some(Prefetch {
+                    state: PrefetchState::Queued { .. },
+                    ..
+                })
+            )
+        {
+            return Ok(());
+        }
+
+        let Some(Prefetch {
+            item,
+            index,
+            path,
+            state:
+                PrefetchState::Queued {
+                    fade,
+                    format,
+                    duration,
+                    ..
+                },
+        }) = self.prefetch.take()
+        else {
+            return Ok(());
+        };
+
+        info!("Gapless transition to {path:?}");
+        self.current_fade = Some(fade);
+        self.switch_current_track(item, index, path, format, duration)
+    }
+
+    /// Starts fading the current track out and the prefetched one in, once
+    /// the current track is about to end.
+    fn start_crossfade(&mut self) -> Result<()> {
+        if self.state != InternalPlaybackState::Playing
+            || !matches!(
+                self.prefetch,
+                Some(Prefetch {
+                    state: PrefetchState::Ready(_),
+                    ..
+                })
+            )
+        {
+            return Ok(());
+        }
+
+        let (Some(sink), Some(duration), Some(stream_handle)) =
+            (&self.sink, self.current_duration, &self.stream_handle)
+        else {
+            return Ok(());
+        };
+        let remaining = duration.saturating_sub(sink.get_pos());
+        if remaining > self.crossfade {
+            return Ok(());
+        }
+
+        let incoming = match try_new_sink(stream_handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                warn!("Failed to create the sink to crossfade into: {e:?}");
+                if let Some(prefetch) = &mut self.prefetch {
+                    prefetch.state = PrefetchState::Declined;
+                }
+                return Ok(());
+            }
+        };
+        incoming.set_volume(self.volume);
+
+        let Some(Prefetch {
+            item,
+            index,
+            path,
+            state: PrefetchState::Ready(source),
+        }) = self.prefetch.take()
+        else {
+            return Ok(());
+        };
+
+        info!("Crossfading to {path:?} over {remaining:?}");
+        let format = (source.sample_rate(), source.channels());
+        let duration = source.total_duration();
+        let (_, fade) = self.append_to_sink(&incoming, source, &item, 0.0);
+        fade.ramp_to(1.0, remaining);
+        if let Some(outgoing) = self.current_fade.replace(fade) {
+            outgoing.ramp_to(0.0, remaining);
+        }
+
+        self.fading_out = self
+            .sink
+            .replace(incoming)
+            .map(|outgoing| (outgoing, Instant::now() + remaining));
+        self.switch_current_track(item, index, path, format, duration)
+    }
+
+    /// Drops the faded out track once its crossfade is over.
+    fn finish_crossfade(&mut self) {
+        if let Some((_, deadline)) = &self.fading_out
+            && Instant::now() >= *deadline
+        {
+            self.fading_out = None;
+        }
+    }
+
+    /// Ends the current track and reports the one which took over in the
+    /// sink.
+    fn switch_current_track(
+        &mut self,
+        item: PlayingItem,
+        index: usize,
+        path: PathBuf,
+        format: (u32, u16),
+        duration: Option<Duration>,
+    ) -> Result<()> {
+        let playback_mode = self.playback_mode;
+        if let (Some(item), Some(index), Some(path)) = (
+            self.current_item.take(),
+            self.current_track_index,
+            self.current_track_path.take(),
+        ) {
+            self.event_sender
+                .send(PlayerEvent::EndOfTrack {
+                    item,
+                    index: self.get_mapped_track_index(index),
+                    path,
+                    playback_mode,
+                })
+                .with_context(|| "Failed to send EndOfTrack event")?;
+        }
+
+        self.current_track_index = Some(index);
+        self.current_item = Some(item.clone());
+        self.current_track_path = Some(path.clone());
+        self.current_format = Some(format);
+        self.current_duration = duration;
+        self.crossfade_skipped = false;
+
+        self.event_sender
+            .send(PlayerEvent::Playing {
+                item,
+                index: self.get_mapped_track_index(index),
+                path,
+                playback_mode,
+                position: Duration::new(0, 0),
+            })
+            .with_context(|| "Failed to send Playing event")?;
+
+        Ok(())
+    }
+
+    /// Forgets the prefetched track, taking it back from the sink if it was
+    /// queued.
+    fn discard_prefetch(&mut self) -> Result<()> {
+        // The queued track may already be playing
+        self.advance_to_prefetched()?;
+
+        if let Some(Prefetch {
+            state: PrefetchState::Queued { retracted, .. },
+            ..
+        }) = self.prefetch.take()
+        {
+            retracted.store(true, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
     fn play(&mut self) -> Result<()> {
         if let Some(sink) = &self.sink {
             sink.play();
@@ -616,6 +1086,7 @@ impl PlayerInternal {
     }
 
     fn pause(&mut self) -> Result<()> {
+        self.fading_out = None;
         if let Some(sink) = &self.sink {
             sink.pause();
             info!("Playback paused");
@@ -638,6 +1109,8 @@ impl PlayerInternal {
     }
 
     fn stop(&mut self) -> Result<()> {
+        self.prefetch = None;
+        self.fading_out = None;
         if let Some(sink) = self.sink.take() {
             sink.stop();
             info!("Playback stopped");
@@ -653,6 +1126,8 @@ impl PlayerInternal {
     }
 
     fn next(&mut self) -> Result<()> {
+        self.advance_to_prefetched()?;
+
         if let Some(index) = self.current_track_index {
             if let Some(next_index) = self.playback_strategy.next(index, self.playlist.len()) {
                 self.load(Some(next_index), true, true)?;
@@ -686,6 +1161,8 @@ impl PlayerInternal {
     }
 
     fn previous(&mut self) -> Result<()> {
+        self.advance_to_prefetched()?;
+
         if let Some(index) = self.current_track_index {
             match &self.sink {
                 Some(sink) => {
@@ -717,6 +1194,17 @@ impl PlayerInternal {
     }
 
     fn seek(&mut self, position: f64) -> Result<()> {
+        self.crossfade_skipped = true;
+        if matches!(
+            self.prefetch,
+            Some(Prefetch {
+                state: PrefetchState::Ready(_),
+                ..
+            })
+        ) {
+            self.prefetch = None;
+        }
+
         if let Some(sink) = &self.sink {
             match sink.try_seek(std::time::Duration::from_secs_f64(position)) {
                 Ok(_) => {
@@ -772,8 +1260,13 @@ impl PlayerInternal {
         Ok(())
     }
 
-    fn add_to_playlist(&mut self, tracks: Vec<(PlayingItem, std::path::PathBuf)>, mode: AddMode) {
+    fn add_to_playlist(
+        &mut self,
+        tracks: Vec<(PlayingItem, std::path::PathBuf)>,
+        mode: AddMode,
+    ) -> Result<()> {
         debug!("Adding tracks to playlist with mode: {:?}", { mode });
+        self.discard_prefetch()?;
         let insert_index = match mode {
             AddMode::PlayNext => {
                 if let Some(current_index) = self.current_track_index {
@@ -811,11 +1304,14 @@ impl PlayerInternal {
             },
         );
         self.schedule_playlist_update();
+
+        Ok(())
     }
 
     fn remove_from_playlist(&mut self, index: usize) -> Result<()> {
         if index < self.playlist.len() {
             debug!("Removing from playlist at index: {}", { index });
+            self.discard_prefetch()?;
             self.playlist.remove(index);
             self.playback_strategy.on_playlist_updated(
                 self.playlist.len(),
@@ -833,12 +1329,16 @@ impl PlayerInternal {
     }
 
     fn clear_playlist(&mut self) -> Result<()> {
+        self.prefetch = None;
         self.playlist.clear();
+        self.track_loudness.clear();
         self.playback_strategy
             .on_playlist_updated(0, UpdateReason::ClearPlaylist);
         self.current_track_index = None;
+        self.fading_out = None;
         self.sink = None;
         self._stream = None;
+        self.stream_handle = None;
         info!("Playlist cleared");
         self.event_sender
             .send(PlayerEvent::Stopped)
@@ -850,6 +1350,7 @@ impl PlayerInternal {
     }
 
     fn set_playback_mode(&mut self, mode: PlaybackMode) -> Result<()> {
+        self.discard_prefetch()?;
         self.playback_mode = mode;
         self.playback_strategy = match mode {
             PlaybackMode::Sequential => Box::new(SequentialStrategy),
@@ -869,6 +1370,8 @@ impl PlayerInternal {
     }
 
     fn send_progress(&mut self) -> Result<()> {
+        self.advance_to_prefetched()?;
+
         let id = self.current_item.clone();
         let index = self.current_track_index;
         let index = index.map(|x| self.get_mapped_track_index(x));
@@ -919,18 +1422,38 @@ impl PlayerInternal {
         Ok(())
     }
 
-    fn move_playlist_item(&mut self, old_index: usize, new_index: usize) {
+    fn relocate_playlist_items(
+        &mut self,
+        paths: Vec<(PlayingItem, std::path::PathBuf)>,
+    ) -> Result<()> {
+        self.discard_prefetch()?;
+
+        let mut relocated = 0;
+        for entry in self.playlist.iter_mut() {
+            if let Some((_, path)) = paths.iter().find(|(item, _)| *item == entry.item) {
+                entry.path = path.clone();
+                relocated += 1;
+            }
+        }
+
+        debug!("Relocated {relocated} playlist items");
+
+        Ok(())
+    }
+
+    fn move_playlist_item(&mut self, old_index: usize, new_index: usize) -> Result<()> {
         if old_index >= self.playlist.len() || new_index >= self.playlist.len() {
             error!("Move command received but index is out of bounds");
-            return;
+            return Ok(());
         }
 
         if old_index == new_index {
             debug!("Move command received but old_index is the same as new_index");
-            return;
+            return Ok(());
         }
 
         debug!("Moving playlist item from index {old_index} to index {new_index}");
+        self.discard_prefetch()?;
 
         let item = self.playlist.remove(old_index);
         self.playlist.insert(new_index, item);
@@ -959,6 +1482,8 @@ impl PlayerInternal {
         }
 
         self.schedule_playlist_update();
+
+        Ok(())
     }
 
     fn schedule_playlist_update(&mut self) {
@@ -980,6 +1505,9 @@ impl PlayerInternal {
         if let Some(sink) = &self.sink {
             sink.set_volume(volume);
         }
+        if let Some((sink, _)) = &self.fading_out {
+            sink.set_volume(volume);
+        }
         self.event_sender
             .send(PlayerEvent::VolumeUpdate(volume))
             .with_context(|| "Failed to send VolumeUpdate event")?;
@@ -1002,4 +1530,162 @@ impl PlayerInternal {
 
         Ok(())
     }
+
+    fn set_gapless(&mut self, x: bool) -> Result<()> {
+        self.gapless = x;
+        if !x {
+            self.discard_prefetch()?;
+        }
+
+        info!("Gapless playback status changed: {x:#?}");
+
+        Ok(())
+    }
+
+    fn set_crossfade(&mut self, x: Duration) -> Result<()> {
+        let x = x.min(MAX_CROSSFADE);
+        if self.crossfade != x {
+            self.crossfade = x;
+            self.discard_prefetch()?;
+        }
+
+        info!("Crossfade duration changed: {x:?}");
+
+        Ok(())
+    }
+
+    fn set_loudness_normalization(&mut self, x: Option<LoudnessNormalization>) -> Result<()> {
+        self.loudness_normalization = x;
+
+        info!("Loudness normalization changed: {x:?}");
+
+        Ok(())
+    }
+
+    /// The gain of a track, 1.0 unless its loudness is normalized.
+    fn normalization_gain(&self, item: &PlayingItem) -> f32 {
+        match (&self.loudness_normalization, self.track_loudness.get(item)) {
+            (Some(normalization), Some(loudness)) => normalization.gain(loudness),
+            _ => 1.0,
+        }
+    }
+
+    fn set_output_device(&mut self, device: Option<String>) -> Result<()> {
+        if self.output_device == device && self.unavailable_output_device.is_none() {
+            return Ok(());
+        }
+        if device.is_none() {
+            self.set_unavailable_output_device(None)?;
+        }
+
+        info!("Output device changed: {device:?}");
+        self.output_device = device;
+
+        self.reopen_output_stream()
+    }
+
+    /// Opens an output stream on the chosen device, or on the default one
+    /// when none is chosen or it can't be opened.
+    fn open_output_stream(&mut self) -> Result<(RuneOutputStream, RuneOutputStreamHandle)> {
+        let error_callback = {
+            let error_sender = self.stream_error_sender.clone();
+            move |error| {
+                let _ = error_sender.send(error);
+            }
+        };
+
+        if let Some(id) = self.output_device.clone() {
+            match RuneOutputStream::try_from_id_with_callback(&id, error_callback.clone()) {
+                Ok(stream) => {
+                    self.set_unavailable_output_device(None)?;
+                    return Ok(stream);
+                }
+                Err(e) => {
+                    warn!("Failed to open output device {id}, using the default one: {e}");
+                    self.set_unavailable_output_device(Some(id))?;
+                }
+            }
+        }
+
+        RuneOutputStream::try_default_with_callback(error_callback)
+            .context("Failed to create output stream")
+    }
+
+    /// Drops the output stream, and reloads the current track where it was
+    /// so it plays on a new stream. Without a track the next one opens it.
+    fn reopen_output_stream(&mut self) -> Result<()> {
+        if self.state == InternalPlaybackState::Loading {
+            // The track being loaded opens a new stream anyway
+            return Ok(());
+        }
+
+        self.discard_prefetch()?;
+        let sink = self.sink.take();
+        self.fading_out = None;
+        self._stream = None;
+        self.stream_handle = None;
+
+        let (Some(sink), Some(index)) = (sink, self.current_track_index) else {
+            return Ok(());
+        };
+        let position = sink.get_pos();
+        let play = self.state == InternalPlaybackState::Playing;
+        sink.stop();
+        info!("Resuming the current track at {position:?} on a new output stream");
+
+        self.load_at(Some(index), play, true, position)
+    }
+
+    fn set_unavailable_output_device(&mut self, device: Option<String>) -> Result<()> {
+        if self.unavailable_output_device != device {
+            self.unavailable_output_device = device.clone();
+            self.event_sender
+                .send(PlayerEvent::OutputDeviceUnavailable(device))
+                .context("Failed to send OutputDeviceUnavailable event")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether two local tracks are tagged with the same album.
+async fn is_same_album(a: &PlaylistItem, b: &PlaylistItem) -> bool {
+    let is_local = |x: &PlaylistItem| {
+        matches!(
+            x.item,
+            PlayingItem::IndependentFile(_) | PlayingItem::InLibrary(_)
+        )
+    };
+    if !is_local(a) || !is_local(b) {
+        return false;
+    }
+
+    let (a, b) = (a.path.clone(), b.path.clone());
+    tokio::task::spawn_blocking(move || match (read_album(&a), read_album(&b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Opens the file or the stream of a playlist item for decoding.
+async fn open_source(item: &PlaylistItem) -> Result<AnySource> {
+    match &item.item {
+        PlayingItem::IndependentFile(_) | PlayingItem::InLibrary(_) => {
+            let file = File::open(item.path.clone())
+                .with_context(|| format!("Failed to open file: {:?}", item.path))?;
+            let decoder = Decoder::new(BufReader::new(file))?;
+            Ok(AnySource::Local(rune_buffered(decoder)))
+        }
+        PlayingItem::Online(url, _) => {
+            info!("Downloading from url: {url}");
+            let reader = crate::stream_utils::create_stream_from_url(url).await?;
+            let decoder = Decoder::new(reader)?;
+            Ok(AnySource::Online(rune_buffered(decoder)))
+        }
+        PlayingItem::Unknown => {
+            bail!("Cannot load unknown item");
+        }
+    }
 }
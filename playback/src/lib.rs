@@ -1,10 +1,13 @@
+mod crossfade;
 mod internal;
 mod realtime_fft;
+mod retractable;
 mod sfx_internal;
 mod shared_source;
 
 pub mod buffered;
 pub mod controller;
+pub mod loudness;
 pub mod output_stream;
 pub mod player;
 pub mod sfx_player;
@@ -14,6 +17,9 @@ pub mod stream_utils;
 #[cfg(target_os = "android")]
 mod dummy_souvlaki;
 
+#[cfg(target_os = "linux")]
+mod mpris;
+
 #[cfg(target_os = "android")]
 pub use dummy_souvlaki::{MediaMetadata, MediaPlayback, MediaPosition};
 
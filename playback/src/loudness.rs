@@ -0,0 +1,39 @@
+/// The loudness of a track measured by the analysis, in LUFS, peaks being
+/// linear sample values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackLoudness {
+    pub track_loudness: f32,
+    pub track_peak: f32,
+    /// Of all the analyzed tracks of its album, if it has one
+    pub album_loudness: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+/// How tracks are brought to the same loudness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessNormalization {
+    /// In LUFS
+    pub target_loudness: f32,
+    /// Keeps the loudness differences between the tracks of an album
+    pub album_gain: bool,
+}
+
+impl LoudnessNormalization {
+    /// The linear gain bringing the track to the target loudness, lowered
+    /// when its peak would clip.
+    pub fn gain(&self, loudness: &TrackLoudness) -> f32 {
+        let (track_loudness, peak) = match (loudness.album_loudness, loudness.album_peak) {
+            (Some(album_loudness), Some(album_peak)) if self.album_gain => {
+                (album_loudness, album_peak)
+            }
+            _ => (loudness.track_loudness, loudness.track_peak),
+        };
+
+        let gain = 10f32.powf((self.target_loudness - track_loudness) / 20.0);
+        if peak > 0.0 {
+            gain.min(1.0 / peak)
+        } else {
+            gain
+        }
+    }
+}
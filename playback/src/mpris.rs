@@ -0,0 +1,526 @@
+//! MPRIS implementation of the media controls on Linux.
+//!
+//! It replaces the D-Bus backend of souvlaki, so the player is registered
+//! once on the session bus and media keys are not handled twice. The API
+//! mirrors `souvlaki::MediaControls`.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use souvlaki::{
+    MediaControlEvent, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig, SeekDirection,
+};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use url::Url;
+use zbus::{
+    ConnectionBuilder, SignalContext, dbus_interface,
+    zvariant::{ObjectPath, OwnedValue, Value},
+};
+
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Position jumps larger than this are reported as seeks.
+const SEEK_TOLERANCE: Duration = Duration::from_secs(1);
+
+type EventHandler = Arc<Mutex<Option<Box<dyn Fn(MediaControlEvent) + Send>>>>;
+
+/// A platform-specific error.
+#[derive(Debug)]
+pub struct Error(pub String);
+
+#[derive(Debug, Default, Clone)]
+struct Track {
+    id: u64,
+    title: Option<String>,
+    album: Option<String>,
+    artist: Option<String>,
+    art_url: Option<String>,
+    length: Option<Duration>,
+}
+
+impl Track {
+    fn object_path(&self) -> String {
+        if self.id == 0 {
+            NO_TRACK.to_owned()
+        } else {
+            format!("/org/mpris/MediaPlayer2/rune/track/{}", self.id)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    track: Track,
+    playing: bool,
+    stopped: bool,
+    position: Duration,
+    position_updated_at: Instant,
+    volume: f64,
+    /// Set when the track changes, so the position reset is not reported
+    /// as a seek.
+    track_changed: bool,
+}
+
+impl State {
+    fn current_position(&self) -> Duration {
+        if self.playing {
+            self.position + self.position_updated_at.elapsed()
+        } else {
+            self.position
+        }
+    }
+}
+
+enum Update {
+    Metadata,
+    PlaybackStatus,
+    Seeked(Duration),
+}
+
+fn emit(events: &EventHandler, event: MediaControlEvent) {
+    match events.lock() {
+        Ok(handler) => {
+            if let Some(handler) = handler.as_ref() {
+                handler(event);
+            }
+        }
+        Err(e) => error!("Failed to lock the MPRIS event handler: {e}"),
+    }
+}
+
+fn micros(duration: Duration) -> i64 {
+    duration.as_micros().min(i64::MAX as u128) as i64
+}
+
+struct RootInterface {
+    identity: String,
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2")]
+impl RootInterface {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[dbus_interface(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[dbus_interface(property)]
+    fn identity(&self) -> String {
+        self.identity.clone()
+    }
+
+    #[dbus_interface(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[dbus_interface(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+struct PlayerInterface {
+    state: Arc<Mutex<State>>,
+    events: EventHandler,
+}
+
+impl PlayerInterface {
+    fn with_state<T>(&self, f: impl FnOnce(&mut State) -> T) -> zbus::fdo::Result<T> {
+        self.state
+            .lock()
+            .map(|mut x| f(&mut x))
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.Player")]
+impl PlayerInterface {
+    fn next(&self) {
+        emit(&self.events, MediaControlEvent::Next);
+    }
+
+    fn previous(&self) {
+        emit(&self.events, MediaControlEvent::Previous);
+    }
+
+    fn pause(&self) {
+        emit(&self.events, MediaControlEvent::Pause);
+    }
+
+    fn play_pause(&self) {
+        emit(&self.events, MediaControlEvent::Toggle);
+    }
+
+    fn stop(&self) {
+        emit(&self.events, MediaControlEvent::Stop);
+    }
+
+    fn play(&self) {
+        emit(&self.events, MediaControlEvent::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        let direction = if offset < 0 {
+            SeekDirection::Backward
+        } else {
+            SeekDirection::Forward
+        };
+        let offset = Duration::from_micros(offset.unsigned_abs());
+        emit(&self.events, MediaControlEvent::SeekBy(direction, offset));
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) -> zbus::fdo::Result<()> {
+        let current_track = self.with_state(|x| x.track.object_path())?;
+
+        // Requests for another track or out of range are ignored, as
+        // required by the specification
+        if track_id.as_str() != current_track || position < 0 {
+            return Ok(());
+        }
+
+        let position = Duration::from_micros(position as u64);
+        emit(
+            &self.events,
+            MediaControlEvent::SetPosition(MediaPosition(position)),
+        );
+        Ok(())
+    }
+
+    fn open_uri(&self, _uri: String) {}
+
+    #[dbus_interface(signal)]
+    async fn seeked(ctxt: &SignalContext<'_>, position: i64) -> zbus::Result<()>;
+
+    #[dbus_interface(property)]
+    fn playback_status(&self) -> zbus::fdo::Result<String> {
+        self.with_state(|x| {
+            match (x.stopped, x.playing) {
+                (true, _) => "Stopped",
+                (false, true) => "Playing",
+                (false, false) => "Paused",
+            }
+            .to_owned()
+        })
+    }
+
+    #[dbus_interface(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[dbus_interface(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[dbus_interface(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[dbus_interface(property)]
+    fn metadata(&self) -> zbus::fdo::Result<HashMap<String, OwnedValue>> {
+        let track = self.with_state(|x| x.track.clone())?;
+
+        let mut metadata = HashMap::new();
+        let track_id = ObjectPath::try_from(track.object_path())
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        metadata.insert("mpris:trackid".to_owned(), Value::from(track_id).into());
+
+        if let Some(length) = track.length {
+            metadata.insert(
+                "mpris:length".to_owned(),
+                Value::from(micros(length)).into(),
+            );
+        }
+        if let Some(art_url) = track.art_url {
+            metadata.insert("mpris:artUrl".to_owned(), Value::from(art_url).into());
+        }
+        if let Some(title) = track.title {
+            metadata.insert("xesam:title".to_owned(), Value::from(title).into());
+        }
+        if let Some(album) = track.album {
+            metadata.insert("xesam:album".to_owned(), Value::from(album).into());
+        }
+        if let Some(artist) = track.artist {
+            metadata.insert("xesam:artist".to_owned(), Value::from(vec![artist]).into());
+        }
+
+        Ok(metadata)
+    }
+
+    #[dbus_interface(property)]
+    fn volume(&self) -> zbus::fdo::Result<f64> {
+        self.with_state(|x| x.volume)
+    }
+
+    #[dbus_interface(property)]
+    fn set_volume(&mut self, volume: f64) {
+        let volume = volume.clamp(0.0, 1.0);
+        if self.with_state(|x| x.volume = volume).is_ok() {
+            emit(&self.events, MediaControlEvent::SetVolume(volume));
+        }
+    }
+
+    #[dbus_interface(property(emits_changed_signal = "false"))]
+    fn position(&self) -> zbus::fdo::Result<i64> {
+        self.with_state(|x| micros(x.current_position()))
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[dbus_interface(property)]
+    fn can_seek(&self) -> zbus::fdo::Result<bool> {
+        self.with_state(|x| x.track.length.is_some())
+    }
+
+    #[dbus_interface(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// Registers the player on the session bus and publishes state changes
+/// until `updates` is closed.
+async fn serve(
+    bus_name: String,
+    identity: String,
+    state: Arc<Mutex<State>>,
+    events: EventHandler,
+    mut updates: UnboundedReceiver<Update>,
+) -> zbus::Result<()> {
+    let player = PlayerInterface { state, events };
+
+    let connection = ConnectionBuilder::session()?
+        .name(bus_name.as_str())?
+        .serve_at(MPRIS_PATH, RootInterface { identity })?
+        .serve_at(MPRIS_PATH, player)?
+        .build()
+        .await?;
+
+    info!("MPRIS player registered as {bus_name}");
+
+    let player = connection
+        .object_server()
+        .interface::<_, PlayerInterface>(MPRIS_PATH)
+        .await?;
+
+    while let Some(update) = updates.recv().await {
+        let ctxt = player.signal_context();
+        let interface = player.get().await;
+
+        let result = match update {
+            Update::Metadata => match interface.metadata_changed(ctxt).await {
+                Ok(_) => interface.can_seek_changed(ctxt).await,
+                Err(e) => Err(e),
+            },
+            Update::PlaybackStatus => interface.playback_status_changed(ctxt).await,
+            Update::Seeked(position) => PlayerInterface::seeked(ctxt, micros(position)).await,
+        };
+
+        if let Err(e) = result {
+            warn!("Failed to publish MPRIS update: {e}");
+        }
+    }
+
+    connection.release_name(bus_name.as_str()).await?;
+    info!("MPRIS player unregistered");
+
+    Ok(())
+}
+
+/// A handle to the MPRIS player, unregistered when dropped.
+pub struct MediaControls {
+    bus_name: String,
+    identity: String,
+    state: Arc<Mutex<State>>,
+    events: EventHandler,
+    updates: Option<UnboundedSender<Update>>,
+}
+
+impl MediaControls {
+    /// Create media controls with the specified config.
+    pub fn new(config: PlatformConfig) -> Result<Self, Error> {
+        Ok(Self {
+            bus_name: format!("org.mpris.MediaPlayer2.{}", config.dbus_name),
+            identity: config.display_name.to_owned(),
+            state: Arc::new(Mutex::new(State {
+                track: Track::default(),
+                playing: false,
+                stopped: true,
+                position: Duration::ZERO,
+                position_updated_at: Instant::now(),
+                volume: 1.0,
+                track_changed: false,
+            })),
+            events: Arc::new(Mutex::new(None)),
+            updates: None,
+        })
+    }
+
+    /// Attach the media control events to a handler.
+    ///
+    /// The player is registered on a thread of its own, failing to reach
+    /// the session bus is logged and leaves the controls inactive.
+    pub fn attach<F>(&mut self, event_handler: F) -> Result<(), Error>
+    where
+        F: Fn(MediaControlEvent) + Send + 'static,
+    {
+        self.detach()?;
+
+        *self.events.lock().map_err(|e| Error(e.to_string()))? = Some(Box::new(event_handler));
+
+        let (sender, receiver) = unbounded_channel();
+        let bus_name = self.bus_name.clone();
+        let identity = self.identity.clone();
+        let state = Arc::clone(&self.state);
+        let events = Arc::clone(&self.events);
+
+        thread::Builder::new()
+            .name("mpris".to_owned())
+            .spawn(move || {
+                if let Err(e) = zbus::block_on(serve(bus_name, identity, state, events, receiver)) {
+                    error!("MPRIS player stopped: {e}");
+                }
+            })
+            .map_err(|e| Error(e.to_string()))?;
+
+        self.updates = Some(sender);
+
+        Ok(())
+    }
+
+    /// Detach the event handler and unregister the player.
+    pub fn detach(&mut self) -> Result<(), Error> {
+        *self.events.lock().map_err(|e| Error(e.to_string()))? = None;
+
+        // Closing the channel lets the server release its name and exit
+        self.updates = None;
+
+        Ok(())
+    }
+
+    fn publish(&self, update: Update) {
+        if let Some(updates) = &self.updates {
+            let _ = updates.send(update);
+        }
+    }
+
+    /// Set the current playback status.
+    pub fn set_playback(&mut self, playback: MediaPlayback) -> Result<(), Error> {
+        let (playing, stopped, progress) = match playback {
+            MediaPlayback::Stopped => (false, true, None),
+            MediaPlayback::Paused { progress } => (false, false, progress),
+            MediaPlayback::Playing { progress } => (true, false, progress),
+        };
+
+        let (status_changed, seeked) = {
+            let mut state = self.state.lock().map_err(|e| Error(e.to_string()))?;
+
+            let status_changed = state.playing != playing || state.stopped != stopped;
+            let mut seeked = None;
+
+            if let Some(MediaPosition(position)) = progress {
+                let expected = state.current_position();
+                let drift = position.abs_diff(expected);
+                if drift > SEEK_TOLERANCE && !state.track_changed {
+                    seeked = Some(position);
+                }
+
+                state.position = position;
+                state.position_updated_at = Instant::now();
+                state.track_changed = false;
+            } else if stopped {
+                state.position = Duration::ZERO;
+            }
+
+            state.playing = playing;
+            state.stopped = stopped;
+
+            (status_changed, seeked)
+        };
+
+        if status_changed {
+            self.publish(Update::PlaybackStatus);
+        }
+        if let Some(position) = seeked {
+            self.publish(Update::Seeked(position));
+        }
+
+        Ok(())
+    }
+
+    /// Set the metadata of the currently playing media item.
+    pub fn set_metadata(&mut self, metadata: MediaMetadata) -> Result<(), Error> {
+        let art_url = metadata.cover_url.map(|x| {
+            if x.contains("://") {
+                x.to_owned()
+            } else {
+                Url::from_file_path(x)
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|_| format!("file://{x}"))
+            }
+        });
+
+        {
+            let mut state = self.state.lock().map_err(|e| Error(e.to_string()))?;
+            state.track = Track {
+                id: state.track.id + 1,
+                title: metadata.title.map(ToOwned::to_owned),
+                album: metadata.album.map(ToOwned::to_owned),
+                artist: metadata.artist.map(ToOwned::to_owned),
+                art_url,
+                length: metadata.duration,
+            };
+            state.track_changed = true;
+        }
+
+        self.publish(Update::Metadata);
+
+        Ok(())
+    }
+}
+
+impl Drop for MediaControls {
+    fn drop(&mut self) {
+        let _ = self.detach();
+    }
+}
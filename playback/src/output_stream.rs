@@ -1,5 +1,7 @@
-use std::sync::{Arc, Weak};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
 
+use anyhow::{Context, Result};
 use rodio::cpal::Sample;
 use rodio::cpal::traits::{HostTrait, StreamTrait};
 use rodio::dynamic_mixer::{self, DynamicMixerController};
@@ -7,6 +9,75 @@ use rodio::source::Source;
 use rodio::{DeviceTrait, SupportedStreamConfig, cpal};
 use rodio::{PlayError, StreamError};
 
+/// The name of the device the last output stream was opened on.
+static OUTPUT_DEVICE: Mutex<String> = Mutex::new(String::new());
+
+/// The audio API the output streams are opened with, like WASAPI or ALSA.
+pub fn audio_host_name() -> String {
+    cpal::default_host().id().name().to_owned()
+}
+
+/// The name of the device the last output stream was opened on, empty
+/// before any was opened.
+pub fn output_device_name() -> String {
+    OUTPUT_DEVICE.lock().map(|x| x.clone()).unwrap_or_default()
+}
+
+/// An output device of the audio host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputDevice {
+    /// The name of the device, followed by `#n` for the nth other device
+    /// with the same name
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// The output devices of the default host with their ids, in the order the
+/// host lists them.
+fn output_devices() -> Result<Vec<(String, cpal::Device)>, cpal::DevicesError> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+    Ok(cpal::default_host()
+        .output_devices()?
+        .filter_map(|device| {
+            let name = device.name().ok()?;
+            let occurrence = occurrences.entry(name.clone()).or_default();
+            let id = match *occurrence {
+                0 => name,
+                n => format!("{name}#{n}"),
+            };
+            *occurrence += 1;
+
+            Some((id, device))
+        })
+        .collect())
+}
+
+/// Lists the output devices streams can be opened on.
+pub fn list_output_devices() -> Result<Vec<OutputDevice>> {
+    let default_name = cpal::default_host()
+        .default_output_device()
+        .and_then(|x| x.name().ok());
+    let mut default_found = false;
+
+    Ok(output_devices()
+        .context("Failed to enumerate output devices")?
+        .into_iter()
+        .map(|(id, device)| {
+            let name = device.name().unwrap_or_default();
+            let is_default = !default_found && default_name.as_ref() == Some(&name);
+            default_found |= is_default;
+
+            OutputDevice {
+                id,
+                name,
+                is_default,
+            }
+        })
+        .collect())
+}
+
 pub struct RuneOutputStream {
     mixer: Arc<DynamicMixerController<f32>>,
     _stream: cpal::Stream,
@@ -46,6 +117,9 @@ impl RuneOutputStream {
         let (mixer, _stream) =
             device.try_new_output_stream_config_with_callback(config, error_callback)?;
         _stream.play().map_err(StreamError::PlayStreamError)?;
+        if let Ok(mut output_device) = OUTPUT_DEVICE.lock() {
+            *output_device = device.name().unwrap_or_default();
+        }
         let out = Self { mixer, _stream };
         let handle = RuneOutputStreamHandle {
             mixer: Arc::downgrade(&out.mixer),
@@ -53,6 +127,25 @@ impl RuneOutputStream {
         Ok((out, handle))
     }
 
+    /// Opens a stream on the device with the given id, as listed by
+    /// [`list_output_devices`].
+    pub fn try_from_id_with_callback<E>(
+        id: &str,
+        error_callback: E,
+    ) -> Result<(Self, RuneOutputStreamHandle), StreamError>
+    where
+        E: FnMut(cpal::StreamError) + Send + 'static + Clone,
+    {
+        let device = output_devices()
+            .map_err(|_| StreamError::NoDevice)?
+            .into_iter()
+            .find(|(x, _)| x == id)
+            .map(|(_, device)| device)
+            .ok_or(StreamError::NoDevice)?;
+
+        Self::try_from_device_with_callback(&device, error_callback)
+    }
+
     pub fn try_default_with_callback<E>(
         error_callback: E,
     ) -> Result<(Self, RuneOutputStreamHandle), StreamError>
@@ -4,11 +4,12 @@ use std::time::Duration;
 use std::{fmt, thread};
 
 use log::{debug, error};
-use simple_channel::{SimpleChannel, SimpleReceiver, SimpleSender};
+use simple_channel::{Overflow, SimpleChannel, SimpleReceiver, SimpleSender};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
 use crate::internal::{InternalLog, PlaybackMode, PlayerCommand, PlayerEvent, PlayerInternal};
+use crate::loudness::{LoudnessNormalization, TrackLoudness};
 use crate::strategies::AddMode;
 
 #[derive(Debug, Clone)]
@@ -22,6 +23,9 @@ pub struct PlayerStatus {
     pub playback_mode: PlaybackMode,
     pub ready: bool,
     pub volume: f32,
+    /// The chosen output device while it can't be opened and the default
+    /// one plays instead
+    pub unavailable_output_device: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -101,11 +105,28 @@ pub trait Playable: Send {
     fn add_to_playlist(&self, tracks: Vec<(PlayingItem, PathBuf)>, mode: AddMode);
     fn remove_from_playlist(&self, index: usize);
     fn clear_playlist(&self);
+    /// Points the playlist items to the new paths of files which moved.
+    fn relocate_playlist_items(&self, paths: Vec<(PlayingItem, PathBuf)>);
+    /// Remembers the loudness of tracks, sent before they are added to the
+    /// playlist so their gain is known when they start.
+    fn set_track_loudness(&self, loudness: Vec<(PlayingItem, TrackLoudness)>);
     fn move_playlist_item(&self, old_index: usize, new_index: usize);
     fn set_playback_mode(&mut self, mode: PlaybackMode);
     fn set_volume(&mut self, volume: f32);
     fn set_realtime_fft_enabled(&mut self, enabled: bool);
     fn set_adaptive_switching_enabled(&mut self, enabled: bool);
+    /// Splices the next track right after the current one, without silence
+    /// in between.
+    fn set_gapless_enabled(&mut self, enabled: bool);
+    /// Fades the outgoing track out while the next one fades in, over
+    /// `duration`; zero turns crossfading off.
+    fn set_crossfade(&mut self, duration: Duration);
+    /// Plays every track at the same loudness, `None` turning it off; it
+    /// applies from the next track on.
+    fn set_loudness_normalization(&mut self, normalization: Option<LoudnessNormalization>);
+    /// Moves playback to the output device with the given id, `None` for
+    /// the default one, resuming the current track where it was.
+    fn set_output_device(&mut self, device: Option<String>);
     fn terminate(&self);
     fn get_status(&self) -> PlayerStatus;
     fn get_playlist(&self) -> Vec<PlayingItem>;
@@ -143,17 +164,20 @@ impl Player {
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
         // Create an unbounded channel for receiving events
         let (event_sender, mut event_receiver) = mpsc::unbounded_channel();
+        // Updates superseding each other drop the oldest ones when the queue is
+        // full, the event thread waits for room for one-off messages
         // Create a broadcast channel for status updates
-        let (status_sender, _) = SimpleChannel::channel(16);
+        let (status_sender, _) = SimpleChannel::channel(16, Overflow::DropOldest);
         // Create a broadcast channel for played through update
-        let (played_through_sender, _) = SimpleChannel::channel(16);
+        let (played_through_sender, _) = SimpleChannel::channel(16, Overflow::Wait);
         // Create a broadcast channel for playlist updates
-        let (playlist_sender, _) = SimpleChannel::channel(16);
-        // Create a broadcast channel for realtime FFT updates
-        let (realtime_fft_sender, _) = SimpleChannel::channel(32);
+        let (playlist_sender, _) = SimpleChannel::channel(16, Overflow::DropOldest);
+        // Create a channel for realtime FFT updates, only the GUI consumes
+        // them so frames aren't cloned for each subscriber
+        let (realtime_fft_sender, _) = SimpleChannel::single(32, Overflow::DropOldest);
         // Create a broadcast channel player crash report
-        let (crash_sender, _) = SimpleChannel::channel(16);
-        let (log_sender, _) = SimpleChannel::channel(16);
+        let (crash_sender, _) = SimpleChannel::channel(16, Overflow::Wait);
+        let (log_sender, _) = SimpleChannel::channel(16, Overflow::Wait);
 
         // Create a cancellation token
         let cancellation_token = cancellation_token.unwrap_or_default();
@@ -169,6 +193,7 @@ impl Player {
             playlist: Vec::new(),
             ready: false,
             volume: 1.0,
+            unavailable_output_device: None,
         }));
 
         let commands = Arc::new(Mutex::new(cmd_tx.clone()));
@@ -201,8 +226,11 @@ impl Player {
             if let Err(e) = runtime.block_on(internal.run()) {
                 error!("PlayerInternal runtime error: {e:?}");
 
-                crash_sender.send(format!("{e:#?}"));
+                if crash_sender.send(format!("{e:#?}")).is_err() {
+                    debug!("No one receives crash reports");
+                }
             }
+            crash_sender.close();
         });
 
         // Start a new thread to handle events and update the status
@@ -295,7 +323,9 @@ impl Player {
                         status.index = Some(index);
                         status.path = Some(path);
                         status.playback_mode = playback_mode;
-                        played_through_sender.send(item);
+                        if played_through_sender.send(item).is_err() {
+                            debug!("No one receives played through updates");
+                        }
                     }
                     PlayerEvent::Error {
                         item,
@@ -308,22 +338,35 @@ impl Player {
                     PlayerEvent::PlaylistUpdated(playlist) => {
                         status.playlist = playlist.clone();
                         debug!("Sending playlist status");
-                        playlist_sender_clone.send(PlaylistStatus {
+                        let _ = playlist_sender_clone.send_lossy_latest(PlaylistStatus {
                             items: playlist.clone(),
                         });
                     }
                     PlayerEvent::RealtimeFFT(data) => {
-                        realtime_fft_sender_clone.send(data);
+                        let _ = realtime_fft_sender_clone.send_lossy_latest(data);
                     }
                     PlayerEvent::VolumeUpdate(value) => {
                         status.volume = value;
                     }
                     PlayerEvent::Log(log) => {
-                        log_sender.send(log);
+                        if log_sender.send(log).is_err() {
+                            debug!("No one receives player logs");
+                        }
+                    }
+                    PlayerEvent::OutputDeviceUnavailable(device) => {
+                        status.unavailable_output_device = device;
                     }
                 }
-                status_sender_clone.send(status.clone());
+                let _ = status_sender_clone.send_lossy_latest(status.clone());
             }
+
+            // The internal player is gone, receivers stop once they got the
+            // last updates
+            status_sender_clone.close();
+            playlist_sender_clone.close();
+            realtime_fft_sender_clone.close();
+            played_through_sender.close();
+            log_sender.close();
         });
 
         player
@@ -387,6 +430,14 @@ impl Playable for Player {
         self.command(PlayerCommand::ClearPlaylist);
     }
 
+    fn relocate_playlist_items(&self, paths: Vec<(PlayingItem, PathBuf)>) {
+        self.command(PlayerCommand::RelocatePlaylistItems { paths });
+    }
+
+    fn set_track_loudness(&self, loudness: Vec<(PlayingItem, TrackLoudness)>) {
+        self.command(PlayerCommand::SetTrackLoudness(loudness));
+    }
+
     fn move_playlist_item(&self, old_index: usize, new_index: usize) {
         self.command(PlayerCommand::MovePlayListItem {
             old_index,
@@ -410,6 +461,22 @@ impl Playable for Player {
         self.command(PlayerCommand::SetAdaptiveSwitchingEnabled(enabled));
     }
 
+    fn set_gapless_enabled(&mut self, enabled: bool) {
+        self.command(PlayerCommand::SetGaplessEnabled(enabled));
+    }
+
+    fn set_crossfade(&mut self, duration: Duration) {
+        self.command(PlayerCommand::SetCrossfade(duration));
+    }
+
+    fn set_loudness_normalization(&mut self, normalization: Option<LoudnessNormalization>) {
+        self.command(PlayerCommand::SetLoudnessNormalization(normalization));
+    }
+
+    fn set_output_device(&mut self, device: Option<String>) {
+        self.command(PlayerCommand::SetOutputDevice(device));
+    }
+
     fn terminate(&self) {
         self.cancellation_token.cancel();
     }
@@ -461,11 +528,17 @@ impl Playable for MockPlayer {
     fn add_to_playlist(&self, _tracks: Vec<(PlayingItem, PathBuf)>, _mode: AddMode) {}
     fn remove_from_playlist(&self, _index: usize) {}
     fn clear_playlist(&self) {}
+    fn relocate_playlist_items(&self, _paths: Vec<(PlayingItem, PathBuf)>) {}
+    fn set_track_loudness(&self, _loudness: Vec<(PlayingItem, TrackLoudness)>) {}
     fn move_playlist_item(&self, _old_index: usize, _new_index: usize) {}
     fn set_playback_mode(&mut self, _mode: PlaybackMode) {}
     fn set_volume(&mut self, _volume: f32) {}
     fn set_realtime_fft_enabled(&mut self, _enabled: bool) {}
     fn set_adaptive_switching_enabled(&mut self, _enabled: bool) {}
+    fn set_gapless_enabled(&mut self, _enabled: bool) {}
+    fn set_crossfade(&mut self, _duration: Duration) {}
+    fn set_loudness_normalization(&mut self, _normalization: Option<LoudnessNormalization>) {}
+    fn set_output_device(&mut self, _device: Option<String>) {}
     fn terminate(&self) {}
     fn get_status(&self) -> PlayerStatus {
         PlayerStatus {
@@ -478,27 +551,28 @@ impl Playable for MockPlayer {
             playback_mode: PlaybackMode::Sequential,
             ready: false,
             volume: 1.0,
+            unavailable_output_device: None,
         }
     }
     fn get_playlist(&self) -> Vec<PlayingItem> {
         Vec::new()
     }
     fn subscribe_status(&self) -> SimpleReceiver<PlayerStatus> {
-        SimpleChannel::channel(1).1
+        SimpleChannel::channel(1, Overflow::Reject).1
     }
     fn subscribe_played_through(&self) -> SimpleReceiver<PlayingItem> {
-        SimpleChannel::channel(1).1
+        SimpleChannel::channel(1, Overflow::Reject).1
     }
     fn subscribe_playlist(&self) -> SimpleReceiver<PlaylistStatus> {
-        SimpleChannel::channel(1).1
+        SimpleChannel::channel(1, Overflow::Reject).1
     }
     fn subscribe_realtime_fft(&self) -> SimpleReceiver<Vec<f32>> {
-        SimpleChannel::channel(1).1
+        SimpleChannel::channel(1, Overflow::Reject).1
     }
     fn subscribe_crash(&self) -> SimpleReceiver<String> {
-        SimpleChannel::channel(1).1
+        SimpleChannel::channel(1, Overflow::Reject).1
     }
     fn subscribe_log(&self) -> SimpleReceiver<InternalLog> {
-        SimpleChannel::channel(1).1
+        SimpleChannel::channel(1, Overflow::Reject).1
     }
 }
@@ -3,7 +3,7 @@ use std::thread;
 
 use rustfft::{FftPlanner, num_complex::Complex};
 
-use simple_channel::{SimpleChannel, SimpleReceiver, SimpleSender};
+use simple_channel::{Overflow, SimpleChannel, SimpleReceiver, SimpleSender};
 
 pub struct RealTimeFFT {
     window_size: usize,
@@ -28,7 +28,7 @@ pub fn build_nuttall_window(window_size: usize) -> Vec<f32> {
 
 impl RealTimeFFT {
     pub fn new(window_size: usize) -> Self {
-        let (fft_result_tx, _) = SimpleChannel::channel(30);
+        let (fft_result_tx, _) = SimpleChannel::single(30, Overflow::DropOldest);
         let window = vec![0.0; window_size];
         RealTimeFFT {
             window_size,
@@ -82,7 +82,8 @@ impl RealTimeFFT {
                 .unwrap();
 
             // Send the FFT result
-            fft_result_tx.send(amp_spectrum.into_iter().map(|x| x / max_value).collect());
+            let _ = fft_result_tx
+                .send_lossy_latest(amp_spectrum.into_iter().map(|x| x / max_value).collect());
         });
     }
 
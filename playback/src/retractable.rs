@@ -0,0 +1,74 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::Duration;
+
+use rodio::{Sample, Source, source::SeekError};
+
+/// A source queued in a sink which can be taken back: once retracted it
+/// ends right away, the sink moving on to whatever follows it.
+pub struct Retractable<S: Source>
+where
+    S::Item: Sample,
+{
+    inner: S,
+    retracted: Arc<AtomicBool>,
+}
+
+impl<S: Source> Retractable<S>
+where
+    S::Item: Sample,
+{
+    pub fn new(inner: S, retracted: Arc<AtomicBool>) -> Self {
+        Self { inner, retracted }
+    }
+
+    fn is_retracted(&self) -> bool {
+        self.retracted.load(Ordering::Relaxed)
+    }
+}
+
+impl<S: Source> Iterator for Retractable<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_retracted() {
+            None
+        } else {
+            self.inner.next()
+        }
+    }
+}
+
+impl<S: Source> Source for Retractable<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.is_retracted() {
+            Some(0)
+        } else {
+            self.inner.current_frame_len()
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)
+    }
+}
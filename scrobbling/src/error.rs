@@ -0,0 +1,257 @@
+use std::{fmt, time::Duration};
+
+use anyhow::Result;
+use reqwest::{
+    Response, StatusCode,
+    header::{HeaderMap, RETRY_AFTER},
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The header ListenBrainz tells when its rate limit resets in.
+const RATE_LIMIT_RESET_IN: &str = "x-ratelimit-reset-in";
+
+/// Why a scrobbling service refused a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScrobblingApiError {
+    /// The session is no longer valid, the service has to be logged in to
+    /// again
+    Authentication(String),
+    /// Too many requests were made, `retry_after` is the wait the service
+    /// asked for, if any
+    RateLimited {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    /// The service failed, the request can be made again later
+    Transient(String),
+    /// The request was refused, making it again won't help
+    Rejected(String),
+}
+
+impl fmt::Display for ScrobblingApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScrobblingApiError::Authentication(message) => {
+                write!(f, "Authentication required: {message}")
+            }
+            ScrobblingApiError::RateLimited { message, .. } => write!(f, "Rate limited: {message}"),
+            ScrobblingApiError::Transient(message) => write!(f, "Service unavailable: {message}"),
+            ScrobblingApiError::Rejected(message) => write!(f, "Request rejected: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ScrobblingApiError {}
+
+impl ScrobblingApiError {
+    /// The answer of the service behind an error returned by a client, if
+    /// the error is one.
+    pub fn of(error: &anyhow::Error) -> Option<&Self> {
+        error.downcast_ref()
+    }
+}
+
+/// The error body of the Last.fm and Libre.fm APIs.
+#[derive(Debug, Deserialize)]
+struct AudioscrobblerError {
+    error: i32,
+    message: Option<String>,
+}
+
+/// The wait a response asks for before the next request.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    [RETRY_AFTER.as_str(), RATE_LIMIT_RESET_IN]
+        .into_iter()
+        .filter_map(|name| headers.get(name)?.to_str().ok()?.trim().parse().ok())
+        .map(Duration::from_secs)
+        .next()
+}
+
+/// Classifies a failed response from its status alone.
+pub(crate) fn classify_status(
+    status: StatusCode,
+    headers: &HeaderMap,
+    message: String,
+) -> ScrobblingApiError {
+    let message = if message.trim().is_empty() {
+        status.to_string()
+    } else {
+        message.trim().to_owned()
+    };
+
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            ScrobblingApiError::Authentication(message)
+        }
+        StatusCode::TOO_MANY_REQUESTS => ScrobblingApiError::RateLimited {
+            retry_after: retry_after(headers),
+            message,
+        },
+        StatusCode::REQUEST_TIMEOUT => ScrobblingApiError::Transient(message),
+        x if x.is_server_error() => ScrobblingApiError::Transient(message),
+        _ => ScrobblingApiError::Rejected(message),
+    }
+}
+
+/// Classifies a failed response of the Last.fm and Libre.fm APIs, which
+/// tell what went wrong with an error code.
+pub(crate) fn classify_audioscrobbler(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &str,
+) -> ScrobblingApiError {
+    let Ok(error) = serde_json::from_str::<AudioscrobblerError>(body) else {
+        return classify_status(status, headers, body.to_owned());
+    };
+    let message = error
+        .message
+        .unwrap_or_else(|| format!("Error {}", error.error));
+
+    match error.error {
+        // Authentication failed, invalid session key, invalid API key,
+        // unauthorized token, suspended API key
+        4 | 9 | 10 | 14 | 26 => ScrobblingApiError::Authentication(message),
+        29 => ScrobblingApiError::RateLimited {
+            retry_after: retry_after(headers),
+            message,
+        },
+        // Operation failed, service offline, temporarily unavailable
+        8 | 11 | 16 => ScrobblingApiError::Transient(message),
+        _ => ScrobblingApiError::Rejected(message),
+    }
+}
+
+/// Classifies a failed response of the ListenBrainz API, which follows the
+/// HTTP status codes.
+pub(crate) fn classify_listenbrainz(
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &str,
+) -> ScrobblingApiError {
+    let message = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|x| x["error"].as_str().map(str::to_owned))
+        .unwrap_or_else(|| body.to_owned());
+
+    classify_status(status, headers, message)
+}
+
+/// Turns a failed response into the error it stands for, leaving the
+/// successful ones alone.
+pub(crate) async fn check_response(
+    response: Response,
+    classify: fn(StatusCode, &HeaderMap, &str) -> ScrobblingApiError,
+) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let headers = response.headers().clone();
+    let body = response.text().await?;
+
+    Err(classify(status, &headers, &body).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn headers(name: &'static str, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn invalid_sessions_require_authentication() {
+        let body = r#"{"error":9,"message":"Invalid session key - Please re-authenticate"}"#;
+        assert_eq!(
+            classify_audioscrobbler(StatusCode::FORBIDDEN, &HeaderMap::new(), body),
+            ScrobblingApiError::Authentication(
+                "Invalid session key - Please re-authenticate".to_owned()
+            )
+        );
+
+        let body = r#"{"code":401,"error":"Invalid authorization token."}"#;
+        assert_eq!(
+            classify_listenbrainz(StatusCode::UNAUTHORIZED, &HeaderMap::new(), body),
+            ScrobblingApiError::Authentication("Invalid authorization token.".to_owned())
+        );
+    }
+
+    #[test]
+    fn rate_limits_carry_the_requested_delay() {
+        let body = r#"{"error":29,"message":"Rate limit exceeded"}"#;
+        assert_eq!(
+            classify_audioscrobbler(
+                StatusCode::TOO_MANY_REQUESTS,
+                &headers("retry-after", "120"),
+                body
+            ),
+            ScrobblingApiError::RateLimited {
+                retry_after: Some(Duration::from_secs(120)),
+                message: "Rate limit exceeded".to_owned(),
+            }
+        );
+
+        let body = r#"{"code":429,"error":"Too many requests"}"#;
+        assert_eq!(
+            classify_listenbrainz(
+                StatusCode::TOO_MANY_REQUESTS,
+                &headers(RATE_LIMIT_RESET_IN, "7"),
+                body
+            ),
+            ScrobblingApiError::RateLimited {
+                retry_after: Some(Duration::from_secs(7)),
+                message: "Too many requests".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            classify_listenbrainz(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), ""),
+            ScrobblingApiError::RateLimited {
+                retry_after: None,
+                message: "429 Too Many Requests".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn server_failures_are_transient() {
+        assert_eq!(
+            classify_audioscrobbler(StatusCode::SERVICE_UNAVAILABLE, &HeaderMap::new(), ""),
+            ScrobblingApiError::Transient("503 Service Unavailable".to_owned())
+        );
+
+        let body =
+            r#"{"error":16,"message":"There was a temporary error processing your request."}"#;
+        assert!(matches!(
+            classify_audioscrobbler(StatusCode::INTERNAL_SERVER_ERROR, &HeaderMap::new(), body),
+            ScrobblingApiError::Transient(_)
+        ));
+
+        assert!(matches!(
+            classify_listenbrainz(StatusCode::BAD_GATEWAY, &HeaderMap::new(), "<html></html>"),
+            ScrobblingApiError::Transient(_)
+        ));
+    }
+
+    #[test]
+    fn invalid_requests_are_rejected() {
+        let body = r#"{"error":6,"message":"Invalid parameters"}"#;
+        assert_eq!(
+            classify_audioscrobbler(StatusCode::BAD_REQUEST, &HeaderMap::new(), body),
+            ScrobblingApiError::Rejected("Invalid parameters".to_owned())
+        );
+
+        let body = r#"{"code":400,"error":"JSON document may only contain listens"}"#;
+        assert!(matches!(
+            classify_listenbrainz(StatusCode::BAD_REQUEST, &HeaderMap::new(), body),
+            ScrobblingApiError::Rejected(_)
+        ));
+    }
+}
@@ -0,0 +1,11 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{ScrobblingTrack, manager::ScrobblingService};
+
+/// Learns which services accepted the scrobbles, whether they were
+/// submitted right away or from the queue.
+#[async_trait]
+pub trait ScrobbleHistory: Send + Sync {
+    async fn accepted(&self, service: &ScrobblingService, track: &ScrobblingTrack) -> Result<()>;
+}
@@ -1,12 +1,103 @@
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, UNIX_EPOCH};
 use std::{collections::HashMap, time::SystemTime};
 
 use anyhow::{Result, bail};
 use async_trait::async_trait;
 use md5;
 use reqwest::{Client, Response};
+use serde::Deserialize;
 
-use crate::{AuthResponse, ScrobblingClient, ScrobblingTrack};
+use crate::{
+    AuthResponse, ScrobblingClient, ScrobblingTrack, batch_scrobble_params,
+    error::{check_response, classify_audioscrobbler},
+    http_client,
+};
+
+/// Loved tracks fetched in one request, the most Last.fm returns.
+const LOVED_TRACKS_PAGE_SIZE: u32 = 1000;
+
+/// A track the user loved on Last.fm.
+#[derive(Debug, Clone)]
+pub struct LovedTrack {
+    pub artist: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct LovedTracksPage {
+    pub tracks: Vec<LovedTrack>,
+    pub total_pages: u32,
+}
+
+/// Last.fm returns a lone item instead of a list of one.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+impl<T> Default for OneOrMany<T> {
+    fn default() -> Self {
+        OneOrMany::Many(Vec::new())
+    }
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::Many(x) => x,
+            OneOrMany::One(x) => vec![x],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserInfoResponse {
+    user: Named,
+}
+
+#[derive(Debug, Deserialize)]
+struct LovedTracksResponse {
+    lovedtracks: LovedTracksBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct LovedTracksBody {
+    #[serde(default)]
+    track: OneOrMany<LovedTrackItem>,
+    #[serde(rename = "@attr")]
+    attr: PageAttr,
+}
+
+#[derive(Debug, Deserialize)]
+struct LovedTrackItem {
+    name: String,
+    artist: Named,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageAttr {
+    #[serde(rename = "totalPages")]
+    total_pages: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackInfoResponse {
+    track: TrackInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackInfo {
+    /// In milliseconds, 0 when unknown
+    #[serde(default)]
+    duration: Option<String>,
+}
 
 #[derive(Clone)]
 pub struct LastFmClient {
@@ -19,7 +110,7 @@ pub struct LastFmClient {
 
 impl LastFmClient {
     pub fn new(api_key: String, api_secret: String) -> Result<Self> {
-        let client = Client::builder().build()?;
+        let client = http_client()?;
         Ok(LastFmClient {
             api_key,
             api_secret,
@@ -42,6 +133,124 @@ impl LastFmClient {
 
         format!("{:x}", md5::compute(sig_str.as_bytes()))
     }
+
+    /// Marks a track as loved, or no longer loved.
+    pub async fn set_loved(&self, track: &ScrobblingTrack, loved: bool) -> Result<Response> {
+        let Some(session_key) = &self.session_key else {
+            bail!("Not authenticated");
+        };
+
+        let method = if loved { "track.love" } else { "track.unlove" };
+
+        let mut params = HashMap::new();
+        params.insert("method".to_string(), method.to_string());
+        params.insert("artist".to_string(), track.artist.clone());
+        params.insert("track".to_string(), track.track.clone());
+        params.insert("api_key".to_string(), self.api_key.clone());
+        params.insert("sk".to_string(), session_key.clone());
+
+        let api_sig = self.generate_signature(&mut params);
+        params.insert("api_sig".to_string(), api_sig);
+
+        let response = self
+            .client
+            .post(&self.base_url)
+            .form(&params)
+            .query(&[("format", "json")])
+            .send()
+            .await?;
+
+        check_response(response, classify_audioscrobbler).await
+    }
+
+    /// The name of the user logged in to.
+    pub async fn username(&self) -> Result<String> {
+        let Some(session_key) = &self.session_key else {
+            bail!("Not authenticated");
+        };
+
+        let mut params = HashMap::new();
+        params.insert("method".to_string(), "user.getInfo".to_string());
+        params.insert("api_key".to_string(), self.api_key.clone());
+        params.insert("sk".to_string(), session_key.clone());
+
+        let api_sig = self.generate_signature(&mut params);
+        params.insert("api_sig".to_string(), api_sig);
+
+        let response = self
+            .client
+            .post(&self.base_url)
+            .form(&params)
+            .query(&[("format", "json")])
+            .send()
+            .await?;
+
+        let response = check_response(response, classify_audioscrobbler).await?;
+        let info: UserInfoResponse = response.json().await?;
+
+        Ok(info.user.name)
+    }
+
+    /// A page of the tracks a user loved, from 1, most recently loved first.
+    pub async fn loved_tracks(&self, username: &str, page: u32) -> Result<LovedTracksPage> {
+        let response = self
+            .client
+            .get(&self.base_url)
+            .query(&[
+                ("method", "user.getLovedTracks"),
+                ("user", username),
+                ("api_key", &self.api_key),
+                ("page", &page.to_string()),
+                ("limit", &LOVED_TRACKS_PAGE_SIZE.to_string()),
+                ("format", "json"),
+            ])
+            .send()
+            .await?;
+
+        let response = check_response(response, classify_audioscrobbler).await?;
+        let loved: LovedTracksResponse = response.json().await?;
+
+        Ok(LovedTracksPage {
+            tracks: loved
+                .lovedtracks
+                .track
+                .into_vec()
+                .into_iter()
+                .map(|x| LovedTrack {
+                    artist: x.artist.name,
+                    title: x.name,
+                })
+                .collect(),
+            total_pages: loved.lovedtracks.attr.total_pages.parse().unwrap_or(0),
+        })
+    }
+
+    /// The length of a track as Last.fm knows it, `None` when unknown.
+    pub async fn track_duration(&self, artist: &str, title: &str) -> Result<Option<Duration>> {
+        let response = self
+            .client
+            .get(&self.base_url)
+            .query(&[
+                ("method", "track.getInfo"),
+                ("artist", artist),
+                ("track", title),
+                ("api_key", &self.api_key),
+                ("autocorrect", "0"),
+                ("format", "json"),
+            ])
+            .send()
+            .await?;
+
+        let response = check_response(response, classify_audioscrobbler).await?;
+        let info: TrackInfoResponse = response.json().await?;
+
+        Ok(info
+            .track
+            .duration
+            .and_then(|x| x.parse::<u64>().ok())
+            .filter(|x| *x > 0)
+            .map(Duration::from_millis))
+    }
 }
 
 #[async_trait]
@@ -121,7 +330,7 @@ impl ScrobblingClient for LastFmClient {
             .send()
             .await?;
 
-        Ok(response)
+        check_response(response, classify_audioscrobbler).await
     }
 
     async fn scrobble(&self, track: &ScrobblingTrack) -> Result<Response> {
@@ -162,7 +371,31 @@ impl ScrobblingClient for LastFmClient {
             .send()
             .await?;
 
-        Ok(response)
+        check_response(response, classify_audioscrobbler).await
+    }
+
+    async fn scrobble_batch(&self, tracks: &[ScrobblingTrack]) -> Result<Response> {
+        let Some(session_key) = &self.session_key else {
+            bail!("Not authenticated");
+        };
+
+        let mut params = batch_scrobble_params(tracks);
+        params.insert("method".to_string(), "track.scrobble".to_string());
+        params.insert("api_key".to_string(), self.api_key.clone());
+        params.insert("sk".to_string(), session_key.clone());
+
+        let api_sig = self.generate_signature(&mut params);
+        params.insert("api_sig".to_string(), api_sig);
+
+        let response = self
+            .client
+            .post(&self.base_url)
+            .form(&params)
+            .query(&[("format", "json")])
+            .send()
+            .await?;
+
+        check_response(response, classify_audioscrobbler).await
     }
 
     fn session_key(&self) -> Option<&str> {
@@ -1,11 +1,17 @@
+pub mod error;
+pub mod history;
 pub mod last_fm;
 pub mod libre_fm;
 pub mod listen_brainz;
 pub mod manager;
+pub mod queue;
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use reqwest::Response;
+use reqwest::{Client, Proxy, Response};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
@@ -20,7 +26,7 @@ struct SessionInfo {
     key: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScrobblingTrack {
     pub artist: String,
     pub track: String,
@@ -30,10 +36,58 @@ pub struct ScrobblingTrack {
     pub timestamp: Option<u64>,
 }
 
+impl ScrobblingTrack {
+    /// When the track was listened to, now if unknown.
+    pub fn listened_at(&self) -> u64 {
+        self.timestamp.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        })
+    }
+}
+
+/// The parameters of a `track.scrobble` call submitting several tracks at
+/// once, as Last.fm and Libre.fm take them.
+fn batch_scrobble_params(tracks: &[ScrobblingTrack]) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+
+    for (i, track) in tracks.iter().enumerate() {
+        params.insert(format!("artist[{i}]"), track.artist.clone());
+        params.insert(format!("track[{i}]"), track.track.clone());
+        params.insert(format!("timestamp[{i}]"), track.listened_at().to_string());
+
+        if let Some(album) = &track.album {
+            params.insert(format!("album[{i}]"), album.clone());
+        }
+        if let Some(album_artist) = &track.album_artist {
+            params.insert(format!("albumArtist[{i}]"), album_artist.clone());
+        }
+        if let Some(duration) = track.duration {
+            params.insert(format!("duration[{i}]"), duration.to_string());
+        }
+    }
+
+    params
+}
+
+/// A client going through the outbound proxy, which is looked up for each
+/// request so changes to the proxy settings apply to existing clients.
+pub(crate) fn http_client() -> Result<Client> {
+    let proxy = Proxy::custom(|url| {
+        http_request::proxy_for(url.host_str()?).map(|proxy| proxy.url())
+    });
+
+    Ok(Client::builder().proxy(proxy).build()?)
+}
+
 #[async_trait]
 pub trait ScrobblingClient: Send {
     async fn authenticate(&mut self, username: &str, password: &str) -> Result<()>;
     async fn update_now_playing(&self, track: &ScrobblingTrack) -> Result<Response>;
     async fn scrobble(&self, track: &ScrobblingTrack) -> Result<Response>;
+    /// Submits several tracks at once, keeping when each was listened to.
+    async fn scrobble_batch(&self, tracks: &[ScrobblingTrack]) -> Result<Response>;
     fn session_key(&self) -> Option<&str>;
 }
@@ -3,9 +3,52 @@ use std::{collections::HashMap, time::SystemTime};
 
 use anyhow::{Result, bail};
 use async_trait::async_trait;
-use reqwest::{Client, Response};
+use reqwest::{Client, Response, Url};
 
-use crate::{AuthResponse, ScrobblingClient, ScrobblingTrack};
+use crate::{
+    AuthResponse, ScrobblingClient, ScrobblingTrack, batch_scrobble_params,
+    error::{check_response, classify_audioscrobbler},
+    http_client,
+};
+
+/// The API of the official Libre.fm instance.
+pub const LIBRE_FM_BASE_URL: &str = "https://libre.fm/2.0/";
+
+/// Where the API of a GNU FM compatible server, such as a self-hosted one,
+/// is reached.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServiceEndpoint {
+    pub base_url: String,
+    /// Allows plain HTTP, for servers on the local network
+    pub allow_insecure: bool,
+}
+
+impl ServiceEndpoint {
+    /// The validated URL of the API, ending with a slash.
+    pub fn url(&self) -> Result<Url> {
+        let mut url = Url::parse(self.base_url.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid service URL {}: {e}", self.base_url))?;
+
+        match url.scheme() {
+            "https" => {}
+            "http" if self.allow_insecure => {}
+            "http" => {
+                bail!("The service URL must use HTTPS unless insecure connections are allowed")
+            }
+            scheme => bail!("Unsupported service URL scheme: {scheme}"),
+        }
+        if url.host_str().is_none_or(str::is_empty) {
+            bail!("The service URL has no host");
+        }
+
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+
+        Ok(url)
+    }
+}
 
 #[derive(Clone)]
 pub struct LibreFmClient {
@@ -16,11 +59,22 @@ pub struct LibreFmClient {
 
 impl LibreFmClient {
     pub fn new() -> Result<Self> {
-        let client = Client::builder().build()?;
+        let client = http_client()?;
+        Ok(LibreFmClient {
+            session_key: None,
+            client,
+            base_url: LIBRE_FM_BASE_URL.to_string(),
+        })
+    }
+
+    /// A client of a GNU FM compatible server other than Libre.fm.
+    pub fn with_endpoint(endpoint: &ServiceEndpoint) -> Result<Self> {
+        let base_url = endpoint.url()?;
+        let client = http_client()?;
         Ok(LibreFmClient {
             session_key: None,
             client,
-            base_url: "https://libre.fm/2.0/".to_string(),
+            base_url: base_url.to_string(),
         })
     }
 }
@@ -106,7 +160,7 @@ impl ScrobblingClient for LibreFmClient {
             .send()
             .await?;
 
-        Ok(response)
+        check_response(response, classify_audioscrobbler).await
     }
 
     async fn scrobble(&self, track: &ScrobblingTrack) -> Result<Response> {
@@ -144,10 +198,70 @@ impl ScrobblingClient for LibreFmClient {
             .send()
             .await?;
 
-        Ok(response)
+        check_response(response, classify_audioscrobbler).await
+    }
+
+    async fn scrobble_batch(&self, tracks: &[ScrobblingTrack]) -> Result<Response> {
+        let Some(session_key) = &self.session_key else {
+            bail!("Not authenticated");
+        };
+
+        let mut params = batch_scrobble_params(tracks);
+        params.insert("method".to_string(), "track.scrobble".to_string());
+        params.insert("api_key".to_string(), "0".repeat(32));
+        params.insert("sk".to_string(), session_key.clone());
+
+        let response = self
+            .client
+            .post(&self.base_url)
+            .form(&params)
+            .query(&[("format", "json")])
+            .send()
+            .await?;
+
+        check_response(response, classify_audioscrobbler).await
     }
 
     fn session_key(&self) -> Option<&str> {
         self.session_key.as_deref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(base_url: &str, allow_insecure: bool) -> ServiceEndpoint {
+        ServiceEndpoint {
+            base_url: base_url.to_owned(),
+            allow_insecure,
+        }
+    }
+
+    #[test]
+    fn https_endpoints_end_with_a_slash() {
+        let url = endpoint("https://gnufm.example.org/2.0", false)
+            .url()
+            .unwrap();
+        assert_eq!(url.as_str(), "https://gnufm.example.org/2.0/");
+
+        let url = endpoint(LIBRE_FM_BASE_URL, false).url().unwrap();
+        assert_eq!(url.as_str(), LIBRE_FM_BASE_URL);
+    }
+
+    #[test]
+    fn plain_http_must_be_allowed() {
+        assert!(endpoint("http://192.168.1.10/2.0/", false).url().is_err());
+        assert!(endpoint("http://192.168.1.10/2.0/", true).url().is_ok());
+    }
+
+    #[test]
+    fn invalid_endpoints_are_refused() {
+        assert!(endpoint("gnufm.example.org", true).url().is_err());
+        assert!(
+            endpoint("ftp://gnufm.example.org/2.0/", true)
+                .url()
+                .is_err()
+        );
+    }
+}
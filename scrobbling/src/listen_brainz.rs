@@ -7,7 +7,11 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use crate::{ScrobblingClient, ScrobblingTrack};
+use crate::{
+    ScrobblingClient, ScrobblingTrack,
+    error::{check_response, classify_listenbrainz},
+    http_client,
+};
 
 impl From<&ScrobblingTrack> for Value {
     fn from(track: &ScrobblingTrack) -> Self {
@@ -50,7 +54,7 @@ pub struct ListenBrainzClient {
 
 impl ListenBrainzClient {
     pub fn new() -> Result<Self> {
-        let client = Client::builder().build()?;
+        let client = http_client()?;
         Ok(ListenBrainzClient {
             client,
             base_url: "https://api.listenbrainz.org".to_string(),
@@ -75,11 +79,7 @@ impl ListenBrainzClient {
                 .send()
                 .await?;
 
-            if response.status().is_success() {
-                Ok(response)
-            } else {
-                bail!("Request failed: {:?}", response.text().await?)
-            }
+            check_response(response, classify_listenbrainz).await
         } else {
             bail!("Client is not authenticated.")
         }
@@ -152,6 +152,28 @@ impl ScrobblingClient for ListenBrainzClient {
         self.post_request("1/submit-listens", &body).await
     }
 
+    async fn scrobble_batch(&self, tracks: &[ScrobblingTrack]) -> Result<Response> {
+        let payload = tracks
+            .iter()
+            .map(|track| {
+                let mut listen = Map::new();
+                listen.insert(
+                    "listened_at".to_string(),
+                    Value::Number(track.listened_at().into()),
+                );
+                listen.insert("track_metadata".to_string(), track.into());
+                Value::Object(listen)
+            })
+            .collect();
+
+        // Listens submitted late are imports
+        let mut body = HashMap::new();
+        body.insert("listen_type", Value::String("import".to_string()));
+        body.insert("payload", Value::Array(payload));
+
+        self.post_request("1/submit-listens", &body).await
+    }
+
     fn session_key(&self) -> Option<&str> {
         self.session_key.as_deref()
     }
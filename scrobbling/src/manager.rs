@@ -1,31 +1,86 @@
-use std::{collections::VecDeque, fmt, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::Future,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use async_trait::async_trait;
 use log::{error, info, warn};
-use simple_channel::{SimpleChannel, SimpleReceiver, SimpleSender};
-use tokio::{sync::Mutex, time::sleep};
+use reqwest::Response;
+use simple_channel::{Overflow, SimpleChannel, SimpleReceiver, SimpleSender};
+use tokio::{
+    sync::{Mutex, Notify},
+    time::{Instant, sleep, sleep_until},
+};
 
 use crate::{
-    ScrobblingClient, ScrobblingTrack, last_fm::LastFmClient, libre_fm::LibreFmClient,
+    ScrobblingClient, ScrobblingTrack,
+    error::ScrobblingApiError,
+    history::ScrobbleHistory,
+    last_fm::LastFmClient,
+    libre_fm::{LibreFmClient, ServiceEndpoint},
     listen_brainz::ListenBrainzClient,
+    queue::ScrobbleQueue,
 };
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How often the queued scrobbles are submitted while the services can be
+/// reached.
+const QUEUE_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+/// The longest wait between two submissions of the queue, the wait doubling
+/// every time a service fails.
+const MAX_QUEUE_FLUSH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Scrobbles submitted in one request, the most Last.fm accepts.
+const QUEUE_BATCH_SIZE: usize = 50;
+/// Queued scrobbles refused this many times by their service are dropped.
+const MAX_QUEUE_ATTEMPTS: u32 = 10;
+/// Rate limits asking for a longer wait leave the scrobble to the queue
+/// rather than holding the request.
+const MAX_INLINE_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The prefix of the GNU FM compatible servers other than Libre.fm, followed
+/// by their instance id.
+const GNU_FM_PREFIX: &str = "GnuFm:";
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ScrobblingService {
     LastFm,
     LibreFm,
+    /// A GNU FM compatible server, such as a self-hosted one, by its instance
+    /// id. Any number of them can be logged in to at once.
+    GnuFm(String),
     ListenBrainz,
 }
 
+impl ScrobblingService {
+    /// The services listed whether they are logged in to or not.
+    pub const BUILT_IN: [ScrobblingService; 3] = [
+        ScrobblingService::LastFm,
+        ScrobblingService::LibreFm,
+        ScrobblingService::ListenBrainz,
+    ];
+
+    /// Whether the service speaks the Libre.fm API, and so can be reached at
+    /// another URL.
+    pub fn accepts_endpoint(&self) -> bool {
+        matches!(
+            self,
+            ScrobblingService::LibreFm | ScrobblingService::GnuFm(_)
+        )
+    }
+}
+
 impl fmt::Display for ScrobblingService {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s = match self {
-            ScrobblingService::LastFm => "LastFm",
-            ScrobblingService::LibreFm => "LibreFm",
-            ScrobblingService::ListenBrainz => "ListenBrainz",
-        };
-        write!(f, "{s}")
+        match self {
+            ScrobblingService::LastFm => write!(f, "LastFm"),
+            ScrobblingService::LibreFm => write!(f, "LibreFm"),
+            ScrobblingService::GnuFm(id) => write!(f, "{GNU_FM_PREFIX}{id}"),
+            ScrobblingService::ListenBrainz => write!(f, "ListenBrainz"),
+        }
     }
 }
 
@@ -37,7 +92,10 @@ impl FromStr for ScrobblingService {
             "LastFm" => Ok(ScrobblingService::LastFm),
             "LibreFm" => Ok(ScrobblingService::LibreFm),
             "ListenBrainz" => Ok(ScrobblingService::ListenBrainz),
-            _ => Err(()),
+            _ => match s.strip_prefix(GNU_FM_PREFIX) {
+                Some(id) if !id.is_empty() => Ok(ScrobblingService::GnuFm(id.to_owned())),
+                _ => Err(()),
+            },
         }
     }
 }
@@ -55,20 +113,59 @@ pub enum ActionType {
     Authenticate,
     Scrobbling,
     UpdateNowPlaying,
+    Love,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ScrobblingError {
     pub service: ScrobblingService,
     pub action: ActionType,
-    pub error: anyhow::Error,
+    /// Shared, so every subscriber gets the error
+    pub error: Arc<anyhow::Error>,
+}
+
+/// What is submitted to a service. Disabling both keeps the service logged
+/// in without sending it anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ServiceFlags {
+    pub scrobbling: bool,
+    pub now_playing: bool,
+}
+
+impl Default for ServiceFlags {
+    fn default() -> Self {
+        Self {
+            scrobbling: true,
+            now_playing: true,
+        }
+    }
+}
+
+/// How the requests to a service logged in to have been going.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServiceHealth {
+    /// The last failure, kept until a request succeeds
+    pub error_detail: Option<String>,
+    /// When the requests started failing (UNIX timestamp)
+    pub degraded_since: Option<u64>,
+    /// The service refused the session, nothing is submitted to it until
+    /// logging in again
+    pub needs_reauthentication: bool,
 }
 
-#[derive(Debug)]
+type SharedHealth = Arc<std::sync::Mutex<HashMap<ScrobblingService, ServiceHealth>>>;
+
+#[derive(Debug, Clone)]
 pub struct LoginStatus {
     pub service: ScrobblingService,
     pub is_available: bool,
     pub error_message: Option<String>,
+    /// Scrobbles waiting for the service to be reachable
+    pub queued: u64,
+    pub flags: ServiceFlags,
+    pub health: ServiceHealth,
+    /// The server reached, when not the default one
+    pub endpoint: Option<ServiceEndpoint>,
 }
 
 #[async_trait]
@@ -88,20 +185,99 @@ pub trait ScrobblingServiceManager: Send + Sync {
     fn update_now_playing_all(&mut self, track: ScrobblingTrack);
     async fn scrobble(&mut self, service: ScrobblingService, track: ScrobblingTrack);
     fn scrobble_all(&mut self, track: ScrobblingTrack);
+    /// Marks a track as loved, or no longer loved, on the services which
+    /// support it.
+    fn set_loved(&mut self, track: ScrobblingTrack, loved: bool);
+    /// The Last.fm client, when logged in to.
+    fn lastfm_client(&self) -> Option<LastFmClient>;
+    /// Sets the server a Libre.fm compatible service is reached at, applied
+    /// on the next login. `None` goes back to Libre.fm.
+    fn set_endpoint(
+        &mut self,
+        service: &ScrobblingService,
+        endpoint: Option<ServiceEndpoint>,
+    ) -> Result<()>;
     async fn logout(&mut self, service: ScrobblingService);
+    async fn set_service_flags(&mut self, service: ScrobblingService, flags: ServiceFlags);
     fn subscribe_error(&self) -> SimpleReceiver<ScrobblingError>;
     fn subscribe_login_status(&self) -> SimpleReceiver<Vec<LoginStatus>>;
     fn error_sender(&self) -> Arc<SimpleSender<ScrobblingError>>;
 }
 
-pub struct ScrobblingManager {
-    lastfm: Option<LastFmClient>,
-    librefm: Option<LibreFmClient>,
-    listenbrainz: Option<ListenBrainzClient>,
+/// The client of a service logged in to.
+#[derive(Clone)]
+enum ServiceClient {
+    LastFm(LastFmClient),
+    LibreFm(LibreFmClient),
+    ListenBrainz(ListenBrainzClient),
+}
+
+impl ServiceClient {
+    fn set_session_key(&mut self, session_key: String) {
+        match self {
+            ServiceClient::LastFm(client) => client.session_key = Some(session_key),
+            ServiceClient::LibreFm(client) => client.session_key = Some(session_key),
+            ServiceClient::ListenBrainz(client) => client.session_key = Some(session_key),
+        }
+    }
+}
 
-    lastfm_error: Option<String>,
-    librefm_error: Option<String>,
-    listenbrainz_error: Option<String>,
+#[async_trait]
+impl ScrobblingClient for ServiceClient {
+    async fn authenticate(&mut self, username: &str, password: &str) -> Result<()> {
+        match self {
+            ServiceClient::LastFm(client) => client.authenticate(username, password).await,
+            ServiceClient::LibreFm(client) => client.authenticate(username, password).await,
+            ServiceClient::ListenBrainz(client) => client.authenticate(username, password).await,
+        }
+    }
+
+    async fn update_now_playing(&self, track: &ScrobblingTrack) -> Result<Response> {
+        match self {
+            ServiceClient::LastFm(client) => client.update_now_playing(track).await,
+            ServiceClient::LibreFm(client) => client.update_now_playing(track).await,
+            ServiceClient::ListenBrainz(client) => client.update_now_playing(track).await,
+        }
+    }
+
+    async fn scrobble(&self, track: &ScrobblingTrack) -> Result<Response> {
+        match self {
+            ServiceClient::LastFm(client) => client.scrobble(track).await,
+            ServiceClient::LibreFm(client) => client.scrobble(track).await,
+            ServiceClient::ListenBrainz(client) => client.scrobble(track).await,
+        }
+    }
+
+    async fn scrobble_batch(&self, tracks: &[ScrobblingTrack]) -> Result<Response> {
+        match self {
+            ServiceClient::LastFm(client) => client.scrobble_batch(tracks).await,
+            ServiceClient::LibreFm(client) => client.scrobble_batch(tracks).await,
+            ServiceClient::ListenBrainz(client) => client.scrobble_batch(tracks).await,
+        }
+    }
+
+    fn session_key(&self) -> Option<&str> {
+        match self {
+            ServiceClient::LastFm(client) => client.session_key(),
+            ServiceClient::LibreFm(client) => client.session_key(),
+            ServiceClient::ListenBrainz(client) => client.session_key(),
+        }
+    }
+}
+
+pub struct ScrobblingManager {
+    /// The services logged in to
+    clients: HashMap<ScrobblingService, ServiceClient>,
+    /// Why logging in to a service failed
+    errors: HashMap<ScrobblingService, String>,
+    /// The servers of the Libre.fm compatible services not reached at
+    /// Libre.fm, every GNU FM instance having one
+    endpoints: HashMap<ScrobblingService, ServiceEndpoint>,
+
+    /// Services missing from the map submit everything
+    flags: HashMap<ScrobblingService, ServiceFlags>,
+    /// Shared with the requests made in the background
+    health: SharedHealth,
 
     max_retries: u32,
     retry_delay: Duration,
@@ -111,6 +287,313 @@ pub struct ScrobblingManager {
     is_authenticating: bool,
     now_playing_cache: VecDeque<ScrobblingTrack>,
     scrobble_cache: VecDeque<ScrobblingTrack>,
+
+    queue: Option<Arc<dyn ScrobbleQueue>>,
+    history: Option<Arc<dyn ScrobbleHistory>>,
+    /// Notified when a service became available, to submit its queue
+    queue_wakeup: Arc<Notify>,
+    /// Notified when the status of a service changed in the background,
+    /// to report it
+    status_changed: Arc<Notify>,
+}
+
+/// The outcome of a submission of the queue.
+#[derive(Debug, Default)]
+struct QueueFlush {
+    /// A service could not be reached or refused scrobbles
+    failed: bool,
+    /// Scrobbles left the queue or a service changed
+    changed: bool,
+    /// The longest wait a service asked for
+    retry_after: Option<Duration>,
+}
+
+/// Whether an error means the service could not be reached, rather than
+/// that it refused the request.
+fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some()
+}
+
+/// How long to wait before making a failed request again, `None` when
+/// making it again right away can't help.
+fn retry_delay_after(error: &anyhow::Error, retry_delay: Duration) -> Option<Duration> {
+    match ScrobblingApiError::of(error) {
+        None => Some(retry_delay),
+        Some(ScrobblingApiError::RateLimited { retry_after, .. }) => {
+            let delay = retry_after.unwrap_or(retry_delay);
+            (delay <= MAX_INLINE_RETRY_AFTER).then_some(delay)
+        }
+        // Scrobbles go to the queue, which backs off
+        Some(ScrobblingApiError::Transient(_)) => None,
+        Some(ScrobblingApiError::Authentication(_) | ScrobblingApiError::Rejected(_)) => None,
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Records the outcome of a request to a service. Returns whether the
+/// health of the service changed.
+fn record_outcome<T>(
+    health: &SharedHealth,
+    service: &ScrobblingService,
+    result: &Result<T>,
+) -> bool {
+    let mut health = health.lock().unwrap();
+    let previous = health.get(service).cloned().unwrap_or_default();
+
+    let current = match result {
+        Ok(_) => ServiceHealth::default(),
+        Err(e) => ServiceHealth {
+            error_detail: Some(format!("{e:#}")),
+            degraded_since: Some(previous.degraded_since.unwrap_or_else(unix_now)),
+            needs_reauthentication: previous.needs_reauthentication
+                || matches!(
+                    ScrobblingApiError::of(e),
+                    Some(ScrobblingApiError::Authentication(_))
+                ),
+        },
+    };
+
+    let changed = current != previous;
+    health.insert(service.clone(), current);
+    changed
+}
+
+/// Records the outcome of a request made in the background, having the
+/// status reported when the health of the service changed.
+fn report_outcome<T>(
+    health: &SharedHealth,
+    status_changed: &Notify,
+    service: &ScrobblingService,
+    result: &Result<T>,
+) {
+    if record_outcome(health, service, result) {
+        status_changed.notify_one();
+    }
+}
+
+/// Hands a failure to the subscribers, waiting for room in the queue
+/// rather than dropping it.
+async fn report_error(sender: &SimpleSender<ScrobblingError>, error: ScrobblingError) {
+    if let Err(e) = sender.send_async(error).await {
+        let error = e.into_inner();
+        warn!(
+            "No one received the {} error: {:#}",
+            error.service, error.error
+        );
+    }
+}
+
+/// Keeps a scrobble which could not be submitted, to submit it once the
+/// service can be reached again.
+async fn enqueue_scrobble(
+    queue: Option<&Arc<dyn ScrobbleQueue>>,
+    status_changed: &Notify,
+    service: &ScrobblingService,
+    track: &ScrobblingTrack,
+) {
+    let Some(queue) = queue else {
+        return;
+    };
+
+    match queue.push(service, track).await {
+        Ok(()) => {
+            info!("Queued scrobble for {service}");
+            status_changed.notify_one();
+        }
+        Err(e) => error!("Failed to queue scrobble for {service}: {e}"),
+    }
+}
+
+/// Keeps a love which could not be submitted, to submit it once the service
+/// can be reached again.
+async fn enqueue_love(
+    queue: Option<&Arc<dyn ScrobbleQueue>>,
+    service: &ScrobblingService,
+    track: &ScrobblingTrack,
+    loved: bool,
+) {
+    let Some(queue) = queue else {
+        return;
+    };
+
+    match queue.push_love(service, track, loved).await {
+        Ok(()) => info!("Queued love for {service}"),
+        Err(e) => error!("Failed to queue love for {service}: {e}"),
+    }
+}
+
+/// Records that a service accepted the given scrobbles.
+async fn record_accepted(
+    history: Option<&Arc<dyn ScrobbleHistory>>,
+    service: &ScrobblingService,
+    tracks: &[ScrobblingTrack],
+) {
+    let Some(history) = history else {
+        return;
+    };
+
+    for track in tracks {
+        if let Err(e) = history.accepted(service, track).await {
+            error!("Failed to record scrobble accepted by {service}: {e}");
+        }
+    }
+}
+
+/// Submits the queued scrobbles of a service in batches, until none is left
+/// or the service fails.
+async fn flush_service<T>(
+    queue: &dyn ScrobbleQueue,
+    history: Option<&Arc<dyn ScrobbleHistory>>,
+    health: &SharedHealth,
+    service: &ScrobblingService,
+    client: &T,
+    flush: &mut QueueFlush,
+) where
+    T: ScrobblingClient + Sync,
+{
+    if client.session_key().is_none() {
+        return;
+    }
+
+    loop {
+        let batch = match queue.peek(service, QUEUE_BATCH_SIZE).await {
+            Ok(x) if x.is_empty() => return,
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to read the scrobble queue of {service}: {e}");
+                return;
+            }
+        };
+        let ids: Vec<i32> = batch.iter().map(|x| x.id).collect();
+        let tracks: Vec<ScrobblingTrack> = batch.iter().map(|x| x.track.clone()).collect();
+
+        let result = client.scrobble_batch(&tracks).await;
+        flush.changed |= record_outcome(health, service, &result);
+
+        match result {
+            Ok(_) => {
+                info!("Submitted {} queued scrobbles to {service}", ids.len());
+                flush.changed = true;
+                record_accepted(history, service, &tracks).await;
+                if let Err(e) = queue.remove(&ids).await {
+                    // Stop rather than submit them again
+                    error!("Failed to remove submitted scrobbles of {service}: {e}");
+                    return;
+                }
+            }
+            Err(e) => {
+                let entries: Vec<(i32, u32)> = batch.iter().map(|x| (x.id, x.attempts)).collect();
+                handle_flush_error(queue, service, &entries, e, "scrobbles", flush).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Submits the loves queued for Last.fm one at a time, until none is left or
+/// the service fails.
+async fn flush_loves(
+    queue: &dyn ScrobbleQueue,
+    health: &SharedHealth,
+    client: &LastFmClient,
+    flush: &mut QueueFlush,
+) {
+    let service = &ScrobblingService::LastFm;
+    if client.session_key.is_none() {
+        return;
+    }
+
+    loop {
+        let loves = match queue.peek_loves(service, QUEUE_BATCH_SIZE).await {
+            Ok(x) if x.is_empty() => return,
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to read the love queue of {service}: {e}");
+                return;
+            }
+        };
+
+        for love in &loves {
+            let result = client.set_loved(&love.track, love.loved).await;
+            flush.changed |= record_outcome(health, service, &result);
+
+            if let Err(e) = result {
+                let entries = [(love.id, love.attempts)];
+                handle_flush_error(queue, service, &entries, e, "loves", flush).await;
+                return;
+            }
+            if let Err(e) = queue.remove(&[love.id]).await {
+                // Stop rather than submit them again
+                error!("Failed to remove submitted loves of {service}: {e}");
+                return;
+            }
+        }
+
+        info!("Submitted {} queued loves to {service}", loves.len());
+    }
+}
+
+/// Handles queued `entries` (ids and attempts) a service failed to take.
+/// They are kept as they are while the service can't be reached, otherwise
+/// the refusal is counted and the entries refused too many times dropped.
+async fn handle_flush_error(
+    queue: &dyn ScrobbleQueue,
+    service: &ScrobblingService,
+    entries: &[(i32, u32)],
+    e: anyhow::Error,
+    kind: &str,
+    flush: &mut QueueFlush,
+) {
+    flush.failed = true;
+    match ScrobblingApiError::of(&e) {
+        // Submitted once logged in again
+        Some(ScrobblingApiError::Authentication(_)) => {
+            error!("{service} requires logging in again: {e}");
+            return;
+        }
+        Some(ScrobblingApiError::RateLimited { retry_after, .. }) => {
+            warn!("{service} is rate limiting: {e}");
+            flush.retry_after = flush.retry_after.max(*retry_after);
+            return;
+        }
+        Some(ScrobblingApiError::Transient(_)) => {
+            warn!("{service} is unavailable: {e}");
+            return;
+        }
+        None if is_unreachable(&e) => {
+            warn!("{service} is still unreachable: {e}");
+            return;
+        }
+        _ => {}
+    }
+
+    error!("{service} refused {} queued {kind}: {e}", entries.len());
+
+    let ids: Vec<i32> = entries.iter().map(|(id, _)| *id).collect();
+    let expired: Vec<i32> = entries
+        .iter()
+        .filter(|(_, attempts)| attempts + 1 >= MAX_QUEUE_ATTEMPTS)
+        .map(|(id, _)| *id)
+        .collect();
+    let result = match queue.record_attempt(&ids).await {
+        Ok(()) if !expired.is_empty() => queue.remove(&expired).await,
+        x => x,
+    };
+    if let Err(e) = result {
+        error!("Failed to update the scrobble queue of {service}: {e}");
+    } else if !expired.is_empty() {
+        warn!(
+            "Dropped {} {kind} refused {MAX_QUEUE_ATTEMPTS} times by {service}",
+            expired.len()
+        );
+        flush.changed = true;
+    }
 }
 
 pub struct ScrobblingCredential {
@@ -119,21 +602,22 @@ pub struct ScrobblingCredential {
     pub password: String,
     pub api_key: Option<String>,
     pub api_secret: Option<String>,
+    pub flags: ServiceFlags,
+    pub endpoint: Option<ServiceEndpoint>,
 }
 
 impl ScrobblingManager {
     pub fn new(max_retries: u32, retry_delay: Duration) -> Self {
-        let (error_sender, _) = SimpleChannel::channel(32);
-        let (login_status_sender, _) = SimpleChannel::channel(32);
+        let (error_sender, _) = SimpleChannel::channel(32, Overflow::Wait);
+        let (login_status_sender, _) = SimpleChannel::channel(32, Overflow::DropOldest);
 
         Self {
-            lastfm: None,
-            librefm: None,
-            listenbrainz: None,
+            clients: HashMap::new(),
+            errors: HashMap::new(),
+            endpoints: HashMap::new(),
 
-            lastfm_error: None,
-            librefm_error: None,
-            listenbrainz_error: None,
+            flags: HashMap::new(),
+            health: Default::default(),
 
             max_retries,
             retry_delay,
@@ -143,9 +627,201 @@ impl ScrobblingManager {
             is_authenticating: false,
             now_playing_cache: VecDeque::with_capacity(1),
             scrobble_cache: VecDeque::with_capacity(48),
+
+            queue: None,
+            history: None,
+            queue_wakeup: Arc::new(Notify::new()),
+            status_changed: Arc::new(Notify::new()),
         }
     }
 
+    /// Keeps the scrobbles which can't be submitted in `queue`, and submits
+    /// them in the background until `shutdown` resolves, backing off while
+    /// the services fail.
+    pub fn start_queue<F>(
+        manager: Arc<Mutex<ScrobblingManager>>,
+        queue: Arc<dyn ScrobbleQueue>,
+        shutdown: F,
+    ) where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let (wakeup, status_changed) = {
+                let mut manager = manager.lock().await;
+                manager.queue = Some(Arc::clone(&queue));
+                manager.send_login_status().await;
+                (
+                    Arc::clone(&manager.queue_wakeup),
+                    Arc::clone(&manager.status_changed),
+                )
+            };
+            tokio::pin!(shutdown);
+
+            // What a previous session left is submitted right away
+            let mut interval = Duration::ZERO;
+            loop {
+                let deadline = Instant::now() + interval;
+                loop {
+                    tokio::select! {
+                        _ = sleep_until(deadline) => break,
+                        _ = wakeup.notified() => break,
+                        _ = status_changed.notified() => manager.lock().await.send_login_status().await,
+                        _ = &mut shutdown => {
+                            let mut manager = manager.lock().await;
+                            if manager.queue.as_ref().is_some_and(|x| Arc::ptr_eq(x, &queue)) {
+                                manager.queue = None;
+                            }
+                            return;
+                        }
+                    }
+                }
+
+                let flush = manager.lock().await.flush_queue();
+                let flush = flush.await;
+                interval = if flush.failed {
+                    (interval * 2).clamp(QUEUE_FLUSH_INTERVAL, MAX_QUEUE_FLUSH_INTERVAL)
+                } else {
+                    QUEUE_FLUSH_INTERVAL
+                };
+                if let Some(retry_after) = flush.retry_after {
+                    interval = interval.max(retry_after);
+                }
+                if flush.changed {
+                    manager.lock().await.send_login_status().await;
+                }
+            }
+        });
+    }
+
+    /// Reports the services accepting every scrobble to `history`.
+    pub fn set_history(&mut self, history: Arc<dyn ScrobbleHistory>) {
+        self.history = Some(history);
+    }
+
+    /// Submits the scrobbles queued for every service logged in to, without
+    /// holding the manager while they are sent.
+    fn flush_queue(&self) -> impl Future<Output = QueueFlush> + Send + 'static {
+        let queue = self.queue.clone();
+        let history = self.history.clone();
+        let health = Arc::clone(&self.health);
+        let clients = self.scrobbling_clients();
+
+        async move {
+            let mut flush = QueueFlush::default();
+            let Some(queue) = queue else {
+                return flush;
+            };
+
+            for (service, client) in clients {
+                flush_service(
+                    &*queue,
+                    history.as_ref(),
+                    &health,
+                    &service,
+                    &client,
+                    &mut flush,
+                )
+                .await;
+                // Loves wait while the scrobbles can't be submitted either,
+                // Last.fm coming first
+                if let ServiceClient::LastFm(client) = &client
+                    && !flush.failed
+                {
+                    flush_loves(&*queue, &health, client, &mut flush).await;
+                }
+            }
+
+            flush
+        }
+    }
+
+    fn flags(&self, service: &ScrobblingService) -> ServiceFlags {
+        self.flags.get(service).copied().unwrap_or_default()
+    }
+
+    fn health(&self, service: &ScrobblingService) -> ServiceHealth {
+        self.health
+            .lock()
+            .unwrap()
+            .get(service)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn needs_reauthentication(&self, service: &ScrobblingService) -> bool {
+        self.health(service).needs_reauthentication
+    }
+
+    /// The built-in services, Last.fm first, then the GNU FM instances set
+    /// up.
+    fn services(&self) -> Vec<ScrobblingService> {
+        let mut instances: Vec<ScrobblingService> = self
+            .endpoints
+            .keys()
+            .filter(|x| matches!(x, ScrobblingService::GnuFm(_)))
+            .cloned()
+            .collect();
+        instances.sort_by_key(|x| x.to_string());
+
+        ScrobblingService::BUILT_IN
+            .into_iter()
+            .chain(instances)
+            .collect()
+    }
+
+    fn lastfm(&self) -> Option<&LastFmClient> {
+        match self.clients.get(&ScrobblingService::LastFm) {
+            Some(ServiceClient::LastFm(client)) => Some(client),
+            _ => None,
+        }
+    }
+
+    /// The clients of the services scrobbles are submitted to.
+    fn scrobbling_clients(&self) -> Vec<(ScrobblingService, ServiceClient)> {
+        self.enabled_clients(|flags| flags.scrobbling)
+    }
+
+    /// The clients of the services logged in to whose `flags` allow a
+    /// submission, in the order of the services.
+    fn enabled_clients(
+        &self,
+        enabled: impl Fn(ServiceFlags) -> bool,
+    ) -> Vec<(ScrobblingService, ServiceClient)> {
+        self.services()
+            .into_iter()
+            .filter(|service| enabled(self.flags(service)) && !self.needs_reauthentication(service))
+            .filter_map(|service| {
+                let client = self.clients.get(&service)?.clone();
+                Some((service, client))
+            })
+            .collect()
+    }
+
+    /// The services logged in to which could not be authenticated, or
+    /// refused the session, whose scrobbles are queued until they can.
+    fn unavailable_services(&self) -> Vec<ScrobblingService> {
+        self.services()
+            .into_iter()
+            .filter(|service| {
+                let unauthenticated =
+                    !self.clients.contains_key(service) && self.errors.contains_key(service);
+                (unauthenticated || self.needs_reauthentication(service))
+                    && self.flags(service).scrobbling
+            })
+            .collect()
+    }
+
+    async fn queue_depth(&self, service: &ScrobblingService) -> u64 {
+        let Some(queue) = &self.queue else {
+            return 0;
+        };
+
+        queue.depth(service).await.unwrap_or_else(|e| {
+            error!("Failed to count the queued scrobbles of {service}: {e}");
+            0
+        })
+    }
+
     async fn process_cache(&mut self) {
         if self.is_authenticating {
             return;
@@ -176,10 +852,13 @@ impl ScrobblingManager {
                 Ok(_) => return Ok(()),
                 Err(e) => {
                     attempts += 1;
+                    let Some(delay) = retry_delay_after(&e, retry_delay) else {
+                        return Err(e);
+                    };
                     if attempts >= max_retries {
                         return Err(e);
                     }
-                    sleep(retry_delay).await;
+                    sleep(delay).await;
                 }
             }
         }
@@ -201,10 +880,13 @@ impl ScrobblingManager {
                 Ok(_) => return Ok(()),
                 Err(e) => {
                     attempts += 1;
+                    let Some(delay) = retry_delay_after(&e, retry_delay) else {
+                        return Err(e);
+                    };
                     if attempts >= max_retries {
                         return Err(e);
                     }
-                    sleep(retry_delay).await;
+                    sleep(delay).await;
                 }
             }
         }
@@ -217,23 +899,37 @@ impl ScrobblingManager {
         tokio::spawn(async move {
             for credentials in credentials_list {
                 let mut manager = manager.lock().await;
-                let result = manager
-                    .authenticate(
-                        &credentials.service,
-                        &credentials.username,
-                        &credentials.password,
-                        credentials.api_key.clone(),
-                        credentials.api_secret.clone(),
-                        true,
-                    )
+                manager
+                    .set_service_flags(credentials.service.clone(), credentials.flags)
                     .await;
+                let result = match manager
+                    .set_endpoint(&credentials.service, credentials.endpoint.clone())
+                {
+                    Ok(()) => {
+                        manager
+                            .authenticate(
+                                &credentials.service,
+                                &credentials.username,
+                                &credentials.password,
+                                credentials.api_key.clone(),
+                                credentials.api_secret.clone(),
+                                true,
+                            )
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
 
                 if let Err(e) = result {
-                    manager.error_sender().send(ScrobblingError {
-                        service: credentials.service,
-                        action: ActionType::Authenticate,
-                        error: e,
-                    });
+                    report_error(
+                        &manager.error_sender(),
+                        ScrobblingError {
+                            service: credentials.service,
+                            action: ActionType::Authenticate,
+                            error: Arc::new(e),
+                        },
+                    )
+                    .await;
                 }
             }
         });
@@ -243,25 +939,21 @@ impl ScrobblingManager {
 #[async_trait]
 impl ScrobblingServiceManager for ScrobblingManager {
     async fn send_login_status(&self) {
-        let statuses = vec![
-            LoginStatus {
-                service: ScrobblingService::LastFm,
-                is_available: self.lastfm.is_some(),
-                error_message: self.lastfm_error.clone(),
-            },
-            LoginStatus {
-                service: ScrobblingService::LibreFm,
-                is_available: self.librefm.is_some(),
-                error_message: self.librefm_error.clone(),
-            },
-            LoginStatus {
-                service: ScrobblingService::ListenBrainz,
-                is_available: self.listenbrainz.is_some(),
-                error_message: self.listenbrainz_error.clone(),
-            },
-        ];
+        let mut statuses = Vec::new();
+        for service in self.services() {
+            statuses.push(LoginStatus {
+                is_available: self.clients.contains_key(&service)
+                    && !self.needs_reauthentication(&service),
+                error_message: self.errors.get(&service).cloned(),
+                queued: self.queue_depth(&service).await,
+                flags: self.flags(&service),
+                health: self.health(&service),
+                endpoint: self.endpoints.get(&service).cloned(),
+                service,
+            });
+        }
 
-        self.login_status_sender.send(statuses);
+        let _ = self.login_status_sender.send_lossy_latest(statuses);
     }
 
     async fn authenticate(
@@ -273,6 +965,11 @@ impl ScrobblingServiceManager for ScrobblingManager {
         api_secret: Option<String>,
         enable_retry: bool,
     ) -> Result<()> {
+        let endpoint = self.endpoints.get(service).cloned();
+        if matches!(service, ScrobblingService::GnuFm(_)) && endpoint.is_none() {
+            bail!("{service} requires the URL of its server");
+        }
+
         self.is_authenticating = true;
         let mut attempts = 0;
 
@@ -287,45 +984,45 @@ impl ScrobblingServiceManager for ScrobblingManager {
                         .clone()
                         .ok_or_else(|| anyhow::anyhow!("Last.fm requires API secret"))?;
                     let mut client = LastFmClient::new(api_key, api_secret)?;
-                    client.authenticate(username, password).await.map(|_| {
-                        self.lastfm = Some(client);
-                        self.lastfm_error = None;
-                    })
+                    client
+                        .authenticate(username, password)
+                        .await
+                        .map(|_| ServiceClient::LastFm(client))
                 }
-                ScrobblingService::LibreFm => {
-                    let mut client = LibreFmClient::new()?;
-                    client.authenticate(username, password).await.map(|_| {
-                        self.librefm = Some(client);
-                        self.librefm_error = None;
-                    })
+                ScrobblingService::LibreFm | ScrobblingService::GnuFm(_) => {
+                    let mut client = match &endpoint {
+                        Some(endpoint) => LibreFmClient::with_endpoint(endpoint)?,
+                        None => LibreFmClient::new()?,
+                    };
+                    client
+                        .authenticate(username, password)
+                        .await
+                        .map(|_| ServiceClient::LibreFm(client))
                 }
                 ScrobblingService::ListenBrainz => {
                     let mut client = ListenBrainzClient::new()?;
-                    client.authenticate(username, password).await.map(|_| {
-                        self.listenbrainz = Some(client);
-                        self.listenbrainz_error = None;
-                    })
+                    client
+                        .authenticate(username, password)
+                        .await
+                        .map(|_| ServiceClient::ListenBrainz(client))
                 }
             };
 
             match result {
-                Ok(_) => {
+                Ok(client) => {
+                    self.clients.insert(service.clone(), client);
+                    self.errors.remove(service);
                     self.is_authenticating = false;
+                    self.health.lock().unwrap().remove(service);
                     self.process_cache().await;
+                    self.queue_wakeup.notify_one();
                     self.send_login_status().await;
                     info!("Authenticated to {}", { service });
                     break;
                 }
                 Err(e) => {
                     attempts += 1;
-                    let error_message = e.to_string();
-                    match service {
-                        ScrobblingService::LastFm => self.lastfm_error = Some(error_message),
-                        ScrobblingService::LibreFm => self.librefm_error = Some(error_message),
-                        ScrobblingService::ListenBrainz => {
-                            self.listenbrainz_error = Some(error_message)
-                        }
-                    }
+                    self.errors.insert(service.clone(), e.to_string());
 
                     error!("Failed to authenticate to {service}: {e}");
 
@@ -353,26 +1050,16 @@ impl ScrobblingServiceManager for ScrobblingManager {
             return;
         }
 
+        if !self.flags(service).now_playing || self.needs_reauthentication(service) {
+            return;
+        }
+
         info!("Updating now playing for {}", { service });
 
         let max_retries = self.max_retries;
         let retry_delay = self.retry_delay;
 
-        let client: Option<&mut dyn ScrobblingClient> = match service {
-            ScrobblingService::LastFm => {
-                self.lastfm.as_mut().map(|c| c as &mut dyn ScrobblingClient)
-            }
-            ScrobblingService::LibreFm => self
-                .librefm
-                .as_mut()
-                .map(|c| c as &mut dyn ScrobblingClient),
-            ScrobblingService::ListenBrainz => self
-                .listenbrainz
-                .as_mut()
-                .map(|c| c as &mut dyn ScrobblingClient),
-        };
-
-        if let Some(client) = client
+        if let Some(client) = self.clients.get_mut(service)
             && client.session_key().is_some()
         {
             let result = ScrobblingManager::retry_update_now_playing(
@@ -382,43 +1069,33 @@ impl ScrobblingServiceManager for ScrobblingManager {
                 retry_delay,
             )
             .await;
+            let health_changed = record_outcome(&self.health, service, &result);
 
             if let Err(e) = result {
                 error!("Failed to update now playing for {service}: {e}");
 
-                self.error_sender.send(ScrobblingError {
-                    service: *service,
-                    action: ActionType::UpdateNowPlaying,
-                    error: e,
-                });
+                report_error(
+                    &self.error_sender,
+                    ScrobblingError {
+                        service: service.clone(),
+                        action: ActionType::UpdateNowPlaying,
+                        error: Arc::new(e),
+                    },
+                )
+                .await;
+            }
+            if health_changed {
+                self.send_login_status().await;
             }
         }
     }
 
     fn restore_session(&mut self, service: &ScrobblingService, session_key: String) -> Result<()> {
-        match service {
-            ScrobblingService::LastFm => {
-                if let Some(client) = &mut self.lastfm {
-                    client.session_key = Some(session_key);
-                } else {
-                    return Err(anyhow::anyhow!("Last.fm client not initialized"));
-                }
-            }
-            ScrobblingService::LibreFm => {
-                if let Some(client) = &mut self.librefm {
-                    client.session_key = Some(session_key);
-                } else {
-                    return Err(anyhow::anyhow!("Libre.fm client not initialized"));
-                }
-            }
-            ScrobblingService::ListenBrainz => {
-                if let Some(client) = &mut self.listenbrainz {
-                    client.session_key = Some(session_key);
-                } else {
-                    return Err(anyhow::anyhow!("ListenBrainz client not initialized"));
-                }
-            }
-        }
+        let Some(client) = self.clients.get_mut(service) else {
+            bail!("{service} client not initialized");
+        };
+
+        client.set_session_key(session_key);
         Ok(())
     }
 
@@ -435,100 +1112,106 @@ impl ScrobblingServiceManager for ScrobblingManager {
 
         info!("Updating now playing for all services");
 
-        let lastfm = self.lastfm.clone();
-        let librefm = self.librefm.clone();
-        let listenbrainz = self.listenbrainz.clone();
+        let clients = self.enabled_clients(|flags| flags.now_playing);
         let error_sender = Arc::clone(&self.error_sender);
+        let health = Arc::clone(&self.health);
+        let status_changed = Arc::clone(&self.status_changed);
 
         tokio::spawn(async move {
-            if let Some(client) = lastfm
-                && client.session_key.is_some()
-                && let Err(e) = client.update_now_playing(&track).await
-            {
-                error_sender.send(ScrobblingError {
-                    service: ScrobblingService::LastFm,
-                    action: ActionType::UpdateNowPlaying,
-                    error: e,
-                });
-            }
+            for (service, client) in clients {
+                if client.session_key().is_none() {
+                    continue;
+                }
 
-            if let Some(client) = librefm
-                && client.session_key.is_some()
-                && let Err(e) = client.update_now_playing(&track).await
-            {
-                error_sender.send(ScrobblingError {
-                    service: ScrobblingService::LibreFm,
-                    action: ActionType::UpdateNowPlaying,
-                    error: e,
-                });
-            }
+                let result = client.update_now_playing(&track).await;
+                report_outcome(&health, &status_changed, &service, &result);
 
-            if let Some(client) = listenbrainz
-                && client.session_key.is_some()
-                && let Err(e) = client.update_now_playing(&track).await
-            {
-                error_sender.send(ScrobblingError {
-                    service: ScrobblingService::ListenBrainz,
-                    action: ActionType::UpdateNowPlaying,
-                    error: e,
-                });
+                if let Err(e) = result {
+                    report_error(
+                        &error_sender,
+                        ScrobblingError {
+                            service,
+                            action: ActionType::UpdateNowPlaying,
+                            error: Arc::new(e),
+                        },
+                    )
+                    .await;
+                }
             }
         });
     }
 
-    async fn scrobble(&mut self, service: ScrobblingService, track: ScrobblingTrack) {
+    async fn scrobble(&mut self, service: ScrobblingService, mut track: ScrobblingTrack) {
+        if !self.flags(&service).scrobbling {
+            return;
+        }
+
+        // Submitted later, the scrobble keeps the time of the listen
+        track.timestamp = Some(track.listened_at());
+
         if self.is_authenticating {
             self.scrobble_cache.push_back(track);
             if self.scrobble_cache.len() > 48 {
                 self.scrobble_cache.pop_front();
             }
 
-            info!("Caching scrobble for {}", { service });
+            info!("Caching scrobble for {}", { &service });
 
             return;
         }
 
         let max_retries = self.max_retries;
         let retry_delay = self.retry_delay;
+        let queue = self.queue.clone();
+        let status_changed = Arc::clone(&self.status_changed);
 
-        let client: Option<&mut dyn ScrobblingClient> = match service {
-            ScrobblingService::LastFm => {
-                self.lastfm.as_mut().map(|c| c as &mut dyn ScrobblingClient)
-            }
-            ScrobblingService::LibreFm => self
-                .librefm
-                .as_mut()
-                .map(|c| c as &mut dyn ScrobblingClient),
-            ScrobblingService::ListenBrainz => self
-                .listenbrainz
-                .as_mut()
-                .map(|c| c as &mut dyn ScrobblingClient),
-        };
+        if self.unavailable_services().contains(&service) {
+            enqueue_scrobble(queue.as_ref(), &status_changed, &service, &track).await;
+            return;
+        }
 
-        if let Some(client) = client {
+        if let Some(client) = self.clients.get_mut(&service) {
             if client.session_key().is_some() {
                 let result =
                     ScrobblingManager::retry_scrobble(client, &track, max_retries, retry_delay)
                         .await;
+                let health_changed = record_outcome(&self.health, &service, &result);
 
                 if let Err(e) = result {
                     error!("Failed to scrobble to {service}: {e}");
-
-                    self.error_sender.send(ScrobblingError {
-                        service,
-                        action: ActionType::Scrobbling,
-                        error: e,
-                    });
+                    enqueue_scrobble(queue.as_ref(), &status_changed, &service, &track).await;
+
+                    report_error(
+                        &self.error_sender,
+                        ScrobblingError {
+                            service,
+                            action: ActionType::Scrobbling,
+                            error: Arc::new(e),
+                        },
+                    )
+                    .await;
                 } else {
-                    info!("Scrobbled to {}", { service });
+                    info!("Scrobbled to {}", { &service });
+                    record_accepted(
+                        self.history.as_ref(),
+                        &service,
+                        std::slice::from_ref(&track),
+                    )
+                    .await;
+                }
+                if health_changed {
+                    self.send_login_status().await;
                 }
             } else {
-                warn!("Not authenticated to {}", { service });
+                warn!("Not authenticated to {}", { &service });
             }
         }
     }
 
-    fn scrobble_all(&mut self, track: ScrobblingTrack) {
+    fn scrobble_all(&mut self, mut track: ScrobblingTrack) {
+        // Submitted later, the scrobble keeps the time of the listen
+        track.timestamp = Some(track.listened_at());
+
         if self.is_authenticating {
             self.scrobble_cache.push_back(track);
             if self.scrobble_cache.len() > 48 {
@@ -538,39 +1221,26 @@ impl ScrobblingServiceManager for ScrobblingManager {
             return;
         }
 
-        let lastfm = self.lastfm.clone();
-        let librefm = self.librefm.clone();
-        let listenbrainz = self.listenbrainz.clone();
+        let clients = self.scrobbling_clients();
         let max_retries = self.max_retries;
         let retry_delay = self.retry_delay;
         let error_sender = Arc::clone(&self.error_sender);
+        let queue = self.queue.clone();
+        let history = self.history.clone();
+        let status_changed = Arc::clone(&self.status_changed);
+        let health = Arc::clone(&self.health);
+        let unavailable = self.unavailable_services();
 
         tokio::spawn(async move {
-            // Handle Last.fm
-            if let Some(mut client) = lastfm
-                && client.session_key.is_some()
-            {
-                let result = ScrobblingManager::retry_scrobble(
-                    &mut client,
-                    &track,
-                    max_retries,
-                    retry_delay,
-                )
-                .await;
+            for service in &unavailable {
+                enqueue_scrobble(queue.as_ref(), &status_changed, service, &track).await;
+            }
 
-                if let Err(e) = result {
-                    error_sender.send(ScrobblingError {
-                        service: ScrobblingService::LastFm,
-                        action: ActionType::Scrobbling,
-                        error: e,
-                    });
+            for (service, mut client) in clients {
+                if client.session_key().is_none() {
+                    continue;
                 }
-            }
 
-            // Handle Libre.fm
-            if let Some(mut client) = librefm
-                && client.session_key.is_some()
-            {
                 let result = ScrobblingManager::retry_scrobble(
                     &mut client,
                     &track,
@@ -578,56 +1248,127 @@ impl ScrobblingServiceManager for ScrobblingManager {
                     retry_delay,
                 )
                 .await;
+                report_outcome(&health, &status_changed, &service, &result);
 
                 if let Err(e) = result {
-                    error_sender.send(ScrobblingError {
-                        service: ScrobblingService::LibreFm,
-                        action: ActionType::Scrobbling,
-                        error: e,
-                    });
+                    enqueue_scrobble(queue.as_ref(), &status_changed, &service, &track).await;
+                    report_error(
+                        &error_sender,
+                        ScrobblingError {
+                            service,
+                            action: ActionType::Scrobbling,
+                            error: Arc::new(e),
+                        },
+                    )
+                    .await;
+                } else {
+                    record_accepted(history.as_ref(), &service, std::slice::from_ref(&track)).await;
                 }
             }
+        });
+    }
 
-            // Handle ListenBrainz
-            if let Some(mut client) = listenbrainz
-                && client.session_key.is_some()
-            {
-                let result = ScrobblingManager::retry_scrobble(
-                    &mut client,
-                    &track,
-                    max_retries,
-                    retry_delay,
-                )
-                .await;
+    fn set_loved(&mut self, track: ScrobblingTrack, loved: bool) {
+        let service = ScrobblingService::LastFm;
+        if !self.flags(&service).scrobbling {
+            return;
+        }
+        let Some(client) = self.lastfm().cloned() else {
+            return;
+        };
 
-                if let Err(e) = result {
-                    error_sender.send(ScrobblingError {
-                        service: ScrobblingService::ListenBrainz,
-                        action: ActionType::Scrobbling,
-                        error: e,
-                    });
+        let queue = self.queue.clone();
+        let error_sender = Arc::clone(&self.error_sender);
+        let status_changed = Arc::clone(&self.status_changed);
+        let health = Arc::clone(&self.health);
+        // Submitted once the service can be reached again
+        let unavailable = self.is_authenticating
+            || client.session_key.is_none()
+            || self.unavailable_services().contains(&service);
+
+        tokio::spawn(async move {
+            if unavailable {
+                enqueue_love(queue.as_ref(), &service, &track, loved).await;
+                return;
+            }
+
+            let result = client.set_loved(&track, loved).await;
+            report_outcome(&health, &status_changed, &service, &result);
+
+            match result {
+                Ok(_) => info!("Updated love of {} to {service}", track.track),
+                Err(e) => {
+                    enqueue_love(queue.as_ref(), &service, &track, loved).await;
+                    report_error(
+                        &error_sender,
+                        ScrobblingError {
+                            service,
+                            action: ActionType::Love,
+                            error: Arc::new(e),
+                        },
+                    )
+                    .await;
                 }
             }
         });
     }
 
+    fn lastfm_client(&self) -> Option<LastFmClient> {
+        self.lastfm()
+            .filter(|client| client.session_key.is_some())
+            .cloned()
+    }
+
+    fn set_endpoint(
+        &mut self,
+        service: &ScrobblingService,
+        endpoint: Option<ServiceEndpoint>,
+    ) -> Result<()> {
+        let Some(endpoint) = endpoint else {
+            self.endpoints.remove(service);
+            return Ok(());
+        };
+
+        if !service.accepts_endpoint() {
+            bail!("{service} can't be reached at another URL");
+        }
+        endpoint.url()?;
+
+        self.endpoints.insert(service.clone(), endpoint);
+        Ok(())
+    }
+
     async fn logout(&mut self, service: ScrobblingService) {
-        match service {
-            ScrobblingService::LastFm => {
-                self.lastfm = None;
-                self.lastfm_error = None;
-            }
-            ScrobblingService::LibreFm => {
-                self.librefm = None;
-                self.librefm_error = None;
-            }
-            ScrobblingService::ListenBrainz => {
-                self.listenbrainz = None;
-                self.listenbrainz_error = None;
-            }
+        self.clients.remove(&service);
+        self.errors.remove(&service);
+        self.endpoints.remove(&service);
+        // The instance is gone with its server
+        if matches!(service, ScrobblingService::GnuFm(_)) {
+            self.flags.remove(&service);
         }
 
-        info!("Logged out from {}", { service });
+        self.health.lock().unwrap().remove(&service);
+
+        // Scrobbles can't be submitted without logging in again
+        if let Some(queue) = &self.queue
+            && let Err(e) = queue.clear(&service).await
+        {
+            error!("Failed to clear the scrobble queue of {service}: {e}");
+        }
+
+        info!("Logged out from {}", { &service });
+        self.send_login_status().await;
+    }
+
+    async fn set_service_flags(&mut self, service: ScrobblingService, flags: ServiceFlags) {
+        info!("Set submissions to {service}: {flags:?}");
+        let previous = self.flags.insert(service, flags).unwrap_or_default();
+
+        // Scrobbles queued before the service was disabled are submitted
+        // again
+        if flags.scrobbling && !previous.scrobbling {
+            self.queue_wakeup.notify_one();
+        }
         self.send_login_status().await;
     }
 
@@ -657,8 +1398,8 @@ impl MockScrobblingManager {
 
 impl Default for MockScrobblingManager {
     fn default() -> Self {
-        let (error_sender, _) = SimpleChannel::channel(32);
-        let (login_status_sender, _) = SimpleChannel::channel(32);
+        let (error_sender, _) = SimpleChannel::channel(32, Overflow::Wait);
+        let (login_status_sender, _) = SimpleChannel::channel(32, Overflow::DropOldest);
 
         Self {
             error_sender: Arc::new(error_sender),
@@ -671,7 +1412,7 @@ impl Default for MockScrobblingManager {
 impl ScrobblingServiceManager for MockScrobblingManager {
     async fn send_login_status(&self) {
         // Mock implementation: send empty login status list
-        self.login_status_sender.send(Vec::new());
+        let _ = self.login_status_sender.send_lossy_latest(Vec::new());
     }
 
     async fn authenticate(
@@ -712,10 +1453,31 @@ impl ScrobblingServiceManager for MockScrobblingManager {
         // Mock implementation: do nothing
     }
 
+    fn set_loved(&mut self, _track: ScrobblingTrack, _loved: bool) {
+        // Mock implementation: do nothing
+    }
+
+    fn lastfm_client(&self) -> Option<LastFmClient> {
+        None
+    }
+
+    fn set_endpoint(
+        &mut self,
+        _service: &ScrobblingService,
+        _endpoint: Option<ServiceEndpoint>,
+    ) -> Result<()> {
+        // Mock implementation: always succeed
+        Ok(())
+    }
+
     async fn logout(&mut self, _service: ScrobblingService) {
         // Mock implementation: do nothing
     }
 
+    async fn set_service_flags(&mut self, _service: ScrobblingService, _flags: ServiceFlags) {
+        // Mock implementation: do nothing
+    }
+
     fn subscribe_error(&self) -> SimpleReceiver<ScrobblingError> {
         self.error_sender.subscribe()
     }
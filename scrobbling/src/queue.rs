@@ -0,0 +1,52 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{ScrobblingTrack, manager::ScrobblingService};
+
+/// A scrobble waiting for its service to be reachable again.
+#[derive(Debug, Clone)]
+pub struct QueuedScrobble {
+    pub id: i32,
+    pub track: ScrobblingTrack,
+    /// How many times the service refused it
+    pub attempts: u32,
+}
+
+/// A love, or an unlove, waiting for its service to be reachable again.
+#[derive(Debug, Clone)]
+pub struct QueuedLove {
+    pub id: i32,
+    pub track: ScrobblingTrack,
+    pub loved: bool,
+    /// How many times the service refused it
+    pub attempts: u32,
+}
+
+/// Durable storage for the scrobbles and loves which could not be submitted,
+/// so they survive until the service can be reached, even across restarts.
+#[async_trait]
+pub trait ScrobbleQueue: Send + Sync {
+    async fn push(&self, service: &ScrobblingService, track: &ScrobblingTrack) -> Result<()>;
+    /// Queues a love, replacing the one queued for the same track.
+    async fn push_love(
+        &self,
+        service: &ScrobblingService,
+        track: &ScrobblingTrack,
+        loved: bool,
+    ) -> Result<()>;
+    /// The oldest scrobbles queued for a service.
+    async fn peek(&self, service: &ScrobblingService, limit: usize) -> Result<Vec<QueuedScrobble>>;
+    /// The oldest loves queued for a service.
+    async fn peek_loves(
+        &self,
+        service: &ScrobblingService,
+        limit: usize,
+    ) -> Result<Vec<QueuedLove>>;
+    /// Removes queued scrobbles or loves.
+    async fn remove(&self, ids: &[i32]) -> Result<()>;
+    /// Records that the service refused the given scrobbles or loves.
+    async fn record_attempt(&self, ids: &[i32]) -> Result<()>;
+    async fn clear(&self, service: &ScrobblingService) -> Result<()>;
+    /// The number of scrobbles queued for a service, leaving out the loves.
+    async fn depth(&self, service: &ScrobblingService) -> Result<u64>;
+}
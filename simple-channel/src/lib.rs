@@ -1,8 +1,9 @@
-use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 
 use tokio::sync::Notify;
-use tokio::sync::broadcast::error::TryRecvError;
 
 pub struct SimpleChannel<T> {
     _queue: Arc<Mutex<VecDeque<T>>>,
@@ -10,61 +11,754 @@ pub struct SimpleChannel<T> {
     _notify: Arc<Notify>,
 }
 
-#[derive(Clone)]
-pub struct SimpleSender<T> {
-    queue: Arc<Mutex<VecDeque<T>>>,
+/// What `SimpleSender::send` does once the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Fail with `SendError::Full`, leaving the queue as it is
+    Reject,
+    /// Drop the oldest message to make room, for updates of which only the
+    /// latest matters
+    DropOldest,
+    /// Block the thread until every receiver has room, for messages which
+    /// must never be dropped. Async code should use `send_async` instead
+    Wait,
+}
+
+/// Why a message wasn't sent, the message is handed back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendError<T> {
+    /// The queue is full
+    Full(T),
+    /// Every receiver was dropped, so no one would receive the message
+    Disconnected(T),
+    /// A sender closed the channel
+    Closed(T),
+}
+
+impl<T> SendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::Full(msg) | SendError::Disconnected(msg) | SendError::Closed(msg) => msg,
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendError::Full(_) => write!(f, "channel is full"),
+            SendError::Disconnected(_) => write!(f, "channel has no receiver"),
+            SendError::Closed(_) => write!(f, "channel is closed"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for SendError<T> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// The channel was closed, or every sender dropped, and no message is
+    /// left
+    Closed,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecvError::Closed => write!(f, "channel is closed"),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+/// The messages waiting for one receiver, or for all of them in single
+/// consumer channels.
+struct Queue<T> {
+    messages: VecDeque<T>,
+    /// Messages dropped before the receiver got them
+    skipped: u64,
+}
+
+impl<T> Queue<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            messages: VecDeque::with_capacity(capacity),
+            skipped: 0,
+        }
+    }
+}
+
+struct Queues<T> {
+    queues: HashMap<usize, Queue<T>>,
+    next_id: usize,
+}
+
+impl<T> Queues<T> {
+    fn is_full(&self, capacity: usize) -> bool {
+        self.queues.values().any(|x| x.messages.len() >= capacity)
+    }
+}
+
+struct Shared<T> {
+    queues: Mutex<Queues<T>>,
     capacity: usize,
-    notify: Arc<Notify>,
+    overflow: Overflow,
+    /// Every receiver reads the same queue, messages are moved instead of
+    /// cloned for each receiver
+    single_consumer: bool,
+    /// Wakes the receivers once a message is queued
+    message: Notify,
+    /// Wakes the senders waiting for room in async code
+    room: Notify,
+    /// Wakes the senders waiting for room in blocked threads
+    room_blocking: Condvar,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    closed: AtomicBool,
+}
+
+impl<T> Shared<T> {
+    fn new(capacity: usize, overflow: Overflow, single_consumer: bool) -> Self {
+        let capacity = capacity.max(1);
+
+        Self {
+            queues: Mutex::new(Queues {
+                queues: HashMap::new(),
+                next_id: 0,
+            }),
+            capacity,
+            overflow,
+            single_consumer,
+            message: Notify::new(),
+            room: Notify::new(),
+            room_blocking: Condvar::new(),
+            senders: AtomicUsize::new(1),
+            receivers: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Queues<T>> {
+        self.queues.lock().unwrap()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.senders.load(Ordering::SeqCst) == 0
+    }
+
+    fn has_receivers(&self) -> bool {
+        self.receivers.load(Ordering::SeqCst) > 0
+    }
+
+    /// Hands the message back if it can't be sent whatever room is left.
+    fn admit(&self, msg: T) -> Result<T, SendError<T>> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(SendError::Closed(msg));
+        }
+        if !self.has_receivers() {
+            return Err(SendError::Disconnected(msg));
+        }
+        Ok(msg)
+    }
+
+    /// Adds the queue of a new receiver, which gets the messages sent from
+    /// then on.
+    fn add_receiver(self: &Arc<Self>) -> SimpleReceiver<T> {
+        let mut queues = self.lock();
+        let id = match self.single_consumer {
+            true => 0,
+            false => {
+                queues.next_id += 1;
+                queues.next_id
+            }
+        };
+        queues
+            .queues
+            .entry(id)
+            .or_insert_with(|| Queue::new(self.capacity));
+        self.receivers.fetch_add(1, Ordering::SeqCst);
+
+        SimpleReceiver {
+            shared: self.clone(),
+            id,
+        }
+    }
+
+    /// Wakes everyone waiting, for them to find out the channel changed.
+    fn wake_all(&self) {
+        // Taken so no waiter is between checking and waiting
+        let _queues = self.lock();
+        self.message.notify_waiters();
+        self.room.notify_waiters();
+        self.room_blocking.notify_all();
+    }
+}
+
+pub struct SimpleSender<T> {
+    shared: Arc<Shared<T>>,
 }
 
-#[derive(Clone)]
+/// Receives every message sent after it subscribed, unless it shares the
+/// queue of a single consumer channel.
 pub struct SimpleReceiver<T> {
-    queue: Arc<Mutex<VecDeque<T>>>,
-    notify: Arc<Notify>,
+    shared: Arc<Shared<T>>,
+    id: usize,
 }
 
 impl<T> SimpleChannel<T> {
-    pub fn channel(capacity: usize) -> (SimpleSender<T>, SimpleReceiver<T>) {
-        let queue = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
-        let notify = Arc::new(Notify::new());
-        let sender = SimpleSender {
-            queue: queue.clone(),
-            capacity,
-            notify: notify.clone(),
-        };
-        let receiver = SimpleReceiver { queue, notify };
+    /// Creates a channel queueing up to `capacity` messages, at least one,
+    /// for each receiver, which handles a full queue as `overflow` says.
+    /// Every receiver gets every message.
+    pub fn channel(capacity: usize, overflow: Overflow) -> (SimpleSender<T>, SimpleReceiver<T>) {
+        Self::with_shared(Shared::new(capacity, overflow, false))
+    }
+
+    /// Creates a channel whose receivers share a single queue, each message
+    /// going to one of them only. Messages are never cloned, for hot paths
+    /// with one consumer.
+    pub fn single(capacity: usize, overflow: Overflow) -> (SimpleSender<T>, SimpleReceiver<T>) {
+        Self::with_shared(Shared::new(capacity, overflow, true))
+    }
+
+    fn with_shared(shared: Shared<T>) -> (SimpleSender<T>, SimpleReceiver<T>) {
+        let shared = Arc::new(shared);
+        let receiver = shared.add_receiver();
+        let sender = SimpleSender { shared };
         (sender, receiver)
     }
 }
 
-impl<T> SimpleSender<T> {
-    pub fn send(&self, msg: T) {
-        let mut queue = self.queue.lock().unwrap();
-        if queue.len() == self.capacity {
-            queue.clear(); // Clear the queue if full
+impl<T: Clone> SimpleSender<T> {
+    /// Queues a message, handling a full queue as the overflow policy of the
+    /// channel says. Fails once the channel is closed or every receiver is
+    /// dropped, so producers can stop, which also stops senders waiting for
+    /// room. A full queue is one of any receiver.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        match self.shared.overflow {
+            Overflow::Reject => self.try_send(msg),
+            Overflow::DropOldest => self.send_lossy_latest(msg),
+            Overflow::Wait => self.send_blocking(msg),
+        }
+    }
+
+    /// Queues a message, dropping the oldest one of the receivers whose
+    /// queue is full, which find out from `SimpleReceiver::take_skipped`.
+    pub fn send_lossy_latest(&self, msg: T) -> Result<(), SendError<T>> {
+        let mut queues = self.shared.lock();
+        let msg = self.shared.admit(msg)?;
+        for queue in queues.queues.values_mut() {
+            if queue.messages.len() >= self.shared.capacity {
+                queue.messages.pop_front();
+                queue.skipped += 1;
+            }
         }
-        queue.push_back(msg);
-        self.notify.notify_one();
+        self.push(queues, msg);
+        Ok(())
+    }
+
+    /// Queues a message, waiting for every receiver to have room if a queue
+    /// is full.
+    pub async fn send_async(&self, mut msg: T) -> Result<(), SendError<T>> {
+        loop {
+            // Registered before checking, so no room made after is missed
+            let room = self.shared.room.notified();
+            msg = match self.try_send(msg) {
+                Err(SendError::Full(msg)) => msg,
+                result => return result,
+            };
+            room.await;
+        }
+    }
+
+    fn try_send(&self, msg: T) -> Result<(), SendError<T>> {
+        let queues = self.shared.lock();
+        let msg = self.shared.admit(msg)?;
+        if queues.is_full(self.shared.capacity) {
+            return Err(SendError::Full(msg));
+        }
+        self.push(queues, msg);
+        Ok(())
+    }
+
+    fn send_blocking(&self, mut msg: T) -> Result<(), SendError<T>> {
+        let mut queues = self.shared.lock();
+        loop {
+            msg = self.shared.admit(msg)?;
+            if !queues.is_full(self.shared.capacity) {
+                break;
+            }
+            queues = self.shared.room_blocking.wait(queues).unwrap();
+        }
+        self.push(queues, msg);
+        Ok(())
+    }
+
+    /// Queues a copy of the message for every receiver, the last one taking
+    /// the message itself.
+    fn push(&self, mut queues: MutexGuard<'_, Queues<T>>, msg: T) {
+        let mut targets = queues.queues.values_mut().peekable();
+        while let Some(queue) = targets.next() {
+            match targets.peek() {
+                Some(_) => queue.messages.push_back(msg.clone()),
+                None => {
+                    queue.messages.push_back(msg);
+                    break;
+                }
+            }
+        }
+        drop(queues);
+        self.shared.message.notify_waiters();
+    }
+}
+
+impl<T> SimpleSender<T> {
+    /// Closes the channel for every sender. Sending fails from then on, and
+    /// receivers get `RecvError::Closed` once they received what is queued.
+    pub fn close(&self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+        self.shared.wake_all();
+    }
+
+    /// Whether every receiver was dropped, for producers to skip work no one
+    /// would receive.
+    pub fn is_disconnected(&self) -> bool {
+        !self.shared.has_receivers()
     }
 
     pub fn subscribe(&self) -> SimpleReceiver<T> {
-        SimpleReceiver {
-            queue: self.queue.clone(),
-            notify: self.notify.clone(),
+        self.shared.add_receiver()
+    }
+}
+
+impl<T> Clone for SimpleSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for SimpleSender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Receivers find the channel closed once no one is left to send
+            self.shared.wake_all();
         }
     }
 }
 
 impl<T> SimpleReceiver<T> {
-    pub async fn recv(&self) -> Result<T, TryRecvError> {
+    /// Receives the next message, failing with `RecvError::Closed` once the
+    /// queue is drained and the channel closed or every sender dropped.
+    pub async fn recv(&self) -> Result<T, RecvError> {
         loop {
+            // Registered before checking, so no message sent after is missed
+            let message = self.shared.message.notified();
             {
-                let mut queue = self.queue.lock().unwrap();
-                if let Some(msg) = queue.pop_front() {
+                let mut queues = self.shared.lock();
+                let queue = queues.queues.get_mut(&self.id);
+                if let Some(msg) = queue.and_then(|x| x.messages.pop_front()) {
+                    drop(queues);
+                    // Senders wait for the room of every receiver
+                    self.shared.room.notify_waiters();
+                    self.shared.room_blocking.notify_all();
                     return Ok(msg);
                 }
+                if self.shared.is_closed() {
+                    return Err(RecvError::Closed);
+                }
+            }
+            message.await;
+        }
+    }
+
+    /// The number of messages dropped before this receiver got them since
+    /// the last call, when it lags behind a lossy channel.
+    pub fn take_skipped(&self) -> u64 {
+        let mut queues = self.shared.lock();
+        queues
+            .queues
+            .get_mut(&self.id)
+            .map(|x| std::mem::take(&mut x.skipped))
+            .unwrap_or_default()
+    }
+}
+
+/// A clone is a new subscriber, getting the messages sent from then on.
+impl<T> Clone for SimpleReceiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.add_receiver()
+    }
+}
+
+impl<T> Drop for SimpleReceiver<T> {
+    fn drop(&mut self) {
+        {
+            let mut queues = self.shared.lock();
+            let last = self.shared.receivers.fetch_sub(1, Ordering::SeqCst) == 1;
+            // A single consumer queue is dropped with its last receiver
+            if !self.shared.single_consumer || last {
+                queues.queues.remove(&self.id);
+            }
+        }
+
+        // Senders waiting for this receiver to make room go on without it,
+        // or give up once no one is left
+        self.shared.wake_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::thread;
+    use std::time::Duration;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    const SENDERS: usize = 4;
+    const MESSAGES: usize = 50;
+
+    fn queued<T: Clone>(receiver: &SimpleReceiver<T>) -> VecDeque<T> {
+        receiver.shared.lock().queues[&receiver.id].messages.clone()
+    }
+
+    /// Receives the messages of the concurrent senders, checking each sender
+    /// got its messages through in order.
+    async fn receive_all(receiver: &SimpleReceiver<(usize, usize)>) {
+        let mut next = [0; SENDERS];
+        for _ in 0..SENDERS * MESSAGES {
+            let (sender, index) = receiver.recv().await.unwrap();
+            assert_eq!(index, next[sender], "Messages of {sender} out of order");
+            next[sender] += 1;
+        }
+        assert_eq!(next, [MESSAGES; SENDERS]);
+    }
+
+    #[test]
+    fn full_queues_reject_messages() {
+        let (sender, receiver) = SimpleChannel::channel(4, Overflow::Reject);
+
+        let handles: Vec<_> = (0..SENDERS)
+            .map(|x| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    (0..MESSAGES)
+                        .filter(|i| sender.send((x, *i)).is_ok())
+                        .count()
+                })
+            })
+            .collect();
+        let accepted: usize = handles.into_iter().map(|x| x.join().unwrap()).sum();
+        assert_eq!(accepted, 4);
+
+        assert_eq!(sender.send((0, 0)), Err(SendError::Full((0, 0))));
+        assert_eq!(queued(&receiver).len(), 4, "Queued messages should be kept");
+    }
+
+    #[tokio::test]
+    async fn lossy_queues_keep_the_latest_messages() {
+        let (sender, receiver) = SimpleChannel::channel(4, Overflow::DropOldest);
+        for i in 0..10 {
+            sender.send(i).unwrap();
+        }
+        for i in 6..10 {
+            assert_eq!(receiver.recv().await.unwrap(), i);
+        }
+
+        let (sender, receiver) = SimpleChannel::channel(4, Overflow::Reject);
+        let handles: Vec<_> = (0..SENDERS)
+            .map(|x| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    for i in 0..MESSAGES {
+                        sender.send_lossy_latest((x, i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let queue = queued(&receiver);
+        assert_eq!(queue.len(), 4);
+        assert_eq!(queue.iter().collect::<HashSet<_>>().len(), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn waiting_senders_never_drop_messages() {
+        let (sender, receiver) = SimpleChannel::channel(2, Overflow::Wait);
+
+        let handles: Vec<_> = (0..SENDERS)
+            .map(|x| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    for i in 0..MESSAGES {
+                        sender.send((x, i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        receive_all(&receiver).await;
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn async_senders_wait_for_room() {
+        let (sender, receiver) = SimpleChannel::channel(2, Overflow::Reject);
+
+        let tasks: Vec<_> = (0..SENDERS)
+            .map(|x| {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    for i in 0..MESSAGES {
+                        sender.send_async((x, i)).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        receive_all(&receiver).await;
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn senders_stop_waiting_without_receivers() {
+        let (sender, receiver) = SimpleChannel::channel(1, Overflow::Wait);
+        sender.send(1).unwrap();
+
+        let blocked = thread::spawn({
+            let sender = sender.clone();
+            move || sender.send(2)
+        });
+        thread::sleep(Duration::from_millis(50));
+        drop(receiver);
+
+        assert_eq!(blocked.join().unwrap(), Err(SendError::Disconnected(2)));
+        assert_eq!(sender.send_async(3).await, Err(SendError::Disconnected(3)));
+        assert!(sender.is_disconnected());
+
+        let receiver = sender.subscribe();
+        assert!(!sender.is_disconnected());
+        assert_eq!(sender.send_lossy_latest(4), Ok(()));
+        assert_eq!(receiver.recv().await, Ok(4));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn receivers_drain_the_queue_once_senders_are_gone() {
+        let (sender, receiver) = SimpleChannel::channel(4, Overflow::Reject);
+        let other = sender.clone();
+        sender.send(1).unwrap();
+        other.send(2).unwrap();
+        drop(sender);
+        drop(other);
+
+        assert_eq!(receiver.recv().await, Ok(1));
+        assert_eq!(receiver.recv().await, Ok(2));
+        assert_eq!(receiver.recv().await, Err(RecvError::Closed));
+
+        // Receivers already waiting stop too
+        let (sender, receiver) = SimpleChannel::<usize>::channel(4, Overflow::Reject);
+        let waiting = tokio::spawn(async move { receiver.recv().await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(sender);
+
+        assert_eq!(waiting.await.unwrap(), Err(RecvError::Closed));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn closed_channels_refuse_messages() {
+        let (sender, receiver) = SimpleChannel::channel(1, Overflow::Wait);
+        sender.send(1).unwrap();
+
+        let blocked = thread::spawn({
+            let sender = sender.clone();
+            move || sender.send(2)
+        });
+        let waiting = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send_async(3).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        sender.close();
+
+        assert_eq!(blocked.join().unwrap(), Err(SendError::Closed(2)));
+        assert_eq!(waiting.await.unwrap(), Err(SendError::Closed(3)));
+        assert_eq!(sender.send_lossy_latest(4), Err(SendError::Closed(4)));
+
+        // What was queued before is still received
+        assert_eq!(receiver.recv().await, Ok(1));
+        assert_eq!(receiver.recv().await, Err(RecvError::Closed));
+    }
+
+    /// Sends `messages` messages from each of `senders` tasks, returning
+    /// what each receiver got and skipped once the senders are done.
+    async fn fan_out(
+        capacity: usize,
+        overflow: Overflow,
+        senders: usize,
+        messages: usize,
+        receivers: usize,
+    ) -> Vec<(Vec<(usize, usize)>, u64)> {
+        let (sender, receiver) = SimpleChannel::channel(capacity, overflow);
+        let consumers: Vec<_> = (0..receivers)
+            .map(|_| {
+                let receiver = receiver.clone();
+                tokio::spawn(async move {
+                    let mut received = Vec::new();
+                    let mut skipped = 0;
+                    while let Ok(msg) = receiver.recv().await {
+                        received.push(msg);
+                        skipped += receiver.take_skipped();
+                    }
+                    (received, skipped + receiver.take_skipped())
+                })
+            })
+            .collect();
+        drop(receiver);
+
+        let producers: Vec<_> = (0..senders)
+            .map(|x| {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    for i in 0..messages {
+                        match overflow {
+                            Overflow::Wait => sender.send_async((x, i)).await.unwrap(),
+                            _ => sender.send((x, i)).unwrap(),
+                        }
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+
+        for producer in producers {
+            producer.await.unwrap();
+        }
+        let mut results = Vec::new();
+        for consumer in consumers {
+            results.push(consumer.await.unwrap());
+        }
+        results
+    }
+
+    /// Checks the messages of each sender came in order, returning how many
+    /// came from each.
+    fn check_order(received: &[(usize, usize)], senders: usize) -> Vec<usize> {
+        let mut next = vec![0; senders];
+        let mut counts = vec![0; senders];
+        for &(sender, index) in received {
+            assert!(index >= next[sender], "Messages of {sender} out of order");
+            next[sender] = index + 1;
+            counts[sender] += 1;
+        }
+        counts
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn every_subscriber_gets_every_message() {
+        for seed in 0..32 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let capacity = rng.gen_range(1..=8);
+            let senders = rng.gen_range(1..=3);
+            let messages = rng.gen_range(1..=100);
+            let receivers = rng.gen_range(1..=4);
+
+            let results = fan_out(capacity, Overflow::Wait, senders, messages, receivers).await;
+            assert_eq!(results.len(), receivers);
+            for (received, skipped) in results {
+                let counts = check_order(&received, senders);
+                assert_eq!(counts, vec![messages; senders], "Case {seed} lost messages");
+                assert_eq!(skipped, 0);
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn lossy_subscribers_account_for_every_message() {
+        for seed in 0..32 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let capacity = rng.gen_range(1..=8);
+            let senders = rng.gen_range(1..=3);
+            let messages = rng.gen_range(1..=100);
+            let receivers = rng.gen_range(1..=4);
+
+            let results =
+                fan_out(capacity, Overflow::DropOldest, senders, messages, receivers).await;
+            for (received, skipped) in results {
+                check_order(&received, senders);
+                assert_eq!(
+                    received.len() as u64 + skipped,
+                    (senders * messages) as u64,
+                    "Case {seed} lost messages without reporting them"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn lagging_subscribers_only_skip_their_own_messages() {
+        let (sender, fast) = SimpleChannel::channel(2, Overflow::DropOldest);
+        let slow = sender.subscribe();
+
+        for i in 0..5 {
+            sender.send(i).unwrap();
+            assert_eq!(fast.recv().await, Ok(i));
+        }
+
+        assert_eq!(fast.take_skipped(), 0);
+        assert_eq!(slow.take_skipped(), 3);
+        assert_eq!(slow.take_skipped(), 0, "Skipped messages are reported once");
+        assert_eq!(slow.recv().await, Ok(3));
+        assert_eq!(slow.recv().await, Ok(4));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn single_consumer_channels_never_clone_messages() {
+        #[derive(Debug, PartialEq)]
+        struct Message(usize);
+
+        impl Clone for Message {
+            fn clone(&self) -> Self {
+                panic!("Message {} was cloned", self.0);
+            }
+        }
+
+        let (sender, receiver) = SimpleChannel::single(4, Overflow::Wait);
+        let other = receiver.clone();
+        let producer = tokio::spawn(async move {
+            for i in 0..MESSAGES {
+                sender.send_async(Message(i)).await.unwrap();
+            }
+        });
+
+        // The receivers share the messages
+        let mut received = Vec::new();
+        loop {
+            let msg = tokio::select! {
+                msg = receiver.recv() => msg,
+                msg = other.recv() => msg,
+            };
+            match msg {
+                Ok(Message(i)) => received.push(i),
+                Err(RecvError::Closed) => break,
             }
-            self.notify.notified().await;
         }
+        producer.await.unwrap();
+
+        received.sort();
+        assert_eq!(received, (0..MESSAGES).collect::<Vec<_>>());
     }
 }
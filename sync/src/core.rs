@@ -1166,6 +1166,18 @@ where
                 let id_str = model.unique_id();
                 info!("Local TXN: Updating record ID {id_str} via delete and insert");
 
+                // Keep the values of the immutable columns of the local record
+                let immutable_columns = E::immutable_columns();
+                let existing_record = if immutable_columns.is_empty() {
+                    None
+                } else {
+                    E::find()
+                        .filter(E::unique_id_column().eq(id_str.clone()))
+                        .one(&txn)
+                        .await
+                        .with_context(|| format!("Failed to read local record {id_str}"))?
+                };
+
                 // First, delete the existing record by its unique ID
                 let delete_result = E::delete_many()
                     .filter(E::unique_id_column().eq(id_str.clone()))
@@ -1190,6 +1202,12 @@ where
                     active_model.reset(pk_col.into_column());
                 }
 
+                if let Some(existing_record) = &existing_record {
+                    for column in immutable_columns {
+                        active_model.set(column, existing_record.get(column));
+                    }
+                }
+
                 if let Some(resolver) = &fk_resolver {
                     resolver
                         .remap_and_set_foreign_keys(&mut active_model, &fk_payload, &txn)
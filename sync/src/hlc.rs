@@ -331,6 +331,12 @@ pub trait HLCModel: EntityTrait + Sized + Send + Sync + 'static {
     /// Returns the SeaORM column definition for the unique identifier.
     fn unique_id_column() -> Self::Column;
 
+    /// Returns the columns which keep the value the record was created with
+    /// locally, updates from other nodes never overwriting them.
+    fn immutable_columns() -> Vec<Self::Column> {
+        Vec::new()
+    }
+
     /// Creates a SeaORM condition for records strictly greater than the given HLC.
     fn gt(hlc: &HLC) -> Result<Condition> {
         let ts_str = hlc
@@ -48,6 +48,16 @@ macro_rules! impl_hlc_record_for_model {
 #[macro_export]
 macro_rules! impl_hlc_model_for_entity {
     ($entity:ty, $col_unique_id:expr, $col_updated_at_time:expr, $col_updated_at_version:expr, $col_updated_at_nid:expr) => {
+        $crate::impl_hlc_model_for_entity!(
+            $entity,
+            $col_unique_id,
+            $col_updated_at_time,
+            $col_updated_at_version,
+            $col_updated_at_nid,
+            immutable = []
+        );
+    };
+    ($entity:ty, $col_unique_id:expr, $col_updated_at_time:expr, $col_updated_at_version:expr, $col_updated_at_nid:expr, immutable = [$($col_immutable:expr),* $(,)?]) => {
         impl HLCModel for $entity {
             fn unique_id_column() -> Self::Column {
                 $col_unique_id // e.g., entity::Column::HlcUuid
@@ -66,6 +76,10 @@ macro_rules! impl_hlc_model_for_entity {
             fn updated_at_node_id_column() -> Self::Column {
                 $col_updated_at_nid // e.g., entity::Column::UpdatedAtNodeId
             }
+
+            fn immutable_columns() -> Vec<Self::Column> {
+                vec![$($col_immutable),*]
+            }
         }
     };
 }
@@ -185,6 +185,21 @@ impl SyncScheduler {
         context: &SyncContext<'_, R>, // Context has concrete R
         jobs: Vec<TableSyncJob<R>>,   // Jobs are specific to this R
     ) -> Vec<TableSyncResult> {
+        self.run_plan_with_callback(context, jobs, |_| {}).await
+    }
+
+    /// Runs a series of synchronization jobs, calling `on_result` with the
+    /// result of every table as soon as it completes.
+    pub async fn run_plan_with_callback<R, F>(
+        &self,
+        context: &SyncContext<'_, R>,
+        jobs: Vec<TableSyncJob<R>>,
+        mut on_result: F,
+    ) -> Vec<TableSyncResult>
+    where
+        R: RemoteDataSource + Send + Sync + Debug + 'static,
+        F: FnMut(&TableSyncResult),
+    {
         let mut results = Vec::with_capacity(jobs.len());
 
         if jobs.is_empty() {
@@ -220,6 +235,10 @@ impl SyncScheduler {
                     });
                 }
             }
+
+            if let Some(result) = results.last() {
+                on_result(result);
+            }
         }
 
         info!(
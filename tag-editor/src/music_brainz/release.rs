@@ -0,0 +1,246 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use once_cell::sync::Lazy;
+use reqwest::{Client, StatusCode, header};
+use serde::Deserialize;
+
+use crate::shazam::rate_limiter::RateLimiter;
+
+const API_ROOT: &str = "https://musicbrainz.org/ws/2";
+/// MusicBrainz asks clients to identify themselves.
+const USER_AGENT: &str = concat!(
+    "Rune/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/Losses/rune )"
+);
+
+/// MusicBrainz allows one request per second.
+static LIMITER: Lazy<RateLimiter> = Lazy::new(|| RateLimiter::new(Duration::from_secs(1)));
+
+#[derive(Deserialize, Debug)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+    artist: ArtistRef,
+}
+
+#[derive(Deserialize, Debug)]
+struct ArtistRef {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordingRef {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseGroupRef {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TrackResponse {
+    id: String,
+    position: u32,
+    title: String,
+    length: Option<u64>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    recording: RecordingRef,
+}
+
+#[derive(Deserialize, Debug)]
+struct MediumResponse {
+    position: u32,
+    #[serde(rename = "track-count")]
+    track_count: u32,
+    #[serde(default)]
+    tracks: Vec<TrackResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseResponse {
+    id: String,
+    title: String,
+    status: Option<String>,
+    date: Option<String>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(rename = "release-group")]
+    release_group: Option<ReleaseGroupRef>,
+    #[serde(default)]
+    media: Vec<MediumResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseBrowseResponse {
+    releases: Vec<ReleaseResponse>,
+}
+
+/// A track of a release, with the credits spelled as MusicBrainz does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseTrack {
+    pub id: String,
+    pub recording_id: String,
+    pub title: String,
+    /// The artist credit with its join phrases, like `A feat. B`
+    pub artist: String,
+    pub artist_ids: Vec<String>,
+    pub disc_number: u32,
+    pub disc_count: u32,
+    pub track_number: u32,
+    /// The number of tracks of its disc
+    pub track_count: u32,
+    /// In seconds
+    pub length: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicBrainzRelease {
+    pub id: String,
+    pub release_group_id: Option<String>,
+    pub title: String,
+    pub artist: String,
+    pub artist_ids: Vec<String>,
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD`
+    pub date: Option<String>,
+    /// Every track of every disc, in order
+    pub tracks: Vec<ReleaseTrack>,
+}
+
+impl MusicBrainzRelease {
+    /// The year of the release, if its date is known.
+    pub fn year(&self) -> Option<&str> {
+        self.date
+            .as_deref()
+            .and_then(|x| x.get(..4))
+            .filter(|x| x.chars().all(|c| c.is_ascii_digit()))
+    }
+}
+
+fn credit_name(credits: &[ArtistCredit]) -> String {
+    credits
+        .iter()
+        .map(|x| format!("{}{}", x.name, x.joinphrase))
+        .collect()
+}
+
+fn credit_ids(credits: &[ArtistCredit]) -> Vec<String> {
+    credits.iter().map(|x| x.artist.id.clone()).collect()
+}
+
+impl From<ReleaseResponse> for MusicBrainzRelease {
+    fn from(release: ReleaseResponse) -> Self {
+        let artist = credit_name(&release.artist_credit);
+        let artist_ids = credit_ids(&release.artist_credit);
+        let disc_count = release.media.len() as u32;
+
+        let tracks = release
+            .media
+            .iter()
+            .flat_map(|medium| {
+                medium.tracks.iter().map(|track| ReleaseTrack {
+                    id: track.id.clone(),
+                    recording_id: track.recording.id.clone(),
+                    title: track.title.clone(),
+                    // Tracks credited like the release have no credit
+                    artist: if track.artist_credit.is_empty() {
+                        artist.clone()
+                    } else {
+                        credit_name(&track.artist_credit)
+                    },
+                    artist_ids: if track.artist_credit.is_empty() {
+                        artist_ids.clone()
+                    } else {
+                        credit_ids(&track.artist_credit)
+                    },
+                    disc_number: medium.position,
+                    disc_count,
+                    track_number: track.position,
+                    track_count: medium.track_count,
+                    length: track.length.map(|x| x as f64 / 1000.0),
+                })
+            })
+            .collect();
+
+        MusicBrainzRelease {
+            id: release.id,
+            release_group_id: release.release_group.map(|x| x.id),
+            title: release.title,
+            artist,
+            artist_ids,
+            date: release.date.filter(|x| !x.is_empty()),
+            tracks,
+        }
+    }
+}
+
+async fn get<T: for<'de> Deserialize<'de>>(path: &str) -> Result<T> {
+    let client = Client::builder().gzip(true).build()?;
+    let url = format!("{API_ROOT}/{path}");
+
+    let mut attempts = 0;
+    loop {
+        LIMITER.acquire().await;
+
+        let response = client
+            .get(&url)
+            .header(header::USER_AGENT, USER_AGENT)
+            .header(header::ACCEPT, "application/json")
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => {
+                return response
+                    .json()
+                    .await
+                    .with_context(|| format!("Unexpected MusicBrainz response for {path}"));
+            }
+            StatusCode::SERVICE_UNAVAILABLE if attempts < 3 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_secs(2)).await;
+            }
+            StatusCode::NOT_FOUND => bail!("MusicBrainz doesn't know {path}"),
+            status => bail!("MusicBrainz request for {path} failed, status: {status}"),
+        }
+    }
+}
+
+/// Fetches a release with its tracks and credits.
+pub async fn fetch_release(release_id: &str) -> Result<MusicBrainzRelease> {
+    let release: ReleaseResponse = get(&format!(
+        "release/{release_id}?inc=recordings+artist-credits+release-groups&fmt=json"
+    ))
+    .await?;
+
+    Ok(release.into())
+}
+
+/// Picks the release of a release group closest to an album of
+/// `track_count` tracks, official releases first, then fetches it.
+pub async fn fetch_release_of_group(
+    release_group_id: &str,
+    track_count: usize,
+) -> Result<MusicBrainzRelease> {
+    let browse: ReleaseBrowseResponse = get(&format!(
+        "release?release-group={release_group_id}&inc=media&limit=100&fmt=json"
+    ))
+    .await?;
+
+    let Some(release) = browse.releases.iter().min_by_key(|release| {
+        let tracks: u32 = release.media.iter().map(|x| x.track_count).sum();
+        (
+            (tracks as i64 - track_count as i64).abs(),
+            release.status.as_deref() != Some("Official"),
+            release.date.clone().unwrap_or_else(|| "9999".to_owned()),
+        )
+    }) else {
+        bail!("Release group {release_group_id} has no release");
+    };
+
+    fetch_release(&release.id).await
+}
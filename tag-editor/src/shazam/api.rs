@@ -54,6 +54,19 @@ pub struct Track {
     pub hub: Hub,
 }
 
+impl Track {
+    /// A field of the song section, like its album or release date.
+    pub fn song_metadata(&self, title: &str) -> Option<String> {
+        self.sections
+            .iter()
+            .filter(|x| x.section_type == "SONG")
+            .filter_map(|x| x.metadata.as_ref())
+            .flatten()
+            .find(|x| x.title == title)
+            .map(|x| x.text.clone())
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Section {
     #[serde(rename = "type")]
@@ -0,0 +1,215 @@
+//! Compact landmark signatures, for matching tracks with each other offline.
+//!
+//! The peaks of a signature computed over a whole track are thinned out to
+//! the strongest ones around each moment, and each of them is paired with
+//! the few peaks following it. A landmark hashes the frequencies of both
+//! peaks and the time between them, so it survives re-encoding and doesn't
+//! depend on where the track starts. Two tracks sharing enough landmarks at
+//! a constant time difference carry the same recording.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+use tokio_util::sync::CancellationToken;
+
+use fsio::FsIo;
+
+use crate::sampler::interval_sampler::IntervalSampler;
+
+use super::recognition::SAMPLE_RATE;
+use super::spectrogram::{FrequencyPeak, compute_signature};
+
+/// Signature passes per second, a pass being 128 samples.
+pub const PASSES_PER_SECOND: f64 = SAMPLE_RATE as f64 / 128.0;
+/// The length of the windows a track is decoded in, in seconds.
+const DECODE_WINDOW: f64 = 4.0;
+/// Passes on each side of a peak it competes with for being kept.
+const ANCHOR_NEIGHBORHOOD: i32 = 62;
+/// How many stronger peaks a neighborhood may hold before a peak is dropped.
+const ANCHORS_PER_NEIGHBORHOOD: usize = 2;
+/// How many following peaks each peak is paired with.
+const FAN_OUT: usize = 3;
+/// The longest time between the peaks of a landmark, in passes.
+const MAX_PAIR_DISTANCE: i32 = 250;
+/// Landmarks two tracks must share at the same time difference to match.
+const MIN_ALIGNED_LANDMARKS: usize = 20;
+/// The share of the landmarks of the shorter track which must align.
+const MIN_MATCH_DENSITY: f64 = 0.05;
+
+/// A pair of peaks, hashed, and the pass of the first one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Landmark {
+    pub hash: u32,
+    pub time: u32,
+}
+
+/// Where two tracks align.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LandmarkAlignment {
+    /// How much later the shared part starts in the first track, in seconds
+    pub offset: f64,
+    /// The share of the landmarks of the shorter track which align
+    pub density: f64,
+}
+
+/// Decodes a whole file at [`SAMPLE_RATE`] and computes its landmarks.
+pub fn file_landmarks(
+    fsio: &FsIo,
+    path: &Path,
+    cancel_token: Option<CancellationToken>,
+) -> Result<Vec<Landmark>> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid path {}", path.display()))?;
+    let mut sampler = IntervalSampler::new(
+        path_str,
+        DECODE_WINDOW,
+        DECODE_WINDOW,
+        SAMPLE_RATE,
+        cancel_token.clone(),
+    );
+    sampler.process(fsio)?;
+    if cancel_token.is_some_and(|x| x.is_cancelled()) {
+        bail!("The landmark computation was cancelled");
+    }
+
+    // Back to back windows make up the whole track
+    let samples: Vec<f64> = sampler.receiver.try_iter().flat_map(|x| x.data).collect();
+    if samples.is_empty() {
+        bail!("{} is too short to compute landmarks", path.display());
+    }
+
+    Ok(landmarks(&samples))
+}
+
+/// Computes the landmarks of samples at [`SAMPLE_RATE`], ordered by time.
+pub fn landmarks(samples: &[f64]) -> Vec<Landmark> {
+    let signature = compute_signature(SAMPLE_RATE as i32, samples);
+    let mut peaks: Vec<&FrequencyPeak> = signature.peaks_by_band.iter().flatten().collect();
+    peaks.sort_by_key(|x| (x.pass, x.bin));
+
+    // Keep the peaks standing out around them. A fixed grid would make the
+    // choice depend on where the track starts.
+    let anchors: Vec<&FrequencyPeak> = peaks
+        .iter()
+        .enumerate()
+        .filter(|(index, peak)| {
+            let start =
+                peaks[..*index].partition_point(|x| x.pass < peak.pass - ANCHOR_NEIGHBORHOOD);
+            peaks[start..]
+                .iter()
+                .take_while(|x| x.pass <= peak.pass + ANCHOR_NEIGHBORHOOD)
+                .filter(|x| x.magnitude > peak.magnitude)
+                .count()
+                < ANCHORS_PER_NEIGHBORHOOD
+        })
+        .map(|(_, peak)| *peak)
+        .collect();
+
+    let mut landmarks = Vec::new();
+    for (index, anchor) in anchors.iter().enumerate() {
+        for target in anchors[index + 1..]
+            .iter()
+            .take_while(|x| x.pass - anchor.pass <= MAX_PAIR_DISTANCE)
+            .take(FAN_OUT)
+        {
+            landmarks.push(Landmark {
+                hash: landmark_hash(anchor, target),
+                time: anchor.pass as u32,
+            });
+        }
+    }
+
+    landmarks
+}
+
+/// Packs the coarse bins of both peaks, 10 bits each, and the passes
+/// between them, 12 bits.
+fn landmark_hash(anchor: &FrequencyPeak, target: &FrequencyPeak) -> u32 {
+    let bin = |peak: &FrequencyPeak| ((peak.bin >> 6) as u32) & 0x3ff;
+    let distance = ((target.pass - anchor.pass) as u32) & 0xfff;
+
+    (bin(anchor) << 22) | (bin(target) << 12) | distance
+}
+
+/// Serializes landmarks, 8 bytes each.
+pub fn encode_landmarks(landmarks: &[Landmark]) -> Vec<u8> {
+    landmarks
+        .iter()
+        .flat_map(|x| {
+            let mut bytes = [0; 8];
+            bytes[..4].copy_from_slice(&x.hash.to_le_bytes());
+            bytes[4..].copy_from_slice(&x.time.to_le_bytes());
+            bytes
+        })
+        .collect()
+}
+
+pub fn decode_landmarks(data: &[u8]) -> Result<Vec<Landmark>> {
+    if !data.len().is_multiple_of(8) {
+        bail!(
+            "Landmark data is {} bytes long, not a multiple of 8",
+            data.len()
+        );
+    }
+
+    Ok(data
+        .chunks_exact(8)
+        .map(|x| Landmark {
+            hash: u32::from_le_bytes([x[0], x[1], x[2], x[3]]),
+            time: u32::from_le_bytes([x[4], x[5], x[6], x[7]]),
+        })
+        .collect())
+}
+
+/// Whether a hash goes into the inverted index. Indexing a fixed share of
+/// the hashes rather than a share of each track keeps the index small while
+/// two copies of a recording still index the same hashes.
+pub fn is_indexed_hash(hash: u32, sample_bits: u32) -> bool {
+    hash.wrapping_mul(0x9e37_79b1) >> (32 - sample_bits) == 0
+}
+
+/// Finds the time difference most shared landmarks agree on, a pass of
+/// jitter allowed, and whether enough of them do for both tracks to carry
+/// the same recording.
+pub fn align_landmarks(a: &[Landmark], b: &[Landmark]) -> Option<LandmarkAlignment> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    let mut times_by_hash: HashMap<u32, Vec<u32>> = HashMap::new();
+    for landmark in a {
+        times_by_hash
+            .entry(landmark.hash)
+            .or_default()
+            .push(landmark.time);
+    }
+
+    let mut histogram: HashMap<i64, usize> = HashMap::new();
+    for landmark in b {
+        if let Some(times) = times_by_hash.get(&landmark.hash) {
+            for &time in times {
+                *histogram
+                    .entry(time as i64 - landmark.time as i64)
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let count = |delta: i64| histogram.get(&delta).copied().unwrap_or_default();
+    let (delta, aligned) = histogram
+        .keys()
+        .map(|&delta| (delta, count(delta - 1) + count(delta) + count(delta + 1)))
+        .max_by_key(|&(delta, aligned)| (aligned, -delta.abs()))?;
+
+    let density = aligned as f64 / a.len().min(b.len()) as f64;
+    if aligned < MIN_ALIGNED_LANDMARKS || density < MIN_MATCH_DENSITY {
+        return None;
+    }
+
+    Some(LandmarkAlignment {
+        offset: delta as f64 / PASSES_PER_SECOND,
+        density: density.min(1.0),
+    })
+}
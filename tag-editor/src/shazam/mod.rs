@@ -11,7 +11,9 @@
 
 pub mod api;
 pub mod hanning;
+pub mod landmark;
 pub mod rate_limiter;
+pub mod recognition;
 pub mod ring;
 pub mod signature;
 pub mod spectrogram;
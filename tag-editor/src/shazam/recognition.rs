@@ -0,0 +1,179 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Result, anyhow, bail};
+use once_cell::sync::Lazy;
+use rubato::{FftFixedInOut, Resampler};
+use tokio_util::sync::CancellationToken;
+
+use fsio::FsIo;
+
+use crate::sampler::interval_sampler::IntervalSampler;
+
+use super::api::identify;
+use super::spectrogram::{Signature, compute_signature};
+
+/// The sample rate signatures are computed at, the one the recognition
+/// service expects.
+pub const SAMPLE_RATE: u32 = 16000;
+/// The length of the excerpts sent for recognition, in seconds.
+pub const EXCERPT_DURATION: f64 = 12.0;
+/// The shortest snippet worth sending, in seconds.
+const MIN_SNIPPET_DURATION: f64 = 3.0;
+/// The number of signatures whose results are kept.
+const CACHE_CAPACITY: usize = 256;
+
+/// A track the recognition service matched an excerpt with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecognizedTrack {
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub date: Option<String>,
+    /// Where the excerpt starts in the track, in seconds
+    pub offset: f64,
+}
+
+/// Results by signature hash, the oldest evicted first.
+#[derive(Default)]
+struct RecognitionCache {
+    results: HashMap<u32, Vec<RecognizedTrack>>,
+    order: VecDeque<u32>,
+}
+
+impl RecognitionCache {
+    fn insert(&mut self, hash: u32, tracks: Vec<RecognizedTrack>) {
+        if self.results.insert(hash, tracks).is_none() {
+            self.order.push_back(hash);
+        }
+        while self.order.len() > CACHE_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.results.remove(&oldest);
+            }
+        }
+    }
+}
+
+static CACHE: Lazy<Mutex<RecognitionCache>> = Lazy::new(Default::default);
+
+/// Decodes the excerpt in the middle of a file, where the song is most
+/// likely to be recognizable.
+pub fn centered_excerpt(
+    fsio: &FsIo,
+    path: &Path,
+    cancel_token: Option<CancellationToken>,
+) -> Result<Vec<f64>> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid path {}", path.display()))?;
+    let mut sampler = IntervalSampler::new(
+        path_str,
+        EXCERPT_DURATION,
+        EXCERPT_DURATION,
+        SAMPLE_RATE,
+        cancel_token.clone(),
+    );
+    sampler.process(fsio)?;
+    if cancel_token.is_some_and(|x| x.is_cancelled()) {
+        bail!("The identification was cancelled");
+    }
+
+    let mut excerpts: Vec<Vec<f64>> = sampler.receiver.try_iter().map(|x| x.data).collect();
+    if excerpts.is_empty() {
+        bail!("{} is too short to be identified", path.display());
+    }
+
+    let middle = excerpts.len() / 2;
+    Ok(excerpts.swap_remove(middle))
+}
+
+/// Turns interleaved 16-bit PCM, like a microphone recording, into an
+/// excerpt at the signature sample rate, keeping its middle part when it's
+/// longer than an excerpt.
+pub fn pcm_excerpt(pcm: &[i16], sample_rate: u32, channels: usize) -> Result<Vec<f64>> {
+    if sample_rate == 0 || channels == 0 {
+        bail!("Invalid PCM format: {sample_rate} Hz, {channels} channels");
+    }
+
+    let frames = pcm.len() / channels;
+    if (frames as f64) < MIN_SNIPPET_DURATION * sample_rate as f64 {
+        bail!("The snippet is too short to be identified");
+    }
+
+    // Mix down to mono
+    let excerpt_frames = (EXCERPT_DURATION * sample_rate as f64) as usize;
+    let start = frames.saturating_sub(excerpt_frames) / 2;
+    let mono: Vec<f64> = pcm
+        .chunks_exact(channels)
+        .skip(start)
+        .take(excerpt_frames)
+        .map(|frame| frame.iter().map(|&x| x as f64 / 32768.0).sum::<f64>() / channels as f64)
+        .collect();
+
+    if sample_rate == SAMPLE_RATE {
+        return Ok(mono);
+    }
+
+    let mut resampler =
+        FftFixedInOut::<f64>::new(sample_rate as usize, SAMPLE_RATE as usize, 1024, 1)?;
+    let expected = mono.len() * SAMPLE_RATE as usize / sample_rate as usize;
+    let mut resampled = Vec::with_capacity(expected);
+    let mut position = 0;
+    while position < mono.len() {
+        let chunk_size = resampler.input_frames_next();
+        let end = (position + chunk_size).min(mono.len());
+        // The last chunk is padded with silence, trimmed afterwards
+        let mut chunk = mono[position..end].to_vec();
+        chunk.resize(chunk_size, 0.0);
+
+        let output = resampler.process(&[chunk], None)?;
+        resampled.extend_from_slice(&output[0]);
+        position = end;
+    }
+    resampled.truncate(expected);
+
+    Ok(resampled)
+}
+
+/// The hash results are cached by, identical excerpts giving identical
+/// signatures.
+pub fn signature_hash(signature: &Signature) -> u32 {
+    crc32fast::hash(&signature.encode())
+}
+
+/// Asks the recognition service which tracks an excerpt at
+/// [`SAMPLE_RATE`] comes from, best match first. No match gives an empty
+/// list rather than an error. Results are cached by signature, and requests
+/// are paced by the rate limiter of the service client.
+pub async fn recognize(
+    samples: &[f64],
+    cancel_token: &CancellationToken,
+) -> Result<Vec<RecognizedTrack>> {
+    let signature = compute_signature(SAMPLE_RATE as i32, samples);
+    let hash = signature_hash(&signature);
+    if let Some(tracks) = CACHE.lock().unwrap().results.get(&hash) {
+        return Ok(tracks.clone());
+    }
+
+    let (matches, track) = tokio::select! {
+        _ = cancel_token.cancelled() => bail!("The identification was cancelled"),
+        result = identify(signature) => result?,
+    };
+
+    // The service answers with its best track, the matches being where the
+    // excerpt aligns with it, best first
+    let tracks: Vec<RecognizedTrack> = track
+        .into_iter()
+        .map(|track| RecognizedTrack {
+            title: track.title.clone(),
+            artist: track.subtitle.clone(),
+            album: track.song_metadata("Album"),
+            date: track.song_metadata("Released"),
+            offset: matches.first().map(|x| x.offset).unwrap_or_default(),
+        })
+        .collect();
+
+    CACHE.lock().unwrap().insert(hash, tracks.clone());
+    Ok(tracks)
+}